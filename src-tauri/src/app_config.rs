@@ -0,0 +1,612 @@
+// 应用设置
+//
+// 音量、界面主题、窗口位置、音质增强、网络歌词来源、内置歌单、远程控制和艺术家拆分设置合在一个文档里，以 JSON 保存在设置表的 app.config 键下，
+// 写入在一条 SQLite 语句内完成，程序崩溃也不会留下写了一半的设置。
+// 各功能自己的设置（缓存、带宽、输出设备等）仍保存在各自的设置键下。
+//
+// 前端通过 config_update 提交部分设置（JSON Merge Patch），合并后整体校验再保存。
+//
+// 同一份设置还写到应用数据目录下的 config.json（先写临时文件再改名），方便在多台电脑间同步。
+// 程序运行时监听这个文件，在外部修改后重新读取，把变化的设置项即时应用；
+// 文件格式错误时保留当前设置，不会覆盖。
+
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::artist_split::ArtistSplitConfig;
+use crate::audio_enhancement::{AudioEnhancementSettings, EqualizerPresets, UserEqualizerPreset, UserEqualizerPresets};
+use crate::db::Database;
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::network_api::NetworkConfig;
+use crate::player::audio::VolumeSettings;
+use crate::playlist::BuiltinPlaylistsConfig;
+use crate::remote_control::RemoteControlConfig;
+
+/// 应用设置的设置键（JSON）
+pub const SETTING_APP_CONFIG: &str = "app.config";
+
+/// 设置文件名（位于应用数据目录）
+pub const CONFIG_FILE_NAME: &str = "config.json";
+
+/// 设置文件在这段时间内没有新的修改才重新读取（编辑器保存时可能连续写入多次）
+const RELOAD_QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+/// 可选的界面主题
+pub const THEMES: [&str; 3] = ["system", "light", "dark"];
+
+/// 窗口最小尺寸（与 tauri.conf.json 中的 minWidth / minHeight 一致）
+pub const MIN_WINDOW_WIDTH: u32 = 950;
+pub const MIN_WINDOW_HEIGHT: u32 = 600;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub audio: AudioConfig,
+    pub ui: UiConfig,
+    /// 网络歌词和封面来源
+    pub network: NetworkConfig,
+    /// 内置歌单（最近添加、最近播放、最常播放）的曲目数和统计范围
+    pub builtin_playlists: BuiltinPlaylistsConfig,
+    /// HTTP远程控制（默认关闭）
+    pub remote_control: RemoteControlConfig,
+    /// 多艺术家拆分的分隔符和不拆分的名称
+    pub artists: ArtistSplitConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// 启动时的音量（0.0 - 1.0）
+    pub default_volume: f32,
+    /// 音量曲线和前置放大
+    pub volume: VolumeSettings,
+    /// 音质增强设置
+    pub enhancement: AudioEnhancementSettings,
+    /// 用户保存的均衡器预设（名称 → 增益和可选的前置放大）
+    pub user_presets: UserEqualizerPresets,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            default_volume: 0.7,
+            volume: VolumeSettings::default(),
+            enhancement: AudioEnhancementSettings::default(),
+            user_presets: UserEqualizerPresets::new(),
+        }
+    }
+}
+
+impl AudioConfig {
+    /// 保存用户预设，同名的用户预设被覆盖，不能与内置预设重名
+    pub fn save_user_preset(&mut self, name: &str, preset: UserEqualizerPreset) -> AppResult<()> {
+        let name = validate_preset_name(name)?;
+        preset.validate().map_err(AppError::InvalidInput)?;
+        self.user_presets.insert(name.to_string(), preset);
+        Ok(())
+    }
+
+    /// 删除用户预设；删除的是当前预设时保留已应用的增益，只清除预设名称
+    pub fn delete_user_preset(&mut self, name: &str) -> AppResult<()> {
+        if self.user_presets.remove(name).is_none() {
+            return Err(AppError::not_found(format!("未找到用户预设: {}", name)));
+        }
+        let equalizer = &mut self.enhancement.equalizer;
+        if equalizer.preset.as_deref() == Some(name) {
+            equalizer.preset = None;
+        }
+        Ok(())
+    }
+
+    /// 重命名用户预设，当前预设的名称随之更新
+    pub fn rename_user_preset(&mut self, name: &str, new_name: &str) -> AppResult<()> {
+        let new_name = validate_preset_name(new_name)?;
+        if new_name != name && self.user_presets.contains_key(new_name) {
+            return Err(AppError::conflict(format!("预设已存在: {}", new_name)));
+        }
+        let preset = self.user_presets.remove(name)
+            .ok_or_else(|| AppError::not_found(format!("未找到用户预设: {}", name)))?;
+        self.user_presets.insert(new_name.to_string(), preset);
+        let equalizer = &mut self.enhancement.equalizer;
+        if equalizer.preset.as_deref() == Some(name) {
+            equalizer.preset = Some(new_name.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 校验用户预设名称，返回去掉首尾空白的名称
+fn validate_preset_name(name: &str) -> AppResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("预设名称不能为空"));
+    }
+    if EqualizerPresets::is_builtin(name) {
+        return Err(AppError::conflict(format!("不能使用内置预设的名称: {}", name)));
+    }
+    Ok(name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// 界面主题：system / light / dark
+    pub theme: String,
+    /// 是否记住窗口大小和位置
+    pub remember_window: bool,
+    /// 上次关闭时的窗口位置和大小
+    pub window: Option<WindowGeometry>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            theme: "system".to_string(),
+            remember_window: true,
+            window: None,
+        }
+    }
+}
+
+/// 窗口位置和大小（物理像素），最大化时保留最大化之前的大小
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+impl AppConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.audio.default_volume) {
+            return Err("默认音量必须在0到1之间".to_string());
+        }
+        self.audio.volume.validate()?;
+        self.audio.enhancement.validate()?;
+        for (name, preset) in &self.audio.user_presets {
+            if EqualizerPresets::is_builtin(name) {
+                return Err(format!("用户预设不能与内置预设重名: {}", name));
+            }
+            preset.validate().map_err(|e| format!("用户预设 {}: {}", name, e))?;
+        }
+        if !THEMES.contains(&self.ui.theme.as_str()) {
+            return Err(format!("无效的主题: {}，可选 {}", self.ui.theme, THEMES.join(" / ")));
+        }
+        if let Some(window) = &self.ui.window {
+            if window.width < MIN_WINDOW_WIDTH || window.height < MIN_WINDOW_HEIGHT {
+                return Err(format!("窗口尺寸不能小于 {}x{}", MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT));
+            }
+        }
+        self.network.validate()?;
+        self.builtin_playlists.validate()?;
+        self.remote_control.validate()?;
+        self.artists.validate()?;
+        Ok(())
+    }
+}
+
+/// 把 patch 合并到 target：对象逐个字段合并，其他值直接替换，null 表示恢复默认值
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    merge_patch(target.entry(key).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// 比较两份设置，返回变化的设置项（如 ui.theme、audio.enhancement.bass_boost.gain）
+pub fn changed_keys(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    fn diff(prefix: &str, old: &serde_json::Value, new: &serde_json::Value, keys: &mut Vec<String>) {
+        match (old, new) {
+            (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+                let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+                names.sort();
+                names.dedup();
+                let null = serde_json::Value::Null;
+                for name in names {
+                    let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                    diff(&key, old.get(name).unwrap_or(&null), new.get(name).unwrap_or(&null), keys);
+                }
+            }
+            (old, new) if old != new => keys.push(prefix.to_string()),
+            _ => {}
+        }
+    }
+    let mut keys = Vec::new();
+    if let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) {
+        diff("", &old, &new, &mut keys);
+    }
+    keys
+}
+
+/// 读取并校验设置文件
+fn read_config_file(path: &Path) -> AppResult<AppConfig> {
+    let json = fs::read_to_string(path)?;
+    let config: AppConfig = serde_json::from_str(&json)
+        .map_err(|e| AppError::invalid_input(format!("{} 格式错误: {}", CONFIG_FILE_NAME, e)))?;
+    config
+        .validate()
+        .map_err(|e| AppError::invalid_input(format!("{} 中的设置无效: {}", CONFIG_FILE_NAME, e)))?;
+    Ok(config)
+}
+
+/// 先写临时文件再改名，写到一半崩溃也不会留下不完整的设置文件
+fn write_config_file(path: &Path, config: &AppConfig) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(config)?;
+    let tmp = path.with_extension("json.tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// 读取保存的应用设置，没有或无法解析时使用默认设置
+fn load_config(db: &Database) -> AppConfig {
+    db.get_setting(SETTING_APP_CONFIG)
+        .ok()
+        .flatten()
+        .and_then(|json| match serde_json::from_str(&json) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                log::warn!("⚠️ 应用设置无法解析，使用默认设置: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// 读取保存的艺术家拆分设置（扫描和数据库迁移时使用，不经过 ConfigManager）
+pub(crate) fn load_artist_split_config(db: &Database) -> ArtistSplitConfig {
+    load_config(db).artists
+}
+
+/// 应用设置管理：内存中保存当前设置，修改时先写入数据库再更新内存
+pub struct ConfigManager {
+    db: Arc<DbPool>,
+    /// 设置文件，为空时只保存在数据库
+    file: Option<PathBuf>,
+    config: RwLock<AppConfig>,
+}
+
+impl ConfigManager {
+    /// 加载设置；设置文件在程序关闭期间被修改（如从其他电脑同步过来）时以文件为准
+    pub fn load(db: Arc<DbPool>, file: Option<PathBuf>) -> AppResult<Self> {
+        let mut config = load_config(&db.read()?);
+        if let Some(path) = &file {
+            match read_config_file(path) {
+                Ok(from_file) => {
+                    if !changed_keys(&config, &from_file).is_empty() {
+                        log::info!("⚙️ 使用 {} 中的设置", path.display());
+                        let json = serde_json::to_string(&from_file)?;
+                        db.with(|db| db.set_setting(SETTING_APP_CONFIG, &json))?;
+                        config = from_file;
+                    }
+                }
+                Err(_) if !path.exists() => {
+                    if let Err(e) = write_config_file(path, &config) {
+                        log::warn!("⚠️ 无法写入设置文件 {}: {}", path.display(), e);
+                    }
+                }
+                // 文件有误时不覆盖，等用户修正后由文件监听重新读取
+                Err(e) => log::warn!("⚠️ {}，使用上次保存的设置", e),
+            }
+        }
+        Ok(Self { db, file, config: RwLock::new(config) })
+    }
+
+    pub async fn get(&self) -> AppConfig {
+        self.config.read().await.clone()
+    }
+
+    /// 合并部分设置，校验通过后保存，返回新的完整设置
+    pub async fn update(&self, patch: serde_json::Value) -> AppResult<AppConfig> {
+        let mut config = self.config.write().await;
+        let mut value = serde_json::to_value(&*config)?;
+        merge_patch(&mut value, patch);
+        let updated: AppConfig =
+            serde_json::from_value(value).map_err(|e| AppError::invalid_input(format!("设置格式错误: {}", e)))?;
+        self.save(&updated).await?;
+        *config = updated.clone();
+        Ok(updated)
+    }
+
+    /// 修改设置，校验通过后保存
+    pub async fn modify(&self, f: impl FnOnce(&mut AppConfig)) -> AppResult<AppConfig> {
+        self.try_modify(|config| {
+            f(config);
+            Ok(())
+        })
+        .await
+    }
+
+    /// 修改设置，f 返回错误时不保存
+    pub async fn try_modify(&self, f: impl FnOnce(&mut AppConfig) -> AppResult<()>) -> AppResult<AppConfig> {
+        let mut config = self.config.write().await;
+        let mut updated = config.clone();
+        f(&mut updated)?;
+        self.save(&updated).await?;
+        *config = updated.clone();
+        Ok(updated)
+    }
+
+    /// 记录窗口位置（在窗口事件回调中调用，不能等待异步锁）
+    ///
+    /// 最大化时保留上次记录的未最大化的大小和位置，恢复后取消最大化能回到原来的大小。
+    pub fn save_window(&self, mut window: WindowGeometry) -> AppResult<()> {
+        let mut config = self.config.try_write().map_err(|_| AppError::internal("应用设置正在修改"))?;
+        if let (true, Some(previous)) = (window.maximized, config.ui.window) {
+            window = WindowGeometry { maximized: true, ..previous };
+        }
+        if !config.ui.remember_window || config.ui.window == Some(window) {
+            return Ok(());
+        }
+        let mut updated = config.clone();
+        updated.ui.window = Some(window);
+        updated.validate().map_err(AppError::InvalidInput)?;
+        let json = serde_json::to_string(&updated)?;
+        self.db.with(|db| db.set_setting(SETTING_APP_CONFIG, &json))?;
+        self.write_file(&updated);
+        *config = updated;
+        Ok(())
+    }
+
+    /// 重新读取设置文件，返回新设置和变化的设置项；文件有误时保留当前设置并返回错误
+    pub async fn reload(&self) -> AppResult<(AppConfig, Vec<String>)> {
+        let Some(path) = &self.file else {
+            return Ok((self.get().await, Vec::new()));
+        };
+        let reloaded = read_config_file(path)?;
+        let mut config = self.config.write().await;
+        let keys = changed_keys(&config, &reloaded);
+        if keys.is_empty() {
+            return Ok((reloaded, keys));
+        }
+        // 文件已是最新内容，只需写入数据库
+        let json = serde_json::to_string(&reloaded)?;
+        self.db.run_write(move |db| db.set_setting(SETTING_APP_CONFIG, &json)).await?;
+        *config = reloaded.clone();
+        Ok((reloaded, keys))
+    }
+
+    async fn save(&self, config: &AppConfig) -> AppResult<()> {
+        config.validate().map_err(AppError::InvalidInput)?;
+        let json = serde_json::to_string(config)?;
+        self.db.run_write(move |db| db.set_setting(SETTING_APP_CONFIG, &json)).await?;
+        self.write_file(config);
+        Ok(())
+    }
+
+    /// 同步写入设置文件，失败只记录日志（数据库中的设置已保存）
+    fn write_file(&self, config: &AppConfig) {
+        if let Some(path) = &self.file {
+            if let Err(e) = write_config_file(path, config) {
+                log::warn!("⚠️ 无法写入设置文件 {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// 监听设置文件，外部修改后重新读取，结果交给 on_reload（设置没有变化时不调用）
+///
+/// 监听所在目录而不是文件本身：编辑器和同步工具通常写临时文件再改名替换。
+pub fn watch<F>(manager: Arc<ConfigManager>, shutdown: &'static AtomicBool, on_reload: F)
+where
+    F: Fn(AppResult<(AppConfig, Vec<String>)>) + Send + 'static,
+{
+    let Some(path) = manager.file.clone() else {
+        return;
+    };
+    let Some(dir) = path.parent().map(Path::to_path_buf) else {
+        return;
+    };
+
+    thread::spawn(move || {
+        let (event_tx, event_rx) = crossbeam_channel::unbounded::<()>();
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) => {
+                if !event.kind.is_access() && event.paths.iter().any(|p| p.file_name() == file_name.as_deref()) {
+                    let _ = event_tx.send(());
+                }
+            }
+            Err(e) => log::warn!("设置文件监听错误: {}", e),
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                log::warn!("⚠️ 无法监听设置文件: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            log::warn!("⚠️ 无法监听设置文件 {}: {}", path.display(), e);
+            return;
+        }
+        log::info!("👀 开始监听设置文件 {}", path.display());
+
+        let mut pending = false;
+        while !shutdown.load(Ordering::Relaxed) {
+            match event_rx.recv_timeout(RELOAD_QUIET_PERIOD) {
+                Ok(()) => pending = true,
+                Err(crossbeam_channel::RecvTimeoutError::Timeout) if pending => {
+                    pending = false;
+                    // 文件在替换过程中可能暂时不存在，等下一次事件
+                    if !path.exists() {
+                        continue;
+                    }
+                    match tauri::async_runtime::block_on(manager.reload()) {
+                        Ok((_, keys)) if keys.is_empty() => {}
+                        result => on_reload(result),
+                    }
+                }
+                Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+                Err(crossbeam_channel::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        log::info!("设置文件监听已停止");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> (Arc<DbPool>, ConfigManager) {
+        let db = Arc::new(DbPool::single(Database::new(":memory:").unwrap()));
+        let manager = ConfigManager::load(Arc::clone(&db), None).unwrap();
+        (db, manager)
+    }
+
+    #[tokio::test]
+    async fn test_partial_update_is_merged_and_persisted() {
+        let (db, manager) = manager();
+        let config = manager
+            .update(serde_json::json!({
+                "audio": { "default_volume": 0.4, "enhancement": { "bass_boost": { "gain": 6.0 } } },
+                "ui": { "theme": "dark" },
+            }))
+            .await
+            .unwrap();
+        assert_eq!(config.audio.default_volume, 0.4);
+        assert_eq!(config.audio.enhancement.bass_boost.gain, 6.0);
+        assert_eq!(config.ui.theme, "dark");
+        // 未提交的字段保持不变
+        assert!(config.ui.remember_window);
+
+        // null 恢复默认值
+        let config = manager.update(serde_json::json!({ "ui": { "theme": null } })).await.unwrap();
+        assert_eq!(config.ui.theme, "system");
+
+        let reloaded = ConfigManager::load(db, None).unwrap().get().await;
+        assert_eq!(reloaded.audio.default_volume, 0.4);
+        assert_eq!(reloaded.audio.enhancement.bass_boost.gain, 6.0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_update_is_rejected() {
+        let (db, manager) = manager();
+        for patch in [
+            serde_json::json!({ "audio": { "default_volume": 1.5 } }),
+            serde_json::json!({ "audio": { "enhancement": { "bass_boost": { "gain": 20.0 } } } }),
+            serde_json::json!({ "ui": { "theme": "purple" } }),
+            serde_json::json!({ "ui": { "window": { "x": 0, "y": 0, "width": 100, "height": 100, "maximized": false } } }),
+            serde_json::json!({ "audio": { "default_volume": "loud" } }),
+        ] {
+            let err = manager.update(patch).await.unwrap_err();
+            assert_eq!(err.kind(), "InvalidInput");
+        }
+        assert_eq!(manager.get().await.audio.default_volume, AudioConfig::default().default_volume);
+        assert!(db.lock().unwrap().get_setting(SETTING_APP_CONFIG).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_user_preset_crud() {
+        let (db, manager) = manager();
+        let preset = UserEqualizerPreset { gains: [1.5; 10], preamp_db: Some(-2.0) };
+        manager.try_modify(|config| config.audio.save_user_preset(" 耳机 ", preset.clone())).await.unwrap();
+        assert_eq!(load_config(&db.lock().unwrap()).audio.user_presets.get("耳机"), Some(&preset));
+
+        // 与内置预设重名、增益超出范围时拒绝，设置不变
+        let err = manager.try_modify(|config| config.audio.save_user_preset("流行", preset.clone())).await.unwrap_err();
+        assert_eq!(err.kind(), "Conflict");
+        let loud = UserEqualizerPreset { gains: [13.0; 10], preamp_db: None };
+        let err = manager.try_modify(|config| config.audio.save_user_preset("太响", loud)).await.unwrap_err();
+        assert_eq!(err.kind(), "InvalidInput");
+        assert_eq!(manager.get().await.audio.user_presets.len(), 1);
+
+        // 重命名当前预设时预设名称随之更新
+        manager.modify(|config| config.audio.enhancement.equalizer.preset = Some("耳机".to_string())).await.unwrap();
+        let config = manager.try_modify(|config| config.audio.rename_user_preset("耳机", "音箱")).await.unwrap();
+        assert_eq!(config.audio.enhancement.equalizer.preset.as_deref(), Some("音箱"));
+        let err = manager.try_modify(|config| config.audio.rename_user_preset("音箱", "人声")).await.unwrap_err();
+        assert_eq!(err.kind(), "Conflict");
+
+        // 删除当前预设时保留增益，只清除名称
+        manager.modify(|config| config.audio.enhancement.equalizer.gains = preset.gains).await.unwrap();
+        let config = manager.try_modify(|config| config.audio.delete_user_preset("音箱")).await.unwrap();
+        assert!(config.audio.user_presets.is_empty());
+        assert_eq!(config.audio.enhancement.equalizer.preset, None);
+        assert_eq!(config.audio.enhancement.equalizer.gains, preset.gains);
+        let err = manager.try_modify(|config| config.audio.delete_user_preset("音箱")).await.unwrap_err();
+        assert_eq!(err.kind(), "NotFound");
+    }
+
+    #[test]
+    fn test_window_geometry_is_saved() {
+        let (db, manager) = manager();
+        let geometry = WindowGeometry { x: 100, y: 80, width: 1280, height: 860, maximized: false };
+        manager.save_window(geometry).unwrap();
+        assert_eq!(load_config(&db.lock().unwrap()).ui.window, Some(geometry));
+
+        // 最大化时保留之前的大小和位置
+        manager.save_window(WindowGeometry { x: 0, y: 0, width: 2560, height: 1400, maximized: true }).unwrap();
+        let geometry = WindowGeometry { maximized: true, ..geometry };
+        assert_eq!(load_config(&db.lock().unwrap()).ui.window, Some(geometry));
+
+        // 关闭“记住窗口”后不再记录
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(manager.modify(|config| config.ui.remember_window = false)).unwrap();
+        manager.save_window(WindowGeometry { x: 0, ..geometry }).unwrap();
+        assert_eq!(load_config(&db.lock().unwrap()).ui.window, Some(geometry));
+    }
+    #[tokio::test]
+    async fn test_reload_from_file() {
+        let dir = std::env::temp_dir().join(format!("windchime-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE_NAME);
+        let db = Arc::new(DbPool::single(Database::new(":memory:").unwrap()));
+
+        // 首次加载时写出设置文件，修改设置时同步更新
+        let manager = ConfigManager::load(Arc::clone(&db), Some(path.clone())).unwrap();
+        assert!(path.exists());
+        manager.update(serde_json::json!({ "audio": { "default_volume": 0.5 } })).await.unwrap();
+        assert_eq!(read_config_file(&path).unwrap().audio.default_volume, 0.5);
+
+        // 外部修改后重新读取，返回变化的设置项
+        let mut edited = manager.get().await;
+        edited.ui.theme = "dark".to_string();
+        edited.audio.enhancement.bass_boost.gain = 3.0;
+        write_config_file(&path, &edited).unwrap();
+        let (config, keys) = manager.reload().await.unwrap();
+        assert_eq!(keys, vec!["audio.enhancement.bass_boost.gain".to_string(), "ui.theme".to_string()]);
+        assert_eq!(config.ui.theme, "dark");
+        assert_eq!(load_config(&db.lock().unwrap()).ui.theme, "dark");
+        assert!(manager.reload().await.unwrap().1.is_empty());
+
+        // 格式错误时保留当前设置
+        fs::write(&path, "{ \"ui\": { \"theme\": ").unwrap();
+        assert_eq!(manager.reload().await.unwrap_err().kind(), "InvalidInput");
+        fs::write(&path, r#"{ "ui": { "theme": "purple" } }"#).unwrap();
+        assert_eq!(manager.reload().await.unwrap_err().kind(), "InvalidInput");
+        assert_eq!(manager.get().await.ui.theme, "dark");
+
+        // 程序关闭期间被修改的设置文件在启动时生效，有误时不覆盖
+        write_config_file(&path, &AppConfig::default()).unwrap();
+        let manager = ConfigManager::load(Arc::clone(&db), Some(path.clone())).unwrap();
+        assert_eq!(manager.get().await.ui.theme, "system");
+        fs::write(&path, "not json").unwrap();
+        let manager = ConfigManager::load(Arc::clone(&db), Some(path.clone())).unwrap();
+        assert_eq!(manager.get().await.ui.theme, "system");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not json");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
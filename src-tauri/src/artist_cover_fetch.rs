@@ -0,0 +1,168 @@
+// 批量获取艺术家封面
+//
+// 为曲库中还没有封面的艺术家请求 LrcApi，进度通过 artist-covers-fetch-progress 事件发送：
+// - 网络请求按每秒请求数限速（与批量获取歌词使用相同的范围）
+// - 图片缩小到 MAX_DIMENSION 以内再保存，艺术家列表不必加载原图
+// - 获取失败（多数是服务器上没有这位艺术家的图片）会记录下来，近期不再重复请求；force_refresh 时忽略该记录
+// - 可随时取消
+
+use crate::cover_cache;
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::lyrics_fetch::{request_interval, validate_rate};
+use crate::network_api::{NetworkApiService, NetworkConfig};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 保存的艺术家图片最大边长
+pub const MAX_DIMENSION: u32 = 600;
+
+/// 获取失败的艺术家在这段时间内不再重试（秒）
+pub const RETRY_AFTER_SECS: i64 = 30 * 24 * 3600;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 批量获取进度（artist-covers-fetch-progress 事件）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArtistCoverFetchProgress {
+    pub processed: usize,
+    pub found: usize,
+    pub failed: usize,
+    pub remaining: usize,
+    /// 正在处理的艺术家
+    pub current: Option<String>,
+    /// 任务已结束（完成或取消）
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// 取消正在进行的批量获取
+pub fn cancel() {
+    if is_running() {
+        CANCELLED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 开始批量获取，返回待处理的艺术家数
+pub fn start<F>(
+    db: Arc<DbPool>,
+    force_refresh: bool,
+    requests_per_second: f64,
+    network: NetworkConfig,
+    shutdown: &'static AtomicBool,
+    emit: F,
+) -> AppResult<usize>
+where
+    F: Fn(&ArtistCoverFetchProgress) + Send + Sync + 'static,
+{
+    let requests_per_second = validate_rate(requests_per_second).map_err(AppError::InvalidInput)?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::invalid_input("批量获取艺术家封面正在进行中"));
+    }
+
+    let attempted_before = if force_refresh {
+        i64::MAX
+    } else {
+        chrono::Utc::now().timestamp() - RETRY_AFTER_SECS
+    };
+    let artists = match db.with(|db| db.artists_missing_covers(attempted_before)) {
+        Ok(artists) => artists,
+        Err(e) => {
+            RUNNING.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+    };
+
+    let total = artists.len();
+    log::info!("🎤 开始批量获取艺术家封面: {} 位艺术家, {} 次请求/秒", total, requests_per_second);
+    CANCELLED.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn(async move {
+        let progress = run(&db, artists, requests_per_second, network, shutdown, &emit).await;
+        log::info!(
+            "🎤 批量获取艺术家封面结束: 处理 {}, 找到 {}, 失败 {}{}",
+            progress.processed,
+            progress.found,
+            progress.failed,
+            if progress.cancelled { "（已取消）" } else { "" }
+        );
+        RUNNING.store(false, Ordering::SeqCst);
+        emit(&progress);
+    });
+    Ok(total)
+}
+
+async fn run<F>(
+    db: &Arc<DbPool>,
+    artists: Vec<String>,
+    requests_per_second: f64,
+    network: NetworkConfig,
+    shutdown: &AtomicBool,
+    emit: &F,
+) -> ArtistCoverFetchProgress
+where
+    F: Fn(&ArtistCoverFetchProgress),
+{
+    let service = NetworkApiService::with_config(network);
+    let mut limiter = tokio::time::interval(request_interval(requests_per_second));
+    limiter.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let mut progress = ArtistCoverFetchProgress {
+        remaining: artists.len(),
+        ..Default::default()
+    };
+    emit(&progress);
+
+    for artist in artists {
+        limiter.tick().await;
+        if CANCELLED.load(Ordering::Relaxed) || shutdown.load(Ordering::Relaxed) {
+            progress.cancelled = true;
+            break;
+        }
+
+        let found = fetch_and_save(db, &service, &artist).await;
+        progress.processed += 1;
+        progress.remaining -= 1;
+        if found {
+            progress.found += 1;
+        } else {
+            progress.failed += 1;
+        }
+        progress.current = Some(artist);
+        emit(&progress);
+    }
+
+    progress.current = None;
+    progress.finished = true;
+    progress
+}
+
+/// 获取并保存一位艺术家的封面，获取失败时记录尝试时间
+async fn fetch_and_save(db: &Arc<DbPool>, service: &NetworkApiService, artist: &str) -> bool {
+    let image = match service.fetch_cover(None, artist, None).await {
+        Ok(cover) => tokio::task::spawn_blocking(move || cover_cache::make_thumbnail(&cover.data, MAX_DIMENSION))
+            .await
+            .unwrap_or_else(|e| Err(e.into())),
+        Err(e) => Err(e),
+    };
+
+    match image {
+        Ok(data) => match db.with(|db| db.save_artist_cover(artist, &data, cover_cache::THUMBNAIL_MIME)) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("⚠️ 保存艺术家封面失败: {} ({})", artist, e);
+                false
+            }
+        },
+        Err(e) => {
+            log::debug!("获取艺术家封面失败: {} ({})", artist, e);
+            let _ = db.with(|db| db.set_artist_cover_attempt(artist, chrono::Utc::now().timestamp()));
+            false
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// 多艺术家拆分
+//
+// 把 "A feat. B"、"A; B; C"、"A & B" 这类艺术家标签拆成各个艺术家，连同角色（主艺术家 / 客串）
+// 写入 track_artists 表；艺术家浏览、艺术家曲目和搜索结果按拆分后的艺术家匹配，
+// 曲目上显示的仍是原始标签文本。
+// 分隔符和不拆分的名称（如 "Simon & Garfunkel"）保存在应用设置的 artists 下，修改后重新拆分即可修正。
+
+use serde::{Deserialize, Serialize};
+
+/// 每个列表最多的条目数
+pub const MAX_ENTRIES: usize = 200;
+
+/// 艺术家在曲目中的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistRole {
+    Primary,
+    Featured,
+}
+
+impl ArtistRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArtistRole::Primary => "primary",
+            ArtistRole::Featured => "featured",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "featured" => ArtistRole::Featured,
+            _ => ArtistRole::Primary,
+        }
+    }
+}
+
+/// 拆分出的一个艺术家
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackArtist {
+    pub name: String,
+    pub role: ArtistRole,
+}
+
+impl TrackArtist {
+    fn new(name: &str, role: ArtistRole) -> Self {
+        Self { name: name.to_string(), role }
+    }
+}
+
+/// 拆分设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistSplitConfig {
+    /// 并列艺术家之间的分隔符
+    pub separators: Vec<String>,
+    /// 客串标记，之后的艺术家为客串（featured）
+    pub featuring: Vec<String>,
+    /// 不拆分的名称（不区分大小写），用于名字里带分隔符的乐队
+    pub exclusions: Vec<String>,
+}
+
+impl Default for ArtistSplitConfig {
+    fn default() -> Self {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            separators: list(&[";", "/", "&", "、"]),
+            featuring: list(&["feat.", "feat", "ft.", "featuring"]),
+            exclusions: list(&[
+                "Simon & Garfunkel",
+                "AC/DC",
+                "Earth, Wind & Fire",
+                "Hall & Oates",
+                "Mumford & Sons",
+                "Belle & Sebastian",
+                "Florence & The Machine",
+            ]),
+        }
+    }
+}
+
+impl ArtistSplitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, list) in [("分隔符", &self.separators), ("客串标记", &self.featuring), ("不拆分的名称", &self.exclusions)] {
+            if list.len() > MAX_ENTRIES {
+                return Err(format!("{}不能超过{}个", name, MAX_ENTRIES));
+            }
+            if list.iter().any(|item| item.trim().is_empty()) {
+                return Err(format!("{}不能为空", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 拆分艺术家标签；标签为空时返回空列表
+pub fn split_artists(text: &str, config: &ArtistSplitConfig) -> Vec<TrackArtist> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    // 较长的分隔符优先（featuring 先于 feat）
+    let mut separators: Vec<(&str, bool)> = config.featuring.iter()
+        .map(|s| (s.as_str(), true))
+        .chain(config.separators.iter().map(|s| (s.as_str(), false)))
+        .filter(|(s, _)| !s.trim().is_empty())
+        .collect();
+    separators.sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+
+    // 找出所有分隔符的位置：(起点, 终点, 是否为客串标记)
+    let mut cuts: Vec<(usize, usize, bool)> = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        match separators.iter().find(|(sep, _)| matches_at(text, i, sep)) {
+            Some((sep, featuring)) => {
+                cuts.push((i, i + sep.len(), *featuring));
+                i += sep.len();
+            }
+            None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    // 分隔符之间的各段
+    let pieces: Vec<(usize, usize)> = (0..=cuts.len())
+        .map(|k| {
+            let start = if k == 0 { 0 } else { cuts[k - 1].1 };
+            let end = cuts.get(k).map_or(text.len(), |cut| cut.0);
+            (start, end)
+        })
+        .collect();
+
+    let exclusions: Vec<String> = config.exclusions.iter().map(|e| e.trim().to_lowercase()).collect();
+    let mut artists: Vec<TrackArtist> = Vec::new();
+    let mut role = ArtistRole::Primary;
+    let mut k = 0;
+    while k < pieces.len() {
+        // 相邻几段合起来是不拆分的名称时作为一个艺术家（取最长的）
+        let end = (k + 1..pieces.len())
+            .rev()
+            .find(|&j| exclusions.contains(&clean(&text[pieces[k].0..pieces[j].1]).to_lowercase()))
+            .unwrap_or(k);
+        let name = clean(&text[pieces[k].0..pieces[end].1]);
+        let lower = name.to_lowercase();
+        if !name.is_empty() && !artists.iter().any(|a| a.name.to_lowercase() == lower) {
+            artists.push(TrackArtist::new(name, role));
+        }
+        if cuts.get(end).is_some_and(|cut| cut.2) {
+            role = ArtistRole::Featured;
+        }
+        k = end + 1;
+    }
+
+    if artists.is_empty() {
+        artists.push(TrackArtist::new(text, ArtistRole::Primary));
+    }
+    artists
+}
+
+/// 分隔符在 i 处出现（不区分大小写）；以字母数字开头或结尾的分隔符还要求是完整的词
+fn matches_at(text: &str, i: usize, sep: &str) -> bool {
+    let Some(candidate) = text.get(i..i + sep.len()) else {
+        return false;
+    };
+    if !candidate.eq_ignore_ascii_case(sep) {
+        return false;
+    }
+    let is_word = |c: char| c.is_alphanumeric();
+    if sep.starts_with(is_word) && text[..i].chars().next_back().is_some_and(is_word) {
+        return false;
+    }
+    if sep.ends_with(is_word) && text[i + sep.len()..].chars().next().is_some_and(is_word) {
+        return false;
+    }
+    true
+}
+
+/// 去掉两端的空白、括号和逗号，如 "A (" → "A"、" B)" → "B"
+fn clean(name: &str) -> &str {
+    name.trim_matches(|c: char| c.is_whitespace() || "()[]（）【】,，".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<(String, ArtistRole)> {
+        split_artists(text, &ArtistSplitConfig::default())
+            .into_iter()
+            .map(|a| (a.name, a.role))
+            .collect()
+    }
+
+    fn primary(name: &str) -> (String, ArtistRole) {
+        (name.to_string(), ArtistRole::Primary)
+    }
+
+    fn featured(name: &str) -> (String, ArtistRole) {
+        (name.to_string(), ArtistRole::Featured)
+    }
+
+    #[test]
+    fn test_split_separators_and_featuring() {
+        assert_eq!(split("Artist A feat. Artist B"), vec![primary("Artist A"), featured("Artist B")]);
+        assert_eq!(split("A; B; C"), vec![primary("A"), primary("B"), primary("C")]);
+        assert_eq!(split("A & B ft. C / D"), vec![primary("A"), primary("B"), featured("C"), featured("D")]);
+        assert_eq!(split("A (Feat. B)"), vec![primary("A"), featured("B")]);
+        assert_eq!(split("A featuring B"), vec![primary("A"), featured("B")]);
+        assert_eq!(split("周杰伦、费玉清"), vec![primary("周杰伦"), primary("费玉清")]);
+        // 重复的艺术家只保留一次
+        assert_eq!(split("A; a; B"), vec![primary("A"), primary("B")]);
+        assert_eq!(split("  "), vec![]);
+    }
+
+    #[test]
+    fn test_separator_words_need_boundaries() {
+        // 词的一部分不算客串标记
+        assert_eq!(split("Daft Punk"), vec![primary("Daft Punk")]);
+        assert_eq!(split("Feather"), vec![primary("Feather")]);
+        assert_eq!(split("Soft.Cell"), vec![primary("Soft.Cell")]);
+    }
+
+    #[test]
+    fn test_exclusions_are_not_split() {
+        assert_eq!(split("Simon & Garfunkel"), vec![primary("Simon & Garfunkel")]);
+        assert_eq!(split("ac/dc"), vec![primary("ac/dc")]);
+        assert_eq!(
+            split("Simon & Garfunkel feat. AC/DC & Someone"),
+            vec![primary("Simon & Garfunkel"), featured("AC/DC"), featured("Someone")]
+        );
+
+        let mut config = ArtistSplitConfig::default();
+        config.exclusions.push("Above & Beyond".to_string());
+        let artists = split_artists("Above & Beyond; Zoë", &config);
+        assert_eq!(artists, vec![TrackArtist::new("Above & Beyond", ArtistRole::Primary), TrackArtist::new("Zoë", ArtistRole::Primary)]);
+    }
+
+    #[test]
+    fn test_only_separators_keeps_text() {
+        assert_eq!(split("&"), vec![primary("&")]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(ArtistSplitConfig::default().validate().is_ok());
+        let mut config = ArtistSplitConfig::default();
+        config.separators.push(" ".to_string());
+        assert!(config.validate().is_err());
+    }
+}
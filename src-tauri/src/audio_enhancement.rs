@@ -2,7 +2,37 @@
 //
 // 提供音质增强配置和管理功能
 
+use crate::db::ReplayGainInfo;
+use crate::player::audio::loudness::loudness_to_gain_db;
+use crate::player::audio::parametric_eq::CorrectionProfile;
+use crate::player::audio::VolumeSettings;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::watch;
+
+/// 均衡器各频段增益范围（dB）
+pub const MIN_EQ_GAIN_DB: f32 = -12.0;
+pub const MAX_EQ_GAIN_DB: f32 = 12.0;
+
+/// 设置下发通道：设置命令发布，播放链路中的均衡器订阅
+static SETTINGS_TX: Lazy<watch::Sender<AudioEnhancementSettings>> =
+    Lazy::new(|| watch::channel(AudioEnhancementSettings::default()).0);
+
+/// 发布新的音质增强设置（正在播放的曲目立即生效）
+pub fn publish_settings(settings: AudioEnhancementSettings) {
+    SETTINGS_TX.send_replace(settings);
+}
+
+/// 订阅音质增强设置
+pub fn subscribe_settings() -> watch::Receiver<AudioEnhancementSettings> {
+    SETTINGS_TX.subscribe()
+}
+
+/// 当前生效的音质增强设置
+pub fn current_settings() -> AudioEnhancementSettings {
+    SETTINGS_TX.borrow().clone()
+}
 
 /// 音质增强设置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +54,22 @@ pub struct AudioEnhancementSettings {
     
     /// 采样率增强（上采样）
     pub upsampling: UpsamplingSettings,
+    
+    /// ReplayGain音量平衡（独立于enabled总开关）
+    #[serde(default)]
+    pub replay_gain: ReplayGainSettings,
+    
+    /// 单声道输出（独立于enabled总开关）
+    #[serde(default)]
+    pub mono: bool,
+    
+    /// 声道平衡（-1.0 全左 .. 1.0 全右，独立于enabled总开关），单声道输出时忽略
+    #[serde(default)]
+    pub balance: f32,
+    
+    /// 耳机频响校正（独立于enabled总开关），与图示均衡器叠加
+    #[serde(default)]
+    pub correction: HeadphoneCorrectionSettings,
 }
 
 impl Default for AudioEnhancementSettings {
@@ -35,10 +81,34 @@ impl Default for AudioEnhancementSettings {
             bass_boost: BassBoostSettings::default(),
             loudness_normalization: false,
             upsampling: UpsamplingSettings::default(),
+            replay_gain: ReplayGainSettings::default(),
+            mono: false,
+            balance: 0.0,
+            correction: HeadphoneCorrectionSettings::default(),
         }
     }
 }
 
+impl AudioEnhancementSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_equalizer_gains(&self.equalizer.gains)?;
+        if self.bass_boost.gain < 0.0 || self.bass_boost.gain > 12.0 {
+            return Err("低音增强必须在0到12dB之间".to_string());
+        }
+        let replay_gain = &self.replay_gain;
+        if !(-15.0..=15.0).contains(&replay_gain.preamp_db) || !(-15.0..=15.0).contains(&replay_gain.default_gain_db) {
+            return Err("ReplayGain前置放大和默认增益必须在-15dB到+15dB之间".to_string());
+        }
+        if !(-1.0..=1.0).contains(&self.balance) {
+            return Err("声道平衡必须在-1.0到1.0之间".to_string());
+        }
+        if let Some(profile) = &self.correction.profile {
+            profile.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// 均衡器设置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EqualizerSettings {
@@ -63,6 +133,25 @@ impl Default for EqualizerSettings {
     }
 }
 
+/// 校验均衡器各频段增益
+pub fn validate_equalizer_gains(gains: &[f32]) -> Result<(), String> {
+    if gains.iter().any(|g| !(MIN_EQ_GAIN_DB..=MAX_EQ_GAIN_DB).contains(g)) {
+        return Err(format!("均衡器增益必须在{}dB到+{}dB之间", MIN_EQ_GAIN_DB, MAX_EQ_GAIN_DB));
+    }
+    Ok(())
+}
+
+/// 耳机频响校正设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadphoneCorrectionSettings {
+    /// 是否启用校正
+    pub enabled: bool,
+    
+    /// 导入的校正配置（AutoEq 参数均衡）
+    pub profile: Option<CorrectionProfile>,
+}
+
 /// 音场设置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoundstageSettings {
@@ -132,6 +221,109 @@ impl Default for UpsamplingSettings {
     }
 }
 
+/// ReplayGain模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayGainMode {
+    /// 关闭
+    #[default]
+    Off,
+    /// 曲目增益（缺失时退回专辑增益）
+    Track,
+    /// 专辑增益（缺失时退回曲目增益）
+    Album,
+}
+
+/// ReplayGain设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayGainSettings {
+    /// 模式
+    pub mode: ReplayGainMode,
+    
+    /// 前置放大（dB）
+    pub preamp_db: f32,
+    
+    /// 既没有ReplayGain标签也没有响度分析结果时使用的增益（dB）
+    pub default_gain_db: f32,
+}
+
+impl Default for ReplayGainSettings {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp_db: 0.0,
+            default_gain_db: 0.0,
+        }
+    }
+}
+
+impl ReplayGainSettings {
+    /// 计算曲目实际应用的增益（dB），关闭时返回None
+    ///
+    /// 优先使用标签增益，其次使用后台响度分析的结果，最后使用默认增益。
+    pub fn resolve_gain_db(&self, info: Option<&ReplayGainInfo>) -> Option<f32> {
+        let info = info.copied().unwrap_or_default();
+        let tagged = match self.mode {
+            ReplayGainMode::Off => return None,
+            ReplayGainMode::Track => info.track_gain_db.or(info.album_gain_db),
+            ReplayGainMode::Album => info.album_gain_db.or(info.track_gain_db),
+        };
+        let gain = tagged
+            .or(info.loudness_lufs.map(loudness_to_gain_db))
+            .unwrap_or(self.default_gain_db);
+        Some(gain + self.preamp_db)
+    }
+}
+
+/// dB转换为线性放大倍数
+pub fn db_to_factor(gain_db: f32) -> f32 {
+    10f32.powf(gain_db / 20.0)
+}
+
+/// 曲目ReplayGain状态（发给前端显示）
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayGainStatus {
+    pub mode: ReplayGainMode,
+    pub track_gain_db: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    pub loudness_lufs: Option<f32>,
+    /// 实际应用的增益，关闭时为None
+    pub applied_gain_db: Option<f32>,
+}
+
+/// 用户保存的均衡器预设
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserEqualizerPreset {
+    /// 各频段增益（dB），顺序同 EqualizerSettings::gains
+    pub gains: [f32; 10],
+    /// 应用预设时一并设置的前置放大（dB），None表示不改变
+    #[serde(default)]
+    pub preamp_db: Option<f32>,
+}
+
+impl UserEqualizerPreset {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_equalizer_gains(&self.gains)?;
+        if let Some(preamp_db) = self.preamp_db {
+            VolumeSettings { preamp_db, ..Default::default() }.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// 预设名称 → 用户预设
+pub type UserEqualizerPresets = BTreeMap<String, UserEqualizerPreset>;
+
+/// 预设列表中的一项（内置预设只读）
+#[derive(Debug, Clone, Serialize)]
+pub struct EqualizerPresetInfo {
+    pub name: String,
+    pub gains: [f32; 10],
+    pub preamp_db: Option<f32>,
+    pub read_only: bool,
+}
+
 /// 均衡器预设
 pub struct EqualizerPresets;
 
@@ -156,6 +348,35 @@ impl EqualizerPresets {
             .find(|(n, _)| *n == name)
             .map(|(_, gains)| gains)
     }
+    
+    /// 是否为内置预设的名称
+    pub fn is_builtin(name: &str) -> bool {
+        Self::get(name).is_some()
+    }
+    
+    /// 按名称查找预设，内置预设优先，其次是用户预设
+    pub fn resolve(user_presets: &UserEqualizerPresets, name: &str) -> Option<UserEqualizerPreset> {
+        Self::get(name)
+            .map(|gains| UserEqualizerPreset { gains, preamp_db: None })
+            .or_else(|| user_presets.get(name).cloned())
+    }
+    
+    /// 内置预设和用户预设合并的列表，内置预设在前
+    pub fn list(user_presets: &UserEqualizerPresets) -> Vec<EqualizerPresetInfo> {
+        let builtin = Self::all().into_iter().map(|(name, gains)| EqualizerPresetInfo {
+            name: name.to_string(),
+            gains,
+            preamp_db: None,
+            read_only: true,
+        });
+        let user = user_presets.iter().map(|(name, preset)| EqualizerPresetInfo {
+            name: name.clone(),
+            gains: preset.gains,
+            preamp_db: preset.preamp_db,
+            read_only: false,
+        });
+        builtin.chain(user).collect()
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +404,96 @@ mod tests {
         assert_eq!(flat.unwrap(), [0.0; 10]);
     }
 
+    #[test]
+    fn test_user_presets_are_listed_and_resolved() {
+        let mut user_presets = UserEqualizerPresets::new();
+        let preset = UserEqualizerPreset { gains: [2.0; 10], preamp_db: Some(-3.0) };
+        user_presets.insert("我的耳机".to_string(), preset.clone());
+
+        let presets = EqualizerPresets::list(&user_presets);
+        assert_eq!(presets.len(), EqualizerPresets::all().len() + 1);
+        assert!(presets.iter().filter(|p| p.name != "我的耳机").all(|p| p.read_only));
+        assert!(!presets.last().unwrap().read_only);
+
+        assert_eq!(EqualizerPresets::resolve(&user_presets, "我的耳机"), Some(preset));
+        assert_eq!(EqualizerPresets::resolve(&user_presets, "平坦").map(|p| p.gains), Some([0.0; 10]));
+        assert_eq!(EqualizerPresets::resolve(&user_presets, "不存在"), None);
+    }
+
+    #[test]
+    fn test_user_preset_validation() {
+        assert!(UserEqualizerPreset { gains: [12.0; 10], preamp_db: Some(-12.0) }.validate().is_ok());
+        let mut gains = [0.0; 10];
+        gains[3] = 12.5;
+        assert!(UserEqualizerPreset { gains, preamp_db: None }.validate().is_err());
+        gains[3] = f32::NAN;
+        assert!(UserEqualizerPreset { gains, preamp_db: None }.validate().is_err());
+        assert!(UserEqualizerPreset { gains: [0.0; 10], preamp_db: Some(15.0) }.validate().is_err());
+    }
+
+    #[test]
+    fn test_replay_gain_resolution() {
+        let tagged = ReplayGainInfo { track_gain_db: Some(-6.0), album_gain_db: Some(-8.0), loudness_lufs: Some(-10.0) };
+        let track_only = ReplayGainInfo { track_gain_db: Some(-3.0), ..Default::default() };
+        let analyzed = ReplayGainInfo { loudness_lufs: Some(-11.0), ..Default::default() };
+        let mut settings = ReplayGainSettings::default();
+
+        assert_eq!(settings.resolve_gain_db(Some(&tagged)), None);
+
+        settings.mode = ReplayGainMode::Track;
+        settings.preamp_db = 2.0;
+        assert_eq!(settings.resolve_gain_db(Some(&tagged)), Some(-4.0));
+
+        settings.mode = ReplayGainMode::Album;
+        assert_eq!(settings.resolve_gain_db(Some(&tagged)), Some(-6.0));
+        // 缺少专辑增益时退回曲目增益
+        assert_eq!(settings.resolve_gain_db(Some(&track_only)), Some(-1.0));
+
+        // 没有标签时使用默认增益
+        settings.default_gain_db = -5.0;
+        assert_eq!(settings.resolve_gain_db(None), Some(-3.0));
+
+        // 没有标签时使用分析响度换算的增益（参考 -18 LUFS）
+        assert_eq!(settings.resolve_gain_db(Some(&analyzed)), Some(-5.0));
+
+        assert!((db_to_factor(-6.0) - 0.501).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_settings_without_replay_gain_still_deserialize() {
+        let mut json = serde_json::to_value(AudioEnhancementSettings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("replay_gain");
+        let settings: AudioEnhancementSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.replay_gain.mode, ReplayGainMode::Off);
+    }
+
+    #[test]
+    fn test_channel_mix_defaults_and_validation() {
+        let mut json = serde_json::to_value(AudioEnhancementSettings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("mono");
+        json.as_object_mut().unwrap().remove("balance");
+        let mut settings: AudioEnhancementSettings = serde_json::from_value(json).unwrap();
+        assert!(!settings.mono);
+        assert_eq!(settings.balance, 0.0);
+
+        settings.balance = -1.0;
+        assert!(settings.validate().is_ok());
+        settings.balance = 1.5;
+        assert!(settings.validate().is_err());
+        settings.balance = f32::NAN;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_correction_profile_is_validated() {
+        let mut settings = AudioEnhancementSettings::default();
+        settings.correction.profile = Some(CorrectionProfile::parse("HD 600", "Filter 1: ON PK Fc 1000 Hz Gain 2 dB Q 1").unwrap());
+        assert!(settings.validate().is_ok());
+
+        settings.correction.profile.as_mut().unwrap().filters[0].q = -1.0;
+        assert!(settings.validate().is_err());
+    }
+
     #[test]
     fn test_serialization() {
         let settings = AudioEnhancementSettings::default();
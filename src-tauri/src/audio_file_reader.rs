@@ -0,0 +1,197 @@
+// Web Audio 播放的分块文件读取
+//
+// 以前 read_audio_file 把整个文件读进内存再经IPC传给前端，几百MB的DSD文件在Rust和JS两侧
+// 各占一份，有时导致webview崩溃。现在前端先打开句柄，再按偏移分块读取：
+// - 只能打开音乐文件夹中的文件，路径规范化后比较，不能用 .. 或符号链接读取文件夹外的文件
+// - 同一窗口重复打开同一文件共用句柄并计数，全部关闭后才释放；窗口销毁时释放该窗口的所有句柄
+// - 句柄只能由打开它的窗口读取和关闭
+// - 每次最多读取 MAX_CHUNK_SIZE 字节
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::cast::audio_mime;
+use crate::error::{AppError, AppResult};
+
+/// 单次读取的最大字节数
+pub const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// 同时打开的句柄上限，前端忘记关闭时不会无限占用文件描述符
+const MAX_OPEN_FILES: usize = 32;
+
+/// 打开的音频文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioFileInfo {
+    pub handle: u64,
+    pub size: u64,
+    pub mime: &'static str,
+}
+
+struct OpenFile {
+    /// 打开文件的窗口
+    window: String,
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+    size: u64,
+    refs: usize,
+}
+
+/// 已打开的句柄
+#[derive(Default)]
+pub struct AudioFileRegistry {
+    next_handle: u64,
+    files: HashMap<u64, OpenFile>,
+}
+
+impl AudioFileRegistry {
+    /// 打开音乐文件夹中的文件；同一窗口已打开时增加引用计数并返回原句柄
+    pub fn open(&mut self, window: &str, path: &Path, music_folders: &[String]) -> AppResult<AudioFileInfo> {
+        let path = allowed_path(path, music_folders)?;
+        let mime = audio_mime(&path.to_string_lossy());
+        if let Some((&handle, open)) = self.files.iter_mut().find(|(_, f)| f.window == window && f.path == path) {
+            open.refs += 1;
+            return Ok(AudioFileInfo { handle, size: open.size, mime });
+        }
+        if self.files.len() >= MAX_OPEN_FILES {
+            return Err(AppError::conflict("打开的音频文件过多，请先关闭不再使用的文件"));
+        }
+
+        let file = File::open(&path)?;
+        let size = file.metadata()?.len();
+        self.next_handle += 1;
+        let handle = self.next_handle;
+        self.files.insert(handle, OpenFile {
+            window: window.to_string(),
+            path,
+            file: Arc::new(Mutex::new(file)),
+            size,
+            refs: 1,
+        });
+        Ok(AudioFileInfo { handle, size, mime })
+    }
+
+    /// 句柄对应的文件，读取在注册表的锁外进行
+    pub fn file(&self, handle: u64, window: &str) -> AppResult<Arc<Mutex<File>>> {
+        let open = self
+            .files
+            .get(&handle)
+            .ok_or_else(|| AppError::not_found(format!("音频文件句柄不存在: {}", handle)))?;
+        check_owner(open, handle, window)?;
+        Ok(open.file.clone())
+    }
+
+    /// 减少引用计数，最后一个引用关闭时释放文件
+    pub fn close(&mut self, handle: u64, window: &str) -> AppResult<()> {
+        let open = self
+            .files
+            .get_mut(&handle)
+            .ok_or_else(|| AppError::not_found(format!("音频文件句柄不存在: {}", handle)))?;
+        check_owner(open, handle, window)?;
+        open.refs -= 1;
+        if open.refs == 0 {
+            self.files.remove(&handle);
+        }
+        Ok(())
+    }
+
+    /// 释放窗口打开的所有句柄，返回释放的数量
+    pub fn close_window(&mut self, window: &str) -> usize {
+        let before = self.files.len();
+        self.files.retain(|_, open| open.window != window);
+        before - self.files.len()
+    }
+}
+
+/// 句柄必须由调用的窗口打开
+fn check_owner(open: &OpenFile, handle: u64, window: &str) -> AppResult<()> {
+    if open.window != window {
+        return Err(AppError::Unauthorized(format!("音频文件句柄 {} 不属于窗口 {}", handle, window)));
+    }
+    Ok(())
+}
+
+/// 从 offset 开始读取最多 len 字节（不超过 MAX_CHUNK_SIZE），到达文件末尾时返回的数据较短或为空
+pub fn read_chunk(file: &Mutex<File>, offset: u64, len: u64) -> AppResult<Vec<u8>> {
+    let mut file = file.lock();
+    file.seek(SeekFrom::Start(offset))?;
+    let mut chunk = Vec::with_capacity(len.min(MAX_CHUNK_SIZE) as usize);
+    (&mut *file).take(len.min(MAX_CHUNK_SIZE)).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+/// 规范化路径并确认位于某个音乐文件夹中
+fn allowed_path(path: &Path, music_folders: &[String]) -> AppResult<PathBuf> {
+    let path = path.canonicalize()?;
+    if !path.is_file() {
+        return Err(AppError::invalid_input(format!("不是文件: {}", path.display())));
+    }
+    let allowed = music_folders
+        .iter()
+        .filter_map(|folder| Path::new(folder).canonicalize().ok())
+        .any(|folder| path.starts_with(folder));
+    if !allowed {
+        return Err(AppError::Unauthorized(format!("文件不在音乐文件夹中: {}", path.display())));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_read_and_close() {
+        let root = std::env::temp_dir().join(format!("windchime-audio-read-{}", uuid::Uuid::new_v4()));
+        let music = root.join("music");
+        std::fs::create_dir_all(&music).unwrap();
+        let song = music.join("song.flac");
+        let data: Vec<u8> = (0..MAX_CHUNK_SIZE + 10).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&song, &data).unwrap();
+        let secret = root.join("secret.txt");
+        std::fs::write(&secret, b"secret").unwrap();
+        let folders = vec![music.to_string_lossy().to_string()];
+
+        let mut registry = AudioFileRegistry::default();
+        let info = registry.open("main", &song, &folders).unwrap();
+        assert_eq!(info.size, data.len() as u64);
+        assert_eq!(info.mime, "audio/flac");
+
+        // 单次读取不超过上限，末尾读取返回剩余部分
+        let file = registry.file(info.handle, "main").unwrap();
+        let chunk = read_chunk(&file, 0, u64::MAX).unwrap();
+        assert_eq!(chunk.len() as u64, MAX_CHUNK_SIZE);
+        assert_eq!(read_chunk(&file, MAX_CHUNK_SIZE, 100).unwrap(), &data[MAX_CHUNK_SIZE as usize..]);
+        assert!(read_chunk(&file, data.len() as u64 + 1, 100).unwrap().is_empty());
+
+        // 音乐文件夹外的文件不能打开，包括用 .. 跳出
+        assert!(matches!(registry.open("main", &secret, &folders), Err(AppError::Unauthorized(_))));
+        assert!(registry.open("main", &music.join("..").join("secret.txt"), &folders).is_err());
+
+        // 同一窗口重复打开共用句柄，全部关闭后释放
+        assert_eq!(registry.open("main", &song, &folders).unwrap().handle, info.handle);
+        registry.close(info.handle, "main").unwrap();
+        assert!(registry.file(info.handle, "main").is_ok());
+        registry.close(info.handle, "main").unwrap();
+        assert!(registry.file(info.handle, "main").is_err());
+
+        // 窗口销毁时释放该窗口的句柄
+        let other = registry.open("mini", &song, &folders).unwrap();
+        let main = registry.open("main", &song, &folders).unwrap();
+        assert_ne!(other.handle, main.handle);
+
+        // 其他窗口不能读取或关闭别人的句柄
+        assert!(matches!(registry.file(main.handle, "mini"), Err(AppError::Unauthorized(_))));
+        assert!(matches!(registry.close(main.handle, "mini"), Err(AppError::Unauthorized(_))));
+        assert!(registry.file(main.handle, "main").is_ok());
+
+        assert_eq!(registry.close_window("mini"), 1);
+        assert!(registry.file(other.handle, "mini").is_err());
+        assert!(registry.file(main.handle, "main").is_ok());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
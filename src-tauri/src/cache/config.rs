@@ -1,5 +1,6 @@
 // 缓存配置
 
+use super::CacheStrategy;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -41,6 +42,10 @@ pub struct CacheConfig {
     /// 是否启用缓存
     pub enabled: bool,
     
+    /// 缓存策略
+    #[serde(default)]
+    pub strategy: CacheStrategy,
+    
     /// 缓存路径（用户可自定义）
     pub cache_path: PathBuf,
     
@@ -79,6 +84,7 @@ impl Default for CacheConfig {
         
         Self {
             enabled: true,
+            strategy: CacheStrategy::Smart,
             cache_path,
             max_size_mb: 2048,  // 默认2GB
             auto_cache_favorites: true,
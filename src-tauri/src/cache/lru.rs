@@ -18,6 +18,12 @@ pub struct LruCache {
     
     /// 当前缓存大小（字节）
     current_size_bytes: u64,
+    
+    /// 正在播放的缓存文件，任何清理都不会删除它
+    protected_path: Option<String>,
+    
+    /// 已删除但尚未同步到数据库的文件路径
+    removed_paths: Vec<String>,
 }
 
 impl LruCache {
@@ -28,7 +34,46 @@ impl LruCache {
             cache_dir,
             max_size_bytes: max_size_mb * 1024 * 1024,
             current_size_bytes: 0,
+            protected_path: None,
+            removed_paths: Vec::new(),
+        }
+    }
+    
+    /// 从数据库记录加载缓存条目
+    pub fn load_entries(&mut self, entries: Vec<CacheEntry>) {
+        self.entries.clear();
+        self.current_size_bytes = entries.iter().map(|e| e.file_size).sum();
+        for entry in entries {
+            self.entries.insert(entry.track_id, entry);
+        }
+        
+        log::info!("加载缓存记录: {} 个文件, {:.2} MB", 
+            self.entries.len(),
+            self.current_size_bytes as f64 / 1024.0 / 1024.0
+        );
+    }
+    
+    /// 设置最大缓存大小，超出部分立即清理
+    pub fn set_max_size_bytes(&mut self, max_size_bytes: u64) -> Result<(), String> {
+        self.max_size_bytes = max_size_bytes;
+        if self.current_size_bytes > max_size_bytes {
+            self.make_space(self.current_size_bytes - max_size_bytes)?;
         }
+        Ok(())
+    }
+    
+    /// 设置正在播放的缓存文件
+    pub fn set_protected(&mut self, path: Option<String>) {
+        self.protected_path = path;
+    }
+    
+    fn is_protected(&self, entry: &CacheEntry) -> bool {
+        self.protected_path.as_deref() == Some(entry.file_path.as_str())
+    }
+    
+    /// 取出已删除的文件路径（用于同步数据库）
+    pub fn take_removed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.removed_paths)
     }
     
     /// 加载现有缓存（从磁盘扫描）
@@ -97,10 +142,19 @@ impl LruCache {
     
     /// 添加缓存条目
     pub fn add(&mut self, track_id: i64, file_path: PathBuf, file_size: u64, priority: CachePriority) -> Result<(), String> {
+        // 同一曲目重新缓存时替换旧条目
+        if let Some(old) = self.entries.remove(&track_id) {
+            self.current_size_bytes = self.current_size_bytes.saturating_sub(old.file_size);
+            if Path::new(&old.file_path) != file_path.as_path() {
+                let _ = std::fs::remove_file(&old.file_path);
+                self.removed_paths.push(old.file_path);
+            }
+        }
+        
         // 检查空间
         if self.current_size_bytes + file_size > self.max_size_bytes {
             // 需要清理空间
-            self.make_space(file_size)?;
+            self.make_space(self.current_size_bytes + file_size - self.max_size_bytes)?;
         }
         
         let entry = CacheEntry {
@@ -129,6 +183,11 @@ impl LruCache {
         }
     }
     
+    /// 是否已固定
+    pub fn is_pinned(&self, track_id: i64) -> bool {
+        self.entries.get(&track_id).is_some_and(|e| e.priority == CachePriority::High)
+    }
+    
     /// 更新优先级
     pub fn set_priority(&mut self, track_id: i64, priority: CachePriority) {
         if let Some(entry) = self.entries.get_mut(&track_id) {
@@ -147,8 +206,8 @@ impl LruCache {
         let mut to_remove = Vec::new();
         
         for entry in sorted_entries {
-            // 高优先级的不删除
-            if entry.priority == CachePriority::High {
+            // 高优先级（已固定）和正在播放的不删除
+            if entry.priority == CachePriority::High || self.is_protected(entry) {
                 continue;
             }
             
@@ -186,6 +245,7 @@ impl LruCache {
             }
             
             self.current_size_bytes = self.current_size_bytes.saturating_sub(entry.file_size);
+            self.removed_paths.push(entry.file_path);
         }
         
         Ok(())
@@ -199,8 +259,8 @@ impl LruCache {
         let mut to_remove = Vec::new();
         
         for (track_id, entry) in &self.entries {
-            // 高优先级的不清理
-            if entry.priority == CachePriority::High {
+            // 高优先级和正在播放的不清理
+            if entry.priority == CachePriority::High || self.is_protected(entry) {
                 continue;
             }
             
@@ -243,9 +303,12 @@ impl LruCache {
         )
     }
     
-    /// 清空所有缓存
+    /// 清空所有缓存（正在播放的文件除外）
     pub fn clear_all(&mut self) -> Result<(), String> {
-        let track_ids: Vec<_> = self.entries.keys().copied().collect();
+        let track_ids: Vec<_> = self.entries.values()
+            .filter(|entry| !self.is_protected(entry))
+            .map(|entry| entry.track_id)
+            .collect();
         
         for track_id in track_ids {
             self.remove(track_id)?;
@@ -262,8 +325,55 @@ impl LruCache {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn entry(dir: &Path, track_id: i64, size: u64, priority: CachePriority, age_days: i64) -> CacheEntry {
+        let path = dir.join(format!("{}.flac", track_id));
+        std::fs::write(&path, vec![0u8; size as usize]).unwrap();
+        let accessed = Utc::now().timestamp() - age_days * 86400;
+        CacheEntry {
+            track_id,
+            file_path: path.to_string_lossy().to_string(),
+            file_size: size,
+            priority,
+            play_count: 0,
+            last_played: accessed,
+            created_at: accessed,
+            last_accessed: accessed,
+        }
+    }
 
+    #[test]
+    fn test_eviction_skips_pinned_and_playing() {
+        let dir = std::env::temp_dir().join(format!("wcp_lru_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
 
+        let mut lru = LruCache::new(dir.clone(), 1);
+        let limit = 1024 * 1024;
+        let pinned = entry(&dir, 1, limit / 4, CachePriority::High, 300);
+        let playing = entry(&dir, 2, limit / 4, CachePriority::Medium, 200);
+        let oldest = entry(&dir, 3, limit / 4, CachePriority::Medium, 100);
+        let recent = entry(&dir, 4, limit / 4, CachePriority::Medium, 0);
+        lru.load_entries(vec![pinned, playing.clone(), oldest.clone(), recent]);
+        lru.set_protected(Some(playing.file_path.clone()));
 
+        let new_path = dir.join("5.flac");
+        lru.add(5, new_path, limit / 4, CachePriority::Medium).unwrap();
 
+        // 固定和正在播放的条目得分更低也不会被删除，删除的是剩余最低分的条目
+        assert!(lru.contains(1));
+        assert!(lru.contains(2));
+        assert!(!lru.contains(3));
+        assert!(lru.contains(4));
+        assert!(!Path::new(&oldest.file_path).exists());
+        assert_eq!(lru.take_removed(), vec![oldest.file_path]);
+
+        lru.clear_all().unwrap();
+        assert!(lru.contains(2));
+        assert!(Path::new(&playing.file_path).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
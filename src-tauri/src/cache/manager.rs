@@ -1,37 +1,143 @@
 // 智能缓存管理器
 
-use super::{CacheConfig, CachePriority, CacheStats};
+use super::{CacheConfig, CacheEntry, CachePriority, CacheStats, CacheStrategy};
 use super::lru::LruCache;
-use std::path::PathBuf;
+use crate::db_pool::DbPool;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 /// 缓存管理器
+///
+/// 远程曲目的缓存文件登记在 remote_cache 表中，启动时从表中加载，
+/// 清理时同步删除对应记录。
 pub struct CacheManager {
     config: Arc<Mutex<CacheConfig>>,
     lru: Arc<Mutex<LruCache>>,
     stats: Arc<Mutex<CacheStats>>,
+    db: Arc<DbPool>,
 }
 
 impl CacheManager {
     /// 创建新的缓存管理器
-    pub fn new(config: CacheConfig) -> Result<Self, String> {
+    pub fn new(config: CacheConfig, db: Arc<DbPool>) -> Result<Self, String> {
         config.validate()?;
         
         let cache_dir = config.ensure_cache_dir()
             .map_err(|e| format!("创建缓存目录失败: {}", e))?;
         
         let mut lru = LruCache::new(cache_dir, config.max_size_mb);
-        lru.load_existing()
-            .map_err(|e| format!("加载现有缓存失败: {}", e))?;
+        lru.load_entries(Self::load_records(&db)?);
         
-        Ok(Self {
+        let manager = Self {
             config: Arc::new(Mutex::new(config)),
             lru: Arc::new(Mutex::new(lru)),
             stats: Arc::new(Mutex::new(CacheStats::default())),
-        })
+            db,
+        };
+        manager.update_stats();
+        
+        Ok(manager)
+    }
+    
+    /// 从数据库加载缓存记录，文件已丢失的记录直接删除
+    fn load_records(db: &Arc<DbPool>) -> Result<Vec<CacheEntry>, String> {
+        let db = db.lock().map_err(|e| e.to_string())?;
+        let records = db.get_cache_records().map_err(|e| format!("加载缓存记录失败: {}", e))?;
+        
+        let mut entries = Vec::new();
+        for record in records {
+            let track_id = match record.track_id {
+                Some(id) if Path::new(&record.local_cache_path).exists() => id,
+                _ => {
+                    let _ = db.remove_cache_entry(&record.local_cache_path);
+                    continue;
+                }
+            };
+            entries.push(CacheEntry {
+                track_id,
+                file_path: record.local_cache_path,
+                file_size: record.file_size.max(0) as u64,
+                priority: if record.pinned { CachePriority::High } else { CachePriority::Medium },
+                play_count: record.access_count.max(0) as u32,
+                last_played: record.last_accessed,
+                created_at: record.cached_at,
+                last_accessed: record.last_accessed,
+            });
+        }
+        Ok(entries)
+    }
+    
+    /// 登记已写入磁盘的远程曲目缓存，超出容量时按得分清理
+    pub fn register_remote(
+        &self,
+        track_id: i64,
+        server_id: &str,
+        remote_path: &str,
+        file_path: &Path,
+        file_size: u64,
+    ) -> Result<(), String> {
+        let pinned = {
+            let mut lru = self.lru.lock();
+            let pinned = lru.is_pinned(track_id);
+            let priority = if pinned { CachePriority::High } else { CachePriority::Medium };
+            let result = lru.add(track_id, file_path.to_path_buf(), file_size, priority);
+            self.sync_removed(&mut lru);
+            result?;
+            pinned
+        };
+        
+        {
+            let db = self.db.lock().map_err(|e| e.to_string())?;
+            db.add_cache_entry(
+                server_id,
+                remote_path,
+                &file_path.to_string_lossy(),
+                Some(file_size as i64),
+                None,
+                Some(track_id),
+            ).map_err(|e| e.to_string())?;
+            if pinned {
+                db.set_cache_pinned(track_id, true).map_err(|e| e.to_string())?;
+            }
+        }
+        
+        self.update_stats();
+        log::info!("💾 远程曲目已缓存: track_id={}, {:.2} MB", track_id, file_size as f64 / 1024.0 / 1024.0);
+        Ok(())
+    }
+    
+    /// 设置正在播放的缓存文件（不会被清理）
+    pub fn set_playing(&self, path: Option<&Path>) {
+        self.lru.lock().set_protected(path.map(|p| p.to_string_lossy().to_string()));
+    }
+    
+    /// 固定曲目缓存（高优先级，永不被自动清理）
+    pub fn set_pinned(&self, track_id: i64, pinned: bool) -> Result<(), String> {
+        let priority = if pinned { CachePriority::High } else { CachePriority::Medium };
+        self.lru.lock().set_priority(track_id, priority);
+        self.db.lock().map_err(|e| e.to_string())?
+            .set_cache_pinned(track_id, pinned)
+            .map_err(|e| e.to_string())?;
+        self.update_stats();
+        Ok(())
+    }
+    
+    /// 把LRU中已删除的文件同步到数据库
+    fn sync_removed(&self, lru: &mut LruCache) {
+        let removed = lru.take_removed();
+        if removed.is_empty() {
+            return;
+        }
+        if let Ok(db) = self.db.lock() {
+            for path in removed {
+                if let Err(e) = db.remove_cache_entry(&path) {
+                    log::warn!("删除缓存记录失败: {} - {}", path, e);
+                }
+            }
+        }
     }
     
     /// 更新配置
@@ -52,10 +158,17 @@ impl CacheManager {
         if new_config.max_size_mb < config.max_size_mb {
             log::info!("缓存大小限制变小: {} MB -> {} MB", 
                 config.max_size_mb, new_config.max_size_mb);
-            // LRU会自动清理
         }
         
+        // 按新上限清理超出部分
+        lru.set_max_size_bytes(new_config.max_size_mb * 1024 * 1024)?;
+        self.sync_removed(&mut lru);
+        
         *config = new_config;
+        drop(lru);
+        drop(config);
+        
+        self.update_stats();
         
         Ok(())
     }
@@ -76,36 +189,40 @@ impl CacheManager {
     }
     
     /// 判断是否应该缓存此曲目
-    pub fn should_cache(
-        &self,
-        _track_id: i64,
-        is_favorite: bool,
-        play_count: u32,
-        last_played_days_ago: u32,
-    ) -> (bool, CachePriority) {
-        let config = self.config.lock();
-        
-        if !config.enabled {
-            return (false, CachePriority::Low);
-        }
-        
-        // 规则1：收藏的歌曲 → 高优先级，必定缓存
-        if config.auto_cache_favorites && is_favorite {
-            return (true, CachePriority::High);
-        }
-        
-        // 规则2：播放次数 >= min_play_count → 中优先级，缓存
-        if play_count >= config.min_play_count {
-            return (true, CachePriority::Medium);
-        }
-        
-        // 规则3：最近N天播放过 → 中优先级，缓存
-        if last_played_days_ago <= config.cache_recent_days {
-            return (true, CachePriority::Medium);
-        }
-        
-        // 规则4：第一次播放 → 不缓存，流式播放
-        (false, CachePriority::Low)
+    pub fn should_cache(&self, is_favorite: bool, play_count: u32) -> (bool, CachePriority) {
+        decide_cache(&self.config.lock(), is_favorite, play_count)
+    }
+    
+    /// 根据收藏状态和播放历史判断远程曲目是否需要完整下载并缓存
+    ///
+    /// 在播放流程中调用，查询放到读连接池的阻塞线程上，不占用写锁也不阻塞异步运行时
+    pub async fn should_cache_track(&self, track_id: i64) -> bool {
+        let (is_favorite, play_count) = self
+            .db
+            .run_read(move |db| {
+                Ok((
+                    db.is_favorite(track_id).unwrap_or(false),
+                    db.get_play_count(track_id).unwrap_or(0),
+                ))
+            })
+            .await
+            .unwrap_or((false, 0));
+        let (cache, priority) = self.should_cache(is_favorite, play_count.max(0) as u32);
+        log::debug!("缓存决策: track_id={}, favorite={}, plays={} → {} ({:?})",
+            track_id, is_favorite, play_count, cache, priority);
+        cache
+    }
+    
+    /// 当前缓存策略
+    pub fn get_strategy(&self) -> CacheStrategy {
+        self.config.lock().strategy
+    }
+    
+    /// 切换缓存策略，立即对之后播放的曲目生效
+    pub fn set_strategy(&self, strategy: CacheStrategy) -> CacheConfig {
+        let mut config = self.config.lock();
+        config.strategy = strategy;
+        config.clone()
     }
     
     /// 添加缓存文件
@@ -145,7 +262,9 @@ impl CacheManager {
         // 添加到LRU
         {
             let mut lru = self.lru.lock();
-            lru.add(track_id, cache_path.clone(), file_size, priority)?;
+            let result = lru.add(track_id, cache_path.clone(), file_size, priority);
+            self.sync_removed(&mut lru);
+            result?;
         }
         
         // 更新统计
@@ -176,6 +295,8 @@ impl CacheManager {
     pub fn remove_cache(&self, track_id: i64) -> Result<(), String> {
         let mut lru = self.lru.lock();
         lru.remove(track_id)?;
+        self.sync_removed(&mut lru);
+        drop(lru);
         
         self.update_stats();
         
@@ -190,6 +311,7 @@ impl CacheManager {
         
         let mut lru = self.lru.lock();
         let count = lru.cleanup_old(days)?;
+        self.sync_removed(&mut lru);
         drop(lru);
         
         self.update_stats();
@@ -203,6 +325,7 @@ impl CacheManager {
     pub fn clear_all(&self) -> Result<(), String> {
         let mut lru = self.lru.lock();
         lru.clear_all()?;
+        self.sync_removed(&mut lru);
         drop(lru);
         
         self.update_stats();
@@ -237,5 +360,65 @@ impl CacheManager {
     }
 }
 
-// TODO: 添加单元测试
+/// 按缓存策略判断曲目是否需要缓存
+///
+/// - StreamOnly：从不写入磁盘
+/// - CacheAll：始终缓存，收藏的曲目为高优先级
+/// - Smart：收藏的曲目必定缓存，播放次数达到阈值的曲目缓存，其余仅流式播放
+pub fn decide_cache(config: &CacheConfig, is_favorite: bool, play_count: u32) -> (bool, CachePriority) {
+    if !config.enabled {
+        return (false, CachePriority::Low);
+    }
+    
+    let favorite = config.auto_cache_favorites && is_favorite;
+    match config.strategy {
+        CacheStrategy::StreamOnly => (false, CachePriority::Low),
+        CacheStrategy::CacheAll if favorite => (true, CachePriority::High),
+        CacheStrategy::CacheAll => (true, CachePriority::Medium),
+        CacheStrategy::Smart if favorite => (true, CachePriority::High),
+        CacheStrategy::Smart if play_count >= config.min_play_count => (true, CachePriority::Medium),
+        CacheStrategy::Smart => (false, CachePriority::Low),
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config(strategy: CacheStrategy) -> CacheConfig {
+        CacheConfig { strategy, ..CacheConfig::default() }
+    }
+    
+    #[test]
+    fn test_stream_only_never_caches() {
+        let config = config(CacheStrategy::StreamOnly);
+        assert_eq!(decide_cache(&config, false, 0), (false, CachePriority::Low));
+        assert_eq!(decide_cache(&config, true, 100), (false, CachePriority::Low));
+    }
+    
+    #[test]
+    fn test_cache_all_always_caches() {
+        let config = config(CacheStrategy::CacheAll);
+        assert_eq!(decide_cache(&config, false, 0), (true, CachePriority::Medium));
+        assert_eq!(decide_cache(&config, true, 0), (true, CachePriority::High));
+        
+        let disabled = CacheConfig { enabled: false, ..config };
+        assert_eq!(decide_cache(&disabled, true, 10), (false, CachePriority::Low));
+    }
+    
+    #[test]
+    fn test_smart_uses_favorites_and_play_count() {
+        let config = config(CacheStrategy::Smart);
+        assert_eq!(decide_cache(&config, true, 0), (true, CachePriority::High));
+        assert_eq!(decide_cache(&config, false, config.min_play_count - 1), (false, CachePriority::Low));
+        assert_eq!(decide_cache(&config, false, config.min_play_count), (true, CachePriority::Medium));
+    }
+    
+    #[test]
+    fn test_strategy_defaults_to_smart_for_old_settings() {
+        let mut json: serde_json::Value = serde_json::to_value(CacheConfig::default()).unwrap();
+        json.as_object_mut().unwrap().remove("strategy");
+        let config: CacheConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.strategy, CacheStrategy::Smart);
+    }
+}
@@ -15,11 +15,12 @@ pub use stats::CacheStats;
 use serde::{Deserialize, Serialize};
 
 /// 缓存策略
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum CacheStrategy {
     /// 仅流式播放，不缓存
     StreamOnly,
     /// 智能缓存（推荐）
+    #[default]
     Smart,
     /// 全部缓存
     CacheAll,
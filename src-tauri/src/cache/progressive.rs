@@ -7,6 +7,7 @@ use parking_lot::Mutex;
 use bytes::Bytes;
 use tokio::io::AsyncWriteExt;
 use std::path::PathBuf;
+use crate::streaming::bandwidth::{self, TrafficClass};
 
 /// 下载状态
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -138,11 +139,13 @@ impl ProgressiveDownloader {
         
         use futures::StreamExt;
         
+        let bandwidth = bandwidth::manager();
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.map_err(|e| {
                 self.progress.lock().state = DownloadState::Failed;
                 format!("读取数据失败: {}", e)
             })?;
+            bandwidth.acquire(TrafficClass::Prefetch, chunk.len()).await;
             
             file.write_all(&chunk).await.map_err(|e| {
                 self.progress.lock().state = DownloadState::Failed;
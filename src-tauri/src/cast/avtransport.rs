@@ -0,0 +1,197 @@
+// AVTransport / RenderingControl - 通过SOAP控制远程设备的播放和音量
+
+use super::ssdp::{AV_TRANSPORT, RENDERING_CONTROL};
+use super::CastDevice;
+use crate::player::Track;
+use anyhow::{anyhow, Result};
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// 设备上的播放状态（CurrentTransportState）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportState {
+    Playing,
+    Paused,
+    Stopped,
+    /// 正在加载（TRANSITIONING）
+    Transitioning,
+}
+
+impl TransportState {
+    fn parse(value: &str) -> Self {
+        match value {
+            "PLAYING" => TransportState::Playing,
+            "PAUSED_PLAYBACK" | "PAUSED_RECORDING" => TransportState::Paused,
+            "TRANSITIONING" => TransportState::Transitioning,
+            // STOPPED、NO_MEDIA_PRESENT
+            _ => TransportState::Stopped,
+        }
+    }
+}
+
+/// 远程设备（MediaRenderer）
+pub struct Renderer {
+    client: reqwest::Client,
+    av_transport_url: String,
+    rendering_control_url: Option<String>,
+}
+
+impl Renderer {
+    pub fn new(client: reqwest::Client, device: &CastDevice) -> Self {
+        Self {
+            client,
+            av_transport_url: device.av_transport_url.clone(),
+            rendering_control_url: device.rendering_control_url.clone(),
+        }
+    }
+
+    /// 设置播放地址并开始播放
+    pub async fn play_uri(&self, uri: &str, metadata: &str) -> Result<()> {
+        self.transport("SetAVTransportURI", &[("CurrentURI", uri), ("CurrentURIMetaData", metadata)]).await?;
+        self.play().await
+    }
+
+    pub async fn play(&self) -> Result<()> {
+        self.transport("Play", &[("Speed", "1")]).await.map(|_| ())
+    }
+
+    pub async fn pause(&self) -> Result<()> {
+        self.transport("Pause", &[]).await.map(|_| ())
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.transport("Stop", &[]).await.map(|_| ())
+    }
+
+    pub async fn seek(&self, position_ms: u64) -> Result<()> {
+        let target = format_time(position_ms);
+        self.transport("Seek", &[("Unit", "REL_TIME"), ("Target", &target)]).await.map(|_| ())
+    }
+
+    /// 当前播放位置（毫秒）
+    pub async fn position(&self) -> Result<u64> {
+        let response = self.transport("GetPositionInfo", &[]).await?;
+        Ok(response_value(&response, "RelTime").and_then(|t| parse_time(&t)).unwrap_or(0))
+    }
+
+    pub async fn transport_state(&self) -> Result<TransportState> {
+        let response = self.transport("GetTransportInfo", &[]).await?;
+        response_value(&response, "CurrentTransportState")
+            .map(|state| TransportState::parse(&state))
+            .ok_or_else(|| anyhow!("设备未返回播放状态"))
+    }
+
+    /// 设置音量（0.0 - 1.0），设备没有 RenderingControl 服务时忽略
+    pub async fn set_volume(&self, volume: f32) -> Result<()> {
+        let Some(url) = &self.rendering_control_url else {
+            log::debug!("投屏设备不支持音量控制");
+            return Ok(());
+        };
+        let volume = ((volume.clamp(0.0, 1.0) * 100.0).round() as u32).to_string();
+        let args = [("Channel", "Master"), ("DesiredVolume", volume.as_str())];
+        self.call(url, RENDERING_CONTROL, "SetVolume", &args).await.map(|_| ())
+    }
+
+    async fn transport(&self, action: &str, args: &[(&str, &str)]) -> Result<String> {
+        self.call(&self.av_transport_url, AV_TRANSPORT, action, args).await
+    }
+
+    async fn call(&self, url: &str, service: &str, action: &str, args: &[(&str, &str)]) -> Result<String> {
+        let response = self
+            .client
+            .post(url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("SOAPACTION", format!("\"{}#{}\"", service, action))
+            .body(envelope(service, action, args))
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let detail = response_value(&body, "errorDescription").unwrap_or_else(|| status.to_string());
+            return Err(anyhow!("{} 失败: {}", action, detail));
+        }
+        Ok(body)
+    }
+}
+
+/// SOAP请求体（InstanceID 固定为0）
+pub(super) fn envelope(service: &str, action: &str, args: &[(&str, &str)]) -> String {
+    let args: String = args
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape(*value)))
+        .collect();
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" ",
+            "s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">",
+            "<s:Body><u:{action} xmlns:u=\"{service}\"><InstanceID>0</InstanceID>{args}</u:{action}></s:Body>",
+            "</s:Envelope>"
+        ),
+        action = action,
+        service = service,
+        args = args
+    )
+}
+
+/// 曲目的 DIDL-Lite 描述（设备用来显示标题、艺术家）
+pub(super) fn didl_metadata(track: &Track, uri: &str, mime: &str) -> String {
+    let text = |value: &Option<String>| escape(value.as_deref().unwrap_or_default()).to_string();
+    let title = track.title.clone().or_else(|| {
+        std::path::Path::new(&track.path).file_stem().map(|stem| stem.to_string_lossy().to_string())
+    });
+    format!(
+        concat!(
+            "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" ",
+            "xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">",
+            "<item id=\"{id}\" parentID=\"0\" restricted=\"1\">",
+            "<dc:title>{title}</dc:title><dc:creator>{artist}</dc:creator>",
+            "<upnp:artist>{artist}</upnp:artist><upnp:album>{album}</upnp:album>",
+            "<upnp:class>object.item.audioItem.musicTrack</upnp:class>",
+            "<res protocolInfo=\"http-get:*:{mime}:*\">{uri}</res>",
+            "</item></DIDL-Lite>"
+        ),
+        id = track.id,
+        title = text(&title),
+        artist = text(&track.artist),
+        album = text(&track.album),
+        mime = mime,
+        uri = escape(uri)
+    )
+}
+
+/// 取出响应中第一个同名元素的文本
+fn response_value(xml: &str, name: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut inside = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => inside = e.local_name().as_ref() == name.as_bytes(),
+            Ok(Event::Text(e)) if inside => return e.unescape().ok().map(|t| t.trim().to_string()),
+            Ok(Event::End(_)) if inside => return Some(String::new()),
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// 毫秒转为 H:MM:SS
+pub(super) fn format_time(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// 解析 H:MM:SS[.mmm]，NOT_IMPLEMENTED 等无效值返回None
+pub(super) fn parse_time(value: &str) -> Option<u64> {
+    let (hms, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let parts: Vec<u64> = hms.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return None;
+    };
+    let millis = format!("{:0<3}", fraction.chars().take(3).collect::<String>()).parse().unwrap_or(0);
+    Some((hours * 3600 + minutes * 60 + seconds) * 1000 + millis)
+}
@@ -0,0 +1,289 @@
+// 投屏媒体服务 - 通过临时HTTP地址把正在投屏的曲目提供给远程设备
+//
+// - 只提供已发布的曲目，地址中带随机键，不能按路径访问任意文件
+// - 本地文件支持 Range 请求（设备跳转时使用）
+// - 远程曲目（WebDAV / Subsonic）转发设备的请求，认证信息不会交给设备；
+//   使用服务器自己的客户端（证书、自定义CA、证书固定），转发的数据计入播放带宽
+// - 请求由固定数量的工作线程处理，排队已满时返回503
+
+use bytes::{Buf, Bytes};
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::net::{IpAddr, UdpSocket};
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::streaming::{bandwidth, TrafficClass};
+use crate::webdav::WebDAVClient;
+
+/// 处理请求的工作线程数（设备常同时发起多个Range请求）
+const WORKER_COUNT: usize = 4;
+
+/// 等待工作线程处理的请求上限
+const QUEUE_CAPACITY: usize = 8;
+
+/// 临时HTTP服务
+pub struct MediaServer {
+    server: Arc<tiny_http::Server>,
+    thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+    port: u16,
+    /// 随机键 -> 曲目路径
+    routes: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl MediaServer {
+    /// 在所有网卡的随机端口上启动
+    pub fn start() -> Result<Self, String> {
+        let server = tiny_http::Server::http("0.0.0.0:0").map_err(|e| format!("启动投屏媒体服务失败: {}", e))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| "无法获取投屏媒体服务端口".to_string())?;
+        let server = Arc::new(server);
+        let routes: Arc<Mutex<HashMap<String, String>>> = Arc::default();
+
+        // 接收线程退出时关闭队列，工作线程处理完手上的请求后退出
+        let (queue_tx, queue_rx) = crossbeam_channel::bounded::<tiny_http::Request>(QUEUE_CAPACITY);
+        for index in 0..WORKER_COUNT {
+            let queue_rx = queue_rx.clone();
+            let routes = Arc::clone(&routes);
+            std::thread::Builder::new()
+                .name(format!("cast-media-{}", index))
+                .spawn(move || {
+                    for request in queue_rx {
+                        handle(request, &routes);
+                    }
+                })
+                .map_err(|e| format!("启动投屏媒体服务线程失败: {}", e))?;
+        }
+
+        let thread_server = Arc::clone(&server);
+        let thread = std::thread::Builder::new()
+            .name("cast-media".to_string())
+            .spawn(move || {
+                for request in thread_server.incoming_requests() {
+                    if let Err(crossbeam_channel::TrySendError::Full(request)) = queue_tx.try_send(request) {
+                        let _ = request.respond(tiny_http::Response::empty(503));
+                    }
+                }
+            })
+            .map_err(|e| format!("启动投屏媒体服务线程失败: {}", e))?;
+
+        log::info!("📡 投屏媒体服务已启动: 端口 {}", port);
+        Ok(Self {
+            server,
+            thread: Mutex::new(Some(thread)),
+            port,
+            routes,
+        })
+    }
+
+    /// 发布曲目，返回设备可访问的地址；只保留最新发布的曲目
+    pub fn publish(&self, track_path: &str, host: IpAddr) -> String {
+        let key = uuid::Uuid::new_v4().simple().to_string();
+        let mut routes = self.routes.lock();
+        routes.clear();
+        routes.insert(key.clone(), track_path.to_string());
+        format!("http://{}:{}/media/{}.{}", host, self.port, key, extension(track_path))
+    }
+
+    /// 关闭服务并等待线程退出
+    pub fn stop(&self) {
+        self.server.unblock();
+        let thread = self.thread.lock().take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+        log::info!("投屏媒体服务已关闭");
+    }
+}
+
+/// 本机上能访问到设备的地址（UDP connect 只选择路由，不发送数据）
+pub fn local_address_for(device_host: IpAddr) -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect((device_host, 9)).ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+fn extension(path: &str) -> String {
+    std::path::Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_else(|| "mp3".to_string())
+}
+
+/// 按扩展名推断MIME类型
+pub fn audio_mime(path: &str) -> &'static str {
+    match extension(path).as_str() {
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "m4a" | "mp4" | "aac" => "audio/mp4",
+        "aiff" | "aif" => "audio/aiff",
+        "ape" => "audio/x-ape",
+        "wma" => "audio/x-ms-wma",
+        _ => "audio/mpeg",
+    }
+}
+
+/// 解析 Range: bytes=start-[end]，返回包含两端的字节范围；无效时返回None
+pub(super) fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // bytes=-500 表示最后500字节
+        ("", suffix) => (len.saturating_sub(suffix.parse().ok()?), len.checked_sub(1)?),
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len.checked_sub(1)?)),
+    };
+    (start <= end).then_some((start, end))
+}
+
+fn header(name: &str, value: &str) -> Option<tiny_http::Header> {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+}
+
+fn request_header(request: &tiny_http::Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str().to_string())
+}
+
+fn handle(request: tiny_http::Request, routes: &Mutex<HashMap<String, String>>) {
+    let path = request
+        .url()
+        .strip_prefix("/media/")
+        .and_then(|name| name.split('.').next())
+        .and_then(|key| routes.lock().get(key).cloned());
+    let allowed = matches!(request.method(), tiny_http::Method::Get | tiny_http::Method::Head);
+    let Some(path) = path.filter(|_| allowed) else {
+        let _ = request.respond(tiny_http::Response::empty(404));
+        return;
+    };
+
+    let range = request_header(&request, "Range");
+    let result = if crate::remote_source::is_remote_track_path(&path) {
+        proxy_remote(request, &path, range)
+    } else {
+        serve_file(request, &path, range)
+    };
+    if let Err(e) = result {
+        log::debug!("投屏媒体请求失败 {}: {}", path, e);
+    }
+}
+
+/// DLNA设备要求的响应头
+fn media_headers(path: &str) -> Vec<tiny_http::Header> {
+    [
+        ("Content-Type", audio_mime(path)),
+        ("Accept-Ranges", "bytes"),
+        ("transferMode.dlna.org", "Streaming"),
+        ("contentFeatures.dlna.org", "DLNA.ORG_OP=01;DLNA.ORG_FLAGS=01700000000000000000000000000000"),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| header(name, value))
+    .collect()
+}
+
+fn serve_file(request: tiny_http::Request, path: &str, range: Option<String>) -> io::Result<()> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            request.respond(tiny_http::Response::empty(404))?;
+            return Err(e);
+        }
+    };
+    let len = file.metadata()?.len();
+    let mut headers = media_headers(path);
+
+    let (status, start, end): (u16, u64, u64) = match range {
+        Some(range) => match parse_range(&range, len) {
+            Some((start, end)) => {
+                headers.extend(header("Content-Range", &format!("bytes {}-{}/{}", start, end, len)));
+                (206, start, end)
+            }
+            None => {
+                let mut response = tiny_http::Response::empty(416);
+                if let Some(content_range) = header("Content-Range", &format!("bytes */{}", len)) {
+                    response.add_header(content_range);
+                }
+                return request.respond(response);
+            }
+        },
+        None => (200, 0, len.saturating_sub(1)),
+    };
+
+    file.seek(SeekFrom::Start(start))?;
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+    let response = tiny_http::Response::new(status.into(), headers, file.take(body_len), Some(body_len as usize), None);
+    request.respond(response)
+}
+
+/// 请求远程曲目，使用该服务器配置的客户端
+async fn fetch_upstream(track_path: &str, range: Option<&str>) -> anyhow::Result<reqwest::Response> {
+    if track_path.starts_with("webdav://") {
+        let target = crate::streaming::full_download::resolve_webdav_track(track_path)?;
+        let client = WebDAVClient::new(target.config)?;
+        Ok(client.get_with_range(&target.remote_path, range).await?)
+    } else {
+        let (client, song_id) = crate::subsonic::client_for_track(track_path)?;
+        Ok(client.stream_response(&song_id, range).await?)
+    }
+}
+
+fn proxy_remote(request: tiny_http::Request, path: &str, range: Option<String>) -> io::Result<()> {
+    let response = tauri::async_runtime::block_on(fetch_upstream(path, range.as_deref()));
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            request.respond(tiny_http::Response::empty(502))?;
+            return Err(io::Error::other(e.to_string()));
+        }
+    };
+
+    let status = response.status().as_u16();
+    let len = response.content_length();
+    let mut headers = media_headers(path);
+    if let Some(content_range) = response.headers().get(reqwest::header::CONTENT_RANGE).and_then(|v| v.to_str().ok()) {
+        headers.extend(header("Content-Range", content_range));
+    }
+    let body = ProxyBody {
+        stream: Box::pin(response.bytes_stream()),
+        chunk: Bytes::new(),
+    };
+    request.respond(tiny_http::Response::new(status.into(), headers, body, len.map(|len| len as usize), None))
+}
+
+/// 把远程HTTP响应体转为同步Read，逐块阻塞读取；每块先申请播放带宽额度
+struct ProxyBody {
+    stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    chunk: Bytes,
+}
+
+impl Read for ProxyBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            let next = tauri::async_runtime::block_on(async {
+                let next = self.stream.next().await;
+                if let Some(Ok(chunk)) = &next {
+                    bandwidth::manager().acquire(TrafficClass::Playback, chunk.len()).await;
+                }
+                next
+            });
+            match next {
+                Some(Ok(chunk)) => self.chunk = chunk,
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk[..len]);
+        self.chunk.advance(len);
+        Ok(len)
+    }
+}
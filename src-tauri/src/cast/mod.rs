@@ -0,0 +1,391 @@
+// 投屏 - 把播放输出到局域网中的 UPnP/DLNA 设备（MediaRenderer）
+//
+// - ssdp: 发现设备；avtransport: AVTransport / RenderingControl SOAP 调用
+// - media_server: 临时HTTP服务，向设备提供本地文件或转发的 WebDAV / Subsonic 流
+// - 投屏期间播放器切换到远程输出（StartRemoteOutput），播放列表和状态仍由播放器管理；
+//   设备上的播放位置每秒轮询一次，作为 PositionChanged 事件发出，界面不需要区分本地和远程
+// - 设备播放完一首后发出 TrackCompleted，与本地播放相同由前端切到下一首
+// - 结束投屏后恢复本地输出，设备正在播放时本地从设备上的位置继续
+
+mod avtransport;
+mod media_server;
+mod ssdp;
+
+use crate::error::{AppError, AppResult};
+use crate::event_channel::EventSender;
+use crate::player::{PlayerCommand, PlayerEvent, PositionUpdate, RemoteOutputCommand, Track};
+use avtransport::{Renderer, TransportState};
+use crossbeam_channel::Sender;
+use media_server::MediaServer;
+pub(crate) use media_server::audio_mime;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// 搜索设备的时长
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 播放位置的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 连续轮询失败这么多次后认为设备已断开
+const MAX_POLL_FAILURES: u32 = 5;
+
+/// SOAP请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 投屏设备
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CastDevice {
+    /// 设备UDN
+    pub id: String,
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    #[serde(skip)]
+    av_transport_url: String,
+    #[serde(skip)]
+    rendering_control_url: Option<String>,
+}
+
+/// 最近一次搜索到的设备（按ID）
+static DEVICES: Lazy<Mutex<HashMap<String, CastDevice>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static SESSION: Lazy<Mutex<Option<CastSession>>> = Lazy::new(|| Mutex::new(None));
+
+struct CastSession {
+    device: CastDevice,
+    commands: mpsc::UnboundedSender<RemoteOutputCommand>,
+    stop_tx: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+    media: Arc<MediaServer>,
+    status: Arc<Mutex<RemoteStatus>>,
+    player_tx: Sender<PlayerCommand>,
+}
+
+/// 设备上的播放进度（结束投屏时用于本地继续播放）
+#[derive(Debug, Clone, Copy, Default)]
+struct RemoteStatus {
+    position_ms: u64,
+    playing: bool,
+}
+
+fn http_client() -> AppResult<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Network(e.to_string()))
+}
+
+/// 搜索局域网中的投屏设备
+pub async fn discover() -> AppResult<Vec<CastDevice>> {
+    let devices = ssdp::discover(&http_client()?, DISCOVERY_TIMEOUT)
+        .await
+        .map_err(|e| AppError::Network(format!("搜索投屏设备失败: {}", e)))?;
+    log::info!("📡 找到 {} 个投屏设备", devices.len());
+    *DEVICES.lock() = devices.iter().map(|device| (device.id.clone(), device.clone())).collect();
+    Ok(devices)
+}
+
+/// 正在投屏的设备
+pub fn active_device() -> Option<CastDevice> {
+    SESSION.lock().as_ref().map(|session| session.device.clone())
+}
+
+/// 开始投屏：播放器切换到远程输出，之后的播放命令由设备执行
+///
+/// 已在投屏时先结束原来的投屏
+pub async fn start(device_id: &str, player_tx: Sender<PlayerCommand>, events: EventSender<PlayerEvent>) -> AppResult<CastDevice> {
+    let device = DEVICES
+        .lock()
+        .get(device_id)
+        .cloned()
+        .ok_or_else(|| AppError::not_found("未找到投屏设备，请重新搜索"))?;
+    stop(false).await;
+
+    let host = url::Url::parse(&device.av_transport_url)
+        .ok()
+        .and_then(|url| url.host_str()?.parse::<IpAddr>().ok())
+        .and_then(media_server::local_address_for)
+        .ok_or_else(|| AppError::Network(format!("无法连接投屏设备: {}", device.name)))?;
+    let media = Arc::new(MediaServer::start().map_err(AppError::Io)?);
+
+    let (commands, commands_rx) = mpsc::unbounded_channel();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let status = Arc::new(Mutex::new(RemoteStatus::default()));
+    let session = SessionTask {
+        renderer: Renderer::new(http_client()?, &device),
+        media: Arc::clone(&media),
+        host,
+        events,
+        status: Arc::clone(&status),
+        track: None,
+        playing: false,
+        started: false,
+    };
+    let task = tauri::async_runtime::spawn(session.run(commands_rx, stop_rx));
+
+    if player_tx.send(PlayerCommand::StartRemoteOutput(commands.clone())).is_err() {
+        task.abort();
+        media.stop();
+        return Err(AppError::internal("播放器未初始化"));
+    }
+    log::info!("📡 开始投屏: {}", device.name);
+    *SESSION.lock() = Some(CastSession {
+        device: device.clone(),
+        commands,
+        stop_tx,
+        task,
+        media,
+        status,
+        player_tx,
+    });
+    Ok(device)
+}
+
+/// 结束投屏并恢复本地输出，返回是否有正在进行的投屏
+///
+/// resume_local 时设备正在播放的曲目在本地从同一位置继续
+pub async fn stop(resume_local: bool) -> bool {
+    let Some(session) = SESSION.lock().take() else {
+        return false;
+    };
+    let _ = session.stop_tx.send(());
+    if tokio::time::timeout(REQUEST_TIMEOUT, session.task).await.is_err() {
+        log::warn!("⚠️ 等待投屏任务结束超时");
+    }
+    let _ = tokio::task::spawn_blocking(move || session.media.stop()).await;
+
+    let status = *session.status.lock();
+    let resume_at = (resume_local && status.playing).then_some(status.position_ms);
+    let _ = session.player_tx.send(PlayerCommand::StopRemoteOutput { resume_at });
+    log::info!("📡 已结束投屏: {}", session.device.name);
+    true
+}
+
+/// 设置设备音量（0.0 - 1.0）
+pub fn set_volume(volume: f32) -> AppResult<()> {
+    if !(0.0..=1.0).contains(&volume) {
+        return Err(AppError::invalid_input("音量必须在0到1之间"));
+    }
+    let session = SESSION.lock();
+    let session = session.as_ref().ok_or_else(|| AppError::invalid_input("当前没有投屏"))?;
+    session
+        .commands
+        .send(RemoteOutputCommand::SetVolume(volume))
+        .map_err(|_| AppError::internal("投屏已断开"))
+}
+
+/// 投屏任务：执行播放器转发的命令，轮询设备状态
+struct SessionTask {
+    renderer: Renderer,
+    media: Arc<MediaServer>,
+    /// 设备访问媒体服务使用的本机地址
+    host: IpAddr,
+    events: EventSender<PlayerEvent>,
+    status: Arc<Mutex<RemoteStatus>>,
+    track: Option<Track>,
+    /// 应当在播放（用户没有暂停或停止）
+    playing: bool,
+    /// 设备已开始播放当前曲目，之后回到停止状态视为播放完成
+    started: bool,
+}
+
+impl SessionTask {
+    async fn run(mut self, mut commands: mpsc::UnboundedReceiver<RemoteOutputCommand>, mut stop_rx: oneshot::Receiver<()>) {
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut failures = 0;
+
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => {
+                    if let Err(e) = self.renderer.stop().await {
+                        log::debug!("停止投屏设备失败: {}", e);
+                    }
+                    break;
+                }
+                command = commands.recv() => {
+                    let Some(command) = command else { break };
+                    if let Err(e) = self.apply(command).await {
+                        log::warn!("⚠️ 投屏命令失败: {}", e);
+                        let _ = self.events.send(PlayerEvent::PlaybackError(format!("投屏失败: {}", e)));
+                    }
+                }
+                _ = poll.tick() => match self.poll().await {
+                    Ok(()) => failures = 0,
+                    Err(e) => {
+                        failures += 1;
+                        log::debug!("读取投屏设备状态失败（{}）: {}", failures, e);
+                        if failures >= MAX_POLL_FAILURES {
+                            log::warn!("⚠️ 投屏设备已断开");
+                            let _ = self.events.send(PlayerEvent::PlaybackError("投屏设备已断开".to_string()));
+                            tauri::async_runtime::spawn(stop(true));
+                            break;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    async fn apply(&mut self, command: RemoteOutputCommand) -> anyhow::Result<()> {
+        log::debug!("📡 投屏命令: {:?}", command);
+        match command {
+            RemoteOutputCommand::Play(track) => {
+                let uri = self.media.publish(&track.path, self.host);
+                let mime = media_server::audio_mime(&track.path);
+                self.renderer.play_uri(&uri, &avtransport::didl_metadata(&track, &uri, mime)).await?;
+                self.track = Some(track);
+                self.started = false;
+                self.set_status(0, true);
+            }
+            RemoteOutputCommand::Pause => {
+                self.renderer.pause().await?;
+                self.set_playing(false);
+            }
+            RemoteOutputCommand::Resume => {
+                self.renderer.play().await?;
+                self.set_playing(true);
+            }
+            RemoteOutputCommand::Stop => {
+                self.renderer.stop().await?;
+                self.set_playing(false);
+            }
+            RemoteOutputCommand::Seek(position_ms) => {
+                self.renderer.seek(position_ms).await?;
+                let playing = self.playing;
+                self.set_status(position_ms, playing);
+                self.send_position(position_ms);
+            }
+            RemoteOutputCommand::SetVolume(volume) => self.renderer.set_volume(volume).await?,
+        }
+        Ok(())
+    }
+
+    /// 设备报告的位置（本地播放已停止，不会有另一路位置事件）
+    fn send_position(&self, position_ms: u64) {
+        let _ = self.events.send(PlayerEvent::PositionChanged(PositionUpdate {
+            position_ms,
+            track_id: self.track.as_ref().map(|t| t.id),
+            is_estimated: false,
+        }));
+    }
+
+    /// 读取设备的播放状态和位置；设备播放完当前曲目时发出 TrackCompleted
+    async fn poll(&mut self) -> anyhow::Result<()> {
+        let Some(track) = self.track.clone() else {
+            return Ok(());
+        };
+        let state = self.renderer.transport_state().await?;
+        let position_ms = self.renderer.position().await?;
+        let playing = self.playing;
+        self.set_status(position_ms, playing);
+        self.send_position(position_ms);
+
+        match state {
+            TransportState::Playing => self.started = true,
+            TransportState::Stopped if self.playing && self.started => {
+                log::info!("📡 投屏设备播放完成: {:?}", track.title);
+                self.started = false;
+                self.set_playing(false);
+                let _ = self.events.send(PlayerEvent::TrackCompleted(track));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn set_playing(&mut self, playing: bool) {
+        let position_ms = self.status.lock().position_ms;
+        self.set_status(position_ms, playing);
+    }
+
+    fn set_status(&mut self, position_ms: u64, playing: bool) {
+        self.playing = playing;
+        *self.status.lock() = RemoteStatus { position_ms, playing };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+    <friendlyName>Living Room &amp; Kitchen</friendlyName>
+    <manufacturer>Acme</manufacturer>
+    <modelName>Speaker One</modelName>
+    <UDN>uuid:1234</UDN>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+        <controlURL>/upnp/control/rendering</controlURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+        <controlURL>upnp/control/transport</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn test_parse_ssdp_and_description() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.20:49152/desc.xml\r\n\r\n";
+        assert_eq!(ssdp::parse_location(response).as_deref(), Some("http://192.168.1.20:49152/desc.xml"));
+        assert_eq!(ssdp::parse_location("NOTIFY * HTTP/1.1\r\nLOCATION: http://x/\r\n"), None);
+
+        let device = ssdp::parse_description(DESCRIPTION, "http://192.168.1.20:49152/dev/desc.xml").unwrap();
+        assert_eq!(device.id, "uuid:1234");
+        assert_eq!(device.name, "Living Room & Kitchen");
+        assert_eq!(device.model.as_deref(), Some("Speaker One"));
+        assert_eq!(device.av_transport_url, "http://192.168.1.20:49152/dev/upnp/control/transport");
+        assert_eq!(device.rendering_control_url.as_deref(), Some("http://192.168.1.20:49152/upnp/control/rendering"));
+
+        // 没有 AVTransport 的设备不能投屏
+        let without_transport = DESCRIPTION.replace("AVTransport", "ConnectionManager");
+        assert!(ssdp::parse_description(&without_transport, "http://192.168.1.20/").is_none());
+    }
+
+    #[test]
+    fn test_soap_messages() {
+        let body = avtransport::envelope(ssdp::AV_TRANSPORT, "Seek", &[("Unit", "REL_TIME"), ("Target", "0:01:05")]);
+        assert!(body.contains("<u:Seek xmlns:u=\"urn:schemas-upnp-org:service:AVTransport:1\"><InstanceID>0</InstanceID>"));
+        assert!(body.contains("<Target>0:01:05</Target></u:Seek>"));
+
+        let mut track = Track::new(7, "/music/a&b.flac".to_string());
+        track.artist = Some("<Artist>".to_string());
+        let didl = avtransport::didl_metadata(&track, "http://10.0.0.2:5000/media/k.flac", "audio/flac");
+        assert!(didl.contains("<dc:title>a&amp;b</dc:title>"));
+        assert!(didl.contains("<upnp:artist>&lt;Artist&gt;</upnp:artist>"));
+        assert!(didl.contains("protocolInfo=\"http-get:*:audio/flac:*\""));
+        // 作为SOAP参数时整体再转义一次
+        let body = avtransport::envelope(ssdp::AV_TRANSPORT, "SetAVTransportURI", &[("CurrentURIMetaData", &didl)]);
+        assert!(body.contains("&lt;DIDL-Lite"));
+        assert!(body.contains("a&amp;amp;b"));
+
+        assert_eq!(avtransport::format_time(3_725_900), "1:02:05");
+        assert_eq!(avtransport::parse_time("1:02:05"), Some(3_725_000));
+        assert_eq!(avtransport::parse_time("0:00:07.5"), Some(7_500));
+        assert_eq!(avtransport::parse_time("NOT_IMPLEMENTED"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(media_server::parse_range("bytes=0-", 1000), Some((0, 999)));
+        assert_eq!(media_server::parse_range("bytes=100-199", 1000), Some((100, 199)));
+        assert_eq!(media_server::parse_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(media_server::parse_range("bytes=-200", 1000), Some((800, 999)));
+        assert_eq!(media_server::parse_range("bytes=1000-", 1000), None);
+        assert_eq!(media_server::parse_range("items=0-1", 1000), None);
+        assert_eq!(media_server::audio_mime("webdav://1#/music/song.FLAC"), "audio/flac");
+    }
+}
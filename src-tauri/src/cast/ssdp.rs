@@ -0,0 +1,143 @@
+// SSDP发现 - 在局域网中搜索 UPnP MediaRenderer 并读取设备描述
+
+use super::CastDevice;
+use anyhow::{anyhow, Result};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// SSDP组播地址
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+
+const MEDIA_RENDERER: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+pub const AV_TRANSPORT: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+pub const RENDERING_CONTROL: &str = "urn:schemas-upnp-org:service:RenderingControl:1";
+
+/// 设备描述最大字节数
+const MAX_DESCRIPTION_BYTES: usize = 1024 * 1024;
+
+fn search_request(mx: u64) -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+        SSDP_ADDR, mx, MEDIA_RENDERER
+    )
+}
+
+/// 搜索局域网中的 MediaRenderer，在 timeout 内收集响应后读取各设备的描述
+pub async fn discover(client: &reqwest::Client, timeout: Duration) -> Result<Vec<CastDevice>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let request = search_request(timeout.as_secs().clamp(1, 5));
+    // UDP可能丢包，发送两次
+    for _ in 0..2 {
+        socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+    }
+
+    let mut locations: Vec<String> = Vec::new();
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut buf = [0u8; 2048];
+    while let Ok(Ok((len, _))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        if let Some(location) = parse_location(&String::from_utf8_lossy(&buf[..len])) {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+    }
+    log::info!("📡 SSDP搜索完成: {} 个响应", locations.len());
+
+    let descriptions = locations.iter().map(|location| fetch_description(client, location));
+    let mut devices: Vec<CastDevice> = Vec::new();
+    for (location, result) in locations.iter().zip(futures::future::join_all(descriptions).await) {
+        match result {
+            Ok(Some(device)) if !devices.iter().any(|d| d.id == device.id) => devices.push(device),
+            Ok(_) => {}
+            Err(e) => log::debug!("读取设备描述失败 {}: {}", location, e),
+        }
+    }
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+async fn fetch_description(client: &reqwest::Client, location: &str) -> Result<Option<CastDevice>> {
+    let response = client.get(location).send().await?.error_for_status()?;
+    let body = response.bytes().await?;
+    if body.len() > MAX_DESCRIPTION_BYTES {
+        return Err(anyhow!("设备描述过大: {} bytes", body.len()));
+    }
+    Ok(parse_description(&String::from_utf8_lossy(&body), location))
+}
+
+/// 从SSDP响应中取出设备描述地址（LOCATION）
+pub(super) fn parse_location(response: &str) -> Option<String> {
+    let mut lines = response.lines();
+    if !lines.next()?.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("location").then(|| value.trim().to_string())
+    })
+}
+
+/// 解析设备描述，没有 AVTransport 服务的设备返回None
+///
+/// 服务可能位于嵌入的子设备中；设备名称、型号和UDN取根设备的值
+pub(super) fn parse_description(xml: &str, location: &str) -> Option<CastDevice> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let (mut udn, mut name, mut manufacturer, mut model, mut url_base) = (None, None, None, None, None);
+    let (mut service_type, mut control_url) = (None::<String>, None::<String>);
+    let mut services: Vec<(String, String)> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(_)) => text.clear(),
+            Ok(Event::Text(e)) => text.push_str(&e.unescape().unwrap_or_default()),
+            Ok(Event::End(e)) => {
+                let value = Some(text.trim().to_string()).filter(|v| !v.is_empty());
+                match e.local_name().as_ref() {
+                    b"UDN" if udn.is_none() => udn = value,
+                    b"friendlyName" if name.is_none() => name = value,
+                    b"manufacturer" if manufacturer.is_none() => manufacturer = value,
+                    b"modelName" if model.is_none() => model = value,
+                    b"URLBase" => url_base = value,
+                    b"serviceType" => service_type = value,
+                    b"controlURL" => control_url = value,
+                    b"service" => {
+                        if let (Some(service), Some(url)) = (service_type.take(), control_url.take()) {
+                            services.push((service, url));
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let base = url::Url::parse(url_base.as_deref().unwrap_or(location)).ok()?;
+    let control = |service_type: &str| {
+        services
+            .iter()
+            .find(|(service, _)| service == service_type)
+            .and_then(|(_, url)| base.join(url).ok())
+            .map(|url| url.to_string())
+    };
+
+    let av_transport_url = control(AV_TRANSPORT)?;
+    let id = udn?;
+    Some(CastDevice {
+        name: name.unwrap_or_else(|| id.clone()),
+        id,
+        manufacturer,
+        model,
+        av_transport_url,
+        rendering_control_url: control(RENDERING_CONTROL),
+    })
+}
@@ -0,0 +1,413 @@
+// 章节提取
+//
+// 从三种来源读取曲目内的章节：
+// - MP4/M4A/M4B 的 Nero 章节（moov.udta.chpl）
+// - Vorbis 注释的 CHAPTERxxx / CHAPTERxxxNAME 标签
+// - 音频文件旁边的 .cue 文件（整轨 + CUE 的专辑按音轨拆成章节）
+// 少于两个章节时视为没有章节
+
+use crate::player::Chapter;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// CUE 时间码每秒的帧数
+const CUE_FRAMES_PER_SECOND: u64 = 75;
+
+/// 读取 .cue 文件的大小上限
+const MAX_CUE_SIZE: u64 = 1024 * 1024;
+
+/// 读取 chpl 的大小上限
+const MAX_CHPL_SIZE: u64 = 1024 * 1024;
+
+/// 原始章节（开始位置和标题），整理后得到带序号和结束位置的章节
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawChapter {
+    pub start_ms: u64,
+    pub title: Option<String>,
+}
+
+/// 按开始位置排序、去重，补全序号和结束位置；少于两个章节时返回空
+pub fn finalize(mut raw: Vec<RawChapter>, duration_ms: Option<u64>) -> Vec<Chapter> {
+    raw.sort_by_key(|c| c.start_ms);
+    raw.dedup_by_key(|c| c.start_ms);
+    if let Some(duration) = duration_ms.filter(|d| *d > 0) {
+        raw.retain(|c| c.start_ms < duration);
+    }
+    if raw.len() < 2 {
+        return Vec::new();
+    }
+
+    let starts: Vec<u64> = raw.iter().map(|c| c.start_ms).collect();
+    raw.into_iter()
+        .enumerate()
+        .map(|(i, c)| Chapter {
+            index: i as u32,
+            title: c.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            start_ms: c.start_ms,
+            end_ms: starts.get(i + 1).copied().or(duration_ms.filter(|d| *d > 0)),
+        })
+        .collect()
+}
+
+// ========== Vorbis 注释 ==========
+
+/// 从 CHAPTER001=00:00:00.000 / CHAPTER001NAME=标题 形式的标签读取章节
+pub fn vorbis_chapters<'a, I>(items: I) -> Vec<RawChapter>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut starts = std::collections::BTreeMap::new();
+    let mut names = std::collections::HashMap::new();
+    for (key, value) in items {
+        let key = key.to_ascii_uppercase();
+        let Some(rest) = key.strip_prefix("CHAPTER") else { continue };
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let Ok(number) = digits.parse::<u32>() else { continue };
+        match &rest[digits.len()..] {
+            "" => {
+                if let Some(start_ms) = parse_timestamp(value) {
+                    starts.insert(number, start_ms);
+                }
+            }
+            "NAME" => {
+                names.insert(number, value.to_string());
+            }
+            _ => {}
+        }
+    }
+    starts.into_iter()
+        .map(|(number, start_ms)| RawChapter { start_ms, title: names.remove(&number) })
+        .collect()
+}
+
+/// 解析 HH:MM:SS.mmm（也接受 MM:SS 和不带小数的形式）
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let (clock, fraction) = match value.trim().split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (value.trim(), None),
+    };
+    let mut seconds = 0u64;
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    for part in parts {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    let millis = match fraction {
+        Some(f) if !f.is_empty() && f.chars().all(|c| c.is_ascii_digit()) => {
+            let padded: String = f.chars().chain("000".chars()).take(3).collect();
+            padded.parse::<u64>().ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(seconds * 1000 + millis)
+}
+
+// ========== CUE ==========
+
+/// CUE 中的一个 FILE 段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueFile {
+    pub file: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// INDEX 01 的位置
+    pub start_ms: Option<u64>,
+}
+
+/// 解析 CUE 文本
+pub fn parse_cue(content: &str) -> Vec<CueFile> {
+    let mut files: Vec<CueFile> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => files.push(CueFile { file: quoted_value(rest), tracks: Vec::new() }),
+            "TRACK" => {
+                let Some(file) = files.last_mut() else { continue };
+                let number = rest.split_whitespace().next().and_then(|n| n.parse().ok()).unwrap_or(0);
+                file.tracks.push(CueTrack { number, title: None, performer: None, start_ms: None });
+            }
+            "TITLE" | "PERFORMER" | "INDEX" => {
+                // 第一个 TRACK 之前的 TITLE/PERFORMER 属于整张专辑
+                let Some(track) = files.last_mut().and_then(|f| f.tracks.last_mut()) else { continue };
+                match command.to_ascii_uppercase().as_str() {
+                    "TITLE" => track.title = Some(quoted_value(rest)),
+                    "PERFORMER" => track.performer = Some(quoted_value(rest)),
+                    _ => {
+                        let mut parts = rest.split_whitespace();
+                        if parts.next().and_then(|n| n.parse::<u32>().ok()) == Some(1) {
+                            track.start_ms = parts.next().and_then(parse_cue_time);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    files
+}
+
+/// 去掉引号；FILE 行的引号之后还有文件类型
+fn quoted_value(rest: &str) -> String {
+    match rest.strip_prefix('"') {
+        Some(inner) => inner.split('"').next().unwrap_or_default().to_string(),
+        None => rest.split_whitespace().next().unwrap_or_default().to_string(),
+    }
+}
+
+/// 解析 mm:ss:ff（ff 为 1/75 秒的帧）
+fn parse_cue_time(value: &str) -> Option<u64> {
+    let mut parts = value.split(':').map(|p| p.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= CUE_FRAMES_PER_SECOND {
+        return None;
+    }
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / CUE_FRAMES_PER_SECOND)
+}
+
+/// CUE 中属于指定音频文件的音轨；只有一个 FILE 段时不要求文件名一致（常见于改过名的整轨）
+pub fn cue_chapters_for(files: &[CueFile], audio_file_name: &str) -> Vec<RawChapter> {
+    let matched = files.iter()
+        .find(|f| {
+            Path::new(&f.file.replace('\\', "/"))
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(audio_file_name))
+        })
+        .or(if files.len() == 1 { files.first() } else { None });
+    matched
+        .map(|file| file.tracks.iter()
+            .filter_map(|track| Some(RawChapter {
+                start_ms: track.start_ms?,
+                title: match (&track.title, &track.performer) {
+                    (Some(title), _) => Some(title.clone()),
+                    (None, Some(performer)) => Some(performer.clone()),
+                    _ => None,
+                },
+            }))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// 查找音频文件旁边的 .cue 文件（同名或以完整文件名加 .cue 命名）并读取章节
+pub fn find_cue_chapters(audio_path: &Path) -> Vec<RawChapter> {
+    let Some(file_name) = audio_path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+        return Vec::new();
+    };
+    let candidates = [
+        audio_path.with_extension("cue"),
+        audio_path.with_file_name(format!("{}.cue", file_name)),
+    ];
+    for cue_path in candidates.iter().filter(|p| p.is_file()) {
+        let Some(content) = read_cue_file(cue_path) else { continue };
+        let chapters = cue_chapters_for(&parse_cue(&content), &file_name);
+        if !chapters.is_empty() {
+            log::debug!("📑 从CUE读取到 {} 个章节: {}", chapters.len(), cue_path.display());
+            return chapters;
+        }
+    }
+    Vec::new()
+}
+
+/// 读取 CUE 文件：UTF-8（可带 BOM），否则按 GBK 解码
+fn read_cue_file(path: &Path) -> Option<String> {
+    if std::fs::metadata(path).ok()?.len() > MAX_CUE_SIZE {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF][..]).unwrap_or(&bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(content) => Some(content.to_string()),
+        Err(_) => {
+            let (decoded, _, had_errors) = encoding_rs::GBK.decode(bytes);
+            (!had_errors).then(|| decoded.into_owned())
+        }
+    }
+}
+
+// ========== MP4 ==========
+
+/// 读取 MP4 的 Nero 章节（moov.udta.chpl）
+pub fn mp4_chapters(path: &Path) -> Vec<RawChapter> {
+    let read = || -> std::io::Result<Option<Vec<RawChapter>>> {
+        let mut file = File::open(path)?;
+        let end = file.metadata()?.len();
+        let Some((start, size)) = find_box_path(&mut file, 0, end, &[b"moov", b"udta", b"chpl"])? else {
+            return Ok(None);
+        };
+        if size > MAX_CHPL_SIZE {
+            return Ok(None);
+        }
+        let mut data = vec![0u8; size as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut data)?;
+        Ok(parse_chpl(&data))
+    };
+    match read() {
+        Ok(chapters) => chapters.unwrap_or_default(),
+        Err(e) => {
+            log::debug!("读取MP4章节失败 {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// 依次进入子box，返回最后一个box内容的位置和大小
+fn find_box_path<R: Read + Seek>(reader: &mut R, mut start: u64, mut end: u64, path: &[&[u8; 4]]) -> std::io::Result<Option<(u64, u64)>> {
+    for name in path {
+        match find_box(reader, start, end, name)? {
+            Some((content_start, content_size)) => {
+                start = content_start;
+                end = content_start + content_size;
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(Some((start, end - start)))
+}
+
+/// 在 [start, end) 范围内查找box，返回内容的位置和大小
+fn find_box<R: Read + Seek>(reader: &mut R, start: u64, end: u64, name: &[u8; 4]) -> std::io::Result<Option<(u64, u64)>> {
+    let mut offset = start;
+    while offset + 8 <= end {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = end - offset;
+        }
+        if size < header_len || offset + size > end {
+            return Ok(None);
+        }
+        if &header[4..8] == name {
+            return Ok(Some((offset + header_len, size - header_len)));
+        }
+        offset += size;
+    }
+    Ok(None)
+}
+
+/// 解析 chpl 内容：版本和标志，版本1多4字节，章节数，每章为100纳秒单位的开始时间和带长度的标题
+fn parse_chpl(data: &[u8]) -> Option<Vec<RawChapter>> {
+    let version = *data.first()?;
+    let mut pos = if version > 0 { 8 } else { 4 };
+    let count = *data.get(pos)? as usize;
+    pos += 1;
+
+    let mut chapters = Vec::with_capacity(count);
+    for _ in 0..count {
+        let start = u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?);
+        let title_len = *data.get(pos + 8)? as usize;
+        let title = data.get(pos + 9..pos + 9 + title_len)?;
+        pos += 9 + title_len;
+        chapters.push(RawChapter {
+            start_ms: start / 10_000,
+            title: Some(String::from_utf8_lossy(title).into_owned()),
+        });
+    }
+    Some(chapters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(start_ms: u64, title: &str) -> RawChapter {
+        RawChapter { start_ms, title: Some(title.to_string()) }
+    }
+
+    #[test]
+    fn test_parse_cue() {
+        let cue = "REM GENRE Pop\r\nPERFORMER \"Album Artist\"\r\nTITLE \"Album\"\r\nFILE \"CDImage.flac\" WAVE\r\n  TRACK 01 AUDIO\r\n    TITLE \"开场\"\r\n    PERFORMER \"歌手\"\r\n    INDEX 01 00:00:00\r\n  TRACK 02 AUDIO\r\n    TITLE \"Second\"\r\n    INDEX 00 04:10:00\r\n    INDEX 01 04:12:37\r\n";
+        let files = parse_cue(cue);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file, "CDImage.flac");
+        assert_eq!(files[0].tracks.len(), 2);
+        assert_eq!(files[0].tracks[0].title.as_deref(), Some("开场"));
+        assert_eq!(files[0].tracks[1].start_ms, Some(252_493));
+
+        // 只有一个FILE段时不要求文件名一致
+        let chapters = cue_chapters_for(&files, "renamed.flac");
+        assert_eq!(chapters, vec![raw(0, "开场"), raw(252_493, "Second")]);
+    }
+
+    #[test]
+    fn test_cue_with_multiple_files_matches_by_name() {
+        let cue = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nFILE \"b.wav\" WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\nTRACK 03 AUDIO\nINDEX 01 01:00:00\n";
+        let files = parse_cue(cue);
+        assert_eq!(cue_chapters_for(&files, "B.WAV").len(), 2);
+        assert!(cue_chapters_for(&files, "c.wav").is_empty());
+    }
+
+    #[test]
+    fn test_vorbis_chapters() {
+        let items = [
+            ("CHAPTER002", "00:10:00.5"),
+            ("CHAPTER001", "00:00:00.000"),
+            ("CHAPTER001NAME", "Intro"),
+            ("chapter002name", "Part 2"),
+            ("CHAPTER003", "invalid"),
+            ("TITLE", "Book"),
+        ];
+        assert_eq!(vorbis_chapters(items), vec![raw(0, "Intro"), raw(600_500, "Part 2")]);
+    }
+
+    #[test]
+    fn test_parse_chpl() {
+        let mut data = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        data.extend_from_slice(&0u64.to_be_bytes());
+        data.push(5);
+        data.extend_from_slice(b"Intro");
+        data.extend_from_slice(&(90_000u64 * 10_000).to_be_bytes());
+        data.push(3);
+        data.extend_from_slice(b"Two");
+        assert_eq!(parse_chpl(&data), Some(vec![raw(0, "Intro"), raw(90_000, "Two")]));
+        // 数据被截断
+        assert_eq!(parse_chpl(&data[..data.len() - 1]), None);
+    }
+
+    #[test]
+    fn test_find_box_path() {
+        fn mp4_box(name: &[u8; 4], content: &[u8]) -> Vec<u8> {
+            let mut data = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+            data.extend_from_slice(name);
+            data.extend_from_slice(content);
+            data
+        }
+        let chpl = mp4_box(b"chpl", b"xyz");
+        let udta = mp4_box(b"udta", &[mp4_box(b"meta", b"..").as_slice(), &chpl].concat());
+        let file = [mp4_box(b"ftyp", b"M4B "), mp4_box(b"mdat", &[0; 32]), mp4_box(b"moov", &udta)].concat();
+
+        let mut reader = std::io::Cursor::new(&file);
+        let (start, size) = find_box_path(&mut reader, 0, file.len() as u64, &[b"moov", b"udta", b"chpl"]).unwrap().unwrap();
+        assert_eq!(&file[start as usize..(start + size) as usize], b"xyz");
+        assert_eq!(find_box_path(&mut reader, 0, file.len() as u64, &[b"moov", b"trak"]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_finalize() {
+        assert!(finalize(vec![raw(0, "Only")], Some(60_000)).is_empty());
+
+        let chapters = finalize(vec![raw(30_000, "B"), raw(0, " "), raw(30_000, "dup"), raw(90_000, "past end")], Some(60_000));
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0], Chapter { index: 0, title: None, start_ms: 0, end_ms: Some(30_000) });
+        assert_eq!(chapters[1], Chapter { index: 1, title: Some("B".to_string()), start_ms: 30_000, end_ms: Some(60_000) });
+    }
+}
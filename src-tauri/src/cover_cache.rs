@@ -0,0 +1,124 @@
+// 封面缓存 - 原图按内容哈希去重存储，缩略图在扫描时生成（旧数据首次访问时补生成）
+
+use anyhow::Result;
+use image::{DynamicImage, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// 小缩略图边长（列表、网格）
+pub const SMALL_SIZE: u32 = 128;
+/// 大缩略图边长（播放页、专辑详情）
+pub const LARGE_SIZE: u32 = 512;
+
+/// 缩略图统一编码为JPEG
+pub const THUMBNAIL_MIME: &str = "image/jpeg";
+const THUMBNAIL_QUALITY: u8 = 85;
+
+/// 请求的封面尺寸
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverSize {
+    Small,
+    #[default]
+    Large,
+    Original,
+}
+
+impl CoverSize {
+    /// 缩略图的最大边长，原图返回None
+    pub fn max_dimension(&self) -> Option<u32> {
+        match self {
+            CoverSize::Small => Some(SMALL_SIZE),
+            CoverSize::Large => Some(LARGE_SIZE),
+            CoverSize::Original => None,
+        }
+    }
+}
+
+/// 封面图片数据
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverImage {
+    pub data: Vec<u8>,
+    pub mime: String,
+}
+
+/// 封面ID：图片内容的MD5，同一专辑内相同的封面只存一份
+pub fn cover_id(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+/// 根据文件头判断图片MIME类型（标签中缺失MIME时使用）
+pub fn sniff_mime(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(&b"WEBP"[..]) {
+        "image/webp"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// 生成不超过 max_dimension 的缩略图（保持宽高比，小图不放大）
+pub fn make_thumbnail(data: &[u8], max_dimension: u32) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(data)?;
+    let image = if image.width() > max_dimension || image.height() > max_dimension {
+        image.thumbnail(max_dimension, max_dimension)
+    } else {
+        image
+    };
+
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut Cursor::new(&mut encoded), ImageOutputFormat::Jpeg(THUMBNAIL_QUALITY))?;
+    Ok(encoded)
+}
+
+/// 生成小、大两种缩略图；图片无法解码时返回None（仍保留原图）
+pub fn make_thumbnails(data: &[u8]) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    match (make_thumbnail(data, SMALL_SIZE), make_thumbnail(data, LARGE_SIZE)) {
+        (Ok(small), Ok(large)) => (Some(small), Some(large)),
+        (Err(e), _) | (_, Err(e)) => {
+            log::warn!("生成封面缩略图失败: {}", e);
+            (None, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgb([200u8, 40, 40]));
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_thumbnail_keeps_aspect_ratio_and_never_upscales() {
+        let thumb = make_thumbnail(&png_bytes(600, 300), SMALL_SIZE).unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 64));
+        assert_eq!(sniff_mime(&thumb), THUMBNAIL_MIME);
+
+        let small = image::load_from_memory(&make_thumbnail(&png_bytes(100, 80), LARGE_SIZE).unwrap()).unwrap();
+        assert_eq!((small.width(), small.height()), (100, 80));
+
+        assert_eq!(make_thumbnails(b"not an image"), (None, None));
+    }
+
+    #[test]
+    fn test_cover_id_and_mime() {
+        let png = png_bytes(2, 2);
+        assert_eq!(cover_id(&png), format!("{:x}", md5::compute(&png)));
+        assert_ne!(cover_id(&png), cover_id(&png_bytes(3, 3)));
+        assert_eq!(sniff_mime(&png), "image/png");
+        assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+    }
+}
@@ -0,0 +1,226 @@
+// 封面取色 - 从专辑封面提取主色调供播放页着色
+//
+// 缩小到 SAMPLE_SIZE 后用中位切分（median cut）量化出不超过 MAX_COLORS 种颜色，
+// 按占比排序，并给出与主色对比度足够的前景色（WCAG 对比度 ≥ 4.5）。
+// 结果按封面ID（内容哈希）缓存在数据库中；封面缺失或无法解码时返回默认配色。
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 取色前缩小到的边长
+const SAMPLE_SIZE: u32 = 64;
+
+/// 最多提取的颜色数
+pub const MAX_COLORS: usize = 6;
+
+/// 前景色与主色的最低对比度
+pub const MIN_CONTRAST: f32 = 4.5;
+
+/// 备选的浅色和深色前景色
+const LIGHT_FOREGROUND: [u8; 3] = [0xFF, 0xFF, 0xFF];
+const DARK_FOREGROUND: [u8; 3] = [0x11, 0x11, 0x11];
+
+/// 配色中的一种颜色
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaletteColor {
+    /// #RRGGBB
+    pub hex: String,
+    /// 占封面像素的比例（0.0 - 1.0）
+    pub population: f32,
+}
+
+/// 封面配色
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverPalette {
+    /// 按占比从高到低排列，第一个为主色
+    pub colors: Vec<PaletteColor>,
+    /// 建议的前景色（文字、图标），与主色的对比度不低于 MIN_CONTRAST
+    pub foreground: String,
+    /// 封面缺失或无法解码时使用的默认配色
+    pub is_default: bool,
+}
+
+impl Default for CoverPalette {
+    fn default() -> Self {
+        let colors = [([0x2B, 0x2B, 0x2E], 0.55), ([0x1C, 0x1C, 0x1F], 0.2), ([0x4A, 0x4A, 0x52], 0.15), ([0x8E, 0x8E, 0x99], 0.1)]
+            .into_iter()
+            .map(|(rgb, population)| PaletteColor { hex: to_hex(rgb), population })
+            .collect();
+        Self { colors, foreground: to_hex(LIGHT_FOREGROUND), is_default: true }
+    }
+}
+
+/// 从封面图片数据提取配色
+pub fn extract_palette(data: &[u8]) -> Result<CoverPalette> {
+    let image = image::load_from_memory(data)?;
+    let image = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgba8();
+    // 透明像素不参与取色
+    let pixels: Vec<[u8; 3]> = image.pixels()
+        .filter(|p| p.0[3] >= 128)
+        .map(|p| [p.0[0], p.0[1], p.0[2]])
+        .collect();
+    if pixels.is_empty() {
+        anyhow::bail!("封面没有不透明的像素");
+    }
+    Ok(palette_from_pixels(pixels))
+}
+
+/// 中位切分量化
+fn palette_from_pixels(pixels: Vec<[u8; 3]>) -> CoverPalette {
+    let total = pixels.len() as f32;
+    let mut boxes = vec![pixels];
+
+    while boxes.len() < MAX_COLORS {
+        // 优先切分 颜色跨度 × 像素数 最大的盒子
+        let candidate = boxes.iter()
+            .enumerate()
+            .map(|(index, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (index, channel, range as usize * pixels.len())
+            })
+            .filter(|(_, _, score)| *score > 0)
+            .max_by_key(|(_, _, score)| *score);
+        let Some((index, channel, _)) = candidate else { break };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| p[channel]);
+        // 在中位附近的取值边界处切分，同一颜色不会被分到两个盒子
+        let median = pixels[pixels.len() / 2][channel];
+        let mut split = pixels.partition_point(|p| p[channel] < median);
+        if split == 0 {
+            split = pixels.partition_point(|p| p[channel] <= median);
+        }
+        let upper = pixels.split_off(split);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    let mut colors: Vec<([u8; 3], f32)> = boxes.iter()
+        .map(|pixels| (average(pixels), pixels.len() as f32 / total))
+        .collect();
+    colors.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let background = colors[0].0;
+    let foreground = colors[1..].iter()
+        .map(|(rgb, _)| *rgb)
+        .find(|rgb| contrast_ratio(*rgb, background) >= MIN_CONTRAST)
+        .unwrap_or_else(|| {
+            if contrast_ratio(LIGHT_FOREGROUND, background) >= contrast_ratio(DARK_FOREGROUND, background) {
+                LIGHT_FOREGROUND
+            } else {
+                DARK_FOREGROUND
+            }
+        });
+
+    CoverPalette {
+        colors: colors.into_iter()
+            .map(|(rgb, population)| PaletteColor { hex: to_hex(rgb), population })
+            .collect(),
+        foreground: to_hex(foreground),
+        is_default: false,
+    }
+}
+
+/// 跨度最大的通道及其跨度
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), p| (min.min(p[channel]), max.max(p[channel])));
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for (total, value) in sum.iter_mut().zip(p) {
+            *total += *value as u64;
+        }
+    }
+    let count = pixels.len().max(1) as u64;
+    sum.map(|s| ((s + count / 2) / count) as u8)
+}
+
+fn to_hex(rgb: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2])
+}
+
+/// WCAG 相对亮度
+fn relative_luminance(rgb: [u8; 3]) -> f32 {
+    let linear = rgb.map(|c| {
+        let c = c as f32 / 255.0;
+        if c <= 0.039_28 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    });
+    0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2]
+}
+
+/// WCAG 对比度（1.0 - 21.0）
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgb};
+    use std::io::Cursor;
+
+    /// 左边3/4为红色，右边1/4为蓝色
+    fn two_tone_png() -> Vec<u8> {
+        let image = ImageBuffer::from_fn(200, 100, |x, _| if x < 150 { Rgb([200u8, 30, 30]) } else { Rgb([20u8, 40, 220]) });
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn hex_to_rgb(hex: &str) -> [u8; 3] {
+        let value = u32::from_str_radix(hex.trim_start_matches('#'), 16).unwrap();
+        [(value >> 16) as u8, (value >> 8) as u8, value as u8]
+    }
+
+    fn is_close(hex: &str, rgb: [u8; 3]) -> bool {
+        hex_to_rgb(hex).iter().zip(rgb).all(|(a, b)| a.abs_diff(b) <= 10)
+    }
+
+    #[test]
+    fn test_dominant_colors_and_weights() {
+        let palette = extract_palette(&two_tone_png()).unwrap();
+        assert!(!palette.is_default);
+        assert!(palette.colors.len() <= MAX_COLORS);
+        assert!(is_close(&palette.colors[0].hex, [200, 30, 30]), "{:?}", palette.colors);
+        assert!((palette.colors[0].population - 0.75).abs() < 0.05, "{:?}", palette.colors);
+        assert!(palette.colors.iter().any(|c| is_close(&c.hex, [20, 40, 220])), "{:?}", palette.colors);
+
+        let total: f32 = palette.colors.iter().map(|c| c.population).sum();
+        assert!((total - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_foreground_has_enough_contrast() {
+        for palette in [extract_palette(&two_tone_png()).unwrap(), CoverPalette::default()] {
+            let background = hex_to_rgb(&palette.colors[0].hex);
+            let foreground = hex_to_rgb(&palette.foreground);
+            assert!(contrast_ratio(foreground, background) >= MIN_CONTRAST, "{:?}", palette);
+        }
+        assert!((contrast_ratio([0, 0, 0], [255, 255, 255]) - 21.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_gradient_is_quantized_to_max_colors() {
+        let pixels: Vec<[u8; 3]> = (0..4096).map(|i| [(i % 256) as u8, (i / 16) as u8, 128]).collect();
+        let palette = palette_from_pixels(pixels);
+        assert_eq!(palette.colors.len(), MAX_COLORS);
+        assert!(palette.colors.windows(2).all(|w| w[0].population >= w[1].population));
+    }
+
+    #[test]
+    fn test_corrupt_cover_is_an_error() {
+        assert!(extract_palette(b"not an image").is_err());
+        assert!(CoverPalette::default().is_default);
+    }
+}
@@ -1,14 +1,29 @@
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::{backup::Backup, params, Connection, ErrorCode, OpenFlags, OptionalExtension, Transaction, TransactionBehavior};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use anyhow::Result;
 // 🔧 性能优化：添加缓存支持
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 // 使用新的PlayerCore的Track类型
-use crate::player::Track;
+use crate::player::{Chapter, RepeatMode, Track};
+use crate::cover_cache::{self, CoverImage, CoverSize};
+use crate::cover_palette::CoverPalette;
+use crate::artist_split::{self, ArtistRole, ArtistSplitConfig, TrackArtist};
+use crate::search_query::{self, RankSignals, SearchQuery};
+use crate::sort_key::{self, TrackSortKeys};
+use crate::player::audio::fingerprint;
+use crate::player::audio::silence::TrimPoints;
+use crate::player::audio::verify::{VerifyResult, VerifyStatus};
+use crate::playlist::smart_playlist::SmartQuery;
+use crate::remote_source;
+use crate::secrets::{self, SecretBox};
+use crate::library_backup::{
+    self, BackupFavorite, BackupLyrics, BackupPlay, BackupPlaylist, BackupPlaylistItem, BackupRemoteServer, BackupTrack,
+    ImportOptions, ImportReport, LibraryBackup,
+};
 
 // 🔧 性能优化：缓存条目结构
 #[derive(Debug, Clone)]
@@ -44,8 +59,22 @@ struct QueryCache {
     // 轨道列表缓存 - 10分钟TTL
     all_tracks: Option<CacheEntry<Vec<Track>>>,
     
+    // 专辑/艺术家浏览列表缓存 - 10分钟TTL
+    albums: Option<CacheEntry<Vec<AlbumSummary>>>,
+    artists: Option<CacheEntry<Vec<ArtistSummary>>>,
+    
+    // 曲库统计缓存 - 5分钟TTL
+    library_statistics: Option<CacheEntry<LibraryStatistics>>,
+    
     // 搜索结果缓存 - 5分钟TTL，最多缓存50个搜索结果
     search_results: HashMap<String, CacheEntry<Vec<Track>>>,
+    
+    // 数据变更检测：上次检查时的 data_version / total_changes / 各表变更计数
+    data_version: i64,
+    total_changes: i64,
+    tracks_version: i64,
+    favorites_version: i64,
+    lyrics_version: i64,
 }
 
 impl QueryCache {
@@ -56,7 +85,15 @@ impl QueryCache {
             album_count: None,
             favorites_count: None,
             all_tracks: None,
+            albums: None,
+            artists: None,
+            library_statistics: None,
             search_results: HashMap::new(),
+            data_version: -1,
+            total_changes: -1,
+            tracks_version: -1,
+            favorites_version: -1,
+            lyrics_version: -1,
         }
     }
     
@@ -108,6 +145,21 @@ impl QueryCache {
                 self.all_tracks = None;
             }
         }
+        if let Some(ref entry) = self.albums {
+            if entry.is_expired() {
+                self.albums = None;
+            }
+        }
+        if let Some(ref entry) = self.artists {
+            if entry.is_expired() {
+                self.artists = None;
+            }
+        }
+        if let Some(ref entry) = self.library_statistics {
+            if entry.is_expired() {
+                self.library_statistics = None;
+            }
+        }
         
         self.cleanup_search_cache();
     }
@@ -118,14 +170,66 @@ impl QueryCache {
         self.artist_count = None;
         self.album_count = None;
         self.all_tracks = None;
+        self.albums = None;
+        self.artists = None;
+        self.library_statistics = None;
         self.search_results.clear();
     }
     
     // 清空与favorites表相关的缓存
-    #[allow(dead_code)]
     fn invalidate_favorites_related(&mut self) {
         self.favorites_count = None;
     }
+    
+    // 清空与lyrics表相关的缓存（曲库统计中的缺少歌词数）
+    fn invalidate_lyrics_related(&mut self) {
+        self.library_statistics = None;
+    }
+    
+    // 清空全部缓存（无法确定变更范围时使用）
+    fn invalidate_all(&mut self) {
+        self.invalidate_track_related();
+        self.invalidate_favorites_related();
+        self.invalidate_lyrics_related();
+        self.data_version = -1;
+        self.total_changes = -1;
+        self.tracks_version = -1;
+        self.favorites_version = -1;
+        self.lyrics_version = -1;
+    }
+}
+
+/// all_tracks 缓存的内存上限（按字段长度估算），超过时不缓存
+const ALL_TRACKS_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// 估算曲目列表占用的内存
+fn approx_tracks_bytes(tracks: &[Track]) -> usize {
+    let text = |value: &Option<String>| value.as_ref().map_or(0, String::len);
+    let blob = |value: &Option<Vec<u8>>| value.as_ref().map_or(0, Vec::len);
+    tracks
+        .iter()
+        .map(|t| {
+            std::mem::size_of::<Track>()
+                + t.path.len()
+                + text(&t.title)
+                + text(&t.artist)
+                + text(&t.album)
+                + text(&t.genre)
+                + text(&t.album_artist)
+                + text(&t.cover_id)
+                + text(&t.embedded_lyrics)
+                + blob(&t.album_cover_data)
+                + blob(&t.artist_photo_data)
+        })
+        .sum()
+}
+
+/// 数据库被其他连接锁定（超过 busy_timeout 仍未释放）
+fn is_busy_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<rusqlite::Error>()
+        .and_then(|e| e.sqlite_error_code())
+        .is_some_and(|code| matches!(code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked))
 }
 
 // 注意：Playlist 和 PlaylistItem 定义已移至 playlist/types.rs，避免重复定义
@@ -140,34 +244,751 @@ pub struct Lyrics {
     pub created_at: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LyricLine {
     pub timestamp_ms: u64,
     pub text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub translation: Option<String>,
+    /// 逐字时间（增强LRC），普通歌词为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<LyricWord>>,
+}
+
+/// 增强LRC中的一个词：<mm:ss.xx>词
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LyricWord {
+    pub timestamp_ms: u64,
+    /// 原样保留空格，行文本为所有词拼接后去掉首尾空白
+    pub text: String,
+}
+
+/// 曲目的ReplayGain增益（dB）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGainInfo {
+    pub track_gain_db: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    /// 后台分析得到的整体响度（LUFS），由 update_loudness 单独写入
+    pub loudness_lufs: Option<f32>,
+}
+
+/// 专辑浏览列表项（按专辑名 + 专辑艺术家聚合）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlbumSummary {
+    pub album: String,
+    /// 专辑艺术家，标签缺失时取曲目艺术家
+    pub album_artist: Option<String>,
+    pub year: Option<i64>,
+    pub track_count: i64,
+    pub total_duration_ms: i64,
+    /// 用于加载封面的代表曲目（优先选择带封面的曲目）
+    pub cover_track_id: i64,
+    /// 专辑封面ID，可直接用于 get_cover
+    pub cover_id: Option<String>,
+}
+
+/// 曲库搜索结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LibrarySearchResult {
+    pub tracks: Vec<Track>,
+    pub playlists: Vec<crate::playlist::Playlist>,
+    /// 包含匹配曲目的专辑
+    pub albums: Vec<AlbumSummary>,
+    /// 匹配曲目的艺术家
+    pub artists: Vec<ArtistSummary>,
+}
+
+/// 重复曲目的判定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// 文件内容完全相同（哈希一致）
+    Exact,
+    /// 声学指纹相似，可能是同一录音的不同编码版本（模糊匹配）
+    AcousticMatch,
+}
+
+/// 一组重复曲目
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// 组内最低的两两相似度，完全相同为1.0
+    pub similarity: f32,
+    pub tracks: Vec<Track>,
+}
+
+/// 声学比对时允许的时长差（毫秒），超出的不做指纹比较
+const ACOUSTIC_DURATION_TOLERANCE_MS: i64 = 5000;
+
+/// 搜索历史保留的条数
+pub const SEARCH_HISTORY_LIMIT: i64 = 20;
+/// 在此时间内被更长搜索词覆盖的前缀记录视为输入过程，不保留
+const SEARCH_TYPING_WINDOW_SECS: i64 = 60;
+
+/// 搜索建议的来源字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Artist,
+    Album,
+    Title,
+}
+
+/// 搜索建议项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchSuggestion {
+    pub kind: SuggestionKind,
+    pub value: String,
+}
+
+/// 批量封面查询结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrackCovers {
+    /// 曲目ID → 封面ID（无封面的曲目不出现）
+    pub track_covers: HashMap<i64, String>,
+    /// 封面ID → 图片数据
+    pub covers: HashMap<String, CoverImage>,
+}
+
+/// 艺术家浏览列表项
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtistSummary {
+    pub artist: String,
+    pub album_count: i64,
+    pub track_count: i64,
+}
+
+/// 文件夹浏览中的子文件夹（数量和时长包含所有下级文件夹）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FolderEntry {
+    pub path: String,
+    pub name: String,
+    pub track_count: i64,
+    pub total_duration_ms: i64,
+}
+
+/// 文件夹浏览结果，path 为 None 表示根目录
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderListing {
+    pub path: Option<String>,
+    pub folders: Vec<FolderEntry>,
+    pub tracks: Vec<Track>,
+}
+
+/// 删除多余的自动备份，只保留最新的 keep 份（文件名带时间戳，按名称排序即按时间排序）
+fn prune_backups(dir: &Path, stem: &str, keep: usize) {
+    let prefix = format!("{}-", stem);
+    let mut backups: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".db"))
+            })
+            .collect(),
+        Err(e) => {
+            log::warn!("⚠️ 读取备份目录失败: {}", e);
+            return;
+        }
+    };
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for path in backups.into_iter().take(excess) {
+        match std::fs::remove_file(&path) {
+            Ok(()) => log::info!("🗑️ 删除旧备份: {}", path.display()),
+            Err(e) => log::warn!("⚠️ 删除旧备份失败 {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// 统一为正斜杠，去掉末尾的斜杠
+fn normalize_folder_path(path: &str) -> String {
+    path.replace('\\', "/").trim_end_matches('/').to_string()
+}
+
+/// 自然排序：数字按数值比较（Disc 2 在 Disc 10 之前），其他部分不区分大小写
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+        let mut digits = String::new();
+        while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+            digits.push(c);
+            chars.next();
+        }
+        digits.trim_start_matches('0').to_string()
+    }
+
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_number(&mut a_chars), take_number(&mut b_chars));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// 可编辑的曲目元数据
+///
+/// None 表示不修改；文本字段传空字符串表示清空（标题除外）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackMetadataUpdate {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<i64>,
+    pub track_number: Option<i64>,
+}
+
+impl TrackMetadataUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 检查字段：标题不能为空，年份在1000–2100之间，音轨号大于0
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.is_empty() {
+            return Err("没有要修改的字段".to_string());
+        }
+        if self.title.as_deref().is_some_and(|title| title.trim().is_empty()) {
+            return Err("标题不能为空".to_string());
+        }
+        if self.year.is_some_and(|year| !(1000..=2100).contains(&year)) {
+            return Err("年份必须在1000到2100之间".to_string());
+        }
+        if self.track_number.is_some_and(|n| n <= 0) {
+            return Err("音轨号必须大于0".to_string());
+        }
+        Ok(())
+    }
+
+    /// 批量修改时标题和音轨号对每首曲目不同，不允许统一设置
+    pub fn validate_bulk(&self) -> std::result::Result<(), String> {
+        if self.title.is_some() || self.track_number.is_some() {
+            return Err("批量修改不支持标题和音轨号".to_string());
+        }
+        self.validate()
+    }
+
+    /// 要更新的列和值（文本去掉首尾空白，空字符串存为NULL）
+    fn assignments(&self) -> Vec<(&'static str, rusqlite::types::Value)> {
+        use rusqlite::types::Value;
+        let text = |value: &str| {
+            let value = value.trim();
+            if value.is_empty() { Value::Null } else { Value::Text(value.to_string()) }
+        };
+        let mut assignments = Vec::new();
+        for (column, value) in [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("album_artist", &self.album_artist),
+            ("genre", &self.genre),
+        ] {
+            if let Some(value) = value {
+                assignments.push((column, text(value)));
+            }
+        }
+        if let Some(year) = self.year {
+            assignments.push(("year", Value::Integer(year)));
+        }
+        if let Some(track_number) = self.track_number {
+            assignments.push(("track_number", Value::Integer(track_number)));
+        }
+        assignments
+    }
+}
+
+/// 曲目评分上限（星数）
+pub const MAX_RATING: u8 = 5;
+
+/// 分页查询的排序字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackSortField {
+    #[default]
+    Title,
+    Artist,
+    Album,
+    Duration,
+    DateAdded,
+    PlayCount,
+    Rating,
+}
+
+impl TrackSortField {
+    fn column(&self) -> &'static str {
+        match self {
+            // 文本字段按排序键（拼音/罗马字）排序
+            TrackSortField::Title => "t.sort_title",
+            TrackSortField::Artist => "t.sort_artist",
+            TrackSortField::Album => "t.sort_album",
+            TrackSortField::Duration => "t.duration_ms",
+            TrackSortField::DateAdded => "t.created_at",
+            TrackSortField::PlayCount => "COALESCE(pc.play_count, 0)",
+            TrackSortField::Rating => "COALESCE(t.rating, 0)",
+        }
+    }
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// 曲目分页结果
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackPage {
+    pub tracks: Vec<Track>,
+    /// 符合筛选条件的曲目总数
+    pub total: i64,
+}
+
+/// 远程缓存记录（remote_cache表）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteCacheRecord {
+    pub track_id: Option<i64>,
+    pub local_cache_path: String,
+    pub file_size: i64,
+    pub access_count: i64,
+    pub cached_at: i64,
+    pub last_accessed: i64,
+    pub pinned: bool,
+}
+
+/// 同步任务（sync_queue表）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyncTask {
+    pub id: i64,
+    pub task_type: String,
+    pub track_id: Option<i64>,
+    pub source_path: String,
+    pub target_path: Option<String>,
+    pub server_id: String,
+    pub status: String,
+    pub progress_percent: i64,
+    pub error_message: Option<String>,
+    pub retry_count: i64,
+    pub max_retries: i64,
+    pub file_size: Option<i64>,
+    pub bytes_transferred: i64,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    pub next_retry_at: Option<i64>,
+}
+
+/// 批量获取歌词的候选曲目
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricsCandidate {
+    pub track_id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// 一页艺术家封面
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistCoverPage {
+    /// (艺术家, 图片数据, MIME类型)
+    pub covers: Vec<(String, Vec<u8>, String)>,
+    /// 有封面的艺术家总数
+    pub total: i64,
+    /// 下一页的起始位置，没有更多时为 None
+    pub next_offset: Option<i64>,
+}
+
+/// 待提交的播放记录（scrobble_queue表）
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedScrobble {
+    pub id: i64,
+    /// 序列化的播放记录（JSON）
+    pub payload: String,
+    pub attempts: i64,
+}
+
+/// 保存的续播位置（track_positions表）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavedPosition {
+    pub track_id: i64,
+    pub position_ms: i64,
+    pub updated_at: i64,
+}
+
+/// 元数据不完整的曲目（scan_issues表），界面据此显示提示
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanIssue {
+    pub track_id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    /// 完整提取失败的原因
+    pub reason: String,
+    pub detected_at: i64,
+}
+
+/// 完整性校验发现问题的曲目
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VerifyIssue {
+    pub track_id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    pub status: VerifyStatus,
+    /// 截断时的实际/应有时长或解码错误信息
+    pub detail: Option<String>,
+    pub verified_at: i64,
+}
+
+/// 扫描时记录的本地文件状态
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalFileState {
+    pub id: i64,
+    pub mtime: Option<i64>,
+    pub size: Option<i64>,
+    pub hash: Option<String>,
+}
+
+/// 曲目路径迁移（文件夹移动后按前缀替换路径）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathRelocation {
+    pub track_id: i64,
+    pub new_path: String,
+    /// 检查过文件内容时为新文件的状态（修改时间、大小、哈希），否则保留原记录
+    pub file_state: Option<(Option<i64>, i64, String)>,
+}
+
+/// 扫描时记录的远程文件状态
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteFileState {
+    pub mtime: Option<i64>,
+    pub size: Option<i64>,
+    pub etag: Option<String>,
+}
+
+/// 同步冲突（sync_conflicts表）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyncConflict {
+    pub id: i64,
+    pub track_id: i64,
+    pub server_id: String,
+    pub conflict_type: String,
+    pub local_path: Option<String>,
+    pub remote_path: Option<String>,
+    pub local_size: Option<i64>,
+    pub remote_size: Option<i64>,
+    pub local_modified: Option<i64>,
+    pub remote_modified: Option<i64>,
+    pub local_hash: Option<String>,
+    pub remote_hash: Option<String>,
+    pub resolution_strategy: Option<String>,
+    pub resolved: bool,
+    pub resolved_at: Option<i64>,
+    pub created_at: i64,
+}
+
+/// 曲目上次同步成功时记录的状态，用于判断两端是否被修改
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncBaseline {
+    pub last_sync: Option<i64>,
+    pub remote_etag: Option<String>,
+    pub remote_modified: Option<i64>,
 }
 
 // 注意：Favorite 结构体已移除，改用 favorites 表的直接操作
 
+/// 列表用的曲目字段（不含封面、艺术家图片等 BLOB），与 list_track_from_row 对应
+const LIST_TRACK_COLUMNS: &str = "t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status";
+
+fn list_track_from_row(row: &rusqlite::Row) -> rusqlite::Result<Track> {
+    Ok(Track {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        title: row.get(2)?,
+        artist: row.get(3)?,
+        album: row.get(4)?,
+        duration_ms: row.get(5)?,
+        album_cover_data: None,
+        album_cover_mime: None,
+        artist_photo_data: None,
+        artist_photo_mime: None,
+        embedded_lyrics: row.get(6)?,
+        genre: row.get(7)?,
+        year: row.get(8)?,
+        track_number: row.get(9)?,
+        disc_number: row.get(10)?,
+        album_artist: row.get(11)?,
+        cover_id: row.get(12)?,
+        rating: row.get(13)?,
+        verify_status: row.get(14)?,
+    })
+}
+
+/// 当前代码期望的数据库结构版本（PRAGMA user_version）
+pub const SCHEMA_VERSION: i64 = 14;
+
+/// 其他连接持有写锁时的等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 迁移前自动备份的目录（数据库所在目录下）
+pub const BACKUP_DIR: &str = "backups";
+
+/// 保留的自动备份数量
+const MAX_AUTO_BACKUPS: usize = 5;
+
+/// 备份时每步复制的页数，两步之间短暂让出数据库
+const BACKUP_PAGES_PER_STEP: std::os::raw::c_int = 1024;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// 曲库统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibraryStatistics {
+    pub total_tracks: i64,
+    /// 已知大小的文件总字节数
+    pub total_bytes: i64,
+    /// 按格式统计（曲目数从多到少）
+    pub formats: Vec<FormatStatistics>,
+    /// 比特率（kbps），没有已知比特率的曲目时为 None
+    pub bitrate_kbps: Option<ValueRange>,
+    /// 采样率（Hz）
+    pub sample_rate: Option<ValueRange>,
+    pub missing_cover: i64,
+    /// 既没有内嵌歌词也没有保存歌词的曲目数
+    pub missing_lyrics: i64,
+    pub missing_duration: i64,
+    /// 各来源（local / webdav / cached）的曲目数
+    pub sources: Vec<SourceCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatStatistics {
+    /// 格式名（如 flac、mp3），扫描时未记录格式的曲目按扩展名推断
+    pub format: String,
+    pub track_count: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueRange {
+    pub avg: f64,
+    pub min: i64,
+    pub max: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceCount {
+    pub source_type: String,
+    pub track_count: i64,
+}
+
+/// 扫描时从文件读取的音频属性
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioProperties {
+    pub format: Option<String>,
+    pub bitrate_kbps: Option<i64>,
+    pub sample_rate: Option<i64>,
+    pub channels: Option<i64>,
+}
+
+/// 时长缺失的曲目
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingDuration {
+    pub track_id: i64,
+    pub path: String,
+    /// 扫描时记录的文件大小（远程曲目为服务器返回的大小）
+    pub file_size: Option<i64>,
+    pub bitrate_kbps: Option<i64>,
+}
+
+/// VACUUM前后的数据库大小
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VacuumStats {
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+}
+
+/// 曲目表行数与全文索引中的文档数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndexCounts {
+    pub tracks: i64,
+    pub indexed: i64,
+}
+
+impl SearchIndexCounts {
+    /// 行数不一致说明索引缺行或残留已删除的曲目
+    pub fn is_consistent(&self) -> bool {
+        self.tracks == self.indexed
+    }
+}
+
+/// 全文索引重建前后的行数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndexRebuild {
+    pub before: SearchIndexCounts,
+    pub after: SearchIndexCounts,
+}
+
+/// 重建全文索引时数据库忙的重试次数和间隔
+const REBUILD_BUSY_RETRIES: u32 = 3;
+const REBUILD_RETRY_DELAY: Duration = Duration::from_millis(500);
+
 pub struct Database {
     conn: Connection,
     // 🔧 性能优化：线程安全的查询缓存
     cache: Arc<Mutex<QueryCache>>,
+    /// 远程服务器凭据加解密
+    secrets: SecretBox,
+    /// 艺术家拆分设置（从应用设置读取后缓存，应用设置被写入时失效）
+    artist_split: Mutex<Option<ArtistSplitConfig>>,
+    /// 是否把中日文转写写入全文索引（同上，对应设置被写入时失效）
+    transliterate_search: Mutex<Option<bool>>,
 }
 
 impl Database {
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
         let conn = Connection::open(db_path)?;
+        // 内存数据库没有对应的密钥文件，使用临时密钥
+        let secrets = if db_path == Path::new(":memory:") {
+            SecretBox::ephemeral()?
+        } else {
+            // WAL模式下读连接不会被写事务阻塞
+            let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+            if !journal_mode.eq_ignore_ascii_case("wal") {
+                log::warn!("⚠️ 无法启用WAL模式，当前为: {}", journal_mode);
+            }
+            conn.pragma_update(None, "synchronous", "NORMAL")?;
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            SecretBox::load_or_create(&secrets::key_path_for(db_path))?
+        };
+        // SQLite默认不检查外键，不开启时 ON DELETE CASCADE 不会生效
+        conn.pragma_update(None, "foreign_keys", true)?;
         let db = Database { 
             conn,
             // 🔧 性能优化：初始化查询缓存
             cache: Arc::new(Mutex::new(QueryCache::new())),
+            secrets,
+            artist_split: Mutex::new(None),
+            transliterate_search: Mutex::new(None),
         };
+        // 旧版本数据库迁移前先备份，迁移出错时可以恢复
+        if db_path != Path::new(":memory:") && db.needs_migration()? {
+            if let Err(e) = db.backup_before_migration(db_path) {
+                log::warn!("⚠️ 迁移前备份数据库失败: {}", e);
+            }
+        }
         db.init_schema()?;
         Ok(db)
     }
 
+    /// 已有数据库的结构版本是否低于当前版本（新建的空数据库不需要迁移）
+    fn needs_migration(&self) -> Result<bool> {
+        let has_tracks: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tracks')",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(has_tracks && self.get_schema_version()? < SCHEMA_VERSION)
+    }
+
+    /// 迁移前备份到数据库目录下的 backups 中，只保留最近几份
+    fn backup_before_migration(&self, db_path: &Path) -> Result<()> {
+        let dir = db_path.parent().unwrap_or_else(|| Path::new(".")).join(BACKUP_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let stem = db_path.file_stem().and_then(|s| s.to_str()).unwrap_or("windchime");
+        let backup_path = dir.join(format!(
+            "{}-{}-v{}.db",
+            stem,
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            self.get_schema_version()?
+        ));
+        log::info!("💾 数据库需要迁移，先备份到: {}", backup_path.display());
+        self.backup_to(&backup_path)?;
+        prune_backups(&dir, stem, MAX_AUTO_BACKUPS);
+        Ok(())
+    }
+
+    /// 使用SQLite在线备份API把数据库复制到指定文件（备份期间可以继续读写）
+    pub fn backup_to<P: AsRef<Path>>(&self, dest: P) -> Result<()> {
+        let dest = dest.as_ref();
+        if let (Some(source), Ok(target)) = (self.conn.path(), dest.canonicalize()) {
+            if Path::new(source).canonicalize().ok().as_deref() == Some(target.as_path()) {
+                return Err(anyhow::anyhow!("备份文件不能是数据库本身"));
+            }
+        }
+        let mut target = Connection::open(dest)?;
+        Backup::new(&self.conn, &mut target)?.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)?;
+        Ok(())
+    }
+
+    /// 整理数据库文件，回收删除数据后留下的空间
+    pub fn vacuum(&self) -> Result<VacuumStats> {
+        let bytes_before = self.database_size()?;
+        self.conn.execute_batch("VACUUM")?;
+        // WAL模式下VACUUM的内容先写入WAL，检查点后主文件才会变小
+        if self.conn.path().is_some_and(|path| !path.is_empty()) {
+            self.conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        }
+        let bytes_after = self.database_size()?;
+        log::info!("🧹 VACUUM完成: {} -> {} 字节", bytes_before, bytes_after);
+        Ok(VacuumStats { bytes_before, bytes_after })
+    }
+
+    fn database_size(&self) -> Result<i64> {
+        let size = self.conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size)
+    }
+
+    /// 完整性检查，返回 PRAGMA integrity_check 的输出（正常时只有一行 "ok"）
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let rows = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(rows)
+    }
+
+    /// 打开只读连接（连接池的读连接），不建表也不迁移，数据库须已由 new 打开过
+    pub fn open_reader<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        )?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(Database {
+            conn,
+            cache: Arc::new(Mutex::new(QueryCache::new())),
+            secrets: SecretBox::load_or_create(&secrets::key_path_for(db_path))?,
+            artist_split: Mutex::new(None),
+            transliterate_search: Mutex::new(None),
+        })
+    }
+
     fn init_schema(&self) -> Result<()> {
         // Create tracks table - 扩展支持多种音乐源
         self.conn.execute(
@@ -193,7 +1014,8 @@ impl Database {
                 cache_status TEXT DEFAULT 'none' CHECK(cache_status IN ('none', 'partial', 'cached', 'expired', 'updating')),
                 remote_modified INTEGER,
                 last_sync INTEGER,
-                server_id TEXT -- 关联的WebDAV服务器ID
+                server_id TEXT, -- 关联的WebDAV服务器ID
+                remote_etag TEXT -- 上次同步时的远程ETag，用于检测远程修改
             )",
             [],
         )?;
@@ -209,6 +1031,40 @@ impl Database {
         
         // Migrate existing schema: Add WebDAV and sync support columns
         self.migrate_webdav_support_columns()?;
+        
+        // Migrate existing schema: Add ReplayGain columns
+        self.migrate_replay_gain_columns()?;
+        
+        // Migrate existing schema: Add loudness analysis column
+        self.migrate_loudness_column()?;
+        
+        // Migrate existing schema: Add silence trim point columns
+        self.migrate_trim_columns()?;
+        
+        // Migrate existing schema: Add file state columns for incremental scans
+        self.migrate_file_state_columns()?;
+        self.migrate_file_etag_column()?;
+
+        // Migrate existing schema: Add genre / year / track number columns
+        self.migrate_tag_columns()?;
+
+        // Migrate existing schema: Add last lyrics fetch attempt column
+        self.migrate_lyrics_attempt_column()?;
+
+        // Migrate existing schema: Add format / bitrate columns for library statistics
+        self.migrate_audio_format_columns()?;
+
+        // Migrate existing schema: Add star rating column
+        self.migrate_rating_column()?;
+
+        // Migrate existing schema: Add sort key and transliteration columns
+        self.migrate_sort_key_columns()?;
+
+        // Migrate existing schema: Add integrity verification columns
+        self.migrate_verify_columns()?;
+        
+        // Migrate existing schema: Move embedded covers into the covers table
+        self.migrate_cover_storage()?;
 
         // Create playlists table
         self.conn.execute(
@@ -239,6 +1095,9 @@ impl Database {
         // Migrate playlist_items table to add extended fields
         self.migrate_playlist_items_extended_columns()?;
 
+        // Playlist folders and manual sidebar order
+        self.migrate_playlist_folders()?;
+
         // Create lyrics table
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS lyrics (
@@ -282,6 +1141,12 @@ impl Database {
             self.conn.execute("ALTER TABLE play_history ADD COLUMN duration_played_ms INTEGER DEFAULT 0", [])?;
         }
 
+        // 记录来源：manual（命令写入/导入）或 player（后端根据实际播放记录）
+        if self.conn.prepare("SELECT source FROM play_history LIMIT 1").is_err() {
+            log::info!("添加source字段到play_history表");
+            self.conn.execute("ALTER TABLE play_history ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'", [])?;
+        }
+
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_play_history_track ON play_history(track_id)",
             [],
@@ -292,13 +1157,9 @@ impl Database {
             [],
         )?;
 
-        // Create FTS table for search
+        // 覆盖索引：按时间范围聚合统计时无需回表
         self.conn.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS tracks_fts USING fts5(
-                title, artist, album, path,
-                content='tracks',
-                content_rowid='id'
-            )",
+            "CREATE INDEX IF NOT EXISTS idx_play_history_time_track ON play_history(played_at, track_id, duration_played_ms)",
             [],
         )?;
 
@@ -344,11 +1205,13 @@ impl Database {
                 created_at INTEGER DEFAULT (strftime('%s', 'now')),
                 started_at INTEGER,
                 completed_at INTEGER,
-                FOREIGN KEY (server_id) REFERENCES webdav_servers (id) ON DELETE CASCADE,
+                next_retry_at INTEGER,
+                FOREIGN KEY (server_id) REFERENCES remote_servers (id) ON DELETE CASCADE,
                 FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
             )",
             [],
         )?;
+        self.migrate_sync_queue_table()?;
 
         // Create sync conflicts table - 单一职责：管理同步冲突
         self.conn.execute(
@@ -370,10 +1233,11 @@ impl Database {
                 resolved_at INTEGER,
                 created_at INTEGER DEFAULT (strftime('%s', 'now')),
                 FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE,
-                FOREIGN KEY (server_id) REFERENCES webdav_servers (id) ON DELETE CASCADE
+                FOREIGN KEY (server_id) REFERENCES remote_servers (id) ON DELETE CASCADE
             )",
             [],
         )?;
+        self.migrate_sync_conflicts_table()?;
 
         // Create sync statistics table - 单一职责：记录同步统计信息
         self.conn.execute(
@@ -420,14 +1284,14 @@ impl Database {
             [],
         )?;
 
-        // ========== 远程音乐源相关表 (仅支持WebDAV) ==========
+        // ========== 远程音乐源相关表 (WebDAV、Subsonic) ==========
         
         // 统一的远程服务器配置表
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS remote_servers (
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
-                server_type TEXT NOT NULL CHECK(server_type IN ('webdav')),
+                server_type TEXT NOT NULL CHECK(server_type IN ('webdav', 'subsonic')),
                 config_json TEXT NOT NULL,
                 enabled INTEGER DEFAULT 1,
                 priority INTEGER DEFAULT 0,
@@ -438,6 +1302,8 @@ impl Database {
             )",
             [],
         )?;
+        self.migrate_remote_server_types()?;
+        self.migrate_remote_server_credentials()?;
 
         // 统一的缓存表
         self.conn.execute(
@@ -473,6 +1339,9 @@ impl Database {
             "CREATE INDEX IF NOT EXISTS idx_cache_status ON remote_cache(cache_status)",
             [],
         )?;
+        
+        // Migrate existing schema: Add cache pinning columns
+        self.migrate_remote_cache_columns()?;
 
         // 艺术家封面表 - 存储从网络获取的艺术家封面
         self.conn.execute(
@@ -488,6 +1357,15 @@ impl Database {
             [],
         )?;
         
+        // 自动获取艺术家封面失败的记录，近期尝试过的艺术家不再重复请求
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS artist_cover_attempts (
+                artist_name TEXT PRIMARY KEY,
+                attempted_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        
         // 同步任务表
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS sync_tasks (
@@ -507,6 +1385,25 @@ impl Database {
 
         log::info!("远程服务器数据库表已创建");
 
+        // 应用设置表 - 通用键值存储
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at INTEGER DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        // 最近搜索记录（同一搜索词只保留一条）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS search_history (
+                query TEXT PRIMARY KEY COLLATE NOCASE,
+                searched_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
         // Create indexes for performance - 低耦合：优化查询性能
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_tracks_path ON tracks(path)",
@@ -537,35 +1434,117 @@ impl Database {
             "CREATE INDEX IF NOT EXISTS idx_sync_queue_server ON sync_queue(server_id, status)",
             [],
         )?;
-        
+
+        // 待提交到 ListenBrainz / Last.fm 的播放记录，提交失败时按退避时间重试
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_sync_conflicts_resolved ON sync_conflicts(resolved, created_at)",
+            "CREATE TABLE IF NOT EXISTS scrobble_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                service TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                listened_at INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
             [],
         )?;
-        
+
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_cache_metadata_expires ON cache_metadata(expires_at)",
+            "CREATE INDEX IF NOT EXISTS idx_scrobble_queue_due ON scrobble_queue(service, next_attempt_at)",
             [],
         )?;
 
+        // 长曲目的续播位置
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tracks_artist ON tracks(artist)",
+            "CREATE TABLE IF NOT EXISTS track_positions (
+                track_id INTEGER PRIMARY KEY,
+                position_ms INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+            )",
             [],
         )?;
 
+        // 单曲播放速度（换曲时默认恢复原速）
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tracks_album ON tracks(album)",
+            "CREATE TABLE IF NOT EXISTS track_playback_rates (
+                track_id INTEGER PRIMARY KEY,
+                rate REAL NOT NULL,
+                updated_at INTEGER NOT NULL,
+                FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+            )",
             [],
         )?;
 
+        // 元数据提取失败、使用了部分元数据或路径推断的曲目
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_playlist_items_playlist ON playlist_items(playlist_id)",
+            "CREATE TABLE IF NOT EXISTS scan_issues (
+                track_id INTEGER PRIMARY KEY,
+                reason TEXT NOT NULL,
+                detected_at INTEGER NOT NULL,
+                FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+            )",
             [],
         )?;
 
+        // 曲目内的章节（MP4章节、Vorbis章节标签、CUE）
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_lyrics_track ON lyrics(track_id)",
-            [],
+            "CREATE TABLE IF NOT EXISTS chapters (
+                track_id INTEGER NOT NULL,
+                idx INTEGER NOT NULL,
+                title TEXT,
+                start_ms INTEGER NOT NULL,
+                end_ms INTEGER,
+                PRIMARY KEY (track_id, idx),
+                FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sync_conflicts_resolved ON sync_conflicts(resolved, created_at)",
+            [],
+        )?;
+        
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_cache_metadata_expires ON cache_metadata(expires_at)",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_tracks_artist ON tracks(artist)",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_tracks_album ON tracks(album)",
+            [],
+        )?;
+
+        // 分页排序用索引
+        for (name, column) in [
+            ("title", "title"),
+            ("duration", "duration_ms"),
+            ("created_at", "created_at"),
+            ("sort_title", "sort_title"),
+            ("sort_artist", "sort_artist"),
+            ("sort_album", "sort_album"),
+        ] {
+            self.conn.execute(
+                &format!("CREATE INDEX IF NOT EXISTS idx_tracks_{} ON tracks({}, id)", name, column),
+                [],
+            )?;
+        }
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_playlist_items_playlist ON playlist_items(playlist_id)",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_lyrics_track ON lyrics(track_id)",
+            [],
         )?;
 
         self.conn.execute(
@@ -573,29 +1552,80 @@ impl Database {
             [],
         )?;
 
-        // Create triggers to sync with FTS
+        // Create FTS table for search (with sync triggers)
+        self.migrate_tracks_fts()?;
+        self.create_tracks_fts()?;
+        self.repair_fts_triggers()?;
+
+        // 缓存失效用的表变更计数：任何连接写入 tracks / favorites / lyrics 都会递增对应计数
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS cache_meta (
+                table_name TEXT PRIMARY KEY,
+                version INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO cache_meta (table_name, version) VALUES ('tracks', 0), ('favorites', 0), ('lyrics', 0)",
+            [],
+        )?;
+        for table in ["tracks", "favorites", "lyrics"] {
+            for (suffix, event) in [("ai", "INSERT"), ("au", "UPDATE"), ("ad", "DELETE")] {
+                self.conn.execute(
+                    &format!(
+                        "CREATE TRIGGER IF NOT EXISTS cache_meta_{table}_{suffix} AFTER {event} ON {table} BEGIN
+                            UPDATE cache_meta SET version = version + 1 WHERE table_name = '{table}';
+                        END"
+                    ),
+                    [],
+                )?;
+            }
+        }
+
+        // 多艺术家拆分结果（重新拆分时会递增 cache_meta 的计数）
+        self.migrate_track_artists()?;
+
+        // 记录结构版本（只升不降，便于发现被新版本程序改过的数据库）
+        if self.get_schema_version()? < SCHEMA_VERSION {
+            self.conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+
+        Ok(())
+    }
+
+    /// 创建全文搜索表及同步触发器
+    fn create_tracks_fts(&self) -> Result<()> {
+        self.conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS tracks_fts USING fts5(
+                title, artist, album, path, genre, album_artist, translit,
+                content='tracks',
+                content_rowid='id'
+            )",
+            [],
+        )?;
+
         self.conn.execute(
             "CREATE TRIGGER IF NOT EXISTS tracks_ai AFTER INSERT ON tracks BEGIN
-                INSERT INTO tracks_fts(rowid, title, artist, album, path) 
-                VALUES (new.id, new.title, new.artist, new.album, new.path);
+                INSERT INTO tracks_fts(rowid, title, artist, album, path, genre, album_artist, translit) 
+                VALUES (new.id, new.title, new.artist, new.album, new.path, new.genre, new.album_artist, new.translit);
             END",
             [],
         )?;
 
         self.conn.execute(
             "CREATE TRIGGER IF NOT EXISTS tracks_ad AFTER DELETE ON tracks BEGIN
-                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, path) 
-                VALUES('delete', old.id, old.title, old.artist, old.album, old.path);
+                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, path, genre, album_artist, translit) 
+                VALUES('delete', old.id, old.title, old.artist, old.album, old.path, old.genre, old.album_artist, old.translit);
             END",
             [],
         )?;
 
         self.conn.execute(
             "CREATE TRIGGER IF NOT EXISTS tracks_au AFTER UPDATE ON tracks BEGIN
-                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, path) 
-                VALUES('delete', old.id, old.title, old.artist, old.album, old.path);
-                INSERT INTO tracks_fts(rowid, title, artist, album, path) 
-                VALUES (new.id, new.title, new.artist, new.album, new.path);
+                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, path, genre, album_artist, translit) 
+                VALUES('delete', old.id, old.title, old.artist, old.album, old.path, old.genre, old.album_artist, old.translit);
+                INSERT INTO tracks_fts(rowid, title, artist, album, path, genre, album_artist, translit) 
+                VALUES (new.id, new.title, new.artist, new.album, new.path, new.genre, new.album_artist, new.translit);
             END",
             [],
         )?;
@@ -603,6 +1633,99 @@ impl Database {
         Ok(())
     }
 
+    /// 旧版全文索引不含流派、专辑艺术家或转写：删除后按新结构重建并重新填充
+    fn migrate_tracks_fts(&self) -> Result<()> {
+        let fts_exists = self.conn.prepare("SELECT title FROM tracks_fts LIMIT 1").is_ok();
+        if !fts_exists || self.conn.prepare("SELECT translit FROM tracks_fts LIMIT 1").is_ok() {
+            return Ok(());
+        }
+
+        log::info!("重建tracks_fts全文索引（新增genre、album_artist、translit）");
+        self.conn.execute_batch(
+            "DROP TRIGGER IF EXISTS tracks_ai;
+             DROP TRIGGER IF EXISTS tracks_ad;
+             DROP TRIGGER IF EXISTS tracks_au;
+             DROP TABLE tracks_fts;",
+        )?;
+        self.create_tracks_fts()?;
+        self.conn.execute("INSERT INTO tracks_fts(tracks_fts) VALUES('rebuild')", [])?;
+        Ok(())
+    }
+
+    /// 同步触发器是旧版本创建的（不含流派、专辑艺术家或转写）时重新创建并重建索引
+    ///
+    /// CREATE TRIGGER IF NOT EXISTS 不会替换已有的旧触发器，索引已是新结构时 migrate_tracks_fts 也不会处理
+    fn repair_fts_triggers(&self) -> Result<()> {
+        let current: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master
+             WHERE type = 'trigger' AND name IN ('tracks_ai', 'tracks_ad', 'tracks_au') AND sql LIKE '%translit%'",
+            [],
+            |row| row.get(0),
+        )?;
+        if current == 3 {
+            return Ok(());
+        }
+
+        log::warn!("⚠️ 全文索引同步触发器缺少新列，重新创建并重建索引");
+        self.recreate_fts_triggers()?;
+        self.conn.execute("INSERT INTO tracks_fts(tracks_fts) VALUES('rebuild')", [])?;
+        Ok(())
+    }
+
+    fn recreate_fts_triggers(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "DROP TRIGGER IF EXISTS tracks_ai;
+             DROP TRIGGER IF EXISTS tracks_ad;
+             DROP TRIGGER IF EXISTS tracks_au;",
+        )?;
+        self.create_tracks_fts()
+    }
+
+    /// 曲目表行数和全文索引中的文档数（docsize 影子表每个已索引的行一条记录）
+    pub fn search_index_counts(&self) -> Result<SearchIndexCounts> {
+        let tracks = self.conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))?;
+        let indexed = self.conn.query_row("SELECT COUNT(*) FROM tracks_fts_docsize", [], |row| row.get(0))?;
+        Ok(SearchIndexCounts { tracks, indexed })
+    }
+
+    /// 按曲目表重建全文索引，同时重新创建同步触发器
+    ///
+    /// 在 IMMEDIATE 事务中执行，期间其他连接无法写入曲目表，读连接不受影响；
+    /// 数据库忙时先按 busy_timeout 等待，仍然忙则稍后重试
+    pub fn rebuild_search_index(&self) -> Result<SearchIndexRebuild> {
+        let mut attempt = 0;
+        loop {
+            match self.try_rebuild_search_index() {
+                Err(e) if attempt < REBUILD_BUSY_RETRIES && is_busy_error(&e) => {
+                    attempt += 1;
+                    log::warn!("⚠️ 重建全文索引时数据库忙，第 {} 次重试", attempt);
+                    std::thread::sleep(REBUILD_RETRY_DELAY);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn try_rebuild_search_index(&self) -> Result<SearchIndexRebuild> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        let before = self.search_index_counts()?;
+        self.recreate_fts_triggers()?;
+        self.conn.execute("INSERT INTO tracks_fts(tracks_fts) VALUES('rebuild')", [])?;
+        let after = self.search_index_counts()?;
+        tx.commit()?;
+        log::info!(
+            "🔎 全文索引已重建: 曲目 {}，索引 {} -> {}",
+            after.tracks, before.indexed, after.indexed
+        );
+        Ok(SearchIndexRebuild { before, after })
+    }
+
+    /// 读取数据库结构版本
+    pub fn get_schema_version(&self) -> Result<i64> {
+        let version = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version)
+    }
+
     /// 迁移专辑封面字段到现有数据库
     fn migrate_album_cover_columns(&self) -> Result<()> {
         // 检查是否需要添加专辑封面字段
@@ -673,145 +1796,583 @@ impl Database {
         Ok(())
     }
     
-    /// 迁移WebDAV和同步支持字段到现有数据库
-    fn migrate_webdav_support_columns(&self) -> Result<()> {
-        // 检查并添加source_type字段
-        let source_type_exists = self.conn.prepare("SELECT source_type FROM tracks LIMIT 1");
-        if source_type_exists.is_err() {
-            log::info!("添加source_type字段到tracks表");
-            self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN source_type TEXT DEFAULT 'local' CHECK(source_type IN ('local', 'webdav', 'cached'))",
-                []
-            )?;
-        }
+    /// 迁移ReplayGain字段到现有数据库
+    fn migrate_replay_gain_columns(&self) -> Result<()> {
+        let column_exists = self.conn.prepare("SELECT replaygain_track_gain FROM tracks LIMIT 1");
         
-        // 检查并添加source_config字段
-        let source_config_exists = self.conn.prepare("SELECT source_config FROM tracks LIMIT 1");
-        if source_config_exists.is_err() {
-            log::info!("添加source_config字段到tracks表");
+        if column_exists.is_err() {
+            log::info!("添加ReplayGain字段到tracks表");
+            
             self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN source_config TEXT",
-                []
+                "ALTER TABLE tracks ADD COLUMN replaygain_track_gain REAL",
+                [],
             )?;
-        }
-        
-        // 检查并添加sync_status字段
-        let sync_status_exists = self.conn.prepare("SELECT sync_status FROM tracks LIMIT 1");
-        if sync_status_exists.is_err() {
-            log::info!("添加sync_status字段到tracks表");
+            
             self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN sync_status TEXT DEFAULT 'local_only' CHECK(sync_status IN ('local_only', 'remote_only', 'synced', 'conflict', 'syncing', 'sync_error'))",
-                []
+                "ALTER TABLE tracks ADD COLUMN replaygain_album_gain REAL",
+                [],
             )?;
+            
+            log::info!("ReplayGain字段添加成功");
         }
         
-        // 检查并添加cache_status字段
-        let cache_status_exists = self.conn.prepare("SELECT cache_status FROM tracks LIMIT 1");
-        if cache_status_exists.is_err() {
-            log::info!("添加cache_status字段到tracks表");
-            self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN cache_status TEXT DEFAULT 'none' CHECK(cache_status IN ('none', 'partial', 'cached', 'expired', 'updating'))",
-                []
-            )?;
-        }
+        Ok(())
+    }
+    
+    /// 迁移缓存固定字段到remote_cache表
+    fn migrate_remote_cache_columns(&self) -> Result<()> {
+        let column_exists = self.conn.prepare("SELECT pinned FROM remote_cache LIMIT 1");
         
-        // 检查并添加remote_modified字段
-        let remote_modified_exists = self.conn.prepare("SELECT remote_modified FROM tracks LIMIT 1");
-        if remote_modified_exists.is_err() {
-            log::info!("添加remote_modified字段到tracks表");
-            self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN remote_modified INTEGER",
-                []
-            )?;
+        if column_exists.is_err() {
+            log::info!("添加track_id和pinned字段到remote_cache表");
+            self.conn.execute("ALTER TABLE remote_cache ADD COLUMN track_id INTEGER", [])?;
+            self.conn.execute("ALTER TABLE remote_cache ADD COLUMN pinned INTEGER DEFAULT 0", [])?;
         }
         
-        // 检查并添加last_sync字段
-        let last_sync_exists = self.conn.prepare("SELECT last_sync FROM tracks LIMIT 1");
-        if last_sync_exists.is_err() {
-            log::info!("添加last_sync字段到tracks表");
-            self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN last_sync INTEGER",
-                []
-            )?;
+        Ok(())
+    }
+    
+    /// 重建表：新表以 <table>_new 创建，复制指定列后替换旧表
+    ///
+    /// SQLite无法修改约束，只能重建表；重建期间关闭外键，避免删除旧表时级联删除关联记录。
+    fn rebuild_table(&self, table: &str, create_new_sql: &str, columns: &str) -> Result<()> {
+        let foreign_keys: bool = self.conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        self.conn.pragma_update(None, "foreign_keys", false)?;
+        let result = self.conn.execute_batch(&format!(
+            "BEGIN;
+             {create_new_sql};
+             INSERT INTO {table}_new ({columns}) SELECT {columns} FROM {table};
+             DROP TABLE {table};
+             ALTER TABLE {table}_new RENAME TO {table};
+             COMMIT;"
+        ));
+        if result.is_err() {
+            let _ = self.conn.execute_batch("ROLLBACK");
         }
+        self.conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+        result?;
+        Ok(())
+    }
+    
+    /// 放宽remote_servers的server_type约束以支持Subsonic
+    fn migrate_remote_server_types(&self) -> Result<()> {
+        let table_sql: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'remote_servers'",
+            [],
+            |row| row.get(0),
+        )?;
         
-        // 检查并添加server_id字段
-        let server_id_exists = self.conn.prepare("SELECT server_id FROM tracks LIMIT 1");
-        if server_id_exists.is_err() {
-            log::info!("添加server_id字段到tracks表");
-            self.conn.execute(
-                "ALTER TABLE tracks ADD COLUMN server_id TEXT",
-                []
+        if !table_sql.contains("'subsonic'") {
+            log::info!("重建remote_servers表以支持Subsonic服务器");
+            self.rebuild_table(
+                "remote_servers",
+                "CREATE TABLE remote_servers_new (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    server_type TEXT NOT NULL CHECK(server_type IN ('webdav', 'subsonic')),
+                    config_json TEXT NOT NULL,
+                    enabled INTEGER DEFAULT 1,
+                    priority INTEGER DEFAULT 0,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    last_connected_at INTEGER,
+                    connection_status TEXT DEFAULT 'unknown'
+                 )",
+                "id, name, server_type, config_json, enabled, priority, created_at, updated_at, last_connected_at, connection_status",
             )?;
         }
         
-        log::info!("WebDAV支持字段迁移完成");
         Ok(())
     }
-
-    /// 迁移歌单表扩展字段
-    fn migrate_playlist_extended_columns(&self) -> Result<()> {
-        // description
-        if self.conn.prepare("SELECT description FROM playlists LIMIT 1").is_err() {
-            log::info!("添加description字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN description TEXT", [])?;
-        }
+    
+    /// 加密旧版本以明文保存的服务器密码
+    fn migrate_remote_server_credentials(&self) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT id, config_json FROM remote_servers")?;
+        let servers = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
         
-        // cover_path
-        if self.conn.prepare("SELECT cover_path FROM playlists LIMIT 1").is_err() {
-            log::info!("添加cover_path字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN cover_path TEXT", [])?;
+        for (id, config_json) in servers {
+            let encrypted = match self.secrets.encrypt_config(&config_json, None) {
+                Ok(encrypted) => encrypted,
+                Err(e) => {
+                    log::warn!("服务器 {} 的配置无法解析，跳过凭据加密: {}", id, e);
+                    continue;
+                }
+            };
+            if encrypted != config_json {
+                self.conn.execute(
+                    "UPDATE remote_servers SET config_json = ?1 WHERE id = ?2",
+                    params![encrypted, id],
+                )?;
+                log::info!("🔐 已加密服务器 {} 的凭据", id);
+            }
         }
         
-        // is_smart
-        if self.conn.prepare("SELECT is_smart FROM playlists LIMIT 1").is_err() {
-            log::info!("添加is_smart字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_smart INTEGER DEFAULT 0", [])?;
-        }
+        Ok(())
+    }
+    
+    /// 修正sync_queue的外键（旧表引用了已废弃的webdav_servers表）并添加重试时间字段
+    fn migrate_sync_queue_table(&self) -> Result<()> {
+        let table_sql: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'sync_queue'",
+            [],
+            |row| row.get(0),
+        )?;
         
-        // smart_rules
-        if self.conn.prepare("SELECT smart_rules FROM playlists LIMIT 1").is_err() {
-            log::info!("添加smart_rules字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN smart_rules TEXT", [])?;
+        if table_sql.contains("webdav_servers") || !table_sql.contains("next_retry_at") {
+            log::info!("重建sync_queue表（外键指向remote_servers，添加next_retry_at字段）");
+            self.rebuild_table(
+                "sync_queue",
+                "CREATE TABLE sync_queue_new (
+                    id INTEGER PRIMARY KEY,
+                    task_type TEXT NOT NULL CHECK(task_type IN ('upload', 'download', 'delete', 'metadata_sync')),
+                    track_id INTEGER,
+                    source_path TEXT NOT NULL,
+                    target_path TEXT,
+                    server_id TEXT NOT NULL,
+                    priority INTEGER DEFAULT 0 CHECK(priority IN (0, 1, 2)),
+                    status TEXT DEFAULT 'pending' CHECK(status IN ('pending', 'running', 'completed', 'failed', 'cancelled')),
+                    progress_percent INTEGER DEFAULT 0 CHECK(progress_percent BETWEEN 0 AND 100),
+                    error_message TEXT,
+                    retry_count INTEGER DEFAULT 0,
+                    max_retries INTEGER DEFAULT 3,
+                    file_size INTEGER,
+                    bytes_transferred INTEGER DEFAULT 0,
+                    created_at INTEGER DEFAULT (strftime('%s', 'now')),
+                    started_at INTEGER,
+                    completed_at INTEGER,
+                    next_retry_at INTEGER,
+                    FOREIGN KEY (server_id) REFERENCES remote_servers (id) ON DELETE CASCADE,
+                    FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+                 )",
+                "id, task_type, track_id, source_path, target_path, server_id, priority, status, progress_percent, \
+                 error_message, retry_count, max_retries, file_size, bytes_transferred, created_at, started_at, completed_at",
+            )?;
         }
         
-        // color_theme
-        if self.conn.prepare("SELECT color_theme FROM playlists LIMIT 1").is_err() {
-            log::info!("添加color_theme字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN color_theme TEXT", [])?;
+        Ok(())
+    }
+    
+    /// 旧版sync_conflicts表的外键指向已废弃的webdav_servers，冲突记录无法写入
+    fn migrate_sync_conflicts_table(&self) -> Result<()> {
+        let table_sql: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'sync_conflicts'",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        if table_sql.contains("webdav_servers") {
+            log::info!("重建sync_conflicts表（外键指向remote_servers）");
+            self.rebuild_table(
+                "sync_conflicts",
+                &table_sql
+                    .replacen("sync_conflicts", "sync_conflicts_new", 1)
+                    .replace("webdav_servers", "remote_servers"),
+                "id, track_id, server_id, conflict_type, local_path, remote_path, local_size, remote_size, \
+                 local_modified, remote_modified, local_hash, remote_hash, resolution_strategy, resolved, resolved_at, created_at",
+            )?;
         }
         
-        // is_favorite
-        if self.conn.prepare("SELECT is_favorite FROM playlists LIMIT 1").is_err() {
-            log::info!("添加is_favorite字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
+        Ok(())
+    }
+    
+    /// 迁移响度分析字段到现有数据库
+    fn migrate_loudness_column(&self) -> Result<()> {
+        let column_exists = self.conn.prepare("SELECT loudness_lufs FROM tracks LIMIT 1");
+        
+        if column_exists.is_err() {
+            log::info!("添加loudness_lufs字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN loudness_lufs REAL", [])?;
         }
         
-        // last_played
-        if self.conn.prepare("SELECT last_played FROM playlists LIMIT 1").is_err() {
-            log::info!("添加last_played字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN last_played INTEGER", [])?;
+        Ok(())
+    }
+    
+    /// 迁移静音裁剪点字段到现有数据库（silence_threshold_db 为分析时使用的阈值，NULL表示未分析）
+    fn migrate_trim_columns(&self) -> Result<()> {
+        let column_exists = self.conn.prepare("SELECT silence_threshold_db FROM tracks LIMIT 1");
+        
+        if column_exists.is_err() {
+            log::info!("添加静音裁剪点字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN trim_start_ms INTEGER", [])?;
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN trim_end_ms INTEGER", [])?;
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN silence_threshold_db REAL", [])?;
         }
         
-        // play_count
-        if self.conn.prepare("SELECT play_count FROM playlists LIMIT 1").is_err() {
-            log::info!("添加play_count字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN play_count INTEGER DEFAULT 0", [])?;
+        Ok(())
+    }
+    
+    /// 迁移增量扫描所需的文件状态字段（last_modified 记录的是写入时间，不是文件修改时间）
+    fn migrate_file_state_columns(&self) -> Result<()> {
+        if self.conn.prepare("SELECT file_mtime FROM tracks LIMIT 1").is_err() {
+            log::info!("添加file_mtime字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN file_mtime INTEGER", [])?;
         }
         
-        // updated_at
-        if self.conn.prepare("SELECT updated_at FROM playlists LIMIT 1").is_err() {
-            log::info!("添加updated_at字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN updated_at INTEGER", [])?;
+        if self.conn.prepare("SELECT file_size FROM tracks LIMIT 1").is_err() {
+            log::info!("添加file_size字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN file_size INTEGER", [])?;
         }
         
-        // is_pinned
-        if self.conn.prepare("SELECT is_pinned FROM playlists LIMIT 1").is_err() {
-            log::info!("添加is_pinned字段到playlists表");
-            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_pinned INTEGER DEFAULT 0", [])?;
+        Ok(())
+    }
+    
+    /// 迁移远程曲目增量扫描所需的ETag字段（远程曲目的 file_mtime / file_size 记录服务器返回的值）
+    ///
+    /// 与同步基线 remote_etag 分开记录，扫描不影响冲突检测
+    fn migrate_file_etag_column(&self) -> Result<()> {
+        if self.conn.prepare("SELECT file_etag FROM tracks LIMIT 1").is_err() {
+            log::info!("添加file_etag字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN file_etag TEXT", [])?;
         }
         
-        log::info!("歌单表扩展字段迁移完成");
+        Ok(())
+    }
+    
+    /// 迁移流派、年份、音轨号等标签字段
+    fn migrate_tag_columns(&self) -> Result<()> {
+        for (column, column_type) in [
+            ("genre", "TEXT"),
+            ("year", "INTEGER"),
+            ("track_number", "INTEGER"),
+            ("disc_number", "INTEGER"),
+            ("album_artist", "TEXT"),
+        ] {
+            if self.conn.prepare(&format!("SELECT {} FROM tracks LIMIT 1", column)).is_err() {
+                log::info!("添加{}字段到tracks表", column);
+                self.conn.execute(&format!("ALTER TABLE tracks ADD COLUMN {} {}", column, column_type), [])?;
+            }
+        }
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_tracks_album_order ON tracks(artist, album, disc_number, track_number)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// 迁移批量获取歌词的尝试时间字段（用于跳过近期已尝试过的曲目）
+    fn migrate_lyrics_attempt_column(&self) -> Result<()> {
+        if self.conn.prepare("SELECT last_lyrics_attempt FROM tracks LIMIT 1").is_err() {
+            log::info!("添加last_lyrics_attempt字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN last_lyrics_attempt INTEGER", [])?;
+        }
+
+        Ok(())
+    }
+
+    /// 迁移曲库统计所需的格式和比特率字段（采样率、声道数字段建表时已有）
+    fn migrate_audio_format_columns(&self) -> Result<()> {
+        for (column, column_type) in [("format", "TEXT"), ("bitrate", "INTEGER")] {
+            if self.conn.prepare(&format!("SELECT {} FROM tracks LIMIT 1", column)).is_err() {
+                log::info!("添加{}字段到tracks表", column);
+                self.conn.execute(&format!("ALTER TABLE tracks ADD COLUMN {} {}", column, column_type), [])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 迁移曲目评分字段（0-5星，NULL表示未评分）
+    fn migrate_rating_column(&self) -> Result<()> {
+        if self.conn.prepare("SELECT rating FROM tracks LIMIT 1").is_err() {
+            log::info!("添加rating字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN rating INTEGER", [])?;
+        }
+
+        Ok(())
+    }
+
+    /// 排序键和转写：新增字段时按当前设置为已有曲目计算
+    fn migrate_sort_key_columns(&self) -> Result<()> {
+        if self.conn.prepare("SELECT sort_title FROM tracks LIMIT 1").is_ok() {
+            return Ok(());
+        }
+        log::info!("添加sort_title、sort_artist、sort_album、translit字段到tracks表");
+        self.conn.execute_batch(
+            "ALTER TABLE tracks ADD COLUMN sort_title TEXT;
+             ALTER TABLE tracks ADD COLUMN sort_artist TEXT;
+             ALTER TABLE tracks ADD COLUMN sort_album TEXT;
+             ALTER TABLE tracks ADD COLUMN translit TEXT;",
+        )?;
+        let updated = self.rebuild_sort_keys()?;
+        if updated > 0 {
+            log::info!("为 {} 首已有曲目计算排序键", updated);
+        }
+        Ok(())
+    }
+
+    fn migrate_verify_columns(&self) -> Result<()> {
+        if self.conn.prepare("SELECT verify_status FROM tracks LIMIT 1").is_err() {
+            log::info!("添加verify_status、verify_detail、verified_at字段到tracks表");
+            self.conn.execute_batch(
+                "ALTER TABLE tracks ADD COLUMN verify_status TEXT;
+                 ALTER TABLE tracks ADD COLUMN verify_detail TEXT;
+                 ALTER TABLE tracks ADD COLUMN verified_at INTEGER;",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 多艺术家拆分：创建track_artists表，新建或补充排序键时按保存的拆分设置拆分已有曲目
+    fn migrate_track_artists(&self) -> Result<()> {
+        if self.conn.prepare("SELECT track_id FROM track_artists LIMIT 1").is_err() {
+            self.conn.execute(
+                "CREATE TABLE track_artists (
+                    track_id INTEGER NOT NULL,
+                    position INTEGER NOT NULL,
+                    artist TEXT NOT NULL,
+                    role TEXT NOT NULL,
+                    sort_key TEXT,
+                    PRIMARY KEY (track_id, position),
+                    FOREIGN KEY (track_id) REFERENCES tracks (id) ON DELETE CASCADE
+                )",
+                [],
+            )?;
+            self.conn.execute("CREATE INDEX IF NOT EXISTS idx_track_artists_artist ON track_artists(artist)", [])?;
+        } else if self.conn.prepare("SELECT sort_key FROM track_artists LIMIT 1").is_ok() {
+            return Ok(());
+        } else {
+            log::info!("添加sort_key字段到track_artists表");
+            self.conn.execute("ALTER TABLE track_artists ADD COLUMN sort_key TEXT", [])?;
+        }
+
+        let config = self.artist_split_config();
+        let tags = self.get_track_artist_tags()?;
+        if !tags.is_empty() {
+            log::info!("拆分 {} 首已有曲目的艺术家标签", tags.len());
+            self.rebuild_track_artists(&tags, &config)?;
+        }
+        Ok(())
+    }
+
+    /// 封面改为独立存储：创建covers表，把tracks中的封面BLOB按内容去重后迁入
+    ///
+    /// 只迁移原图（缩略图在首次访问时生成，避免拖慢启动），迁移成功的曲目才清空旧BLOB
+    fn migrate_cover_storage(&self) -> Result<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS covers (
+                hash TEXT PRIMARY KEY,
+                mime TEXT,
+                data BLOB NOT NULL,
+                thumb_small BLOB,
+                thumb_large BLOB,
+                created_at INTEGER DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+        // 封面取色结果，随封面一起删除
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS cover_palettes (
+                hash TEXT PRIMARY KEY,
+                palette TEXT NOT NULL,
+                FOREIGN KEY (hash) REFERENCES covers (hash) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        if self.conn.prepare("SELECT cover_id FROM tracks LIMIT 1").is_err() {
+            log::info!("添加cover_id字段到tracks表");
+            self.conn.execute("ALTER TABLE tracks ADD COLUMN cover_id TEXT", [])?;
+        }
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tracks_cover ON tracks(cover_id)", [])?;
+
+        let pending: Vec<i64> = {
+            let mut stmt = self.conn.prepare("SELECT id FROM tracks WHERE album_cover_data IS NOT NULL")?;
+            let ids = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+            ids
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        log::info!("迁移 {} 首曲目的封面到covers表", pending.len());
+        let tx = self.conn.unchecked_transaction()?;
+        let mut migrated = 0;
+        for track_id in pending {
+            let (data, mime): (Vec<u8>, Option<String>) = tx.query_row(
+                "SELECT album_cover_data, album_cover_mime FROM tracks WHERE id = ?1",
+                [track_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let id = cover_cache::cover_id(&data);
+            let mime = mime.unwrap_or_else(|| cover_cache::sniff_mime(&data).to_string());
+            migrated += tx.execute(
+                "INSERT OR IGNORE INTO covers (hash, mime, data) VALUES (?1, ?2, ?3)",
+                params![id, mime, data],
+            )?;
+            tx.execute(
+                "UPDATE tracks SET cover_id = ?2, album_cover_data = NULL, album_cover_mime = NULL WHERE id = ?1",
+                params![track_id, id],
+            )?;
+        }
+        tx.commit()?;
+        log::info!("封面迁移完成，去重后共 {} 张", migrated);
+        Ok(())
+    }
+
+    /// 迁移WebDAV和同步支持字段到现有数据库
+    fn migrate_webdav_support_columns(&self) -> Result<()> {
+        // 检查并添加source_type字段
+        let source_type_exists = self.conn.prepare("SELECT source_type FROM tracks LIMIT 1");
+        if source_type_exists.is_err() {
+            log::info!("添加source_type字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN source_type TEXT DEFAULT 'local' CHECK(source_type IN ('local', 'webdav', 'cached'))",
+                []
+            )?;
+        }
+        
+        // 检查并添加source_config字段
+        let source_config_exists = self.conn.prepare("SELECT source_config FROM tracks LIMIT 1");
+        if source_config_exists.is_err() {
+            log::info!("添加source_config字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN source_config TEXT",
+                []
+            )?;
+        }
+        
+        // 检查并添加sync_status字段
+        let sync_status_exists = self.conn.prepare("SELECT sync_status FROM tracks LIMIT 1");
+        if sync_status_exists.is_err() {
+            log::info!("添加sync_status字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN sync_status TEXT DEFAULT 'local_only' CHECK(sync_status IN ('local_only', 'remote_only', 'synced', 'conflict', 'syncing', 'sync_error'))",
+                []
+            )?;
+        }
+        
+        // 检查并添加cache_status字段
+        let cache_status_exists = self.conn.prepare("SELECT cache_status FROM tracks LIMIT 1");
+        if cache_status_exists.is_err() {
+            log::info!("添加cache_status字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN cache_status TEXT DEFAULT 'none' CHECK(cache_status IN ('none', 'partial', 'cached', 'expired', 'updating'))",
+                []
+            )?;
+        }
+        
+        // 检查并添加remote_modified字段
+        let remote_modified_exists = self.conn.prepare("SELECT remote_modified FROM tracks LIMIT 1");
+        if remote_modified_exists.is_err() {
+            log::info!("添加remote_modified字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN remote_modified INTEGER",
+                []
+            )?;
+        }
+        
+        // 检查并添加last_sync字段
+        let last_sync_exists = self.conn.prepare("SELECT last_sync FROM tracks LIMIT 1");
+        if last_sync_exists.is_err() {
+            log::info!("添加last_sync字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN last_sync INTEGER",
+                []
+            )?;
+        }
+        
+        // 检查并添加server_id字段
+        let server_id_exists = self.conn.prepare("SELECT server_id FROM tracks LIMIT 1");
+        if server_id_exists.is_err() {
+            log::info!("添加server_id字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN server_id TEXT",
+                []
+            )?;
+        }
+        
+        // 检查并添加remote_etag字段
+        let remote_etag_exists = self.conn.prepare("SELECT remote_etag FROM tracks LIMIT 1");
+        if remote_etag_exists.is_err() {
+            log::info!("添加remote_etag字段到tracks表");
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN remote_etag TEXT",
+                []
+            )?;
+        }
+        
+        log::info!("WebDAV支持字段迁移完成");
+        Ok(())
+    }
+
+    /// 迁移歌单表扩展字段
+    fn migrate_playlist_extended_columns(&self) -> Result<()> {
+        // description
+        if self.conn.prepare("SELECT description FROM playlists LIMIT 1").is_err() {
+            log::info!("添加description字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN description TEXT", [])?;
+        }
+        
+        // cover_path
+        if self.conn.prepare("SELECT cover_path FROM playlists LIMIT 1").is_err() {
+            log::info!("添加cover_path字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN cover_path TEXT", [])?;
+        }
+        
+        // is_smart
+        if self.conn.prepare("SELECT is_smart FROM playlists LIMIT 1").is_err() {
+            log::info!("添加is_smart字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_smart INTEGER DEFAULT 0", [])?;
+        }
+        
+        // smart_rules
+        if self.conn.prepare("SELECT smart_rules FROM playlists LIMIT 1").is_err() {
+            log::info!("添加smart_rules字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN smart_rules TEXT", [])?;
+        }
+        
+        // color_theme
+        if self.conn.prepare("SELECT color_theme FROM playlists LIMIT 1").is_err() {
+            log::info!("添加color_theme字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN color_theme TEXT", [])?;
+        }
+        
+        // is_favorite
+        if self.conn.prepare("SELECT is_favorite FROM playlists LIMIT 1").is_err() {
+            log::info!("添加is_favorite字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
+        }
+        
+        // last_played
+        if self.conn.prepare("SELECT last_played FROM playlists LIMIT 1").is_err() {
+            log::info!("添加last_played字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN last_played INTEGER", [])?;
+        }
+        
+        // play_count
+        if self.conn.prepare("SELECT play_count FROM playlists LIMIT 1").is_err() {
+            log::info!("添加play_count字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN play_count INTEGER DEFAULT 0", [])?;
+        }
+        
+        // updated_at
+        if self.conn.prepare("SELECT updated_at FROM playlists LIMIT 1").is_err() {
+            log::info!("添加updated_at字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN updated_at INTEGER", [])?;
+        }
+        
+        // is_pinned
+        if self.conn.prepare("SELECT is_pinned FROM playlists LIMIT 1").is_err() {
+            log::info!("添加is_pinned字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN is_pinned INTEGER DEFAULT 0", [])?;
+        }
+        
+        // 播放偏好（NULL 表示沿用全局设置）
+        if self.conn.prepare("SELECT default_shuffle FROM playlists LIMIT 1").is_err() {
+            log::info!("添加default_shuffle、default_repeat_mode、default_crossfade_ms字段到playlists表");
+            self.conn.execute_batch(
+                "ALTER TABLE playlists ADD COLUMN default_shuffle INTEGER;
+                 ALTER TABLE playlists ADD COLUMN default_repeat_mode TEXT;
+                 ALTER TABLE playlists ADD COLUMN default_crossfade_ms INTEGER;",
+            )?;
+        }
+        
+        log::info!("歌单表扩展字段迁移完成");
         Ok(())
     }
 
@@ -827,21 +2388,58 @@ impl Database {
         Ok(())
     }
 
+    /// 歌单文件夹表，以及歌单所在文件夹和手动顺序字段
+    fn migrate_playlist_folders(&self) -> Result<()> {
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS playlist_folders (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                parent_id INTEGER,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        if self.conn.prepare("SELECT folder_id FROM playlists LIMIT 1").is_err() {
+            log::info!("添加folder_id字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN folder_id INTEGER", [])?;
+        }
+
+        if self.conn.prepare("SELECT sort_order FROM playlists LIMIT 1").is_err() {
+            log::info!("添加sort_order字段到playlists表");
+            self.conn.execute("ALTER TABLE playlists ADD COLUMN sort_order INTEGER DEFAULT 0", [])?;
+        }
+
+        Ok(())
+    }
+
+    /// 写入或更新曲目；重新扫描时保留应用内设置的评分，未评分时才使用文件标签中的评分
     pub fn insert_track(&self, track: &Track) -> Result<i64> {
         let mut stmt = self.conn.prepare(
-            "INSERT INTO tracks (path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics, last_modified)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            "INSERT INTO tracks (path, title, artist, album, duration_ms, cover_id, artist_photo_data, artist_photo_mime, embedded_lyrics, last_modified,
+                                 genre, year, track_number, disc_number, album_artist, rating, sort_title, sort_artist, sort_album, translit)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
              ON CONFLICT(path) DO UPDATE SET
                 title = excluded.title,
                 artist = excluded.artist,
                 album = excluded.album,
                 duration_ms = excluded.duration_ms,
-                album_cover_data = excluded.album_cover_data,
-                album_cover_mime = excluded.album_cover_mime,
+                cover_id = excluded.cover_id,
                 artist_photo_data = excluded.artist_photo_data,
                 artist_photo_mime = excluded.artist_photo_mime,
                 embedded_lyrics = excluded.embedded_lyrics,
-                last_modified = excluded.last_modified"
+                last_modified = excluded.last_modified,
+                genre = excluded.genre,
+                year = excluded.year,
+                track_number = excluded.track_number,
+                disc_number = excluded.disc_number,
+                album_artist = excluded.album_artist,
+                rating = COALESCE(tracks.rating, excluded.rating),
+                sort_title = excluded.sort_title,
+                sort_artist = excluded.sort_artist,
+                sort_album = excluded.sort_album,
+                translit = excluded.translit"
         )?;
 
         let last_modified = std::time::SystemTime::now()
@@ -849,46 +2447,465 @@ impl Database {
             .unwrap()
             .as_secs() as i64;
 
+        // 封面原图存入covers表，曲目只记录封面ID
+        let cover_id = match &track.album_cover_data {
+            Some(data) => Some(self.store_cover(data, track.album_cover_mime.as_deref())?),
+            None => track.cover_id.clone(),
+        };
+        let keys = TrackSortKeys::compute(
+            track.title.as_deref(),
+            track.artist.as_deref(),
+            track.album.as_deref(),
+            track.album_artist.as_deref(),
+            self.transliterate_search(),
+        );
+
         stmt.execute(params![
             track.path,
             track.title,
             track.artist,
             track.album,
             track.duration_ms,
-            track.album_cover_data,
-            track.album_cover_mime,
+            cover_id,
             track.artist_photo_data,
             track.artist_photo_mime,
             track.embedded_lyrics,
-            last_modified
+            last_modified,
+            track.genre,
+            track.year,
+            track.track_number,
+            track.disc_number,
+            track.album_artist,
+            track.rating,
+            keys.title,
+            keys.artist,
+            keys.album,
+            keys.translit
         ])?;
 
-        // 🔧 性能优化：失效与tracks表相关的缓存
-        if let Ok(mut cache) = self.cache.lock() {
-            cache.invalidate_track_related();
-        }
+        // 更新已有曲目时 last_insert_rowid 不会变化，按路径取回ID
+        let track_id: i64 = self.conn.query_row("SELECT id FROM tracks WHERE path = ?1", [&track.path], |row| row.get(0))?;
+        let artists = artist_split::split_artists(track.artist.as_deref().unwrap_or_default(), &self.artist_split_config());
+        Self::write_track_artists(&self.conn, track_id, &artists)?;
 
-        Ok(self.conn.last_insert_rowid())
+        Ok(track_id)
     }
 
-    pub fn get_track_by_id(&self, id: i64) -> Result<Option<Track>> {
+    /// 记录扫描时读取的音频属性
+    pub fn update_audio_properties(&self, path: &str, properties: &AudioProperties) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET format = ?1, bitrate = ?2, sample_rate = ?3, channels = ?4 WHERE path = ?5",
+            params![properties.format, properties.bitrate_kbps, properties.sample_rate, properties.channels, path],
+        )?;
+        Ok(())
+    }
+
+    /// 本地曲目的文件状态（路径 → 状态），用于增量扫描
+    pub fn get_local_file_states(&self) -> Result<HashMap<String, LocalFileState>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics FROM tracks WHERE id = ?1"
+            "SELECT path, id, file_mtime, file_size, file_hash FROM tracks
+             WHERE path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%' AND COALESCE(source_type, 'local') != 'webdav'"
         )?;
+        let states = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, LocalFileState {
+                id: row.get(1)?,
+                mtime: row.get(2)?,
+                size: row.get(3)?,
+                hash: row.get(4)?,
+            }))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(states)
+    }
 
-        let track = stmt.query_row([id], |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                title: row.get(2)?,
-                artist: row.get(3)?,
+    /// 记录扫描时的文件状态（文件变化后之前的完整性校验结果作废）
+    pub fn update_file_state(&self, path: &str, mtime: Option<i64>, size: i64, hash: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_mtime = ?1, file_size = ?2, file_hash = COALESCE(?3, file_hash),
+                fingerprint = CASE WHEN ?3 IS NOT NULL AND ?3 IS NOT file_hash THEN NULL ELSE fingerprint END,
+                verify_status = CASE WHEN ?1 IS NOT file_mtime OR ?2 IS NOT file_size THEN NULL ELSE verify_status END,
+                verify_detail = CASE WHEN ?1 IS NOT file_mtime OR ?2 IS NOT file_size THEN NULL ELSE verify_detail END,
+                verified_at = CASE WHEN ?1 IS NOT file_mtime OR ?2 IS NOT file_size THEN NULL ELSE verified_at END
+             WHERE path = ?4",
+            params![mtime, size, hash, path],
+        )?;
+        Ok(())
+    }
+
+    /// 某个远程服务器上曲目的文件状态（路径 → 状态），用于增量扫描
+    pub fn get_remote_file_states(&self, server_id: &str) -> Result<HashMap<String, RemoteFileState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, file_mtime, file_size, file_etag FROM tracks
+             WHERE substr(path, 1, length(?1)) = ?1 OR substr(path, 1, length(?2)) = ?2"
+        )?;
+        let states = stmt.query_map(
+            params![format!("webdav://{}#", server_id), format!("subsonic://{}#", server_id)],
+            |row| {
+                Ok((row.get::<_, String>(0)?, RemoteFileState {
+                    mtime: row.get(1)?,
+                    size: row.get(2)?,
+                    etag: row.get(3)?,
+                }))
+            },
+        )?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(states)
+    }
+
+    /// 记录扫描时的远程文件状态
+    pub fn update_remote_file_state(&self, path: &str, state: &RemoteFileState) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_mtime = ?1, file_size = ?2, file_etag = ?3 WHERE path = ?4",
+            params![state.mtime, state.size, state.etag, path],
+        )?;
+        Ok(())
+    }
+
+    /// 批量修改曲目路径，全部在一个事务中完成，中途出错时不会留下只迁移了一半的曲库
+    pub fn relocate_tracks(&self, relocations: &[PathRelocation]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        {
+            let mut move_stmt = tx.prepare("UPDATE tracks SET path = ?1 WHERE id = ?2")?;
+            let mut state_stmt = tx.prepare(
+                "UPDATE tracks SET file_mtime = ?1, file_size = ?2, file_hash = ?3, last_modified = strftime('%s', 'now')
+                 WHERE id = ?4",
+            )?;
+            for relocation in relocations {
+                updated += move_stmt.execute(params![relocation.new_path, relocation.track_id])?;
+                if let Some((mtime, size, hash)) = &relocation.file_state {
+                    state_stmt.execute(params![mtime, size, hash, relocation.track_id])?;
+                }
+            }
+        }
+        tx.commit()?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(updated)
+    }
+
+    /// 写回标签后更新文件状态（音频内容不变，保留声学指纹）
+    pub fn record_tag_write(&self, track_id: i64, mtime: Option<i64>, size: i64, hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET file_mtime = ?1, file_size = ?2, file_hash = ?3, last_modified = strftime('%s', 'now')
+             WHERE id = ?4",
+            params![mtime, size, hash, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 删除磁盘上已不存在的曲目，返回删除数量
+    pub fn delete_tracks_by_paths(&self, paths: &[String]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM tracks WHERE path = ?1")?;
+            for path in paths {
+                deleted += stmt.execute([path])?;
+            }
+        }
+        tx.commit()?;
+        if deleted > 0 {
+            self.prune_unused_covers()?;
+        }
+        Ok(deleted)
+    }
+
+    /// 更新曲目的ReplayGain增益（扫描时从标签读取）
+    pub fn update_replay_gain(&self, path: &str, info: &ReplayGainInfo) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET replaygain_track_gain = ?1, replaygain_album_gain = ?2 WHERE path = ?3",
+            params![info.track_gain_db.map(f64::from), info.album_gain_db.map(f64::from), path],
+        )?;
+        Ok(())
+    }
+
+    /// 获取曲目的ReplayGain增益
+    pub fn get_replay_gain(&self, track_id: i64) -> Result<Option<ReplayGainInfo>> {
+        let info = self.conn.query_row(
+            "SELECT replaygain_track_gain, replaygain_album_gain, loudness_lufs FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| Ok(ReplayGainInfo {
+                track_gain_db: row.get::<_, Option<f64>>(0)?.map(|g| g as f32),
+                album_gain_db: row.get::<_, Option<f64>>(1)?.map(|g| g as f32),
+                loudness_lufs: row.get::<_, Option<f64>>(2)?.map(|l| l as f32),
+            }),
+        ).optional()?;
+        Ok(info)
+    }
+
+    /// 获取尚未计算声学指纹的本地曲目 (id, path, file_hash)
+    pub fn get_tracks_without_fingerprint(&self) -> Result<Vec<(i64, String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, file_hash FROM tracks
+             WHERE fingerprint IS NULL AND path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%'
+               AND COALESCE(source_type, 'local') != 'webdav'
+             ORDER BY id"
+        )?;
+        let tracks = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 保存声学指纹（空字符串表示无法生成，如静音或过短），缺少文件哈希时一并补上
+    pub fn update_fingerprint(&self, track_id: i64, fingerprint: &str, file_hash: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET fingerprint = ?1, file_hash = COALESCE(file_hash, ?2) WHERE id = ?3",
+            params![fingerprint, file_hash, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 查找重复曲目：文件哈希相同的为完全重复，声学指纹相似度不低于 threshold 的为声学匹配
+    pub fn find_duplicates(&self, threshold: f32) -> Result<Vec<DuplicateGroup>> {
+        let mut groups = Vec::new();
+
+        // 完全重复
+        let mut stmt = self.conn.prepare(
+            "SELECT file_hash, id FROM tracks
+             WHERE file_hash IN (
+                SELECT file_hash FROM tracks WHERE file_hash IS NOT NULL GROUP BY file_hash HAVING COUNT(*) > 1
+             )
+             ORDER BY file_hash, path"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut exact: Vec<(String, Vec<i64>)> = Vec::new();
+        for (hash, id) in rows {
+            match exact.last_mut() {
+                Some((last, ids)) if *last == hash => ids.push(id),
+                _ => exact.push((hash, vec![id])),
+            }
+        }
+        for (_, ids) in exact {
+            groups.push(DuplicateGroup { kind: DuplicateKind::Exact, similarity: 1.0, tracks: self.get_tracks_by_ids(&ids)? });
+        }
+
+        // 声学匹配：按时长排序后只比较时长接近的曲目
+        let mut stmt = self.conn.prepare(
+            "SELECT id, COALESCE(duration_ms, 0), file_hash, fingerprint FROM tracks
+             WHERE fingerprint IS NOT NULL AND fingerprint != ''
+             ORDER BY COALESCE(duration_ms, 0), id"
+        )?;
+        let candidates: Vec<(i64, i64, Option<String>, Vec<u32>)> = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, String>(3)?))
+        })?
+        .filter_map(|row| match row {
+            Ok((id, duration, hash, encoded)) => fingerprint::decode(&encoded).map(|fp| Ok((id, duration, hash, fp))),
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<_, _>>()?;
+
+        // 并查集合并相似的曲目
+        let mut parent: Vec<usize> = (0..candidates.len()).collect();
+        fn find(parent: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[i] = root;
+            root
+        }
+        let mut min_similarity: HashMap<usize, f32> = HashMap::new();
+        for i in 0..candidates.len() {
+            for j in i + 1..candidates.len() {
+                let (_, duration_i, hash_i, fp_i) = &candidates[i];
+                let (_, duration_j, hash_j, fp_j) = &candidates[j];
+                if duration_j - duration_i > ACOUSTIC_DURATION_TOLERANCE_MS {
+                    break;
+                }
+                // 内容完全相同的已作为完全重复列出
+                if hash_i.is_some() && hash_i == hash_j {
+                    continue;
+                }
+                let score = fingerprint::similarity(fp_i, fp_j);
+                if score >= threshold {
+                    let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+                    let merged = [min_similarity.remove(&root_i), min_similarity.remove(&root_j), Some(score)]
+                        .into_iter()
+                        .flatten()
+                        .fold(1.0f32, f32::min);
+                    parent[root_j] = root_i;
+                    min_similarity.insert(root_i, merged);
+                }
+            }
+        }
+
+        let mut acoustic: HashMap<usize, Vec<i64>> = HashMap::new();
+        for (i, (id, ..)) in candidates.iter().enumerate() {
+            let root = find(&mut parent, i);
+            acoustic.entry(root).or_default().push(*id);
+        }
+        let mut acoustic: Vec<(usize, Vec<i64>)> = acoustic.into_iter().filter(|(_, ids)| ids.len() > 1).collect();
+        acoustic.sort_by_key(|(_, ids)| ids[0]);
+        for (root, ids) in acoustic {
+            groups.push(DuplicateGroup {
+                kind: DuplicateKind::AcousticMatch,
+                similarity: min_similarity.get(&root).copied().unwrap_or(threshold),
+                tracks: self.get_tracks_by_ids(&ids)?,
+            });
+        }
+
+        Ok(groups)
+    }
+
+    /// 按ID批量获取曲目（不含封面数据），按路径排序
+    fn get_tracks_by_ids(&self, ids: &[i64]) -> Result<Vec<Track>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, path, title, artist, album, duration_ms, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status
+             FROM tracks WHERE id IN ({})
+             ORDER BY path",
+            placeholders
+        ))?;
+        let tracks = stmt.query_map(rusqlite::params_from_iter(ids.iter()), |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
                 album: row.get(4)?,
                 duration_ms: row.get(5)?,
-                album_cover_data: row.get(6)?,
-                album_cover_mime: row.get(7)?,
-                artist_photo_data: row.get(8)?,
-                artist_photo_mime: row.get(9)?,
-                embedded_lyrics: row.get(10)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 获取尚未做响度分析的本地曲目 (id, path)
+    pub fn get_tracks_without_loudness(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path FROM tracks
+             WHERE loudness_lufs IS NULL AND path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%'
+               AND COALESCE(source_type, 'local') != 'webdav'
+             ORDER BY id"
+        )?;
+        let tracks = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 保存曲目的响度分析结果
+    pub fn update_loudness(&self, track_id: i64, loudness_lufs: f32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET loudness_lufs = ?1 WHERE id = ?2",
+            params![loudness_lufs as f64, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 获取时长缺失或为0的曲目（包括远程曲目）
+    pub fn get_tracks_without_duration(&self) -> Result<Vec<MissingDuration>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, file_size, bitrate FROM tracks
+             WHERE duration_ms IS NULL OR duration_ms <= 0
+             ORDER BY id"
+        )?;
+        let tracks = stmt.query_map([], |row| {
+            Ok(MissingDuration {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                file_size: row.get(2)?,
+                bitrate_kbps: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 更新曲目时长（补算或播放时更正），返回曲目是否存在
+    pub fn update_track_duration(&self, track_id: i64, duration_ms: i64) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE tracks SET duration_ms = ?1 WHERE id = ?2",
+            params![duration_ms, track_id],
+        )?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(updated > 0)
+    }
+
+    /// 获取尚未按该阈值做静音分析的本地曲目 (id, path)
+    pub fn get_tracks_without_trim_points(&self, threshold_db: f32) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path FROM tracks
+             WHERE (silence_threshold_db IS NULL OR ABS(silence_threshold_db - ?1) > 0.01)
+               AND path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%'
+               AND COALESCE(source_type, 'local') != 'webdav'
+             ORDER BY id"
+        )?;
+        let tracks = stmt.query_map(params![threshold_db as f64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 保存曲目的静音裁剪点
+    pub fn update_trim_points(&self, track_id: i64, threshold_db: f32, trim: &TrimPoints) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET trim_start_ms = ?1, trim_end_ms = ?2, silence_threshold_db = ?3 WHERE id = ?4",
+            params![trim.start_ms as i64, trim.end_ms.map(|e| e as i64), threshold_db as f64, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 获取曲目的静音裁剪点，尚未分析时返回None
+    pub fn get_trim_points(&self, track_id: i64) -> Result<Option<TrimPoints>> {
+        let trim = self.conn.query_row(
+            "SELECT trim_start_ms, trim_end_ms FROM tracks WHERE id = ?1 AND silence_threshold_db IS NOT NULL",
+            [track_id],
+            |row| Ok(TrimPoints {
+                start_ms: row.get::<_, Option<i64>>(0)?.unwrap_or(0) as u64,
+                end_ms: row.get::<_, Option<i64>>(1)?.map(|e| e as u64),
+            }),
+        ).optional()?;
+        Ok(trim)
+    }
+
+    pub fn get_track_by_id(&self, id: i64) -> Result<Option<Track>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, title, artist, album, duration_ms, artist_photo_data, artist_photo_mime, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status FROM tracks WHERE id = ?1"
+        )?;
+
+        let track = stmt.query_row([id], |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: row.get(6)?,
+                artist_photo_mime: row.get(7)?,
+                embedded_lyrics: row.get(8)?,
+                genre: row.get(9)?,
+                year: row.get(10)?,
+                track_number: row.get(11)?,
+                disc_number: row.get(12)?,
+                album_artist: row.get(13)?,
+                cover_id: row.get(14)?,
+                rating: row.get(15)?,
+                verify_status: row.get(16)?,
             })
         });
 
@@ -901,7 +2918,7 @@ impl Database {
 
     pub fn get_track_by_path(&self, path: &str) -> Result<Option<Track>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics FROM tracks WHERE path = ?1"
+            "SELECT id, path, title, artist, album, duration_ms, artist_photo_data, artist_photo_mime, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status FROM tracks WHERE path = ?1"
         )?;
 
         let track = stmt.query_row([path], |row| {
@@ -912,11 +2929,19 @@ impl Database {
                 artist: row.get(3)?,
                 album: row.get(4)?,
                 duration_ms: row.get(5)?,
-                album_cover_data: row.get(6)?,
-                album_cover_mime: row.get(7)?,
-                artist_photo_data: row.get(8)?,
-                artist_photo_mime: row.get(9)?,
-                embedded_lyrics: row.get(10)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: row.get(6)?,
+                artist_photo_mime: row.get(7)?,
+                embedded_lyrics: row.get(8)?,
+                genre: row.get(9)?,
+                year: row.get(10)?,
+                track_number: row.get(11)?,
+                disc_number: row.get(12)?,
+                album_artist: row.get(13)?,
+                cover_id: row.get(14)?,
+                rating: row.get(15)?,
+                verify_status: row.get(16)?,
             })
         });
 
@@ -927,12 +2952,22 @@ impl Database {
         }
     }
 
-    pub fn get_all_tracks(&self) -> Result<Vec<Track>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics FROM tracks ORDER BY artist, album, title"
-        )?;
+    /// 批量获取带封面数据的曲目（列表查询不再携带封面BLOB，仅供拼贴图等确实需要封面的场景使用）
+    pub fn get_tracks_with_covers(&self, track_ids: &[i64]) -> Result<Vec<Track>> {
+        if track_ids.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let track_iter = stmt.query_map([], |row| {
+        let placeholders = vec!["?"; track_ids.len()].join(", ");
+        let sql = format!(
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, c.data, c.mime, t.artist_photo_data, t.artist_photo_mime, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
+             FROM tracks t LEFT JOIN covers c ON c.hash = t.cover_id
+             WHERE t.id IN ({})",
+            placeholders
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let track_iter = stmt.query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
             Ok(Track {
                 id: row.get(0)?,
                 path: row.get(1)?,
@@ -945,6 +2980,192 @@ impl Database {
                 artist_photo_data: row.get(8)?,
                 artist_photo_mime: row.get(9)?,
                 embedded_lyrics: row.get(10)?,
+                genre: row.get(11)?,
+                year: row.get(12)?,
+                track_number: row.get(13)?,
+                disc_number: row.get(14)?,
+                album_artist: row.get(15)?,
+                cover_id: row.get(16)?,
+                rating: row.get(17)?,
+                verify_status: row.get(18)?,
+            })
+        })?;
+
+        let mut by_id = HashMap::new();
+        for track in track_iter {
+            let track = track?;
+            by_id.insert(track.id, track);
+        }
+
+        // 保持调用方传入的顺序
+        Ok(track_ids.iter().filter_map(|id| by_id.remove(id)).collect())
+    }
+
+    pub fn get_all_tracks(&self) -> Result<Vec<Track>> {
+        if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
+            cache.cleanup_expired();
+            
+            if let Some(ref entry) = cache.all_tracks {
+                if !entry.is_expired() {
+                    return Ok(entry.data.clone());
+                }
+            }
+        }
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, title, artist, album, duration_ms, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status
+             FROM tracks
+             ORDER BY sort_artist, sort_album, disc_number, track_number, sort_title, title"
+        )?;
+
+        let track_iter = stmt.query_map([], |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
+            })
+        })?;
+
+        let mut tracks = Vec::new();
+        for track in track_iter {
+            tracks.push(track?);
+        }
+
+        // 列表查询不读取封面数据；歌词很多的超大曲库不缓存，避免常驻占用过多内存
+        if approx_tracks_bytes(&tracks) <= ALL_TRACKS_CACHE_MAX_BYTES {
+            if let Ok(mut cache) = self.cache.lock() {
+                cache.all_tracks = Some(CacheEntry::new(tracks.clone(), Duration::from_secs(600))); // 10分钟TTL
+            }
+        }
+
+        Ok(tracks)
+    }
+
+    /// 曲库搜索：支持 artist:"..." album:... genre:... year:1959 等字段限定，同时匹配歌单
+    ///
+    /// 没有字段限定时沿用 search_tracks 的模糊搜索
+    pub fn search_library(&self, query: &str) -> Result<LibrarySearchResult> {
+        let parsed = SearchQuery::parse(query);
+        let tracks = if parsed.is_qualified() {
+            let tracks = self.search_tracks_qualified(&parsed)?;
+            let free_text = parsed.free_text();
+            if free_text.is_empty() {
+                tracks
+            } else {
+                self.rank_search_results(&free_text, tracks)?
+            }
+        } else {
+            self.search_tracks(query)?
+        };
+
+        let album_keys: HashSet<(&str, Option<&str>)> = tracks.iter()
+            .filter_map(|t| {
+                let album = t.album.as_deref().filter(|a| !a.is_empty())?;
+                let album_artist = t.album_artist.as_deref().filter(|a| !a.is_empty()).or(t.artist.as_deref());
+                Some((album, album_artist))
+            })
+            .collect();
+        let albums = self.get_albums()?
+            .into_iter()
+            .filter(|a| album_keys.contains(&(a.album.as_str(), a.album_artist.as_deref())))
+            .collect();
+
+        let artist_names = self.get_artist_names_for_tracks(&tracks.iter().map(|t| t.id).collect::<Vec<_>>())?;
+        let artists = self.get_artists()?
+            .into_iter()
+            .filter(|a| artist_names.contains(a.artist.as_str()))
+            .collect();
+
+        let playlists = self.search_playlists(&parsed.free_text())?;
+
+        Ok(LibrarySearchResult { tracks, playlists, albums, artists })
+    }
+
+    /// 曲目拆分后的所有艺术家名称
+    fn get_artist_names_for_tracks(&self, track_ids: &[i64]) -> Result<HashSet<String>> {
+        let mut names = HashSet::new();
+        for chunk in track_ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT DISTINCT artist FROM track_artists WHERE track_id IN ({})",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(chunk.iter()), |row| row.get::<_, String>(0))?;
+            for name in rows {
+                names.insert(name?);
+            }
+        }
+        Ok(names)
+    }
+
+    /// 按字段限定条件搜索曲目：文本条件走FTS列过滤，年份走WHERE条件
+    fn search_tracks_qualified(&self, query: &SearchQuery) -> Result<Vec<Track>> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<rusqlite::types::Value> = Vec::new();
+
+        let fts = query.fts_match();
+        if let Some(expression) = &fts {
+            conditions.push("tracks_fts MATCH ?".to_string());
+            params.push(expression.clone().into());
+        }
+        if !query.years.is_empty() {
+            let ranges: Vec<&str> = query.years.iter().map(|_| "t.year BETWEEN ? AND ?").collect();
+            conditions.push(format!("({})", ranges.join(" OR ")));
+            for range in &query.years {
+                params.push(range.from.into());
+                params.push(range.to.into());
+            }
+        }
+
+        let join = if fts.is_some() { "JOIN tracks_fts fts ON t.id = fts.rowid" } else { "" };
+        let sql = format!(
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
+             FROM tracks t {}
+             WHERE {}
+             ORDER BY t.artist, t.album, t.disc_number, t.track_number, t.title",
+            join,
+            conditions.join(" AND ")
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let track_iter = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
             })
         })?;
 
@@ -956,6 +3177,23 @@ impl Database {
         Ok(tracks)
     }
 
+    /// 按名称和描述搜索歌单（所有关键词都需出现，不区分大小写）
+    fn search_playlists(&self, text: &str) -> Result<Vec<crate::playlist::Playlist>> {
+        let keywords: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+        if keywords.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let playlists = self.get_all_playlists_extended()?
+            .into_iter()
+            .filter(|p| {
+                let haystack = format!("{} {}", p.name, p.description.as_deref().unwrap_or_default()).to_lowercase();
+                keywords.iter().all(|k| haystack.contains(k.as_str()))
+            })
+            .collect();
+        Ok(playlists)
+    }
+
     pub fn search_tracks(&self, query: &str) -> Result<Vec<Track>> {
         if query.trim().is_empty() {
             return self.get_all_tracks();
@@ -969,7 +3207,7 @@ impl Database {
         // 尝试多种搜索策略，按相关性排序
         for (search_query, _priority) in fuzzy_queries {
             let mut stmt = self.conn.prepare(
-                "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.album_cover_data, t.album_cover_mime, t.artist_photo_data, t.artist_photo_mime, t.embedded_lyrics 
+                "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status 
                  FROM tracks t
                  JOIN tracks_fts fts ON t.id = fts.rowid 
                  WHERE tracks_fts MATCH ?1
@@ -984,11 +3222,19 @@ impl Database {
                     artist: row.get(3)?,
                     album: row.get(4)?,
                     duration_ms: row.get(5)?,
-                    album_cover_data: row.get(6)?,
-                    album_cover_mime: row.get(7)?,
-                    artist_photo_data: row.get(8)?,
-                    artist_photo_mime: row.get(9)?,
-                    embedded_lyrics: row.get(10)?,
+                    album_cover_data: None,
+                    album_cover_mime: None,
+                    artist_photo_data: None,
+                    artist_photo_mime: None,
+                    embedded_lyrics: row.get(6)?,
+                    genre: row.get(7)?,
+                    year: row.get(8)?,
+                    track_number: row.get(9)?,
+                    disc_number: row.get(10)?,
+                    album_artist: row.get(11)?,
+                    cover_id: row.get(12)?,
+                    rating: row.get(13)?,
+                    verify_status: row.get(14)?,
                 })
             });
 
@@ -1009,15 +3255,126 @@ impl Database {
             all_tracks = self.fallback_like_search(query)?;
         }
 
-        Ok(all_tracks)
+        self.rank_search_results(query, all_tracks)
     }
 
-    fn build_fuzzy_search_queries(&self, query: &str) -> Vec<(String, i32)> {
-        let mut queries = Vec::new();
-        let normalized_query = query.trim().to_lowercase();
-
-        // 1. 精确匹配（最高优先级）
-        queries.push((format!("\"{}\"", normalized_query), 1));
+    /// 按相关度重新排序搜索结果（结合收藏和播放次数）
+    fn rank_search_results(&self, query: &str, tracks: Vec<Track>) -> Result<Vec<Track>> {
+        if tracks.is_empty() {
+            return Ok(tracks);
+        }
+
+        let ids: Vec<i64> = tracks.iter().map(|t| t.id).collect();
+        let placeholders = vec!["?"; ids.len()].join(", ");
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT track_id FROM favorites WHERE track_id IN ({})",
+            placeholders
+        ))?;
+        let favorites: HashSet<i64> = stmt.query_map(rusqlite::params_from_iter(ids.iter()), |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT track_id, COUNT(*) FROM play_history WHERE track_id IN ({}) GROUP BY track_id",
+            placeholders
+        ))?;
+        let play_counts: HashMap<i64, i64> = stmt.query_map(rusqlite::params_from_iter(ids.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<_, _>>()?;
+
+        Ok(search_query::rank_tracks(query, tracks, |track| RankSignals {
+            is_favorite: favorites.contains(&track.id),
+            play_count: play_counts.get(&track.id).copied().unwrap_or(0),
+        }))
+    }
+
+    /// 记录一次搜索，超出条数的旧记录被删除
+    pub fn record_search(&self, query: &str) -> Result<()> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        // 边输入边搜索会依次产生 "mi"、"mil"、"miles"，只保留最终的搜索词
+        self.conn.execute(
+            "DELETE FROM search_history
+             WHERE searched_at >= ?2 - ?3
+               AND LENGTH(query) < LENGTH(?1)
+               AND SUBSTR(LOWER(?1), 1, LENGTH(query)) = LOWER(query)",
+            params![query, now, SEARCH_TYPING_WINDOW_SECS],
+        )?;
+        // 先删后插，让重复的搜索词排到最前
+        self.conn.execute("DELETE FROM search_history WHERE query = ?1", [query])?;
+        self.conn.execute(
+            "INSERT INTO search_history (query, searched_at) VALUES (?1, ?2)",
+            params![query, now],
+        )?;
+        self.conn.execute(
+            "DELETE FROM search_history WHERE query NOT IN (
+                SELECT query FROM search_history ORDER BY searched_at DESC, rowid DESC LIMIT ?1
+             )",
+            [SEARCH_HISTORY_LIMIT],
+        )?;
+        Ok(())
+    }
+
+    /// 最近的搜索词（新的在前）
+    pub fn get_search_history(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT query FROM search_history ORDER BY searched_at DESC, rowid DESC LIMIT ?1"
+        )?;
+        let queries = stmt.query_map([SEARCH_HISTORY_LIMIT], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(queries)
+    }
+
+    pub fn clear_search_history(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM search_history", [])?;
+        Ok(())
+    }
+
+    /// 按前缀补全艺术家、专辑、标题（依次排列，各自去重）
+    pub fn suggest_search(&self, prefix: &str, limit: i64) -> Result<Vec<SearchSuggestion>> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() || limit <= 0 {
+            return Ok(Vec::new());
+        }
+
+        let pattern = format!(
+            "{}%",
+            prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, value FROM (
+                SELECT 0 AS kind, artist AS value FROM tracks WHERE artist LIKE ?1 ESCAPE '\\' GROUP BY artist COLLATE NOCASE
+                UNION ALL
+                SELECT 1, album FROM tracks WHERE album LIKE ?1 ESCAPE '\\' GROUP BY album COLLATE NOCASE
+                UNION ALL
+                SELECT 2, title FROM tracks WHERE title LIKE ?1 ESCAPE '\\' GROUP BY title COLLATE NOCASE
+             )
+             ORDER BY kind, value COLLATE NOCASE
+             LIMIT ?2"
+        )?;
+        let suggestions = stmt.query_map(params![pattern, limit], |row| {
+            let kind = match row.get::<_, i64>(0)? {
+                0 => SuggestionKind::Artist,
+                1 => SuggestionKind::Album,
+                _ => SuggestionKind::Title,
+            };
+            Ok(SearchSuggestion { kind, value: row.get(1)? })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(suggestions)
+    }
+
+    fn build_fuzzy_search_queries(&self, query: &str) -> Vec<(String, i32)> {
+        let mut queries = Vec::new();
+        let normalized_query = query.trim().to_lowercase();
+
+        // 1. 精确匹配（最高优先级）
+        queries.push((format!("\"{}\"", normalized_query), 1));
 
         // 2. 前缀匹配
         let terms: Vec<&str> = normalized_query.split_whitespace().collect();
@@ -1045,7 +3402,7 @@ impl Database {
         let pattern = format!("%{}%", query.trim().to_lowercase());
         
         let mut stmt = self.conn.prepare(
-            "SELECT id, path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics
+            "SELECT id, path, title, artist, album, duration_ms, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status
              FROM tracks 
              WHERE LOWER(title) LIKE ?1 
                 OR LOWER(artist) LIKE ?1 
@@ -1068,11 +3425,19 @@ impl Database {
                 artist: row.get(3)?,
                 album: row.get(4)?,
                 duration_ms: row.get(5)?,
-                album_cover_data: row.get(6)?,
-                album_cover_mime: row.get(7)?,
-                artist_photo_data: row.get(8)?,
-                artist_photo_mime: row.get(9)?,
-                embedded_lyrics: row.get(10)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
             })
         })?;
 
@@ -1084,6 +3449,92 @@ impl Database {
         Ok(tracks)
     }
 
+    /// 分页获取曲目（不含封面数据）
+    ///
+    /// filter 对标题、艺术家、专辑、专辑艺术家、流派做不区分大小写的包含匹配
+    pub fn get_tracks_page(
+        &self,
+        offset: i64,
+        limit: i64,
+        sort_by: TrackSortField,
+        sort_dir: SortDirection,
+        filter: Option<&str>,
+    ) -> Result<TrackPage> {
+        let pattern = filter
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(|f| format!("%{}%", f.to_lowercase()));
+        let where_clause = if pattern.is_some() {
+            "WHERE LOWER(t.title) LIKE ?1 OR LOWER(t.artist) LIKE ?1 OR LOWER(t.album) LIKE ?1
+                OR LOWER(t.album_artist) LIKE ?1 OR LOWER(t.genre) LIKE ?1"
+        } else {
+            ""
+        };
+
+        let total: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM tracks t {}", where_clause),
+            rusqlite::params_from_iter(pattern.iter()),
+            |row| row.get(0),
+        )?;
+
+        // 播放次数排序才需要关联播放历史
+        let join = if sort_by == TrackSortField::PlayCount {
+            "LEFT JOIN (SELECT track_id, COUNT(*) AS play_count FROM play_history GROUP BY track_id) pc
+                ON pc.track_id = t.id"
+        } else {
+            ""
+        };
+        let direction = match sort_dir {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let sql = format!(
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
+             FROM tracks t {} {}
+             ORDER BY {} {}, t.id {}
+             LIMIT {} OFFSET {}",
+            join,
+            where_clause,
+            sort_by.column(),
+            direction,
+            direction,
+            limit.max(0),
+            offset.max(0)
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let track_iter = stmt.query_map(rusqlite::params_from_iter(pattern.iter()), |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
+            })
+        })?;
+
+        let mut tracks = Vec::new();
+        for track in track_iter {
+            tracks.push(track?);
+        }
+
+        Ok(TrackPage { tracks, total })
+    }
+
     /// 创建歌单（简化版，已被 create_playlist_extended 替代）
     #[allow(dead_code)]
     pub fn create_playlist(&self, name: &str) -> Result<i64> {
@@ -1098,6 +3549,11 @@ impl Database {
     // 注意：get_all_playlists() 已被 get_all_playlists_extended() 替代
 
     pub fn add_track_to_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
+        self.add_track_to_playlist_at(playlist_id, track_id, None)
+    }
+
+    /// 添加曲目并指定加入时间（从备份还原时使用），None 表示当前时间
+    pub fn add_track_to_playlist_at(&self, playlist_id: i64, track_id: i64, added_at: Option<i64>) -> Result<()> {
         // Get the next order index
         let order_index: i64 = self.conn.query_row(
             "SELECT COALESCE(MAX(order_index), -1) + 1 FROM playlist_items WHERE playlist_id = ?1",
@@ -1106,16 +3562,31 @@ impl Database {
         )?;
 
         let mut stmt = self.conn.prepare(
-            "INSERT INTO playlist_items (playlist_id, track_id, order_index) VALUES (?1, ?2, ?3)"
+            "INSERT INTO playlist_items (playlist_id, track_id, order_index, added_at)
+             VALUES (?1, ?2, ?3, COALESCE(?4, strftime('%s', 'now')))"
         )?;
 
-        stmt.execute(params![playlist_id, track_id, order_index])?;
+        stmt.execute(params![playlist_id, track_id, order_index, added_at])?;
         Ok(())
     }
 
+    /// 歌单中每首曲目的加入时间，顺序与 get_playlist_tracks 一致
+    pub fn get_playlist_added_at(&self, playlist_id: i64) -> Result<Vec<Option<i64>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT pi.added_at
+             FROM tracks t
+             JOIN playlist_items pi ON t.id = pi.track_id
+             WHERE pi.playlist_id = ?1
+             ORDER BY pi.order_index"
+        )?;
+
+        let rows = stmt.query_map([playlist_id], |row| row.get(0))?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
     pub fn get_playlist_tracks(&self, playlist_id: i64) -> Result<Vec<Track>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.album_cover_data, t.album_cover_mime, t.artist_photo_data, t.artist_photo_mime, t.embedded_lyrics
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
              FROM tracks t
              JOIN playlist_items pi ON t.id = pi.track_id
              WHERE pi.playlist_id = ?1
@@ -1130,11 +3601,19 @@ impl Database {
                 artist: row.get(3)?,
                 album: row.get(4)?,
                 duration_ms: row.get(5)?,
-                album_cover_data: row.get(6)?,
-                album_cover_mime: row.get(7)?,
-                artist_photo_data: row.get(8)?,
-                artist_photo_mime: row.get(9)?,
-                embedded_lyrics: row.get(10)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
             })
         })?;
 
@@ -1155,15 +3634,214 @@ impl Database {
         Ok(())
     }
 
+    /// 批量添加曲目（一个事务，更新一次歌单时间）
+    ///
+    /// skip_existing 时跳过歌单中已有的曲目和 track_ids 中重复的曲目
+    pub fn add_tracks_to_playlist_bulk(
+        &self,
+        playlist_id: i64,
+        track_ids: &[i64],
+        skip_existing: bool,
+    ) -> Result<crate::playlist::AddTracksResult> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut present: HashSet<i64> = if skip_existing {
+            let mut stmt = tx.prepare("SELECT track_id FROM playlist_items WHERE playlist_id = ?1")?;
+            let ids = stmt.query_map([playlist_id], |row| row.get(0))?;
+            ids.collect::<rusqlite::Result<_>>()?
+        } else {
+            HashSet::new()
+        };
+        let mut order_index: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(order_index), -1) + 1 FROM playlist_items WHERE playlist_id = ?1",
+            [playlist_id],
+            |row| row.get(0),
+        )?;
+
+        let mut result = crate::playlist::AddTracksResult::default();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO playlist_items (playlist_id, track_id, order_index, added_at)
+                 VALUES (?1, ?2, ?3, strftime('%s', 'now'))"
+            )?;
+            for &track_id in track_ids {
+                if skip_existing && !present.insert(track_id) {
+                    result.skipped += 1;
+                    continue;
+                }
+                stmt.execute(params![playlist_id, track_id, order_index])?;
+                order_index += 1;
+                result.added += 1;
+            }
+        }
+        self.touch_playlist(playlist_id)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// 专辑中的曲目ID（按碟号、音轨号），album_artist 的匹配方式与 get_album_tracks 相同
+    pub fn get_album_track_ids(&self, album: &str, album_artist: Option<&str>) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM tracks
+             WHERE album = ?1 AND COALESCE(NULLIF(album_artist, ''), artist) IS ?2
+             ORDER BY COALESCE(disc_number, 1), track_number, title"
+        )?;
+        let ids = stmt.query_map(params![album, album_artist], |row| row.get(0))?;
+        Ok(ids.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// 艺术家的曲目ID（按专辑、碟号、音轨号），包括作为客串艺术家的曲目
+    pub fn get_artist_track_ids(&self, artist: &str) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id FROM tracks t
+             WHERE EXISTS (SELECT 1 FROM track_artists ta WHERE ta.track_id = t.id AND ta.artist = ?1)
+             ORDER BY t.sort_album, t.album COLLATE NOCASE, COALESCE(t.disc_number, 1), t.track_number, t.title"
+        )?;
+        let ids = stmt.query_map([artist], |row| row.get(0))?;
+        Ok(ids.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// 批量移除曲目（同一曲目出现多次时全部移除），返回移除的条目数
+    pub fn remove_tracks_from_playlist(&self, playlist_id: i64, track_ids: &[i64]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM playlist_items WHERE playlist_id = ?1 AND track_id = ?2")?;
+            for &track_id in track_ids {
+                removed += stmt.execute(params![playlist_id, track_id])?;
+            }
+        }
+        self.compact_playlist_order(playlist_id)?;
+        self.touch_playlist(playlist_id)?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// 移除歌单中重复的曲目（保留第一次出现的位置），返回移除的条目数
+    pub fn deduplicate_playlist(&self, playlist_id: i64) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let items: Vec<(i64, i64)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, track_id FROM playlist_items WHERE playlist_id = ?1 ORDER BY order_index, id"
+            )?;
+            let rows = stmt.query_map([playlist_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut seen = HashSet::new();
+        let mut removed = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM playlist_items WHERE id = ?1")?;
+            for (item_id, track_id) in items {
+                if !seen.insert(track_id) {
+                    removed += stmt.execute([item_id])?;
+                }
+            }
+        }
+        if removed > 0 {
+            self.compact_playlist_order(playlist_id)?;
+            self.touch_playlist(playlist_id)?;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// 把歌单项的 order_index 重新编为 0..n，保持原来的顺序
+    fn compact_playlist_order(&self, playlist_id: i64) -> Result<()> {
+        let item_ids: Vec<i64> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id FROM playlist_items WHERE playlist_id = ?1 ORDER BY order_index, id"
+            )?;
+            let ids = stmt.query_map([playlist_id], |row| row.get(0))?;
+            ids.collect::<rusqlite::Result<_>>()?
+        };
+        let mut stmt = self.conn.prepare("UPDATE playlist_items SET order_index = ?1 WHERE id = ?2")?;
+        for (index, item_id) in item_ids.iter().enumerate() {
+            stmt.execute(params![index as i64, item_id])?;
+        }
+        Ok(())
+    }
+
     pub fn delete_playlist(&self, playlist_id: i64) -> Result<()> {
         let mut stmt = self.conn.prepare("DELETE FROM playlists WHERE id = ?1")?;
         stmt.execute([playlist_id])?;
         Ok(())
     }
 
+    /// 检查数据是否变化并失效对应缓存
+    ///
+    /// 先比较 PRAGMA data_version（其他连接的提交）和 total_changes()（本连接的写入），
+    /// 两者都没变时直接返回；否则读取 cache_meta 中各表的变更计数，按表失效。
+    /// TTL 仍作为兜底上限。
+    fn sync_cache_with_db(&self, cache: &mut QueryCache) {
+        let (data_version, total_changes) = match self.read_change_markers() {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("读取data_version失败，清空查询缓存: {}", e);
+                cache.invalidate_all();
+                return;
+            }
+        };
+        
+        if data_version == cache.data_version && total_changes == cache.total_changes {
+            return;
+        }
+        cache.data_version = data_version;
+        cache.total_changes = total_changes;
+        
+        match self.read_table_versions() {
+            Ok((tracks_version, favorites_version, lyrics_version)) => {
+                if tracks_version != cache.tracks_version {
+                    cache.invalidate_track_related();
+                    cache.tracks_version = tracks_version;
+                }
+                if favorites_version != cache.favorites_version {
+                    cache.invalidate_favorites_related();
+                    cache.favorites_version = favorites_version;
+                }
+                if lyrics_version != cache.lyrics_version {
+                    cache.invalidate_lyrics_related();
+                    cache.lyrics_version = lyrics_version;
+                }
+            }
+            Err(e) => {
+                log::warn!("读取表变更计数失败，清空查询缓存: {}", e);
+                cache.invalidate_all();
+            }
+        }
+    }
+
+    /// 读取 (data_version, total_changes)
+    fn read_change_markers(&self) -> rusqlite::Result<(i64, i64)> {
+        let data_version = self.conn
+            .prepare_cached("PRAGMA data_version")?
+            .query_row([], |row| row.get(0))?;
+        let total_changes = self.conn
+            .prepare_cached("SELECT total_changes()")?
+            .query_row([], |row| row.get(0))?;
+        Ok((data_version, total_changes))
+    }
+
+    /// 读取 tracks / favorites / lyrics 表的变更计数
+    fn read_table_versions(&self) -> rusqlite::Result<(i64, i64, i64)> {
+        let mut stmt = self.conn.prepare_cached("SELECT table_name, version FROM cache_meta")?;
+        let mut rows = stmt.query([])?;
+        let (mut tracks, mut favorites, mut lyrics) = (0, 0, 0);
+        while let Some(row) = rows.next()? {
+            let table: String = row.get(0)?;
+            match table.as_str() {
+                "tracks" => tracks = row.get(1)?,
+                "favorites" => favorites = row.get(1)?,
+                "lyrics" => lyrics = row.get(1)?,
+                _ => {}
+            }
+        }
+        Ok((tracks, favorites, lyrics))
+    }
+
     pub fn get_track_count(&self) -> Result<i64> {
         // 🔧 性能优化：检查缓存
         if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
             cache.cleanup_expired();
             
             if let Some(ref entry) = cache.track_count {
@@ -1191,6 +3869,7 @@ impl Database {
     pub fn get_artist_count(&self) -> Result<i64> {
         // 🔧 性能优化：检查缓存
         if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
             cache.cleanup_expired();
             
             if let Some(ref entry) = cache.artist_count {
@@ -1202,7 +3881,7 @@ impl Database {
         
         // 缓存未命中，执行查询
         let count: i64 = self.conn.query_row(
-            "SELECT COUNT(DISTINCT artist) FROM tracks WHERE artist IS NOT NULL AND artist != ''",
+            "SELECT COUNT(DISTINCT artist) FROM track_artists",
             [],
             |row| row.get(0),
         )?;
@@ -1218,6 +3897,7 @@ impl Database {
     pub fn get_album_count(&self) -> Result<i64> {
         // 🔧 性能优化：检查缓存
         if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
             cache.cleanup_expired();
             
             if let Some(ref entry) = cache.album_count {
@@ -1242,6 +3922,205 @@ impl Database {
         Ok(count)
     }
 
+    /// 专辑列表（不读取封面数据，封面通过 cover_track_id 按需加载）
+    pub fn get_albums(&self) -> Result<Vec<AlbumSummary>> {
+        if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
+            cache.cleanup_expired();
+            
+            if let Some(ref entry) = cache.albums {
+                if !entry.is_expired() {
+                    return Ok(entry.data.clone());
+                }
+            }
+        }
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT album,
+                    COALESCE(NULLIF(album_artist, ''), artist) AS effective_artist,
+                    MIN(year),
+                    COUNT(*),
+                    COALESCE(SUM(duration_ms), 0),
+                    COALESCE(MIN(CASE WHEN cover_id IS NOT NULL THEN id END), MIN(id)),
+                    MIN(cover_id)
+             FROM tracks
+             WHERE album IS NOT NULL AND album != ''
+             GROUP BY album, effective_artist
+             ORDER BY MIN(sort_album), album COLLATE NOCASE, effective_artist COLLATE NOCASE"
+        )?;
+        let albums = stmt.query_map([], |row| {
+            Ok(AlbumSummary {
+                album: row.get(0)?,
+                album_artist: row.get(1)?,
+                year: row.get(2)?,
+                track_count: row.get(3)?,
+                total_duration_ms: row.get(4)?,
+                cover_track_id: row.get(5)?,
+                cover_id: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.albums = Some(CacheEntry::new(albums.clone(), Duration::from_secs(600))); // 10分钟TTL
+        }
+        
+        Ok(albums)
+    }
+
+    /// 艺术家列表
+    pub fn get_artists(&self) -> Result<Vec<ArtistSummary>> {
+        if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
+            cache.cleanup_expired();
+            
+            if let Some(ref entry) = cache.artists {
+                if !entry.is_expired() {
+                    return Ok(entry.data.clone());
+                }
+            }
+        }
+        
+        // 按拆分后的艺术家统计，"A feat. B" 同时计入 A 和 B
+        let mut stmt = self.conn.prepare(
+            "SELECT ta.artist,
+                    COUNT(DISTINCT NULLIF(t.album, '')),
+                    COUNT(DISTINCT t.id)
+             FROM track_artists ta
+             JOIN tracks t ON t.id = ta.track_id
+             GROUP BY ta.artist
+             ORDER BY MIN(ta.sort_key), ta.artist COLLATE NOCASE"
+        )?;
+        let artists = stmt.query_map([], |row| {
+            Ok(ArtistSummary {
+                artist: row.get(0)?,
+                album_count: row.get(1)?,
+                track_count: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.artists = Some(CacheEntry::new(artists.clone(), Duration::from_secs(600))); // 10分钟TTL
+        }
+        
+        Ok(artists)
+    }
+
+    /// 专辑内的曲目，按碟片号、音轨号排序
+    ///
+    /// album_artist 与 get_albums 返回的值一致（标签缺失时为曲目艺术家）
+    /// 修改曲目元数据（FTS索引由 tracks_au 触发器同步），返回实际修改的曲目数
+    pub fn update_tracks_metadata(&self, track_ids: &[i64], update: &TrackMetadataUpdate) -> Result<usize> {
+        let assignments = update.assignments();
+        if assignments.is_empty() || track_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let set_clause = assignments.iter()
+            .enumerate()
+            .map(|(i, (column, _))| format!("{} = ?{}", column, i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!("UPDATE tracks SET {} WHERE id = ?{}", set_clause, assignments.len() + 1);
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        let mut updated_ids = Vec::new();
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for &track_id in track_ids {
+                let mut values: Vec<rusqlite::types::Value> = assignments.iter().map(|(_, value)| value.clone()).collect();
+                values.push(rusqlite::types::Value::Integer(track_id));
+                let changed = stmt.execute(rusqlite::params_from_iter(values))?;
+                if changed > 0 {
+                    updated_ids.push(track_id);
+                }
+                updated += changed;
+            }
+        }
+        // 艺术家被修改时重新拆分
+        if let Some(artist) = &update.artist {
+            let artists = artist_split::split_artists(artist, &self.artist_split_config());
+            for &track_id in &updated_ids {
+                Self::write_track_artists(&tx, track_id, &artists)?;
+            }
+        }
+        // 按修改后的标题、艺术家、专辑重新计算排序键
+        let transliterate = self.transliterate_search();
+        for &track_id in &updated_ids {
+            Self::refresh_sort_keys(&tx, track_id, transliterate)?;
+        }
+        tx.commit()?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(updated)
+    }
+
+    /// 设置曲目评分（None 表示清除），返回曲目是否存在
+    pub fn set_track_rating(&self, track_id: i64, rating: Option<u8>) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE tracks SET rating = ?1 WHERE id = ?2",
+            params![rating, track_id],
+        )?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(updated > 0)
+    }
+
+    /// 曲目评分，曲目不存在时返回 None
+    pub fn get_track_rating(&self, track_id: i64) -> Result<Option<Option<u8>>> {
+        Ok(self.conn.query_row(
+            "SELECT rating FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    pub fn get_album_tracks(&self, album: &str, album_artist: Option<&str>) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, title, artist, album, duration_ms, embedded_lyrics, genre, year, track_number, disc_number, album_artist, cover_id, rating, verify_status
+             FROM tracks
+             WHERE album = ?1 AND COALESCE(NULLIF(album_artist, ''), artist) IS ?2
+             ORDER BY COALESCE(disc_number, 1), track_number, title"
+        )?;
+
+        let track_iter = stmt.query_map(params![album, album_artist], |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
+            })
+        })?;
+
+        let mut tracks = Vec::new();
+        for track in track_iter {
+            tracks.push(track?);
+        }
+
+        Ok(tracks)
+    }
+
     // Lyrics methods
     pub fn insert_lyrics(&self, track_id: i64, content: &str, format: &str, source: &str) -> Result<i64> {
         let mut stmt = self.conn.prepare(
@@ -1276,6 +4155,36 @@ impl Database {
         }
     }
     
+    /// 缺少歌词（或只有临时歌词）且在 attempted_before 之前未尝试过的曲目
+    pub fn tracks_missing_lyrics(&self, attempted_before: i64) -> Result<Vec<LyricsCandidate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.path, t.title, t.artist, t.album FROM tracks t
+             LEFT JOIN lyrics l ON l.track_id = t.id
+             WHERE (l.id IS NULL OR l.source = 'temp')
+               AND (t.last_lyrics_attempt IS NULL OR t.last_lyrics_attempt < ?1)
+             ORDER BY t.artist, t.album, t.id"
+        )?;
+        let rows = stmt.query_map([attempted_before], |row| {
+            Ok(LyricsCandidate {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 记录一次批量获取歌词的尝试
+    pub fn set_lyrics_attempt(&self, track_id: i64, attempted_at: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tracks SET last_lyrics_attempt = ?1 WHERE id = ?2",
+            params![attempted_at, track_id],
+        )?;
+        Ok(())
+    }
+
     // ========== 艺术家封面相关操作 ==========
     
     /// 保存艺术家封面到数据库
@@ -1327,6 +4236,58 @@ impl Database {
         Ok(covers)
     }
 
+    /// 分页获取艺术家封面，一页最多 limit 个且图片总大小不超过 max_bytes（至少返回一个）
+    pub fn get_artist_covers_page(&self, offset: i64, limit: i64, max_bytes: usize) -> Result<ArtistCoverPage> {
+        let total: i64 = self.conn.query_row("SELECT COUNT(*) FROM artist_covers", [], |row| row.get(0))?;
+        let mut stmt = self.conn.prepare(
+            "SELECT artist_name, cover_data, cover_mime FROM artist_covers ORDER BY artist_name LIMIT ?1 OFFSET ?2"
+        )?;
+        let mut rows = stmt.query(params![limit, offset])?;
+
+        let mut covers = Vec::new();
+        let mut bytes = 0;
+        while let Some(row) = rows.next()? {
+            let data: Vec<u8> = row.get(1)?;
+            if !covers.is_empty() && bytes + data.len() > max_bytes {
+                break;
+            }
+            bytes += data.len();
+            covers.push((row.get(0)?, data, row.get(2)?));
+        }
+
+        let next = offset + covers.len() as i64;
+        Ok(ArtistCoverPage {
+            covers,
+            total,
+            next_offset: (next < total).then_some(next),
+        })
+    }
+
+    /// 没有封面、且在 attempted_before 之前未自动获取失败过的艺术家
+    pub fn artists_missing_covers(&self, attempted_before: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT ta.artist FROM track_artists ta
+             WHERE TRIM(ta.artist) != ''
+               AND NOT EXISTS (SELECT 1 FROM artist_covers c WHERE c.artist_name = ta.artist)
+               AND NOT EXISTS (SELECT 1 FROM artist_cover_attempts a
+                               WHERE a.artist_name = ta.artist AND a.attempted_at >= ?1)
+             ORDER BY ta.artist"
+        )?;
+        let artists = stmt.query_map([attempted_before], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(artists)
+    }
+
+    /// 记录一次获取艺术家封面失败
+    pub fn set_artist_cover_attempt(&self, artist_name: &str, attempted_at: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO artist_cover_attempts (artist_name, attempted_at) VALUES (?1, ?2)
+             ON CONFLICT(artist_name) DO UPDATE SET attempted_at = excluded.attempted_at",
+            params![artist_name, attempted_at],
+        )?;
+        Ok(())
+    }
+
     pub fn delete_lyrics(&self, track_id: i64) -> Result<()> {
         let mut stmt = self.conn.prepare("DELETE FROM lyrics WHERE track_id = ?1")?;
         stmt.execute([track_id])?;
@@ -1345,13 +4306,138 @@ impl Database {
 
     /// 更新曲目的专辑封面
     pub fn update_track_cover(&self, track_id: i64, cover_data: Option<Vec<u8>>, mime_type: Option<String>) -> Result<()> {
-        let mut stmt = self.conn.prepare(
-            "UPDATE tracks SET album_cover_data = ?2, album_cover_mime = ?3 WHERE id = ?1"
+        let cover_id = match cover_data {
+            Some(data) => Some(self.store_cover(&data, mime_type.as_deref())?),
+            None => None,
+        };
+        self.conn.execute(
+            "UPDATE tracks SET cover_id = ?2 WHERE id = ?1",
+            params![track_id, cover_id],
+        )?;
+        self.prune_unused_covers()?;
+        Ok(())
+    }
+
+    /// 保存封面原图并生成缩略图，返回封面ID；相同图片只保存一份
+    pub fn store_cover(&self, data: &[u8], mime: Option<&str>) -> Result<String> {
+        let id = cover_cache::cover_id(data);
+        let exists = self.conn
+            .prepare_cached("SELECT 1 FROM covers WHERE hash = ?1")?
+            .exists([&id])?;
+        if !exists {
+            let mime = mime.unwrap_or_else(|| cover_cache::sniff_mime(data));
+            let (small, large) = cover_cache::make_thumbnails(data);
+            self.conn.execute(
+                "INSERT OR IGNORE INTO covers (hash, mime, data, thumb_small, thumb_large) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, mime, data, small, large],
+            )?;
+        }
+        Ok(id)
+    }
+
+    /// 获取指定尺寸的封面；缩略图缺失（迁移来的旧封面）时现场生成并保存
+    pub fn get_cover(&self, cover_id: &str, size: CoverSize) -> Result<Option<CoverImage>> {
+        let column = match size {
+            CoverSize::Small => "thumb_small",
+            CoverSize::Large => "thumb_large",
+            CoverSize::Original => "NULL",
+        };
+        let row = self.conn.query_row(
+            &format!("SELECT data, mime, {} FROM covers WHERE hash = ?1", column),
+            [cover_id],
+            |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<Vec<u8>>>(2)?)),
+        ).optional()?;
+        let Some((data, mime, thumbnail)) = row else {
+            return Ok(None);
+        };
+
+        let Some(max_dimension) = size.max_dimension() else {
+            let mime = mime.unwrap_or_else(|| cover_cache::sniff_mime(&data).to_string());
+            return Ok(Some(CoverImage { data, mime }));
+        };
+        if let Some(thumbnail) = thumbnail {
+            return Ok(Some(CoverImage { data: thumbnail, mime: cover_cache::THUMBNAIL_MIME.to_string() }));
+        }
+
+        match cover_cache::make_thumbnail(&data, max_dimension) {
+            Ok(thumbnail) => {
+                self.conn.execute(
+                    &format!("UPDATE covers SET {} = ?2 WHERE hash = ?1", column),
+                    params![cover_id, thumbnail],
+                )?;
+                Ok(Some(CoverImage { data: thumbnail, mime: cover_cache::THUMBNAIL_MIME.to_string() }))
+            }
+            Err(e) => {
+                // 无法解码的图片原样返回，交给前端尝试显示
+                log::warn!("生成封面缩略图失败，返回原图: {} ({})", cover_id, e);
+                let mime = mime.unwrap_or_else(|| cover_cache::sniff_mime(&data).to_string());
+                Ok(Some(CoverImage { data, mime }))
+            }
+        }
+    }
+
+    /// 批量获取曲目封面：返回 曲目ID → 封面ID 以及 封面ID → 图片，同一专辑的封面只传输一次
+    pub fn get_covers_for_tracks(&self, track_ids: &[i64], size: CoverSize) -> Result<TrackCovers> {
+        let mut result = TrackCovers::default();
+        if track_ids.is_empty() {
+            return Ok(result);
+        }
+
+        let placeholders = vec!["?"; track_ids.len()].join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, cover_id FROM tracks WHERE cover_id IS NOT NULL AND id IN ({})",
+            placeholders
+        ))?;
+        let pairs = stmt.query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        for (track_id, cover_id) in pairs {
+            if !result.covers.contains_key(&cover_id) {
+                match self.get_cover(&cover_id, size)? {
+                    Some(image) => {
+                        result.covers.insert(cover_id.clone(), image);
+                    }
+                    None => continue,
+                }
+            }
+            result.track_covers.insert(track_id, cover_id);
+        }
+        Ok(result)
+    }
+
+    /// 获取缓存的封面配色
+    pub fn get_cover_palette(&self, cover_id: &str) -> Result<Option<CoverPalette>> {
+        let palette = self.conn.query_row(
+            "SELECT palette FROM cover_palettes WHERE hash = ?1",
+            [cover_id],
+            |row| row.get::<_, String>(0),
+        ).optional()?;
+        match palette {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 缓存封面配色
+    pub fn save_cover_palette(&self, cover_id: &str, palette: &CoverPalette) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cover_palettes (hash, palette) VALUES (?1, ?2)",
+            params![cover_id, serde_json::to_string(palette)?],
         )?;
-        stmt.execute(params![track_id, cover_data, mime_type])?;
         Ok(())
     }
 
+    /// 删除已没有曲目引用的封面
+    pub fn prune_unused_covers(&self) -> Result<usize> {
+        let removed = self.conn.execute(
+            "DELETE FROM covers WHERE hash NOT IN (SELECT cover_id FROM tracks WHERE cover_id IS NOT NULL)",
+            [],
+        )?;
+        Ok(removed)
+    }
+
     /// 删除指定来源的歌词（用于清理临时歌词，预留功能）
     #[allow(dead_code)]
     pub fn delete_lyrics_by_source(&self, track_id: i64, source: &str) -> Result<()> {
@@ -1407,6 +4493,37 @@ impl Database {
         Ok(folders)
     }
 
+    /// 曲目ID对应的文件路径（不存在的ID忽略）
+    pub fn get_track_paths(&self, track_ids: &[i64]) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT path FROM tracks WHERE id = ?1")?;
+        let mut paths = Vec::new();
+        for &track_id in track_ids {
+            if let Some(path) = stmt.query_row([track_id], |row| row.get::<_, String>(0)).optional()? {
+                paths.push((track_id, path));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// 删除曲目记录（一个事务），歌单项、收藏、歌词、播放历史等由外键级联删除，返回删除的曲目数
+    pub fn delete_tracks(&self, track_ids: &[i64]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM tracks WHERE id = ?1")?;
+            for &track_id in track_ids {
+                deleted += stmt.execute([track_id])?;
+            }
+        }
+        tx.commit()?;
+
+        // 收藏数等也随级联删除变化
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_all();
+        }
+        Ok(deleted)
+    }
+
     /// 删除指定文件夹路径下的所有音乐文件
     pub fn delete_folder_tracks(&self, folder_path: &str) -> Result<usize> {
         // 标准化路径格式
@@ -1441,18 +4558,100 @@ impl Database {
             let mut delete_stmt = self.conn.prepare("DELETE FROM tracks WHERE id = ?1")?;
             delete_stmt.execute([track_id])?;
         }
-        
-        // 🔧 性能优化：删除后失效缓存
         if deleted_count > 0 {
-            if let Ok(mut cache) = self.cache.lock() {
-                cache.invalidate_track_related();
-            }
+            self.prune_unused_covers()?;
         }
 
         log::info!("删除了文件夹 '{}' 下的 {} 首曲目", folder_path, deleted_count);
         Ok(deleted_count)
     }
 
+    /// 按磁盘目录结构浏览曲库，只使用数据库中的路径，远程曲目同样适用
+    ///
+    /// 根目录列出各音乐文件夹（去掉被上级覆盖的子目录）和各远程服务器；
+    /// 其他目录返回直接子文件夹和直接位于该目录下的曲目
+    pub fn browse_folder(&self, path: Option<&str>) -> Result<FolderListing> {
+        let mut stmt = self.conn.prepare("SELECT id, path, COALESCE(duration_ms, 0) FROM tracks")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, normalize_folder_path(&row.get::<_, String>(1)?), row.get::<_, i64>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut folders: Vec<FolderEntry> = Vec::new();
+        let mut track_ids = Vec::new();
+        let add_to_folder = |folders: &mut Vec<FolderEntry>, folder_path: String, name: &str, duration_ms: i64| {
+            match folders.iter_mut().find(|folder| folder.path == folder_path) {
+                Some(folder) => {
+                    folder.track_count += 1;
+                    folder.total_duration_ms += duration_ms;
+                }
+                None => folders.push(FolderEntry {
+                    path: folder_path,
+                    name: name.to_string(),
+                    track_count: 1,
+                    total_duration_ms: duration_ms,
+                }),
+            }
+        };
+
+        match path.map(normalize_folder_path) {
+            None => {
+                let server_names: HashMap<String, String> = self.get_remote_servers()?
+                    .into_iter()
+                    .map(|(id, name, ..)| (id, name))
+                    .collect();
+
+                // 本地根目录：曲目所在目录中没有被其他目录包含的那些
+                let mut local_dirs: Vec<String> = rows.iter()
+                    .filter(|(_, track_path, _)| !remote_source::is_remote_track_path(track_path))
+                    .filter_map(|(_, track_path, _)| track_path.rsplit_once('/').map(|(dir, _)| dir.to_string()))
+                    .collect();
+                local_dirs.sort();
+                local_dirs.dedup();
+                let mut roots: Vec<String> = Vec::new();
+                for dir in local_dirs {
+                    if !roots.iter().any(|root| dir.starts_with(&format!("{}/", root))) {
+                        roots.push(dir);
+                    }
+                }
+
+                for (_, track_path, duration_ms) in &rows {
+                    if let Some((server_root, _)) = track_path.split_once('#').filter(|_| remote_source::is_remote_track_path(track_path)) {
+                        let server_id = server_root.split_once("://").map(|(_, id)| id).unwrap_or(server_root);
+                        let name = server_names.get(server_id).map(String::as_str).unwrap_or(server_id);
+                        add_to_folder(&mut folders, format!("{}#", server_root), name, *duration_ms);
+                    } else if let Some(root) = roots.iter().find(|root| track_path.starts_with(&format!("{}/", root))) {
+                        let name = root.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(root);
+                        add_to_folder(&mut folders, root.clone(), name, *duration_ms);
+                    }
+                }
+            }
+            Some(folder_path) => {
+                let prefix = format!("{}/", folder_path);
+                for (id, track_path, duration_ms) in &rows {
+                    let Some(rest) = track_path.strip_prefix(&prefix) else { continue };
+                    match rest.split_once('/') {
+                        Some((child, _)) => add_to_folder(&mut folders, format!("{}{}", prefix, child), child, *duration_ms),
+                        None => track_ids.push(*id),
+                    }
+                }
+            }
+        }
+
+        folders.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+        let mut tracks = self.get_tracks_by_ids(&track_ids)?;
+        tracks.sort_by(|a, b| {
+            let file_name = |track: &Track| normalize_folder_path(&track.path).rsplit('/').next().unwrap_or_default().to_string();
+            natural_cmp(&file_name(a), &file_name(b))
+        });
+
+        Ok(FolderListing {
+            path: path.map(normalize_folder_path),
+            folders,
+            tracks,
+        })
+    }
+
     // Favorites methods
     pub fn add_favorite(&self, track_id: i64) -> Result<i64> {
         let mut stmt = self.conn.prepare(
@@ -1483,7 +4682,7 @@ impl Database {
 
     pub fn get_all_favorites(&self) -> Result<Vec<Track>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.album_cover_data, t.album_cover_mime, t.artist_photo_data, t.artist_photo_mime, t.embedded_lyrics
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
              FROM tracks t
              JOIN favorites f ON t.id = f.track_id
              ORDER BY f.created_at DESC"
@@ -1497,11 +4696,19 @@ impl Database {
                 artist: row.get(3)?,
                 album: row.get(4)?,
                 duration_ms: row.get(5)?,
-                album_cover_data: row.get(6)?,
-                album_cover_mime: row.get(7)?,
-                artist_photo_data: row.get(8)?,
-                artist_photo_mime: row.get(9)?,
-                embedded_lyrics: row.get(10)?,
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6)?,
+                genre: row.get(7)?,
+                year: row.get(8)?,
+                track_number: row.get(9)?,
+                disc_number: row.get(10)?,
+                album_artist: row.get(11)?,
+                cover_id: row.get(12)?,
+                rating: row.get(13)?,
+                verify_status: row.get(14)?,
             })
         })?;
 
@@ -1513,15 +4720,154 @@ impl Database {
         Ok(tracks)
     }
 
+    /// 最近添加的曲目（不含封面数据）
+    pub fn get_recently_added_tracks(&self, limit: i64) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks t ORDER BY t.created_at DESC, t.id DESC LIMIT ?1",
+            LIST_TRACK_COLUMNS
+        ))?;
+        let tracks = stmt.query_map([limit], list_track_from_row)?;
+        Ok(tracks.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// since 之后播放过的曲目，按最后播放时间倒序（不含封面数据）
+    pub fn get_recently_played_tracks(&self, since: i64, limit: i64) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks t
+             JOIN (SELECT track_id, MAX(played_at) AS last_played
+                   FROM play_history WHERE played_at >= ?1 GROUP BY track_id) h ON h.track_id = t.id
+             ORDER BY h.last_played DESC
+             LIMIT ?2",
+            LIST_TRACK_COLUMNS
+        ))?;
+        let tracks = stmt.query_map(params![since, limit], list_track_from_row)?;
+        Ok(tracks.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// since 之后播放次数最多的曲目，次数相同时最近播放的在前（不含封面数据）
+    pub fn get_most_played_tracks(&self, since: i64, limit: i64) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks t
+             JOIN (SELECT track_id, COUNT(*) AS plays, MAX(played_at) AS last_played
+                   FROM play_history WHERE played_at >= ?1 GROUP BY track_id) h ON h.track_id = t.id
+             ORDER BY h.plays DESC, h.last_played DESC
+             LIMIT ?2",
+            LIST_TRACK_COLUMNS
+        ))?;
+        let tracks = stmt.query_map(params![since, limit], list_track_from_row)?;
+        Ok(tracks.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
     pub fn get_favorites_count(&self) -> Result<i64> {
+        // 🔧 性能优化：检查缓存
+        if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
+            cache.cleanup_expired();
+            
+            if let Some(ref entry) = cache.favorites_count {
+                if !entry.is_expired() {
+                    return Ok(entry.data);
+                }
+            }
+        }
+        
         let count: i64 = self.conn.query_row(
             "SELECT COUNT(*) FROM favorites",
             [],
             |row| row.get(0),
         )?;
+        
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.favorites_count = Some(CacheEntry::new(count, Duration::from_secs(300))); // 5分钟TTL
+        }
+        
         Ok(count)
     }
 
+    /// 曲库统计：占用空间、格式分布、比特率和采样率范围、缺少封面/歌词/时长的曲目数、来源分布
+    pub fn get_library_statistics(&self) -> Result<LibraryStatistics> {
+        if let Ok(mut cache) = self.cache.lock() {
+            self.sync_cache_with_db(&mut cache);
+            cache.cleanup_expired();
+            
+            if let Some(ref entry) = cache.library_statistics {
+                if !entry.is_expired() {
+                    return Ok(entry.data.clone());
+                }
+            }
+        }
+        
+        let mut stats = self.conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(file_size), 0),
+                    COALESCE(SUM(cover_id IS NULL), 0),
+                    COALESCE(SUM(embedded_lyrics IS NULL
+                        AND NOT EXISTS (SELECT 1 FROM lyrics WHERE lyrics.track_id = tracks.id)), 0),
+                    COALESCE(SUM(COALESCE(duration_ms, 0) <= 0), 0)
+             FROM tracks",
+            [],
+            |row| {
+                Ok(LibraryStatistics {
+                    total_tracks: row.get(0)?,
+                    total_bytes: row.get(1)?,
+                    missing_cover: row.get(2)?,
+                    missing_lyrics: row.get(3)?,
+                    missing_duration: row.get(4)?,
+                    ..Default::default()
+                })
+            },
+        )?;
+        
+        let value_range = |column: &str| -> Result<Option<ValueRange>> {
+            let (avg, min, max) = self.conn.query_row(
+                &format!("SELECT AVG({0}), MIN({0}), MAX({0}) FROM tracks WHERE {0} > 0", column),
+                [],
+                |row| Ok((row.get::<_, Option<f64>>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Option<i64>>(2)?)),
+            )?;
+            Ok(match (avg, min, max) {
+                (Some(avg), Some(min), Some(max)) => Some(ValueRange { avg, min, max }),
+                _ => None,
+            })
+        };
+        stats.bitrate_kbps = value_range("bitrate")?;
+        stats.sample_rate = value_range("sample_rate")?;
+        
+        // 格式按扫描时记录的值统计，旧曲目没有记录时按扩展名推断
+        let mut formats: HashMap<String, FormatStatistics> = HashMap::new();
+        let mut stmt = self.conn.prepare("SELECT format, path, file_size FROM tracks")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let format: Option<String> = row.get(0)?;
+            let path: String = row.get(1)?;
+            let size: Option<i64> = row.get(2)?;
+            let format = format
+                .or_else(|| Path::new(&path).extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+                .unwrap_or_else(|| "unknown".to_string());
+            let entry = formats.entry(format.clone()).or_insert(FormatStatistics {
+                format,
+                track_count: 0,
+                total_bytes: 0,
+            });
+            entry.track_count += 1;
+            entry.total_bytes += size.unwrap_or(0);
+        }
+        stats.formats = formats.into_values().collect();
+        stats.formats.sort_by(|a, b| b.track_count.cmp(&a.track_count).then_with(|| a.format.cmp(&b.format)));
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT COALESCE(source_type, 'local'), COUNT(*) FROM tracks GROUP BY 1 ORDER BY 2 DESC, 1"
+        )?;
+        stats.sources = stmt.query_map([], |row| {
+            Ok(SourceCount { source_type: row.get(0)?, track_count: row.get(1)? })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.library_statistics = Some(CacheEntry::new(stats.clone(), Duration::from_secs(300))); // 5分钟TTL
+        }
+        
+        Ok(stats)
+    }
+
     pub fn toggle_favorite(&self, track_id: i64) -> Result<bool> {
         if self.is_favorite(track_id)? {
             self.remove_favorite(track_id)?;
@@ -1534,7 +4880,9 @@ impl Database {
 
     // ========== 远程服务器管理 ==========
 
+    /// 添加远程服务器（密码加密后保存）
     pub fn add_remote_server(&self, id: &str, name: &str, server_type: &str, config_json: &str) -> Result<()> {
+        let config_json = &self.secrets.encrypt_config(config_json, None)?;
         let now = chrono::Utc::now().timestamp();
         self.conn.execute(
             "INSERT INTO remote_servers (id, name, server_type, config_json, created_at, updated_at) 
@@ -1545,6 +4893,7 @@ impl Database {
         Ok(())
     }
 
+    /// 获取远程服务器（id, 名称, 类型, 配置, 是否启用），配置中的密码已解密
     pub fn get_remote_servers(&self) -> Result<Vec<(String, String, String, String, bool)>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, name, server_type, config_json, enabled FROM remote_servers ORDER BY priority DESC, name ASC"
@@ -1552,16 +4901,27 @@ impl Database {
         
         let servers = stmt.query_map([], |row| {
             Ok((
-                row.get(0)?,
+                row.get::<_, String>(0)?,
                 row.get(1)?,
                 row.get(2)?,
-                row.get(3)?,
+                row.get::<_, String>(3)?,
                 row.get::<_, i64>(4)? == 1,
             ))
         })?
         .collect::<Result<Vec<_>, _>>()?;
         
-        Ok(servers)
+        Ok(servers.into_iter()
+            .map(|(id, name, server_type, config_json, enabled)| {
+                // 密钥丢失时密码置空，用户重新填写即可，不影响其他服务器
+                let config_json = self.secrets.decrypt_config(&config_json).unwrap_or_else(|e| {
+                    log::warn!("服务器 {} 的凭据解密失败: {}", id, e);
+                    let mut config = secrets::redact_config(&config_json);
+                    config[secrets::PASSWORD_FIELD] = "".into();
+                    config.to_string()
+                });
+                (id, name, server_type, config_json, enabled)
+            })
+            .collect())
     }
 
     pub fn delete_remote_server(&self, id: &str) -> Result<()> {
@@ -1570,7 +4930,14 @@ impl Database {
         Ok(())
     }
 
+    /// 更新远程服务器（配置中没有密码时保留已保存的密码）
     pub fn update_remote_server(&self, id: &str, name: &str, config_json: &str) -> Result<()> {
+        let previous: Option<String> = self.conn.query_row(
+            "SELECT config_json FROM remote_servers WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        ).optional()?;
+        let config_json = &self.secrets.encrypt_config(config_json, previous.as_deref())?;
         let now = chrono::Utc::now().timestamp();
         self.conn.execute(
             "UPDATE remote_servers SET name = ?1, config_json = ?2, updated_at = ?3 WHERE id = ?4",
@@ -1582,8 +4949,7 @@ impl Database {
 
     // ========== 缓存管理 ==========
 
-    /// 添加缓存条目（预留功能）
-    #[allow(dead_code)]
+    /// 添加缓存条目（保留已有的固定状态）
     pub fn add_cache_entry(
         &self,
         server_id: &str,
@@ -1591,21 +4957,67 @@ impl Database {
         local_cache_path: &str,
         file_size: Option<i64>,
         mime_type: Option<&str>,
+        track_id: Option<i64>,
     ) -> Result<i64> {
         let now = chrono::Utc::now().timestamp();
         
         self.conn.execute(
-            "INSERT OR REPLACE INTO remote_cache 
-             (server_id, remote_path, local_cache_path, file_size, mime_type, cached_at, last_accessed, access_count)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1)",
-            params![server_id, remote_path, local_cache_path, file_size, mime_type, now, now],
+            "INSERT INTO remote_cache 
+             (server_id, remote_path, local_cache_path, file_size, mime_type, cached_at, last_accessed, access_count, track_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8)
+             ON CONFLICT(server_id, remote_path) DO UPDATE SET
+                local_cache_path = excluded.local_cache_path,
+                file_size = excluded.file_size,
+                mime_type = excluded.mime_type,
+                cached_at = excluded.cached_at,
+                last_accessed = excluded.last_accessed,
+                track_id = excluded.track_id,
+                cache_status = 'valid'",
+            params![server_id, remote_path, local_cache_path, file_size, mime_type, now, now, track_id],
         )?;
         
         Ok(self.conn.last_insert_rowid())
     }
 
-    /// 获取缓存条目（预留功能）
-    #[allow(dead_code)]
+    /// 获取所有有效的缓存记录
+    pub fn get_cache_records(&self) -> Result<Vec<RemoteCacheRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, local_cache_path, COALESCE(file_size, 0), access_count, cached_at, last_accessed, COALESCE(pinned, 0)
+             FROM remote_cache WHERE cache_status = 'valid'"
+        )?;
+        let records = stmt.query_map([], |row| {
+            Ok(RemoteCacheRecord {
+                track_id: row.get(0)?,
+                local_cache_path: row.get(1)?,
+                file_size: row.get(2)?,
+                access_count: row.get(3)?,
+                cached_at: row.get(4)?,
+                last_accessed: row.get(5)?,
+                pinned: row.get::<_, i64>(6)? != 0,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// 按本地文件路径删除缓存记录
+    pub fn remove_cache_entry(&self, local_cache_path: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM remote_cache WHERE local_cache_path = ?1",
+            [local_cache_path],
+        )?;
+        Ok(())
+    }
+
+    /// 固定或取消固定曲目的缓存
+    pub fn set_cache_pinned(&self, track_id: i64, pinned: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE remote_cache SET pinned = ?1 WHERE track_id = ?2",
+            params![pinned as i64, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 获取缓存条目
     pub fn get_cache_entry(&self, server_id: &str, remote_path: &str) -> Result<Option<String>> {
         let mut stmt = self.conn.prepare(
             "SELECT local_cache_path FROM remote_cache 
@@ -1684,7 +5096,9 @@ impl Database {
                     p.is_smart, p.smart_rules, p.is_favorite, p.is_pinned, p.created_at, 
                     p.updated_at, p.last_played, p.play_count,
                     COUNT(pi.id) as track_count,
-                    COALESCE(SUM(t.duration_ms), 0) as total_duration
+                    COALESCE(SUM(t.duration_ms), 0) as total_duration,
+                    p.folder_id, COALESCE(p.sort_order, 0),
+                    p.default_shuffle, p.default_repeat_mode, p.default_crossfade_ms
              FROM playlists p
              LEFT JOIN playlist_items pi ON p.id = pi.playlist_id
              LEFT JOIN tracks t ON pi.track_id = t.id
@@ -1709,6 +5123,11 @@ impl Database {
                 play_count: row.get(12)?,
                 track_count: row.get(13)?,
                 total_duration_ms: row.get(14)?,
+                folder_id: row.get(15)?,
+                sort_order: row.get(16)?,
+                default_shuffle: row.get::<_, Option<i64>>(17)?.map(|v| v != 0),
+                default_repeat_mode: row.get::<_, Option<String>>(18)?.as_deref().and_then(RepeatMode::parse),
+                default_crossfade_ms: row.get::<_, Option<i64>>(19)?.map(|v| v.max(0) as u64),
             })
         })?;
 
@@ -1727,7 +5146,9 @@ impl Database {
                     p.is_smart, p.smart_rules, p.is_favorite, p.is_pinned, p.created_at, 
                     p.updated_at, p.last_played, p.play_count,
                     COUNT(pi.id) as track_count,
-                    COALESCE(SUM(t.duration_ms), 0) as total_duration
+                    COALESCE(SUM(t.duration_ms), 0) as total_duration,
+                    p.folder_id, COALESCE(p.sort_order, 0),
+                    p.default_shuffle, p.default_repeat_mode, p.default_crossfade_ms
              FROM playlists p
              LEFT JOIN playlist_items pi ON p.id = pi.playlist_id
              LEFT JOIN tracks t ON pi.track_id = t.id
@@ -1752,6 +5173,11 @@ impl Database {
                 play_count: row.get(12)?,
                 track_count: row.get(13)?,
                 total_duration_ms: row.get(14)?,
+                folder_id: row.get(15)?,
+                sort_order: row.get(16)?,
+                default_shuffle: row.get::<_, Option<i64>>(17)?.map(|v| v != 0),
+                default_repeat_mode: row.get::<_, Option<String>>(18)?.as_deref().and_then(RepeatMode::parse),
+                default_crossfade_ms: row.get::<_, Option<i64>>(19)?.map(|v| v.max(0) as u64),
             })
         });
 
@@ -1762,6 +5188,23 @@ impl Database {
         }
     }
 
+    /// 设置歌单的播放偏好（为None的项清除，恢复使用全局设置）
+    pub fn set_playlist_playback_defaults(
+        &self,
+        playlist_id: i64,
+        shuffle: Option<bool>,
+        repeat_mode: Option<RepeatMode>,
+        crossfade_ms: Option<u64>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE playlists SET default_shuffle = ?1, default_repeat_mode = ?2, default_crossfade_ms = ?3,
+                updated_at = strftime('%s', 'now')
+             WHERE id = ?4",
+            params![shuffle, repeat_mode.map(|m| m.as_str()), crossfade_ms.map(|ms| ms as i64), playlist_id],
+        )?;
+        Ok(())
+    }
+
     /// 更新歌单元数据
     pub fn update_playlist_metadata(
         &self,
@@ -1992,232 +5435,3314 @@ impl Database {
         Ok(new_value == 1)
     }
 
-    // ========== 播放历史管理 ==========
+    // ========== 歌单文件夹 ==========
 
-    /// 记录播放历史
-    pub fn add_play_history(&self, track_id: i64, duration_played_ms: i64) -> Result<()> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
-        
+    /// 获取所有歌单文件夹（按手动顺序）
+    pub fn get_playlist_folders(&self) -> Result<Vec<crate::playlist::PlaylistFolder>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, parent_id, sort_order, created_at FROM playlist_folders ORDER BY sort_order, id"
+        )?;
+        let folders = stmt.query_map([], |row| {
+            Ok(crate::playlist::PlaylistFolder {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                parent_id: row.get(2)?,
+                sort_order: row.get(3)?,
+                created_at: row.get::<_, Option<i64>>(4)?.unwrap_or_default(),
+            })
+        })?;
+        Ok(folders.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// 创建歌单文件夹，排在同级文件夹的最后
+    pub fn create_playlist_folder(&self, name: &str, parent_id: Option<i64>) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO play_history (track_id, played_at, duration_played_ms) VALUES (?1, ?2, ?3)",
-            params![track_id, now, duration_played_ms],
+            "INSERT INTO playlist_folders (name, parent_id, sort_order)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM playlist_folders WHERE parent_id IS ?2))",
+            params![name, parent_id],
         )?;
-        Ok(())
+        Ok(self.conn.last_insert_rowid())
     }
 
-    /// 获取播放历史（带统计）
-    pub fn get_play_history(&self, sort_by: &str, limit: i64) -> Result<Vec<(Track, i64, i64, i64)>> {
-        let order_clause = match sort_by {
-            "play_count" => "play_count DESC, last_played DESC",
-            "first_played" => "first_played ASC",
-            _ => "last_played DESC", // default: last_played
-        };
-        
-        let sql = format!(
-            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms,
-                    COUNT(ph.id) as play_count,
-                    MAX(ph.played_at) as last_played,
-                    MIN(ph.played_at) as first_played
-             FROM tracks t
-             INNER JOIN play_history ph ON t.id = ph.track_id
-             GROUP BY t.id
-             ORDER BY {}
-             LIMIT ?1",
-            order_clause
-        );
-        
-        let mut stmt = self.conn.prepare(&sql)?;
-        let rows = stmt.query_map([limit], |row| {
-            Ok((
-                Track {
-                    id: row.get(0)?,
-                    path: row.get(1)?,
-                    title: row.get(2).ok(),
-                    artist: row.get(3).ok(),
-                    album: row.get(4).ok(),
-                    duration_ms: row.get(5).ok(),
-                    album_cover_data: None,
-                    album_cover_mime: None,
-                    artist_photo_data: None,
-                    artist_photo_mime: None,
-                    embedded_lyrics: None,
-                },
-                row.get(6)?, // play_count
-                row.get(7)?, // last_played
-                row.get(8)?, // first_played
-            ))
-        })?;
-        
-        let mut result = Vec::new();
-        for row in rows {
-            result.push(row?);
-        }
-        Ok(result)
+    /// 重命名歌单文件夹，文件夹不存在时返回 false
+    pub fn rename_playlist_folder(&self, folder_id: i64, name: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE playlist_folders SET name = ?1 WHERE id = ?2",
+            params![name, folder_id],
+        )?;
+        Ok(updated > 0)
     }
 
-    /// 获取播放统计
-    pub fn get_play_statistics(&self) -> Result<(i64, i64, i64)> {
-        let total_plays: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM play_history",
-            [],
+    /// 删除歌单文件夹，其中的歌单和子文件夹移到上一级（排在原有项目之后，保持原来的相对顺序）
+    ///
+    /// 文件夹不存在时返回 false
+    pub fn delete_playlist_folder(&self, folder_id: i64) -> Result<bool> {
+        let parent_id: Option<i64> = match self.conn.query_row(
+            "SELECT parent_id FROM playlist_folders WHERE id = ?1",
+            [folder_id],
             |row| row.get(0),
-        )?;
-        
-        let unique_tracks: i64 = self.conn.query_row(
-            "SELECT COUNT(DISTINCT track_id) FROM play_history",
-            [],
+        ).optional()? {
+            Some(parent_id) => parent_id,
+            None => return Ok(false),
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        let playlist_offset: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM playlists WHERE folder_id IS ?1",
+            params![parent_id],
             |row| row.get(0),
         )?;
-        
-        // 使用实际播放时长而非曲目完整时长
-        let total_duration_ms: i64 = self.conn.query_row(
-            "SELECT COALESCE(SUM(duration_played_ms), 0) FROM play_history",
-            [],
+        tx.execute(
+            "UPDATE playlists SET folder_id = ?1, sort_order = COALESCE(sort_order, 0) + ?2 WHERE folder_id = ?3",
+            params![parent_id, playlist_offset, folder_id],
+        )?;
+        let folder_offset: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM playlist_folders WHERE parent_id IS ?1",
+            params![parent_id],
             |row| row.get(0),
         )?;
-        
-        Ok((total_plays, unique_tracks, total_duration_ms))
+        tx.execute(
+            "UPDATE playlist_folders SET parent_id = ?1, sort_order = sort_order + ?2 WHERE parent_id = ?3",
+            params![parent_id, folder_offset, folder_id],
+        )?;
+        tx.execute("DELETE FROM playlist_folders WHERE id = ?1", [folder_id])?;
+        tx.commit()?;
+        Ok(true)
     }
 
-    /// 清空播放历史
-    pub fn clear_play_history(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM play_history", [])?;
+    /// 把文件夹放到 parent_id 下，顺序为 ordered_ids 中的位置
+    pub fn place_playlist_folders(&self, parent_id: Option<i64>, ordered_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("UPDATE playlist_folders SET parent_id = ?1, sort_order = ?2 WHERE id = ?3")?;
+            for (index, folder_id) in ordered_ids.iter().enumerate() {
+                stmt.execute(params![parent_id, index as i64, folder_id])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    /// 从历史中删除某曲目（预留功能）
-    #[allow(dead_code)]
-    pub fn remove_from_history(&self, track_id: i64) -> Result<()> {
+    /// 把歌单放到文件夹中（folder_id 为 None 时放在最外层），顺序为 ordered_ids 中的位置
+    pub fn place_playlists(&self, folder_id: Option<i64>, ordered_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("UPDATE playlists SET folder_id = ?1, sort_order = ?2 WHERE id = ?3")?;
+            for (index, playlist_id) in ordered_ids.iter().enumerate() {
+                stmt.execute(params![folder_id, index as i64, playlist_id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // ========== 启动健康检查 ==========
+
+    /// 获取所有本地曲目路径（不含远程曲目）
+    pub fn get_local_track_paths(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path FROM tracks
+             WHERE path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%' AND COALESCE(source_type, 'local') != 'webdav'"
+        )?;
+        let paths = stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(paths)
+    }
+
+    /// 最近一次扫描时间（以曲目最后写入时间近似）
+    pub fn get_last_scan_time(&self) -> Result<Option<i64>> {
+        let last = self.conn.query_row("SELECT MAX(last_modified) FROM tracks", [], |row| row.get(0))?;
+        Ok(last)
+    }
+
+    /// 获取在指定时间之后未刷新过的智能歌单
+    pub fn get_stale_smart_playlist_ids(&self, since: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM playlists WHERE is_smart = 1 AND (updated_at IS NULL OR updated_at < ?1)"
+        )?;
+        let ids = stmt.query_map([since], |row| row.get(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(ids)
+    }
+
+    // ========== 应用设置 ==========
+
+    /// 读取设置项
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value = self.conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(value)
+    }
+
+    /// 写入设置项（存在则覆盖）
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
         self.conn.execute(
-            "DELETE FROM play_history WHERE track_id = ?1",
-            params![track_id],
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![key, value, now],
         )?;
+        self.invalidate_setting(key);
         Ok(())
     }
-    
-    // 🔧 P2新增：播放历史相关的查询方法
-    
-    /// 删除指定时间之前的播放历史（预留功能）
-    #[allow(dead_code)]
-    pub fn delete_play_history_before(&self, timestamp: i64) -> Result<usize> {
-        let deleted = self.conn.execute(
-            "DELETE FROM play_history WHERE played_at < ?1",
-            params![timestamp],
-        )?;
-        Ok(deleted)
+
+    /// 删除设置项
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM app_settings WHERE key = ?1", [key])?;
+        self.invalidate_setting(key);
+        Ok(())
     }
-    
-    /// 获取最近播放历史（返回PlayHistoryEntry结构，预留功能）
-    #[allow(dead_code)]
-    pub fn get_recent_play_history(&self, limit: usize) -> Result<Vec<crate::play_history::PlayHistoryEntry>> {
-        let history_data = self.get_play_history("last_played", limit as i64)?;
-        
-        Ok(history_data.into_iter().map(|(track, play_count, last_played, first_played)| {
-            crate::play_history::PlayHistoryEntry {
-                track,
-                play_count,
-                last_played_at: last_played,
-                first_played_at: first_played,
+
+    /// 清除由设置项派生的缓存
+    fn invalidate_setting(&self, key: &str) {
+        if key == crate::app_config::SETTING_APP_CONFIG {
+            if let Ok(mut config) = self.artist_split.lock() {
+                *config = None;
             }
-        }).collect())
+        }
+        if key == sort_key::SETTING_TRANSLITERATE_SEARCH {
+            if let Ok(mut enabled) = self.transliterate_search.lock() {
+                *enabled = None;
+            }
+        }
     }
-    
-    /// 🔧 修复：获取播放统计信息（返回PlayStatistics结构，预留功能）
-    #[allow(dead_code)]
-    pub fn get_play_statistics_struct(&self) -> Result<crate::play_history::PlayStatistics> {
-        let (total_plays, unique_tracks, total_duration_ms) = self.get_play_statistics()?;
-        Ok(crate::play_history::PlayStatistics {
-            total_plays,
-            unique_tracks,
-            total_duration_ms,
+
+    /// 所有设置项（按键排序）
+    pub fn get_all_settings(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT key, value FROM app_settings ORDER BY key")?;
+        let settings = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(settings)
+    }
+
+    // ========== 同步队列 ==========
+
+    const SYNC_TASK_COLUMNS: &'static str = "id, task_type, track_id, source_path, target_path, server_id, status, progress_percent, \
+        error_message, retry_count, max_retries, file_size, bytes_transferred, created_at, started_at, completed_at, next_retry_at";
+
+    fn row_to_sync_task(row: &rusqlite::Row) -> rusqlite::Result<SyncTask> {
+        Ok(SyncTask {
+            id: row.get(0)?,
+            task_type: row.get(1)?,
+            track_id: row.get(2)?,
+            source_path: row.get(3)?,
+            target_path: row.get(4)?,
+            server_id: row.get(5)?,
+            status: row.get(6)?,
+            progress_percent: row.get(7)?,
+            error_message: row.get(8)?,
+            retry_count: row.get(9)?,
+            max_retries: row.get(10)?,
+            file_size: row.get(11)?,
+            bytes_transferred: row.get(12)?,
+            created_at: row.get(13)?,
+            started_at: row.get(14)?,
+            completed_at: row.get(15)?,
+            next_retry_at: row.get(16)?,
         })
     }
-    
-    // 🔧 P2新增：智能歌单扩展字段查询
-    
-    /// 获取曲目的添加时间
-    pub fn get_track_date_added(&self, track_id: i64) -> Result<Option<i64>> {
-        // 从tracks表的created_at字段或文件系统时间获取
-        let timestamp: Option<i64> = self.conn.query_row(
-            "SELECT created_at FROM tracks WHERE id = ?1",
-            params![track_id],
+
+    /// 添加同步任务；同一曲目到同一服务器已有未完成的同类任务时返回已有任务
+    pub fn enqueue_sync_task(
+        &self,
+        task_type: &str,
+        track_id: Option<i64>,
+        source_path: &str,
+        target_path: Option<&str>,
+        server_id: &str,
+        file_size: Option<i64>,
+    ) -> Result<i64> {
+        let existing: Option<i64> = self.conn.query_row(
+            "SELECT id FROM sync_queue
+             WHERE task_type = ?1 AND source_path = ?2 AND server_id = ?3 AND status IN ('pending', 'running')",
+            params![task_type, source_path, server_id],
             |row| row.get(0),
         ).optional()?;
-        
-        Ok(timestamp)
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+
+        self.conn.execute(
+            "INSERT INTO sync_queue (task_type, track_id, source_path, target_path, server_id, file_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![task_type, track_id, source_path, target_path, server_id, file_size],
+        )?;
+        Ok(self.conn.last_insert_rowid())
     }
-    
-    /// 获取曲目的最后播放时间
-    pub fn get_track_last_played(&self, track_id: i64) -> Result<Option<i64>> {
-        let timestamp: Option<i64> = self.conn.query_row(
-            "SELECT MAX(played_at) FROM play_history WHERE track_id = ?1",
-            params![track_id],
-            |row| row.get(0),
+
+    /// 取出下一个可执行的任务（等待重试的任务需到达重试时间）
+    pub fn next_sync_task(&self, task_type: &str, now: i64) -> Result<Option<SyncTask>> {
+        let task = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM sync_queue
+                 WHERE task_type = ?1 AND status = 'pending' AND COALESCE(next_retry_at, 0) <= ?2
+                 ORDER BY priority DESC, id
+                 LIMIT 1",
+                Self::SYNC_TASK_COLUMNS
+            ),
+            params![task_type, now],
+            Self::row_to_sync_task,
         ).optional()?;
-        
-        Ok(timestamp)
+        Ok(task)
     }
-    
-    /// 获取曲目的播放次数
-    pub fn get_track_play_count(&self, track_id: i64) -> Result<i64> {
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM play_history WHERE track_id = ?1",
-            params![track_id],
+
+    /// 最早的重试时间，用于空闲时决定等待多久
+    pub fn next_sync_retry_at(&self, task_type: &str) -> Result<Option<i64>> {
+        let at = self.conn.query_row(
+            "SELECT MIN(next_retry_at) FROM sync_queue WHERE task_type = ?1 AND status = 'pending'",
+            [task_type],
             |row| row.get(0),
-        ).unwrap_or(0);
-        
+        )?;
+        Ok(at)
+    }
+
+    /// 同步队列（最新的在前）
+    pub fn get_sync_queue(&self, limit: i64) -> Result<Vec<SyncTask>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sync_queue ORDER BY id DESC LIMIT ?1",
+            Self::SYNC_TASK_COLUMNS
+        ))?;
+        let tasks = stmt.query_map([limit], Self::row_to_sync_task)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tasks)
+    }
+
+    pub fn start_sync_task(&self, id: i64, file_size: i64) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE sync_queue SET status = 'running', started_at = ?1, file_size = ?2,
+                bytes_transferred = 0, progress_percent = 0
+             WHERE id = ?3",
+            params![now, file_size, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_sync_progress(&self, id: i64, bytes_transferred: i64, progress_percent: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sync_queue SET bytes_transferred = ?1, progress_percent = ?2 WHERE id = ?3",
+            params![bytes_transferred, progress_percent.clamp(0, 100), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn complete_sync_task(&self, id: i64) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE sync_queue SET status = 'completed', progress_percent = 100, completed_at = ?1,
+                error_message = NULL, next_retry_at = NULL
+             WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    }
+
+    /// 记录失败；未超过最大重试次数时回到 pending 并在 next_retry_at 后重试
+    ///
+    /// 返回是否还会重试。
+    pub fn fail_sync_task(&self, id: i64, error: &str, next_retry_at: i64) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE sync_queue SET
+                retry_count = retry_count + 1,
+                error_message = ?1,
+                status = CASE WHEN retry_count + 1 >= max_retries THEN 'failed' ELSE 'pending' END,
+                completed_at = CASE WHEN retry_count + 1 >= max_retries THEN ?2 ELSE NULL END,
+                next_retry_at = CASE WHEN retry_count + 1 >= max_retries THEN NULL ELSE ?3 END
+             WHERE id = ?4",
+            params![error, now, next_retry_at, id],
+        )?;
+        let status: String = self.conn.query_row(
+            "SELECT status FROM sync_queue WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+        Ok(status == "pending")
+    }
+
+    /// 程序退出时正在执行的任务重新排队
+    pub fn reset_running_sync_tasks(&self) -> Result<usize> {
+        let count = self.conn.execute(
+            "UPDATE sync_queue SET status = 'pending' WHERE status = 'running'",
+            [],
+        )?;
         Ok(count)
     }
-    
-    /// 检查曲目是否被收藏
-    pub fn is_track_favorite(&self, track_id: i64) -> Result<bool> {
-        self.is_favorite(track_id)
+
+    // ========== 续播位置 ==========
+
+    /// 保存曲目的续播位置
+    pub fn save_track_position(&self, track_id: i64, position_ms: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_positions (track_id, position_ms, updated_at)
+             VALUES (?1, ?2, strftime('%s', 'now'))
+             ON CONFLICT(track_id) DO UPDATE SET position_ms = excluded.position_ms, updated_at = excluded.updated_at",
+            params![track_id, position_ms],
+        )?;
+        Ok(())
     }
-    
-    /// 🔧 P2新增：使用SQL WHERE子句查询曲目（智能歌单优化）
-    pub fn query_tracks_by_smart_rules(
-        &self,
-        where_clause: &str,
-        params: &[String],
-        limit: Option<u32>,
-    ) -> Result<Vec<Track>> {
-        let limit_clause = limit.map(|l| format!(" LIMIT {}", l)).unwrap_or_default();
-        
-        let sql = format!(
-            "SELECT id, path, title, artist, album, duration_ms, album_cover_data, album_cover_mime, artist_photo_data, artist_photo_mime, embedded_lyrics 
-             FROM tracks 
-             WHERE {} 
-             ORDER BY artist, album, title{}",
-            where_clause,
-            limit_clause
-        );
-        
-        let mut stmt = self.conn.prepare(&sql)?;
-        
-        // 转换参数为rusqlite可接受的格式
-        let rusqlite_params: Vec<&dyn rusqlite::ToSql> = params.iter()
-            .map(|p| p as &dyn rusqlite::ToSql)
-            .collect();
-        
-        let tracks = stmt.query_map(rusqlite_params.as_slice(), |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                title: row.get(2).ok(),
-                artist: row.get(3).ok(),
-                album: row.get(4).ok(),
-                duration_ms: row.get(5).ok(),
-                album_cover_data: row.get(6).ok(),
-                album_cover_mime: row.get(7).ok(),
-                artist_photo_data: row.get(8).ok(),
-                artist_photo_mime: row.get(9).ok(),
-                embedded_lyrics: row.get(10).ok(),
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        
-        Ok(tracks)
+
+    pub fn get_track_position(&self, track_id: i64) -> Result<Option<SavedPosition>> {
+        let position = self.conn.query_row(
+            "SELECT track_id, position_ms, updated_at FROM track_positions WHERE track_id = ?1",
+            [track_id],
+            |row| Ok(SavedPosition { track_id: row.get(0)?, position_ms: row.get(1)?, updated_at: row.get(2)? }),
+        ).optional()?;
+        Ok(position)
+    }
+
+    pub fn clear_track_position(&self, track_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM track_positions WHERE track_id = ?1", [track_id])?;
+        Ok(())
+    }
+
+    /// 保存曲目的播放速度
+    pub fn save_track_rate(&self, track_id: i64, rate: f32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO track_playback_rates (track_id, rate, updated_at)
+             VALUES (?1, ?2, strftime('%s', 'now'))
+             ON CONFLICT(track_id) DO UPDATE SET rate = excluded.rate, updated_at = excluded.updated_at",
+            params![track_id, rate as f64],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_track_rate(&self, track_id: i64) -> Result<Option<f32>> {
+        let rate: Option<f64> = self.conn.query_row(
+            "SELECT rate FROM track_playback_rates WHERE track_id = ?1",
+            [track_id],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(rate.map(|r| r as f32))
+    }
+
+    pub fn clear_track_rate(&self, track_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM track_playback_rates WHERE track_id = ?1", [track_id])?;
+        Ok(())
+    }
+
+    // ========== 多艺术家 ==========
+
+    fn write_track_artists(conn: &Connection, track_id: i64, artists: &[TrackArtist]) -> Result<()> {
+        conn.execute("DELETE FROM track_artists WHERE track_id = ?1", [track_id])?;
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO track_artists (track_id, position, artist, role, sort_key) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        for (position, artist) in artists.iter().enumerate() {
+            stmt.execute(params![
+                track_id,
+                position as i64,
+                artist.name,
+                artist.role.as_str(),
+                sort_key::sort_key(&artist.name)
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// 当前的艺术家拆分设置
+    pub fn artist_split_config(&self) -> ArtistSplitConfig {
+        let mut cached = match self.artist_split.lock() {
+            Ok(cached) => cached,
+            Err(_) => return crate::app_config::load_artist_split_config(self),
+        };
+        cached.get_or_insert_with(|| crate::app_config::load_artist_split_config(self)).clone()
+    }
+
+    /// 所有曲目的ID和原始艺术家标签，用于重新拆分
+    pub fn get_track_artist_tags(&self) -> Result<Vec<(i64, Option<String>)>> {
+        let mut stmt = self.conn.prepare("SELECT id, artist FROM tracks ORDER BY id")?;
+        let tags = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// 在一个事务内重新拆分一批曲目
+    pub fn rebuild_track_artists(&self, tags: &[(i64, Option<String>)], config: &ArtistSplitConfig) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (track_id, artist) in tags {
+            let artists = artist_split::split_artists(artist.as_deref().unwrap_or_default(), config);
+            Self::write_track_artists(&tx, *track_id, &artists)?;
+        }
+        // 其他连接上缓存的艺术家列表随之失效
+        tx.execute("UPDATE cache_meta SET version = version + 1 WHERE table_name = 'tracks'", [])?;
+        tx.commit()?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(())
+    }
+
+    /// 曲目拆分后的艺术家（按标签中的顺序）
+    pub fn get_track_artists(&self, track_id: i64) -> Result<Vec<TrackArtist>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT artist, role FROM track_artists WHERE track_id = ?1 ORDER BY position"
+        )?;
+        let artists = stmt.query_map([track_id], |row| {
+            Ok(TrackArtist { name: row.get(0)?, role: ArtistRole::parse(&row.get::<_, String>(1)?) })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(artists)
+    }
+
+    // ========== 排序键 ==========
+
+    /// 是否把中日文转写写入全文索引（默认开启）
+    pub fn transliterate_search(&self) -> bool {
+        let load = || {
+            // 首次建库时设置表可能还不存在，按默认值处理
+            let value = self.get_setting(sort_key::SETTING_TRANSLITERATE_SEARCH).ok().flatten();
+            value.as_deref() != Some("false")
+        };
+        match self.transliterate_search.lock() {
+            Ok(mut cached) => *cached.get_or_insert_with(load),
+            Err(_) => load(),
+        }
+    }
+
+    /// 开启或关闭转写搜索，并为所有曲目重新计算（全文索引由 tracks_au 触发器同步），返回曲目数
+    pub fn set_transliterate_search(&self, enabled: bool) -> Result<usize> {
+        self.set_setting(sort_key::SETTING_TRANSLITERATE_SEARCH, if enabled { "true" } else { "false" })?;
+        self.rebuild_sort_keys()
+    }
+
+    fn write_sort_keys(conn: &Connection, track_id: i64, keys: &TrackSortKeys) -> Result<()> {
+        conn.prepare_cached(
+            "UPDATE tracks SET sort_title = ?1, sort_artist = ?2, sort_album = ?3, translit = ?4 WHERE id = ?5"
+        )?
+        .execute(params![keys.title, keys.artist, keys.album, keys.translit, track_id])?;
+        Ok(())
+    }
+
+    /// 按曲目当前的标签重新计算排序键
+    fn refresh_sort_keys(conn: &Connection, track_id: i64, transliterate: bool) -> Result<()> {
+        let keys = conn.query_row(
+            "SELECT title, artist, album, album_artist FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| {
+                Ok(TrackSortKeys::compute(
+                    row.get::<_, Option<String>>(0)?.as_deref(),
+                    row.get::<_, Option<String>>(1)?.as_deref(),
+                    row.get::<_, Option<String>>(2)?.as_deref(),
+                    row.get::<_, Option<String>>(3)?.as_deref(),
+                    transliterate,
+                ))
+            },
+        )?;
+        Self::write_sort_keys(conn, track_id, &keys)
+    }
+
+    /// 在一个事务内为所有曲目重新计算排序键和转写，返回曲目数
+    pub fn rebuild_sort_keys(&self) -> Result<usize> {
+        let transliterate = self.transliterate_search();
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = self.conn.prepare("SELECT id FROM tracks ORDER BY id")?;
+        let ids = stmt.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        for &track_id in &ids {
+            Self::refresh_sort_keys(&tx, track_id, transliterate)?;
+        }
+        tx.commit()?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(ids.len())
+    }
+
+    // ========== 扫描问题 ==========
+
+    /// 记录曲目元数据提取失败的原因
+    pub fn save_scan_issue(&self, track_id: i64, reason: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO scan_issues (track_id, reason, detected_at)
+             VALUES (?1, ?2, strftime('%s', 'now'))
+             ON CONFLICT(track_id) DO UPDATE SET reason = excluded.reason, detected_at = excluded.detected_at",
+            params![track_id, reason],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_scan_issue(&self, track_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM scan_issues WHERE track_id = ?1", [track_id])?;
+        Ok(())
+    }
+
+    /// 元数据不完整的曲目，按路径排序
+    pub fn get_scan_issues(&self) -> Result<Vec<ScanIssue>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.track_id, t.path, t.title, s.reason, s.detected_at
+             FROM scan_issues s JOIN tracks t ON t.id = s.track_id
+             ORDER BY t.path"
+        )?;
+        let issues = stmt.query_map([], |row| {
+            Ok(ScanIssue {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                reason: row.get(3)?,
+                detected_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(issues)
+    }
+
+    // ========== 完整性校验 ==========
+
+    /// 待校验的本地曲目（ID、路径）：track_ids 为 None 时只取从未校验或文件变化后未重新校验的曲目
+    pub fn get_tracks_to_verify(&self, track_ids: Option<&[i64]>) -> Result<Vec<(i64, String)>> {
+        let local = "path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%' AND COALESCE(source_type, 'local') != 'webdav'";
+        let rows = match track_ids {
+            None => {
+                let mut stmt = self.conn.prepare(&format!(
+                    "SELECT id, path FROM tracks WHERE verified_at IS NULL AND {} ORDER BY id",
+                    local
+                ))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                rows
+            }
+            Some(ids) => {
+                let mut stmt = self.conn.prepare(&format!("SELECT id, path FROM tracks WHERE id = ?1 AND {}", local))?;
+                let mut rows = Vec::with_capacity(ids.len());
+                for &id in ids {
+                    if let Some(row) = stmt.query_row([id], |row| Ok((row.get(0)?, row.get(1)?))).optional()? {
+                        rows.push(row);
+                    }
+                }
+                rows
+            }
+        };
+        Ok(rows)
+    }
+
+    /// 本地曲目的ID（重新校验整个曲库）
+    pub fn get_local_track_ids(&self) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM tracks
+             WHERE path NOT LIKE 'webdav://%' AND path NOT LIKE 'subsonic://%' AND COALESCE(source_type, 'local') != 'webdav'
+             ORDER BY id"
+        )?;
+        let ids = stmt.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        Ok(ids)
+    }
+
+    /// 记录曲目的校验结果，返回曲目是否存在
+    pub fn save_verify_result(&self, track_id: i64, result: &VerifyResult) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE tracks SET verify_status = ?1, verify_detail = ?2, verified_at = strftime('%s', 'now') WHERE id = ?3",
+            params![result.status.as_str(), result.detail, track_id],
+        )?;
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.invalidate_track_related();
+        }
+        Ok(updated > 0)
+    }
+
+    /// 校验未通过的曲目，按路径排序
+    pub fn get_verify_issues(&self) -> Result<Vec<VerifyIssue>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, title, verify_status, verify_detail, verified_at FROM tracks
+             WHERE verify_status IS NOT NULL AND verify_status != 'ok'
+             ORDER BY path"
+        )?;
+        let issues = stmt.query_map([], |row| {
+            Ok(VerifyIssue {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                status: row.get(3)?,
+                detail: row.get(4)?,
+                verified_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(issues)
+    }
+
+    // ========== 章节 ==========
+
+    /// 替换曲目的章节（扫描时重新提取）
+    pub fn replace_track_chapters(&self, track_id: i64, chapters: &[Chapter]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM chapters WHERE track_id = ?1", [track_id])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO chapters (track_id, idx, title, start_ms, end_ms) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for chapter in chapters {
+                stmt.execute(params![
+                    track_id,
+                    chapter.index,
+                    chapter.title,
+                    chapter.start_ms as i64,
+                    chapter.end_ms.map(|end| end as i64)
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 曲目的章节（按序号）
+    pub fn get_track_chapters(&self, track_id: i64) -> Result<Vec<Chapter>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT idx, title, start_ms, end_ms FROM chapters WHERE track_id = ?1 ORDER BY idx"
+        )?;
+        let chapters = stmt.query_map([track_id], |row| {
+            Ok(Chapter {
+                index: row.get(0)?,
+                title: row.get(1)?,
+                start_ms: row.get::<_, i64>(2)? as u64,
+                end_ms: row.get::<_, Option<i64>>(3)?.map(|end| end as u64),
+            })
+        })?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(chapters)
+    }
+
+    // ========== Scrobble队列 ==========
+
+    /// 播放记录加入提交队列
+    pub fn enqueue_scrobble(&self, service: &str, payload: &str, listened_at: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO scrobble_queue (service, payload, listened_at) VALUES (?1, ?2, ?3)",
+            params![service, payload, listened_at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// 已到重试时间的播放记录（按收听时间先后）
+    pub fn due_scrobbles(&self, service: &str, now: i64, limit: i64) -> Result<Vec<QueuedScrobble>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, payload, attempts FROM scrobble_queue
+             WHERE service = ?1 AND next_attempt_at <= ?2
+             ORDER BY listened_at, id
+             LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![service, now, limit], |row| {
+            Ok(QueuedScrobble {
+                id: row.get(0)?,
+                payload: row.get(1)?,
+                attempts: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 删除已提交（或被服务拒绝）的播放记录
+    pub fn delete_scrobbles(&self, ids: &[i64]) -> Result<()> {
+        let mut stmt = self.conn.prepare("DELETE FROM scrobble_queue WHERE id = ?1")?;
+        for id in ids {
+            stmt.execute([id])?;
+        }
+        Ok(())
+    }
+
+    /// 提交失败，记录错误并推迟到 next_attempt_at 后重试
+    pub fn defer_scrobbles(&self, ids: &[i64], next_attempt_at: i64, error: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(
+            "UPDATE scrobble_queue SET attempts = attempts + 1, next_attempt_at = ?1, last_error = ?2
+             WHERE id = ?3",
+        )?;
+        for id in ids {
+            stmt.execute(params![next_attempt_at, error, id])?;
+        }
+        Ok(())
+    }
+
+    /// 队列中等待提交的记录数
+    pub fn scrobble_queue_depth(&self, service: &str) -> Result<i64> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM scrobble_queue WHERE service = ?1",
+            [service],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// 最早的重试时间，用于空闲时决定等待多久
+    pub fn next_scrobble_attempt_at(&self, service: &str) -> Result<Option<i64>> {
+        let at = self.conn.query_row(
+            "SELECT MIN(next_attempt_at) FROM scrobble_queue WHERE service = ?1",
+            [service],
+            |row| row.get(0),
+        )?;
+        Ok(at)
+    }
+
+    /// 更新曲目的同步状态
+    pub fn set_track_sync_status(&self, track_id: i64, status: &str, server_id: Option<&str>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE tracks SET sync_status = ?1,
+                last_sync = CASE WHEN ?1 = 'synced' THEN ?2 ELSE last_sync END,
+                server_id = COALESCE(?3, server_id)
+             WHERE id = ?4",
+            params![status, now, server_id, track_id],
+        )?;
+        Ok(())
+    }
+
+    /// 上传/下载成功后记录同步基线
+    pub fn record_track_sync(
+        &self,
+        track_id: i64,
+        server_id: &str,
+        remote_etag: Option<&str>,
+        remote_modified: Option<i64>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE tracks SET sync_status = 'synced', last_sync = ?1, server_id = ?2,
+                remote_etag = ?3, remote_modified = ?4
+             WHERE id = ?5",
+            params![now, server_id, remote_etag, remote_modified, track_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_sync_baseline(&self, track_id: i64) -> Result<SyncBaseline> {
+        let baseline = self.conn.query_row(
+            "SELECT last_sync, remote_etag, remote_modified FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| Ok(SyncBaseline {
+                last_sync: row.get(0)?,
+                remote_etag: row.get(1)?,
+                remote_modified: row.get(2)?,
+            }),
+        ).optional()?;
+        Ok(baseline.unwrap_or_default())
+    }
+
+    // ========== 同步冲突 ==========
+
+    const SYNC_CONFLICT_COLUMNS: &'static str = "id, track_id, server_id, conflict_type, local_path, remote_path, \
+        local_size, remote_size, local_modified, remote_modified, local_hash, remote_hash, \
+        resolution_strategy, resolved, resolved_at, created_at";
+
+    fn row_to_sync_conflict(row: &rusqlite::Row) -> rusqlite::Result<SyncConflict> {
+        Ok(SyncConflict {
+            id: row.get(0)?,
+            track_id: row.get(1)?,
+            server_id: row.get(2)?,
+            conflict_type: row.get(3)?,
+            local_path: row.get(4)?,
+            remote_path: row.get(5)?,
+            local_size: row.get(6)?,
+            remote_size: row.get(7)?,
+            local_modified: row.get(8)?,
+            remote_modified: row.get(9)?,
+            local_hash: row.get(10)?,
+            remote_hash: row.get(11)?,
+            resolution_strategy: row.get(12)?,
+            resolved: row.get(13)?,
+            resolved_at: row.get(14)?,
+            created_at: row.get(15)?,
+        })
+    }
+
+    /// 记录冲突；同一曲目在同一服务器上已有未解决的冲突时更新该记录
+    ///
+    /// 曲目的 sync_status 同时置为 conflict。
+    pub fn add_sync_conflict(&self, conflict: &SyncConflict) -> Result<i64> {
+        let existing: Option<i64> = self.conn.query_row(
+            "SELECT id FROM sync_conflicts WHERE track_id = ?1 AND server_id = ?2 AND resolved = 0",
+            params![conflict.track_id, conflict.server_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let id = match existing {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE sync_conflicts SET conflict_type = ?1, local_path = ?2, remote_path = ?3,
+                        local_size = ?4, remote_size = ?5, local_modified = ?6, remote_modified = ?7,
+                        local_hash = ?8, remote_hash = ?9
+                     WHERE id = ?10",
+                    params![
+                        conflict.conflict_type, conflict.local_path, conflict.remote_path,
+                        conflict.local_size, conflict.remote_size, conflict.local_modified, conflict.remote_modified,
+                        conflict.local_hash, conflict.remote_hash, id
+                    ],
+                )?;
+                id
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO sync_conflicts
+                     (track_id, server_id, conflict_type, local_path, remote_path, local_size, remote_size,
+                      local_modified, remote_modified, local_hash, remote_hash)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        conflict.track_id, conflict.server_id, conflict.conflict_type,
+                        conflict.local_path, conflict.remote_path, conflict.local_size, conflict.remote_size,
+                        conflict.local_modified, conflict.remote_modified, conflict.local_hash, conflict.remote_hash
+                    ],
+                )?;
+                self.conn.last_insert_rowid()
+            }
+        };
+
+        self.set_track_sync_status(conflict.track_id, "conflict", Some(&conflict.server_id))?;
+        Ok(id)
+    }
+
+    pub fn get_sync_conflict(&self, id: i64) -> Result<Option<SyncConflict>> {
+        let conflict = self.conn.query_row(
+            &format!("SELECT {} FROM sync_conflicts WHERE id = ?1", Self::SYNC_CONFLICT_COLUMNS),
+            [id],
+            Self::row_to_sync_conflict,
+        ).optional()?;
+        Ok(conflict)
+    }
+
+    /// 冲突列表（最新的在前）
+    pub fn get_sync_conflicts(&self, include_resolved: bool) -> Result<Vec<SyncConflict>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sync_conflicts WHERE resolved = 0 OR ?1 ORDER BY created_at DESC, id DESC",
+            Self::SYNC_CONFLICT_COLUMNS
+        ))?;
+        let conflicts = stmt.query_map([include_resolved], Self::row_to_sync_conflict)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(conflicts)
+    }
+
+    /// 标记冲突已解决并更新曲目的同步状态
+    pub fn resolve_sync_conflict(&self, id: i64, strategy: &str, track_sync_status: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE sync_conflicts SET resolved = 1, resolved_at = ?1, resolution_strategy = ?2 WHERE id = ?3",
+            params![now, strategy, id],
+        )?;
+        self.conn.execute(
+            "UPDATE tracks SET sync_status = ?1
+             WHERE id = (SELECT track_id FROM sync_conflicts WHERE id = ?2)",
+            params![track_sync_status, id],
+        )?;
+        Ok(())
+    }
+
+    /// 未执行的任务因冲突取消
+    pub fn cancel_sync_task(&self, id: i64, reason: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE sync_queue SET status = 'cancelled', error_message = ?1, completed_at = ?2 WHERE id = ?3",
+            params![reason, now, id],
+        )?;
+        Ok(())
+    }
+
+    // ========== 播放历史管理 ==========
+
+    /// 记录播放历史（命令写入或导入）
+    pub fn add_play_history(&self, track_id: i64, duration_played_ms: i64) -> Result<()> {
+        self.add_play_history_with_source(track_id, duration_played_ms, crate::play_history::PLAY_SOURCE_MANUAL)
+    }
+
+    /// 记录播放历史并标记来源
+    pub fn add_play_history_with_source(&self, track_id: i64, duration_played_ms: i64, source: &str) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        
+        self.conn.execute(
+            "INSERT INTO play_history (track_id, played_at, duration_played_ms, source) VALUES (?1, ?2, ?3, ?4)",
+            params![track_id, now, duration_played_ms, source],
+        )?;
+        Ok(())
+    }
+
+    /// 曲目的播放次数
+    pub fn get_play_count(&self, track_id: i64) -> Result<i64> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM play_history WHERE track_id = ?1",
+            [track_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// 获取播放历史（带统计）
+    pub fn get_play_history(&self, sort_by: &str, limit: i64) -> Result<Vec<(Track, i64, i64, i64)>> {
+        let order_clause = match sort_by {
+            "play_count" => "play_count DESC, last_played DESC",
+            "first_played" => "first_played ASC",
+            _ => "last_played DESC", // default: last_played
+        };
+        
+        let sql = format!(
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.duration_ms,
+                    COUNT(ph.id) as play_count,
+                    MAX(ph.played_at) as last_played,
+                    MIN(ph.played_at) as first_played, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status
+             FROM tracks t
+             INNER JOIN play_history ph ON t.id = ph.track_id
+             GROUP BY t.id
+             ORDER BY {}
+             LIMIT ?1",
+            order_clause
+        );
+        
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([limit], |row| {
+            Ok((
+                Track {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    title: row.get(2).ok(),
+                    artist: row.get(3).ok(),
+                    album: row.get(4).ok(),
+                    duration_ms: row.get(5).ok(),
+                    album_cover_data: None,
+                    album_cover_mime: None,
+                    artist_photo_data: None,
+                    artist_photo_mime: None,
+                    embedded_lyrics: None,
+                    genre: row.get(9).ok(),
+                    year: row.get(10).ok(),
+                    track_number: row.get(11).ok(),
+                    disc_number: row.get(12).ok(),
+                    album_artist: row.get(13).ok(),
+                    cover_id: row.get(14).ok(),
+                    rating: row.get(15).ok(),
+                    verify_status: row.get(16).ok(),
+                },
+                row.get(6)?, // play_count
+                row.get(7)?, // last_played
+                row.get(8)?, // first_played
+            ))
+        })?;
+        
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// 获取播放统计
+    pub fn get_play_statistics(&self) -> Result<(i64, i64, i64)> {
+        let total_plays: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM play_history",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        let unique_tracks: i64 = self.conn.query_row(
+            "SELECT COUNT(DISTINCT track_id) FROM play_history",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        // 使用实际播放时长而非曲目完整时长
+        let total_duration_ms: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(duration_played_ms), 0) FROM play_history",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        Ok((total_plays, unique_tracks, total_duration_ms))
+    }
+
+    /// 清空播放历史
+    pub fn clear_play_history(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM play_history", [])?;
+        Ok(())
+    }
+
+    /// 从历史中删除某曲目（预留功能）
+    #[allow(dead_code)]
+    pub fn remove_from_history(&self, track_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM play_history WHERE track_id = ?1",
+            params![track_id],
+        )?;
+        Ok(())
+    }
+    
+    // 🔧 P2新增：播放历史相关的查询方法
+    
+    /// 删除指定时间之前的播放历史（预留功能）
+    #[allow(dead_code)]
+    pub fn delete_play_history_before(&self, timestamp: i64) -> Result<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM play_history WHERE played_at < ?1",
+            params![timestamp],
+        )?;
+        Ok(deleted)
+    }
+    
+    /// 获取最近播放历史（返回PlayHistoryEntry结构，预留功能）
+    #[allow(dead_code)]
+    pub fn get_recent_play_history(&self, limit: usize) -> Result<Vec<crate::play_history::PlayHistoryEntry>> {
+        let history_data = self.get_play_history("last_played", limit as i64)?;
+        
+        Ok(history_data.into_iter().map(|(track, play_count, last_played, first_played)| {
+            crate::play_history::PlayHistoryEntry {
+                track,
+                play_count,
+                last_played_at: last_played,
+                first_played_at: first_played,
+            }
+        }).collect())
+    }
+    
+    /// 🔧 修复：获取播放统计信息（返回PlayStatistics结构，预留功能）
+    #[allow(dead_code)]
+    pub fn get_play_statistics_struct(&self) -> Result<crate::play_history::PlayStatistics> {
+        let (total_plays, unique_tracks, total_duration_ms) = self.get_play_statistics()?;
+        Ok(crate::play_history::PlayStatistics {
+            total_plays,
+            unique_tracks,
+            total_duration_ms,
+        })
+    }
+    
+    // ========== 收听统计 ==========
+
+    /// 统计范围的起点（UNIX秒）
+    fn stats_since(range: crate::play_history::StatsRange) -> i64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        range.since(now)
+    }
+
+    /// 播放次数最多的艺术家
+    pub fn stats_top_artists(&self, range: crate::play_history::StatsRange, limit: i64) -> Result<Vec<crate::play_history::ArtistPlayStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.artist, COUNT(*) AS play_count, COUNT(DISTINCT ph.track_id),
+                    COALESCE(SUM(ph.duration_played_ms), 0)
+             FROM play_history ph
+             INNER JOIN tracks t ON t.id = ph.track_id
+             WHERE ph.played_at >= ?1 AND t.artist IS NOT NULL AND t.artist != ''
+             GROUP BY t.artist
+             ORDER BY play_count DESC, t.artist
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![Self::stats_since(range), limit], |row| {
+            Ok(crate::play_history::ArtistPlayStats {
+                artist: row.get(0)?,
+                play_count: row.get(1)?,
+                unique_tracks: row.get(2)?,
+                total_duration_ms: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 播放次数最多的专辑（按专辑名和专辑艺术家分组，缺少专辑艺术家时使用艺术家）
+    pub fn stats_top_albums(&self, range: crate::play_history::StatsRange, limit: i64) -> Result<Vec<crate::play_history::AlbumPlayStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.album, COALESCE(NULLIF(t.album_artist, ''), t.artist) AS group_artist,
+                    COUNT(*) AS play_count, COALESCE(SUM(ph.duration_played_ms), 0), MAX(t.cover_id)
+             FROM play_history ph
+             INNER JOIN tracks t ON t.id = ph.track_id
+             WHERE ph.played_at >= ?1 AND t.album IS NOT NULL AND t.album != ''
+             GROUP BY t.album, group_artist
+             ORDER BY play_count DESC, t.album
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![Self::stats_since(range), limit], |row| {
+            Ok(crate::play_history::AlbumPlayStats {
+                album: row.get(0)?,
+                album_artist: row.get(1)?,
+                play_count: row.get(2)?,
+                total_duration_ms: row.get(3)?,
+                cover_id: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 每日播放次数和收听时长（本地日期，按日期升序，没有播放的日期不返回）
+    pub fn stats_plays_per_day(&self, range: crate::play_history::StatsRange) -> Result<Vec<crate::play_history::DailyPlayStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date(played_at, 'unixepoch', 'localtime') AS day, COUNT(*),
+                    COALESCE(SUM(duration_played_ms), 0)
+             FROM play_history
+             WHERE played_at >= ?1
+             GROUP BY day
+             ORDER BY day",
+        )?;
+        let rows = stmt.query_map(params![Self::stats_since(range)], |row| {
+            Ok(crate::play_history::DailyPlayStats {
+                date: row.get(0)?,
+                play_count: row.get(1)?,
+                duration_ms: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 按本地时间小时统计播放次数，总是返回0-23点共24项
+    pub fn stats_listening_clock(&self, range: crate::play_history::StatsRange) -> Result<Vec<crate::play_history::HourlyPlayStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST(strftime('%H', played_at, 'unixepoch', 'localtime') AS INTEGER) AS hour, COUNT(*)
+             FROM play_history
+             WHERE played_at >= ?1
+             GROUP BY hour",
+        )?;
+        let mut clock: Vec<_> = (0..24)
+            .map(|hour| crate::play_history::HourlyPlayStats { hour, play_count: 0 })
+            .collect();
+        let rows = stmt.query_map(params![Self::stats_since(range)], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (hour, play_count) = row?;
+            if let Some(bucket) = clock.get_mut(hour as usize) {
+                bucket.play_count = play_count;
+            }
+        }
+        Ok(clock)
+    }
+    
+    // 🔧 P2新增：智能歌单扩展字段查询（智能歌单刷新已改为JOIN查询，单曲查询保留备用）
+    
+    /// 获取曲目的添加时间
+    #[allow(dead_code)]
+    pub fn get_track_date_added(&self, track_id: i64) -> Result<Option<i64>> {
+        // 从tracks表的created_at字段或文件系统时间获取
+        let timestamp: Option<i64> = self.conn.query_row(
+            "SELECT created_at FROM tracks WHERE id = ?1",
+            params![track_id],
+            |row| row.get(0),
+        ).optional()?;
+        
+        Ok(timestamp)
+    }
+    
+    /// 获取曲目的最后播放时间
+    #[allow(dead_code)]
+    pub fn get_track_last_played(&self, track_id: i64) -> Result<Option<i64>> {
+        let timestamp: Option<i64> = self.conn.query_row(
+            "SELECT MAX(played_at) FROM play_history WHERE track_id = ?1",
+            params![track_id],
+            |row| row.get(0),
+        ).optional()?;
+        
+        Ok(timestamp)
+    }
+    
+    /// 获取曲目的播放次数
+    #[allow(dead_code)]
+    pub fn get_track_play_count(&self, track_id: i64) -> Result<i64> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM play_history WHERE track_id = ?1",
+            params![track_id],
+            |row| row.get(0),
+        ).unwrap_or(0);
+        
+        Ok(count)
+    }
+    
+    /// 检查曲目是否被收藏
+    #[allow(dead_code)]
+    pub fn is_track_favorite(&self, track_id: i64) -> Result<bool> {
+        self.is_favorite(track_id)
+    }
+    
+    /// 🔧 P2新增：按智能歌单规则生成的查询获取曲目
+    pub fn query_tracks_by_smart_rules(&self, query: &SmartQuery) -> Result<Vec<Track>> {
+        let sql = query.to_sql(
+            "t.id, t.path, t.title, t.artist, t.album, t.duration_ms, t.embedded_lyrics, t.genre, t.year, t.track_number, t.disc_number, t.album_artist, t.cover_id, t.rating, t.verify_status"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rusqlite_params = rusqlite::params_from_iter(query.params.iter());
+        
+        let tracks = stmt.query_map(rusqlite_params, |row| {
+            Ok(Track {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2).ok(),
+                artist: row.get(3).ok(),
+                album: row.get(4).ok(),
+                duration_ms: row.get(5).ok(),
+                album_cover_data: None,
+                album_cover_mime: None,
+                artist_photo_data: None,
+                artist_photo_mime: None,
+                embedded_lyrics: row.get(6).ok(),
+                genre: row.get(7).ok(),
+                year: row.get(8).ok(),
+                track_number: row.get(9).ok(),
+                disc_number: row.get(10).ok(),
+                album_artist: row.get(11).ok(),
+                cover_id: row.get(12).ok(),
+                rating: row.get(13).ok(),
+                verify_status: row.get(14).ok(),
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(tracks)
+    }
+
+    // ========== 曲库备份 ==========
+
+    /// 曲目识别信息（用于备份和还原时匹配曲目）
+    fn get_track_identities(&self) -> Result<Vec<BackupTrack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, path, title, artist, album, duration_ms, file_hash, rating FROM tracks ORDER BY id"
+        )?;
+        let tracks = stmt.query_map([], |row| {
+            Ok(BackupTrack {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                duration_ms: row.get(5)?,
+                file_hash: row.get(6)?,
+                rating: row.get(7)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(tracks)
+    }
+
+    /// 导出曲库备份（不含音频和封面），include_credentials 为 false 时不导出远程服务器密码
+    pub fn export_library_backup(&self, include_credentials: bool) -> Result<LibraryBackup> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, color_theme, COALESCE(is_smart, 0), smart_rules, COALESCE(is_favorite, 0),
+                    COALESCE(is_pinned, 0), COALESCE(play_count, 0), last_played, created_at
+             FROM playlists ORDER BY id"
+        )?;
+        let mut playlists = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, BackupPlaylist {
+                name: row.get(1)?,
+                description: row.get(2)?,
+                color_theme: row.get(3)?,
+                is_smart: row.get::<_, i64>(4)? != 0,
+                smart_rules: row.get(5)?,
+                is_favorite: row.get::<_, i64>(6)? != 0,
+                is_pinned: row.get::<_, i64>(7)? != 0,
+                play_count: row.get(8)?,
+                last_played: row.get(9)?,
+                created_at: row.get(10)?,
+                items: Vec::new(),
+            }))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        let mut items_stmt = self.conn.prepare(
+            "SELECT track_id, added_at FROM playlist_items WHERE playlist_id = ?1 ORDER BY order_index"
+        )?;
+        for (id, playlist) in &mut playlists {
+            playlist.items = items_stmt.query_map([*id], |row| {
+                Ok(BackupPlaylistItem { track_id: row.get(0)?, added_at: row.get(1)? })
+            })?.collect::<Result<Vec<_>, _>>()?;
+        }
+
+        let mut stmt = self.conn.prepare("SELECT track_id, created_at FROM favorites ORDER BY id")?;
+        let favorites = stmt.query_map([], |row| {
+            Ok(BackupFavorite { track_id: row.get(0)?, created_at: row.get(1)? })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT track_id, played_at, COALESCE(duration_played_ms, 0), source FROM play_history ORDER BY id"
+        )?;
+        let play_history = stmt.query_map([], |row| {
+            Ok(BackupPlay {
+                track_id: row.get(0)?,
+                played_at: row.get(1)?,
+                duration_played_ms: row.get(2)?,
+                source: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = self.conn.prepare("SELECT track_id, content, format, source FROM lyrics ORDER BY track_id")?;
+        let lyrics = stmt.query_map([], |row| {
+            Ok(BackupLyrics {
+                track_id: row.get(0)?,
+                content: row.get(1)?,
+                format: row.get(2)?,
+                source: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let remote_servers = self.get_remote_servers()?
+            .into_iter()
+            .map(|(id, name, server_type, config_json, enabled)| BackupRemoteServer {
+                config_json: if include_credentials {
+                    config_json
+                } else {
+                    secrets::redact_config(&config_json).to_string()
+                },
+                id,
+                name,
+                server_type,
+                enabled,
+            })
+            .collect();
+
+        Ok(LibraryBackup {
+            version: library_backup::BACKUP_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            tracks: self.get_track_identities()?,
+            playlists: playlists.into_iter().map(|(_, playlist)| playlist).collect(),
+            favorites,
+            play_history,
+            lyrics,
+            remote_servers,
+        })
+    }
+
+    /// 把备份还原到当前曲库（一个事务内完成，失败时不留下部分数据）
+    pub fn import_library_backup(&self, backup: &LibraryBackup, options: &ImportOptions) -> Result<ImportReport> {
+        let matched = library_backup::match_tracks(&backup.tracks, &self.get_track_identities()?);
+        let mut report = ImportReport {
+            tracks_matched: matched.len(),
+            tracks_unmatched: backup.tracks.len() - matched.len(),
+            ..Default::default()
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        for playlist in &backup.playlists {
+            let existing: Option<i64> = tx.query_row(
+                "SELECT id FROM playlists WHERE name = ?1 ORDER BY id LIMIT 1",
+                [&playlist.name],
+                |row| row.get(0),
+            ).optional()?;
+            let playlist_id = match existing {
+                Some(id) if options.overwrite => {
+                    tx.execute(
+                        "UPDATE playlists SET description = ?1, color_theme = ?2, is_smart = ?3, smart_rules = ?4, is_favorite = ?5,
+                            is_pinned = ?6, play_count = ?7, last_played = ?8, updated_at = strftime('%s', 'now')
+                         WHERE id = ?9",
+                        params![playlist.description, playlist.color_theme, playlist.is_smart, playlist.smart_rules,
+                                playlist.is_favorite, playlist.is_pinned, playlist.play_count, playlist.last_played, id],
+                    )?;
+                    tx.execute("DELETE FROM playlist_items WHERE playlist_id = ?1", [id])?;
+                    report.playlists.merged += 1;
+                    id
+                }
+                _ => {
+                    let name = library_backup::unique_name(&playlist.name, |name| {
+                        tx.query_row("SELECT 1 FROM playlists WHERE name = ?1", [name], |_| Ok(()))
+                            .optional()
+                            .map_or(true, |found| found.is_some())
+                    });
+                    tx.execute(
+                        "INSERT INTO playlists (name, description, color_theme, is_smart, smart_rules, is_favorite, is_pinned,
+                            play_count, last_played, created_at, updated_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, COALESCE(?10, strftime('%s', 'now')), strftime('%s', 'now'))",
+                        params![name, playlist.description, playlist.color_theme, playlist.is_smart, playlist.smart_rules,
+                                playlist.is_favorite, playlist.is_pinned, playlist.play_count, playlist.last_played,
+                                playlist.created_at],
+                    )?;
+                    report.playlists.imported += 1;
+                    tx.last_insert_rowid()
+                }
+            };
+            for item in &playlist.items {
+                match matched.get(&item.track_id) {
+                    Some(&track_id) => {
+                        self.add_track_to_playlist_at(playlist_id, track_id, item.added_at)?;
+                        report.playlist_items.imported += 1;
+                    }
+                    None => report.playlist_items.skipped += 1,
+                }
+            }
+        }
+
+        for favorite in &backup.favorites {
+            let Some(&track_id) = matched.get(&favorite.track_id) else {
+                report.favorites.skipped += 1;
+                continue;
+            };
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO favorites (track_id, created_at) VALUES (?1, COALESCE(?2, strftime('%s', 'now')))",
+                params![track_id, favorite.created_at],
+            )?;
+            if inserted > 0 {
+                report.favorites.imported += 1;
+            } else {
+                report.favorites.merged += 1;
+            }
+        }
+
+        // 已有评分的曲目只在覆盖时替换
+        for track in backup.tracks.iter().filter(|track| track.rating.is_some()) {
+            let Some(&track_id) = matched.get(&track.id) else {
+                report.ratings.skipped += 1;
+                continue;
+            };
+            let current: Option<u8> = tx.query_row("SELECT rating FROM tracks WHERE id = ?1", [track_id], |row| row.get(0))?;
+            if current.is_some() && (!options.overwrite || current == track.rating) {
+                report.ratings.skipped += 1;
+                continue;
+            }
+            tx.execute("UPDATE tracks SET rating = ?1 WHERE id = ?2", params![track.rating, track_id])?;
+            if current.is_some() {
+                report.ratings.merged += 1;
+            } else {
+                report.ratings.imported += 1;
+            }
+        }
+
+        // 同一曲目同一时间的播放记录视为已存在（重复还原同一个备份不会重复计数）
+        for play in &backup.play_history {
+            let Some(&track_id) = matched.get(&play.track_id) else {
+                report.play_history.skipped += 1;
+                continue;
+            };
+            let exists = tx.query_row(
+                "SELECT 1 FROM play_history WHERE track_id = ?1 AND played_at = ?2",
+                params![track_id, play.played_at],
+                |_| Ok(()),
+            ).optional()?.is_some();
+            if exists {
+                report.play_history.merged += 1;
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO play_history (track_id, played_at, duration_played_ms, source) VALUES (?1, ?2, ?3, ?4)",
+                params![track_id, play.played_at, play.duration_played_ms, play.source],
+            )?;
+            report.play_history.imported += 1;
+        }
+
+        for lyrics in &backup.lyrics {
+            let Some(&track_id) = matched.get(&lyrics.track_id) else {
+                report.lyrics.skipped += 1;
+                continue;
+            };
+            let exists = self.get_lyrics_by_track_id(track_id)?.is_some();
+            if exists && !options.overwrite {
+                report.lyrics.skipped += 1;
+                continue;
+            }
+            self.insert_lyrics(track_id, &lyrics.content, &lyrics.format, &lyrics.source)?;
+            if exists {
+                report.lyrics.merged += 1;
+            } else {
+                report.lyrics.imported += 1;
+            }
+        }
+
+        if options.include_remote_servers {
+            for server in &backup.remote_servers {
+                let exists = tx.query_row("SELECT 1 FROM remote_servers WHERE id = ?1", [&server.id], |_| Ok(()))
+                    .optional()?
+                    .is_some();
+                if exists && !options.overwrite {
+                    report.remote_servers.skipped += 1;
+                    continue;
+                }
+                if exists {
+                    self.update_remote_server(&server.id, &server.name, &server.config_json)?;
+                    report.remote_servers.merged += 1;
+                } else {
+                    self.add_remote_server(&server.id, &server.name, &server.server_type, &server.config_json)?;
+                    report.remote_servers.imported += 1;
+                }
+                tx.execute(
+                    "UPDATE remote_servers SET enabled = ?1 WHERE id = ?2",
+                    params![server.enabled, server.id],
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        log::info!(
+            "曲库备份已还原: 匹配 {} 首曲目，未匹配 {} 首",
+            report.tracks_matched,
+            report.tracks_unmatched
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track_with_cover(path: &str, title: &str) -> Track {
+        let mut track = Track::new(0, path.to_string());
+        track.title = Some(title.to_string());
+        track.artist = Some("Artist".to_string());
+        track.album = Some("Album".to_string());
+        track.album_cover_data = Some(vec![0xFF; 1024]);
+        track.album_cover_mime = Some("image/jpeg".to_string());
+        track.artist_photo_data = Some(vec![0xAB; 512]);
+        track.artist_photo_mime = Some("image/png".to_string());
+        track
+    }
+
+    #[test]
+    fn test_browse_folder_mirrors_paths() {
+        let db = Database::new(":memory:").unwrap();
+        db.add_remote_server("srv1", "NAS", "webdav", "{}").unwrap();
+        for (path, duration_ms) in [
+            ("/music/Book/Disc 10/01.mp3", 1000),
+            ("/music/Book/Disc 2/10.mp3", 1000),
+            ("/music/Book/Disc 2/9.mp3", 2000),
+            ("/music/Book/Disc 2/01.mp3", 3000),
+            ("/music/Book/intro.mp3", 500),
+            ("C:\\Music\\Sets\\live.mp3", 4000),
+            ("webdav://srv1#/DJ/set1.mp3", 6000),
+        ] {
+            let mut track = track_with_cover(path, "T");
+            track.duration_ms = Some(duration_ms);
+            db.insert_track(&track).unwrap();
+        }
+
+        let root = db.browse_folder(None).unwrap();
+        let names: Vec<&str> = root.folders.iter().map(|folder| folder.name.as_str()).collect();
+        assert_eq!(names, vec!["Book", "NAS", "Sets"]);
+        assert_eq!(root.folders[0].track_count, 5);
+        assert_eq!(root.folders[0].total_duration_ms, 7500);
+        assert_eq!(root.folders[1].path, "webdav://srv1#");
+        assert_eq!(root.folders[2].path, "C:/Music/Sets");
+        assert!(root.tracks.is_empty());
+
+        let book = db.browse_folder(Some("/music/Book/")).unwrap();
+        let names: Vec<&str> = book.folders.iter().map(|folder| folder.name.as_str()).collect();
+        assert_eq!(names, vec!["Disc 2", "Disc 10"]);
+        assert_eq!(book.folders[0].track_count, 3);
+        assert_eq!(book.folders[0].total_duration_ms, 6000);
+        assert_eq!(book.tracks.len(), 1);
+        assert_eq!(book.tracks[0].path, "/music/Book/intro.mp3");
+
+        let disc = db.browse_folder(Some("/music/Book/Disc 2")).unwrap();
+        let paths: Vec<&str> = disc.tracks.iter().map(|track| track.path.as_str()).collect();
+        assert_eq!(paths, vec!["/music/Book/Disc 2/01.mp3", "/music/Book/Disc 2/9.mp3", "/music/Book/Disc 2/10.mp3"]);
+
+        assert_eq!(db.browse_folder(Some("C:\\Music\\Sets")).unwrap().tracks.len(), 1);
+        let server = db.browse_folder(Some("webdav://srv1#")).unwrap();
+        assert_eq!(server.folders[0].path, "webdav://srv1#/DJ");
+        assert_eq!(db.browse_folder(Some("webdav://srv1#/DJ")).unwrap().tracks.len(), 1);
+    }
+
+    #[test]
+    fn test_track_position_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/mix.flac", "Mix")).unwrap();
+        assert_eq!(db.get_track_position(id).unwrap(), None);
+
+        db.save_track_position(id, 60_000).unwrap();
+        db.save_track_position(id, 2_832_000).unwrap();
+        assert_eq!(db.get_track_position(id).unwrap().map(|p| p.position_ms), Some(2_832_000));
+
+        db.clear_track_position(id).unwrap();
+        assert_eq!(db.get_track_position(id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_track_rate_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/lecture.mp3", "Lecture")).unwrap();
+        assert_eq!(db.get_track_rate(id).unwrap(), None);
+
+        db.save_track_rate(id, 1.25).unwrap();
+        db.save_track_rate(id, 1.5).unwrap();
+        assert_eq!(db.get_track_rate(id).unwrap(), Some(1.5));
+
+        db.clear_track_rate(id).unwrap();
+        assert_eq!(db.get_track_rate(id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_track_artists_are_split() {
+        let db = Database::new(":memory:").unwrap();
+        for (path, artist, album) in [
+            ("/m/1.flac", "A feat. B", "One"),
+            ("/m/2.flac", "A & C", "Two"),
+            ("/m/3.flac", "Simon & Garfunkel", "Three"),
+        ] {
+            let mut track = track_with_cover(path, path);
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            db.insert_track(&track).unwrap();
+        }
+        let first = db.get_track_by_path("/m/1.flac").unwrap().unwrap();
+        // 曲目上仍显示原始标签
+        assert_eq!(first.artist.as_deref(), Some("A feat. B"));
+        assert_eq!(
+            db.get_track_artists(first.id).unwrap(),
+            vec![
+                TrackArtist { name: "A".to_string(), role: ArtistRole::Primary },
+                TrackArtist { name: "B".to_string(), role: ArtistRole::Featured },
+            ]
+        );
+
+        let names: Vec<String> = db.get_artists().unwrap().into_iter().map(|a| a.artist).collect();
+        assert_eq!(names, vec!["A", "B", "C", "Simon & Garfunkel"]);
+        assert_eq!(db.get_artists().unwrap()[0], ArtistSummary { artist: "A".to_string(), album_count: 2, track_count: 2 });
+        assert_eq!(db.get_artist_count().unwrap(), 4);
+        assert_eq!(db.get_artist_track_ids("B").unwrap(), vec![first.id]);
+
+        let result = db.search_library("album:One").unwrap();
+        let names: Vec<&str> = result.artists.iter().map(|a| a.artist.as_str()).collect();
+        assert_eq!(names, vec!["A", "B"]);
+
+        // 修改艺术家后重新拆分
+        let update = TrackMetadataUpdate { artist: Some("D; E".to_string()), ..Default::default() };
+        db.update_tracks_metadata(&[first.id, 999], &update).unwrap();
+        let names: Vec<String> = db.get_track_artists(first.id).unwrap().into_iter().map(|a| a.name).collect();
+        assert_eq!(names, vec!["D", "E"]);
+
+        // 修改不拆分的名称后重新拆分
+        let mut config = crate::app_config::AppConfig::default();
+        config.artists.exclusions.clear();
+        db.set_setting(crate::app_config::SETTING_APP_CONFIG, &serde_json::to_string(&config).unwrap()).unwrap();
+        db.rebuild_track_artists(&db.get_track_artist_tags().unwrap(), &db.artist_split_config()).unwrap();
+        assert_eq!(db.get_artist_track_ids("Garfunkel").unwrap().len(), 1);
+        assert!(db.get_artist_track_ids("Simon & Garfunkel").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cjk_sort_keys_and_transliteration_search() {
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (path, title, artist) in [
+            ("/m/1.flac", "晴天", "周杰伦"),
+            ("/m/2.flac", "Hello", "Adele"),
+            ("/m/3.flac", "十年", "陈奕迅"),
+            ("/m/4.flac", "Run", "Zara"),
+        ] {
+            let mut track = track_with_cover(path, title);
+            track.artist = Some(artist.to_string());
+            ids.push(db.insert_track(&track).unwrap());
+        }
+
+        // 中文按拼音与拉丁字母混排
+        let artists: Vec<String> = db.get_artists().unwrap().into_iter().map(|a| a.artist).collect();
+        assert_eq!(artists, vec!["Adele", "陈奕迅", "Zara", "周杰伦"]);
+        let page = db.get_tracks_page(0, 10, TrackSortField::Title, SortDirection::Asc, None).unwrap();
+        let titles: Vec<String> = page.tracks.into_iter().filter_map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Hello", "晴天", "Run", "十年"]);
+
+        // 拼音全拼和首字母都能搜到
+        assert_eq!(fts_hits(&db, "zhoujielun"), 1);
+        assert_eq!(fts_hits(&db, "zjl"), 1);
+        assert_eq!(db.search_tracks("qingtian").unwrap().len(), 1);
+
+        // 修改元数据后排序键和转写随之更新
+        let update = TrackMetadataUpdate { artist: Some("林俊杰".to_string()), ..Default::default() };
+        db.update_tracks_metadata(&[ids[0]], &update).unwrap();
+        assert_eq!(fts_hits(&db, "zhoujielun"), 0);
+        assert_eq!(fts_hits(&db, "linjunjie"), 1);
+        let artists: Vec<String> = db.get_artists().unwrap().into_iter().map(|a| a.artist).collect();
+        assert_eq!(artists, vec!["Adele", "陈奕迅", "林俊杰", "Zara"]);
+
+        // 关闭转写搜索后索引中不再有转写
+        assert_eq!(db.set_transliterate_search(false).unwrap(), 4);
+        assert!(!db.transliterate_search());
+        assert_eq!(fts_hits(&db, "linjunjie"), 0);
+        assert_eq!(fts_hits(&db, "Adele"), 1);
+    }
+
+    #[test]
+    fn test_scan_issues() {
+        let db = Database::new(":memory:").unwrap();
+        let broken = db.insert_track(&track_with_cover("/music/b.mp3", "B")).unwrap();
+        let other = db.insert_track(&track_with_cover("/music/a.mp3", "A")).unwrap();
+        assert!(db.get_scan_issues().unwrap().is_empty());
+
+        db.save_scan_issue(broken, "invalid frame header").unwrap();
+        db.save_scan_issue(other, "old reason").unwrap();
+        db.save_scan_issue(other, "bad tag").unwrap();
+        let issues = db.get_scan_issues().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].path.as_str(), issues[0].reason.as_str()), ("/music/a.mp3", "bad tag"));
+        assert_eq!(issues[1].title.as_deref(), Some("B"));
+
+        db.clear_scan_issue(other).unwrap();
+        db.delete_tracks_by_paths(&["/music/b.mp3".to_string()]).unwrap();
+        assert!(db.get_scan_issues().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verify_results() {
+        let db = Database::new(":memory:").unwrap();
+        let good = db.insert_track(&track_with_cover("/music/a.flac", "A")).unwrap();
+        let cut = db.insert_track(&track_with_cover("/music/b.flac", "B")).unwrap();
+        let remote = db.insert_track(&track_with_cover("webdav://srv#/c.flac", "C")).unwrap();
+        assert_eq!(db.get_tracks_to_verify(None).unwrap().len(), 2);
+        assert_eq!(db.get_tracks_to_verify(Some(&[cut, remote])).unwrap(), vec![(cut, "/music/b.flac".to_string())]);
+
+        let ok = VerifyResult { status: VerifyStatus::Ok, detail: None };
+        let truncated = VerifyResult { status: VerifyStatus::Truncated, detail: Some("只能解码 1000 毫秒".to_string()) };
+        assert!(db.save_verify_result(good, &ok).unwrap());
+        assert!(db.save_verify_result(cut, &truncated).unwrap());
+        assert!(!db.save_verify_result(9999, &ok).unwrap());
+        assert!(db.get_tracks_to_verify(None).unwrap().is_empty());
+
+        let issues = db.get_verify_issues().unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].track_id, issues[0].status), (cut, VerifyStatus::Truncated));
+        assert_eq!(db.get_track_by_id(cut).unwrap().unwrap().verify_status, Some(VerifyStatus::Truncated));
+
+        // 文件变化后校验结果作废
+        db.update_file_state("/music/b.flac", Some(100), 10, None).unwrap();
+        assert_eq!(db.get_track_by_id(cut).unwrap().unwrap().verify_status, None);
+        assert!(db.get_verify_issues().unwrap().is_empty());
+        db.update_file_state("/music/a.flac", None, 0, None).unwrap();
+        db.save_verify_result(good, &ok).unwrap();
+        db.update_file_state("/music/a.flac", None, 0, None).unwrap();
+        assert_eq!(db.get_track_by_id(good).unwrap().unwrap().verify_status, Some(VerifyStatus::Ok));
+        assert_eq!(db.get_tracks_to_verify(None).unwrap(), vec![(cut, "/music/b.flac".to_string())]);
+    }
+
+    #[test]
+    fn test_track_chapters_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/book.m4b", "Book")).unwrap();
+        assert!(db.get_track_chapters(id).unwrap().is_empty());
+
+        let chapters = vec![
+            Chapter { index: 0, title: Some("序章".to_string()), start_ms: 0, end_ms: Some(600_000) },
+            Chapter { index: 1, title: None, start_ms: 600_000, end_ms: None },
+        ];
+        db.replace_track_chapters(id, &chapters).unwrap();
+        assert_eq!(db.get_track_chapters(id).unwrap(), chapters);
+
+        db.replace_track_chapters(id, &chapters[..1]).unwrap();
+        assert_eq!(db.get_track_chapters(id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        use std::cmp::Ordering;
+        assert_eq!(natural_cmp("Disc 2", "Disc 10"), Ordering::Less);
+        assert_eq!(natural_cmp("track9", "Track10"), Ordering::Less);
+        assert_eq!(natural_cmp("01 a", "1 b"), Ordering::Less);
+        assert_eq!(natural_cmp("b", "A"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_replay_gain_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        assert_eq!(db.get_replay_gain(id).unwrap(), Some(ReplayGainInfo::default()));
+
+        let info = ReplayGainInfo { track_gain_db: Some(-6.5), album_gain_db: Some(-7.25), loudness_lufs: None };
+        db.update_replay_gain("/music/a.flac", &info).unwrap();
+        assert_eq!(db.get_replay_gain(id).unwrap(), Some(info));
+        assert_eq!(db.get_replay_gain(id + 100).unwrap(), None);
+    }
+
+    #[test]
+    fn test_loudness_analysis_skips_analyzed_tracks() {
+        let db = Database::new(":memory:").unwrap();
+        let a = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let b = db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        db.insert_track(&track_with_cover("webdav://server/c.flac", "Gamma")).unwrap();
+
+        assert_eq!(db.get_tracks_without_loudness().unwrap().len(), 2);
+
+        db.update_loudness(a, -9.5).unwrap();
+        assert_eq!(db.get_tracks_without_loudness().unwrap(), vec![(b, "/music/b.flac".to_string())]);
+        assert_eq!(db.get_replay_gain(a).unwrap().unwrap().loudness_lufs, Some(-9.5));
+
+        // 重新扫描不会清除分析结果
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        db.update_replay_gain("/music/a.flac", &ReplayGainInfo::default()).unwrap();
+        assert_eq!(db.get_replay_gain(a).unwrap().unwrap().loudness_lufs, Some(-9.5));
+    }
+
+    #[test]
+    fn test_trim_points_follow_threshold() {
+        let db = Database::new(":memory:").unwrap();
+        let a = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let b = db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        assert_eq!(db.get_trim_points(a).unwrap(), None);
+
+        let trim = TrimPoints { start_ms: 4_800, end_ms: Some(185_200) };
+        db.update_trim_points(a, -60.0, &trim).unwrap();
+        db.update_trim_points(b, -60.0, &TrimPoints::default()).unwrap();
+        assert_eq!(db.get_trim_points(a).unwrap(), Some(trim));
+        assert_eq!(db.get_trim_points(b).unwrap(), Some(TrimPoints::default()));
+        assert!(db.get_tracks_without_trim_points(-60.0).unwrap().is_empty());
+
+        // 修改阈值后需要重新分析
+        assert_eq!(db.get_tracks_without_trim_points(-50.0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_remote_file_states_are_per_server() {
+        let db = Database::new(":memory:").unwrap();
+        db.insert_track(&track_with_cover("webdav://nas#/music/a.flac", "Alpha")).unwrap();
+        db.insert_track(&track_with_cover("webdav://nas2#/music/b.flac", "Beta")).unwrap();
+        db.insert_track(&track_with_cover("/music/c.flac", "Gamma")).unwrap();
+
+        let state = RemoteFileState { mtime: Some(1_700_000_000), size: Some(1024), etag: Some("abc".into()) };
+        db.update_remote_file_state("webdav://nas#/music/a.flac", &state).unwrap();
+
+        let states = db.get_remote_file_states("nas").unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states["webdav://nas#/music/a.flac"], state);
+        assert_eq!(db.get_remote_file_states("nas2").unwrap()["webdav://nas2#/music/b.flac"], RemoteFileState::default());
+        // 本地曲目的增量扫描状态不受影响
+        assert_eq!(db.get_local_file_states().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_subsonic_servers_and_tracks_are_remote() {
+        let db = Database::new(":memory:").unwrap();
+        db.add_remote_server("nd", "Navidrome", "subsonic", "{}").unwrap();
+        assert!(db.add_remote_server("ftp", "FTP", "ftp", "{}").is_err());
+
+        db.insert_track(&track_with_cover("subsonic://nd#/song/tr-1.flac", "Remote")).unwrap();
+        assert!(db.get_tracks_without_loudness().unwrap().is_empty());
+        assert!(db.get_local_track_paths().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_old_remote_servers_table_is_migrated() {
+        let path = temp_db_path("remote-servers");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE remote_servers (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    server_type TEXT NOT NULL CHECK(server_type IN ('webdav')),
+                    config_json TEXT NOT NULL,
+                    enabled INTEGER DEFAULT 1,
+                    priority INTEGER DEFAULT 0,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    last_connected_at INTEGER,
+                    connection_status TEXT DEFAULT 'unknown'
+                 );
+                 INSERT INTO remote_servers (id, name, server_type, config_json, created_at, updated_at)
+                    VALUES ('dav', 'NAS', 'webdav', '{}', 0, 0);"
+            ).unwrap();
+        }
+
+        let db = Database::new(&path).unwrap();
+        assert_eq!(db.get_remote_servers().unwrap().len(), 1);
+        db.add_remote_server("nd", "Navidrome", "subsonic", "{}").unwrap();
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(secrets::key_path_for(&path));
+    }
+
+    #[test]
+    fn test_plaintext_credentials_are_encrypted_on_open() {
+        let path = temp_db_path("credentials");
+        {
+            let db = Database::new(&path).unwrap();
+            // 模拟旧版本直接写入的明文配置
+            db.conn.execute(
+                "INSERT INTO remote_servers (id, name, server_type, config_json, created_at, updated_at)
+                 VALUES ('dav', 'NAS', 'webdav', '{\"username\":\"wind\",\"password\":\"hunter2\"}', 0, 0)",
+                [],
+            ).unwrap();
+        }
+
+        let raw_config = |db: &Database| -> String {
+            db.conn.query_row("SELECT config_json FROM remote_servers WHERE id = 'dav'", [], |row| row.get(0)).unwrap()
+        };
+        let db = Database::new(&path).unwrap();
+        let stored = raw_config(&db);
+        assert!(!stored.contains("hunter2"));
+        assert!(stored.contains("enc:v1:"));
+
+        let config: serde_json::Value = serde_json::from_str(&db.get_remote_servers().unwrap()[0].3).unwrap();
+        assert_eq!(config["password"], "hunter2");
+        assert_eq!(config["username"], "wind");
+
+        // 再次打开不会重复加密；编辑时未回传密码则保留原密码
+        drop(db);
+        let db = Database::new(&path).unwrap();
+        assert_eq!(raw_config(&db), stored);
+        db.update_remote_server("dav", "NAS 2", r#"{"username":"wind2"}"#).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&db.get_remote_servers().unwrap()[0].3).unwrap();
+        assert_eq!(config["password"], "hunter2");
+        assert_eq!(config["username"], "wind2");
+
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(secrets::key_path_for(&path));
+    }
+
+    #[test]
+    fn test_cache_entry_keeps_pinned_on_update() {
+        let db = Database::new(":memory:").unwrap();
+        db.add_remote_server("srv", "NAS", "webdav", "{}").unwrap();
+        db.add_cache_entry("srv", "/a.flac", "/cache/a.flac", Some(100), None, Some(7)).unwrap();
+        db.set_cache_pinned(7, true).unwrap();
+
+        // 重新下载同一文件只更新记录
+        db.add_cache_entry("srv", "/a.flac", "/cache/a2.flac", Some(200), None, Some(7)).unwrap();
+        let records = db.get_cache_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].local_cache_path, "/cache/a2.flac");
+        assert_eq!(records[0].file_size, 200);
+        assert!(records[0].pinned);
+
+        db.remove_cache_entry("/cache/a2.flac").unwrap();
+        assert!(db.get_cache_records().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sync_upload_retries_until_max() {
+        let db = Database::new(":memory:").unwrap();
+        db.add_remote_server("srv", "NAS", "webdav", "{}").unwrap();
+        let track_id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+
+        let id = db.enqueue_sync_task("upload", Some(track_id), "/music/a.flac", Some("/up/a.flac"), "srv", Some(100)).unwrap();
+        // 未完成的任务不会重复入队
+        assert_eq!(db.enqueue_sync_task("upload", Some(track_id), "/music/a.flac", Some("/up/a.flac"), "srv", Some(100)).unwrap(), id);
+
+        db.start_sync_task(id, 100).unwrap();
+        assert!(db.fail_sync_task(id, "timeout", 1000).unwrap());
+        // 重试时间未到
+        assert!(db.next_sync_task("upload", 999).unwrap().is_none());
+        assert_eq!(db.next_sync_retry_at("upload").unwrap(), Some(1000));
+        assert_eq!(db.next_sync_task("upload", 1000).unwrap().unwrap().retry_count, 1);
+
+        assert!(db.fail_sync_task(id, "timeout", 2000).unwrap());
+        assert!(!db.fail_sync_task(id, "timeout", 3000).unwrap());
+        let task = &db.get_sync_queue(10).unwrap()[0];
+        assert_eq!(task.status, "failed");
+        assert_eq!(task.error_message.as_deref(), Some("timeout"));
+        assert!(db.next_sync_task("upload", i64::MAX).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_file_state_and_prune() {
+        let db = Database::new(":memory:").unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        db.insert_track(&track_with_cover("webdav://srv#/c.flac", "Gamma")).unwrap();
+
+        db.update_file_state("/music/a.flac", Some(100), 10, Some("abc")).unwrap();
+        // 未提供哈希时保留原有哈希
+        db.update_file_state("/music/a.flac", Some(200), 10, None).unwrap();
+        let states = db.get_local_file_states().unwrap();
+        assert_eq!(states.len(), 2);
+        let a = &states["/music/a.flac"];
+        assert_eq!((a.mtime, a.size, a.hash.as_deref()), (Some(200), Some(10), Some("abc")));
+
+        assert_eq!(db.delete_tracks_by_paths(&["/music/b.flac".to_string(), "/music/x.flac".to_string()]).unwrap(), 1);
+        assert_eq!(db.get_local_file_states().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tags_order_albums_and_are_searchable() {
+        let db = Database::new(":memory:").unwrap();
+        for (path, title, disc, number) in [
+            ("/music/d2t1.flac", "Alpha", 2, 1),
+            ("/music/d1t2.flac", "Beta", 1, 2),
+            ("/music/d1t1.flac", "Gamma", 1, 1),
+        ] {
+            let mut track = track_with_cover(path, title);
+            track.genre = Some("Jazz".to_string());
+            track.year = Some(1959);
+            track.disc_number = Some(disc);
+            track.track_number = Some(number);
+            track.album_artist = Some("Various Artists".to_string());
+            db.insert_track(&track).unwrap();
+        }
+
+        let titles: Vec<_> = db.get_all_tracks().unwrap().into_iter().filter_map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Gamma", "Beta", "Alpha"]);
+
+        let track = db.get_track_by_path("/music/d2t1.flac").unwrap().unwrap();
+        assert_eq!((track.genre.as_deref(), track.year), (Some("Jazz"), Some(1959)));
+        assert_eq!(track.album_artist.as_deref(), Some("Various Artists"));
+
+        assert_eq!(db.search_tracks("jazz").unwrap().len(), 3);
+        assert_eq!(db.search_tracks("various").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_album_and_artist_browse() {
+        let db = Database::new(":memory:").unwrap();
+        for (path, artist, album, number, cover) in [
+            ("/m/a2.flac", "Band", "First", 2, false),
+            ("/m/a1.flac", "Band", "First", 1, true),
+            ("/m/b1.flac", "Band", "Second", 1, false),
+            ("/m/c1.flac", "Solo", "First", 1, false),
+        ] {
+            let mut track = track_with_cover(path, path);
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            track.track_number = Some(number);
+            track.duration_ms = Some(1000);
+            if !cover {
+                track.album_cover_data = None;
+            }
+            db.insert_track(&track).unwrap();
+        }
+        let cover_id = db.get_track_by_path("/m/a1.flac").unwrap().unwrap().id;
+
+        let albums = db.get_albums().unwrap();
+        assert_eq!(albums.len(), 3);
+        assert_eq!(
+            albums[0],
+            AlbumSummary {
+                album: "First".to_string(),
+                album_artist: Some("Band".to_string()),
+                year: None,
+                track_count: 2,
+                total_duration_ms: 2000,
+                cover_track_id: cover_id,
+                cover_id: Some(cover_cache::cover_id(&[0xFF; 1024])),
+            }
+        );
+
+        let artists = db.get_artists().unwrap();
+        assert_eq!(artists[0], ArtistSummary { artist: "Band".to_string(), album_count: 2, track_count: 3 });
+
+        let paths: Vec<_> = db.get_album_tracks("First", Some("Band")).unwrap().into_iter().map(|t| t.path).collect();
+        assert_eq!(paths, vec!["/m/a1.flac", "/m/a2.flac"]);
+
+        // 写入后缓存失效
+        db.delete_tracks_by_paths(&["/m/c1.flac".to_string()]).unwrap();
+        assert_eq!(db.get_albums().unwrap().len(), 2);
+        assert_eq!(db.get_artists().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_track_metadata_update_validation() {
+        let update = |f: fn(&mut TrackMetadataUpdate)| {
+            let mut update = TrackMetadataUpdate::default();
+            f(&mut update);
+            update
+        };
+        assert!(TrackMetadataUpdate::default().validate().is_err());
+        assert!(update(|u| u.title = Some(" ".into())).validate().is_err());
+        assert!(update(|u| u.year = Some(999)).validate().is_err());
+        assert!(update(|u| u.year = Some(2101)).validate().is_err());
+        assert!(update(|u| u.track_number = Some(0)).validate().is_err());
+        assert!(update(|u| u.year = Some(2024)).validate().is_ok());
+        assert!(update(|u| u.genre = Some(String::new())).validate().is_ok());
+        assert!(update(|u| u.track_number = Some(3)).validate_bulk().is_err());
+        assert!(update(|u| u.album_artist = Some("V.A.".into())).validate_bulk().is_ok());
+    }
+
+    #[test]
+    fn test_update_tracks_metadata_updates_search_and_cache() {
+        let db = Database::new(":memory:").unwrap();
+        let a = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let b = db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        assert_eq!(db.get_artist_count().unwrap(), 1);
+
+        let fix = TrackMetadataUpdate {
+            artist: Some("  Fixed Artist ".into()),
+            genre: Some(String::new()),
+            year: Some(1999),
+            ..Default::default()
+        };
+        assert_eq!(db.update_tracks_metadata(&[a], &fix).unwrap(), 1);
+        // 缓存的艺术家数随之失效
+        assert_eq!(db.get_artist_count().unwrap(), 2);
+
+        let track = db.get_track_by_id(a).unwrap().unwrap();
+        assert_eq!(track.artist.as_deref(), Some("Fixed Artist"));
+        assert_eq!(track.genre, None);
+        assert_eq!(track.year, Some(1999));
+        assert_eq!(track.title.as_deref(), Some("Alpha"));
+
+        assert_eq!(db.update_tracks_metadata(&[a, b], &fix).unwrap(), 2);
+        // FTS 通过触发器同步
+        assert_eq!(db.search_tracks("Fixed").unwrap().len(), 2);
+        assert_eq!(db.get_artist_count().unwrap(), 1);
+
+        let rename = TrackMetadataUpdate { title: Some("Gamma".into()), ..Default::default() };
+        assert_eq!(db.update_tracks_metadata(&[b], &rename).unwrap(), 1);
+        assert_eq!(db.get_track_by_id(b).unwrap().unwrap().title.as_deref(), Some("Gamma"));
+        assert_eq!(db.update_tracks_metadata(&[9999], &rename).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_tracks_missing_lyrics_skips_recent_attempts() {
+        let db = Database::new(":memory:").unwrap();
+        let with_lyrics = db.insert_track(&track_with_cover("/music/a.flac", "A")).unwrap();
+        let temp_only = db.insert_track(&track_with_cover("/music/b.flac", "B")).unwrap();
+        let missing = db.insert_track(&track_with_cover("/music/c.flac", "C")).unwrap();
+        let attempted = db.insert_track(&track_with_cover("/music/d.flac", "D")).unwrap();
+        db.insert_lyrics(with_lyrics, "[00:01.00]a", "lrc", "online").unwrap();
+        db.insert_lyrics(temp_only, "[00:01.00]b", "lrc", "temp").unwrap();
+        db.set_lyrics_attempt(attempted, 1_000).unwrap();
+
+        let ids = |before| {
+            db.tracks_missing_lyrics(before).unwrap().into_iter().map(|c| c.track_id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(1_000), vec![temp_only, missing]);
+        assert_eq!(ids(1_001), vec![temp_only, missing, attempted]);
+    }
+
+    #[test]
+    fn test_scrobble_queue_retry_and_delete() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.enqueue_scrobble("listenbrainz", "{\"n\":1}", 200).unwrap();
+        let second = db.enqueue_scrobble("listenbrainz", "{\"n\":2}", 100).unwrap();
+        db.enqueue_scrobble("lastfm", "{\"n\":3}", 50).unwrap();
+        assert_eq!(db.scrobble_queue_depth("listenbrainz").unwrap(), 2);
+
+        // 按收听时间先后，只取当前服务
+        let due = db.due_scrobbles("listenbrainz", 0, 10).unwrap();
+        assert_eq!(due.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+
+        db.defer_scrobbles(&[second], 500, "offline").unwrap();
+        let due = db.due_scrobbles("listenbrainz", 499, 10).unwrap();
+        assert_eq!(due.iter().map(|s| s.id).collect::<Vec<_>>(), vec![first]);
+        assert_eq!(db.next_scrobble_attempt_at("listenbrainz").unwrap(), Some(0));
+        let due = db.due_scrobbles("listenbrainz", 500, 10).unwrap();
+        assert_eq!(due[0].attempts, 1);
+
+        db.delete_scrobbles(&[first, second]).unwrap();
+        assert_eq!(db.scrobble_queue_depth("listenbrainz").unwrap(), 0);
+        assert_eq!(db.next_scrobble_attempt_at("listenbrainz").unwrap(), None);
+    }
+
+    #[test]
+    fn test_play_history_records_source() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&Track::new(0, "/m/a.flac".to_string())).unwrap();
+        db.add_play_history(id, 1000).unwrap();
+        db.add_play_history_with_source(id, 2000, crate::play_history::PLAY_SOURCE_PLAYER).unwrap();
+
+        let mut stmt = db.conn.prepare("SELECT source FROM play_history ORDER BY id").unwrap();
+        let sources: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(sources, vec!["manual", "player"]);
+    }
+
+    #[test]
+    fn test_listening_stats_aggregation() {
+        use crate::play_history::{ArtistPlayStats, StatsRange};
+
+        let db = Database::new(":memory:").unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        let day = 86_400;
+        let mut ids = Vec::new();
+        for (title, artist, album, album_artist) in [
+            ("One", "Amy", "Blue", None),
+            ("Two", "Amy", "Blue", None),
+            ("Three", "Zed", "Blue", Some("Various")),
+        ] {
+            let mut track = Track::new(0, format!("/m/{}.flac", title));
+            track.title = Some(title.to_string());
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            track.album_artist = album_artist.map(str::to_string);
+            ids.push(db.insert_track(&track).unwrap());
+        }
+        // (曲目, 几天前, 实际播放时长)
+        for (index, days_ago, played_ms) in [(0, 1, 1000), (1, 1, 2000), (2, 2, 500), (2, 3, 500), (2, 3, 500), (0, 100, 4000)] {
+            db.conn.execute(
+                "INSERT INTO play_history (track_id, played_at, duration_played_ms) VALUES (?1, ?2, ?3)",
+                params![ids[index], now - days_ago * day, played_ms],
+            ).unwrap();
+        }
+
+        let artists = db.stats_top_artists(StatsRange::Days(7), 10).unwrap();
+        assert_eq!(artists, vec![
+            ArtistPlayStats { artist: "Zed".to_string(), play_count: 3, unique_tracks: 1, total_duration_ms: 1500 },
+            ArtistPlayStats { artist: "Amy".to_string(), play_count: 2, unique_tracks: 2, total_duration_ms: 3000 },
+        ]);
+        let artists = db.stats_top_artists(StatsRange::All, 1).unwrap();
+        assert_eq!((artists[0].artist.as_str(), artists[0].play_count), ("Amy", 3));
+
+        // 同名专辑按专辑艺术家区分
+        let albums = db.stats_top_albums(StatsRange::Days(30), 10).unwrap();
+        let albums: Vec<_> = albums.iter().map(|a| (a.album_artist.as_deref(), a.play_count)).collect();
+        assert_eq!(albums, vec![(Some("Various"), 3), (Some("Amy"), 2)]);
+
+        let days = db.stats_plays_per_day(StatsRange::All).unwrap();
+        assert_eq!(days.iter().map(|d| d.play_count).sum::<i64>(), 6);
+        assert_eq!(days.iter().map(|d| d.duration_ms).sum::<i64>(), 8500);
+        assert!(days.windows(2).all(|w| w[0].date < w[1].date));
+        assert_eq!(db.stats_plays_per_day(StatsRange::Days(7)).unwrap().len(), 3);
+
+        let clock = db.stats_listening_clock(StatsRange::Days(7)).unwrap();
+        assert_eq!(clock.len(), 24);
+        assert_eq!(clock.iter().map(|h| h.play_count).sum::<i64>(), 5);
+    }
+
+    #[test]
+    fn test_tracks_page_sorting_filter_and_total() {
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (title, artist, duration) in [("Charlie", "Zed", 300), ("Alpha", "Amy", 200), ("Bravo", "Zed", 100)] {
+            let mut track = track_with_cover(&format!("/m/{}.flac", title), title);
+            track.artist = Some(artist.to_string());
+            track.duration_ms = Some(duration);
+            ids.push(db.insert_track(&track).unwrap());
+        }
+        db.add_play_history(ids[2], 0).unwrap();
+        db.add_play_history(ids[2], 0).unwrap();
+        db.add_play_history(ids[0], 0).unwrap();
+
+        let titles = |page: TrackPage| page.tracks.into_iter().filter_map(|t| t.title).collect::<Vec<_>>();
+
+        let page = db.get_tracks_page(0, 2, TrackSortField::Title, SortDirection::Asc, None).unwrap();
+        assert_eq!(page.total, 3);
+        assert!(page.tracks.iter().all(|t| t.album_cover_data.is_none()));
+        assert_eq!(titles(page), vec!["Alpha", "Bravo"]);
+
+        let page = db.get_tracks_page(2, 2, TrackSortField::Title, SortDirection::Asc, None).unwrap();
+        assert_eq!(titles(page), vec!["Charlie"]);
+
+        let page = db.get_tracks_page(0, 10, TrackSortField::Duration, SortDirection::Desc, None).unwrap();
+        assert_eq!(titles(page), vec!["Charlie", "Alpha", "Bravo"]);
+
+        let page = db.get_tracks_page(0, 10, TrackSortField::PlayCount, SortDirection::Desc, None).unwrap();
+        assert_eq!(titles(page), vec!["Bravo", "Charlie", "Alpha"]);
+
+        let page = db.get_tracks_page(0, 1, TrackSortField::Title, SortDirection::Asc, Some("zed")).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(titles(page), vec!["Bravo"]);
+    }
+
+    fn png_cover(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([10, 20, 30]));
+        let mut data = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut std::io::Cursor::new(&mut data), image::ImageOutputFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_covers_are_deduplicated_and_thumbnailed() {
+        let db = Database::new(":memory:").unwrap();
+        let cover = png_cover(800, 800);
+        let mut ids = Vec::new();
+        for path in ["/m/1.flac", "/m/2.flac"] {
+            let mut track = track_with_cover(path, path);
+            track.album_cover_data = Some(cover.clone());
+            track.album_cover_mime = Some("image/png".to_string());
+            ids.push(db.insert_track(&track).unwrap());
+        }
+        let covers: i64 = db.conn.query_row("SELECT COUNT(*) FROM covers", [], |row| row.get(0)).unwrap();
+        assert_eq!(covers, 1);
+
+        let cover_id = cover_cache::cover_id(&cover);
+        let original = db.get_cover(&cover_id, CoverSize::Original).unwrap().unwrap();
+        assert_eq!((original.data.len(), original.mime.as_str()), (cover.len(), "image/png"));
+        let small = db.get_cover(&cover_id, CoverSize::Small).unwrap().unwrap();
+        assert_eq!(small.mime, cover_cache::THUMBNAIL_MIME);
+        assert_eq!(image::load_from_memory(&small.data).unwrap().width(), cover_cache::SMALL_SIZE);
+        assert!(db.get_cover("missing", CoverSize::Small).unwrap().is_none());
+
+        let batch = db.get_covers_for_tracks(&[ids[0], ids[1], 999], CoverSize::Large).unwrap();
+        assert_eq!(batch.track_covers.len(), 2);
+        assert_eq!(batch.covers.len(), 1);
+
+        // 没有曲目引用后封面被清理
+        db.delete_tracks_by_paths(&["/m/1.flac".to_string(), "/m/2.flac".to_string()]).unwrap();
+        assert!(db.get_cover(&cover_id, CoverSize::Original).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cover_palette_cache() {
+        let db = Database::new(":memory:").unwrap();
+        let mut track = track_with_cover("/m/1.flac", "/m/1.flac");
+        let cover = png_cover(64, 64);
+        track.album_cover_data = Some(cover.clone());
+        track.album_cover_mime = Some("image/png".to_string());
+        db.insert_track(&track).unwrap();
+        let cover_id = cover_cache::cover_id(&cover);
+
+        assert!(db.get_cover_palette(&cover_id).unwrap().is_none());
+        let palette = CoverPalette::default();
+        db.save_cover_palette(&cover_id, &palette).unwrap();
+        assert_eq!(db.get_cover_palette(&cover_id).unwrap(), Some(palette));
+
+        // 封面被清理后配色缓存一起删除
+        db.delete_tracks_by_paths(&["/m/1.flac".to_string()]).unwrap();
+        let cached: i64 = db.conn.query_row("SELECT COUNT(*) FROM cover_palettes", [], |row| row.get(0)).unwrap();
+        assert_eq!(cached, 0);
+    }
+
+    #[test]
+    fn test_legacy_cover_blobs_are_migrated() {
+        let db = Database::new(":memory:").unwrap();
+        let cover = png_cover(600, 600);
+        for path in ["/m/1.flac", "/m/2.flac", "/m/3.flac"] {
+            db.conn.execute(
+                "INSERT INTO tracks (path, title, album_cover_data, album_cover_mime) VALUES (?1, 'x', ?2, NULL)",
+                params![path, cover],
+            ).unwrap();
+        }
+        db.conn.execute("INSERT INTO tracks (path, title) VALUES ('/m/4.flac', 'no cover')", []).unwrap();
+
+        db.migrate_cover_storage().unwrap();
+
+        let legacy: i64 = db.conn
+            .query_row("SELECT COUNT(*) FROM tracks WHERE album_cover_data IS NOT NULL", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(legacy, 0);
+        let covers: i64 = db.conn.query_row("SELECT COUNT(*) FROM covers", [], |row| row.get(0)).unwrap();
+        assert_eq!(covers, 1);
+
+        let track = db.get_track_by_path("/m/2.flac").unwrap().unwrap();
+        let original = db.get_cover(track.cover_id.as_deref().unwrap(), CoverSize::Original).unwrap().unwrap();
+        assert_eq!(original.data, cover);
+        assert_eq!(original.mime, "image/png");
+        assert!(db.get_track_by_path("/m/4.flac").unwrap().unwrap().cover_id.is_none());
+    }
+
+    #[test]
+    fn test_search_library_with_field_qualifiers() {
+        let db = Database::new(":memory:").unwrap();
+        for (path, title, artist, album, genre, year) in [
+            ("/m/1.flac", "So What", "Miles Davis", "Kind of Blue", "Jazz", 1959),
+            ("/m/2.flac", "Blue Train", "John Coltrane", "Blue Train", "Jazz", 1957),
+            ("/m/3.flac", "Blue Monday", "New Order", "Power", "Electronic", 1983),
+        ] {
+            let mut track = Track::new(0, path.to_string());
+            track.title = Some(title.to_string());
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            track.genre = Some(genre.to_string());
+            track.year = Some(year);
+            db.insert_track(&track).unwrap();
+        }
+        let playlist_id = db.create_playlist("Blue Note favourites").unwrap();
+        db.create_playlist("Workout").unwrap();
+
+        let titles = |result: &LibrarySearchResult| {
+            result.tracks.iter().filter_map(|t| t.title.clone()).collect::<Vec<_>>()
+        };
+
+        let result = db.search_library(r#"artist:"miles davis""#).unwrap();
+        assert_eq!(titles(&result), vec!["So What"]);
+        assert_eq!(result.albums.len(), 1);
+        assert_eq!(result.artists[0].artist, "Miles Davis");
+
+        let result = db.search_library("blue genre:jazz year:1950-1958").unwrap();
+        assert_eq!(titles(&result), vec!["Blue Train"]);
+        assert_eq!(result.playlists.iter().map(|p| p.id).collect::<Vec<_>>(), vec![playlist_id]);
+
+        let result = db.search_library("year:1983").unwrap();
+        assert_eq!(titles(&result), vec!["Blue Monday"]);
+        assert!(result.playlists.is_empty());
+
+        // 无字段限定时沿用模糊搜索
+        assert_eq!(db.search_library("blue").unwrap().tracks.len(), 3);
+    }
+
+    #[test]
+    fn test_search_results_are_ranked() {
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (i, (title, artist, album)) in [
+            ("Blue in Green", "Miles Davis", "Kind of Blue"),
+            ("Mr. Blue Sky", "Electric Light Orchestra", "Out of the Blue"),
+            ("Blue", "Joni Mitchell", "Blue"),
+            ("Blue Monday", "New Order", "Power"),
+            ("Blue Train", "John Coltrane", "Blue Train"),
+        ].into_iter().enumerate() {
+            let mut track = Track::new(0, format!("/m/{}.flac", i));
+            track.title = Some(title.to_string());
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            ids.push(db.insert_track(&track).unwrap());
+        }
+        db.add_favorite(ids[4]).unwrap();
+        for _ in 0..3 {
+            db.add_play_history(ids[3], 1000).unwrap();
+        }
+
+        let titles: Vec<String> = db.search_tracks("blue").unwrap()
+            .into_iter()
+            .filter_map(|t| t.title)
+            .collect();
+        // 精确标题 > 标题前缀（收藏 > 播放3次）> 其他
+        assert_eq!(&titles[..3], ["Blue", "Blue Train", "Blue Monday"]);
+        assert_eq!(titles.len(), 5);
+    }
+
+    #[test]
+    fn test_search_history_and_suggestions() {
+        let db = Database::new(":memory:").unwrap();
+        for (i, (title, artist, album)) in [
+            ("So What", "Miles Davis", "Kind of Blue"),
+            ("Milestones", "Miles Davis", "Milestones"),
+            ("100%_Pure", "Mild", "Mild Stuff"),
+        ].into_iter().enumerate() {
+            let mut track = Track::new(0, format!("/m/{}.flac", i));
+            track.title = Some(title.to_string());
+            track.artist = Some(artist.to_string());
+            track.album = Some(album.to_string());
+            db.insert_track(&track).unwrap();
+        }
+
+        // 输入过程中的前缀被最终搜索词替换，重复搜索只保留一条
+        for query in ["mi", "mil", "miles", "  so what ", "MILES"] {
+            db.record_search(query).unwrap();
+        }
+        assert_eq!(db.get_search_history().unwrap(), vec!["MILES", "so what"]);
+        db.clear_search_history().unwrap();
+        assert!(db.get_search_history().unwrap().is_empty());
+
+        for i in 0..(SEARCH_HISTORY_LIMIT + 5) {
+            db.record_search(&format!("query {}", i)).unwrap();
+        }
+        assert_eq!(db.get_search_history().unwrap().len() as i64, SEARCH_HISTORY_LIMIT);
+
+        let suggestions = db.suggest_search("mil", 10).unwrap();
+        let values: Vec<(SuggestionKind, &str)> = suggestions.iter().map(|s| (s.kind, s.value.as_str())).collect();
+        assert_eq!(values, vec![
+            (SuggestionKind::Artist, "Mild"),
+            (SuggestionKind::Artist, "Miles Davis"),
+            (SuggestionKind::Album, "Mild Stuff"),
+            (SuggestionKind::Album, "Milestones"),
+            (SuggestionKind::Title, "Milestones"),
+        ]);
+        assert_eq!(db.suggest_search("mil", 2).unwrap().len(), 2);
+        // LIKE 通配符按字面匹配
+        assert!(db.suggest_search("%", 10).unwrap().is_empty());
+        assert_eq!(db.suggest_search("100%_", 10).unwrap()[0].value, "100%_Pure");
+    }
+
+    #[test]
+    fn test_find_exact_and_acoustic_duplicates() {
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (i, duration) in [180_000, 180_000, 181_000, 240_000, 182_000].into_iter().enumerate() {
+            let mut track = Track::new(0, format!("/m/{}.mp3", i));
+            track.title = Some(format!("Track {}", i));
+            track.duration_ms = Some(duration);
+            ids.push(db.insert_track(&track).unwrap());
+        }
+
+        let mut seed = 7u32;
+        let mut random_fp = || -> Vec<u32> {
+            (0..200).map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                seed
+            }).collect()
+        };
+        let song = random_fp();
+        // 重新编码：少量比特不同
+        let reencoded: Vec<u32> = song.iter().enumerate().map(|(i, v)| if i % 4 == 0 { v ^ 0b1011 } else { *v }).collect();
+        let other = random_fp();
+
+        // 0 和 1 内容完全相同；2 是 0 的重新编码；3 时长相差太多；4 是另一首歌
+        db.update_file_state("/m/0.mp3", Some(1), 10, Some("same")).unwrap();
+        db.update_file_state("/m/1.mp3", Some(1), 10, Some("same")).unwrap();
+        db.update_fingerprint(ids[0], &fingerprint::encode(&song), None).unwrap();
+        db.update_fingerprint(ids[1], &fingerprint::encode(&song), None).unwrap();
+        db.update_fingerprint(ids[2], &fingerprint::encode(&reencoded), Some("reencoded")).unwrap();
+        db.update_fingerprint(ids[3], &fingerprint::encode(&song), Some("far")).unwrap();
+        db.update_fingerprint(ids[4], &fingerprint::encode(&other), Some("other")).unwrap();
+
+        let groups = db.find_duplicates(fingerprint::DEFAULT_SIMILARITY_THRESHOLD).unwrap();
+        let summary: Vec<(DuplicateKind, Vec<i64>)> = groups.iter()
+            .map(|g| (g.kind, g.tracks.iter().map(|t| t.id).collect()))
+            .collect();
+        assert_eq!(summary, vec![
+            (DuplicateKind::Exact, vec![ids[0], ids[1]]),
+            (DuplicateKind::AcousticMatch, vec![ids[0], ids[1], ids[2]]),
+        ]);
+        assert!(groups[1].similarity > 0.9 && groups[1].similarity < 1.0);
+        // 已有哈希不被覆盖
+        assert_eq!(db.get_local_file_states().unwrap()["/m/0.mp3"].hash.as_deref(), Some("same"));
+
+        // 文件内容变化后指纹需要重新计算
+        assert!(db.get_tracks_without_fingerprint().unwrap().is_empty());
+        db.update_file_state("/m/2.mp3", Some(2), 11, Some("changed")).unwrap();
+        let pending = db.get_tracks_without_fingerprint().unwrap();
+        assert_eq!(pending, vec![(ids[2], "/m/2.mp3".to_string(), Some("changed".to_string()))]);
+    }
+
+    #[test]
+    fn test_smart_rules_query_play_stats_and_dates() {
+        use crate::playlist::smart_playlist::SmartPlaylistEngine;
+        use crate::playlist::{RuleField, RuleGroup, RuleOperator, RuleValue, SmartRule, SmartRules, SmartSort, SmartSortField};
+
+        let db = Database::new(":memory:").unwrap();
+        let now = 1_700_000_000;
+        let day = 86_400;
+        // (标题, 流派, 添加于几天前, 播放次数, 最后播放于几天前, 收藏)
+        let fixture = [
+            ("New Rock", "Rock", 5, 0, None, true),
+            ("Old Rock Hit", "Rock", 400, 25, Some(2), true),
+            ("Forgotten Jazz", "Jazz", 300, 3, Some(200), false),
+            ("Never Played", "Pop", 100, 0, None, false),
+            ("Jazz Favourite", "Jazz", 10, 21, Some(1), true),
+        ];
+        let mut ids = Vec::new();
+        for (i, (title, genre, added, plays, last, favorite)) in fixture.into_iter().enumerate() {
+            let mut track = Track::new(0, format!("/m/{}.flac", i));
+            track.title = Some(title.to_string());
+            track.genre = Some(genre.to_string());
+            let id = db.insert_track(&track).unwrap();
+            db.conn.execute("UPDATE tracks SET created_at = ?1 WHERE id = ?2", params![now - added * day, id]).unwrap();
+            for p in 0..plays {
+                let played_at = now - last.unwrap() * day - p as i64;
+                db.conn.execute("INSERT INTO play_history (track_id, played_at) VALUES (?1, ?2)", params![id, played_at]).unwrap();
+            }
+            if favorite {
+                db.add_favorite(id).unwrap();
+            }
+            ids.push(id);
+        }
+
+        let rule = |field, operator, value| SmartRule { field, operator, value };
+        let titles = |rules: &SmartRules| -> Vec<String> {
+            let query = SmartPlaylistEngine::build_sql_query(rules, now).unwrap();
+            db.query_tracks_by_smart_rules(&query).unwrap().into_iter().filter_map(|t| t.title).collect()
+        };
+        let by_title = Some(SmartSort { field: SmartSortField::Title, descending: false });
+
+        // 最近30天添加
+        let recent = SmartRules {
+            rules: vec![rule(RuleField::DateAdded, RuleOperator::After, RuleValue::LastNDays { last_n_days: 30 })],
+            match_all: true,
+            sort: by_title.clone(),
+            ..Default::default()
+        };
+        assert_eq!(titles(&recent), vec!["Jazz Favourite", "New Rock"]);
+
+        // 6个月内没播放过（含从未播放）
+        let stale = SmartRules {
+            rules: vec![rule(RuleField::LastPlayed, RuleOperator::NotWithinDays, RuleValue::LastNDays { last_n_days: 180 })],
+            match_all: true,
+            sort: by_title.clone(),
+            ..Default::default()
+        };
+        assert_eq!(titles(&stale), vec!["Forgotten Jazz", "Never Played", "New Rock"]);
+
+        // 播放超过20次，按播放次数倒序
+        let most_played = SmartRules {
+            rules: vec![rule(RuleField::PlayCount, RuleOperator::GreaterThan, RuleValue::Number(20))],
+            match_all: true,
+            sort: Some(SmartSort { field: SmartSortField::PlayCount, descending: true }),
+            ..Default::default()
+        };
+        assert_eq!(titles(&most_played), vec!["Old Rock Hit", "Jazz Favourite"]);
+
+        // 收藏 AND (流派=rock OR 最近30天添加)，限制1首
+        let grouped = SmartRules {
+            rules: vec![rule(RuleField::IsFavorite, RuleOperator::IsTrue, "".into())],
+            groups: vec![RuleGroup {
+                rules: vec![
+                    rule(RuleField::Genre, RuleOperator::Equals, "rock".into()),
+                    rule(RuleField::DateAdded, RuleOperator::WithinDays, RuleValue::Number(30)),
+                ],
+                match_all: false,
+            }],
+            match_all: true,
+            limit: None,
+            sort: by_title.clone(),
+        };
+        assert_eq!(titles(&grouped), vec!["Jazz Favourite", "New Rock", "Old Rock Hit"]);
+        assert_eq!(titles(&SmartRules { limit: Some(1), ..grouped }), vec!["Jazz Favourite"]);
+    }
+
+    #[test]
+    fn test_tracks_without_duration() {
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (path, duration_ms) in [("/m/a.mp3", None), ("/m/b.mp3", Some(0)), ("/m/c.mp3", Some(180_000)), ("webdav://s#/d.mp3", None)] {
+            let mut track = Track::new(0, path.to_string());
+            track.duration_ms = duration_ms;
+            ids.push(db.insert_track(&track).unwrap());
+        }
+        db.update_remote_file_state("webdav://s#/d.mp3", &RemoteFileState { mtime: None, size: Some(4_000_000), etag: None }).unwrap();
+        db.update_audio_properties("webdav://s#/d.mp3", &AudioProperties { bitrate_kbps: Some(320), ..Default::default() }).unwrap();
+
+        let missing = db.get_tracks_without_duration().unwrap();
+        assert_eq!(missing.iter().map(|m| m.track_id).collect::<Vec<_>>(), vec![ids[0], ids[1], ids[3]]);
+        assert_eq!(missing[2].file_size, Some(4_000_000));
+        assert_eq!(missing[2].bitrate_kbps, Some(320));
+
+        // 已缓存的全部曲目列表随之更新
+        assert_eq!(db.get_all_tracks().unwrap().iter().find(|t| t.id == ids[0]).unwrap().duration_ms, None);
+        assert!(db.update_track_duration(ids[0], 215_000).unwrap());
+        assert_eq!(db.get_all_tracks().unwrap().iter().find(|t| t.id == ids[0]).unwrap().duration_ms, Some(215_000));
+        assert!(!db.update_track_duration(9999, 1000).unwrap());
+        assert_eq!(db.get_tracks_without_duration().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_track_rating_rules_and_sort() {
+        use crate::playlist::smart_playlist::SmartPlaylistEngine;
+        use crate::playlist::{RuleField, RuleOperator, RuleValue, SmartRule, SmartRules, SmartSort, SmartSortField};
+
+        let db = Database::new(":memory:").unwrap();
+        let mut ids = Vec::new();
+        for (i, (title, rating)) in [("One", Some(1)), ("Five", Some(5)), ("Unrated", None), ("Four", Some(4))].into_iter().enumerate() {
+            let mut track = Track::new(0, format!("/m/{}.flac", i));
+            track.title = Some(title.to_string());
+            let id = db.insert_track(&track).unwrap();
+            assert!(db.set_track_rating(id, rating).unwrap());
+            ids.push(id);
+        }
+        assert_eq!(db.get_track_rating(ids[1]).unwrap(), Some(Some(5)));
+        assert_eq!(db.get_track_rating(ids[2]).unwrap(), Some(None));
+        assert_eq!(db.get_track_rating(9999).unwrap(), None);
+        assert!(!db.set_track_rating(9999, Some(3)).unwrap());
+        assert_eq!(db.get_track_by_id(ids[3]).unwrap().unwrap().rating, Some(4));
+
+        // 4星及以上，按评分倒序
+        let rules = SmartRules {
+            rules: vec![SmartRule { field: RuleField::Rating, operator: RuleOperator::GreaterOrEqual, value: RuleValue::Number(4) }],
+            match_all: true,
+            sort: Some(SmartSort { field: SmartSortField::Rating, descending: true }),
+            ..Default::default()
+        };
+        let query = SmartPlaylistEngine::build_sql_query(&rules, 0).unwrap();
+        let titles: Vec<_> = db.query_tracks_by_smart_rules(&query).unwrap().into_iter().filter_map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Five", "Four"]);
+
+        // 未评分排在最后（按0星）
+        let page = db.get_tracks_page(0, 10, TrackSortField::Rating, SortDirection::Desc, None).unwrap();
+        let ratings: Vec<_> = page.tracks.iter().map(|t| t.rating).collect();
+        assert_eq!(ratings, vec![Some(5), Some(4), Some(1), None]);
+
+        // 重新扫描：保留已有评分，未评分的曲目采用文件标签中的评分
+        let mut rescanned = Track::new(0, "/m/0.flac".to_string());
+        rescanned.rating = Some(3);
+        db.insert_track(&rescanned).unwrap();
+        let mut unrated = Track::new(0, "/m/2.flac".to_string());
+        unrated.rating = Some(2);
+        db.insert_track(&unrated).unwrap();
+        assert_eq!(db.get_track_rating(ids[0]).unwrap(), Some(Some(1)));
+        assert_eq!(db.get_track_rating(ids[2]).unwrap(), Some(Some(2)));
+    }
+
+    #[test]
+    fn test_old_fts_index_is_rebuilt_with_tag_columns() {
+        let db = Database::new(":memory:").unwrap();
+        let mut track = track_with_cover("/music/a.flac", "Alpha");
+        track.genre = Some("Bossa Nova".to_string());
+        db.insert_track(&track).unwrap();
+
+        // 模拟旧版数据库：索引只有 title/artist/album/path
+        db.conn.execute_batch(
+            "DROP TRIGGER tracks_ai; DROP TRIGGER tracks_ad; DROP TRIGGER tracks_au; DROP TABLE tracks_fts;
+             CREATE VIRTUAL TABLE tracks_fts USING fts5(title, artist, album, path, content='tracks', content_rowid='id');
+             INSERT INTO tracks_fts(tracks_fts) VALUES('rebuild');",
+        ).unwrap();
+        assert!(db.search_tracks("bossa").unwrap().is_empty());
+
+        db.migrate_tracks_fts().unwrap();
+        assert_eq!(db.search_tracks("bossa").unwrap().len(), 1);
+    }
+
+    /// 直接查询全文索引（search_tracks 在索引没有结果时会退回 LIKE 搜索）
+    fn fts_hits(db: &Database, query: &str) -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM tracks_fts WHERE tracks_fts MATCH ?1", [query], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rebuild_search_index_repairs_missing_and_orphan_rows() {
+        let db = Database::new(":memory:").unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let gone = db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        assert!(db.search_index_counts().unwrap().is_consistent());
+
+        // 模拟触发器短暂缺失期间的写入：新曲目没有索引，已删除的曲目残留在索引中
+        db.conn.execute_batch("DROP TRIGGER tracks_ai; DROP TRIGGER tracks_ad; DROP TRIGGER tracks_au;").unwrap();
+        db.conn.execute("INSERT INTO tracks (path, title, genre) VALUES ('/music/c.flac', 'Gamma', 'Bossa Nova')", []).unwrap();
+        db.conn.execute("INSERT INTO tracks (path, title) VALUES ('/music/d.flac', 'Delta')", []).unwrap();
+        db.conn.execute("DELETE FROM tracks WHERE id = ?1", [gone]).unwrap();
+        assert_eq!(db.search_index_counts().unwrap(), SearchIndexCounts { tracks: 3, indexed: 2 });
+        assert_eq!(fts_hits(&db, "Gamma"), 0);
+
+        let result = db.rebuild_search_index().unwrap();
+        assert_eq!(result.before, SearchIndexCounts { tracks: 3, indexed: 2 });
+        assert_eq!(result.after, SearchIndexCounts { tracks: 3, indexed: 3 });
+        assert_eq!(fts_hits(&db, "Gamma"), 1);
+        assert_eq!(fts_hits(&db, "Beta"), 0);
+        assert_eq!(db.search_library("genre:bossa").unwrap().tracks.len(), 1);
+
+        // 触发器已恢复
+        db.insert_track(&track_with_cover("/music/e.flac", "Epsilon")).unwrap();
+        assert!(db.search_index_counts().unwrap().is_consistent());
+        assert_eq!(fts_hits(&db, "Epsilon"), 1);
+    }
+
+    #[test]
+    fn test_outdated_fts_triggers_are_recreated() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+
+        // 索引已是新结构，但更新触发器还是不含流派的旧版本
+        db.conn.execute_batch(
+            "DROP TRIGGER tracks_au;
+             CREATE TRIGGER tracks_au AFTER UPDATE ON tracks BEGIN
+                INSERT INTO tracks_fts(tracks_fts, rowid, title, artist, album, path) VALUES('delete', old.id, old.title, old.artist, old.album, old.path);
+                INSERT INTO tracks_fts(rowid, title, artist, album, path) VALUES (new.id, new.title, new.artist, new.album, new.path);
+             END;",
+        ).unwrap();
+        db.conn.execute("UPDATE tracks SET genre = 'Bossa Nova' WHERE id = ?1", [id]).unwrap();
+        assert!(db.search_library("genre:bossa").unwrap().tracks.is_empty());
+
+        db.repair_fts_triggers().unwrap();
+        assert_eq!(db.search_library("genre:bossa").unwrap().tracks.len(), 1);
+        db.conn.execute("UPDATE tracks SET album_artist = 'Joao' WHERE id = ?1", [id]).unwrap();
+        assert_eq!(fts_hits(&db, "album_artist:Joao"), 1);
+    }
+
+    #[test]
+    fn test_rebuild_search_index_waits_for_other_writer() {
+        let path = temp_db_path("fts-rebuild");
+        let db = Database::new(&path).unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+
+        // 另一个连接正在写入曲目表：重建等待其提交后进行，结果包含新写入的曲目
+        let writer = Connection::open(&path).unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE; INSERT INTO tracks (path, title) VALUES ('/music/b.flac', 'Beta');").unwrap();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            writer.execute_batch("COMMIT").unwrap();
+        });
+
+        let result = db.rebuild_search_index().unwrap();
+        handle.join().unwrap();
+        assert_eq!(result.after, SearchIndexCounts { tracks: 2, indexed: 2 });
+        assert_eq!(fts_hits(&db, "Beta"), 1);
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_sync_conflict_lifecycle() {
+        let db = Database::new(":memory:").unwrap();
+        db.add_remote_server("srv", "NAS", "webdav", "{}").unwrap();
+        let track_id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        db.record_track_sync(track_id, "srv", Some("\"v1\""), Some(100)).unwrap();
+        assert_eq!(db.get_sync_baseline(track_id).unwrap().remote_etag.as_deref(), Some("\"v1\""));
+
+        let mut conflict = SyncConflict {
+            id: 0,
+            track_id,
+            server_id: "srv".to_string(),
+            conflict_type: "modified_both".to_string(),
+            local_path: Some("/music/a.flac".to_string()),
+            remote_path: Some("/up/a.flac".to_string()),
+            local_size: Some(10),
+            remote_size: Some(11),
+            local_modified: Some(200),
+            remote_modified: Some(300),
+            local_hash: None,
+            remote_hash: Some("\"v2\"".to_string()),
+            resolution_strategy: None,
+            resolved: false,
+            resolved_at: None,
+            created_at: 0,
+        };
+        let sync_status = |db: &Database| -> String {
+            db.conn.query_row("SELECT sync_status FROM tracks WHERE id = ?1", [track_id], |row| row.get(0)).unwrap()
+        };
+        let id = db.add_sync_conflict(&conflict).unwrap();
+        // 同一曲目的未解决冲突只保留一条
+        conflict.conflict_type = "different_size".to_string();
+        assert_eq!(db.add_sync_conflict(&conflict).unwrap(), id);
+        assert_eq!(db.get_sync_conflicts(false).unwrap()[0].conflict_type, "different_size");
+        assert_eq!(sync_status(&db), "conflict");
+
+        db.resolve_sync_conflict(id, "prefer_local", "synced").unwrap();
+        assert!(db.get_sync_conflicts(false).unwrap().is_empty());
+        let resolved = db.get_sync_conflict(id).unwrap().unwrap();
+        assert!(resolved.resolved);
+        assert!(resolved.resolved_at.is_some());
+        assert_eq!(resolved.resolution_strategy.as_deref(), Some("prefer_local"));
+        assert_eq!(sync_status(&db), "synced");
+    }
+
+    #[test]
+    fn test_library_statistics() {
+        let db = Database::new(":memory:").unwrap();
+        assert_eq!(db.get_library_statistics().unwrap(), LibraryStatistics::default());
+
+        let a = db.insert_track(&track_with_cover("/music/a.flac", "A")).unwrap();
+        let mut b = Track::new(0, "/music/b.FLAC".to_string());
+        b.duration_ms = Some(1000);
+        b.embedded_lyrics = Some("[00:01.00]la".to_string());
+        db.insert_track(&b).unwrap();
+        let c = db.insert_track(&Track::new(0, "/music/c.mp3".to_string())).unwrap();
+        db.insert_lyrics(c, "[00:01.00]la", "lrc", "file").unwrap();
+        db.update_file_state("/music/a.flac", None, 3000, None).unwrap();
+        db.update_file_state("/music/b.FLAC", None, 2000, None).unwrap();
+        db.update_file_state("/music/c.mp3", None, 500, None).unwrap();
+        db.update_audio_properties("/music/a.flac", &AudioProperties {
+            format: Some("flac".to_string()),
+            bitrate_kbps: Some(900),
+            sample_rate: Some(96000),
+            channels: Some(2),
+        }).unwrap();
+        db.update_audio_properties("/music/c.mp3", &AudioProperties {
+            format: Some("mp3".to_string()),
+            bitrate_kbps: Some(320),
+            sample_rate: Some(44100),
+            channels: Some(2),
+        }).unwrap();
+        db.conn.execute("UPDATE tracks SET duration_ms = 5000, source_type = 'webdav' WHERE id = ?1", [a]).unwrap();
+
+        let stats = db.get_library_statistics().unwrap();
+        assert_eq!(stats.total_tracks, 3);
+        assert_eq!(stats.total_bytes, 5500);
+        // 没有记录格式的曲目按扩展名（小写）推断
+        assert_eq!(stats.formats, vec![
+            FormatStatistics { format: "flac".to_string(), track_count: 2, total_bytes: 5000 },
+            FormatStatistics { format: "mp3".to_string(), track_count: 1, total_bytes: 500 },
+        ]);
+        assert_eq!(stats.bitrate_kbps, Some(ValueRange { avg: 610.0, min: 320, max: 900 }));
+        assert_eq!(stats.sample_rate.as_ref().map(|r| (r.min, r.max)), Some((44100, 96000)));
+        assert_eq!(stats.missing_cover, 2);
+        assert_eq!(stats.missing_lyrics, 1);
+        assert_eq!(stats.missing_duration, 1);
+        assert_eq!(stats.sources, vec![
+            SourceCount { source_type: "local".to_string(), track_count: 2 },
+            SourceCount { source_type: "webdav".to_string(), track_count: 1 },
+        ]);
+
+        // 曲目变化后缓存失效
+        db.delete_tracks_by_paths(&["/music/c.mp3".to_string()]).unwrap();
+        let stats = db.get_library_statistics().unwrap();
+        assert_eq!(stats.total_tracks, 2);
+        assert_eq!(stats.bitrate_kbps.map(|r| r.max), Some(900));
+    }
+
+    #[test]
+    fn test_library_backup_round_trip() {
+        let source = Database::new(":memory:").unwrap();
+        let insert = |db: &Database, path: &str, title: &str, duration_ms: i64, hash: Option<&str>| -> i64 {
+            let mut track = Track::new(0, path.to_string());
+            track.title = Some(title.to_string());
+            track.artist = Some("Band".to_string());
+            track.duration_ms = Some(duration_ms);
+            let id = db.insert_track(&track).unwrap();
+            db.update_file_state(path, None, 100, hash).unwrap();
+            id
+        };
+        let a = insert(&source, "/old/a.flac", "A", 200_000, Some("hash-a"));
+        let b = insert(&source, "/old/b.flac", "B", 180_000, None);
+        let c = insert(&source, "/old/c.flac", "C", 100_000, None);
+        let playlist = source.create_playlist("Mix").unwrap();
+        for id in [b, a, c] {
+            source.add_track_to_playlist(playlist, id).unwrap();
+        }
+        source.add_favorite(a).unwrap();
+        source.add_favorite(c).unwrap();
+        source.set_track_rating(a, Some(5)).unwrap();
+        source.set_track_rating(c, Some(3)).unwrap();
+        source.add_play_history(b, 1000).unwrap();
+        source.insert_lyrics(a, "[00:01.00]a", "lrc", "file").unwrap();
+        source.add_remote_server("srv", "NAS", "webdav", r#"{"url":"https://nas","password":"secret"}"#).unwrap();
+
+        let backup = source.export_library_backup(false).unwrap();
+        assert_eq!(backup.tracks.len(), 3);
+        assert!(!backup.remote_servers[0].config_json.contains("secret"));
+
+        // 新电脑上路径不同；a 按哈希匹配，b 按元数据匹配，c 不在曲库中
+        let target = Database::new(":memory:").unwrap();
+        let new_b = insert(&target, "/new/b.flac", "B", 180_500, None);
+        let new_a = insert(&target, "/new/a.flac", "Renamed", 1, Some("hash-a"));
+        target.create_playlist("Mix").unwrap();
+
+        let report = target.import_library_backup(&backup, &ImportOptions::default()).unwrap();
+        assert_eq!((report.tracks_matched, report.tracks_unmatched), (2, 1));
+        assert_eq!(report.playlists, library_backup::ImportCounts { imported: 1, merged: 0, skipped: 0 });
+        assert_eq!(report.playlist_items, library_backup::ImportCounts { imported: 2, merged: 0, skipped: 1 });
+        assert_eq!(report.favorites, library_backup::ImportCounts { imported: 1, merged: 0, skipped: 1 });
+        assert_eq!(report.ratings, library_backup::ImportCounts { imported: 1, merged: 0, skipped: 1 });
+        assert_eq!(report.play_history.imported, 1);
+        assert_eq!(report.lyrics.imported, 1);
+        assert_eq!(report.remote_servers.imported, 1);
+
+        // 同名歌单加后缀另存，曲目顺序不变
+        let playlists = target.get_all_playlists_extended().unwrap();
+        let restored = playlists.iter().find(|p| p.name == "Mix (2)").unwrap();
+        let ids: Vec<i64> = target.get_playlist_tracks(restored.id).unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![new_b, new_a]);
+        assert!(target.is_favorite(new_a).unwrap());
+        assert_eq!(target.get_play_count(new_b).unwrap(), 1);
+        assert!(target.get_lyrics_by_track_id(new_a).unwrap().is_some());
+        assert_eq!(target.get_track_rating(new_a).unwrap(), Some(Some(5)));
+        assert_eq!(target.get_track_rating(new_b).unwrap(), Some(None));
+
+        // 再次还原：已存在的记录不重复，覆盖时替换同名歌单
+        let options = ImportOptions { overwrite: true, ..Default::default() };
+        let report = target.import_library_backup(&backup, &options).unwrap();
+        assert_eq!(report.playlists.merged, 1);
+        assert_eq!(report.favorites.merged, 1);
+        assert_eq!(report.ratings.skipped, 2);
+        assert_eq!(report.play_history.merged, 1);
+        assert_eq!(report.lyrics.merged, 1);
+        assert_eq!(report.remote_servers.merged, 1);
+        assert_eq!(target.get_all_playlists_extended().unwrap().len(), 2);
+        assert_eq!(target.get_play_count(new_b).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_recent_and_most_played_tracks() {
+        let db = Database::new(":memory:").unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let mut ids = Vec::new();
+        for (index, path) in ["/m/old.flac", "/m/mid.flac", "/m/new.flac"].into_iter().enumerate() {
+            let id = db.insert_track(&track_with_cover(path, path)).unwrap();
+            db.conn.execute("UPDATE tracks SET created_at = ?1 WHERE id = ?2", params![now - 100 + index as i64, id]).unwrap();
+            ids.push(id);
+        }
+        // mid 播放3次（其中1次在一周前），old 最近播放1次
+        for (id, played_at) in [(ids[1], now - 10 * 86_400), (ids[1], now - 300), (ids[1], now - 200), (ids[0], now - 10)] {
+            db.conn.execute("INSERT INTO play_history (track_id, played_at) VALUES (?1, ?2)", params![id, played_at]).unwrap();
+        }
+        let paths = |tracks: Vec<Track>| -> Vec<String> { tracks.into_iter().map(|t| t.path).collect() };
+
+        let added = db.get_recently_added_tracks(2).unwrap();
+        assert!(added.iter().all(|t| t.album_cover_data.is_none()));
+        assert_eq!(paths(added), ["/m/new.flac", "/m/mid.flac"]);
+        assert_eq!(paths(db.get_recently_played_tracks(0, 10).unwrap()), ["/m/old.flac", "/m/mid.flac"]);
+        assert_eq!(paths(db.get_most_played_tracks(0, 10).unwrap()), ["/m/mid.flac", "/m/old.flac"]);
+
+        // 只统计一天内：mid 2次仍多于 old 1次；限制1首
+        assert_eq!(paths(db.get_most_played_tracks(now - 86_400, 1).unwrap()), ["/m/mid.flac"]);
+        assert!(db.get_recently_played_tracks(now, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_tracks_cascades_and_invalidates_cache() {
+        let path = std::env::temp_dir().join(format!("windchime-delete-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+        let keep = db.insert_track(&track_with_cover("/m/keep.flac", "Keep")).unwrap();
+        let gone = db.insert_track(&track_with_cover("/m/gone.flac", "Gone")).unwrap();
+        let playlist = db.create_playlist("Mix").unwrap();
+        db.add_track_to_playlist(playlist, keep).unwrap();
+        db.add_track_to_playlist(playlist, gone).unwrap();
+        db.add_favorite(gone).unwrap();
+        db.insert_lyrics(gone, "[00:01.00]la", "lrc", "manual").unwrap();
+        db.add_play_history(gone, 1000).unwrap();
+
+        // 先填充缓存
+        assert_eq!(db.get_track_count().unwrap(), 2);
+        assert_eq!(db.get_favorites_count().unwrap(), 1);
+
+        assert_eq!(db.delete_tracks(&[gone, 999]).unwrap(), 1);
+        assert_eq!(db.get_track_count().unwrap(), 1);
+        assert_eq!(db.get_favorites_count().unwrap(), 0);
+        let remaining: Vec<i64> = db.get_playlist_tracks(playlist).unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(remaining, [keep]);
+        for table in ["lyrics", "play_history", "favorites"] {
+            let count: i64 = db
+                .conn
+                .query_row(&format!("SELECT COUNT(*) FROM {} WHERE track_id = ?1", table), [gone], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 0, "{} 中仍有已删除曲目的记录", table);
+        }
+        assert_eq!(db.get_track_paths(&[keep, gone]).unwrap(), [(keep, "/m/keep.flac".to_string())]);
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_artist_covers_missing_and_paged() {
+        let db = Database::new(":memory:").unwrap();
+        for (path, artist) in [("/m/1.mp3", Some("B")), ("/m/2.mp3", Some("A")), ("/m/3.mp3", Some("A")),
+                               ("/m/4.mp3", Some("C")), ("/m/5.mp3", Some(" ")), ("/m/6.mp3", None)] {
+            let mut track = Track::new(0, path.to_string());
+            track.artist = artist.map(str::to_string);
+            db.insert_track(&track).unwrap();
+        }
+        db.save_artist_cover("B", &[0; 100], "image/jpeg").unwrap();
+        db.set_artist_cover_attempt("C", 1000).unwrap();
+
+        // C 在 1000 尝试过：之后的时间点跳过，强制刷新（更晚的时间点）时重新获取
+        assert_eq!(db.artists_missing_covers(500).unwrap(), vec!["A".to_string()]);
+        assert_eq!(db.artists_missing_covers(2000).unwrap(), vec!["A".to_string(), "C".to_string()]);
+
+        db.save_artist_cover("A", &[0; 100], "image/jpeg").unwrap();
+        db.save_artist_cover("C", &[0; 100], "image/jpeg").unwrap();
+        let page = db.get_artist_covers_page(0, 10, 250).unwrap();
+        assert_eq!(page.covers.iter().map(|c| c.0.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+        assert_eq!((page.total, page.next_offset), (3, Some(2)));
+        let page = db.get_artist_covers_page(2, 10, 10).unwrap();
+        // 单张超过上限时仍返回一张
+        assert_eq!(page.covers.len(), 1);
+        assert_eq!(page.next_offset, None);
+    }
+
+    #[test]
+    fn test_list_queries_do_not_load_covers() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        let playlist_id = db.create_playlist("test").unwrap();
+        db.add_track_to_playlist(playlist_id, id).unwrap();
+        db.add_favorite(id).unwrap();
+
+        let lists = vec![
+            db.get_all_tracks().unwrap(),
+            db.search_tracks("Alpha").unwrap(),
+            db.fallback_like_search("alp").unwrap(),
+            db.get_playlist_tracks(playlist_id).unwrap(),
+            db.get_all_favorites().unwrap(),
+            db.query_tracks_by_smart_rules(&crate::playlist::smart_playlist::SmartPlaylistEngine::build_sql_query(&Default::default(), 0).unwrap()).unwrap(),
+        ];
+        for tracks in lists {
+            assert!(!tracks.is_empty());
+            for track in tracks {
+                assert!(track.album_cover_data.is_none());
+                assert!(track.album_cover_mime.is_none());
+                assert!(track.artist_photo_data.is_none());
+                assert!(track.artist_photo_mime.is_none());
+            }
+        }
+
+        // 单曲查询只返回封面ID，批量封面查询仍然返回原图
+        let single = db.get_track_by_id(id).unwrap().unwrap();
+        assert!(single.album_cover_data.is_none());
+        assert_eq!(single.cover_id, Some(cover_cache::cover_id(&[0xFF; 1024])));
+        assert_eq!(single.artist_photo_data.map(|d| d.len()), Some(512));
+
+        let with_covers = db.get_tracks_with_covers(&[id]).unwrap();
+        assert_eq!(with_covers.len(), 1);
+        assert_eq!(with_covers[0].album_cover_mime.as_deref(), Some("image/jpeg"));
+        assert!(db.get_tracks_with_covers(&[]).unwrap().is_empty());
+    }
+
+    fn temp_db_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("windchime-test-{}-{}.db", name, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_foreign_keys_cascade_and_integrity_check() {
+        let db = Database::new(":memory:").unwrap();
+        let track_id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let playlist_id = db.create_playlist("Mix").unwrap();
+        db.add_track_to_playlist(playlist_id, track_id).unwrap();
+        db.add_favorite(track_id).unwrap();
+
+        // 引用不存在的曲目会被拒绝
+        assert!(db.add_track_to_playlist(playlist_id, track_id + 100).is_err());
+
+        db.delete_tracks_by_paths(&["/music/a.flac".to_string()]).unwrap();
+        assert!(db.get_playlist_tracks(playlist_id).unwrap().is_empty());
+        assert!(!db.is_favorite(track_id).unwrap());
+        assert_eq!(db.integrity_check().unwrap(), vec!["ok".to_string()]);
+    }
+
+    #[test]
+    fn test_backup_and_vacuum() {
+        let dir = std::env::temp_dir().join(format!("windchime-test-backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("windchime.db")).unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+
+        let backup_path = dir.join("copy.db");
+        db.backup_to(&backup_path).unwrap();
+        assert!(db.backup_to(dir.join("windchime.db")).is_err());
+        let copy = Connection::open(&backup_path).unwrap();
+        let count: i64 = copy.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        db.conn.execute("DELETE FROM tracks", []).unwrap();
+        let stats = db.vacuum().unwrap();
+        assert!(stats.bytes_after <= stats.bytes_before);
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_old_schema_is_backed_up_before_migration() {
+        let dir = std::env::temp_dir().join(format!("windchime-test-migrate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("windchime.db");
+        let backups = || -> Vec<String> {
+            let mut names: Vec<String> = std::fs::read_dir(dir.join(BACKUP_DIR))
+                .map(|entries| entries.filter_map(|e| e.ok()?.file_name().into_string().ok()).collect())
+                .unwrap_or_default();
+            names.sort();
+            names
+        };
+
+        // 新建数据库不需要备份
+        drop(Database::new(&path).unwrap());
+        assert!(backups().is_empty());
+
+        // 模拟旧版本程序创建的数据库
+        Connection::open(&path).unwrap().pragma_update(None, "user_version", 8).unwrap();
+        let db = Database::new(&path).unwrap();
+        assert_eq!(db.get_schema_version().unwrap(), SCHEMA_VERSION);
+        let names = backups();
+        assert_eq!(names.len(), 1);
+        assert!(names[0].starts_with("windchime-") && names[0].ends_with("-v8.db"));
+        drop(db);
+
+        // 只保留最近几份
+        for i in 0..MAX_AUTO_BACKUPS + 2 {
+            std::fs::write(dir.join(BACKUP_DIR).join(format!("windchime-20000101-00000{}-v7.db", i)), b"").unwrap();
+        }
+        prune_backups(&dir.join(BACKUP_DIR), "windchime", MAX_AUTO_BACKUPS);
+        let names = backups();
+        assert_eq!(names.len(), MAX_AUTO_BACKUPS);
+        assert!(names.last().unwrap().ends_with("-v8.db"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cache_notices_writes_from_other_connection() {
+        let path = temp_db_path("data-version");
+        let db = Database::new(&path).unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        assert_eq!(db.get_track_count().unwrap(), 1);
+        assert_eq!(db.get_artist_count().unwrap(), 1);
+
+        // 模拟扫描器：通过第二个连接直接写入
+        {
+            let scanner = Connection::open(&path).unwrap();
+            scanner.execute(
+                "INSERT INTO tracks (path, title, artist, album) VALUES ('/music/b.flac', 'Beta', 'Other', 'Album 2')",
+                [],
+            ).unwrap();
+        }
+
+        assert_eq!(db.get_track_count().unwrap(), 2);
+        assert_eq!(db.get_artist_count().unwrap(), 2);
+        assert_eq!(db.get_album_count().unwrap(), 2);
+
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_cache_notices_writes_on_same_connection() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        assert_eq!(db.get_favorites_count().unwrap(), 0);
+
+        // 收藏、删除等写路径不再需要手动失效缓存
+        db.add_favorite(id).unwrap();
+        assert_eq!(db.get_favorites_count().unwrap(), 1);
+
+        db.conn.execute("DELETE FROM tracks WHERE id = ?1", [id]).unwrap();
+        assert_eq!(db.get_track_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_cached_reads_are_fresh_after_each_mutation() {
+        let path = temp_db_path("cache-fresh");
+        let db = Database::new(&path).unwrap();
+        let titles = |db: &Database| -> Vec<String> {
+            db.get_all_tracks().unwrap().into_iter().filter_map(|t| t.title).collect()
+        };
+        let first = db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        assert_eq!(titles(&db), ["Alpha"]);
+        assert!(db.cache.lock().unwrap().all_tracks.is_some());
+
+        // 新增曲目（扫描）
+        let second = db.insert_track(&track_with_cover("/music/b.flac", "Beta")).unwrap();
+        assert_eq!(titles(&db), ["Alpha", "Beta"]);
+
+        // 修改元数据
+        let update = TrackMetadataUpdate { title: Some("Gamma".to_string()), ..Default::default() };
+        db.update_tracks_metadata(&[second], &update).unwrap();
+        assert_eq!(titles(&db), ["Alpha", "Gamma"]);
+
+        // 更换封面
+        let cover_of = |db: &Database| db.get_all_tracks().unwrap()[0].cover_id.clone();
+        let old_cover = cover_of(&db);
+        db.update_track_cover(first, Some(vec![1, 2, 3]), Some("image/png".to_string())).unwrap();
+        assert_ne!(cover_of(&db), old_cover);
+        assert_eq!(db.get_library_statistics().unwrap().missing_cover, 0);
+        db.update_track_cover(second, None, None).unwrap();
+        assert_eq!(db.get_library_statistics().unwrap().missing_cover, 1);
+
+        // 评分
+        db.set_track_rating(first, Some(4)).unwrap();
+        assert_eq!(db.get_all_tracks().unwrap()[0].rating, Some(4));
+
+        // 收藏
+        assert_eq!(db.get_favorites_count().unwrap(), 0);
+        db.add_favorite(first).unwrap();
+        assert_eq!(db.get_favorites_count().unwrap(), 1);
+        db.remove_favorite(first).unwrap();
+        assert_eq!(db.get_favorites_count().unwrap(), 0);
+
+        // 歌词只写 lyrics 表，统计中的缺少歌词数也要更新
+        assert_eq!(db.get_library_statistics().unwrap().missing_lyrics, 2);
+        db.insert_lyrics(first, "[00:01.00]la", "lrc", "manual").unwrap();
+        assert_eq!(db.get_library_statistics().unwrap().missing_lyrics, 1);
+        db.delete_lyrics(first).unwrap();
+        assert_eq!(db.get_library_statistics().unwrap().missing_lyrics, 2);
+
+        // 其他连接写入（远程扫描等）
+        {
+            let scanner = Connection::open(&path).unwrap();
+            scanner.execute("INSERT INTO tracks (path, title) VALUES ('/music/c.flac', 'Delta')", []).unwrap();
+            scanner.execute("INSERT INTO lyrics (track_id, content, format, source) VALUES (?1, 'x', 'plain', 'file')", [second]).unwrap();
+        }
+        assert_eq!(titles(&db), ["Delta", "Alpha", "Gamma"]);
+        assert_eq!(db.get_library_statistics().unwrap().missing_lyrics, 2);
+
+        // 删除曲目
+        db.delete_tracks(&[first]).unwrap();
+        assert_eq!(titles(&db), ["Delta", "Gamma"]);
+        assert_eq!(db.get_track_count().unwrap(), 2);
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_all_tracks_cache_is_bounded() {
+        let db = Database::new(":memory:").unwrap();
+        db.insert_track(&track_with_cover("/music/a.flac", "Alpha")).unwrap();
+        let tracks = db.get_all_tracks().unwrap();
+        assert!(approx_tracks_bytes(&tracks) < 4096);
+        assert!(db.cache.lock().unwrap().all_tracks.is_some());
+
+        // 内嵌歌词超过上限时只返回结果不缓存
+        db.conn.execute("UPDATE tracks SET embedded_lyrics = ?1", [" ".repeat(ALL_TRACKS_CACHE_MAX_BYTES)]).unwrap();
+        let tracks = db.get_all_tracks().unwrap();
+        assert!(approx_tracks_bytes(&tracks) > ALL_TRACKS_CACHE_MAX_BYTES);
+        assert!(db.cache.lock().unwrap().all_tracks.is_none());
+    }
+
+    /// 基准：带 data_version 检查的缓存命中必须明显快于它省掉的查询（cargo test -- --ignored）
+    ///
+    /// 两者在同一进程中交替测量，只比较相对耗时，不依赖机器快慢
+    #[test]
+    #[ignore]
+    fn bench_cache_hit_with_version_check() {
+        let db = Database::new(":memory:").unwrap();
+        for i in 0..1000 {
+            db.insert_track(&track_with_cover(&format!("/music/{}.flac", i), "T")).unwrap();
+        }
+        db.get_track_count().unwrap();
+
+        let iterations: u32 = 20_000;
+        let mut checked = Duration::ZERO;
+        let mut uncached = Duration::ZERO;
+        for _ in 0..iterations {
+            let start = Instant::now();
+            std::hint::black_box(db.get_track_count().unwrap());
+            checked += start.elapsed();
+
+            let start = Instant::now();
+            let count: i64 = db.conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0)).unwrap();
+            std::hint::black_box(count);
+            uncached += start.elapsed();
+        }
+
+        assert!(
+            checked * 3 < uncached,
+            "缓存命中 {:?}/次，直接查询 {:?}/次",
+            checked / iterations,
+            uncached / iterations
+        );
     }
 }
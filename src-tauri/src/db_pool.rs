@@ -0,0 +1,267 @@
+// 数据库连接池
+//
+// SQLite 同一时间只允许一个写事务，所以连接分为两类：
+// - 写连接：只有一个，建表和迁移在它上面完成，所有写入通过 lock() 串行执行
+// - 读连接：若干只读连接，启用 WAL 后读取不会被写事务和其他读取阻塞
+//
+// 锁使用 parking_lot，持锁的线程 panic 不会让之后所有命令都失败；
+// 取连接等待超时返回 DbPoolError::Busy，而不是无限期挂起。
+// 耗时的查询用 run_read/run_write 放到阻塞线程池执行，不占用异步运行时。
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use anyhow::Result;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// 读连接数量
+pub const READER_COUNT: usize = 4;
+
+/// 等待连接的最长时间
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum DbPoolError {
+    #[error("数据库繁忙，请稍后重试")]
+    Busy,
+}
+
+/// 数据库连接池
+pub struct DbPool {
+    writer: Mutex<Database>,
+    readers: Mutex<Vec<Database>>,
+    reader_returned: Condvar,
+    /// 读连接总数（包括已借出的）
+    reader_count: usize,
+}
+
+/// 读连接，释放时归还连接池
+///
+/// 内存数据库无法共享给其他连接，此时直接使用写连接。
+pub enum DbReadGuard<'a> {
+    Reader { pool: &'a DbPool, db: Option<Database> },
+    Writer(MutexGuard<'a, Database>),
+}
+
+impl Deref for DbReadGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        match self {
+            DbReadGuard::Reader { db, .. } => db.as_ref().expect("读连接已归还"),
+            DbReadGuard::Writer(db) => db,
+        }
+    }
+}
+
+impl Drop for DbReadGuard<'_> {
+    fn drop(&mut self) {
+        if let DbReadGuard::Reader { pool, db } = self {
+            if let Some(db) = db.take() {
+                pool.readers.lock().push(db);
+                pool.reader_returned.notify_one();
+            }
+        }
+    }
+}
+
+impl DbPool {
+    /// 打开数据库（执行建表和迁移），文件数据库同时创建读连接
+    pub fn open<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        let writer = Database::new(db_path)?;
+        let readers = if db_path == Path::new(":memory:") {
+            Vec::new()
+        } else {
+            (0..READER_COUNT)
+                .map(|_| Database::open_reader(db_path))
+                .collect::<Result<Vec<_>>>()?
+        };
+        log::info!("🗄️ 数据库连接池已打开: 1个写连接, {}个读连接", readers.len());
+        Ok(Self::from_parts(writer, readers))
+    }
+
+    /// 只有一个连接的连接池（测试和内存数据库使用）
+    pub fn single(db: Database) -> Self {
+        Self::from_parts(db, Vec::new())
+    }
+
+    fn from_parts(writer: Database, readers: Vec<Database>) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            reader_count: readers.len(),
+            readers: Mutex::new(readers),
+            reader_returned: Condvar::new(),
+        }
+    }
+
+    /// 获取写连接（写入和需要读到自己刚写入内容的操作使用）
+    pub fn lock(&self) -> Result<MutexGuard<'_, Database>, DbPoolError> {
+        self.writer.try_lock_for(ACQUIRE_TIMEOUT).ok_or_else(|| {
+            log::warn!("⚠️ 等待数据库写连接超时");
+            DbPoolError::Busy
+        })
+    }
+
+    /// 获取读连接，不会被写入和其他读取阻塞
+    pub fn read(&self) -> Result<DbReadGuard<'_>, DbPoolError> {
+        if self.reader_count == 0 {
+            return self.lock().map(DbReadGuard::Writer);
+        }
+        let deadline = Instant::now() + ACQUIRE_TIMEOUT;
+        let mut readers = self.readers.lock();
+        loop {
+            if let Some(db) = readers.pop() {
+                return Ok(DbReadGuard::Reader { pool: self, db: Some(db) });
+            }
+            if self.reader_returned.wait_until(&mut readers, deadline).timed_out() && readers.is_empty() {
+                log::warn!("⚠️ 等待数据库读连接超时");
+                return Err(DbPoolError::Busy);
+            }
+        }
+    }
+
+    /// 用写连接执行操作
+    pub fn with<T>(&self, f: impl FnOnce(&Database) -> Result<T>) -> Result<T> {
+        f(&*self.lock()?)
+    }
+
+    /// 用读连接执行查询
+    pub fn with_read<T>(&self, f: impl FnOnce(&Database) -> Result<T>) -> Result<T> {
+        f(&*self.read()?)
+    }
+
+    /// 在阻塞线程池中用读连接执行查询
+    pub async fn run_read<T, F>(self: &Arc<Self>, f: F) -> AppResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+    {
+        let pool = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let db = pool.read()?;
+            f(&db).map_err(AppError::from)
+        })
+        .await?
+    }
+
+    /// 在阻塞线程池中用写连接执行操作
+    pub async fn run_write<T, F>(self: &Arc<Self>, f: F) -> AppResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+    {
+        let pool = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let db = pool.lock()?;
+            f(&db).map_err(AppError::from)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Track;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    fn track(i: usize) -> Track {
+        let mut track = Track::new(0, format!("/music/scan/{:05}.flac", i));
+        track.title = Some(format!("Song {}", i));
+        track.artist = Some("Pool Artist".to_string());
+        track.album = Some("Pool Album".to_string());
+        track
+    }
+
+    #[test]
+    fn test_reads_proceed_while_scan_writes() {
+        let path = std::env::temp_dir().join(format!("windchime-pool-{}.db", uuid::Uuid::new_v4()));
+        let pool = Arc::new(DbPool::open(&path).unwrap());
+        let playlist_id = pool
+            .with(|db| {
+                let playlist_id = db.create_playlist("Mix")?;
+                let track_id = db.insert_track(&track(0))?;
+                db.add_track_to_playlist(playlist_id, track_id)?;
+                Ok(playlist_id)
+            })
+            .unwrap();
+
+        // 模拟扫描：一直占用写连接并持续插入曲目
+        let scanning = Arc::new(AtomicBool::new(true));
+        let (started_tx, started_rx) = mpsc::channel();
+        let scanner = {
+            let pool = Arc::clone(&pool);
+            let scanning = Arc::clone(&scanning);
+            std::thread::spawn(move || {
+                let db = pool.lock().unwrap();
+                started_tx.send(()).unwrap();
+                for i in 1..=300 {
+                    db.insert_track(&track(i)).unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(300));
+                scanning.store(false, Ordering::SeqCst);
+            })
+        };
+        started_rx.recv().unwrap();
+
+        // 同时进行搜索和歌单读取
+        let reads_during_scan = Arc::new(AtomicUsize::new(0));
+        let readers: Vec<_> = (0..READER_COUNT * 2)
+            .map(|n| {
+                let pool = Arc::clone(&pool);
+                let scanning = Arc::clone(&scanning);
+                let reads_during_scan = Arc::clone(&reads_during_scan);
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        let db = pool.read().unwrap();
+                        if n % 2 == 0 {
+                            db.search_tracks("Song").unwrap();
+                        } else {
+                            assert_eq!(db.get_playlist_tracks(playlist_id).unwrap().len(), 1);
+                        }
+                        if scanning.load(Ordering::SeqCst) {
+                            reads_during_scan.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        scanner.join().unwrap();
+
+        // 写连接被占用期间读取没有被阻塞，扫描结束后能读到全部曲目
+        assert!(reads_during_scan.load(Ordering::SeqCst) > 0);
+        assert_eq!(pool.with_read(|db| db.get_track_count()).unwrap(), 301);
+
+        drop(pool);
+        for suffix in ["", "-wal", "-shm", ".key"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    fn test_panic_while_holding_connection_does_not_break_pool() {
+        let pool = Arc::new(DbPool::single(Database::new(":memory:").unwrap()));
+        let panicking = Arc::clone(&pool);
+        let result = std::thread::spawn(move || {
+            let _db = panicking.lock().unwrap();
+            panic!("查询中途panic");
+        })
+        .join();
+        assert!(result.is_err());
+
+        // 内存数据库的读取使用写连接
+        assert_eq!(pool.with(|db| db.get_track_count()).unwrap(), 0);
+        assert_eq!(pool.with_read(|db| db.get_track_count()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,419 @@
+// 诊断
+//
+// 音频系统检查和修复（按平台执行不同的检查）、音频设备调试报告，以及诊断包导出。
+// 诊断包是一个 zip 文件，用户报告播放问题时附上即可：
+// - info.txt：程序版本、操作系统
+// - audio.txt：音频系统检查和设备调试报告
+// - library.txt：曲库统计
+// - cache.json：缓存统计
+// - settings.json：设置和远程服务器配置（去掉密码、令牌等）
+// - logs/：最近的日志文件
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::cache::CacheStats;
+use crate::db::Database;
+
+/// 设置中需要隐去的字段（JSON 字段名或设置键中包含这些词）
+const SENSITIVE_KEYS: [&str; 5] = ["password", "token", "secret", "api_key", "pin_hash"];
+
+const REDACTED: &str = "***";
+
+/// 可能和本程序争用音频设备的应用
+#[cfg(target_os = "windows")]
+const CONFLICTING_APPS: [&str; 5] = ["spotify.exe", "chrome.exe", "firefox.exe", "vlc.exe", "wmplayer.exe"];
+#[cfg(not(target_os = "windows"))]
+const CONFLICTING_APPS: [&str; 4] = ["spotify", "chrome", "firefox", "vlc"];
+
+/// 进程是否在运行，无法检查时为 None
+#[cfg(target_os = "windows")]
+fn process_running(image_name: &str) -> Option<bool> {
+    let output = Command::new("tasklist")
+        .args(["/FI", &format!("IMAGENAME eq {}", image_name)])
+        .output()
+        .ok()?;
+    let pattern = image_name.trim_end_matches('*');
+    Some(String::from_utf8_lossy(&output.stdout).contains(pattern))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn process_running(name: &str) -> Option<bool> {
+    let status = Command::new("pgrep").args(["-x", name]).output().ok()?.status;
+    Some(status.success())
+}
+
+/// 音频服务检查（Windows Audio 服务和音频引擎进程）
+#[cfg(target_os = "windows")]
+fn check_audio_service(lines: &mut Vec<String>) {
+    if let Ok(output) = Command::new("sc").args(["query", "audiosrv"]).output() {
+        if String::from_utf8_lossy(&output.stdout).contains("RUNNING") {
+            lines.push("✅ Windows Audio服务运行正常".to_string());
+        } else {
+            lines.push("❌ Windows Audio服务未运行".to_string());
+        }
+    }
+
+    match process_running("audiodg.exe") {
+        Some(true) => lines.push("✅ Windows音频引擎进程运行正常".to_string()),
+        Some(false) => lines.push("❌ Windows音频引擎进程未找到".to_string()),
+        None => {}
+    }
+
+    if process_running("SenaryAudioApp*") == Some(true) {
+        lines.push("⚠️ 检测到Senary音频增强软件，可能造成设备独占".to_string());
+    }
+}
+
+/// 音频服务检查（Core Audio 守护进程）
+#[cfg(target_os = "macos")]
+fn check_audio_service(lines: &mut Vec<String>) {
+    match process_running("coreaudiod") {
+        Some(true) => lines.push("✅ Core Audio服务 (coreaudiod) 运行正常".to_string()),
+        Some(false) => lines.push("❌ Core Audio服务 (coreaudiod) 未运行".to_string()),
+        None => {}
+    }
+}
+
+/// 音频服务检查（PipeWire / PulseAudio 声音服务器）
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn check_audio_service(lines: &mut Vec<String>) {
+    match Command::new("pactl").arg("info").output() {
+        Ok(output) if output.status.success() => {
+            let info = String::from_utf8_lossy(&output.stdout);
+            let server = info
+                .lines()
+                .find_map(|line| line.strip_prefix("Server Name:"))
+                .map(str::trim)
+                .unwrap_or("未知");
+            lines.push(format!("✅ 声音服务器运行正常: {}", server));
+        }
+        _ => {
+            let running = ["pipewire", "pulseaudio"]
+                .into_iter()
+                .find(|name| process_running(name) == Some(true));
+            match running {
+                Some(name) => lines.push(format!("✅ 声音服务器进程运行中: {}", name)),
+                None => lines.push("⚠️ 未检测到 PipeWire 或 PulseAudio，将直接使用 ALSA".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+const SWITCH_DEVICE_HINT: &str = "在Windows设置中切换默认音频设备";
+#[cfg(target_os = "macos")]
+const SWITCH_DEVICE_HINT: &str = "在系统设置的“声音”中切换输出设备";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SWITCH_DEVICE_HINT: &str = "在系统声音设置（或 pavucontrol）中切换输出设备";
+
+/// 音频系统检查：音频服务、可能冲突的应用和建议的解决方案
+pub fn audio_system_checks() -> String {
+    let mut lines = vec!["=== WindChime Player 音频系统诊断 ===".to_string()];
+
+    check_audio_service(&mut lines);
+    for app in CONFLICTING_APPS {
+        if process_running(app) == Some(true) {
+            lines.push(format!("⚠️ 发现可能冲突的应用: {}", app));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("=== 建议的解决方案 ===".to_string());
+    lines.push("1. 关闭其他音频应用".to_string());
+    lines.push("2. 重新插拔耳机设备".to_string());
+    lines.push(format!("3. {}", SWITCH_DEVICE_HINT));
+    lines.push("4. 尝试重启WindChime Player".to_string());
+    lines.push("5. 如果问题持续，请尝试重启计算机".to_string());
+    lines.join("\n")
+}
+
+/// 重启 Windows Audio 服务
+#[cfg(target_os = "windows")]
+pub fn fix_audio_system() -> String {
+    let mut results = Vec::new();
+
+    match Command::new("net").args(["stop", "audiosrv"]).output() {
+        Ok(_) => {
+            results.push("✅ 已停止Windows Audio服务".to_string());
+            std::thread::sleep(std::time::Duration::from_millis(2000));
+        }
+        Err(e) => results.push(format!("❌ 停止音频服务失败: {}", e)),
+    }
+
+    match Command::new("net").args(["start", "audiosrv"]).output() {
+        Ok(_) => results.push("✅ 已启动Windows Audio服务".to_string()),
+        Err(e) => results.push(format!("❌ 启动音频服务失败: {}", e)),
+    }
+
+    // 等待服务稳定
+    std::thread::sleep(std::time::Duration::from_millis(3000));
+    results.push("⏱️ 等待音频服务稳定...".to_string());
+
+    results.push(String::new());
+    results.push("🎵 音频系统修复完成！请尝试重新播放音乐。".to_string());
+    results.join("\n")
+}
+
+/// 其他平台的音频服务需要管理员权限或由桌面会话管理，不自动重启
+#[cfg(not(target_os = "windows"))]
+pub fn fix_audio_system() -> String {
+    let mut results = vec!["ℹ️ 当前平台不支持自动重启音频服务".to_string()];
+    if cfg!(target_os = "macos") {
+        results.push("可以在终端执行 sudo killall coreaudiod 重启 Core Audio".to_string());
+    } else {
+        results.push("可以执行 systemctl --user restart pipewire（或 pulseaudio）重启声音服务器".to_string());
+    }
+    results.push("也可以使用“重置音频设备”重新打开输出设备".to_string());
+    results.join("\n")
+}
+
+/// 音频设备调试报告：音频主机、默认设备及其配置、能否打开输出流、所有输出设备
+pub fn audio_debug_report() -> String {
+    use cpal::traits::{DeviceTrait, HostTrait};
+    use rodio::OutputStream;
+
+    let mut result = String::new();
+    result.push_str("音频系统调试报告:\n\n");
+
+    result.push_str(&format!("可用音频主机: {:?}\n", cpal::available_hosts()));
+
+    let host = cpal::default_host();
+    if let Some(device) = host.default_output_device() {
+        if let Ok(name) = device.name() {
+            result.push_str(&format!("默认输出设备: {}\n", name));
+
+            if let Ok(config) = device.default_output_config() {
+                result.push_str(&format!("默认配置: {:?}\n", config));
+            }
+
+            match OutputStream::try_from_device(&device) {
+                Ok((_stream, handle)) => {
+                    result.push_str("✅ OutputStream创建成功\n");
+                    match rodio::Sink::try_new(&handle) {
+                        Ok(_) => result.push_str("✅ Sink创建成功\n"),
+                        Err(e) => result.push_str(&format!("❌ Sink创建失败: {}\n", e)),
+                    }
+                }
+                Err(e) => result.push_str(&format!("❌ OutputStream创建失败: {}\n", e)),
+            }
+        }
+    } else {
+        result.push_str("❌ 未找到默认输出设备\n");
+    }
+
+    match host.output_devices() {
+        Ok(devices) => {
+            result.push_str("\n所有输出设备:\n");
+            for (i, device) in devices.enumerate() {
+                if let Ok(name) = device.name() {
+                    result.push_str(&format!("  {}. {}\n", i + 1, name));
+                }
+            }
+        }
+        Err(e) => result.push_str(&format!("❌ 无法枚举输出设备: {}\n", e)),
+    }
+
+    result
+}
+
+/// 程序版本和操作系统信息
+pub fn version_info() -> String {
+    format!(
+        "WindChime Player {}\n系统: {}\n内核: {}\n平台: {} / {}\n导出时间: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        sysinfo::System::long_os_version().unwrap_or_else(|| "未知".to_string()),
+        sysinfo::System::kernel_version().unwrap_or_else(|| "未知".to_string()),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        chrono::Local::now().to_rfc3339(),
+    )
+}
+
+/// 曲库统计
+pub fn library_stats(db: &Database) -> Result<String> {
+    let remote_servers = db.get_remote_servers()?;
+    Ok(format!(
+        "曲目: {}\n艺术家: {}\n专辑: {}\n远程服务器: {}（已启用 {}）\n",
+        db.get_track_count()?,
+        db.get_artist_count()?,
+        db.get_album_count()?,
+        remote_servers.len(),
+        remote_servers.iter().filter(|(.., enabled)| *enabled).count(),
+    ))
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key != crate::secrets::HAS_PASSWORD_FIELD && SENSITIVE_KEYS.iter().any(|sensitive| key.contains(sensitive))
+}
+
+/// 隐去 JSON 中的敏感字段
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if is_sensitive(key) {
+                    if !value.is_null() {
+                        *value = REDACTED.into();
+                    }
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// 设置和远程服务器配置，敏感内容已隐去
+pub fn redacted_settings(db: &Database) -> Result<serde_json::Value> {
+    let mut settings = serde_json::Map::new();
+    for (key, value) in db.get_all_settings()? {
+        let value = if is_sensitive(&key) {
+            REDACTED.into()
+        } else {
+            let mut value = serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+            redact_json(&mut value);
+            value
+        };
+        settings.insert(key, value);
+    }
+
+    let servers: Vec<_> = db
+        .get_remote_servers()?
+        .into_iter()
+        .map(|(id, name, server_type, config_json, enabled)| {
+            let mut config = crate::secrets::redact_config(&config_json);
+            redact_json(&mut config);
+            serde_json::json!({
+                "id": id,
+                "name": name,
+                "server_type": server_type,
+                "enabled": enabled,
+                "config": config,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({ "settings": settings, "remote_servers": servers }))
+}
+
+/// 诊断包内容
+pub struct DiagnosticsBundle {
+    entries: Vec<(String, Vec<u8>)>,
+    log_files: Vec<PathBuf>,
+}
+
+impl DiagnosticsBundle {
+    /// 收集诊断信息，单项失败时在对应文件中写入错误而不是中止导出
+    pub fn collect(db: &Database, cache_stats: Option<CacheStats>, log_files: Vec<PathBuf>) -> Self {
+        let library = library_stats(db).unwrap_or_else(|e| format!("❌ 读取曲库统计失败: {:#}\n", e));
+        let settings = redacted_settings(db)
+            .unwrap_or_else(|e| serde_json::json!({ "error": format!("读取设置失败: {:#}", e) }));
+        let cache = match cache_stats {
+            Some(stats) => serde_json::to_value(stats).unwrap_or_default(),
+            None => serde_json::json!({ "error": "缓存管理器未初始化" }),
+        };
+        let audio = format!("{}\n\n{}", audio_system_checks(), audio_debug_report());
+
+        let entries = vec![
+            ("info.txt".to_string(), version_info().into_bytes()),
+            ("audio.txt".to_string(), audio.into_bytes()),
+            ("library.txt".to_string(), library.into_bytes()),
+            ("cache.json".to_string(), serde_json::to_vec_pretty(&cache).unwrap_or_default()),
+            ("settings.json".to_string(), serde_json::to_vec_pretty(&settings).unwrap_or_default()),
+        ];
+        Self { entries, log_files }
+    }
+
+    /// 写入 zip 文件
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).with_context(|| format!("无法创建诊断包: {}", path.display()))?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        for (name, data) in &self.entries {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(data)?;
+        }
+        for log_file in &self.log_files {
+            let Some(name) = log_file.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // 日志文件可能在导出期间滚动，读取失败的跳过
+            let Ok(data) = std::fs::read(log_file) else {
+                log::warn!("⚠️ 读取日志文件失败，跳过: {}", log_file.display());
+                continue;
+            };
+            zip.start_file(format!("logs/{}", name), options)?;
+            zip.write_all(&data)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_settings_are_redacted() {
+        let db = Database::new(":memory:").unwrap();
+        db.set_setting("scrobbler.config", r#"{"service":"listenbrainz","token":"abc123","api_secret":"s3cret","enabled":true}"#)
+            .unwrap();
+        db.set_setting(crate::party_mode::SETTING_PIN_HASH, "hash-value").unwrap();
+        db.set_setting("audio.output_device", "USB DAC").unwrap();
+        db.add_remote_server("webdav_1", "NAS", "webdav", r#"{"url":"https://nas","username":"me","password":"hunter2"}"#)
+            .unwrap();
+
+        let redacted = redacted_settings(&db).unwrap();
+        let text = redacted.to_string();
+        for secret in ["abc123", "s3cret", "hash-value", "hunter2"] {
+            assert!(!text.contains(secret), "{} 未被隐去", secret);
+        }
+        let settings = &redacted["settings"];
+        assert_eq!(settings["scrobbler.config"]["service"], "listenbrainz");
+        assert_eq!(settings["scrobbler.config"]["token"], REDACTED);
+        assert_eq!(settings["audio.output_device"], "USB DAC");
+        assert_eq!(redacted["remote_servers"][0]["config"]["username"], "me");
+        assert_eq!(redacted["remote_servers"][0]["config"]["has_password"], true);
+    }
+
+    #[test]
+    fn test_bundle_contains_all_sections() {
+        let dir = std::env::temp_dir().join(format!("windchime-diag-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log_file = dir.join("windchime.log");
+        std::fs::write(&log_file, "INFO 启动\n").unwrap();
+
+        let bundle = DiagnosticsBundle {
+            entries: vec![
+                ("info.txt".to_string(), version_info().into_bytes()),
+                ("library.txt".to_string(), library_stats(&Database::new(":memory:").unwrap()).unwrap().into_bytes()),
+            ],
+            log_files: vec![log_file, dir.join("windchime.log.1")],
+        };
+        let zip_path = dir.join("diagnostics.zip");
+        bundle.write_to(&zip_path).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        // 不存在的日志文件被跳过
+        assert_eq!(names, vec!["info.txt", "library.txt", "logs/windchime.log"]);
+
+        let mut library = String::new();
+        archive.by_name("library.txt").unwrap().read_to_string(&mut library).unwrap();
+        assert!(library.starts_with("曲目: 0\n"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
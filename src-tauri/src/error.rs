@@ -0,0 +1,406 @@
+// 命令层统一错误类型
+//
+// Tauri 命令返回 AppError，序列化为 { kind, message, detail }：
+// - kind：错误类别，前端据此区分"未找到""磁盘已满""认证失败"等情况并做本地化
+// - message：可直接展示的错误描述，前端通过 src/utils/appError.ts 读取
+// - detail：附加信息，例如网络错误的HTTP状态码、不受信任证书的指纹，没有时为 null
+//
+// 各模块自己的错误类型（PlayerError、WebDAVError、DbPoolError等）在这里映射到对应类别。
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use thiserror::Error;
+
+use crate::db_pool::DbPoolError;
+use crate::network_api::musicbrainz::MetadataLookupError;
+use crate::party_mode::PartyModeError;
+use crate::player::PlayerError;
+use crate::webdav::types::WebDAVError;
+
+/// 命令层结果类型
+pub type AppResult<T> = std::result::Result<T, AppError>;
+
+#[derive(Debug, Clone, Error)]
+pub enum AppError {
+    /// 曲目、歌单、文件等不存在
+    #[error("{0}")]
+    NotFound(String),
+
+    /// 数据库读写失败
+    #[error("{0}")]
+    Database(String),
+
+    /// 本地文件读写失败
+    #[error("{0}")]
+    Io(String),
+
+    /// 网络请求失败，status 为HTTP状态码（连接失败、超时等没有状态码）
+    #[error("{message}")]
+    Network { status: Option<u16>, message: String },
+
+    /// 认证失败或无权限
+    #[error("{0}")]
+    Unauthorized(String),
+
+    /// 服务器证书不受信任，前端询问用户后可用 fingerprint 固定该证书
+    #[error("{message}")]
+    UntrustedCertificate { fingerprint: String, message: String },
+
+    /// 参数无效
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// 与当前状态冲突（如删除正在播放的曲目）
+    #[error("{0}")]
+    Conflict(String),
+
+    /// 暂时无法完成，稍后重试即可（如音频尚未缓存完成时跳转）
+    #[error("{0}")]
+    NotReady(String),
+
+    /// 其他内部错误
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        AppError::InvalidInput(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict(message.into())
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::Internal(message.into())
+    }
+
+    /// 错误类别（序列化后的 kind 字段）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NotFound",
+            AppError::Database(_) => "Database",
+            AppError::Io(_) => "Io",
+            AppError::Network { .. } => "Network",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::UntrustedCertificate { .. } => "UntrustedCertificate",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::Conflict(_) => "Conflict",
+            AppError::NotReady(_) => "NotReady",
+            AppError::Internal(_) => "Internal",
+        }
+    }
+
+    /// 在消息前加上说明，类别和附加信息不变
+    pub fn context(mut self, context: &str) -> Self {
+        let message = format!("{}: {}", context, self);
+        self.set_message(message);
+        self
+    }
+
+    fn set_message(&mut self, new_message: String) {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Database(message)
+            | AppError::Io(message)
+            | AppError::Unauthorized(message)
+            | AppError::InvalidInput(message)
+            | AppError::Conflict(message)
+            | AppError::NotReady(message)
+            | AppError::Internal(message)
+            | AppError::Network { message, .. }
+            | AppError::UntrustedCertificate { message, .. } => *message = new_message,
+        }
+    }
+
+    fn detail(&self) -> serde_json::Value {
+        match self {
+            AppError::Network { status: Some(status), .. } => serde_json::json!({ "status": status }),
+            AppError::UntrustedCertificate { fingerprint, .. } => serde_json::json!({ "fingerprint": fingerprint }),
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    fn from_io(err: &std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(err.to_string()),
+            std::io::ErrorKind::PermissionDenied => AppError::Unauthorized(err.to_string()),
+            _ => AppError::Io(err.to_string()),
+        }
+    }
+
+    fn from_reqwest(err: &reqwest::Error) -> Self {
+        let status = err.status().map(|s| s.as_u16());
+        match status {
+            Some(401) | Some(403) => AppError::Unauthorized(err.to_string()),
+            _ => AppError::Network { status, message: err.to_string() },
+        }
+    }
+
+    fn from_rusqlite(err: &rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(err.to_string()),
+            _ => AppError::Database(err.to_string()),
+        }
+    }
+
+    fn from_webdav(err: &WebDAVError) -> Self {
+        match err {
+            WebDAVError::NetworkError(e) => AppError::from_reqwest(e),
+            WebDAVError::HttpStatusError { status: 401 | 403, .. } | WebDAVError::AuthenticationFailed(_) => {
+                AppError::Unauthorized(err.to_string())
+            }
+            WebDAVError::PermissionDenied { .. } => AppError::Unauthorized(err.to_string()),
+            WebDAVError::HttpStatusError { status: 404, .. } | WebDAVError::FileNotFound { .. } => {
+                AppError::NotFound(err.to_string())
+            }
+            WebDAVError::HttpStatusError { status, .. } => AppError::Network {
+                status: Some(*status),
+                message: err.to_string(),
+            },
+            WebDAVError::ServerError(_) | WebDAVError::Timeout => AppError::Network {
+                status: None,
+                message: err.to_string(),
+            },
+            WebDAVError::IoError(e) => AppError::from_io(e),
+            WebDAVError::ConfigError(_) | WebDAVError::UnsupportedOperation(_) => {
+                AppError::InvalidInput(err.to_string())
+            }
+            WebDAVError::XmlParseError(_) | WebDAVError::HttpMethodError(_) => AppError::Internal(err.to_string()),
+            WebDAVError::UntrustedCertificate { fingerprint, .. } => AppError::UntrustedCertificate {
+                fingerprint: fingerprint.clone(),
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("detail", &self.detail())?;
+        state.end()
+    }
+}
+
+/// anyhow 错误按其中的原始错误类型归类，message 使用最外层的上下文描述（与 to_string 一致）
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        let message = err.to_string();
+        let mut mapped = if let Some(e) = err.downcast_ref::<AppError>() {
+            e.clone()
+        } else if let Some(e) = err.downcast_ref::<rusqlite::Error>() {
+            AppError::from_rusqlite(e)
+        } else if let Some(e) = err.downcast_ref::<std::io::Error>() {
+            AppError::from_io(e)
+        } else if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+            AppError::from_reqwest(e)
+        } else if let Some(e) = err.downcast_ref::<DbPoolError>() {
+            AppError::Database(e.to_string())
+        } else if let Some(e) = err.downcast_ref::<WebDAVError>() {
+            AppError::from_webdav(e)
+        } else if let Some(e) = err.downcast_ref::<serde_json::Error>() {
+            AppError::InvalidInput(e.to_string())
+        } else {
+            AppError::Internal(message.clone())
+        };
+        mapped.set_message(message);
+        mapped
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        AppError::from_rusqlite(&err)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        AppError::from_io(&err)
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(err: reqwest::Error) -> Self {
+        AppError::from_reqwest(&err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::InvalidInput(err.to_string())
+    }
+}
+
+impl From<DbPoolError> for AppError {
+    fn from(err: DbPoolError) -> Self {
+        AppError::Database(err.to_string())
+    }
+}
+
+impl From<WebDAVError> for AppError {
+    fn from(err: WebDAVError) -> Self {
+        AppError::from_webdav(&err)
+    }
+}
+
+impl From<PlayerError> for AppError {
+    fn from(err: PlayerError) -> Self {
+        let message = err.to_string();
+        match err {
+            PlayerError::FileNotFound(_) | PlayerError::TrackNotFound(_) => AppError::NotFound(message),
+            PlayerError::FileReadError(_) => AppError::Io(message),
+            PlayerError::EmptyPlaylist | PlayerError::InvalidLoopRegion(_) => AppError::InvalidInput(message),
+            PlayerError::LoopUnavailable => AppError::Conflict(message),
+            PlayerError::NotCached => AppError::NotReady(message),
+            _ => AppError::Internal(message),
+        }
+    }
+}
+
+impl From<MetadataLookupError> for AppError {
+    fn from(err: MetadataLookupError) -> Self {
+        let message = err.to_string();
+        match err {
+            MetadataLookupError::MissingTags => AppError::InvalidInput(message),
+            MetadataLookupError::NoResults => AppError::NotFound(message),
+            MetadataLookupError::Network(_) => AppError::Network { status: None, message },
+            MetadataLookupError::RateLimited => AppError::Network { status: Some(429), message },
+            MetadataLookupError::Service { status, .. } => AppError::Network { status: Some(status), message },
+            MetadataLookupError::InvalidResponse(_) => AppError::Internal(message),
+        }
+    }
+}
+
+impl From<PartyModeError> for AppError {
+    fn from(err: PartyModeError) -> Self {
+        let message = err.to_string();
+        match err {
+            PartyModeError::Active(_) | PartyModeError::InvalidPin | PartyModeError::TooManyAttempts { .. } => {
+                AppError::Unauthorized(message)
+            }
+            PartyModeError::RandomUnavailable => AppError::Internal(message),
+            _ => AppError::InvalidInput(message),
+        }
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl<T> From<crossbeam_channel::SendError<T>> for AppError {
+    fn from(err: crossbeam_channel::SendError<T>) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl From<tokio::sync::oneshot::error::RecvError> for AppError {
+    fn from(err: tokio::sync::oneshot::error::RecvError) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+/// 尚未归类的字符串错误
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Internal(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Internal(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialized_shape() {
+        let json = serde_json::to_value(AppError::not_found("歌曲不存在: 42")).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "NotFound", "message": "歌曲不存在: 42", "detail": null }));
+
+        let json = serde_json::to_value(AppError::from(WebDAVError::HttpStatusError {
+            status: 503,
+            message: "Service Unavailable".to_string(),
+        }))
+        .unwrap();
+        assert_eq!(json["kind"], "Network");
+        assert_eq!(json["detail"], serde_json::json!({ "status": 503 }));
+        assert_eq!(json["message"], "HTTP状态错误: 503, 消息: Service Unavailable");
+
+        let json = serde_json::to_value(AppError::from(WebDAVError::AuthenticationFailed("401".to_string()))).unwrap();
+        assert_eq!(json["kind"], "Unauthorized");
+
+        let err = AppError::from(WebDAVError::FileNotFound { path: "/a.flac".to_string() }).context("下载文件失败");
+        assert_eq!(err.kind(), "NotFound");
+        assert!(err.to_string().starts_with("下载文件失败: "));
+
+        let err = AppError::from(WebDAVError::UntrustedCertificate {
+            fingerprint: "AB:CD".to_string(),
+            reason: "invalid peer certificate: UnknownIssuer".to_string(),
+        })
+        .context("创建目录失败");
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "UntrustedCertificate");
+        assert_eq!(json["detail"], serde_json::json!({ "fingerprint": "AB:CD" }));
+        assert!(json["message"].as_str().unwrap().starts_with("创建目录失败: 服务器证书不受信任"));
+    }
+
+    #[test]
+    fn test_anyhow_errors_keep_context_and_category() {
+        let err = anyhow::Error::from(rusqlite::Error::QueryReturnedNoRows).context("获取歌曲失败");
+        let err = AppError::from(err);
+        assert_eq!(err.kind(), "NotFound");
+        assert_eq!(err.to_string(), "获取歌曲失败");
+
+        let io = std::io::Error::new(std::io::ErrorKind::Other, "No space left on device");
+        let err = AppError::from(anyhow::Error::from(io));
+        assert_eq!(err.kind(), "Io");
+        assert_eq!(err.to_string(), "No space left on device");
+
+        let err = AppError::from(anyhow::anyhow!("未知错误"));
+        assert_eq!(err.kind(), "Internal");
+
+        // 已经归类的错误经过 anyhow 后类别不变
+        let err = AppError::from(anyhow::Error::from(AppError::invalid_input("PIN至少需要4位")));
+        assert_eq!(err.kind(), "InvalidInput");
+        assert_eq!(serde_json::to_value(&err).unwrap()["message"], "PIN至少需要4位");
+    }
+
+    #[test]
+    fn test_module_errors_are_mapped() {
+        assert_eq!(AppError::from(PlayerError::TrackNotFound(7)).kind(), "NotFound");
+        assert_eq!(AppError::from(PlayerError::DeviceLost).kind(), "Internal");
+        assert_eq!(AppError::from(PlayerError::LoopUnavailable).kind(), "Conflict");
+        assert_eq!(AppError::from(PlayerError::NotCached).kind(), "NotReady");
+        assert_eq!(AppError::from(PartyModeError::InvalidPin).kind(), "Unauthorized");
+        assert_eq!(AppError::from(DbPoolError::Busy).kind(), "Database");
+        let party = AppError::from(PartyModeError::Active("library_scan".to_string()));
+        assert!(party.to_string().starts_with("PartyModeActive"));
+    }
+}
@@ -0,0 +1,219 @@
+// 事件通道 - 后端事件转发到前端
+//
+// 发送端是同步的（扫描线程、投屏会话等都可以直接发送），接收端可以 await，监听任务收到即转发：
+// - 不需要加锁轮询，空闲时不占用CPU，也没有轮询间隔带来的延迟
+// - 可合并的事件（位置、进度等）在接收端来不及处理时只保留最新一条
+// - 其他事件（TrackChanged、ScanComplete等）从不丢弃
+// - 合并不会越过不可合并的事件，事件之间的先后顺序不变
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// 事件的合并规则
+pub trait Coalesce {
+    /// 同一键的事件在队列中只保留最新一条；None 表示不可合并、从不丢弃
+    fn coalesce_key(&self) -> Option<&'static str>;
+}
+
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    notify: Notify,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+}
+
+/// 接收端已关闭
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+/// 发送端，可克隆；所有发送端释放后接收端返回 None
+pub struct EventSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 接收端
+pub struct EventReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 创建事件通道（无界，可合并的事件按键合并）
+pub fn channel<T: Coalesce>() -> (EventSender<T>, EventReceiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+    });
+    (
+        EventSender { shared: Arc::clone(&shared) },
+        EventReceiver { shared },
+    )
+}
+
+impl<T: Coalesce> EventSender<T> {
+    /// 发送事件，不会阻塞
+    pub fn send(&self, event: T) -> Result<(), Closed> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(Closed);
+        }
+        push(&mut self.shared.queue.lock(), event);
+        self.shared.notify.notify_one();
+        Ok(())
+    }
+}
+
+/// 入队：队尾到上一个不可合并事件之间有同键事件时原地替换，否则追加
+fn push<T: Coalesce>(queue: &mut VecDeque<T>, event: T) {
+    if let Some(key) = event.coalesce_key() {
+        for queued in queue.iter_mut().rev() {
+            match queued.coalesce_key() {
+                Some(queued_key) if queued_key == key => {
+                    *queued = event;
+                    return;
+                }
+                Some(_) => continue,
+                None => break,
+            }
+        }
+    }
+    queue.push_back(event);
+}
+
+impl<T> Clone for EventSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for EventSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+impl<T> EventReceiver<T> {
+    /// 等待下一个事件；所有发送端都已释放且队列为空时返回 None
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(event) = self.shared.queue.lock().pop_front() {
+                return Some(event);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            // notify_one 在没有等待者时保留一次通知，检查队列后再等待不会漏掉事件
+            self.shared.notify.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for EventReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+        self.shared.queue.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum TestEvent {
+        Position(u64),
+        Progress(u64),
+        TrackChanged(i64),
+    }
+
+    impl Coalesce for TestEvent {
+        fn coalesce_key(&self) -> Option<&'static str> {
+            match self {
+                TestEvent::Position(_) => Some("position"),
+                TestEvent::Progress(_) => Some("progress"),
+                _ => None,
+            }
+        }
+    }
+
+    fn drain(rx: &mut EventReceiver<TestEvent>) -> Vec<TestEvent> {
+        std::iter::from_fn(|| rx.shared.queue.lock().pop_front()).collect()
+    }
+
+    #[test]
+    fn test_coalesces_without_crossing_barriers() {
+        let (tx, mut rx) = channel();
+        tx.send(TestEvent::Position(1)).unwrap();
+        tx.send(TestEvent::Progress(1)).unwrap();
+        tx.send(TestEvent::Position(2)).unwrap();
+        tx.send(TestEvent::TrackChanged(7)).unwrap();
+        tx.send(TestEvent::Position(0)).unwrap();
+        tx.send(TestEvent::Position(100)).unwrap();
+        tx.send(TestEvent::TrackChanged(8)).unwrap();
+        tx.send(TestEvent::TrackChanged(8)).unwrap();
+
+        assert_eq!(
+            drain(&mut rx),
+            vec![
+                TestEvent::Position(2),
+                TestEvent::Progress(1),
+                TestEvent::TrackChanged(7),
+                TestEvent::Position(100),
+                TestEvent::TrackChanged(8),
+                TestEvent::TrackChanged(8),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closes_when_senders_dropped() {
+        let (tx, mut rx) = channel();
+        let tx2 = tx.clone();
+        tx.send(TestEvent::TrackChanged(1)).unwrap();
+        drop(tx);
+        std::thread::spawn(move || tx2.send(TestEvent::TrackChanged(2)).unwrap());
+
+        assert_eq!(rx.recv().await, Some(TestEvent::TrackChanged(1)));
+        assert_eq!(rx.recv().await, Some(TestEvent::TrackChanged(2)));
+        assert_eq!(rx.recv().await, None);
+
+        let (tx, rx) = channel::<TestEvent>();
+        drop(rx);
+        assert_eq!(tx.send(TestEvent::Position(1)), Err(Closed));
+    }
+
+    /// 记录是否被唤醒的 Waker
+    #[derive(Default)]
+    struct WakeFlag(AtomicBool);
+
+    impl futures::task::ArcWake for WakeFlag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// 接收端在 recv 上等待时，发送即唤醒接收端，不依赖轮询间隔
+    #[test]
+    fn test_send_wakes_waiting_receiver() {
+        use std::future::Future;
+        use std::task::{Context, Poll};
+
+        let (tx, mut rx) = channel::<TestEvent>();
+        let flag = Arc::new(WakeFlag::default());
+        let waker = futures::task::waker(Arc::clone(&flag));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut recv = Box::pin(rx.recv());
+        assert!(recv.as_mut().poll(&mut cx).is_pending());
+        assert!(!flag.0.load(Ordering::SeqCst));
+
+        std::thread::spawn(move || tx.send(TestEvent::TrackChanged(1)).unwrap()).join().unwrap();
+        assert!(flag.0.load(Ordering::SeqCst), "发送后应立即唤醒等待中的接收端");
+        assert_eq!(recv.as_mut().poll(&mut cx), Poll::Ready(Some(TestEvent::TrackChanged(1))));
+    }
+}
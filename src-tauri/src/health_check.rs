@@ -0,0 +1,286 @@
+// 启动健康检查模块
+//
+// 应用就绪后在后台做一次快速自检，结果通过 startup-health 事件交给前端显示：
+// - 本地曲目缺失估算（先检查卷根目录和父文件夹，整盘缺失时直接短路）
+// - 已启用但无法连接的远程服务器
+// - 相对上次扫描已过期的智能歌单
+// - 数据库结构版本是否匹配
+// - 全文索引与曲目表行数是否一致
+
+use crate::db::SCHEMA_VERSION;
+use crate::db_pool::DbPool;
+use crate::remote_source::{ConnectionStatus, RemoteClientManager};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 每个文件夹最多抽样检查的曲目数
+pub const MAX_SAMPLES_PER_FOLDER: usize = 200;
+
+/// 单个远程服务器的连接检查超时
+const SERVER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 启动健康检查摘要
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupHealthSummary {
+    /// 估算的缺失曲目数
+    pub missing_tracks_estimate: usize,
+    /// 整个缺失的文件夹（用于"重新定位文件夹"）
+    pub missing_folders: Vec<String>,
+    /// 无法连接的远程服务器ID
+    pub offline_servers: Vec<String>,
+    /// 需要刷新的智能歌单ID
+    pub stale_smart_playlists: Vec<i64>,
+    /// 数据库结构版本是否匹配
+    pub schema_ok: bool,
+    /// 全文索引与曲目表行数一致（不一致时可以重建搜索索引）
+    pub search_index_ok: bool,
+}
+
+/// 缺失曲目估算结果
+#[derive(Debug, Default, PartialEq)]
+pub struct MissingEstimate {
+    pub missing_tracks_estimate: usize,
+    pub missing_folders: Vec<String>,
+}
+
+/// 获取路径所在的卷根目录
+///
+/// Windows 取盘符（如 `D:\`），Unix 下识别常见挂载点（/media/用户/卷、/run/media/用户/卷、/mnt/卷、/Volumes/卷），
+/// 其余路径归到 `/`。
+pub fn volume_root(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    match components.next() {
+        Some(Component::Prefix(prefix)) => {
+            let mut root = PathBuf::from(prefix.as_os_str());
+            root.push(std::path::MAIN_SEPARATOR.to_string());
+            root
+        }
+        Some(Component::RootDir) => {
+            let rest: Vec<_> = components
+                .take(4)
+                .filter_map(|c| match c {
+                    Component::Normal(s) => s.to_str(),
+                    _ => None,
+                })
+                .collect();
+            let depth = match rest.as_slice() {
+                ["run", "media", ..] => 4,
+                ["media", ..] => 3,
+                ["mnt", ..] | ["Volumes", ..] => 2,
+                _ => 0,
+            };
+            let mut root = PathBuf::from("/");
+            for part in rest.iter().take(depth) {
+                root.push(part);
+            }
+            root
+        }
+        _ => PathBuf::new(),
+    }
+}
+
+/// 估算缺失曲目数
+///
+/// 按父文件夹分组：卷根目录不存在时整组直接计为缺失（不再逐个检查），
+/// 文件夹不存在时同理；文件夹存在时最多抽样 MAX_SAMPLES_PER_FOLDER 首按比例估算。
+pub fn estimate_missing_tracks<R: Rng>(
+    paths: &[String],
+    exists: &mut dyn FnMut(&Path) -> bool,
+    rng: &mut R,
+    should_stop: &dyn Fn() -> bool,
+) -> MissingEstimate {
+    let mut folders: BTreeMap<PathBuf, Vec<&str>> = BTreeMap::new();
+    for path in paths {
+        let parent = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+        folders.entry(parent).or_default().push(path.as_str());
+    }
+
+    let mut root_exists: HashMap<PathBuf, bool> = HashMap::new();
+    let mut result = MissingEstimate::default();
+
+    for (folder, tracks) in folders {
+        if should_stop() {
+            break;
+        }
+
+        let root = volume_root(&folder);
+        let root_ok = *root_exists
+            .entry(root.clone())
+            .or_insert_with(|| root.as_os_str().is_empty() || exists(&root));
+
+        if !root_ok || !exists(&folder) {
+            result.missing_tracks_estimate += tracks.len();
+            result.missing_folders.push(folder.to_string_lossy().replace('\\', "/"));
+            continue;
+        }
+
+        let sample: Vec<&str> = if tracks.len() <= MAX_SAMPLES_PER_FOLDER {
+            tracks.clone()
+        } else {
+            rand::seq::index::sample(rng, tracks.len(), MAX_SAMPLES_PER_FOLDER)
+                .into_iter()
+                .map(|i| tracks[i])
+                .collect()
+        };
+
+        let missing_in_sample = sample.iter().filter(|p| !exists(Path::new(p))).count();
+        let estimate = (missing_in_sample as f64 / sample.len() as f64 * tracks.len() as f64).round();
+        result.missing_tracks_estimate += estimate as usize;
+    }
+
+    result
+}
+
+/// 运行启动健康检查，收到关闭信号时返回 None
+pub async fn run_startup_health_check(
+    db: Arc<DbPool>,
+    shutdown: &'static AtomicBool,
+) -> Option<StartupHealthSummary> {
+    let mut summary = StartupHealthSummary::default();
+
+    // 1. 数据库快照（尽快释放锁）
+    let (paths, servers, stale_playlists, schema_version, search_index) = {
+        let db = db.lock().ok()?;
+        let paths = db.get_local_track_paths().unwrap_or_default();
+        let servers = db.get_remote_servers().unwrap_or_default();
+        let stale = match db.get_last_scan_time() {
+            Ok(Some(last_scan)) => db.get_stale_smart_playlist_ids(last_scan).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let version = db.get_schema_version().unwrap_or(-1);
+        let search_index = db.search_index_counts();
+        (paths, servers, stale, version, search_index)
+    };
+    summary.stale_smart_playlists = stale_playlists;
+    summary.schema_ok = schema_version == SCHEMA_VERSION;
+    summary.search_index_ok = match search_index {
+        Ok(counts) if counts.is_consistent() => true,
+        Ok(counts) => {
+            log::warn!(
+                "⚠️ 全文索引与曲目表不一致（曲目 {}，索引 {}），搜索结果可能不完整，可在设置中重建搜索索引",
+                counts.tracks, counts.indexed
+            );
+            false
+        }
+        Err(e) => {
+            log::warn!("⚠️ 检查全文索引失败: {}", e);
+            false
+        }
+    };
+
+    // 2. 文件系统检查（阻塞IO放到专用线程）
+    let estimate = tokio::task::spawn_blocking(move || {
+        let mut rng = rand::thread_rng();
+        estimate_missing_tracks(
+            &paths,
+            &mut |p: &Path| p.exists(),
+            &mut rng,
+            &|| shutdown.load(Ordering::Relaxed),
+        )
+    })
+    .await
+    .ok()?;
+    summary.missing_tracks_estimate = estimate.missing_tracks_estimate;
+    summary.missing_folders = estimate.missing_folders;
+
+    if shutdown.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    // 3. 远程服务器连通性
+    let manager = RemoteClientManager::new(db);
+    for (id, _name, _server_type, _config_json, enabled) in servers {
+        if !enabled {
+            continue;
+        }
+        if shutdown.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let online = match manager.get_client(&id).await {
+            Ok(client) => matches!(
+                tokio::time::timeout(SERVER_CHECK_TIMEOUT, client.test_connection()).await,
+                Ok(Ok(ConnectionStatus::Connected))
+            ),
+            Err(_) => false,
+        };
+        if !online {
+            summary.offline_servers.push(id);
+        }
+    }
+
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_missing_drive_short_circuits() {
+        let paths: Vec<String> = (0..500)
+            .map(|i| format!("/mnt/usb/album{}/track{}.flac", i % 5, i))
+            .collect();
+        let mut checked = Vec::new();
+        let mut exists = |p: &Path| {
+            checked.push(p.to_path_buf());
+            false
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let result = estimate_missing_tracks(&paths, &mut exists, &mut rng, &|| false);
+
+        assert_eq!(result.missing_tracks_estimate, 500);
+        assert_eq!(result.missing_folders.len(), 5);
+        // 卷根只检查一次，且不逐个检查曲目文件
+        assert_eq!(checked, vec![PathBuf::from("/mnt/usb")]);
+    }
+
+    #[test]
+    fn test_sampling_estimate_within_bounds() {
+        let mut seed_rng = StdRng::seed_from_u64(42);
+        let paths: Vec<String> = (0..5000)
+            .map(|i| format!("/music/big/track{}.mp3", i))
+            .collect();
+        let missing: HashSet<String> = paths
+            .iter()
+            .filter(|_| seed_rng.gen_bool(0.3))
+            .cloned()
+            .collect();
+
+        let mut file_checks = 0;
+        let mut exists = |p: &Path| {
+            let s = p.to_string_lossy().to_string();
+            if s.ends_with(".mp3") {
+                file_checks += 1;
+                !missing.contains(&s)
+            } else {
+                true
+            }
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let result = estimate_missing_tracks(&paths, &mut exists, &mut rng, &|| false);
+
+        assert_eq!(file_checks, MAX_SAMPLES_PER_FOLDER);
+        let actual = missing.len() as f64;
+        let estimate = result.missing_tracks_estimate as f64;
+        assert!((estimate - actual).abs() / (paths.len() as f64) < 0.1, "estimate {} vs actual {}", estimate, actual);
+        assert!(result.missing_folders.is_empty());
+    }
+
+    #[test]
+    fn test_volume_root() {
+        assert_eq!(volume_root(Path::new("/media/alice/disk/music")), PathBuf::from("/media/alice/disk"));
+        assert_eq!(volume_root(Path::new("/mnt/usb/a/b")), PathBuf::from("/mnt/usb"));
+        assert_eq!(volume_root(Path::new("/home/alice/music")), PathBuf::from("/"));
+    }
+}
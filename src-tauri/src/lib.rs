@@ -1,4 +1,4 @@
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::Sender;
 use std::sync::{Arc, Mutex, OnceLock, atomic::{AtomicBool, Ordering}};
 use tauri::{AppHandle, Emitter, Manager, State};
 use anyhow::Result;
@@ -6,82 +6,139 @@ use anyhow::Result;
 mod player; // 新的模块化player（已完成重构）
 mod player_adapter; // PlayerCore适配器
 mod library;
+mod library_watcher; // 新增：音乐文件夹监听
 mod db;
 mod lyrics;
+mod lyrics_fetch; // 新增：批量获取歌词
+mod artist_cover_fetch; // 新增：批量获取艺术家封面
 mod playlist; // 企业级歌单系统
 mod webdav; // 新增：WebDAV客户端模块
 mod remote_source; // 新增：远程音乐源统一抽象层
+mod subsonic; // 新增：Subsonic/Navidrome客户端
 mod audio_enhancement; // 新增：音质增强设置
 mod metadata_extractor; // 新增：通用元数据提取器
+mod cover_cache; // 新增：封面存储与缩略图
+mod cover_palette; // 新增：封面取色
+mod search_query; // 新增：曲库搜索语法解析
+mod artist_split; // 新增：多艺术家拆分
+mod sort_key; // 新增：排序键和拼音/罗马字转写
+mod play_context; // 新增：按播放来源在后端生成播放队列
 mod play_history; // 新增：播放历史管理
 mod streaming; // 新增：流式播放服务（高内聚低耦合设计）
 mod network_api; // 新增：网络API服务（LrcApi集成）
 mod cache; // 新增：智能音频缓存系统
+mod party_mode; // 新增：派对模式（访客安全命令白名单）
+mod health_check; // 新增：启动健康检查
+mod sync; // 新增：上传同步队列
+mod media_session; // 新增：系统媒体会话（媒体键、SMTC、Now Playing）
+mod tray; // 新增：系统托盘
+mod tag_writer; // 新增：歌词和封面写回文件标签
+mod scrobbler; // 新增：ListenBrainz / Last.fm 播放记录提交
+mod resume_position; // 新增：长曲目续播位置
+mod chapters; // 新增：章节提取（MP4、Vorbis、CUE）
+mod secrets; // 新增：远程服务器凭据加密
+mod db_pool; // 新增：数据库连接池（单写多读）
+mod error; // 新增：命令层统一错误类型
+mod logging; // 新增：日志（tracing、滚动日志文件）
+mod diagnostics; // 新增：音频诊断和诊断包导出
+mod app_config; // 新增：应用设置（音量、主题、窗口位置、音质增强）
+mod library_backup; // 新增：曲库备份（歌单、收藏、播放历史迁移到新电脑）
+mod track_delete; // 新增：从曲库和磁盘删除曲目
+mod library_relocate; // 新增：音乐文件夹移动后批量迁移曲目路径
+mod remote_control; // 新增：HTTP远程控制
+mod cast; // 新增：UPnP/DLNA投屏
+mod transcode; // 新增：导出时音频转码（MP3、Ogg Vorbis）
+mod event_channel; // 新增：后端事件通道（可等待接收、合并高频事件）
+mod audio_file_reader; // 新增：Web Audio 播放的分块文件读取
 
 // 使用新的PlayerCore（通过适配器）
-use player::{PlayerCommand, PlayerEvent, Track, RepeatMode};
-use play_history::{PlayHistoryEntry, PlayStatistics};
+use player::{PlayerCommand, PlayerEvent, Track, Chapter, SilenceSettings, TrimPoints, RepeatMode, SleepTimer, SleepTimerStatus, MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS, DEFAULT_SLEEP_FADE_SECS, DEFAULT_PAUSE_FADE_MS, DEFAULT_SEEK_STEP_MS, DEFAULT_POSITION_INTERVAL_MS, MIN_POSITION_INTERVAL_MS, MAX_POSITION_INTERVAL_MS};
+use play_history::{PlayHistoryEntry, PlayStatistics, StatsRange, ArtistPlayStats, AlbumPlayStats, DailyPlayStats, HourlyPlayStats, PlayTracker, ScrobbleThreshold, CompletedPlay};
+use scrobbler::ScrobblerConfig;
+use resume_position::{ResumeAction, ResumeSettings, ResumeTracker};
 use player_adapter::PlayerAdapter;
+use event_channel::EventReceiver;
 use library::{Library, LibraryCommand, LibraryEvent};
-use db::{Database, Lyrics};
-use lyrics::{LyricsParser, ParsedLyrics};
+use db::{AlbumSummary, ArtistCoverPage, ArtistSummary, Database, DuplicateGroup, FolderListing, LibraryStatistics, Lyrics, SavedPosition, ScanIssue, SearchIndexRebuild, SearchSuggestion, SortDirection, TrackCovers, TrackMetadataUpdate, TrackPage, TrackSortField, VacuumStats, VerifyIssue};
+use db_pool::DbPool;
+use error::{AppError, AppResult};
+use app_config::{AppConfig, ConfigManager, WindowGeometry};
+use cover_cache::{CoverImage, CoverSize};
+use audio_file_reader::{AudioFileInfo, AudioFileRegistry};
+use lyrics::{CurrentWord, LrcParseOptions, LyricsParser, ParsedLyrics};
 use webdav::WebDAVClient;
-use webdav::types::{WebDAVConfig, WebDAVFileInfo};
-use network_api::NetworkApiService;
+use webdav::types::{WebDAVConfig, WebDAVConnectionOptions, WebDAVFileInfo};
+use network_api::{NetworkApiService, NetworkConfig, NetworkSource, SourceTestResult};
+use network_api::musicbrainz::{AlbumMatch, LookupQuery, MetadataCandidate, MusicBrainzClient};
 
 // Global state
 static PLAYER_TX: OnceLock<Sender<PlayerCommand>> = OnceLock::new();
 static LIBRARY_TX: OnceLock<Sender<LibraryCommand>> = OnceLock::new();
-pub(crate) static DB: OnceLock<Arc<Mutex<Database>>> = OnceLock::new();
+pub(crate) static DB: OnceLock<Arc<DbPool>> = OnceLock::new();
+pub(crate) static CACHE_MANAGER: OnceLock<Arc<cache::manager::CacheManager>> = OnceLock::new();
 static SHUTDOWN_SIGNAL: AtomicBool = AtomicBool::new(false);
+/// 唤醒正在等待事件的监听任务，配合 SHUTDOWN_SIGNAL 使用
+static EVENT_LISTENER_SHUTDOWN: tokio::sync::Notify = tokio::sync::Notify::const_new();
+/// 当前加载的曲目ID（随 TrackChanged 事件更新）
+static CURRENT_TRACK_ID: Mutex<Option<i64>> = Mutex::new(None);
 
 struct AppState {
-    player_rx: Arc<Mutex<Receiver<PlayerEvent>>>,
-    library_rx: Arc<Mutex<Receiver<LibraryEvent>>>,
-    db: Arc<Mutex<Database>>,
+    db: Arc<DbPool>,
+    config: Arc<ConfigManager>,
     #[allow(dead_code)]
     player_adapter: Arc<PlayerAdapter>,
 }
 
 // Tauri Commands
 
-/// 读取音频文件的完整数据（用于 Web Audio API）
+/// Web Audio 播放时打开的音频文件句柄
+static AUDIO_FILES: Lazy<Mutex<AudioFileRegistry>> = Lazy::new(|| Mutex::new(AudioFileRegistry::default()));
+
+fn audio_files() -> AppResult<std::sync::MutexGuard<'static, AudioFileRegistry>> {
+    AUDIO_FILES.lock().map_err(|_| AppError::internal("音频文件句柄表不可用"))
+}
+
+/// 打开音频文件用于 Web Audio 分块读取（只能打开音乐文件夹中的文件）
 #[tauri::command]
-async fn read_audio_file(file_path: String) -> Result<Vec<u8>, String> {
-    println!("📖 [COMMAND] read_audio_file 被调用: {}", file_path);
-    let start = std::time::Instant::now();
-    
-    // 🔥 使用 tokio 异步读取（不阻塞）
-    let data = tokio::fs::read(&file_path)
-        .await
-        .map_err(|e| format!("读取文件失败: {}", e))?;
-    
-    println!("✅ [COMMAND] 文件读取完成: {} 字节, 耗时: {}ms", 
-        data.len(), 
-        start.elapsed().as_millis()
-    );
-    
-    Ok(data)
+async fn read_audio_file_open(path: String, window: tauri::Window, state: State<'_, AppState>) -> AppResult<AudioFileInfo> {
+    let folders = state.db.run_read(|db| db.get_music_folder_paths()).await?;
+    let info = audio_files()?.open(window.label(), std::path::Path::new(&path), &folders)?;
+    log::debug!("📖 [COMMAND] 打开音频文件: {} (句柄 {}, {} 字节)", path, info.handle, info.size);
+    Ok(info)
+}
+
+/// 从 offset 开始读取一块数据（最多 MAX_CHUNK_SIZE 字节），以二进制返回
+#[tauri::command]
+async fn read_audio_file_chunk(handle: u64, offset: u64, len: u64, window: tauri::Window) -> AppResult<tauri::ipc::Response> {
+    let file = audio_files()?.file(handle, window.label())?;
+    let chunk = tokio::task::spawn_blocking(move || audio_file_reader::read_chunk(&file, offset, len)).await??;
+    Ok(tauri::ipc::Response::new(chunk))
+}
+
+/// 关闭音频文件句柄（引用计数减一）
+#[tauri::command]
+async fn read_audio_file_close(handle: u64, window: tauri::Window) -> AppResult<()> {
+    audio_files()?.close(handle, window.label())
 }
 
 /// 从数据库获取歌曲信息（用于 Web Audio Player）
 #[tauri::command]
-async fn get_track(track_id: i64, state: State<'_, AppState>) -> Result<Track, String> {
-    println!("📖 [COMMAND] get_track 被调用: track_id={}", track_id);
+async fn get_track(track_id: i64, state: State<'_, AppState>) -> AppResult<Track> {
+    log::debug!("📖 [COMMAND] get_track 被调用: track_id={}", track_id);
     
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
+    let db = state.db.lock()?;
     
     // 从数据库获取歌曲信息
     let track = db.get_track_by_id(track_id)
-        .map_err(|e| format!("获取歌曲失败: {}", e))?
-        .ok_or_else(|| format!("歌曲不存在: {}", track_id))?;
+        .map_err(|e| AppError::Database(format!("获取歌曲失败: {}", e)))?
+        .ok_or_else(|| AppError::not_found(format!("歌曲不存在: {}", track_id)))?;
     
     Ok(track)
 }
 
 /// 获取当前播放位置（用于引擎切换）
 #[tauri::command]
-async fn get_current_position() -> Result<u64, String> {
+async fn get_current_position() -> AppResult<u64> {
     let tx = PLAYER_TX.get().ok_or_else(|| "Player not initialized".to_string())?;
     
     let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
@@ -97,99 +154,419 @@ async fn get_current_position() -> Result<u64, String> {
 }
 
 #[tauri::command]
-async fn player_play(track_id: i64, timestamp: i64) -> Result<(), String> {
-    println!("🎵 [COMMAND] player_play 被调用: track_id={}, timestamp={}", track_id, timestamp);
+async fn player_play(track_id: i64, timestamp: i64) -> AppResult<()> {
     log::info!("🎵 [COMMAND] player_play 被调用: track_id={}, timestamp={}", track_id, timestamp);
     
     let tx = PLAYER_TX.get().ok_or_else(|| {
-        println!("❌ [COMMAND] PLAYER_TX 未初始化！");
         log::error!("❌ [COMMAND] PLAYER_TX 未初始化！");
         "Player not initialized".to_string()
     })?;
     
-    println!("📤 [COMMAND] 发送 Play 命令到 PlayerAdapter...");
     log::info!("📤 [COMMAND] 发送 Play 命令到 PlayerAdapter...");
     
     tx.send(PlayerCommand::Play(track_id, timestamp))
         .map_err(|e| {
-            println!("❌ [COMMAND] 发送命令失败: {}", e);
             log::error!("❌ [COMMAND] 发送命令失败: {}", e);
             e.to_string()
         })?;
     
-    println!("✅ [COMMAND] Play 命令已发送");
     log::info!("✅ [COMMAND] Play 命令已发送");
     Ok(())
 }
 
 #[tauri::command]
-async fn player_pause() -> Result<(), String> {
+async fn player_pause() -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Pause).map_err(|e| e.to_string())
+    tx.send(PlayerCommand::Pause).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_resume() -> Result<(), String> {
+async fn player_resume() -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Resume).map_err(|e| e.to_string())
+    tx.send(PlayerCommand::Resume).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_stop() -> Result<(), String> {
+async fn player_stop() -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Stop).map_err(|e| e.to_string())
+    tx.send(PlayerCommand::Stop).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_next() -> Result<(), String> {
+async fn player_next() -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Next).map_err(|e| e.to_string())
+    tx.send(PlayerCommand::Next).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_previous() -> Result<(), String> {
+async fn player_previous() -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Previous).map_err(|e| e.to_string())
+    tx.send(PlayerCommand::Previous).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_seek(position_ms: u64) -> Result<(), String> {
+async fn player_seek(position_ms: u64) -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::Seek(position_ms))
-        .map_err(|e| e.to_string())
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::SeekAndWait { position_ms, reply: reply_tx })?;
+    reply_rx.await?.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_seek_relative(steps: i64) -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SeekRelative(steps))
+        .map_err(AppError::from)
+}
+
+/// 前端外部引擎（Web Audio）同步播放位置；首次调用时由外部引擎接管，Rust端暂停输出并停止上报位置
+#[tauri::command]
+async fn player_sync_external_position(position_ms: u64, is_playing: bool) -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SyncExternalPosition { position_ms, is_playing })
+        .map_err(AppError::from)
+}
+
+/// 外部引擎交还原生播放：从指定位置开始原生播放当前曲目
+#[tauri::command]
+async fn player_handoff_to_native(position_ms: u64) -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::HandoffToNative { position_ms, reply: reply_tx })?;
+    reply_rx.await?.map_err(AppError::from)
+}
+
+/// 设置当前曲目的A-B循环区间
+#[tauri::command]
+async fn player_set_loop_region(start_ms: u64, end_ms: u64) -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::SetLoopRegion { region: Some((start_ms, end_ms)), reply: reply_tx })?;
+    reply_rx.await?.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_clear_loop_region() -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::SetLoopRegion { region: None, reply: reply_tx })?;
+    reply_rx.await?.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_next_chapter() -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::NextChapter)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_previous_chapter() -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::PreviousChapter)
+        .map_err(AppError::from)
+}
+
+/// 开启或关闭可视化数据（player-visualization-data 事件），关闭时不做任何分析
+#[tauri::command]
+async fn player_set_visualization_enabled(enabled: bool) -> AppResult<()> {
+    log::info!("📊 可视化数据: {}", if enabled { "开启" } else { "关闭" });
+    player::visualization::set_enabled(enabled);
+    Ok(())
 }
 
 #[tauri::command]
-async fn player_set_volume(volume: f32) -> Result<(), String> {
+async fn player_set_volume(volume: f32) -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
     tx.send(PlayerCommand::SetVolume(volume))
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_set_repeat(mode: RepeatMode) -> Result<(), String> {
+async fn player_set_repeat(mode: RepeatMode) -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
     tx.send(PlayerCommand::SetRepeatMode(mode))
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_set_shuffle(shuffle: bool) -> Result<(), String> {
+async fn player_set_shuffle(shuffle: bool) -> AppResult<()> {
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
     tx.send(PlayerCommand::SetShuffle(shuffle))
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// 随机播放时跳过校验未通过曲目的设置键
+const SETTING_SHUFFLE_SKIP_BROKEN: &str = "playback.shuffle_skip_broken";
+
+/// 随机播放时跳过完整性校验未通过（截断、无法解码）的曲目
+#[tauri::command]
+async fn player_set_shuffle_skip_broken(enabled: bool, state: State<'_, AppState>) -> AppResult<()> {
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_SHUFFLE_SKIP_BROKEN, &enabled.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetShuffleSkipBroken(enabled))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_shuffle_skip_broken(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_SHUFFLE_SKIP_BROKEN)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(false))
+}
+
+#[tauri::command]
+async fn player_set_gapless(enabled: bool) -> AppResult<()> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetGapless(enabled))
+        .map_err(AppError::from)
+}
+
+/// 交叉淡入淡出时长的设置键
+const SETTING_CROSSFADE_MS: &str = "audio.crossfade_ms";
+
+/// 交叉淡入淡出时长上限(ms)
+const MAX_CROSSFADE_MS: u64 = 12_000;
+
+#[tauri::command]
+async fn player_set_crossfade(duration_ms: u64, state: State<'_, AppState>) -> AppResult<()> {
+    if duration_ms > MAX_CROSSFADE_MS {
+        return Err(AppError::InvalidInput(format!("交叉淡入淡出时长不能超过 {}ms", MAX_CROSSFADE_MS)));
+    }
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_CROSSFADE_MS, &duration_ms.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetCrossfade(duration_ms))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_crossfade(state: State<'_, AppState>) -> AppResult<u64> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_CROSSFADE_MS)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+/// 睡眠定时淡出时长的设置键
+const SETTING_SLEEP_FADE_SECS: &str = "audio.sleep_fade_secs";
+
+#[tauri::command]
+async fn player_set_sleep_timer(timer: Option<SleepTimer>) -> AppResult<()> {
+    if let Some(timer) = &timer {
+        timer.validate().map_err(AppError::InvalidInput)?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetSleepTimer(timer))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_sleep_timer() -> AppResult<Option<SleepTimerStatus>> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::GetSleepTimer(reply_tx))?;
+    reply_rx.await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_set_sleep_fade(fade_secs: u64, state: State<'_, AppState>) -> AppResult<()> {
+    if !(MIN_SLEEP_FADE_SECS..=MAX_SLEEP_FADE_SECS).contains(&fade_secs) {
+        return Err(AppError::InvalidInput(format!("睡眠定时淡出时长必须在 {}-{} 秒之间", MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS)));
+    }
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_SLEEP_FADE_SECS, &fade_secs.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetSleepFade(fade_secs))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_sleep_fade(state: State<'_, AppState>) -> AppResult<u64> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_SLEEP_FADE_SECS)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_SLEEP_FADE_SECS))
+}
+
+/// 暂停/恢复淡变时长的设置键
+const SETTING_PAUSE_FADE_MS: &str = "audio.pause_fade_ms";
+
+/// 暂停/恢复淡变时长上限(ms)
+const MAX_PAUSE_FADE_MS: u64 = 500;
+
+#[tauri::command]
+async fn player_set_pause_fade(duration_ms: u64, state: State<'_, AppState>) -> AppResult<()> {
+    if duration_ms > MAX_PAUSE_FADE_MS {
+        return Err(AppError::InvalidInput(format!("暂停淡变时长不能超过 {}ms", MAX_PAUSE_FADE_MS)));
+    }
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_PAUSE_FADE_MS, &duration_ms.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetPauseFade(duration_ms))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_pause_fade(state: State<'_, AppState>) -> AppResult<u64> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_PAUSE_FADE_MS)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_PAUSE_FADE_MS))
+}
+
+/// 设置播放速度，remember 为 true 时作为当前曲目的单曲速度保存（1.0 即清除）
+#[tauri::command]
+async fn player_set_rate(rate: f32, remember: Option<bool>, state: State<'_, AppState>) -> AppResult<()> {
+    player::audio::time_stretch::validate_rate(rate).map_err(AppError::InvalidInput)?;
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetPlaybackRate(rate))?;
+
+    if remember.unwrap_or(false) {
+        let current = CURRENT_TRACK_ID.lock().ok().and_then(|id| *id);
+        if let Some(track_id) = current {
+            let db = state.inner().db.lock()?;
+            if (rate - 1.0).abs() < f32::EPSILON {
+                db.clear_track_rate(track_id)?;
+            } else {
+                db.save_track_rate(track_id, rate)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn player_get_rate() -> AppResult<f32> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::GetPlaybackRate(reply_tx))?;
+    reply_rx.await.map_err(AppError::from)
+}
+
+/// 变速时保持音高的设置键
+const SETTING_PRESERVE_PITCH: &str = "playback.preserve_pitch";
+
+#[tauri::command]
+async fn player_set_preserve_pitch(enabled: bool, state: State<'_, AppState>) -> AppResult<()> {
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_PRESERVE_PITCH, &enabled.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetPreservePitch(enabled))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_preserve_pitch(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_PRESERVE_PITCH)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(false))
+}
+
+/// 新曲目保存了单曲速度时应用（换曲时播放器已恢复原速）
+fn load_track_rate(track_id: i64) {
+    let rate = DB.get()
+        .and_then(|db| db.lock().ok()?.get_track_rate(track_id).ok().flatten());
+    if let (Some(rate), Some(tx)) = (rate, PLAYER_TX.get()) {
+        log::info!("⏩ 单曲播放速度: track_id={}, {:.2}x", track_id, rate);
+        let _ = tx.send(PlayerCommand::SetPlaybackRate(rate));
+    }
+}
+
+/// 相对跳转步长的设置键
+const SETTING_SEEK_STEP_MS: &str = "audio.seek_step_ms";
+
+/// 相对跳转步长范围(ms)
+const MIN_SEEK_STEP_MS: u64 = 1_000;
+const MAX_SEEK_STEP_MS: u64 = 60_000;
+
+#[tauri::command]
+async fn player_set_seek_step(step_ms: u64, state: State<'_, AppState>) -> AppResult<()> {
+    if !(MIN_SEEK_STEP_MS..=MAX_SEEK_STEP_MS).contains(&step_ms) {
+        return Err(AppError::InvalidInput(format!("跳转步长必须在 {}-{}ms 之间", MIN_SEEK_STEP_MS, MAX_SEEK_STEP_MS)));
+    }
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_SEEK_STEP_MS, &step_ms.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetSeekStep(step_ms))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_seek_step(state: State<'_, AppState>) -> AppResult<u64> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_SEEK_STEP_MS)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_SEEK_STEP_MS))
+}
+
+/// 位置事件上报间隔的设置键
+const SETTING_POSITION_INTERVAL_MS: &str = "audio.position_interval_ms";
+
+#[tauri::command]
+async fn player_set_position_interval(interval_ms: u64, state: State<'_, AppState>) -> AppResult<()> {
+    if !(MIN_POSITION_INTERVAL_MS..=MAX_POSITION_INTERVAL_MS).contains(&interval_ms) {
+        return Err(AppError::InvalidInput(format!(
+            "位置上报间隔必须在 {}-{}ms 之间", MIN_POSITION_INTERVAL_MS, MAX_POSITION_INTERVAL_MS
+        )));
+    }
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_POSITION_INTERVAL_MS, &interval_ms.to_string())?;
+    }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetPositionInterval(interval_ms))
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn player_load_playlist(tracks: Vec<Track>) -> Result<(), String> {
+async fn player_get_position_interval(state: State<'_, AppState>) -> AppResult<u64> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_POSITION_INTERVAL_MS)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_POSITION_INTERVAL_MS))
+}
+
+#[tauri::command]
+async fn player_load_playlist(tracks: Vec<Track>) -> AppResult<()> {
+    party_mode_record_queue_additions(tracks.len())?;
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    remote_control::update_queue(&tracks);
     tx.send(PlayerCommand::LoadPlaylist(tracks))
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// 从指定来源（曲库、专辑、歌单、收藏、搜索结果）播放曲目：后端按界面顺序取出曲目载入队列，
+/// 从 track_id 开始播放，返回来源名称和曲目数
+#[tauri::command]
+async fn player_play_track(
+    track_id: i64,
+    context: play_context::PlayContext,
+    state: State<'_, AppState>,
+) -> AppResult<play_context::PlayContextInfo> {
+    let config = state.inner().config.get().await.builtin_playlists;
+    let (tracks, info) = context.resolve_for_track(track_id, &state.inner().db, &config)?;
+    party_mode_record_queue_additions(tracks.len())?;
+
+    log::info!("🎵 从 {:?} 播放曲目 {}（{} 首）", context, track_id, info.track_count);
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    remote_control::update_queue(&tracks);
+    tx.send(PlayerCommand::LoadPlaylist(tracks))?;
+    tx.send(PlayerCommand::Play(track_id, chrono::Utc::now().timestamp_millis()))?;
+    Ok(info)
 }
 
 // 📊 系统性能监控命令
 #[tauri::command]
-async fn get_system_performance() -> Result<serde_json::Value, String> {
+async fn get_system_performance() -> AppResult<serde_json::Value> {
     use sysinfo::{System, Disks};
     
     let mut sys = System::new_all();
@@ -205,7 +582,7 @@ async fn get_system_performance() -> Result<serde_json::Value, String> {
     let memory_usage = (used_memory as f64 / total_memory as f64 * 100.0) as f32;
     
     // 当前进程信息
-    let pid = sysinfo::get_current_pid().map_err(|e| e.to_string())?;
+    let pid = sysinfo::get_current_pid()?;
     let process_memory = sys.process(pid)
         .map(|p| p.memory())
         .unwrap_or(0);
@@ -245,216 +622,206 @@ async fn get_system_performance() -> Result<serde_json::Value, String> {
     }))
 }
 
+// ⚙️ 应用设置命令
+#[tauri::command]
+async fn config_get(state: State<'_, AppState>) -> AppResult<AppConfig> {
+    Ok(state.inner().config.get().await)
+}
+
+/// 提交部分设置（JSON Merge Patch，null 恢复默认值），返回新的完整设置
+#[tauri::command]
+async fn config_update(patch: serde_json::Value, state: State<'_, AppState>) -> AppResult<AppConfig> {
+    let previous = state.inner().config.get().await;
+    let config = state.inner().config.update(patch).await?;
+    audio_enhancement::publish_settings(config.audio.enhancement.clone());
+    if config.audio.volume != previous.audio.volume {
+        apply_volume_settings(config.audio.volume);
+    }
+    if config.network != previous.network {
+        network_api::clear_cache();
+    }
+    log::info!("⚙️ 应用设置已更新");
+    Ok(config)
+}
+
+/// 把音量曲线和前置放大下发到播放器
+fn apply_volume_settings(settings: player::audio::VolumeSettings) {
+    if let Some(tx) = PLAYER_TX.get() {
+        let _ = tx.send(PlayerCommand::SetVolumeSettings(settings));
+    }
+}
+
 // 🎵 音质增强命令
-use audio_enhancement::{AudioEnhancementSettings, EqualizerPresets};
+use audio_enhancement::{AudioEnhancementSettings, EqualizerPresetInfo, EqualizerPresets, ReplayGainStatus, UserEqualizerPreset};
+use player::audio::parametric_eq::CorrectionProfile;
 use once_cell::sync::Lazy;
 
-// 全局音质增强设置存储
-static AUDIO_ENHANCEMENT_SETTINGS: Lazy<Mutex<AudioEnhancementSettings>> = 
-    Lazy::new(|| Mutex::new(AudioEnhancementSettings::default()));
-
 #[tauri::command]
-async fn get_audio_enhancement_settings() -> Result<AudioEnhancementSettings, String> {
+async fn get_audio_enhancement_settings(state: State<'_, AppState>) -> AppResult<AudioEnhancementSettings> {
     log::info!("🎵 获取音质增强设置");
-    let settings = AUDIO_ENHANCEMENT_SETTINGS
-        .lock()
-        .map_err(|e| format!("锁定设置失败: {}", e))?
-        .clone();
-    Ok(settings)
+    Ok(state.inner().config.get().await.audio.enhancement)
 }
 
 #[tauri::command]
-async fn set_audio_enhancement_settings(settings: AudioEnhancementSettings) -> Result<(), String> {
-    log::info!("🎵 更新音质增强设置: enabled={}", settings.enabled);
+async fn set_audio_enhancement_settings(settings: AudioEnhancementSettings, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("🎵 更新音质增强设置: enabled={}, mono={}, balance={:.2}", settings.enabled, settings.mono, settings.balance);
     
-    // 验证设置
-    if settings.equalizer.gains.iter().any(|&g| g < -12.0 || g > 12.0) {
-        return Err("均衡器增益必须在-12dB到+12dB之间".to_string());
-    }
-    
-    if settings.bass_boost.gain < 0.0 || settings.bass_boost.gain > 12.0 {
-        return Err("低音增强必须在0到12dB之间".to_string());
-    }
-    
-    // 更新全局设置
-    *AUDIO_ENHANCEMENT_SETTINGS
-        .lock()
-        .map_err(|e| format!("锁定设置失败: {}", e))? = settings;
+    // 保存到应用设置（保存前校验），并下发到播放链路
+    let config = state.inner().config.modify(|config| config.audio.enhancement = settings).await?;
+    audio_enhancement::publish_settings(config.audio.enhancement);
     
     log::info!("✅ 音质增强设置已更新");
     Ok(())
 }
 
+/// 获取曲目的ReplayGain信息和按当前设置实际应用的增益
+#[tauri::command]
+async fn player_get_replay_gain(track_id: i64, state: State<'_, AppState>) -> AppResult<ReplayGainStatus> {
+    let info = {
+        let db = state.inner().db.lock()?;
+        db.get_replay_gain(track_id)?
+    };
+    let settings = audio_enhancement::current_settings().replay_gain;
+    Ok(ReplayGainStatus {
+        mode: settings.mode,
+        track_gain_db: info.and_then(|i| i.track_gain_db),
+        album_gain_db: info.and_then(|i| i.album_gain_db),
+        loudness_lufs: info.and_then(|i| i.loudness_lufs),
+        applied_gain_db: settings.resolve_gain_db(info.as_ref()),
+    })
+}
+
+/// 获取均衡器预设列表：内置预设（只读）在前，其后是用户预设
 #[tauri::command]
-async fn get_equalizer_presets() -> Result<Vec<(String, Vec<f32>)>, String> {
+async fn get_equalizer_presets(state: State<'_, AppState>) -> AppResult<Vec<EqualizerPresetInfo>> {
     log::info!("🎵 获取均衡器预设列表");
-    let presets = EqualizerPresets::all()
-        .into_iter()
-        .map(|(name, gains)| (name.to_string(), gains.to_vec()))
-        .collect();
-    Ok(presets)
+    let config = state.inner().config.get().await;
+    Ok(EqualizerPresets::list(&config.audio.user_presets))
 }
 
 #[tauri::command]
-async fn apply_equalizer_preset(preset_name: String) -> Result<(), String> {
+async fn apply_equalizer_preset(preset_name: String, state: State<'_, AppState>) -> AppResult<()> {
     log::info!("🎵 应用均衡器预设: {}", preset_name);
     
-    let gains = EqualizerPresets::get(&preset_name)
-        .ok_or_else(|| format!("未找到预设: {}", preset_name))?;
-    
-    let mut settings = AUDIO_ENHANCEMENT_SETTINGS
-        .lock()
-        .map_err(|e| format!("锁定设置失败: {}", e))?;
-    
-    settings.equalizer.gains = gains;
-    settings.equalizer.preset = Some(preset_name.clone());
+    let previous = state.inner().config.get().await;
+    let config = state.inner().config.try_modify(|config| {
+        let preset = EqualizerPresets::resolve(&config.audio.user_presets, &preset_name)
+            .ok_or_else(|| AppError::not_found(format!("未找到预设: {}", preset_name)))?;
+        config.audio.enhancement.equalizer.gains = preset.gains;
+        config.audio.enhancement.equalizer.preset = Some(preset_name.clone());
+        if let Some(preamp_db) = preset.preamp_db {
+            config.audio.volume.preamp_db = preamp_db;
+        }
+        Ok(())
+    }).await?;
+    if config.audio.volume != previous.audio.volume {
+        apply_volume_settings(config.audio.volume);
+    }
+    audio_enhancement::publish_settings(config.audio.enhancement);
     
     log::info!("✅ 已应用预设: {}", preset_name);
     Ok(())
 }
 
-// 🔧 音频设备诊断和修复命令
-
+/// 导入 AutoEq 参数均衡配置作为耳机校正配置（替换当前的校正配置，是否启用不变）
+///
+/// text_or_path 为单行且指向存在的文件时读取该文件，名称默认取文件名；否则作为配置文本解析
 #[tauri::command]
-async fn diagnose_audio_system() -> Result<String, String> {
-    use std::process::Command;
-    
-    let mut diagnostics = Vec::new();
-    diagnostics.push("=== WindChime Player 音频系统诊断 ===".to_string());
-    
-    // 1. 检查Windows音频服务状态
-    if let Ok(output) = Command::new("sc")
-        .args(&["query", "audiosrv"])
-        .output()
-    {
-        let status = String::from_utf8_lossy(&output.stdout);
-        if status.contains("RUNNING") {
-            diagnostics.push("✅ Windows Audio服务运行正常".to_string());
-        } else {
-            diagnostics.push("❌ Windows Audio服务未运行".to_string());
-        }
-    }
-    
-    // 2. 检查音频进程
-    if let Ok(output) = Command::new("tasklist")
-        .args(&["/FI", "IMAGENAME eq audiodg.exe"])
-        .output()
-    {
-        let processes = String::from_utf8_lossy(&output.stdout);
-        if processes.contains("audiodg.exe") {
-            diagnostics.push("✅ Windows音频引擎进程运行正常".to_string());
-        } else {
-            diagnostics.push("❌ Windows音频引擎进程未找到".to_string());
-        }
-    }
-    
-    // 3. 检查可能冲突的音频应用
-    let audio_apps = ["spotify.exe", "chrome.exe", "firefox.exe", "vlc.exe", "wmplayer.exe"];
-    for app in &audio_apps {
-        if let Ok(output) = Command::new("tasklist")
-            .args(&["/FI", &format!("IMAGENAME eq {}", app)])
-            .output()
-        {
-            let processes = String::from_utf8_lossy(&output.stdout);
-            if processes.contains(app) {
-                diagnostics.push(format!("⚠️ 发现可能冲突的应用: {}", app));
-            }
-        }
-    }
-    
-    // 4. 检查Senary音频应用
-    if let Ok(output) = Command::new("tasklist")
-        .args(&["/FI", "IMAGENAME eq SenaryAudioApp*"])
-        .output()
-    {
-        let processes = String::from_utf8_lossy(&output.stdout);
-        if processes.contains("SenaryAudioApp") {
-            diagnostics.push("⚠️ 检测到Senary音频增强软件，可能造成设备独占".to_string());
-        }
-    }
+async fn equalizer_import_autoeq(text_or_path: String, name: Option<String>, state: State<'_, AppState>) -> AppResult<CorrectionProfile> {
+    let path = std::path::Path::new(text_or_path.trim());
+    let (text, default_name) = if !text_or_path.contains('\n') && path.is_file() {
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().trim_end_matches("ParametricEQ").trim().to_string())
+            .unwrap_or_default();
+        (std::fs::read_to_string(path)?, name)
+    } else {
+        (text_or_path.clone(), String::new())
+    };
+    let name = name.filter(|n| !n.trim().is_empty())
+        .or_else(|| (!default_name.is_empty()).then_some(default_name))
+        .unwrap_or_else(|| "AutoEq".to_string());
     
-    diagnostics.push("".to_string());
-    diagnostics.push("=== 建议的解决方案 ===".to_string());
-    diagnostics.push("1. 关闭其他音频应用".to_string());
-    diagnostics.push("2. 重新插拔耳机设备".to_string());
-    diagnostics.push("3. 在Windows设置中切换默认音频设备".to_string());
-    diagnostics.push("4. 尝试重启WindChime Player".to_string());
-    diagnostics.push("5. 如果问题持续，请尝试重启计算机".to_string());
+    let profile = CorrectionProfile::parse(&name, &text).map_err(AppError::InvalidInput)?;
+    log::info!("🎧 导入耳机校正配置: {}（{}个滤波器，前置放大 {}dB）", profile.name, profile.filters.len(), profile.preamp_db);
     
-    Ok(diagnostics.join("\n"))
+    let imported = profile.clone();
+    let config = state.inner().config.modify(|config| config.audio.enhancement.correction.profile = Some(imported)).await?;
+    audio_enhancement::publish_settings(config.audio.enhancement);
+    Ok(profile)
 }
 
+/// 导出当前的耳机校正配置（ParametricEQ 文本，可在其他设备导入）
 #[tauri::command]
-async fn fix_audio_system() -> Result<String, String> {
-    use std::process::Command;
-    
-    log::info!("🔧 用户请求修复音频系统");
-    
-    // 尝试重启Windows音频服务
-    let mut results = Vec::new();
-    
-    // 1. 停止音频服务
-    match Command::new("net")
-        .args(&["stop", "audiosrv"])
-        .output()
-    {
-        Ok(_) => {
-            results.push("✅ 已停止Windows Audio服务".to_string());
-            std::thread::sleep(std::time::Duration::from_millis(2000));
-        }
-        Err(e) => {
-            results.push(format!("❌ 停止音频服务失败: {}", e));
-        }
-    }
-    
-    // 2. 启动音频服务
-    match Command::new("net")
-        .args(&["start", "audiosrv"])
-        .output()
-    {
-        Ok(_) => {
-            results.push("✅ 已启动Windows Audio服务".to_string());
-        }
-        Err(e) => {
-            results.push(format!("❌ 启动音频服务失败: {}", e));
-        }
-    }
-    
-    // 3. 等待服务稳定
-    std::thread::sleep(std::time::Duration::from_millis(3000));
-    results.push("⏱️ 等待音频服务稳定...".to_string());
-    
-    results.push("".to_string());
-    results.push("🎵 音频系统修复完成！请尝试重新播放音乐。".to_string());
-    
-    Ok(results.join("\n"))
+async fn equalizer_export_autoeq(state: State<'_, AppState>) -> AppResult<String> {
+    let config = state.inner().config.get().await;
+    let profile = config.audio.enhancement.correction.profile
+        .ok_or_else(|| AppError::not_found("没有导入的耳机校正配置"))?;
+    Ok(profile.export())
 }
 
+/// 保存用户均衡器预设（同名的用户预设被覆盖）
 #[tauri::command]
-async fn reset_audio_device() -> Result<String, String> {
-    log::info!("🔧 用户请求重置音频设备");
-    
-    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
-    tx.send(PlayerCommand::ResetAudioDevice)
-        .map_err(|e| e.to_string())?;
-    
-    Ok("🎵 音频设备重置命令已发送，请稍候...".to_string())
+async fn equalizer_save_preset(name: String, gains: [f32; 10], preamp_db: Option<f32>, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("🎵 保存均衡器预设: {}", name);
+    let preset = UserEqualizerPreset { gains, preamp_db };
+    state.inner().config.try_modify(|config| config.audio.save_user_preset(&name, preset)).await?;
+    Ok(())
 }
 
+/// 删除用户均衡器预设；删除的是当前预设时增益保持不变
 #[tauri::command]
-async fn library_scan(paths: Vec<String>) -> Result<(), String> {
-    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
-    tx.send(LibraryCommand::Scan(paths))
-        .map_err(|e| e.to_string())
+async fn equalizer_delete_preset(name: String, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("🎵 删除均衡器预设: {}", name);
+    let config = state.inner().config.try_modify(|config| config.audio.delete_user_preset(&name)).await?;
+    audio_enhancement::publish_settings(config.audio.enhancement);
+    Ok(())
 }
 
 #[tauri::command]
-async fn library_get_tracks() -> Result<(), String> {
-    log::info!("📞 前端调用library_get_tracks命令");
+async fn equalizer_rename_preset(name: String, new_name: String, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("🎵 重命名均衡器预设: {} -> {}", name, new_name);
+    let config = state.inner().config.try_modify(|config| config.audio.rename_user_preset(&name, &new_name)).await?;
+    audio_enhancement::publish_settings(config.audio.enhancement);
+    Ok(())
+}
+
+// 🔧 音频设备诊断和修复命令
+
+#[tauri::command]
+async fn diagnose_audio_system() -> AppResult<String> {
+    Ok(tokio::task::spawn_blocking(diagnostics::audio_system_checks).await?)
+}
+
+#[tauri::command]
+async fn fix_audio_system() -> AppResult<String> {
+    log::info!("🔧 用户请求修复音频系统");
+    Ok(tokio::task::spawn_blocking(diagnostics::fix_audio_system).await?)
+}
+
+#[tauri::command]
+async fn reset_audio_device() -> AppResult<String> {
+    log::info!("🔧 用户请求重置音频设备");
+    
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::ResetAudioDevice)?;
+    
+    Ok("🎵 音频设备重置命令已发送，请稍候...".to_string())
+}
+
+#[tauri::command]
+async fn library_scan(paths: Vec<String>) -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::Scan(paths))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_get_tracks() -> AppResult<()> {
+    log::info!("📞 前端调用library_get_tracks命令");
     let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
     log::info!("📨 向Library发送GetTracks命令...");
     let send_result = tx.send(LibraryCommand::GetTracks)
-        .map_err(|e| e.to_string());
+        .map_err(AppError::from);
     if send_result.is_ok() {
         log::info!("✅ GetTracks命令已发送");
     } else {
@@ -464,79 +831,454 @@ async fn library_get_tracks() -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn library_search(query: String) -> Result<(), String> {
+async fn library_set_tracks_chunk_size(chunk_size: usize, state: State<'_, AppState>) -> AppResult<()> {
+    if !(library::MIN_TRACKS_CHUNK_SIZE..=library::MAX_TRACKS_CHUNK_SIZE).contains(&chunk_size) {
+        return Err(AppError::InvalidInput(format!(
+            "每批曲目数必须在 {}-{} 之间", library::MIN_TRACKS_CHUNK_SIZE, library::MAX_TRACKS_CHUNK_SIZE
+        )));
+    }
+    let db = state.inner().db.lock()?;
+    db.set_setting(library::SETTING_TRACKS_CHUNK_SIZE, &chunk_size.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn library_get_tracks_chunk_size(state: State<'_, AppState>) -> AppResult<usize> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(library::SETTING_TRACKS_CHUNK_SIZE)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(library::DEFAULT_TRACKS_CHUNK_SIZE))
+}
+
+#[tauri::command]
+async fn library_search(query: String) -> AppResult<()> {
     let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
     tx.send(LibraryCommand::SearchTracks(query))
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// 搜索补全：按前缀匹配艺术家、专辑、标题
+#[tauri::command]
+async fn library_search_suggest(
+    prefix: String,
+    limit: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SearchSuggestion>> {
+    let db = state.inner().db.lock()?;
+    db.suggest_search(&prefix, limit.unwrap_or(10).min(50)).map_err(AppError::from)
 }
 
+/// 最近的搜索词（新的在前）
 #[tauri::command]
-async fn library_get_stats() -> Result<(), String> {
+async fn search_history_get(state: State<'_, AppState>) -> AppResult<Vec<String>> {
+    let db = state.inner().db.lock()?;
+    db.get_search_history().map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn search_history_clear(state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.clear_search_history().map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_get_stats() -> AppResult<()> {
     let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
     tx.send(LibraryCommand::GetStats)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
+/// 曲库统计（占用空间、格式分布、比特率、缺少封面/歌词的曲目数等）
 #[tauri::command]
-async fn library_rescan_covers() -> Result<(), String> {
+async fn library_get_statistics(state: State<'_, AppState>) -> AppResult<LibraryStatistics> {
+    state.inner().db.run_read(|db| db.get_library_statistics()).await
+}
+
+/// 导出曲库备份（歌单、收藏、播放历史、歌词和远程服务器配置，不含音频和封面）
+#[tauri::command]
+async fn library_export_backup(
+    path: String,
+    include_credentials: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    log::info!("💾 导出曲库备份: {}", path);
+    state.inner().db.run_read(move |db| {
+        let backup = db.export_library_backup(include_credentials.unwrap_or(false))?;
+        library_backup::write_archive(std::path::Path::new(&path), &backup)
+    }).await
+}
+
+/// 把曲库备份还原到当前曲库，返回各类记录的匹配和还原数量
+#[tauri::command]
+async fn library_import_backup(
+    path: String,
+    options: Option<library_backup::ImportOptions>,
+    state: State<'_, AppState>,
+) -> AppResult<library_backup::ImportReport> {
+    log::info!("💾 还原曲库备份: {}", path);
+    let backup = tokio::task::spawn_blocking(move || library_backup::read_archive(std::path::Path::new(&path)))
+        .await?
+        .map_err(|e| AppError::invalid_input(format!("{:#}", e)))?;
+    let options = options.unwrap_or_default();
+    state.inner().db.run_write(move |db| db.import_library_backup(&backup, &options)).await
+}
+
+#[tauri::command]
+async fn library_rescan_covers() -> AppResult<()> {
     let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
     tx.send(LibraryCommand::RescanAll)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_analyze_loudness() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::AnalyzeLoudness)
+        .map_err(AppError::from)
+}
+
+/// 按当前静音阈值分析曲库中尚未分析的曲目（与响度分析共用后台任务，进度见 library-analysis-progress）
+#[tauri::command]
+async fn library_analyze_silence() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::AnalyzeSilence)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_cancel_loudness_analysis() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::CancelLoudnessAnalysis)
+        .map_err(AppError::from)
+}
+
+/// 开启/关闭扫描后的声学指纹计算（用于发现重新编码的重复曲目）
+#[tauri::command]
+async fn library_set_fingerprinting(enabled: bool) -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::EnableFingerprinting(enabled))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_get_fingerprinting(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(library::SETTING_FINGERPRINT_ENABLED)?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+#[tauri::command]
+async fn library_cancel_fingerprinting() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::CancelFingerprinting)
+        .map_err(AppError::from)
 }
 
+/// 补算时长缺失或为0的曲目（后台任务，进度见 library-duration-backfill-progress，每首更新后发出 track-updated）
 #[tauri::command]
-async fn library_get_music_folders(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.get_music_folder_paths().map_err(|e| e.to_string())
+async fn library_backfill_durations() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::BackfillDurations)
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_cancel_duration_backfill() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::CancelDurationBackfill)
+        .map_err(AppError::from)
+}
+
+/// 查找重复曲目：文件哈希相同的为完全重复，声学指纹相似的标记为 acoustic_match
+#[tauri::command]
+async fn library_find_duplicates(
+    threshold: Option<f32>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<DuplicateGroup>> {
+    let threshold = threshold.unwrap_or(player::audio::fingerprint::DEFAULT_SIMILARITY_THRESHOLD);
+    state.inner().db.run_read(move |db| db.find_duplicates(threshold.clamp(0.5, 1.0))).await
+}
+
+/// 开启/关闭音乐文件夹自动监听
+#[tauri::command]
+async fn library_set_watcher(enabled: bool) -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::EnableWatcher(enabled))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_get_watcher(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(library::SETTING_WATCHER_ENABLED)?;
+    Ok(value.as_deref() == Some("true"))
 }
 
+/// 按应用设置中的分隔符和不拆分的名称重新拆分所有曲目的艺术家（进度见 library-artist-split-progress）
 #[tauri::command]
-async fn library_delete_folder(folder_path: String, state: State<'_, AppState>) -> Result<usize, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.delete_folder_tracks(&folder_path).map_err(|e| e.to_string())
+async fn library_rebuild_track_artists() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::RebuildTrackArtists)
+        .map_err(AppError::from)
+}
+
+/// 曲目拆分后的艺术家及角色（主艺术家 / 客串）
+#[tauri::command]
+async fn library_get_track_artists(track_id: i64, state: State<'_, AppState>) -> AppResult<Vec<artist_split::TrackArtist>> {
+    state.inner().db.run_read(move |db| db.get_track_artists(track_id)).await
+}
+
+/// 元数据不完整的曲目及原因（界面显示"元数据不完整"标记）
+#[tauri::command]
+async fn library_get_scan_issues(state: State<'_, AppState>) -> AppResult<Vec<ScanIssue>> {
+    state.inner().db.run_read(|db| db.get_scan_issues()).await
+}
+
+/// 完整解码校验曲目（后台任务，进度见 library-verify-progress，结束时发出 library-verify-complete），
+/// track_ids 为空时校验全部本地曲目
+#[tauri::command]
+async fn library_verify(track_ids: Option<Vec<i64>>) -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::Verify(track_ids))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn library_cancel_verify() -> AppResult<()> {
+    let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+    tx.send(LibraryCommand::CancelVerify)
+        .map_err(AppError::from)
+}
+
+/// 完整性校验未通过的曲目（截断或无法解码）
+#[tauri::command]
+async fn library_get_verify_issues(state: State<'_, AppState>) -> AppResult<Vec<VerifyIssue>> {
+    state.inner().db.run_read(|db| db.get_verify_issues()).await
+}
+
+/// 开启/关闭扫描后对新曲目的完整性校验
+#[tauri::command]
+async fn library_set_verify_on_scan(enabled: bool, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.set_setting(library::SETTING_VERIFY_ON_SCAN, if enabled { "true" } else { "false" })?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn library_get_verify_on_scan(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(library::SETTING_VERIFY_ON_SCAN)?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// 设置元数据读取失败时从路径推断字段的模板，如 "{artist}/{album}/{track} {title}"
+#[tauri::command]
+async fn library_set_path_pattern(pattern: String, state: State<'_, AppState>) -> AppResult<()> {
+    let pattern = metadata_extractor::PathPattern::parse(&pattern).map_err(AppError::InvalidInput)?;
+    let db = state.inner().db.lock()?;
+    db.set_setting(library::SETTING_PATH_PATTERN, pattern.as_str())?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn library_get_path_pattern(state: State<'_, AppState>) -> AppResult<String> {
+    let db = state.inner().db.lock()?;
+    Ok(library::load_path_pattern(&db).as_str().to_string())
+}
+
+/// 开启或关闭拼音/罗马字搜索，为所有曲目重新生成转写，返回曲目数
+#[tauri::command]
+async fn library_set_transliterate_search(enabled: bool, state: State<'_, AppState>) -> AppResult<usize> {
+    state.inner().db.run_write(move |db| db.set_transliterate_search(enabled)).await
+}
+
+#[tauri::command]
+async fn library_get_transliterate_search(state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    Ok(db.transliterate_search())
+}
+
+/// 关闭窗口时隐藏到托盘（继续播放）还是退出
+#[tauri::command]
+async fn ui_set_close_to_tray(enabled: bool, state: State<'_, AppState>) -> AppResult<()> {
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(tray::SETTING_CLOSE_TO_TRAY, if enabled { "true" } else { "false" })?;
+    }
+    tray::set_close_to_tray(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+async fn ui_get_close_to_tray() -> AppResult<bool> {
+    Ok(tray::close_to_tray())
+}
+
+#[tauri::command]
+async fn library_get_music_folders(state: State<'_, AppState>) -> AppResult<Vec<String>> {
+    let db = state.inner().db.lock()?;
+    db.get_music_folder_paths().map_err(AppError::from)
+}
+
+/// 按文件夹浏览曲库，path 为空时列出各音乐文件夹和远程服务器
+#[tauri::command]
+async fn library_browse_folder(path: Option<String>, state: State<'_, AppState>) -> AppResult<FolderListing> {
+    state.inner().db.run_read(move |db| db.browse_folder(path.as_deref().filter(|p| !p.is_empty()))).await
+}
+
+/// 分页获取曲目（不含封面，直接查询数据库，不经过Library）
+#[tauri::command]
+async fn library_get_tracks_page(
+    offset: i64,
+    limit: i64,
+    sort_by: Option<TrackSortField>,
+    sort_dir: Option<SortDirection>,
+    filter: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<TrackPage> {
+    state.inner().db.run_read(move |db| db.get_tracks_page(
+        offset,
+        limit,
+        sort_by.unwrap_or_default(),
+        sort_dir.unwrap_or_default(),
+        filter.as_deref(),
+    ))
+    .await
+}
+
+/// 专辑列表（聚合数据，不含封面）
+#[tauri::command]
+async fn library_get_albums(state: State<'_, AppState>) -> AppResult<Vec<AlbumSummary>> {
+    state.inner().db.run_read(|db| db.get_albums()).await
+}
+
+/// 艺术家列表（聚合数据）
+#[tauri::command]
+async fn library_get_artists(state: State<'_, AppState>) -> AppResult<Vec<ArtistSummary>> {
+    state.inner().db.run_read(|db| db.get_artists()).await
+}
+
+/// 专辑曲目，按碟片号和音轨号排序
+#[tauri::command]
+async fn library_get_album_tracks(
+    album: String,
+    album_artist: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<Track>> {
+    state.inner().db.run_read(move |db| db.get_album_tracks(&album, album_artist.as_deref())).await
+}
+
+#[tauri::command]
+async fn library_delete_folder(folder_path: String, state: State<'_, AppState>) -> AppResult<usize> {
+    let db = state.inner().db.lock()?;
+    db.delete_folder_tracks(&folder_path).map_err(AppError::from)
+}
+
+/// 从曲库删除曲目，delete_files 时同时把文件移到回收站，返回各文件的删除结果
+///
+/// 正在播放的曲目默认拒绝删除，force 时先停止播放
+#[tauri::command]
+async fn library_delete_tracks(
+    track_ids: Vec<i64>,
+    delete_files: bool,
+    force: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<track_delete::DeleteTracksReport> {
+    let current = CURRENT_TRACK_ID.lock().ok().and_then(|id| *id);
+    if let Some(current) = current.filter(|id| track_ids.contains(id)) {
+        if !force.unwrap_or(false) {
+            return Err(AppError::conflict(format!("曲目正在播放，无法删除: {}", current)));
+        }
+        let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+        tx.send(PlayerCommand::Stop).map_err(AppError::from)?;
+    }
+
+    let db = state.inner().db.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        track_delete::delete_tracks(&db, &track_ids, delete_files, track_delete::move_to_trash)
+    })
+    .await??;
+    let _ = app.emit(
+        "library-tracks-changed",
+        &LibraryEvent::TracksChanged { added: 0, updated: 0, removed: report.removed },
+    );
+    Ok(report)
+}
+
+/// 音乐文件夹移动后把 old_prefix 下的曲目路径改为 new_prefix（dry_run 时只返回预览）
+#[tauri::command]
+async fn library_relocate(
+    old_prefix: String,
+    new_prefix: String,
+    dry_run: bool,
+    verify_content: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<library_relocate::RelocateReport> {
+    let db = state.inner().db.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        library_relocate::relocate(&db, &old_prefix, &new_prefix, dry_run, verify_content.unwrap_or(false))
+    })
+    .await??;
+    if report.updated > 0 {
+        let _ = app.emit(
+            "library-tracks-changed",
+            &LibraryEvent::TracksChanged { added: 0, updated: report.updated, removed: 0 },
+        );
+    }
+    Ok(report)
+}
+
+/// 列出文件已不存在的本地曲目
+#[tauri::command]
+async fn library_find_missing(state: State<'_, AppState>) -> AppResult<Vec<library_relocate::MissingTrack>> {
+    let db = state.inner().db.clone();
+    tokio::task::spawn_blocking(move || library_relocate::find_missing(&db)).await?
 }
 
 // Lyrics commands
 #[tauri::command]
-async fn lyrics_get(track_id: i64, state: State<'_, AppState>) -> Result<Option<Lyrics>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.get_lyrics_by_track_id(track_id).map_err(|e| e.to_string())
+async fn lyrics_get(track_id: i64, state: State<'_, AppState>) -> AppResult<Option<Lyrics>> {
+    let db = state.inner().db.lock()?;
+    db.get_lyrics_by_track_id(track_id).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_parse(content: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_parse(content: String, merge_translations: Option<bool>) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.parse_lrc(&content).map_err(|e| e.to_string())
+    let options = LrcParseOptions {
+        merge_translations: merge_translations.unwrap_or(true),
+    };
+    parser.parse_lrc_with_options(&content, options).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_save(track_id: i64, content: String, format: String, source: String, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
+async fn lyrics_save(track_id: i64, content: String, format: String, source: String, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
     db.insert_lyrics(track_id, &content, &format, &source)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
         .map(|_| ())
 }
 
 #[tauri::command]
-async fn lyrics_delete(track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.delete_lyrics(track_id).map_err(|e| e.to_string())
+async fn lyrics_delete(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.delete_lyrics(track_id).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_refresh(track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
+async fn lyrics_refresh(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
     // 获取曲目信息
     let track = {
-        let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-        db.get_track_by_id(track_id)
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Track not found".to_string())?
+        let db = state.inner().db.lock()?;
+        db.get_track_by_id(track_id)?
+            .ok_or_else(|| AppError::not_found("Track not found"))?
     };
 
     // 删除旧的临时歌词（保留用户手动添加的）
     {
-        let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-        db.delete_lyrics_by_source(track_id, "temp").map_err(|e| e.to_string())?;
+        let db = state.inner().db.lock()?;
+        db.delete_lyrics_by_source(track_id, "temp")?;
     }
 
     // 重新搜索歌词
@@ -546,123 +1288,515 @@ async fn lyrics_refresh(track_id: i64, state: State<'_, AppState>) -> Result<(),
         let lrc_content = parser.format_as_lrc(&parsed);
         
         // 保存到数据库
-        let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-        db.insert_lyrics(track_id, &lrc_content, "lrc", "temp")
-            .map_err(|e| e.to_string())?;
+        let db = state.inner().db.lock()?;
+        db.insert_lyrics(track_id, &lrc_content, "lrc", "temp")?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn lyrics_search_file(audio_path: String) -> Result<Option<String>, String> {
+async fn lyrics_search_file(audio_path: String) -> AppResult<Option<String>> {
     let parser = LyricsParser::new();
     Ok(parser.find_lyrics_file(&audio_path))
 }
 
 #[tauri::command]
-async fn lyrics_load_file(file_path: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_load_file(file_path: String) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.load_from_file(&file_path).map_err(|e| e.to_string())
+    parser.load_from_file(&file_path).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_extract_from_metadata(audio_path: String) -> Result<Option<ParsedLyrics>, String> {
+async fn lyrics_extract_from_metadata(audio_path: String) -> AppResult<Option<ParsedLyrics>> {
     let parser = LyricsParser::new();
-    parser.extract_from_audio_metadata(&audio_path).map_err(|e| e.to_string())
+    parser.extract_from_audio_metadata(&audio_path).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_search_comprehensive(audio_path: String) -> Result<Option<ParsedLyrics>, String> {
+async fn lyrics_search_comprehensive(audio_path: String) -> AppResult<Option<ParsedLyrics>> {
     let parser = LyricsParser::new();
-    parser.search_lyrics_comprehensive(&audio_path).map_err(|e| e.to_string())
+    parser.search_lyrics_comprehensive(&audio_path).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_validate(content: String) -> Result<(bool, String, Vec<String>), String> {
+async fn lyrics_validate(content: String) -> AppResult<(bool, String, Vec<String>)> {
     let parser = LyricsParser::new();
     Ok(parser.validate_lyrics(&content))
 }
 
 #[tauri::command]
-async fn lyrics_parse_srt(content: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_parse_srt(content: String) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.parse_srt(&content).map_err(|e| e.to_string())
+    parser.parse_srt(&content).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_parse_ass(content: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_parse_ass(content: String) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.parse_ass(&content).map_err(|e| e.to_string())
+    parser.parse_ass(&content).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_parse_vtt(content: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_parse_vtt(content: String) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.parse_vtt(&content).map_err(|e| e.to_string())
+    parser.parse_vtt(&content).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_auto_detect(content: String) -> Result<ParsedLyrics, String> {
+async fn lyrics_auto_detect(content: String) -> AppResult<ParsedLyrics> {
     let parser = LyricsParser::new();
-    parser.auto_detect_format(&content).map_err(|e| e.to_string())
+    parser.auto_detect_format(&content).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn lyrics_format_as_lrc(lyrics: ParsedLyrics) -> Result<String, String> {
+async fn lyrics_format_as_lrc(lyrics: ParsedLyrics) -> AppResult<String> {
     let parser = LyricsParser::new();
     Ok(parser.format_as_lrc(&lyrics))
 }
 
 #[tauri::command]
-async fn lyrics_get_current_line(track_id: i64, position_ms: u64, state: State<'_, AppState>) -> Result<Option<usize>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let lyrics = db.get_lyrics_by_track_id(track_id).map_err(|e| e.to_string())?;
+async fn lyrics_get_current_line(track_id: i64, position_ms: u64, state: State<'_, AppState>) -> AppResult<Option<usize>> {
+    let db = state.inner().db.lock()?;
+    let lyrics = db.get_lyrics_by_track_id(track_id)?;
     
     if let Some(lyrics) = lyrics {
         let parser = LyricsParser::new();
-        let parsed = parser.parse_lrc(&lyrics.content).map_err(|e| e.to_string())?;
+        let parsed = parser.parse_lrc(&lyrics.content)?;
         Ok(parser.get_current_line(&parsed.lines, position_ms))
     } else {
         Ok(None)
     }
 }
 
+/// 逐字歌词中当前正在唱的词（普通LRC返回None）
+#[tauri::command]
+async fn lyrics_get_current_word(track_id: i64, position_ms: u64, state: State<'_, AppState>) -> AppResult<Option<CurrentWord>> {
+    let db = state.inner().db.lock()?;
+    let lyrics = db.get_lyrics_by_track_id(track_id)?;
+
+    if let Some(lyrics) = lyrics {
+        let parser = LyricsParser::new();
+        let parsed = parser.parse_lrc(&lyrics.content)?;
+        Ok(parser.get_current_word(&parsed.lines, position_ms))
+    } else {
+        Ok(None)
+    }
+}
+
+/// 为缺少歌词的曲目批量获取歌词（本地文件优先），返回待处理的曲目数
+///
+/// 进度通过 lyrics-fetch-progress 事件发送
+#[tauri::command]
+async fn lyrics_fetch_missing(
+    requests_per_second: Option<f64>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<usize> {
+    let db = state.inner().db.clone();
+    lyrics_fetch::start(
+        db,
+        requests_per_second.unwrap_or(lyrics_fetch::DEFAULT_REQUESTS_PER_SECOND),
+        state.inner().config.get().await.network,
+        &SHUTDOWN_SIGNAL,
+        move |progress| {
+            let _ = app.emit("lyrics-fetch-progress", progress);
+        },
+    )
+}
+
+#[tauri::command]
+async fn lyrics_fetch_cancel() -> AppResult<()> {
+    lyrics_fetch::cancel();
+    Ok(())
+}
+
+#[tauri::command]
+async fn lyrics_fetch_is_running() -> AppResult<bool> {
+    Ok(lyrics_fetch::is_running())
+}
+
+/// 更新曲库中的元数据，write_to_file 时同时写入文件标签，返回各文件的写入结果
+async fn update_tracks_metadata(
+    app: &AppHandle,
+    db: Arc<DbPool>,
+    track_ids: Vec<i64>,
+    fields: TrackMetadataUpdate,
+    write_to_file: bool,
+) -> AppResult<Vec<tag_writer::TagWriteResult>> {
+    let updated = db.lock()?
+        .update_tracks_metadata(&track_ids, &fields)?;
+    log::info!("✏️ 已修改 {} 首曲目的元数据", updated);
+    let _ = app.emit("library-tracks-changed", &LibraryEvent::TracksChanged { added: 0, updated, removed: 0 });
+
+    if !write_to_file {
+        return Ok(Vec::new());
+    }
+    tokio::task::spawn_blocking(move || tag_writer::write_tracks_metadata(&db, &track_ids, &fields))
+        .await
+        .map_err(AppError::from)
+}
+
+/// 修改一首曲目的元数据（标题、艺术家、专辑等），可选同时写入文件标签
+#[tauri::command]
+async fn track_update_metadata(
+    track_id: i64,
+    fields: TrackMetadataUpdate,
+    write_to_file: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    fields.validate().map_err(AppError::InvalidInput)?;
+    let db = state.inner().db.clone();
+    let results = update_tracks_metadata(&app, db, vec![track_id], fields, write_to_file.unwrap_or(false)).await?;
+    match results.into_iter().find_map(|result| result.error) {
+        Some(e) => Err(AppError::Io(format!("曲库已更新，但写入文件失败: {}", e))),
+        None => Ok(()),
+    }
+}
+
+/// 批量修改元数据（如统一修正一张专辑的专辑艺术家），不支持标题和音轨号
+#[tauri::command]
+async fn tracks_update_metadata_bulk(
+    track_ids: Vec<i64>,
+    fields: TrackMetadataUpdate,
+    write_to_file: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<tag_writer::TagWriteResult>> {
+    fields.validate_bulk().map_err(AppError::InvalidInput)?;
+    let db = state.inner().db.clone();
+    update_tracks_metadata(&app, db, track_ids, fields, write_to_file.unwrap_or(false)).await
+}
+
+/// 设置曲目评分（0-5星，None 清除评分），可选同时写入文件标签
+#[tauri::command]
+async fn track_set_rating(
+    track_id: i64,
+    rating: Option<u8>,
+    write_to_file: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    if rating.is_some_and(|stars| stars > db::MAX_RATING) {
+        return Err(AppError::invalid_input(format!("评分必须在0到{}之间", db::MAX_RATING)));
+    }
+    let db = state.inner().db.clone();
+    let found = db.run_write(move |db| db.set_track_rating(track_id, rating)).await?;
+    if !found {
+        return Err(AppError::not_found(format!("曲目不存在: {}", track_id)));
+    }
+    let _ = app.emit("library-tracks-changed", &LibraryEvent::TracksChanged { added: 0, updated: 1, removed: 0 });
+
+    if !write_to_file.unwrap_or(false) {
+        return Ok(());
+    }
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let path = db.with_read(|db| db.get_track_by_id(track_id))?
+            .map(|track| track.path)
+            .unwrap_or_default();
+        let state = tag_writer::write_rating(std::path::Path::new(&path), rating)?;
+        db.with(|db| db.record_tag_write(track_id, state.mtime, state.size, &state.hash))
+    })
+    .await?
+    .map_err(|e| AppError::Io(format!("曲库已更新，但写入文件失败: {}", e)))
+}
+
+#[tauri::command]
+async fn track_get_rating(track_id: i64, state: State<'_, AppState>) -> AppResult<Option<u8>> {
+    state.inner().db.run_read(move |db| db.get_track_rating(track_id)).await?
+        .ok_or_else(|| AppError::not_found(format!("曲目不存在: {}", track_id)))
+}
+
+/// 开启HTTP远程控制并保存设置，返回配对信息
+#[tauri::command]
+async fn remote_control_enable(
+    port: Option<u16>,
+    allow_lan: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<remote_control::RemotePairing> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?.clone();
+    let mut candidate = state.inner().config.get().await.remote_control;
+    candidate.enabled = true;
+    if let Some(port) = port {
+        candidate.port = port;
+    }
+    if let Some(allow_lan) = allow_lan {
+        candidate.allow_lan = allow_lan;
+    }
+    if candidate.token.is_empty() {
+        candidate.token = remote_control::generate_token();
+    }
+    candidate.validate().map_err(AppError::InvalidInput)?;
+    remote_control::start(&candidate, tx, state.inner().db.clone()).map_err(AppError::Io)?;
+
+    let saved = candidate.clone();
+    state.inner().config.modify(move |c| c.remote_control = saved).await?;
+    Ok(remote_control::pairing(&candidate))
+}
+
+/// 关闭HTTP远程控制并保存设置
+#[tauri::command]
+async fn remote_control_disable(state: State<'_, AppState>) -> AppResult<()> {
+    remote_control::stop();
+    state.inner().config.modify(|c| c.remote_control.enabled = false).await?;
+    Ok(())
+}
+
+/// 当前的配对信息（地址和令牌）
+#[tauri::command]
+async fn remote_control_pairing() -> AppResult<remote_control::RemotePairing> {
+    remote_control::running_config()
+        .map(|config| remote_control::pairing(&config))
+        .ok_or_else(|| AppError::not_found("远程控制未开启"))
+}
+
+/// 搜索局域网中的投屏设备（UPnP/DLNA MediaRenderer）
+#[tauri::command]
+async fn cast_list_devices() -> AppResult<Vec<cast::CastDevice>> {
+    cast::discover().await
+}
+
+/// 投屏到设备并播放曲目，之后的播放控制都由设备执行
+#[tauri::command]
+async fn cast_start(device_id: String, track_id: i64, state: State<'_, AppState>) -> AppResult<cast::CastDevice> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let device = cast::start(&device_id, tx.clone(), state.inner().player_adapter.event_sender()).await?;
+    tx.send(PlayerCommand::Play(track_id, chrono::Utc::now().timestamp_millis()))?;
+    Ok(device)
+}
+
+/// 结束投屏，恢复本地播放
+#[tauri::command]
+async fn cast_stop() -> AppResult<()> {
+    if !cast::stop(true).await {
+        return Err(AppError::invalid_input("当前没有投屏"));
+    }
+    Ok(())
+}
+
+/// 设置投屏设备的音量（0.0 - 1.0）
+#[tauri::command]
+async fn cast_set_volume(volume: f32) -> AppResult<()> {
+    cast::set_volume(volume)
+}
+
+/// 正在投屏的设备（未投屏时为None）
+#[tauri::command]
+async fn cast_get_active_device() -> AppResult<Option<cast::CastDevice>> {
+    Ok(cast::active_device())
+}
+
+/// 写回标签会修改用户的音乐文件，必须由前端确认后传入 confirm = true
+fn require_tag_write_confirmation(confirm: bool) -> AppResult<()> {
+    if confirm {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input("写入标签会修改音乐文件，请确认后再执行"))
+    }
+}
+
+/// 在后台线程中批量写入标签
+async fn write_tags_for_tracks(
+    db: Arc<DbPool>,
+    track_ids: Vec<i64>,
+    fields: tag_writer::TagFields,
+) -> AppResult<Vec<tag_writer::TagWriteResult>> {
+    tokio::task::spawn_blocking(move || tag_writer::write_tracks_tags(&db, &track_ids, fields))
+        .await
+        .map_err(AppError::from)
+}
+
+/// 把数据库中的歌词写入音频文件标签
+#[tauri::command]
+async fn track_write_lyrics_tag(track_id: i64, confirm: bool, state: State<'_, AppState>) -> AppResult<()> {
+    require_tag_write_confirmation(confirm)?;
+    let db = state.inner().db.clone();
+    let fields = tag_writer::TagFields { lyrics: true, cover: false };
+    tokio::task::spawn_blocking(move || tag_writer::write_track_tags(&db, track_id, fields))
+        .await?
+        .map_err(AppError::from)
+}
+
+/// 把专辑封面写入音频文件标签（替换原有封面）
+#[tauri::command]
+async fn track_write_cover_tag(track_id: i64, confirm: bool, state: State<'_, AppState>) -> AppResult<()> {
+    require_tag_write_confirmation(confirm)?;
+    let db = state.inner().db.clone();
+    let fields = tag_writer::TagFields { lyrics: false, cover: true };
+    tokio::task::spawn_blocking(move || tag_writer::write_track_tags(&db, track_id, fields))
+        .await?
+        .map_err(AppError::from)
+}
+
+/// 把歌单中曲目的歌词和/或封面写入文件标签，逐个文件返回结果
+#[tauri::command]
+async fn playlist_write_tags(
+    playlist_id: i64,
+    lyrics: bool,
+    cover: bool,
+    confirm: bool,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<tag_writer::TagWriteResult>> {
+    require_tag_write_confirmation(confirm)?;
+    let track_ids = {
+        let db = state.inner().db.lock()?;
+        db.get_playlist_tracks(playlist_id)?
+            .into_iter()
+            .map(|track| track.id)
+            .collect()
+    };
+    write_tags_for_tracks(state.inner().db.clone(), track_ids, tag_writer::TagFields { lyrics, cover }).await
+}
+
+/// 把专辑中曲目的歌词和/或封面写入文件标签，逐个文件返回结果
+#[tauri::command]
+async fn album_write_tags(
+    album: String,
+    album_artist: Option<String>,
+    lyrics: bool,
+    cover: bool,
+    confirm: bool,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<tag_writer::TagWriteResult>> {
+    require_tag_write_confirmation(confirm)?;
+    let track_ids = {
+        let db = state.inner().db.lock()?;
+        db.get_album_tracks(&album, album_artist.as_deref())?
+            .into_iter()
+            .map(|track| track.id)
+            .collect()
+    };
+    write_tags_for_tracks(state.inner().db.clone(), track_ids, tag_writer::TagFields { lyrics, cover }).await
+}
+
 // Network API commands (LrcApi integration)
 /// 从网络API获取歌词
 #[tauri::command]
 async fn network_fetch_lyrics(
     title: String,
     artist: String,
-    album: Option<String>
-) -> Result<(String, String), String> {
+    album: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<(String, String)> {
     log::info!("🌐 [COMMAND] 网络获取歌词: {} - {}", title, artist);
     
-    let service = NetworkApiService::new();
+    let service = NetworkApiService::with_config(state.inner().config.get().await.network);
     let result = service
         .fetch_lyrics(&title, &artist, album.as_deref())
+        .await?;
+    
+    Ok((result.content, result.source))
+}
+
+/// 从网络API获取封面
+#[tauri::command]
+async fn network_fetch_cover(
+    title: Option<String>,
+    artist: String,
+    album: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<(Vec<u8>, String, String)> {
+    log::info!("🌐 [COMMAND] 网络获取封面: {} - {:?}", artist, album);
+    
+    let service = NetworkApiService::with_config(state.inner().config.get().await.network);
+    let result = service
+        .fetch_cover(title.as_deref(), &artist, album.as_deref())
+        .await?;
+    
+    Ok((result.data, result.mime_type, result.source))
+}
+
+/// 获取网络歌词和封面来源设置
+#[tauri::command]
+async fn network_sources_get(state: State<'_, AppState>) -> AppResult<NetworkConfig> {
+    Ok(state.inner().config.get().await.network)
+}
+
+/// 添加或修改来源（按名称），返回新的来源设置
+#[tauri::command]
+async fn network_source_save(source: NetworkSource, state: State<'_, AppState>) -> AppResult<NetworkConfig> {
+    log::info!("🌐 [COMMAND] 保存网络来源: {} ({})", source.name, source.base_url);
+    let config = state.inner().config.modify(|config| {
+        let sources = &mut config.network.sources;
+        match sources.iter_mut().find(|s| s.name.eq_ignore_ascii_case(&source.name)) {
+            Some(existing) => *existing = source,
+            None => sources.push(source),
+        }
+    }).await?;
+    network_api::clear_cache();
+    Ok(config.network)
+}
+
+/// 删除来源，返回新的来源设置
+#[tauri::command]
+async fn network_source_remove(name: String, state: State<'_, AppState>) -> AppResult<NetworkConfig> {
+    log::info!("🌐 [COMMAND] 删除网络来源: {}", name);
+    let current = state.inner().config.get().await.network;
+    if !current.sources.iter().any(|s| s.name.eq_ignore_ascii_case(&name)) {
+        return Err(AppError::not_found(format!("网络来源不存在: {}", name)));
+    }
+    let config = state.inner().config.modify(|config| {
+        config.network.sources.retain(|s| !s.name.eq_ignore_ascii_case(&name));
+    }).await?;
+    network_api::clear_cache();
+    Ok(config.network)
+}
+
+/// 测试来源是否可用（不需要先保存），超时使用当前设置中的单个来源超时
+#[tauri::command]
+async fn network_source_test(source: NetworkSource, state: State<'_, AppState>) -> AppResult<SourceTestResult> {
+    log::info!("🌐 [COMMAND] 测试网络来源: {} ({})", source.name, source.base_url);
+    let mut network = state.inner().config.get().await.network;
+    network.sources = vec![source.clone()];
+    network.validate().map_err(AppError::InvalidInput)?;
+    Ok(NetworkApiService::with_config(network).test_source(&source).await)
+}
+
+/// 读取曲目，不存在时报错
+fn load_tracks(db: &DbPool, track_ids: &[i64]) -> AppResult<Vec<Track>> {
+    let db = db.lock()?;
+    track_ids.iter()
+        .map(|&id| {
+            db.get_track_by_id(id)?
+                .ok_or_else(|| AppError::not_found(format!("曲目不存在: {}", id)))
+        })
+        .collect()
+}
+
+/// 在 MusicBrainz 查询曲目的正确元数据，返回按匹配程度排序的候选
+#[tauri::command]
+async fn metadata_lookup(track_id: i64, state: State<'_, AppState>) -> AppResult<Vec<MetadataCandidate>> {
+    let track = load_tracks(&state.db, &[track_id])?.remove(0);
+    MusicBrainzClient::new()
+        .lookup_track(&LookupQuery::from_track(&track))
         .await
-        .map_err(|e| e.to_string())?;
-    
-    Ok((result.content, result.source))
+        .map_err(AppError::from)
 }
 
-/// 从网络API获取封面
+/// 把一组曲目（通常是一个文件夹）作为整张专辑查询，按时长为每首曲目分配音轨号
 #[tauri::command]
-async fn network_fetch_cover(
-    title: Option<String>,
-    artist: String,
-    album: Option<String>
-) -> Result<(Vec<u8>, String, String), String> {
-    log::info!("🌐 [COMMAND] 网络获取封面: {} - {:?}", artist, album);
-    
-    let service = NetworkApiService::new();
-    let result = service
-        .fetch_cover(title.as_deref(), &artist, album.as_deref())
+async fn metadata_lookup_album(track_ids: Vec<i64>, state: State<'_, AppState>) -> AppResult<Vec<AlbumMatch>> {
+    if track_ids.is_empty() {
+        return Err(AppError::invalid_input("没有要查询的曲目"));
+    }
+    let tracks = load_tracks(&state.db, &track_ids)?;
+    MusicBrainzClient::new()
+        .lookup_album(&tracks)
         .await
-        .map_err(|e| e.to_string())?;
-    
-    Ok((result.data, result.mime_type, result.source))
+        .map_err(AppError::from)
+}
+
+/// 应用查询到的候选元数据，与手动修改走同一流程
+#[tauri::command]
+async fn metadata_apply(
+    track_id: i64,
+    candidate: MetadataCandidate,
+    write_to_file: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    track_update_metadata(track_id, candidate.to_update(), write_to_file, app, state).await
 }
 
 /// 保存艺术家封面到数据库
@@ -672,12 +1806,11 @@ async fn artist_cover_save(
     artist_name: String,
     cover_data: Vec<u8>,
     cover_mime: String
-) -> Result<(), String> {
+) -> AppResult<()> {
     log::info!("💾 [COMMAND] 保存艺术家封面: {}", artist_name);
     
-    let db = state.db.lock().unwrap();
-    db.save_artist_cover(&artist_name, &cover_data, &cover_mime)
-        .map_err(|e| e.to_string())?;
+    let db = state.db.lock()?;
+    db.save_artist_cover(&artist_name, &cover_data, &cover_mime)?;
     
     Ok(())
 }
@@ -687,97 +1820,124 @@ async fn artist_cover_save(
 async fn artist_cover_get(
     state: State<'_, AppState>,
     artist_name: String
-) -> Result<Option<(Vec<u8>, String)>, String> {
+) -> AppResult<Option<(Vec<u8>, String)>> {
     log::info!("📖 [COMMAND] 获取艺术家封面: {}", artist_name);
     
-    let db = state.db.lock().unwrap();
+    let db = state.db.read()?;
     db.get_artist_cover(&artist_name)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// 批量获取所有艺术家封面
 #[tauri::command]
 async fn artist_covers_get_all(
     state: State<'_, AppState>
-) -> Result<Vec<(String, Vec<u8>, String)>, String> {
+) -> AppResult<Vec<(String, Vec<u8>, String)>> {
     log::info!("📚 [COMMAND] 批量获取所有艺术家封面");
     
-    let db = state.db.lock().unwrap();
+    let db = state.db.read()?;
     db.get_all_artist_covers()
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// 分页获取艺术家封面，每页总大小不超过 max_bytes（至少返回一张）
+#[tauri::command]
+async fn artist_covers_get_page(
+    offset: Option<i64>,
+    limit: Option<i64>,
+    max_bytes: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<ArtistCoverPage> {
+    let offset = offset.unwrap_or(0).max(0);
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let max_bytes = max_bytes.unwrap_or(8 * 1024 * 1024);
+    state.inner().db.run_read(move |db| db.get_artist_covers_page(offset, limit, max_bytes)).await
+}
+
+/// 为还没有封面的艺术家批量获取封面，返回待处理的艺术家数
+///
+/// 进度通过 artist-covers-fetch-progress 事件发送
+#[tauri::command]
+async fn artists_fetch_missing_covers(
+    force_refresh: Option<bool>,
+    requests_per_second: Option<f64>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<usize> {
+    let db = state.inner().db.clone();
+    artist_cover_fetch::start(
+        db,
+        force_refresh.unwrap_or(false),
+        requests_per_second.unwrap_or(lyrics_fetch::DEFAULT_REQUESTS_PER_SECOND),
+        state.inner().config.get().await.network,
+        &SHUTDOWN_SIGNAL,
+        move |progress| {
+            let _ = app.emit("artist-covers-fetch-progress", progress);
+        },
+    )
+}
+
+#[tauri::command]
+async fn artists_fetch_covers_cancel() -> AppResult<()> {
+    artist_cover_fetch::cancel();
+    Ok(())
+}
+
+#[tauri::command]
+async fn artists_fetch_covers_is_running() -> AppResult<bool> {
+    Ok(artist_cover_fetch::is_running())
 }
 
 // Playlist generation commands
 #[tauri::command]
-async fn generate_sequential_playlist(state: State<'_, AppState>) -> Result<Vec<Track>, String> {
+async fn generate_sequential_playlist(state: State<'_, AppState>) -> AppResult<Vec<Track>> {
     log::info!("生成顺序播放列表");
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let all_tracks = db.get_all_tracks().map_err(|e| e.to_string())?;
+    let all_tracks = state.inner().db.run_read(|db| db.get_all_tracks()).await?;
     
     log::info!("顺序播放列表生成完成，共 {} 首歌曲", all_tracks.len());
     Ok(all_tracks)
 }
 
+/// 随机排列整个曲库；limit 限制返回数量（缺省为全部）
 #[tauri::command]
-async fn generate_random_playlist(state: State<'_, AppState>) -> Result<Vec<Track>, String> {
+async fn generate_random_playlist(limit: Option<usize>, state: State<'_, AppState>) -> AppResult<Vec<Track>> {
     log::info!("生成随机播放列表");
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let all_tracks = db.get_all_tracks().map_err(|e| e.to_string())?;
+    let mut all_tracks = state.inner().db.run_read(|db| db.get_all_tracks()).await?;
     
     if all_tracks.is_empty() {
         log::warn!("音乐库为空，无法生成随机播放列表");
         return Ok(Vec::new());
     }
     
-    // 随机播放最多100首歌
-    let max_tracks = 100.min(all_tracks.len());
-    let mut random_tracks = Vec::new();
-    
-    // 使用简单的伪随机算法选择歌曲
-    use std::collections::HashSet;
-    let mut selected_indices = HashSet::new();
-    
-    // 使用当前时间作为种子
-    let seed = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
-    
-    let mut rng_state = seed;
-    while random_tracks.len() < max_tracks {
-        // 简单的线性同余生成器
-        rng_state = rng_state.wrapping_mul(1103515245).wrapping_add(12345);
-        let index = (rng_state % all_tracks.len() as u64) as usize;
-        
-        if !selected_indices.contains(&index) {
-            selected_indices.insert(index);
-            random_tracks.push(all_tracks[index].clone());
-        }
+    use rand::seq::SliceRandom;
+    all_tracks.shuffle(&mut rand::thread_rng());
+    if let Some(limit) = limit {
+        all_tracks.truncate(limit);
     }
     
-    log::info!("随机播放列表生成完成，共 {} 首歌曲", random_tracks.len());
-    Ok(random_tracks)
+    log::info!("随机播放列表生成完成，共 {} 首歌曲", all_tracks.len());
+    Ok(all_tracks)
 }
 
 #[tauri::command]
-async fn load_playlist_by_mode(shuffle: bool, state: State<'_, AppState>) -> Result<(), String> {
+async fn load_playlist_by_mode(shuffle: bool, state: State<'_, AppState>) -> AppResult<()> {
     log::info!("根据播放模式加载播放列表，随机模式: {}", shuffle);
     
     let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
     
     let playlist = if shuffle {
-        generate_random_playlist(state).await?
+        generate_random_playlist(None, state).await?
     } else {
         generate_sequential_playlist(state).await?
     };
     
     if playlist.is_empty() {
-        return Err("音乐库为空，无法生成播放列表".to_string());
+        return Err(AppError::not_found("音乐库为空，无法生成播放列表"));
     }
     
     // 加载播放列表到播放器
-    tx.send(PlayerCommand::LoadPlaylist(playlist))
-        .map_err(|e| e.to_string())?;
+    remote_control::update_queue(&playlist);
+    tx.send(PlayerCommand::LoadPlaylist(playlist))?;
     
     log::info!("播放列表已加载到播放器");
     Ok(())
@@ -785,140 +1945,277 @@ async fn load_playlist_by_mode(shuffle: bool, state: State<'_, AppState>) -> Res
 
 // Favorites commands
 #[tauri::command]
-async fn favorites_add(track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.add_favorite(track_id).map_err(|e| e.to_string()).map(|_| ())
+async fn favorites_add(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.add_favorite(track_id).map(|_| ()).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn favorites_remove(track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.remove_favorite(track_id).map_err(|e| e.to_string())
+async fn favorites_remove(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.remove_favorite(track_id).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn favorites_is_favorite(track_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.is_favorite(track_id).map_err(|e| e.to_string())
+async fn favorites_is_favorite(track_id: i64, state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    db.is_favorite(track_id).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn favorites_get_all(state: State<'_, AppState>) -> Result<Vec<Track>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.get_all_favorites().map_err(|e| e.to_string())
+async fn favorites_get_all(state: State<'_, AppState>) -> AppResult<Vec<Track>> {
+    state.inner().db.run_read(|db| db.get_all_favorites()).await
 }
 
 #[tauri::command]
-async fn favorites_toggle(track_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.toggle_favorite(track_id).map_err(|e| e.to_string())
+async fn favorites_toggle(track_id: i64, state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    db.toggle_favorite(track_id).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn favorites_get_count(state: State<'_, AppState>) -> Result<i64, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.get_favorites_count().map_err(|e| e.to_string())
+async fn favorites_get_count(state: State<'_, AppState>) -> AppResult<i64> {
+    let db = state.inner().db.lock()?;
+    db.get_favorites_count().map_err(AppError::from)
 }
 
 // ========== 企业级歌单管理命令 ==========
 
 use playlist::{
-    Playlist, PlaylistWithTracks, CreatePlaylistOptions, UpdatePlaylistOptions,
+    AddTracksResult, PlaylistTree, PlaylistWithTracks, CreatePlaylistOptions, UpdatePlaylistOptions,
     PlaylistManager, PlaylistExporter, PlaylistImporter, ExportFormat,
-    SmartRules, PlaylistStats,
+    SmartRules, PlaylistStats, ImportPreview, ImportOverride, PathRewrite, ExportOptions,
 };
+use playlist::importer::ParsedPlaylist;
+use playlist::audio_export::{self, AudioExportFormat};
 
 // 基础 CRUD 命令
+/// 侧边栏歌单树：文件夹（含子文件夹和歌单）在前，之后是最外层的歌单
 #[tauri::command]
-async fn playlists_list(state: State<'_, AppState>) -> Result<Vec<Playlist>, String> {
+async fn playlists_list(state: State<'_, AppState>) -> AppResult<PlaylistTree> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.get_all_playlists().map_err(|e| e.to_string())
+    manager.get_playlist_tree()
 }
 
 #[tauri::command]
-async fn playlists_create(options: CreatePlaylistOptions, state: State<'_, AppState>) -> Result<i64, String> {
+async fn playlists_create(options: CreatePlaylistOptions, state: State<'_, AppState>) -> AppResult<i64> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.create_playlist(options).map_err(|e| e.to_string())
+    manager.create_playlist(options)
 }
 
+/// 歌单详情（内置歌单使用保留的负数ID）
 #[tauri::command]
-async fn playlists_get_detail(playlist_id: i64, state: State<'_, AppState>) -> Result<PlaylistWithTracks, String> {
+async fn playlists_get_detail(playlist_id: i64, state: State<'_, AppState>) -> AppResult<PlaylistWithTracks> {
+    let config = state.inner().config.get().await.builtin_playlists;
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.get_playlist_with_tracks(playlist_id).map_err(|e| e.to_string())
+    manager.resolve_playlist(playlist_id, &config)
 }
 
+/// 内置歌单：最近添加、最近播放、最常播放、我的收藏（曲目数和统计范围见应用设置 builtin_playlists）
 #[tauri::command]
-async fn playlists_update(playlist_id: i64, options: UpdatePlaylistOptions, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_get_builtin(state: State<'_, AppState>) -> AppResult<Vec<PlaylistWithTracks>> {
+    let config = state.inner().config.get().await.builtin_playlists;
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.get_builtin_playlists(&config)
+}
+
+#[tauri::command]
+async fn playlists_update(playlist_id: i64, options: UpdatePlaylistOptions, state: State<'_, AppState>) -> AppResult<()> {
+    if let Some(Some(duration_ms)) = options.default_crossfade_ms {
+        if duration_ms > MAX_CROSSFADE_MS {
+            return Err(AppError::InvalidInput(format!("交叉淡入淡出时长不能超过 {}ms", MAX_CROSSFADE_MS)));
+        }
+    }
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.update_playlist(playlist_id, options).map_err(|e| e.to_string())
+    manager.update_playlist(playlist_id, options)
+}
+
+/// 播放歌单：载入播放器并应用歌单的播放偏好，从 start_track_id（缺省为第一首）开始播放
+///
+/// 未设置的随机/重复沿用播放器当前状态，未设置的交叉淡入淡出恢复为全局设置。
+/// 偏好只在开始播放时应用，之后在播放器中的切换不会写回歌单，也不改动全局设置。
+#[tauri::command]
+async fn playlists_play(playlist_id: i64, start_track_id: Option<i64>, state: State<'_, AppState>) -> AppResult<()> {
+    let config = state.inner().config.get().await.builtin_playlists;
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    let (PlaylistWithTracks { playlist, tracks }, start_track_id) = manager.play_playlist(playlist_id, start_track_id, &config)?;
+    party_mode_record_queue_additions(tracks.len())?;
+
+    let crossfade_ms = match playlist.default_crossfade_ms {
+        Some(duration_ms) => duration_ms,
+        None => {
+            let db = state.inner().db.lock()?;
+            db.get_setting(SETTING_CROSSFADE_MS)?.and_then(|v| v.parse().ok()).unwrap_or(0)
+        }
+    };
+
+    log::info!("📋 播放歌单: {} ({} 首)", playlist.name, tracks.len());
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    remote_control::update_queue(&tracks);
+    tx.send(PlayerCommand::LoadPlaylist(tracks))?;
+    if let Some(shuffle) = playlist.default_shuffle {
+        tx.send(PlayerCommand::SetShuffle(shuffle))?;
+    }
+    if let Some(mode) = playlist.default_repeat_mode {
+        tx.send(PlayerCommand::SetRepeatMode(mode))?;
+    }
+    tx.send(PlayerCommand::SetCrossfade(crossfade_ms))?;
+    tx.send(PlayerCommand::Play(start_track_id, chrono::Utc::now().timestamp_millis()))
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn playlists_delete(playlist_id: i64, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_delete(playlist_id: i64, state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.delete_playlist(playlist_id).map_err(|e| e.to_string())
+    manager.delete_playlist(playlist_id)
+}
+
+// 歌单文件夹命令
+#[tauri::command]
+async fn playlist_folders_create(name: String, parent_id: Option<i64>, state: State<'_, AppState>) -> AppResult<i64> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.create_folder(&name, parent_id)
+}
+
+#[tauri::command]
+async fn playlist_folders_rename(folder_id: i64, name: String, state: State<'_, AppState>) -> AppResult<()> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.rename_folder(folder_id, &name)
+}
+
+/// 删除文件夹，其中的歌单移到上一级
+#[tauri::command]
+async fn playlist_folders_delete(folder_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.delete_folder(folder_id)
+}
+
+#[tauri::command]
+async fn playlist_folders_move(
+    folder_id: i64,
+    parent_id: Option<i64>,
+    position: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.move_folder(folder_id, parent_id, position)
+}
+
+#[tauri::command]
+async fn playlists_move_to_folder(playlist_ids: Vec<i64>, folder_id: Option<i64>, state: State<'_, AppState>) -> AppResult<()> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.move_playlists_to_folder(playlist_ids, folder_id)
+}
+
+#[tauri::command]
+async fn playlists_set_order(folder_id: Option<i64>, ordered_ids: Vec<i64>, state: State<'_, AppState>) -> AppResult<()> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.set_playlist_order(folder_id, ordered_ids)
 }
 
 // 曲目管理命令
+/// 添加曲目，skip_existing 时跳过歌单中已有的曲目
 #[tauri::command]
-async fn playlists_add_tracks(playlist_id: i64, track_ids: Vec<i64>, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_add_tracks(
+    playlist_id: i64,
+    track_ids: Vec<i64>,
+    skip_existing: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<AddTracksResult> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.add_tracks_to_playlist(playlist_id, track_ids).map_err(|e| e.to_string())
+    manager.add_tracks_to_playlist(playlist_id, track_ids, skip_existing.unwrap_or(false))
+}
+
+/// 添加整张专辑，默认跳过歌单中已有的曲目
+#[tauri::command]
+async fn playlists_add_album(
+    playlist_id: i64,
+    album: String,
+    album_artist: Option<String>,
+    skip_existing: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<AddTracksResult> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.add_album(playlist_id, &album, album_artist.as_deref(), skip_existing.unwrap_or(true))
+}
+
+/// 添加艺术家的全部曲目，默认跳过歌单中已有的曲目
+#[tauri::command]
+async fn playlists_add_artist(
+    playlist_id: i64,
+    artist: String,
+    skip_existing: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<AddTracksResult> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.add_artist(playlist_id, &artist, skip_existing.unwrap_or(true))
+}
+
+#[tauri::command]
+async fn playlists_remove_tracks(playlist_id: i64, track_ids: Vec<i64>, state: State<'_, AppState>) -> AppResult<usize> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.remove_tracks(playlist_id, track_ids)
+}
+
+/// 移除重复的曲目（保留第一次出现的位置），返回移除的数量
+#[tauri::command]
+async fn playlists_deduplicate(playlist_id: i64, state: State<'_, AppState>) -> AppResult<usize> {
+    let manager = PlaylistManager::new(state.inner().db.clone());
+    manager.deduplicate(playlist_id)
 }
 
 #[tauri::command]
-async fn playlists_remove_track(playlist_id: i64, track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_remove_track(playlist_id: i64, track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.remove_track_from_playlist(playlist_id, track_id).map_err(|e| e.to_string())
+    manager.remove_track_from_playlist(playlist_id, track_id)
 }
 
 #[tauri::command]
-async fn playlists_reorder_tracks(playlist_id: i64, track_ids: Vec<i64>, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_reorder_tracks(playlist_id: i64, track_ids: Vec<i64>, state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.reorder_tracks(playlist_id, track_ids).map_err(|e| e.to_string())
+    manager.reorder_tracks(playlist_id, track_ids)
 }
 
 #[tauri::command]
-async fn playlists_get_tracks(playlist_id: i64, state: State<'_, AppState>) -> Result<Vec<Track>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.get_playlist_tracks(playlist_id).map_err(|e| e.to_string())
+async fn playlists_get_tracks(playlist_id: i64, state: State<'_, AppState>) -> AppResult<Vec<Track>> {
+    state.inner().db.run_read(move |db| db.get_playlist_tracks(playlist_id)).await
 }
 
 // 智能歌单命令
 #[tauri::command]
-async fn playlists_create_smart(name: String, rules: SmartRules, state: State<'_, AppState>) -> Result<i64, String> {
+async fn playlists_create_smart(name: String, rules: SmartRules, state: State<'_, AppState>) -> AppResult<i64> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.create_smart_playlist(name, rules).map_err(|e| e.to_string())
+    manager.create_smart_playlist(name, rules)
 }
 
 #[tauri::command]
-async fn playlists_update_smart_rules(playlist_id: i64, rules: SmartRules, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_update_smart_rules(playlist_id: i64, rules: SmartRules, state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.update_smart_playlist(playlist_id, rules).map_err(|e| e.to_string())
+    manager.update_smart_playlist(playlist_id, rules)
 }
 
 #[tauri::command]
-async fn playlists_refresh_smart(playlist_id: i64, state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_refresh_smart(playlist_id: i64, state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.refresh_smart_playlist(playlist_id).map_err(|e| e.to_string())
+    manager.refresh_smart_playlist(playlist_id)
 }
 
 #[tauri::command]
-async fn playlists_refresh_all_smart(state: State<'_, AppState>) -> Result<(), String> {
+async fn playlists_refresh_all_smart(state: State<'_, AppState>) -> AppResult<()> {
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
-    manager.refresh_all_smart_playlists().map_err(|e| e.to_string())
+    manager.refresh_all_smart_playlists()
 }
 
 // 导出命令
@@ -927,204 +2224,593 @@ async fn playlists_export(
     playlist_id: i64,
     file_path: String,
     format: ExportFormat,
+    options: Option<ExportOptions>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
+    let config = state.inner().config.get().await.builtin_playlists;
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
     
-    let playlist_with_tracks = manager.get_playlist_with_tracks(playlist_id)
-        .map_err(|e| e.to_string())?;
+    let playlist_with_tracks = manager.resolve_playlist(playlist_id, &config)?;
+    let added_at = manager.resolve_added_at(playlist_id, playlist_with_tracks.tracks.len())?;
     
     PlaylistExporter::export_to_file(
         &playlist_with_tracks.playlist,
         &playlist_with_tracks.tracks,
+        &added_at,
         &file_path,
         format,
-    ).map_err(|e| e.to_string())
+        &options.unwrap_or_default(),
+    ).map_err(AppError::from)
+}
+
+/// 把歌单曲目复制或转码到 dest_dir，并写入引用这些文件的M3U，返回曲目数
+///
+/// 进度通过 playlist-audio-export-progress 事件发送；bitrate 为空时使用默认码率
+#[tauri::command]
+async fn playlists_export_with_audio(
+    playlist_id: i64,
+    dest_dir: String,
+    format: AudioExportFormat,
+    bitrate: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<usize> {
+    let config = state.inner().config.get().await.builtin_playlists;
+    let db = state.inner().db.clone();
+    let manager = PlaylistManager::new(db);
+
+    let playlist_with_tracks = manager.resolve_playlist(playlist_id, &config)?;
+    audio_export::start(
+        playlist_with_tracks.playlist,
+        playlist_with_tracks.tracks,
+        dest_dir,
+        format,
+        bitrate,
+        &SHUTDOWN_SIGNAL,
+        move |progress| {
+            let _ = app.emit("playlist-audio-export-progress", progress);
+        },
+    )
+}
+
+#[tauri::command]
+async fn playlists_export_with_audio_cancel() -> AppResult<()> {
+    audio_export::cancel();
+    Ok(())
+}
+
+#[tauri::command]
+async fn playlists_export_with_audio_is_running() -> AppResult<bool> {
+    Ok(audio_export::is_running())
 }
 
 #[tauri::command]
 async fn playlists_export_preview(
     playlist_id: i64,
     format: ExportFormat,
+    options: Option<ExportOptions>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> AppResult<String> {
+    let config = state.inner().config.get().await.builtin_playlists;
     let db = state.inner().db.clone();
     let manager = PlaylistManager::new(db);
     
-    let playlist_with_tracks = manager.get_playlist_with_tracks(playlist_id)
-        .map_err(|e| e.to_string())?;
+    let playlist_with_tracks = manager.resolve_playlist(playlist_id, &config)?;
+    let added_at = manager.resolve_added_at(playlist_id, playlist_with_tracks.tracks.len())?;
     
     PlaylistExporter::export_to_string(
         &playlist_with_tracks.playlist,
         &playlist_with_tracks.tracks,
+        &added_at,
         format,
-    ).map_err(|e| e.to_string())
+        &options.unwrap_or_default(),
+    ).map_err(AppError::from)
 }
 
 // 导入命令
+
+/// 解析歌单文件并与曲库匹配（不创建歌单）
+fn build_import_preview(
+    file_path: &str,
+    path_rewrite: Option<&PathRewrite>,
+    overrides: &[ImportOverride],
+    state: &AppState,
+) -> AppResult<(ParsedPlaylist, ImportPreview)> {
+    let parsed = PlaylistImporter::import_from_file(file_path)?;
+    
+    let library = state.db.with_read(|db| db.get_all_tracks())?;
+    
+    let preview = PlaylistImporter::resolve(
+        parsed.name.clone(),
+        &parsed.entries,
+        &library,
+        path_rewrite,
+        overrides,
+    );
+    Ok((parsed, preview))
+}
+
 #[tauri::command]
-async fn playlists_import(file_path: String, state: State<'_, AppState>) -> Result<i64, String> {
-    let (name, paths) = PlaylistImporter::import_from_file(&file_path)
-        .map_err(|e| e.to_string())?;
-    
-    // 验证路径
-    let (valid_paths, invalid_paths) = PlaylistImporter::validate_paths(&paths);
-    
-    if valid_paths.is_empty() {
-        return Err(format!("没有有效的曲目路径。无效数量: {}", invalid_paths.len()));
+async fn playlists_import_preview(
+    file_path: String,
+    path_rewrite: Option<PathRewrite>,
+    state: State<'_, AppState>,
+) -> AppResult<ImportPreview> {
+    build_import_preview(&file_path, path_rewrite.as_ref(), &[], state.inner())
+        .map(|(_, preview)| preview)
+}
+
+#[tauri::command]
+async fn playlists_import(
+    file_path: String,
+    overrides: Option<Vec<ImportOverride>>,
+    path_rewrite: Option<PathRewrite>,
+    state: State<'_, AppState>,
+) -> AppResult<i64> {
+    let (parsed, preview) = build_import_preview(
+        &file_path,
+        path_rewrite.as_ref(),
+        overrides.as_deref().unwrap_or_default(),
+        state.inner(),
+    )?;
+    
+    // 完整备份中的智能歌单：还原规则后按当前曲库重新生成曲目
+    let is_smart = parsed.smart_rules.is_some();
+    if preview.resolved.is_empty() && !is_smart {
+        return Err(AppError::not_found(format!("没有匹配到曲库中的曲目。未匹配数量: {}", preview.unresolved.len())));
     }
     
-    log::info!("导入歌单: {} ({} 有效, {} 无效)", name, valid_paths.len(), invalid_paths.len());
+    log::info!(
+        "导入歌单: {} ({} 精确匹配, {} 模糊匹配, {} 手动指定, {} 未匹配)",
+        preview.name,
+        preview.exact_matches,
+        preview.fuzzy_matches,
+        preview.manual_matches,
+        preview.unresolved.len()
+    );
     
     // 创建歌单
     let db = state.inner().db.clone();
-    let manager = PlaylistManager::new(db.clone());
+    let manager = PlaylistManager::new(db);
     
     let options = CreatePlaylistOptions {
-        name,
-        description: Some(format!("从文件导入 ({})", file_path)),
-        color_theme: None,
-        is_smart: false,
-        smart_rules: None,
+        name: preview.name,
+        description: parsed.description.or_else(|| Some(format!("从文件导入 ({})", file_path))),
+        color_theme: parsed.color_theme,
+        is_smart,
+        smart_rules: parsed.smart_rules,
     };
     
-    let playlist_id = manager.create_playlist(options).map_err(|e| e.to_string())?;
+    let playlist_id = manager.create_playlist(options)?;
+    
+    if is_smart {
+        manager.refresh_smart_playlist(playlist_id)?;
+        return Ok(playlist_id);
+    }
+    
+    // 添加曲目（保持源文件顺序，备份中的加入时间原样还原）
+    let added_at: std::collections::HashMap<usize, Option<i64>> = parsed.entries.iter()
+        .map(|e| (e.line, e.added_at))
+        .collect();
+    let items = preview.resolved.iter()
+        .map(|r| (r.track_id, added_at.get(&r.source_line).copied().flatten()))
+        .collect();
+    manager.restore_tracks(playlist_id, items)?;
+    
+    Ok(playlist_id)
+}
+
+// 其他功能命令
+#[tauri::command]
+async fn playlists_get_stats(state: State<'_, AppState>) -> AppResult<PlaylistStats> {
+    let db = state.inner().db.clone();
+    let manager = PlaylistManager::new(db);
+    manager.get_stats()
+}
+
+#[tauri::command]
+async fn playlists_mark_played(playlist_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.clone();
+    let manager = PlaylistManager::new(db);
+    manager.mark_played(playlist_id)
+}
+
+#[tauri::command]
+async fn playlists_toggle_favorite(playlist_id: i64, state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.clone();
+    let manager = PlaylistManager::new(db);
+    manager.toggle_favorite(playlist_id)
+}
+
+// Pin歌单命令
+#[tauri::command]
+async fn playlists_pin(playlist_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.pin_playlist(playlist_id).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn playlists_unpin(playlist_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.unpin_playlist(playlist_id).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn playlists_toggle_pin(playlist_id: i64, state: State<'_, AppState>) -> AppResult<bool> {
+    let db = state.inner().db.lock()?;
+    db.toggle_pin(playlist_id).map_err(AppError::from)
+}
+
+// 播放历史命令
+#[tauri::command]
+async fn get_play_history(
+    sort_by: Option<String>,
+    limit: Option<i64>,
+    state: State<'_, AppState>
+) -> AppResult<Vec<PlayHistoryEntry>> {
+    let db = state.inner().db.lock()?;
+    let sort = sort_by.unwrap_or_else(|| "last_played".to_string());
+    let lim = limit.unwrap_or(50);
+    
+    let results = db.get_play_history(&sort, lim)?;
     
-    // 添加曲目
-    let db_guard = db.lock().map_err(|e| e.to_string())?;
-    let mut track_ids = Vec::new();
+    Ok(results.into_iter().map(|(track, play_count, last_played, first_played)| {
+        PlayHistoryEntry {
+            track,
+            play_count,
+            last_played_at: last_played,
+            first_played_at: first_played,
+        }
+    }).collect())
+}
+
+#[tauri::command]
+async fn get_play_statistics(state: State<'_, AppState>) -> AppResult<PlayStatistics> {
+    let (total_plays, unique_tracks, total_duration_ms) = state.inner().db
+        .run_read(|db| db.get_play_statistics())
+        .await?;
     
-    for path in valid_paths {
-        if let Ok(Some(track)) = db_guard.get_track_by_path(&path) {
-            track_ids.push(track.id);
+    Ok(PlayStatistics {
+        total_plays,
+        unique_tracks,
+        total_duration_ms,
+    })
+}
+
+/// 收听统计：播放最多的艺术家
+#[tauri::command]
+async fn stats_top_artists(range: StatsRange, limit: Option<i64>, state: State<'_, AppState>) -> AppResult<Vec<ArtistPlayStats>> {
+    let db = state.inner().db.lock()?;
+    db.stats_top_artists(range, limit.unwrap_or(10)).map_err(AppError::from)
+}
+
+/// 收听统计：播放最多的专辑
+#[tauri::command]
+async fn stats_top_albums(range: StatsRange, limit: Option<i64>, state: State<'_, AppState>) -> AppResult<Vec<AlbumPlayStats>> {
+    let db = state.inner().db.lock()?;
+    db.stats_top_albums(range, limit.unwrap_or(10)).map_err(AppError::from)
+}
+
+/// 收听统计：每日播放次数和时长（热力图）
+#[tauri::command]
+async fn stats_plays_per_day(range: StatsRange, state: State<'_, AppState>) -> AppResult<Vec<DailyPlayStats>> {
+    let db = state.inner().db.lock()?;
+    db.stats_plays_per_day(range).map_err(AppError::from)
+}
+
+/// 收听统计：按小时分布的播放次数
+#[tauri::command]
+async fn stats_listening_clock(range: StatsRange, state: State<'_, AppState>) -> AppResult<Vec<HourlyPlayStats>> {
+    let db = state.inner().db.lock()?;
+    db.stats_listening_clock(range).map_err(AppError::from)
+}
+
+/// 计入播放历史门槛的设置键
+const SETTING_SCROBBLE_THRESHOLD: &str = "history.scrobble_threshold";
+
+/// 根据播放器事件累计实际播放时长，达到门槛后由后端写入播放历史
+static PLAY_TRACKER: Lazy<Mutex<PlayTracker>> = Lazy::new(|| Mutex::new(PlayTracker::default()));
+
+/// 写入一次后端记录的播放，成功返回true
+fn save_completed_play(play: CompletedPlay) -> bool {
+    let db = match DB.get() {
+        Some(db) => db,
+        None => return false,
+    };
+    let result = db.lock()
+        .map_err(|e| e.to_string())
+        .and_then(|db| db.add_play_history_with_source(play.track_id, play.played_ms as i64, play_history::PLAY_SOURCE_PLAYER)
+            .map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => {
+            log::info!("📝 记录播放: track_id={}, 时长={}ms", play.track_id, play.played_ms);
+            scrobbler::submit_play(play.track_id, play.played_ms);
+            true
+        }
+        Err(e) => {
+            log::warn!("⚠️ 记录播放历史失败: track_id={}, {}", play.track_id, e);
+            false
+        }
+    }
+}
+
+/// 写入播放历史并通知前端刷新
+/// 保存播放时更正的曲目时长，成功后通知前端
+fn save_corrected_duration(app: &AppHandle, track_id: i64, duration_ms: u64) {
+    let Some(db) = DB.get() else { return };
+    match db.with(|db| db.update_track_duration(track_id, duration_ms as i64)) {
+        Ok(true) => {
+            let _ = app.emit("track-updated", serde_json::json!({"trackId": track_id, "durationMs": duration_ms}));
+        }
+        Ok(false) => {}
+        Err(e) => log::warn!("⚠️ 保存曲目时长失败: track_id={}, {}", track_id, e),
+    }
+}
+
+fn record_completed_play(app: &AppHandle, play: Option<CompletedPlay>) {
+    if let Some(play) = play {
+        if save_completed_play(play) {
+            let _ = app.emit("play-history-recorded", serde_json::json!({"trackId": play.track_id, "durationPlayedMs": play.played_ms}));
         }
     }
-    drop(db_guard);
-    
-    manager.add_tracks_to_playlist(playlist_id, track_ids).map_err(|e| e.to_string())?;
-    
-    Ok(playlist_id)
 }
 
-// 其他功能命令
 #[tauri::command]
-async fn playlists_get_stats(state: State<'_, AppState>) -> Result<PlaylistStats, String> {
-    let db = state.inner().db.clone();
-    let manager = PlaylistManager::new(db);
-    manager.get_stats().map_err(|e| e.to_string())
+async fn history_get_scrobble_threshold() -> AppResult<ScrobbleThreshold> {
+    let db = DB.get().ok_or("Database not initialized")?;
+    let db = db.lock()?;
+    let value = db.get_setting(SETTING_SCROBBLE_THRESHOLD)?;
+    Ok(value.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn history_set_scrobble_threshold(threshold: ScrobbleThreshold) -> AppResult<()> {
+    threshold.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&threshold)?;
+    {
+        let db = DB.get().ok_or("Database not initialized")?;
+        let db = db.lock()?;
+        db.set_setting(SETTING_SCROBBLE_THRESHOLD, &json)?;
+    }
+    PLAY_TRACKER.lock()?.set_threshold(threshold);
+    Ok(())
+}
+
+/// 续播设置的设置键（JSON）
+const SETTING_RESUME: &str = "playback.resume_positions";
+
+/// 跟踪长曲目的播放位置，暂停、切歌时保存，重新播放时续播
+static RESUME_TRACKER: Lazy<Mutex<ResumeTracker>> = Lazy::new(|| Mutex::new(ResumeTracker::default()));
+
+/// 写入续播位置的变化
+fn apply_resume_action(action: Option<ResumeAction>) {
+    let (Some(action), Some(db)) = (action, DB.get()) else { return };
+    let result = db.lock()
+        .map_err(|e| e.to_string())
+        .and_then(|db| match action {
+            ResumeAction::Save { track_id, position_ms } => db.save_track_position(track_id, position_ms as i64),
+            ResumeAction::Clear { track_id } => db.clear_track_position(track_id),
+        }.map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log::warn!("⚠️ 保存续播位置失败: {:?}, {}", action, e);
+    }
+}
+
+/// 新曲目有保存的位置时请求播放器续播
+fn resume_saved_position(track_id: i64) {
+    let position = DB.get()
+        .and_then(|db| db.lock().ok()?.get_track_position(track_id).ok().flatten());
+    if let (Some(position), Some(tx)) = (position, PLAYER_TX.get()) {
+        log::info!("⏯️ 续播: track_id={}, {}ms", track_id, position.position_ms);
+        let _ = tx.send(PlayerCommand::ResumeAt { track_id, position_ms: position.position_ms as u64 });
+    }
+}
+
+/// 获取曲目保存的续播位置
+#[tauri::command]
+async fn track_get_saved_position(track_id: i64, state: State<'_, AppState>) -> AppResult<Option<SavedPosition>> {
+    let db = state.inner().db.lock()?;
+    db.get_track_position(track_id).map_err(AppError::from)
+}
+
+/// 清除曲目保存的续播位置
+#[tauri::command]
+async fn track_clear_saved_position(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.clear_track_position(track_id).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn player_get_resume_settings(state: State<'_, AppState>) -> AppResult<ResumeSettings> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_RESUME)?;
+    Ok(value.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn player_set_resume_settings(settings: ResumeSettings, state: State<'_, AppState>) -> AppResult<()> {
+    settings.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&settings)?;
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_RESUME, &json)?;
+    }
+    RESUME_TRACKER.lock()?.set_settings(settings);
+    Ok(())
+}
+
+/// 把新曲目的章节交给播放器，用于章节跳转和章节变化通知
+fn load_track_chapters(track_id: i64) {
+    let chapters = DB.get()
+        .and_then(|db| db.lock().ok()?.get_track_chapters(track_id).ok())
+        .unwrap_or_default();
+    if let Some(tx) = PLAYER_TX.get() {
+        let _ = tx.send(PlayerCommand::SetChapters { track_id, chapters });
+    }
+}
+
+/// 获取曲目的章节
+#[tauri::command]
+async fn track_get_chapters(track_id: i64, state: State<'_, AppState>) -> AppResult<Vec<Chapter>> {
+    let db = state.inner().db.lock()?;
+    db.get_track_chapters(track_id).map_err(AppError::from)
 }
 
-#[tauri::command]
-async fn playlists_mark_played(playlist_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.clone();
-    let manager = PlaylistManager::new(db);
-    manager.mark_played(playlist_id).map_err(|e| e.to_string())
+/// 开启跳过静音时把新曲目的裁剪点交给播放器
+fn load_track_trim_points(track_id: i64) {
+    let trim = DB.get().and_then(|db| {
+        let db = db.lock().ok()?;
+        if !library::load_silence_settings(&db).skip_silence {
+            return None;
+        }
+        db.get_trim_points(track_id).ok().flatten()
+    });
+    if let Some(tx) = PLAYER_TX.get() {
+        let _ = tx.send(PlayerCommand::SetTrimPoints { track_id, trim });
+    }
 }
 
+/// 获取曲目的静音裁剪点，尚未分析时返回None
 #[tauri::command]
-async fn playlists_toggle_favorite(playlist_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
-    let db = state.inner().db.clone();
-    let manager = PlaylistManager::new(db);
-    manager.toggle_favorite(playlist_id).map_err(|e| e.to_string())
+async fn track_get_trim_points(track_id: i64, state: State<'_, AppState>) -> AppResult<Option<TrimPoints>> {
+    let db = state.inner().db.lock()?;
+    db.get_trim_points(track_id).map_err(AppError::from)
 }
 
-// Pin歌单命令
 #[tauri::command]
-async fn playlists_pin(playlist_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.pin_playlist(playlist_id).map_err(|e| e.to_string())
+async fn player_get_silence_settings(state: State<'_, AppState>) -> AppResult<SilenceSettings> {
+    let db = state.inner().db.lock()?;
+    Ok(library::load_silence_settings(&db))
 }
 
+/// 保存静音处理设置，阈值变化后在后台重新分析（从下一首起生效）
 #[tauri::command]
-async fn playlists_unpin(playlist_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.unpin_playlist(playlist_id).map_err(|e| e.to_string())
+async fn player_set_silence_settings(settings: SilenceSettings, state: State<'_, AppState>) -> AppResult<()> {
+    settings.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&settings)?;
+    let previous = {
+        let db = state.inner().db.lock()?;
+        let previous = library::load_silence_settings(&db);
+        db.set_setting(library::SETTING_SILENCE, &json)?;
+        previous
+    };
+    if previous.threshold_db != settings.threshold_db {
+        log::info!("🔇 静音阈值改为 {}dB，重新分析曲库", settings.threshold_db);
+        let tx = LIBRARY_TX.get().ok_or("Library not initialized")?;
+        tx.send(LibraryCommand::AnalyzeSilence)?;
+    }
+    Ok(())
 }
 
-#[tauri::command]
-async fn playlists_toggle_pin(playlist_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.toggle_pin(playlist_id).map_err(|e| e.to_string())
+/// 播放记录提交设置的设置键（JSON）
+const SETTING_SCROBBLER_CONFIG: &str = "scrobbler.config";
+
+fn load_scrobbler_config(db: &Database) -> Option<ScrobblerConfig> {
+    db.get_setting(SETTING_SCROBBLER_CONFIG)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
 }
 
-// 播放历史命令
+/// 保存提交设置，令牌留空时沿用已保存的令牌和密钥
 #[tauri::command]
-async fn get_play_history(
-    sort_by: Option<String>,
-    limit: Option<i64>,
-    state: State<'_, AppState>
-) -> Result<Vec<PlayHistoryEntry>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let sort = sort_by.unwrap_or_else(|| "last_played".to_string());
-    let lim = limit.unwrap_or(50);
-    
-    let results = db.get_play_history(&sort, lim).map_err(|e| e.to_string())?;
-    
-    Ok(results.into_iter().map(|(track, play_count, last_played, first_played)| {
-        PlayHistoryEntry {
-            track,
-            play_count,
-            last_played_at: last_played,
-            first_played_at: first_played,
+async fn scrobbler_configure(mut config: ScrobblerConfig) -> AppResult<scrobbler::ScrobblerStatus> {
+    let db = DB.get().ok_or("Database not initialized")?;
+    let db = db.lock()?;
+    if let Some(saved) = load_scrobbler_config(&db).filter(|saved| saved.service == config.service) {
+        if config.token.trim().is_empty() {
+            config.token = saved.token;
         }
-    }).collect())
+        if config.api_secret.trim().is_empty() {
+            config.api_secret = saved.api_secret;
+        }
+    }
+    if config.enabled {
+        config.validate().map_err(AppError::InvalidInput)?;
+    }
+
+    let json = serde_json::to_string(&config)?;
+    db.set_setting(SETTING_SCROBBLER_CONFIG, &json)?;
+    log::info!("🎵 播放记录提交: {} ({})", if config.enabled { "已启用" } else { "已关闭" }, config.service.as_str());
+    scrobbler::configure(Some(config));
+    Ok(scrobbler::status(&db))
 }
 
 #[tauri::command]
-async fn get_play_statistics(state: State<'_, AppState>) -> Result<PlayStatistics, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let (total_plays, unique_tracks, total_duration_ms) = db.get_play_statistics()
-        .map_err(|e| e.to_string())?;
-    
-    Ok(PlayStatistics {
-        total_plays,
-        unique_tracks,
-        total_duration_ms,
-    })
+async fn scrobbler_get_status() -> AppResult<scrobbler::ScrobblerStatus> {
+    let db = DB.get().ok_or("Database not initialized")?;
+    let db = db.lock()?;
+    Ok(scrobbler::status(&db))
+}
+
+/// 测试连接，返回服务端用户名；不传设置时使用已保存的设置
+#[tauri::command]
+async fn scrobbler_test_connection(config: Option<ScrobblerConfig>) -> AppResult<String> {
+    let saved = {
+        let db = DB.get().ok_or("Database not initialized")?;
+        let db = db.lock()?;
+        load_scrobbler_config(&db)
+    };
+    let config = match (config, saved) {
+        (Some(mut config), Some(saved)) if saved.service == config.service => {
+            if config.token.trim().is_empty() {
+                config.token = saved.token;
+            }
+            if config.api_secret.trim().is_empty() {
+                config.api_secret = saved.api_secret;
+            }
+            config
+        }
+        (Some(config), _) => config,
+        (None, Some(saved)) => saved,
+        (None, None) => return Err(AppError::invalid_input("尚未配置播放记录提交")),
+    };
+    scrobbler::test_connection(&config).await.map_err(|e| AppError::Network { status: None, message: e })
 }
 
+/// 手动或导入写入播放历史（正常播放由后端自动记录）
 #[tauri::command]
-async fn add_play_history(track_id: i64, duration_played_ms: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.add_play_history(track_id, duration_played_ms).map_err(|e| e.to_string())
+async fn add_play_history(track_id: i64, duration_played_ms: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.add_play_history(track_id, duration_played_ms).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn clear_play_history(state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.clear_play_history().map_err(|e| e.to_string())
+async fn clear_play_history(state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.clear_play_history().map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn remove_from_history(track_id: i64, state: State<'_, AppState>) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.remove_from_history(track_id).map_err(|e| e.to_string())
+async fn remove_from_history(track_id: i64, state: State<'_, AppState>) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.remove_from_history(track_id).map_err(AppError::from)
 }
 
 // Window control commands
 #[tauri::command]
-async fn minimize_window(window: tauri::Window) -> Result<(), String> {
-    window.minimize().map_err(|e| e.to_string())
+async fn minimize_window(window: tauri::Window) -> AppResult<()> {
+    window.minimize().map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn toggle_maximize(window: tauri::Window) -> Result<(), String> {
-    if window.is_maximized().map_err(|e| e.to_string())? {
-        window.unmaximize().map_err(|e| e.to_string())
+async fn toggle_maximize(window: tauri::Window) -> AppResult<()> {
+    if window.is_maximized()? {
+        window.unmaximize().map_err(AppError::from)
     } else {
-        window.maximize().map_err(|e| e.to_string())
+        window.maximize().map_err(AppError::from)
     }
 }
 
 #[tauri::command]
-async fn close_window(window: tauri::Window) -> Result<(), String> {
-    window.close().map_err(|e| e.to_string())
+async fn close_window(window: tauri::Window) -> AppResult<()> {
+    window.close().map_err(AppError::from)
 }
 
 // Audio device commands
 #[tauri::command]
-async fn check_audio_devices() -> Result<String, String> {
+async fn check_audio_devices() -> AppResult<String> {
     use rodio::{OutputStream, Sink};
     
     log::info!("检查音频设备...");
@@ -1156,64 +2842,129 @@ async fn check_audio_devices() -> Result<String, String> {
                 }
                 Err(e) => {
                     log::error!("❌ 音频sink创建失败: {}", e);
-                    Err(format!("音频设备部分可用但无法创建sink: {}", e))
+                    Err(AppError::internal(format!("音频设备部分可用但无法创建sink: {}", e)))
                 }
             }
         }
         Err(e) => {
             log::error!("❌ 找不到音频设备: {}", e);
-            Err(format!("找不到音频设备: {}", e))
+            Err(AppError::not_found(format!("找不到音频设备: {}", e)))
         }
     }
 }
 
 // Album cover commands
+/// 按封面ID获取封面（size: small=128px / large=512px 缩略图，original=原图）
 #[tauri::command]
-async fn get_album_cover(track_id: i64, state: State<'_, AppState>) -> Result<Option<(Vec<u8>, String)>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    
-    match db.get_track_by_id(track_id).map_err(|e| e.to_string())? {
-        Some(track) => {
-            if let Some(cover_data) = track.album_cover_data {
-                // 宽松处理MIME类型：有数据就返回，没有MIME时使用默认值
-                let mime_type = track.album_cover_mime.unwrap_or_else(|| {
-                    // 根据数据头判断图片格式
-                    if cover_data.len() >= 4 {
-                        if &cover_data[0..4] == b"\xFF\xD8\xFF\xE0" || &cover_data[0..4] == b"\xFF\xD8\xFF\xE1" {
-                            "image/jpeg".to_string()
-                        } else if &cover_data[0..4] == b"\x89PNG" {
-                            "image/png".to_string()
-                        } else {
-                            "image/jpeg".to_string() // 默认JPEG
-                        }
-                    } else {
-                        "image/jpeg".to_string()
-                    }
-                });
-                
-                log::info!("✅ 返回封面数据: track_id={}, size={}, mime={}", track_id, cover_data.len(), mime_type);
-                Ok(Some((cover_data, mime_type)))
-            } else {
-                log::warn!("❌ 数据库中无封面数据: track_id={}, path={}", track_id, track.path);
-                Ok(None)
-            }
+async fn get_cover(
+    cover_id: String,
+    size: Option<CoverSize>,
+    state: State<'_, AppState>,
+) -> AppResult<Option<CoverImage>> {
+    let db = state.inner().db.lock()?;
+    db.get_cover(&cover_id, size.unwrap_or_default()).map_err(AppError::from)
+}
+
+/// 批量获取曲目封面，相同封面只返回一份
+#[tauri::command]
+async fn get_covers_for_tracks(
+    track_ids: Vec<i64>,
+    size: Option<CoverSize>,
+    state: State<'_, AppState>,
+) -> AppResult<TrackCovers> {
+    let db = state.inner().db.lock()?;
+    db.get_covers_for_tracks(&track_ids, size.unwrap_or_default()).map_err(AppError::from)
+}
+
+/// 旧接口：按曲目ID返回封面原图，新代码请使用 get_cover
+#[tauri::command]
+async fn get_album_cover(track_id: i64, state: State<'_, AppState>) -> AppResult<Option<(Vec<u8>, String)>> {
+    let db = state.inner().db.lock()?;
+    
+    let track = db.get_track_by_id(track_id)?
+        .ok_or_else(|| AppError::not_found("Track not found"))?;
+    let Some(cover_id) = track.cover_id else {
+        log::warn!("❌ 数据库中无封面数据: track_id={}, path={}", track_id, track.path);
+        return Ok(None);
+    };
+    let cover = db.get_cover(&cover_id, CoverSize::Original)?;
+    Ok(cover.map(|c| (c.data, c.mime)))
+}
+
+/// 获取曲目封面的配色，用于播放页着色；结果按封面缓存，封面缺失或损坏时返回默认配色
+#[tauri::command]
+async fn get_album_cover_palette(track_id: i64, state: State<'_, AppState>) -> AppResult<cover_palette::CoverPalette> {
+    let (cover_id, cover) = {
+        let db = state.inner().db.lock()?;
+        let track = db.get_track_by_id(track_id)?
+            .ok_or_else(|| AppError::not_found("Track not found"))?;
+        let Some(cover_id) = track.cover_id else {
+            return Ok(cover_palette::CoverPalette::default());
+        };
+        if let Some(palette) = db.get_cover_palette(&cover_id)? {
+            return Ok(palette);
         }
-        None => {
-            log::error!("❌ 未找到曲目: track_id={}", track_id);
-            Err("Track not found".to_string())
+        let cover = db.get_cover(&cover_id, CoverSize::Large)?;
+        (cover_id, cover)
+    };
+    let Some(cover) = cover else {
+        return Ok(cover_palette::CoverPalette::default());
+    };
+
+    let palette = tokio::task::spawn_blocking(move || cover_palette::extract_palette(&cover.data))
+        .await?
+        .unwrap_or_else(|e| {
+            log::warn!("封面取色失败，使用默认配色: track_id={} ({})", track_id, e);
+            cover_palette::CoverPalette::default()
+        });
+
+    let db = state.inner().db.lock()?;
+    db.save_cover_palette(&cover_id, &palette)?;
+    Ok(palette)
+}
+
+/// 重新完整提取单个曲目的元数据（用户在外部修复文件后），成功后清除"元数据不完整"标记
+#[tauri::command]
+async fn refresh_track_metadata(track_id: i64, state: State<'_, AppState>) -> AppResult<Track> {
+    let db = state.inner().db.clone();
+    let track = db.run_read(move |db| db.get_track_by_id(track_id)).await?
+        .ok_or_else(|| AppError::not_found("Track not found"))?;
+
+    log::info!("🔄 重新提取元数据: track_id={}, path={}", track_id, track.path);
+    let path = track.path.clone();
+    let metadata = tokio::task::spawn_blocking(move || {
+        metadata_extractor::MetadataExtractor::new().extract_from_file(std::path::Path::new(&path))
+    }).await?;
+
+    let metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            log::warn!("⚠️ 重新提取元数据失败: track_id={}, error={}", track_id, e);
+            let reason = e.to_string();
+            db.run_write(move |db| db.save_scan_issue(track_id, &reason)).await?;
+            return Err(AppError::Io(format!("提取元数据失败: {}", e)));
         }
-    }
+    };
+
+    let path = track.path;
+    let pool = db.clone();
+    tokio::task::spawn_blocking(move || library::save_track_metadata(&pool, &path, Some(track_id), metadata)).await??;
+    db.run_write(move |db| {
+        db.prune_unused_covers()?;
+        db.get_track_by_id(track_id)
+    }).await?
+        .ok_or_else(|| AppError::not_found("Track not found"))
 }
 
 // 重新提取单个曲目的封面
 #[tauri::command]
-async fn refresh_track_cover(track_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+async fn refresh_track_cover(track_id: i64, state: State<'_, AppState>) -> AppResult<bool> {
     use crate::metadata_extractor::MetadataExtractor;
     use std::path::Path;
     
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
+    let db = state.inner().db.lock()?;
     
-    match db.get_track_by_id(track_id).map_err(|e| e.to_string())? {
+    match db.get_track_by_id(track_id)? {
         Some(track) => {
             log::info!("🔄 重新提取封面: track_id={}, path={}", track_id, track.path);
             
@@ -1224,8 +2975,7 @@ async fn refresh_track_cover(track_id: i64, state: State<'_, AppState>) -> Resul
                 Ok(metadata) => {
                     if let (Some(cover_data), Some(mime)) = (metadata.album_cover_data, metadata.album_cover_mime) {
                         // 更新数据库中的封面
-                        db.update_track_cover(track_id, Some(cover_data), Some(mime))
-                            .map_err(|e| e.to_string())?;
+                        db.update_track_cover(track_id, Some(cover_data), Some(mime))?;
                         
                         log::info!("✅ 封面更新成功: track_id={}", track_id);
                         Ok(true)
@@ -1236,85 +2986,118 @@ async fn refresh_track_cover(track_id: i64, state: State<'_, AppState>) -> Resul
                 }
                 Err(e) => {
                     log::error!("❌ 提取元数据失败: track_id={}, error={}", track_id, e);
-                    Err(format!("提取元数据失败: {}", e))
+                    Err(AppError::Io(format!("提取元数据失败: {}", e)))
                 }
             }
         }
         None => {
             log::error!("❌ 未找到曲目: track_id={}", track_id);
-            Err("Track not found".to_string())
+            Err(AppError::not_found("Track not found"))
         }
     }
 }
 
 
-// Audio debug commands
+/// 输出设备的设置键
+const SETTING_OUTPUT_DEVICE: &str = "audio.output_device";
+
+/// 列出可用的输出设备及其能力
 #[tauri::command]
-async fn debug_audio_system() -> Result<String, String> {
-    use rodio::OutputStream;
-    use cpal::traits::{DeviceTrait, HostTrait};
-    
-    log::info!("调试音频系统...");
-    
-    let mut result = String::new();
-    result.push_str("音频系统调试报告:\n\n");
-    
-    // 1. 检查音频主机
-    let hosts = cpal::available_hosts();
-    result.push_str(&format!("可用音频主机: {:?}\n", hosts));
-    
-    // 2. 检查默认主机和设备
-    let host = cpal::default_host();
-    if let Some(device) = host.default_output_device() {
-        if let Ok(name) = device.name() {
-            result.push_str(&format!("默认输出设备: {}\n", name));
-            
-            // 3. 检查设备配置
-            if let Ok(config) = device.default_output_config() {
-                result.push_str(&format!("默认配置: {:?}\n", config));
-            }
-            
-            // 4. 测试OutputStream创建
-            match OutputStream::try_from_device(&device) {
-                Ok((stream, handle)) => {
-                    result.push_str("✅ OutputStream创建成功\n");
-                    
-                    // 5. 测试Sink创建
-                    match rodio::Sink::try_new(&handle) {
-                        Ok(_) => result.push_str("✅ Sink创建成功\n"),
-                        Err(e) => result.push_str(&format!("❌ Sink创建失败: {}\n", e)),
-                    }
-                    
-                    std::mem::forget(stream); // 保持stream存活
-                }
-                Err(e) => {
-                    result.push_str(&format!("❌ OutputStream创建失败: {}\n", e));
-                }
-            }
-        }
-    } else {
-        result.push_str("❌ 未找到默认输出设备\n");
+async fn audio_list_output_devices() -> AppResult<Vec<player::OutputDeviceInfo>> {
+    player::list_output_devices().map_err(AppError::from)
+}
+
+/// 设置输出设备（None 为系统默认设备），正在播放时切换到新设备继续播放
+#[tauri::command]
+async fn audio_set_output_device(name: Option<String>, state: State<'_, AppState>) -> AppResult<()> {
+    {
+        let db = state.inner().db.lock()?;
+        match &name {
+            Some(name) => db.set_setting(SETTING_OUTPUT_DEVICE, name),
+            None => db.delete_setting(SETTING_OUTPUT_DEVICE),
+        }?;
     }
-    
-    // 6. 列出所有输出设备
-    match host.output_devices() {
-        Ok(devices) => {
-            result.push_str("\n所有输出设备:\n");
-            for (i, device) in devices.enumerate() {
-                if let Ok(name) = device.name() {
-                    result.push_str(&format!("  {}. {}\n", i + 1, name));
-                }
-            }
-        }
-        Err(e) => {
-            result.push_str(&format!("❌ 无法枚举输出设备: {}\n", e));
-        }
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetOutputDevice(name))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn audio_get_output_device(state: State<'_, AppState>) -> AppResult<Option<String>> {
+    let db = state.inner().db.lock()?;
+    db.get_setting(SETTING_OUTPUT_DEVICE).map_err(AppError::from)
+}
+
+/// 输出格式设置的设置键（JSON）
+const SETTING_OUTPUT_CONFIG: &str = "audio.output_config";
+
+/// 设置输出格式（原始采样率、独占模式），正在播放时重新打开设备继续播放
+#[tauri::command]
+async fn audio_set_output_config(config: player::OutputConfig, state: State<'_, AppState>) -> AppResult<()> {
+    let json = serde_json::to_string(&config)?;
+    {
+        let db = state.inner().db.lock()?;
+        db.set_setting(SETTING_OUTPUT_CONFIG, &json)?;
     }
-    
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    tx.send(PlayerCommand::SetOutputConfig(config))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn audio_get_output_config(state: State<'_, AppState>) -> AppResult<player::OutputConfig> {
+    let db = state.inner().db.lock()?;
+    let value = db.get_setting(SETTING_OUTPUT_CONFIG)?;
+    Ok(value.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+/// 实际协商得到的输出格式（如 24-bit / 96 kHz），设备尚未打开时为None
+#[tauri::command]
+async fn audio_get_output_format() -> AppResult<Option<player::OutputFormat>> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::GetOutputFormat(reply_tx))?;
+    reply_rx.await.map_err(AppError::from)
+}
+
+/// 当前流的缓冲量、下载速度、重新缓冲和重连次数，不是流式播放时为None
+#[tauri::command]
+async fn player_get_stream_stats() -> AppResult<Option<streaming::buffering::StreamStats>> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::GetStreamStats(reply_tx))?;
+    reply_rx.await.map_err(AppError::from)
+}
+
+/// 预加载缓存和命中统计，未启用预加载时为None
+#[tauri::command]
+async fn debug_preload_stats() -> AppResult<Option<player::PreloadStats>> {
+    let tx = PLAYER_TX.get().ok_or("Player not initialized")?;
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    tx.send(PlayerCommand::GetPreloadStats(reply_tx))?;
+    reply_rx.await.map_err(AppError::from)
+}
+
+// Audio debug commands
+#[tauri::command]
+async fn debug_audio_system() -> AppResult<String> {
+    log::info!("调试音频系统...");
+    let result = tokio::task::spawn_blocking(diagnostics::audio_debug_report).await?;
     log::info!("音频系统调试完成");
     Ok(result)
 }
 
+/// 导出诊断包（日志、设置、曲库和缓存统计、音频诊断、版本信息）到指定的 zip 文件
+#[tauri::command]
+async fn diagnostics_export(path: String, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("🩺 导出诊断包: {}", path);
+    let cache_stats = CACHE_MANAGER.get().map(|m| m.get_stats());
+    let log_files = logging::log_files();
+    state.inner().db.run_read(move |db| {
+        diagnostics::DiagnosticsBundle::collect(db, cache_stats, log_files).write_to(std::path::Path::new(&path))
+    }).await
+}
+
 // ============================================================
 // WebDAV 命令
 // ============================================================
@@ -1325,10 +3108,11 @@ async fn webdav_test_connection(
     url: String,
     username: String,
     password: String,
-) -> Result<String, String> {
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<String> {
     log::info!("测试 WebDAV 连接: {}", url);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "test".to_string(),
         name: "测试服务器".to_string(),
         url,
@@ -1336,24 +3120,28 @@ async fn webdav_test_connection(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
+    
+    // 先用 OPTIONS 确认连接并取得协商的HTTP版本，再测试获取根目录信息
+    let protocol = client.test_connection().await
+        .map_err(|e| AppError::from(e).context("❌ WebDAV 连接失败"))?;
     
-    // 测试获取根目录信息
     match client.get_file_info("/").await {
         Ok(file_info) => {
             Ok(format!(
-                "✅ WebDAV 连接成功！\n服务器: {}\n类型: {}",
+                "✅ WebDAV 连接成功！\n服务器: {}\n类型: {}\n协议: {}",
                 file_info.name,
-                if file_info.is_directory { "目录" } else { "文件" }
+                if file_info.is_directory { "目录" } else { "文件" },
+                protocol
             ))
         }
-        Err(e) => Err(format!("❌ WebDAV 连接失败: {}", e)),
+        Err(e) => Err(AppError::from(e).context("❌ WebDAV 连接失败")),
     }
 }
 
@@ -1364,10 +3152,11 @@ async fn webdav_list_directory(
     username: String,
     password: String,
     path: String,
-) -> Result<Vec<WebDAVFileInfo>, String> {
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<Vec<WebDAVFileInfo>> {
     log::info!("列出 WebDAV 目录: {}", path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "browse".to_string(),
         name: "浏览服务器".to_string(),
         url,
@@ -1375,17 +3164,17 @@ async fn webdav_list_directory(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     match client.list_directory(&path).await {
         Ok(listing) => Ok(listing.files),
-        Err(e) => Err(format!("列出目录失败: {}", e)),
+        Err(e) => Err(AppError::from(e).context("列出目录失败")),
     }
 }
 
@@ -1396,10 +3185,11 @@ async fn webdav_get_file_info(
     username: String,
     password: String,
     file_path: String,
-) -> Result<WebDAVFileInfo, String> {
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<WebDAVFileInfo> {
     log::info!("获取 WebDAV 文件信息: {}", file_path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "info".to_string(),
         name: "信息查询".to_string(),
         url,
@@ -1407,17 +3197,17 @@ async fn webdav_get_file_info(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     match client.get_file_info(&file_path).await {
         Ok(file_info) => Ok(file_info),
-        Err(e) => Err(format!("获取文件信息失败: {}", e)),
+        Err(e) => Err(AppError::from(e).context("获取文件信息失败")),
     }
 }
 
@@ -1428,10 +3218,11 @@ async fn webdav_file_exists(
     username: String,
     password: String,
     file_path: String,
-) -> Result<bool, String> {
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<bool> {
     log::info!("检查 WebDAV 文件是否存在: {}", file_path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "check".to_string(),
         name: "文件检查".to_string(),
         url,
@@ -1439,17 +3230,17 @@ async fn webdav_file_exists(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     match client.file_exists(&file_path).await {
         Ok(exists) => Ok(exists),
-        Err(e) => Err(format!("检查文件失败: {}", e)),
+        Err(e) => Err(AppError::from(e).context("检查文件失败")),
     }
 }
 
@@ -1460,10 +3251,11 @@ async fn webdav_download_file(
     username: String,
     password: String,
     file_path: String,
-) -> Result<Vec<u8>, String> {
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<Vec<u8>> {
     log::info!("下载 WebDAV 文件到内存: {}", file_path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "download".to_string(),
         name: "文件下载".to_string(),
         url,
@@ -1471,17 +3263,17 @@ async fn webdav_download_file(
         password,
         timeout_seconds: 120, // 增加超时时间用于大文件
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     // 下载文件流
     let stream = client.download_stream(&file_path).await
-        .map_err(|e| format!("下载文件失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("下载文件失败"))?;
     
     // 收集所有字节到内存
     use futures::StreamExt;
@@ -1489,7 +3281,7 @@ async fn webdav_download_file(
     let mut stream = Box::pin(stream);
     
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("读取数据块失败: {}", e))?;
+        let chunk = chunk.map_err(|e| AppError::from(e).context("读取数据块失败"))?;
         bytes.extend_from_slice(&chunk);
     }
     
@@ -1504,10 +3296,12 @@ async fn webdav_create_directory(
     username: String,
     password: String,
     dir_path: String,
-) -> Result<(), String> {
+    server_id: Option<String>,
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<()> {
     log::info!("创建 WebDAV 目录: {}", dir_path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "mkdir".to_string(),
         name: "创建目录".to_string(),
         url,
@@ -1515,17 +3309,21 @@ async fn webdav_create_directory(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     match client.create_directory(&dir_path).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("创建目录失败: {}", e)),
+        Ok(_) => {
+            // 未指定服务器时按路径使所有服务器的目录列表缓存失效
+            remote_source::listing_cache::LISTING_CACHE.invalidate_entry(server_id.as_deref(), &dir_path);
+            Ok(())
+        }
+        Err(e) => Err(AppError::from(e).context("创建目录失败")),
     }
 }
 
@@ -1536,10 +3334,12 @@ async fn webdav_delete_file(
     username: String,
     password: String,
     file_path: String,
-) -> Result<(), String> {
+    server_id: Option<String>,
+    options: Option<WebDAVConnectionOptions>,
+) -> AppResult<()> {
     log::info!("删除 WebDAV 文件: {}", file_path);
     
-    let config = WebDAVConfig {
+    let mut config = WebDAVConfig {
         server_id: "delete".to_string(),
         name: "删除文件".to_string(),
         url,
@@ -1547,17 +3347,20 @@ async fn webdav_delete_file(
         password,
         timeout_seconds: 30,
         max_redirects: 5,
-        verify_ssl: true,
         user_agent: "WindChimePlayer/1.0".to_string(),
         ..Default::default()
     };
+    options.unwrap_or_default().apply_to(&mut config);
     
     let client = WebDAVClient::new(config)
-        .map_err(|e| format!("创建 WebDAV 客户端失败: {}", e))?;
+        .map_err(|e| AppError::from(e).context("创建 WebDAV 客户端失败"))?;
     
     match client.delete_file(&file_path).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("删除文件失败: {}", e)),
+        Ok(_) => {
+            remote_source::listing_cache::LISTING_CACHE.invalidate_entry(server_id.as_deref(), &file_path);
+            Ok(())
+        }
+        Err(e) => Err(AppError::from(e).context("删除文件失败")),
     }
 }
 
@@ -1576,24 +3379,23 @@ async fn remote_add_server(
     server_type: String,
     name: String,
     config_json: String,
-) -> Result<String, String> {
+) -> AppResult<String> {
     let id = format!("{}_{}", server_type, uuid::Uuid::new_v4().to_string());
     
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.add_remote_server(&id, &name, &server_type, &config_json)
-        .map_err(|e| e.to_string())?;
+    let db = state.inner().db.lock()?;
+    db.add_remote_server(&id, &name, &server_type, &config_json)?;
     
     log::info!("添加远程服务器: {} ({})", name, server_type);
     Ok(id)
 }
 
+/// 获取远程服务器列表，配置中不含密码（has_password 表示是否已保存）
 #[tauri::command]
 async fn remote_get_servers(
     state: State<'_, AppState>,
-) -> Result<Vec<serde_json::Value>, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let servers = db.get_remote_servers()
-        .map_err(|e| e.to_string())?;
+) -> AppResult<Vec<serde_json::Value>> {
+    let db = state.inner().db.lock()?;
+    let servers = db.get_remote_servers()?;
     
     let result: Vec<serde_json::Value> = servers.into_iter()
         .map(|(id, name, server_type, config_json, enabled)| {
@@ -1601,7 +3403,7 @@ async fn remote_get_servers(
                 "id": id,
                 "name": name,
                 "server_type": server_type,
-                "config": serde_json::from_str::<serde_json::Value>(&config_json).unwrap_or(serde_json::json!({})),
+                "config": secrets::redact_config(&config_json),
                 "enabled": enabled
             })
         })
@@ -1610,14 +3412,32 @@ async fn remote_get_servers(
     Ok(result)
 }
 
+/// 获取服务器的用户名和密码（编辑服务器时显式调用）
+#[tauri::command]
+async fn remote_get_server_credentials(
+    state: State<'_, AppState>,
+    server_id: String,
+) -> AppResult<serde_json::Value> {
+    let db = state.inner().db.lock()?;
+    let (_, _, _, config_json, _) = db.get_remote_servers()?
+        .into_iter()
+        .find(|(id, ..)| *id == server_id)
+        .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+    
+    let config: serde_json::Value = serde_json::from_str(&config_json)?;
+    Ok(serde_json::json!({
+        "username": config.get("username").cloned().unwrap_or_default(),
+        "password": config.get(secrets::PASSWORD_FIELD).cloned().unwrap_or_default(),
+    }))
+}
+
 #[tauri::command]
 async fn remote_delete_server(
     state: State<'_, AppState>,
     server_id: String,
-) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.delete_remote_server(&server_id)
-        .map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.delete_remote_server(&server_id)?;
     
     log::info!("删除远程服务器: {}", server_id);
     Ok(())
@@ -1629,22 +3449,48 @@ async fn remote_update_server(
     server_id: String,
     name: String,
     config_json: String,
-) -> Result<(), String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    db.update_remote_server(&server_id, &name, &config_json)
-        .map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    let db = state.inner().db.lock()?;
+    db.update_remote_server(&server_id, &name, &config_json)?;
     
     log::info!("更新远程服务器: {} ({})", name, server_id);
     Ok(())
 }
 
+/// 信任服务器证书：保存用户确认的证书指纹，之后该服务器使用此证书时不再校验证书链
+#[tauri::command]
+async fn remote_trust_certificate(
+    state: State<'_, AppState>,
+    server_id: String,
+    fingerprint: String,
+) -> AppResult<()> {
+    if !webdav::tls::is_valid_fingerprint(&fingerprint) {
+        return Err(AppError::invalid_input(format!("无效的证书指纹: {}", fingerprint)));
+    }
+    
+    let db = state.inner().db.lock()?;
+    let (_, name, server_type, config_json, _) = db.get_remote_servers()?
+        .into_iter()
+        .find(|(id, ..)| *id == server_id)
+        .ok_or_else(|| AppError::not_found(format!("服务器不存在: {}", server_id)))?;
+    if server_type != "webdav" {
+        return Err(AppError::invalid_input(format!("只有WebDAV服务器支持固定证书: {}", server_type)));
+    }
+    
+    let mut config: serde_json::Value = serde_json::from_str(&config_json)?;
+    config["pinned_certificate"] = fingerprint.clone().into();
+    db.update_remote_server(&server_id, &name, &config.to_string())?;
+    
+    log::info!("已信任服务器证书: {} ({})", server_id, fingerprint);
+    Ok(())
+}
+
 #[tauri::command]
 async fn remote_get_cache_stats(
     state: State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-    let (count, total_size) = db.get_cache_stats()
-        .map_err(|e| e.to_string())?;
+) -> AppResult<serde_json::Value> {
+    let db = state.inner().db.lock()?;
+    let (count, total_size) = db.get_cache_stats()?;
     
     Ok(serde_json::json!({
         "file_count": count,
@@ -1652,23 +3498,106 @@ async fn remote_get_cache_stats(
     }))
 }
 
+// ==================== 数据库维护命令 ====================
+
+/// 整理数据库文件，返回整理前后的大小
+#[tauri::command]
+async fn db_vacuum(state: State<'_, AppState>) -> AppResult<VacuumStats> {
+    state.inner().db.run_write(|db| db.vacuum()).await
+}
+
+/// 重建全文搜索索引，返回重建前后的曲目数和索引行数
+#[tauri::command]
+async fn library_rebuild_search_index(state: State<'_, AppState>) -> AppResult<SearchIndexRebuild> {
+    log::info!("🔎 重建全文搜索索引");
+    state.inner().db.run_write(|db| db.rebuild_search_index()).await
+}
+
+/// 数据库完整性检查，返回 PRAGMA integrity_check 的输出
+#[tauri::command]
+async fn db_integrity_check(state: State<'_, AppState>) -> AppResult<Vec<String>> {
+    state.inner().db.run_read(|db| db.integrity_check()).await
+}
+
+/// 在线备份数据库到指定文件
+#[tauri::command]
+async fn db_backup(path: String, state: State<'_, AppState>) -> AppResult<()> {
+    log::info!("💾 备份数据库到: {}", path);
+    state.inner().db.run_read(move |db| db.backup_to(&path)).await
+}
+
+// ==================== 日志命令 ====================
+
+#[tauri::command]
+async fn logging_get_config(state: State<'_, AppState>) -> AppResult<logging::LoggingConfig> {
+    let db = state.inner().db.read()?;
+    Ok(logging::load_config(&db))
+}
+
+/// 保存日志设置，级别和日志文件立即按新设置生效
+#[tauri::command]
+async fn logging_update_config(config: logging::LoggingConfig, state: State<'_, AppState>) -> AppResult<()> {
+    config.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&config)?;
+    state.inner().db.lock()?.set_setting(logging::SETTING_LOGGING_CONFIG, &json)?;
+    logging::apply(&config);
+    Ok(())
+}
+
+/// 修改日志级别（报告问题时打开 debug 日志），无需重启
+#[tauri::command]
+async fn logging_set_level(level: String, state: State<'_, AppState>) -> AppResult<()> {
+    logging::validate_level(&level).map_err(AppError::InvalidInput)?;
+    {
+        let db = state.inner().db.lock()?;
+        let config = logging::LoggingConfig { level: level.clone(), ..logging::load_config(&db) };
+        db.set_setting(logging::SETTING_LOGGING_CONFIG, &serde_json::to_string(&config)?)?;
+    }
+    logging::set_level(&level).map_err(AppError::Internal)
+}
+
+/// 最近的 n 行日志（默认200行），供诊断页面显示
+#[tauri::command]
+async fn logging_get_recent(n: Option<usize>) -> AppResult<Vec<String>> {
+    Ok(logging::recent_lines(n.unwrap_or(200)))
+}
+
 // ==================== 音频缓存命令 ====================
 
+/// app_settings中保存缓存配置的键
+const SETTING_CACHE_CONFIG: &str = "cache.config";
+
+fn cache_manager() -> AppResult<&'static cache::manager::CacheManager> {
+    CACHE_MANAGER.get().map(|m| m.as_ref()).ok_or_else(|| "缓存管理器未初始化".to_string())
+}
+
+/// 读取保存的缓存配置，没有时使用默认配置
+fn load_cache_config(db: &Database) -> cache::CacheConfig {
+    db.get_setting(SETTING_CACHE_CONFIG)
+        .ok()
+        .flatten()
+        .and_then(|json| cache::CacheConfig::from_json(&json).ok())
+        .unwrap_or_default()
+}
+
 #[tauri::command]
-async fn cache_get_config() -> Result<String, String> {
-    // TODO: 从数据库加载配置
-    let config = cache::CacheConfig::default();
-    config.to_json().map_err(|e| e.to_string())
+async fn cache_get_config() -> AppResult<String> {
+    let config = CACHE_MANAGER.get().map(|m| m.get_config()).unwrap_or_default();
+    config.to_json().map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn cache_update_config(config_json: String) -> Result<(), String> {
+async fn cache_update_config(config_json: String, state: State<'_, AppState>) -> AppResult<()> {
     let config = cache::CacheConfig::from_json(&config_json)
-        .map_err(|e| format!("解析配置失败: {}", e))?;
+        .map_err(|e| AppError::invalid_input(format!("解析配置失败: {}", e)))?;
+    
+    config.validate().map_err(AppError::InvalidInput)?;
+    cache_manager()?.update_config(config.clone())?;
     
-    config.validate()?;
+    let json = config.to_json()?;
+    state.inner().db.lock()?
+        .set_setting(SETTING_CACHE_CONFIG, &json)?;
     
-    // TODO: 保存到数据库
     log::info!("缓存配置已更新: max_size={} MB, path={:?}", 
         config.max_size_mb, config.cache_path);
     
@@ -1676,13 +3605,31 @@ async fn cache_update_config(config_json: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn cache_get_stats() -> Result<serde_json::Value, String> {
-    // TODO: 从CacheManager获取统计
-    let stats = cache::CacheStats::default();
+async fn get_cache_strategy() -> AppResult<cache::CacheStrategy> {
+    Ok(CACHE_MANAGER.get().map(|m| m.get_strategy()).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn set_cache_strategy(strategy: cache::CacheStrategy, state: State<'_, AppState>) -> AppResult<()> {
+    let config = cache_manager()?.set_strategy(strategy);
+    
+    let json = config.to_json()?;
+    state.inner().db.lock()?
+        .set_setting(SETTING_CACHE_CONFIG, &json)?;
+    
+    log::info!("缓存策略已切换: {:?}", strategy);
+    Ok(())
+}
+
+#[tauri::command]
+async fn cache_get_stats() -> AppResult<serde_json::Value> {
+    let manager = cache_manager()?;
+    let stats = manager.get_stats();
     
     Ok(serde_json::json!({
         "file_count": stats.file_count,
         "total_size_mb": stats.total_size_mb,
+        "max_size_mb": manager.get_config().max_size_mb,
         "usage_percent": stats.usage_percent,
         "hit_rate": stats.hit_rate,
         "saved_bandwidth_mb": stats.saved_bandwidth_mb,
@@ -1693,24 +3640,81 @@ async fn cache_get_stats() -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-async fn cache_clear_all() -> Result<(), String> {
-    // TODO: 调用CacheManager清空缓存
+async fn cache_clear_all() -> AppResult<()> {
     log::info!("清空所有缓存");
+    cache_manager()?.clear_all()
+}
+
+#[tauri::command]
+async fn cache_auto_cleanup() -> AppResult<u32> {
+    log::info!("执行自动清理");
+    cache_manager()?.auto_cleanup()
+}
+
+/// 固定曲目缓存，固定后不会被自动清理
+#[tauri::command]
+async fn cache_pin_track(track_id: i64, pinned: bool) -> AppResult<()> {
+    log::info!("📌 {}曲目缓存: {}", if pinned { "固定" } else { "取消固定" }, track_id);
+    cache_manager()?.set_pinned(track_id, pinned)
+}
+
+// ==================== 网络带宽命令 ====================
+
+/// app_settings中保存带宽限制的键（JSON）
+const SETTING_BANDWIDTH: &str = "network.bandwidth";
+
+#[tauri::command]
+async fn network_get_bandwidth_limit() -> AppResult<streaming::bandwidth::BandwidthSettings> {
+    Ok(streaming::bandwidth::manager().settings())
+}
+
+/// 设置全局带宽上限和计费网络下的上限（KB/s，0表示不限速）
+#[tauri::command]
+async fn network_set_bandwidth_limit(settings: streaming::bandwidth::BandwidthSettings) -> AppResult<()> {
+    settings.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&settings)?;
+    {
+        let db = DB.get().ok_or("Database not initialized")?;
+        let db = db.lock()?;
+        db.set_setting(SETTING_BANDWIDTH, &json)?;
+    }
+    streaming::bandwidth::manager().set_settings(settings);
     Ok(())
 }
 
+/// 各流量类别（播放、预加载、同步）最近的吞吐量
+#[tauri::command]
+async fn network_get_bandwidth_usage() -> AppResult<streaming::bandwidth::BandwidthUsage> {
+    Ok(streaming::bandwidth::manager().usage())
+}
+
+/// app_settings中保存流式缓冲设置的键（JSON）
+const SETTING_STREAM_BUFFER: &str = "network.stream_buffer";
+
+#[tauri::command]
+async fn network_get_stream_buffer() -> AppResult<streaming::buffering::StreamBufferSettings> {
+    Ok(streaming::buffering::settings())
+}
+
+/// 设置流式播放的初始缓冲、重新缓冲的低/高水位（KB）和断线重连次数，从下一首流式曲目起生效
 #[tauri::command]
-async fn cache_auto_cleanup() -> Result<u32, String> {
-    // TODO: 调用CacheManager自动清理
-    log::info!("执行自动清理");
-    Ok(0)
+async fn network_set_stream_buffer(settings: streaming::buffering::StreamBufferSettings) -> AppResult<()> {
+    settings.validate().map_err(AppError::InvalidInput)?;
+    let json = serde_json::to_string(&settings)?;
+    {
+        let db = DB.get().ok_or("Database not initialized")?;
+        let db = db.lock()?;
+        db.set_setting(SETTING_STREAM_BUFFER, &json)?;
+    }
+    streaming::buffering::set_settings(settings);
+    Ok(())
 }
 
 #[tauri::command]
 async fn remote_test_connection(
     server_type: String,
     config_json: String,
-) -> Result<String, String> {
+) -> AppResult<String> {
     log::info!("测试{}连接", server_type);
     
     use remote_source::{ConnectionStatus, RemoteSourceClient};
@@ -1719,18 +3723,30 @@ async fn remote_test_connection(
     match server_type.as_str() {
         "webdav" => {
             let config: WebDAVConfig = serde_json::from_str(&config_json)
-                .map_err(|e| format!("配置解析失败: {}", e))?;
+                .map_err(|e| AppError::invalid_input(format!("配置解析失败: {}", e)))?;
             let client = WebDAVClient::new(config)
-                .map_err(|e| format!("创建客户端失败: {}", e))?;
-            let adapter = webdav::WebDAVRemoteAdapter::new(client);
+                .map_err(|e| AppError::from(e).context("创建客户端失败"))?;
+            
+            // 直接使用 WebDAV 客户端，证书不受信任等错误保留原类别，前端据此询问是否信任证书
+            match client.test_connection().await {
+                Ok(protocol) => Ok(format!("✅ WebDAV连接成功！（{}）", protocol)),
+                Err(e) => Err(AppError::from(e).context("❌ 连接失败")),
+            }
+        },
+        "subsonic" => {
+            let config: subsonic::types::SubsonicConfig = serde_json::from_str(&config_json)
+                .map_err(|e| AppError::invalid_input(format!("配置解析失败: {}", e)))?;
+            let client = subsonic::SubsonicClient::new(config)
+                .map_err(|e| AppError::invalid_input(format!("创建客户端失败: {}", e)))?;
+            let adapter = subsonic::SubsonicRemoteAdapter::new(client);
             
             match RemoteSourceClient::test_connection(&adapter).await {
-                Ok(ConnectionStatus::Connected) => Ok("✅ WebDAV连接成功！".to_string()),
-                Ok(ConnectionStatus::Error(e)) => Err(format!("❌ 连接失败: {}", e)),
-                _ => Err("❌ 连接失败：未知错误".to_string()),
+                Ok(ConnectionStatus::Connected) => Ok("✅ Subsonic连接成功！".to_string()),
+                Ok(ConnectionStatus::Error(e)) => Err(AppError::Network { status: None, message: format!("❌ 连接失败: {}", e) }),
+                _ => Err(AppError::Network { status: None, message: "❌ 连接失败：未知错误".to_string() }),
             }
         },
-        _ => Err(format!("不支持的服务器类型: {}，仅支持WebDAV", server_type)),
+        _ => Err(AppError::invalid_input(format!("不支持的服务器类型: {}，仅支持WebDAV和Subsonic", server_type))),
     }
 }
 
@@ -1738,14 +3754,14 @@ async fn remote_test_connection(
 #[tauri::command]
 async fn remote_check_all_connections(
     state: State<'_, AppState>,
-) -> Result<Vec<serde_json::Value>, String> {
+) -> AppResult<Vec<serde_json::Value>> {
     log::info!("检查所有远程服务器连接状态");
     
     use remote_source::{RemoteClientManager, ConnectionStatus};
     
     let servers = {
-        let db = state.inner().db.lock().map_err(|e| e.to_string())?;
-        db.get_remote_servers().map_err(|e| e.to_string())?
+        let db = state.inner().db.lock()?;
+        db.get_remote_servers()?
     };
     
     let db_arc = state.inner().db.clone();
@@ -1783,27 +3799,56 @@ async fn remote_check_all_connections(
     Ok(results)
 }
 
+/// 浏览远程目录，优先使用目录列表缓存；force_refresh 为 true 时直接请求服务器
+///
+/// 缓存过期时先返回旧列表，后台重新请求后内容有变化则发送 remote-directory-changed 事件
 #[tauri::command]
 async fn remote_browse_directory(
+    app: AppHandle,
     state: State<'_, AppState>,
     server_id: String,
     path: String,
-) -> Result<Vec<serde_json::Value>, String> {
+    force_refresh: Option<bool>,
+) -> AppResult<Vec<serde_json::Value>> {
     log::info!("浏览远程目录: {} - {}", server_id, path);
     
     use remote_source::RemoteClientManager;
+    use remote_source::listing_cache::{self, LISTING_CACHE};
     
     // 创建客户端管理器
     let db_arc = state.inner().db.clone();
     let manager = RemoteClientManager::new(db_arc);
     
-    let client = manager.get_client(&server_id).await
-        .map_err(|e| e.to_string())?;
-    
-    let files = client.list_directory(&path).await
-        .map_err(|e| e.to_string())?;
+    let client = manager.get_client(&server_id).await?;
+    
+    let listing = listing_cache::list(&LISTING_CACHE, client.as_ref(), &server_id, &path, force_refresh.unwrap_or(false)).await?;
+    
+    // 返回的是过期列表时在后台重新请求，有变化再通知前端
+    if listing.needs_revalidate {
+        let server_id = server_id.clone();
+        let path = path.clone();
+        tokio::spawn(async move {
+            match listing_cache::revalidate(&LISTING_CACHE, client.as_ref(), &server_id, &path).await {
+                Ok(Some(files)) => {
+                    log::info!("远程目录内容已变化: {} - {}", server_id, path);
+                    let _ = app.emit("remote-directory-changed", serde_json::json!({
+                        "server_id": server_id,
+                        "path": path,
+                        "files": remote_files_json(&files),
+                    }));
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("后台刷新远程目录失败: {} - {}: {}", server_id, path, e),
+            }
+        });
+    }
     
-    let result: Vec<serde_json::Value> = files.into_iter()
+    Ok(remote_files_json(&listing.files))
+}
+
+/// 目录列表返回给前端的字段
+fn remote_files_json(files: &[remote_source::RemoteFileInfo]) -> Vec<serde_json::Value> {
+    files.iter()
         .map(|f| serde_json::json!({
             "path": f.path,
             "name": f.name,
@@ -1812,17 +3857,20 @@ async fn remote_browse_directory(
             "mime_type": f.mime_type,
             "last_modified": f.last_modified,
         }))
-        .collect();
-    
-    Ok(result)
+        .collect()
 }
 
+/// 扫描远程音乐库，只处理新增或变化的文件
+///
+/// concurrency 为同时进行的请求数（默认4），进度通过 remote-scan-progress 事件发送
 #[tauri::command]
 async fn remote_scan_library(
+    app: AppHandle,
     state: State<'_, AppState>,
     server_id: String,
     root_path: String,
-) -> Result<serde_json::Value, String> {
+    concurrency: Option<usize>,
+) -> AppResult<serde_json::Value> {
     log::info!("开始扫描远程音乐库: {} - {}", server_id, root_path);
     
     use remote_source::{RemoteClientManager, RemoteScanner};
@@ -1831,15 +3879,22 @@ async fn remote_scan_library(
     let db_arc = state.inner().db.clone();
     let manager = RemoteClientManager::new(db_arc.clone());
     
-    let client = manager.get_client(&server_id).await
-        .map_err(|e| e.to_string())?;
+    let client = manager.get_client(&server_id).await?;
     
     // 创建扫描器
-    let scanner = RemoteScanner::new(client, db_arc, server_id);
+    let server_id_for_cache = server_id.clone();
+    let scanner = RemoteScanner::new(client, db_arc, server_id)
+        .with_concurrency(concurrency.unwrap_or(remote_source::scanner::DEFAULT_SCAN_CONCURRENCY))
+        .with_progress(move |progress| {
+            let _ = app.emit("remote-scan-progress", progress);
+        });
     
     // 执行扫描
-    let result = scanner.scan(&root_path).await
-        .map_err(|e| e.to_string())?;
+    let result = scanner.scan(&root_path).await;
+    
+    // 扫描过程中已重新列出子树，之后浏览时不再使用扫描前的列表
+    remote_source::listing_cache::LISTING_CACHE.invalidate_subtree(Some(&server_id_for_cache), &root_path);
+    let result = result?;
     
     // 🔧 扫描完成后，自动刷新音乐库数据
     log::info!("✅ 扫描完成，触发音乐库刷新...");
@@ -1855,25 +3910,256 @@ async fn remote_scan_library(
         "total_files": result.total_files,
         "added": result.added,
         "updated": result.updated,
+        "unchanged": result.unchanged,
         "failed": result.failed,
         "errors": result.errors,
         "duration_seconds": result.duration_seconds,
+        "cancelled": result.cancelled,
+    }))
+}
+
+/// 取消指定服务器正在进行的扫描，没有扫描时返回false
+#[tauri::command]
+async fn remote_cancel_scan(server_id: String) -> AppResult<bool> {
+    Ok(remote_source::scanner::cancel_scan(&server_id))
+}
+
+// ========== 同步上传命令 ==========
+
+/// 本地曲目在远程目录下的目标路径
+fn upload_target_path(remote_dir: &str, local_path: &str) -> AppResult<String> {
+    let file_name = std::path::Path::new(local_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| AppError::invalid_input(format!("无效的文件路径: {}", local_path)))?;
+    Ok(format!("{}/{}", remote_dir.trim_end_matches('/'), file_name))
+}
+
+/// 把一首本地曲目加入上传队列，返回任务ID
+fn enqueue_track_upload(db: &db::Database, track: &Track, server_id: &str, remote_dir: &str) -> AppResult<i64> {
+    if remote_source::is_remote_track_path(&track.path) {
+        return Err(AppError::invalid_input(format!("不是本地曲目: {}", track.path)));
+    }
+    let file_size = std::fs::metadata(&track.path)
+        .map_err(|e| AppError::from(e).context(&format!("读取文件失败 ({})", track.path)))?
+        .len() as i64;
+    let target_path = upload_target_path(remote_dir, &track.path)?;
+    db.enqueue_sync_task(sync::TASK_UPLOAD, Some(track.id), &track.path, Some(&target_path), server_id, Some(file_size))
+        .map_err(AppError::from)
+}
+
+/// 上传单首本地曲目到WebDAV服务器
+#[tauri::command]
+async fn sync_enqueue_track_upload(
+    track_id: i64,
+    server_id: String,
+    remote_dir: String,
+    state: State<'_, AppState>,
+) -> AppResult<i64> {
+    let task_id = {
+        let db = state.inner().db.lock()?;
+        let track = db.get_track_by_id(track_id)?
+            .ok_or_else(|| AppError::not_found(format!("曲目不存在: {}", track_id)))?;
+        enqueue_track_upload(&db, &track, &server_id, &remote_dir)?
+    };
+    log::info!("⬆️ 曲目 {} 已加入上传队列 (任务 {})", track_id, task_id);
+    sync::wake();
+    Ok(task_id)
+}
+
+/// 上传歌单中的全部本地曲目，远程曲目会被跳过
+#[tauri::command]
+async fn sync_enqueue_playlist_upload(
+    playlist_id: i64,
+    server_id: String,
+    remote_dir: String,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<i64>> {
+    let task_ids = {
+        let db = state.inner().db.lock()?;
+        let tracks = db.get_playlist_tracks(playlist_id)?;
+        let mut task_ids = Vec::new();
+        for track in tracks.iter().filter(|t| !remote_source::is_remote_track_path(&t.path)) {
+            match enqueue_track_upload(&db, track, &server_id, &remote_dir) {
+                Ok(id) => task_ids.push(id),
+                Err(e) => log::warn!("⚠️ 跳过曲目 {}: {}", track.id, e),
+            }
+        }
+        task_ids
+    };
+    log::info!("⬆️ 歌单 {} 的 {} 首曲目已加入上传队列", playlist_id, task_ids.len());
+    sync::wake();
+    Ok(task_ids)
+}
+
+#[tauri::command]
+async fn sync_get_queue(limit: Option<i64>, state: State<'_, AppState>) -> AppResult<Vec<db::SyncTask>> {
+    state.inner().db.lock()?
+        .get_sync_queue(limit.unwrap_or(100))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn sync_list_conflicts(include_resolved: Option<bool>, state: State<'_, AppState>) -> AppResult<Vec<db::SyncConflict>> {
+    state.inner().db.lock()?
+        .get_sync_conflicts(include_resolved.unwrap_or(false))
+        .map_err(AppError::from)
+}
+
+/// 按策略解决单个冲突（prefer_local / prefer_remote / prefer_newer）
+#[tauri::command]
+async fn sync_resolve_conflict(
+    conflict_id: i64,
+    strategy: sync::conflict::ResolutionStrategy,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    sync::worker::resolve_conflict(&state.inner().db, conflict_id, strategy).await
+        .map_err(AppError::from)
+}
+
+/// 用同一策略解决全部未解决的冲突
+#[tauri::command]
+async fn sync_resolve_all_conflicts(
+    strategy: sync::conflict::ResolutionStrategy,
+    state: State<'_, AppState>,
+) -> AppResult<serde_json::Value> {
+    let db = state.inner().db.clone();
+    let conflicts = db.lock()?
+        .get_sync_conflicts(false)?;
+
+    let mut resolved = 0;
+    let mut errors = Vec::new();
+    for conflict in conflicts {
+        match sync::worker::resolve_conflict(&db, conflict.id, strategy).await {
+            Ok(()) => resolved += 1,
+            Err(e) => errors.push(format!("#{}: {}", conflict.id, e)),
+        }
+    }
+    log::info!("🤝 批量解决冲突: 成功 {}, 失败 {}", resolved, errors.len());
+
+    Ok(serde_json::json!({
+        "resolved": resolved,
+        "failed": errors.len(),
+        "errors": errors,
     }))
 }
 
+// ========== 派对模式命令 ==========
+
+use party_mode::{PartyMode, PartyModeStatus};
+
+static PARTY_MODE: Lazy<Mutex<PartyMode>> = Lazy::new(|| Mutex::new(PartyMode::new()));
+
+/// 队列添加限速检查
+fn party_mode_record_queue_additions(count: usize) -> AppResult<()> {
+    let mut party = PARTY_MODE
+        .lock()
+        .map_err(|e| format!("锁定派对模式状态失败: {}", e))?;
+    party.record_queue_additions(count).map_err(AppError::from)
+}
+
+/// 从设置表恢复派对模式（重启后保持锁定）
+fn restore_party_mode(db: &Database) -> Result<()> {
+    let active = db.get_setting(party_mode::SETTING_ACTIVE)?.as_deref() == Some("1");
+    let pin_hash = db.get_setting(party_mode::SETTING_PIN_HASH)?;
+    let rate_limit = db
+        .get_setting(party_mode::SETTING_QUEUE_RATE_LIMIT)?
+        .and_then(|v| v.parse::<u32>().ok());
+
+    let restored = PartyMode::restore(active, pin_hash, rate_limit);
+    if restored.is_active() {
+        log::info!("🎉 派对模式已从设置恢复");
+    }
+    if let Ok(mut party) = PARTY_MODE.lock() {
+        *party = restored;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn party_mode_enable(pin: String, app: AppHandle, state: State<'_, AppState>) -> AppResult<PartyModeStatus> {
+    let status = {
+        let mut party = PARTY_MODE.lock()?;
+        let pin_hash = party.enable(&pin)?;
+        
+        let db = state.inner().db.lock()?;
+        db.set_setting(party_mode::SETTING_PIN_HASH, &pin_hash)?;
+        db.set_setting(party_mode::SETTING_ACTIVE, "1")?;
+        party.status()
+    };
+    
+    log::info!("🎉 派对模式已开启");
+    let _ = app.emit("party-mode-changed", &status);
+    Ok(status)
+}
+
+#[tauri::command]
+async fn party_mode_disable(pin: String, app: AppHandle, state: State<'_, AppState>) -> AppResult<PartyModeStatus> {
+    let status = {
+        let mut party = PARTY_MODE.lock()?;
+        party.disable(&pin)?;
+        
+        let db = state.inner().db.lock()?;
+        db.set_setting(party_mode::SETTING_ACTIVE, "0")?;
+        party.status()
+    };
+    
+    log::info!("🎉 派对模式已关闭");
+    let _ = app.emit("party-mode-changed", &status);
+    Ok(status)
+}
+
+#[tauri::command]
+async fn party_mode_get_status() -> AppResult<PartyModeStatus> {
+    let party = PARTY_MODE.lock()?;
+    Ok(party.status())
+}
+
+/// 设置派对模式下的队列限速（每分钟最多添加的曲目数，None 为不限制）
+#[tauri::command]
+async fn party_mode_set_queue_rate_limit(max_per_minute: Option<u32>, app: AppHandle, state: State<'_, AppState>) -> AppResult<PartyModeStatus> {
+    let status = {
+        let mut party = PARTY_MODE.lock()?;
+        party.set_queue_rate_limit(max_per_minute);
+        
+        let db = state.inner().db.lock()?;
+        match party.status().queue_rate_limit {
+            Some(limit) => db.set_setting(party_mode::SETTING_QUEUE_RATE_LIMIT, &limit.to_string()),
+            None => db.delete_setting(party_mode::SETTING_QUEUE_RATE_LIMIT),
+        }?;
+        party.status()
+    };
+    
+    let _ = app.emit("party-mode-changed", &status);
+    Ok(status)
+}
+
+/// 包装命令处理器：派对模式下拒绝未登记为访客安全的命令
+fn with_party_mode_guard<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command().to_string();
+        party_mode::dispatch(&PARTY_MODE, &command, invoke, &handler, |invoke, e| {
+            log::warn!("🎉 {}", e);
+            invoke.resolver.reject(AppError::from(e));
+        })
+    }
+}
+
 // 测试命令：直接检查库统计数据
 #[tauri::command]
-async fn test_library_stats(state: State<'_, AppState>) -> Result<String, String> {
+async fn test_library_stats(state: State<'_, AppState>) -> AppResult<String> {
     log::info!("测试库统计数据");
     
-    let db = state.inner().db.lock().map_err(|e| e.to_string())?;
+    let db = state.inner().db.lock()?;
     
-    let total_tracks = db.get_track_count().map_err(|e| e.to_string())?;
-    let total_artists = db.get_artist_count().map_err(|e| e.to_string())?;
-    let total_albums = db.get_album_count().map_err(|e| e.to_string())?;
+    let total_tracks = db.get_track_count()?;
+    let total_artists = db.get_artist_count()?;
+    let total_albums = db.get_album_count()?;
     
     // 获取实际的曲目数据来验证
-    let tracks = db.get_all_tracks().map_err(|e| e.to_string())?;
+    let tracks = db.get_all_tracks()?;
     
     let mut result = format!("库统计数据测试:\n");
     result.push_str(&format!("- 总曲目数: {}\n", total_tracks));
@@ -1901,7 +4187,7 @@ async fn test_library_stats(state: State<'_, AppState>) -> Result<String, String
 // 测试命令：直接检查音频文件封面
 #[allow(dead_code)]
 #[tauri::command]
-async fn test_audio_cover(file_path: String) -> Result<String, String> {
+async fn test_audio_cover(file_path: String) -> AppResult<String> {
     use lofty::{probe::Probe, prelude::*};
     
     log::info!("测试音频文件封面: {}", file_path);
@@ -1938,35 +4224,37 @@ async fn test_audio_cover(file_path: String) -> Result<String, String> {
                     
                     Ok(result)
                 }
-                Err(e) => Err(format!("读取文件失败: {}", e))
+                Err(e) => Err(AppError::Io(format!("读取文件失败: {}", e)))
             }
         }
-        Err(e) => Err(format!("打开文件失败: {}", e))
+        Err(e) => Err(AppError::Io(format!("打开文件失败: {}", e)))
     }
 }
 
 // Initialize the application - 异步初始化避免阻塞UI
 fn init_app(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger
-    env_logger::init();
-    println!("🚀 [INIT] WindChime Player 启动中...");
+    // Initialize logger：数据库打开前先用默认设置，之后再应用保存的设置
+    let log_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join("logs"))
+        .unwrap_or_else(|_| std::env::temp_dir().join("windchime-logs"));
+    logging::init(&logging::LoggingConfig::default(), log_dir);
     log::info!("🚀 WindChime Player 启动中...");
 
     let app_handle_clone = app_handle.clone();
     
     // 🔥 关键优化：在后台线程异步初始化，避免阻塞主线程和UI
     tauri::async_runtime::spawn(async move {
-        println!("📦 [INIT] 进入异步初始化函数...");
+        log::debug!("📦 [INIT] 进入异步初始化函数...");
         match init_app_async(&app_handle_clone).await {
             Ok(_) => {
-                println!("✅ [INIT] WindChime Player 初始化完成");
                 log::info!("✅ WindChime Player 初始化完成");
                 // 通知前端初始化完成
                 let _ = app_handle_clone.emit("app-ready", ());
-                println!("📤 [INIT] 已发送 app-ready 事件");
+                log::debug!("📤 [INIT] 已发送 app-ready 事件");
             }
             Err(e) => {
-                println!("❌ [INIT] WindChime Player 初始化失败: {}", e);
                 log::error!("❌ WindChime Player 初始化失败: {}", e);
                 // 通知前端初始化失败
                 let _ = app_handle_clone.emit("app-init-error", e.to_string());
@@ -1974,18 +4262,16 @@ fn init_app(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    println!("✅ [INIT] UI 线程已就绪，后台初始化进行中...");
     log::info!("✅ UI 线程已就绪，后台初始化进行中...");
     Ok(())
 }
 
 // 异步初始化函数 - 在后台执行耗时操作
 async fn init_app_async(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    println!("📦 [INIT] 开始后台初始化...");
     log::info!("📦 开始后台初始化...");
     
     // Get app data directory
-    println!("📁 [INIT] 获取应用数据目录...");
+    log::debug!("📁 [INIT] 获取应用数据目录...");
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
@@ -1993,44 +4279,47 @@ async fn init_app_async(app_handle: &AppHandle) -> Result<(), Box<dyn std::error
 
     // Create app data directory if it doesn't exist
     std::fs::create_dir_all(&app_data_dir)?;
-    println!("✅ [INIT] 应用数据目录已创建");
+    log::debug!("✅ [INIT] 应用数据目录已创建");
 
     // Initialize database
-    println!("💾 [INIT] 初始化数据库...");
     log::info!("💾 初始化数据库...");
     let db_path = app_data_dir.join("windchime.db");
-    let db = Arc::new(Mutex::new(Database::new(db_path)?));
-    println!("✅ [INIT] 数据库初始化完成");
+    let db = Arc::new(DbPool::open(db_path)?);
     log::info!("✅ 数据库初始化完成");
+    logging::apply(&logging::load_config(&*db.read()?));
+
+    // 加载应用设置，恢复音质增强设置和窗口位置
+    let config = Arc::new(ConfigManager::load(Arc::clone(&db), Some(app_data_dir.join(app_config::CONFIG_FILE_NAME)))?);
+    let app_config = config.get().await;
+    audio_enhancement::publish_settings(app_config.audio.enhancement.clone());
+    if let Some(geometry) = app_config.ui.window.filter(|_| app_config.ui.remember_window) {
+        restore_window(app_handle, geometry);
+    }
 
     // Initialize library
-    println!("📚 [INIT] 初始化音乐库...");
     log::info!("📚 初始化音乐库...");
     let (library, library_tx, library_rx) = Library::new(Arc::clone(&db))?;
     library.run();
-    println!("✅ [INIT] 音乐库初始化完成");
     log::info!("✅ 音乐库初始化完成");
 
     // Initialize player（使用新的PlayerCore）
-    println!("🎵 [INIT] 初始化播放器（使用PlayerCore架构）...");
     log::info!("🎵 [INIT] 初始化播放器（使用PlayerCore架构）...");
     let player_adapter = PlayerAdapter::new().await
         .map_err(|e| {
-            println!("❌ [INIT] 播放器初始化失败: {}", e);
             log::error!("❌ [INIT] 播放器初始化失败: {}", e);
             format!("播放器初始化失败: {}", e)
         })?;
     
-    println!("🎵 [INIT] 获取播放器通道...");
+    log::debug!("🎵 [INIT] 获取播放器通道...");
     let player_tx = player_adapter.command_sender();
-    let player_rx = player_adapter.event_receiver();
+    let player_rx = player_adapter
+        .take_event_receiver()
+        .ok_or_else(|| anyhow::anyhow!("播放器事件接收端已被占用"))?;
     
-    println!("✅ [INIT] 播放器初始化完成（懒加载，无阻塞）");
     log::info!("✅ 播放器初始化完成（懒加载，无阻塞）");
     
     // 🔧 添加：等待一小段时间确保异步任务启动
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    println!("✅ [INIT] 播放器异步任务已启动");
     log::info!("✅ 播放器异步任务已启动");
 
     // Store senders in global state
@@ -2038,95 +4327,465 @@ async fn init_app_async(app_handle: &AppHandle) -> Result<(), Box<dyn std::error
     LIBRARY_TX.set(library_tx.clone()).map_err(|_| "Failed to set library sender")?;
     DB.set(Arc::clone(&db)).map_err(|_| "Failed to set database")?;
 
+    // 应用音量曲线和启动音量
+    let _ = player_tx.send(PlayerCommand::SetVolumeSettings(app_config.audio.volume));
+    let _ = player_tx.send(PlayerCommand::SetVolume(app_config.audio.default_volume));
+
+    // 恢复派对模式状态
+    if let Err(e) = db.with(restore_party_mode) {
+        log::warn!("⚠️ 恢复派对模式失败: {}", e);
+    }
+
+    // 恢复交叉淡入淡出设置
+    let crossfade_ms = db.with(|db| db.get_setting(SETTING_CROSSFADE_MS))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    if crossfade_ms > 0 {
+        if let Some(tx) = PLAYER_TX.get() {
+            let _ = tx.send(PlayerCommand::SetCrossfade(crossfade_ms.min(MAX_CROSSFADE_MS)));
+        }
+    }
+
+    // 恢复关闭到托盘设置
+    let close_to_tray = db.with(|db| db.get_setting(tray::SETTING_CLOSE_TO_TRAY))
+        .ok()
+        .flatten();
+    tray::set_close_to_tray(close_to_tray.as_deref() == Some("true"));
+
+    // 恢复睡眠定时淡出时长
+    let sleep_fade_secs = db.with(|db| db.get_setting(SETTING_SLEEP_FADE_SECS))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok());
+    if let (Some(fade_secs), Some(tx)) = (sleep_fade_secs, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetSleepFade(fade_secs));
+    }
+
+    // 恢复计入播放历史的门槛
+    let scrobble_threshold = db.with(|db| db.get_setting(SETTING_SCROBBLE_THRESHOLD))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<ScrobbleThreshold>(&json).ok());
+    if let (Some(threshold), Ok(mut tracker)) = (scrobble_threshold, PLAY_TRACKER.lock()) {
+        tracker.set_threshold(threshold);
+    }
+
+    // 恢复续播设置
+    let resume_settings = db.with(|db| db.get_setting(SETTING_RESUME))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<ResumeSettings>(&json).ok());
+    if let (Some(settings), Ok(mut tracker)) = (resume_settings, RESUME_TRACKER.lock()) {
+        tracker.set_settings(settings);
+    }
+
+    // 恢复输出设备（首次播放时打开）
+    let output_device = db.with(|db| db.get_setting(SETTING_OUTPUT_DEVICE))
+        .ok()
+        .flatten();
+    if let (Some(device_name), Some(tx)) = (output_device, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetOutputDevice(Some(device_name)));
+    }
+    let output_config = db.with(|db| db.get_setting(SETTING_OUTPUT_CONFIG))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<player::OutputConfig>(&json).ok());
+    if let (Some(config), Some(tx)) = (output_config, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetOutputConfig(config));
+    }
+
+    // 恢复暂停淡变时长、变速音高保持和相对跳转步长
+    let pause_fade_ms = db.with(|db| db.get_setting(SETTING_PAUSE_FADE_MS))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok());
+    if let (Some(duration_ms), Some(tx)) = (pause_fade_ms, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetPauseFade(duration_ms));
+    }
+    let preserve_pitch = db.with(|db| db.get_setting(SETTING_PRESERVE_PITCH))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<bool>().ok());
+    if let (Some(enabled), Some(tx)) = (preserve_pitch, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetPreservePitch(enabled));
+    }
+    let shuffle_skip_broken = db.with(|db| db.get_setting(SETTING_SHUFFLE_SKIP_BROKEN))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<bool>().ok());
+    if let (Some(enabled), Some(tx)) = (shuffle_skip_broken, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetShuffleSkipBroken(enabled));
+    }
+    let seek_step_ms = db.with(|db| db.get_setting(SETTING_SEEK_STEP_MS))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok());
+    if let (Some(step_ms), Some(tx)) = (seek_step_ms, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetSeekStep(step_ms));
+    }
+    let position_interval_ms = db.with(|db| db.get_setting(SETTING_POSITION_INTERVAL_MS))
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok());
+    if let (Some(interval_ms), Some(tx)) = (position_interval_ms, PLAYER_TX.get()) {
+        let _ = tx.send(PlayerCommand::SetPositionInterval(interval_ms));
+    }
+
+    // 恢复带宽限制并开始检测计费网络
+    let bandwidth = db.with(|db| db.get_setting(SETTING_BANDWIDTH))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<streaming::bandwidth::BandwidthSettings>(&json).ok());
+    if let Some(settings) = bandwidth {
+        streaming::bandwidth::manager().set_settings(settings);
+    }
+    streaming::bandwidth::spawn_metered_monitor();
+    
+    // 恢复流式缓冲设置
+    let stream_buffer = db.with(|db| db.get_setting(SETTING_STREAM_BUFFER))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<streaming::buffering::StreamBufferSettings>(&json).ok())
+        .filter(|settings| settings.validate().is_ok());
+    if let Some(settings) = stream_buffer {
+        streaming::buffering::set_settings(settings);
+    }
+
+    // 初始化远程曲目缓存
+    let cache_config = db.lock().map(|db| load_cache_config(&db)).unwrap_or_default();
+    match cache::manager::CacheManager::new(cache_config, Arc::clone(&db)) {
+        Ok(manager) => {
+            let _ = CACHE_MANAGER.set(Arc::new(manager));
+        }
+        Err(e) => log::warn!("⚠️ 初始化缓存管理器失败: {}", e),
+    }
+
     // 流式播放服务已移除，新架构中直接在播放时创建Reader
-    println!("📺 [INIT] 流式播放服务已简化为按需创建");
     log::info!("📺 流式播放服务已简化为按需创建");
-    println!("✅ [INIT] 流式播放服务初始化完成");
     log::info!("✅ 流式播放服务初始化完成");
 
     // Store state in Tauri
     let state = AppState {
-        player_rx: Arc::new(Mutex::new(player_rx)),
-        library_rx: Arc::new(Mutex::new(library_rx)),
         db,
+        config,
         player_adapter: Arc::new(player_adapter),
     };
     app_handle.manage(state);
 
     // Start event listeners
-    start_event_listeners(app_handle.clone());
+    start_event_listeners(app_handle.clone(), player_rx, library_rx);
+
+    // 启动健康检查（后台执行，不阻塞启动）
+    spawn_startup_health_check(app_handle.clone());
+
+    // 启动上传同步工作器
+    spawn_sync_worker(app_handle.clone());
+
+    // 启动播放记录提交工作器（未启用时不会发起任何请求）
+    spawn_scrobbler(app_handle);
+
+    // 监听设置文件的外部修改
+    spawn_config_watcher(app_handle);
+
+    // 注册系统媒体会话（媒体键、系统播放控件）
+    start_media_session(app_handle, player_tx.clone());
+
+    // 按设置开启HTTP远程控制
+    if app_config.remote_control.enabled {
+        let db = app_handle.state::<AppState>().db.clone();
+        if let Err(e) = remote_control::start(&app_config.remote_control, player_tx, db) {
+            log::warn!("⚠️ 启动远程控制失败: {}", e);
+        }
+    }
 
     log::info!("🎉 WindChime Player 完全就绪");
     Ok(())
 }
 
-fn start_event_listeners(app_handle: AppHandle) {
-    let app_handle_clone = app_handle.clone();
-
-    // Player event listener
+/// 应用就绪几秒后在后台运行启动健康检查，完成后发送 startup-health 事件
+fn spawn_startup_health_check(app_handle: AppHandle) {
     tauri::async_runtime::spawn(async move {
-        let state: State<AppState> = app_handle_clone.state();
-        let rx = state.inner().player_rx.clone();
-
-        loop {
-            // 检查关闭信号
-            if SHUTDOWN_SIGNAL.load(Ordering::Relaxed) {
-                log::info!("播放器事件监听器收到关闭信号，正在退出...");
-                break;
+        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        if SHUTDOWN_SIGNAL.load(Ordering::Relaxed) {
+            return;
+        }
+        
+        let db = {
+            let state: State<AppState> = app_handle.state();
+            state.inner().db.clone()
+        };
+        
+        log::info!("🩺 开始启动健康检查...");
+        match health_check::run_startup_health_check(db, &SHUTDOWN_SIGNAL).await {
+            Some(summary) => {
+                log::info!(
+                    "🩺 启动健康检查完成: 缺失曲目≈{}, 离线服务器{}个, 过期智能歌单{}个, 结构版本{}",
+                    summary.missing_tracks_estimate,
+                    summary.offline_servers.len(),
+                    summary.stale_smart_playlists.len(),
+                    if summary.schema_ok { "正常" } else { "不匹配" }
+                );
+                let _ = app_handle.emit("startup-health", &summary);
             }
+            None => log::info!("🩺 启动健康检查已取消"),
+        }
+    });
+}
 
-            let event_received = {
-                if let Ok(guard) = rx.try_lock() {
-                    guard.try_recv().ok()
-                } else {
-                    None
-                }
-            };
+/// 后台执行同步队列中的上传任务，进度通过 sync-progress 事件发送
+fn spawn_sync_worker(app_handle: AppHandle) {
+    let db = {
+        let state: State<AppState> = app_handle.state();
+        state.inner().db.clone()
+    };
+    tauri::async_runtime::spawn(sync::worker::run(db, &SHUTDOWN_SIGNAL, move |progress| {
+        let _ = app_handle.emit("sync-progress", &progress);
+    }));
+}
 
-            if let Some(event) = event_received {
-                match &event {
-                    PlayerEvent::StateChanged(state) => {
-                        let _ = app_handle_clone.emit("player-state-changed", state);
-                    }
-                    PlayerEvent::TrackChanged(track) => {
-                        if let Some(ref t) = track {
-                            log::debug!("🎵 TrackChanged事件: title={:?}, duration_ms={:?}", 
-                                t.title, t.duration_ms);
-                            println!("🎵 [EVENT] TrackChanged: title={:?}, duration_ms={:?}ms", 
-                                t.title, t.duration_ms);
-                        } else {
-                            println!("🎵 [EVENT] TrackChanged: None");
-                        }
-                        let _ = app_handle_clone.emit("player-track-changed", track);
+/// 恢复上次关闭时的窗口大小和位置，位置不在任何显示器上时（如外接显示器已拔掉）只恢复大小
+fn restore_window(app_handle: &AppHandle, geometry: WindowGeometry) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    let _ = window.set_size(tauri::PhysicalSize::new(geometry.width, geometry.height));
+    let on_screen = window.available_monitors().unwrap_or_default().iter().any(|monitor| {
+        let position = monitor.position();
+        let size = monitor.size();
+        (position.x..position.x + size.width as i32).contains(&geometry.x)
+            && (position.y..position.y + size.height as i32).contains(&geometry.y)
+    });
+    if on_screen {
+        let _ = window.set_position(tauri::PhysicalPosition::new(geometry.x, geometry.y));
+    }
+    if geometry.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// 记录窗口大小和位置；最小化时不记录，最大化时保留最大化之前的大小和位置
+fn save_window_geometry(window: &tauri::Window) {
+    let Some(state) = window.app_handle().try_state::<AppState>() else {
+        return;
+    };
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized().unwrap_or(false),
+    };
+    if let Err(e) = state.config.save_window(geometry) {
+        log::warn!("⚠️ 保存窗口位置失败: {}", e);
+    }
+}
+
+/// 监听设置文件，外部修改后即时应用变化的设置项，并通知前端（config-changed / config-error）
+///
+/// 启动音量只在下次启动时生效，窗口位置同样不做调整。
+fn spawn_config_watcher(app_handle: &AppHandle) {
+    let config = {
+        let state: State<AppState> = app_handle.state();
+        state.inner().config.clone()
+    };
+    let app_handle = app_handle.clone();
+    app_config::watch(config, &SHUTDOWN_SIGNAL, move |result| match result {
+        Ok((config, keys)) => {
+            log::info!("⚙️ 设置文件已修改: {}", keys.join(", "));
+            if keys.iter().any(|key| key.starts_with("audio.enhancement")) {
+                audio_enhancement::publish_settings(config.audio.enhancement.clone());
+            }
+            if keys.iter().any(|key| key.starts_with("audio.volume")) {
+                apply_volume_settings(config.audio.volume);
+            }
+            if keys.iter().any(|key| key.starts_with("network")) {
+                network_api::clear_cache();
+            }
+            let _ = app_handle.emit("config-changed", serde_json::json!({
+                "keys": keys,
+                "config": config,
+            }));
+        }
+        Err(e) => {
+            log::warn!("⚠️ 设置文件未生效: {}", e);
+            let _ = app_handle.emit("config-error", &e);
+        }
+    });
+}
+
+/// 按已保存的设置启动播放记录提交工作器
+fn spawn_scrobbler(app_handle: &AppHandle) {
+    let db = {
+        let state: State<AppState> = app_handle.state();
+        state.inner().db.clone()
+    };
+    let config = db.lock().ok().and_then(|db| load_scrobbler_config(&db));
+    scrobbler::start(db, config, &SHUTDOWN_SIGNAL);
+}
+
+/// 注册系统媒体会话，封面临时文件放在应用缓存目录
+fn start_media_session(app_handle: &AppHandle, player_tx: Sender<PlayerCommand>) {
+    let cover_dir = app_handle.path().app_cache_dir()
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join("media_session");
+
+    #[cfg(target_os = "windows")]
+    let window_handle = app_handle.get_webview_window("main")
+        .and_then(|window| window.hwnd().ok())
+        .map(|hwnd| hwnd.0 as usize);
+    #[cfg(not(target_os = "windows"))]
+    let window_handle = None;
+
+    media_session::start(player_tx, media_session::BackendConfig { window_handle }, cover_dir);
+}
+
+/// 等待下一个事件；收到关闭信号或所有发送端都已释放时返回 None
+async fn next_event<T>(rx: &mut EventReceiver<T>) -> Option<T> {
+    // 先注册唤醒再检查关闭信号，避免两者之间发出的通知被漏掉
+    let shutdown = EVENT_LISTENER_SHUTDOWN.notified();
+    tokio::pin!(shutdown);
+    shutdown.as_mut().enable();
+    if SHUTDOWN_SIGNAL.load(Ordering::SeqCst) {
+        return None;
+    }
+    tokio::select! {
+        event = rx.recv() => event,
+        _ = shutdown => None,
+    }
+}
+
+fn start_event_listeners(
+    app_handle: AppHandle,
+    mut player_rx: EventReceiver<PlayerEvent>,
+    mut library_rx: EventReceiver<LibraryEvent>,
+) {
+    let app_handle_clone = app_handle.clone();
+
+    // Player event listener
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = next_event(&mut player_rx).await {
+            match &event {
+                PlayerEvent::StateChanged(state) => {
+                    if !state.is_playing {
+                        apply_resume_action(RESUME_TRACKER.lock().ok().and_then(|mut t| t.checkpoint()));
                     }
-                    PlayerEvent::PositionChanged(position) => {
-                        let _ = app_handle_clone.emit("player-position-changed", position);
+                    media_session::update_state(state.is_playing, state.current_track.is_some(), state.volume);
+                    remote_control::update_state(state.is_playing, state.volume);
+                    tray::update_state(state.is_playing);
+                    let _ = app_handle_clone.emit("player-state-changed", state);
+                }
+                PlayerEvent::TrackChanged(track) => {
+                    if let Some(ref t) = track {
+                        log::debug!("🎵 TrackChanged事件: title={:?}, duration_ms={:?}", 
+                            t.title, t.duration_ms);
+                        log::debug!("🎵 [EVENT] TrackChanged: title={:?}, duration_ms={:?}ms", 
+                            t.title, t.duration_ms);
+                    } else {
+                        log::debug!("🎵 [EVENT] TrackChanged: None");
                     }
-                    PlayerEvent::PlaybackError(error) => {
-                        let _ = app_handle_clone.emit("player-error", error);
+                    if let Ok(mut current) = CURRENT_TRACK_ID.lock() {
+                        *current = track.as_ref().map(|t| t.id);
                     }
-                    PlayerEvent::TrackCompleted(track) => {
-                        let _ = app_handle_clone.emit("track-completed", track);
+                    media_session::update_track(track.as_ref());
+                    remote_control::update_track(track.as_ref());
+                    tray::update_track(track.as_ref());
+                    if let Some(track) = track.as_ref() {
+                        scrobbler::now_playing(track);
+                        load_track_chapters(track.id);
+                        load_track_trim_points(track.id);
+                        load_track_rate(track.id);
                     }
-                    PlayerEvent::PlaylistCompleted => {
-                        let _ = app_handle_clone.emit("playlist-completed", &());
+                    let finished = PLAY_TRACKER.lock().ok().and_then(|mut t| t.track_changed(track.as_ref()));
+                    record_completed_play(&app_handle_clone, finished);
+                    if let Some((previous, resume_track)) = RESUME_TRACKER.lock().ok().map(|mut t| t.track_changed(track.as_ref())) {
+                        apply_resume_action(previous);
+                        if let Some(track_id) = resume_track {
+                            resume_saved_position(track_id);
+                        }
                     }
-                    PlayerEvent::SeekCompleted { position, elapsed_ms } => {
-                        log::debug!("⚡ Seek完成: position={}ms, elapsed={}ms", position, elapsed_ms);
-                        let _ = app_handle_clone.emit("seek-completed", serde_json::json!({"position": position, "elapsed": elapsed_ms}));
+                    let _ = app_handle_clone.emit("player-track-changed", track);
+                }
+                PlayerEvent::PositionChanged(update) => {
+                    let position = update.position_ms;
+                    media_session::update_position(position);
+                    remote_control::update_position(position);
+                    if let Ok(mut tracker) = PLAY_TRACKER.lock() {
+                        tracker.position(position);
                     }
-                    PlayerEvent::AudioDeviceReady => {
-                        log::info!("🎵 音频设备就绪");
-                        let _ = app_handle_clone.emit("audio-device-ready", ());
+                    if let Ok(mut tracker) = RESUME_TRACKER.lock() {
+                        tracker.position(position);
                     }
-                    PlayerEvent::AudioDeviceFailed { error, recoverable } => {
-                        log::error!("❌ 音频设备失败: {} (可恢复: {})", error, recoverable);
-                        let _ = app_handle_clone.emit("audio-device-failed", serde_json::json!({"error": error, "recoverable": recoverable}));
+                    let _ = app_handle_clone.emit("player-position-changed", update);
+                }
+                PlayerEvent::PlaybackError(error) => {
+                    let _ = app_handle_clone.emit("player-error", error);
+                }
+                PlayerEvent::TrackCompleted(track) => {
+                    let finished = PLAY_TRACKER.lock().ok().and_then(|mut t| t.track_completed());
+                    record_completed_play(&app_handle_clone, finished);
+                    apply_resume_action(RESUME_TRACKER.lock().ok().and_then(|mut t| t.track_completed()));
+                    let _ = app_handle_clone.emit("track-completed", track);
+                }
+                PlayerEvent::PlaylistCompleted => {
+                    let _ = app_handle_clone.emit("playlist-completed", &());
+                }
+                PlayerEvent::SeekCompleted { position, elapsed_ms } => {
+                    log::debug!("⚡ Seek完成: position={}ms, elapsed={}ms", position, elapsed_ms);
+                    if let Ok(mut tracker) = PLAY_TRACKER.lock() {
+                        tracker.seeked(*position);
                     }
+                    let _ = app_handle_clone.emit("seek-completed", serde_json::json!({"position": position, "elapsed": elapsed_ms}));
+                }
+                PlayerEvent::PositionResumed { track_id, position_ms } => {
+                    let _ = app_handle_clone.emit("player-position-resumed", serde_json::json!({"trackId": track_id, "positionMs": position_ms}));
+                }
+                PlayerEvent::EngineChanged { engine, track_id, position_ms } => {
+                    let _ = app_handle_clone.emit("player-engine-changed", serde_json::json!({"engine": engine, "trackId": track_id, "positionMs": position_ms}));
+                }
+                PlayerEvent::VisualizationData(data) => {
+                    let _ = app_handle_clone.emit("player-visualization-data", data);
+                }
+                PlayerEvent::ChapterChanged { track_id, chapter } => {
+                    let _ = app_handle_clone.emit("player-chapter-changed", serde_json::json!({"trackId": track_id, "chapter": chapter}));
+                }
+                PlayerEvent::LoopRegionChanged { track_id, region } => {
+                    let _ = app_handle_clone.emit("player-loop-region-changed", serde_json::json!({"trackId": track_id, "region": region}));
+                }
+                PlayerEvent::DurationCorrected { track_id, duration_ms } => {
+                    save_corrected_duration(&app_handle_clone, *track_id, *duration_ms);
+                }
+                PlayerEvent::Buffering { percent } => {
+                    let _ = app_handle_clone.emit("player-buffering", serde_json::json!({"percent": percent}));
+                }
+                PlayerEvent::CrossfadeStateChanged { active, duration_ms } => {
+                    let _ = app_handle_clone.emit("crossfade-state-changed", serde_json::json!({"active": active, "durationMs": duration_ms}));
+                }
+                PlayerEvent::SleepTimerTick(status) => {
+                    let _ = app_handle_clone.emit("sleep-timer-tick", status);
+                }
+                PlayerEvent::SleepTimerFired => {
+                    log::info!("😴 睡眠定时器已触发");
+                    let _ = app_handle_clone.emit("sleep-timer-fired", ());
+                }
+                PlayerEvent::SleepTimerCancelled => {
+                    let _ = app_handle_clone.emit("sleep-timer-cancelled", ());
+                }
+                PlayerEvent::AudioDeviceReady { device_name, format } => {
+                    log::info!("🎵 音频设备就绪: {}", device_name);
+                    let _ = app_handle_clone.emit("audio-device-ready", serde_json::json!({"deviceName": device_name, "format": format}));
+                }
+                PlayerEvent::AudioDeviceFailed { error, recoverable } => {
+                    log::error!("❌ 音频设备失败: {} (可恢复: {})", error, recoverable);
+                    let _ = app_handle_clone.emit("audio-device-failed", serde_json::json!({"error": error, "recoverable": recoverable}));
                 }
-            } else {
-                // No events available, sleep briefly
-                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
             }
         }
         log::info!("播放器事件监听器已退出");
@@ -2134,62 +4793,72 @@ fn start_event_listeners(app_handle: AppHandle) {
 
     // Library event listener
     tauri::async_runtime::spawn(async move {
-        let state: State<AppState> = app_handle.state();
-        let rx = state.inner().library_rx.clone();
-
-        loop {
-            // 检查关闭信号
-            if SHUTDOWN_SIGNAL.load(Ordering::Relaxed) {
-                log::info!("音乐库事件监听器收到关闭信号，正在退出...");
-                break;
-            }
-
-            let event_received = {
-                if let Ok(guard) = rx.try_lock() {
-                    guard.try_recv().ok()
-                } else {
-                    None
+        while let Some(event) = next_event(&mut library_rx).await {
+            match &event {
+                LibraryEvent::ScanStarted { .. } => {
+                    let _ = app_handle.emit("library-scan-started", &event);
                 }
-            };
-
-            if let Some(event) = event_received {
-                match &event {
-                    LibraryEvent::ScanStarted { .. } => {
-                        let _ = app_handle.emit("library-scan-started", &event);
-                    }
-                    LibraryEvent::ScanProgress(_) => {
-                        let _ = app_handle.emit("library-scan-progress", &event);
-                    }
-                    LibraryEvent::ScanComplete { .. } => {
-                        let _ = app_handle.emit("library-scan-complete", &event);
-                    }
-                    LibraryEvent::TracksLoaded(tracks) => {
-                        log::info!("🔔 后端收到TracksLoaded事件，曲目数: {}", tracks.len());
-                        let emit_result = app_handle.emit("library-tracks-loaded", tracks);
-                        if emit_result.is_ok() {
-                            log::info!("✅ 已向前端发送library-tracks-loaded事件");
-                        } else {
-                            log::error!("❌ 向前端发送library-tracks-loaded事件失败: {:?}", emit_result);
-                        }
-                    }
-                    LibraryEvent::SearchResults(tracks) => {
-                        let _ = app_handle.emit("library-search-results", tracks);
-                    }
-                    LibraryEvent::LibraryStats { total_tracks, total_artists, total_albums } => {
-                        let stats_data = serde_json::json!({
-                            "total_tracks": total_tracks,
-                            "total_artists": total_artists,
-                            "total_albums": total_albums
-                        });
-                        let _ = app_handle.emit("library-stats", stats_data);
+                LibraryEvent::ScanProgress(_) => {
+                    let _ = app_handle.emit("library-scan-progress", &event);
+                }
+                LibraryEvent::ScanComplete { .. } => {
+                    let _ = app_handle.emit("library-scan-complete", &event);
+                }
+                LibraryEvent::TracksLoadBegin(info) => {
+                    log::info!("🔔 开始向前端分批发送曲目，曲目数: {}", info.total);
+                    let _ = app_handle.emit("library-tracks-loaded-begin", info);
+                }
+                LibraryEvent::TracksChunk(chunk) => {
+                    if let Err(e) = app_handle.emit("library-tracks-loaded-chunk", chunk) {
+                        log::error!("❌ 向前端发送曲目批次失败 (offset={}): {:?}", chunk.offset, e);
                     }
-                    LibraryEvent::Error(_) => {
-                        let _ = app_handle.emit("library-error", &event);
+                }
+                LibraryEvent::TracksLoadEnd(info) => {
+                    let emit_result = app_handle.emit("library-tracks-loaded-end", info);
+                    if emit_result.is_ok() {
+                        log::info!("✅ 已向前端发送全部 {} 首曲目", info.total);
+                    } else {
+                        log::error!("❌ 向前端发送library-tracks-loaded-end事件失败: {:?}", emit_result);
                     }
                 }
-            } else {
-                // No events available, sleep briefly
-                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                LibraryEvent::TracksChanged { .. } => {
+                    let _ = app_handle.emit("library-tracks-changed", &event);
+                }
+                LibraryEvent::SearchResults(results) => {
+                    let _ = app_handle.emit("library-search-results", results);
+                }
+                LibraryEvent::LibraryStats { total_tracks, total_artists, total_albums } => {
+                    let stats_data = serde_json::json!({
+                        "total_tracks": total_tracks,
+                        "total_artists": total_artists,
+                        "total_albums": total_albums
+                    });
+                    let _ = app_handle.emit("library-stats", stats_data);
+                }
+                LibraryEvent::AnalysisProgress { .. } => {
+                    let _ = app_handle.emit("library-analysis-progress", &event);
+                }
+                LibraryEvent::FingerprintProgress { .. } => {
+                    let _ = app_handle.emit("library-fingerprint-progress", &event);
+                }
+                LibraryEvent::VerifyProgress { .. } => {
+                    let _ = app_handle.emit("library-verify-progress", &event);
+                }
+                LibraryEvent::VerifyComplete(summary) => {
+                    let _ = app_handle.emit("library-verify-complete", summary);
+                }
+                LibraryEvent::DurationBackfillProgress { .. } => {
+                    let _ = app_handle.emit("library-duration-backfill-progress", &event);
+                }
+                LibraryEvent::ArtistSplitProgress { .. } => {
+                    let _ = app_handle.emit("library-artist-split-progress", &event);
+                }
+                LibraryEvent::TrackDurationUpdated { track_id, duration_ms } => {
+                    let _ = app_handle.emit("track-updated", serde_json::json!({"trackId": track_id, "durationMs": duration_ms}));
+                }
+                LibraryEvent::Error(_) => {
+                    let _ = app_handle.emit("library-error", &event);
+                }
             }
         }
         log::info!("音乐库事件监听器已退出");
@@ -2202,9 +4871,11 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(with_party_mode_guard(tauri::generate_handler![
             // Audio file reading (for Web Audio API)
-            read_audio_file,
+            read_audio_file_open,
+            read_audio_file_chunk,
+            read_audio_file_close,
             get_track,
             get_current_position,
             // Player commands
@@ -2215,10 +4886,49 @@ pub fn run() {
             player_next,
             player_previous,
             player_seek,
+            player_sync_external_position,
+            player_handoff_to_native,
+            player_seek_relative,
+            player_set_loop_region,
+            player_clear_loop_region,
+            player_next_chapter,
+            player_previous_chapter,
+            player_set_visualization_enabled,
             player_set_volume,
             player_set_repeat,
             player_set_shuffle,
+            player_set_shuffle_skip_broken,
+            player_get_shuffle_skip_broken,
+            player_set_gapless,
+            player_set_crossfade,
+            player_get_crossfade,
+            ui_set_close_to_tray,
+            ui_get_close_to_tray,
+            player_set_sleep_timer,
+            player_get_sleep_timer,
+            player_set_sleep_fade,
+            player_get_sleep_fade,
+            player_set_pause_fade,
+            player_get_pause_fade,
+            player_set_rate,
+            player_get_rate,
+            player_set_preserve_pitch,
+            player_get_preserve_pitch,
+            player_set_seek_step,
+            player_get_seek_step,
+            player_set_position_interval,
+            player_get_position_interval,
+            player_get_resume_settings,
+            player_set_resume_settings,
+            track_get_saved_position,
+            track_clear_saved_position,
+            track_get_chapters,
+            track_get_trim_points,
+            player_get_silence_settings,
+            player_set_silence_settings,
+            player_get_replay_gain,
             player_load_playlist,
+            player_play_track,
             // Playlist generation commands
             generate_sequential_playlist,
             generate_random_playlist,
@@ -2226,11 +4936,50 @@ pub fn run() {
             // Library commands
             library_scan,
             library_get_tracks,
+            library_set_tracks_chunk_size,
+            library_get_tracks_chunk_size,
             library_search,
+            library_search_suggest,
+            search_history_get,
+            search_history_clear,
             library_get_stats,
+            library_get_statistics,
+            library_export_backup,
+            library_import_backup,
             library_rescan_covers,
+            library_analyze_loudness,
+            library_analyze_silence,
+            library_cancel_loudness_analysis,
+            library_set_fingerprinting,
+            library_get_fingerprinting,
+            library_cancel_fingerprinting,
+            library_backfill_durations,
+            library_cancel_duration_backfill,
+            library_find_duplicates,
+            library_set_watcher,
+            library_get_watcher,
+            library_get_scan_issues,
+            library_verify,
+            library_cancel_verify,
+            library_get_verify_issues,
+            library_set_verify_on_scan,
+            library_get_verify_on_scan,
+            library_rebuild_track_artists,
+            library_get_track_artists,
+            library_set_path_pattern,
+            library_get_path_pattern,
+            library_set_transliterate_search,
+            library_get_transliterate_search,
             library_get_music_folders,
+            library_browse_folder,
+            library_get_tracks_page,
+            library_get_albums,
+            library_get_artists,
+            library_get_album_tracks,
             library_delete_folder,
+            library_delete_tracks,
+            library_relocate,
+            library_find_missing,
             // Lyrics commands
             lyrics_get,
             lyrics_parse,
@@ -2248,12 +4997,43 @@ pub fn run() {
             lyrics_auto_detect,
             lyrics_format_as_lrc,
             lyrics_get_current_line,
+            lyrics_get_current_word,
+            track_write_lyrics_tag,
+            track_update_metadata,
+            tracks_update_metadata_bulk,
+            track_set_rating,
+            track_get_rating,
+            remote_control_enable,
+            remote_control_disable,
+            remote_control_pairing,
+            cast_list_devices,
+            cast_start,
+            cast_stop,
+            cast_set_volume,
+            cast_get_active_device,
+            metadata_lookup,
+            metadata_lookup_album,
+            metadata_apply,
+            track_write_cover_tag,
+            playlist_write_tags,
+            album_write_tags,
             // Network API commands (LrcApi)
+            lyrics_fetch_missing,
+            lyrics_fetch_cancel,
+            lyrics_fetch_is_running,
             network_fetch_lyrics,
             network_fetch_cover,
+            network_sources_get,
+            network_source_save,
+            network_source_remove,
+            network_source_test,
             artist_cover_save,
             artist_cover_get,
             artist_covers_get_all,
+            artist_covers_get_page,
+            artists_fetch_missing_covers,
+            artists_fetch_covers_cancel,
+            artists_fetch_covers_is_running,
             // Favorites commands
             favorites_add,
             favorites_remove,
@@ -2263,11 +5043,23 @@ pub fn run() {
             favorites_get_count,
             // 企业级歌单命令
             playlists_list,
+            playlist_folders_create,
+            playlist_folders_rename,
+            playlist_folders_delete,
+            playlist_folders_move,
+            playlists_move_to_folder,
+            playlists_set_order,
             playlists_create,
             playlists_get_detail,
+            playlists_get_builtin,
             playlists_update,
+            playlists_play,
             playlists_delete,
             playlists_add_tracks,
+            playlists_add_album,
+            playlists_add_artist,
+            playlists_remove_tracks,
+            playlists_deduplicate,
             playlists_remove_track,
             playlists_reorder_tracks,
             playlists_get_tracks,
@@ -2277,6 +5069,10 @@ pub fn run() {
             playlists_refresh_all_smart,
             playlists_export,
             playlists_export_preview,
+            playlists_export_with_audio,
+            playlists_export_with_audio_cancel,
+            playlists_export_with_audio_is_running,
+            playlists_import_preview,
             playlists_import,
             playlists_get_stats,
             playlists_mark_played,
@@ -2288,6 +5084,15 @@ pub fn run() {
             // 播放历史命令
             get_play_history,
             get_play_statistics,
+            history_get_scrobble_threshold,
+            history_set_scrobble_threshold,
+            scrobbler_configure,
+            scrobbler_get_status,
+            scrobbler_test_connection,
+            stats_top_artists,
+            stats_top_albums,
+            stats_plays_per_day,
+            stats_listening_clock,
             add_play_history,
             clear_play_history,
             remove_from_history,
@@ -2298,14 +5103,34 @@ pub fn run() {
             // Audio device commands
             check_audio_devices,
             debug_audio_system,
+            diagnostics_export,
+            debug_preload_stats,
+            audio_list_output_devices,
+            audio_set_output_device,
+            audio_get_output_device,
+            audio_set_output_config,
+            audio_get_output_config,
+            audio_get_output_format,
+            player_get_stream_stats,
             // Album cover commands
+            get_cover,
+            get_covers_for_tracks,
             get_album_cover,
+            get_album_cover_palette,
             refresh_track_cover,
+            refresh_track_metadata,
             // Audio enhancement commands
+            config_get,
+            config_update,
             get_audio_enhancement_settings,
             set_audio_enhancement_settings,
             get_equalizer_presets,
             apply_equalizer_preset,
+            equalizer_import_autoeq,
+            equalizer_export_autoeq,
+            equalizer_save_preset,
+            equalizer_delete_preset,
+            equalizer_rename_preset,
             // Audio diagnostic commands
             diagnose_audio_system,
             fix_audio_system,
@@ -2323,23 +5148,56 @@ pub fn run() {
             // 远程音乐源命令 (仅支持WebDAV)
             remote_add_server,
             remote_get_servers,
+            remote_get_server_credentials,
             remote_delete_server,
             remote_update_server,
+            remote_trust_certificate,
             remote_get_cache_stats,
+            // 数据库维护命令
+            db_vacuum,
+            library_rebuild_search_index,
+            db_integrity_check,
+            db_backup,
+            logging_get_config,
+            logging_update_config,
+            logging_set_level,
+            logging_get_recent,
             remote_test_connection,
             remote_check_all_connections,
             remote_browse_directory,
             remote_scan_library,
+            remote_cancel_scan,
+            // 同步上传命令
+            sync_enqueue_track_upload,
+            sync_enqueue_playlist_upload,
+            sync_get_queue,
+            sync_list_conflicts,
+            sync_resolve_conflict,
+            sync_resolve_all_conflicts,
             // 音频缓存命令
             cache_get_config,
             cache_update_config,
             cache_get_stats,
             cache_clear_all,
             cache_auto_cleanup,
+            cache_pin_track,
+            get_cache_strategy,
+            set_cache_strategy,
+            // 网络带宽命令
+            network_get_bandwidth_limit,
+            network_set_bandwidth_limit,
+            network_get_bandwidth_usage,
+            network_get_stream_buffer,
+            network_set_stream_buffer,
+            // 派对模式命令
+            party_mode_enable,
+            party_mode_disable,
+            party_mode_get_status,
+            party_mode_set_queue_rate_limit,
             // Streaming commands已移除（新架构中后端内部处理）
             // Test commands
             test_library_stats,
-        ])
+        ]))
         .setup(|app| {
             if let Err(e) = init_app(app.handle()) {
                 log::error!("Failed to initialize app: {}", e);
@@ -2348,10 +5206,29 @@ pub fn run() {
                     format!("Initialization failed: {}", e),
                 )));
             }
+            // 托盘创建失败不影响使用，关闭窗口时直接退出
+            if let Err(e) = tray::create(app.handle()) {
+                log::warn!("⚠️ 创建系统托盘失败: {}", e);
+            }
             Ok(())
         })
-        .on_window_event(|_window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Ok(mut files) = AUDIO_FILES.lock() {
+                    let released = files.close_window(window.label());
+                    if released > 0 {
+                        log::debug!("窗口 {} 已销毁，释放 {} 个音频文件句柄", window.label(), released);
+                    }
+                }
+            }
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                save_window_geometry(window);
+                if tray::should_hide_on_close() {
+                    api.prevent_close();
+                    let _ = window.hide();
+                    log::info!("窗口已隐藏到托盘，继续播放");
+                    return;
+                }
                 log::info!("程序正在关闭，开始清理资源...");
                 cleanup_resources();
                 log::info!("资源清理完成");
@@ -2361,12 +5238,25 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
+/// 真正退出（托盘菜单“退出”）：清理资源后结束进程
+fn quit_app(app_handle: &AppHandle) {
+    log::info!("程序正在退出，开始清理资源...");
+    tray::mark_quitting();
+    if let Some(window) = app_handle.get_webview_window("main") {
+        save_window_geometry(&window.as_ref().window());
+    }
+    cleanup_resources();
+    log::info!("资源清理完成");
+    app_handle.exit(0);
+}
+
 // 资源清理函数
 fn cleanup_resources() {
     log::info!("开始清理应用资源...");
     
     // 设置关闭信号，通知所有监听器退出
-    SHUTDOWN_SIGNAL.store(true, Ordering::Relaxed);
+    SHUTDOWN_SIGNAL.store(true, Ordering::SeqCst);
+    EVENT_LISTENER_SHUTDOWN.notify_waiters();
     log::info!("已发送关闭信号给事件监听器");
     
     // 停止播放器
@@ -2377,6 +5267,21 @@ fn cleanup_resources() {
         std::thread::sleep(std::time::Duration::from_millis(200));
     }
     
+    // 退出前记录正在播放的曲目
+    if let Some(play) = PLAY_TRACKER.lock().ok().and_then(|mut t| t.finish()) {
+        save_completed_play(play);
+    }
+    apply_resume_action(RESUME_TRACKER.lock().ok().and_then(|mut t| t.checkpoint()));
+    
+    // 从系统注销媒体会话
+    media_session::shutdown();
+
+    // 关闭远程控制服务
+    remote_control::stop();
+    
+    // 结束投屏（让设备停止播放）
+    tauri::async_runtime::block_on(cast::stop(false));
+    
     // 给事件监听器一些时间来优雅退出
     std::thread::sleep(std::time::Duration::from_millis(100));
     
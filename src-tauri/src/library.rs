@@ -1,24 +1,365 @@
-use crate::db::Database;
+use crate::db::{Database, LibrarySearchResult, LocalFileState, MissingDuration, ReplayGainInfo};
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
 // 使用新的PlayerCore的Track类型
 use crate::player::Track;
-use crate::metadata_extractor::MetadataExtractor;
+use crate::metadata_extractor::{MetadataExtractor, MusicMetadata, PathPattern};
+use crate::player::audio::fingerprint::{self, compute_fingerprint};
+use crate::player::audio::loudness::LoudnessMeter;
+use crate::player::audio::silence::{SilenceDetector, SilenceSettings, TrimPoints};
+use crate::player::audio::verify::{self, VerifyStatus};
+use crate::player::audio::{duration, AudioDecoder};
+use crate::library_watcher::LibraryWatcher;
+use crate::event_channel::{self, Coalesce, EventReceiver, EventSender};
 use anyhow::Result;
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use lofty::prelude::*;
 use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::cell::Cell;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// 静音处理设置（app_settings，JSON）
+pub const SETTING_SILENCE: &str = "playback.silence";
+
+/// 音频分析每首曲目之间的间隔，降低后台任务对播放和界面的影响
+const LOUDNESS_ANALYSIS_PAUSE: Duration = Duration::from_millis(50);
+
+/// 时长补算每首曲目之间的间隔
+const DURATION_BACKFILL_PAUSE: Duration = Duration::from_millis(20);
+
+/// 是否自动监听音乐文件夹（app_settings）
+pub const SETTING_WATCHER_ENABLED: &str = "library.watch_folders";
+
+/// 是否在扫描后计算声学指纹（app_settings，默认关闭）
+pub const SETTING_FINGERPRINT_ENABLED: &str = "library.fingerprint";
+
+/// 指纹计算每首之后至少停顿的时间
+const FINGERPRINT_MIN_PAUSE: Duration = Duration::from_millis(200);
+
+/// 是否在扫描后完整解码新曲目校验完整性（app_settings，默认关闭）
+pub const SETTING_VERIFY_ON_SCAN: &str = "library.verify_on_scan";
+
+/// 完整性校验每首之后至少停顿的时间
+const VERIFY_MIN_PAUSE: Duration = Duration::from_millis(200);
+
+/// 并行提取元数据的工作线程上限
+const MAX_SCAN_WORKERS: usize = 8;
+
+/// 重新拆分艺术家时每批处理的曲目数（每批一个事务，批之间释放写连接）
+const ARTIST_SPLIT_BATCH: usize = 500;
+
+/// 跳过未变化文件时，每跳过这么多个发送一次进度，避免大曲库刷屏
+const SKIP_PROGRESS_INTERVAL: usize = 100;
+
+/// 元数据读取失败时从路径推断字段的模板（app_settings，默认见 DEFAULT_PATH_PATTERN）
+pub const SETTING_PATH_PATTERN: &str = "library.path_pattern";
+
+/// 全量曲目分批发送给前端时每批的曲目数（app_settings）
+pub const SETTING_TRACKS_CHUNK_SIZE: &str = "library.tracks_chunk_size";
+pub const DEFAULT_TRACKS_CHUNK_SIZE: usize = 500;
+pub const MIN_TRACKS_CHUNK_SIZE: usize = 50;
+pub const MAX_TRACKS_CHUNK_SIZE: usize = 5000;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ScanProgress {
     pub current_file: String,
+    /// 已处理的文件数（含跳过的）
     pub processed: usize,
     pub total: usize,
+    /// 未变化而跳过的文件数
+    pub skipped: usize,
+    /// 重新提取了元数据的文件数
+    pub extracted: usize,
     pub errors: Vec<String>,
 }
 
+/// 全量曲目加载的开始/结束通知
+#[derive(Debug, Clone, Serialize)]
+pub struct TracksLoadInfo {
+    /// 每次加载递增，前端据此丢弃被新加载打断的旧批次
+    pub load_id: u64,
+    pub total: usize,
+}
+
+/// 一批曲目，offset 为该批第一首在全量列表中的位置
+#[derive(Debug, Clone, Serialize)]
+pub struct TracksChunk {
+    pub load_id: u64,
+    pub offset: usize,
+    pub tracks: Vec<Track>,
+}
+
+/// 一次完整性校验的结果统计，failed 为无法打开的文件数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifySummary {
+    pub ok: usize,
+    pub truncated: usize,
+    pub decode_error: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+}
+
+impl VerifySummary {
+    fn add(&mut self, status: VerifyStatus) {
+        match status {
+            VerifyStatus::Ok => self.ok += 1,
+            VerifyStatus::Truncated => self.truncated += 1,
+            VerifyStatus::DecodeError => self.decode_error += 1,
+        }
+    }
+}
+
+/// 扫描时对单个文件的处理方式
+#[derive(Debug, PartialEq, Eq)]
+enum ScanAction {
+    /// 大小和修改时间都与数据库记录一致
+    Skip,
+    /// 只有修改时间变了，需要比较哈希确认内容是否变化
+    VerifyHash,
+    /// 新文件或内容已变化
+    Extract,
+}
+
+/// 根据数据库中记录的文件状态决定如何处理
+fn classify_file(stored: Option<&LocalFileState>, mtime: Option<i64>, size: i64) -> ScanAction {
+    let Some(stored) = stored else {
+        return ScanAction::Extract;
+    };
+    if stored.size != Some(size) {
+        return ScanAction::Extract;
+    }
+    if stored.mtime.is_some() && stored.mtime == mtime {
+        return ScanAction::Skip;
+    }
+    if stored.hash.is_some() {
+        ScanAction::VerifyHash
+    } else {
+        ScanAction::Extract
+    }
+}
+
+pub(crate) fn file_mtime(metadata: &std::fs::Metadata) -> Option<i64> {
+    metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+pub(crate) fn file_md5(path: &Path) -> Result<String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        context.consume(&buf[..n]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// 是否为支持的音频文件（按扩展名判断）
+pub(crate) fn is_audio_file(path: &Path) -> bool {
+    if let Some(extension) = path.extension() {
+        let ext = extension.to_string_lossy().to_lowercase();
+        // 支持的音频格式 - 与播放器保持一致
+        matches!(
+            ext.as_str(),
+            // 常见无损格式
+            "flac" | "wav" | "aiff" | "aif" | "aifc" |
+            // 常见有损格式  
+            "mp3" | "aac" | "m4a" | "ogg" | "oga" | "opus" |
+            // 其他格式
+            "wma" | "ape" | "tak" | "tta" | "dsd" | "dsf" | "dff" |
+            // 模块音乐格式
+            "mod" | "it" | "s3m" | "xm" |
+            // 其他无损格式
+            "alac" | "wv" | "mka"
+        )
+    } else {
+        false
+    }
+}
+
+/// 读取静音处理设置，未设置时使用默认值
+pub(crate) fn load_silence_settings(db: &Database) -> SilenceSettings {
+    db.get_setting(SETTING_SILENCE)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// 读取路径模板，未设置或无效时使用默认模板
+pub(crate) fn load_path_pattern(db: &Database) -> PathPattern {
+    db.get_setting(SETTING_PATH_PATTERN)
+        .ok()
+        .flatten()
+        .and_then(|pattern| PathPattern::parse(&pattern).ok())
+        .unwrap_or_default()
+}
+
+/// 把提取到的元数据写入曲库（扫描和单曲重新提取共用）
+pub(crate) fn save_track_metadata(db: &DbPool, path_str: &str, existing_id: Option<i64>, metadata: MusicMetadata) -> Result<()> {
+    let track_id = existing_id.unwrap_or(0);
+
+    // 保存内嵌歌词到数据库（如果有）
+    if let Some(lyrics_content) = &metadata.embedded_lyrics {
+        if track_id > 0 {
+            if let Err(e) = db.with(|db| db.insert_lyrics(track_id, lyrics_content, "lrc", "embedded")) {
+                log::warn!("保存内嵌歌词失败: {}", e);
+            }
+        }
+    }
+
+    let rating = metadata.star_rating();
+    let track = Track {
+        id: track_id,
+        path: path_str.to_string(),
+        title: metadata.title,
+        artist: metadata.artist,
+        album: metadata.album,
+        duration_ms: metadata.duration_ms.map(|d| d as i64),
+        genre: metadata.genre,
+        year: metadata.year.map(i64::from),
+        track_number: metadata.track_number.map(i64::from),
+        disc_number: metadata.disc_number.map(i64::from),
+        album_artist: metadata.album_artist,
+        cover_id: None,
+        rating,
+        album_cover_data: metadata.album_cover_data,
+        album_cover_mime: metadata.album_cover_mime,
+        artist_photo_data: metadata.artist_photo_data,
+        artist_photo_mime: metadata.artist_photo_mime,
+        embedded_lyrics: metadata.embedded_lyrics,
+    };
+
+    let replay_gain = ReplayGainInfo {
+        track_gain_db: metadata.replaygain_track_gain,
+        album_gain_db: metadata.replaygain_album_gain,
+        loudness_lufs: None,
+    };
+
+    let audio_properties = metadata.audio_properties();
+
+    let db = db.lock()?;
+    db.insert_track(&track)?;
+    db.update_replay_gain(&track.path, &replay_gain)?;
+    db.update_audio_properties(&track.path, &audio_properties)?;
+
+    // 新曲目的ID在写入后才能确定；重新扫描时同样替换，CUE被删除后章节随之清空
+    let saved_id = match existing_id {
+        Some(id) => Some(id),
+        None => db.get_track_by_path(&track.path)?.map(|t| t.id),
+    };
+    if let Some(id) = saved_id {
+        db.replace_track_chapters(id, &metadata.chapters)?;
+        // 完整提取失败时记录原因，成功提取后清除
+        match &metadata.extraction_error {
+            Some(reason) => db.save_scan_issue(id, reason)?,
+            None => db.clear_scan_issue(id)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// 后台音频分析中的一首曲目
+struct AnalysisTask {
+    track_id: i64,
+    path: String,
+    needs_loudness: bool,
+    needs_trim: bool,
+}
+
+/// 合并缺少响度和缺少静音裁剪点的曲目，按ID排序
+fn pending_analysis(db: &Database, threshold_db: f32) -> Result<Vec<AnalysisTask>> {
+    let mut tasks: BTreeMap<i64, AnalysisTask> = BTreeMap::new();
+    for (track_id, path) in db.get_tracks_without_loudness()? {
+        tasks.insert(track_id, AnalysisTask { track_id, path, needs_loudness: true, needs_trim: false });
+    }
+    for (track_id, path) in db.get_tracks_without_trim_points(threshold_db)? {
+        tasks.entry(track_id)
+            .or_insert(AnalysisTask { track_id, path, needs_loudness: false, needs_trim: false })
+            .needs_trim = true;
+    }
+    Ok(tasks.into_values().collect())
+}
+
+/// 一次遍历音频源完成响度测量和静音检测，should_stop 返回 true 时中止并返回 None
+fn analyze_source<S>(
+    source: S,
+    measure_loudness: bool,
+    silence_threshold_db: Option<f32>,
+    should_stop: &dyn Fn() -> bool,
+) -> Option<(Option<f32>, Option<TrimPoints>)>
+where
+    S: rodio::Source<Item = i16>,
+{
+    let (channels, sample_rate) = (source.channels(), source.sample_rate());
+    let mut meter = measure_loudness.then(|| LoudnessMeter::new(channels, sample_rate));
+    let mut detector = silence_threshold_db.map(|threshold| SilenceDetector::new(channels, sample_rate, threshold));
+    for (i, sample) in source.enumerate() {
+        // 每64K个采样检查一次取消标志
+        if i & 0xFFFF == 0 && should_stop() {
+            return None;
+        }
+        if let Some(meter) = &mut meter {
+            meter.push_sample(sample);
+        }
+        if let Some(detector) = &mut detector {
+            detector.push_sample(sample);
+        }
+    }
+    Some((meter.map(|m| m.integrated_loudness()), detector.map(SilenceDetector::finish)))
+}
+
+/// 计算一首曲目的时长(ms)，无法确定或被取消时返回 None
+fn backfill_duration(track: &MissingDuration, should_stop: &dyn Fn() -> bool) -> Result<Option<u64>> {
+    if !crate::remote_source::is_remote_track_path(&track.path) {
+        return duration::probe_file(Path::new(&track.path), should_stop);
+    }
+    if let Some(cached) = crate::streaming::full_download::cached_file_path(&track.path) {
+        match duration::probe_file(&cached, should_stop) {
+            Ok(Some(duration_ms)) => return Ok(Some(duration_ms)),
+            Ok(None) if should_stop() => return Ok(None),
+            Ok(None) => {}
+            Err(e) => log::debug!("读取缓存文件时长失败 {:?}: {}", cached, e),
+        }
+    }
+    Ok(match (track.file_size, track.bitrate_kbps) {
+        (Some(size), Some(bitrate)) if size > 0 && bitrate > 0 => {
+            duration::estimate_from_bitrate(size as u64, bitrate as u32)
+        }
+        _ => None,
+    })
+}
+
+/// 需要提取元数据的文件
+struct ExtractJob {
+    path: PathBuf,
+    existing_id: Option<i64>,
+    mtime: Option<i64>,
+    size: i64,
+    hash: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ScanStats {
+    processed: usize,
+    added: usize,
+    updated: usize,
+    removed: usize,
+    skipped: usize,
+}
+
 #[derive(Debug)]
 pub enum LibraryCommand {
     Scan(Vec<String>),      // paths to scan
@@ -26,6 +367,17 @@ pub enum LibraryCommand {
     GetTracks,
     SearchTracks(String),   // search query
     GetStats,
+    AnalyzeLoudness,        // start background loudness analysis
+    AnalyzeSilence,         // 按当前阈值分析开头/结尾静音（与响度分析共用后台任务）
+    CancelLoudnessAnalysis, // 取消后台音频分析
+    EnableFingerprinting(bool), // 开启/关闭声学指纹计算（设置会保存）
+    CancelFingerprinting,
+    Verify(Option<Vec<i64>>), // 完整解码校验指定曲目，None 为全部本地曲目
+    CancelVerify,
+    BackfillDurations,      // 补算时长缺失的曲目
+    CancelDurationBackfill,
+    EnableWatcher(bool),    // 开启/关闭音乐文件夹监听（设置会保存）
+    RebuildTrackArtists,    // 按当前设置重新拆分所有曲目的艺术家
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -37,37 +389,120 @@ pub enum LibraryEvent {
     ScanComplete {
         tracks_added: usize,
         tracks_updated: usize,
+        tracks_removed: usize,
+        tracks_skipped: usize,
         errors: Vec<String>,
     },
-    TracksLoaded(Vec<Track>),
-    SearchResults(Vec<Track>),
+    /// 全量曲目按 Begin -> Chunk... -> End 的顺序分批发送，避免一次序列化整个曲库
+    TracksLoadBegin(TracksLoadInfo),
+    TracksChunk(TracksChunk),
+    TracksLoadEnd(TracksLoadInfo),
+    /// 文件夹监听触发的增量更新
+    TracksChanged {
+        added: usize,
+        updated: usize,
+        removed: usize,
+    },
+    SearchResults(LibrarySearchResult),
     LibraryStats {
         total_tracks: i64,
         total_artists: i64,
         total_albums: i64,
     },
-    Error(String),
+    /// 后台音频分析（响度和静音）进度
+    AnalysisProgress {
+        done: usize,
+        total: usize,
+    },
+    FingerprintProgress {
+        done: usize,
+        total: usize,
+    },
+    /// 完整性校验进度
+    VerifyProgress {
+        done: usize,
+        total: usize,
+    },
+    /// 完整性校验结束（完成或取消）
+    VerifyComplete(VerifySummary),
+    /// 时长补算进度，updated 为已补上时长的曲目数
+    DurationBackfillProgress {
+        done: usize,
+        total: usize,
+        updated: usize,
+    },
+    /// 重新拆分艺术家的进度
+    ArtistSplitProgress {
+        done: usize,
+        total: usize,
+    },
+    /// 曲目时长已补算
+    TrackDurationUpdated {
+        track_id: i64,
+        duration_ms: i64,
+    },
+    Error(AppError),
+}
+
+impl Coalesce for LibraryEvent {
+    /// 进度事件只需要最新一条
+    fn coalesce_key(&self) -> Option<&'static str> {
+        match self {
+            LibraryEvent::ScanProgress(_) => Some("scan-progress"),
+            LibraryEvent::AnalysisProgress { .. } => Some("analysis-progress"),
+            LibraryEvent::FingerprintProgress { .. } => Some("fingerprint-progress"),
+            LibraryEvent::VerifyProgress { .. } => Some("verify-progress"),
+            LibraryEvent::DurationBackfillProgress { .. } => Some("duration-backfill-progress"),
+            LibraryEvent::ArtistSplitProgress { .. } => Some("artist-split-progress"),
+            _ => None,
+        }
+    }
 }
 
 pub struct Library {
-    db: Arc<Mutex<Database>>,
+    db: Arc<DbPool>,
     command_rx: Receiver<LibraryCommand>,
-    event_tx: Sender<LibraryEvent>,
+    event_tx: EventSender<LibraryEvent>,
     is_scanning: Arc<Mutex<bool>>,
+    is_analyzing: Arc<AtomicBool>,
+    cancel_analysis: Arc<AtomicBool>,
+    is_fingerprinting: Arc<AtomicBool>,
+    cancel_fingerprinting: Arc<AtomicBool>,
+    is_verifying: Arc<AtomicBool>,
+    cancel_verify: Arc<AtomicBool>,
+    is_backfilling: Arc<AtomicBool>,
+    cancel_backfill: Arc<AtomicBool>,
     metadata_extractor: MetadataExtractor,
+    watcher: Mutex<Option<LibraryWatcher>>,
+    file_change_tx: Sender<Vec<PathBuf>>,
+    file_change_rx: Receiver<Vec<PathBuf>>,
+    last_load_id: AtomicU64,
 }
 
 impl Library {
-    pub fn new(db: Arc<Mutex<Database>>) -> Result<(Self, Sender<LibraryCommand>, Receiver<LibraryEvent>)> {
+    pub fn new(db: Arc<DbPool>) -> Result<(Self, Sender<LibraryCommand>, EventReceiver<LibraryEvent>)> {
         let (command_tx, command_rx) = unbounded();
-        let (event_tx, event_rx) = unbounded();
+        let (event_tx, event_rx) = event_channel::channel();
+        let (file_change_tx, file_change_rx) = unbounded();
 
         let library = Library {
             db,
             command_rx,
             event_tx,
             is_scanning: Arc::new(Mutex::new(false)),
+            is_analyzing: Arc::new(AtomicBool::new(false)),
+            cancel_analysis: Arc::new(AtomicBool::new(false)),
+            is_fingerprinting: Arc::new(AtomicBool::new(false)),
+            cancel_fingerprinting: Arc::new(AtomicBool::new(false)),
+            is_verifying: Arc::new(AtomicBool::new(false)),
+            cancel_verify: Arc::new(AtomicBool::new(false)),
+            is_backfilling: Arc::new(AtomicBool::new(false)),
+            cancel_backfill: Arc::new(AtomicBool::new(false)),
             metadata_extractor: MetadataExtractor::new(),
+            watcher: Mutex::new(None),
+            file_change_tx,
+            file_change_rx,
+            last_load_id: AtomicU64::new(0),
         };
 
         Ok((library, command_tx, event_rx))
@@ -77,24 +512,41 @@ impl Library {
         thread::spawn(move || {
             log::info!("Library thread started");
 
+            let watcher_enabled = self.db.with(|db| db.get_setting(SETTING_WATCHER_ENABLED))
+                .ok()
+                .flatten()
+                .is_some_and(|v| v == "true");
+            if watcher_enabled {
+                self.restart_watcher();
+            }
+
             loop {
-                match self.command_rx.recv() {
-                    Ok(command) => {
-                        if let Err(e) = self.handle_command(command) {
-                            log::error!("Error handling library command: {}", e);
-                            let _ = self.event_tx.send(LibraryEvent::Error(e.to_string()));
+                crossbeam_channel::select! {
+                    recv(self.command_rx) -> command => match command {
+                        Ok(command) => {
+                            if let Err(e) = self.handle_command(command) {
+                                log::error!("Error handling library command: {}", e);
+                                let _ = self.event_tx.send(LibraryEvent::Error(e));
+                            }
                         }
-                    }
-                    Err(_) => {
-                        log::info!("Library command channel disconnected, stopping library thread");
-                        break;
-                    }
+                        Err(_) => {
+                            log::info!("Library command channel disconnected, stopping library thread");
+                            break;
+                        }
+                    },
+                    recv(self.file_change_rx) -> paths => {
+                        if let Ok(paths) = paths {
+                            if let Err(e) = self.apply_file_changes(paths) {
+                                log::error!("处理文件变化失败: {}", e);
+                            }
+                        }
+                    },
                 }
             }
         });
     }
 
-    fn handle_command(&self, command: LibraryCommand) -> Result<()> {
+    fn handle_command(&self, command: LibraryCommand) -> AppResult<()> {
         match command {
             LibraryCommand::Scan(paths) => {
                 self.scan_paths(paths)?;
@@ -106,23 +558,177 @@ impl Library {
                 log::info!("📥 收到GetTracks命令，开始从数据库加载曲目...");
                 let tracks = self.get_all_tracks()?;
                 log::info!("✅ 从数据库加载了 {} 首曲目", tracks.len());
-                log::info!("📤 发送TracksLoaded事件...");
-                let send_result = self.event_tx.send(LibraryEvent::TracksLoaded(tracks));
-                if send_result.is_ok() {
-                    log::info!("✅ TracksLoaded事件已成功发送");
-                } else {
-                    log::error!("❌ TracksLoaded事件发送失败: {:?}", send_result);
+                let load_id = self.last_load_id.fetch_add(1, Ordering::Relaxed) + 1;
+                let chunk_size = self.tracks_chunk_size();
+                log::info!("📤 分批发送曲目（每批 {} 首）...", chunk_size);
+                for event in tracks_load_events(load_id, tracks, chunk_size) {
+                    if let Err(e) = self.event_tx.send(event) {
+                        log::error!("❌ 曲目事件发送失败: {:?}", e);
+                        return Ok(());
+                    }
                 }
+                log::info!("✅ 曲目已全部发送");
             }
             LibraryCommand::SearchTracks(query) => {
-                let tracks = self.search_tracks(&query)?;
-                let _ = self.event_tx.send(LibraryEvent::SearchResults(tracks));
+                let results = self.search_library(&query)?;
+                let _ = self.event_tx.send(LibraryEvent::SearchResults(results));
             }
             LibraryCommand::GetStats => {
                 let stats = self.get_library_stats()?;
                 let _ = self.event_tx.send(stats);
             }
+            LibraryCommand::AnalyzeLoudness | LibraryCommand::AnalyzeSilence => {
+                self.start_audio_analysis();
+            }
+            LibraryCommand::CancelLoudnessAnalysis => {
+                if self.is_analyzing.load(Ordering::SeqCst) {
+                    log::info!("⏹️ 取消音频分析");
+                    self.cancel_analysis.store(true, Ordering::SeqCst);
+                }
+            }
+            LibraryCommand::EnableFingerprinting(enabled) => {
+                self.db.with(|db| db.set_setting(SETTING_FINGERPRINT_ENABLED, if enabled { "true" } else { "false" }))?;
+                if enabled {
+                    self.start_fingerprinting();
+                } else {
+                    self.cancel_fingerprinting();
+                }
+            }
+            LibraryCommand::CancelFingerprinting => {
+                self.cancel_fingerprinting();
+            }
+            LibraryCommand::Verify(track_ids) => {
+                let track_ids = match track_ids {
+                    Some(ids) => ids,
+                    None => self.db.with(|db| db.get_local_track_ids())?,
+                };
+                self.start_verification(Some(track_ids));
+            }
+            LibraryCommand::CancelVerify => {
+                if self.is_verifying.load(Ordering::SeqCst) {
+                    log::info!("⏹️ 取消完整性校验");
+                    self.cancel_verify.store(true, Ordering::SeqCst);
+                }
+            }
+            LibraryCommand::BackfillDurations => {
+                self.start_duration_backfill();
+            }
+            LibraryCommand::CancelDurationBackfill => {
+                if self.is_backfilling.load(Ordering::SeqCst) {
+                    log::info!("⏹️ 取消时长补算");
+                    self.cancel_backfill.store(true, Ordering::SeqCst);
+                }
+            }
+            LibraryCommand::EnableWatcher(enabled) => {
+                self.db.with(|db| db.set_setting(SETTING_WATCHER_ENABLED, if enabled { "true" } else { "false" }))?;
+                if enabled {
+                    self.restart_watcher();
+                } else if self.watcher.lock().unwrap().take().is_some() {
+                    log::info!("⏹️ 已停止监听音乐文件夹");
+                }
+            }
+            LibraryCommand::RebuildTrackArtists => {
+                self.rebuild_track_artists()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按当前的拆分设置重新拆分所有曲目的艺术家（修改分隔符或不拆分的名称后使用）
+    fn rebuild_track_artists(&self) -> Result<()> {
+        let (tags, config) = self.db.with(|db| Ok((db.get_track_artist_tags()?, db.artist_split_config())))?;
+        let total = tags.len();
+        log::info!("🎤 重新拆分 {} 首曲目的艺术家", total);
+        let _ = self.event_tx.send(LibraryEvent::ArtistSplitProgress { done: 0, total });
+
+        let mut done = 0;
+        for batch in tags.chunks(ARTIST_SPLIT_BATCH) {
+            self.db.with(|db| db.rebuild_track_artists(batch, &config))?;
+            done += batch.len();
+            let _ = self.event_tx.send(LibraryEvent::ArtistSplitProgress { done, total });
         }
+
+        log::info!("✅ 艺术家拆分完成");
+        Ok(())
+    }
+
+    /// 按当前曲库中的文件夹（重新）开始监听
+    fn restart_watcher(&self) {
+        let mut watcher = self.watcher.lock().unwrap();
+        // 先停止旧的监听
+        *watcher = None;
+
+        let folders = match self.db.with(|db| db.get_music_folder_paths()) {
+            Ok(folders) => folders,
+            Err(e) => {
+                log::error!("获取音乐文件夹失败: {}", e);
+                return;
+            }
+        };
+        match LibraryWatcher::start(&folders, self.file_change_tx.clone()) {
+            Ok(new_watcher) => *watcher = Some(new_watcher),
+            Err(e) => log::error!("启动文件夹监听失败: {}", e),
+        }
+    }
+
+    /// 处理监听到的文件变化：新增/修改的文件增量更新，消失的文件或文件夹从曲库移除
+    #[tracing::instrument(skip_all, fields(files = paths.len()))]
+    fn apply_file_changes(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let stored = self.db.with(|db| db.get_local_file_states())?;
+        let pattern = self.db.with_read(|db| Ok(load_path_pattern(db)))?;
+        let mut stats = ScanStats::default();
+        let mut removed_paths = Vec::new();
+
+        for path in paths {
+            if path.exists() {
+                for file in self.collect_audio_files(&path)? {
+                    let path_str = file.to_string_lossy().to_string();
+                    let job = match self.check_file(&file, stored.get(&path_str)) {
+                        Ok(Some(job)) => job,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            log::warn!("检查文件失败 {}: {}", file.display(), e);
+                            continue;
+                        }
+                    };
+                    let metadata = self.metadata_extractor.extract_lenient(&job.path, &pattern);
+                    let saved = self.save_track(&path_str, job.existing_id, metadata).and_then(|()| {
+                        self.db.with(|db| db.update_file_state(&path_str, job.mtime, job.size, job.hash.as_deref()))
+                    });
+                    match saved {
+                        Ok(()) if job.existing_id.is_none() => stats.added += 1,
+                        Ok(()) => stats.updated += 1,
+                        Err(e) => log::warn!("更新曲目失败 {}: {}", file.display(), e),
+                    }
+                }
+            } else {
+                // 文件被删除或移走；整个文件夹被删除时移除其下所有曲目
+                removed_paths.extend(
+                    stored.keys()
+                        .filter(|p| Path::new(p).starts_with(&path))
+                        .cloned(),
+                );
+            }
+        }
+
+        if !removed_paths.is_empty() {
+            stats.removed = self.db.with(|db| db.delete_tracks_by_paths(&removed_paths))?;
+        }
+
+        if stats.added + stats.updated + stats.removed > 0 {
+            log::info!(
+                "📂 曲库已自动更新: {} added, {} updated, {} removed",
+                stats.added,
+                stats.updated,
+                stats.removed
+            );
+            let _ = self.event_tx.send(LibraryEvent::TracksChanged {
+                added: stats.added,
+                updated: stats.updated,
+                removed: stats.removed,
+            });
+        }
+
         Ok(())
     }
 
@@ -136,6 +742,39 @@ impl Library {
             *is_scanning = true;
         }
 
+        let result = self.run_scan(&paths);
+
+        // Mark scanning as complete
+        {
+            let mut is_scanning = self.is_scanning.lock().unwrap();
+            *is_scanning = false;
+        }
+
+        result?;
+
+        // 扫描可能添加了新的文件夹，正在监听时一并纳入
+        if self.watcher.lock().unwrap().is_some() {
+            self.restart_watcher();
+        }
+
+        // 元数据扫描完成后，在后台分析新曲目的响度和静音
+        self.start_audio_analysis();
+
+        if self.fingerprinting_enabled() {
+            self.start_fingerprinting();
+        }
+
+        if self.verify_on_scan_enabled() {
+            self.start_verification(None);
+        }
+
+        Ok(())
+    }
+
+    /// 增量扫描：路径、修改时间、大小都未变的文件直接跳过，
+    /// 新文件和已变化的文件并行提取元数据，磁盘上已删除的文件从曲库移除
+    #[tracing::instrument(skip_all, fields(paths = ?paths))]
+    fn run_scan(&self, paths: &[String]) -> Result<()> {
         log::info!("Starting library scan of {} paths", paths.len());
         let _ = self.event_tx.send(LibraryEvent::ScanStarted {
             total_paths: paths.len(),
@@ -144,11 +783,22 @@ impl Library {
         // Collect all audio files
         let mut audio_files = Vec::new();
         let mut scan_errors = Vec::new();
+        // 存在且完整遍历过的扫描目录；未挂载、离线或遍历出错的目录不移除其下的曲目
+        let mut prunable_roots = Vec::new();
 
-        for path_str in &paths {
+        for path_str in paths {
             let path = PathBuf::from(path_str);
+            if !path.exists() {
+                let error_msg = format!("扫描路径不可用，已跳过: {}", path_str);
+                log::warn!("{}", error_msg);
+                scan_errors.push(error_msg);
+                continue;
+            }
             match self.collect_audio_files(&path) {
-                Ok(mut files) => audio_files.append(&mut files),
+                Ok(mut files) => {
+                    audio_files.append(&mut files);
+                    prunable_roots.push(path);
+                }
                 Err(e) => {
                     let error_msg = format!("Error scanning path {}: {}", path_str, e);
                     log::error!("{}", error_msg);
@@ -159,69 +809,539 @@ impl Library {
 
         log::info!("Found {} audio files to process", audio_files.len());
 
-        // Process files
-        let mut tracks_added = 0;
-        let mut tracks_updated = 0;
+        let stored = self.db.with(|db| db.get_local_file_states())?;
+        let total = audio_files.len();
+        let mut stats = ScanStats::default();
         let mut process_errors = Vec::new();
+        let mut jobs = Vec::new();
 
+        // 第一步：比对文件状态，找出需要提取元数据的文件
         for (index, file_path) in audio_files.iter().enumerate() {
-            let progress = ScanProgress {
-                current_file: file_path.to_string_lossy().to_string(),
-                processed: index,
-                total: audio_files.len(),
-                errors: process_errors.clone(),
-            };
+            let path_str = file_path.to_string_lossy().to_string();
+            match self.check_file(file_path, stored.get(&path_str)) {
+                Ok(Some(job)) => jobs.push(job),
+                Ok(None) => {
+                    stats.skipped += 1;
+                    stats.processed += 1;
+                    if stats.skipped % SKIP_PROGRESS_INTERVAL == 0 {
+                        self.send_scan_progress(&path_str, &stats, total, &process_errors);
+                    }
+                }
+                Err(e) => {
+                    let error_msg = format!("Error processing {}: {}", file_path.display(), e);
+                    log::error!("{}", error_msg);
+                    process_errors.push(error_msg);
+                }
+            }
 
-            let _ = self.event_tx.send(LibraryEvent::ScanProgress(progress));
+            if index + 1 == total {
+                self.send_scan_progress(&path_str, &stats, total, &process_errors);
+            }
+        }
 
-            match self.process_audio_file(file_path) {
-                Ok(was_new) => {
-                    if was_new {
-                        tracks_added += 1;
-                    } else {
-                        tracks_updated += 1;
+        log::info!("{} 个文件未变化，{} 个文件需要提取元数据", stats.skipped, jobs.len());
+
+        // 第二步：有限数量的工作线程并行提取元数据，写库仍在当前线程串行进行
+        if !jobs.is_empty() {
+            let workers = thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+                .clamp(1, MAX_SCAN_WORKERS)
+                .min(jobs.len());
+            let extractor = &self.metadata_extractor;
+            let pattern = &self.db.with_read(|db| Ok(load_path_pattern(db)))?;
+            let (job_tx, job_rx) = bounded::<ExtractJob>(workers * 2);
+            let (result_tx, result_rx) = unbounded();
+
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    let job_rx = job_rx.clone();
+                    let result_tx = result_tx.clone();
+                    scope.spawn(move || {
+                        for mut job in job_rx {
+                            // 已有曲目内容变化时记录哈希，之后只有修改时间变化时可据此确认内容
+                            if job.existing_id.is_some() && job.hash.is_none() {
+                                job.hash = file_md5(&job.path).ok();
+                            }
+                            let metadata = extractor.extract_lenient(&job.path, pattern);
+                            if result_tx.send((job, metadata)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+                drop(job_rx);
+                drop(result_tx);
+
+                scope.spawn(move || {
+                    for job in jobs {
+                        if job_tx.send(job).is_err() {
+                            break;
+                        }
                     }
+                });
+
+                for (job, metadata) in result_rx {
+                    let path_str = job.path.to_string_lossy().to_string();
+                    let saved = self.save_track(&path_str, job.existing_id, metadata).and_then(|()| {
+                        self.db.with(|db| db.update_file_state(&path_str, job.mtime, job.size, job.hash.as_deref()))
+                    });
+                    match saved {
+                        Ok(()) if job.existing_id.is_none() => stats.added += 1,
+                        Ok(()) => stats.updated += 1,
+                        Err(e) => {
+                            let error_msg = format!("Error processing {}: {}", job.path.display(), e);
+                            log::error!("{}", error_msg);
+                            process_errors.push(error_msg);
+                        }
+                    }
+                    stats.processed += 1;
+                    self.send_scan_progress(&path_str, &stats, total, &process_errors);
                 }
+            });
+        }
+
+        // 第三步：移除扫描目录下磁盘上已不存在的曲目（只在可用的扫描目录下移除）
+        let seen: HashSet<String> = audio_files.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let missing: Vec<String> = stored.keys()
+            .filter(|path| !seen.contains(*path))
+            .filter(|path| prunable_roots.iter().any(|root| Path::new(path).starts_with(root)))
+            .filter(|path| !Path::new(path).exists())
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            match self.db.with(|db| db.delete_tracks_by_paths(&missing)) {
+                Ok(removed) => stats.removed = removed,
                 Err(e) => {
-                    let error_msg = format!("Error processing {}: {}", file_path.display(), e);
+                    let error_msg = format!("移除已删除的曲目失败: {}", e);
                     log::error!("{}", error_msg);
                     process_errors.push(error_msg);
                 }
             }
+        }
 
-            // Small delay to prevent overwhelming the system
-            thread::sleep(Duration::from_millis(1));
+        // 重新提取后被替换的旧封面不再被引用
+        if stats.updated > 0 {
+            if let Err(e) = self.db.with(|db| db.prune_unused_covers()) {
+                log::warn!("清理未引用的封面失败: {}", e);
+            }
         }
 
         // Combine all errors
         scan_errors.extend(process_errors);
 
-        // Mark scanning as complete
-        {
-            let mut is_scanning = self.is_scanning.lock().unwrap();
-            *is_scanning = false;
-        }
-
         let _ = self.event_tx.send(LibraryEvent::ScanComplete {
-            tracks_added,
-            tracks_updated,
+            tracks_added: stats.added,
+            tracks_updated: stats.updated,
+            tracks_removed: stats.removed,
+            tracks_skipped: stats.skipped,
             errors: scan_errors,
         });
 
         log::info!(
-            "Library scan complete: {} added, {} updated",
-            tracks_added,
-            tracks_updated
+            "Library scan complete: {} added, {} updated, {} removed, {} skipped",
+            stats.added,
+            stats.updated,
+            stats.removed,
+            stats.skipped
         );
 
         Ok(())
     }
 
+    /// 比对文件与数据库记录；内容未变时返回 None（只有修改时间变化时会更新记录的文件状态）
+    fn check_file(&self, path: &Path, existing: Option<&LocalFileState>) -> Result<Option<ExtractJob>> {
+        let metadata = std::fs::metadata(path)?;
+        let mtime = file_mtime(&metadata);
+        let size = metadata.len() as i64;
+
+        let mut hash = None;
+        let unchanged = match classify_file(existing, mtime, size) {
+            ScanAction::Skip => true,
+            ScanAction::Extract => false,
+            ScanAction::VerifyHash => match file_md5(path) {
+                Ok(new_hash) => {
+                    let unchanged = existing.and_then(|e| e.hash.as_deref()) == Some(new_hash.as_str());
+                    hash = Some(new_hash);
+                    unchanged
+                }
+                Err(e) => {
+                    log::warn!("计算文件哈希失败 {}: {}", path.display(), e);
+                    false
+                }
+            },
+        };
+
+        if unchanged {
+            // 内容未变（只是修改时间变了），只更新记录的文件状态
+            if hash.is_some() {
+                let path_str = path.to_string_lossy();
+                self.db.with(|db| db.update_file_state(&path_str, mtime, size, hash.as_deref()))?;
+            }
+            return Ok(None);
+        }
+
+        Ok(Some(ExtractJob {
+            path: path.to_path_buf(),
+            existing_id: existing.map(|e| e.id),
+            mtime,
+            size,
+            hash,
+        }))
+    }
+
+    fn send_scan_progress(&self, current_file: &str, stats: &ScanStats, total: usize, errors: &[String]) {
+        let _ = self.event_tx.send(LibraryEvent::ScanProgress(ScanProgress {
+            current_file: current_file.to_string(),
+            processed: stats.processed,
+            total,
+            skipped: stats.skipped,
+            extracted: stats.added + stats.updated,
+            errors: errors.to_vec(),
+        }));
+    }
+
+    /// 启动后台音频分析任务（已在运行时忽略）
+    ///
+    /// 分析尚无响度记录、或尚未按当前阈值做静音检测的本地曲目，
+    /// 在独立线程中逐首解码，一次解码同时完成两项分析，
+    /// 每首之间稍作停顿，可通过 CancelLoudnessAnalysis 随时取消。
+    fn start_audio_analysis(&self) {
+        if self.is_analyzing.swap(true, Ordering::SeqCst) {
+            log::info!("音频分析已在进行中");
+            return;
+        }
+        self.cancel_analysis.store(false, Ordering::SeqCst);
+
+        let db = self.db.clone();
+        let event_tx = self.event_tx.clone();
+        let is_analyzing = self.is_analyzing.clone();
+        let cancel = self.cancel_analysis.clone();
+
+        thread::spawn(move || {
+            let threshold_db = match db.lock() {
+                Ok(db) => load_silence_settings(&db).threshold_db,
+                Err(e) => {
+                    log::error!("读取静音设置失败: {}", e);
+                    is_analyzing.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+            let pending = match db.with_read(|db| pending_analysis(db, threshold_db)) {
+                Ok(pending) => pending,
+                Err(e) => {
+                    log::error!("获取待分析曲目失败: {}", e);
+                    is_analyzing.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+
+            let total = pending.len();
+            if total > 0 {
+                log::info!("🔊 开始音频分析，共 {} 首曲目（静音阈值 {}dB）", total, threshold_db);
+                let _ = event_tx.send(LibraryEvent::AnalysisProgress { done: 0, total });
+            }
+
+            let should_stop = || cancel.load(Ordering::Relaxed);
+            for (index, task) in pending.iter().enumerate() {
+                if should_stop() {
+                    break;
+                }
+
+                let silence_threshold = task.needs_trim.then_some(threshold_db);
+                let decoded = AudioDecoder::new(&task.path).decode();
+                match decoded.map(|source| analyze_source(source, task.needs_loudness, silence_threshold, &should_stop)) {
+                    Ok(Some((loudness, trim))) => {
+                        let db = match db.lock() {
+                            Ok(db) => db,
+                            Err(e) => {
+                                log::warn!("保存分析结果失败 {}: {}", task.path, e);
+                                continue;
+                            }
+                        };
+                        if let Some(loudness) = loudness {
+                            if let Err(e) = db.update_loudness(task.track_id, loudness) {
+                                log::warn!("保存响度失败 {}: {}", task.path, e);
+                            }
+                        }
+                        if let Some(trim) = trim {
+                            if trim.is_trimmed() {
+                                log::debug!("🔇 静音裁剪点 {}: {:?}", task.path, trim);
+                            }
+                            if let Err(e) = db.update_trim_points(task.track_id, threshold_db, &trim) {
+                                log::warn!("保存静音裁剪点失败 {}: {}", task.path, e);
+                            }
+                        }
+                    }
+                    // 中途取消，不记录结果
+                    Ok(None) => break,
+                    Err(e) => log::warn!("音频分析解码失败 {}: {}", task.path, e),
+                }
+
+                let _ = event_tx.send(LibraryEvent::AnalysisProgress { done: index + 1, total });
+                thread::sleep(LOUDNESS_ANALYSIS_PAUSE);
+            }
+
+            if should_stop() {
+                log::info!("音频分析已取消");
+            } else if total > 0 {
+                log::info!("✅ 音频分析完成");
+            }
+            is_analyzing.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn fingerprinting_enabled(&self) -> bool {
+        self.db.with(|db| db.get_setting(SETTING_FINGERPRINT_ENABLED))
+            .ok()
+            .flatten()
+            .is_some_and(|v| v == "true")
+    }
+
+    fn cancel_fingerprinting(&self) {
+        if self.is_fingerprinting.load(Ordering::SeqCst) {
+            log::info!("⏹️ 取消声学指纹计算");
+            self.cancel_fingerprinting.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// 启动后台声学指纹计算（已在运行时忽略）
+    ///
+    /// 解码每首本地曲目开头约120秒计算指纹，缺少文件哈希的顺便补上。
+    /// 单线程逐首处理，每首之后停顿不少于处理耗时，CPU占用约为单核的一半，
+    /// 避免影响播放；可通过 CancelFingerprinting 随时取消。
+    fn start_fingerprinting(&self) {
+        if self.is_fingerprinting.swap(true, Ordering::SeqCst) {
+            log::info!("声学指纹计算已在进行中");
+            return;
+        }
+        self.cancel_fingerprinting.store(false, Ordering::SeqCst);
+
+        let db = self.db.clone();
+        let event_tx = self.event_tx.clone();
+        let is_fingerprinting = self.is_fingerprinting.clone();
+        let cancel = self.cancel_fingerprinting.clone();
+
+        thread::spawn(move || {
+            let pending = match db.with(|db| db.get_tracks_without_fingerprint()) {
+                Ok(pending) => pending,
+                Err(e) => {
+                    log::error!("获取待计算指纹的曲目失败: {}", e);
+                    is_fingerprinting.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+
+            let total = pending.len();
+            if total > 0 {
+                log::info!("🎼 开始计算声学指纹，共 {} 首曲目", total);
+                let _ = event_tx.send(LibraryEvent::FingerprintProgress { done: 0, total });
+            }
+
+            let should_stop = || cancel.load(Ordering::Relaxed);
+            for (index, (track_id, path, file_hash)) in pending.iter().enumerate() {
+                if should_stop() {
+                    break;
+                }
+
+                let started = Instant::now();
+                let decoded = AudioDecoder::new(path).decode();
+                match decoded.map(|source| compute_fingerprint(source, &should_stop)) {
+                    Ok(Some(fp)) => {
+                        let hash = match file_hash {
+                            Some(_) => None,
+                            None => file_md5(Path::new(path)).ok(),
+                        };
+                        let encoded = fingerprint::encode(&fp);
+                        if let Err(e) = db.with(|db| db.update_fingerprint(*track_id, &encoded, hash.as_deref())) {
+                            log::warn!("保存声学指纹失败 {}: {}", path, e);
+                        }
+                    }
+                    // 中途取消，不记录结果
+                    Ok(None) => break,
+                    Err(e) => log::warn!("声学指纹解码失败 {}: {}", path, e),
+                }
+
+                let _ = event_tx.send(LibraryEvent::FingerprintProgress { done: index + 1, total });
+                thread::sleep(started.elapsed().max(FINGERPRINT_MIN_PAUSE));
+            }
+
+            if should_stop() {
+                log::info!("声学指纹计算已取消");
+            } else if total > 0 {
+                log::info!("✅ 声学指纹计算完成");
+            }
+            is_fingerprinting.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn verify_on_scan_enabled(&self) -> bool {
+        self.db.with(|db| db.get_setting(SETTING_VERIFY_ON_SCAN))
+            .ok()
+            .flatten()
+            .is_some_and(|v| v == "true")
+    }
+
+    /// 启动后台完整性校验（已在运行时忽略），track_ids 为 None 时只校验尚未校验过的本地曲目
+    ///
+    /// 完整解码每首曲目找出截断或损坏的文件，结果写入tracks表。单线程逐首处理，
+    /// 解码过程中每检查一次取消标志就停顿与解码耗时相同的时间，CPU占用约为单核的一半，
+    /// 可以在播放时整夜运行；可通过 CancelVerify 随时取消，结束时发送各状态的数量。
+    fn start_verification(&self, track_ids: Option<Vec<i64>>) {
+        if self.is_verifying.swap(true, Ordering::SeqCst) {
+            log::info!("完整性校验已在进行中");
+            return;
+        }
+        self.cancel_verify.store(false, Ordering::SeqCst);
+
+        let db = self.db.clone();
+        let event_tx = self.event_tx.clone();
+        let is_verifying = self.is_verifying.clone();
+        let cancel = self.cancel_verify.clone();
+
+        thread::spawn(move || {
+            let explicit = track_ids.is_some();
+            let pending = match db.with(|db| db.get_tracks_to_verify(track_ids.as_deref())) {
+                Ok(pending) => pending,
+                Err(e) => {
+                    log::error!("获取待校验的曲目失败: {}", e);
+                    is_verifying.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+
+            let total = pending.len();
+            if total > 0 {
+                log::info!("🔍 开始完整性校验，共 {} 首曲目", total);
+                let _ = event_tx.send(LibraryEvent::VerifyProgress { done: 0, total });
+            }
+
+            let busy_since = Cell::new(Instant::now());
+            let should_stop = || {
+                thread::sleep(busy_since.get().elapsed());
+                busy_since.set(Instant::now());
+                cancel.load(Ordering::Relaxed)
+            };
+            let mut summary = VerifySummary::default();
+            for (index, (track_id, path)) in pending.iter().enumerate() {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                busy_since.set(Instant::now());
+                match verify::verify_file(Path::new(path), &should_stop) {
+                    Ok(Some(result)) => {
+                        if result.status.is_broken() {
+                            log::warn!("文件校验未通过 {}: {:?}", path, result.detail);
+                        }
+                        summary.add(result.status);
+                        if let Err(e) = db.with(|db| db.save_verify_result(*track_id, &result)) {
+                            log::warn!("保存校验结果失败 {}: {}", path, e);
+                        }
+                    }
+                    // 中途取消，不记录结果
+                    Ok(None) => break,
+                    Err(e) => {
+                        summary.failed += 1;
+                        log::warn!("无法打开文件进行校验 {}: {}", path, e);
+                    }
+                }
+
+                let _ = event_tx.send(LibraryEvent::VerifyProgress { done: index + 1, total });
+                thread::sleep(busy_since.get().elapsed().max(VERIFY_MIN_PAUSE));
+            }
+
+            summary.cancelled = cancel.load(Ordering::Relaxed);
+            if summary.cancelled {
+                log::info!("完整性校验已取消");
+            } else if total > 0 {
+                log::info!(
+                    "✅ 完整性校验完成: 正常 {}，截断 {}，解码失败 {}，无法打开 {}",
+                    summary.ok, summary.truncated, summary.decode_error, summary.failed
+                );
+            }
+            if explicit || total > 0 {
+                let _ = event_tx.send(LibraryEvent::VerifyComplete(summary));
+            }
+            is_verifying.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// 启动后台时长补算（已在运行时忽略）
+    ///
+    /// 本地曲目用Symphonia读取容器头，没有总帧数时逐包累加；远程曲目已完整缓存的
+    /// 读取缓存文件，否则按比特率和文件大小估算，两者都未知的留到播放时更正。
+    /// 可通过 CancelDurationBackfill 随时取消。
+    fn start_duration_backfill(&self) {
+        if self.is_backfilling.swap(true, Ordering::SeqCst) {
+            log::info!("时长补算已在进行中");
+            return;
+        }
+        self.cancel_backfill.store(false, Ordering::SeqCst);
+
+        let db = self.db.clone();
+        let event_tx = self.event_tx.clone();
+        let is_backfilling = self.is_backfilling.clone();
+        let cancel = self.cancel_backfill.clone();
+
+        thread::spawn(move || {
+            let pending = match db.with_read(|db| db.get_tracks_without_duration()) {
+                Ok(pending) => pending,
+                Err(e) => {
+                    log::error!("获取时长缺失的曲目失败: {}", e);
+                    is_backfilling.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+
+            let total = pending.len();
+            let mut updated = 0;
+            if total > 0 {
+                log::info!("⏱️ 开始补算时长，共 {} 首曲目", total);
+                let _ = event_tx.send(LibraryEvent::DurationBackfillProgress { done: 0, total, updated });
+            }
+
+            let should_stop = || cancel.load(Ordering::Relaxed);
+            for (index, track) in pending.iter().enumerate() {
+                if should_stop() {
+                    break;
+                }
+
+                match backfill_duration(track, &should_stop) {
+                    Ok(Some(duration_ms)) => {
+                        let duration_ms = duration_ms as i64;
+                        match db.with(|db| db.update_track_duration(track.track_id, duration_ms)) {
+                            Ok(true) => {
+                                updated += 1;
+                                let _ = event_tx.send(LibraryEvent::TrackDurationUpdated { track_id: track.track_id, duration_ms });
+                            }
+                            Ok(false) => {}
+                            Err(e) => log::warn!("保存时长失败 {}: {}", track.path, e),
+                        }
+                    }
+                    Ok(None) if should_stop() => break,
+                    Ok(None) => log::debug!("无法确定时长: {}", track.path),
+                    Err(e) => log::warn!("时长补算失败 {}: {}", track.path, e),
+                }
+
+                let _ = event_tx.send(LibraryEvent::DurationBackfillProgress { done: index + 1, total, updated });
+                thread::sleep(DURATION_BACKFILL_PAUSE);
+            }
+
+            if should_stop() {
+                log::info!("时长补算已取消");
+            } else if total > 0 {
+                log::info!("✅ 时长补算完成，更新了 {} 首曲目", updated);
+            }
+            is_backfilling.store(false, Ordering::SeqCst);
+        });
+    }
+
     fn collect_audio_files(&self, path: &Path) -> Result<Vec<PathBuf>> {
         let mut files = Vec::new();
 
         if path.is_file() {
-            if self.is_audio_file(path) {
+            if is_audio_file(path) {
                 files.push(path.to_path_buf());
             }
         } else if path.is_dir() {
@@ -244,90 +1364,55 @@ impl Library {
                     }
                 }
                 self.scan_directory_recursive(&path, files)?;
-            } else if self.is_audio_file(&path) {
+            } else if is_audio_file(&path) {
                 files.push(path);
             }
         }
         Ok(())
     }
 
-    fn is_audio_file(&self, path: &Path) -> bool {
-        if let Some(extension) = path.extension() {
-            let ext = extension.to_string_lossy().to_lowercase();
-            // 支持的音频格式 - 与播放器保持一致
-            matches!(
-                ext.as_str(),
-                // 常见无损格式
-                "flac" | "wav" | "aiff" | "aif" | "aifc" |
-                // 常见有损格式  
-                "mp3" | "aac" | "m4a" | "ogg" | "oga" | "opus" |
-                // 其他格式
-                "wma" | "ape" | "tak" | "tta" | "dsd" | "dsf" | "dff" |
-                // 模块音乐格式
-                "mod" | "it" | "s3m" | "xm" |
-                // 其他无损格式
-                "alac" | "wv" | "mka"
-            )
-        } else {
-            false
-        }
-    }
-
     fn process_audio_file(&self, path: &Path) -> Result<bool> {
         // Check if file already exists in database
         let path_str = path.to_string_lossy().to_string();
-        let db = self.db.lock().unwrap();
-        let existing_track = db.get_track_by_path(&path_str)?;
-        let track_id = existing_track.as_ref().map(|t| t.id).unwrap_or(0);
-        drop(db); // 释放数据库锁
+        let existing_id = self.db.with(|db| db.get_track_by_path(&path_str))?.map(|t| t.id);
 
         // 使用新的元数据提取器
-        let metadata = self.metadata_extractor.extract_from_file(path)?;
-        
-        // 保存内嵌歌词到数据库（如果有）
-        if let Some(lyrics_content) = &metadata.embedded_lyrics {
-            if track_id > 0 {
-                let db = self.db.lock().unwrap();
-                if let Err(e) = db.insert_lyrics(track_id, lyrics_content, "lrc", "embedded") {
-                    log::warn!("保存内嵌歌词失败: {}", e);
-                }
-                drop(db);
-            }
-        }
-
-        let track = Track {
-            id: track_id,
-            path: path_str,
-            title: metadata.title,
-            artist: metadata.artist,
-            album: metadata.album,
-            duration_ms: metadata.duration_ms.map(|d| d as i64),
-            album_cover_data: metadata.album_cover_data,
-            album_cover_mime: metadata.album_cover_mime,
-            artist_photo_data: metadata.artist_photo_data,
-            artist_photo_mime: metadata.artist_photo_mime,
-            embedded_lyrics: metadata.embedded_lyrics,
-        };
+        let pattern = self.db.with_read(|db| Ok(load_path_pattern(db)))?;
+        let metadata = self.metadata_extractor.extract_lenient(path, &pattern);
+        self.save_track(&path_str, existing_id, metadata)?;
 
-        let db = self.db.lock().unwrap();
-        db.insert_track(&track)?;
+        Ok(existing_id.is_none()) // true if new track, false if updated
+    }
+
+    /// 保存提取到的元数据
+    fn save_track(&self, path_str: &str, existing_id: Option<i64>, metadata: MusicMetadata) -> Result<()> {
+        save_track_metadata(&self.db, path_str, existing_id, metadata)
+    }
 
-        Ok(existing_track.is_none()) // true if new track, false if updated
+    /// 每批发送的曲目数，超出范围的设置值按边界处理
+    fn tracks_chunk_size(&self) -> usize {
+        self.db.with(|db| db.get_setting(SETTING_TRACKS_CHUNK_SIZE))
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse::<usize>().ok())
+            .map(|size| size.clamp(MIN_TRACKS_CHUNK_SIZE, MAX_TRACKS_CHUNK_SIZE))
+            .unwrap_or(DEFAULT_TRACKS_CHUNK_SIZE)
     }
 
-    fn get_all_tracks(&self) -> Result<Vec<Track>> {
-        let db = self.db.lock().unwrap();
-        db.get_all_tracks()
+    fn get_all_tracks(&self) -> AppResult<Vec<Track>> {
+        Ok(self.db.with_read(|db| db.get_all_tracks())?)
     }
 
-    fn search_tracks(&self, query: &str) -> Result<Vec<Track>> {
-        let db = self.db.lock().unwrap();
-        db.search_tracks(query)
+    fn search_library(&self, query: &str) -> AppResult<LibrarySearchResult> {
+        if let Err(e) = self.db.with(|db| db.record_search(query)) {
+            log::warn!("记录搜索历史失败: {}", e);
+        }
+        Ok(self.db.with_read(|db| db.search_library(query))?)
     }
 
-    fn get_library_stats(&self) -> Result<LibraryEvent> {
+    fn get_library_stats(&self) -> AppResult<LibraryEvent> {
         log::info!("开始获取库统计数据");
-        let db = self.db.lock().unwrap();
+        let db = self.db.read()?;
         let total_tracks = db.get_track_count()?;
         let total_artists = db.get_artist_count()?;
         let total_albums = db.get_album_count()?;
@@ -450,6 +1535,7 @@ impl Library {
     }
 
     /// 重新扫描所有现有曲目，更新封面数据
+    #[tracing::instrument(skip_all)]
     fn rescan_all_tracks(&self) -> Result<()> {
         log::info!("开始重新扫描所有曲目以更新封面数据");
         
@@ -468,6 +1554,8 @@ impl Library {
                 current_file: track.path.clone(),
                 processed: index,
                 total: tracks.len(),
+                skipped: 0,
+                extracted: updated_count,
                 errors: errors.clone(),
             };
             let _ = self.event_tx.send(LibraryEvent::ScanProgress(progress));
@@ -489,6 +1577,8 @@ impl Library {
         let _ = self.event_tx.send(LibraryEvent::ScanComplete {
             tracks_added: 0,
             tracks_updated: updated_count,
+            tracks_removed: 0,
+            tracks_skipped: 0,
             errors,
         });
 
@@ -496,3 +1586,235 @@ impl Library {
         Ok(())
     }
 }
+
+/// 把全量曲目拆成 Begin、按顺序的若干 Chunk、End 三类事件，并去掉封面等二进制数据
+fn tracks_load_events(load_id: u64, tracks: Vec<Track>, chunk_size: usize) -> Vec<LibraryEvent> {
+    let total = tracks.len();
+    let chunk_size = chunk_size.max(1);
+    let mut events = Vec::with_capacity(total.div_ceil(chunk_size) + 2);
+    events.push(LibraryEvent::TracksLoadBegin(TracksLoadInfo { load_id, total }));
+
+    let mut tracks = tracks.into_iter().map(|mut track| {
+        track.album_cover_data = None;
+        track.album_cover_mime = None;
+        track.artist_photo_data = None;
+        track.artist_photo_mime = None;
+        track
+    });
+    let mut offset = 0;
+    loop {
+        let chunk: Vec<Track> = tracks.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len();
+        events.push(LibraryEvent::TracksChunk(TracksChunk { load_id, offset, tracks: chunk }));
+        offset += len;
+    }
+
+    events.push(LibraryEvent::TracksLoadEnd(TracksLoadInfo { load_id, total }));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(mtime: i64, size: i64, hash: Option<&str>) -> LocalFileState {
+        LocalFileState { id: 1, mtime: Some(mtime), size: Some(size), hash: hash.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_classify_file() {
+        assert_eq!(classify_file(None, Some(100), 10), ScanAction::Extract);
+        assert_eq!(classify_file(Some(&stored(100, 10, None)), Some(100), 10), ScanAction::Skip);
+        assert_eq!(classify_file(Some(&stored(100, 10, None)), Some(100), 11), ScanAction::Extract);
+        assert_eq!(classify_file(Some(&stored(100, 10, None)), Some(200), 10), ScanAction::Extract);
+        assert_eq!(classify_file(Some(&stored(100, 10, Some("abc"))), Some(200), 10), ScanAction::VerifyHash);
+        // 旧数据库没有记录文件状态
+        assert_eq!(classify_file(Some(&LocalFileState::default()), Some(100), 10), ScanAction::Extract);
+    }
+
+    #[test]
+    fn test_scan_keeps_tracks_under_unavailable_root() {
+        let root = std::env::temp_dir().join(format!("windchime-scan-{}", uuid::Uuid::new_v4()));
+        let present = root.join("present");
+        let absent = root.join("usb-drive");
+        std::fs::create_dir_all(&present).unwrap();
+
+        let db = Database::new(":memory:").unwrap();
+        let offline = absent.join("song.flac").to_string_lossy().to_string();
+        let deleted = present.join("deleted.flac").to_string_lossy().to_string();
+        for path in [&offline, &deleted] {
+            db.insert_track(&Track::new(0, path.clone())).unwrap();
+        }
+        let (library, _tx, _events) = Library::new(Arc::new(DbPool::single(db))).unwrap();
+
+        let roots = [absent.to_string_lossy().to_string(), present.to_string_lossy().to_string()];
+        library.run_scan(&roots).unwrap();
+
+        // 未挂载的目录下的曲目保留，可用目录下已删除的文件照常移除
+        let remaining = library.db.with(|db| db.get_local_file_states()).unwrap();
+        assert!(remaining.contains_key(&offline));
+        assert!(!remaining.contains_key(&deleted));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn fixture_track(id: i64) -> Track {
+        let mut track = Track::new(id, format!("/music/Artist {}/Album {}/{:02} Title {}.flac", id % 500, id % 4000, id % 12 + 1, id));
+        track.title = Some(format!("Title {}", id));
+        track.artist = Some(format!("Artist {}", id % 500));
+        track.album = Some(format!("Album {}", id % 4000));
+        track.album_artist = track.artist.clone();
+        track.duration_ms = Some(180_000 + id % 120_000);
+        track.genre = Some("Rock".to_string());
+        track.year = Some(1970 + id % 50);
+        track.track_number = Some(id % 12 + 1);
+        track.cover_id = Some(format!("{:064x}", id % 4000));
+        track
+    }
+
+    #[test]
+    fn test_tracks_load_events_preserve_order() {
+        let mut tracks: Vec<Track> = (1..=1234).map(fixture_track).collect();
+        tracks[3].album_cover_data = Some(vec![0; 1024]);
+        tracks[3].album_cover_mime = Some("image/jpeg".to_string());
+
+        let events = tracks_load_events(7, tracks, 500);
+        assert_eq!(events.len(), 5);
+        assert!(matches!(&events[0], LibraryEvent::TracksLoadBegin(TracksLoadInfo { load_id: 7, total: 1234 })));
+        assert!(matches!(events.last(), Some(LibraryEvent::TracksLoadEnd(TracksLoadInfo { load_id: 7, total: 1234 }))));
+
+        let mut ids = Vec::new();
+        for event in &events[1..events.len() - 1] {
+            let LibraryEvent::TracksChunk(chunk) = event else { panic!("开始和结束之间只能是曲目批次") };
+            assert_eq!(chunk.load_id, 7);
+            assert_eq!(chunk.offset, ids.len());
+            assert!(chunk.tracks.len() <= 500);
+            assert!(chunk.tracks.iter().all(|t| t.album_cover_data.is_none() && t.album_cover_mime.is_none()));
+            ids.extend(chunk.tracks.iter().map(|t| t.id));
+        }
+        assert_eq!(ids, (1..=1234).collect::<Vec<i64>>());
+
+        // 空曲库只有开始和结束
+        assert_eq!(tracks_load_events(8, Vec::new(), 500).len(), 2);
+    }
+
+    /// 5万首曲目的事件负载：改造前整个列表是一个事件，改造后每批一个事件。
+    /// webview 解析单个事件时界面无法响应，卡顿时长取决于最大的单个负载，
+    /// 默认每批500首时单个负载约为原来的1%
+    #[test]
+    fn test_tracks_load_payload_size_50k() {
+        const COUNT: i64 = 50_000;
+        let tracks: Vec<Track> = (1..=COUNT).map(fixture_track).collect();
+        let before_bytes = serde_json::to_string(&tracks).unwrap().len();
+
+        let after_max_bytes = tracks_load_events(1, tracks, DEFAULT_TRACKS_CHUNK_SIZE)
+            .iter()
+            .map(|event| match event {
+                LibraryEvent::TracksChunk(chunk) => serde_json::to_string(chunk),
+                LibraryEvent::TracksLoadBegin(info) | LibraryEvent::TracksLoadEnd(info) => serde_json::to_string(info),
+                _ => unreachable!(),
+            }
+            .unwrap()
+            .len())
+            .max()
+            .unwrap();
+
+        assert!(after_max_bytes * 50 < before_bytes, "单个事件负载应远小于整个列表: {} vs {}", after_max_bytes, before_bytes);
+    }
+
+    fn track_with_cover(id: i64) -> Track {
+        let mut track = fixture_track(id);
+        track.cover_id = None;
+        track.album_cover_data = Some(vec![0xFF; 4096]);
+        track.album_cover_mime = Some("image/jpeg".to_string());
+        track.artist_photo_data = Some(vec![0xAB; 1024]);
+        track.artist_photo_mime = Some("image/png".to_string());
+        track
+    }
+
+    /// 只统计字节数的写入端，序列化大列表时不分配整个字符串
+    #[derive(Default)]
+    struct ByteCount(usize);
+
+    impl std::io::Write for ByteCount {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn json_bytes(tracks: &[Track]) -> usize {
+        let mut count = ByteCount::default();
+        serde_json::to_writer(&mut count, tracks).unwrap();
+        count.0
+    }
+
+    /// 结果集占用的堆内存（字符串和BLOB），不含 Vec 本身
+    fn heap_bytes(tracks: &[Track]) -> usize {
+        let text = |value: &Option<String>| value.as_ref().map_or(0, String::len);
+        let blob = |value: &Option<Vec<u8>>| value.as_ref().map_or(0, Vec::len);
+        tracks
+            .iter()
+            .map(|t| {
+                t.path.len()
+                    + text(&t.title) + text(&t.artist) + text(&t.album) + text(&t.genre) + text(&t.album_artist)
+                    + text(&t.cover_id) + text(&t.album_cover_mime) + text(&t.artist_photo_mime) + text(&t.embedded_lyrics)
+                    + blob(&t.album_cover_data) + blob(&t.artist_photo_data)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_get_tracks_rows_carry_no_cover_blobs() {
+        let db = Database::new(":memory:").unwrap();
+        for id in 1..=3 {
+            db.insert_track(&track_with_cover(id)).unwrap();
+        }
+        let (library, _tx, mut events) = Library::new(Arc::new(DbPool::single(db))).unwrap();
+        library.handle_command(LibraryCommand::GetTracks).unwrap();
+
+        use futures::FutureExt;
+        let mut loaded = 0;
+        while let Some(Some(event)) = events.recv().now_or_never() {
+            let LibraryEvent::TracksChunk(chunk) = event else { continue };
+            for track in &chunk.tracks {
+                assert!(track.album_cover_data.is_none() && track.album_cover_mime.is_none());
+                assert!(track.artist_photo_data.is_none() && track.artist_photo_mime.is_none());
+            }
+            let json = serde_json::to_string(&chunk).unwrap();
+            assert!(!json.contains("album_cover_data") && !json.contains("artist_photo_data"));
+            loaded += chunk.tracks.len();
+        }
+        assert_eq!(loaded, 3);
+    }
+
+    /// 4万首曲目（每首 4KiB 封面 + 1KiB 艺术家照片）的列表查询：
+    /// 改造前的查询（等同 get_tracks_with_covers）与 get_all_tracks 的结果集内存和IPC负载对比
+    /// （cargo test measure_track_list_40k -- --ignored）
+    #[test]
+    #[ignore]
+    fn measure_track_list_40k() {
+        const COUNT: i64 = 40_000;
+        let db = Database::new(":memory:").unwrap();
+        let ids: Vec<i64> = (1..=COUNT).map(|id| db.insert_track(&track_with_cover(id)).unwrap()).collect();
+
+        let mut before = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(500) {
+            before.extend(db.get_tracks_with_covers(chunk).unwrap());
+        }
+        let after = db.get_all_tracks().unwrap();
+        assert_eq!(before.len(), after.len());
+
+        let (before_heap, after_heap) = (heap_bytes(&before), heap_bytes(&after));
+        let (before_json, after_json) = (json_bytes(&before), json_bytes(&after));
+        assert!(after_heap * 10 < before_heap, "结果集内存: {} -> {} 字节", before_heap, after_heap);
+        assert!(after_json * 10 < before_json, "IPC负载: {} -> {} 字节", before_json, after_json);
+    }
+}
@@ -0,0 +1,294 @@
+// 曲库备份（换电脑时迁移播放记录、收藏和歌单，不用重新整理）
+//
+// 备份是一个 zip 文件，其中 library.json 包含：
+// - 曲目识别信息（路径、标题、艺术家、专辑、时长、文件哈希）和评分，不含音频和封面
+// - 歌单（含智能规则和加入时间）、收藏、播放历史、歌词
+// - 远程服务器配置（可选择不导出密码）
+//
+// 还原到已扫描好的曲库：先按文件哈希匹配曲目，再按标题 + 艺术家 + 时长匹配，
+// 匹配不到的曲目相关记录跳过。
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// 备份格式版本，结构有不兼容的变化时递增
+pub const BACKUP_VERSION: u32 = 1;
+
+/// 备份数据在 zip 中的文件名
+const BACKUP_ENTRY: &str = "library.json";
+
+/// 按元数据匹配时允许的时长误差
+const DURATION_TOLERANCE_MS: i64 = 2000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LibraryBackup {
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub tracks: Vec<BackupTrack>,
+    pub playlists: Vec<BackupPlaylist>,
+    pub favorites: Vec<BackupFavorite>,
+    pub play_history: Vec<BackupPlay>,
+    pub lyrics: Vec<BackupLyrics>,
+    pub remote_servers: Vec<BackupRemoteServer>,
+}
+
+/// 曲目识别信息，id 为导出时的曲目ID，其他记录通过它引用曲目
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupTrack {
+    pub id: i64,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub file_hash: Option<String>,
+    /// 评分（0-5星），旧版备份中没有
+    #[serde(default)]
+    pub rating: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupPlaylist {
+    pub name: String,
+    pub description: Option<String>,
+    pub color_theme: Option<String>,
+    pub is_smart: bool,
+    pub smart_rules: Option<String>,
+    pub is_favorite: bool,
+    pub is_pinned: bool,
+    pub play_count: i64,
+    pub last_played: Option<i64>,
+    pub created_at: Option<i64>,
+    pub items: Vec<BackupPlaylistItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPlaylistItem {
+    pub track_id: i64,
+    pub added_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFavorite {
+    pub track_id: i64,
+    pub created_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPlay {
+    pub track_id: i64,
+    pub played_at: i64,
+    pub duration_played_ms: i64,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupLyrics {
+    pub track_id: i64,
+    pub content: String,
+    pub format: String,
+    pub source: String,
+}
+
+/// 远程服务器配置，不导出密码时 config_json 中没有密码字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRemoteServer {
+    pub id: String,
+    pub name: String,
+    pub server_type: String,
+    pub config_json: String,
+    pub enabled: bool,
+}
+
+/// 还原选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    /// 覆盖同名歌单、已有歌词和同ID的远程服务器；否则同名歌单加后缀另存，其余跳过
+    pub overwrite: bool,
+    /// 是否还原远程服务器配置
+    pub include_remote_servers: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            overwrite: false,
+            include_remote_servers: true,
+        }
+    }
+}
+
+/// 各类记录的还原结果：新增 / 与已有记录合并（或覆盖） / 跳过（曲目未匹配或已存在）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportCounts {
+    pub imported: usize,
+    pub merged: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub tracks_matched: usize,
+    pub tracks_unmatched: usize,
+    pub playlists: ImportCounts,
+    pub playlist_items: ImportCounts,
+    pub favorites: ImportCounts,
+    pub ratings: ImportCounts,
+    pub play_history: ImportCounts,
+    pub lyrics: ImportCounts,
+    pub remote_servers: ImportCounts,
+}
+
+/// 写入备份文件
+pub fn write_archive(path: &Path, backup: &LibraryBackup) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("无法创建备份文件: {}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(BACKUP_ENTRY, options)?;
+    zip.write_all(&serde_json::to_vec(backup)?)?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// 读取备份文件，拒绝更新版本程序导出的备份
+pub fn read_archive(path: &Path) -> Result<LibraryBackup> {
+    let file = std::fs::File::open(path).with_context(|| format!("无法打开备份文件: {}", path.display()))?;
+    let mut archive = ZipArchive::new(file).context("不是有效的备份文件")?;
+    let mut json = String::new();
+    archive
+        .by_name(BACKUP_ENTRY)
+        .context("备份文件中没有曲库数据")?
+        .read_to_string(&mut json)?;
+    let backup: LibraryBackup = serde_json::from_str(&json).context("备份数据格式错误")?;
+    if backup.version > BACKUP_VERSION {
+        return Err(anyhow!(
+            "备份由更新版本的程序导出（格式版本 {}），请先升级程序",
+            backup.version
+        ));
+    }
+    Ok(backup)
+}
+
+/// 备份中的曲目 → 当前曲库中的曲目ID
+///
+/// 先按文件哈希匹配；没有哈希或哈希不同（如重新编码）时，按标题、艺术家（忽略大小写）和时长匹配，
+/// 多个候选时取时长最接近的。
+pub fn match_tracks(backup: &[BackupTrack], library: &[BackupTrack]) -> HashMap<i64, i64> {
+    let normalize = |value: &Option<String>| value.as_deref().map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty());
+
+    let by_hash: HashMap<&str, i64> = library
+        .iter()
+        .filter_map(|track| track.file_hash.as_deref().map(|hash| (hash, track.id)))
+        .collect();
+    let mut by_metadata: HashMap<(String, String), Vec<&BackupTrack>> = HashMap::new();
+    for track in library {
+        if let (Some(title), Some(artist)) = (normalize(&track.title), normalize(&track.artist)) {
+            by_metadata.entry((title, artist)).or_default().push(track);
+        }
+    }
+
+    let mut matched = HashMap::new();
+    for track in backup {
+        if let Some(&id) = track.file_hash.as_deref().and_then(|hash| by_hash.get(hash)) {
+            matched.insert(track.id, id);
+            continue;
+        }
+        let (Some(title), Some(artist), Some(duration)) = (normalize(&track.title), normalize(&track.artist), track.duration_ms) else {
+            continue;
+        };
+        let best = by_metadata
+            .get(&(title, artist))
+            .into_iter()
+            .flatten()
+            .filter_map(|candidate| candidate.duration_ms.map(|d| ((d - duration).abs(), candidate.id)))
+            .filter(|(diff, _)| *diff <= DURATION_TOLERANCE_MS)
+            .min();
+        if let Some((_, id)) = best {
+            matched.insert(track.id, id);
+        }
+    }
+    matched
+}
+
+/// 与已有歌单重名时加后缀：名称 (2)、名称 (3)……
+pub fn unique_name(name: &str, exists: impl Fn(&str) -> bool) -> String {
+    if !exists(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !exists(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64, title: &str, artist: &str, duration_ms: i64, hash: Option<&str>) -> BackupTrack {
+        BackupTrack {
+            id,
+            path: format!("/music/{}.flac", id),
+            title: Some(title.to_string()),
+            artist: Some(artist.to_string()),
+            album: None,
+            duration_ms: Some(duration_ms),
+            file_hash: hash.map(str::to_string),
+            rating: None,
+        }
+    }
+
+    #[test]
+    fn test_match_tracks_by_hash_then_metadata() {
+        let library = vec![
+            track(10, "Song", "Band", 200_000, Some("h1")),
+            track(11, "Other", "Band", 180_000, Some("h2")),
+            track(12, "Other", "Band", 181_500, None),
+            track(13, "Live", "Band", 300_000, None),
+        ];
+        let backup = vec![
+            // 哈希一致，元数据不同也匹配
+            track(1, "Renamed", "Someone", 1, Some("h1")),
+            // 哈希不同，按元数据匹配时长最接近的
+            track(2, " other ", "BAND", 181_400, Some("changed")),
+            // 时长相差太多
+            track(3, "Live", "Band", 310_000, None),
+            track(4, "Missing", "Band", 100_000, None),
+        ];
+        let matched = match_tracks(&backup, &library);
+        assert_eq!(matched.get(&1), Some(&10));
+        assert_eq!(matched.get(&2), Some(&12));
+        assert_eq!(matched.len(), 2);
+    }
+
+    #[test]
+    fn test_archive_round_trip_and_version_check() {
+        let path = std::env::temp_dir().join(format!("windchime-backup-{}.zip", uuid::Uuid::new_v4()));
+        let mut backup = LibraryBackup {
+            version: BACKUP_VERSION,
+            tracks: vec![track(1, "Song", "Band", 1000, Some("h"))],
+            ..Default::default()
+        };
+        write_archive(&path, &backup).unwrap();
+        assert_eq!(read_archive(&path).unwrap().tracks, backup.tracks);
+
+        backup.version = BACKUP_VERSION + 1;
+        write_archive(&path, &backup).unwrap();
+        assert!(read_archive(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let existing = ["Mix", "Mix (2)"];
+        assert_eq!(unique_name("New", |n| existing.contains(&n)), "New");
+        assert_eq!(unique_name("Mix", |n| existing.contains(&n)), "Mix (3)");
+    }
+}
@@ -0,0 +1,269 @@
+// 曲库路径迁移：音乐文件夹整体移动后按前缀批量修改曲目路径
+//
+// - 保留曲目ID，播放次数、歌单、收藏、歌词等都不受影响
+// - 前缀比较时 `/` 和 `\` 视为相同，前缀必须在路径分隔处结束（/Music 不会匹配 /Musical）
+// - 只迁移新路径下文件确实存在的曲目；dry_run 只返回将要迁移和仍然缺失的曲目
+// - verify_content 时计算新文件的哈希，与原记录不一致的曲目不迁移，一致时同时更新文件状态
+// - 所有路径在一个事务中修改
+// - 远程曲目（WebDAV / Subsonic）不参与
+
+use crate::db_pool::DbPool;
+use crate::db::{LocalFileState, PathRelocation};
+use crate::error::{AppError, AppResult};
+use crate::library::{file_md5, file_mtime};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 一首曲目的旧路径和新路径
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelocatedTrack {
+    pub track_id: i64,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// 迁移结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelocateReport {
+    pub dry_run: bool,
+    /// 新路径下找到文件、已迁移（dry_run 时为将要迁移）的曲目
+    pub fixed: Vec<RelocatedTrack>,
+    /// 新路径下也找不到文件的曲目
+    pub still_missing: Vec<RelocatedTrack>,
+    /// 新路径已被曲库中的其他曲目占用（例如移动后已重新扫描过）
+    pub conflicts: Vec<RelocatedTrack>,
+    /// 检查内容时与原记录的哈希不一致的曲目
+    pub content_mismatch: Vec<RelocatedTrack>,
+    /// 实际修改的曲目数（dry_run 时为0）
+    pub updated: usize,
+}
+
+/// 路径已不存在的本地曲目
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MissingTrack {
+    pub track_id: i64,
+    pub path: String,
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// 按前缀替换路径，不匹配时返回 None；剩余部分的分隔符统一为新前缀使用的分隔符
+pub fn substitute_prefix(path: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    let old = old_prefix.trim_end_matches(is_separator);
+    if old.is_empty() || path.len() < old.len() || !path.is_char_boundary(old.len()) {
+        return None;
+    }
+
+    let (head, rest) = path.split_at(old.len());
+    let matches = head
+        .chars()
+        .zip(old.chars())
+        .all(|(a, b)| a == b || (is_separator(a) && is_separator(b)));
+    if !matches || !(rest.is_empty() || rest.starts_with(is_separator)) {
+        return None;
+    }
+
+    let new = new_prefix.trim_end_matches(is_separator);
+    let separator = if new.contains('\\') && !new.contains('/') { '\\' } else { '/' };
+    let rest: String = rest.chars().map(|c| if is_separator(c) { separator } else { c }).collect();
+    Some(format!("{}{}", new, rest))
+}
+
+/// 新文件的状态（修改时间、大小、哈希）
+fn read_file_state(path: &Path) -> anyhow::Result<(Option<i64>, i64, String)> {
+    let metadata = std::fs::metadata(path)?;
+    Ok((file_mtime(&metadata), metadata.len() as i64, file_md5(path)?))
+}
+
+/// 把 old_prefix 下的曲目迁移到 new_prefix
+pub fn relocate(
+    db: &DbPool,
+    old_prefix: &str,
+    new_prefix: &str,
+    dry_run: bool,
+    verify_content: bool,
+) -> AppResult<RelocateReport> {
+    if old_prefix.trim_end_matches(is_separator).is_empty() || new_prefix.trim_end_matches(is_separator).is_empty() {
+        return Err(AppError::invalid_input("新旧路径前缀都不能为空"));
+    }
+
+    let states = db.with(|db| db.get_local_file_states())?;
+    let mut candidates: Vec<(&String, &LocalFileState, String)> = states
+        .iter()
+        .filter_map(|(path, state)| {
+            substitute_prefix(path, old_prefix, new_prefix)
+                .filter(|new_path| new_path != path)
+                .map(|new_path| (path, state, new_path))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.cmp(b.0));
+
+    let owners: HashMap<&str, i64> = states.iter().map(|(path, state)| (path.as_str(), state.id)).collect();
+    let mut report = RelocateReport { dry_run, ..Default::default() };
+    let mut relocations = Vec::new();
+    for (old_path, state, new_path) in candidates {
+        let track = RelocatedTrack { track_id: state.id, old_path: old_path.clone(), new_path };
+        let file = Path::new(&track.new_path);
+
+        if owners.get(track.new_path.as_str()).is_some_and(|&owner| owner != state.id) {
+            report.conflicts.push(track);
+            continue;
+        }
+        if !file.exists() {
+            report.still_missing.push(track);
+            continue;
+        }
+
+        let file_state = if verify_content {
+            match read_file_state(file) {
+                Ok((_, _, ref hash)) if state.hash.as_ref().is_some_and(|stored| stored != hash) => {
+                    report.content_mismatch.push(track);
+                    continue;
+                }
+                Ok(file_state) => Some(file_state),
+                Err(e) => {
+                    log::warn!("⚠️ 读取文件失败 {}: {}", track.new_path, e);
+                    report.still_missing.push(track);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        relocations.push(PathRelocation { track_id: state.id, new_path: track.new_path.clone(), file_state });
+        report.fixed.push(track);
+    }
+
+    if !dry_run && !relocations.is_empty() {
+        report.updated = db.with(|db| db.relocate_tracks(&relocations))?;
+    }
+    log::info!(
+        "📂 路径迁移{} {} -> {}: 找到 {}，仍缺失 {}，冲突 {}，内容不一致 {}",
+        if dry_run { "预览" } else { "" },
+        old_prefix,
+        new_prefix,
+        report.fixed.len(),
+        report.still_missing.len(),
+        report.conflicts.len(),
+        report.content_mismatch.len()
+    );
+    Ok(report)
+}
+
+/// 列出文件已不存在的本地曲目（按路径排序）
+pub fn find_missing(db: &DbPool) -> AppResult<Vec<MissingTrack>> {
+    let states = db.with(|db| db.get_local_file_states())?;
+    let mut missing: Vec<MissingTrack> = states
+        .into_iter()
+        .filter(|(path, _)| !Path::new(path).exists())
+        .map(|(path, state)| MissingTrack { track_id: state.id, path })
+        .collect();
+    missing.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::player::Track;
+
+    #[test]
+    fn test_substitute_prefix() {
+        assert_eq!(substitute_prefix("/Music/a/b.flac", "/Music", "/Audio/Music").as_deref(), Some("/Audio/Music/a/b.flac"));
+        assert_eq!(substitute_prefix("/Music/a/b.flac", "/Music/", "/Audio/Music/").as_deref(), Some("/Audio/Music/a/b.flac"));
+        // 只匹配完整的文件夹名
+        assert_eq!(substitute_prefix("/Musical/b.flac", "/Music", "/Audio"), None);
+        assert_eq!(substitute_prefix("/Other/Music/b.flac", "/Music", "/Audio"), None);
+        // 两种分隔符混用
+        assert_eq!(substitute_prefix("D:\\Music\\a\\b.flac", "D:/Music", "E:\\Audio").as_deref(), Some("E:\\Audio\\a\\b.flac"));
+        assert_eq!(substitute_prefix("D:/Music/a/b.flac", "D:\\Music\\", "E:/Audio").as_deref(), Some("E:/Audio/a/b.flac"));
+        assert_eq!(substitute_prefix("/Music", "/Music", "/Audio").as_deref(), Some("/Audio"));
+        assert_eq!(substitute_prefix("/Music/a.flac", "/", "/Audio"), None);
+    }
+
+    fn track(path: &Path) -> Track {
+        let mut track = Track::new(0, path.to_string_lossy().to_string());
+        track.title = Some("Song".to_string());
+        track
+    }
+
+    #[test]
+    fn test_relocate_moved_folder() {
+        let root = std::env::temp_dir().join(format!("windchime-relocate-{}", uuid::Uuid::new_v4()));
+        let (old_dir, new_dir) = (root.join("Music"), root.join("Audio").join("Music"));
+        std::fs::create_dir_all(new_dir.join("Album")).unwrap();
+        for (name, content) in [("a.flac", "a"), ("b.flac", "b"), ("changed.flac", "new content")] {
+            std::fs::write(new_dir.join("Album").join(name), content).unwrap();
+        }
+
+        let pool = DbPool::single(Database::new(":memory:").unwrap());
+        let insert = |path: &Path| pool.with(|db| db.insert_track(&track(path))).unwrap();
+        let moved = insert(&old_dir.join("Album").join("a.flac"));
+        let gone = insert(&old_dir.join("Album").join("gone.flac"));
+        let changed = insert(&old_dir.join("Album").join("changed.flac"));
+        let rescanned = insert(&old_dir.join("Album").join("b.flac"));
+        // 移动后已经重新扫描过一次，新路径已有记录
+        let duplicate = insert(&new_dir.join("Album").join("b.flac"));
+        let playlist = pool.with(|db| db.create_playlist("Mix")).unwrap();
+        pool.with(|db| db.add_track_to_playlist(playlist, moved)).unwrap();
+        let old_changed = old_dir.join("Album").join("changed.flac").to_string_lossy().to_string();
+        pool.with(|db| db.update_file_state(&old_changed, Some(1), 11, Some("0123456789abcdef"))).unwrap();
+
+        let old_prefix = old_dir.to_string_lossy().to_string();
+        let new_prefix = new_dir.to_string_lossy().to_string();
+        let ids = |tracks: &[RelocatedTrack]| tracks.iter().map(|t| t.track_id).collect::<Vec<_>>();
+
+        // 预览不修改数据库
+        let preview = relocate(&pool, &old_prefix, &new_prefix, true, true).unwrap();
+        assert_eq!(ids(&preview.fixed), [moved]);
+        assert_eq!(ids(&preview.still_missing), [gone]);
+        assert_eq!(ids(&preview.conflicts), [rescanned]);
+        assert_eq!(ids(&preview.content_mismatch), [changed]);
+        assert_eq!(preview.updated, 0);
+        let missing = find_missing(&pool).unwrap();
+        assert_eq!(missing.iter().map(|t| t.track_id).collect::<Vec<_>>(), [moved, rescanned, changed, gone]);
+
+        // 不检查内容时内容变化的曲目也会迁移
+        let report = relocate(&pool, &old_prefix, &new_prefix, false, false).unwrap();
+        assert_eq!(ids(&report.fixed), [moved, changed]);
+        assert_eq!(report.updated, 2);
+        let paths = pool.with(|db| db.get_track_paths(&[moved, duplicate])).unwrap();
+        assert_eq!(paths[0], (moved, report.fixed[0].new_path.clone()));
+        assert!(Path::new(&paths[0].1).exists());
+        assert_eq!(paths[1].1, new_dir.join("Album").join("b.flac").to_string_lossy());
+        // 曲目ID不变，歌单成员保留
+        let in_playlist: Vec<i64> = pool.with(|db| db.get_playlist_tracks(playlist)).unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(in_playlist, [moved]);
+        assert_eq!(find_missing(&pool).unwrap().iter().map(|t| t.track_id).collect::<Vec<_>>(), [rescanned, gone]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_relocate_updates_file_state_when_verified() {
+        let root = std::env::temp_dir().join(format!("windchime-relocate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("new")).unwrap();
+        let new_file = root.join("new").join("a.flac");
+        std::fs::write(&new_file, "audio").unwrap();
+
+        let pool = DbPool::single(Database::new(":memory:").unwrap());
+        let old_file = root.join("old").join("a.flac");
+        let id = pool.with(|db| db.insert_track(&track(&old_file))).unwrap();
+
+        let report = relocate(&pool, &root.join("old").to_string_lossy(), &root.join("new").to_string_lossy(), false, true).unwrap();
+        assert_eq!(report.updated, 1);
+        let states = pool.with(|db| db.get_local_file_states()).unwrap();
+        let state = &states[new_file.to_string_lossy().as_ref()];
+        assert_eq!(state.id, id);
+        assert_eq!(state.size, Some(5));
+        assert_eq!(state.hash.as_deref(), Some(file_md5(&new_file).unwrap().as_str()));
+
+        assert!(relocate(&pool, "/", "/new", true, false).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
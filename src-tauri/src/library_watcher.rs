@@ -0,0 +1,208 @@
+// 音乐文件夹监听 - 文件变化后自动增量更新曲库
+//
+// notify 的原始事件先经过防抖：同一路径在一段时间内没有新事件、
+// 且文件大小在两次检查之间不再变化（复制已完成）后，才交给 Library 处理。
+
+use crate::library::is_audio_file;
+use anyhow::Result;
+use crossbeam_channel::{unbounded, RecvTimeoutError, Sender};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 路径在这段时间内没有新事件才处理
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+/// 防抖线程检查待处理路径的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+struct PendingPath {
+    last_event: Instant,
+    /// 上次检查时的文件大小；None 表示还未检查过
+    last_size: Option<Option<u64>>,
+}
+
+/// 事件防抖与文件大小稳定性检查
+#[derive(Default)]
+pub struct ChangeDebouncer {
+    pending: HashMap<PathBuf, PendingPath>,
+}
+
+impl ChangeDebouncer {
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        self.pending
+            .entry(path)
+            .and_modify(|p| p.last_event = now)
+            .or_insert(PendingPath { last_event: now, last_size: None });
+    }
+
+    /// 取出已稳定的路径
+    ///
+    /// size_of 返回文件大小，路径不存在或是目录时返回 None（删除和目录无需等待大小稳定）。
+    /// 文件大小与上次检查不同时说明仍在写入，继续等待一个静默期。
+    pub fn take_ready(&mut self, now: Instant, size_of: impl Fn(&Path) -> Option<u64>) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.pending.retain(|path, pending| {
+            if now.duration_since(pending.last_event) < QUIET_PERIOD {
+                return true;
+            }
+            let size = size_of(path);
+            if size.is_some() && pending.last_size != Some(size) {
+                pending.last_size = Some(size);
+                pending.last_event = now;
+                return true;
+            }
+            ready.push(path.clone());
+            false
+        });
+        ready.sort();
+        ready
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// 需要监听的根目录：去掉已被上级目录覆盖的子目录（递归监听已包含它们）
+pub fn watch_roots(folders: &[String]) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = folders.iter().map(PathBuf::from).collect();
+    candidates.sort();
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for folder in candidates {
+        if roots.iter().any(|root| folder.starts_with(root)) {
+            continue;
+        }
+        roots.push(folder);
+    }
+    roots
+}
+
+/// 事件是否需要处理：音频文件、目录（整个文件夹复制/移动/删除），其他扩展名忽略
+fn is_relevant(path: &Path) -> bool {
+    is_audio_file(path) || path.is_dir() || (!path.exists() && path.extension().is_none())
+}
+
+/// 文件夹监听器，被 drop 时停止监听
+pub struct LibraryWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl LibraryWatcher {
+    /// 开始监听，稳定后的变化路径批量发回 Library
+    pub fn start(folders: &[String], change_tx: Sender<Vec<PathBuf>>) -> Result<Self> {
+        let (event_tx, event_rx) = unbounded::<PathBuf>();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) => {
+                    if event.kind.is_access() {
+                        return;
+                    }
+                    for path in event.paths {
+                        let _ = event_tx.send(path);
+                    }
+                }
+                Err(e) => log::warn!("文件监听错误: {}", e),
+            }
+        })?;
+
+        let mut watched = 0;
+        for root in watch_roots(folders).into_iter().filter(|r| r.is_dir()) {
+            match watcher.watch(&root, RecursiveMode::Recursive) {
+                Ok(()) => watched += 1,
+                Err(e) => log::warn!("无法监听文件夹 {}: {}", root.display(), e),
+            }
+        }
+        log::info!("👀 开始监听 {} 个音乐文件夹", watched);
+
+        // watcher 被 drop 后 event_tx 随回调一起释放，防抖线程随之退出
+        thread::spawn(move || {
+            let mut debouncer = ChangeDebouncer::default();
+            let mut last_check = Instant::now();
+            loop {
+                match event_rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(path) => {
+                        if is_relevant(&path) {
+                            debouncer.record(path, Instant::now());
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                if debouncer.is_empty() || last_check.elapsed() < POLL_INTERVAL {
+                    continue;
+                }
+                last_check = Instant::now();
+                let ready = debouncer.take_ready(Instant::now(), |path| {
+                    std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len())
+                });
+                if !ready.is_empty() {
+                    log::info!("📂 检测到 {} 个文件变化", ready.len());
+                    if change_tx.send(ready).is_err() {
+                        break;
+                    }
+                }
+            }
+            log::info!("文件夹监听已停止");
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_period_and_stable_size() {
+        let start = Instant::now();
+        let mut debouncer = ChangeDebouncer::default();
+        let file = PathBuf::from("/music/new/a.flac");
+        let deleted = PathBuf::from("/music/old/b.flac");
+        debouncer.record(file.clone(), start);
+        debouncer.record(deleted.clone(), start);
+
+        // 静默期内不处理
+        assert!(debouncer.take_ready(start + Duration::from_secs(1), |_| Some(10)).is_empty());
+
+        // 删除的文件静默期后即可处理，新文件需要再确认一次大小
+        let size_of = |path: &Path| if path == file { Some(10) } else { None };
+        assert_eq!(debouncer.take_ready(start + QUIET_PERIOD, size_of), vec![deleted]);
+
+        // 仍在复制：大小变化，继续等待
+        let later = start + QUIET_PERIOD * 2;
+        assert!(debouncer.take_ready(later, |_| Some(20)).is_empty());
+        assert_eq!(debouncer.take_ready(later + QUIET_PERIOD, |_| Some(20)), vec![file]);
+        assert!(debouncer.is_empty());
+    }
+
+    #[test]
+    fn test_new_events_restart_quiet_period() {
+        let start = Instant::now();
+        let mut debouncer = ChangeDebouncer::default();
+        let path = PathBuf::from("/music/a");
+        debouncer.record(path.clone(), start);
+        debouncer.record(path.clone(), start + Duration::from_secs(1));
+        assert!(debouncer.take_ready(start + QUIET_PERIOD, |_| None).is_empty());
+        assert_eq!(debouncer.take_ready(start + Duration::from_secs(1) + QUIET_PERIOD, |_| None), vec![path]);
+    }
+
+    #[test]
+    fn test_watch_roots_drops_nested_folders() {
+        let folders = vec![
+            "/music/a/cd1".to_string(),
+            "/music/a".to_string(),
+            "/music/ab".to_string(),
+            "/other".to_string(),
+        ];
+        assert_eq!(
+            watch_roots(&folders),
+            vec![PathBuf::from("/music/a"), PathBuf::from("/music/ab"), PathBuf::from("/other")]
+        );
+    }
+}
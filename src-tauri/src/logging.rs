@@ -0,0 +1,337 @@
+// 日志
+//
+// 启动时初始化 tracing（各模块的 log:: 宏通过 tracing-log 转发进来），日志同时写到：
+// - 控制台
+// - 日志文件：windchime.log 超过大小上限后依次改名为 windchime.log.1、.2……，超出数量的旧文件删除
+// - 内存中最近的日志行，供诊断页面查看
+//
+// 日志级别可以在运行时修改，报告问题时打开 debug 日志无需重启。
+// 播放器和扫描中耗时的操作包在 span 里，span 结束时输出耗时。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::db::Database;
+
+/// 日志设置的设置键（JSON）
+pub const SETTING_LOGGING_CONFIG: &str = "logging.config";
+
+/// 正在写入的日志文件名
+pub const LOG_FILE_NAME: &str = "windchime.log";
+
+/// 可用的日志级别
+pub const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// 内存中保留的最近日志行数
+const RECENT_CAPACITY: usize = 2000;
+
+/// 日志设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// 本程序的日志级别：error / warn / info / debug / trace（依赖库固定为 warn）
+    pub level: String,
+    /// 日志目录，为空时使用应用数据目录下的 logs
+    pub file_path: Option<PathBuf>,
+    /// 单个日志文件的大小上限
+    pub max_file_size_mb: u64,
+    /// 保留的日志文件数量（包括正在写入的文件）
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            file_path: None,
+            max_file_size_mb: 10,
+            max_files: 5,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_level(&self.level)?;
+        if !(1..=1024).contains(&self.max_file_size_mb) {
+            return Err("单个日志文件大小必须在 1-1024 MB 之间".to_string());
+        }
+        if !(1..=50).contains(&self.max_files) {
+            return Err("日志文件数量必须在 1-50 之间".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_level(level: &str) -> Result<(), String> {
+    if LOG_LEVELS.contains(&level) {
+        Ok(())
+    } else {
+        Err(format!("无效的日志级别: {}，可选 {}", level, LOG_LEVELS.join(" / ")))
+    }
+}
+
+/// 读取保存的日志设置，没有时使用默认设置
+pub fn load_config(db: &Database) -> LoggingConfig {
+    db.get_setting(SETTING_LOGGING_CONFIG)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// 按大小滚动的日志文件
+struct RollingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RollingFile {
+    fn open(dir: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut rolling = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            max_files,
+            file: Self::open_file(&dir.join(LOG_FILE_NAME))?,
+            size: 0,
+        };
+        rolling.size = rolling.file.metadata()?.len();
+        // 保留数量调小后删除多出来的旧文件
+        let mut index = max_files;
+        while rolling.path(index).exists() {
+            fs::remove_file(rolling.path(index))?;
+            index += 1;
+        }
+        Ok(rolling)
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// 第 index 个日志文件，0 为正在写入的文件
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(LOG_FILE_NAME)
+        } else {
+            self.dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = self.path(self.max_files - 1);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..self.max_files - 1).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(from, self.path(index + 1))?;
+            }
+        }
+        self.file = Self::open_file(&self.path(0))?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// 日志文件和最近日志行
+struct LogSink {
+    file: Option<RollingFile>,
+    recent: VecDeque<String>,
+}
+
+impl LogSink {
+    fn write(&mut self, buf: &[u8]) {
+        if let Some(file) = &mut self.file {
+            // 写文件失败时不能再记日志，只能丢弃
+            let _ = file.write(buf);
+        }
+        for line in String::from_utf8_lossy(buf).lines().filter(|line| !line.is_empty()) {
+            if self.recent.len() == RECENT_CAPACITY {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(line.to_string());
+        }
+    }
+
+    fn recent(&self, n: usize) -> Vec<String> {
+        self.recent.iter().skip(self.recent.len().saturating_sub(n)).cloned().collect()
+    }
+}
+
+static LOG_SINK: Lazy<Mutex<LogSink>> = Lazy::new(|| {
+    Mutex::new(LogSink {
+        file: None,
+        recent: VecDeque::with_capacity(RECENT_CAPACITY),
+    })
+});
+
+/// 启动时的默认日志目录
+static DEFAULT_LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 运行时修改日志级别
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// 写入 LOG_SINK 的 writer，每条日志写一次
+struct SinkWriter;
+
+impl Write for SinkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        LOG_SINK.lock().write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 本程序按设置的级别输出，依赖库只输出警告和错误
+fn filter_for(level: &str) -> EnvFilter {
+    EnvFilter::new(format!("warn,{}={}", env!("CARGO_CRATE_NAME"), level))
+}
+
+/// 初始化日志（只调用一次），设置了 RUST_LOG 时以它为准
+pub fn init(config: &LoggingConfig, default_dir: PathBuf) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| filter_for(&config.level));
+    let (filter, handle) = reload::Layer::new(filter);
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_span_events(FmtSpan::CLOSE))
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(|| SinkWriter),
+        )
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("日志初始化失败: {}", e);
+        return;
+    }
+    let _ = FILTER_HANDLE.set(handle);
+    let _ = DEFAULT_LOG_DIR.set(default_dir);
+    configure_file(config);
+}
+
+/// 应用保存的日志设置（级别和日志文件）
+pub fn apply(config: &LoggingConfig) {
+    if let Err(e) = set_level(&config.level) {
+        log::warn!("⚠️ {}", e);
+    }
+    configure_file(config);
+}
+
+/// 修改日志级别，立即生效
+pub fn set_level(level: &str) -> Result<(), String> {
+    validate_level(level)?;
+    let handle = FILTER_HANDLE.get().ok_or("日志尚未初始化")?;
+    handle.reload(filter_for(level)).map_err(|e| format!("修改日志级别失败: {}", e))?;
+    log::info!("📝 日志级别已改为 {}", level);
+    Ok(())
+}
+
+/// 按设置重新打开日志文件
+fn configure_file(config: &LoggingConfig) {
+    let Some(dir) = config.file_path.clone().or_else(|| DEFAULT_LOG_DIR.get().cloned()) else {
+        return;
+    };
+    // 打开文件时不持有 LOG_SINK 的锁，失败时要记日志
+    let max_bytes = config.max_file_size_mb * 1024 * 1024;
+    match RollingFile::open(&dir, max_bytes, config.max_files.max(1)) {
+        Ok(file) => {
+            LOG_SINK.lock().file = Some(file);
+            log::info!("📝 日志文件: {}", dir.join(LOG_FILE_NAME).display());
+        }
+        Err(e) => log::warn!("⚠️ 无法打开日志文件 {}: {}", dir.display(), e),
+    }
+}
+
+/// 当前的日志文件（由新到旧），未写入文件时为空
+pub fn log_files() -> Vec<PathBuf> {
+    let sink = LOG_SINK.lock();
+    let Some(file) = &sink.file else {
+        return Vec::new();
+    };
+    (0..file.max_files).map(|index| file.path(index)).filter(|path| path.exists()).collect()
+}
+
+/// 最近的 n 行日志（由旧到新）
+pub fn recent_lines(n: usize) -> Vec<String> {
+    LOG_SINK.lock().recent(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_file_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("windchime-logs-{}", uuid::Uuid::new_v4()));
+        let mut file = RollingFile::open(&dir, 100, 3).unwrap();
+        for i in 0..20 {
+            file.write(format!("{:039}\n", i).as_bytes()).unwrap();
+        }
+        drop(file);
+
+        // 每个文件最多两行，只保留最新的3个文件
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap().lines().count(), 2);
+        assert!(dir.join("windchime.log.1").exists());
+        assert!(dir.join("windchime.log.2").exists());
+        assert!(!dir.join("windchime.log.3").exists());
+        let newest = fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap();
+        assert!(newest.ends_with(&format!("{:039}\n", 19)));
+
+        // 保留数量调小后多出来的文件被删除
+        RollingFile::open(&dir, 100, 1).unwrap();
+        assert!(!dir.join("windchime.log.1").exists());
+        assert!(dir.join(LOG_FILE_NAME).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recent_lines() {
+        let mut sink = LogSink { file: None, recent: VecDeque::new() };
+        for i in 0..RECENT_CAPACITY + 5 {
+            sink.write(format!("line {}\n", i).as_bytes());
+        }
+        sink.write(b"multi\nline event\n");
+
+        assert_eq!(sink.recent.len(), RECENT_CAPACITY);
+        assert_eq!(sink.recent(3), vec![format!("line {}", RECENT_CAPACITY + 4), "multi".to_string(), "line event".to_string()]);
+        assert_eq!(sink.recent(usize::MAX).len(), RECENT_CAPACITY);
+    }
+
+    #[test]
+    fn test_validate_config() {
+        assert!(LoggingConfig::default().validate().is_ok());
+        assert!(LoggingConfig { level: "verbose".to_string(), ..Default::default() }.validate().is_err());
+        assert!(LoggingConfig { max_files: 0, ..Default::default() }.validate().is_err());
+        assert!(LoggingConfig { max_file_size_mb: 0, ..Default::default() }.validate().is_err());
+    }
+}
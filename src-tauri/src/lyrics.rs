@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
-use crate::db::LyricLine;
+use crate::db::{LyricLine, LyricWord};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedLyrics {
@@ -12,6 +12,29 @@ pub struct ParsedLyrics {
     pub metadata: HashMap<String, String>,
 }
 
+/// 当前正在唱的词（行序号、词序号）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CurrentWord {
+    pub line_index: usize,
+    pub word_index: usize,
+}
+
+/// LRC解析选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LrcParseOptions {
+    /// 把同时间戳的两行、或用分隔符写在同一行的原文和译文合并为一行
+    pub merge_translations: bool,
+}
+
+impl Default for LrcParseOptions {
+    fn default() -> Self {
+        Self { merge_translations: true }
+    }
+}
+
+/// 同一行中分隔原文和译文的分隔符
+const TRANSLATION_DELIMITERS: [&str; 3] = [" / ", " | ", "｜"];
+
 pub struct LyricsParser;
 
 impl LyricsParser {
@@ -21,6 +44,11 @@ impl LyricsParser {
 
     /// 解析LRC格式歌词文件（支持双语歌词）
     pub fn parse_lrc(&self, content: &str) -> Result<ParsedLyrics> {
+        self.parse_lrc_with_options(content, LrcParseOptions::default())
+    }
+
+    /// 按选项解析LRC歌词，关闭合并时按原始行返回
+    pub fn parse_lrc_with_options(&self, content: &str, options: LrcParseOptions) -> Result<ParsedLyrics> {
         let mut lines = Vec::new();
         let mut metadata = HashMap::new();
         
@@ -56,12 +84,13 @@ impl LyricsParser {
                 };
 
                 let timestamp_ms = (minutes * 60 + seconds) * 1000 + milliseconds;
-                let text = captures.get(4).unwrap().as_str().trim().to_string();
+                let (text, words) = parse_enhanced_words(captures.get(4).unwrap().as_str(), timestamp_ms);
 
                 lines.push(LyricLine {
                     timestamp_ms,
                     text,
                     translation: None, // 稍后会处理翻译
+                    words,
                 });
             }
             // 尝试解析元数据
@@ -72,11 +101,15 @@ impl LyricsParser {
             }
         }
 
-        // 按时间戳排序
+        // 按时间戳排序（稳定排序，同时间戳的行保持文件中的先后顺序）
         lines.sort_by_key(|line| line.timestamp_ms);
+
+        if !options.merge_translations {
+            return Ok(ParsedLyrics { lines, metadata });
+        }
         
         // 🌐 处理双语歌词（网易云/QQ音乐格式）
-        // 如果两行有相同的时间戳，第二行作为翻译
+        // 如果两行有相同的时间戳，第二行作为翻译；否则尝试拆分“原文 / 译文”
         let mut merged_lines = Vec::new();
         let mut i = 0;
         while i < lines.len() {
@@ -88,6 +121,12 @@ impl LyricsParser {
                 current.translation = Some(lines[i + 1].text.clone());
                 i += 2; // 跳过下一行
             } else {
+                if current.words.is_none() {
+                    if let Some((original, translation)) = split_inline_translation(&current.text) {
+                        current.text = original;
+                        current.translation = Some(translation);
+                    }
+                }
                 i += 1;
             }
             
@@ -125,7 +164,7 @@ impl LyricsParser {
             Ok(content) => return Ok(content),
             Err(_) => {
                 // UTF-8 失败，尝试读取原始字节并处理编码
-                println!("UTF-8读取失败，尝试其他编码...");
+                log::debug!("UTF-8读取失败，尝试其他编码...");
             }
         }
         
@@ -145,7 +184,7 @@ impl LyricsParser {
         for encoding_name in &encodings {
             if let Ok(content) = self.try_decode_with_encoding(&bytes, encoding_name) {
                 if !content.trim().is_empty() && self.is_valid_text(&content) {
-                    println!("成功使用 {} 编码读取文件", encoding_name);
+                    log::debug!("成功使用 {} 编码读取文件", encoding_name);
                     return Ok(content);
                 }
             }
@@ -154,7 +193,7 @@ impl LyricsParser {
         // 如果所有编码都失败，最后尝试lossy UTF-8解析
         match String::from_utf8_lossy(&bytes).into_owned() {
             content if !content.trim().is_empty() => {
-                println!("使用lossy UTF-8解析");
+                log::debug!("使用lossy UTF-8解析");
                 Ok(content)
             },
             _ => Err(anyhow::anyhow!("无法以任何已知编码读取文件"))
@@ -263,6 +302,7 @@ impl LyricsParser {
                     timestamp_ms: (index as u64) * 3000, // 假设每行3秒
                     text: line.to_string(),
                     translation: None,
+                    words: None,
                 });
             }
         }
@@ -300,6 +340,7 @@ impl LyricsParser {
                             timestamp_ms,
                             text,
                             translation: None,
+                            words: None,
                         });
                     }
                 }
@@ -354,6 +395,7 @@ impl LyricsParser {
                                 timestamp_ms,
                                 text: clean_text,
                                 translation: None,
+                                words: None,
                             });
                         }
                     }
@@ -410,6 +452,7 @@ impl LyricsParser {
                             timestamp_ms,
                             text,
                             translation: None,
+                            words: None,
                         });
                     }
                 }
@@ -646,19 +689,23 @@ impl LyricsParser {
             result.push('\n');
         }
 
-        // 添加歌词行
+        // 添加歌词行（逐字歌词按增强LRC格式输出每个词的时间）
         for line in &parsed.lines {
-            let minutes = line.timestamp_ms / 60000;
-            let seconds = (line.timestamp_ms % 60000) / 1000;
-            let milliseconds = line.timestamp_ms % 1000;
-            
-            result.push_str(&format!(
-                "[{:02}:{:02}.{:02}]{}\n",
-                minutes,
-                seconds,
-                milliseconds / 10, // 转换为厘秒
-                line.text
-            ));
+            result.push_str(&format!("[{}]", format_timestamp(line.timestamp_ms)));
+            match &line.words {
+                Some(words) => {
+                    for word in words {
+                        result.push_str(&format!("<{}>{}", format_timestamp(word.timestamp_ms), word.text));
+                    }
+                }
+                None => result.push_str(&line.text),
+            }
+            result.push('\n');
+
+            // 翻译按同时间戳的下一行输出
+            if let Some(translation) = &line.translation {
+                result.push_str(&format!("[{}]{}\n", format_timestamp(line.timestamp_ms), translation));
+            }
         }
 
         result
@@ -675,6 +722,109 @@ impl LyricsParser {
             .map(|(index, _)| index)
     }
 
+    /// 获取指定时间点正在唱的词（只对逐字歌词有效）
+    ///
+    /// 位置在当前行第一个词之前，或已过最后的结束标记（空词）时返回None
+    pub fn get_current_word(&self, lines: &[LyricLine], position_ms: u64) -> Option<CurrentWord> {
+        let line_index = self.get_current_line(lines, position_ms)?;
+        let words = lines[line_index].words.as_ref()?;
+        let word_index = words.iter().rposition(|word| word.timestamp_ms <= position_ms)?;
+        if words[word_index].text.trim().is_empty() {
+            return None;
+        }
+        Some(CurrentWord { line_index, word_index })
+    }
+}
+
+/// 文字的大致书写系统，用于判断一行中分隔符两边是否为不同语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Kana,
+    Hangul,
+    Han,
+    Other,
+}
+
+fn script_of(text: &str) -> Script {
+    let has = |range: &[(char, char)]| text.chars().any(|c| range.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)));
+    if has(&[('\u{3040}', '\u{30FF}'), ('\u{31F0}', '\u{31FF}')]) {
+        Script::Kana
+    } else if has(&[('\u{AC00}', '\u{D7AF}'), ('\u{1100}', '\u{11FF}')]) {
+        Script::Hangul
+    } else if has(&[('\u{4E00}', '\u{9FFF}'), ('\u{3400}', '\u{4DBF}')]) {
+        Script::Han
+    } else {
+        Script::Other
+    }
+}
+
+/// 拆分写在同一行的“原文 / 译文”
+///
+/// 只在分隔符恰好出现一次、两边都有内容且书写系统不同时拆分，
+/// 避免把 "Love / Hate" 这类歌词本身的斜杠当作分隔符
+fn split_inline_translation(text: &str) -> Option<(String, String)> {
+    TRANSLATION_DELIMITERS.iter().find_map(|delimiter| {
+        if text.matches(delimiter).count() != 1 {
+            return None;
+        }
+        let (original, translation) = text.split_once(delimiter)?;
+        let (original, translation) = (original.trim(), translation.trim());
+        if original.is_empty() || translation.is_empty() || script_of(original) == script_of(translation) {
+            return None;
+        }
+        Some((original.to_string(), translation.to_string()))
+    })
+}
+
+/// 时间标签文本：mm:ss.xx，毫秒不是10的倍数时保留3位
+fn format_timestamp(timestamp_ms: u64) -> String {
+    let minutes = timestamp_ms / 60000;
+    let seconds = (timestamp_ms % 60000) / 1000;
+    let milliseconds = timestamp_ms % 1000;
+    if milliseconds % 10 == 0 {
+        format!("{:02}:{:02}.{:02}", minutes, seconds, milliseconds / 10) // 厘秒
+    } else {
+        format!("{:02}:{:02}.{:03}", minutes, seconds, milliseconds)
+    }
+}
+
+/// 解析行内的 <mm:ss.xx> 逐字标签，返回行文本和逐字时间
+///
+/// 没有标签时返回None；第一个标签前的文本算作从行时间开始的词；
+/// 格式不对的标签按普通文本保留
+fn parse_enhanced_words(raw: &str, line_timestamp_ms: u64) -> (String, Option<Vec<LyricWord>>) {
+    let word_regex = Regex::new(r"<(\d{1,2}):(\d{2})(?:\.(\d{1,3}))?>").unwrap();
+    if !word_regex.is_match(raw) {
+        return (raw.trim().to_string(), None);
+    }
+
+    let mut words = Vec::new();
+    let mut current = (line_timestamp_ms, 0);
+    for captures in word_regex.captures_iter(raw) {
+        let tag = captures.get(0).unwrap();
+        let text = &raw[current.1..tag.start()];
+        if current.1 > 0 || !text.trim().is_empty() {
+            words.push(LyricWord { timestamp_ms: current.0, text: text.to_string() });
+        }
+
+        let minutes: u64 = captures[1].parse().unwrap_or(0);
+        let seconds: u64 = captures[2].parse().unwrap_or(0);
+        let milliseconds = captures.get(3)
+            .map(|ms| format!("{:0<3}", ms.as_str()).parse().unwrap_or(0))
+            .unwrap_or(0);
+        current = ((minutes * 60 + seconds) * 1000 + milliseconds, tag.end());
+    }
+    words.push(LyricWord { timestamp_ms: current.0, text: raw[current.1..].to_string() });
+
+    // 行尾的空白不算作词，但保留结束标记（<时间>后无文字）
+    if let Some(last) = words.last_mut() {
+        if !last.text.is_empty() && last.text.trim().is_empty() {
+            last.text.clear();
+        }
+    }
+
+    let text = words.iter().map(|word| word.text.as_str()).collect::<String>().trim().to_string();
+    (text, Some(words))
 }
 
 #[cfg(test)]
@@ -703,13 +853,152 @@ mod tests {
         assert_eq!(result.lines[0].text, "First line of lyrics");
     }
 
+    #[test]
+    fn test_parse_enhanced_lrc_mixed_with_plain_lines() {
+        let parser = LyricsParser::new();
+        let content = "[00:10.00]<00:10.00>Hello <00:10.50>wor<00:10.80>ld<00:11.20>\n\
+                       [00:12.00]Plain line\n\
+                       [00:14.00]Lead <00:14.5>in <01:02>\n";
+        let result = parser.parse_lrc(content).unwrap();
+        assert_eq!(result.lines.len(), 3);
+
+        let words = result.lines[0].words.as_ref().unwrap();
+        assert_eq!(result.lines[0].text, "Hello world");
+        assert_eq!(
+            words.iter().map(|w| (w.timestamp_ms, w.text.as_str())).collect::<Vec<_>>(),
+            vec![(10000, "Hello "), (10500, "wor"), (10800, "ld"), (11200, "")]
+        );
+
+        assert_eq!(result.lines[1].text, "Plain line");
+        assert!(result.lines[1].words.is_none());
+
+        // 第一个标签前的文本从行时间开始；单位数厘秒补齐为毫秒
+        let words = result.lines[2].words.as_ref().unwrap();
+        assert_eq!(result.lines[2].text, "Lead in");
+        assert_eq!(
+            words.iter().map(|w| (w.timestamp_ms, w.text.as_str())).collect::<Vec<_>>(),
+            vec![(14000, "Lead "), (14500, "in "), (62000, "")]
+        );
+    }
+
+    #[test]
+    fn test_malformed_word_tags_stay_as_text() {
+        let parser = LyricsParser::new();
+        let result = parser.parse_lrc("[00:01.00]a <0x:12.00>b <00:02.00\n[00:03.00]<abc>c").unwrap();
+        assert_eq!(result.lines[0].text, "a <0x:12.00>b <00:02.00");
+        assert!(result.lines[0].words.is_none());
+        assert_eq!(result.lines[1].text, "<abc>c");
+        assert!(result.lines[1].words.is_none());
+    }
+
+    #[test]
+    fn test_format_enhanced_lrc_round_trip() {
+        let parser = LyricsParser::new();
+        let content = "[00:10.00]<00:10.00>Hello <00:10.50>wor<00:10.805>ld<00:11.20>\n[00:12.34]Plain line\n";
+        let parsed = parser.parse_lrc(content).unwrap();
+        let formatted = parser.format_as_lrc(&parsed);
+        assert_eq!(formatted, content);
+
+        let reparsed = parser.parse_lrc(&formatted).unwrap();
+        assert_eq!(reparsed.lines[0].words, parsed.lines[0].words);
+        assert_eq!(reparsed.lines[1].text, "Plain line");
+    }
+
+    #[test]
+    fn test_get_current_word() {
+        let parser = LyricsParser::new();
+        let parsed = parser.parse_lrc("[00:01.00]<00:01.50>One <00:02.00>two<00:03.00>\n[00:04.00]Plain").unwrap();
+        let lines = &parsed.lines;
+
+        assert_eq!(parser.get_current_word(lines, 500), None);
+        // 行已开始但第一个词还没到
+        assert_eq!(parser.get_current_word(lines, 1200), None);
+        assert_eq!(parser.get_current_word(lines, 1600), Some(CurrentWord { line_index: 0, word_index: 0 }));
+        assert_eq!(parser.get_current_word(lines, 2500), Some(CurrentWord { line_index: 0, word_index: 1 }));
+        // 过了结束标记
+        assert_eq!(parser.get_current_word(lines, 3500), None);
+        // 普通行没有逐字时间
+        assert_eq!(parser.get_current_word(lines, 4500), None);
+    }
+
+    #[test]
+    fn test_chinese_with_pinyin_same_timestamp_pairs() {
+        let parser = LyricsParser::new();
+        let content = "[ti:晴天]\n\
+                       [00:10.00]故事的小黄花\n\
+                       [00:10.00]gù shì de xiǎo huáng huā\n\
+                       [00:14.50]从出生那年就飘着\n\
+                       [00:14.50]cóng chū shēng nà nián jiù piāo zhe\n";
+        let result = parser.parse_lrc(content).unwrap();
+        assert_eq!(result.lines.len(), 2);
+        assert_eq!(result.lines[0].text, "故事的小黄花");
+        assert_eq!(result.lines[0].translation.as_deref(), Some("gù shì de xiǎo huáng huā"));
+        assert_eq!(result.lines[1].text, "从出生那年就飘着");
+        // 返回的是合并后的行序号
+        assert_eq!(parser.get_current_line(&result.lines, 15_000), Some(1));
+    }
+
+    #[test]
+    fn test_japanese_with_romaji_on_one_line() {
+        let parser = LyricsParser::new();
+        let content = "[00:05.00]君の名前を呼んだ / kimi no namae wo yonda\n\
+                       [00:09.00]夜空 | yozora\n\
+                       [00:12.00]僕らはまだ｜我们还\n";
+        let result = parser.parse_lrc(content).unwrap();
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].text, "君の名前を呼んだ");
+        assert_eq!(result.lines[0].translation.as_deref(), Some("kimi no namae wo yonda"));
+        assert_eq!(result.lines[1].text, "夜空");
+        assert_eq!(result.lines[1].translation.as_deref(), Some("yozora"));
+        // 日文（含假名）和中文翻译也能区分
+        assert_eq!(result.lines[2].text, "僕らはまだ");
+        assert_eq!(result.lines[2].translation.as_deref(), Some("我们还"));
+    }
+
+    #[test]
+    fn test_original_with_chinese_translation() {
+        let parser = LyricsParser::new();
+        let content = "[00:20.00]Love / Hate\n\
+                       [00:01.00]Hello darkness my old friend\n\
+                       [00:01.00]你好黑暗，我的老朋友\n\
+                       [00:04.00]I've come to talk with you again\n";
+        let result = parser.parse_lrc(content).unwrap();
+        assert_eq!(
+            result.lines.iter().map(|l| (l.text.as_str(), l.translation.as_deref())).collect::<Vec<_>>(),
+            vec![
+                ("Hello darkness my old friend", Some("你好黑暗，我的老朋友")),
+                ("I've come to talk with you again", None),
+                // 同一种文字的斜杠不拆分
+                ("Love / Hate", None),
+            ]
+        );
+
+        // 翻译输出为同时间戳的下一行
+        let formatted = parser.format_as_lrc(&result);
+        assert!(formatted.starts_with("[00:01.00]Hello darkness my old friend\n[00:01.00]你好黑暗，我的老朋友\n"));
+        assert_eq!(parser.parse_lrc(&formatted).unwrap().lines, result.lines);
+    }
+
+    #[test]
+    fn test_disable_translation_merging() {
+        let parser = LyricsParser::new();
+        let content = "[00:01.00]Hello\n[00:01.00]你好\n[00:02.00]世界 / world\n";
+        let options = LrcParseOptions { merge_translations: false };
+        let result = parser.parse_lrc_with_options(content, options).unwrap();
+        assert_eq!(
+            result.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>(),
+            vec!["Hello", "你好", "世界 / world"]
+        );
+        assert!(result.lines.iter().all(|l| l.translation.is_none()));
+    }
+
     #[test]
     fn test_get_current_line() {
         let parser = LyricsParser::new();
         let lines = vec![
-            LyricLine { timestamp_ms: 1000, text: "Line 1".to_string(), translation: None },
-            LyricLine { timestamp_ms: 3000, text: "Line 2".to_string(), translation: None },
-            LyricLine { timestamp_ms: 5000, text: "Line 3".to_string(), translation: None },
+            LyricLine { timestamp_ms: 1000, text: "Line 1".to_string(), translation: None, words: None },
+            LyricLine { timestamp_ms: 3000, text: "Line 2".to_string(), translation: None, words: None },
+            LyricLine { timestamp_ms: 5000, text: "Line 3".to_string(), translation: None, words: None },
         ];
 
         assert_eq!(parser.get_current_line(&lines, 500), None);
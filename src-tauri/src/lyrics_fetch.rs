@@ -0,0 +1,237 @@
+// 批量获取歌词
+//
+// 为曲库中缺少歌词（或只有临时歌词）的曲目补全歌词，进度通过 lyrics-fetch-progress 事件发送：
+// - 优先使用本地 .lrc 文件和内嵌歌词，找不到再请求 LrcApi
+// - 网络请求按每秒请求数限速，避免给服务器造成压力
+// - 每次网络尝试记录 last_lyrics_attempt，近期尝试过的曲目下次跳过，中断后可继续
+// - 可随时取消
+
+use crate::db::LyricsCandidate;
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::lyrics::LyricsParser;
+use crate::network_api::{NetworkApiService, NetworkConfig};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 默认每秒请求数
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 1.0;
+
+/// 每秒请求数范围
+pub const MIN_REQUESTS_PER_SECOND: f64 = 0.1;
+pub const MAX_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// 网络获取失败的曲目在这段时间内不再重试（秒）
+pub const RETRY_AFTER_SECS: i64 = 7 * 24 * 3600;
+
+/// 网络获取的歌词来源
+pub const SOURCE_ONLINE: &str = "online";
+
+/// 本地文件或内嵌歌词的来源
+pub const SOURCE_FILE: &str = "file";
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 批量获取进度（lyrics-fetch-progress 事件）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LyricsFetchProgress {
+    pub processed: usize,
+    pub found: usize,
+    pub failed: usize,
+    pub remaining: usize,
+    /// 任务已结束（完成或取消）
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
+/// 检查每秒请求数
+pub fn validate_rate(requests_per_second: f64) -> Result<f64, String> {
+    if !(MIN_REQUESTS_PER_SECOND..=MAX_REQUESTS_PER_SECOND).contains(&requests_per_second) {
+        return Err(format!(
+            "每秒请求数必须在 {} 到 {} 之间",
+            MIN_REQUESTS_PER_SECOND, MAX_REQUESTS_PER_SECOND
+        ));
+    }
+    Ok(requests_per_second)
+}
+
+/// 两次网络请求之间的间隔
+pub fn request_interval(requests_per_second: f64) -> Duration {
+    Duration::from_secs_f64(1.0 / requests_per_second)
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// 取消正在进行的批量获取
+pub fn cancel() {
+    if is_running() {
+        CANCELLED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 开始批量获取，返回待处理的曲目数
+pub fn start<F>(
+    db: Arc<DbPool>,
+    requests_per_second: f64,
+    network: NetworkConfig,
+    shutdown: &'static AtomicBool,
+    emit: F,
+) -> AppResult<usize>
+where
+    F: Fn(&LyricsFetchProgress) + Send + Sync + 'static,
+{
+    let requests_per_second = validate_rate(requests_per_second).map_err(AppError::InvalidInput)?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::invalid_input("批量获取歌词正在进行中"));
+    }
+
+    let attempted_before = chrono::Utc::now().timestamp() - RETRY_AFTER_SECS;
+    let candidates = match db.with(|db| db.tracks_missing_lyrics(attempted_before)) {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            RUNNING.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+    };
+
+    let total = candidates.len();
+    log::info!("📝 开始批量获取歌词: {} 首曲目, {} 次请求/秒", total, requests_per_second);
+    CANCELLED.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn(async move {
+        let progress = run(&db, candidates, requests_per_second, network, shutdown, &emit).await;
+        log::info!(
+            "📝 批量获取歌词结束: 处理 {}, 找到 {}, 失败 {}{}",
+            progress.processed,
+            progress.found,
+            progress.failed,
+            if progress.cancelled { "（已取消）" } else { "" }
+        );
+        RUNNING.store(false, Ordering::SeqCst);
+        emit(&progress);
+    });
+    Ok(total)
+}
+
+/// 单首曲目的处理结果
+enum FetchOutcome {
+    Found,
+    Failed,
+}
+
+async fn run<F>(
+    db: &Arc<DbPool>,
+    candidates: Vec<LyricsCandidate>,
+    requests_per_second: f64,
+    network: NetworkConfig,
+    shutdown: &AtomicBool,
+    emit: &F,
+) -> LyricsFetchProgress
+where
+    F: Fn(&LyricsFetchProgress),
+{
+    let service = NetworkApiService::with_config(network);
+    let mut limiter = tokio::time::interval(request_interval(requests_per_second));
+    limiter.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let mut progress = LyricsFetchProgress {
+        remaining: candidates.len(),
+        ..Default::default()
+    };
+    emit(&progress);
+
+    for candidate in candidates {
+        if CANCELLED.load(Ordering::Relaxed) || shutdown.load(Ordering::Relaxed) {
+            progress.cancelled = true;
+            break;
+        }
+
+        let outcome = match load_local(&candidate).await {
+            Some(content) => save(db, candidate.track_id, &content, SOURCE_FILE),
+            None => {
+                limiter.tick().await;
+                if CANCELLED.load(Ordering::Relaxed) || shutdown.load(Ordering::Relaxed) {
+                    progress.cancelled = true;
+                    break;
+                }
+                fetch_online(db, &service, &candidate).await
+            }
+        };
+
+        progress.processed += 1;
+        progress.remaining -= 1;
+        match outcome {
+            FetchOutcome::Found => progress.found += 1,
+            FetchOutcome::Failed => progress.failed += 1,
+        }
+        emit(&progress);
+    }
+
+    progress.finished = true;
+    progress
+}
+
+/// 查找同目录歌词文件和内嵌歌词
+async fn load_local(candidate: &LyricsCandidate) -> Option<String> {
+    let path = candidate.path.clone();
+    tokio::task::spawn_blocking(move || {
+        let parser = LyricsParser::new();
+        match parser.search_lyrics_comprehensive(&path) {
+            Ok(Some(parsed)) => Some(parser.format_as_lrc(&parsed)),
+            _ => None,
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+async fn fetch_online(db: &Arc<DbPool>, service: &NetworkApiService, candidate: &LyricsCandidate) -> FetchOutcome {
+    let result = match (&candidate.title, &candidate.artist) {
+        (Some(title), Some(artist)) => service.fetch_lyrics(title, artist, candidate.album.as_deref()).await,
+        _ => Err(anyhow::anyhow!("缺少标题或艺术家")),
+    };
+
+    if let Ok(db) = db.lock() {
+        let _ = db.set_lyrics_attempt(candidate.track_id, chrono::Utc::now().timestamp());
+    }
+
+    match result {
+        Ok(lyrics) => save(db, candidate.track_id, &lyrics.content, SOURCE_ONLINE),
+        Err(e) => {
+            log::debug!("获取歌词失败: {} ({})", candidate.path, e);
+            FetchOutcome::Failed
+        }
+    }
+}
+
+fn save(db: &Arc<DbPool>, track_id: i64, content: &str, source: &str) -> FetchOutcome {
+    let result = db.lock()
+        .map_err(|e| e.to_string())
+        .and_then(|db| db.insert_lyrics(track_id, content, "lrc", source).map_err(|e| e.to_string()));
+    match result {
+        Ok(_) => FetchOutcome::Found,
+        Err(e) => {
+            log::warn!("⚠️ 保存歌词失败: track_id={}, {}", track_id, e);
+            FetchOutcome::Failed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_validation_and_interval() {
+        assert!(validate_rate(0.0).is_err());
+        assert!(validate_rate(10.0).is_err());
+        assert_eq!(validate_rate(2.0), Ok(2.0));
+        assert_eq!(request_interval(2.0), Duration::from_millis(500));
+        assert_eq!(request_interval(DEFAULT_REQUESTS_PER_SECOND), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,471 @@
+// 系统媒体会话 - 媒体键和系统播放控件（Windows SMTC、macOS Now Playing、Linux MPRIS）
+//
+// 设计：
+// - 平台后端实现 MediaSessionBackend，在独立线程中创建和使用（部分平台对象不是Send）
+// - 系统发来的控制（媒体键、系统浮层）转换为 PlayerCommand 发送到播放器
+// - 播放器事件更新系统可见的曲目信息、封面和播放位置
+// - 按平台和 media-session 特性编译，暂不支持的平台不创建会话
+
+#[cfg(all(feature = "media-session", any(target_os = "windows", target_os = "macos")))]
+mod souvlaki_backend;
+#[cfg(all(feature = "media-session", target_os = "linux"))]
+mod mpris_backend;
+
+use crate::cover_cache::CoverSize;
+use crate::player::{PlayerCommand, Track};
+use crossbeam_channel::Sender;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 系统没有给出跳转幅度时的快进/快退步长
+const DEFAULT_SEEK_STEP: Duration = Duration::from_secs(10);
+
+/// 播放中实际位置与按时间推算的位置相差超过该值时认为发生了跳转，需要同步给系统
+const POSITION_DRIFT_MS: u64 = 1500;
+
+/// 当前会话
+static SESSION: Lazy<Mutex<Option<MediaSession>>> = Lazy::new(|| Mutex::new(None));
+
+/// 系统发来的播放控制
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaAction {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    /// 跳转到指定位置（系统浮层拖动进度条）
+    SetPosition(Duration),
+    /// 相对跳转，None表示使用默认步长
+    SeekBy { forward: bool, offset: Option<Duration> },
+    /// 设置音量（0.0 - 1.0）
+    SetVolume(f64),
+}
+
+/// 系统控制回调
+pub type ActionHandler = Box<dyn Fn(MediaAction) + Send + Sync + 'static>;
+
+/// 系统可见的播放状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// 系统可见的曲目信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NowPlaying {
+    /// 曲目ID（MPRIS的 mpris:trackid）
+    pub track_id: Option<i64>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+    /// 封面图片文件（系统只接受URL/文件）
+    pub cover_path: Option<PathBuf>,
+}
+
+/// 平台媒体会话后端
+pub trait MediaSessionBackend {
+    /// 更新曲目信息
+    fn set_metadata(&mut self, now_playing: &NowPlaying) -> Result<(), String>;
+
+    /// 更新播放状态和位置
+    fn set_playback(&mut self, status: PlaybackStatus, position: Option<Duration>) -> Result<(), String>;
+
+    /// 更新音量（0.0 - 1.0），不支持音量的平台忽略
+    fn set_volume(&mut self, _volume: f64) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// 从系统注销
+    fn shutdown(&mut self);
+}
+
+/// 创建后端需要的平台参数
+#[derive(Debug, Clone, Default)]
+pub struct BackendConfig {
+    /// 主窗口句柄（Windows SMTC需要），以整数形式跨线程传递
+    pub window_handle: Option<usize>,
+}
+
+/// 会话线程收到的更新
+enum SessionUpdate {
+    Track(Option<Track>),
+    Playback {
+        status: PlaybackStatus,
+        position_ms: u64,
+    },
+    Volume(f64),
+    Shutdown,
+}
+
+/// 系统控制回调需要读取的播放器状态
+#[derive(Debug, Clone, Default)]
+struct SessionState {
+    is_playing: bool,
+    has_track: bool,
+    position_ms: u64,
+    duration_ms: Option<u64>,
+    volume: Option<f32>,
+    /// 最近一次同步给系统的位置和时间
+    last_pushed: Option<(Instant, u64)>,
+}
+
+impl SessionState {
+    fn status(&self) -> PlaybackStatus {
+        match (self.has_track, self.is_playing) {
+            (false, _) => PlaybackStatus::Stopped,
+            (true, true) => PlaybackStatus::Playing,
+            (true, false) => PlaybackStatus::Paused,
+        }
+    }
+}
+
+/// 运行中的媒体会话
+struct MediaSession {
+    updates: std::sync::mpsc::Sender<SessionUpdate>,
+    state: Arc<Mutex<SessionState>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// 启动系统媒体会话（平台不支持时只记录日志）
+pub fn start(player_tx: Sender<PlayerCommand>, config: BackendConfig, cover_dir: PathBuf) {
+    if !is_supported() {
+        log::info!("ℹ️ 当前平台暂不支持系统媒体会话");
+        return;
+    }
+
+    let mut session = SESSION.lock();
+    if session.is_some() {
+        return;
+    }
+
+    let state = Arc::new(Mutex::new(SessionState::default()));
+    let (updates_tx, updates_rx) = std::sync::mpsc::channel();
+    let thread_state = Arc::clone(&state);
+    let thread = std::thread::Builder::new()
+        .name("media-session".to_string())
+        .spawn(move || run_session(config, player_tx, thread_state, updates_rx, cover_dir));
+
+    match thread {
+        Ok(thread) => {
+            *session = Some(MediaSession {
+                updates: updates_tx,
+                state,
+                thread: Some(thread),
+            });
+        }
+        Err(e) => log::warn!("⚠️ 启动媒体会话线程失败: {}", e),
+    }
+}
+
+/// 曲目变化
+pub fn update_track(track: Option<&Track>) {
+    with_session(|session| {
+        {
+            let mut state = session.state.lock();
+            state.has_track = track.is_some();
+            state.position_ms = 0;
+            state.duration_ms = track.and_then(|t| t.duration_ms).map(|d| d.max(0) as u64);
+            state.last_pushed = None;
+        }
+        let _ = session.updates.send(SessionUpdate::Track(track.cloned()));
+        push_playback(session, true);
+    });
+}
+
+/// 播放/暂停状态和音量变化
+pub fn update_state(is_playing: bool, has_track: bool, volume: f32) {
+    with_session(|session| {
+        let (changed, volume_changed) = {
+            let mut state = session.state.lock();
+            let changed = state.is_playing != is_playing || state.has_track != has_track;
+            let volume_changed = state.volume != Some(volume);
+            state.is_playing = is_playing;
+            state.has_track = has_track;
+            state.volume = Some(volume);
+            (changed, volume_changed)
+        };
+        push_playback(session, changed);
+        if volume_changed {
+            let _ = session.updates.send(SessionUpdate::Volume(volume as f64));
+        }
+    });
+}
+
+/// 播放位置更新（只在跳转后同步，系统会根据播放状态自行推算位置）
+pub fn update_position(position_ms: u64) {
+    with_session(|session| {
+        session.state.lock().position_ms = position_ms;
+        push_playback(session, false);
+    });
+}
+
+/// 注销媒体会话并等待会话线程退出
+pub fn shutdown() {
+    let session = SESSION.lock().take();
+    if let Some(mut session) = session {
+        let _ = session.updates.send(SessionUpdate::Shutdown);
+        if let Some(thread) = session.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("媒体会话已关闭");
+    }
+}
+
+fn with_session(f: impl FnOnce(&MediaSession)) {
+    if let Some(session) = SESSION.lock().as_ref() {
+        f(session);
+    }
+}
+
+/// 同步播放状态和位置，force 为 false 时只在位置跳变时同步
+fn push_playback(session: &MediaSession, force: bool) {
+    let now = Instant::now();
+    let update = {
+        let mut state = session.state.lock();
+        if !force && !position_jumped(state.last_pushed, state.is_playing, state.position_ms, now) {
+            return;
+        }
+        state.last_pushed = Some((now, state.position_ms));
+        SessionUpdate::Playback {
+            status: state.status(),
+            position_ms: state.position_ms,
+        }
+    };
+    let _ = session.updates.send(update);
+}
+
+/// 当前位置是否偏离了上次同步后按时间推算的位置
+fn position_jumped(last_pushed: Option<(Instant, u64)>, is_playing: bool, position_ms: u64, now: Instant) -> bool {
+    let (pushed_at, pushed_ms) = match last_pushed {
+        Some(last) => last,
+        None => return true,
+    };
+    let expected = if is_playing {
+        pushed_ms + now.saturating_duration_since(pushed_at).as_millis() as u64
+    } else {
+        pushed_ms
+    };
+    expected.abs_diff(position_ms) > POSITION_DRIFT_MS
+}
+
+/// 将系统控制转换为播放器命令
+fn action_to_command(action: MediaAction, is_playing: bool, position_ms: u64, duration_ms: Option<u64>) -> PlayerCommand {
+    match action {
+        MediaAction::Play => PlayerCommand::Resume,
+        MediaAction::Pause => PlayerCommand::Pause,
+        MediaAction::Toggle if is_playing => PlayerCommand::Pause,
+        MediaAction::Toggle => PlayerCommand::Resume,
+        MediaAction::Stop => PlayerCommand::Stop,
+        MediaAction::Next => PlayerCommand::Next,
+        MediaAction::Previous => PlayerCommand::Previous,
+        MediaAction::SetPosition(position) => PlayerCommand::Seek(position.as_millis() as u64),
+        MediaAction::SetVolume(volume) => PlayerCommand::SetVolume(volume.clamp(0.0, 1.0) as f32),
+        MediaAction::SeekBy { forward, offset } => {
+            let offset_ms = offset.unwrap_or(DEFAULT_SEEK_STEP).as_millis() as u64;
+            let target = if forward {
+                position_ms.saturating_add(offset_ms)
+            } else {
+                position_ms.saturating_sub(offset_ms)
+            };
+            // 快进超过结尾时交给下一曲处理
+            match duration_ms {
+                Some(duration) if forward && target >= duration => PlayerCommand::Next,
+                _ => PlayerCommand::Seek(target),
+            }
+        }
+    }
+}
+
+/// 会话线程：创建平台后端并处理更新
+fn run_session(
+    config: BackendConfig,
+    player_tx: Sender<PlayerCommand>,
+    state: Arc<Mutex<SessionState>>,
+    updates: std::sync::mpsc::Receiver<SessionUpdate>,
+    cover_dir: PathBuf,
+) {
+    let callback_state = Arc::clone(&state);
+    let on_action: ActionHandler = Box::new(move |action: MediaAction| {
+        let command = {
+            let state = callback_state.lock();
+            action_to_command(action, state.is_playing, state.position_ms, state.duration_ms)
+        };
+        log::info!("🎹 系统媒体控制: {:?} -> {}", action, command.name());
+        if let Err(e) = player_tx.send(command) {
+            log::warn!("⚠️ 发送媒体控制命令失败: {}", e);
+        }
+    });
+
+    let mut backend = match create_backend(&config, on_action) {
+        Ok(backend) => backend,
+        Err(e) => {
+            log::warn!("⚠️ 创建系统媒体会话失败: {}", e);
+            return;
+        }
+    };
+    log::info!("✅ 系统媒体会话已启动");
+
+    let mut current_cover: Option<PathBuf> = None;
+    while let Ok(update) = updates.recv() {
+        let result = match update {
+            SessionUpdate::Track(track) => {
+                let cover = track.as_ref().and_then(|t| write_cover_file(t, &cover_dir));
+                if current_cover != cover {
+                    if let Some(old) = current_cover.take() {
+                        let _ = std::fs::remove_file(old);
+                    }
+                    current_cover = cover.clone();
+                }
+                let now_playing = track.map(|t| now_playing(&t, cover)).unwrap_or_default();
+                backend.set_metadata(&now_playing)
+            }
+            SessionUpdate::Playback { status, position_ms } => {
+                let position = (status != PlaybackStatus::Stopped).then(|| Duration::from_millis(position_ms));
+                backend.set_playback(status, position)
+            }
+            SessionUpdate::Volume(volume) => backend.set_volume(volume),
+            SessionUpdate::Shutdown => break,
+        };
+        if let Err(e) = result {
+            log::warn!("⚠️ 更新系统媒体会话失败: {}", e);
+        }
+    }
+
+    backend.shutdown();
+    if let Some(old) = current_cover {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+fn now_playing(track: &Track, cover_path: Option<PathBuf>) -> NowPlaying {
+    NowPlaying {
+        track_id: Some(track.id),
+        title: track.title.clone().or_else(|| {
+            Path::new(&track.path).file_stem().map(|s| s.to_string_lossy().into_owned())
+        }),
+        artist: track.artist.clone(),
+        album: track.album.clone(),
+        duration: track.duration_ms.filter(|d| *d > 0).map(|d| Duration::from_millis(d as u64)),
+        cover_path,
+    }
+}
+
+/// 将曲目封面写入临时文件，系统媒体控件通过文件URL加载
+fn write_cover_file(track: &Track, cover_dir: &Path) -> Option<PathBuf> {
+    let data = match &track.album_cover_data {
+        Some(data) => data.clone(),
+        None => {
+            let cover_id = track.cover_id.as_deref()?;
+            let db = crate::DB.get()?.lock().ok()?;
+            db.get_cover(cover_id, CoverSize::Large).ok().flatten()?.data
+        }
+    };
+
+    let name = format!("{}.{}", crate::cover_cache::cover_id(&data), cover_extension(&data));
+    let path = cover_dir.join(name);
+    if !path.exists() {
+        if let Err(e) = std::fs::create_dir_all(cover_dir).and_then(|_| std::fs::write(&path, &data)) {
+            log::warn!("⚠️ 写入媒体会话封面失败: {}", e);
+            return None;
+        }
+    }
+    Some(path)
+}
+
+fn cover_extension(data: &[u8]) -> &'static str {
+    match crate::cover_cache::sniff_mime(data) {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "jpg",
+    }
+}
+
+/// 当前平台是否有可用的后端
+fn is_supported() -> bool {
+    cfg!(all(feature = "media-session", any(target_os = "windows", target_os = "macos", target_os = "linux")))
+}
+
+#[cfg(all(feature = "media-session", any(target_os = "windows", target_os = "macos")))]
+fn create_backend(config: &BackendConfig, on_action: ActionHandler) -> Result<Box<dyn MediaSessionBackend>, String> {
+    souvlaki_backend::SouvlakiBackend::new(config, on_action)
+        .map(|backend| Box::new(backend) as Box<dyn MediaSessionBackend>)
+}
+
+#[cfg(all(feature = "media-session", target_os = "linux"))]
+fn create_backend(config: &BackendConfig, on_action: ActionHandler) -> Result<Box<dyn MediaSessionBackend>, String> {
+    mpris_backend::MprisBackend::new(config, on_action)
+        .map(|backend| Box::new(backend) as Box<dyn MediaSessionBackend>)
+}
+
+#[cfg(not(all(feature = "media-session", any(target_os = "windows", target_os = "macos", target_os = "linux"))))]
+fn create_backend(
+    _config: &BackendConfig,
+    _on_action: ActionHandler,
+) -> Result<Box<dyn MediaSessionBackend>, String> {
+    Err("当前平台暂不支持系统媒体会话".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seek_target(command: PlayerCommand) -> Option<u64> {
+        match command {
+            PlayerCommand::Seek(ms) => Some(ms),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_toggle_follows_playing_state() {
+        assert!(matches!(action_to_command(MediaAction::Toggle, true, 0, None), PlayerCommand::Pause));
+        assert!(matches!(action_to_command(MediaAction::Toggle, false, 0, None), PlayerCommand::Resume));
+        assert!(matches!(action_to_command(MediaAction::Play, true, 0, None), PlayerCommand::Resume));
+        assert!(matches!(action_to_command(MediaAction::Next, true, 0, None), PlayerCommand::Next));
+        assert!(matches!(action_to_command(MediaAction::SetVolume(1.5), true, 0, None), PlayerCommand::SetVolume(v) if v == 1.0));
+    }
+
+    #[test]
+    fn test_seek_actions() {
+        let set = action_to_command(MediaAction::SetPosition(Duration::from_millis(42_000)), true, 0, Some(180_000));
+        assert_eq!(seek_target(set), Some(42_000));
+
+        let back = action_to_command(MediaAction::SeekBy { forward: false, offset: None }, true, 4_000, Some(180_000));
+        assert_eq!(seek_target(back), Some(0));
+
+        let forward = action_to_command(
+            MediaAction::SeekBy { forward: true, offset: Some(Duration::from_secs(5)) },
+            true,
+            60_000,
+            Some(180_000),
+        );
+        assert_eq!(seek_target(forward), Some(65_000));
+
+        let past_end = action_to_command(MediaAction::SeekBy { forward: true, offset: None }, true, 175_000, Some(180_000));
+        assert!(matches!(past_end, PlayerCommand::Next));
+    }
+
+    #[test]
+    fn test_position_jump_detection() {
+        let now = Instant::now();
+        assert!(position_jumped(None, true, 0, now));
+
+        // 正常播放：位置随时间前进，不需要同步
+        let last = Some((now, 10_000));
+        assert!(!position_jumped(last, true, 13_000, now + Duration::from_secs(3)));
+        // 跳转：位置与推算值相差过大
+        assert!(position_jumped(last, true, 60_000, now + Duration::from_secs(3)));
+        // 暂停时位置不变
+        assert!(!position_jumped(last, false, 10_000, now + Duration::from_secs(3)));
+        assert!(position_jumped(last, false, 30_000, now + Duration::from_secs(3)));
+    }
+}
@@ -0,0 +1,391 @@
+// MPRIS D-Bus 媒体会话后端（Linux）
+//
+// 实现 org.mpris.MediaPlayer2 和 org.mpris.MediaPlayer2.Player 接口，
+// playerctl、GNOME媒体控件、KDE媒体小部件通过会话总线控制播放器
+
+use super::{ActionHandler, BackendConfig, MediaAction, MediaSessionBackend, NowPlaying, PlaybackStatus};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zbus::blocking::{connection, Connection};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+/// 总线名称，已被占用（多开）时追加实例后缀
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.windchime";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// 没有曲目时的 mpris:trackid
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// org.mpris.MediaPlayer2 根接口
+struct RootInterface;
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "WindChime Player".to_string()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        "windchime".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// org.mpris.MediaPlayer2.Player 接口
+struct PlayerInterface {
+    on_action: ActionHandler,
+    status: PlaybackStatus,
+    track_path: OwnedObjectPath,
+    metadata: HashMap<String, OwnedValue>,
+    volume: f64,
+    /// 最近一次同步的位置，播放中按经过的时间推算
+    position: Duration,
+    position_at: Instant,
+}
+
+impl PlayerInterface {
+    fn new(on_action: ActionHandler) -> Self {
+        let track_path = no_track_path();
+        Self {
+            on_action,
+            status: PlaybackStatus::Stopped,
+            metadata: metadata_map(&track_path, &NowPlaying::default()),
+            track_path,
+            volume: 1.0,
+            position: Duration::ZERO,
+            position_at: Instant::now(),
+        }
+    }
+
+    fn current_position(&self) -> Duration {
+        match self.status {
+            PlaybackStatus::Playing => self.position + self.position_at.elapsed(),
+            PlaybackStatus::Paused => self.position,
+            PlaybackStatus::Stopped => Duration::ZERO,
+        }
+    }
+
+    fn has_track(&self) -> bool {
+        self.track_path.as_str() != NO_TRACK
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) {
+        (self.on_action)(MediaAction::Next);
+    }
+
+    fn previous(&self) {
+        (self.on_action)(MediaAction::Previous);
+    }
+
+    fn pause(&self) {
+        (self.on_action)(MediaAction::Pause);
+    }
+
+    fn play_pause(&self) {
+        (self.on_action)(MediaAction::Toggle);
+    }
+
+    fn stop(&self) {
+        (self.on_action)(MediaAction::Stop);
+    }
+
+    fn play(&self) {
+        (self.on_action)(MediaAction::Play);
+    }
+
+    /// 相对跳转（微秒）
+    fn seek(&self, offset: i64) {
+        if !self.has_track() {
+            return;
+        }
+        (self.on_action)(MediaAction::SeekBy {
+            forward: offset >= 0,
+            offset: Some(Duration::from_micros(offset.unsigned_abs())),
+        });
+    }
+
+    /// 跳转到指定位置（微秒），曲目ID与当前曲目不一致时忽略（规范要求）
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if track_id.as_str() != self.track_path.as_str() || position < 0 {
+            return;
+        }
+        (self.on_action)(MediaAction::SetPosition(Duration::from_micros(position as u64)));
+    }
+
+    fn open_uri(&self, _uri: String) {}
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        match self.status {
+            PlaybackStatus::Playing => "Playing",
+            PlaybackStatus::Paused => "Paused",
+            PlaybackStatus::Stopped => "Stopped",
+        }
+        .to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.metadata.clone()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    /// 外部修改音量，播放器的StateChanged会再同步回来
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let volume = volume.clamp(0.0, 1.0);
+        self.volume = volume;
+        (self.on_action)(MediaAction::SetVolume(volume));
+    }
+
+    /// 位置由客户端按需读取，不发送属性变化信号
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.current_position().as_micros() as i64
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.has_track()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+pub struct MprisBackend {
+    connection: Connection,
+    bus_name: String,
+}
+
+impl MprisBackend {
+    pub fn new(_config: &BackendConfig, on_action: ActionHandler) -> Result<Self, String> {
+        let connection = connection::Builder::session()
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, RootInterface))
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, PlayerInterface::new(on_action)))
+            .and_then(|builder| builder.build())
+            .map_err(|e| format!("连接D-Bus会话总线失败: {}", e))?;
+
+        let bus_name = match connection.request_name(BUS_NAME) {
+            Ok(()) => BUS_NAME.to_string(),
+            Err(_) => {
+                let instance = format!("{}.instance{}", BUS_NAME, std::process::id());
+                connection.request_name(instance.as_str())
+                    .map_err(|e| format!("注册MPRIS总线名称失败: {}", e))?;
+                instance
+            }
+        };
+        log::info!("🎛️ MPRIS已注册: {}", bus_name);
+
+        Ok(Self { connection, bus_name })
+    }
+
+    fn player(&self) -> Result<zbus::blocking::object_server::InterfaceRef<PlayerInterface>, String> {
+        self.connection.object_server()
+            .interface::<_, PlayerInterface>(OBJECT_PATH)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl MediaSessionBackend for MprisBackend {
+    fn set_metadata(&mut self, now_playing: &NowPlaying) -> Result<(), String> {
+        let player = self.player()?;
+        let mut iface = player.get_mut();
+        iface.track_path = now_playing.track_id.map(track_path).unwrap_or_else(no_track_path);
+        iface.metadata = metadata_map(&iface.track_path, now_playing);
+        zbus::block_on(async {
+            iface.metadata_changed(player.signal_emitter()).await?;
+            iface.can_seek_changed(player.signal_emitter()).await
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    fn set_playback(&mut self, status: PlaybackStatus, position: Option<Duration>) -> Result<(), String> {
+        let player = self.player()?;
+        let mut iface = player.get_mut();
+        let status_changed = iface.status != status;
+        iface.status = status;
+        iface.position = position.unwrap_or(Duration::ZERO);
+        iface.position_at = Instant::now();
+
+        zbus::block_on(async {
+            if status_changed {
+                iface.playback_status_changed(player.signal_emitter()).await
+            } else if let Some(position) = position {
+                // 状态没变说明是跳转，按规范发送Seeked
+                PlayerInterface::seeked(player.signal_emitter(), position.as_micros() as i64).await
+            } else {
+                Ok(())
+            }
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    fn set_volume(&mut self, volume: f64) -> Result<(), String> {
+        let player = self.player()?;
+        let mut iface = player.get_mut();
+        if (iface.volume - volume).abs() < f64::EPSILON {
+            return Ok(());
+        }
+        iface.volume = volume;
+        zbus::block_on(iface.volume_changed(player.signal_emitter())).map_err(|e| e.to_string())
+    }
+
+    fn shutdown(&mut self) {
+        let object_server = self.connection.object_server();
+        let _ = object_server.remove::<PlayerInterface, _>(OBJECT_PATH);
+        let _ = object_server.remove::<RootInterface, _>(OBJECT_PATH);
+        if let Err(e) = self.connection.release_name(self.bus_name.as_str()) {
+            log::warn!("⚠️ 注销MPRIS总线名称失败: {}", e);
+        }
+    }
+}
+
+fn no_track_path() -> OwnedObjectPath {
+    ObjectPath::from_static_str_unchecked(NO_TRACK).into()
+}
+
+fn track_path(track_id: i64) -> OwnedObjectPath {
+    // 对象路径只允许 [A-Za-z0-9_]，负数ID（远程曲目）用下划线代替负号
+    let id = track_id.to_string().replace('-', "_");
+    ObjectPath::try_from(format!("/org/windchime/track/{}", id))
+        .map(OwnedObjectPath::from)
+        .unwrap_or_else(|_| no_track_path())
+}
+
+/// 按MPRIS规范生成元数据（xesam:artist 为字符串数组，mpris:length 单位为微秒）
+fn metadata_map(track_path: &OwnedObjectPath, now_playing: &NowPlaying) -> HashMap<String, OwnedValue> {
+    let mut entries: Vec<(&str, Value)> = vec![("mpris:trackid", Value::from(track_path.as_ref()))];
+    if let Some(title) = &now_playing.title {
+        entries.push(("xesam:title", Value::from(title.as_str())));
+    }
+    if let Some(artist) = &now_playing.artist {
+        entries.push(("xesam:artist", Value::from(vec![artist.as_str()])));
+    }
+    if let Some(album) = &now_playing.album {
+        entries.push(("xesam:album", Value::from(album.as_str())));
+    }
+    if let Some(duration) = now_playing.duration {
+        entries.push(("mpris:length", Value::from(duration.as_micros() as i64)));
+    }
+    if let Some(url) = now_playing.cover_path.as_ref().and_then(|path| url::Url::from_file_path(path).ok()) {
+        entries.push(("mpris:artUrl", Value::from(url.to_string())));
+    }
+
+    entries.into_iter()
+        .filter_map(|(key, value)| OwnedValue::try_from(value).ok().map(|value| (key.to_string(), value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_metadata_map() {
+        let now_playing = NowPlaying {
+            track_id: Some(-42),
+            title: Some("晴天".to_string()),
+            artist: Some("周杰伦".to_string()),
+            album: None,
+            duration: Some(Duration::from_millis(269_000)),
+            cover_path: Some(PathBuf::from("/tmp/cover.jpg")),
+        };
+        let path = track_path(-42);
+        assert_eq!(path.as_str(), "/org/windchime/track/_42");
+
+        let metadata = metadata_map(&path, &now_playing);
+        assert_eq!(metadata.len(), 5);
+        assert_eq!(i64::try_from(&metadata["mpris:length"]).unwrap(), 269_000_000);
+        assert_eq!(<&str>::try_from(&metadata["xesam:title"]).unwrap(), "晴天");
+        assert_eq!(<&str>::try_from(&metadata["mpris:artUrl"]).unwrap(), "file:///tmp/cover.jpg");
+        assert!(!metadata.contains_key("xesam:album"));
+    }
+
+    #[test]
+    fn test_empty_metadata_uses_no_track() {
+        let metadata = metadata_map(&no_track_path(), &NowPlaying::default());
+        assert_eq!(metadata.len(), 1);
+        let track_id: &Value = &metadata["mpris:trackid"];
+        let track_id = ObjectPath::try_from(track_id).unwrap();
+        assert_eq!(track_id.as_str(), NO_TRACK);
+    }
+}
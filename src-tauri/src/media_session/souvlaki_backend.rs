@@ -0,0 +1,97 @@
+// 基于souvlaki的媒体会话后端（Windows SMTC、macOS MPNowPlayingInfoCenter）
+
+use super::{ActionHandler, BackendConfig, MediaAction, MediaSessionBackend, NowPlaying, PlaybackStatus};
+use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection};
+use std::time::Duration;
+
+pub struct SouvlakiBackend {
+    controls: MediaControls,
+}
+
+impl SouvlakiBackend {
+    pub fn new(config: &BackendConfig, on_action: ActionHandler) -> Result<Self, String> {
+        #[cfg(target_os = "windows")]
+        let hwnd = match config.window_handle {
+            Some(handle) => Some(handle as *mut std::ffi::c_void),
+            None => return Err("缺少主窗口句柄，无法注册SMTC".to_string()),
+        };
+        #[cfg(not(target_os = "windows"))]
+        let hwnd = {
+            let _ = config;
+            None
+        };
+
+        let platform_config = PlatformConfig {
+            dbus_name: "windchime",
+            display_name: "WindChime Player",
+            hwnd,
+        };
+        let mut controls = MediaControls::new(platform_config)
+            .map_err(|e| format!("创建媒体控件失败: {:?}", e))?;
+
+        controls.attach(move |event: MediaControlEvent| {
+            if let Some(action) = to_action(event) {
+                on_action(action);
+            }
+        })
+        .map_err(|e| format!("注册媒体控件回调失败: {:?}", e))?;
+
+        Ok(Self { controls })
+    }
+}
+
+impl MediaSessionBackend for SouvlakiBackend {
+    fn set_metadata(&mut self, now_playing: &NowPlaying) -> Result<(), String> {
+        let cover_url = now_playing.cover_path.as_ref()
+            .and_then(|path| url::Url::from_file_path(path).ok())
+            .map(|url| url.to_string());
+
+        self.controls.set_metadata(MediaMetadata {
+            title: now_playing.title.as_deref(),
+            artist: now_playing.artist.as_deref(),
+            album: now_playing.album.as_deref(),
+            cover_url: cover_url.as_deref(),
+            duration: now_playing.duration,
+        })
+        .map_err(|e| format!("{:?}", e))
+    }
+
+    fn set_playback(&mut self, status: PlaybackStatus, position: Option<Duration>) -> Result<(), String> {
+        let progress = position.map(MediaPosition);
+        let playback = match status {
+            PlaybackStatus::Playing => MediaPlayback::Playing { progress },
+            PlaybackStatus::Paused => MediaPlayback::Paused { progress },
+            PlaybackStatus::Stopped => MediaPlayback::Stopped,
+        };
+        self.controls.set_playback(playback).map_err(|e| format!("{:?}", e))
+    }
+
+    fn shutdown(&mut self) {
+        if let Err(e) = self.controls.detach() {
+            log::warn!("⚠️ 注销媒体控件失败: {:?}", e);
+        }
+    }
+}
+
+/// souvlaki事件转换为播放控制，窗口类事件（Raise、Quit、OpenUri等）忽略
+fn to_action(event: MediaControlEvent) -> Option<MediaAction> {
+    let action = match event {
+        MediaControlEvent::Play => MediaAction::Play,
+        MediaControlEvent::Pause => MediaAction::Pause,
+        MediaControlEvent::Toggle => MediaAction::Toggle,
+        MediaControlEvent::Stop => MediaAction::Stop,
+        MediaControlEvent::Next => MediaAction::Next,
+        MediaControlEvent::Previous => MediaAction::Previous,
+        MediaControlEvent::SetPosition(MediaPosition(position)) => MediaAction::SetPosition(position),
+        MediaControlEvent::Seek(direction) => MediaAction::SeekBy {
+            forward: direction == SeekDirection::Forward,
+            offset: None,
+        },
+        MediaControlEvent::SeekBy(direction, offset) => MediaAction::SeekBy {
+            forward: direction == SeekDirection::Forward,
+            offset: Some(offset),
+        },
+        _ => return None,
+    };
+    Some(action)
+}
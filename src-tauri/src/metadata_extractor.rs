@@ -4,6 +4,12 @@ use lofty::prelude::*;
 use lofty::probe::Probe;
 use std::path::Path;
 use std::fs;
+use lofty::config::{ParseOptions, ParsingMode};
+use lofty::file::{FileType, TaggedFile};
+use regex::Regex;
+use crate::chapters;
+use crate::db::AudioProperties;
+use crate::player::Chapter;
 
 /// 音乐元数据
 #[derive(Debug, Clone, Default)]
@@ -14,7 +20,6 @@ pub struct MusicMetadata {
     pub album: Option<String>,
     pub album_artist: Option<String>,
     pub track_number: Option<u32>,
-    #[allow(dead_code)]
     pub disc_number: Option<u32>,
     pub year: Option<u32>,
     pub genre: Option<String>,
@@ -44,9 +49,8 @@ pub struct MusicMetadata {
     pub duration_ms: Option<u64>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u16>,
-    pub bit_rate: Option<u32>,
-    #[allow(dead_code)]
-    pub format: Option<String>,
+    pub bit_rate: Option<u32>,             // kbps
+    pub format: Option<String>,            // 格式名，见 format_name
     pub encoder: Option<String>,           // 编码器
     pub encoder_settings: Option<String>,  // 编码设置
     
@@ -55,6 +59,10 @@ pub struct MusicMetadata {
     pub description: Option<String>,       // 描述
     pub url: Option<String>,               // 相关URL
     pub rating: Option<u32>,               // 评分 (0-100)
+
+    // ReplayGain
+    pub replaygain_track_gain: Option<f32>, // 曲目增益（dB）
+    pub replaygain_album_gain: Option<f32>, // 专辑增益（dB）
     
     // 图片资源
     pub album_cover_data: Option<Vec<u8>>,
@@ -65,6 +73,138 @@ pub struct MusicMetadata {
     // 歌词
     pub embedded_lyrics: Option<String>,   // 同步歌词（带时间戳）
     pub unsynchronised_lyrics: Option<String>, // 非同步歌词（纯文本）
+
+    // 章节（内嵌章节或同目录的CUE文件）
+    pub chapters: Vec<Chapter>,
+
+    // 完整提取失败的原因（此时部分字段来自宽松模式或路径推断）
+    pub extraction_error: Option<String>,
+}
+
+impl MusicMetadata {
+    /// 需要记录到曲库的音频属性
+    pub fn audio_properties(&self) -> AudioProperties {
+        AudioProperties {
+            format: self.format.clone(),
+            bitrate_kbps: self.bit_rate.filter(|&b| b > 0).map(i64::from),
+            sample_rate: self.sample_rate.filter(|&r| r > 0).map(i64::from),
+            channels: self.channels.map(i64::from),
+        }
+    }
+
+    /// 标签中的评分（0-100）换算为0-5星
+    pub fn star_rating(&self) -> Option<u8> {
+        self.rating.map(|value| ((value.min(100) + 10) / 20) as u8)
+    }
+}
+
+/// 格式名，与常见扩展名一致（mp3、m4a、ogg……），便于和按扩展名推断的格式一起统计
+pub fn format_name(file_type: FileType) -> String {
+    match file_type {
+        FileType::Mpeg => "mp3".to_string(),
+        FileType::Mp4 => "m4a".to_string(),
+        FileType::Vorbis => "ogg".to_string(),
+        FileType::WavPack => "wv".to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// 默认的路径模板：艺术家/专辑/音轨号 标题
+pub const DEFAULT_PATH_PATTERN: &str = "{artist}/{album}/{track} {title}";
+
+/// 元数据读取失败时从路径推断字段的模板
+///
+/// 按 / 分隔，从后往前对应文件所在的各级目录，最后一段对应不含扩展名的文件名。
+/// 支持的占位符：{artist}、{album}、{track}（数字）、{title}，其余文字按原样匹配。
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    components: Vec<Regex>,
+}
+
+impl Default for PathPattern {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PATH_PATTERN).expect("默认路径模板有效")
+    }
+}
+
+impl PathPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim().trim_matches('/');
+        if pattern.is_empty() {
+            return Err("路径模板不能为空".to_string());
+        }
+
+        let placeholder = Regex::new(r"\{(\w+)\}").expect("占位符正则有效");
+        let mut used: Vec<String> = Vec::new();
+        let mut components = Vec::new();
+        for component in pattern.split('/') {
+            let mut regex = String::from("^");
+            let mut last = 0;
+            for capture in placeholder.captures_iter(component) {
+                let whole = capture.get(0).expect("整体匹配");
+                let name = &capture[1];
+                let group = match name {
+                    "track" => r"(?P<track>\d{1,3})".to_string(),
+                    "artist" | "album" | "title" => format!("(?P<{}>.+?)", name),
+                    _ => return Err(format!("不支持的占位符: {{{}}}", name)),
+                };
+                if used.iter().any(|n| n == name) {
+                    return Err(format!("占位符重复: {{{}}}", name));
+                }
+                used.push(name.to_string());
+                regex.push_str(&regex::escape(&component[last..whole.start()]));
+                regex.push_str(&group);
+                last = whole.end();
+            }
+            regex.push_str(&regex::escape(&component[last..]));
+            regex.push('$');
+            components.push(Regex::new(&regex).map_err(|e| format!("路径模板无效: {}", e))?);
+        }
+        if !used.iter().any(|n| n == "title") {
+            return Err("路径模板必须包含 {title}".to_string());
+        }
+
+        Ok(Self { pattern: pattern.to_string(), components })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 用路径推断的字段填补缺失的元数据；文件名与模板不符时标题使用文件名
+    pub fn fill(&self, path: &Path, metadata: &mut MusicMetadata) {
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let names = std::iter::once(stem.clone())
+            .chain(path.ancestors().skip(1).filter_map(|p| p.file_name()).map(|n| n.to_string_lossy().to_string()));
+
+        let (mut artist, mut album, mut track, mut title) = (None, None, None, None);
+        for (regex, name) in self.components.iter().rev().zip(names) {
+            let Some(captures) = regex.captures(&name) else { continue };
+            let field = |key: &str| {
+                captures.name(key)
+                    .map(|m| m.as_str().trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '_' | '.')).to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            artist = artist.or_else(|| field("artist"));
+            album = album.or_else(|| field("album"));
+            title = title.or_else(|| field("title"));
+            track = track.or_else(|| captures.name("track").and_then(|m| m.as_str().parse::<u32>().ok()));
+        }
+
+        if metadata.title.is_none() {
+            metadata.title = title.or_else(|| Some(stem).filter(|s| !s.is_empty()));
+        }
+        if metadata.artist.is_none() {
+            metadata.artist = artist;
+        }
+        if metadata.album.is_none() {
+            metadata.album = album;
+        }
+        if metadata.track_number.is_none() {
+            metadata.track_number = track;
+        }
+    }
 }
 
 /// 元数据提取器
@@ -78,11 +218,46 @@ impl MetadataExtractor {
     /// 从文件提取元数据
     pub fn extract_from_file(&self, path: &Path) -> Result<MusicMetadata> {
         let tagged_file = lofty::read_from_path(path)?;
-        
+        Ok(Self::metadata_from_tagged_file(path, &tagged_file))
+    }
+
+    /// 容错提取：完整提取失败时以宽松模式重读，仍失败时只保留能确定的信息；
+    /// 缺失的标题、艺术家、专辑、音轨号按模板从路径推断，失败原因记录在 extraction_error
+    pub fn extract_lenient(&self, path: &Path, pattern: &PathPattern) -> MusicMetadata {
+        let error = match self.extract_from_file(path) {
+            Ok(metadata) => return metadata,
+            Err(e) => e,
+        };
+        log::warn!("⚠️ 元数据提取失败，尝试容错提取 {}: {}", path.display(), error);
+
+        let mut metadata = match Self::read_relaxed(path) {
+            Ok(tagged_file) => Self::metadata_from_tagged_file(path, &tagged_file),
+            Err(e) => {
+                log::warn!("⚠️ 宽松模式仍无法读取，使用文件名推断 {}: {}", path.display(), e);
+                let mut metadata = MusicMetadata {
+                    format: path.extension().map(|ext| ext.to_string_lossy().to_lowercase()),
+                    ..Default::default()
+                };
+                Self::fill_from_directory(path, &mut metadata);
+                metadata
+            }
+        };
+        pattern.fill(path, &mut metadata);
+        metadata.extraction_error = Some(error.to_string());
+        metadata
+    }
+
+    /// 宽松模式读取：跳过无法解析的标签项而不是整体失败
+    fn read_relaxed(path: &Path) -> lofty::error::LoftyResult<TaggedFile> {
+        let options = ParseOptions::new().parsing_mode(ParsingMode::Relaxed);
+        Probe::open(path)?.options(options).guess_file_type()?.read()
+    }
+
+    fn metadata_from_tagged_file(path: &Path, tagged_file: &TaggedFile) -> MusicMetadata {
         let tag = tagged_file.primary_tag().or_else(|| tagged_file.first_tag());
         
         let mut metadata = MusicMetadata {
-            format: Some(format!("{:?}", tagged_file.file_type())),
+            format: Some(format_name(tagged_file.file_type())),
             ..Default::default()
         };
 
@@ -100,6 +275,7 @@ impl MetadataExtractor {
             metadata.album = tag.album().map(|s| s.to_string());
             metadata.album_artist = tag.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string());
             metadata.track_number = tag.track();
+            metadata.disc_number = tag.disk();
             metadata.year = tag.year();
             metadata.genre = tag.genre().map(|s| s.to_string());
             
@@ -138,6 +314,12 @@ impl MetadataExtractor {
             metadata.rating = tag.get_string(&ItemKey::Popularimeter)
                 .and_then(|s| s.parse::<u32>().ok());
 
+            // ReplayGain（dB）
+            metadata.replaygain_track_gain = tag.get_string(&ItemKey::ReplayGainTrackGain)
+                .and_then(parse_replay_gain);
+            metadata.replaygain_album_gain = tag.get_string(&ItemKey::ReplayGainAlbumGain)
+                .and_then(parse_replay_gain);
+
             // 提取专辑封面 - 优先选择前封面
             let pictures = tag.pictures();
             
@@ -226,6 +408,15 @@ impl MetadataExtractor {
             }
         }
         
+        Self::fill_from_directory(path, &mut metadata);
+
+        metadata.chapters = Self::extract_chapters(path, tagged_file, tag, metadata.duration_ms);
+
+        metadata
+    }
+
+    /// 没有内嵌的封面、艺术家照片、歌词时从同目录的文件补充
+    fn fill_from_directory(path: &Path, metadata: &mut MusicMetadata) {
         // 如果没有内嵌封面，尝试从目录中查找
         if metadata.album_cover_data.is_none() {
             if let Some((cover_data, mime_type)) = Self::find_cover_in_directory(path) {
@@ -246,8 +437,37 @@ impl MetadataExtractor {
         if metadata.embedded_lyrics.is_none() {
             metadata.embedded_lyrics = Self::find_lyrics_file(path);
         }
+    }
+    
+    /// 提取章节：优先使用内嵌章节（MP4 chpl、Vorbis CHAPTERxxx），没有时查找同目录的CUE文件
+    fn extract_chapters(
+        path: &Path,
+        tagged_file: &lofty::file::TaggedFile,
+        tag: Option<&lofty::tag::Tag>,
+        duration_ms: Option<u64>,
+    ) -> Vec<Chapter> {
+        let mut raw = if tagged_file.file_type() == FileType::Mp4 {
+            chapters::mp4_chapters(path)
+        } else {
+            Vec::new()
+        };
+        if raw.is_empty() {
+            if let Some(tag) = tag {
+                raw = chapters::vorbis_chapters(tag.items().filter_map(|item| match item.key() {
+                    ItemKey::Unknown(key) => Some((key.as_str(), item.value().text()?)),
+                    _ => None,
+                }));
+            }
+        }
+        if raw.is_empty() {
+            raw = chapters::find_cue_chapters(path);
+        }
 
-        Ok(metadata)
+        let chapters = chapters::finalize(raw, duration_ms);
+        if !chapters.is_empty() {
+            log::info!("📑 提取到 {} 个章节: {}", chapters.len(), path.display());
+        }
+        chapters
     }
     
     /// 从音频文件所在目录查找封面图片
@@ -376,7 +596,7 @@ impl MetadataExtractor {
         let tag = tagged_file.primary_tag().or_else(|| tagged_file.first_tag());
         
         let mut metadata = MusicMetadata {
-            format: Some(format!("{:?}", tagged_file.file_type())),
+            format: Some(format_name(tagged_file.file_type())),
             ..Default::default()
         };
 
@@ -394,6 +614,7 @@ impl MetadataExtractor {
             metadata.album = tag.album().map(|s| s.to_string());
             metadata.album_artist = tag.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string());
             metadata.track_number = tag.track();
+            metadata.disc_number = tag.disk();
             metadata.year = tag.year();
             metadata.genre = tag.genre().map(|s| s.to_string());
             
@@ -432,6 +653,12 @@ impl MetadataExtractor {
             metadata.rating = tag.get_string(&ItemKey::Popularimeter)
                 .and_then(|s| s.parse::<u32>().ok());
 
+            // ReplayGain（dB）
+            metadata.replaygain_track_gain = tag.get_string(&ItemKey::ReplayGainTrackGain)
+                .and_then(parse_replay_gain);
+            metadata.replaygain_album_gain = tag.get_string(&ItemKey::ReplayGainAlbumGain)
+                .and_then(parse_replay_gain);
+
             // 提取专辑封面 - 优先选择前封面
             let pictures = tag.pictures();
             
@@ -500,3 +727,96 @@ impl MetadataExtractor {
     }
 }
 
+/// 解析ReplayGain增益标签，如 "-6.54 dB"、"+1.2dB"
+pub fn parse_replay_gain(value: &str) -> Option<f32> {
+    let trimmed = value.trim();
+    let number = trimmed
+        .strip_suffix("dB")
+        .or_else(|| trimmed.strip_suffix("db"))
+        .or_else(|| trimmed.strip_suffix("DB"))
+        .unwrap_or(trimmed)
+        .trim();
+    number.parse::<f32>().ok().filter(|g| g.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_replay_gain() {
+        assert_eq!(parse_replay_gain("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_replay_gain("+1.20dB"), Some(1.2));
+        assert_eq!(parse_replay_gain("0.5"), Some(0.5));
+        assert_eq!(parse_replay_gain("loud"), None);
+        assert_eq!(parse_replay_gain(""), None);
+    }
+
+    #[test]
+    fn test_path_pattern() {
+        let pattern = PathPattern::default();
+        let mut metadata = MusicMetadata::default();
+        pattern.fill(Path::new("/music/Queen/A Night at the Opera/11 Bohemian Rhapsody.flac"), &mut metadata);
+        assert_eq!(metadata.artist.as_deref(), Some("Queen"));
+        assert_eq!(metadata.album.as_deref(), Some("A Night at the Opera"));
+        assert_eq!(metadata.track_number, Some(11));
+        assert_eq!(metadata.title.as_deref(), Some("Bohemian Rhapsody"));
+
+        // 已读到的字段不被覆盖；分隔符被去掉
+        let mut metadata = MusicMetadata { artist: Some("Tagged".to_string()), ..Default::default() };
+        pattern.fill(Path::new("/music/Queen/Album/03 - Title.mp3"), &mut metadata);
+        assert_eq!(metadata.artist.as_deref(), Some("Tagged"));
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+
+        // 文件名与模板不符时标题使用文件名
+        let mut metadata = MusicMetadata::default();
+        pattern.fill(Path::new("/music/Queen/Album/Untitled.mp3"), &mut metadata);
+        assert_eq!(metadata.title.as_deref(), Some("Untitled"));
+        assert_eq!(metadata.track_number, None);
+
+        let pattern = PathPattern::parse("{album}/{artist} - {title}").unwrap();
+        let mut metadata = MusicMetadata::default();
+        pattern.fill(Path::new("/m/Hits/ABBA - Waterloo.ogg"), &mut metadata);
+        assert_eq!(
+            (metadata.album.as_deref(), metadata.artist.as_deref(), metadata.title.as_deref()),
+            (Some("Hits"), Some("ABBA"), Some("Waterloo"))
+        );
+
+        assert!(PathPattern::parse("").is_err());
+        assert!(PathPattern::parse("{artist}/{album}").is_err());
+        assert!(PathPattern::parse("{genre}/{title}").is_err());
+        assert!(PathPattern::parse("{title}/{title}").is_err());
+    }
+
+    #[test]
+    fn test_lenient_extraction_of_corrupt_file() {
+        let root = std::env::temp_dir().join(format!("windchime-metadata-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("Artist").join("Album");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("07 Song.mp3");
+        fs::write(&path, b"this is not an audio file").unwrap();
+
+        let extractor = MetadataExtractor::new();
+        assert!(extractor.extract_from_file(&path).is_err());
+        let metadata = extractor.extract_lenient(&path, &PathPattern::default());
+        assert!(metadata.extraction_error.is_some());
+        assert_eq!(metadata.title.as_deref(), Some("Song"));
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.album.as_deref(), Some("Album"));
+        assert_eq!(metadata.track_number, Some(7));
+        assert_eq!(metadata.format.as_deref(), Some("mp3"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_star_rating() {
+        let stars = |rating| MusicMetadata { rating, ..Default::default() }.star_rating();
+        assert_eq!(stars(None), None);
+        assert_eq!(stars(Some(0)), Some(0));
+        assert_eq!(stars(Some(20)), Some(1));
+        assert_eq!(stars(Some(50)), Some(3));
+        assert_eq!(stars(Some(100)), Some(5));
+        assert_eq!(stars(Some(255)), Some(5));
+    }
+}
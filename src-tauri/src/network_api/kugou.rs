@@ -0,0 +1,64 @@
+// 酷狗歌词
+//
+// 按「艺术家 - 标题」搜索歌词候选，下载第一条（内容为 base64 编码的 LRC）。
+// 酷狗歌词接口不提供封面。
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use serde::Deserialize;
+
+pub const DEFAULT_BASE_URL: &str = "https://lyrics.kugou.com";
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    id: String,
+    accesskey: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadResponse {
+    content: Option<String>,
+}
+
+/// 获取歌词（LRC）
+pub async fn fetch_lyrics(client: &reqwest::Client, base_url: &str, title: &str, artist: &str) -> Result<String> {
+    let keyword = format!("{} - {}", artist, title);
+    let response = client
+        .get(format!("{}/search", base_url))
+        .query(&[("ver", "1"), ("man", "yes"), ("client", "pc"), ("keyword", keyword.as_str())])
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: SearchResponse = response.json().await.map_err(|e| anyhow!("搜索结果格式错误: {}", e))?;
+    let candidate = body.candidates.into_iter().next().ok_or_else(|| anyhow!("没有找到歌词"))?;
+
+    let response = client
+        .get(format!("{}/download", base_url))
+        .query(&[
+            ("ver", "1"),
+            ("client", "pc"),
+            ("id", candidate.id.as_str()),
+            ("accesskey", candidate.accesskey.as_str()),
+            ("fmt", "lrc"),
+            ("charset", "utf8"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: DownloadResponse = response.json().await.map_err(|e| anyhow!("歌词格式错误: {}", e))?;
+    let encoded = body.content.ok_or_else(|| anyhow!("歌词内容为空"))?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| anyhow!("歌词解码失败: {}", e))?;
+    let lyrics = String::from_utf8_lossy(&decoded).trim_start_matches('\u{feff}').to_string();
+    if lyrics.trim().is_empty() {
+        return Err(anyhow!("歌词内容为空"));
+    }
+    Ok(lyrics)
+}
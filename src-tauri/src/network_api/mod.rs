@@ -0,0 +1,540 @@
+// 网络歌词和封面
+//
+// 来源在应用设置的 network 中配置（LrcApi、网易云、酷狗或兼容 LrcApi 接口的自建服务），
+// 获取时按优先级依次尝试已启用的来源，直到某个来源返回可用的结果，结果的 source 为该来源的名称。
+// - 每个来源有单独的超时，一次获取另有总时限，个别服务器无响应不会让歌词加载卡住
+// - 成功的结果在本次运行期间按（标题、艺术家、专辑）缓存，重新打开歌词面板不会重复请求
+
+pub mod kugou;
+pub mod musicbrainz;
+pub mod netease;
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LRCAPI_URL: &str = "https://api.lrc.cx";
+
+/// 测试来源时查询的歌曲
+const TEST_TITLE: &str = "晴天";
+const TEST_ARTIST: &str = "周杰伦";
+
+/// 缓存的歌词条数和封面张数上限（封面较大，单独限制）
+const LYRICS_CACHE_CAPACITY: usize = 500;
+const COVER_CACHE_CAPACITY: usize = 50;
+
+static LYRICS_CACHE: Lazy<Mutex<SessionCache<LyricsResult>>> =
+    Lazy::new(|| Mutex::new(SessionCache::new(LYRICS_CACHE_CAPACITY)));
+static COVER_CACHE: Lazy<Mutex<SessionCache<CoverResult>>> =
+    Lazy::new(|| Mutex::new(SessionCache::new(COVER_CACHE_CAPACITY)));
+
+/// 来源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceType {
+    Lrcapi,
+    Netease,
+    /// 只提供歌词
+    Kugou,
+    /// 兼容 LrcApi 接口（/lyrics、/cover）的自建服务
+    Custom,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkSource {
+    pub name: String,
+    pub base_url: String,
+    #[serde(rename = "type")]
+    pub source_type: SourceType,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 数值小的先尝试
+    #[serde(default)]
+    pub priority: i32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 网络来源设置（应用设置中的 network）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub sources: Vec<NetworkSource>,
+    /// 单个来源的超时（毫秒）
+    pub source_timeout_ms: u64,
+    /// 一次获取依次尝试所有来源的总时限（毫秒）
+    pub total_timeout_ms: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![NetworkSource {
+                name: "LrcApi".to_string(),
+                base_url: DEFAULT_LRCAPI_URL.to_string(),
+                source_type: SourceType::Lrcapi,
+                enabled: true,
+                priority: 0,
+            }],
+            source_timeout_ms: 3000,
+            total_timeout_ms: 8000,
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, source) in self.sources.iter().enumerate() {
+            if source.name.trim().is_empty() {
+                return Err("来源名称不能为空".to_string());
+            }
+            if self.sources[..index].iter().any(|other| other.name.eq_ignore_ascii_case(&source.name)) {
+                return Err(format!("来源名称重复: {}", source.name));
+            }
+            if !source.base_url.starts_with("http://") && !source.base_url.starts_with("https://") {
+                return Err(format!("来源 {} 的地址必须以 http:// 或 https:// 开头", source.name));
+            }
+        }
+        if !(100..=30_000).contains(&self.source_timeout_ms) {
+            return Err("单个来源的超时必须在100到30000毫秒之间".to_string());
+        }
+        if !(self.source_timeout_ms..=60_000).contains(&self.total_timeout_ms) {
+            return Err("总时限不能小于单个来源的超时，且不能超过60000毫秒".to_string());
+        }
+        Ok(())
+    }
+
+    /// 已启用的来源，按优先级排序（优先级相同时保持列表中的顺序）
+    pub fn chain(&self) -> Vec<&NetworkSource> {
+        let mut chain: Vec<&NetworkSource> = self.sources.iter().filter(|source| source.enabled).collect();
+        chain.sort_by_key(|source| source.priority);
+        chain
+    }
+}
+
+/// 来源测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceTestResult {
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub message: String,
+}
+
+/// 本次运行期间的结果缓存，超过容量时淘汰最早加入的
+struct SessionCache<T> {
+    capacity: usize,
+    entries: HashMap<CacheKey, T>,
+    order: VecDeque<CacheKey>,
+}
+
+type CacheKey = (String, String, String);
+
+impl<T: Clone> SessionCache<T> {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, entries: HashMap::new(), order: VecDeque::new() }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<T> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: CacheKey, value: T) {
+        if self.entries.insert(key.clone(), value).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+fn cache_key(title: Option<&str>, artist: &str, album: Option<&str>) -> CacheKey {
+    let normalize = |value: Option<&str>| value.unwrap_or_default().trim().to_lowercase();
+    (normalize(title), normalize(Some(artist)), normalize(album))
+}
+
+/// 清空缓存（来源设置改变后调用）
+pub fn clear_cache() {
+    if let Ok(mut cache) = LYRICS_CACHE.lock() {
+        cache.clear();
+    }
+    if let Ok(mut cache) = COVER_CACHE.lock() {
+        cache.clear();
+    }
+}
+
+/// 网络API服务 - 按设置的来源获取歌词和封面
+pub struct NetworkApiService {
+    client: reqwest::Client,
+    config: NetworkConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LyricsResult {
+    pub content: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverResult {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+    pub source: String,
+}
+
+impl NetworkApiService {
+    /// 使用默认来源（LrcApi）
+    pub fn new() -> Self {
+        Self::with_config(NetworkConfig::default())
+    }
+
+    pub fn with_config(config: NetworkConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_millis(config.total_timeout_ms))
+                .user_agent("WindChimePlayer/0.4.0")
+                .build()
+                .unwrap(),
+            config,
+        }
+    }
+
+    /// 从网络API获取歌词
+    /// 
+    /// # 参数
+    /// - `title`: 歌曲标题
+    /// - `artist`: 艺术家名称
+    /// - `album`: 专辑名称（可选）
+    pub async fn fetch_lyrics(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+    ) -> Result<LyricsResult> {
+        let key = cache_key(Some(title), artist, album);
+        if let Some(cached) = LYRICS_CACHE.lock().ok().and_then(|cache| cache.get(&key)) {
+            log::debug!("使用缓存的网络歌词: {} - {} ({})", title, artist, cached.source);
+            return Ok(cached);
+        }
+
+        log::info!("🌐 从网络API获取歌词: {} - {}", title, artist);
+        let result = self
+            .walk_chain("歌词", |source| self.lyrics_from(source, title, artist, album))
+            .await?;
+        log::info!("✅ 网络歌词获取成功（{}），长度: {} 字符", result.source, result.content.len());
+
+        if let Ok(mut cache) = LYRICS_CACHE.lock() {
+            cache.insert(key, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// 从网络API获取封面
+    /// 
+    /// # 参数
+    /// - `title`: 歌曲标题（可选）
+    /// - `artist`: 艺术家名称
+    /// - `album`: 专辑名称（可选）
+    pub async fn fetch_cover(
+        &self,
+        title: Option<&str>,
+        artist: &str,
+        album: Option<&str>,
+    ) -> Result<CoverResult> {
+        let key = cache_key(title, artist, album);
+        if let Some(cached) = COVER_CACHE.lock().ok().and_then(|cache| cache.get(&key)) {
+            log::debug!("使用缓存的网络封面: {} - {:?} ({})", artist, album, cached.source);
+            return Ok(cached);
+        }
+
+        log::info!("🌐 从网络API获取封面: {} - {:?}", artist, album);
+        let result = self
+            .walk_chain("封面", |source| self.cover_from(source, title, artist, album))
+            .await?;
+        log::info!("✅ 网络封面获取成功（{}），大小: {} 字节", result.source, result.data.len());
+
+        if let Ok(mut cache) = COVER_CACHE.lock() {
+            cache.insert(key, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// 测试来源：查询一首常见歌曲的歌词，不使用缓存
+    pub async fn test_source(&self, source: &NetworkSource) -> SourceTestResult {
+        let started = Instant::now();
+        let timeout = Duration::from_millis(self.config.source_timeout_ms);
+        let result = tokio::time::timeout(timeout, self.lyrics_from(source, TEST_TITLE, TEST_ARTIST, None)).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (ok, message) = match result {
+            Ok(Ok(lyrics)) => (true, format!("获取到 {} 字符的歌词", lyrics.content.len())),
+            Ok(Err(e)) => (false, e.to_string()),
+            Err(_) => (false, format!("超过 {} 毫秒没有响应", self.config.source_timeout_ms)),
+        };
+        SourceTestResult { ok, latency_ms, message }
+    }
+
+    /// 按优先级依次尝试各来源，返回第一个可用的结果
+    async fn walk_chain<'a, T, F, Fut>(&'a self, what: &str, fetch: F) -> Result<T>
+    where
+        F: Fn(&'a NetworkSource) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let chain = self.config.chain();
+        if chain.is_empty() {
+            return Err(anyhow!("没有启用的网络来源"));
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(self.config.total_timeout_ms);
+        let source_timeout = Duration::from_millis(self.config.source_timeout_ms);
+        let mut errors = Vec::new();
+        for source in chain {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                errors.push(format!("超过总时限 {} 毫秒", self.config.total_timeout_ms));
+                break;
+            }
+            match tokio::time::timeout(source_timeout.min(remaining), fetch(source)).await {
+                Ok(Ok(result)) => return Ok(result),
+                Ok(Err(e)) => errors.push(format!("{}: {}", source.name, e)),
+                Err(_) => errors.push(format!("{}: 超时", source.name)),
+            }
+            log::debug!("网络{}来源失败: {}", what, errors.last().map(String::as_str).unwrap_or_default());
+        }
+        Err(anyhow!("所有来源都没有获取到{}（{}）", what, errors.join("; ")))
+    }
+
+    async fn lyrics_from(
+        &self,
+        source: &NetworkSource,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+    ) -> Result<LyricsResult> {
+        let base_url = source.base_url.trim_end_matches('/');
+        let content = match source.source_type {
+            SourceType::Lrcapi | SourceType::Custom => self.lrcapi_lyrics(base_url, title, artist, album).await?,
+            SourceType::Netease => netease::fetch_lyrics(&self.client, base_url, title, artist).await?,
+            SourceType::Kugou => kugou::fetch_lyrics(&self.client, base_url, title, artist).await?,
+        };
+
+        if content.trim().is_empty() {
+            return Err(anyhow!("API返回空内容"));
+        }
+        Ok(LyricsResult {
+            content,
+            source: source.name.clone(),
+        })
+    }
+
+    async fn cover_from(
+        &self,
+        source: &NetworkSource,
+        title: Option<&str>,
+        artist: &str,
+        album: Option<&str>,
+    ) -> Result<CoverResult> {
+        let base_url = source.base_url.trim_end_matches('/');
+        let (data, mime_type) = match source.source_type {
+            SourceType::Lrcapi | SourceType::Custom => self.lrcapi_cover(base_url, title, artist, album).await?,
+            SourceType::Netease => {
+                let url = netease::find_cover_url(&self.client, base_url, title, artist).await?;
+                self.download_image(&url).await?
+            }
+            SourceType::Kugou => return Err(anyhow!("该来源不提供封面")),
+        };
+
+        // 有的服务找不到时返回 200 和错误页面，不是图片的结果视为没有找到
+        if data.is_empty() || image::guess_format(&data).is_err() {
+            return Err(anyhow!("API返回的不是图片"));
+        }
+        Ok(CoverResult {
+            data,
+            mime_type,
+            source: source.name.clone(),
+        })
+    }
+
+    async fn lrcapi_lyrics(&self, base_url: &str, title: &str, artist: &str, album: Option<&str>) -> Result<String> {
+        let url = format!("{}/lyrics", base_url);
+        let mut params = vec![
+            ("title", title),
+            ("artist", artist),
+        ];
+        
+        if let Some(album_name) = album {
+            params.push(("album", album_name));
+        }
+
+        let response = self.client
+            .get(&url)
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| anyhow!("网络请求失败: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("API返回错误状态: {}", response.status()));
+        }
+
+        response.text().await
+            .map_err(|e| anyhow!("读取响应失败: {}", e))
+    }
+
+    async fn lrcapi_cover(
+        &self,
+        base_url: &str,
+        title: Option<&str>,
+        artist: &str,
+        album: Option<&str>,
+    ) -> Result<(Vec<u8>, String)> {
+        let url = format!("{}/cover", base_url);
+        let mut params: Vec<(&str, &str)> = vec![("artist", artist)];
+        
+        if let Some(title_name) = title {
+            params.push(("title", title_name));
+        }
+        
+        if let Some(album_name) = album {
+            params.push(("album", album_name));
+        }
+
+        let response = self.client
+            .get(&url)
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| anyhow!("网络请求失败: {}", e))?;
+
+        read_image(response).await
+    }
+
+    async fn download_image(&self, url: &str) -> Result<(Vec<u8>, String)> {
+        let response = self.client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| anyhow!("网络请求失败: {}", e))?;
+        read_image(response).await
+    }
+}
+
+/// 读取图片响应，返回数据和 MIME 类型
+async fn read_image(response: reqwest::Response) -> Result<(Vec<u8>, String)> {
+    if !response.status().is_success() {
+        return Err(anyhow!("API返回错误状态: {}", response.status()));
+    }
+
+    // 获取 MIME 类型
+    let mime_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("image/jpeg")
+        .to_string();
+
+    let data = response.bytes().await
+        .map_err(|e| anyhow!("读取响应失败: {}", e))?
+        .to_vec();
+    Ok((data, mime_type))
+}
+
+impl Default for NetworkApiService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, priority: i32, enabled: bool) -> NetworkSource {
+        NetworkSource {
+            name: name.to_string(),
+            base_url: "https://example.com".to_string(),
+            source_type: SourceType::Custom,
+            enabled,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_chain_order_and_validation() {
+        let mut config = NetworkConfig {
+            sources: vec![source("b", 1, true), source("off", 0, false), source("a", 0, true), source("c", 1, true)],
+            ..Default::default()
+        };
+        let names: Vec<&str> = config.chain().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(config.validate().is_ok());
+
+        config.sources.push(source("A", 2, true));
+        assert!(config.validate().is_err());
+        config.sources.pop();
+
+        config.sources[0].base_url = "example.com".to_string();
+        assert!(config.validate().is_err());
+        config.sources[0].base_url = "https://example.com".to_string();
+
+        config.total_timeout_ms = config.source_timeout_ms - 1;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_session_cache_evicts_oldest() {
+        let mut cache = SessionCache::new(2);
+        cache.insert(cache_key(Some("A"), "X", None), 1);
+        cache.insert(cache_key(Some("B"), "X", None), 2);
+        cache.insert(cache_key(Some(" a "), "x", None), 3);
+        cache.insert(cache_key(Some("C"), "X", None), 4);
+        assert_eq!(cache.get(&cache_key(Some("A"), "X", None)), None);
+        assert_eq!(cache.get(&cache_key(Some("B"), "X", None)), Some(2));
+        assert_eq!(cache.get(&cache_key(Some("C"), "X", None)), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_lyrics() {
+        let service = NetworkApiService::new();
+        let result = service.fetch_lyrics("告白气球", "周杰伦", None).await;
+        
+        match result {
+            Ok(lyrics) => {
+                println!("歌词长度: {}", lyrics.content.len());
+                assert!(!lyrics.content.is_empty());
+            }
+            Err(e) => {
+                println!("获取歌词失败: {}", e);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_cover() {
+        let service = NetworkApiService::new();
+        let result = service.fetch_cover(Some("告白气球"), "周杰伦", None).await;
+        
+        match result {
+            Ok(cover) => {
+                println!("封面大小: {} 字节", cover.data.len());
+                assert!(!cover.data.is_empty());
+            }
+            Err(e) => {
+                println!("获取封面失败: {}", e);
+            }
+        }
+    }
+}
+
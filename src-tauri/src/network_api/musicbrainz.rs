@@ -0,0 +1,795 @@
+// MusicBrainz 元数据查询
+// 文档: https://musicbrainz.org/doc/MusicBrainz_API
+//
+// 根据曲目现有的标签和时长查询 MusicBrainz，返回候选元数据，用于修正错误或缺失的标签：
+// - 单曲：按标题、艺术家搜索录音，结合时长差异和专辑名排序
+// - 整张专辑：搜索发行版并获取曲目列表，按时长把本地曲目对应到发行版曲目，分配音轨号
+// - "Track 01"、"Unknown Artist" 之类的占位标签不参与查询，标题缺失时使用文件名
+// - 遵守 MusicBrainz 每秒1次请求的限制，并带上可识别的 User-Agent
+
+use crate::db::TrackMetadataUpdate;
+use crate::player::Track;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub const DEFAULT_ENDPOINT: &str = "https://musicbrainz.org/ws/2";
+
+/// MusicBrainz 要求 User-Agent 包含应用名、版本和联系方式
+const USER_AGENT: &str = concat!(
+    "WindChimePlayer/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/16Mu/wind-chime-player )"
+);
+
+/// 两次请求的最小间隔
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 单曲查询返回的候选数
+pub const MAX_CANDIDATES: usize = 10;
+
+/// 专辑查询时获取曲目列表的发行版数（每个发行版一次请求）
+const MAX_RELEASES: usize = 3;
+
+/// 时长相差超过这个值且标题不同的曲目不视为同一首（毫秒）
+const MAX_DURATION_DIFF_MS: i64 = 15_000;
+
+/// 上次请求时间，所有查询共用，保证全局每秒最多1次请求
+static LAST_REQUEST: Lazy<tokio::sync::Mutex<Option<Instant>>> = Lazy::new(|| tokio::sync::Mutex::new(None));
+
+/// 元数据查询错误
+#[derive(Debug, Error, PartialEq)]
+pub enum MetadataLookupError {
+    #[error("MetadataMissingTags: 曲目缺少可用于查询的标题或专辑名")]
+    MissingTags,
+
+    #[error("MetadataNoResults: MusicBrainz 没有找到匹配的结果")]
+    NoResults,
+
+    #[error("MetadataNetworkError: 无法连接 MusicBrainz: {0}")]
+    Network(String),
+
+    #[error("MetadataRateLimited: MusicBrainz 请求过于频繁，请稍后再试")]
+    RateLimited,
+
+    #[error("MetadataServiceError: MusicBrainz 返回错误 {status}: {message}")]
+    Service { status: u16, message: String },
+
+    #[error("MetadataInvalidResponse: 无法解析 MusicBrainz 响应: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<reqwest::Error> for MetadataLookupError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Self::InvalidResponse(e.to_string())
+        } else {
+            Self::Network(e.to_string())
+        }
+    }
+}
+
+/// 查询条件（来自曲目现有标签）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LookupQuery {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+impl LookupQuery {
+    /// 从曲目生成查询：忽略占位标签，标题缺失时从文件名（"01 - 艺术家 - 标题"）推断
+    pub fn from_track(track: &Track) -> Self {
+        let mut title = meaningful(&track.title);
+        let mut artist = meaningful(&track.artist).or_else(|| meaningful(&track.album_artist));
+        if title.is_none() {
+            let (file_artist, file_title) = parse_file_name(&track.path);
+            title = file_title;
+            artist = artist.or(file_artist);
+        }
+        Self {
+            title,
+            artist,
+            album: meaningful(&track.album),
+            duration_ms: track.duration_ms.filter(|ms| *ms > 0),
+        }
+    }
+}
+
+/// 候选元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataCandidate {
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub year: Option<i64>,
+    pub track_number: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub recording_mbid: Option<String>,
+    pub release_mbid: Option<String>,
+    /// 匹配程度 0–1
+    #[serde(default)]
+    pub confidence: f64,
+}
+
+impl MetadataCandidate {
+    /// 转为元数据修改，候选中没有的字段保持原样
+    pub fn to_update(&self) -> TrackMetadataUpdate {
+        TrackMetadataUpdate {
+            title: Some(self.title.clone()),
+            artist: self.artist.clone(),
+            album: self.album.clone(),
+            album_artist: self.album_artist.clone(),
+            genre: None,
+            year: self.year,
+            track_number: self.track_number,
+        }
+    }
+}
+
+/// 整张专辑与一个发行版的匹配结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumMatch {
+    pub release_mbid: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub year: Option<i64>,
+    pub track_count: usize,
+    /// 匹配程度 0–1
+    pub confidence: f64,
+    pub tracks: Vec<AlbumTrackMatch>,
+    /// 没有对应到发行版曲目的本地曲目
+    pub unmatched_track_ids: Vec<i64>,
+}
+
+/// 本地曲目与发行版曲目的对应
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumTrackMatch {
+    pub track_id: i64,
+    pub candidate: MetadataCandidate,
+}
+
+// ---- MusicBrainz JSON ----
+
+#[derive(Debug, Deserialize)]
+struct RecordingSearch {
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseSearch {
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Recording {
+    id: String,
+    title: String,
+    #[serde(default)]
+    score: Option<u32>,
+    length: Option<i64>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    id: String,
+    title: String,
+    date: Option<String>,
+    #[serde(default)]
+    score: Option<u32>,
+    #[serde(rename = "track-count")]
+    track_count: Option<usize>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    media: Vec<Medium>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Medium {
+    #[serde(rename = "track-offset")]
+    track_offset: Option<i64>,
+    /// 搜索结果中为 track，发行版查询中为 tracks
+    #[serde(default, alias = "track")]
+    tracks: Vec<ReleaseTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseTrack {
+    position: Option<i64>,
+    number: Option<String>,
+    title: String,
+    length: Option<i64>,
+    recording: Option<RecordingRef>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordingRef {
+    id: String,
+    length: Option<i64>,
+}
+
+/// MusicBrainz 客户端
+pub struct MusicBrainzClient {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl MusicBrainzClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(15))
+                .user_agent(USER_AGENT)
+                .build()
+                .unwrap(),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+
+    /// 查询单首曲目，返回按匹配程度排序的候选
+    pub async fn lookup_track(&self, query: &LookupQuery) -> Result<Vec<MetadataCandidate>, MetadataLookupError> {
+        let search = recording_query(query).ok_or(MetadataLookupError::MissingTags)?;
+        log::info!("🔎 MusicBrainz 查询录音: {}", search);
+
+        let response: RecordingSearch = self.get("recording", &[("query", search.as_str()), ("limit", "25")]).await?;
+        let candidates = rank_recordings(query, response.recordings);
+        if candidates.is_empty() {
+            return Err(MetadataLookupError::NoResults);
+        }
+        Ok(candidates)
+    }
+
+    /// 把一组曲目（通常是同一文件夹）作为整张专辑查询
+    pub async fn lookup_album(&self, tracks: &[Track]) -> Result<Vec<AlbumMatch>, MetadataLookupError> {
+        let (album, artist) = album_query(tracks).ok_or(MetadataLookupError::MissingTags)?;
+        let search = release_query(&album, artist.as_deref(), tracks.len());
+        log::info!("🔎 MusicBrainz 查询专辑: {}", search);
+
+        let response: ReleaseSearch = self.get("release", &[("query", search.as_str()), ("limit", "10")]).await?;
+        let mut releases = response.releases;
+        releases.sort_by(|a, b| release_score(b, tracks.len()).total_cmp(&release_score(a, tracks.len())));
+        releases.truncate(MAX_RELEASES);
+
+        let mut matches = Vec::new();
+        for release in releases {
+            let search_score = release.score;
+            let path = format!("release/{}", release.id);
+            let mut release: Release = self.get(&path, &[("inc", "recordings+artist-credits")]).await?;
+            release.score = search_score;
+            if let Some(album_match) = match_album(tracks, &release) {
+                matches.push(album_match);
+            }
+        }
+
+        if matches.is_empty() {
+            return Err(MetadataLookupError::NoResults);
+        }
+        matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        Ok(matches)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T, MetadataLookupError> {
+        throttle().await;
+
+        let response = self.client
+            .get(format!("{}/{}", self.endpoint, path))
+            .query(params)
+            .query(&[("fmt", "json")])
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.as_u16() == 503 || status.as_u16() == 429 {
+            return Err(MetadataLookupError::RateLimited);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(MetadataLookupError::Service {
+                status: status.as_u16(),
+                message: body.chars().take(200).collect(),
+            });
+        }
+
+        let body = response.text().await?;
+        serde_json::from_str(&body).map_err(|e| MetadataLookupError::InvalidResponse(e.to_string()))
+    }
+}
+
+impl Default for MusicBrainzClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 等待到距上次请求至少1秒
+async fn throttle() {
+    let mut last = LAST_REQUEST.lock().await;
+    if let Some(previous) = *last {
+        let elapsed = previous.elapsed();
+        if elapsed < MIN_REQUEST_INTERVAL {
+            tokio::time::sleep(MIN_REQUEST_INTERVAL - elapsed).await;
+        }
+    }
+    *last = Some(Instant::now());
+}
+
+/// 去掉首尾空白后的有效标签，占位值视为没有
+fn meaningful(value: &Option<String>) -> Option<String> {
+    value.as_deref()
+        .map(str::trim)
+        .filter(|v| !is_placeholder(v))
+        .map(str::to_string)
+}
+
+/// "Track 01"、"Unknown Artist"、"未知艺术家" 等占位标签
+fn is_placeholder(value: &str) -> bool {
+    let lower = value.trim().to_lowercase();
+    if matches!(
+        lower.as_str(),
+        "" | "unknown" | "unknown artist" | "unknown album" | "unknown title" | "未知" | "未知艺术家" | "未知专辑"
+    ) {
+        return true;
+    }
+    ["audio track", "track", "曲目"].iter().any(|prefix| {
+        lower.strip_prefix(prefix)
+            .map(|rest| rest.trim())
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// 从文件名推断（艺术家, 标题），去掉开头的音轨号
+fn parse_file_name(path: &str) -> (Option<String>, Option<String>) {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = stem.trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '_'));
+    let non_empty = |value: &str| Some(value.trim().to_string()).filter(|v| !is_placeholder(v));
+    match stem.split_once(" - ") {
+        Some((artist, title)) => (non_empty(artist), non_empty(title)),
+        None => (None, non_empty(stem)),
+    }
+}
+
+/// Lucene 短语：加引号并转义引号和反斜杠
+fn phrase(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 录音搜索语句，没有标题时无法查询
+fn recording_query(query: &LookupQuery) -> Option<String> {
+    let title = query.title.as_deref()?;
+    let mut search = format!("recording:{}", phrase(title));
+    if let Some(artist) = &query.artist {
+        search.push_str(&format!(" AND artist:{}", phrase(artist)));
+    }
+    Some(search)
+}
+
+/// 发行版搜索语句
+fn release_query(album: &str, artist: Option<&str>, track_count: usize) -> String {
+    let mut search = format!("release:{}", phrase(album));
+    if let Some(artist) = artist {
+        search.push_str(&format!(" AND artist:{}", phrase(artist)));
+    }
+    // 曲目数只用于提高相同曲目数的发行版的得分，不作为必要条件
+    search.push_str(&format!(" tracks:{}", track_count));
+    search
+}
+
+/// 一组曲目中最常见的专辑名和艺术家；专辑名都缺失时用文件夹名（"艺术家 - 专辑"）
+fn album_query(tracks: &[Track]) -> Option<(String, Option<String>)> {
+    let album = most_common(tracks.iter().filter_map(|track| meaningful(&track.album)));
+    let artist = most_common(tracks.iter().filter_map(|track| {
+        meaningful(&track.album_artist).or_else(|| meaningful(&track.artist))
+    }));
+    if let Some(album) = album {
+        return Some((album, artist));
+    }
+
+    let folder = tracks.first()
+        .and_then(|track| Path::new(&track.path).parent())
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())?;
+    match folder.split_once(" - ") {
+        Some((folder_artist, folder_album)) if !folder_album.trim().is_empty() => Some((
+            folder_album.trim().to_string(),
+            artist.or_else(|| Some(folder_artist.trim().to_string()).filter(|a| !is_placeholder(a))),
+        )),
+        _ => Some(folder.trim().to_string()).filter(|f| !is_placeholder(f)).map(|f| (f, artist)),
+    }
+}
+
+fn most_common(values: impl Iterator<Item = String>) -> Option<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for value in values {
+        let count = counts.entry(value.clone()).or_insert(0);
+        if *count == 0 {
+            order.push(value);
+        }
+        *count += 1;
+    }
+    // 次数相同时取先出现的
+    order.into_iter().rev().max_by_key(|value| counts[value])
+}
+
+fn credit_name(credits: &[ArtistCredit]) -> Option<String> {
+    if credits.is_empty() {
+        return None;
+    }
+    Some(credits.iter().map(|credit| format!("{}{}", credit.name, credit.joinphrase)).collect())
+}
+
+/// 日期 "2003-07-31" / "2003" 的年份
+fn release_year(date: &Option<String>) -> Option<i64> {
+    date.as_deref()?.get(..4)?.parse().ok()
+}
+
+/// 用于比较的标题：小写，只保留字母和数字
+fn normalize(value: &str) -> String {
+    value.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// 时长接近程度 0–1，两边都知道时长才有意义
+fn duration_closeness(local_ms: Option<i64>, remote_ms: Option<i64>) -> Option<f64> {
+    let diff = (local_ms? - remote_ms?).abs().min(MAX_DURATION_DIFF_MS);
+    Some(1.0 - diff as f64 / MAX_DURATION_DIFF_MS as f64)
+}
+
+/// 按搜索得分、时长和专辑名为录音打分，每个录音取最合适的发行版
+fn rank_recordings(query: &LookupQuery, recordings: Vec<Recording>) -> Vec<MetadataCandidate> {
+    let query_album = query.album.as_deref().map(normalize);
+    let mut candidates: Vec<MetadataCandidate> = recordings.into_iter()
+        .map(|recording| {
+            // 优先和现有专辑名相同的发行版，其次最早发行的
+            let release = recording.releases.iter()
+                .min_by_key(|release| {
+                    let same_album = query_album.as_deref() == Some(normalize(&release.title).as_str());
+                    (!same_album, release_year(&release.date).unwrap_or(i64::MAX))
+                });
+            let album_matches = match (&query_album, release) {
+                (Some(album), Some(release)) => *album == normalize(&release.title),
+                _ => false,
+            };
+
+            let mut confidence = recording.score.unwrap_or(0).min(100) as f64 / 100.0 * 0.6;
+            confidence += 0.3 * duration_closeness(query.duration_ms, recording.length).unwrap_or(0.5);
+            if album_matches || query_album.is_none() {
+                confidence += 0.1;
+            }
+
+            let track_number = release
+                .and_then(|release| release.media.first())
+                .and_then(|medium| {
+                    let track = medium.tracks.first();
+                    track.and_then(|t| t.position)
+                        .or_else(|| track.and_then(|t| t.number.as_deref()?.parse().ok()))
+                        .or_else(|| medium.track_offset.map(|offset| offset + 1))
+                });
+
+            MetadataCandidate {
+                title: recording.title,
+                artist: credit_name(&recording.artist_credit),
+                album: release.map(|release| release.title.clone()),
+                album_artist: release.and_then(|release| credit_name(&release.artist_credit)),
+                year: release.and_then(|release| release_year(&release.date)),
+                track_number,
+                duration_ms: recording.length,
+                recording_mbid: Some(recording.id),
+                release_mbid: release.map(|release| release.id.clone()),
+                confidence,
+            }
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+/// 发行版搜索结果的排序：搜索得分，加上曲目数与本地一致的奖励
+fn release_score(release: &Release, local_count: usize) -> f64 {
+    let score = release.score.unwrap_or(0).min(100) as f64 / 100.0;
+    let count_bonus = match release.track_count {
+        Some(count) if count == local_count => 0.3,
+        Some(count) if count.abs_diff(local_count) <= 2 => 0.1,
+        _ => 0.0,
+    };
+    score + count_bonus
+}
+
+/// 发行版曲目（多碟展开），音轨号为碟内序号
+struct FlatTrack<'a> {
+    track: &'a ReleaseTrack,
+    track_number: i64,
+}
+
+impl FlatTrack<'_> {
+    fn length(&self) -> Option<i64> {
+        self.track.length.or_else(|| self.track.recording.as_ref()?.length)
+    }
+}
+
+/// 把本地曲目对应到发行版曲目
+///
+/// 按（时长差 - 标题相同的奖励）从小到大贪心分配，每首发行版曲目只用一次。
+/// 时长相差太大且标题不同的组合不会被分配
+fn assign_tracks(locals: &[(Option<i64>, Option<String>)], remotes: &[(Option<i64>, String)]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, (local_ms, local_title)) in locals.iter().enumerate() {
+        for (j, (remote_ms, remote_title)) in remotes.iter().enumerate() {
+            let same_title = local_title.as_deref()
+                .is_some_and(|title| !title.is_empty() && normalize(title) == normalize(remote_title));
+            let diff = match (local_ms, remote_ms) {
+                (Some(a), Some(b)) => Some((a - b).abs()),
+                _ => None,
+            };
+            let cost = match (diff, same_title) {
+                (Some(diff), true) => diff - MAX_DURATION_DIFF_MS,
+                (None, true) => 0,
+                (Some(diff), false) if diff <= MAX_DURATION_DIFF_MS => diff,
+                _ => continue,
+            };
+            pairs.push((cost, i, j));
+        }
+    }
+    pairs.sort();
+
+    let mut used_local = vec![false; locals.len()];
+    let mut used_remote = vec![false; remotes.len()];
+    let mut assigned = Vec::new();
+    for (_, i, j) in pairs {
+        if !used_local[i] && !used_remote[j] {
+            used_local[i] = true;
+            used_remote[j] = true;
+            assigned.push((i, j));
+        }
+    }
+    assigned.sort();
+    assigned
+}
+
+/// 一组本地曲目与一个发行版的匹配，一首都对应不上时返回 None
+fn match_album(tracks: &[Track], release: &Release) -> Option<AlbumMatch> {
+    let flat: Vec<FlatTrack> = release.media.iter()
+        .flat_map(|medium| {
+            medium.tracks.iter().enumerate().map(|(index, track)| FlatTrack {
+                track,
+                track_number: track.position.unwrap_or(index as i64 + 1),
+            })
+        })
+        .collect();
+
+    let locals: Vec<(Option<i64>, Option<String>)> = tracks.iter()
+        .map(|track| (track.duration_ms.filter(|ms| *ms > 0), LookupQuery::from_track(track).title))
+        .collect();
+    let remotes: Vec<(Option<i64>, String)> = flat.iter()
+        .map(|flat_track| (flat_track.length(), flat_track.track.title.clone()))
+        .collect();
+    let assigned = assign_tracks(&locals, &remotes);
+    if assigned.is_empty() {
+        return None;
+    }
+
+    let album_artist = credit_name(&release.artist_credit);
+    let year = release_year(&release.date);
+    let mut closeness_total = 0.0;
+    let matched: Vec<AlbumTrackMatch> = assigned.iter()
+        .map(|&(i, j)| {
+            let flat_track = &flat[j];
+            closeness_total += duration_closeness(locals[i].0, flat_track.length()).unwrap_or(0.5);
+            AlbumTrackMatch {
+                track_id: tracks[i].id,
+                candidate: MetadataCandidate {
+                    title: flat_track.track.title.clone(),
+                    artist: credit_name(&flat_track.track.artist_credit).or_else(|| album_artist.clone()),
+                    album: Some(release.title.clone()),
+                    album_artist: album_artist.clone(),
+                    year,
+                    track_number: Some(flat_track.track_number),
+                    duration_ms: flat_track.length(),
+                    recording_mbid: flat_track.track.recording.as_ref().map(|recording| recording.id.clone()),
+                    release_mbid: Some(release.id.clone()),
+                    confidence: duration_closeness(locals[i].0, flat_track.length()).unwrap_or(0.5),
+                },
+            }
+        })
+        .collect();
+
+    let unmatched_track_ids = tracks.iter()
+        .enumerate()
+        .filter(|(i, _)| !assigned.iter().any(|(local, _)| local == i))
+        .map(|(_, track)| track.id)
+        .collect();
+
+    // 搜索得分、本地曲目覆盖率、曲目数一致、平均时长接近程度
+    let coverage = matched.len() as f64 / tracks.len().max(1) as f64;
+    let count_matches = if flat.len() == tracks.len() { 1.0 } else { 0.0 };
+    let confidence = release.score.unwrap_or(0).min(100) as f64 / 100.0 * 0.3
+        + coverage * 0.4
+        + count_matches * 0.1
+        + closeness_total / matched.len() as f64 * 0.2;
+
+    Some(AlbumMatch {
+        release_mbid: release.id.clone(),
+        title: release.title.clone(),
+        artist: album_artist,
+        year,
+        track_count: flat.len(),
+        confidence,
+        tracks: matched,
+        unmatched_track_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64, path: &str, title: Option<&str>, artist: Option<&str>, album: Option<&str>, duration_ms: i64) -> Track {
+        Track {
+            id,
+            path: path.to_string(),
+            title: title.map(str::to_string),
+            artist: artist.map(str::to_string),
+            album: album.map(str::to_string),
+            duration_ms: Some(duration_ms),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            cover_id: None,
+            rating: None,
+            verify_status: None,
+            album_cover_data: None,
+            album_cover_mime: None,
+            artist_photo_data: None,
+            artist_photo_mime: None,
+            embedded_lyrics: None,
+        }
+    }
+
+    #[test]
+    fn test_placeholder_tags_fall_back_to_file_name() {
+        assert!(is_placeholder("Track 01"));
+        assert!(is_placeholder("unknown artist"));
+        assert!(is_placeholder("未知艺术家"));
+        assert!(!is_placeholder("Track of the Year"));
+
+        let bad = track(1, "/music/07 - Radiohead - Airbag.mp3", Some("Track 07"), Some("Unknown Artist"), None, 284_000);
+        let query = LookupQuery::from_track(&bad);
+        assert_eq!(query.title.as_deref(), Some("Airbag"));
+        assert_eq!(query.artist.as_deref(), Some("Radiohead"));
+        assert_eq!(recording_query(&query).unwrap(), "recording:\"Airbag\" AND artist:\"Radiohead\"");
+
+        let nothing = track(2, "/music/01.mp3", Some("Track 01"), None, None, 1000);
+        assert_eq!(recording_query(&LookupQuery::from_track(&nothing)), None);
+    }
+
+    #[test]
+    fn test_phrase_escapes_quotes() {
+        assert_eq!(phrase("Say \"Hi\"\\"), "\"Say \\\"Hi\\\"\\\\\"");
+    }
+
+    #[test]
+    fn test_rank_recordings_prefers_close_duration_and_same_album() {
+        let json = r#"{"recordings": [
+            {"id": "r1", "score": 100, "title": "Airbag", "length": 200000,
+             "artist-credit": [{"name": "Radiohead"}],
+             "releases": [{"id": "x", "title": "Live", "date": "2001"}]},
+            {"id": "r2", "score": 95, "title": "Airbag", "length": 284000,
+             "artist-credit": [{"name": "Radiohead"}],
+             "releases": [
+                {"id": "best-of", "title": "The Best Of", "date": "2008"},
+                {"id": "okc", "title": "OK Computer", "date": "1997-05-21",
+                 "artist-credit": [{"name": "Radiohead"}],
+                 "media": [{"track-offset": 0, "track": [{"number": "1", "title": "Airbag"}]}]}
+             ]}
+        ]}"#;
+        let search: RecordingSearch = serde_json::from_str(json).unwrap();
+        let query = LookupQuery {
+            title: Some("Airbag".into()),
+            artist: Some("Radiohead".into()),
+            album: Some("OK Computer".into()),
+            duration_ms: Some(284_500),
+        };
+
+        let candidates = rank_recordings(&query, search.recordings);
+        assert_eq!(candidates.len(), 2);
+        let best = &candidates[0];
+        assert_eq!(best.recording_mbid.as_deref(), Some("r2"));
+        assert_eq!(best.release_mbid.as_deref(), Some("okc"));
+        assert_eq!(best.album.as_deref(), Some("OK Computer"));
+        assert_eq!(best.year, Some(1997));
+        assert_eq!(best.track_number, Some(1));
+        assert!(best.confidence > candidates[1].confidence);
+    }
+
+    #[test]
+    fn test_assign_tracks_by_duration() {
+        let locals = vec![(Some(241_000), None), (Some(180_000), None), (Some(300_000), None), (Some(10_000), None)];
+        let remotes = vec![
+            (Some(179_000), "A".to_string()),
+            (Some(240_000), "B".to_string()),
+            (Some(301_000), "C".to_string()),
+        ];
+        assert_eq!(assign_tracks(&locals, &remotes), vec![(0, 1), (1, 0), (2, 2)]);
+
+        // 标题相同优先于时长更接近
+        let locals = vec![(Some(200_000), Some("Song B".to_string())), (Some(201_000), None)];
+        let remotes = vec![(Some(200_500), "Song A".to_string()), (Some(205_000), "Song B".to_string())];
+        assert_eq!(assign_tracks(&locals, &remotes), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_match_album_assigns_track_numbers() {
+        let json = r#"{"id": "rel", "title": "Album", "date": "2010-01-01",
+            "artist-credit": [{"name": "A", "joinphrase": " & "}, {"name": "B"}],
+            "media": [
+                {"position": 1, "tracks": [
+                    {"position": 1, "title": "One", "length": 100000, "recording": {"id": "rec1"}},
+                    {"position": 2, "title": "Two", "length": 200000, "recording": {"id": "rec2"}}
+                ]},
+                {"position": 2, "tracks": [
+                    {"position": 1, "title": "Three", "recording": {"id": "rec3", "length": 300000}}
+                ]}
+            ]}"#;
+        let release: Release = serde_json::from_str(json).unwrap();
+        let tracks = vec![
+            track(10, "/a/Album/x.mp3", Some("Track 01"), None, Some("Album"), 299_000),
+            track(11, "/a/Album/y.mp3", Some("Track 02"), None, Some("Album"), 101_000),
+            track(12, "/a/Album/z.mp3", Some("Track 03"), None, Some("Album"), 999_000),
+        ];
+
+        let album = match_album(&tracks, &release).unwrap();
+        assert_eq!(album.artist.as_deref(), Some("A & B"));
+        assert_eq!(album.track_count, 3);
+        assert_eq!(album.unmatched_track_ids, vec![12]);
+        assert_eq!(album.tracks.len(), 2);
+        assert_eq!(album.tracks[0].track_id, 10);
+        assert_eq!(album.tracks[0].candidate.title, "Three");
+        assert_eq!(album.tracks[0].candidate.track_number, Some(1));
+        assert_eq!(album.tracks[0].candidate.recording_mbid.as_deref(), Some("rec3"));
+        assert_eq!(album.tracks[1].candidate.title, "One");
+        assert_eq!(album.tracks[1].candidate.year, Some(2010));
+    }
+
+    #[test]
+    fn test_album_query_uses_folder_name_when_album_missing() {
+        let tracks = vec![track(1, "/music/Radiohead - OK Computer/01.mp3", None, None, None, 1000)];
+        assert_eq!(
+            album_query(&tracks),
+            Some(("OK Computer".to_string(), Some("Radiohead".to_string())))
+        );
+
+        let tagged = vec![
+            track(1, "/m/x/1.mp3", None, Some("A"), Some("Right"), 1000),
+            track(2, "/m/x/2.mp3", None, Some("A"), Some("Right"), 1000),
+            track(3, "/m/x/3.mp3", None, Some("B"), Some("Wrong"), 1000),
+        ];
+        assert_eq!(album_query(&tagged), Some(("Right".to_string(), Some("A".to_string()))));
+    }
+}
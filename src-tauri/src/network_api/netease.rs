@@ -0,0 +1,137 @@
+// 网易云音乐歌词和封面
+//
+// 使用网页端公开接口：先搜索歌曲（或艺术家）得到ID，再获取歌词、专辑封面或艺术家图片。
+// 搜索结果优先取标题和艺术家都一致的歌曲，否则取第一条。
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+pub const DEFAULT_BASE_URL: &str = "https://music.163.com";
+
+/// 搜索类型：单曲 / 艺术家
+const SEARCH_SONG: &str = "1";
+const SEARCH_ARTIST: &str = "100";
+
+/// 每次搜索返回的结果数
+const SEARCH_LIMIT: &str = "5";
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    result: Option<SearchResult>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SearchResult {
+    songs: Vec<Song>,
+    artists: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Song {
+    id: i64,
+    name: String,
+    #[serde(default)]
+    artists: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artist {
+    name: String,
+    #[serde(rename = "picUrl")]
+    pic_url: Option<String>,
+    #[serde(rename = "img1v1Url")]
+    img1v1_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricResponse {
+    lrc: Option<LyricContent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricContent {
+    lyric: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SongDetailResponse {
+    #[serde(default)]
+    songs: Vec<SongDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SongDetail {
+    album: Option<AlbumDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlbumDetail {
+    #[serde(rename = "picUrl")]
+    pic_url: Option<String>,
+}
+
+async fn search(client: &reqwest::Client, base_url: &str, keyword: &str, search_type: &str) -> Result<SearchResult> {
+    let response = client
+        .get(format!("{}/api/search/get/web", base_url))
+        .query(&[("s", keyword), ("type", search_type), ("limit", SEARCH_LIMIT), ("offset", "0")])
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: SearchResponse = response.json().await.map_err(|e| anyhow!("搜索结果格式错误: {}", e))?;
+    Ok(body.result.unwrap_or_default())
+}
+
+/// 搜索歌曲ID，标题和艺术家都一致的优先
+async fn find_song(client: &reqwest::Client, base_url: &str, title: &str, artist: &str) -> Result<i64> {
+    let songs = search(client, base_url, &format!("{} {}", title, artist), SEARCH_SONG).await?.songs;
+    let exact = songs.iter().find(|song| {
+        song.name.eq_ignore_ascii_case(title) && song.artists.iter().any(|a| a.name.eq_ignore_ascii_case(artist))
+    });
+    exact
+        .or(songs.first())
+        .map(|song| song.id)
+        .ok_or_else(|| anyhow!("没有找到歌曲"))
+}
+
+/// 获取歌词（LRC）
+pub async fn fetch_lyrics(client: &reqwest::Client, base_url: &str, title: &str, artist: &str) -> Result<String> {
+    let id = find_song(client, base_url, title, artist).await?;
+    let response = client
+        .get(format!("{}/api/song/lyric", base_url))
+        .query(&[("id", id.to_string().as_str()), ("lv", "1")])
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: LyricResponse = response.json().await.map_err(|e| anyhow!("歌词格式错误: {}", e))?;
+    body.lrc
+        .and_then(|lrc| lrc.lyric)
+        .filter(|lyric| !lyric.trim().is_empty())
+        .ok_or_else(|| anyhow!("歌曲没有歌词"))
+}
+
+/// 获取封面图片地址：有标题时取歌曲所在专辑的封面，否则取艺术家图片
+pub async fn find_cover_url(client: &reqwest::Client, base_url: &str, title: Option<&str>, artist: &str) -> Result<String> {
+    let url = match title {
+        Some(title) => {
+            let id = find_song(client, base_url, title, artist).await?;
+            let response = client
+                .get(format!("{}/api/song/detail", base_url))
+                .query(&[("ids", format!("[{}]", id))])
+                .send()
+                .await?
+                .error_for_status()?;
+            let body: SongDetailResponse = response.json().await.map_err(|e| anyhow!("歌曲信息格式错误: {}", e))?;
+            body.songs.into_iter().next().and_then(|song| song.album).and_then(|album| album.pic_url)
+        }
+        None => {
+            let artists = search(client, base_url, artist, SEARCH_ARTIST).await?.artists;
+            let found = artists
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(artist))
+                .or(artists.first());
+            found.and_then(|a| a.pic_url.clone().or_else(|| a.img1v1_url.clone()))
+        }
+    };
+    url.filter(|url| !url.is_empty()).ok_or_else(|| anyhow!("没有找到封面"))
+}
@@ -0,0 +1,557 @@
+// 派对模式模块
+//
+// 派对模式下朋友可以控制播放和队列，但不能改动曲库、歌单和设置。
+// 命令放行采用白名单：未登记在 GUEST_SAFE_COMMANDS 中的命令一律拒绝，
+// 新增命令默认不对访客开放。
+//
+// PIN 以 PBKDF2-HMAC-SHA256 加随机盐存储；连续输错后按指数退避锁定，
+// 4 位 PIN 不能靠反复调用 party_mode_disable 穷举。
+
+use base64::Engine;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// 设置表中的键
+pub const SETTING_PIN_HASH: &str = "party_mode.pin_hash";
+pub const SETTING_ACTIVE: &str = "party_mode.active";
+pub const SETTING_QUEUE_RATE_LIMIT: &str = "party_mode.queue_rate_limit";
+
+/// 队列限速的统计窗口
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// PIN 哈希格式：`pbkdf2-sha256$迭代次数$base64(盐)$base64(摘要)`
+const PIN_HASH_SCHEME: &str = "pbkdf2-sha256";
+const PIN_HASH_ITERATIONS: u32 = 100_000;
+const PIN_SALT_LEN: usize = 16;
+const PIN_DIGEST_LEN: usize = 32;
+
+/// 不触发锁定的连续输错次数
+const FREE_PIN_ATTEMPTS: u32 = 3;
+
+/// 锁定时长上限
+const MAX_PIN_LOCKOUT: Duration = Duration::from_secs(300);
+
+/// 派对模式下仍可调用的命令（访客安全）
+///
+/// 只登记播放控制、队列、搜索、歌词等只读或不破坏数据的命令。
+pub const GUEST_SAFE_COMMANDS: &[&str] = &[
+    // 音频读取
+    "read_audio_file_open",
+    "read_audio_file_chunk",
+    "read_audio_file_close",
+    "get_track",
+    "get_current_position",
+    // 播放控制
+    "player_play",
+    "player_pause",
+    "player_resume",
+    "player_stop",
+    "player_next",
+    "player_previous",
+    "player_seek",
+    "player_seek_relative",
+    "player_next_chapter",
+    "player_previous_chapter",
+    "player_set_loop_region",
+    "player_clear_loop_region",
+    "player_set_visualization_enabled",
+    "player_set_volume",
+    "player_set_repeat",
+    "player_set_shuffle",
+    "player_set_gapless",
+    "player_get_crossfade",
+    "player_get_sleep_timer",
+    "player_get_sleep_fade",
+    "player_get_pause_fade",
+    "player_get_rate",
+    "player_get_preserve_pitch",
+    "player_get_shuffle_skip_broken",
+    "player_get_seek_step",
+    "player_get_position_interval",
+    "player_get_resume_settings",
+    "track_get_saved_position",
+    "track_get_chapters",
+    "track_get_trim_points",
+    "player_get_silence_settings",
+    "player_get_replay_gain",
+    "player_load_playlist",
+    "player_play_track",
+    // 队列生成
+    "generate_sequential_playlist",
+    "generate_random_playlist",
+    "load_playlist_by_mode",
+    // 曲库浏览与搜索
+    "library_get_tracks",
+    "library_get_tracks_chunk_size",
+    "library_search",
+    "library_get_stats",
+    "library_get_music_folders",
+    "library_browse_folder",
+    "library_get_tracks_page",
+    "library_get_albums",
+    "library_get_artists",
+    "library_get_album_tracks",
+    "library_search_suggest",
+    "search_history_get",
+    "library_get_watcher",
+    "library_get_scan_issues",
+    "library_get_verify_issues",
+    "library_get_verify_on_scan",
+    "library_get_track_artists",
+    "library_get_path_pattern",
+    "library_get_transliterate_search",
+    "library_get_fingerprinting",
+    "library_find_duplicates",
+    // 歌词（只读）
+    "lyrics_get",
+    "lyrics_parse",
+    "lyrics_search_file",
+    "lyrics_load_file",
+    "lyrics_extract_from_metadata",
+    "lyrics_search_comprehensive",
+    "lyrics_validate",
+    "lyrics_parse_srt",
+    "lyrics_parse_ass",
+    "lyrics_parse_vtt",
+    "lyrics_auto_detect",
+    "lyrics_format_as_lrc",
+    "lyrics_get_current_line",
+    "lyrics_get_current_word",
+    "lyrics_fetch_is_running",
+    "network_fetch_lyrics",
+    "network_fetch_cover",
+    "artist_cover_get",
+    "artist_covers_get_all",
+    // 收藏（只允许添加和查询）
+    "favorites_add",
+    "favorites_is_favorite",
+    "favorites_get_all",
+    "favorites_get_count",
+    // 歌单（只读）
+    "playlists_list",
+    "playlists_get_detail",
+    "playlists_get_tracks",
+    "playlists_get_stats",
+    "playlists_export_preview",
+    "playlists_mark_played",
+    "playlists_play",
+    // 播放历史
+    "get_play_history",
+    "get_play_statistics",
+    "stats_top_artists",
+    "stats_top_albums",
+    "stats_plays_per_day",
+    "stats_listening_clock",
+    "add_play_history",
+    "history_get_scrobble_threshold",
+    // 窗口
+    "minimize_window",
+    "toggle_maximize",
+    "close_window",
+    // 封面与只读信息
+    "get_cover",
+    "get_covers_for_tracks",
+    "get_album_cover",
+    "get_album_cover_palette",
+    "get_audio_enhancement_settings",
+    "get_equalizer_presets",
+    "get_system_performance",
+    "check_audio_devices",
+    "audio_list_output_devices",
+    "audio_get_output_device",
+    "audio_get_output_config",
+    "audio_get_output_format",
+    "player_get_stream_stats",
+    "remote_get_servers",
+    "cache_get_config",
+    "get_cache_strategy",
+    "cache_get_stats",
+    "network_get_bandwidth_limit",
+    "network_get_bandwidth_usage",
+    "network_get_stream_buffer",
+    // 派对模式自身
+    "party_mode_get_status",
+    "party_mode_disable",
+];
+
+/// 判断命令是否对访客开放
+pub fn is_guest_safe(command: &str) -> bool {
+    GUEST_SAFE_COMMANDS.contains(&command)
+}
+
+/// 派对模式错误
+#[derive(Debug, Error, PartialEq)]
+pub enum PartyModeError {
+    #[error("PartyModeActive: 派对模式已开启，禁止调用 {0}")]
+    Active(String),
+
+    #[error("PIN错误")]
+    InvalidPin,
+
+    #[error("PIN输错次数过多，请 {retry_after_secs} 秒后再试")]
+    TooManyAttempts { retry_after_secs: u64 },
+
+    #[error("无法生成随机盐")]
+    RandomUnavailable,
+
+    #[error("PIN至少需要4位")]
+    PinTooShort,
+
+    #[error("派对模式已开启")]
+    AlreadyActive,
+
+    #[error("派对模式未开启")]
+    NotActive,
+
+    #[error("QueueRateLimited: 添加过于频繁，每分钟最多 {limit} 首")]
+    RateLimited { limit: u32 },
+}
+
+/// 派对模式状态（发给前端）
+#[derive(Debug, Clone, Serialize)]
+pub struct PartyModeStatus {
+    pub active: bool,
+    pub queue_rate_limit: Option<u32>,
+}
+
+/// 派对模式
+#[derive(Debug, Default)]
+pub struct PartyMode {
+    active: bool,
+    pin_hash: Option<String>,
+    queue_rate_limit: Option<u32>,
+    /// 最近一分钟内的队列添加记录（时间, 曲目数）
+    recent_additions: VecDeque<(Instant, usize)>,
+    /// 连续输错PIN的次数
+    failed_pin_attempts: u32,
+    /// 锁定期内不再校验PIN
+    pin_locked_until: Option<Instant>,
+}
+
+impl PartyMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从持久化设置恢复状态
+    pub fn restore(active: bool, pin_hash: Option<String>, queue_rate_limit: Option<u32>) -> Self {
+        Self {
+            // 没有可校验的PIN就无法关闭，这种状态不恢复
+            active: active && pin_hash.as_deref().is_some_and(is_pin_hash),
+            pin_hash,
+            queue_rate_limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn status(&self) -> PartyModeStatus {
+        PartyModeStatus {
+            active: self.active,
+            queue_rate_limit: self.queue_rate_limit,
+        }
+    }
+
+    /// 开启派对模式，返回需要持久化的PIN哈希
+    pub fn enable(&mut self, pin: &str) -> Result<String, PartyModeError> {
+        if self.active {
+            return Err(PartyModeError::AlreadyActive);
+        }
+        if pin.trim().chars().count() < 4 {
+            return Err(PartyModeError::PinTooShort);
+        }
+
+        let hash = hash_pin(pin.trim())?;
+        self.pin_hash = Some(hash.clone());
+        self.active = true;
+        self.recent_additions.clear();
+        self.failed_pin_attempts = 0;
+        self.pin_locked_until = None;
+        Ok(hash)
+    }
+
+    /// 关闭派对模式，需要正确的PIN；连续输错后锁定一段时间
+    pub fn disable(&mut self, pin: &str) -> Result<(), PartyModeError> {
+        self.disable_at(pin, Instant::now())
+    }
+
+    fn disable_at(&mut self, pin: &str, now: Instant) -> Result<(), PartyModeError> {
+        if !self.active {
+            return Err(PartyModeError::NotActive);
+        }
+        if let Some(until) = self.pin_locked_until.filter(|until| *until > now) {
+            let remaining = until - now;
+            return Err(PartyModeError::TooManyAttempts {
+                retry_after_secs: remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+            });
+        }
+
+        let valid = self.pin_hash.as_deref().is_some_and(|stored| verify_pin(pin.trim(), stored));
+        if !valid {
+            self.failed_pin_attempts += 1;
+            if self.failed_pin_attempts >= FREE_PIN_ATTEMPTS {
+                // 第3次输错锁1秒，之后每次翻倍
+                let exponent = (self.failed_pin_attempts - FREE_PIN_ATTEMPTS).min(16);
+                let lockout = Duration::from_secs(1 << exponent).min(MAX_PIN_LOCKOUT);
+                self.pin_locked_until = Some(now + lockout);
+            }
+            return Err(PartyModeError::InvalidPin);
+        }
+
+        self.active = false;
+        self.recent_additions.clear();
+        self.failed_pin_attempts = 0;
+        self.pin_locked_until = None;
+        Ok(())
+    }
+
+    /// 检查命令在当前模式下是否允许调用
+    pub fn check_command(&self, command: &str) -> Result<(), PartyModeError> {
+        if self.active && !is_guest_safe(command) {
+            return Err(PartyModeError::Active(command.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 设置队列限速（None 表示不限制）
+    pub fn set_queue_rate_limit(&mut self, limit: Option<u32>) {
+        self.queue_rate_limit = limit.filter(|l| *l > 0);
+        self.recent_additions.clear();
+    }
+
+    /// 记录队列添加，超过限速时拒绝（仅在派对模式下生效）
+    pub fn record_queue_additions(&mut self, count: usize) -> Result<(), PartyModeError> {
+        self.record_queue_additions_at(count, Instant::now())
+    }
+
+    fn record_queue_additions_at(&mut self, count: usize, now: Instant) -> Result<(), PartyModeError> {
+        let limit = match self.queue_rate_limit {
+            Some(limit) if self.active => limit,
+            _ => return Ok(()),
+        };
+
+        while let Some((at, _)) = self.recent_additions.front() {
+            if now.duration_since(*at) >= RATE_LIMIT_WINDOW {
+                self.recent_additions.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let used: usize = self.recent_additions.iter().map(|(_, n)| n).sum();
+        if used + count > limit as usize {
+            return Err(PartyModeError::RateLimited { limit });
+        }
+
+        self.recent_additions.push_back((now, count));
+        Ok(())
+    }
+}
+
+/// 命令分发：派对模式检查通过时执行 run，否则交给 reject
+///
+/// 锁只在检查期间持有，命令本身（如 party_mode_disable）还要获取它。
+pub fn dispatch<I>(
+    party: &Mutex<PartyMode>,
+    command: &str,
+    invoke: I,
+    run: impl FnOnce(I) -> bool,
+    reject: impl FnOnce(I, PartyModeError),
+) -> bool {
+    let checked = match party.lock() {
+        Ok(party) => party.check_command(command),
+        // 状态不可用时按派对模式处理，不放行
+        Err(_) => Err(PartyModeError::Active(command.to_string())),
+    };
+    match checked {
+        Ok(()) => run(invoke),
+        Err(e) => {
+            reject(invoke, e);
+            true
+        }
+    }
+}
+
+/// 用随机盐计算PIN哈希
+pub fn hash_pin(pin: &str) -> Result<String, PartyModeError> {
+    let mut salt = [0u8; PIN_SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| PartyModeError::RandomUnavailable)?;
+    Ok(hash_pin_with(pin, &salt, PIN_HASH_ITERATIONS))
+}
+
+fn hash_pin_with(pin: &str, salt: &[u8], iterations: u32) -> String {
+    let mut digest = [0u8; PIN_DIGEST_LEN];
+    let rounds = NonZeroU32::new(iterations).unwrap_or(NonZeroU32::MIN);
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, rounds, salt, pin.as_bytes(), &mut digest);
+    let b64 = base64::engine::general_purpose::STANDARD;
+    format!("{}${}${}${}", PIN_HASH_SCHEME, iterations, b64.encode(salt), b64.encode(digest))
+}
+
+/// 拆分PIN哈希为（迭代次数, 盐, 摘要）
+fn parse_pin_hash(stored: &str) -> Option<(NonZeroU32, Vec<u8>, Vec<u8>)> {
+    let mut parts = stored.split('$');
+    if parts.next()? != PIN_HASH_SCHEME {
+        return None;
+    }
+    let iterations = NonZeroU32::new(parts.next()?.parse().ok()?)?;
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let salt = b64.decode(parts.next()?).ok()?;
+    let digest = b64.decode(parts.next()?).ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((iterations, salt, digest))
+}
+
+/// 是否为可校验的PIN哈希（旧版 MD5 格式不再接受）
+pub fn is_pin_hash(stored: &str) -> bool {
+    parse_pin_hash(stored).is_some()
+}
+
+/// 校验PIN（摘要比较为常数时间）
+pub fn verify_pin(pin: &str, stored: &str) -> bool {
+    match parse_pin_hash(stored) {
+        Some((iterations, salt, digest)) => {
+            pbkdf2::verify(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, pin.as_bytes(), &digest).is_ok()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::db_pool::DbPool;
+    use crate::playlist::{CreatePlaylistOptions, PlaylistManager};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_playlist_delete_blocked_in_party_mode() {
+        let db = Arc::new(DbPool::single(Database::new(":memory:").unwrap()));
+        let manager = PlaylistManager::new(db.clone());
+        let playlist_id = manager.create_playlist(CreatePlaylistOptions {
+            name: "Party".to_string(),
+            description: None,
+            color_theme: None,
+            is_smart: false,
+            smart_rules: None,
+        }).unwrap();
+
+        let party = Mutex::new(PartyMode::new());
+        party.lock().unwrap().enable("1234").unwrap();
+
+        // 经命令分发调用，放行检查先于命令本身执行
+        let delete = |party: &Mutex<PartyMode>| {
+            let mut rejected = None;
+            let handled = dispatch(
+                party,
+                "playlists_delete",
+                playlist_id,
+                |id| {
+                    manager.delete_playlist(id).unwrap();
+                    true
+                },
+                |_, e| rejected = Some(e),
+            );
+            assert!(handled);
+            rejected
+        };
+        assert_eq!(delete(&party), Some(PartyModeError::Active("playlists_delete".to_string())));
+        assert!(db.lock().unwrap().get_playlist_by_id(playlist_id).unwrap().is_some());
+
+        // 关闭派对模式后正常执行
+        party.lock().unwrap().disable("1234").unwrap();
+        assert_eq!(delete(&party), None);
+        assert!(db.lock().unwrap().get_playlist_by_id(playlist_id).unwrap().is_none());
+
+        let mut party = PartyMode::new();
+        party.enable("1234").unwrap();
+        assert!(party.check_command("player_play").is_ok());
+        assert!(party.check_command("library_search").is_ok());
+        // 未登记的命令默认拒绝
+        assert!(party.check_command("some_future_command").is_err());
+    }
+
+    #[test]
+    fn test_disable_requires_correct_pin() {
+        let mut party = PartyMode::new();
+        let hash = party.enable("1234").unwrap();
+        assert!(hash.starts_with("pbkdf2-sha256$"));
+        assert!(verify_pin("1234", &hash));
+        assert!(!verify_pin("1235", &hash));
+        // 每次使用新的随机盐
+        assert_ne!(hash_pin("1234").unwrap(), hash);
+
+        assert_eq!(party.disable("0000"), Err(PartyModeError::InvalidPin));
+        assert!(party.is_active());
+
+        assert!(party.disable("1234").is_ok());
+        assert!(!party.is_active());
+
+        // 旧版 MD5 哈希不能校验，也不恢复为开启状态
+        assert!(!verify_pin("1234", "salt$81dc9bdb52d04dc20036dbd8313ed055"));
+        assert!(!PartyMode::restore(true, Some("salt$81dc9bdb52d04dc20036dbd8313ed055".to_string()), None).is_active());
+        assert!(PartyMode::restore(true, Some(hash), None).is_active());
+    }
+
+    #[test]
+    fn test_pin_attempts_back_off() {
+        let mut party = PartyMode::new();
+        party.enable("1234").unwrap();
+        let start = Instant::now();
+
+        // 前两次输错不锁定
+        assert_eq!(party.disable_at("0000", start), Err(PartyModeError::InvalidPin));
+        assert_eq!(party.disable_at("0001", start), Err(PartyModeError::InvalidPin));
+        // 第3次输错后锁定1秒，锁定期内正确PIN也被拒绝
+        assert_eq!(party.disable_at("0002", start), Err(PartyModeError::InvalidPin));
+        assert_eq!(
+            party.disable_at("1234", start + Duration::from_millis(500)),
+            Err(PartyModeError::TooManyAttempts { retry_after_secs: 1 })
+        );
+        assert!(party.is_active());
+
+        // 再输错一次锁定翻倍
+        let later = start + Duration::from_secs(1);
+        assert_eq!(party.disable_at("0003", later), Err(PartyModeError::InvalidPin));
+        assert_eq!(
+            party.disable_at("1234", later),
+            Err(PartyModeError::TooManyAttempts { retry_after_secs: 2 })
+        );
+
+        // 锁定结束后正确PIN可以关闭，计数清零
+        assert!(party.disable_at("1234", later + Duration::from_secs(2)).is_ok());
+        assert!(!party.is_active());
+        assert_eq!(party.failed_pin_attempts, 0);
+    }
+
+    #[test]
+    fn test_queue_rate_limit() {
+        let mut party = PartyMode::new();
+        party.set_queue_rate_limit(Some(10));
+        let start = Instant::now();
+
+        // 未开启派对模式时不限速
+        assert!(party.record_queue_additions_at(500, start).is_ok());
+
+        party.enable("1234").unwrap();
+        assert!(party.record_queue_additions_at(8, start).is_ok());
+        assert_eq!(
+            party.record_queue_additions_at(5, start + Duration::from_secs(10)),
+            Err(PartyModeError::RateLimited { limit: 10 })
+        );
+        assert!(party.record_queue_additions_at(5, start + Duration::from_secs(61)).is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+// 播放上下文
+//
+// 在曲库、专辑、歌单、收藏或搜索结果中点播一首曲目时，前端只需说明"从哪里播放"，
+// 由后端按界面相同的顺序从数据库取出曲目列表（不含封面数据）载入播放队列，
+// 避免为一次点击把成千上万个曲目对象经IPC传给后端。
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::db::{SortDirection, TrackSortField};
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::player::Track;
+use crate::playlist::{BuiltinPlaylistsConfig, PlaylistManager};
+
+/// 播放来源
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayContext {
+    /// 曲库列表，排序和筛选与 library_get_tracks_page 相同
+    Library {
+        #[serde(default)]
+        sort_by: Option<TrackSortField>,
+        #[serde(default)]
+        sort_dir: Option<SortDirection>,
+        #[serde(default)]
+        filter: Option<String>,
+    },
+    /// 专辑（按碟号、音轨号）
+    Album {
+        album: String,
+        #[serde(default)]
+        album_artist: Option<String>,
+    },
+    /// 歌单，内置歌单使用保留的负数ID
+    Playlist { id: i64 },
+    /// 我的收藏（最近收藏的在前）
+    Favorites,
+    /// 搜索结果
+    SearchResults { query: String },
+}
+
+/// 已载入队列的播放来源，界面据此显示"正在播放：专辑 X（12 首）"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlayContextInfo {
+    /// 专辑名、歌单名或搜索词，曲库和收藏为None
+    pub name: Option<String>,
+    pub track_count: usize,
+}
+
+impl PlayContext {
+    /// 按界面的顺序取出上下文中的曲目
+    pub fn resolve(&self, db: &Arc<DbPool>, builtin: &BuiltinPlaylistsConfig) -> AppResult<(Vec<Track>, Option<String>)> {
+        let resolved = match self {
+            PlayContext::Library { sort_by, sort_dir, filter } => {
+                let page = db.with_read(|db| db.get_tracks_page(
+                    0,
+                    i64::MAX,
+                    sort_by.unwrap_or_default(),
+                    sort_dir.unwrap_or_default(),
+                    filter.as_deref(),
+                ))?;
+                (page.tracks, None)
+            }
+            PlayContext::Album { album, album_artist } => {
+                let tracks = db.with_read(|db| db.get_album_tracks(album, album_artist.as_deref()))?;
+                (tracks, Some(album.clone()))
+            }
+            PlayContext::Playlist { id } => {
+                let detail = PlaylistManager::new(db.clone()).resolve_playlist(*id, builtin)?;
+                (detail.tracks, Some(detail.playlist.name))
+            }
+            PlayContext::Favorites => (db.with_read(|db| db.get_all_favorites())?, None),
+            PlayContext::SearchResults { query } => {
+                let result = db.with_read(|db| db.search_library(query))?;
+                (result.tracks, Some(query.clone()))
+            }
+        };
+        Ok(resolved)
+    }
+
+    /// 取出曲目并确认 track_id 在其中
+    pub fn resolve_for_track(
+        &self,
+        track_id: i64,
+        db: &Arc<DbPool>,
+        builtin: &BuiltinPlaylistsConfig,
+    ) -> AppResult<(Vec<Track>, PlayContextInfo)> {
+        let (tracks, name) = self.resolve(db, builtin)?;
+        if !tracks.iter().any(|track| track.id == track_id) {
+            return Err(AppError::not_found(format!("曲目 {} 不在播放来源中: {:?}", track_id, self)));
+        }
+        let info = PlayContextInfo { name, track_count: tracks.len() };
+        Ok((tracks, info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn pool_with_tracks() -> (Arc<DbPool>, Vec<i64>) {
+        let db = Database::new(":memory:").unwrap();
+        let ids = [("/m/b.flac", "Beta", 2), ("/m/a.flac", "Alpha", 1), ("/m/x.flac", "Other", 1)]
+            .into_iter()
+            .map(|(path, title, number)| {
+                let mut track = Track::new(0, path.to_string());
+                track.title = Some(title.to_string());
+                track.artist = Some("Band".to_string());
+                track.album = Some(if title == "Other" { "Single" } else { "Record" }.to_string());
+                track.track_number = Some(number);
+                db.insert_track(&track).unwrap()
+            })
+            .collect();
+        (Arc::new(DbPool::single(db)), ids)
+    }
+
+    fn context(json: &str) -> PlayContext {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_contexts() {
+        let (pool, ids) = pool_with_tracks();
+        let config = BuiltinPlaylistsConfig::default();
+        let titles = |tracks: &[Track]| -> Vec<String> {
+            tracks.iter().filter_map(|t| t.title.clone()).collect()
+        };
+
+        let (tracks, info) = context(r#"{"type": "library", "sort_by": "title", "sort_dir": "desc"}"#)
+            .resolve_for_track(ids[0], &pool, &config)
+            .unwrap();
+        assert_eq!(titles(&tracks), ["Other", "Beta", "Alpha"]);
+        assert_eq!(info, PlayContextInfo { name: None, track_count: 3 });
+
+        let (tracks, info) = context(r#"{"type": "album", "album": "Record", "album_artist": "Band"}"#)
+            .resolve_for_track(ids[1], &pool, &config)
+            .unwrap();
+        assert_eq!(titles(&tracks), ["Alpha", "Beta"]);
+        assert_eq!(info.name.as_deref(), Some("Record"));
+
+        pool.with(|db| db.add_favorite(ids[2])).unwrap();
+        let (tracks, _) = context(r#"{"type": "favorites"}"#).resolve_for_track(ids[2], &pool, &config).unwrap();
+        assert_eq!(tracks.len(), 1);
+
+        let (_, info) = context(r#"{"type": "library", "filter": "alpha"}"#)
+            .resolve_for_track(ids[1], &pool, &config)
+            .unwrap();
+        assert_eq!(info.track_count, 1);
+
+        // 曲目不在上下文中
+        assert!(context(r#"{"type": "album", "album": "Single", "album_artist": "Band"}"#)
+            .resolve_for_track(ids[0], &pool, &config)
+            .is_err());
+        assert!(context(r#"{"type": "playlist", "id": 999}"#).resolve_for_track(ids[0], &pool, &config).is_err());
+    }
+}
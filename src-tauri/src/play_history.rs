@@ -24,3 +24,325 @@ pub struct PlayStatistics {
     pub total_duration_ms: i64,
 }
 
+
+/// 统计时间范围（"7d"、"30d"、"365d"、"all"）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum StatsRange {
+    /// 最近N天
+    Days(u32),
+    /// 全部历史
+    All,
+}
+
+impl StatsRange {
+    /// 范围起点（UNIX秒），全部历史为0
+    pub fn since(&self, now: i64) -> i64 {
+        match self {
+            StatsRange::Days(days) => now - *days as i64 * 86_400,
+            StatsRange::All => 0,
+        }
+    }
+}
+
+impl std::str::FromStr for StatsRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "all" {
+            return Ok(StatsRange::All);
+        }
+        s.strip_suffix('d')
+            .and_then(|days| days.parse::<u32>().ok())
+            .filter(|days| *days > 0)
+            .map(StatsRange::Days)
+            .ok_or_else(|| format!("无效的统计范围: {}（可选 7d、30d、365d、all）", s))
+    }
+}
+
+impl TryFrom<String> for StatsRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// 艺术家播放排行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtistPlayStats {
+    pub artist: String,
+    pub play_count: i64,
+    pub unique_tracks: i64,
+    pub total_duration_ms: i64,
+}
+
+/// 专辑播放排行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AlbumPlayStats {
+    pub album: String,
+    pub album_artist: Option<String>,
+    pub play_count: i64,
+    pub total_duration_ms: i64,
+    pub cover_id: Option<String>,
+}
+
+/// 每日播放统计（热力图）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyPlayStats {
+    /// 本地日期 YYYY-MM-DD
+    pub date: String,
+    pub play_count: i64,
+    pub duration_ms: i64,
+}
+
+/// 按小时统计的播放次数（本地时间0-23点）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HourlyPlayStats {
+    pub hour: u32,
+    pub play_count: i64,
+}
+
+/// 播放历史来源：前端或导入通过命令写入
+pub const PLAY_SOURCE_MANUAL: &str = "manual";
+/// 播放历史来源：后端根据实际播放自动记录
+pub const PLAY_SOURCE_PLAYER: &str = "player";
+
+/// 短于此时长的播放不计入（曲目本身更短时需完整播放）
+const MIN_SCROBBLE_MS: u64 = 30_000;
+
+/// 相邻两次位置更新的最大间隔，超过视为跳转而不计时
+const MAX_POSITION_STEP_MS: u64 = 2_000;
+
+/// 计入播放历史的门槛（参考Last.fm：播放一半或4分钟，先到者为准）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrobbleThreshold {
+    /// 播放比例（1-100）
+    pub percent: u8,
+    /// 播放时长达到该值即计入(ms)
+    pub max_ms: u64,
+}
+
+impl Default for ScrobbleThreshold {
+    fn default() -> Self {
+        Self { percent: 50, max_ms: 240_000 }
+    }
+}
+
+impl ScrobbleThreshold {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.percent) {
+            return Err("播放比例必须在 1-100 之间".to_string());
+        }
+        if self.max_ms < MIN_SCROBBLE_MS {
+            return Err(format!("播放时长门槛不能少于 {} 秒", MIN_SCROBBLE_MS / 1000));
+        }
+        Ok(())
+    }
+
+    /// 计入播放历史所需的实际播放时长，时长未知时使用上限
+    pub fn required_ms(&self, duration_ms: Option<i64>) -> u64 {
+        match duration_ms.filter(|d| *d > 0).map(|d| d as u64) {
+            Some(duration) => (duration * self.percent as u64 / 100)
+                .min(self.max_ms)
+                .max(MIN_SCROBBLE_MS.min(duration)),
+            None => self.max_ms,
+        }
+    }
+}
+
+/// 达到门槛、需要写入播放历史的一次播放
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompletedPlay {
+    pub track_id: i64,
+    pub played_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedPlay {
+    track_id: i64,
+    duration_ms: Option<i64>,
+    played_ms: u64,
+    last_position_ms: Option<u64>,
+}
+
+impl TrackedPlay {
+    fn new(track_id: i64, duration_ms: Option<i64>) -> Self {
+        Self { track_id, duration_ms, played_ms: 0, last_position_ms: None }
+    }
+}
+
+/// 播放进度跟踪：根据播放器事件累计实际播放时长
+///
+/// 只累计连续前进的位置变化，暂停、跳转不计时；回跳后重听的时间计入，
+/// 但总数不超过曲目时长
+#[derive(Debug, Default)]
+pub struct PlayTracker {
+    threshold: ScrobbleThreshold,
+    current: Option<TrackedPlay>,
+}
+
+impl PlayTracker {
+    pub fn set_threshold(&mut self, threshold: ScrobbleThreshold) {
+        self.threshold = threshold;
+    }
+
+    /// 曲目切换：结束上一首（达到门槛时返回），开始跟踪新曲目
+    pub fn track_changed(&mut self, track: Option<&Track>) -> Option<CompletedPlay> {
+        let finished = self.finish();
+        self.current = track.map(|t| TrackedPlay::new(t.id, t.duration_ms));
+        finished
+    }
+
+    /// 播放位置更新
+    pub fn position(&mut self, position_ms: u64) {
+        let play = match &mut self.current {
+            Some(play) => play,
+            None => return,
+        };
+        if let Some(last) = play.last_position_ms {
+            let step = position_ms.saturating_sub(last);
+            if step > 0 && step <= MAX_POSITION_STEP_MS {
+                play.played_ms += step;
+            }
+        }
+        play.last_position_ms = Some(position_ms);
+        if let Some(duration) = play.duration_ms.filter(|d| *d > 0) {
+            play.played_ms = play.played_ms.min(duration as u64);
+        }
+    }
+
+    /// 跳转完成：以新位置为基准，跳过的部分不计时
+    pub fn seeked(&mut self, position_ms: u64) {
+        if let Some(play) = &mut self.current {
+            play.last_position_ms = Some(position_ms);
+        }
+    }
+
+    /// 曲目播放完成：结束本次播放，单曲循环时同一曲目重新计时
+    pub fn track_completed(&mut self) -> Option<CompletedPlay> {
+        let play = self.current.as_ref().map(|p| TrackedPlay::new(p.track_id, p.duration_ms));
+        let finished = self.finish();
+        self.current = play;
+        finished
+    }
+
+    /// 结束当前播放（达到门槛时返回）
+    pub fn finish(&mut self) -> Option<CompletedPlay> {
+        let play = self.current.take()?;
+        let required_ms = self.threshold.required_ms(play.duration_ms);
+        (play.played_ms >= required_ms).then_some(CompletedPlay {
+            track_id: play.track_id,
+            played_ms: play.played_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_range_parse() {
+        assert_eq!("7d".parse::<StatsRange>(), Ok(StatsRange::Days(7)));
+        assert_eq!("365d".parse::<StatsRange>(), Ok(StatsRange::Days(365)));
+        assert_eq!("all".parse::<StatsRange>(), Ok(StatsRange::All));
+        assert!("0d".parse::<StatsRange>().is_err());
+        assert!("30".parse::<StatsRange>().is_err());
+        assert!("week".parse::<StatsRange>().is_err());
+
+        assert_eq!(StatsRange::Days(7).since(1_000_000), 1_000_000 - 7 * 86_400);
+        assert_eq!(StatsRange::All.since(1_000_000), 0);
+    }
+
+    fn track(id: i64, duration_ms: i64) -> Track {
+        let mut track = Track::new(id, format!("/m/{}.flac", id));
+        track.duration_ms = Some(duration_ms);
+        track
+    }
+
+    /// 从 from 到 to 每100ms上报一次位置
+    fn play(tracker: &mut PlayTracker, from: u64, to: u64) {
+        for position in (from..=to).step_by(100) {
+            tracker.position(position);
+        }
+    }
+
+    #[test]
+    fn test_required_ms_follows_lastfm_rules() {
+        let threshold = ScrobbleThreshold::default();
+        assert_eq!(threshold.required_ms(Some(180_000)), 90_000);
+        // 长曲目4分钟即可
+        assert_eq!(threshold.required_ms(Some(1_200_000)), 240_000);
+        // 短曲目至少30秒，比30秒更短时需完整播放
+        assert_eq!(threshold.required_ms(Some(40_000)), 30_000);
+        assert_eq!(threshold.required_ms(Some(20_000)), 20_000);
+        assert_eq!(threshold.required_ms(None), 240_000);
+    }
+
+    #[test]
+    fn test_skipping_quickly_records_nothing() {
+        let mut tracker = PlayTracker::default();
+        assert_eq!(tracker.track_changed(Some(&track(1, 180_000))), None);
+        play(&mut tracker, 0, 5_000);
+        assert_eq!(tracker.track_changed(Some(&track(2, 180_000))), None);
+        play(&mut tracker, 0, 2_000);
+        assert_eq!(tracker.track_changed(Some(&track(3, 180_000))), None);
+    }
+
+    #[test]
+    fn test_seek_forward_is_not_counted() {
+        let mut tracker = PlayTracker::default();
+        tracker.track_changed(Some(&track(1, 180_000)));
+        play(&mut tracker, 0, 10_000);
+        // 跳到结尾附近，直接跳过的部分不计时
+        tracker.seeked(170_000);
+        play(&mut tracker, 170_000, 180_000);
+        assert_eq!(tracker.track_completed(), None);
+
+        // 没有SeekCompleted时大幅跳跃同样不计时
+        tracker.track_changed(Some(&track(2, 180_000)));
+        play(&mut tracker, 0, 10_000);
+        play(&mut tracker, 150_000, 180_000);
+        assert_eq!(tracker.track_completed(), None);
+    }
+
+    #[test]
+    fn test_seek_backwards_does_not_double_count() {
+        let mut tracker = PlayTracker::default();
+        tracker.track_changed(Some(&track(1, 60_000)));
+        play(&mut tracker, 0, 50_000);
+        tracker.seeked(0);
+        play(&mut tracker, 0, 60_000);
+        // 重听部分计入，但不超过曲目时长
+        assert_eq!(tracker.track_completed(), Some(CompletedPlay { track_id: 1, played_ms: 60_000 }));
+    }
+
+    #[test]
+    fn test_pause_does_not_accumulate() {
+        let mut tracker = PlayTracker::default();
+        tracker.track_changed(Some(&track(1, 180_000)));
+        play(&mut tracker, 0, 80_000);
+        // 暂停期间位置不变
+        for _ in 0..100 {
+            tracker.position(80_000);
+        }
+        assert_eq!(tracker.track_changed(None), None);
+
+        tracker.track_changed(Some(&track(2, 180_000)));
+        play(&mut tracker, 0, 95_000);
+        assert_eq!(tracker.track_changed(None), Some(CompletedPlay { track_id: 2, played_ms: 95_000 }));
+    }
+
+    #[test]
+    fn test_repeat_one_records_each_pass() {
+        let mut tracker = PlayTracker::default();
+        tracker.track_changed(Some(&track(1, 60_000)));
+        play(&mut tracker, 0, 60_000);
+        assert!(tracker.track_completed().is_some());
+        play(&mut tracker, 0, 60_000);
+        assert!(tracker.track_completed().is_some());
+        // 完成后切歌不会重复记录
+        assert_eq!(tracker.track_changed(Some(&track(2, 60_000))), None);
+    }
+}
@@ -121,18 +121,21 @@ impl AudioActor {
     }
     
     /// 处理初始化请求
+    #[tracing::instrument(level = "debug", skip_all)]
     async fn handle_initialize(&mut self) {
         log::info!("🎵 开始初始化音频设备");
         
         match self.device.get_or_init().await {
-            Ok(_device) => {
+            Ok(device) => {
                 log::info!("✅ 音频设备初始化成功");
+                let device_name = device.name.clone();
+                let format = Some(device.format.clone());
                 // 标记设备已初始化
                 self.device_cache = Some(Arc::new(()));
                 self.failure_count = 0;
                 
                 // 发送设备就绪事件
-                let _ = self.event_tx.send(PlayerEvent::AudioDeviceReady).await;
+                let _ = self.event_tx.send(PlayerEvent::AudioDeviceReady { device_name, format }).await;
             }
             Err(e) => {
                 log::error!("❌ 音频设备初始化失败: {}", e);
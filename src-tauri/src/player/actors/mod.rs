@@ -16,16 +16,18 @@ mod audio_actor;
 pub mod playback_actor;
 pub mod playlist_actor;
 pub mod preload_actor;
+mod position_reporter;
 #[allow(dead_code)]  // core.rs 中使用
 mod state_actor;
 
 // 公开导出Actor类型
 #[allow(unused_imports)]
 pub use audio_actor::{AudioActor, AudioActorHandle};
-pub use playback_actor::{PlaybackActor, PlaybackActorHandle};
+pub use playback_actor::{PlaybackActor, PlaybackActorHandle, PlaybackLinks, DEFAULT_PAUSE_FADE_MS, DEFAULT_PRELOAD_LEAD_MS, DEFAULT_SEEK_STEP_MS};
 pub use playlist_actor::{PlaylistActor, PlaylistActorHandle};
+pub use position_reporter::{DEFAULT_POSITION_INTERVAL_MS, MIN_POSITION_INTERVAL_MS, MAX_POSITION_INTERVAL_MS};
 pub use preload_actor::{
-    PreloadActor, PreloadActorHandle,
+    PreloadActor, PreloadActorHandle, PreloadStats,
 };
 #[allow(unused_imports)]
 pub use state_actor::{StateActor, StateActorHandle};
@@ -3,8 +3,43 @@
 
 use tokio::sync::{mpsc, oneshot, watch};
 use std::time::{Duration, Instant};
-use super::super::audio::{SinkPool, PooledSink, AudioDecoder, LazyAudioDevice};
-use super::super::types::{Track, PlayerError, PlayerEvent, Result, PlayerState};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use rodio::Source as _;
+use super::super::audio::{duration, time_stretch, visualization, VolumeSettings};
+use super::super::audio::silence::TrimPoints;
+use super::super::audio::{SinkPool, PooledSink, AudioDecoder, LazyAudioDevice, CancellableSource, Fade, EqualizerSource, ChannelMixSource, PlaybackRate, TimeStretchSource, VisualizationTap, StreamSeekHandle, OutputConfig, OutputFormat, OutputRequest, is_device_lost};
+use super::super::types::{Track, PlaybackEngine, PlayerError, PlayerEvent, Result, PlayerState, SleepTimer, SleepTimerStatus, SleepCountdown, DEFAULT_SLEEP_FADE_SECS, MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS, Chapter, chapter_at, next_chapter_start, previous_chapter_start, LoopRegion};
+use super::{PlaylistActorHandle, PreloadActorHandle, StateActorHandle};
+use super::preload_actor::PreloadedAudio;
+use super::position_reporter::{PositionReporter, DEFAULT_POSITION_INTERVAL_MS};
+use crate::streaming::{buffering, full_download, SimpleHttpReader, StreamMonitor, TrafficClass};
+use crate::streaming::buffering::{Rebuffer, RebufferAction, StreamStats};
+use tokio_util::sync::CancellationToken;
+
+/// 无缝模式下距离曲目结束多久开始准备下一首(ms)
+const GAPLESS_PREPARE_THRESHOLD_MS: u64 = 5000;
+
+/// 淡入淡出期间的音量刷新间隔(ms)
+const FADE_TICK_MS: u64 = 20;
+
+/// 睡眠定时器剩余时间通知间隔(秒)
+const SLEEP_TICK_SECS: u64 = 30;
+
+/// 输出设备断开检测间隔(秒)
+const DEVICE_CHECK_SECS: u64 = 2;
+
+/// 设备恢复重试的最长间隔(秒)
+const MAX_DEVICE_RETRY_SECS: u64 = 30;
+
+/// 暂停/恢复时的默认淡出淡入时长(ms)
+pub const DEFAULT_PAUSE_FADE_MS: u64 = 200;
+
+/// 相对跳转的默认步长(ms)
+pub const DEFAULT_SEEK_STEP_MS: u64 = 10_000;
+
+/// 默认在曲目剩余30秒时预加载下一首(ms)
+pub const DEFAULT_PRELOAD_LEAD_MS: u64 = 30_000;
 
 /// 播放Actor消息
 #[derive(Debug)]
@@ -30,12 +65,114 @@ pub enum PlaybackMsg {
         reply: oneshot::Sender<Result<()>>,
     },
     
+    /// 按步长相对跳转（正数前进，负数后退）
+    SeekRelative {
+        steps: i64,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 续播到保存的位置，不能跳转时延后到缓存完成
+    ResumeAt {
+        track_id: i64,
+        position_ms: u64,
+    },
+    
+    /// 外部引擎同步的位置和播放状态，首次同步时暂停原生输出
+    SyncExternalPosition {
+        position_ms: u64,
+        is_playing: bool,
+    },
+    
+    /// 外部引擎交还原生播放，从指定位置开始
+    HandoffToNative {
+        position_ms: u64,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 设置曲目的章节
+    SetChapters {
+        track_id: i64,
+        chapters: Vec<Chapter>,
+    },
+    
+    /// 设置曲目的静音裁剪点，None表示不跳过静音
+    SetTrimPoints {
+        track_id: i64,
+        trim: Option<TrimPoints>,
+    },
+    
+    /// 跳到下一章节（true）或上一章节（false）
+    SeekChapter {
+        forward: bool,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 设置A-B循环区间，None表示清除
+    SetLoopRegion {
+        region: Option<(u64, u64)>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 设置暂停/恢复淡变时长(ms)，0表示关闭
+    SetPauseFade(u64),
+    
+    /// 设置相对跳转步长(ms)
+    SetSeekStep(u64),
+    
+    /// 设置位置事件的上报间隔(ms)
+    SetPositionInterval(u64),
+    
+    /// 切换输出设备（None为系统默认设备）
+    SetOutputDevice {
+        device_name: Option<String>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 设置输出格式（原始采样率、独占模式）
+    SetOutputConfig {
+        config: OutputConfig,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    
+    /// 获取实际协商得到的输出格式
+    GetOutputFormat(oneshot::Sender<Option<OutputFormat>>),
+    
+    /// 获取当前流的缓冲统计（不是流式播放时为None）
+    GetStreamStats(oneshot::Sender<Option<StreamStats>>),
+    
     /// 设置音量(0.0-1.0)
     SetVolume(f32),
     
+    /// 设置音量曲线和前置放大
+    SetVolumeSettings(VolumeSettings),
+    
+    /// 设置播放速度
+    SetPlaybackRate(f32),
+    
+    /// 设置变速时是否保持音高
+    SetPreservePitch(bool),
+    
     /// 获取当前播放位置(ms)
     GetPosition(oneshot::Sender<Option<u64>>),
     
+    /// 设置无缝播放
+    SetGapless(bool),
+    
+    /// 取消已追加的下一首（播放列表或播放模式变化时）
+    CancelGaplessNext,
+    
+    /// 设置交叉淡入淡出时长(ms)，0表示关闭
+    SetCrossfade(u64),
+    
+    /// 设置睡眠定时器，None表示取消
+    SetSleepTimer(Option<SleepTimer>),
+    
+    /// 设置睡眠定时器触发时的淡出时长(秒)
+    SetSleepFade(u64),
+    
+    /// 获取睡眠定时器状态
+    GetSleepTimer(oneshot::Sender<Option<SleepTimerStatus>>),
+    
     /// 后台缓存完成通知
     CacheSamples {
         track_path: String,
@@ -64,6 +201,183 @@ struct CachedAudioSamples {
     sample_rate: u32,
 }
 
+/// 自动切歌（无缝播放、交叉淡入淡出）需要访问的其他Actor
+#[derive(Clone)]
+pub struct PlaybackLinks {
+    pub playlist: PlaylistActorHandle,
+    pub state: StateActorHandle,
+    pub preload: Option<PreloadActorHandle>,
+}
+
+/// 已追加到Sink队列中的下一首
+struct GaplessNext {
+    track: Track,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// 在解码输出和Sink之间插入变速、ReplayGain、前置放大、均衡器、单声道/声道平衡和可视化分析抽头，设置变化实时生效
+fn with_effects<S: rodio::Source<Item = i16>>(
+    source: S,
+    track: Option<&Track>,
+    preamp_db: f32,
+    rate: &Arc<PlaybackRate>,
+) -> VisualizationTap<ChannelMixSource<EqualizerSource<TimeStretchSource<S>>>> {
+    let factor = track.map(replay_gain_factor).unwrap_or(1.0) * crate::audio_enhancement::db_to_factor(preamp_db);
+    let stretched = TimeStretchSource::new(source, Arc::clone(rate));
+    let equalized = EqualizerSource::new(stretched, crate::audio_enhancement::subscribe_settings()).with_pre_gain(factor);
+    VisualizationTap::new(ChannelMixSource::new(equalized, crate::audio_enhancement::subscribe_settings()))
+}
+
+/// 在阻塞线程中解码本地文件，避免阻塞Actor
+async fn decode_local_file(path: String) -> Result<Box<dyn rodio::Source<Item = i16> + Send>> {
+    tokio::task::spawn_blocking(move || {
+        let decoder = AudioDecoder::new(&path);
+        match decoder.decode() {
+            Ok(s) => {
+                log::debug!("[PlaybackActor] Local decoder created");
+                Ok(Box::new(s) as Box<dyn rodio::Source<Item = i16> + Send>)
+            }
+            Err(e) => {
+                log::warn!("[PlaybackActor] Decode failed: {}", e);
+                Err(e)
+            }
+        }
+    })
+    .await
+    .map_err(|e| PlayerError::decode_error(format!("异步解码任务失败: {}", e)))?
+}
+
+/// 建立远程曲目的HTTP流并等待初始缓冲，返回流和HTTP地址（预加载和流式播放共用）
+///
+/// traffic_class 决定带宽限制下的优先级：播放为 Playback，预加载为 Prefetch
+pub(super) async fn open_stream_reader(track_path: &str, traffic_class: TrafficClass) -> Result<(SimpleHttpReader, String)> {
+    use tokio::time::{timeout, Duration};
+    
+    let (http_url, username, password) = if track_path.starts_with("webdav://") {
+        // 解析WEBDAV URL（包含完整配置）
+        let (http_url, username, password) = webdav_http_url(track_path)?;
+        log::info!("📡 HTTP URL: {}", http_url);
+        (http_url, username, password)
+    } else if track_path.starts_with("subsonic://") {
+        // 令牌认证参数已包含在URL中，不记录日志
+        let http_url = crate::subsonic::stream_url_for_track(track_path)
+            .map_err(|e| PlayerError::decode_error(e.to_string()))?;
+        (http_url, String::new(), String::new())
+    } else {
+        return Err(PlayerError::decode_error("不支持的协议，仅支持WebDAV和Subsonic流式播放".to_string()));
+    };
+    log::debug!("📡 [PlaybackActor] 创建HTTP流式Reader（即点即播模式）...");
+    
+    // 🚀 创建SimpleHttpReader（零等待，立即返回）
+    let create_future = SimpleHttpReader::new(http_url.clone(), username, password, traffic_class);
+    
+    let reader = match timeout(Duration::from_secs(5), create_future).await {
+        Ok(Ok(r)) => {
+            log::debug!("✅ [PlaybackActor] HTTP Reader创建成功（零延迟）");
+            r
+        }
+        Ok(Err(e)) => {
+            let err_msg = format!("创建HTTP Reader失败: {}", e);
+            log::error!("❌ {}", err_msg);
+            return Err(PlayerError::decode_error(err_msg));
+        }
+        Err(_) => {
+            let err_msg = "创建HTTP Reader超时（5秒）";
+            log::error!("❌ {}", err_msg);
+            return Err(PlayerError::decode_error(err_msg.to_string()));
+        }
+    };
+    
+    log::info!("✅ HTTP Reader已创建，等待初始缓冲...");
+    log::debug!("🎵 [PlaybackActor] 等待初始缓冲（提升播放流畅度）...");
+    
+    // 🔧 等待初始缓冲（默认256KB），确保格式探测不会因网络延迟而卡顿
+    let initial_buffer_size = buffering::settings().initial_bytes();
+    let buffer_timeout = Duration::from_secs(3);
+    let buffer_start = std::time::Instant::now();
+    
+    loop {
+        let available = reader.get_buffered_size();
+        
+        if available >= initial_buffer_size || reader.is_complete() {
+            log::info!("✅ 初始缓冲完成: {}KB", available / 1024);
+            break;
+        }
+        
+        if buffer_start.elapsed() > buffer_timeout {
+            log::warn!("⚠️ 初始缓冲超时（仅缓冲了{}KB），继续播放", available / 1024);
+            break;
+        }
+        
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    
+    Ok((reader, http_url))
+}
+
+/// 解析WEBDAV路径为HTTP URL和认证信息
+fn webdav_http_url(track_path: &str) -> Result<(String, String, String)> {
+    // webdav://server_id#/path/to/file.flac
+    let target = full_download::resolve_webdav_track(track_path)
+        .map_err(|e| PlayerError::decode_error(e.to_string()))?;
+    
+    // 使用WebDAVConfig的build_full_url方法
+    let url = target.config.build_full_url(&target.remote_path);
+    Ok((url, target.config.username, target.config.password))
+}
+
+/// 曲目的ReplayGain放大倍数（关闭或查询失败时为1.0）
+fn replay_gain_factor(track: &Track) -> f32 {
+    let settings = crate::audio_enhancement::current_settings().replay_gain;
+    let info = crate::DB.get()
+        .and_then(|db| db.lock().ok()?.get_replay_gain(track.id).ok().flatten());
+    match settings.resolve_gain_db(info.as_ref()) {
+        Some(gain_db) => {
+            log::info!("🔊 ReplayGain: {:.2}dB ({:?})", gain_db, track.title);
+            crate::audio_enhancement::db_to_factor(gain_db)
+        }
+        None => 1.0,
+    }
+}
+
+/// 暂停前的淡出或恢复后的淡入（线性，中途反向时从当前增益继续）
+#[derive(Debug, Clone, Copy)]
+struct PauseRamp {
+    fade: Fade,
+    from: f32,
+    /// true为淡出后暂停，false为恢复后淡入
+    pausing: bool,
+}
+
+impl PauseRamp {
+    fn start(from: f32, pausing: bool, duration_ms: u64) -> Self {
+        Self { fade: Fade::start(duration_ms), from, pausing }
+    }
+    
+    fn gain(&self) -> f32 {
+        let to = if self.pausing { 0.0 } else { 1.0 };
+        self.from + (to - self.from) * self.fade.progress()
+    }
+}
+
+/// 输出设备断开后等待恢复的播放状态（位置冻结，恢复后从此处继续）
+#[derive(Debug, Clone)]
+struct DeviceRecovery {
+    track: Option<Track>,
+    position_ms: u64,
+    was_playing: bool,
+    attempts: u32,
+    retry_at: Instant,
+}
+
+/// 交叉淡入淡出中正在淡出的旧Sink
+struct FadingSink {
+    sink: PooledSink,
+    fade: Fade,
+    /// 开始淡出时的增益（上一次淡入尚未完成时小于1）
+    start_gain: f32,
+}
+
 /// 播放控制Actor
 pub struct PlaybackActor {
     inbox: mpsc::Receiver<PlaybackMsg>,
@@ -78,7 +392,63 @@ pub struct PlaybackActor {
     cached_samples: Option<CachedAudioSamples>,
     current_track_path: Option<String>,
     webdav_full_cache: Option<Vec<u8>>,
+    /// 当前WebDAV流的跳转句柄（缓存完成前通过Range请求跳转）
+    stream_seek: Option<StreamSeekHandle>,
+    /// 当前流的缓冲状态（流式播放时）
+    stream_monitor: Option<StreamMonitor>,
+    /// 流式播放缓冲不足时的暂停和恢复
+    rebuffer: Rebuffer,
+    /// WebDAV后台完整下载的取消令牌
+    download_cancel: Option<CancellationToken>,
     current_track: Option<Track>,
+    gapless_enabled: bool,
+    links: Option<PlaybackLinks>,
+    gapless_next: Option<GaplessNext>,
+    /// 已为该曲目尝试过准备自动切歌（避免每次位置更新都重复尝试）
+    advance_prepared_for: Option<i64>,
+    /// 用户音量（界面上的值，按音量曲线换算后再乘淡入淡出增益）
+    volume: f32,
+    volume_settings: VolumeSettings,
+    /// 播放速度（与音频链路中的变速音频源共享）
+    rate: Arc<PlaybackRate>,
+    crossfade_ms: u64,
+    fading_out: Option<FadingSink>,
+    fade_in: Option<Fade>,
+    sleep_timer: Option<SleepCountdown>,
+    sleep_fade_secs: u64,
+    /// 睡眠定时器到期后的淡出
+    sleep_fade: Option<Fade>,
+    pause_fade_ms: u64,
+    pause_ramp: Option<PauseRamp>,
+    seek_step_ms: u64,
+    /// 等待可以跳转时执行的续播（曲目ID, 位置ms）
+    pending_resume: Option<(i64, u64)>,
+    /// 章节（曲目ID, 按开始位置排序的章节）
+    chapters: Option<(i64, Vec<Chapter>)>,
+    /// 已通知前端的当前章节（曲目ID, 章节下标）
+    current_chapter: Option<(i64, usize)>,
+    /// 剩余时长不超过该值时开始预加载下一首(ms)
+    preload_lead_ms: u64,
+    /// 已为该曲目请求过预加载
+    preload_requested_for: Option<i64>,
+    /// 跳过静音的裁剪点（曲目ID, 裁剪点）
+    trim: Option<(i64, TrimPoints)>,
+    /// 等待可以跳转时跳过的开头静音（曲目ID, 有效开始位置ms）
+    pending_trim_start: Option<(i64, u64)>,
+    /// A-B循环区间（曲目ID, 区间），换曲或停止时清除
+    loop_region: Option<(i64, LoopRegion)>,
+    /// 指定的输出设备名称，None为系统默认设备
+    output_device: Option<String>,
+    output_config: OutputConfig,
+    /// 按原始采样率输出时请求的采样率（最近播放的音源）
+    output_rate: Option<u32>,
+    /// 当前设备实际协商得到的输出格式
+    output_format: Option<OutputFormat>,
+    /// 实际打开的输出设备名称（用于断开检测）
+    active_device: Option<String>,
+    device_recovery: Option<DeviceRecovery>,
+    /// 本Actor的 TrackChanged 和 PositionChanged 都经过这里发出
+    positions: PositionReporter,
 }
 
 impl PlaybackActor {
@@ -88,6 +458,7 @@ impl PlaybackActor {
         state_rx: watch::Receiver<PlayerState>,
     ) -> (Self, mpsc::Sender<PlaybackMsg>) {
         let (tx, rx) = mpsc::channel(32);
+        let volume = state_rx.borrow().volume;
         
         let actor = Self {
             inbox: rx,
@@ -102,7 +473,42 @@ impl PlaybackActor {
             cached_samples: None,
             current_track_path: None,
             webdav_full_cache: None,
+            stream_seek: None,
+            stream_monitor: None,
+            rebuffer: Rebuffer::default(),
+            download_cancel: None,
             current_track: None,
+            gapless_enabled: false,
+            links: None,
+            gapless_next: None,
+            advance_prepared_for: None,
+            volume,
+            volume_settings: VolumeSettings::default(),
+            rate: Arc::new(PlaybackRate::default()),
+            crossfade_ms: 0,
+            fading_out: None,
+            fade_in: None,
+            sleep_timer: None,
+            sleep_fade_secs: DEFAULT_SLEEP_FADE_SECS,
+            sleep_fade: None,
+            pause_fade_ms: DEFAULT_PAUSE_FADE_MS,
+            pause_ramp: None,
+            seek_step_ms: DEFAULT_SEEK_STEP_MS,
+            pending_resume: None,
+            chapters: None,
+            current_chapter: None,
+            trim: None,
+            pending_trim_start: None,
+            loop_region: None,
+            preload_lead_ms: DEFAULT_PRELOAD_LEAD_MS,
+            preload_requested_for: None,
+            output_device: None,
+            output_config: OutputConfig::default(),
+            output_rate: None,
+            output_format: None,
+            active_device: None,
+            device_recovery: None,
+            positions: PositionReporter::new(DEFAULT_POSITION_INTERVAL_MS),
         };
         
         (actor, tx)
@@ -115,6 +521,7 @@ impl PlaybackActor {
         event_tx: mpsc::Sender<PlayerEvent>,
         state_rx: watch::Receiver<PlayerState>,
     ) -> Self {
+        let volume = state_rx.borrow().volume;
         Self {
             inbox,
             inbox_tx,
@@ -128,10 +535,57 @@ impl PlaybackActor {
             cached_samples: None,
             current_track_path: None,
             webdav_full_cache: None,
+            stream_seek: None,
+            stream_monitor: None,
+            rebuffer: Rebuffer::default(),
+            download_cancel: None,
             current_track: None,
+            gapless_enabled: false,
+            links: None,
+            gapless_next: None,
+            advance_prepared_for: None,
+            volume,
+            volume_settings: VolumeSettings::default(),
+            rate: Arc::new(PlaybackRate::default()),
+            crossfade_ms: 0,
+            fading_out: None,
+            fade_in: None,
+            sleep_timer: None,
+            sleep_fade_secs: DEFAULT_SLEEP_FADE_SECS,
+            sleep_fade: None,
+            pause_fade_ms: DEFAULT_PAUSE_FADE_MS,
+            pause_ramp: None,
+            seek_step_ms: DEFAULT_SEEK_STEP_MS,
+            pending_resume: None,
+            chapters: None,
+            current_chapter: None,
+            trim: None,
+            pending_trim_start: None,
+            loop_region: None,
+            preload_lead_ms: DEFAULT_PRELOAD_LEAD_MS,
+            preload_requested_for: None,
+            output_device: None,
+            output_config: OutputConfig::default(),
+            output_rate: None,
+            output_format: None,
+            active_device: None,
+            device_recovery: None,
+            positions: PositionReporter::new(DEFAULT_POSITION_INTERVAL_MS),
         }
     }
     
+    /// 设置无缝播放需要的Actor句柄
+    pub fn with_links(mut self, links: PlaybackLinks) -> Self {
+        self.links = Some(links);
+        self
+    }
+    
+    /// 设置预加载提前量（剩余时长不超过该值时预加载下一首）
+    pub fn with_preload_lead(mut self, lead_ms: u64) -> Self {
+        self.preload_lead_ms = lead_ms;
+        self
+    }
+    
     /// 运行Actor事件循环
     pub async fn run(mut self) {
         log::info!("PlaybackActor started");
@@ -140,6 +594,14 @@ impl PlaybackActor {
         
         let mut position_update_timer = tokio::time::interval(Duration::from_millis(100));
         position_update_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut fade_timer = tokio::time::interval(Duration::from_millis(FADE_TICK_MS));
+        fade_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut sleep_tick_timer = tokio::time::interval(Duration::from_secs(SLEEP_TICK_SECS));
+        sleep_tick_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut device_check_timer = tokio::time::interval(Duration::from_secs(DEVICE_CHECK_SECS));
+        device_check_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut visualization_timer = tokio::time::interval(visualization::ANALYSIS_INTERVAL);
+        visualization_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         
         loop {
             tokio::select! {
@@ -158,20 +620,129 @@ impl PlaybackActor {
                         }
                         PlaybackMsg::Stop => {
                             self.handle_stop();
+                            self.clear_loop_region().await;
                         }
                         PlaybackMsg::Seek { position_ms, reply } => {
+                            // 用户已手动跳转，不再执行等待中的续播
+                            self.pending_resume = None;
+                            self.pending_trim_start = None;
                             let result = self.handle_seek(position_ms).await;
                             let _ = reply.send(result);
                         }
+                        PlaybackMsg::SeekRelative { steps, reply } => {
+                            self.pending_resume = None;
+                            self.pending_trim_start = None;
+                            let result = self.handle_seek_relative(steps).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::ResumeAt { track_id, position_ms } => {
+                            self.pending_resume = Some((track_id, position_ms));
+                            self.apply_pending_resume().await;
+                        }
+                        PlaybackMsg::SyncExternalPosition { position_ms, is_playing } => {
+                            self.handle_sync_external_position(position_ms, is_playing).await;
+                        }
+                        PlaybackMsg::HandoffToNative { position_ms, reply } => {
+                            let result = self.handle_handoff_to_native(position_ms).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::SetChapters { track_id, mut chapters } => {
+                            log::info!("📑 设置章节: track_id={}, {}个章节", track_id, chapters.len());
+                            chapters.sort_by_key(|c| c.start_ms);
+                            self.chapters = (!chapters.is_empty()).then_some((track_id, chapters));
+                            self.current_chapter = None;
+                        }
+                        PlaybackMsg::SetTrimPoints { track_id, trim } => {
+                            self.trim = trim.filter(|t| t.is_trimmed()).map(|t| (track_id, t));
+                            if let Some((_, trim)) = self.trim.filter(|(_, t)| t.start_ms > 0) {
+                                self.pending_trim_start = Some((track_id, trim.start_ms));
+                                self.apply_pending_trim_start().await;
+                            }
+                        }
+                        PlaybackMsg::SeekChapter { forward, reply } => {
+                            self.pending_resume = None;
+                            self.pending_trim_start = None;
+                            let result = self.handle_seek_chapter(forward).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::SetLoopRegion { region, reply } => {
+                            let result = self.handle_set_loop_region(region).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::SetPauseFade(duration_ms) => {
+                            log::info!("🔉 设置暂停淡变: {}ms", duration_ms);
+                            self.pause_fade_ms = duration_ms;
+                        }
+                        PlaybackMsg::SetSeekStep(step_ms) => {
+                            log::info!("⏩ 设置跳转步长: {}ms", step_ms);
+                            self.seek_step_ms = step_ms;
+                        }
+                        PlaybackMsg::SetPositionInterval(interval_ms) => {
+                            log::info!("⏱️ 设置位置上报间隔: {}ms", interval_ms);
+                            self.positions.set_interval(interval_ms);
+                        }
+                        PlaybackMsg::SetOutputDevice { device_name, reply } => {
+                            let result = self.handle_set_output_device(device_name).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::SetOutputConfig { config, reply } => {
+                            let result = self.handle_set_output_config(config).await;
+                            let _ = reply.send(result);
+                        }
+                        PlaybackMsg::GetOutputFormat(reply) => {
+                            let _ = reply.send(self.output_format.clone());
+                        }
+                        PlaybackMsg::GetStreamStats(reply) => {
+                            let _ = reply.send(self.stream_stats());
+                        }
                         PlaybackMsg::SetVolume(volume) => {
                             self.handle_set_volume(volume);
                         }
+                        PlaybackMsg::SetVolumeSettings(settings) => {
+                            self.handle_set_volume_settings(settings);
+                        }
+                        PlaybackMsg::SetPlaybackRate(rate) => {
+                            self.handle_set_playback_rate(rate).await;
+                        }
+                        PlaybackMsg::SetPreservePitch(preserve_pitch) => {
+                            log::info!("⏩ 变速时保持音高: {}", preserve_pitch);
+                            self.rate.set_preserve_pitch(preserve_pitch);
+                        }
                         PlaybackMsg::GetPosition(reply) => {
-                            let position = self.get_current_position();
+                            // 外部引擎接管期间以前端同步的位置为准
+                            let position = self.positions.external_position().or_else(|| self.get_current_position());
                             let _ = reply.send(position);
                         }
+                        PlaybackMsg::SetGapless(enabled) => {
+                            self.handle_set_gapless(enabled);
+                        }
+                        PlaybackMsg::CancelGaplessNext => {
+                            self.cancel_gapless_next();
+                        }
+                        PlaybackMsg::SetCrossfade(duration_ms) => {
+                            log::info!("🎚️ 设置交叉淡入淡出: {}ms", duration_ms);
+                            self.crossfade_ms = duration_ms;
+                        }
+                        PlaybackMsg::SetSleepTimer(timer) => {
+                            self.handle_set_sleep_timer(timer).await;
+                            // 从设置时起重新计算通知间隔
+                            sleep_tick_timer.reset();
+                        }
+                        PlaybackMsg::SetSleepFade(fade_secs) => {
+                            self.sleep_fade_secs = fade_secs.clamp(MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS);
+                            log::info!("😴 设置睡眠定时淡出: {}s", self.sleep_fade_secs);
+                        }
+                        PlaybackMsg::GetSleepTimer(reply) => {
+                            let _ = reply.send(self.sleep_timer.as_ref().map(|t| t.status()));
+                        }
                         PlaybackMsg::CacheSamples { track_path, samples, channels, sample_rate } => {
                             self.handle_cache_samples(track_path, samples, channels, sample_rate);
+                            // 完整下载解码后得到准确时长
+                            let decoded_ms = self.cached_samples.as_ref()
+                                .and_then(|c| duration::from_samples(c.samples.len(), c.channels, c.sample_rate));
+                            self.report_decoded_duration(decoded_ms).await;
+                            self.apply_pending_trim_start().await;
+                            self.apply_pending_resume().await;
                         }
                         PlaybackMsg::Shutdown => {
                             log::info!("PlaybackActor shutdown requested");
@@ -185,6 +756,38 @@ impl PlaybackActor {
                     self.update_position().await;
                 }
                 
+                // 交叉淡入淡出、睡眠淡出和暂停淡变期间刷新音量
+                _ = fade_timer.tick(), if self.is_fading() || self.sleep_fade.is_some() || self.pause_ramp.is_some() => {
+                    if self.is_fading() {
+                        self.update_fades().await;
+                    }
+                    if self.sleep_fade.is_some() {
+                        self.update_sleep_fade().await;
+                    }
+                    if self.pause_ramp.is_some() {
+                        self.update_pause_ramp();
+                    }
+                }
+                
+                // 定期通知睡眠定时器剩余时间
+                _ = sleep_tick_timer.tick(), if self.sleep_timer.is_some() => {
+                    self.send_sleep_tick().await;
+                }
+                
+                // 可视化数据（前端启用时约每秒30次）
+                _ = visualization_timer.tick(), if visualization::is_enabled() => {
+                    self.emit_visualization_data().await;
+                }
+                
+                // 检测输出设备断开，断开后按退避间隔重试恢复
+                _ = device_check_timer.tick(), if self.active_device.is_some() || self.device_recovery.is_some() => {
+                    if self.device_recovery.is_some() {
+                        self.retry_device_recovery().await;
+                    } else {
+                        self.check_device_lost().await;
+                    }
+                }
+                
                 // 收件箱关闭
                 else => {
                     log::warn!("PlaybackActor inbox closed");
@@ -196,25 +799,221 @@ impl PlaybackActor {
         log::info!("PlaybackActor stopped");
     }
     
-    /// 初始化Sink池
+    /// 初始化Sink池（使用指定的输出设备，不存在时回退到默认设备）
+    #[tracing::instrument(level = "debug", skip_all)]
     async fn initialize_sink_pool(&mut self) -> Result<()> {
+        match self.open_sink_pool().await {
+            Ok(device_name) => {
+                // 用户操作成功打开了设备，不再需要自动恢复
+                self.device_recovery = None;
+                let format = self.output_format.clone();
+                let _ = self.event_tx.send(PlayerEvent::AudioDeviceReady { device_name, format }).await;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.event_tx.send(PlayerEvent::AudioDeviceFailed {
+                    error: e.to_string(),
+                    recoverable: true,
+                }).await;
+                Err(e)
+            }
+        }
+    }
+    
+    /// 打开输出设备并创建Sink池，返回实际使用的设备名称
+    async fn open_sink_pool(&mut self) -> Result<String> {
         log::info!("Initializing sink pool");
         
-        let device = LazyAudioDevice::default();
+        let request = OutputRequest {
+            sample_rate: self.output_rate.filter(|_| self.output_config.native_sample_rate),
+            exclusive: self.output_config.exclusive,
+        };
+        let device = LazyAudioDevice::default()
+            .with_device_name(self.output_device.clone())
+            .with_output_request(request);
         let dev = device.get_or_init().await?;
+        let device_name = dev.name.clone();
+        let format = dev.format.clone();
         let pool = SinkPool::with_default_capacity(dev.handle().clone());
         
         pool.warm_up(2)?;
         
+        if let Some(reason) = &format.fallback_reason {
+            log::warn!("⚠️ 输出格式未按请求打开: {}", reason);
+        }
         self.audio_device = Some(device);
         self.sink_pool = Some(pool);
-        log::info!("Sink pool initialized");
+        self.active_device = Some(device_name.clone());
+        self.output_format = Some(format);
+        log::info!("Sink pool initialized on {}", device_name);
+        
+        Ok(device_name)
+    }
+    
+    /// 释放当前设备和Sink池
+    fn release_device(&mut self) {
+        self.sink_pool = None;
+        self.audio_device = None;
+        self.active_device = None;
+        self.output_format = None;
+    }
+    
+    /// 检测当前输出设备是否已断开，断开后冻结播放位置并开始恢复
+    async fn check_device_lost(&mut self) {
+        let device_name = match self.active_device.clone() {
+            Some(name) => name,
+            None => return,
+        };
+        let name = device_name.clone();
+        let lost = tokio::task::spawn_blocking(move || is_device_lost(&name))
+            .await
+            .unwrap_or(false);
+        if !lost {
+            return;
+        }
+        
+        log::warn!("🔌 输出设备已断开: {}", device_name);
+        let position_ms = self.get_current_position().unwrap_or(0);
+        let was_playing = self.play_start_time.is_some() && !self.pause_ramp.is_some_and(|r| r.pausing);
+        let track = self.current_sink.as_ref().and(self.current_track.clone());
+        
+        self.handle_stop();
+        self.release_device();
+        // 保持逻辑位置不变，恢复后从这里继续
+        self.play_start_position_ms = position_ms;
+        self.device_recovery = Some(DeviceRecovery {
+            track,
+            position_ms,
+            was_playing,
+            attempts: 0,
+            retry_at: Instant::now(),
+        });
+        
+        let _ = self.event_tx.send(PlayerEvent::AudioDeviceFailed {
+            error: format!("输出设备已断开: {}", device_name),
+            recoverable: true,
+        }).await;
+        self.retry_device_recovery().await;
+    }
+    
+    /// 尝试在新的默认设备（或重新出现的指定设备）上恢复播放
+    async fn retry_device_recovery(&mut self) {
+        let recovery = match &self.device_recovery {
+            Some(recovery) if Instant::now() >= recovery.retry_at => recovery.clone(),
+            _ => return,
+        };
         
+        match self.open_sink_pool().await {
+            Ok(device_name) => {
+                log::info!("✅ 输出设备已恢复: {} (重试{}次)", device_name, recovery.attempts);
+                self.device_recovery = None;
+                let format = self.output_format.clone();
+                let _ = self.event_tx.send(PlayerEvent::AudioDeviceReady { device_name, format }).await;
+                if let Some(track) = recovery.track {
+                    if let Err(e) = self.resume_track_at(track, recovery.position_ms, recovery.was_playing).await {
+                        log::error!("❌ 设备恢复后继续播放失败: {}", e);
+                        let _ = self.event_tx.send(PlayerEvent::PlaybackError(e.to_string())).await;
+                    }
+                }
+            }
+            Err(e) => {
+                let attempts = recovery.attempts + 1;
+                let delay = device_retry_delay(attempts);
+                log::warn!("⚠️ 没有可用的输出设备（第{}次），{}秒后重试: {}", attempts, delay.as_secs(), e);
+                self.release_device();
+                self.device_recovery = Some(DeviceRecovery {
+                    attempts,
+                    retry_at: Instant::now() + delay,
+                    ..recovery
+                });
+            }
+        }
+    }
+    
+    /// 在新设备上从指定位置继续播放曲目
+    async fn resume_track_at(&mut self, track: Track, position_ms: u64, was_playing: bool) -> Result<()> {
+        if self.cached_samples.is_some() {
+            // 已缓存的样本可以直接从当前位置重建
+            self.handle_seek(position_ms).await?;
+        } else {
+            // 流式播放重新打开流后再跳转
+            self.handle_play(track).await?;
+            if position_ms > 0 {
+                if let Err(e) = self.handle_seek(position_ms).await {
+                    log::warn!("⚠️ 恢复播放位置失败: {}", e);
+                }
+            }
+        }
+        if !was_playing {
+            self.pause_sink();
+        }
         Ok(())
     }
     
-    /// 清理缓存
+    /// 切换输出设备：重建设备和Sink池，正在播放的曲目从当前位置继续
+    async fn handle_set_output_device(&mut self, device_name: Option<String>) -> Result<()> {
+        log::info!("🔈 切换输出设备: {}", device_name.as_deref().unwrap_or("系统默认"));
+        self.output_device = device_name;
+        self.reopen_output().await
+    }
+    
+    /// 设置输出格式：重建设备和Sink池，正在播放的曲目从当前位置继续
+    async fn handle_set_output_config(&mut self, config: OutputConfig) -> Result<()> {
+        if self.output_config == config {
+            return Ok(());
+        }
+        log::info!("🔈 设置输出格式: 原始采样率={}, 独占模式={}", config.native_sample_rate, config.exclusive);
+        self.output_config = config;
+        self.reopen_output().await
+    }
+    
+    /// 按当前设置重新打开输出设备，正在播放的曲目从当前位置继续
+    async fn reopen_output(&mut self) -> Result<()> {
+        // 尚未播放过，首次播放时按新设置初始化
+        if self.sink_pool.is_none() {
+            return Ok(());
+        }
+        
+        let position_ms = self.get_current_position().unwrap_or(0);
+        let was_playing = self.play_start_time.is_some() && !self.pause_ramp.is_some_and(|r| r.pausing);
+        let track = self.current_sink.as_ref().and(self.current_track.clone());
+        
+        self.handle_stop();
+        self.release_device();
+        self.initialize_sink_pool().await?;
+        
+        match track {
+            Some(track) => self.resume_track_at(track, position_ms, was_playing).await,
+            None => Ok(()),
+        }
+    }
+    
+    /// 按原始采样率输出时，设备格式与音源采样率不同且尚未按该采样率请求过
+    ///
+    /// 已请求过但设备只能使用最接近的采样率时不再重复打开
+    fn needs_output_reopen(&self, sample_rate: u32) -> bool {
+        self.output_config.native_sample_rate
+            && self.output_format.as_ref().is_some_and(|format| {
+                format.sample_rate != sample_rate && format.requested_sample_rate != Some(sample_rate)
+            })
+    }
+    
+    /// 以新的采样率重新打开输出设备（切歌时调用，旧曲目直接停止）
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn reopen_output_at(&mut self, sample_rate: u32) -> Result<()> {
+        log::info!("🔈 采样率变化，重新打开输出设备: {} Hz", sample_rate);
+        self.finish_fades();
+        self.handle_stop();
+        self.release_device();
+        self.output_rate = Some(sample_rate);
+        self.initialize_sink_pool().await
+    }
+    
+    /// 清理缓存，并取消上一首的后台下载
     fn clear_cache(&mut self) {
+        if let Some(token) = self.download_cancel.take() {
+            token.cancel();
+        }
         if self.cached_samples.is_some() || self.webdav_full_cache.is_some() {
             log::info!("Clearing track cache");
             self.cached_samples = None;
@@ -223,34 +1022,52 @@ impl PlaybackActor {
     }
     
     /// 处理播放请求
+    #[tracing::instrument(level = "debug", skip_all, fields(track_id = track.id))]
     async fn handle_play(&mut self, track: Track) -> Result<()> {
         use std::time::Instant;
         let start = Instant::now();
         log::info!("Playing: {:?}", track.title);
-        println!("[PlaybackActor] Starting playback: {:?}", track.title);
+        log::debug!("[PlaybackActor] Starting playback: {:?}", track.title);
         
         if self.current_track_path.as_ref() != Some(&track.path) {
             self.clear_cache();
         }
         
+        // 设备断开期间点播了新曲目，恢复后不再继续旧曲目
+        if let Some(recovery) = &mut self.device_recovery {
+            recovery.track = None;
+        }
+        
+        // 换曲时恢复原速，有单曲速度的曲目随后重新设置
+        if self.current_track.as_ref().map(|t| t.id) != Some(track.id) {
+            self.handle_set_playback_rate(1.0).await;
+            self.clear_loop_region().await;
+        }
+        
         self.current_track = Some(track.clone());
         self.current_track_path = Some(track.path.clone());
+        self.stream_seek = None;
+        self.clear_stream_monitor();
+        self.pending_resume = None;
+        self.pending_trim_start = None;
         
         if self.sink_pool.is_none() {
             let init_start = Instant::now();
             log::info!("First playback, initializing sink pool");
-            println!("[PlaybackActor] Initializing sink pool");
+            log::debug!("[PlaybackActor] Initializing sink pool");
             if let Err(e) = self.initialize_sink_pool().await {
                 log::error!("Failed to initialize sink pool: {}", e);
                 return Err(e);
             }
-            println!("[PlaybackActor] Sink pool ready ({}ms)", init_start.elapsed().as_millis());
+            log::debug!("[PlaybackActor] Sink pool ready ({}ms)", init_start.elapsed().as_millis());
         }
         
         let stop_start = Instant::now();
-        println!("[PlaybackActor] Stopping current playback");
-        self.handle_stop();
-        println!("[PlaybackActor] Stopped ({}ms)", stop_start.elapsed().as_millis());
+        log::debug!("[PlaybackActor] Stopping current playback");
+        let crossfading = self.begin_fade_out_or_stop();
+        // 新曲目总是以正常音量开始
+        self.pause_ramp = None;
+        log::debug!("[PlaybackActor] Stopped ({}ms, crossfade: {})", stop_start.elapsed().as_millis(), crossfading);
         
         // 确保Sink池已初始化
         if self.sink_pool.is_none() {
@@ -263,7 +1080,7 @@ impl PlaybackActor {
         use rodio::Source;
         let decode_start = Instant::now();
         let source: Box<dyn Source<Item = i16> + Send> = if has_cache {
-            println!("[PlaybackActor] Using cached samples");
+            log::debug!("[PlaybackActor] Using cached samples");
             let cached = self.cached_samples.as_ref().unwrap();
             use rodio::buffer::SamplesBuffer;
             Box::new(SamplesBuffer::new(
@@ -272,101 +1089,188 @@ impl PlaybackActor {
                 cached.samples.to_vec(),
             ))
         } else {
-            println!("[PlaybackActor] Preparing audio");
+            log::debug!("[PlaybackActor] Preparing audio");
             
-            let source_result: Result<Box<dyn rodio::Source<Item = i16> + Send>> = if track.path.starts_with("webdav://") {
-                println!("[PlaybackActor] WebDAV streaming playback");
-                self.decode_streaming(&track.path).await
-            } else {
-                println!("[PlaybackActor] Decoding local file: {}", track.path);
-                // 🚀 性能优化：使用spawn_blocking异步解码本地文件，避免阻塞
-                let path = track.path.clone();
-                tokio::task::spawn_blocking(move || {
-                    let decoder = AudioDecoder::new(&path);
-                    match decoder.decode() {
-                        Ok(s) => {
-                            println!("[PlaybackActor] Local decoder created");
-                            Ok(Box::new(s) as Box<dyn rodio::Source<Item = i16> + Send>)
-                        }
-                        Err(e) => {
-                            println!("[PlaybackActor] Decode failed: {}", e);
-                            Err(e)
-                        }
+            // 优先使用预加载好的解码器或HTTP流
+            let (preloaded_source, preloaded_stream) = match self.take_preloaded(&track).await {
+                Some(PreloadedAudio::Decoded(source)) => (Some(source), None),
+                Some(PreloadedAudio::Stream { reader, url }) => (None, Some((reader, url))),
+                None => (None, None),
+            };
+            
+            let source_result: Result<Box<dyn rodio::Source<Item = i16> + Send>> = if let Some(source) = preloaded_source {
+                full_download::mark_playing(None);
+                log::debug!("[PlaybackActor] Using preloaded decoder");
+                Ok(source)
+            } else if track.path.starts_with("webdav://") {
+                let cached_path = full_download::cached_file_for(track.id, &track.path);
+                full_download::mark_playing(cached_path.as_deref());
+                match cached_path {
+                    Some(cached_path) => {
+                        log::debug!("[PlaybackActor] WebDAV file cached locally: {:?}", cached_path);
+                        decode_local_file(cached_path.to_string_lossy().to_string()).await
                     }
-                })
-                .await
-                .map_err(|e| PlayerError::decode_error(format!("异步解码任务失败: {}", e)))?
+                    None => {
+                        log::debug!("[PlaybackActor] WebDAV streaming playback");
+                        self.decode_streaming(&track.path, preloaded_stream).await
+                    }
+                }
+            } else if track.path.starts_with("subsonic://") {
+                full_download::mark_playing(None);
+                log::debug!("[PlaybackActor] Subsonic streaming playback");
+                self.decode_streaming(&track.path, preloaded_stream).await
+            } else {
+                full_download::mark_playing(None);
+                log::debug!("[PlaybackActor] Decoding local file: {}", track.path);
+                decode_local_file(track.path.clone()).await
             };
             
             match source_result {
                 Ok(s) => {
-                    println!("[PlaybackActor] Audio source ready ({}ms)", decode_start.elapsed().as_millis());
+                    log::debug!("[PlaybackActor] Audio source ready ({}ms)", decode_start.elapsed().as_millis());
                     s
                 }
                 Err(e) => {
-                    println!("[PlaybackActor] Source preparation failed: {}", e);
+                    log::warn!("[PlaybackActor] Source preparation failed: {}", e);
                     return Err(e);
                 }
             }
         };
-        println!("[PlaybackActor] Audio prepared ({}ms)", decode_start.elapsed().as_millis());
+        log::debug!("[PlaybackActor] Audio prepared ({}ms)", decode_start.elapsed().as_millis());
+        
+        // 按原始采样率输出时，采样率不同的曲目需要重新打开设备，此时不做交叉淡入淡出
+        let crossfading = if self.needs_output_reopen(source.sample_rate()) {
+            self.reopen_output_at(source.sample_rate()).await?;
+            false
+        } else {
+            crossfading
+        };
         
         let sink_start = Instant::now();
-        println!("[PlaybackActor] Acquiring sink");
+        log::debug!("[PlaybackActor] Acquiring sink");
         let pool = self.sink_pool.as_ref().unwrap();
         let sink = match pool.acquire() {
             Ok(s) => {
-                println!("[PlaybackActor] Sink acquired ({}ms)", sink_start.elapsed().as_millis());
+                log::debug!("[PlaybackActor] Sink acquired ({}ms)", sink_start.elapsed().as_millis());
                 s
             }
             Err(e) => {
-                println!("[PlaybackActor] Sink acquisition failed: {}", e);
+                log::warn!("[PlaybackActor] Sink acquisition failed: {}", e);
                 return Err(e);
             }
         };
         
         let play_start = Instant::now();
-        let volume = self.state_rx.borrow().volume;
-        sink.set_volume(volume);
+        // 交叉淡入淡出时新Sink从静音开始淡入
+        sink.set_volume(if crossfading { 0.0 } else { self.output_volume() });
         
-        println!("[PlaybackActor] Starting playback");
-        sink.append(source);
+        let decoded_ms = source.total_duration().map(|d| d.as_millis() as u64);
+        log::debug!("[PlaybackActor] Starting playback");
+        sink.append(with_effects(source, Some(&track), self.volume_settings.preamp_db, &self.rate));
         sink.play();
-        println!("[PlaybackActor] Playback started ({}ms)", play_start.elapsed().as_millis());
+        log::debug!("[PlaybackActor] Playback started ({}ms)", play_start.elapsed().as_millis());
         
         self.current_sink = Some(sink);
         self.play_start_time = Some(Instant::now());
         self.play_start_position_ms = 0;
         
-        println!("[PlaybackActor] Play complete ({}ms)", start.elapsed().as_millis());
+        if crossfading {
+            self.fade_in = Some(Fade::start(self.crossfade_ms));
+            let _ = self.event_tx.send(PlayerEvent::CrossfadeStateChanged {
+                active: true,
+                duration_ms: self.crossfade_ms,
+            }).await;
+        }
+        
+        log::debug!("[PlaybackActor] Play complete ({}ms)", start.elapsed().as_millis());
         
-        if !has_cache && track.path.starts_with("webdav://") {
-            println!("[PlaybackActor] Starting background download for seek support");
+        // 同一曲目的下载仍在进行时不重复启动；是否下载由缓存策略决定，未下载时跳转走Range请求
+        let download_running = self.download_cancel.as_ref().is_some_and(|t| !t.is_cancelled());
+        let should_download = !has_cache && !download_running && track.path.starts_with("webdav://")
+            && match crate::CACHE_MANAGER.get() {
+                Some(manager) => manager.should_cache_track(track.id).await,
+                None => false,
+            };
+        if should_download {
+            log::debug!("[PlaybackActor] Starting background download for seek support");
+            let track_id = track.id;
             let track_path = track.path.clone();
             let inbox_tx = self.inbox_tx.clone();
+            let cancel = CancellationToken::new();
+            self.download_cancel = Some(cancel.clone());
             
             tokio::task::spawn(async move {
-                println!("[Background] Downloading WebDAV file");
-                
-                // TODO: Implement WebDAV full download
-                println!("[Background] WebDAV full download not yet implemented");
+                log::debug!("[Background] Downloading WebDAV file");
                 
-                let _ = inbox_tx;
-                let _ = track_path;
+                match full_download::fetch_samples(track_id, &track_path, &cancel).await {
+                    Ok(Some(decoded)) => {
+                        let _ = inbox_tx.send(PlaybackMsg::CacheSamples {
+                            track_path,
+                            samples: decoded.samples,
+                            channels: decoded.channels,
+                            sample_rate: decoded.sample_rate,
+                        }).await;
+                    }
+                    Ok(None) => log::debug!("[Background] WebDAV download cancelled"),
+                    Err(e) => log::warn!("⚠️ WebDAV后台下载失败: {}", e),
+                }
             });
+        } else if !has_cache && crate::remote_source::is_remote_track_path(&track.path) {
+            log::debug!("[PlaybackActor] Cache strategy: stream only");
         } else if !has_cache {
-            println!("[PlaybackActor] Local file uses hybrid player");
+            log::debug!("[PlaybackActor] Local file uses hybrid player");
+        }
+        
+        // 原生开始播放新曲目时外部引擎不再接管
+        if self.positions.external_playing().is_some() {
+            self.positions.end_external();
+            log::info!("🔀 原生播放新曲目，外部引擎不再接管: track_id={}", track.id);
+            let _ = self.event_tx.send(PlayerEvent::EngineChanged {
+                engine: PlaybackEngine::Native,
+                track_id: Some(track.id),
+                position_ms: 0,
+            }).await;
         }
         
         log::info!("Sending TrackChanged event");
-        let _ = self.event_tx.send(PlayerEvent::TrackChanged(Some(track))).await;
+        self.positions.announce(&self.event_tx, Some(track)).await;
+        self.report_decoded_duration(decoded_ms).await;
         
         log::info!("Playback started successfully");
         Ok(())
     }
     
-    /// 处理暂停
+    /// 处理暂停：先短暂淡出再暂停Sink，避免爆音
     fn handle_pause(&mut self) {
+        // 设备断开期间暂停，恢复设备后保持暂停
+        if let Some(recovery) = &mut self.device_recovery {
+            recovery.was_playing = false;
+        }
+        // 重新缓冲期间暂停，缓冲完成后保持暂停
+        self.rebuffer.set_resume(false);
+        self.finish_fades();
+        if self.current_sink.is_none() || self.pause_ramp.is_some_and(|r| r.pausing) {
+            return;
+        }
+        
+        // 从当前实际增益开始淡出（可能正在恢复淡入或睡眠淡出）
+        let sleep_gain = self.sleep_fade.map(|f| f.fade_out_gain()).unwrap_or(1.0);
+        let from = self.pause_gain() * sleep_gain;
+        // 睡眠淡出中手动暂停，定时器已到期，下次位置更新时直接触发
+        self.sleep_fade = None;
+        
+        if self.pause_fade_ms == 0 || self.play_start_time.is_none() {
+            self.pause_sink();
+            return;
+        }
+        log::info!("Pausing playback (fade {}ms)", self.pause_fade_ms);
+        self.pause_ramp = Some(PauseRamp::start(from, true, self.pause_fade_ms));
+        self.apply_fade_volumes();
+    }
+    
+    /// 立即暂停Sink并记录位置
+    fn pause_sink(&mut self) {
+        self.pause_ramp = None;
         if let Some(sink) = &self.current_sink {
             log::info!("Pausing playback");
             sink.pause();
@@ -376,30 +1280,281 @@ impl PlaybackActor {
             }
             self.play_start_time = None;
         }
+        // 暂停后恢复正常音量，恢复播放时再从静音淡入
+        self.apply_fade_volumes();
     }
     
-    /// 处理恢复
+    /// 处理恢复：从静音淡入；暂停淡出尚未完成时从当前增益反向淡入
     fn handle_resume(&mut self) {
-        if let Some(sink) = &self.current_sink {
-            log::info!("Resuming playback");
-            sink.play();
-            
+        if let Some(recovery) = &mut self.device_recovery {
+            recovery.was_playing = true;
+        }
+        // 重新缓冲期间恢复，等缓冲完成后再继续播放
+        if self.rebuffer.is_active() {
+            self.rebuffer.set_resume(true);
+            return;
+        }
+        if self.current_sink.is_none() {
+            return;
+        }
+        // 外部引擎接管期间由前端输出声音，原生输出保持暂停
+        if self.positions.external_playing().is_some() {
+            log::debug!("外部引擎接管中，不恢复原生输出");
+            return;
+        }
+        let pausing = self.pause_ramp.is_some_and(|r| r.pausing);
+        if self.play_start_time.is_some() && !pausing {
+            return;
+        }
+        
+        let from = if pausing { self.pause_gain() } else { 0.0 };
+        self.pause_ramp = (self.pause_fade_ms > 0)
+            .then(|| PauseRamp::start(from, false, self.pause_fade_ms));
+        self.apply_fade_volumes();
+        
+        if !pausing {
+            if let Some(sink) = &self.current_sink {
+                log::info!("Resuming playback");
+                sink.play();
+            }
             self.play_start_time = Some(Instant::now());
         }
     }
     
-    /// 处理停止
-    fn handle_stop(&mut self) {
-        if let Some(sink) = self.current_sink.take() {
-            log::info!("Stopping playback");
-            sink.clear();
-        }
-        
+    /// 暂停淡变的当前增益
+    fn pause_gain(&self) -> f32 {
+        self.pause_ramp.map(|r| r.gain()).unwrap_or(1.0)
+    }
+    
+    /// 刷新暂停淡变音量，淡出完成后暂停Sink
+    fn update_pause_ramp(&mut self) {
+        let ramp = match self.pause_ramp {
+            Some(ramp) => ramp,
+            None => return,
+        };
+        if !ramp.fade.is_finished() {
+            self.apply_fade_volumes();
+        } else if ramp.pausing {
+            self.pause_sink();
+        } else {
+            self.pause_ramp = None;
+            self.apply_fade_volumes();
+        }
+    }
+    
+    /// 按步长相对跳转，目标位置限制在曲目范围内
+    async fn handle_seek_relative(&mut self, steps: i64) -> Result<()> {
+        let position_ms = self.get_current_position().unwrap_or(0);
+        let duration_ms = self.current_track.as_ref()
+            .and_then(|t| t.duration_ms)
+            .filter(|d| *d > 0)
+            .map(|d| d as u64);
+        let target = relative_seek_target(position_ms, duration_ms, steps, self.seek_step_ms);
+        log::info!("⏩ 相对跳转: {}步 {}ms -> {}ms", steps, position_ms, target);
+        self.handle_seek(target).await
+    }
+    
+    /// 执行延后的续播：本地曲目等样本缓存完成，流式曲目等可以按Range跳转
+    async fn apply_pending_resume(&mut self) {
+        let Some((track_id, position_ms)) = self.pending_resume else { return };
+        if self.current_track.as_ref().map(|t| t.id) != Some(track_id) {
+            self.pending_resume = None;
+            return;
+        }
+        if !self.can_seek() {
+            log::debug!("⏳ 续播等待缓存完成: track_id={}, {}ms", track_id, position_ms);
+            return;
+        }
+        
+        self.pending_resume = None;
+        match self.handle_seek(position_ms).await {
+            Ok(()) => {
+                log::info!("⏯️ 已续播: track_id={}, {}ms", track_id, position_ms);
+                let _ = self.event_tx.send(PlayerEvent::PositionResumed { track_id, position_ms }).await;
+            }
+            Err(e) => log::warn!("⚠️ 续播失败: {}", e),
+        }
+    }
+    
+    /// 外部引擎同步位置：首次同步时暂停原生输出并通知前端引擎已切换
+    async fn handle_sync_external_position(&mut self, position_ms: u64, is_playing: bool) {
+        if !self.positions.sync_external(position_ms, is_playing) {
+            return;
+        }
+        
+        log::info!("🔀 外部引擎接管播放: {}ms", position_ms);
+        self.pending_resume = None;
+        self.pending_trim_start = None;
+        self.finish_fades();
+        self.pause_sink();
+        self.play_start_position_ms = position_ms;
+        let track_id = self.current_track.as_ref().map(|t| t.id);
+        let _ = self.event_tx.send(PlayerEvent::EngineChanged {
+            engine: PlaybackEngine::External,
+            track_id,
+            position_ms,
+        }).await;
+    }
+    
+    /// 外部引擎交还原生播放：用缓存的样本从交接位置开始播放，外部引擎暂停时交接后同样暂停
+    ///
+    /// 缓存完成前无法精确定位，返回错误，由外部引擎继续播放
+    async fn handle_handoff_to_native(&mut self, position_ms: u64) -> Result<()> {
+        let Some(is_playing) = self.positions.external_playing() else {
+            log::debug!("外部引擎未接管，交接按普通跳转处理: {}ms", position_ms);
+            return self.handle_seek(position_ms).await;
+        };
+        if self.cached_samples.is_none() {
+            log::warn!("⚠️ 音频尚未缓存完成，暂时无法交还原生播放");
+            return Err(PlayerError::NotCached);
+        }
+        
+        self.positions.end_external();
+        if let Err(e) = self.handle_seek(position_ms).await {
+            // 交接失败时仍由外部引擎播放
+            self.positions.sync_external(position_ms, is_playing);
+            return Err(e);
+        }
+        if !is_playing {
+            self.pause_sink();
+        }
+        
+        log::info!("🔀 交还原生播放: {}ms", position_ms);
+        let track_id = self.current_track.as_ref().map(|t| t.id);
+        let _ = self.event_tx.send(PlayerEvent::EngineChanged {
+            engine: PlaybackEngine::Native,
+            track_id,
+            position_ms,
+        }).await;
+        Ok(())
+    }
+    
+    /// 当前能否立即跳转（有缓存，或WebDAV流可以在流上跳转）
+    fn can_seek(&self) -> bool {
+        self.cached_samples.is_some() || (self.stream_seek.is_some() && self.current_sink.is_some())
+    }
+    
+    /// 跳过开头的静音；已播放过有效开始位置（续播、手动跳转）时不再跳转
+    async fn apply_pending_trim_start(&mut self) {
+        let Some((track_id, start_ms)) = self.pending_trim_start else { return };
+        if self.current_track.as_ref().map(|t| t.id) != Some(track_id)
+            || self.get_current_position().unwrap_or(0) >= start_ms
+        {
+            self.pending_trim_start = None;
+            return;
+        }
+        if !self.can_seek() {
+            return;
+        }
+        
+        self.pending_trim_start = None;
+        match self.handle_seek(start_ms).await {
+            Ok(()) => log::info!("🔇 跳过开头静音: track_id={}, {}ms", track_id, start_ms),
+            Err(e) => log::warn!("⚠️ 跳过开头静音失败: {}", e),
+        }
+    }
+    
+    /// 跳过静音时当前曲目的有效结束位置(ms)
+    ///
+    /// 从结尾裁剪点之后开始播放（手动跳转进结尾静音）时返回None，照常播放到文件结尾
+    fn trim_end_ms(&self) -> Option<u64> {
+        let (track_id, trim) = self.trim.as_ref()?;
+        if self.current_track.as_ref().map(|t| t.id) != Some(*track_id) {
+            return None;
+        }
+        trim.end_ms.filter(|end| self.play_start_position_ms < *end)
+    }
+    
+    /// 设置或清除A-B循环区间
+    ///
+    /// 循环依赖样本缓存做即时跳转，流式曲目缓存完成前返回 LoopUnavailable
+    async fn handle_set_loop_region(&mut self, region: Option<(u64, u64)>) -> Result<()> {
+        let Some((start_ms, end_ms)) = region else {
+            self.clear_loop_region().await;
+            return Ok(());
+        };
+        let Some(track_id) = self.current_track.as_ref().map(|t| t.id) else {
+            return Err(PlayerError::InvalidLoopRegion("没有正在播放的曲目".to_string()));
+        };
+        
+        let region = LoopRegion::new(start_ms, end_ms);
+        // 缓存的样本时长最准确，其次是曲库记录的时长
+        let duration_ms = self.cached_samples.as_ref()
+            .and_then(|c| duration::from_samples(c.samples.len(), c.channels, c.sample_rate))
+            .or(self.current_track.as_ref().and_then(|t| t.duration_ms).filter(|d| *d > 0).map(|d| d as u64));
+        region.validate(duration_ms).map_err(PlayerError::InvalidLoopRegion)?;
+        if self.cached_samples.is_none() {
+            log::warn!("⚠️ 音频尚未缓存完成，暂时无法A-B循环: track_id={}", track_id);
+            return Err(PlayerError::LoopUnavailable);
+        }
+        
+        log::info!("🔁 设置A-B循环: track_id={}, {}ms - {}ms", track_id, start_ms, end_ms);
+        self.loop_region = Some((track_id, region));
+        let _ = self.event_tx.send(PlayerEvent::LoopRegionChanged { track_id: Some(track_id), region: Some(region) }).await;
+        Ok(())
+    }
+    
+    /// 清除A-B循环区间并通知前端
+    async fn clear_loop_region(&mut self) {
+        let Some((track_id, _)) = self.loop_region.take() else { return };
+        log::info!("🔁 清除A-B循环: track_id={}", track_id);
+        let _ = self.event_tx.send(PlayerEvent::LoopRegionChanged { track_id: Some(track_id), region: None }).await;
+    }
+    
+    /// 当前曲目的A-B循环区间；从B点之后开始播放（手动跳过B点）时返回None，照常向后播放
+    fn active_loop_region(&self) -> Option<LoopRegion> {
+        let (track_id, region) = self.loop_region.as_ref()?;
+        if self.current_track.as_ref().map(|t| t.id) != Some(*track_id) {
+            return None;
+        }
+        Some(*region).filter(|r| self.play_start_position_ms < r.end_ms)
+    }
+    
+    /// 播放越过B点时跳回A点，返回是否已跳转
+    async fn check_loop_region(&mut self) -> bool {
+        if self.play_start_time.is_none() {
+            return false;
+        }
+        let Some(region) = self.active_loop_region() else { return false };
+        if self.get_current_position().unwrap_or(0) < region.end_ms {
+            return false;
+        }
+        match self.handle_seek(region.start_ms).await {
+            Ok(()) => {
+                log::debug!("🔁 A-B循环: 跳回 {}ms", region.start_ms);
+                true
+            }
+            Err(e) => {
+                log::warn!("⚠️ A-B循环跳转失败，已清除循环区间: {}", e);
+                self.clear_loop_region().await;
+                false
+            }
+        }
+    }
+    
+    /// 处理停止
+    fn handle_stop(&mut self) {
+        // 跳转、切歌和停止都会丢弃已追加的下一首和正在淡出的旧曲目
+        self.cancel_gapless_next();
+        // 设备恢复后不再继续已停止的曲目
+        if let Some(recovery) = &mut self.device_recovery {
+            recovery.track = None;
+        }
+        self.finish_fades();
+        self.pause_ramp = None;
+        self.clear_stream_monitor();
+        
+        if let Some(sink) = self.current_sink.take() {
+            log::info!("Stopping playback");
+            sink.clear();
+        }
+        
         self.play_start_time = None;
         self.play_start_position_ms = 0;
     }
     
-    /// 处理跳转，需要缓存支持
+    /// 处理跳转，优先使用缓存，WebDAV流在缓存完成前直接在流上跳转
+    #[tracing::instrument(level = "debug", skip(self))]
     async fn handle_seek(&mut self, position_ms: u64) -> Result<()> {
         let seek_start = Instant::now();
         log::info!("Seeking to: {}ms", position_ms);
@@ -412,8 +1567,11 @@ impl PlaybackActor {
                 cached.sample_rate,
             ),
             None => {
+                if let (Some(handle), Some(_)) = (self.stream_seek.clone(), &self.current_sink) {
+                    return self.handle_stream_seek(handle, position_ms, seek_start).await;
+                }
                 log::warn!("⚠️ 没有缓存的样本数据，seek暂时不可用（等待后台缓存中...）");
-                return Err(PlayerError::Internal("音频尚未缓存完成，请稍后再试".to_string()));
+                return Err(PlayerError::NotCached);
             }
         };
         
@@ -449,11 +1607,10 @@ impl PlaybackActor {
         let sink = pool.acquire()?;
         
         // 设置音量
-        let volume = self.state_rx.borrow().volume;
-        sink.set_volume(volume);
+        sink.set_volume(self.output_volume());
         
         // 添加音频源并播放
-        sink.append(source);
+        sink.append(with_effects(source, self.current_track.as_ref(), self.volume_settings.preamp_db, &self.rate));
         sink.play();
         
         // 更新播放状态
@@ -470,6 +1627,63 @@ impl PlaybackActor {
             position: position_ms,
             elapsed_ms,
         }).await;
+        self.positions.seeked();
+        self.report_position(true).await;
+        
+        Ok(())
+    }
+    
+    /// 在WebDAV流上跳转
+    ///
+    /// 暂停Sink后由格式读取器定位，目标已在缓冲区内时不会发起新请求，
+    /// 否则底层读取器用Range请求从估算的字节位置重新下载。
+    #[tracing::instrument(level = "debug", skip(self, handle, seek_start))]
+    async fn handle_stream_seek(&mut self, handle: StreamSeekHandle, position_ms: u64, seek_start: Instant) -> Result<()> {
+        self.finish_fades();
+        let was_playing = self.play_start_time.is_some();
+        if let Some(sink) = &self.current_sink {
+            sink.pause();
+        }
+        
+        let result = tokio::task::spawn_blocking(move || handle.seek(position_ms))
+            .await
+            .map_err(|e| PlayerError::SeekFailed(format!("跳转任务失败: {}", e)))?;
+        // 变速音频源中缓冲的是跳转前的样本
+        self.rate.reset();
+        
+        if let Some(sink) = &self.current_sink {
+            if was_playing {
+                sink.play();
+            }
+        }
+        
+        let actual_ms = match result {
+            Ok(actual_ms) => actual_ms,
+            Err(e) => {
+                let unsupported = matches!(&e, symphonia::core::errors::Error::IoError(io) if io.kind() == std::io::ErrorKind::Unsupported);
+                let err = if unsupported {
+                    PlayerError::SeekFailed("WebDAV服务器不支持Range请求".to_string())
+                } else {
+                    PlayerError::SeekFailed(e.to_string())
+                };
+                log::error!("❌ 流式跳转失败: {}", err);
+                let _ = self.event_tx.send(PlayerEvent::PlaybackError(err.to_string())).await;
+                return Err(err);
+            }
+        };
+        
+        self.play_start_position_ms = actual_ms;
+        self.play_start_time = if was_playing { Some(Instant::now()) } else { None };
+        
+        let elapsed_ms = seek_start.elapsed().as_millis() as u64;
+        log::info!("⚡ 流式Seek完成: {}ms (目标{}ms, 耗时: {}ms)", actual_ms, position_ms, elapsed_ms);
+        
+        let _ = self.event_tx.send(PlayerEvent::SeekCompleted {
+            position: actual_ms,
+            elapsed_ms,
+        }).await;
+        self.positions.seeked();
+        self.report_position(true).await;
         
         Ok(())
     }
@@ -477,15 +1691,49 @@ impl PlaybackActor {
     /// 处理设置音量请求
     fn handle_set_volume(&mut self, volume: f32) {
         let clamped_volume = volume.clamp(0.0, 1.0);
-        log::info!("🔊 设置音量: {:.0}%", clamped_volume * 100.0);
+        self.volume = clamped_volume;
+        log::info!("🔊 设置音量: {:.0}%（增益 {:.4}）", clamped_volume * 100.0, self.output_volume());
         
-        if let Some(sink) = &self.current_sink {
-            sink.set_volume(clamped_volume);
-        }
+        // 淡入淡出期间两个Sink按各自增益同比例缩放
+        self.apply_fade_volumes();
         
         // 注意：音量应该由StateActor管理，这里只是应用到sink
     }
     
+    /// Sink音量：用户音量按音量曲线换算后的增益
+    fn output_volume(&self) -> f32 {
+        self.volume_settings.gain(self.volume)
+    }
+    
+    /// 处理设置音量曲线请求，音量曲线立即生效，前置放大从下一首起生效
+    fn handle_set_volume_settings(&mut self, settings: VolumeSettings) {
+        log::info!(
+            "🔊 设置音量曲线: {:?}, 范围{}dB, 前置放大{:+.1}dB",
+            settings.curve, settings.range_db, settings.preamp_db
+        );
+        self.volume_settings = settings;
+        self.apply_fade_volumes();
+    }
+    
+    /// 处理设置播放速度：先按旧速度结算当前位置，之后的位置按新速度推算
+    async fn handle_set_playback_rate(&mut self, rate: f32) {
+        let rate = rate.clamp(time_stretch::MIN_RATE, time_stretch::MAX_RATE);
+        if (rate - self.rate.rate()).abs() < f32::EPSILON {
+            return;
+        }
+        if self.play_start_time.is_some() {
+            if let Some(position) = self.get_current_position() {
+                self.play_start_position_ms = position;
+            }
+            self.play_start_time = Some(Instant::now());
+        }
+        log::info!("⏩ 设置播放速度: {:.2}x", rate);
+        self.rate.set_rate(rate);
+        if let Some(links) = &self.links {
+            links.state.update_playback_rate(rate).await;
+        }
+    }
+    
     /// 处理缓存样本完成通知
     fn handle_cache_samples(
         &mut self,
@@ -515,12 +1763,77 @@ impl PlaybackActor {
         }
     }
     
+    /// 流式播放时检查缓冲：低于低水位时暂停等待，缓冲到高水位或下载结束后恢复
+    async fn check_stream_buffer(&mut self) {
+        let Some(monitor) = &self.stream_monitor else { return };
+        let level = monitor.level();
+        let playing = self.play_start_time.is_some() && self.pause_ramp.is_none();
+        
+        match self.rebuffer.update(level, playing, &buffering::settings()) {
+            Some(RebufferAction::Started(percent)) => {
+                log::warn!("⏳ 缓冲不足（{}KB），暂停等待重新缓冲", level.buffered / 1024);
+                if let Some(sink) = &self.current_sink {
+                    sink.pause();
+                }
+                if let Some(position) = self.get_current_position() {
+                    self.play_start_position_ms = position;
+                }
+                self.play_start_time = None;
+                let _ = self.event_tx.send(PlayerEvent::Buffering { percent }).await;
+            }
+            Some(RebufferAction::Progress(percent)) => {
+                let _ = self.event_tx.send(PlayerEvent::Buffering { percent }).await;
+            }
+            Some(RebufferAction::Finished { resume }) => {
+                log::info!("✅ 重新缓冲完成（{}KB），{}", level.buffered / 1024, if resume { "继续播放" } else { "保持暂停" });
+                if resume {
+                    if let Some(sink) = &self.current_sink {
+                        sink.play();
+                    }
+                    self.play_start_time = Some(Instant::now());
+                }
+                let _ = self.event_tx.send(PlayerEvent::Buffering { percent: 100 }).await;
+            }
+            None => {}
+        }
+    }
+    
+    /// 当前流不再播放：丢弃缓冲状态，重新缓冲中时通知前端结束
+    fn clear_stream_monitor(&mut self) {
+        self.stream_monitor = None;
+        if self.rebuffer.is_active() {
+            let _ = self.event_tx.try_send(PlayerEvent::Buffering { percent: 100 });
+        }
+        self.rebuffer = Rebuffer::default();
+    }
+    
+    /// 当前流的缓冲统计
+    fn stream_stats(&self) -> Option<StreamStats> {
+        let mut stats = self.stream_monitor.as_ref()?.stats();
+        stats.rebuffer_count = self.rebuffer.count();
+        stats.buffering = self.rebuffer.is_active();
+        Some(stats)
+    }
+    
+    /// 解码得到的时长与曲库记录不符时更正当前曲目，并通知前端和曲库
+    async fn report_decoded_duration(&mut self, duration_ms: Option<u64>) {
+        let (Some(duration_ms), Some(track)) = (duration_ms, &mut self.current_track) else { return };
+        if !duration::needs_correction(track.duration_ms, duration_ms) {
+            return;
+        }
+        log::info!("⏱️ 更正曲目时长: {:?} {:?}ms -> {}ms", track.title, track.duration_ms, duration_ms);
+        track.duration_ms = Some(duration_ms as i64);
+        let track_id = track.id;
+        let _ = self.event_tx.send(PlayerEvent::DurationCorrected { track_id, duration_ms }).await;
+    }
+    
     /// 获取当前播放位置
     fn get_current_position(&self) -> Option<u64> {
         // 如果正在播放，计算当前位置
         if let Some(start_time) = self.play_start_time {
-            let elapsed = start_time.elapsed().as_millis() as u64;
-            Some(self.play_start_position_ms + elapsed)
+            // 经过的时间按播放速度换算为曲目时间
+            let elapsed = start_time.elapsed().as_secs_f64() * 1000.0 * self.rate.rate() as f64;
+            Some(self.play_start_position_ms + elapsed as u64)
         } else {
             // 暂停或停止状态，返回保存的位置
             Some(self.play_start_position_ms)
@@ -529,6 +1842,38 @@ impl PlaybackActor {
     
     /// 更新位置（发送事件）
     async fn update_position(&mut self) {
+        if self.sleep_fade.is_none() && self.sleep_timer.as_ref().is_some_and(|t| t.is_due()) {
+            self.begin_sleep_fade().await;
+        }
+        
+        // A-B循环：越过B点时跳回A点，循环期间不准备下一首
+        if self.check_loop_region().await {
+            return;
+        }
+        let looping = self.active_loop_region().is_some();
+        
+        // 无缝播放：上一首已播完、Sink只剩追加的下一首
+        let reached_boundary = match (&self.current_sink, &self.gapless_next) {
+            (Some(sink), Some(_)) => sink.len() <= 1,
+            _ => false,
+        };
+        if self.should_request_preload() {
+            self.request_preload().await;
+        }
+        if reached_boundary {
+            self.handle_gapless_boundary().await;
+        } else if !looping && self.should_prepare_gapless() {
+            self.prepare_gapless_next().await;
+        } else if !looping && self.should_start_auto_crossfade() {
+            self.start_auto_crossfade().await;
+        }
+        
+        self.check_stream_buffer().await;
+        
+        // 跳过静音时播放到结尾裁剪点即视为完成
+        let reached_trim_end = self.play_start_time.is_some()
+            && self.trim_end_ms().is_some_and(|end| self.get_current_position().unwrap_or(0) >= end);
+        
         // 检查播放是否完成
         if let Some(sink) = &self.current_sink {
             // 从状态读取当前曲目信息
@@ -537,13 +1882,23 @@ impl PlaybackActor {
             
             // 🔧 修复：只有在播放一段时间后（至少500ms）才检查empty
             // 避免刚append音频就被判断为空而停止
-            if sink.empty() && is_playing {
+            if (sink.empty() || reached_trim_end) && is_playing {
                 if let Some(start_time) = self.play_start_time {
                     let elapsed = start_time.elapsed().as_millis();
                     
                     // 只有播放超过500ms且队列为空，才认为播放完成
                     if elapsed > 500 {
                         log::info!("✅ 曲目播放完成（播放时长: {}ms）", elapsed);
+                        if reached_trim_end {
+                            log::info!("🔇 跳过结尾静音");
+                        }
+                        
+                        // 睡眠定时器在此触发时不发送TrackCompleted，前端据此自动切歌
+                        if self.count_sleep_track_completed() {
+                            self.handle_stop();
+                            self.fire_sleep_timer().await;
+                            return;
+                        }
                         
                         if let Some(track) = current_track {
                             let _ = self.event_tx.send(PlayerEvent::TrackCompleted(track)).await;
@@ -560,83 +1915,517 @@ impl PlaybackActor {
         
         // ✅ 修复3: 发送位置更新事件（播放和暂停时都发送，确保UI能正确显示暂停位置）
         // 即使在暂停状态，也需要定期发送位置更新，否则前端会认为位置为0
+        self.report_position(false).await;
         if let Some(position) = self.get_current_position() {
-            let _ = self.event_tx.send(PlayerEvent::PositionChanged(position)).await;
+            self.check_chapter_change(position).await;
         }
     }
     
-    /// WEBDAV流式播放（真正的即点即播）
-    async fn decode_streaming(&self, track_path: &str) -> Result<Box<dyn rodio::Source<Item = i16> + Send>> {
-        use crate::streaming::SimpleHttpReader;
-        use tokio::time::{timeout, Duration};
-        use symphonia::core::io::MediaSourceStream;
-        use symphonia::core::probe::Hint;
-        use crate::player::audio::SymphoniaDecoder;
+    /// 上报当前位置；停止后（没有Sink）不再上报，投屏时由投屏会话上报
+    async fn report_position(&mut self, force: bool) {
+        if self.current_sink.is_none() {
+            return;
+        }
+        let Some(position) = self.get_current_position() else { return };
+        // 播放中的位置按经过时间推算，暂停时为记录的准确位置
+        let is_estimated = self.play_start_time.is_some();
+        let track_id = self.current_track.as_ref().map(|t| t.id);
+        self.positions.report(&self.event_tx, track_id, position, is_estimated, force).await;
+    }
+    
+    /// 播放中发送可视化数据；暂停（包括暂停淡出）和停止时不发送
+    async fn emit_visualization_data(&mut self) {
+        let playing = self.play_start_time.is_some() && !self.pause_ramp.is_some_and(|r| r.pausing);
+        if !playing {
+            return;
+        }
+        if let Some(data) = visualization::analyze() {
+            let _ = self.event_tx.send(PlayerEvent::VisualizationData(data)).await;
+        }
+    }
+    
+    /// 当前曲目的章节，章节属于其他曲目时为None
+    fn current_chapters(&self) -> Option<&[Chapter]> {
+        let (track_id, chapters) = self.chapters.as_ref()?;
+        (self.current_track.as_ref().map(|t| t.id) == Some(*track_id)).then_some(chapters.as_slice())
+    }
+    
+    /// 播放跨过章节边界时通知前端
+    async fn check_chapter_change(&mut self, position_ms: u64) {
+        let Some(track_id) = self.current_track.as_ref().map(|t| t.id) else { return };
+        let Some((index, chapter)) = self.current_chapters()
+            .and_then(|chapters| chapter_at(chapters, position_ms).map(|i| (i, chapters[i].clone())))
+        else {
+            return;
+        };
+        if self.current_chapter == Some((track_id, index)) {
+            return;
+        }
+        self.current_chapter = Some((track_id, index));
+        log::debug!("📑 进入章节 {}: {:?}", chapter.index, chapter.title);
+        let _ = self.event_tx.send(PlayerEvent::ChapterChanged { track_id, chapter }).await;
+    }
+    
+    /// 跳到相邻章节；已在最后一章时不能前进
+    async fn handle_seek_chapter(&mut self, forward: bool) -> Result<()> {
+        let position_ms = self.get_current_position().unwrap_or(0);
+        let chapters = self.current_chapters()
+            .ok_or_else(|| PlayerError::SeekFailed("当前曲目没有章节".to_string()))?;
+        let target = if forward {
+            next_chapter_start(chapters, position_ms)
+                .ok_or_else(|| PlayerError::SeekFailed("已经是最后一章".to_string()))?
+        } else {
+            previous_chapter_start(chapters, position_ms)
+        };
+        log::info!("📑 章节跳转: {}ms -> {}ms", position_ms, target);
+        self.handle_seek(target).await
+    }
+    
+    /// 处理设置无缝播放
+    fn handle_set_gapless(&mut self, enabled: bool) {
+        log::info!("🔗 设置无缝播放: {}", enabled);
+        self.gapless_enabled = enabled;
+        if !enabled {
+            self.cancel_gapless_next();
+        }
+    }
+    
+    /// 取消已追加的下一首，允许按新的播放列表/模式重新准备
+    fn cancel_gapless_next(&mut self) {
+        if let Some(next) = self.gapless_next.take() {
+            log::info!("🔗 取消已追加的下一首: {:?}", next.track.title);
+            next.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.advance_prepared_for = None;
+    }
+    
+    /// 是否到了准备下一首的时机
+    fn should_prepare_gapless(&self) -> bool {
+        // 交叉淡入淡出开启时由淡变接管曲目衔接
+        if !self.gapless_enabled || self.crossfade_ms > 0 || self.links.is_none() || self.gapless_next.is_some() {
+            return false;
+        }
+        // 睡眠定时器在当前曲目结束时触发，不衔接下一首
+        if self.sleep_stops_after_current() {
+            return false;
+        }
+        // 跳过结尾静音时在裁剪点结束，追加的下一首会排在静音之后
+        if self.trim_end_ms().is_some() {
+            return false;
+        }
+        // 暂停时不准备
+        if self.current_sink.is_none() || self.play_start_time.is_none() {
+            return false;
+        }
         
-        log::info!("🌊 WEBDAV流式播放: {}", track_path);
-        println!("🌊 [PlaybackActor] WEBDAV流式播放（真正的流式解码）: {}", track_path);
+        let track = match &self.current_track {
+            Some(track) => track,
+            None => return false,
+        };
+        if self.advance_prepared_for == Some(track.id) {
+            return false;
+        }
         
-        // 只支持WEBDAV
-        if !track_path.starts_with("webdav://") {
-            return Err(PlayerError::decode_error("不支持的协议，仅支持WebDAV流式播放".to_string()));
+        // 时长未知时无法判断何时接近结尾
+        let duration_ms = match track.duration_ms {
+            Some(d) if d > 0 => d as u64,
+            _ => return false,
+        };
+        let position_ms = self.get_current_position().unwrap_or(0);
+        duration_ms.saturating_sub(position_ms) <= GAPLESS_PREPARE_THRESHOLD_MS
+    }
+    
+    /// 准备下一首并追加到当前Sink
+    async fn prepare_gapless_next(&mut self) {
+        let links = match &self.links {
+            Some(links) => links.clone(),
+            None => return,
+        };
+        if let Some(track) = &self.current_track {
+            self.advance_prepared_for = Some(track.id);
         }
         
-        // 解析WEBDAV URL（包含完整配置）
-        let (http_url, username, password, _http_protocol) = self.parse_webdav_url_with_config(track_path)?;
+        // 最后一首且不循环时没有下一首，按普通方式播放完成
+        let next = match links.playlist.peek_next().await {
+            Ok(Some(next)) => next,
+            Ok(None) => {
+                log::debug!("🔗 没有下一首，无需准备无缝播放");
+                return;
+            }
+            Err(e) => {
+                log::warn!("⚠️ 获取下一首失败: {}", e);
+                return;
+            }
+        };
         
-        log::info!("📡 HTTP URL: {}", http_url);
-        println!("📡 [PlaybackActor] 创建HTTP流式Reader（即点即播模式）...");
+        // 远程曲目需要网络缓冲，仍走常规切歌流程
+        if crate::remote_source::is_remote_track_path(&next.path) {
+            log::debug!("🔗 下一首为远程曲目，跳过无缝播放: {}", next.path);
+            return;
+        }
         
-        // 🚀 创建SimpleHttpReader（零等待，立即返回）
-        let create_future = SimpleHttpReader::new(http_url.clone(), username, password);
+        // 优先使用PreloadActor预先创建的解码器，未命中时直接解码本地文件
+        let source = match self.take_preloaded(&next).await {
+            Some(PreloadedAudio::Decoded(source)) => source,
+            _ => {
+                let path = next.path.clone();
+                let decoded = tokio::task::spawn_blocking(move || {
+                    AudioDecoder::new(&path).decode()
+                        .map(|s| Box::new(s) as Box<dyn rodio::Source<Item = i16> + Send>)
+                })
+                .await;
+                match decoded {
+                    Ok(Ok(source)) => source,
+                    Ok(Err(e)) => {
+                        log::warn!("⚠️ 下一首解码失败: {}", e);
+                        return;
+                    }
+                    Err(e) => {
+                        log::warn!("⚠️ 下一首解码任务失败: {}", e);
+                        return;
+                    }
+                }
+            }
+        };
+        
+        // 采样率不同时需要重新打开设备，不能追加到同一个Sink
+        if self.needs_output_reopen(source.sample_rate()) {
+            log::debug!("🔗 下一首采样率不同（{} Hz），跳过无缝播放", source.sample_rate());
+            return;
+        }
         
-        let reader = match timeout(Duration::from_secs(5), create_future).await {
-            Ok(Ok(r)) => {
-                println!("✅ [PlaybackActor] HTTP Reader创建成功（零延迟）");
-                r
+        let sink = match &self.current_sink {
+            Some(sink) => sink,
+            None => return,
+        };
+        
+        let (source, cancelled) = CancellableSource::new(with_effects(source, Some(&next), self.volume_settings.preamp_db, &self.rate));
+        sink.append(source);
+        log::info!("🔗 已追加下一首到当前Sink: {:?}", next.title);
+        self.gapless_next = Some(GaplessNext { track: next, cancelled });
+    }
+    
+    /// 无缝切换到已追加的下一首
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn handle_gapless_boundary(&mut self) {
+        let next = match self.gapless_next.take() {
+            Some(next) => next,
+            None => return,
+        };
+        let track = next.track;
+        log::info!("🔗 无缝切换到: {:?}", track.title);
+        // 已追加下一首说明当前曲目不是睡眠定时的最后一首，这里只计数
+        self.count_sleep_track_completed();
+        
+        // 样本缓存属于上一首
+        self.clear_cache();
+        self.handle_set_playback_rate(1.0).await;
+        self.clear_loop_region().await;
+        self.current_track = Some(track.clone());
+        self.current_track_path = Some(track.path.clone());
+        self.play_start_time = Some(Instant::now());
+        self.play_start_position_ms = 0;
+        self.advance_prepared_for = None;
+        
+        self.sync_actors_to_track(&track).await;
+        
+        self.positions.announce(&self.event_tx, Some(track)).await;
+    }
+    
+    /// 自动切歌后同步播放列表索引、状态和预加载
+    async fn sync_actors_to_track(&self, track: &Track) {
+        let links = match &self.links {
+            Some(links) => links,
+            None => return,
+        };
+        
+        // 推进播放列表索引（与预览的是同一首）
+        match links.playlist.get_next().await {
+            Ok(Some(t)) if t.id == track.id => {}
+            _ => {
+                log::warn!("⚠️ 播放列表下一首与自动切换的曲目不一致，按实际播放曲目校正");
+                let _ = links.playlist.jump_to(track.id).await;
             }
-            Ok(Err(e)) => {
-                let err_msg = format!("创建HTTP Reader失败: {}", e);
-                log::error!("❌ {}", err_msg);
-                println!("❌ [PlaybackActor] {}", err_msg);
-                return Err(PlayerError::decode_error(err_msg));
+        }
+        
+        links.state.update_current_track(Some(track.clone())).await;
+    }
+    
+    /// 切歌时开始淡出当前Sink，无法交叉淡入淡出时直接停止
+    ///
+    /// 返回是否进入交叉淡入淡出
+    fn begin_fade_out_or_stop(&mut self) -> bool {
+        // 暂停或停止状态下没有可淡出的声音
+        if self.crossfade_ms == 0 || self.play_start_time.is_none() || self.current_sink.is_none() {
+            self.handle_stop();
+            return false;
+        }
+        
+        // 上一次淡变尚未完成时，旧的淡出Sink直接结束
+        let start_gain = self.fade_in.map(|f| f.fade_in_gain()).unwrap_or(1.0);
+        self.cancel_gapless_next();
+        self.finish_fades();
+        
+        if let Some(sink) = self.current_sink.take() {
+            log::info!("🎚️ 开始交叉淡入淡出: {}ms", self.crossfade_ms);
+            self.fading_out = Some(FadingSink {
+                sink,
+                fade: Fade::start(self.crossfade_ms),
+                start_gain,
+            });
+        }
+        self.play_start_time = None;
+        self.play_start_position_ms = 0;
+        true
+    }
+    
+    /// 是否处于交叉淡入淡出中
+    fn is_fading(&self) -> bool {
+        self.fading_out.is_some() || self.fade_in.is_some()
+    }
+    
+    /// 按当前淡变进度设置两个Sink的音量
+    fn apply_fade_volumes(&self) {
+        let volume = self.output_volume();
+        let sleep_gain = self.sleep_fade.map(|f| f.fade_out_gain()).unwrap_or(1.0);
+        if let Some(sink) = &self.current_sink {
+            let gain = self.fade_in.map(|f| f.fade_in_gain()).unwrap_or(1.0);
+            sink.set_volume(volume * gain * sleep_gain * self.pause_gain());
+        }
+        if let Some(fading) = &self.fading_out {
+            fading.sink.set_volume(volume * fading.start_gain * fading.fade.fade_out_gain() * sleep_gain);
+        }
+    }
+    
+    /// 刷新淡变音量，完成后释放旧Sink
+    async fn update_fades(&mut self) {
+        self.apply_fade_volumes();
+        
+        if self.fading_out.as_ref().is_some_and(|f| f.fade.is_finished()) {
+            // 归还到Sink池
+            self.fading_out = None;
+        }
+        if self.fade_in.is_some_and(|f| f.is_finished()) {
+            self.fade_in = None;
+            self.apply_fade_volumes();
+        }
+        
+        if !self.is_fading() {
+            log::info!("🎚️ 交叉淡入淡出完成");
+            let _ = self.event_tx.send(PlayerEvent::CrossfadeStateChanged {
+                active: false,
+                duration_ms: self.crossfade_ms,
+            }).await;
+        }
+    }
+    
+    /// 立即结束淡变：丢弃淡出中的旧Sink，当前Sink恢复正常音量
+    fn finish_fades(&mut self) {
+        if !self.is_fading() {
+            return;
+        }
+        self.fading_out = None;
+        self.fade_in = None;
+        self.apply_fade_volumes();
+        let _ = self.event_tx.try_send(PlayerEvent::CrossfadeStateChanged {
+            active: false,
+            duration_ms: self.crossfade_ms,
+        });
+    }
+    
+    /// 设置或取消睡眠定时器
+    async fn handle_set_sleep_timer(&mut self, timer: Option<SleepTimer>) {
+        let had_timer = self.sleep_timer.is_some();
+        if self.sleep_fade.take().is_some() {
+            self.apply_fade_volumes();
+        }
+        
+        match timer {
+            Some(timer) => {
+                log::info!("😴 设置睡眠定时器: {:?}", timer);
+                let countdown = SleepCountdown::start(timer);
+                // 已追加的下一首不能再播放
+                if countdown.is_last_track() {
+                    self.cancel_gapless_next();
+                }
+                self.sleep_timer = Some(countdown);
+                self.send_sleep_tick().await;
             }
-            Err(_) => {
-                let err_msg = "创建HTTP Reader超时（5秒）";
-                log::error!("❌ {}", err_msg);
-                println!("❌ [PlaybackActor] {}", err_msg);
-                return Err(PlayerError::decode_error(err_msg.to_string()));
+            None => {
+                self.sleep_timer = None;
+                if had_timer {
+                    log::info!("😴 取消睡眠定时器");
+                    let _ = self.event_tx.send(PlayerEvent::SleepTimerCancelled).await;
+                }
             }
+        }
+    }
+    
+    /// 发送睡眠定时器剩余时间
+    async fn send_sleep_tick(&self) {
+        if let Some(timer) = &self.sleep_timer {
+            let _ = self.event_tx.send(PlayerEvent::SleepTimerTick(timer.status())).await;
+        }
+    }
+    
+    /// 当前曲目结束时睡眠定时器是否触发
+    fn sleep_stops_after_current(&self) -> bool {
+        self.sleep_timer.as_ref().is_some_and(|t| t.is_last_track())
+    }
+    
+    /// 曲目播放完成时为睡眠定时器计数，返回是否应当触发
+    fn count_sleep_track_completed(&mut self) -> bool {
+        self.sleep_timer.as_mut().is_some_and(|t| t.on_track_completed())
+    }
+    
+    /// 睡眠定时器到期：播放中先淡出，暂停时直接触发
+    async fn begin_sleep_fade(&mut self) {
+        if self.current_sink.is_none() || self.play_start_time.is_none() {
+            self.fire_sleep_timer().await;
+            return;
+        }
+        log::info!("😴 睡眠定时器到期，{}秒内淡出", self.sleep_fade_secs);
+        self.sleep_fade = Some(Fade::start(self.sleep_fade_secs * 1000));
+    }
+    
+    /// 刷新睡眠淡出音量，完成后暂停
+    async fn update_sleep_fade(&mut self) {
+        self.apply_fade_volumes();
+        if self.sleep_fade.is_some_and(|f| f.is_finished()) {
+            // 暂停而不是停止，恢复播放时从当前位置继续（音量已淡出，直接暂停）
+            self.pause_sink();
+            self.fire_sleep_timer().await;
+        }
+    }
+    
+    /// 清除睡眠定时器并通知暂停
+    async fn fire_sleep_timer(&mut self) {
+        log::info!("😴 睡眠定时器触发，暂停播放");
+        self.sleep_timer = None;
+        if self.sleep_fade.take().is_some() {
+            self.apply_fade_volumes();
+        }
+        if let Some(links) = &self.links {
+            links.state.update_playing_state(false).await;
+        }
+        let _ = self.event_tx.send(PlayerEvent::SleepTimerFired).await;
+    }
+    
+    /// 是否到了自然结束前开始交叉淡入淡出的时机
+    fn should_start_auto_crossfade(&self) -> bool {
+        if self.crossfade_ms == 0 || self.links.is_none() || self.is_fading() || self.sleep_stops_after_current() {
+            return false;
+        }
+        if self.current_sink.is_none() || self.play_start_time.is_none() {
+            return false;
+        }
+        
+        let track = match &self.current_track {
+            Some(track) => track,
+            None => return false,
+        };
+        if self.advance_prepared_for == Some(track.id) {
+            return false;
+        }
+        
+        // 跳过结尾静音时在裁剪点前开始淡变
+        let duration_ms = match self.trim_end_ms().or(track.duration_ms.filter(|d| *d > 0).map(|d| d as u64)) {
+            Some(d) => d,
+            None => return false,
+        };
+        let position_ms = self.get_current_position().unwrap_or(0);
+        duration_ms.saturating_sub(position_ms) <= self.crossfade_ms
+    }
+    
+    /// 曲目自然结束前淡入下一首
+    async fn start_auto_crossfade(&mut self) {
+        let links = match &self.links {
+            Some(links) => links.clone(),
+            None => return,
         };
+        if let Some(track) = &self.current_track {
+            self.advance_prepared_for = Some(track.id);
+        }
         
-        log::info!("✅ HTTP Reader已创建，等待初始缓冲...");
-        println!("🎵 [PlaybackActor] 等待初始缓冲（提升播放流畅度）...");
+        // 最后一首且不循环时正常播放完成
+        let next = match links.playlist.peek_next().await {
+            Ok(Some(next)) => next,
+            _ => return,
+        };
         
-        // 🔧 等待初始缓冲（256KB），确保格式探测不会因网络延迟而卡顿
-        const INITIAL_BUFFER_SIZE: usize = 256 * 1024; // 256KB
-        let buffer_timeout = Duration::from_secs(3);
-        let buffer_start = std::time::Instant::now();
+        // 淡出的曲目按播放完成计数
+        self.count_sleep_track_completed();
         
-        loop {
-            let available = reader.get_buffered_size();
-            
-            if available >= INITIAL_BUFFER_SIZE {
-                log::info!("✅ 初始缓冲完成: {}KB", available / 1024);
-                println!("✅ [PlaybackActor] 初始缓冲完成: {}KB", available / 1024);
-                break;
-            }
-            
-            if buffer_start.elapsed() > buffer_timeout {
-                log::warn!("⚠️ 初始缓冲超时（仅缓冲了{}KB），继续播放", available / 1024);
-                println!("⚠️ [PlaybackActor] 初始缓冲超时（仅缓冲了{}KB），继续播放", available / 1024);
-                break;
+        match self.handle_play(next.clone()).await {
+            Ok(()) => self.sync_actors_to_track(&next).await,
+            Err(e) => {
+                log::error!("❌ 交叉淡入下一首失败: {}", e);
+                let _ = self.event_tx.send(PlayerEvent::PlaybackError(e.to_string())).await;
             }
-            
-            tokio::time::sleep(Duration::from_millis(50)).await;
         }
+    }
+    
+    /// 从PreloadActor取出曲目的预加载音频（同时计入命中/未命中统计）
+    async fn take_preloaded(&self, track: &Track) -> Option<PreloadedAudio> {
+        let preload = self.links.as_ref()?.preload.as_ref()?;
+        preload.take(track).await.unwrap_or_else(|e| {
+            log::warn!("⚠️ 获取预加载音频失败: {}", e);
+            None
+        })
+    }
+    
+    /// 当前曲目接近结尾（剩余不超过预加载提前量，短曲目开始播放即满足）时请求预加载下一首
+    fn should_request_preload(&self) -> bool {
+        let Some(links) = &self.links else { return false };
+        if links.preload.is_none() || self.current_sink.is_none() || self.play_start_time.is_none() {
+            return false;
+        }
+        let Some(track) = &self.current_track else { return false };
+        if self.preload_requested_for == Some(track.id) {
+            return false;
+        }
+        let Some(duration_ms) = track.duration_ms.filter(|d| *d > 0) else { return false };
+        let position_ms = self.get_current_position().unwrap_or(0);
+        (duration_ms as u64).saturating_sub(position_ms) <= self.preload_lead_ms
+    }
+    
+    /// 请PlaylistActor按播放顺序通知PreloadActor准备下一首
+    async fn request_preload(&mut self) {
+        let (Some(links), Some(track)) = (&self.links, &self.current_track) else { return };
+        self.preload_requested_for = Some(track.id);
+        log::debug!("🔄 请求预加载下一首（当前: {:?}）", track.title);
+        if let Err(e) = links.playlist.preload_upcoming().await {
+            log::warn!("⚠️ 请求预加载失败: {}", e);
+        }
+    }
+    
+    /// WEBDAV流式播放（真正的即点即播），有预加载的HTTP流时直接使用
+    async fn decode_streaming(
+        &mut self,
+        track_path: &str,
+        preloaded: Option<(SimpleHttpReader, String)>,
+    ) -> Result<Box<dyn rodio::Source<Item = i16> + Send>> {
+        use symphonia::core::io::MediaSourceStream;
+        use symphonia::core::probe::Hint;
+        use crate::player::audio::SymphoniaDecoder;
+        
+        log::info!("🌊 远程流式播放: {}", track_path);
+        log::debug!("🌊 [PlaybackActor] 远程流式播放（真正的流式解码）: {}", track_path);
+        
+        let (reader, http_url) = match preloaded {
+            Some(preloaded) => {
+                log::info!("⚡ 使用预加载的HTTP流（已缓冲{}KB）", preloaded.0.get_buffered_size() / 1024);
+                // 开始播放后按播放流的优先级继续下载
+                preloaded.0.set_traffic_class(TrafficClass::Playback);
+                preloaded
+            }
+            None => open_stream_reader(track_path, TrafficClass::Playback).await?,
+        };
+        
+        let monitor = reader.monitor();
         
         log::info!("🎵 使用SymphoniaDecoder进行真正的流式解码");
-        println!("🎵 [PlaybackActor] 使用SymphoniaDecoder（真正的流式，不等待metadata）...");
+        log::debug!("🎵 [PlaybackActor] 使用SymphoniaDecoder（真正的流式，不等待metadata）...");
         
         // 🔥 P0-4修复: 使用SymphoniaDecoder替代rodio::Decoder
         // Symphonia支持真正的流式播放，不需要预先读取完整metadata
@@ -658,7 +2447,6 @@ impl PlaybackActor {
             .map_err(|e| {
                 let err_msg = format!("格式探测失败: {}", e);
                 log::error!("❌ {}", err_msg);
-                println!("❌ [PlaybackActor] {}", err_msg);
                 PlayerError::decode_error(err_msg)
             })?;
         
@@ -671,7 +2459,6 @@ impl PlaybackActor {
             .ok_or_else(|| {
                 let err_msg = "没有找到有效音轨";
                 log::error!("❌ {}", err_msg);
-                println!("❌ [PlaybackActor] {}", err_msg);
                 PlayerError::decode_error(err_msg.to_string())
             })?;
         
@@ -685,7 +2472,6 @@ impl PlaybackActor {
             .map_err(|e| {
                 let err_msg = format!("创建解码器失败: {}", e);
                 log::error!("❌ {}", err_msg);
-                println!("❌ [PlaybackActor] {}", err_msg);
                 PlayerError::decode_error(err_msg)
             })?;
         
@@ -696,43 +2482,13 @@ impl PlaybackActor {
             track_id
         );
         
+        self.stream_seek = Some(symphonia_decoder.seek_handle());
+        self.stream_monitor = Some(monitor);
+        
         log::info!("✅ SymphoniaDecoder创建成功，真正的流式播放已启动");
-        println!("✅ [PlaybackActor] SymphoniaDecoder创建成功（真正的流式播放）！");
+        log::debug!("✅ [PlaybackActor] SymphoniaDecoder创建成功（真正的流式播放）！");
         Ok(Box::new(symphonia_decoder))
     }
-    
-    /// 解析WEBDAV路径为HTTP URL（包含完整配置）
-    fn parse_webdav_url_with_config(&self, track_path: &str) -> Result<(String, String, String, crate::webdav::types::HttpProtocolPreference)> {
-        // webdav://server_id#/path/to/file.flac
-        let path_without_prefix = track_path.strip_prefix("webdav://")
-            .ok_or_else(|| PlayerError::decode_error("无效的WEBDAV路径".to_string()))?;
-        
-        let (server_id, file_path) = path_without_prefix.split_once('#')
-            .ok_or_else(|| PlayerError::decode_error("WEBDAV路径格式错误".to_string()))?;
-        
-        // 从数据库获取服务器配置
-        let db = crate::DB.get()
-            .ok_or_else(|| PlayerError::decode_error("数据库未初始化".to_string()))?;
-        
-        let servers = db.lock().unwrap().get_remote_servers()
-            .map_err(|e| PlayerError::decode_error(format!("获取服务器列表失败: {}", e)))?;
-        
-        // 找到对应的服务器
-        let server_config = servers.iter()
-            .find(|(id, _, server_type, _, _)| id == server_id && server_type == "webdav")
-            .ok_or_else(|| PlayerError::decode_error(format!("找不到WEBDAV服务器: {}", server_id)))?;
-        
-        // 解析配置JSON为WebDAVConfig
-        use crate::webdav::types::WebDAVConfig;
-        let webdav_config: WebDAVConfig = serde_json::from_str(&server_config.3)
-            .map_err(|e| PlayerError::decode_error(format!("解析配置失败: {}", e)))?;
-        
-        // 使用WebDAVConfig的build_full_url方法
-        let url = webdav_config.build_full_url(file_path);
-        
-        // 返回URL、认证信息和HTTP协议偏好
-        Ok((url, webdav_config.username, webdav_config.password, webdav_config.http_protocol))
-    }
 }
 
 /// PlaybackActor的句柄
@@ -791,6 +2547,151 @@ impl PlaybackActorHandle {
             .map_err(|e| PlayerError::Internal(format!("接收跳转响应失败: {}", e)))?
     }
     
+    /// 设置A-B循环区间（None为清除），等待校验结果
+    pub async fn set_loop_region(&self, region: Option<(u64, u64)>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::SetLoopRegion { region, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置循环区间消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收设置循环区间响应失败: {}", e)))?
+    }
+    
+    /// 续播到保存的位置（不等待跳转完成）
+    pub async fn resume_at(&self, track_id: i64, position_ms: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::ResumeAt { track_id, position_ms })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送续播消息失败: {}", e)))
+    }
+    
+    /// 同步外部引擎的位置和播放状态
+    pub async fn sync_external_position(&self, position_ms: u64, is_playing: bool) -> Result<()> {
+        self.tx.send(PlaybackMsg::SyncExternalPosition { position_ms, is_playing })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送外部位置消息失败: {}", e)))
+    }
+    
+    /// 交还原生播放，等待开始播放
+    pub async fn handoff_to_native(&self, position_ms: u64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::HandoffToNative { position_ms, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送交接消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收交接响应失败: {}", e)))?
+    }
+    
+    /// 设置曲目的章节
+    pub async fn set_chapters(&self, track_id: i64, chapters: Vec<Chapter>) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetChapters { track_id, chapters })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送章节消息失败: {}", e)))
+    }
+    
+    /// 设置曲目的静音裁剪点
+    pub async fn set_trim_points(&self, track_id: i64, trim: Option<TrimPoints>) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetTrimPoints { track_id, trim })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送静音裁剪点消息失败: {}", e)))
+    }
+    
+    /// 跳到下一章节或上一章节
+    pub async fn seek_chapter(&self, forward: bool) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::SeekChapter { forward, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送章节跳转消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收章节跳转响应失败: {}", e)))?
+    }
+    
+    /// 按步长相对跳转
+    pub async fn seek_relative(&self, steps: i64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::SeekRelative { steps, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送相对跳转消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收相对跳转响应失败: {}", e)))?
+    }
+    
+    /// 设置暂停/恢复淡变时长
+    pub async fn set_pause_fade(&self, duration_ms: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetPauseFade(duration_ms))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置暂停淡变消息失败: {}", e)))
+    }
+    
+    /// 设置相对跳转步长
+    pub async fn set_seek_step(&self, step_ms: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetSeekStep(step_ms))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置跳转步长消息失败: {}", e)))
+    }
+    
+    /// 设置位置事件的上报间隔
+    pub async fn set_position_interval(&self, interval_ms: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetPositionInterval(interval_ms))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置位置上报间隔消息失败: {}", e)))
+    }
+    
+    /// 切换输出设备
+    pub async fn set_output_device(&self, device_name: Option<String>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::SetOutputDevice { device_name, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送切换输出设备消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收切换输出设备响应失败: {}", e)))?
+    }
+    
+    /// 设置输出格式
+    pub async fn set_output_config(&self, config: OutputConfig) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::SetOutputConfig { config, reply: tx })
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置输出格式消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收设置输出格式响应失败: {}", e)))?
+    }
+    
+    /// 获取实际协商得到的输出格式
+    pub async fn get_output_format(&self) -> Result<Option<OutputFormat>> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::GetOutputFormat(tx))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送获取输出格式消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收输出格式响应失败: {}", e)))
+    }
+    
+    /// 获取当前流的缓冲统计
+    pub async fn get_stream_stats(&self) -> Result<Option<StreamStats>> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::GetStreamStats(tx))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送获取流统计消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收流统计响应失败: {}", e)))
+    }
+    
     /// 设置音量
     pub async fn set_volume(&self, volume: f32) -> Result<()> {
         self.tx.send(PlaybackMsg::SetVolume(volume))
@@ -798,6 +2699,74 @@ impl PlaybackActorHandle {
             .map_err(|e| PlayerError::Internal(format!("发送设置音量消息失败: {}", e)))
     }
     
+    /// 设置音量曲线和前置放大
+    pub async fn set_volume_settings(&self, settings: VolumeSettings) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetVolumeSettings(settings))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置音量曲线消息失败: {}", e)))
+    }
+    
+    /// 设置播放速度
+    pub async fn set_playback_rate(&self, rate: f32) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetPlaybackRate(rate))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置播放速度消息失败: {}", e)))
+    }
+    
+    /// 设置变速时是否保持音高
+    pub async fn set_preserve_pitch(&self, preserve_pitch: bool) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetPreservePitch(preserve_pitch))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置音高保持消息失败: {}", e)))
+    }
+    
+    /// 设置无缝播放
+    pub async fn set_gapless(&self, enabled: bool) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetGapless(enabled))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置无缝播放消息失败: {}", e)))
+    }
+    
+    /// 设置交叉淡入淡出时长
+    pub async fn set_crossfade(&self, duration_ms: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetCrossfade(duration_ms))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置交叉淡入淡出消息失败: {}", e)))
+    }
+    
+    /// 设置睡眠定时器
+    pub async fn set_sleep_timer(&self, timer: Option<SleepTimer>) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetSleepTimer(timer))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置睡眠定时器消息失败: {}", e)))
+    }
+    
+    /// 设置睡眠定时淡出时长
+    pub async fn set_sleep_fade(&self, fade_secs: u64) -> Result<()> {
+        self.tx.send(PlaybackMsg::SetSleepFade(fade_secs))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置睡眠淡出消息失败: {}", e)))
+    }
+    
+    /// 获取睡眠定时器状态
+    pub async fn get_sleep_timer(&self) -> Result<Option<SleepTimerStatus>> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaybackMsg::GetSleepTimer(tx))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送获取睡眠定时器消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收睡眠定时器响应失败: {}", e)))
+    }
+    
+    /// 取消已追加的下一首
+    pub async fn cancel_gapless_next(&self) -> Result<()> {
+        self.tx.send(PlaybackMsg::CancelGaplessNext)
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送取消无缝下一首消息失败: {}", e)))
+    }
+    
     /// 获取位置
     pub async fn get_position(&self) -> Result<Option<u64>> {
         let (tx, rx) = oneshot::channel();
@@ -817,3 +2786,56 @@ impl PlaybackActorHandle {
             .map_err(|e| PlayerError::Internal(format!("发送关闭消息失败: {}", e)))
     }
 }
+
+/// 相对跳转的目标位置：限制在0到曲目时长之间（时长未知时只限制下界）
+fn relative_seek_target(position_ms: u64, duration_ms: Option<u64>, steps: i64, step_ms: u64) -> u64 {
+    let offset = steps.unsigned_abs().saturating_mul(step_ms);
+    let target = if steps >= 0 {
+        position_ms.saturating_add(offset)
+    } else {
+        position_ms.saturating_sub(offset)
+    };
+    match duration_ms {
+        Some(duration) => target.min(duration),
+        None => target,
+    }
+}
+
+/// 设备恢复的重试间隔：1秒起每次翻倍，最长30秒
+fn device_retry_delay(attempts: u32) -> Duration {
+    let secs = 1u64.checked_shl(attempts.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_secs(secs.min(MAX_DEVICE_RETRY_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_relative_seek_target_clamps_to_track() {
+        assert_eq!(relative_seek_target(30_000, Some(180_000), 1, 10_000), 40_000);
+        assert_eq!(relative_seek_target(30_000, Some(180_000), -2, 10_000), 10_000);
+        assert_eq!(relative_seek_target(5_000, Some(180_000), -1, 10_000), 0);
+        assert_eq!(relative_seek_target(175_000, Some(180_000), 1, 10_000), 180_000);
+        assert_eq!(relative_seek_target(175_000, None, 1, 10_000), 185_000);
+    }
+    
+    #[test]
+    fn test_device_retry_delay_backs_off() {
+        assert_eq!(device_retry_delay(1), Duration::from_secs(1));
+        assert_eq!(device_retry_delay(2), Duration::from_secs(2));
+        assert_eq!(device_retry_delay(4), Duration::from_secs(8));
+        assert_eq!(device_retry_delay(10), Duration::from_secs(MAX_DEVICE_RETRY_SECS));
+        assert_eq!(device_retry_delay(100), Duration::from_secs(MAX_DEVICE_RETRY_SECS));
+    }
+    
+    #[test]
+    fn test_pause_ramp_gain() {
+        let ramp = PauseRamp { fade: Fade::start_at(Instant::now() - Duration::from_secs(1), 200), from: 0.6, pausing: true };
+        assert_eq!(ramp.gain(), 0.0);
+        let ramp = PauseRamp { fade: Fade::start_at(Instant::now() - Duration::from_secs(1), 200), from: 0.6, pausing: false };
+        assert_eq!(ramp.gain(), 1.0);
+        let ramp = PauseRamp::start(0.6, true, 10_000);
+        assert!(ramp.gain() <= 0.6 && ramp.gain() > 0.5);
+    }
+}
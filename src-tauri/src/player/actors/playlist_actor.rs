@@ -2,14 +2,205 @@
 //
 // 职责：
 // - 播放列表管理
-// - 随机播放
+// - 随机播放（Fisher-Yates 排列，每轮每首恰好播放一次，上一曲沿随机历史回退；可跳过校验未通过的曲目）
 // - 循环模式控制
 // - 智能预加载（可选）
 
 use tokio::sync::{mpsc, oneshot};
 use std::collections::VecDeque;
 use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use super::super::types::{Track, PlayerError, PlayerEvent, RepeatMode, Result};
+use super::preload_actor::PreloadActorHandle;
+
+/// 随机顺序中保留的上一轮历史条数（供上一曲跨轮回退）
+const MAX_SHUFFLE_HISTORY: usize = 50;
+
+/// 随机播放顺序
+///
+/// order 中存放 original_playlist 的下标：order[..cycle_start] 是上一轮的播放历史，
+/// order[cycle_start..] 是本轮的排列，position 指向当前曲目。
+/// 下一曲沿 order 前进、上一曲后退，本轮播完（且列表循环）时才生成新排列。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ShuffleOrder {
+    order: Vec<usize>,
+    position: usize,
+    cycle_start: usize,
+    /// 当前条目是否已真正开始播放（刚加载列表时为 false，跳转会直接以目标曲目重建排列）
+    started: bool,
+    /// 不参与随机排列的下标（升序，校验未通过的曲目）；手动跳转仍可播放
+    skip: Vec<usize>,
+}
+
+impl ShuffleOrder {
+    /// 生成新排列，current 固定在首位，skip 中的曲目不排入
+    fn new(len: usize, current: usize, skip: Vec<usize>) -> Self {
+        let mut rest: Vec<usize> = Self::candidates(len, &skip)
+            .into_iter()
+            .filter(|&i| i != current)
+            .collect();
+        rest.shuffle(&mut rand::thread_rng());
+
+        let mut order = Vec::with_capacity(len);
+        if current < len {
+            order.push(current);
+        }
+        order.extend(rest);
+        Self { order, position: 0, cycle_start: 0, started: true, skip }
+    }
+
+    /// 可排入随机顺序的下标；全部被跳过时退回整个列表
+    fn candidates(len: usize, skip: &[usize]) -> Vec<usize> {
+        let candidates: Vec<usize> = (0..len).filter(|i| skip.binary_search(i).is_err()).collect();
+        if candidates.is_empty() {
+            (0..len).collect()
+        } else {
+            candidates
+        }
+    }
+
+    /// 更新跳过列表，本轮尚未播放的被跳过曲目从排列中移除
+    fn set_skip(&mut self, skip: Vec<usize>) {
+        let upcoming = (self.position + 1).min(self.order.len());
+        let mut kept: Vec<usize> = self.order[upcoming..]
+            .iter()
+            .copied()
+            .filter(|i| skip.binary_search(i).is_err())
+            .collect();
+        // 剩余曲目全部被跳过时保持原样，由下一轮按退回规则处理
+        if kept.is_empty() {
+            kept = self.order[upcoming..].to_vec();
+        }
+        self.order.truncate(upcoming);
+        self.order.extend(kept);
+        self.skip = skip;
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+
+    /// 下一首（不移动位置）；本轮已播完时按需生成下一轮
+    fn peek_next(&mut self, len: usize, repeat_all: bool) -> Option<usize> {
+        if self.position + 1 >= self.order.len() {
+            if !repeat_all || len == 0 {
+                return None;
+            }
+            self.start_new_cycle(len);
+        }
+        self.order.get(self.position + 1).copied()
+    }
+
+    fn advance(&mut self, len: usize, repeat_all: bool) -> Option<usize> {
+        let next = self.peek_next(len, repeat_all)?;
+        self.position += 1;
+        self.started = true;
+        Some(next)
+    }
+
+    /// 沿随机历史后退一首
+    fn back(&mut self) -> Option<usize> {
+        if self.position == 0 {
+            return None;
+        }
+        self.position -= 1;
+        self.current()
+    }
+
+    /// 跳转到指定曲目：本轮未播放的从剩余排列中移到当前位置之后，已播放的作为额外条目插入
+    fn jump_to(&mut self, len: usize, index: usize) {
+        if !self.started {
+            *self = Self::new(len, index, std::mem::take(&mut self.skip));
+            return;
+        }
+        if self.current() == Some(index) {
+            return;
+        }
+
+        let search_from = (self.position + 1).max(self.cycle_start).min(self.order.len());
+        if let Some(offset) = self.order[search_from..].iter().position(|&i| i == index) {
+            self.order.remove(search_from + offset);
+        }
+        self.order.insert(self.position + 1, index);
+        // 插入到历史区时本轮起点后移
+        if self.position + 1 < self.cycle_start {
+            self.cycle_start += 1;
+        }
+        self.position += 1;
+    }
+
+    /// 本轮结束：保留有限历史，追加新一轮排列，且新一轮的第一首不与刚播放的曲目重复
+    fn start_new_cycle(&mut self, len: usize) {
+        let last = self.current();
+        let mut rng = rand::thread_rng();
+        let mut cycle = Self::candidates(len, &self.skip);
+        cycle.shuffle(&mut rng);
+        if cycle.len() > 1 && cycle.first().copied() == last {
+            let swap_with = rng.gen_range(1..cycle.len());
+            cycle.swap(0, swap_with);
+        }
+
+        let trimmed = self.order.len().saturating_sub(MAX_SHUFFLE_HISTORY);
+        self.order.drain(..trimmed);
+        self.position = self.position.saturating_sub(trimmed);
+        self.cycle_start = self.order.len();
+        self.order.extend(cycle);
+
+        log::debug!("🔀 本轮随机播放结束，已生成新的随机顺序");
+    }
+
+    /// 导出为可持久化的状态（下标转为曲目ID）
+    fn to_state(&self, playlist: &[Track]) -> ShuffleState {
+        ShuffleState {
+            order: self.order.iter().filter_map(|&i| playlist.get(i).map(|t| t.id)).collect(),
+            position: self.position,
+            cycle_start: self.cycle_start,
+        }
+    }
+
+    /// 从持久化状态恢复：丢弃已不在列表中的曲目，本轮缺失的曲目随机追加到末尾
+    fn from_state(state: &ShuffleState, playlist: &[Track]) -> Option<Self> {
+        let index_of = |id: i64| playlist.iter().position(|t| t.id == id);
+
+        let mut restored = Self { started: true, ..Self::default() };
+        for (i, id) in state.order.iter().enumerate() {
+            let Some(index) = index_of(*id) else { continue };
+            restored.order.push(index);
+            // 位置按保留下来的条目重新计数
+            if i < state.position {
+                restored.position += 1;
+            }
+            if i < state.cycle_start {
+                restored.cycle_start += 1;
+            }
+        }
+
+        if restored.order.is_empty() {
+            return None;
+        }
+        restored.position = restored.position.min(restored.order.len() - 1);
+        restored.cycle_start = restored.cycle_start.min(restored.order.len());
+
+        let mut missing: Vec<usize> = (0..playlist.len())
+            .filter(|i| !restored.order[restored.cycle_start..].contains(i))
+            .collect();
+        missing.shuffle(&mut rand::thread_rng());
+        restored.order.extend(missing);
+        Some(restored)
+    }
+}
+
+/// 可持久化的随机播放状态（随播放会话保存，恢复同一列表时继续原来的随机顺序）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShuffleState {
+    /// 随机顺序（曲目ID），含上一轮的部分历史
+    pub order: Vec<i64>,
+    /// 当前曲目在 order 中的位置
+    pub position: usize,
+    /// 本轮排列在 order 中的起点
+    pub cycle_start: usize,
+}
 
 /// 播放列表Actor消息
 #[derive(Debug)]
@@ -23,6 +214,9 @@ pub enum PlaylistMsg {
     /// 获取下一曲
     GetNext(oneshot::Sender<Option<Track>>),
     
+    /// 预览下一曲（不移动当前索引，无缝播放预备使用）
+    PeekNext(oneshot::Sender<Option<Track>>),
+    
     /// 获取上一曲
     GetPrevious(oneshot::Sender<Option<Track>>),
     
@@ -35,6 +229,9 @@ pub enum PlaylistMsg {
     /// 设置随机播放
     SetShuffle(bool),
     
+    /// 随机播放时是否跳过校验未通过（截断、无法解码）的曲目
+    SetShuffleSkipBroken(bool),
+    
     /// 设置重复模式
     SetRepeatMode(RepeatMode),
     
@@ -44,6 +241,15 @@ pub enum PlaylistMsg {
     /// 获取当前索引
     GetCurrentIndex(oneshot::Sender<Option<usize>>),
     
+    /// 获取随机播放状态（未开启随机时为None）
+    GetShuffleState(oneshot::Sender<Option<ShuffleState>>),
+    
+    /// 恢复随机播放状态（需先加载同一播放列表）
+    RestoreShuffleState(ShuffleState),
+    
+    /// 让PreloadActor准备下一曲（当前曲目即将结束时由PlaybackActor发送）
+    PreloadUpcoming,
+    
     /// 关闭Actor
     Shutdown,
 }
@@ -56,8 +262,8 @@ pub struct PlaylistActor {
     /// 原始播放列表（按加载顺序）
     original_playlist: Vec<Track>,
     
-    /// 当前播放队列（可能是随机后的）
-    current_queue: VecDeque<Track>,
+    /// 随机播放顺序（仅随机模式使用）
+    shuffle_order: ShuffleOrder,
     
     /// 当前播放索引
     current_index: Option<usize>,
@@ -65,10 +271,13 @@ pub struct PlaylistActor {
     /// 是否随机播放
     shuffle: bool,
     
+    /// 随机播放时跳过校验未通过的曲目
+    shuffle_skip_broken: bool,
+    
     /// 重复模式
     repeat_mode: RepeatMode,
     
-    /// 播放历史（用于上一曲）
+    /// 播放历史（顺序模式的上一曲；随机模式沿 shuffle_order 回退）
     history: VecDeque<Track>,
     
     /// 历史记录最大长度
@@ -77,6 +286,12 @@ pub struct PlaylistActor {
     /// 事件发送器
     #[allow(dead_code)]
     event_tx: mpsc::Sender<PlayerEvent>,
+    
+    /// 预加载Actor（未启用预加载时为None）
+    preload: Option<PreloadActorHandle>,
+    
+    /// 当前曲目是否已请求预加载（播放顺序变化时需要重新请求）
+    preload_requested: bool,
 }
 
 impl PlaylistActor {
@@ -87,18 +302,27 @@ impl PlaylistActor {
         let actor = Self {
             inbox: rx,
             original_playlist: Vec::new(),
-            current_queue: VecDeque::new(),
+            shuffle_order: ShuffleOrder::default(),
             current_index: None,
             shuffle: false,
+            shuffle_skip_broken: false,
             repeat_mode: RepeatMode::Off,
             history: VecDeque::new(),
             max_history: 50,
             event_tx,
+            preload: None,
+            preload_requested: false,
         };
         
         (actor, tx)
     }
     
+    /// 连接PreloadActor
+    pub fn with_preload(mut self, preload: Option<PreloadActorHandle>) -> Self {
+        self.preload = preload;
+        self
+    }
+    
     /// 运行Actor事件循环
     pub async fn run(mut self) {
         log::info!("📋 PlaylistActor 启动");
@@ -114,22 +338,35 @@ impl PlaylistActor {
                             let _ = reply.send(result);
                         }
                         PlaylistMsg::GetNext(reply) => {
+                            self.preload_requested = false;
                             let track = self.handle_get_next();
                             let _ = reply.send(track);
                         }
+                        PlaylistMsg::PeekNext(reply) => {
+                            let track = self.handle_peek_next();
+                            let _ = reply.send(track);
+                        }
                         PlaylistMsg::GetPrevious(reply) => {
+                            self.preload_requested = false;
                             let track = self.handle_get_previous();
                             let _ = reply.send(track);
                         }
                         PlaylistMsg::JumpTo { track_id, reply } => {
+                            self.preload_requested = false;
                             let result = self.handle_jump_to(track_id);
                             let _ = reply.send(result);
                         }
                         PlaylistMsg::SetShuffle(enabled) => {
                             self.handle_set_shuffle(enabled).await;
+                            self.refresh_preload().await;
+                        }
+                        PlaylistMsg::SetShuffleSkipBroken(enabled) => {
+                            self.handle_set_shuffle_skip_broken(enabled);
+                            self.refresh_preload().await;
                         }
                         PlaylistMsg::SetRepeatMode(mode) => {
                             self.handle_set_repeat_mode(mode).await;
+                            self.refresh_preload().await;
                         }
                         PlaylistMsg::GetPlaylist(reply) => {
                             let _ = reply.send(self.original_playlist.clone());
@@ -137,6 +374,18 @@ impl PlaylistActor {
                         PlaylistMsg::GetCurrentIndex(reply) => {
                             let _ = reply.send(self.current_index);
                         }
+                        PlaylistMsg::GetShuffleState(reply) => {
+                            let state = self.shuffle
+                                .then(|| self.shuffle_order.to_state(&self.original_playlist));
+                            let _ = reply.send(state);
+                        }
+                        PlaylistMsg::RestoreShuffleState(state) => {
+                            self.handle_restore_shuffle_state(state);
+                        }
+                        PlaylistMsg::PreloadUpcoming => {
+                            self.preload_requested = true;
+                            self.handle_preload_upcoming().await;
+                        }
                         PlaylistMsg::Shutdown => {
                             log::info!("📋 PlaylistActor 收到关闭信号");
                             break;
@@ -154,6 +403,7 @@ impl PlaylistActor {
     }
     
     /// 处理加载播放列表
+    #[tracing::instrument(level = "debug", skip_all, fields(tracks = tracks.len()))]
     async fn handle_load_playlist(&mut self, tracks: Vec<Track>) -> Result<()> {
         if tracks.is_empty() {
             return Err(PlayerError::EmptyPlaylist);
@@ -164,9 +414,19 @@ impl PlaylistActor {
         self.original_playlist = tracks;
         self.current_index = Some(0);
         self.history.clear();
+        self.preload_requested = false;
         
-        // 重建播放队列
+        // 淘汰已不在新列表中的预加载曲目（同一列表重新加载时保留）
+        if let Some(preload) = &self.preload {
+            let paths = self.original_playlist.iter().map(|t| t.path.clone()).collect();
+            if let Err(e) = preload.retain_paths(paths).await {
+                log::warn!("通知PreloadActor播放列表变化失败: {}", e);
+            }
+        }
+        
+        // 列表变化：重新生成随机顺序（首次跳转时以目标曲目为起点）
         self.rebuild_queue();
+        self.shuffle_order.started = false;
         
         Ok(())
     }
@@ -184,6 +444,14 @@ impl PlaylistActor {
             }
         }
         
+        // 随机模式：沿随机顺序前进（历史即随机顺序本身）
+        if self.shuffle {
+            let repeat_all = self.repeat_mode == RepeatMode::All;
+            let index = self.shuffle_order.advance(self.original_playlist.len(), repeat_all)?;
+            self.current_index = Some(index);
+            return self.original_playlist.get(index).cloned();
+        }
+        
         // 🔥 先保存当前曲目到历史（在切换之前）
         if let Some(current_idx) = self.current_index {
             if let Some(current_track) = self.original_playlist.get(current_idx).cloned() {
@@ -192,18 +460,6 @@ impl PlaylistActor {
             }
         }
         
-        // 随机模式
-        if self.shuffle {
-            let track = self.get_next_shuffle();
-            // 更新 current_index 到该曲目在原始列表中的位置
-            if let Some(t) = &track {
-                if let Some(index) = self.original_playlist.iter().position(|item| item.id == t.id) {
-                    self.current_index = Some(index);
-                }
-            }
-            return track;
-        }
-        
         // 顺序播放
         let next_index = match self.current_index {
             Some(idx) => {
@@ -225,8 +481,50 @@ impl PlaylistActor {
         self.original_playlist.get(next_index).cloned()
     }
     
+    /// 处理预览下一曲
+    ///
+    /// 与 handle_get_next 的选曲规则一致，但不修改索引和历史，
+    /// 保证随后调用 GetNext 时得到同一首曲目
+    fn handle_peek_next(&mut self) -> Option<Track> {
+        if self.original_playlist.is_empty() {
+            return None;
+        }
+        
+        // 单曲循环：下一首就是当前曲目
+        if self.repeat_mode == RepeatMode::One {
+            if let Some(idx) = self.current_index {
+                return self.original_playlist.get(idx).cloned();
+            }
+        }
+        
+        // 随机模式：本轮结束且列表循环时提前生成下一轮，GetNext会前进到同一首
+        if self.shuffle {
+            let repeat_all = self.repeat_mode == RepeatMode::All;
+            let index = self.shuffle_order.peek_next(self.original_playlist.len(), repeat_all)?;
+            return self.original_playlist.get(index).cloned();
+        }
+        
+        // 顺序播放
+        let next_index = match self.current_index {
+            Some(idx) if idx + 1 >= self.original_playlist.len() => match self.repeat_mode {
+                RepeatMode::All => 0,
+                _ => return None,
+            },
+            Some(idx) => idx + 1,
+            None => 0,
+        };
+        self.original_playlist.get(next_index).cloned()
+    }
+    
     /// 处理获取上一曲
     fn handle_get_previous(&mut self) -> Option<Track> {
+        // 随机模式：沿随机顺序回退，回到起点后不再后退
+        if self.shuffle {
+            let index = self.shuffle_order.back()?;
+            self.current_index = Some(index);
+            return self.original_playlist.get(index).cloned();
+        }
+        
         // 从历史记录中获取
         if let Some(track) = self.history.pop_back() {
             log::debug!("⏮️ 从历史获取上一曲: {}", track.title.as_deref().unwrap_or("未知"));
@@ -282,6 +580,9 @@ impl PlaylistActor {
             })?;
         
         self.current_index = Some(position);
+        if self.shuffle {
+            self.shuffle_order.jump_to(self.original_playlist.len(), position);
+        }
         let track = self.original_playlist[position].clone();
         
         log::debug!("✅ 跳转成功: {:?} (position={})", track.title, position);
@@ -293,58 +594,108 @@ impl PlaylistActor {
     async fn handle_set_shuffle(&mut self, enabled: bool) {
         log::info!("🔀 设置随机播放: {}", enabled);
         
+        if enabled == self.shuffle {
+            return;
+        }
+        
+        if !enabled {
+            // 随机期间播放过的曲目转入顺序模式的历史，上一曲仍可回退
+            let played: Vec<Track> = self.shuffle_order.order[..self.shuffle_order.position]
+                .iter()
+                .filter_map(|&i| self.original_playlist.get(i).cloned())
+                .collect();
+            for track in played {
+                self.add_to_history(track);
+            }
+        }
+        
         self.shuffle = enabled;
         
         if enabled {
+            // 当前曲目保持不动，其余曲目打乱排在其后
             self.rebuild_queue();
         }
     }
     
+    /// 恢复持久化的随机顺序
+    fn handle_restore_shuffle_state(&mut self, state: ShuffleState) {
+        match ShuffleOrder::from_state(&state, &self.original_playlist) {
+            Some(mut order) => {
+                order.set_skip(self.shuffle_skip());
+                self.current_index = order.current();
+                self.shuffle_order = order;
+                self.shuffle = true;
+                log::info!("🔀 已恢复随机播放顺序（{} 项）", self.shuffle_order.order.len());
+            }
+            None => log::warn!("🔀 随机播放状态与当前播放列表不匹配，忽略"),
+        }
+    }
+    
+    /// 设置随机播放是否跳过校验未通过的曲目（从下一首起生效）
+    fn handle_set_shuffle_skip_broken(&mut self, enabled: bool) {
+        log::info!("🔀 随机播放跳过损坏曲目: {}", enabled);
+        self.shuffle_skip_broken = enabled;
+        if self.shuffle {
+            self.shuffle_order.set_skip(self.shuffle_skip());
+        }
+    }
+    
+    /// 随机播放要跳过的曲目下标
+    fn shuffle_skip(&self) -> Vec<usize> {
+        if !self.shuffle_skip_broken {
+            return Vec::new();
+        }
+        self.original_playlist
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.verify_status.is_some_and(|s| s.is_broken()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    
     /// 处理设置重复模式
     async fn handle_set_repeat_mode(&mut self, mode: RepeatMode) {
         log::info!("🔁 设置重复模式: {:?}", mode);
         self.repeat_mode = mode;
     }
     
-    /// 重建播放队列
-    fn rebuild_queue(&mut self) {
-        self.current_queue.clear();
+    /// 把下一曲交给PreloadActor准备
+    async fn handle_preload_upcoming(&mut self) {
+        let Some(preload) = self.preload.clone() else {
+            return;
+        };
         
-        if self.shuffle {
-            // 随机打乱
-            let mut rng = rand::thread_rng();
-            let mut shuffled = self.original_playlist.clone();
-            shuffled.shuffle(&mut rng);
-            self.current_queue = shuffled.into();
-            
-            log::debug!("🔀 播放列表已随机打乱");
-        } else {
-            self.current_queue = self.original_playlist.iter().cloned().collect();
+        let current_id = self.current_index
+            .and_then(|idx| self.original_playlist.get(idx))
+            .map(|t| t.id);
+        // 单曲循环时下一曲就是当前曲目，无需预加载
+        let upcoming: Vec<Track> = self.handle_peek_next()
+            .filter(|track| Some(track.id) != current_id)
+            .into_iter()
+            .collect();
+        
+        if let Err(e) = preload.preload_upcoming(upcoming).await {
+            log::warn!("发送预加载请求失败: {}", e);
         }
     }
     
-    /// 获取下一首（随机模式）
-    fn get_next_shuffle(&mut self) -> Option<Track> {
-        // 如果队列为空，重建队列
-        if self.current_queue.is_empty() {
-            match self.repeat_mode {
-                RepeatMode::All => {
-                    self.rebuild_queue();
-                }
-                RepeatMode::One => {
-                    // 单曲循环，返回当前曲目
-                    if let Some(idx) = self.current_index {
-                        return self.original_playlist.get(idx).cloned();
-                    }
-                }
-                RepeatMode::Off => {
-                    return None;
-                }
-            }
+    /// 播放顺序变化后，已请求过预加载的重新按新的下一曲请求
+    async fn refresh_preload(&mut self) {
+        if self.preload_requested {
+            self.handle_preload_upcoming().await;
+        }
+    }
+    
+    /// 重建随机顺序（当前曲目固定在首位）
+    fn rebuild_queue(&mut self) {
+        if !self.shuffle || self.original_playlist.is_empty() {
+            self.shuffle_order = ShuffleOrder::default();
+            return;
         }
         
-        // 🔥 从队列中弹出下一首（历史记录已在 handle_get_next 中处理）
-        self.current_queue.pop_front()
+        let current = self.current_index.unwrap_or(0);
+        self.shuffle_order = ShuffleOrder::new(self.original_playlist.len(), current, self.shuffle_skip());
+        log::debug!("🔀 播放列表已随机打乱");
     }
     
     /// 添加到历史记录
@@ -394,6 +745,18 @@ impl PlaylistActorHandle {
             .map_err(|e| PlayerError::Internal(format!("接收下一曲响应失败: {}", e)))
     }
     
+    /// 预览下一曲（不移动当前索引）
+    pub async fn peek_next(&self) -> Result<Option<Track>> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaylistMsg::PeekNext(tx))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送预览下一曲消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收预览下一曲响应失败: {}", e)))
+    }
+    
     /// 获取上一曲
     pub async fn get_previous(&self) -> Result<Option<Track>> {
         let (tx, rx) = oneshot::channel();
@@ -425,6 +788,13 @@ impl PlaylistActorHandle {
             .map_err(|e| PlayerError::Internal(format!("发送设置随机消息失败: {}", e)))
     }
     
+    /// 设置随机播放是否跳过校验未通过的曲目
+    pub async fn set_shuffle_skip_broken(&self, enabled: bool) -> Result<()> {
+        self.tx.send(PlaylistMsg::SetShuffleSkipBroken(enabled))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送设置随机跳过消息失败: {}", e)))
+    }
+    
     /// 设置重复模式
     pub async fn set_repeat_mode(&self, mode: RepeatMode) -> Result<()> {
         self.tx.send(PlaylistMsg::SetRepeatMode(mode))
@@ -456,6 +826,34 @@ impl PlaylistActorHandle {
             .map_err(|e| PlayerError::Internal(format!("接收索引响应失败: {}", e)))
     }
     
+    /// 获取随机播放状态（用于保存播放会话）
+    #[allow(dead_code)]
+    pub async fn get_shuffle_state(&self) -> Result<Option<ShuffleState>> {
+        let (tx, rx) = oneshot::channel();
+        
+        self.tx.send(PlaylistMsg::GetShuffleState(tx))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送获取随机状态消息失败: {}", e)))?;
+        
+        rx.await
+            .map_err(|e| PlayerError::Internal(format!("接收随机状态响应失败: {}", e)))
+    }
+    
+    /// 恢复随机播放状态（用于恢复播放会话，需先加载同一播放列表）
+    #[allow(dead_code)]
+    pub async fn restore_shuffle_state(&self, state: ShuffleState) -> Result<()> {
+        self.tx.send(PlaylistMsg::RestoreShuffleState(state))
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送恢复随机状态消息失败: {}", e)))
+    }
+    
+    /// 预加载下一曲（不等待加载完成）
+    pub async fn preload_upcoming(&self) -> Result<()> {
+        self.tx.send(PlaylistMsg::PreloadUpcoming)
+            .await
+            .map_err(|e| PlayerError::Internal(format!("发送预加载消息失败: {}", e)))
+    }
+    
     /// 关闭
     pub async fn shutdown(&self) -> Result<()> {
         self.tx.send(PlaylistMsg::Shutdown)
@@ -463,3 +861,176 @@ impl PlaylistActorHandle {
             .map_err(|e| PlayerError::Internal(format!("发送关闭消息失败: {}", e)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actor_with_tracks(count: i64) -> PlaylistActor {
+        let (event_tx, _event_rx) = mpsc::channel(8);
+        let (mut actor, _tx) = PlaylistActor::new(event_tx);
+        actor.original_playlist = (1..=count)
+            .map(|id| Track::new(id, format!("/music/{}.flac", id)))
+            .collect();
+        actor.current_index = Some(0);
+        actor.rebuild_queue();
+        actor
+    }
+
+    #[test]
+    fn test_peek_next_matches_get_next() {
+        let mut actor = actor_with_tracks(3);
+        let peeked = actor.handle_peek_next().map(|t| t.id);
+        assert_eq!(peeked, Some(2));
+        assert_eq!(actor.current_index, Some(0));
+        assert_eq!(actor.handle_get_next().map(|t| t.id), peeked);
+
+        // 随机模式下预览的也是GetNext将弹出的曲目
+        actor.shuffle = true;
+        actor.rebuild_queue();
+        let peeked = actor.handle_peek_next().map(|t| t.id);
+        assert_eq!(actor.handle_get_next().map(|t| t.id), peeked);
+    }
+
+    #[test]
+    fn test_peek_next_edge_cases() {
+        let mut actor = actor_with_tracks(3);
+
+        // 单曲循环：下一首是当前曲目
+        actor.repeat_mode = RepeatMode::One;
+        assert_eq!(actor.handle_peek_next().map(|t| t.id), Some(1));
+
+        // 最后一首且不循环：没有下一首
+        actor.repeat_mode = RepeatMode::Off;
+        actor.current_index = Some(2);
+        assert!(actor.handle_peek_next().is_none());
+
+        // 列表循环：回到第一首
+        actor.repeat_mode = RepeatMode::All;
+        assert_eq!(actor.handle_peek_next().map(|t| t.id), Some(1));
+    }
+
+    fn next_ids(actor: &mut PlaylistActor, count: usize) -> Vec<i64> {
+        (0..count).filter_map(|_| actor.handle_get_next().map(|t| t.id)).collect()
+    }
+
+    fn shuffled_actor(count: i64, current_index: usize) -> PlaylistActor {
+        let mut actor = actor_with_tracks(count);
+        actor.current_index = Some(current_index);
+        actor.shuffle = true;
+        actor.rebuild_queue();
+        actor
+    }
+
+    #[test]
+    fn test_shuffle_plays_every_track_once_per_cycle() {
+        let mut actor = shuffled_actor(20, 4);
+        // 开启随机时当前曲目保持不动
+        assert_eq!(actor.current_index, Some(4));
+
+        let mut first_cycle = next_ids(&mut actor, 19);
+        first_cycle.push(5);
+        first_cycle.sort();
+        assert_eq!(first_cycle, (1..=20).collect::<Vec<_>>());
+        // 不循环：一轮结束后没有下一首
+        assert!(actor.handle_get_next().is_none());
+
+        actor.repeat_mode = RepeatMode::All;
+        for _ in 0..5 {
+            let last = actor.original_playlist[actor.current_index.unwrap()].id;
+            let mut cycle = next_ids(&mut actor, 20);
+            assert_ne!(cycle[0], last, "新一轮第一首不应与上一首重复");
+            cycle.sort();
+            assert_eq!(cycle, (1..=20).collect::<Vec<_>>());
+        }
+        // 历史长度受限
+        assert!(actor.shuffle_order.order.len() <= MAX_SHUFFLE_HISTORY + 20);
+    }
+
+    #[test]
+    fn test_shuffle_previous_walks_back_through_history() {
+        let mut actor = shuffled_actor(10, 0);
+        let played = next_ids(&mut actor, 5);
+
+        let back: Vec<i64> = (0..5).filter_map(|_| actor.handle_get_previous().map(|t| t.id)).collect();
+        assert_eq!(back, vec![played[3], played[2], played[1], played[0], 1]);
+        // 回到起点后不再后退
+        assert!(actor.handle_get_previous().is_none());
+
+        // 再次前进得到同样的顺序
+        assert_eq!(next_ids(&mut actor, 5), played);
+    }
+
+    #[test]
+    fn test_shuffle_jump_keeps_cycle_complete() {
+        let mut actor = shuffled_actor(8, 0);
+        let upcoming = actor.shuffle_order.order[5];
+        let jumped = actor.handle_jump_to(upcoming as i64 + 1).unwrap();
+        assert_eq!(jumped.id, upcoming as i64 + 1);
+
+        let mut cycle = vec![1, jumped.id];
+        cycle.extend(next_ids(&mut actor, 6));
+        cycle.sort();
+        assert_eq!(cycle, (1..=8).collect::<Vec<_>>());
+        assert!(actor.handle_get_next().is_none());
+
+        // 刚加载的列表：第一次跳转直接以目标曲目为本轮起点
+        actor.shuffle_order.started = false;
+        actor.handle_jump_to(3).unwrap();
+        assert_eq!(actor.shuffle_order.order[0], 2);
+        assert_eq!(actor.shuffle_order.position, 0);
+    }
+
+    #[test]
+    fn test_shuffle_state_round_trip() {
+        let mut actor = shuffled_actor(6, 2);
+        next_ids(&mut actor, 3);
+        let state = actor.shuffle_order.to_state(&actor.original_playlist);
+        assert_eq!(state.position, 3);
+
+        let restored = ShuffleOrder::from_state(&state, &actor.original_playlist).unwrap();
+        assert_eq!(restored, actor.shuffle_order);
+
+        // 恢复时列表中已删除的曲目被丢弃，位置按剩余条目重新计算
+        let removed = state.order[1];
+        let playlist: Vec<Track> = actor.original_playlist.iter()
+            .filter(|t| t.id != removed)
+            .cloned()
+            .collect();
+        let restored = ShuffleOrder::from_state(&state, &playlist).unwrap();
+        assert_eq!(restored.position, 2);
+        assert_eq!(restored.order.len(), 5);
+        assert_eq!(playlist[restored.current().unwrap()].id, state.order[3]);
+
+        // 顺序模式下没有随机状态可恢复
+        assert!(ShuffleOrder::from_state(&ShuffleState::default(), &playlist).is_none());
+    }
+
+    #[test]
+    fn test_shuffle_skips_broken_tracks() {
+        use crate::player::audio::verify::VerifyStatus;
+
+        let mut actor = shuffled_actor(10, 0);
+        for index in [3, 6] {
+            actor.original_playlist[index].verify_status = Some(VerifyStatus::Truncated);
+        }
+        actor.original_playlist[5].verify_status = Some(VerifyStatus::Ok);
+        actor.repeat_mode = RepeatMode::All;
+
+        // 开启后本轮剩余和之后各轮都不再排入损坏的曲目
+        actor.handle_set_shuffle_skip_broken(true);
+        let played = next_ids(&mut actor, 30);
+        assert_eq!(played.len(), 30);
+        assert!(!played.contains(&4) && !played.contains(&7));
+
+        // 手动跳转仍可播放
+        assert_eq!(actor.handle_jump_to(4).unwrap().id, 4);
+
+        // 全部损坏时退回整个列表
+        for track in &mut actor.original_playlist {
+            track.verify_status = Some(VerifyStatus::DecodeError);
+        }
+        actor.rebuild_queue();
+        assert_eq!(next_ids(&mut actor, 9).len(), 9);
+    }
+}
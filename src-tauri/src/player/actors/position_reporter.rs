@@ -0,0 +1,361 @@
+// 播放位置上报
+//
+// PlaybackActor 的 TrackChanged 和 PositionChanged 都经过这里发出：
+// - 只上报已发出 TrackChanged 的曲目的位置，快速切歌时前端不会先收到下一首的位置
+// - 同一曲目内位置单调递增（暂停时记录的位置可能略小于上一次推算值），跳转后从新位置重新开始
+// - 按设置的间隔节流，跳转完成等需要立即刷新的位置不受节流限制
+// - 前端的外部引擎（Web Audio）接管播放期间不上报，位置以前端同步的为准；交还原生播放后从交接位置重新开始
+
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use super::super::types::{PlayerEvent, PositionUpdate, Track};
+
+/// 默认上报间隔(ms)
+pub const DEFAULT_POSITION_INTERVAL_MS: u64 = 100;
+
+/// 上报间隔范围(ms)；位置定时器每100ms检查一次，更短的间隔没有意义
+pub const MIN_POSITION_INTERVAL_MS: u64 = 100;
+pub const MAX_POSITION_INTERVAL_MS: u64 = 2000;
+
+/// 定时器抖动容差，避免间隔等于定时器周期时隔一次才发一次
+const THROTTLE_SLACK: Duration = Duration::from_millis(20);
+
+pub(super) struct PositionReporter {
+    interval: Duration,
+    /// 最近一次发出 TrackChanged 的曲目
+    announced: Option<i64>,
+    /// 最近上报的位置（曲目ID, 位置ms），跳转或切歌后清空
+    last: Option<(i64, u64)>,
+    last_emit: Option<Instant>,
+    /// 外部引擎接管播放时最近同步的位置
+    external: Option<ExternalClock>,
+}
+
+/// 外部引擎同步的位置，播放中按经过时间推算
+#[derive(Debug, Clone, Copy)]
+struct ExternalClock {
+    position_ms: u64,
+    is_playing: bool,
+    synced_at: Instant,
+}
+
+impl ExternalClock {
+    fn position_at(&self, now: Instant) -> u64 {
+        if self.is_playing {
+            self.position_ms + now.saturating_duration_since(self.synced_at).as_millis() as u64
+        } else {
+            self.position_ms
+        }
+    }
+}
+
+impl PositionReporter {
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval: clamp_interval(interval_ms),
+            announced: None,
+            last: None,
+            last_emit: None,
+            external: None,
+        }
+    }
+
+    pub fn set_interval(&mut self, interval_ms: u64) {
+        self.interval = clamp_interval(interval_ms);
+    }
+
+    /// 发出 TrackChanged，之后才上报该曲目的位置
+    pub async fn announce(&mut self, event_tx: &mpsc::Sender<PlayerEvent>, track: Option<Track>) {
+        self.announced = track.as_ref().map(|t| t.id);
+        self.last = None;
+        self.last_emit = None;
+        let _ = event_tx.send(PlayerEvent::TrackChanged(track)).await;
+    }
+
+    /// 跳转后位置可以变小
+    pub fn seeked(&mut self) {
+        self.last = None;
+    }
+
+    /// 记录外部引擎同步的位置，之后不再上报原生位置；返回是否刚由外部引擎接管
+    pub fn sync_external(&mut self, position_ms: u64, is_playing: bool) -> bool {
+        self.sync_external_at(position_ms, is_playing, Instant::now())
+    }
+
+    fn sync_external_at(&mut self, position_ms: u64, is_playing: bool, now: Instant) -> bool {
+        let took_over = self.external.is_none();
+        self.external = Some(ExternalClock { position_ms, is_playing, synced_at: now });
+        took_over
+    }
+
+    /// 外部引擎接管期间推算的当前位置
+    pub fn external_position(&self) -> Option<u64> {
+        self.external_position_at(Instant::now())
+    }
+
+    fn external_position_at(&self, now: Instant) -> Option<u64> {
+        self.external.map(|clock| clock.position_at(now))
+    }
+
+    /// 外部引擎是否在播放（暂停时为 false）；未接管时为None
+    pub fn external_playing(&self) -> Option<bool> {
+        self.external.map(|clock| clock.is_playing)
+    }
+
+    /// 交还原生播放：恢复上报，交接位置可能小于接管前上报的位置
+    pub fn end_external(&mut self) {
+        self.external = None;
+        self.seeked();
+    }
+
+    /// 上报位置；force 为 true 时不受节流限制
+    pub async fn report(
+        &mut self,
+        event_tx: &mpsc::Sender<PlayerEvent>,
+        track_id: Option<i64>,
+        position_ms: u64,
+        is_estimated: bool,
+        force: bool,
+    ) {
+        self.report_at(event_tx, track_id, position_ms, is_estimated, force, Instant::now()).await;
+    }
+
+    async fn report_at(
+        &mut self,
+        event_tx: &mpsc::Sender<PlayerEvent>,
+        track_id: Option<i64>,
+        position_ms: u64,
+        is_estimated: bool,
+        force: bool,
+        now: Instant,
+    ) {
+        if let Some(update) = self.next(track_id, position_ms, is_estimated, force, now) {
+            let _ = event_tx.send(PlayerEvent::PositionChanged(update)).await;
+        }
+    }
+
+    fn next(
+        &mut self,
+        track_id: Option<i64>,
+        position_ms: u64,
+        is_estimated: bool,
+        force: bool,
+        now: Instant,
+    ) -> Option<PositionUpdate> {
+        if self.external.is_some() {
+            return None;
+        }
+        let track_id = track_id.filter(|id| self.announced == Some(*id))?;
+        let throttled = self
+            .last_emit
+            .is_some_and(|last| now.duration_since(last) + THROTTLE_SLACK < self.interval);
+        if throttled && !force {
+            return None;
+        }
+
+        let position_ms = match self.last {
+            Some((id, last)) if id == track_id => position_ms.max(last),
+            _ => position_ms,
+        };
+        self.last = Some((track_id, position_ms));
+        self.last_emit = Some(now);
+        Some(PositionUpdate {
+            position_ms,
+            track_id: Some(track_id),
+            is_estimated,
+        })
+    }
+}
+
+fn clamp_interval(interval_ms: u64) -> Duration {
+    Duration::from_millis(interval_ms.clamp(MIN_POSITION_INTERVAL_MS, MAX_POSITION_INTERVAL_MS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟Sink：按手动推进的时间计算位置，代替真实音频输出
+    struct MockSink {
+        track_id: i64,
+        start_position_ms: u64,
+        elapsed_ms: u64,
+    }
+
+    impl MockSink {
+        fn play(track_id: i64, position_ms: u64) -> Self {
+            Self { track_id, start_position_ms: position_ms, elapsed_ms: 0 }
+        }
+
+        fn position(&self) -> u64 {
+            self.start_position_ms + self.elapsed_ms
+        }
+    }
+
+    /// 按PlaybackActor的顺序驱动上报器：播放 -> 定时上报 -> 跳转 -> 下一首
+    struct Harness {
+        reporter: PositionReporter,
+        tx: mpsc::Sender<PlayerEvent>,
+        sink: Option<MockSink>,
+        now: Instant,
+    }
+
+    impl Harness {
+        async fn play(&mut self, track_id: i64) {
+            self.sink = Some(MockSink::play(track_id, 0));
+            self.reporter.announce(&self.tx, Some(Track::new(track_id, format!("/music/{}.flac", track_id)))).await;
+        }
+
+        async fn advance(&mut self, ms: u64) {
+            for _ in 0..ms / 100 {
+                let Some(sink) = &mut self.sink else { return };
+                sink.elapsed_ms += 100;
+                let (track_id, position) = (sink.track_id, sink.position());
+                self.now += Duration::from_millis(100);
+                self.reporter.report_at(&self.tx, Some(track_id), position, true, false, self.now).await;
+            }
+        }
+
+        async fn seek(&mut self, position_ms: u64) {
+            let track_id = self.sink.as_ref().unwrap().track_id;
+            self.sink = Some(MockSink::play(track_id, position_ms));
+            self.reporter.seeked();
+            self.reporter.report_at(&self.tx, Some(track_id), position_ms, false, true, self.now).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_ordering_across_play_seek_next() {
+        let (tx, mut rx) = mpsc::channel(256);
+        let mut harness = Harness { reporter: PositionReporter::new(200), tx, sink: None, now: Instant::now() };
+
+        // 曲目尚未通知时不上报
+        harness.reporter.report(&harness.tx, Some(1), 500, true, true).await;
+
+        harness.play(1).await;
+        harness.advance(1000).await;
+        harness.seek(60_000).await;
+        harness.advance(500).await;
+        harness.play(2).await;
+        // 快速切歌：上一首残留的位置不再上报
+        harness.reporter.report(&harness.tx, Some(1), 61_100, true, true).await;
+        harness.advance(400).await;
+        drop(harness);
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+
+        let mut current: Option<i64> = None;
+        let mut last_position: Option<u64> = None;
+        let mut positions: Vec<(i64, u64, bool)> = Vec::new();
+        for event in &events {
+            match event {
+                PlayerEvent::TrackChanged(track) => {
+                    current = track.as_ref().map(|t| t.id);
+                    last_position = None;
+                }
+                PlayerEvent::PositionChanged(update) => {
+                    assert_eq!(update.track_id, current, "位置事件必须在所属曲目的TrackChanged之后");
+                    let track_id = update.track_id.unwrap();
+                    if !update.is_estimated {
+                        last_position = None;
+                    }
+                    if let Some(last) = last_position {
+                        assert!(update.position_ms >= last, "同一曲目内位置不能倒退");
+                    }
+                    last_position = Some(update.position_ms);
+                    positions.push((track_id, update.position_ms, update.is_estimated));
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // 200ms间隔下，100ms的定时器每两次上报一次
+        assert_eq!(
+            positions,
+            vec![
+                (1, 100, true), (1, 300, true), (1, 500, true), (1, 700, true), (1, 900, true),
+                (1, 60_000, false), (1, 60_200, true), (1, 60_400, true),
+                (2, 100, true), (2, 300, true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handoff_from_external_engine() {
+        let (tx, mut rx) = mpsc::channel(256);
+        let mut harness = Harness { reporter: PositionReporter::new(MIN_POSITION_INTERVAL_MS), tx, sink: None, now: Instant::now() };
+        harness.play(1).await;
+        harness.advance(3_000).await;
+        while rx.try_recv().is_ok() {}
+
+        // 前端接管：原生输出暂停在3秒，外部引擎从这里继续播放
+        let native = harness.sink.take().unwrap();
+        assert!(harness.reporter.sync_external_at(native.position(), true, harness.now));
+        let mut external = MockSink::play(1, native.position());
+        for _ in 0..20 {
+            harness.now += Duration::from_millis(250);
+            external.elapsed_ms += 250;
+            // 外部引擎每250ms同步一次，其间原生定时器仍在运行
+            assert!(!harness.reporter.sync_external_at(external.position(), true, harness.now));
+            harness.reporter.report_at(&harness.tx, Some(1), native.position(), true, true, harness.now).await;
+        }
+        assert!(rx.try_recv().is_err(), "外部引擎接管期间不上报原生位置");
+
+        // 外部引擎上往回跳转，同步间隔之间按经过时间推算
+        external = MockSink::play(1, 1_000);
+        harness.reporter.sync_external_at(external.position(), true, harness.now);
+        harness.now += Duration::from_millis(180);
+        external.elapsed_ms += 180;
+        assert_eq!(harness.reporter.external_position_at(harness.now), Some(external.position()));
+        assert_eq!(harness.reporter.external_playing(), Some(true));
+
+        // 交还原生播放：从前端报告的位置开始播放，不受接管前更大的位置影响
+        let handoff_ms = harness.reporter.external_position_at(harness.now).unwrap();
+        harness.reporter.end_external();
+        harness.sink = Some(MockSink::play(1, handoff_ms));
+        harness.reporter.report_at(&harness.tx, Some(1), handoff_ms, false, true, harness.now).await;
+        let Ok(PlayerEvent::PositionChanged(update)) = rx.try_recv() else { panic!("交接后应立即上报位置") };
+        assert_eq!(update.position_ms, handoff_ms);
+        for _ in 0..10 {
+            harness.advance(100).await;
+            let actual = harness.sink.as_ref().unwrap().position();
+            let Ok(PlayerEvent::PositionChanged(update)) = rx.try_recv() else { panic!("交接后应恢复上报位置") };
+            assert!(update.position_ms.abs_diff(actual) < 100, "交接后漂移过大: {} vs {}", update.position_ms, actual);
+        }
+        assert_eq!(harness.reporter.external_position(), None);
+    }
+
+    #[test]
+    fn test_position_is_monotonic_until_seek() {
+        let mut reporter = PositionReporter::new(MIN_POSITION_INTERVAL_MS);
+        reporter.announced = Some(7);
+        let start = Instant::now();
+
+        let first = reporter.next(Some(7), 5_050, true, false, start).unwrap();
+        assert_eq!(first.position_ms, 5_050);
+        // 暂停时记录的位置比推算值略小
+        let paused = reporter.next(Some(7), 5_000, false, false, start + Duration::from_millis(100)).unwrap();
+        assert_eq!(paused.position_ms, 5_050);
+        assert!(!paused.is_estimated);
+
+        reporter.seeked();
+        let seeked = reporter.next(Some(7), 1_000, false, true, start + Duration::from_millis(110)).unwrap();
+        assert_eq!(seeked.position_ms, 1_000);
+        assert!(reporter.next(Some(7), 1_050, true, false, start + Duration::from_millis(150)).is_none());
+        assert!(reporter.next(None, 0, false, true, start).is_none());
+    }
+
+    #[test]
+    fn test_interval_is_clamped() {
+        let mut reporter = PositionReporter::new(10);
+        assert_eq!(reporter.interval, Duration::from_millis(MIN_POSITION_INTERVAL_MS));
+        reporter.announced = Some(1);
+        reporter.set_interval(60_000);
+        assert_eq!(reporter.interval, Duration::from_millis(MAX_POSITION_INTERVAL_MS));
+        // 修改间隔不影响已通知的曲目
+        assert_eq!(reporter.announced, Some(1));
+    }
+}
@@ -1,22 +1,25 @@
 // ============================================================================
-// PreloadActor - 智能预加载管理器
+// PreloadActor - 下一首预加载管理器
 // ============================================================================
 //
 // 职责：
-// - 预测下一首将要播放的曲目
-// - 提前加载音频数据到缓存
-// - 管理预加载缓存（LRU策略）
-// - 根据播放模式调整预测策略
+// - 按PlaylistActor给出的即将播放曲目提前准备音频（遵循随机、循环规则）
+// - 本地曲目：读入内存并创建好解码器
+// - 远程曲目：建立HTTP连接并完成初始缓冲
+// - 按路径缓存最多一两首，播放时取出，播放列表变化时淘汰
+// - 统计命中/未命中次数，衡量预加载效果
 //
 // 设计原则：
 // - 高内聚：只负责预加载相关的逻辑，不涉及播放控制
 // - 低耦合：通过消息与其他Actor通信，不直接依赖其他模块
 // ============================================================================
 
-use crate::player::{PlayerEvent, RepeatMode, Track};
+use crate::player::{PlayerEvent, Track};
+use crate::streaming::{SimpleHttpReader, TrafficClass};
 use anyhow::{Context, Result};
 use lru::LruCache;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -31,123 +34,96 @@ use tokio::task::JoinHandle;
 
 /// PreloadActor的消息类型
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum PreloadMsg {
-    /// 内部消息：预加载完成，将数据存入缓存
-    CacheLoadedData {
-        track_id: i64,
-        data: Vec<u8>,
-    },
-    
-    /// 开始预加载指定曲目
-    PreloadTrack {
+    /// 内部消息：预加载完成，存入缓存
+    CacheLoaded {
         track: Track,
-        priority: PreloadPriority,
-    },
-
-    /// 批量预加载
-    PreloadBatch {
-        tracks: Vec<Track>,
-        priority: PreloadPriority,
+        audio: PreloadedAudio,
+        size: usize,
     },
 
-    /// 取消预加载
-    CancelPreload { track_id: i64 },
+    /// 内部消息：预加载失败
+    LoadFailed { path: String },
 
-    /// 清空缓存
-    ClearCache,
+    /// 准备即将播放的曲目（按播放顺序，已缓存的保留）
+    PreloadUpcoming(Vec<Track>),
 
-    /// 获取缓存状态
-    GetCacheStatus(oneshot::Sender<CacheStatus>),
+    /// 播放列表变化：淘汰不在列表中的曲目
+    RetainPaths(HashSet<String>),
 
-    /// 更新播放模式（影响预测逻辑）
-    UpdatePlayMode {
-        repeat_mode: RepeatMode,
-        shuffle: bool,
+    /// 取出曲目的预加载音频（取出后从缓存移除），同时记录命中/未命中
+    Take {
+        path: String,
+        respond_to: oneshot::Sender<Option<PreloadedAudio>>,
     },
 
-    /// 更新播放列表（用于预测）
-    UpdatePlaylist {
-        playlist: Vec<Track>,
-        current_index: Option<usize>,
-    },
-
-    /// 通知当前播放曲目变化
-    OnTrackChanged {
-        current_track: Track,
-        current_index: usize,
-    },
-
-    /// 检查缓存是否包含指定曲目
-    HasCached {
-        track_id: i64,
-        respond_to: oneshot::Sender<bool>,
-    },
+    /// 清空缓存
+    ClearCache,
 
-    /// 获取缓存的音频数据
-    GetCached {
-        track_id: i64,
-        respond_to: oneshot::Sender<Option<Arc<Vec<u8>>>>,
-    },
+    /// 获取缓存状态
+    GetCacheStatus(oneshot::Sender<PreloadStats>),
 
     /// 关闭Actor
     Shutdown,
 }
 
-/// 预加载优先级
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-#[allow(dead_code)]
-pub enum PreloadPriority {
-    /// 低优先级：预测的可能播放的歌曲
-    Low = 0,
-    /// 中优先级：预测的下一首
-    Medium = 1,
-    /// 高优先级：用户明确指定的下一首
-    High = 2,
-    /// 紧急：即将播放，必须立即加载
-    Urgent = 3,
+/// 预加载好的音频
+pub enum PreloadedAudio {
+    /// 本地曲目：已读入内存并创建好的解码器
+    Decoded(Box<dyn rodio::Source<Item = i16> + Send>),
+    /// 远程曲目：已完成初始缓冲的HTTP流
+    Stream {
+        reader: SimpleHttpReader,
+        url: String,
+    },
+}
+
+impl std::fmt::Debug for PreloadedAudio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreloadedAudio::Decoded(_) => f.write_str("Decoded"),
+            PreloadedAudio::Stream { url, .. } => f.debug_struct("Stream").field("url", url).finish(),
+        }
+    }
 }
 
-/// 缓存状态信息
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct CacheStatus {
+/// 缓存状态和命中统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PreloadStats {
     /// 已缓存的曲目数量
     pub cached_count: usize,
-    /// 缓存总大小（字节）
+    /// 缓存总大小（字节，远程曲目按取得时已缓冲的大小计算）
     pub total_size: usize,
+    /// 正在预加载的曲目数量
+    pub loading_count: usize,
+    /// 播放时命中预加载的次数
+    pub hits: u64,
+    /// 播放时未命中的次数
+    pub misses: u64,
 }
 
 // ============================================================================
 // 缓存数据结构
 // ============================================================================
 
-/// 缓存的音频数据
-#[derive(Clone)]
-#[allow(dead_code)]
+/// 缓存的预加载音频
 struct CachedAudio {
-    /// 曲目ID
     track_id: i64,
-    /// 音频原始数据
-    data: Arc<Vec<u8>>,
-    /// 文件大小（字节）
+    audio: PreloadedAudio,
+    /// 占用内存（字节）
     size: usize,
     /// 缓存时间
     cached_at: Instant,
-    /// 访问次数
-    access_count: u32,
-    /// 优先级
-    priority: PreloadPriority,
 }
 
 // ============================================================================
 // 音频缓存管理
 // ============================================================================
 
-/// 音频缓存管理器
+/// 预加载缓存（按路径索引）
 struct AudioCache {
     /// LRU缓存
-    cache: LruCache<i64, CachedAudio>,
+    cache: LruCache<String, CachedAudio>,
     /// 最大缓存大小（字节）
     max_size_bytes: usize,
     /// 当前缓存大小（字节）
@@ -162,7 +138,7 @@ impl AudioCache {
     /// 创建新的缓存管理器
     fn new(max_capacity: usize, max_size_mb: usize) -> Self {
         Self {
-            cache: LruCache::new(NonZeroUsize::new(max_capacity).unwrap()),
+            cache: LruCache::new(NonZeroUsize::new(max_capacity.max(1)).unwrap()),
             max_size_bytes: max_size_mb * 1024 * 1024,
             current_size: 0,
             hits: 0,
@@ -171,12 +147,20 @@ impl AudioCache {
     }
 
     /// 插入缓存
-    fn put(&mut self, audio: CachedAudio) {
-        // 如果已存在，先移除旧的
-        if let Some(old) = self.cache.get(&audio.track_id) {
-            self.current_size -= old.size;
+    fn put(&mut self, path: String, audio: CachedAudio) {
+        // 如果单个曲目就超过最大容量，则不缓存
+        if audio.size > self.max_size_bytes {
+            log::warn!(
+                "曲目 {} 大小 {}MB 超过最大缓存容量，跳过缓存",
+                audio.track_id,
+                audio.size / 1024 / 1024
+            );
+            return;
         }
 
+        // 如果已存在，先移除旧的
+        self.remove(&path);
+
         // 检查是否需要腾出空间
         while self.current_size + audio.size > self.max_size_bytes && !self.cache.is_empty() {
             if let Some((_, removed)) = self.cache.pop_lru() {
@@ -185,41 +169,41 @@ impl AudioCache {
             }
         }
 
-        // 如果单个文件就超过最大容量，则不缓存
-        if audio.size > self.max_size_bytes {
-            log::warn!(
-                "曲目 {} 大小 {}MB 超过最大缓存容量，跳过缓存",
-                audio.track_id,
-                audio.size / 1024 / 1024
-            );
-            return;
-        }
-
         self.current_size += audio.size;
-        self.cache.put(audio.track_id, audio);
+        if let Some((_, evicted)) = self.cache.push(path, audio) {
+            self.current_size -= evicted.size;
+            log::debug!("缓存数量已满，移除曲目 {}", evicted.track_id);
+        }
     }
 
-    /// 获取缓存
-    fn get(&mut self, track_id: i64) -> Option<Arc<Vec<u8>>> {
-        if let Some(audio) = self.cache.get_mut(&track_id) {
-            audio.access_count += 1;
-            self.hits += 1;
-            Some(audio.data.clone())
-        } else {
-            self.misses += 1;
-            None
+    /// 取出缓存（取出后移除），记录命中/未命中
+    fn take(&mut self, path: &str) -> Option<PreloadedAudio> {
+        match self.cache.pop(path) {
+            Some(audio) => {
+                self.current_size -= audio.size;
+                self.hits += 1;
+                log::info!(
+                    "🎯 预加载命中: {} (已缓存 {}ms)",
+                    audio.track_id,
+                    audio.cached_at.elapsed().as_millis()
+                );
+                Some(audio.audio)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
         }
     }
 
     /// 检查是否缓存
-    fn contains(&self, track_id: i64) -> bool {
-        self.cache.contains(&track_id)
+    fn contains(&self, path: &str) -> bool {
+        self.cache.contains(path)
     }
 
     /// 移除缓存
-    #[allow(dead_code)]
-    fn remove(&mut self, track_id: i64) -> bool {
-        if let Some(audio) = self.cache.pop(&track_id) {
+    fn remove(&mut self, path: &str) -> bool {
+        if let Some(audio) = self.cache.pop(path) {
             self.current_size -= audio.size;
             true
         } else {
@@ -227,6 +211,18 @@ impl AudioCache {
         }
     }
 
+    /// 移除不在集合中的曲目
+    fn retain(&mut self, paths: &HashSet<String>) {
+        let stale: Vec<String> = self.cache.iter()
+            .map(|(path, _)| path.clone())
+            .filter(|path| !paths.contains(path))
+            .collect();
+        for path in stale {
+            self.remove(&path);
+            log::debug!("播放列表已变化，移除预加载: {}", path);
+        }
+    }
+
     /// 清空缓存
     fn clear(&mut self) {
         self.cache.clear();
@@ -234,10 +230,13 @@ impl AudioCache {
     }
 
     /// 获取缓存状态
-    fn status(&self) -> CacheStatus {
-        CacheStatus {
+    fn status(&self) -> PreloadStats {
+        PreloadStats {
             cached_count: self.cache.len(),
             total_size: self.current_size,
+            loading_count: 0,
+            hits: self.hits,
+            misses: self.misses,
         }
     }
 }
@@ -250,7 +249,7 @@ impl AudioCache {
 pub struct PreloadActor {
     /// 消息接收器
     inbox: mpsc::Receiver<PreloadMsg>,
-    
+
     /// 消息发送器（用于内部任务回传数据）
     inbox_tx: mpsc::Sender<PreloadMsg>,
 
@@ -261,20 +260,8 @@ pub struct PreloadActor {
     #[allow(dead_code)]
     event_tx: mpsc::Sender<PlayerEvent>,
 
-    /// 当前播放列表
-    playlist: Vec<Track>,
-
-    /// 当前播放索引
-    current_index: Option<usize>,
-
-    /// 播放模式
-    repeat_mode: RepeatMode,
-
-    /// 是否随机播放
-    shuffle: bool,
-
-    /// 正在加载的任务
-    loading_tasks: HashMap<i64, JoinHandle<()>>,
+    /// 正在加载的任务（按路径）
+    loading_tasks: HashMap<String, JoinHandle<()>>,
 }
 
 impl PreloadActor {
@@ -291,98 +278,68 @@ impl PreloadActor {
             inbox_tx,
             cache: AudioCache::new(max_cache_capacity, max_cache_size_mb),
             event_tx,
-            playlist: Vec::new(),
-            current_index: None,
-            repeat_mode: RepeatMode::Off,
-            shuffle: false,
             loading_tasks: HashMap::new(),
         }
     }
 
     /// 运行Actor主循环
     pub async fn run(mut self) {
-        println!("🔄 [CORE] PreloadActor.run() 方法开始执行");
+        log::debug!("🔄 [CORE] PreloadActor.run() 方法开始执行");
         log::info!("🔄 PreloadActor 已启动");
-        
-        println!("🔄 [CORE] PreloadActor 进入事件循环，等待消息...");
+
+        log::debug!("🔄 [CORE] PreloadActor 进入事件循环，等待消息...");
         while let Some(msg) = self.inbox.recv().await {
             match msg {
-                PreloadMsg::CacheLoadedData { track_id, data } => {
-                    // 将加载的数据存入缓存
-                    let cached_audio = CachedAudio {
-                        track_id,
-                        data: Arc::new(data.clone()),
-                        size: data.len(),
-                        cached_at: std::time::Instant::now(),  // ✅ 使用正确的字段名
-                        access_count: 0,
-                        priority: PreloadPriority::High,  // ✅ 添加缺失的字段
-                    };
-                    self.cache.put(cached_audio);
-                    // 从加载任务列表中移除
-                    self.loading_tasks.remove(&track_id);
-                    log::info!("💾 曲目 {} 已存入缓存 ({:.2}MB)", track_id, data.len() as f64 / 1024.0 / 1024.0);
-                }
-                
-                PreloadMsg::PreloadTrack { track, priority } => {
-                    self.handle_preload_track(track, priority).await;
-                }
-
-                PreloadMsg::PreloadBatch { tracks, priority } => {
-                    self.handle_preload_batch(tracks, priority).await;
-                }
-
-                PreloadMsg::CancelPreload { track_id } => {
-                    self.handle_cancel_preload(track_id).await;
-                }
-
-                PreloadMsg::ClearCache => {
-                    self.handle_clear_cache().await;
+                PreloadMsg::CacheLoaded { track, audio, size } => {
+                    // 加载期间被淘汰（播放列表变化）的结果直接丢弃
+                    if self.loading_tasks.remove(&track.path).is_none() {
+                        log::debug!("曲目 {} 的预加载已取消，丢弃结果", track.id);
+                        continue;
+                    }
+                    log::info!("💾 曲目 {} 已预加载 ({:.2}MB)", track.id, size as f64 / 1024.0 / 1024.0);
+                    self.cache.put(track.path, CachedAudio {
+                        track_id: track.id,
+                        audio,
+                        size,
+                        cached_at: Instant::now(),
+                    });
                 }
 
-                PreloadMsg::GetCacheStatus(respond_to) => {
-                    let _ = respond_to.send(self.cache.status());
+                PreloadMsg::LoadFailed { path } => {
+                    self.loading_tasks.remove(&path);
                 }
 
-                PreloadMsg::UpdatePlayMode {
-                    repeat_mode,
-                    shuffle,
-                } => {
-                    self.handle_update_play_mode(repeat_mode, shuffle).await;
+                PreloadMsg::PreloadUpcoming(tracks) => {
+                    for track in tracks {
+                        self.handle_preload_track(track);
+                    }
                 }
 
-                PreloadMsg::UpdatePlaylist {
-                    playlist,
-                    current_index,
-                } => {
-                    self.handle_update_playlist(playlist, current_index).await;
+                PreloadMsg::RetainPaths(paths) => {
+                    self.handle_retain(&paths);
                 }
 
-                PreloadMsg::OnTrackChanged {
-                    current_track,
-                    current_index,
-                } => {
-                    self.handle_track_changed(current_track, current_index)
-                        .await;
+                PreloadMsg::Take { path, respond_to } => {
+                    // 正在加载的曲目已来不及使用，播放方自行解码
+                    if let Some(handle) = self.loading_tasks.remove(&path) {
+                        handle.abort();
+                    }
+                    let _ = respond_to.send(self.cache.take(&path));
                 }
 
-                PreloadMsg::HasCached {
-                    track_id,
-                    respond_to,
-                } => {
-                    let _ = respond_to.send(self.cache.contains(track_id));
+                PreloadMsg::ClearCache => {
+                    self.handle_clear_cache();
                 }
 
-                PreloadMsg::GetCached {
-                    track_id,
-                    respond_to,
-                } => {
-                    let data = self.cache.get(track_id);
-                    let _ = respond_to.send(data);
+                PreloadMsg::GetCacheStatus(respond_to) => {
+                    let mut status = self.cache.status();
+                    status.loading_count = self.loading_tasks.len();
+                    let _ = respond_to.send(status);
                 }
 
                 PreloadMsg::Shutdown => {
                     log::info!("🛑 PreloadActor 正在关闭...");
-                    self.shutdown().await;
+                    self.shutdown();
                     break;
                 }
             }
@@ -392,219 +349,98 @@ impl PreloadActor {
     }
 
     /// 处理预加载单个曲目
-    async fn handle_preload_track(&mut self, track: Track, priority: PreloadPriority) {
-        // 🔧 跳过远程文件（WebDAV等流式源不需要预加载）
-        if track.path.starts_with("webdav://") || 
-           track.path.starts_with("http://") || 
-           track.path.starts_with("https://") {
-            log::debug!("曲目 {} 是远程文件，跳过预加载: {}", track.id, track.path);
-            return;
-        }
-        
-        // 如果已在缓存中，跳过
-        if self.cache.contains(track.id) {
-            log::debug!("曲目 {} 已在缓存中，跳过预加载", track.id);
-            return;
-        }
-
-        // 如果正在加载，跳过
-        if self.loading_tasks.contains_key(&track.id) {
-            log::debug!("曲目 {} 正在加载中，跳过", track.id);
+    fn handle_preload_track(&mut self, track: Track) {
+        // 已在缓存中或正在加载，跳过
+        if self.cache.contains(&track.path) || self.loading_tasks.contains_key(&track.path) {
+            log::debug!("曲目 {} 已预加载或正在加载，跳过", track.id);
             return;
         }
 
-        log::info!(
-            "🔄 开始预加载曲目 {} - {:?} (优先级: {:?})",
-            track.id,
-            track.title,
-            priority
-        );
+        log::info!("🔄 开始预加载曲目 {} - {:?}", track.id, track.title);
 
-        // 启动加载任务
-        let track_id = track.id;
-        let path = PathBuf::from(&track.path);
+        let path = track.path.clone();
         let inbox_tx = self.inbox_tx.clone();
-
         let handle = tokio::spawn(async move {
-            match Self::load_audio_data(&path).await {
-                Ok(data) => {
-                    log::info!(
-                        "✅ 预加载成功: {} ({:.2}MB)",
-                        track_id,
-                        data.len() as f64 / 1024.0 / 1024.0
-                    );
-
-                    // 发送加载完成的数据到Actor（存入缓存）
-                    let _ = inbox_tx.send(PreloadMsg::CacheLoadedData {
-                        track_id,
-                        data,
-                    }).await;
+            match Self::load_track(&track).await {
+                Ok((audio, size)) => {
+                    let _ = inbox_tx.send(PreloadMsg::CacheLoaded { track, audio, size }).await;
                 }
                 Err(e) => {
-                    log::error!("❌ 预加载失败: {} - {:?}", track_id, e);
+                    log::warn!("❌ 预加载失败: {} - {:?}", track.id, e);
+                    let _ = inbox_tx.send(PreloadMsg::LoadFailed { path: track.path }).await;
                 }
             }
         });
 
-        self.loading_tasks.insert(track_id, handle);
+        self.loading_tasks.insert(path, handle);
     }
 
-    /// 处理批量预加载
-    async fn handle_preload_batch(&mut self, tracks: Vec<Track>, priority: PreloadPriority) {
-        log::info!("🔄 批量预加载 {} 首曲目 (优先级: {:?})", tracks.len(), priority);
-
-        for track in tracks {
-            self.handle_preload_track(track, priority).await;
-        }
+    /// 播放列表变化：淘汰不在列表中的缓存和加载任务
+    fn handle_retain(&mut self, paths: &HashSet<String>) {
+        self.cache.retain(paths);
+        self.loading_tasks.retain(|path, handle| {
+            let keep = paths.contains(path);
+            if !keep {
+                handle.abort();
+            }
+            keep
+        });
     }
 
-    /// 处理取消预加载
-    async fn handle_cancel_preload(&mut self, track_id: i64) {
-        if let Some(handle) = self.loading_tasks.remove(&track_id) {
+    /// 处理清空缓存
+    fn handle_clear_cache(&mut self) {
+        for (_, handle) in self.loading_tasks.drain() {
             handle.abort();
-            log::debug!("已取消预加载任务: {}", track_id);
         }
-    }
-
-    /// 处理清空缓存
-    async fn handle_clear_cache(&mut self) {
         self.cache.clear();
         log::info!("🗑️ 缓存已清空");
     }
 
-    /// 处理播放模式更新
-    async fn handle_update_play_mode(&mut self, repeat_mode: RepeatMode, shuffle: bool) {
-        self.repeat_mode = repeat_mode;
-        self.shuffle = shuffle;
-        log::debug!(
-            "播放模式已更新: repeat={:?}, shuffle={}",
-            repeat_mode,
-            shuffle
-        );
-
-        // 根据新模式预测并预加载
-        self.predict_and_preload().await;
-    }
-
-    /// 处理播放列表更新
-    async fn handle_update_playlist(&mut self, playlist: Vec<Track>, current_index: Option<usize>) {
-        self.playlist = playlist;
-        self.current_index = current_index;
-        log::debug!(
-            "播放列表已更新: {} 首曲目, 当前索引: {:?}",
-            self.playlist.len(),
-            current_index
-        );
-
-        // 预测并预加载
-        self.predict_and_preload().await;
-    }
-
-    /// 处理当前曲目变化
-    async fn handle_track_changed(&mut self, _current_track: Track, current_index: usize) {
-        self.current_index = Some(current_index);
-        log::debug!("当前曲目已变化: 索引 {}", current_index);
-
-        // 清理已完成的加载任务
-        self.loading_tasks.retain(|_, handle| !handle.is_finished());
-
-        // 预测并预加载下一首
-        self.predict_and_preload().await;
-    }
-
-    /// 预测并预加载下一首曲目
-    async fn predict_and_preload(&mut self) {
-        let tracks_to_preload = self.predict_next_tracks();
-
-        for (track, priority) in tracks_to_preload {
-            self.handle_preload_track(track, priority).await;
-        }
-    }
-
-    /// 预测下一首曲目（根据播放模式）
-    fn predict_next_tracks(&self) -> Vec<(Track, PreloadPriority)> {
-        if self.playlist.is_empty() {
-            return Vec::new();
-        }
-
-        let Some(current_idx) = self.current_index else {
-            return Vec::new();
+    /// 按曲目来源准备音频，返回音频和占用内存大小
+    #[tracing::instrument(level = "debug", skip_all, fields(track_id = track.id))]
+    async fn load_track(track: &Track) -> Result<(PreloadedAudio, usize)> {
+        // WebDAV曲目已缓存到本地时按本地文件处理
+        let local_path = if track.path.starts_with("webdav://") {
+            crate::streaming::full_download::cached_file_for(track.id, &track.path)
+        } else if crate::remote_source::is_remote_track_path(&track.path) {
+            None
+        } else {
+            Some(PathBuf::from(&track.path))
         };
 
-        let mut predictions = Vec::new();
-
-        match self.repeat_mode {
-            RepeatMode::Off => {
-                // 顺序播放：预加载下一首
-                if !self.shuffle && current_idx + 1 < self.playlist.len() {
-                    predictions.push((
-                        self.playlist[current_idx + 1].clone(),
-                        PreloadPriority::High,
-                    ));
-
-                    // 预加载下下首（低优先级）
-                    if current_idx + 2 < self.playlist.len() {
-                        predictions.push((
-                            self.playlist[current_idx + 2].clone(),
-                            PreloadPriority::Low,
-                        ));
-                    }
-                }
-            }
-
-            RepeatMode::All => {
-                // 列表循环：预加载下一首（考虑循环）
-                let next_idx = (current_idx + 1) % self.playlist.len();
-                predictions.push((self.playlist[next_idx].clone(), PreloadPriority::High));
-
-                // 预加载下下首
-                let next_next_idx = (current_idx + 2) % self.playlist.len();
-                predictions.push((
-                    self.playlist[next_next_idx].clone(),
-                    PreloadPriority::Medium,
-                ));
-            }
-
-            RepeatMode::One => {
-                // 单曲循环：不需要预加载（当前曲目已在播放）
+        match local_path {
+            Some(path) => {
+                let data = Arc::new(Self::load_audio_data(&path).await?);
+                let size = data.len();
+                let source = tokio::task::spawn_blocking(move || {
+                    crate::player::AudioDecoder::new(&path).decode_from_memory(data)
+                })
+                .await
+                .context("解码任务失败")??;
+                Ok((PreloadedAudio::Decoded(Box::new(source)), size))
             }
-        }
-
-        // 随机模式：预加载随机2-3首
-        if self.shuffle {
-            predictions.clear();
-            use rand::seq::SliceRandom;
-            let mut rng = rand::thread_rng();
-            let mut indices: Vec<usize> = (0..self.playlist.len())
-                .filter(|&i| i != current_idx)
-                .collect();
-            indices.shuffle(&mut rng);
-
-            for idx in indices.iter().take(2) {
-                predictions.push((
-                    self.playlist[*idx].clone(),
-                    PreloadPriority::Medium,
-                ));
+            None => {
+                let (reader, url) = super::playback_actor::open_stream_reader(&track.path, TrafficClass::Prefetch).await?;
+                let size = reader.get_buffered_size();
+                Ok((PreloadedAudio::Stream { reader, url }, size))
             }
         }
-
-        predictions
     }
 
     /// 加载音频数据
-    /// 
+    ///
     /// 安全措施：
     /// - 添加文件大小检查，防止OOM
     /// - 最大限制：200MB
     async fn load_audio_data(path: &PathBuf) -> Result<Vec<u8>> {
         // 最大预加载大小：200MB（预加载不应该处理超大文件）
         const MAX_PRELOAD_SIZE: u64 = 200 * 1024 * 1024;
-        
+
         // 检查文件大小
         let metadata = fs::metadata(path)
             .await
             .context("获取文件信息失败")?;
-        
+
         let file_size = metadata.len();
         if file_size > MAX_PRELOAD_SIZE {
             anyhow::bail!(
@@ -613,9 +449,9 @@ impl PreloadActor {
                 MAX_PRELOAD_SIZE as f64 / 1024.0 / 1024.0
             );
         }
-        
+
         log::debug!("预加载文件: {} ({:.2} MB)", path.display(), file_size as f64 / 1024.0 / 1024.0);
-        
+
         let data = fs::read(path)
             .await
             .context("读取音频文件失败")?;
@@ -624,14 +460,9 @@ impl PreloadActor {
     }
 
     /// 关闭Actor
-    async fn shutdown(&mut self) {
-        // 取消所有加载任务
-        for (_, handle) in self.loading_tasks.drain() {
-            handle.abort();
-        }
-
-        // 清空缓存
-        self.cache.clear();
+    fn shutdown(&mut self) {
+        // 取消所有加载任务并清空缓存
+        self.handle_clear_cache();
     }
 }
 
@@ -651,38 +482,35 @@ impl PreloadActorHandle {
         Self { tx }
     }
 
-    /// 预加载单个曲目
-    #[allow(dead_code)]
-    pub async fn preload_track(&self, track: Track, priority: PreloadPriority) -> Result<()> {
+    /// 准备即将播放的曲目
+    pub async fn preload_upcoming(&self, tracks: Vec<Track>) -> Result<()> {
         self.tx
-            .send(PreloadMsg::PreloadTrack { track, priority })
+            .send(PreloadMsg::PreloadUpcoming(tracks))
             .await
-            .context("发送PreloadTrack消息失败")?;
+            .context("发送PreloadUpcoming消息失败")?;
         Ok(())
     }
 
-    /// 批量预加载
-    #[allow(dead_code)]
-    pub async fn preload_batch(
-        &self,
-        tracks: Vec<Track>,
-        priority: PreloadPriority,
-    ) -> Result<()> {
+    /// 只保留仍在播放列表中的曲目
+    pub async fn retain_paths(&self, paths: HashSet<String>) -> Result<()> {
         self.tx
-            .send(PreloadMsg::PreloadBatch { tracks, priority })
+            .send(PreloadMsg::RetainPaths(paths))
             .await
-            .context("发送PreloadBatch消息失败")?;
+            .context("发送RetainPaths消息失败")?;
         Ok(())
     }
 
-    /// 取消预加载
-    #[allow(dead_code)]
-    pub async fn cancel_preload(&self, track_id: i64) -> Result<()> {
+    /// 取出曲目的预加载音频，未命中时返回 None，由调用方自行解码
+    pub async fn take(&self, track: &Track) -> Result<Option<PreloadedAudio>> {
+        let (tx, rx) = oneshot::channel();
         self.tx
-            .send(PreloadMsg::CancelPreload { track_id })
+            .send(PreloadMsg::Take {
+                path: track.path.clone(),
+                respond_to: tx,
+            })
             .await
-            .context("发送CancelPreload消息失败")?;
-        Ok(())
+            .context("发送Take消息失败")?;
+        rx.await.context("接收Take响应失败")
     }
 
     /// 清空缓存
@@ -695,96 +523,53 @@ impl PreloadActorHandle {
         Ok(())
     }
 
-    /// 获取缓存状态
-    #[allow(dead_code)]
-    pub async fn get_cache_status(&self) -> Result<CacheStatus> {
+    /// 获取缓存状态和命中统计
+    pub async fn get_cache_status(&self) -> Result<PreloadStats> {
         let (tx, rx) = oneshot::channel();
         self.tx
             .send(PreloadMsg::GetCacheStatus(tx))
             .await
             .context("发送GetCacheStatus消息失败")?;
-        rx.await.context("接收CacheStatus响应失败")
+        rx.await.context("接收缓存状态响应失败")
     }
 
-    /// 更新播放模式
+    /// 关闭Actor
     #[allow(dead_code)]
-    pub async fn update_play_mode(&self, repeat_mode: RepeatMode, shuffle: bool) -> Result<()> {
+    pub async fn shutdown(&self) -> Result<()> {
         self.tx
-            .send(PreloadMsg::UpdatePlayMode {
-                repeat_mode,
-                shuffle,
-            })
+            .send(PreloadMsg::Shutdown)
             .await
-            .context("发送UpdatePlayMode消息失败")?;
+            .context("发送Shutdown消息失败")?;
         Ok(())
     }
+}
 
-    /// 更新播放列表
-    #[allow(dead_code)]
-    pub async fn update_playlist(
-        &self,
-        playlist: Vec<Track>,
-        current_index: Option<usize>,
-    ) -> Result<()> {
-        self.tx
-            .send(PreloadMsg::UpdatePlaylist {
-                playlist,
-                current_index,
-            })
-            .await
-            .context("发送UpdatePlaylist消息失败")?;
-        Ok(())
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /// 通知曲目变化
-    #[allow(dead_code)]
-    pub async fn on_track_changed(&self, current_track: Track, current_index: usize) -> Result<()> {
-        self.tx
-            .send(PreloadMsg::OnTrackChanged {
-                current_track,
-                current_index,
-            })
-            .await
-            .context("发送OnTrackChanged消息失败")?;
-        Ok(())
+    fn entry(track_id: i64, size: usize) -> CachedAudio {
+        let source = rodio::buffer::SamplesBuffer::new(2, 44100, vec![0i16; 16]);
+        CachedAudio { track_id, audio: PreloadedAudio::Decoded(Box::new(source)), size, cached_at: Instant::now() }
     }
 
-    /// 检查是否已缓存
-    #[allow(dead_code)]
-    pub async fn has_cached(&self, track_id: i64) -> Result<bool> {
-        let (tx, rx) = oneshot::channel();
-        self.tx
-            .send(PreloadMsg::HasCached {
-                track_id,
-                respond_to: tx,
-            })
-            .await
-            .context("发送HasCached消息失败")?;
-        rx.await.context("接收HasCached响应失败")
-    }
+    #[test]
+    fn test_cache_take_counts_hits_and_evicts() {
+        let mut cache = AudioCache::new(2, 1);
+        cache.put("/a.flac".into(), entry(1, 1000));
+        cache.put("/b.flac".into(), entry(2, 1000));
+        cache.put("/c.flac".into(), entry(3, 1000));
+        // 数量上限为2，最早的被淘汰
+        assert!(!cache.contains("/a.flac"));
+        assert_eq!(cache.status().total_size, 2000);
 
-    /// 获取缓存的音频数据
-    #[allow(dead_code)]
-    pub async fn get_cached(&self, track_id: i64) -> Result<Option<Arc<Vec<u8>>>> {
-        let (tx, rx) = oneshot::channel();
-        self.tx
-            .send(PreloadMsg::GetCached {
-                track_id,
-                respond_to: tx,
-            })
-            .await
-            .context("发送GetCached消息失败")?;
-        rx.await.context("接收GetCached响应失败")
-    }
+        assert!(cache.take("/b.flac").is_some());
+        assert!(cache.take("/b.flac").is_none());
+        let status = cache.status();
+        assert_eq!((status.hits, status.misses, status.cached_count, status.total_size), (1, 1, 1, 1000));
 
-    /// 关闭Actor
-    #[allow(dead_code)]
-    pub async fn shutdown(&self) -> Result<()> {
-        self.tx
-            .send(PreloadMsg::Shutdown)
-            .await
-            .context("发送Shutdown消息失败")?;
-        Ok(())
+        cache.retain(&HashSet::from(["/x.flac".to_string()]));
+        assert_eq!(cache.status().cached_count, 0);
+        assert_eq!(cache.status().total_size, 0);
     }
 }
-
@@ -31,6 +31,9 @@ pub enum StateMsg {
     /// 更新随机播放
     UpdateShuffle(bool),
     
+    /// 更新播放速度
+    UpdatePlaybackRate(f32),
+    
     /// 获取完整状态
     GetState(tokio::sync::oneshot::Sender<PlayerState>),
     
@@ -75,10 +78,10 @@ impl StateActor {
     
     /// 运行Actor事件循环
     pub async fn run(mut self) {
-        println!("📊 [CORE] StateActor.run() 方法开始执行");
+        log::debug!("📊 [CORE] StateActor.run() 方法开始执行");
         log::info!("📊 StateActor 启动");
         
-        println!("📊 [CORE] StateActor 进入事件循环，等待消息...");
+        log::debug!("📊 [CORE] StateActor 进入事件循环，等待消息...");
         loop {
             match self.inbox.recv().await {
                 Some(msg) => {
@@ -101,6 +104,9 @@ impl StateActor {
                         StateMsg::UpdateShuffle(shuffle) => {
                             self.handle_update_shuffle(shuffle).await;
                         }
+                        StateMsg::UpdatePlaybackRate(rate) => {
+                            self.handle_update_playback_rate(rate).await;
+                        }
                         StateMsg::GetState(reply) => {
                             let state = self.state.read().clone();
                             let _ = reply.send(state);
@@ -207,6 +213,21 @@ impl StateActor {
         self.broadcast_state().await;
     }
     
+    /// 处理更新播放速度
+    async fn handle_update_playback_rate(&mut self, rate: f32) {
+        {
+            let mut state = self.state.write();
+            if (state.playback_rate - rate).abs() > f32::EPSILON {
+                state.playback_rate = rate;
+                log::debug!("📊 播放速度更新: {:.2}x", rate);
+            } else {
+                return;
+            }
+        }
+        
+        self.broadcast_state().await;
+    }
+    
     /// 广播状态变化
     async fn broadcast_state(&self) {
         let state = self.state.read().clone();
@@ -261,6 +282,11 @@ impl StateActorHandle {
         let _ = self.tx.send(StateMsg::UpdateShuffle(shuffle)).await;
     }
     
+    /// 更新播放速度
+    pub async fn update_playback_rate(&self, rate: f32) {
+        let _ = self.tx.send(StateMsg::UpdatePlaybackRate(rate)).await;
+    }
+    
     /// 获取当前状态
     pub fn get_state(&self) -> PlayerState {
         self.state.read().clone()
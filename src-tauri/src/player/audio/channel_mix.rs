@@ -0,0 +1,284 @@
+// 单声道和声道平衡
+//
+// 在均衡器之后插入的音频源包装（无障碍选项）：
+// - 单声道：左右声道混合后两侧输出相同内容
+// - 声道平衡：按 -1.0（全左）.. 1.0（全右）衰减另一侧，开启单声道时忽略
+// - 多于2个声道时先下混到前置左右声道，其余声道输出静音，声道数保持不变
+// 两项均为默认值时样本原样透传。设置与均衡器共用同一个watch通道，播放中修改约在一个缓冲周期内生效。
+
+use std::time::Duration;
+use tokio::sync::watch;
+use crate::audio_enhancement::AudioEnhancementSettings;
+use super::volume::soft_limit;
+
+/// 每隔多少帧检查一次设置变化
+const SETTINGS_CHECK_FRAMES: u32 = 1024;
+
+/// 中置和环绕声道下混到左右声道的系数（-3dB）
+const SURROUND_WEIGHT: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// 声道混合设置
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ChannelMix {
+    mono: bool,
+    balance: f32,
+}
+
+impl ChannelMix {
+    fn from_settings(settings: &AudioEnhancementSettings) -> Self {
+        Self {
+            mono: settings.mono,
+            balance: settings.balance.clamp(-1.0, 1.0),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        !self.mono && self.balance.abs() < 0.001
+    }
+}
+
+/// 声道平衡对应的左右增益
+pub fn balance_gains(balance: f32) -> (f32, f32) {
+    let balance = balance.clamp(-1.0, 1.0);
+    ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0))
+}
+
+/// 各声道下混到左右声道的系数（按 WAV/FLAC 声道顺序：L R C LFE 后左 后右 侧左 侧右）
+fn downmix_weights(channels: u16) -> Vec<(f32, f32)> {
+    let channels = channels as usize;
+    (0..channels)
+        .map(|index| match index {
+            0 => (1.0, 0.0),
+            1 => (0.0, 1.0),
+            // 四声道没有中置，后两个为后左、后右
+            2 if channels == 4 => (SURROUND_WEIGHT, 0.0),
+            3 if channels == 4 => (0.0, SURROUND_WEIGHT),
+            2 => (SURROUND_WEIGHT, SURROUND_WEIGHT),
+            // LFE 不参与下混
+            3 if channels >= 6 => (0.0, 0.0),
+            _ => {
+                let first_surround = if channels >= 6 { 4 } else { 3 };
+                if (index - first_surround) % 2 == 0 {
+                    (SURROUND_WEIGHT, 0.0)
+                } else {
+                    (0.0, SURROUND_WEIGHT)
+                }
+            }
+        })
+        .collect()
+}
+
+/// 处理一帧：下混到左右声道，应用单声道或声道平衡，其余声道置为静音
+fn mix_frame(frame: &mut [i16], weights: &[(f32, f32)], mix: ChannelMix) {
+    let (mut left, mut right) = (0.0f32, 0.0f32);
+    for (&sample, &(to_left, to_right)) in frame.iter().zip(weights) {
+        let x = sample as f32 / 32768.0;
+        left += x * to_left;
+        right += x * to_right;
+    }
+
+    if mix.mono {
+        let mid = (left + right) * 0.5;
+        left = mid;
+        right = mid;
+    } else {
+        let (left_gain, right_gain) = balance_gains(mix.balance);
+        left *= left_gain;
+        right *= right_gain;
+    }
+
+    frame[0] = (soft_limit(left) * 32767.0) as i16;
+    frame[1] = (soft_limit(right) * 32767.0) as i16;
+    for sample in frame[2..].iter_mut() {
+        *sample = 0;
+    }
+}
+
+/// 单声道和声道平衡音频源包装
+pub struct ChannelMixSource<S> {
+    inner: S,
+    settings_rx: watch::Receiver<AudioEnhancementSettings>,
+    mix: ChannelMix,
+    weights: Vec<(f32, f32)>,
+    channels: u16,
+    /// 已处理、等待输出的一帧
+    frame: Vec<i16>,
+    frame_pos: usize,
+    /// 透传时在帧内的位置，只在帧边界切换模式
+    channel_index: usize,
+    frames_until_check: u32,
+}
+
+impl<S> ChannelMixSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    pub fn new(inner: S, mut settings_rx: watch::Receiver<AudioEnhancementSettings>) -> Self {
+        let channels = inner.channels().max(1);
+        let mix = ChannelMix::from_settings(&settings_rx.borrow_and_update());
+
+        Self {
+            inner,
+            settings_rx,
+            mix,
+            weights: downmix_weights(channels),
+            channels,
+            frame: Vec::with_capacity(channels as usize),
+            frame_pos: 0,
+            channel_index: 0,
+            frames_until_check: SETTINGS_CHECK_FRAMES,
+        }
+    }
+
+    /// 单声道音源和默认设置时透传
+    fn is_active(&self) -> bool {
+        self.channels >= 2 && !self.mix.is_default()
+    }
+
+    fn refresh_mix(&mut self) {
+        if !self.settings_rx.has_changed().unwrap_or(false) {
+            return;
+        }
+        self.mix = ChannelMix::from_settings(&self.settings_rx.borrow_and_update());
+    }
+
+    /// 读取并处理下一帧，返回其第一个样本
+    fn next_mixed_frame(&mut self) -> Option<i16> {
+        self.frame.clear();
+        for _ in 0..self.channels {
+            match self.inner.next() {
+                Some(sample) => self.frame.push(sample),
+                None => break,
+            }
+        }
+        if self.frame.is_empty() {
+            return None;
+        }
+        // 音源末尾不完整的帧原样输出
+        if self.frame.len() == self.channels as usize {
+            mix_frame(&mut self.frame, &self.weights, self.mix);
+        }
+        self.frame_pos = 1;
+        Some(self.frame[0])
+    }
+}
+
+impl<S> Iterator for ChannelMixSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.frame_pos < self.frame.len() {
+            let sample = self.frame[self.frame_pos];
+            self.frame_pos += 1;
+            return Some(sample);
+        }
+
+        if self.channel_index == 0 {
+            self.frames_until_check -= 1;
+            if self.frames_until_check == 0 {
+                self.frames_until_check = SETTINGS_CHECK_FRAMES;
+                self.refresh_mix();
+            }
+            if self.is_active() {
+                return self.next_mixed_frame();
+            }
+        }
+
+        let sample = self.inner.next()?;
+        self.channel_index = (self.channel_index + 1) % self.channels as usize;
+        Some(sample)
+    }
+}
+
+impl<S> rodio::Source for ChannelMixSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn settings(mono: bool, balance: f32) -> AudioEnhancementSettings {
+        AudioEnhancementSettings { mono, balance, ..Default::default() }
+    }
+
+    fn run(channels: u16, input: Vec<i16>, settings: AudioEnhancementSettings) -> Vec<i16> {
+        let (_tx, rx) = watch::channel(settings);
+        ChannelMixSource::new(SamplesBuffer::new(channels, 44100, input), rx).collect()
+    }
+
+    #[test]
+    fn test_defaults_pass_through_untouched() {
+        let input: Vec<i16> = (0..600).map(|i| (i * 37 % 2000) as i16 - 1000).collect();
+        assert_eq!(run(2, input.clone(), settings(false, 0.0)), input);
+        assert_eq!(run(6, input.clone(), settings(false, 0.0)), input);
+    }
+
+    #[test]
+    fn test_mono_downmix() {
+        let output = run(2, vec![8000, 0, 0, -4000], settings(true, 0.0));
+        assert_eq!(output, vec![3999, 3999, -1999, -1999]);
+    }
+
+    #[test]
+    fn test_balance_attenuates_other_side() {
+        let output = run(2, vec![8000, 8000], settings(false, 1.0));
+        assert_eq!(output, vec![0, 7999]);
+
+        let output = run(2, vec![8000, 8000], settings(false, -0.5));
+        assert_eq!(output, vec![7999, 3999]);
+    }
+
+    #[test]
+    fn test_surround_downmixed_to_front_pair() {
+        // 5.1：L R C LFE 后左 后右
+        let output = run(6, vec![4000, 0, 4000, 8000, 0, 4000], settings(false, 0.5));
+        let left = 4000.0 + 4000.0 * SURROUND_WEIGHT;
+        let right = 4000.0 * SURROUND_WEIGHT * 2.0;
+        assert!((output[0] as f32 - left * 0.5).abs() < 2.0, "{:?}", output);
+        assert!((output[1] as f32 - right).abs() < 2.0, "{:?}", output);
+        assert_eq!(&output[2..], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_settings_change_applies_while_playing() {
+        let frames = SETTINGS_CHECK_FRAMES as usize * 2;
+        let input: Vec<i16> = [8000, 0].repeat(frames);
+        let (tx, rx) = watch::channel(settings(false, 0.0));
+        let source = ChannelMixSource::new(SamplesBuffer::new(2, 44100, input), rx);
+
+        tx.send(settings(true, 0.0)).unwrap();
+        let output: Vec<i16> = source.collect();
+        // 下一次检查前的帧保持原样，之后两侧相同
+        assert_eq!(&output[..2], &[8000, 0]);
+        assert_eq!(&output[output.len() - 2..], &[3999, 3999]);
+    }
+
+    #[test]
+    fn test_balance_gains() {
+        assert_eq!(balance_gains(0.0), (1.0, 1.0));
+        assert_eq!(balance_gains(-1.0), (1.0, 0.0));
+        assert_eq!(balance_gains(0.25), (0.75, 1.0));
+    }
+}
@@ -8,8 +8,9 @@
 
 use rodio::Decoder;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Cursor};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use super::super::types::{PlayerError, Result};
 
 /// 支持的音频格式
@@ -96,6 +97,16 @@ impl AudioDecoder {
         Ok(decoder)
     }
     
+    /// 从内存数据解码（预加载缓存命中时使用，避免再次读盘）
+    pub fn decode_from_memory(&self, data: Arc<Vec<u8>>) -> Result<Decoder<Cursor<SharedBytes>>> {
+        log::debug!("🎵 从内存解码: {:?} ({} 字节)", self.path, data.len());
+        
+        Decoder::new(Cursor::new(SharedBytes(data)))
+            .map_err(|e| PlayerError::decode_error(
+                format!("内存解码失败: {:?} - {}", self.path, e)
+            ))
+    }
+    
     /// 获取文件路径 - 调试和日志使用
     #[allow(dead_code)]  // 调试工具方法，保留
     pub fn path(&self) -> &Path {
@@ -133,6 +144,16 @@ impl AudioDecoder {
     }
 }
 
+/// 共享的音频字节（Arc包装，解码时不拷贝预加载缓存）
+#[derive(Clone)]
+pub struct SharedBytes(pub Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
 impl Clone for AudioDecoder {
     fn clone(&self) -> Self {
         Self {
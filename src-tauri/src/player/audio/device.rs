@@ -4,19 +4,172 @@
 // - 懒加载音频设备（启动时不初始化，首次播放时才初始化）
 // - 超时保护（3秒超时，避免无限卡死）
 // - 自动故障恢复
+// - 指定输出设备（设备不存在时回退到默认设备）
+// - 按音源原始采样率打开设备（设备不支持时选最接近的采样率，失败时回退到共享模式默认格式）
 
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{SupportedStreamConfig, SupportedStreamConfigRange};
 use rodio::{OutputStream, OutputStreamHandle};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::OnceCell;
 use tokio::time::timeout;
 use super::super::types::{PlayerError, Result};
 
+/// 无法获取默认设备名称时使用的名称
+const DEFAULT_DEVICE_LABEL: &str = "默认设备";
+
+/// 请求独占模式时的说明：cpal 只提供共享模式的 WASAPI 输出
+#[cfg(windows)]
+const EXCLUSIVE_UNSUPPORTED: &str = "当前音频后端不支持WASAPI独占模式，已使用共享模式";
+#[cfg(not(windows))]
+const EXCLUSIVE_UNSUPPORTED: &str = "独占模式仅在Windows上可用，已使用共享模式";
+
+/// 输出配置（用户设置）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// 按音源的原始采样率打开设备，避免系统混音器重采样
+    pub native_sample_rate: bool,
+    /// 请求WASAPI独占模式（仅Windows）
+    pub exclusive: bool,
+}
+
+/// 打开设备时请求的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputRequest {
+    /// 请求的采样率(Hz)，None为设备默认格式
+    pub sample_rate: Option<u32>,
+    pub exclusive: bool,
+}
+
+/// 实际协商得到的输出格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutputFormat {
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 采样格式（如 i16、f32）
+    pub sample_format: String,
+    pub bits_per_sample: u16,
+    /// 是否以独占模式打开
+    pub exclusive: bool,
+    pub requested_sample_rate: Option<u32>,
+    /// 未能按请求打开时的原因（已回退到共享模式或最接近的采样率）
+    pub fallback_reason: Option<String>,
+}
+
+impl OutputFormat {
+    fn new(device_name: &str, config: &SupportedStreamConfig, request: OutputRequest, notes: Vec<String>) -> Self {
+        Self {
+            device_name: device_name.to_string(),
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            sample_format: config.sample_format().to_string(),
+            bits_per_sample: (config.sample_format().sample_size() * 8) as u16,
+            exclusive: false,
+            requested_sample_rate: request.sample_rate,
+            fallback_reason: (!notes.is_empty()).then(|| notes.join("；")),
+        }
+    }
+}
+
+/// 从设备支持的配置中选出最接近目标采样率的配置
+///
+/// 依次比较：采样率差距、声道数是否为目标声道数、位深（越高越好）
+pub fn choose_stream_config(ranges: &[SupportedStreamConfigRange], sample_rate: u32, channels: u16) -> Option<SupportedStreamConfig> {
+    ranges.iter()
+        .map(|range| {
+            let rate = sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+            (range, rate)
+        })
+        .min_by_key(|(range, rate)| (
+            rate.abs_diff(sample_rate),
+            range.channels() != channels,
+            std::cmp::Reverse(range.sample_format().sample_size()),
+        ))
+        .map(|(range, rate)| range.with_sample_rate(cpal::SampleRate(rate)))
+}
+
+/// 输出设备信息（设备列表）
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    /// 支持的最大声道数
+    pub max_channels: u16,
+    /// 支持的采样率范围(Hz)
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    /// 支持的采样格式（如 i16、f32）
+    pub sample_formats: Vec<String>,
+}
+
+/// 列出所有输出设备及其能力
+pub fn list_output_devices() -> Result<Vec<OutputDeviceInfo>> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|d| d.name().ok());
+    let devices = host.output_devices()
+        .map_err(|e| PlayerError::device_error(format!("无法枚举输出设备: {}", e)))?;
+    
+    let mut result = Vec::new();
+    for device in devices {
+        let name = match device.name() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let mut info = OutputDeviceInfo {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            name,
+            max_channels: 0,
+            min_sample_rate: 0,
+            max_sample_rate: 0,
+            sample_formats: Vec::new(),
+        };
+        if let Ok(configs) = device.supported_output_configs() {
+            for config in configs {
+                info.max_channels = info.max_channels.max(config.channels());
+                let min_rate = config.min_sample_rate().0;
+                if info.min_sample_rate == 0 || min_rate < info.min_sample_rate {
+                    info.min_sample_rate = min_rate;
+                }
+                info.max_sample_rate = info.max_sample_rate.max(config.max_sample_rate().0);
+                let format = config.sample_format().to_string();
+                if !info.sample_formats.contains(&format) {
+                    info.sample_formats.push(format);
+                }
+            }
+        }
+        result.push(info);
+    }
+    Ok(result)
+}
+
+/// 已打开的输出设备是否已断开
+///
+/// 枚举失败或无法确定设备名称时不视为断开，避免误判后反复重建
+pub fn is_device_lost(name: &str) -> bool {
+    if name == DEFAULT_DEVICE_LABEL {
+        return false;
+    }
+    match cpal::default_host().output_devices() {
+        Ok(mut devices) => !devices.any(|d| d.name().is_ok_and(|n| n == name)),
+        Err(e) => {
+            log::debug!("枚举输出设备失败，跳过断开检测: {}", e);
+            false
+        }
+    }
+}
+
 /// 音频设备（封装OutputStream和Handle）
 pub struct AudioDevice {
     #[allow(dead_code)]
     pub stream: OutputStream,
     pub handle: OutputStreamHandle,
+    /// 实际打开的设备名称
+    pub name: String,
+    /// 实际协商得到的输出格式
+    pub format: OutputFormat,
 }
 
 impl AudioDevice {
@@ -28,9 +181,123 @@ impl AudioDevice {
             .map_err(|e| PlayerError::device_error(
                 format!("无法打开默认音频设备: {}", e)
             ))?;
+        let device = cpal::default_host().default_output_device();
+        let name = device.as_ref()
+            .and_then(|d| d.name().ok())
+            .unwrap_or_else(|| DEFAULT_DEVICE_LABEL.to_string());
+        let format = Self::default_format(device.as_ref(), &name);
         
-        log::info!("✅ 音频设备初始化成功");
-        Ok(Self { stream, handle })
+        log::info!("✅ 音频设备初始化成功: {}", name);
+        Ok(Self { stream, handle, name, format })
+    }
+    
+    /// 打开指定名称的输出设备，找不到或打开失败时回退到默认设备
+    pub fn open(device_name: Option<&str>) -> Result<Self> {
+        Self::open_with(device_name, OutputRequest::default())
+    }
+    
+    /// 按请求的格式打开输出设备
+    ///
+    /// 请求的采样率不受支持时使用最接近的采样率；无法按该格式打开时回退到共享模式默认格式，
+    /// 原因记录在 `format.fallback_reason`
+    pub fn open_with(device_name: Option<&str>, request: OutputRequest) -> Result<Self> {
+        let mut notes = Vec::new();
+        if request.exclusive {
+            notes.push(EXCLUSIVE_UNSUPPORTED.to_string());
+        }
+        
+        let host = cpal::default_host();
+        let named = device_name.and_then(|name| {
+            let device = host.output_devices().ok()
+                .and_then(|mut devices| devices.find(|d| d.name().is_ok_and(|n| n == name)));
+            if device.is_none() {
+                log::warn!("⚠️ 未找到输出设备 \"{}\"，回退到默认设备", name);
+            }
+            device
+        });
+        
+        if let Some(sample_rate) = request.sample_rate {
+            if let Some(device) = named.clone().or_else(|| host.default_output_device()) {
+                let name = device.name().unwrap_or_else(|_| DEFAULT_DEVICE_LABEL.to_string());
+                match Self::open_at_rate(&device, &name, sample_rate, request, &mut notes) {
+                    Some(device) => return Ok(device),
+                    None => log::warn!("⚠️ 无法以 {} Hz 打开输出设备 \"{}\"，回退到共享模式默认格式", sample_rate, name),
+                }
+            }
+        }
+        
+        let mut opened = match named {
+            Some(device) => {
+                let name = device.name().unwrap_or_else(|_| DEFAULT_DEVICE_LABEL.to_string());
+                log::info!("🎵 初始化音频设备: {}", name);
+                match OutputStream::try_from_device(&device) {
+                    Ok((stream, handle)) => {
+                        log::info!("✅ 音频设备初始化成功: {}", name);
+                        let format = Self::default_format(Some(&device), &name);
+                        Self { stream, handle, name, format }
+                    }
+                    Err(e) => {
+                        log::warn!("⚠️ 无法打开输出设备 \"{}\": {}，回退到默认设备", name, e);
+                        Self::try_default()?
+                    }
+                }
+            }
+            None => Self::try_default()?,
+        };
+        opened.format.requested_sample_rate = request.sample_rate;
+        if !notes.is_empty() {
+            opened.format.fallback_reason = Some(notes.join("；"));
+        }
+        Ok(opened)
+    }
+    
+    /// 以最接近请求采样率的配置打开设备，失败时记录原因并返回None
+    fn open_at_rate(device: &cpal::Device, name: &str, sample_rate: u32, request: OutputRequest, notes: &mut Vec<String>) -> Option<Self> {
+        let ranges: Vec<SupportedStreamConfigRange> = match device.supported_output_configs() {
+            Ok(configs) => configs.collect(),
+            Err(e) => {
+                notes.push(format!("无法查询设备支持的格式: {}", e));
+                return None;
+            }
+        };
+        let channels = device.default_output_config().map(|c| c.channels()).unwrap_or(2);
+        let Some(config) = choose_stream_config(&ranges, sample_rate, channels) else {
+            notes.push("设备没有可用的输出格式".to_string());
+            return None;
+        };
+        
+        let actual_rate = config.sample_rate().0;
+        match OutputStream::try_from_device_config(device, config.clone()) {
+            Ok((stream, handle)) => {
+                if actual_rate != sample_rate {
+                    notes.push(format!("设备不支持 {} Hz，使用最接近的 {} Hz", sample_rate, actual_rate));
+                }
+                log::info!("✅ 音频设备初始化成功: {} ({} Hz, {})", name, actual_rate, config.sample_format());
+                let format = OutputFormat::new(name, &config, request, std::mem::take(notes));
+                Some(Self { stream, handle, name: name.to_string(), format })
+            }
+            Err(e) => {
+                notes.push(format!("无法以 {} Hz 打开设备: {}", actual_rate, e));
+                None
+            }
+        }
+    }
+    
+    /// 按设备默认配置打开时的格式（共享模式，由系统混音器决定）
+    fn default_format(device: Option<&cpal::Device>, name: &str) -> OutputFormat {
+        match device.and_then(|d| d.default_output_config().ok()) {
+            Some(config) => OutputFormat::new(name, &config, OutputRequest::default(), Vec::new()),
+            None => OutputFormat {
+                device_name: name.to_string(),
+                sample_rate: 0,
+                channels: 0,
+                sample_format: String::new(),
+                bits_per_sample: 0,
+                exclusive: false,
+                requested_sample_rate: None,
+                fallback_reason: None,
+            },
+        }
     }
     
     /// 获取音频输出句柄
@@ -49,6 +316,10 @@ impl AudioDevice {
 pub struct LazyAudioDevice {
     inner: Arc<OnceCell<AudioDevice>>,
     timeout_duration: Duration,
+    /// 指定的输出设备名称，None为系统默认设备
+    device_name: Option<String>,
+    /// 请求的输出格式
+    request: OutputRequest,
 }
 
 impl LazyAudioDevice {
@@ -63,9 +334,23 @@ impl LazyAudioDevice {
         Self {
             inner: Arc::new(OnceCell::new()),
             timeout_duration,
+            device_name: None,
+            request: OutputRequest::default(),
         }
     }
     
+    /// 指定输出设备（None为系统默认设备）
+    pub fn with_device_name(mut self, device_name: Option<String>) -> Self {
+        self.device_name = device_name;
+        self
+    }
+    
+    /// 指定请求的输出格式
+    pub fn with_output_request(mut self, request: OutputRequest) -> Self {
+        self.request = request;
+        self
+    }
+    
     /// 创建默认配置（3秒超时）
     pub fn default() -> Self {
         Self::new(Duration::from_secs(3))
@@ -82,7 +367,7 @@ impl LazyAudioDevice {
             log::info!("🎵 首次访问音频设备，开始初始化");
             
             // 使用超时保护执行初始化
-            match timeout(self.timeout_duration, Self::init_device()).await {
+            match timeout(self.timeout_duration, Self::init_device(self.device_name.as_deref(), self.request)).await {
                 Ok(Ok(device)) => {
                     log::info!("✅ 音频设备初始化成功（耗时 < {}秒）", 
                         self.timeout_duration.as_secs());
@@ -106,9 +391,9 @@ impl LazyAudioDevice {
     /// 执行实际的设备初始化
     /// 
     /// 注意：直接在当前线程中执行，因为AudioDevice包含裸指针无法跨线程传递
-    async fn init_device() -> Result<AudioDevice> {
+    async fn init_device(device_name: Option<&str>, request: OutputRequest) -> Result<AudioDevice> {
         // 直接调用，不使用spawn_blocking
-        AudioDevice::try_default()
+        AudioDevice::open_with(device_name, request)
     }
     
     /// 检查设备是否已初始化
@@ -123,6 +408,8 @@ impl Clone for LazyAudioDevice {
         Self {
             inner: Arc::clone(&self.inner),
             timeout_duration: self.timeout_duration,
+            device_name: self.device_name.clone(),
+            request: self.request,
         }
     }
 }
@@ -147,6 +434,40 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_missing_device_falls_back_to_default() {
+        // 没有音频设备的环境下两者都会失败
+        let named = AudioDevice::open(Some("不存在的输出设备"));
+        let default = AudioDevice::try_default();
+        assert_eq!(named.is_ok(), default.is_ok());
+    }
+    
+    #[test]
+    fn test_choose_stream_config_prefers_closest_rate() {
+        use cpal::{SampleFormat, SampleRate, SupportedBufferSize};
+        let range = |channels, min, max, format| SupportedStreamConfigRange::new(
+            channels, SampleRate(min), SampleRate(max), SupportedBufferSize::Unknown, format,
+        );
+        let ranges = vec![
+            range(2, 44_100, 48_000, SampleFormat::I16),
+            range(2, 44_100, 192_000, SampleFormat::F32),
+            range(8, 44_100, 192_000, SampleFormat::F32),
+        ];
+        
+        let config = choose_stream_config(&ranges, 96_000, 2).unwrap();
+        assert_eq!(config.sample_rate().0, 96_000);
+        assert_eq!(config.channels(), 2);
+        
+        // 多个配置都支持时选位深更高的格式
+        let config = choose_stream_config(&ranges, 44_100, 2).unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::F32);
+        
+        // 不支持时选最接近的采样率
+        let config = choose_stream_config(&ranges[..1], 96_000, 2).unwrap();
+        assert_eq!(config.sample_rate().0, 48_000);
+        assert!(choose_stream_config(&[], 44_100, 2).is_none());
+    }
+    
     #[tokio::test]
     async fn test_timeout_protection() {
         let device = LazyAudioDevice::new(Duration::from_millis(1)); // 1ms超时
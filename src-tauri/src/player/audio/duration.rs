@@ -0,0 +1,187 @@
+// 曲目时长计算
+//
+// 标签里没有时长（缺少Xing头的VBR MP3、远程扫描时跳过了元数据的文件等）时补算：
+// - 容器头中记录了总帧数时直接换算
+// - 否则逐个读取数据包累加时长，只解析不解码；数据包没有时长时才解码该包
+// - 远程文件尚未缓存时按 比特率 × 文件大小 估算
+
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+
+/// 实际时长与记录的时长相差超过该值才更正(ms)
+pub const CORRECTION_TOLERANCE_MS: u64 = 1000;
+
+/// 逐包累加时每处理这么多个数据包检查一次取消标志
+const STOP_CHECK_PACKETS: u64 = 1024;
+
+/// 记录的时长是否需要更正（缺失、为0或与实际时长相差过大）
+pub fn needs_correction(stored_ms: Option<i64>, actual_ms: u64) -> bool {
+    if actual_ms == 0 {
+        return false;
+    }
+    match stored_ms {
+        Some(stored) if stored > 0 => (stored as u64).abs_diff(actual_ms) > CORRECTION_TOLERANCE_MS,
+        _ => true,
+    }
+}
+
+/// 按平均比特率和文件大小估算时长(ms)
+pub fn estimate_from_bitrate(size_bytes: u64, bitrate_kbps: u32) -> Option<u64> {
+    if size_bytes == 0 || bitrate_kbps == 0 {
+        return None;
+    }
+    // 字节数 × 8 / (kbps × 1000) 秒 = 字节数 × 8 / kbps 毫秒
+    Some(size_bytes * 8 / bitrate_kbps as u64)
+}
+
+/// 完整解码后的交错样本数换算为时长(ms)
+pub fn from_samples(samples: usize, channels: u16, sample_rate: u32) -> Option<u64> {
+    if channels == 0 || sample_rate == 0 {
+        return None;
+    }
+    let frames = samples as u64 / channels as u64;
+    Some(frames * 1000 / sample_rate as u64)
+}
+
+fn frames_to_ms(frames: u64, time_base: Option<TimeBase>, sample_rate: Option<u32>) -> Option<u64> {
+    match (time_base, sample_rate) {
+        (Some(tb), _) => {
+            let time = tb.calc_time(frames);
+            Some(time.seconds * 1000 + (time.frac * 1000.0) as u64)
+        }
+        (None, Some(rate)) if rate > 0 => Some(frames * 1000 / rate as u64),
+        _ => None,
+    }
+}
+
+/// 计算本地音频文件的时长(ms)，should_stop 返回 true 时中止并返回 None
+pub fn probe_file(path: &Path, should_stop: &dyn Fn() -> bool) -> anyhow::Result<Option<u64>> {
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())?;
+    let mut format = probed.format;
+    let track = format.tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("没有找到有效音轨"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    // 容器头中有总帧数（FLAC、WAV、带Xing头的MP3等）
+    if let Some(n_frames) = params.n_frames.filter(|&n| n > 0) {
+        return Ok(frames_to_ms(n_frames, params.time_base, params.sample_rate));
+    }
+
+    let mut decoder = None;
+    let mut total_frames = 0u64;
+    let mut packets = 0u64;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(e.into()),
+        };
+        packets += 1;
+        if packets % STOP_CHECK_PACKETS == 0 && should_stop() {
+            return Ok(None);
+        }
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        if packet.dur > 0 {
+            total_frames += packet.dur;
+            continue;
+        }
+        // 数据包没有记录时长，解码后按帧数计算
+        if decoder.is_none() {
+            decoder = Some(symphonia::default::get_codecs().make(&params, &DecoderOptions::default())?);
+        }
+        if let Some(decoder) = &mut decoder {
+            match decoder.decode(&packet) {
+                Ok(decoded) => total_frames += decoded.frames() as u64,
+                // 个别损坏的数据包不影响总时长
+                Err(SymphoniaError::DecodeError(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    if total_frames == 0 {
+        return Ok(None);
+    }
+    Ok(frames_to_ms(total_frames, params.time_base, params.sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成指定时长的16位单声道WAV文件
+    fn write_wav(path: &Path, sample_rate: u32, frames: u32) {
+        let data_len = frames * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        bytes.resize(bytes.len() + data_len as usize, 0);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_needs_correction() {
+        assert!(needs_correction(None, 180_000));
+        assert!(needs_correction(Some(0), 180_000));
+        assert!(needs_correction(Some(120_000), 180_000));
+        assert!(!needs_correction(Some(179_500), 180_000));
+        // 算不出时长时保留原值
+        assert!(!needs_correction(None, 0));
+    }
+
+    #[test]
+    fn test_estimate_from_bitrate() {
+        // 320kbps 的 4MB 文件约 104.8 秒
+        assert_eq!(estimate_from_bitrate(4 * 1024 * 1024, 320), Some(104_857));
+        assert_eq!(estimate_from_bitrate(0, 320), None);
+        assert_eq!(estimate_from_bitrate(1024, 0), None);
+    }
+
+    #[test]
+    fn test_from_samples() {
+        assert_eq!(from_samples(44_100 * 2 * 3, 2, 44_100), Some(3000));
+        assert_eq!(from_samples(100, 0, 44_100), None);
+    }
+
+    #[test]
+    fn test_probe_wav_duration() {
+        let path = std::env::temp_dir().join(format!("windchime-duration-{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&path, 8000, 8000 * 5 / 2);
+
+        let duration = probe_file(&path, &|| false);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(duration.unwrap(), Some(2500));
+    }
+}
@@ -0,0 +1,449 @@
+// 均衡器DSP模块
+//
+// 在解码器输出和Sink之间插入的音频源包装：
+// - 10段均衡器：峰值(peaking)双二阶滤波器
+// - 低音增强：低架(low-shelf)双二阶滤波器
+// - 耳机频响校正：导入的参数均衡滤波器（峰值、低架、高架），先于图示均衡器应用
+// - 前置增益（ReplayGain × 前置放大）在滤波前以浮点应用，输出经过软限幅，叠加的提升不会削波
+// 设置通过watch通道实时下发，播放中修改无需重新开始曲目。
+
+use std::f32::consts::PI;
+use std::time::Duration;
+use tokio::sync::watch;
+use crate::audio_enhancement::AudioEnhancementSettings;
+use super::parametric_eq::{FilterKind, ParametricFilter};
+use super::volume::soft_limit;
+
+/// 10段均衡器中心频率（Hz），与 EqualizerSettings.gains 顺序一致
+pub const EQ_BAND_FREQUENCIES: [f32; 10] = [
+    32.0, 64.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// 增益范围（dB），与设置命令的校验一致
+pub const MIN_GAIN_DB: f32 = -12.0;
+pub const MAX_GAIN_DB: f32 = 12.0;
+
+/// 倍频程频段的Q值
+const BAND_Q: f32 = 1.41;
+
+/// 低架滤波器斜率
+const SHELF_SLOPE: f32 = 1.0;
+
+/// 每隔多少帧检查一次设置变化
+const SETTINGS_CHECK_FRAMES: u32 = 1024;
+
+/// 双二阶滤波器系数（已按a0归一化）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl BiquadCoefficients {
+    /// 峰值滤波器（RBJ Audio EQ Cookbook）
+    pub fn peaking(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+
+    /// 纯增益（用于校正配置的前置放大）
+    pub fn gain(gain_db: f32) -> Self {
+        Self {
+            b0: 10f32.powf(gain_db / 20.0),
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        }
+    }
+
+    /// 低架滤波器（RBJ Audio EQ Cookbook）
+    pub fn low_shelf(sample_rate: f32, freq: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / 2.0 * ((a + 1.0 / a) * (1.0 / SHELF_SLOPE - 1.0) + 2.0).sqrt();
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let a0 = (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        Self {
+            b0: a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha) / a0,
+            b1: 2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0) / a0,
+            b2: a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+            a1: -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0) / a0,
+            a2: ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+        }
+    }
+
+    /// 按Q值指定的低架滤波器
+    pub fn low_shelf_q(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let cos_w0 = w0.cos();
+        let sqrt_a_alpha = 2.0 * a.sqrt() * w0.sin() / (2.0 * q);
+
+        let a0 = (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        Self {
+            b0: a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha) / a0,
+            b1: 2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0) / a0,
+            b2: a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+            a1: -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0) / a0,
+            a2: ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+        }
+    }
+
+    /// 按Q值指定的高架滤波器（RBJ Audio EQ Cookbook）
+    pub fn high_shelf(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let cos_w0 = w0.cos();
+        let sqrt_a_alpha = 2.0 * a.sqrt() * w0.sin() / (2.0 * q);
+
+        let a0 = (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        Self {
+            b0: a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha) / a0,
+            b1: -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0) / a0,
+            b2: a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+            a1: 2.0 * ((a - 1.0) - (a + 1.0) * cos_w0) / a0,
+            a2: ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha) / a0,
+        }
+    }
+
+    /// 参数均衡滤波器
+    pub fn parametric(sample_rate: f32, filter: &ParametricFilter) -> Self {
+        match filter.kind {
+            FilterKind::Peaking => Self::peaking(sample_rate, filter.frequency, filter.q, filter.gain_db),
+            FilterKind::LowShelf => Self::low_shelf_q(sample_rate, filter.frequency, filter.q, filter.gain_db),
+            FilterKind::HighShelf => Self::high_shelf(sample_rate, filter.frequency, filter.q, filter.gain_db),
+        }
+    }
+}
+
+/// 单个声道的滤波器状态（直接II型转置）
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    z1: f32,
+    z2: f32,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, c: &BiquadCoefficients, x: f32) -> f32 {
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y
+    }
+}
+
+/// 根据设置生成滤波器组，不需要处理时返回空列表
+pub fn build_filters(settings: &AudioEnhancementSettings, sample_rate: u32) -> Vec<BiquadCoefficients> {
+    let mut filters = Vec::new();
+    if sample_rate == 0 {
+        return filters;
+    }
+
+    let sr = sample_rate as f32;
+    // 超过奈奎斯特频率的频段无法实现
+    let max_freq = sr * 0.45;
+
+    // 耳机校正独立于总开关
+    if let (true, Some(profile)) = (settings.correction.enabled, &settings.correction.profile) {
+        if profile.preamp_db.abs() > 0.01 {
+            filters.push(BiquadCoefficients::gain(profile.preamp_db));
+        }
+        filters.extend(profile.filters.iter()
+            .filter(|f| f.frequency < max_freq && f.gain_db.abs() > 0.01)
+            .map(|f| BiquadCoefficients::parametric(sr, f)));
+    }
+
+    if !settings.enabled {
+        return filters;
+    }
+
+    if settings.bass_boost.enabled {
+        let gain = settings.bass_boost.gain.clamp(0.0, MAX_GAIN_DB);
+        let cutoff = (settings.bass_boost.cutoff_frequency as f32).clamp(20.0, 250.0);
+        if gain.abs() > 0.01 {
+            filters.push(BiquadCoefficients::low_shelf(sr, cutoff, gain));
+        }
+    }
+
+    if settings.equalizer.enabled {
+        for (freq, gain) in EQ_BAND_FREQUENCIES.iter().zip(settings.equalizer.gains.iter()) {
+            let gain = gain.clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+            // 0dB频段不参与计算
+            if gain.abs() > 0.01 && *freq < max_freq {
+                filters.push(BiquadCoefficients::peaking(sr, *freq, BAND_Q, gain));
+            }
+        }
+    }
+
+    filters
+}
+
+/// 均衡器音频源包装
+pub struct EqualizerSource<S> {
+    inner: S,
+    settings_rx: watch::Receiver<AudioEnhancementSettings>,
+    filters: Vec<BiquadCoefficients>,
+    /// 滤波前的线性增益
+    pre_gain: f32,
+    /// 每个声道一组滤波器状态
+    states: Vec<Vec<BiquadState>>,
+    channels: u16,
+    sample_rate: u32,
+    channel_index: usize,
+    frames_until_check: u32,
+}
+
+impl<S> EqualizerSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    pub fn new(inner: S, mut settings_rx: watch::Receiver<AudioEnhancementSettings>) -> Self {
+        let channels = inner.channels().max(1);
+        let sample_rate = inner.sample_rate();
+        let filters = build_filters(&settings_rx.borrow_and_update(), sample_rate);
+        let states = vec![vec![BiquadState::default(); filters.len()]; channels as usize];
+
+        Self {
+            inner,
+            settings_rx,
+            filters,
+            pre_gain: 1.0,
+            states,
+            channels,
+            sample_rate,
+            channel_index: 0,
+            frames_until_check: SETTINGS_CHECK_FRAMES,
+        }
+    }
+
+    /// 设置滤波前的线性增益（ReplayGain、前置放大）
+    pub fn with_pre_gain(mut self, gain: f32) -> Self {
+        self.pre_gain = gain;
+        self
+    }
+
+    /// 设置变化时重建系数；滤波器数量不变时保留状态避免爆音
+    fn refresh_filters(&mut self) {
+        if !self.settings_rx.has_changed().unwrap_or(false) {
+            return;
+        }
+        let filters = build_filters(&self.settings_rx.borrow_and_update(), self.sample_rate);
+        if filters.len() != self.filters.len() {
+            self.states = vec![vec![BiquadState::default(); filters.len()]; self.channels as usize];
+        }
+        self.filters = filters;
+    }
+}
+
+impl<S> Iterator for EqualizerSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.channel_index == 0 {
+            self.frames_until_check -= 1;
+            if self.frames_until_check == 0 {
+                self.frames_until_check = SETTINGS_CHECK_FRAMES;
+                self.refresh_filters();
+            }
+        }
+
+        let sample = self.inner.next()?;
+        let channel = self.channel_index;
+        self.channel_index = (self.channel_index + 1) % self.channels as usize;
+
+        // 未启用且没有增益时原样输出
+        if self.filters.is_empty() && self.pre_gain == 1.0 {
+            return Some(sample);
+        }
+
+        let mut x = sample as f32 / 32768.0 * self.pre_gain;
+        let states = &mut self.states[channel];
+        for (state, coeffs) in states.iter_mut().zip(self.filters.iter()) {
+            x = state.process(coeffs, x);
+        }
+        Some((soft_limit(x) * 32767.0) as i16)
+    }
+}
+
+impl<S> rodio::Source for EqualizerSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parametric_eq::CorrectionProfile;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(freq: f32, sample_rate: u32, len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| ((2.0 * PI * freq * i as f32 / sample_rate as f32).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    fn rms(samples: &[i16]) -> f32 {
+        let sum: f32 = samples.iter().map(|&s| (s as f32).powi(2)).sum();
+        (sum / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_disabled_passes_through_untouched() {
+        let input = sine(1000.0, 44100, 4096);
+        let (_tx, rx) = watch::channel(AudioEnhancementSettings::default());
+        let source = EqualizerSource::new(SamplesBuffer::new(1, 44100, input.clone()), rx);
+
+        assert!(source.filters.is_empty());
+        let output: Vec<i16> = source.collect();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_band_boost_raises_level() {
+        let input = sine(1000.0, 44100, 44100);
+        let mut settings = AudioEnhancementSettings::default();
+        settings.enabled = true;
+        settings.equalizer.enabled = true;
+        settings.equalizer.gains[5] = 6.0; // 1kHz
+
+        let (_tx, rx) = watch::channel(settings);
+        let output: Vec<i16> = EqualizerSource::new(SamplesBuffer::new(1, 44100, input.clone()), rx).collect();
+
+        // 跳过滤波器起振阶段后比较，+6dB约为2倍幅度
+        let ratio = rms(&output[4410..]) / rms(&input[4410..]);
+        assert!(ratio > 1.8 && ratio < 2.2, "ratio = {}", ratio);
+    }
+
+    #[test]
+    fn test_gains_are_clamped() {
+        let mut settings = AudioEnhancementSettings::default();
+        settings.enabled = true;
+        settings.equalizer.enabled = true;
+        settings.equalizer.gains[5] = 40.0;
+
+        let filters = build_filters(&settings, 44100);
+        assert_eq!(filters, vec![BiquadCoefficients::peaking(44100.0, 1000.0, BAND_Q, MAX_GAIN_DB)]);
+    }
+
+    #[test]
+    fn test_pre_gain_is_soft_limited() {
+        let input = sine(1000.0, 44100, 4410);
+        let (_tx, rx) = watch::channel(AudioEnhancementSettings::default());
+        // +12dB 使峰值超过满幅
+        let output: Vec<i16> = EqualizerSource::new(SamplesBuffer::new(1, 44100, input.clone()), rx)
+            .with_pre_gain(4.0)
+            .collect();
+
+        let peak = output.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak < i16::MAX as u16, "peak = {}", peak);
+        // 低于 -1 dBFS 的样本按增益原样放大
+        assert!((output[1] - input[1] * 4).abs() <= 1);
+    }
+
+    fn correction_settings(text: &str) -> AudioEnhancementSettings {
+        let mut settings = AudioEnhancementSettings::default();
+        settings.correction.enabled = true;
+        settings.correction.profile = Some(CorrectionProfile::parse("Test", text).unwrap());
+        settings
+    }
+
+    #[test]
+    fn test_correction_filters_apply_without_master_switch() {
+        let settings = correction_settings("Preamp: -6 dB\nFilter 1: ON PK Fc 1000 Hz Gain 6 dB Q 1.41\n");
+        assert!(!settings.enabled);
+        assert_eq!(build_filters(&settings, 44100).len(), 2);
+
+        // 前置放大和 +6dB 峰值相互抵消
+        let input = sine(1000.0, 44100, 44100);
+        let (_tx, rx) = watch::channel(settings.clone());
+        let output: Vec<i16> = EqualizerSource::new(SamplesBuffer::new(1, 44100, input.clone()), rx).collect();
+        let ratio = rms(&output[4410..]) / rms(&input[4410..]);
+        assert!((ratio - 1.0).abs() < 0.05, "ratio = {}", ratio);
+
+        // 与图示均衡器叠加
+        let mut combined = settings.clone();
+        combined.enabled = true;
+        combined.equalizer.enabled = true;
+        combined.equalizer.gains[5] = 3.0;
+        assert_eq!(build_filters(&combined, 44100).len(), 3);
+
+        let mut disabled = settings;
+        disabled.correction.enabled = false;
+        assert!(build_filters(&disabled, 44100).is_empty());
+    }
+
+    #[test]
+    fn test_shelf_filters() {
+        let input_low = sine(50.0, 44100, 44100);
+        let input_high = sine(12000.0, 44100, 44100);
+        let filter = |text: &str, input: &[i16]| -> f32 {
+            let (_tx, rx) = watch::channel(correction_settings(text));
+            let output: Vec<i16> = EqualizerSource::new(SamplesBuffer::new(1, 44100, input.to_vec()), rx).collect();
+            rms(&output[4410..]) / rms(&input[4410..])
+        };
+
+        // 架式滤波器提升架内频率，架外基本不变
+        let low_shelf = "Filter 1: ON LSC Fc 200 Hz Gain 6 dB Q 0.7";
+        assert!(filter(low_shelf, &input_low) > 1.8);
+        assert!((filter(low_shelf, &input_high) - 1.0).abs() < 0.05);
+        let high_shelf = "Filter 1: ON HSC Fc 4000 Hz Gain -6 dB Q 0.7";
+        assert!(filter(high_shelf, &input_high) < 0.55);
+        assert!((filter(high_shelf, &input_low) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_settings_change_applies_while_playing() {
+        let input = sine(1000.0, 44100, 44100);
+        let (tx, rx) = watch::channel(AudioEnhancementSettings::default());
+        let mut source = EqualizerSource::new(SamplesBuffer::new(1, 44100, input), rx);
+        assert!(source.filters.is_empty());
+
+        let mut settings = AudioEnhancementSettings::default();
+        settings.enabled = true;
+        settings.equalizer.enabled = true;
+        settings.equalizer.gains[0] = 3.0;
+        tx.send(settings).unwrap();
+
+        for _ in 0..SETTINGS_CHECK_FRAMES {
+            source.next();
+        }
+        assert_eq!(source.filters.len(), 1);
+    }
+}
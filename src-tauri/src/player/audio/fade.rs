@@ -0,0 +1,101 @@
+// 淡入淡出模块
+//
+// 交叉淡入淡出时两个Sink同时播放，按等功率曲线调整各自音量，
+// 避免线性曲线在中点出现的响度凹陷。
+
+use std::f32::consts::FRAC_PI_2;
+use std::time::{Duration, Instant};
+
+/// 一次淡入或淡出的进度
+#[derive(Debug, Clone, Copy)]
+pub struct Fade {
+    started: Instant,
+    duration: Duration,
+}
+
+impl Fade {
+    /// 从现在开始的淡变
+    pub fn start(duration_ms: u64) -> Self {
+        Self::start_at(Instant::now(), duration_ms)
+    }
+
+    pub fn start_at(started: Instant, duration_ms: u64) -> Self {
+        Self {
+            started,
+            duration: Duration::from_millis(duration_ms),
+        }
+    }
+
+    /// 进度（0.0 - 1.0）
+    pub fn progress_at(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.started);
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress_at(Instant::now())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// 淡入增益
+    pub fn fade_in_gain(&self) -> f32 {
+        fade_in_gain(self.progress())
+    }
+
+    /// 淡出增益
+    pub fn fade_out_gain(&self) -> f32 {
+        fade_out_gain(self.progress())
+    }
+}
+
+/// 等功率淡入曲线
+pub fn fade_in_gain(progress: f32) -> f32 {
+    (progress.clamp(0.0, 1.0) * FRAC_PI_2).sin()
+}
+
+/// 等功率淡出曲线
+pub fn fade_out_gain(progress: f32) -> f32 {
+    (progress.clamp(0.0, 1.0) * FRAC_PI_2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_power_curve() {
+        assert_eq!(fade_in_gain(0.0), 0.0);
+        assert!((fade_in_gain(1.0) - 1.0).abs() < 1e-6);
+        assert!((fade_out_gain(0.0) - 1.0).abs() < 1e-6);
+        assert!(fade_out_gain(1.0).abs() < 1e-6);
+
+        // 任意时刻两路功率之和保持为1
+        for i in 0..=10 {
+            let p = i as f32 / 10.0;
+            let power = fade_in_gain(p).powi(2) + fade_out_gain(p).powi(2);
+            assert!((power - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_zero_duration_is_finished() {
+        let fade = Fade::start(0);
+        assert!(fade.is_finished());
+        assert_eq!(fade.fade_in_gain(), 1.0);
+    }
+
+    #[test]
+    fn test_progress() {
+        let start = Instant::now();
+        let fade = Fade::start_at(start, 1000);
+        assert_eq!(fade.progress_at(start), 0.0);
+        assert!((fade.progress_at(start + Duration::from_millis(500)) - 0.5).abs() < 1e-3);
+        assert_eq!(fade.progress_at(start + Duration::from_secs(5)), 1.0);
+    }
+}
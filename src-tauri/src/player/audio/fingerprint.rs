@@ -0,0 +1,366 @@
+// 音频指纹模块
+//
+// 参考 Chromaprint 的思路计算声学指纹，用于发现同一录音的不同编码版本：
+// - 下混为单声道，按区间平均重采样到 11025Hz
+// - 4096点帧、2/3重叠做FFT，把 28Hz~3520Hz 的能量折叠到12个半音（色度）
+// - 色度在时间上平滑后，按半音间、帧间的能量差生成32位子指纹
+// 比较时在一定偏移范围内对齐，取比特错误率最低的位置计算相似度。
+
+use base64::Engine;
+use std::f64::consts::PI;
+
+/// 指纹计算使用的采样率
+pub const SAMPLE_RATE: u32 = 11025;
+
+/// 只分析开头这么多秒
+pub const MAX_FINGERPRINT_SECONDS: u32 = 120;
+
+/// 判定为同一录音的默认相似度（随机内容约为0.5）
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.8;
+
+const FRAME_SIZE: usize = 4096;
+const HOP_SIZE: usize = FRAME_SIZE / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+
+/// 色度平滑使用的帧数
+const SMOOTHING_FRAMES: usize = 4;
+
+/// 低于此能量的帧视为静音
+const SILENCE_ENERGY: f64 = 1e-6;
+
+/// 有效（非静音）帧不足时不生成指纹（约2秒）
+const MIN_FRAMES: usize = 16;
+
+/// 对齐时允许的最大偏移帧数（约3秒，容忍开头静音长度不同）
+const MAX_ALIGN_OFFSET: isize = 24;
+
+/// 流式指纹计算器
+pub struct Fingerprinter {
+    channels: usize,
+    channel_index: usize,
+    frame_sum: f64,
+    /// 输入采样率 / 目标采样率
+    step: f64,
+    input_pos: f64,
+    next_output: f64,
+    resample_sum: f64,
+    resample_count: usize,
+    samples: Vec<f64>,
+    window: Vec<f64>,
+    bin_to_chroma: Vec<Option<usize>>,
+    chroma: Vec<[f64; 12]>,
+    voiced_frames: usize,
+}
+
+impl Fingerprinter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let step = sample_rate.max(1) as f64 / SAMPLE_RATE as f64;
+        let bin_to_chroma = (0..FRAME_SIZE / 2)
+            .map(|bin| {
+                let freq = bin as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+                if !(MIN_FREQ..=MAX_FREQ).contains(&freq) {
+                    return None;
+                }
+                let note = 12.0 * (freq / 440.0).log2() + 69.0;
+                Some((note.round() as i64).rem_euclid(12) as usize)
+            })
+            .collect();
+
+        Self {
+            channels: channels.max(1) as usize,
+            channel_index: 0,
+            frame_sum: 0.0,
+            step,
+            input_pos: 0.0,
+            next_output: step,
+            resample_sum: 0.0,
+            resample_count: 0,
+            samples: Vec::with_capacity(FRAME_SIZE * 2),
+            window: (0..FRAME_SIZE)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / (FRAME_SIZE - 1) as f64).cos())
+                .collect(),
+            bin_to_chroma,
+            chroma: Vec::new(),
+            voiced_frames: 0,
+        }
+    }
+
+    /// 输入一个交错排列的采样
+    pub fn push_sample(&mut self, sample: i16) {
+        self.frame_sum += sample as f64 / 32768.0;
+        self.channel_index += 1;
+        if self.channel_index < self.channels {
+            return;
+        }
+        let mono = self.frame_sum / self.channels as f64;
+        self.channel_index = 0;
+        self.frame_sum = 0.0;
+
+        // 区间平均重采样（同时起到简单的抗混叠作用）
+        self.resample_sum += mono;
+        self.resample_count += 1;
+        self.input_pos += 1.0;
+        if self.input_pos >= self.next_output {
+            let value = self.resample_sum / self.resample_count as f64;
+            while self.input_pos >= self.next_output {
+                self.samples.push(value);
+                self.next_output += self.step;
+            }
+            self.resample_sum = 0.0;
+            self.resample_count = 0;
+        }
+
+        if self.samples.len() >= FRAME_SIZE {
+            self.process_frame();
+            self.samples.drain(..HOP_SIZE);
+        }
+    }
+
+    fn process_frame(&mut self) {
+        let mut re: Vec<f64> = self.samples[..FRAME_SIZE].iter().zip(&self.window).map(|(s, w)| s * w).collect();
+        let mut im = vec![0.0; FRAME_SIZE];
+        fft(&mut re, &mut im);
+
+        let mut chroma = [0.0; 12];
+        for (bin, pitch) in self.bin_to_chroma.iter().enumerate() {
+            if let Some(pitch) = pitch {
+                chroma[*pitch] += re[bin] * re[bin] + im[bin] * im[bin];
+            }
+        }
+
+        let energy: f64 = chroma.iter().sum();
+        if energy > SILENCE_ENERGY {
+            let norm = chroma.iter().map(|c| c * c).sum::<f64>().sqrt();
+            chroma.iter_mut().for_each(|c| *c /= norm);
+            self.voiced_frames += 1;
+        } else {
+            chroma = [0.0; 12];
+        }
+        self.chroma.push(chroma);
+    }
+
+    /// 生成子指纹序列；有效帧不足（过短或静音）时返回空序列
+    pub fn finish(self) -> Vec<u32> {
+        if self.voiced_frames < MIN_FRAMES || self.chroma.len() < SMOOTHING_FRAMES + 1 {
+            return Vec::new();
+        }
+
+        let smoothed: Vec<[f64; 12]> = self.chroma
+            .windows(SMOOTHING_FRAMES)
+            .map(|frames| {
+                let mut sum = [0.0; 12];
+                for frame in frames {
+                    for (s, c) in sum.iter_mut().zip(frame) {
+                        *s += c;
+                    }
+                }
+                sum
+            })
+            .collect();
+
+        smoothed.windows(2).map(|pair| sub_fingerprint(&pair[0], &pair[1])).collect()
+    }
+}
+
+/// 32位子指纹：
+/// - 0~11位：相邻半音能量差随时间的变化（Haitsma-Kalker）
+/// - 12~23位：各半音能量是否增强
+/// - 24~31位：相隔大三度的半音能量比较
+fn sub_fingerprint(prev: &[f64; 12], cur: &[f64; 12]) -> u32 {
+    let mut bits = 0u32;
+    for b in 0..12 {
+        let next = (b + 1) % 12;
+        if (cur[b] - cur[next]) - (prev[b] - prev[next]) > 0.0 {
+            bits |= 1 << b;
+        }
+        if cur[b] > prev[b] {
+            bits |= 1 << (12 + b);
+        }
+    }
+    for b in 0..8 {
+        if cur[b] > cur[(b + 4) % 12] {
+            bits |= 1 << (24 + b);
+        }
+    }
+    bits
+}
+
+/// 原地基2 FFT（长度必须为2的幂）
+pub(super) fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// 计算音频源开头部分的指纹，should_stop 返回 true 时中止并返回 None
+pub fn compute_fingerprint<S>(source: S, should_stop: &dyn Fn() -> bool) -> Option<Vec<u32>>
+where
+    S: rodio::Source<Item = i16>,
+{
+    let limit = source.sample_rate() as usize * source.channels() as usize * MAX_FINGERPRINT_SECONDS as usize;
+    let mut fingerprinter = Fingerprinter::new(source.channels(), source.sample_rate());
+    for (i, sample) in source.take(limit).enumerate() {
+        // 每64K个采样检查一次取消标志
+        if i & 0xFFFF == 0 && should_stop() {
+            return None;
+        }
+        fingerprinter.push_sample(sample);
+    }
+    Some(fingerprinter.finish())
+}
+
+/// 两个指纹的相似度（0~1），在允许的偏移范围内取最佳对齐
+pub fn similarity(a: &[u32], b: &[u32]) -> f32 {
+    let mut best_error_rate = 1.0f32;
+    for offset in -MAX_ALIGN_OFFSET..=MAX_ALIGN_OFFSET {
+        let (a_start, b_start) = if offset >= 0 { (offset as usize, 0) } else { (0, (-offset) as usize) };
+        if a_start >= a.len() || b_start >= b.len() {
+            continue;
+        }
+        let overlap = (a.len() - a_start).min(b.len() - b_start);
+        if overlap < MIN_FRAMES {
+            continue;
+        }
+        let errors: u32 = a[a_start..a_start + overlap]
+            .iter()
+            .zip(&b[b_start..b_start + overlap])
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum();
+        best_error_rate = best_error_rate.min(errors as f32 / (overlap * 32) as f32);
+    }
+    1.0 - best_error_rate
+}
+
+/// 指纹编码为base64（小端u32序列），空指纹编码为空字符串
+pub fn encode(fingerprint: &[u32]) -> String {
+    let bytes: Vec<u8> = fingerprint.iter().flat_map(|v| v.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+pub fn decode(encoded: &str) -> Option<Vec<u32>> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).ok()?;
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    Some(bytes.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    /// 简单的和弦进行：每半秒换一个和弦，可选开头静音和噪声
+    fn progression(chords: &[[f64; 3]], sample_rate: u32, lead_in_secs: f64, gain: f64, noise: f64) -> Vec<i16> {
+        let chord_len = sample_rate as usize / 2;
+        let lead_in = (lead_in_secs * sample_rate as f64) as usize;
+        let mut seed = 12345u32;
+        let total = lead_in + chord_len * chords.len() * 4;
+        (0..total)
+            .flat_map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let jitter = (seed >> 16) as f64 / 65536.0 - 0.5;
+                let v = if i < lead_in {
+                    0.0
+                } else {
+                    let n = i - lead_in;
+                    let chord = &chords[(n / chord_len) % chords.len()];
+                    let t = n as f64 / sample_rate as f64;
+                    chord.iter().map(|f| (2.0 * PI * f * t).sin()).sum::<f64>() / 3.0 * gain + jitter * noise
+                };
+                let s = (v * 20000.0) as i16;
+                [s, s]
+            })
+            .collect()
+    }
+
+    const SONG_A: [[f64; 3]; 6] = [
+        [261.63, 329.63, 392.00],
+        [220.00, 261.63, 329.63],
+        [174.61, 220.00, 261.63],
+        [196.00, 246.94, 293.66],
+        [293.66, 369.99, 440.00],
+        [246.94, 311.13, 369.99],
+    ];
+    const SONG_B: [[f64; 3]; 6] = [
+        [277.18, 349.23, 415.30],
+        [185.00, 233.08, 277.18],
+        [207.65, 261.63, 311.13],
+        [155.56, 196.00, 233.08],
+        [233.08, 293.66, 349.23],
+        [311.13, 392.00, 466.16],
+    ];
+
+    fn fingerprint(samples: Vec<i16>, sample_rate: u32) -> Vec<u32> {
+        compute_fingerprint(SamplesBuffer::new(2, sample_rate, samples), &|| false).unwrap()
+    }
+
+    #[test]
+    fn test_same_recording_matches_across_encodings() {
+        let original = fingerprint(progression(&SONG_A, 44100, 0.0, 1.0, 0.0), 44100);
+        // 不同采样率、音量、少量噪声和开头静音
+        let reencoded = fingerprint(progression(&SONG_A, 48000, 1.0, 0.7, 0.05), 48000);
+        let other = fingerprint(progression(&SONG_B, 44100, 0.0, 1.0, 0.0), 44100);
+
+        assert!(!original.is_empty());
+        let same = similarity(&original, &reencoded);
+        let different = similarity(&original, &other);
+        assert!(same >= DEFAULT_SIMILARITY_THRESHOLD, "same recording: {}", same);
+        assert!(different < DEFAULT_SIMILARITY_THRESHOLD, "different recording: {}", different);
+        assert_eq!(similarity(&original, &original), 1.0);
+    }
+
+    #[test]
+    fn test_silence_and_short_clips_have_no_fingerprint() {
+        assert!(fingerprint(vec![0; 44100 * 2 * 10], 44100).is_empty());
+        assert!(fingerprint(progression(&SONG_A[..1], 44100, 0.0, 1.0, 0.0)[..44100].to_vec(), 44100).is_empty());
+        assert_eq!(similarity(&[], &[1, 2, 3]), 0.0);
+    }
+
+    #[test]
+    fn test_cancel_and_encoding_round_trip() {
+        let samples = progression(&SONG_A, 44100, 0.0, 1.0, 0.0);
+        assert_eq!(compute_fingerprint(SamplesBuffer::new(2, 44100, samples), &|| true), None);
+
+        let values = vec![0, 1, u32::MAX, 0xDEADBEEF];
+        assert_eq!(decode(&encode(&values)), Some(values));
+        assert_eq!(encode(&[]), "");
+        assert_eq!(decode(""), Some(Vec::new()));
+        assert_eq!(decode("AAA="), None);
+    }
+}
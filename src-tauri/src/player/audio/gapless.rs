@@ -0,0 +1,82 @@
+// 无缝播放辅助模块
+//
+// 无缝模式下下一首会提前追加到同一个Sink的队列中。
+// rodio的Sink无法移除已追加的音频源，因此用可取消的包装源：
+// 取消后该源立即结束，Sink会直接跳过它。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 可取消的音频源
+pub struct CancellableSource<S> {
+    inner: S,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<S> CancellableSource<S> {
+    /// 包装音频源，返回包装源和取消标志
+    pub fn new(inner: S) -> (Self, Arc<AtomicBool>) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        (
+            Self {
+                inner,
+                cancelled: cancelled.clone(),
+            },
+            cancelled,
+        )
+    }
+}
+
+impl<S> Iterator for CancellableSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.inner.next()
+    }
+}
+
+impl<S> rodio::Source for CancellableSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Some(0);
+        }
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn test_cancel_ends_source_immediately() {
+        let (mut source, cancelled) = CancellableSource::new(SamplesBuffer::new(2, 44100, vec![1i16; 100]));
+
+        assert_eq!(source.next(), Some(1));
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(source.next(), None);
+    }
+}
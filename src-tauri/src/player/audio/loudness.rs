@@ -0,0 +1,255 @@
+// 响度分析模块
+//
+// 按 EBU R128 / ITU-R BS.1770 计算整体响度（LUFS）：
+// - K计权：高架预滤波 + RLB高通，两级双二阶滤波器
+// - 400ms测量块，75%重叠（每100ms一块）
+// - 绝对门限 -70 LUFS，相对门限为门限内平均响度 -10 LU
+// 用于给没有ReplayGain标签的曲目计算音量平衡增益。
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// ReplayGain 2.0 参考响度（LUFS）
+pub const REFERENCE_LUFS: f32 = -18.0;
+
+/// 绝对门限（LUFS），静音曲目也按此值记录
+pub const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+
+/// 根据分析响度换算的增益上限（dB），避免安静曲目被过度放大
+pub const MAX_LOUDNESS_GAIN_DB: f32 = 12.0;
+
+/// 相对门限（LU）
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// 每个测量块包含的100ms分段数
+const SEGMENTS_PER_BLOCK: usize = 4;
+
+/// 响度对应的ReplayGain增益（dB）
+pub fn loudness_to_gain_db(loudness_lufs: f32) -> f32 {
+    (REFERENCE_LUFS - loudness_lufs).min(MAX_LOUDNESS_GAIN_DB)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z1: 0.0, z2: 0.0 }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z1;
+        self.z1 = self.b[1] * x - self.a[0] * y + self.z2;
+        self.z2 = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// K计权滤波器（任意采样率，系数推导同 libebur128）
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    // 第一级：高架滤波器，模拟头部声学效应
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // 第二级：RLB高通滤波器
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, highpass]
+}
+
+/// 声道权重（5.1布局下LFE不计入，环绕声道+1.5dB）
+fn channel_weight(channels: u16, index: usize) -> f64 {
+    if channels >= 6 {
+        match index {
+            3 => 0.0,
+            4 | 5 => 1.41,
+            _ => 1.0,
+        }
+    } else {
+        1.0
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// 整体响度测量器
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    /// 每个100ms分段的帧数
+    segment_frames: usize,
+    segment_pos: usize,
+    segment_sum: f64,
+    recent_segments: VecDeque<f64>,
+    /// 各测量块的均方能量
+    blocks: Vec<f64>,
+    channel_index: usize,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels: channels as usize,
+            filters: vec![k_weighting(sample_rate.max(1)); channels as usize],
+            weights: (0..channels as usize).map(|i| channel_weight(channels, i)).collect(),
+            segment_frames: (sample_rate as usize / 10).max(1),
+            segment_pos: 0,
+            segment_sum: 0.0,
+            recent_segments: VecDeque::with_capacity(SEGMENTS_PER_BLOCK),
+            blocks: Vec::new(),
+            channel_index: 0,
+        }
+    }
+
+    /// 输入一个交错排列的采样
+    pub fn push_sample(&mut self, sample: i16) {
+        let channel = self.channel_index;
+        let x = sample as f64 / 32768.0;
+        let [shelf, highpass] = &mut self.filters[channel];
+        let y = highpass.process(shelf.process(x));
+        self.segment_sum += self.weights[channel] * y * y;
+
+        self.channel_index += 1;
+        if self.channel_index < self.channels {
+            return;
+        }
+        self.channel_index = 0;
+
+        self.segment_pos += 1;
+        if self.segment_pos == self.segment_frames {
+            self.finish_segment();
+        }
+    }
+
+    fn finish_segment(&mut self) {
+        if self.recent_segments.len() == SEGMENTS_PER_BLOCK {
+            self.recent_segments.pop_front();
+        }
+        self.recent_segments.push_back(self.segment_sum / self.segment_frames as f64);
+        self.segment_sum = 0.0;
+        self.segment_pos = 0;
+
+        if self.recent_segments.len() == SEGMENTS_PER_BLOCK {
+            let energy = self.recent_segments.iter().sum::<f64>() / SEGMENTS_PER_BLOCK as f64;
+            self.blocks.push(energy);
+        }
+    }
+
+    /// 整体响度（LUFS），不足一个测量块或全部低于绝对门限时返回绝对门限值
+    pub fn integrated_loudness(&self) -> f32 {
+        let absolute_gate = ABSOLUTE_GATE_LUFS as f64;
+        let gated: Vec<f64> = self.blocks
+            .iter()
+            .copied()
+            .filter(|&e| e > 0.0 && energy_to_lufs(e) > absolute_gate)
+            .collect();
+        if gated.is_empty() {
+            return ABSOLUTE_GATE_LUFS;
+        }
+
+        let relative_gate = energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64) + RELATIVE_GATE_LU;
+        let (sum, count) = gated
+            .iter()
+            .filter(|&&e| energy_to_lufs(e) > relative_gate)
+            .fold((0.0, 0usize), |(sum, count), &e| (sum + e, count + 1));
+        if count == 0 {
+            return ABSOLUTE_GATE_LUFS;
+        }
+
+        energy_to_lufs(sum / count as f64) as f32
+    }
+}
+
+/// 测量音频源的整体响度，should_stop 返回 true 时中止并返回 None
+#[allow(dead_code)]  // 曲库分析已与静音检测合并为一次遍历，保留单独测量的接口
+pub fn measure_integrated_loudness<S>(source: S, should_stop: &dyn Fn() -> bool) -> Option<f32>
+where
+    S: rodio::Source<Item = i16>,
+{
+    let mut meter = LoudnessMeter::new(source.channels(), source.sample_rate());
+    for (i, sample) in source.enumerate() {
+        // 每64K个采样检查一次取消标志
+        if i & 0xFFFF == 0 && should_stop() {
+            return None;
+        }
+        meter.push_sample(sample);
+    }
+    Some(meter.integrated_loudness())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn stereo_sine(amplitude: f32, sample_rate: u32, seconds: usize) -> Vec<i16> {
+        (0..sample_rate as usize * seconds)
+            .flat_map(|i| {
+                let s = (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32).sin();
+                let v = (s * amplitude * 32767.0) as i16;
+                [v, v]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stereo_sine_loudness() {
+        // 双声道1kHz正弦波，-20dBFS峰值约为 -20 LUFS
+        for sample_rate in [44100, 48000] {
+            let samples = stereo_sine(0.1, sample_rate, 5);
+            let lufs = measure_integrated_loudness(SamplesBuffer::new(2, sample_rate, samples), &|| false).unwrap();
+            assert!((lufs + 20.0).abs() < 0.5, "{}Hz: {} LUFS", sample_rate, lufs);
+        }
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        let mut samples = stereo_sine(0.1, 48000, 3);
+        samples.extend(std::iter::repeat(0).take(48000 * 2 * 10));
+        let lufs = measure_integrated_loudness(SamplesBuffer::new(2, 48000, samples), &|| false).unwrap();
+        assert!((lufs + 20.0).abs() < 0.5, "{} LUFS", lufs);
+
+        let silence = vec![0i16; 48000 * 2];
+        let lufs = measure_integrated_loudness(SamplesBuffer::new(2, 48000, silence), &|| false).unwrap();
+        assert_eq!(lufs, ABSOLUTE_GATE_LUFS);
+        assert_eq!(loudness_to_gain_db(lufs), MAX_LOUDNESS_GAIN_DB);
+    }
+
+    #[test]
+    fn test_cancel_stops_measurement() {
+        let samples = stereo_sine(0.1, 48000, 1);
+        assert_eq!(measure_integrated_loudness(SamplesBuffer::new(2, 48000, samples), &|| true), None);
+    }
+}
@@ -6,9 +6,29 @@ pub mod device;
 pub mod decoder;
 pub mod sink_pool;
 pub mod symphonia_decoder;
+pub mod gapless;
+pub mod fade;
+pub mod equalizer;
+pub mod parametric_eq;
+pub mod channel_mix;
+pub mod time_stretch;
+pub mod loudness;
+pub mod silence;
+pub mod fingerprint;
+pub mod duration;
+pub mod verify;
+pub mod volume;
+pub mod visualization;
 
 // 公开导出常用类型
-pub use device::{AudioDevice, LazyAudioDevice};
+pub use device::{AudioDevice, LazyAudioDevice, OutputDeviceInfo, OutputConfig, OutputFormat, OutputRequest, list_output_devices, is_device_lost};
 pub use decoder::{AudioFormat, AudioDecoder};
 pub use sink_pool::{SinkPool, PooledSink};
-pub use symphonia_decoder::SymphoniaDecoder;
+pub use symphonia_decoder::{SymphoniaDecoder, StreamSeekHandle};
+pub use gapless::CancellableSource;
+pub use fade::Fade;
+pub use equalizer::EqualizerSource;
+pub use channel_mix::ChannelMixSource;
+pub use time_stretch::{PlaybackRate, TimeStretchSource};
+pub use visualization::VisualizationTap;
+pub use volume::VolumeSettings;
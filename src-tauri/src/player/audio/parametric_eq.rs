@@ -0,0 +1,264 @@
+// 参数均衡（耳机频响校正）
+//
+// 导入 AutoEq 发布的 ParametricEQ.txt（与 Equalizer APO 的格式相同）：
+//   Preamp: -6.2 dB
+//   Filter 1: ON LSC Fc 105 Hz Gain 5.3 dB Q 0.70
+//   Filter 2: ON PK Fc 188 Hz Gain -2.4 dB Q 0.56
+// 兼容常见的格式差异：滤波器类型的不同写法（LS/LSC/LowShelf、PK/PEQ/Peaking、HS/HSC/HighShelf）、
+// 省略编号、数值和单位连写、逗号小数点、注释和空行；OFF 的滤波器跳过。
+// 校正配置与10段图示均衡器相互独立，在均衡器链路中先于图示均衡器应用。
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// 频率范围（Hz）
+pub const MIN_FREQUENCY_HZ: f32 = 10.0;
+pub const MAX_FREQUENCY_HZ: f32 = 24_000.0;
+
+/// Q值范围
+pub const MIN_Q: f32 = 0.05;
+pub const MAX_Q: f32 = 20.0;
+
+/// 单个滤波器的增益范围（dB）
+pub const MAX_FILTER_GAIN_DB: f32 = 30.0;
+
+/// 前置放大范围（dB）
+pub const MIN_PREAMP_DB: f32 = -30.0;
+pub const MAX_PREAMP_DB: f32 = 12.0;
+
+/// 一个配置最多包含的滤波器数
+pub const MAX_FILTERS: usize = 32;
+
+/// 滤波器类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    /// 峰值
+    Peaking,
+    /// 低架
+    LowShelf,
+    /// 高架
+    HighShelf,
+}
+
+impl FilterKind {
+    fn parse(token: &str) -> Option<Self> {
+        match token.to_ascii_uppercase().as_str() {
+            "PK" | "PEQ" | "PEAKING" => Some(FilterKind::Peaking),
+            "LS" | "LSC" | "LSQ" | "LOWSHELF" => Some(FilterKind::LowShelf),
+            "HS" | "HSC" | "HSQ" | "HIGHSHELF" => Some(FilterKind::HighShelf),
+            _ => None,
+        }
+    }
+
+    /// 导出时使用 AutoEq 的写法
+    fn code(&self) -> &'static str {
+        match self {
+            FilterKind::Peaking => "PK",
+            FilterKind::LowShelf => "LSC",
+            FilterKind::HighShelf => "HSC",
+        }
+    }
+}
+
+/// 参数均衡滤波器
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParametricFilter {
+    pub kind: FilterKind,
+    pub frequency: f32,
+    pub q: f32,
+    pub gain_db: f32,
+}
+
+impl ParametricFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_FREQUENCY_HZ..=MAX_FREQUENCY_HZ).contains(&self.frequency) {
+            return Err(format!("频率必须在{}Hz到{}Hz之间: {}", MIN_FREQUENCY_HZ, MAX_FREQUENCY_HZ, self.frequency));
+        }
+        if !(MIN_Q..=MAX_Q).contains(&self.q) {
+            return Err(format!("Q值必须在{}到{}之间: {}", MIN_Q, MAX_Q, self.q));
+        }
+        if !(-MAX_FILTER_GAIN_DB..=MAX_FILTER_GAIN_DB).contains(&self.gain_db) {
+            return Err(format!("增益必须在-{}dB到+{}dB之间: {}", MAX_FILTER_GAIN_DB, MAX_FILTER_GAIN_DB, self.gain_db));
+        }
+        Ok(())
+    }
+}
+
+/// 耳机校正配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrectionProfile {
+    /// 配置名称（通常为耳机型号）
+    pub name: String,
+    /// 前置放大（dB），AutoEq 用负值为提升预留余量
+    pub preamp_db: f32,
+    pub filters: Vec<ParametricFilter>,
+}
+
+impl CorrectionProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("校正配置名称不能为空".to_string());
+        }
+        if !(MIN_PREAMP_DB..=MAX_PREAMP_DB).contains(&self.preamp_db) {
+            return Err(format!("前置放大必须在{}dB到+{}dB之间: {}", MIN_PREAMP_DB, MAX_PREAMP_DB, self.preamp_db));
+        }
+        if self.filters.is_empty() {
+            return Err("校正配置中没有启用的滤波器".to_string());
+        }
+        if self.filters.len() > MAX_FILTERS {
+            return Err(format!("滤波器不能超过{}个", MAX_FILTERS));
+        }
+        for (index, filter) in self.filters.iter().enumerate() {
+            filter.validate().map_err(|e| format!("滤波器 {}: {}", index + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// 解析 ParametricEQ 文本并校验
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let mut preamp_db = 0.0;
+        let mut filters = Vec::new();
+
+        for (line_no, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, rest)) = line.split_once(':') else {
+                return Err(format!("第{}行无法识别: {}", line_no + 1, raw.trim()));
+            };
+            let key = key.trim().to_ascii_lowercase();
+            if key == "preamp" {
+                preamp_db = parse_number(rest.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic()))
+                    .ok_or_else(|| format!("第{}行前置放大无效: {}", line_no + 1, raw.trim()))?;
+            } else if key.starts_with("filter") {
+                if let Some(filter) = parse_filter(rest).map_err(|e| format!("第{}行{}", line_no + 1, e))? {
+                    filters.push(filter);
+                }
+            } else {
+                return Err(format!("第{}行无法识别: {}", line_no + 1, raw.trim()));
+            }
+        }
+
+        let profile = Self { name: name.trim().to_string(), preamp_db, filters };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// 导出为 ParametricEQ 文本，可以再次导入
+    pub fn export(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "Preamp: {} dB", self.preamp_db);
+        for (index, filter) in self.filters.iter().enumerate() {
+            let _ = writeln!(
+                text,
+                "Filter {}: ON {} Fc {} Hz Gain {} dB Q {}",
+                index + 1,
+                filter.kind.code(),
+                filter.frequency,
+                filter.gain_db,
+                filter.q,
+            );
+        }
+        text
+    }
+}
+
+/// 解析数值，兼容逗号小数点
+fn parse_number(token: &str) -> Option<f32> {
+    token.trim().replace(',', ".").parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// 解析 "ON PK Fc 188 Hz Gain -2.4 dB Q 0.56"，OFF 时返回None
+fn parse_filter(rest: &str) -> Result<Option<ParametricFilter>, String> {
+    let mut tokens = rest.split_whitespace();
+    match tokens.next().map(|t| t.to_ascii_uppercase()) {
+        Some(state) if state == "ON" => {}
+        Some(state) if state == "OFF" => return Ok(None),
+        _ => return Err("缺少 ON/OFF".to_string()),
+    }
+    let kind_token = tokens.next().ok_or("缺少滤波器类型")?;
+    let kind = FilterKind::parse(kind_token).ok_or_else(|| format!("不支持的滤波器类型: {}", kind_token))?;
+
+    let (mut frequency, mut gain_db, mut q) = (None, None, None);
+    while let Some(key) = tokens.next() {
+        let slot = match key.to_ascii_lowercase().as_str() {
+            "fc" => &mut frequency,
+            "gain" => &mut gain_db,
+            "q" => &mut q,
+            // 单位和其他参数（如 BW）跳过
+            _ => continue,
+        };
+        let value = tokens.next().ok_or_else(|| format!("{} 缺少数值", key))?;
+        // 数值和单位可能连写（如 105Hz、-2.4dB）
+        let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        *slot = Some(parse_number(number).ok_or_else(|| format!("{} 的数值无效: {}", key, value))?);
+    }
+
+    let frequency = frequency.ok_or("缺少频率 Fc")?;
+    let gain_db = gain_db.ok_or("缺少增益 Gain")?;
+    // 架式滤波器省略Q时按 0.7071（斜率1）
+    let q = match (q, kind) {
+        (Some(q), _) => q,
+        (None, FilterKind::LowShelf | FilterKind::HighShelf) => std::f32::consts::FRAC_1_SQRT_2,
+        (None, FilterKind::Peaking) => return Err("缺少 Q 值".to_string()),
+    };
+    Ok(Some(ParametricFilter { kind, frequency, q, gain_db }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENNHEISER_HD600: &str = include_str!("../../../tests/fixtures/autoeq/Sennheiser HD 600 ParametricEQ.txt");
+    const VARIANT_FORMAT: &str = include_str!("../../../tests/fixtures/autoeq/variant_format.txt");
+    const INVALID_VALUES: &str = include_str!("../../../tests/fixtures/autoeq/invalid_values.txt");
+
+    #[test]
+    fn test_parse_autoeq_profile() {
+        let profile = CorrectionProfile::parse("Sennheiser HD 600", SENNHEISER_HD600).unwrap();
+        assert_eq!(profile.preamp_db, -6.3);
+        assert_eq!(profile.filters.len(), 10);
+        assert_eq!(
+            profile.filters[0],
+            ParametricFilter { kind: FilterKind::LowShelf, frequency: 105.0, q: 0.7, gain_db: 6.3 }
+        );
+        assert_eq!(profile.filters[9].kind, FilterKind::HighShelf);
+    }
+
+    #[test]
+    fn test_parse_format_variations() {
+        let profile = CorrectionProfile::parse("Variant", VARIANT_FORMAT).unwrap();
+        assert_eq!(profile.preamp_db, -4.5);
+        // OFF 的滤波器被跳过
+        let kinds: Vec<FilterKind> = profile.filters.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, vec![FilterKind::LowShelf, FilterKind::Peaking, FilterKind::Peaking, FilterKind::HighShelf]);
+        assert_eq!(profile.filters[1].frequency, 250.0);
+        assert_eq!(profile.filters[1].gain_db, -1.5);
+        assert_eq!(profile.filters[3].q, std::f32::consts::FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn test_rejects_nonsense_values() {
+        let err = CorrectionProfile::parse("Invalid", INVALID_VALUES).unwrap_err();
+        assert!(err.contains("频率"), "{}", err);
+
+        assert!(CorrectionProfile::parse("Empty", "Preamp: -3 dB\n").is_err());
+        assert!(CorrectionProfile::parse("Bad", "Filter 1: ON XX Fc 100 Hz Gain 1 dB Q 1").is_err());
+        assert!(CorrectionProfile::parse("Bad", "Filter 1: ON PK Fc 100 Hz Gain 80 dB Q 1").is_err());
+        assert!(CorrectionProfile::parse("Bad", "Filter 1: ON PK Fc 100 Hz Gain 1 dB Q 0").is_err());
+        assert!(CorrectionProfile::parse("Bad", "Filter 1: ON PK Fc NaN Hz Gain 1 dB Q 1").is_err());
+        assert!(CorrectionProfile::parse("Bad", "hello world").is_err());
+        assert!(CorrectionProfile::parse(" ", SENNHEISER_HD600).is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        for (name, text) in [("Sennheiser HD 600", SENNHEISER_HD600), ("Variant", VARIANT_FORMAT)] {
+            let profile = CorrectionProfile::parse(name, text).unwrap();
+            let exported = profile.export();
+            assert_eq!(CorrectionProfile::parse(name, &exported).unwrap(), profile);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// 静音检测模块
+//
+// 检测曲目开头和结尾的长时间静音（隐藏音轨前的空白、结尾的长静音），得到裁剪点：
+// - 按10ms窗口取各声道峰值，低于阈值的窗口视为静音
+// - 只有不短于 MIN_SILENCE_MS 的静音才裁剪，短暂的淡入淡出不受影响
+// - 裁剪点两侧保留 TRIM_MARGIN_MS，避免切掉声音的起音和余韵
+// 由后台分析任务和响度测量在同一次解码中完成。
+
+use serde::{Deserialize, Serialize};
+
+/// 默认静音阈值(dBFS)
+pub const DEFAULT_SILENCE_THRESHOLD_DB: f32 = -60.0;
+
+/// 可设置的静音阈值范围(dBFS)
+pub const MIN_SILENCE_THRESHOLD_DB: f32 = -90.0;
+pub const MAX_SILENCE_THRESHOLD_DB: f32 = -20.0;
+
+/// 开头或结尾的静音至少这么长才裁剪(ms)
+const MIN_SILENCE_MS: u64 = 2_000;
+
+/// 裁剪点与声音之间保留的余量(ms)
+const TRIM_MARGIN_MS: u64 = 200;
+
+/// 检测窗口长度(ms)
+const WINDOW_MS: u64 = 10;
+
+/// 静音处理设置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SilenceSettings {
+    /// 播放时跳过开头和结尾的静音
+    pub skip_silence: bool,
+    /// 低于该电平视为静音(dBFS)，修改后需要重新分析
+    pub threshold_db: f32,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self { skip_silence: false, threshold_db: DEFAULT_SILENCE_THRESHOLD_DB }
+    }
+}
+
+impl SilenceSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_SILENCE_THRESHOLD_DB..=MAX_SILENCE_THRESHOLD_DB).contains(&self.threshold_db) {
+            return Err(format!(
+                "静音阈值必须在 {} 到 {} dB 之间",
+                MIN_SILENCE_THRESHOLD_DB, MAX_SILENCE_THRESHOLD_DB
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 曲目的裁剪点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TrimPoints {
+    /// 有效开始位置(ms)，开头没有长静音时为0
+    pub start_ms: u64,
+    /// 有效结束位置(ms)，结尾没有长静音时为None
+    pub end_ms: Option<u64>,
+}
+
+impl TrimPoints {
+    /// 是否需要裁剪
+    pub fn is_trimmed(&self) -> bool {
+        self.start_ms > 0 || self.end_ms.is_some()
+    }
+}
+
+/// 开头和结尾静音检测器
+pub struct SilenceDetector {
+    channels: usize,
+    threshold: f32,
+    window_frames: usize,
+    channel_index: usize,
+    frame_pos: usize,
+    window_peak: f32,
+    /// 已完成的窗口数
+    windows: u64,
+    /// 第一个和最后一个有声音的窗口
+    first_sound: Option<u64>,
+    last_sound: Option<u64>,
+}
+
+impl SilenceDetector {
+    pub fn new(channels: u16, sample_rate: u32, threshold_db: f32) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            threshold: 10f32.powf(threshold_db / 20.0),
+            window_frames: (sample_rate as u64 * WINDOW_MS / 1000).max(1) as usize,
+            channel_index: 0,
+            frame_pos: 0,
+            window_peak: 0.0,
+            windows: 0,
+            first_sound: None,
+            last_sound: None,
+        }
+    }
+
+    /// 输入一个交错排列的采样
+    pub fn push_sample(&mut self, sample: i16) {
+        self.window_peak = self.window_peak.max((sample as f32 / 32768.0).abs());
+
+        self.channel_index += 1;
+        if self.channel_index < self.channels {
+            return;
+        }
+        self.channel_index = 0;
+
+        self.frame_pos += 1;
+        if self.frame_pos == self.window_frames {
+            self.finish_window();
+        }
+    }
+
+    fn finish_window(&mut self) {
+        if self.window_peak >= self.threshold {
+            self.first_sound.get_or_insert(self.windows);
+            self.last_sound = Some(self.windows);
+        }
+        self.windows += 1;
+        self.frame_pos = 0;
+        self.window_peak = 0.0;
+    }
+
+    /// 结束检测，返回裁剪点；整首都是静音时不裁剪
+    pub fn finish(mut self) -> TrimPoints {
+        if self.frame_pos > 0 {
+            self.finish_window();
+        }
+        let (Some(first), Some(last)) = (self.first_sound, self.last_sound) else {
+            return TrimPoints::default();
+        };
+
+        let total_ms = self.windows * WINDOW_MS;
+        let sound_start_ms = first * WINDOW_MS;
+        let sound_end_ms = (last + 1) * WINDOW_MS;
+
+        let start_ms = if sound_start_ms >= MIN_SILENCE_MS {
+            sound_start_ms - TRIM_MARGIN_MS
+        } else {
+            0
+        };
+        let end_ms = (total_ms - sound_end_ms >= MIN_SILENCE_MS).then_some(sound_end_ms + TRIM_MARGIN_MS);
+        TrimPoints { start_ms, end_ms }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(segments: &[(f32, u64)]) -> TrimPoints {
+        // 双声道，44.1kHz，(幅度, 时长ms)
+        let mut detector = SilenceDetector::new(2, 44100, DEFAULT_SILENCE_THRESHOLD_DB);
+        let mut i = 0u64;
+        for &(amplitude, ms) in segments {
+            for _ in 0..44100 * ms / 1000 {
+                let s = (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin();
+                let v = (s * amplitude * 32767.0) as i16;
+                detector.push_sample(v);
+                detector.push_sample(v);
+                i += 1;
+            }
+        }
+        detector.finish()
+    }
+
+    #[test]
+    fn test_long_leading_and_trailing_silence() {
+        let trim = detect(&[(0.0, 5_000), (0.5, 10_000), (0.0, 30_000)]);
+        assert_eq!(trim.start_ms, 5_000 - TRIM_MARGIN_MS);
+        let end = trim.end_ms.unwrap();
+        assert!((15_000 + TRIM_MARGIN_MS..=15_000 + TRIM_MARGIN_MS + 2 * WINDOW_MS).contains(&end), "{}", end);
+        assert!(trim.is_trimmed());
+    }
+
+    #[test]
+    fn test_short_silence_and_quiet_noise_are_kept() {
+        // 短于阈值的静音不裁剪，高于阈值的底噪不算静音
+        let trim = detect(&[(0.0, 1_000), (0.5, 5_000), (0.01, 10_000)]);
+        assert_eq!(trim, TrimPoints::default());
+        assert!(!trim.is_trimmed());
+
+        assert_eq!(detect(&[(0.0, 3_000)]), TrimPoints::default());
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        assert!(SilenceSettings::default().validate().is_ok());
+        assert!(SilenceSettings { skip_silence: true, threshold_db: -10.0 }.validate().is_err());
+        assert!(SilenceSettings { skip_silence: true, threshold_db: -100.0 }.validate().is_err());
+    }
+}
@@ -4,13 +4,19 @@
 // - 不需要 seek 支持
 // - 边读边解码
 // - 真正的流式播放
+// - 通过 StreamSeekHandle 在播放中跳转（底层读取器按需发起Range请求）
 
-use symphonia::core::formats::FormatReader;
+use symphonia::core::formats::{FormatReader, SeekMode, SeekTo};
 use symphonia::core::codecs::Decoder;
 use symphonia::core::audio::SampleBuffer;
 use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::units::{Time, TimeBase};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// 连续解码失败超过该次数才结束曲目（网络抖动后个别数据包损坏时跳过继续播放）
+const MAX_CONSECUTIVE_DECODE_ERRORS: u32 = 16;
+
 /// Symphonia 流式解码器（实现 rodio::Source）
 pub struct SymphoniaDecoder {
     format: Arc<Mutex<Box<dyn FormatReader>>>,
@@ -20,6 +26,46 @@ pub struct SymphoniaDecoder {
     sample_index: usize,
     channels: u16,
     sample_rate: u32,
+    /// 跳转后需要丢弃已解码但未输出的样本
+    flush: Arc<AtomicBool>,
+    /// 连续解码失败的数据包数
+    decode_errors: u32,
+    /// 容器头中记录的总时长（没有总帧数时为None）
+    total_duration: Option<std::time::Duration>,
+}
+
+/// 流式解码器的跳转句柄
+///
+/// 与正在播放的 SymphoniaDecoder 共享格式读取器和解码器，
+/// 调用前应先暂停Sink，避免音频线程在跳转期间等待锁。
+#[derive(Clone)]
+pub struct StreamSeekHandle {
+    format: Arc<Mutex<Box<dyn FormatReader>>>,
+    decoder: Arc<Mutex<Box<dyn Decoder>>>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    flush: Arc<AtomicBool>,
+}
+
+impl StreamSeekHandle {
+    /// 跳转到指定位置，返回实际到达的位置(ms)
+    pub fn seek(&self, position_ms: u64) -> Result<u64, SymphoniaError> {
+        let mut format = self.format.lock().unwrap();
+        let time = Time::new(position_ms / 1000, (position_ms % 1000) as f64 / 1000.0);
+        let seeked = format.seek(SeekMode::Coarse, SeekTo::Time { time, track_id: Some(self.track_id) })?;
+        
+        self.decoder.lock().unwrap().reset();
+        self.flush.store(true, Ordering::SeqCst);
+        
+        let actual_ms = match self.time_base {
+            Some(tb) => {
+                let t = tb.calc_time(seeked.actual_ts);
+                t.seconds * 1000 + (t.frac * 1000.0) as u64
+            }
+            None => position_ms,
+        };
+        Ok(actual_ms)
+    }
 }
 
 impl SymphoniaDecoder {
@@ -31,6 +77,13 @@ impl SymphoniaDecoder {
         let codec_params = &decoder.codec_params();
         let channels = codec_params.channels.map(|c| c.count() as u16).unwrap_or(2);
         let sample_rate = codec_params.sample_rate.unwrap_or(44100);
+        let total_duration = match (codec_params.n_frames, codec_params.time_base) {
+            (Some(n_frames), Some(tb)) if n_frames > 0 => {
+                let time = tb.calc_time(n_frames);
+                Some(std::time::Duration::from_secs(time.seconds) + std::time::Duration::from_secs_f64(time.frac))
+            }
+            _ => None,
+        };
         
         log::info!("🎵 SymphoniaDecoder 创建: {}Hz, {}通道", sample_rate, channels);
         
@@ -42,6 +95,26 @@ impl SymphoniaDecoder {
             sample_index: 0,
             channels,
             sample_rate,
+            flush: Arc::new(AtomicBool::new(false)),
+            decode_errors: 0,
+            total_duration,
+        }
+    }
+    
+    /// 获取跳转句柄
+    pub fn seek_handle(&self) -> StreamSeekHandle {
+        let time_base = self.format.lock().unwrap()
+            .tracks()
+            .iter()
+            .find(|t| t.id == self.track_id)
+            .and_then(|t| t.codec_params.time_base);
+        
+        StreamSeekHandle {
+            format: self.format.clone(),
+            decoder: self.decoder.clone(),
+            track_id: self.track_id,
+            time_base,
+            flush: self.flush.clone(),
         }
     }
     
@@ -87,6 +160,13 @@ impl Iterator for SymphoniaDecoder {
     type Item = i16;
     
     fn next(&mut self) -> Option<Self::Item> {
+        // 跳转后丢弃旧位置的残留样本
+        if self.flush.swap(false, Ordering::SeqCst) {
+            if let Some(ref buf) = self.sample_buffer {
+                self.sample_index = buf.len();
+            }
+        }
+        
         loop {
             // 如果有缓存的样本，直接返回
             if let Some(ref buf) = self.sample_buffer {
@@ -99,11 +179,20 @@ impl Iterator for SymphoniaDecoder {
             
             // 解码下一个数据包
             match self.decode_next_packet() {
-                Ok(_) => continue,
+                Ok(_) => {
+                    self.decode_errors = 0;
+                    continue;
+                }
                 Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
                     // 正常结束
                     return None;
                 }
+                Err(SymphoniaError::DecodeError(e)) if self.decode_errors < MAX_CONSECUTIVE_DECODE_ERRORS => {
+                    // 损坏的数据包只丢弃这一包，不当作曲目结束
+                    self.decode_errors += 1;
+                    log::warn!("⚠️ 跳过无法解码的数据包（连续{}次）: {}", self.decode_errors, e);
+                    continue;
+                }
                 Err(SymphoniaError::ResetRequired) => {
                     // 需要重置解码器
                     log::warn!("⚠️ 解码器需要重置");
@@ -132,7 +221,8 @@ impl rodio::Source for SymphoniaDecoder {
     }
     
     fn total_duration(&self) -> Option<std::time::Duration> {
-        None // 流式播放，总时长未知
+        // 流式播放，只有容器头记录了总帧数时才知道总时长
+        self.total_duration
     }
 }
 
@@ -0,0 +1,499 @@
+// 播放速度
+//
+// 紧跟在解码输出之后的音频源包装，改变播放速度：
+// - 变速变调：线性插值重采样，开销最小
+// - 变速不变调：WSOLA（波形相似重叠相加），在容差范围内按互相关挑选与上一段最衔接的分析帧，汉宁窗重叠相加
+// 速度和模式通过共享的 PlaybackRate 实时下发；从未变速时样本原样透传。
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 播放速度范围
+pub const MIN_RATE: f32 = 0.5;
+pub const MAX_RATE: f32 = 3.0;
+
+/// WSOLA 合成步长（一段为两倍步长）
+const WSOLA_HOP_MS: u32 = 20;
+
+/// WSOLA 搜索容差
+const WSOLA_TOLERANCE_MS: u32 = 10;
+
+/// 计算互相关时每隔几帧取一个点
+const CORRELATION_STRIDE: usize = 4;
+
+pub fn validate_rate(rate: f32) -> Result<(), String> {
+    if !(MIN_RATE..=MAX_RATE).contains(&rate) {
+        return Err(format!("播放速度必须在{}x到{}x之间", MIN_RATE, MAX_RATE));
+    }
+    Ok(())
+}
+
+/// 共享的播放速度控制：PlaybackActor 写入，音频线程读取
+#[derive(Debug)]
+pub struct PlaybackRate {
+    rate: AtomicU32,
+    preserve_pitch: AtomicBool,
+    /// 流式跳转后递增，音频源据此丢弃缓冲的旧样本
+    generation: AtomicU32,
+}
+
+impl Default for PlaybackRate {
+    fn default() -> Self {
+        Self {
+            rate: AtomicU32::new(1.0f32.to_bits()),
+            preserve_pitch: AtomicBool::new(false),
+            generation: AtomicU32::new(0),
+        }
+    }
+}
+
+impl PlaybackRate {
+    pub fn rate(&self) -> f32 {
+        f32::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        self.rate.store(rate.clamp(MIN_RATE, MAX_RATE).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn preserve_pitch(&self) -> bool {
+        self.preserve_pitch.load(Ordering::Relaxed)
+    }
+
+    pub fn set_preserve_pitch(&self, preserve_pitch: bool) {
+        self.preserve_pitch.store(preserve_pitch, Ordering::Relaxed);
+    }
+
+    /// 丢弃各音频源中缓冲的样本（解码器跳转后调用）
+    pub fn reset(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
+
+/// 读取完整的一帧，音源结束时返回 false
+fn read_frame<S: Iterator<Item = i16>>(inner: &mut S, channels: usize, frame: &mut Vec<f32>) -> bool {
+    frame.clear();
+    for _ in 0..channels {
+        match inner.next() {
+            Some(sample) => frame.push(sample as f32),
+            None => return false,
+        }
+    }
+    true
+}
+
+#[inline]
+fn to_sample(x: f32) -> i16 {
+    x.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// 线性插值重采样（变速变调）
+#[derive(Debug, Default)]
+struct Resampler {
+    current: Vec<f32>,
+    next: Vec<f32>,
+    /// current 与 next 之间的位置
+    frac: f64,
+    primed: bool,
+}
+
+impl Resampler {
+    fn reset(&mut self) {
+        self.frac = 0.0;
+        self.primed = false;
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.primed
+    }
+
+    /// 输出一帧，音源结束时不输出
+    fn process<S: Iterator<Item = i16>>(&mut self, inner: &mut S, channels: usize, rate: f32, out: &mut VecDeque<i16>) {
+        if !self.primed {
+            if !read_frame(inner, channels, &mut self.current) || !read_frame(inner, channels, &mut self.next) {
+                return;
+            }
+            self.frac = 0.0;
+            self.primed = true;
+        }
+
+        let frac = self.frac as f32;
+        for (current, next) in self.current.iter().zip(&self.next) {
+            out.push_back(to_sample(current + (next - current) * frac));
+        }
+
+        self.frac += rate as f64;
+        while self.frac >= 1.0 {
+            self.frac -= 1.0;
+            std::mem::swap(&mut self.current, &mut self.next);
+            if !read_frame(inner, channels, &mut self.next) {
+                self.primed = false;
+                return;
+            }
+        }
+    }
+}
+
+/// WSOLA（变速不变调）
+#[derive(Debug)]
+struct Wsola {
+    channels: usize,
+    /// 合成步长（帧），分析帧长度为两倍步长
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+    /// 交错排列的输入样本
+    input: Vec<f32>,
+    /// 下一个分析帧的名义位置（相对于 input 开头，帧）
+    nominal: f64,
+    /// 上一个分析帧的自然延续位置，尚未输出过时为 None
+    natural: Option<usize>,
+    /// 上一个分析帧后半段加窗后的样本，与下一帧前半段重叠相加
+    overlap: Vec<f32>,
+    frame: Vec<f32>,
+}
+
+impl Wsola {
+    fn new(channels: usize, sample_rate: u32) -> Self {
+        let hop = (sample_rate * WSOLA_HOP_MS / 1000).max(16) as usize;
+        let tolerance = (sample_rate * WSOLA_TOLERANCE_MS / 1000).max(4) as usize;
+        // 周期汉宁窗，间隔半个窗长的两点之和为1
+        let length = hop * 2;
+        let window = (0..length)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / length as f32).cos())
+            .collect();
+        Self {
+            channels,
+            hop,
+            tolerance,
+            window,
+            input: Vec::new(),
+            nominal: 0.0,
+            natural: None,
+            overlap: vec![0.0; hop * channels],
+            frame: Vec::with_capacity(channels),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.nominal = 0.0;
+        self.natural = None;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.input.is_empty() && self.natural.is_none()
+    }
+
+    fn frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    /// 单声道下混后两段的互相关
+    fn correlation(&self, a: usize, b: usize) -> f32 {
+        let channels = self.channels;
+        let mut sum = 0.0;
+        for i in (0..self.hop).step_by(CORRELATION_STRIDE) {
+            let (a, b) = ((a + i) * channels, (b + i) * channels);
+            let x: f32 = self.input[a..a + channels].iter().sum();
+            let y: f32 = self.input[b..b + channels].iter().sum();
+            sum += x * y;
+        }
+        sum
+    }
+
+    /// 输出一个步长的帧；音源结束、输入不足时输出剩余的重叠部分
+    fn process<S: Iterator<Item = i16>>(&mut self, inner: &mut S, rate: f32, out: &mut VecDeque<i16>) {
+        let channels = self.channels;
+        let segment = self.hop * 2;
+        let nominal = self.nominal as usize;
+        let needed = (nominal + self.tolerance).max(self.natural.unwrap_or(0)) + segment;
+        while self.frames() < needed {
+            if !read_frame(inner, channels, &mut self.frame) {
+                break;
+            }
+            self.input.extend_from_slice(&self.frame);
+        }
+
+        let available = self.frames();
+        let pos = match self.natural {
+            // 第一段直接取名义位置，前半段按原样输出
+            None => {
+                if available < nominal + segment {
+                    return;
+                }
+                for i in 0..self.hop {
+                    for c in 0..channels {
+                        self.overlap[i * channels + c] = self.window[self.hop + i] * self.input[(nominal + i) * channels + c];
+                    }
+                }
+                nominal
+            }
+            // 原速时沿用自然延续，输出与输入完全一致
+            Some(natural) if (rate - 1.0).abs() < f32::EPSILON && natural + segment <= available => natural,
+            Some(natural) => {
+                let low = nominal.saturating_sub(self.tolerance);
+                let high = available.checked_sub(segment).map(|last| last.min(nominal + self.tolerance));
+                let Some(high) = high.filter(|&high| high >= low && natural + self.hop <= available) else {
+                    // 音源已结束：输出剩余的重叠部分
+                    out.extend(self.overlap.iter().map(|&x| to_sample(x)));
+                    self.reset();
+                    return;
+                };
+                (low..=high)
+                    .step_by(2)
+                    .map(|candidate| (candidate, self.correlation(natural, candidate)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map_or(low, |(candidate, _)| candidate)
+            }
+        };
+
+        for i in 0..self.hop {
+            for c in 0..channels {
+                let index = i * channels + c;
+                out.push_back(to_sample(self.overlap[index] + self.window[i] * self.input[(pos + i) * channels + c]));
+                self.overlap[index] = self.window[self.hop + i] * self.input[(pos + self.hop + i) * channels + c];
+            }
+        }
+
+        let natural = pos + self.hop;
+        let nominal = match self.natural {
+            None => natural as f64,
+            Some(_) => self.nominal + self.hop as f64 * rate as f64,
+        };
+        // 丢弃之后不会再用到的输入
+        let keep_from = natural.min((nominal as usize).saturating_sub(self.tolerance));
+        self.input.drain(..keep_from * channels);
+        self.natural = Some(natural - keep_from);
+        self.nominal = nominal - keep_from as f64;
+    }
+}
+
+/// 播放速度音频源包装
+pub struct TimeStretchSource<S> {
+    inner: S,
+    control: Arc<PlaybackRate>,
+    channels: u16,
+    sample_rate: u32,
+    generation: u32,
+    preserve_pitch: bool,
+    /// 变速过后即使回到原速也继续处理，直到缓冲的样本用完
+    engaged: bool,
+    /// 透传时在帧内的位置，只在帧边界切换模式
+    channel_index: usize,
+    resampler: Resampler,
+    wsola: Wsola,
+    output: VecDeque<i16>,
+}
+
+impl<S> TimeStretchSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    pub fn new(inner: S, control: Arc<PlaybackRate>) -> Self {
+        let channels = inner.channels().max(1);
+        let sample_rate = inner.sample_rate().max(1);
+        Self {
+            generation: control.generation(),
+            preserve_pitch: control.preserve_pitch(),
+            inner,
+            control,
+            channels,
+            sample_rate,
+            engaged: false,
+            channel_index: 0,
+            resampler: Resampler::default(),
+            wsola: Wsola::new(channels as usize, sample_rate),
+            output: VecDeque::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.wsola.reset();
+    }
+
+    /// 输出缓冲用完时（帧边界）读取最新的速度和模式
+    fn refresh(&mut self) -> f32 {
+        let generation = self.control.generation();
+        if generation != self.generation {
+            self.generation = generation;
+            self.reset();
+        }
+        let preserve_pitch = self.control.preserve_pitch();
+        if preserve_pitch != self.preserve_pitch {
+            self.preserve_pitch = preserve_pitch;
+            self.reset();
+        }
+        let rate = self.control.rate();
+        let idle = self.resampler.is_idle() && self.wsola.is_idle();
+        self.engaged = (rate - 1.0).abs() >= f32::EPSILON || !idle;
+        rate
+    }
+}
+
+impl<S> Iterator for TimeStretchSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(sample) = self.output.pop_front() {
+            return Some(sample);
+        }
+
+        // 处理后的输出总是整帧，透传时等到帧边界再检查速度，避免声道错位
+        if self.channel_index == 0 {
+            let rate = self.refresh();
+            if self.engaged {
+                let channels = self.channels as usize;
+                if self.preserve_pitch {
+                    self.wsola.process(&mut self.inner, rate, &mut self.output);
+                } else {
+                    self.resampler.process(&mut self.inner, channels, rate, &mut self.output);
+                }
+                return self.output.pop_front();
+            }
+        }
+
+        let sample = self.inner.next()?;
+        self.channel_index = (self.channel_index + 1) % self.channels as usize;
+        Some(sample)
+    }
+}
+
+impl<S> rodio::Source for TimeStretchSource<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// 曲目本身的时长（不随速度缩放，播放位置同样按曲目时间计算）
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(freq: f32, sample_rate: u32, frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|i| ((2.0 * PI * freq * i as f32 / sample_rate as f32).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    fn control(rate: f32, preserve_pitch: bool) -> Arc<PlaybackRate> {
+        let control = Arc::new(PlaybackRate::default());
+        control.set_rate(rate);
+        control.set_preserve_pitch(preserve_pitch);
+        control
+    }
+
+    /// 过零次数估算频率
+    fn zero_crossings(samples: &[i16]) -> usize {
+        samples.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count()
+    }
+
+    #[test]
+    fn test_normal_rate_passes_through() {
+        let input = sine(440.0, 8000, 4000);
+        let output: Vec<i16> = TimeStretchSource::new(SamplesBuffer::new(1, 8000, input.clone()), control(1.0, true)).collect();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_resample_changes_length_and_pitch() {
+        let input = sine(440.0, 8000, 8000);
+        let output: Vec<i16> = TimeStretchSource::new(SamplesBuffer::new(1, 8000, input.clone()), control(2.0, false)).collect();
+        assert!((output.len() as i64 - 4000).abs() <= 2, "len = {}", output.len());
+        // 频率加倍：过零次数与原曲相同但时长减半
+        let ratio = zero_crossings(&output) as f32 / zero_crossings(&input) as f32;
+        assert!((ratio - 1.0).abs() < 0.05, "ratio = {}", ratio);
+    }
+
+    #[test]
+    fn test_wsola_changes_length_keeps_pitch() {
+        let sample_rate = 8000;
+        let input = sine(440.0, sample_rate, sample_rate as usize * 2);
+        for rate in [0.75, 1.5, 2.0] {
+            let output: Vec<i16> = TimeStretchSource::new(SamplesBuffer::new(1, sample_rate, input.clone()), control(rate, true)).collect();
+            let expected = input.len() as f32 / rate;
+            assert!((output.len() as f32 - expected).abs() < expected * 0.05, "rate {}: len {} vs {}", rate, output.len(), expected);
+            // 音高不变：单位时间内的过零次数与原曲相同
+            let per_sample = zero_crossings(&output) as f32 / output.len() as f32;
+            let original = zero_crossings(&input) as f32 / input.len() as f32;
+            assert!((per_sample / original - 1.0).abs() < 0.05, "rate {}: {} vs {}", rate, per_sample, original);
+        }
+    }
+
+    #[test]
+    fn test_wsola_keeps_channels_interleaved() {
+        let left = sine(440.0, 8000, 4000);
+        let input: Vec<i16> = left.iter().flat_map(|&s| [s, 0]).collect();
+        let output: Vec<i16> = TimeStretchSource::new(SamplesBuffer::new(2, 8000, input), control(1.5, true)).collect();
+        assert_eq!(output.len() % 2, 0);
+        assert!(output.iter().skip(1).step_by(2).all(|&s| s == 0));
+    }
+
+    #[test]
+    fn test_rate_change_applies_while_playing() {
+        let input = sine(440.0, 8000, 8000);
+        let control = control(1.0, false);
+        let mut source = TimeStretchSource::new(SamplesBuffer::new(1, 8000, input), Arc::clone(&control));
+        let first: Vec<i16> = source.by_ref().take(4000).collect();
+        assert_eq!(first.len(), 4000);
+
+        control.set_rate(2.0);
+        let rest = source.count();
+        assert!((rest as i64 - 2000).abs() <= 2, "rest = {}", rest);
+    }
+
+    #[test]
+    fn test_rate_change_keeps_channel_order() {
+        // 左声道为正、右声道为负
+        let input: Vec<i16> = (0..8000).flat_map(|_| [4000, -4000]).collect();
+        let control = control(1.0, false);
+        let mut source = TimeStretchSource::new(SamplesBuffer::new(2, 8000, input), Arc::clone(&control));
+        // 在帧中间（奇数个样本后）改变速度
+        let mut output: Vec<i16> = source.by_ref().take(3001).collect();
+        control.set_rate(2.0);
+        output.extend(source.by_ref());
+
+        assert!(output.len() > 3001 + 1000);
+        for (index, sample) in output.iter().enumerate() {
+            if index % 2 == 0 {
+                assert!(*sample >= 0, "第{}个样本应为左声道: {}", index, sample);
+            } else {
+                assert!(*sample <= 0, "第{}个样本应为右声道: {}", index, sample);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_rate() {
+        assert!(validate_rate(1.25).is_ok());
+        assert!(validate_rate(0.25).is_err());
+        assert!(validate_rate(f32::NAN).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+// 音频文件完整性校验
+//
+// 完整解码文件的每个数据包（不输出声音），找出播放到一半就中断的文件：
+// - 解码出的帧数明显少于容器头记录的总帧数（下载不完整）→ truncated
+// - 无法识别格式、数据包读取失败或多个数据包解码失败 → decode_error，记录错误信息
+// 容器头没有总帧数（缺少Xing头的MP3等）时无法判断截断，只检查能否解码。
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// 每处理这么多个数据包检查一次取消标志（调用方可在检查时限速）
+const STOP_CHECK_PACKETS: u64 = 256;
+
+/// 少量数据包解码失败（文件末尾的垃圾数据等）不影响播放，超过该数量才算损坏
+const MAX_IGNORED_DECODE_ERRORS: usize = 2;
+
+/// 解码出的时长比容器头记录的短这么多以上才算截断(ms)
+const TRUNCATION_TOLERANCE_MS: u64 = 1000;
+
+/// 校验结果状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    Ok,
+    Truncated,
+    DecodeError,
+}
+
+impl VerifyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyStatus::Ok => "ok",
+            VerifyStatus::Truncated => "truncated",
+            VerifyStatus::DecodeError => "decode_error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ok" => Some(VerifyStatus::Ok),
+            "truncated" => Some(VerifyStatus::Truncated),
+            "decode_error" => Some(VerifyStatus::DecodeError),
+            _ => None,
+        }
+    }
+
+    /// 文件已损坏（截断或无法解码）
+    pub fn is_broken(&self) -> bool {
+        *self != VerifyStatus::Ok
+    }
+}
+
+impl FromSql for VerifyStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Self::parse(text).ok_or_else(|| FromSqlError::Other(format!("未知的校验状态: {}", text).into()))
+    }
+}
+
+/// 一个文件的校验结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyResult {
+    pub status: VerifyStatus,
+    /// 截断时为实际/应有时长，解码失败时为错误信息
+    pub detail: Option<String>,
+}
+
+impl VerifyResult {
+    fn ok() -> Self {
+        Self { status: VerifyStatus::Ok, detail: None }
+    }
+
+    fn truncated(detail: String) -> Self {
+        Self { status: VerifyStatus::Truncated, detail: Some(detail) }
+    }
+
+    fn decode_error(detail: String) -> Self {
+        Self { status: VerifyStatus::DecodeError, detail: Some(detail) }
+    }
+}
+
+/// 完整解码本地音频文件并判断是否完好
+///
+/// 文件无法打开时返回错误（不记录结果）；should_stop 返回 true 时中止并返回 None
+pub fn verify_file(path: &Path, should_stop: &dyn Fn() -> bool) -> anyhow::Result<Option<VerifyResult>> {
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = match symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+    {
+        Ok(probed) => probed,
+        Err(e) => return Ok(Some(VerifyResult::decode_error(format!("无法识别文件格式: {}", e)))),
+    };
+    let mut format = probed.format;
+    let Some(track) = format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL) else {
+        return Ok(Some(VerifyResult::decode_error("没有找到有效音轨".to_string())));
+    };
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let mut decoder = match symphonia::default::get_codecs().make(&params, &DecoderOptions::default()) {
+        Ok(decoder) => decoder,
+        Err(e) => return Ok(Some(VerifyResult::decode_error(format!("不支持的编码: {}", e)))),
+    };
+
+    let mut decoded = DecodeStats::default();
+    let mut packets = 0u64;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // 到达文件末尾（截断的文件也在这里结束，按帧数判断）
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Ok(Some(VerifyResult::decode_error(format!("读取数据包失败: {}", e)))),
+        };
+        packets += 1;
+        if packets % STOP_CHECK_PACKETS == 0 && should_stop() {
+            return Ok(None);
+        }
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(buffer) => decoded.frames += buffer.frames() as u64,
+            Err(SymphoniaError::DecodeError(e)) => {
+                decoded.bad_packets += 1;
+                decoded.first_error.get_or_insert_with(|| e.to_string());
+            }
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Ok(Some(VerifyResult::decode_error(e.to_string()))),
+        }
+    }
+
+    Ok(Some(classify(&decoded, params.n_frames, params.sample_rate)))
+}
+
+#[derive(Debug, Default)]
+struct DecodeStats {
+    frames: u64,
+    bad_packets: usize,
+    first_error: Option<String>,
+}
+
+fn classify(decoded: &DecodeStats, expected_frames: Option<u64>, sample_rate: Option<u32>) -> VerifyResult {
+    if decoded.frames == 0 {
+        let reason = decoded.first_error.as_deref().unwrap_or("没有解码出任何音频");
+        return VerifyResult::decode_error(reason.to_string());
+    }
+
+    if let (Some(expected), Some(rate)) = (expected_frames.filter(|&n| n > 0), sample_rate.filter(|&r| r > 0)) {
+        let tolerance = TRUNCATION_TOLERANCE_MS * rate as u64 / 1000;
+        if decoded.frames + tolerance < expected {
+            let to_ms = |frames: u64| frames * 1000 / rate as u64;
+            return VerifyResult::truncated(format!(
+                "只能解码 {} 毫秒，文件头记录为 {} 毫秒",
+                to_ms(decoded.frames),
+                to_ms(expected)
+            ));
+        }
+    }
+
+    if decoded.bad_packets > MAX_IGNORED_DECODE_ERRORS {
+        return VerifyResult::decode_error(format!(
+            "{} 个数据包解码失败: {}",
+            decoded.bad_packets,
+            decoded.first_error.as_deref().unwrap_or_default()
+        ));
+    }
+    VerifyResult::ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成16位单声道WAV文件，头部声明 declared_frames 帧，实际只写入 frames 帧
+    fn write_wav(path: &Path, sample_rate: u32, declared_frames: u32, frames: u32) {
+        let data_len = declared_frames * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        bytes.resize(bytes.len() + frames as usize * 2, 0);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn verify_temp(name: &str, bytes: impl FnOnce(&Path)) -> VerifyResult {
+        let path = std::env::temp_dir().join(format!("windchime-verify-{}-{}", uuid::Uuid::new_v4(), name));
+        bytes(&path);
+        let result = verify_file(&path, &|| false);
+        let _ = std::fs::remove_file(&path);
+        result.unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_complete_file_is_ok() {
+        let result = verify_temp("ok.wav", |path| write_wav(path, 8000, 8000 * 2, 8000 * 2));
+        assert_eq!(result, VerifyResult::ok());
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let result = verify_temp("cut.wav", |path| write_wav(path, 8000, 8000 * 3, 8000));
+        assert_eq!(result.status, VerifyStatus::Truncated);
+        assert!(result.detail.unwrap().contains("3000"));
+    }
+
+    #[test]
+    fn test_garbage_file_is_decode_error() {
+        let result = verify_temp("junk.mp3", |path| std::fs::write(path, vec![0x5au8; 4096]).unwrap());
+        assert_eq!(result.status, VerifyStatus::DecodeError);
+        assert!(result.detail.is_some());
+    }
+
+    #[test]
+    fn test_missing_file_is_error() {
+        assert!(verify_file(Path::new("/nonexistent/windchime-verify.flac"), &|| false).is_err());
+    }
+
+    #[test]
+    fn test_classify_decode_errors() {
+        let stats = DecodeStats { frames: 8000, bad_packets: 1, first_error: Some("bad frame".to_string()) };
+        assert_eq!(classify(&stats, None, Some(8000)), VerifyResult::ok());
+
+        let stats = DecodeStats { frames: 8000, bad_packets: 5, first_error: Some("bad frame".to_string()) };
+        let result = classify(&stats, None, Some(8000));
+        assert_eq!(result.status, VerifyStatus::DecodeError);
+        assert_eq!(result.detail.as_deref(), Some("5 个数据包解码失败: bad frame"));
+
+        // 差距在容差内不算截断
+        let stats = DecodeStats { frames: 7500, ..Default::default() };
+        assert_eq!(classify(&stats, Some(8000), Some(8000)), VerifyResult::ok());
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for status in [VerifyStatus::Ok, VerifyStatus::Truncated, VerifyStatus::DecodeError] {
+            assert_eq!(VerifyStatus::parse(status.as_str()), Some(status));
+        }
+        assert!(!VerifyStatus::Ok.is_broken());
+        assert!(VerifyStatus::Truncated.is_broken());
+    }
+}
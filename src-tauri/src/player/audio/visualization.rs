@@ -0,0 +1,270 @@
+// 播放可视化分析
+//
+// 在解码输出和Sink之间插入的分析抽头，为频谱和电平表提供数据：
+// - 样本原样透传，不引入额外延迟；只在启用时下混为单声道并分批写入环形缓冲区
+// - 音频线程只尝试加锁，竞争时丢弃这一批，绝不阻塞播放
+// - PlaybackActor 约每秒30次对最近的样本做 FFT，生成峰值、RMS 和对数分布的频段幅度
+// 关闭时抽头只读取一个原子标志，没有其他开销。
+
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::fingerprint::fft;
+
+/// 频段数量
+pub const BAND_COUNT: usize = 32;
+
+/// 分析间隔（约30Hz）
+pub const ANALYSIS_INTERVAL: Duration = Duration::from_millis(33);
+
+/// FFT长度
+const FFT_SIZE: usize = 2048;
+
+/// 音频线程每积累这么多帧写入一次缓冲区
+const BATCH_FRAMES: usize = 256;
+
+/// 频段范围(Hz)
+const MIN_FREQ: f64 = 20.0;
+const MAX_FREQ: f64 = 20_000.0;
+
+/// 频段幅度映射到0~1的分贝范围
+const MIN_DB: f32 = -80.0;
+
+/// 超过这段时间没有新样本（暂停、停止）时不再输出数据
+const STALE_AFTER: Duration = Duration::from_millis(200);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static BUFFER: Lazy<Mutex<TapBuffer>> = Lazy::new(|| Mutex::new(TapBuffer::default()));
+
+/// 最近播放的单声道样本
+#[derive(Default)]
+struct TapBuffer {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+    updated_at: Option<Instant>,
+}
+
+/// 启用或关闭分析，关闭时清空缓冲区
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        if let Ok(mut buffer) = BUFFER.lock() {
+            *buffer = TapBuffer::default();
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 分析最近的样本：`[峰值, RMS, 频段0..BAND_COUNT]`，均为0~1
+///
+/// 未启用或最近没有新样本（暂停、停止）时返回None
+pub fn analyze() -> Option<Vec<f32>> {
+    if !is_enabled() {
+        return None;
+    }
+    let (samples, sample_rate) = {
+        let buffer = BUFFER.lock().ok()?;
+        if buffer.updated_at.is_none_or(|t| t.elapsed() > STALE_AFTER) || buffer.samples.is_empty() {
+            return None;
+        }
+        (buffer.samples.iter().copied().collect::<Vec<f32>>(), buffer.sample_rate)
+    };
+    Some(analyze_samples(&samples, sample_rate))
+}
+
+/// 计算一段单声道样本的峰值、RMS和频段幅度
+pub fn analyze_samples(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let mut data = Vec::with_capacity(2 + BAND_COUNT);
+    let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    let rms = if samples.is_empty() {
+        0.0
+    } else {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    };
+    data.push(peak.min(1.0));
+    data.push(rms.min(1.0));
+    data.extend(band_magnitudes(samples, sample_rate));
+    data
+}
+
+/// 加汉宁窗做FFT，按对数间隔的频段取最大幅度，映射到0~1
+fn band_magnitudes(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    if sample_rate == 0 {
+        return vec![0.0; BAND_COUNT];
+    }
+    // 不足一帧时前面补零
+    let offset = FFT_SIZE.saturating_sub(samples.len());
+    let tail = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+    let mut re = vec![0.0f64; FFT_SIZE];
+    let mut im = vec![0.0f64; FFT_SIZE];
+    let mut window_sum = 0.0;
+    for (i, value) in re.iter_mut().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / (FFT_SIZE - 1) as f64).cos();
+        window_sum += window;
+        if i >= offset {
+            *value = tail[i - offset] as f64 * window;
+        }
+    }
+    fft(&mut re, &mut im);
+
+    let bin_hz = sample_rate as f64 / FFT_SIZE as f64;
+    let max_freq = MAX_FREQ.min(sample_rate as f64 / 2.0);
+    let ratio = (max_freq / MIN_FREQ).powf(1.0 / BAND_COUNT as f64);
+    (0..BAND_COUNT)
+        .map(|band| {
+            let low = MIN_FREQ * ratio.powi(band as i32);
+            let high = low * ratio;
+            let first = ((low / bin_hz).floor() as usize).max(1);
+            let last = ((high / bin_hz).ceil() as usize).clamp(first + 1, FFT_SIZE / 2);
+            let magnitude = (first..last)
+                .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+                .fold(0.0, f64::max);
+            // 单边谱幅度：正弦波满幅为1
+            let amplitude = (2.0 * magnitude / window_sum) as f32;
+            let db = 20.0 * amplitude.max(1e-9).log10();
+            ((db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/// 可视化分析抽头
+pub struct VisualizationTap<S> {
+    inner: S,
+    channels: u16,
+    sample_rate: u32,
+    channel_index: u16,
+    frame_sum: f32,
+    batch: Vec<f32>,
+}
+
+impl<S> VisualizationTap<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    pub fn new(inner: S) -> Self {
+        let channels = inner.channels().max(1);
+        let sample_rate = inner.sample_rate();
+        Self {
+            inner,
+            channels,
+            sample_rate,
+            channel_index: 0,
+            frame_sum: 0.0,
+            batch: Vec::new(),
+        }
+    }
+
+    /// 把一批样本写入缓冲区；正在被读取时丢弃，不等待
+    fn flush(&mut self) {
+        if let Ok(mut buffer) = BUFFER.try_lock() {
+            if buffer.sample_rate != self.sample_rate {
+                buffer.samples.clear();
+                buffer.sample_rate = self.sample_rate;
+            }
+            let len = buffer.samples.len();
+            let overflow = (len + self.batch.len()).saturating_sub(FFT_SIZE).min(len);
+            buffer.samples.drain(..overflow);
+            buffer.samples.extend(self.batch.iter().copied());
+            buffer.updated_at = Some(Instant::now());
+        }
+        self.batch.clear();
+    }
+}
+
+impl<S> Iterator for VisualizationTap<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        if !is_enabled() {
+            return Some(sample);
+        }
+
+        self.frame_sum += sample as f32 / 32768.0;
+        self.channel_index += 1;
+        if self.channel_index == self.channels {
+            self.batch.push(self.frame_sum / self.channels as f32);
+            self.frame_sum = 0.0;
+            self.channel_index = 0;
+            if self.batch.len() >= BATCH_FRAMES {
+                self.flush();
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S> rodio::Source for VisualizationTap<S>
+where
+    S: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, amplitude: f64, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| ((2.0 * PI * freq * i as f64 / sample_rate as f64).sin() * amplitude) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_lands_in_its_band() {
+        let data = analyze_samples(&sine(1000.0, 0.5, 44100, FFT_SIZE), 44100);
+        assert_eq!(data.len(), 2 + BAND_COUNT);
+        assert!((data[0] - 0.5).abs() < 0.01);
+        assert!((data[1] - 0.5 / 2f32.sqrt()).abs() < 0.01);
+
+        let bands = &data[2..];
+        let loudest = (0..BAND_COUNT).max_by(|&a, &b| bands[a].total_cmp(&bands[b])).unwrap();
+        let ratio = (MAX_FREQ / MIN_FREQ).powf(1.0 / BAND_COUNT as f64);
+        let low = MIN_FREQ * ratio.powi(loudest as i32);
+        assert!(low <= 1000.0 && 1000.0 < low * ratio * 1.1, "band {} starts at {}Hz", loudest, low);
+        // -6dB 约映射到 0.92
+        assert!(bands[loudest] > 0.85);
+        assert!(bands[0] < 0.5);
+    }
+
+    #[test]
+    fn test_silence_and_short_input() {
+        let data = analyze_samples(&[0.0; 100], 48000);
+        assert!(data.iter().all(|v| *v == 0.0));
+        assert_eq!(analyze_samples(&[], 0).len(), 2 + BAND_COUNT);
+    }
+
+    #[test]
+    fn test_tap_passes_samples_through() {
+        let input: Vec<i16> = (0..4096).map(|i| (i % 200) as i16 * 100).collect();
+        let tap = VisualizationTap::new(rodio::buffer::SamplesBuffer::new(2, 44100, input.clone()));
+        let output: Vec<i16> = tap.collect();
+        assert_eq!(output, input);
+    }
+}
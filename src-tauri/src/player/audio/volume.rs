@@ -0,0 +1,156 @@
+// 音量曲线和防削波
+//
+// 界面上的音量（0.0 - 1.0）按人耳的感知换算为输出增益：
+// - 分贝曲线：音量线性对应 -range_db..0 dB，最低10%再线性过渡到静音
+// - 线性曲线：直接作为增益（旧行为）
+// PlayerState.volume 始终保存界面上的值，只在设置Sink音量时换算。
+//
+// 前置放大（负值即为均衡器提升预留的余量）和ReplayGain在解码输出上以浮点计算，
+// 再经过软限幅：超过 -1 dBFS 的部分平滑压缩，叠加后的增益不会削波。
+
+use serde::{Deserialize, Serialize};
+
+/// 分贝曲线的动态范围（dB）
+pub const MIN_RANGE_DB: f32 = 20.0;
+pub const MAX_RANGE_DB: f32 = 100.0;
+
+/// 前置放大范围（dB）
+pub const MIN_PREAMP_DB: f32 = -12.0;
+pub const MAX_PREAMP_DB: f32 = 12.0;
+
+/// 软限幅的起始电平（-1 dBFS）
+pub const SOFT_LIMIT_THRESHOLD: f32 = 0.891_250_9;
+
+/// 低于该音量时从曲线最低点线性过渡到静音
+const ROLLOFF_VOLUME: f32 = 0.1;
+
+/// 音量曲线
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeCurve {
+    /// 线性
+    Linear,
+    /// 分贝（感知均匀）
+    #[default]
+    Decibel,
+}
+
+/// 音量设置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSettings {
+    /// 音量曲线
+    pub curve: VolumeCurve,
+    /// 分贝曲线的动态范围（dB），音量最低档对应 -range_db
+    pub range_db: f32,
+    /// 前置放大（dB），负值为均衡器提升预留余量，下一首曲目起生效
+    pub preamp_db: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            curve: VolumeCurve::Decibel,
+            range_db: 60.0,
+            preamp_db: 0.0,
+        }
+    }
+}
+
+impl VolumeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_RANGE_DB..=MAX_RANGE_DB).contains(&self.range_db) {
+            return Err(format!("音量曲线范围必须在{}dB到{}dB之间", MIN_RANGE_DB, MAX_RANGE_DB));
+        }
+        if !(MIN_PREAMP_DB..=MAX_PREAMP_DB).contains(&self.preamp_db) {
+            return Err(format!("前置放大必须在{}dB到+{}dB之间", MIN_PREAMP_DB, MAX_PREAMP_DB));
+        }
+        Ok(())
+    }
+
+    /// 界面音量换算为Sink增益（0.0 - 1.0）
+    pub fn gain(&self, volume: f32) -> f32 {
+        let volume = volume.clamp(0.0, 1.0);
+        match self.curve {
+            VolumeCurve::Linear => volume,
+            VolumeCurve::Decibel => {
+                if volume <= 0.0 {
+                    return 0.0;
+                }
+                let gain = 10f32.powf(-self.range_db * (1.0 - volume) / 20.0);
+                if volume < ROLLOFF_VOLUME {
+                    gain * volume / ROLLOFF_VOLUME
+                } else {
+                    gain
+                }
+            }
+        }
+    }
+}
+
+/// 软限幅：-1 dBFS 以下原样输出，以上平滑压缩，输出不超过满幅
+#[inline]
+pub fn soft_limit(x: f32) -> f32 {
+    let magnitude = x.abs();
+    if magnitude <= SOFT_LIMIT_THRESHOLD {
+        return x;
+    }
+    let knee = 1.0 - SOFT_LIMIT_THRESHOLD;
+    let limited = SOFT_LIMIT_THRESHOLD + knee * ((magnitude - SOFT_LIMIT_THRESHOLD) / knee).tanh();
+    limited.min(1.0).copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decibel_curve_endpoints() {
+        let settings = VolumeSettings::default();
+        assert_eq!(settings.gain(0.0), 0.0);
+        assert_eq!(settings.gain(1.0), 1.0);
+        // 一半音量为 -30dB
+        assert!((settings.gain(0.5) - 0.031_6).abs() < 0.001);
+        // 超出范围的值按端点处理
+        assert_eq!(settings.gain(-0.5), 0.0);
+        assert_eq!(settings.gain(1.5), 1.0);
+    }
+
+    #[test]
+    fn test_curves_are_monotonic() {
+        for curve in [VolumeCurve::Decibel, VolumeCurve::Linear] {
+            for range_db in [MIN_RANGE_DB, 60.0, MAX_RANGE_DB] {
+                let settings = VolumeSettings { curve, range_db, ..Default::default() };
+                let mut previous = settings.gain(0.0);
+                for step in 1..=1000 {
+                    let gain = settings.gain(step as f32 / 1000.0);
+                    assert!(gain > previous, "{:?} {}dB 在 {} 处不单调", curve, range_db, step);
+                    previous = gain;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_linear_curve() {
+        let settings = VolumeSettings { curve: VolumeCurve::Linear, ..Default::default() };
+        assert_eq!(settings.gain(0.25), 0.25);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(VolumeSettings::default().validate().is_ok());
+        assert!(VolumeSettings { range_db: 5.0, ..Default::default() }.validate().is_err());
+        assert!(VolumeSettings { preamp_db: 20.0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_soft_limit() {
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert_eq!(soft_limit(-SOFT_LIMIT_THRESHOLD), -SOFT_LIMIT_THRESHOLD);
+        assert!(soft_limit(4.0) <= 1.0);
+        assert!(soft_limit(-4.0) >= -1.0);
+        assert!(soft_limit(1.2) > soft_limit(1.0));
+        assert!(soft_limit(1.0) > SOFT_LIMIT_THRESHOLD);
+    }
+}
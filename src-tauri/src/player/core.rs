@@ -16,13 +16,14 @@ use tauri::async_runtime::JoinHandle;
 
 use super::actors::{
     AudioActor, AudioActorHandle,
-    PlaybackActor, PlaybackActorHandle,
+    PlaybackActor, PlaybackActorHandle, PlaybackLinks,
     PlaylistActor, PlaylistActorHandle,
     PreloadActor, PreloadActorHandle,
     StateActor, StateActorHandle,
+    DEFAULT_PRELOAD_LEAD_MS,
 };
 use super::types::{
-    Track, PlayerState, PlayerEvent, PlayerCommand, Result, PlayerError,
+    Track, PlayerState, PlayerEvent, PlayerCommand, RemoteOutputCommand, Result, PlayerError,
 };
 
 #[cfg(test)]
@@ -41,6 +42,8 @@ pub struct PlayerCoreConfig {
     pub preload_cache_size_mb: usize,
     /// 是否启用智能预加载
     pub enable_preload: bool,
+    /// 距当前曲目结束多久开始预加载下一曲（毫秒）
+    pub preload_lead_ms: u64,
 }
 
 impl Default for PlayerCoreConfig {
@@ -48,9 +51,10 @@ impl Default for PlayerCoreConfig {
         Self {
             event_channel_capacity: 100,
             auto_init_audio: false, // 懒加载
-            preload_cache_capacity: 3, // 最多缓存3首歌曲
+            preload_cache_capacity: 2, // 只为下一曲预加载，最多缓存2首
             preload_cache_size_mb: 150, // 最大缓存150MB
             enable_preload: true, // 默认启用预加载
+            preload_lead_ms: DEFAULT_PRELOAD_LEAD_MS,
         }
     }
 }
@@ -92,6 +96,9 @@ pub struct PlayerCore {
     
     /// 最新播放请求时间戳（用于快速切歌优化）
     latest_play_timestamp: Arc<AtomicI64>,
+    
+    /// 投屏时的远程输出（None为本地输出）
+    remote_output: Option<mpsc::UnboundedSender<RemoteOutputCommand>>,
 }
 
 impl PlayerCore {
@@ -103,44 +110,34 @@ impl PlayerCore {
     /// # 返回
     /// - `Result<Self>`: PlayerCore实例
     pub async fn new(config: PlayerCoreConfig) -> Result<Self> {
-        println!("🚀 [CORE] 开始创建PlayerCore...");
         log::info!("🚀 开始创建PlayerCore...");
         
         // 创建事件通道
-        println!("📡 [CORE] 创建事件通道...");
         log::info!("📡 创建事件通道...");
         let (event_tx, event_rx) = mpsc::channel(config.event_channel_capacity);
-        println!("✅ [CORE] 事件通道创建完成");
         log::info!("✅ 事件通道创建完成");
         
         // 创建Audio Actor
-        println!("🎧 [CORE] 创建AudioActor...");
         log::info!("🎧 创建AudioActor...");
         let (audio_actor, audio_tx) = AudioActor::new(event_tx.clone());
         let audio_handle = AudioActorHandle::new(audio_tx);
-        println!("✅ [CORE] AudioActor创建完成");
         log::info!("✅ AudioActor创建完成");
         
         // 创建Playlist Actor
-        println!("📋 [CORE] 创建PlaylistActor...");
         log::info!("📋 创建PlaylistActor...");
         let (playlist_actor, playlist_tx) = PlaylistActor::new(event_tx.clone());
         let playlist_handle = PlaylistActorHandle::new(playlist_tx);
-        println!("✅ [CORE] PlaylistActor创建完成");
         log::info!("✅ PlaylistActor创建完成");
         
         // 创建State Actor
-        println!("📊 [CORE] 创建StateActor...");
         log::info!("📊 创建StateActor...");
         let (state_actor, state_tx, state_watch) = StateActor::new(event_tx.clone());
         let state = Arc::new(RwLock::new(PlayerState::default()));
         let state_handle = StateActorHandle::new(state_tx, state);
-        println!("✅ [CORE] StateActor创建完成");
         log::info!("✅ StateActor创建完成");
         
         // 创建Preload Actor（可选）
         let (preload_actor, preload_handle) = if config.enable_preload {
-            println!("🔄 [CORE] 创建PreloadActor...");
             log::info!("🔄 创建PreloadActor...");
             let (preload_tx, preload_rx) = mpsc::channel(100);
             let actor = PreloadActor::new(
@@ -151,42 +148,46 @@ impl PlayerCore {
                 config.preload_cache_size_mb,
             );
             let handle = PreloadActorHandle::new(preload_tx);
-            println!("✅ [CORE] PreloadActor创建完成");
             log::info!("✅ PreloadActor创建完成");
             (Some(actor), Some(handle))
         } else {
-            println!("⏭️ [CORE] 预加载功能已禁用");
             log::info!("⏭️ 预加载功能已禁用");
             (None, None)
         };
         
         // 启动所有Actor
-        println!("🚀 [CORE] 开始启动所有Actor...");
         log::info!("🚀 开始启动所有Actor...");
         drop(audio_actor); // AudioActor暂不使用
         
         // PlaybackActor在独立线程中运行（因为AudioDevice不是Send）
         // 关键：在线程内部创建PlaybackActor，避免跨线程传递
-        println!("🧵 [CORE] 创建PlaybackActor独立线程...");
         log::info!("🧵 创建PlaybackActor独立线程...");
         
         let event_tx_for_playback = event_tx.clone();
         let state_watch_for_playback = state_watch.clone();
+        let preload_lead_ms = config.preload_lead_ms;
         let (playback_tx, playback_rx) = mpsc::channel(100);
         let playback_tx_clone = playback_tx.clone();
         let playback_handle = PlaybackActorHandle::new(playback_tx);
+        // 自动切歌（无缝播放、交叉淡入淡出）需要推进播放列表、更新状态
+        let links = PlaybackLinks {
+            playlist: playlist_handle.clone(),
+            state: state_handle.clone(),
+            preload: preload_handle.clone(),
+        };
         
         // 🔧 P1修复：使用catch_unwind处理panic，防止线程崩溃
         let playback_thread = thread::Builder::new()
             .name("playback-actor".to_string())
             .spawn(move || {
-                println!("🧵 [CORE] PlaybackActor线程已启动");
                 log::info!("🧵 PlaybackActor线程已启动");
                 
                 // 使用catch_unwind捕获panic
                 let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                     // 在线程内部创建PlaybackActor（避免Send问题）
-                    let playback_actor = PlaybackActor::new_with_receiver(playback_rx, playback_tx_clone, event_tx_for_playback, state_watch_for_playback);
+                    let playback_actor = PlaybackActor::new_with_receiver(playback_rx, playback_tx_clone, event_tx_for_playback, state_watch_for_playback)
+                        .with_links(links)
+                        .with_preload_lead(preload_lead_ms);
                     
                     // 🔧 修复：使用多线程runtime以支持流式播放中的block_in_place
                     // 虽然AudioDevice不是Send，但PlaybackActor已经在专用线程中，
@@ -198,14 +199,11 @@ impl PlayerCore {
                         .build()
                         .expect("创建playback runtime失败");
                     
-                    println!("⚡ [CORE] PlaybackActor runtime已创建");
                     log::info!("⚡ PlaybackActor runtime已创建");
                     // 在该runtime上执行playback_actor
                     rt.block_on(async move {
-                        println!("▶️ [CORE] PlaybackActor.run() 开始执行");
                         log::info!("▶️ PlaybackActor.run() 开始执行");
                         playback_actor.run().await;
-                        println!("⏹️ [CORE] PlaybackActor已退出");
                         log::info!("⏹️ PlaybackActor已退出");
                     });
                 }));
@@ -221,19 +219,17 @@ impl PlayerCore {
                     };
                     
                     log::error!("❌ [CORE] PlaybackActor线程panic: {}", panic_msg);
-                    println!("❌ [CORE] PlaybackActor线程panic: {}", panic_msg);
                 }
             })
             .map_err(|e| PlayerError::Internal(format!("创建playback线程失败: {}", e)))?;
         
-        println!("✅ [CORE] PlaybackActor线程创建成功");
         log::info!("✅ PlaybackActor线程创建成功");
         
         // 🔧 修复：使用tauri::async_runtime::spawn确保Actor在正确的runtime中运行
-        println!("🚀 [CORE] 启动PlaylistActor、StateActor和PreloadActor...");
         log::info!("🚀 启动PlaylistActor、StateActor和PreloadActor...");
         let mut handles = vec![
-            tauri::async_runtime::spawn(playlist_actor.run()),
+            // PlaylistActor决定下一曲，由它通知PreloadActor
+            tauri::async_runtime::spawn(playlist_actor.with_preload(preload_handle.clone()).run()),
             tauri::async_runtime::spawn(state_actor.run()),
         ];
         
@@ -242,12 +238,10 @@ impl PlayerCore {
             handles.push(tauri::async_runtime::spawn(preload_actor.run()));
         }
         
-        println!("🎉 [CORE] PlayerCore创建完成，所有Actor已启动！");
         log::info!("🎉 PlayerCore创建完成，所有Actor已启动！");
         
         // 如果配置要求，初始化音频设备
         if config.auto_init_audio {
-            println!("🎵 [CORE] 自动初始化音频设备");
             log::info!("🎵 自动初始化音频设备");
             let _ = audio_handle.initialize().await;
         }
@@ -264,6 +258,7 @@ impl PlayerCore {
             playback_thread: Some(playback_thread),
             config,
             latest_play_timestamp: Arc::new(AtomicI64::new(0)),
+            remote_output: None,
         })
     }
     
@@ -277,19 +272,17 @@ impl PlayerCore {
     /// 
     /// 这是主要的命令入口，分发命令到对应的Actor
     pub async fn handle_command(&mut self, command: PlayerCommand) -> Result<()> {
-        println!("📨 [CORE] 处理命令: {:?}", command);
         log::info!("📨 [CORE] 处理命令: {:?}", command);
         
         match command {
             // 播放控制命令
             PlayerCommand::Play(track_id, timestamp) => {
-                println!("▶️ [CORE] 处理Play命令: track_id={}, timestamp={}", track_id, timestamp);
                 log::info!("▶️ [CORE] 处理Play命令: track_id={}, timestamp={}", track_id, timestamp);
                 
                 // 🎯 关键优化：在入口处立即检查时间戳，避免过期请求执行任何操作
                 let current_latest = self.latest_play_timestamp.load(Ordering::SeqCst);
                 if timestamp < current_latest {
-                    println!("⏭️ [CORE] 播放请求已过期（入口检查: 请求={}, 最新={}），立即拒绝", timestamp, current_latest);
+                    log::debug!("⏭️ [CORE] 播放请求已过期（入口检查: 请求={}, 最新={}），立即拒绝", timestamp, current_latest);
                     log::info!("⏭️ [CORE] 播放请求已过期（入口检查），立即拒绝");
                     return Ok(()); // 直接返回，不执行任何操作
                 }
@@ -300,23 +293,71 @@ impl PlayerCore {
                 self.handle_play(track_id, timestamp).await
             }
             PlayerCommand::Pause => {
-                self.playback_handle.pause().await?;
+                if !self.send_remote(RemoteOutputCommand::Pause)? {
+                    self.playback_handle.pause().await?;
+                }
                 self.state_handle.update_playing_state(false).await;
                 Ok(())
             }
             PlayerCommand::Resume => {
-                self.playback_handle.resume().await?;
+                if !self.send_remote(RemoteOutputCommand::Resume)? {
+                    self.playback_handle.resume().await?;
+                }
                 self.state_handle.update_playing_state(true).await;
                 Ok(())
             }
             PlayerCommand::Stop => {
-                self.playback_handle.stop().await?;
+                if !self.send_remote(RemoteOutputCommand::Stop)? {
+                    self.playback_handle.stop().await?;
+                }
+                // 切歌时内部也会停止播放，只有用户停止才清除睡眠定时器
+                self.playback_handle.set_sleep_timer(None).await?;
                 self.state_handle.update_playing_state(false).await;
                 Ok(())
             }
             PlayerCommand::Seek(position_ms) => {
-                // 执行seek操作（方案5：依赖后台缓存）
-                self.playback_handle.seek(position_ms).await?;
+                self.handle_seek(position_ms).await
+            }
+            PlayerCommand::SeekAndWait { position_ms, reply } => {
+                let result = self.handle_seek(position_ms).await;
+                let _ = reply.send(result);
+                Ok(())
+            }
+            PlayerCommand::SeekRelative(steps) => {
+                self.playback_handle.seek_relative(steps).await
+            }
+            PlayerCommand::ResumeAt { track_id, position_ms } => {
+                self.playback_handle.resume_at(track_id, position_ms).await
+            }
+            PlayerCommand::SyncExternalPosition { position_ms, is_playing } => {
+                self.playback_handle.sync_external_position(position_ms, is_playing).await?;
+                self.state_handle.update_position(position_ms).await;
+                self.state_handle.update_playing_state(is_playing).await;
+                Ok(())
+            }
+            PlayerCommand::HandoffToNative { position_ms, reply } => {
+                let result = self.playback_handle.handoff_to_native(position_ms).await;
+                if result.is_ok() {
+                    self.state_handle.update_position(position_ms).await;
+                }
+                let _ = reply.send(result);
+                Ok(())
+            }
+            PlayerCommand::SetChapters { track_id, chapters } => {
+                self.playback_handle.set_chapters(track_id, chapters).await
+            }
+            PlayerCommand::SetTrimPoints { track_id, trim } => {
+                self.playback_handle.set_trim_points(track_id, trim).await
+            }
+            PlayerCommand::NextChapter => {
+                self.playback_handle.seek_chapter(true).await
+            }
+            PlayerCommand::PreviousChapter => {
+                self.playback_handle.seek_chapter(false).await
+            }
+            PlayerCommand::SetLoopRegion { region, reply } => {
+                let result = self.playback_handle.set_loop_region(region).await;
+                let _ = reply.send(result);
                 Ok(())
             }
             PlayerCommand::GetPosition(reply) => {
@@ -334,49 +375,107 @@ impl PlayerCore {
             
             // 音量控制
             PlayerCommand::SetVolume(volume) => {
-                self.playback_handle.set_volume(volume).await?;
+                if !self.send_remote(RemoteOutputCommand::SetVolume(volume))? {
+                    self.playback_handle.set_volume(volume).await?;
+                }
                 self.state_handle.update_volume(volume).await;
                 Ok(())
             }
+            PlayerCommand::SetVolumeSettings(settings) => {
+                self.playback_handle.set_volume_settings(settings).await
+            }
+            PlayerCommand::SetPlaybackRate(rate) => {
+                self.playback_handle.set_playback_rate(rate).await
+            }
+            PlayerCommand::SetPreservePitch(preserve_pitch) => {
+                self.playback_handle.set_preserve_pitch(preserve_pitch).await
+            }
+            PlayerCommand::GetPlaybackRate(reply) => {
+                let _ = reply.send(self.state_watch.borrow().playback_rate);
+                Ok(())
+            }
             
             // 播放列表命令
             PlayerCommand::LoadPlaylist(tracks) => {
-                println!("📋 [CORE] 处理LoadPlaylist命令: {} 首曲目", tracks.len());
                 log::info!("📋 [CORE] 处理LoadPlaylist命令: {} 首曲目", tracks.len());
                 
-                println!("📋 [CORE] 调用playlist_handle.load_playlist...");
-                self.playlist_handle.load_playlist(tracks.clone()).await?;
-                println!("✅ [CORE] playlist_handle.load_playlist 完成");
+                log::debug!("📋 [CORE] 调用playlist_handle.load_playlist...");
+                self.playlist_handle.load_playlist(tracks).await?;
+                log::debug!("✅ [CORE] playlist_handle.load_playlist 完成");
                 
-                // 通知PreloadActor播放列表已更新
-                if let Some(preload) = &self.preload_handle {
-                    println!("🔄 [CORE] 通知PreloadActor更新播放列表...");
-                    let current_index = self.playlist_handle.get_current_index().await.ok().flatten();
-                    let _ = preload.update_playlist(tracks, current_index).await;
-                    println!("✅ [CORE] PreloadActor通知完成");
-                }
+                // 已追加的下一首来自旧列表，需要重新准备
+                self.playback_handle.cancel_gapless_next().await?;
                 
-                println!("✅ [CORE] LoadPlaylist命令处理完成");
                 log::info!("✅ [CORE] LoadPlaylist命令处理完成");
                 Ok(())
             }
             PlayerCommand::SetShuffle(enabled) => {
                 self.playlist_handle.set_shuffle(enabled).await?;
                 self.state_handle.update_shuffle(enabled).await;
-                // 通知PreloadActor播放模式已更新
-                if let Some(preload) = &self.preload_handle {
-                    let state = self.get_state();
-                    let _ = preload.update_play_mode(state.repeat_mode, enabled).await;
-                }
+                self.playback_handle.cancel_gapless_next().await?;
+                Ok(())
+            }
+            PlayerCommand::SetShuffleSkipBroken(enabled) => {
+                self.playlist_handle.set_shuffle_skip_broken(enabled).await?;
+                // 已排好的下一首可能被跳过
+                self.playback_handle.cancel_gapless_next().await?;
                 Ok(())
             }
             PlayerCommand::SetRepeatMode(mode) => {
                 self.playlist_handle.set_repeat_mode(mode).await?;
                 self.state_handle.update_repeat_mode(mode).await;
-                // 通知PreloadActor播放模式已更新
-                if let Some(preload) = &self.preload_handle {
-                    let state = self.get_state();
-                    let _ = preload.update_play_mode(mode, state.shuffle).await;
+                self.playback_handle.cancel_gapless_next().await?;
+                Ok(())
+            }
+            
+            PlayerCommand::SetGapless(enabled) => {
+                self.playback_handle.set_gapless(enabled).await
+            }
+            PlayerCommand::SetCrossfade(duration_ms) => {
+                self.playback_handle.set_crossfade(duration_ms).await
+            }
+            PlayerCommand::SetSleepTimer(timer) => {
+                self.playback_handle.set_sleep_timer(timer).await
+            }
+            PlayerCommand::SetSleepFade(fade_secs) => {
+                self.playback_handle.set_sleep_fade(fade_secs).await
+            }
+            PlayerCommand::SetPauseFade(duration_ms) => {
+                self.playback_handle.set_pause_fade(duration_ms).await
+            }
+            PlayerCommand::SetSeekStep(step_ms) => {
+                self.playback_handle.set_seek_step(step_ms).await
+            }
+            PlayerCommand::SetPositionInterval(interval_ms) => {
+                self.playback_handle.set_position_interval(interval_ms).await
+            }
+            PlayerCommand::GetSleepTimer(reply) => {
+                let status = self.playback_handle.get_sleep_timer().await?;
+                let _ = reply.send(status);
+                Ok(())
+            }
+            
+            // 投屏
+            PlayerCommand::StartRemoteOutput(remote) => {
+                log::info!("📡 [CORE] 切换到远程输出");
+                self.playback_handle.stop().await?;
+                self.remote_output = Some(remote);
+                Ok(())
+            }
+            PlayerCommand::StopRemoteOutput { resume_at } => {
+                if self.remote_output.take().is_none() {
+                    return Ok(());
+                }
+                log::info!("🔈 [CORE] 恢复本地输出");
+                let track = self.get_state().current_track;
+                match (track, resume_at) {
+                    (Some(track), Some(position_ms)) => {
+                        let track_id = track.id;
+                        self.playback_handle.play(track).await?;
+                        self.playback_handle.resume_at(track_id, position_ms).await?;
+                        self.state_handle.update_playing_state(true).await;
+                    }
+                    _ => self.state_handle.update_playing_state(false).await,
                 }
                 Ok(())
             }
@@ -385,6 +484,30 @@ impl PlayerCore {
             PlayerCommand::ResetAudioDevice => {
                 self.audio_handle.reset().await
             }
+            PlayerCommand::SetOutputDevice(device_name) => {
+                self.playback_handle.set_output_device(device_name).await
+            }
+            PlayerCommand::SetOutputConfig(config) => {
+                self.playback_handle.set_output_config(config).await
+            }
+            PlayerCommand::GetOutputFormat(reply) => {
+                let format = self.playback_handle.get_output_format().await?;
+                let _ = reply.send(format);
+                Ok(())
+            }
+            PlayerCommand::GetStreamStats(reply) => {
+                let stats = self.playback_handle.get_stream_stats().await?;
+                let _ = reply.send(stats);
+                Ok(())
+            }
+            PlayerCommand::GetPreloadStats(reply) => {
+                let stats = match &self.preload_handle {
+                    Some(preload) => preload.get_cache_status().await.ok(),
+                    None => None,
+                };
+                let _ = reply.send(stats);
+                Ok(())
+            }
             
             // 关闭
             PlayerCommand::Shutdown => {
@@ -397,19 +520,18 @@ impl PlayerCore {
     async fn handle_play(&mut self, track_id: i64, timestamp: i64) -> Result<()> {
         use std::time::Instant;
         let start_time = Instant::now();
-        println!("🎵 [CORE] 处理播放命令: track_id={}, timestamp={}", track_id, timestamp);
         log::info!("🎵 [CORE] 处理播放命令: track_id={}, timestamp={}", track_id, timestamp);
         
         // 从播放列表获取曲目
         let step1 = Instant::now();
-        println!("📋 [CORE] 从播放列表获取曲目...");
+        log::debug!("📋 [CORE] 从播放列表获取曲目...");
         let track = match self.playlist_handle.jump_to(track_id).await {
             Ok(t) => {
-                println!("✅ [CORE] 曲目获取成功: {:?} (耗时: {}ms)", t.title, step1.elapsed().as_millis());
+                log::debug!("✅ [CORE] 曲目获取成功: {:?} (耗时: {}ms)", t.title, step1.elapsed().as_millis());
                 t
             }
             Err(e) => {
-                println!("❌ [CORE] 获取曲目失败: {}", e);
+                log::warn!("❌ [CORE] 获取曲目失败: {}", e);
                 return Err(e);
             }
         };
@@ -417,7 +539,14 @@ impl PlayerCore {
         // 检查时间戳（防止在获取曲目过程中有新请求）
         let latest_timestamp = self.latest_play_timestamp.load(Ordering::SeqCst);
         if timestamp < latest_timestamp {
-            println!("⏭️ [CORE] 播放请求已过期，跳过");
+            log::debug!("⏭️ [CORE] 播放请求已过期，跳过");
+            return Ok(());
+        }
+        
+        // 投屏时由远程设备播放
+        if self.send_remote(RemoteOutputCommand::Play(track.clone()))? {
+            self.state_handle.update_current_track(Some(track)).await;
+            self.state_handle.update_playing_state(true).await;
             return Ok(());
         }
         
@@ -426,42 +555,32 @@ impl PlayerCore {
         let current_state = self.get_state();
         if let Some(ref curr) = current_state.current_track {
             if curr.id != track.id {
-                println!("⏸️ [CORE] 先停止当前播放...");
+                log::debug!("⏸️ [CORE] 先停止当前播放...");
                 let _ = self.playback_handle.stop().await;
-                println!("✅ [CORE] 停止完成 (耗时: {}ms)", step2.elapsed().as_millis());
+                log::debug!("✅ [CORE] 停止完成 (耗时: {}ms)", step2.elapsed().as_millis());
             }
         }
         
         // 再次检查时间戳
         let latest_timestamp = self.latest_play_timestamp.load(Ordering::SeqCst);
         if timestamp < latest_timestamp {
-            println!("⏭️ [CORE] 播放请求已过期（播放前检查），跳过");
+            log::debug!("⏭️ [CORE] 播放请求已过期（播放前检查），跳过");
             return Ok(());
         }
         
         // 播放曲目
         let step3 = Instant::now();
-        println!("▶️ [CORE] 调用PlaybackActor播放...");
+        log::debug!("▶️ [CORE] 调用PlaybackActor播放...");
         self.playback_handle.play(track.clone()).await?;
-        println!("✅ [CORE] PlaybackActor播放完成 (耗时: {}ms)", step3.elapsed().as_millis());
+        log::debug!("✅ [CORE] PlaybackActor播放完成 (耗时: {}ms)", step3.elapsed().as_millis());
         
         // 更新状态（异步，不等待）
         let step4 = Instant::now();
         self.state_handle.update_current_track(Some(track.clone())).await;
         self.state_handle.update_playing_state(true).await;
-        println!("✅ [CORE] 状态更新完成 (耗时: {}ms)", step4.elapsed().as_millis());
+        log::debug!("✅ [CORE] 状态更新完成 (耗时: {}ms)", step4.elapsed().as_millis());
         
-        // 触发预加载（异步，不阻塞）
-        if let Some(preload) = &self.preload_handle {
-            let current_index = self.playlist_handle.get_current_index().await.ok().flatten().unwrap_or(0);
-            let playlist = self.playlist_handle.get_playlist().await.unwrap_or_default();
-            if !playlist.is_empty() {
-                let _ = preload.update_playlist(playlist.clone(), Some(current_index)).await;
-            }
-            let _ = preload.on_track_changed(track.clone(), current_index).await;
-        }
-        
-        println!("✅ [CORE] 播放命令处理完成 (总耗时: {}ms)", start_time.elapsed().as_millis());
+        log::debug!("✅ [CORE] 播放命令处理完成 (总耗时: {}ms)", start_time.elapsed().as_millis());
         Ok(())
     }
     
@@ -473,22 +592,18 @@ impl PlayerCore {
         match next_track {
             Some(track) => {
                 // 播放下一曲
-                self.playback_handle.play(track.clone()).await?;
-                self.state_handle.update_current_track(Some(track.clone())).await;
+                self.play_track(&track).await?;
+                self.state_handle.update_current_track(Some(track)).await;
                 self.state_handle.update_playing_state(true).await;
                 
-                // 触发预加载
-                if let Some(preload) = &self.preload_handle {
-                    let current_index = self.playlist_handle.get_current_index().await.ok().flatten().unwrap_or(0);
-                    let _ = preload.on_track_changed(track, current_index).await;
-                }
-                
                 Ok(())
             }
             None => {
                 // 没有下一曲，停止播放
                 log::info!("📋 播放列表已结束");
-                self.playback_handle.stop().await?;
+                if !self.send_remote(RemoteOutputCommand::Stop)? {
+                    self.playback_handle.stop().await?;
+                }
                 self.state_handle.update_playing_state(false).await;
                 Ok(())
             }
@@ -503,16 +618,10 @@ impl PlayerCore {
         match prev_track {
             Some(track) => {
                 // 播放上一曲
-                self.playback_handle.play(track.clone()).await?;
-                self.state_handle.update_current_track(Some(track.clone())).await;
+                self.play_track(&track).await?;
+                self.state_handle.update_current_track(Some(track)).await;
                 self.state_handle.update_playing_state(true).await;
                 
-                // 触发预加载
-                if let Some(preload) = &self.preload_handle {
-                    let current_index = self.playlist_handle.get_current_index().await.ok().flatten().unwrap_or(0);
-                    let _ = preload.on_track_changed(track, current_index).await;
-                }
-                
                 Ok(())
             }
             None => {
@@ -522,6 +631,33 @@ impl PlayerCore {
         }
     }
     
+    /// 播放曲目，投屏时发送到远程输出
+    async fn play_track(&self, track: &Track) -> Result<()> {
+        if self.send_remote(RemoteOutputCommand::Play(track.clone()))? {
+            return Ok(());
+        }
+        self.playback_handle.play(track.clone()).await
+    }
+    
+    /// 跳转，投屏时由远程设备跳转
+    async fn handle_seek(&self, position_ms: u64) -> Result<()> {
+        if self.send_remote(RemoteOutputCommand::Seek(position_ms))? {
+            return Ok(());
+        }
+        // 执行seek操作（方案5：依赖后台缓存）
+        self.playback_handle.seek(position_ms).await
+    }
+    
+    /// 投屏时把命令发送到远程输出，返回是否已发送（未投屏时返回false，由本地输出处理）
+    fn send_remote(&self, command: RemoteOutputCommand) -> Result<bool> {
+        match &self.remote_output {
+            Some(remote) => remote.send(command)
+                .map(|_| true)
+                .map_err(|_| PlayerError::Internal("投屏已断开".to_string())),
+            None => Ok(false),
+        }
+    }
+    
     /// 获取当前状态
     pub fn get_state(&self) -> PlayerState {
         self.state_handle.get_state()
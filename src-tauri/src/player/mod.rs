@@ -25,7 +25,9 @@ pub mod core;
 // 公开导出常用类型
 pub use types::{
     Track, RepeatMode,
-    PlayerCommand, PlayerEvent,
+    PlayerCommand, PlayerEvent, PositionUpdate, RemoteOutputCommand,
+    SleepTimer, SleepTimerStatus, Chapter,
+    MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS, DEFAULT_SLEEP_FADE_SECS,
 };
 
 // 内部使用的类型（暂不导出）
@@ -34,6 +36,11 @@ pub(crate) use types::{
     PlayerState, PlayerError, Result,
 };
 
+// 输出设备列表
+pub use audio::{OutputDeviceInfo, OutputConfig, OutputFormat, list_output_devices};
+pub use audio::visualization;
+pub use audio::silence::{SilenceSettings, TrimPoints};
+
 // 内部使用的音频模块类型（暂不导出）
 #[allow(unused_imports)]
 pub(crate) use audio::{
@@ -51,6 +58,15 @@ pub(crate) use actors::{
     StateActor, StateActorHandle,
 };
 
+// 暂停淡变和相对跳转的默认值
+pub use actors::{DEFAULT_PAUSE_FADE_MS, DEFAULT_SEEK_STEP_MS};
+
+// 位置事件上报间隔
+pub use actors::{DEFAULT_POSITION_INTERVAL_MS, MIN_POSITION_INTERVAL_MS, MAX_POSITION_INTERVAL_MS};
+
+// 预加载统计（调试用）
+pub use actors::PreloadStats;
+
 // 公开导出PlayerCore
 pub use core::{PlayerCore, PlayerCoreConfig};
 
@@ -0,0 +1,67 @@
+// 章节定义
+
+use serde::{Deserialize, Serialize};
+
+/// 后退到上一章节时，当前章节已播放超过该时长则回到本章开头(ms)
+pub const CHAPTER_RESTART_THRESHOLD_MS: u64 = 3_000;
+
+/// 曲目内的章节（有声书、DJ混音、整轨CUE）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chapter {
+    /// 从0开始的章节序号
+    pub index: u32,
+    pub title: Option<String>,
+    pub start_ms: u64,
+    /// 结束位置，最后一章未知时为None（到曲目结尾）
+    pub end_ms: Option<u64>,
+}
+
+/// 位置所在的章节（章节按开始位置排序）
+pub fn chapter_at(chapters: &[Chapter], position_ms: u64) -> Option<usize> {
+    chapters.iter().rposition(|c| c.start_ms <= position_ms)
+}
+
+/// 下一章节的开始位置，已在最后一章时为None
+pub fn next_chapter_start(chapters: &[Chapter], position_ms: u64) -> Option<u64> {
+    chapters.iter().map(|c| c.start_ms).find(|start| *start > position_ms)
+}
+
+/// 上一章节的开始位置；本章已播放超过阈值时回到本章开头，第一章之前回到曲目开头
+pub fn previous_chapter_start(chapters: &[Chapter], position_ms: u64) -> u64 {
+    let Some(current) = chapter_at(chapters, position_ms) else { return 0 };
+    let start = chapters[current].start_ms;
+    if position_ms.saturating_sub(start) > CHAPTER_RESTART_THRESHOLD_MS || current == 0 {
+        start
+    } else {
+        chapters[current - 1].start_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters(starts: &[u64]) -> Vec<Chapter> {
+        starts.iter().enumerate()
+            .map(|(i, &start_ms)| Chapter { index: i as u32, title: None, start_ms, end_ms: None })
+            .collect()
+    }
+
+    #[test]
+    fn test_chapter_navigation() {
+        let chapters = chapters(&[0, 60_000, 180_000]);
+        assert_eq!(chapter_at(&chapters, 0), Some(0));
+        assert_eq!(chapter_at(&chapters, 90_000), Some(1));
+        assert_eq!(chapter_at(&chapters, 500_000), Some(2));
+
+        assert_eq!(next_chapter_start(&chapters, 30_000), Some(60_000));
+        assert_eq!(next_chapter_start(&chapters, 60_000), Some(180_000));
+        assert_eq!(next_chapter_start(&chapters, 200_000), None);
+
+        // 本章刚开始时回到上一章，播放一段时间后回到本章开头
+        assert_eq!(previous_chapter_start(&chapters, 61_000), 0);
+        assert_eq!(previous_chapter_start(&chapters, 90_000), 60_000);
+        assert_eq!(previous_chapter_start(&chapters, 1_000), 0);
+        assert_eq!(previous_chapter_start(&[], 90_000), 0);
+    }
+}
@@ -1,6 +1,11 @@
 // 播放器命令定义
 
-use super::{track::Track, state::RepeatMode};
+use super::{track::Track, state::RepeatMode, sleep_timer::{SleepTimer, SleepTimerStatus}, chapter::Chapter};
+use super::Result;
+use super::super::audio::{OutputConfig, OutputFormat, VolumeSettings};
+use super::super::audio::silence::TrimPoints;
+use super::super::actors::preload_actor::PreloadStats;
+use crate::streaming::buffering::StreamStats;
 
 /// 播放器命令
 #[derive(Debug)]
@@ -20,6 +25,45 @@ pub enum PlayerCommand {
     /// 跳转到指定位置（毫秒）
     Seek(u64),
     
+    /// 跳转并等待结果（音频尚未缓存时返回 NotCached）
+    SeekAndWait {
+        position_ms: u64,
+        reply: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    
+    /// 按步长相对跳转（步数，负数表示后退），限制在曲目范围内
+    SeekRelative(i64),
+    
+    /// 续播到保存的位置（毫秒），曲目尚不能跳转时等缓存完成后再跳转
+    ResumeAt { track_id: i64, position_ms: u64 },
+    
+    /// 前端外部引擎（Web Audio）同步的位置和播放状态；首次同步时由外部引擎接管，暂停原生输出
+    SyncExternalPosition { position_ms: u64, is_playing: bool },
+    
+    /// 外部引擎交还原生播放：从指定位置（毫秒）开始原生播放当前曲目
+    HandoffToNative {
+        position_ms: u64,
+        reply: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    
+    /// 设置曲目的章节（切歌后由曲库提供）
+    SetChapters { track_id: i64, chapters: Vec<Chapter> },
+    
+    /// 设置曲目的静音裁剪点（切歌后由曲库提供，未开启跳过静音时为None）
+    SetTrimPoints { track_id: i64, trim: Option<TrimPoints> },
+    
+    /// 跳到下一章节
+    NextChapter,
+    
+    /// 跳到上一章节（本章已播放一段时间时回到本章开头）
+    PreviousChapter,
+    
+    /// 设置A-B循环区间（A点、B点，毫秒），None表示清除；换曲或停止时自动清除
+    SetLoopRegion {
+        region: Option<(u64, u64)>,
+        reply: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    
     /// 下一曲
     Next,
     
@@ -29,18 +73,78 @@ pub enum PlayerCommand {
     /// 设置音量（0.0 - 1.0）
     SetVolume(f32),
     
+    /// 设置音量曲线和前置放大
+    SetVolumeSettings(VolumeSettings),
+    
+    /// 设置播放速度（0.5 - 3.0，换曲时恢复为1.0）
+    SetPlaybackRate(f32),
+    
+    /// 设置变速时是否保持音高
+    SetPreservePitch(bool),
+    
+    /// 获取当前播放速度
+    GetPlaybackRate(tokio::sync::oneshot::Sender<f32>),
+    
     /// 设置重复模式
     SetRepeatMode(RepeatMode),
     
     /// 设置随机播放
     SetShuffle(bool),
     
+    /// 随机播放时是否跳过校验未通过的曲目
+    SetShuffleSkipBroken(bool),
+    
     /// 加载播放列表
     LoadPlaylist(Vec<Track>),
     
+    /// 设置无缝播放（提前把下一首追加到同一个Sink）
+    SetGapless(bool),
+    
+    /// 设置交叉淡入淡出时长（毫秒，0表示关闭）
+    SetCrossfade(u64),
+    
+    /// 设置睡眠定时器，None表示取消
+    SetSleepTimer(Option<SleepTimer>),
+    
+    /// 设置睡眠定时器触发时的淡出时长（秒）
+    SetSleepFade(u64),
+    
+    /// 设置暂停/恢复时的淡出淡入时长（毫秒，0表示关闭）
+    SetPauseFade(u64),
+    
+    /// 设置相对跳转步长（毫秒）
+    SetSeekStep(u64),
+    
+    /// 设置位置事件的上报间隔（毫秒）
+    SetPositionInterval(u64),
+    
+    /// 获取睡眠定时器状态
+    GetSleepTimer(tokio::sync::oneshot::Sender<Option<SleepTimerStatus>>),
+    
+    /// 切换输出设备（None为系统默认设备），正在播放的曲目从当前位置继续
+    SetOutputDevice(Option<String>),
+    
+    /// 设置输出格式（原始采样率、独占模式），正在播放的曲目从当前位置继续
+    SetOutputConfig(OutputConfig),
+    
+    /// 获取实际协商得到的输出格式（设备尚未打开时为None）
+    GetOutputFormat(tokio::sync::oneshot::Sender<Option<OutputFormat>>),
+    
+    /// 获取当前流的缓冲统计（不是流式播放时为None）
+    GetStreamStats(tokio::sync::oneshot::Sender<Option<StreamStats>>),
+    
+    /// 获取预加载缓存和命中统计（未启用预加载时为None）
+    GetPreloadStats(tokio::sync::oneshot::Sender<Option<PreloadStats>>),
+    
     /// 获取当前播放位置（毫秒）
     GetPosition(tokio::sync::oneshot::Sender<Option<u64>>),
     
+    /// 开始投屏：本地输出停止，播放控制转发到远程输出
+    StartRemoteOutput(tokio::sync::mpsc::UnboundedSender<RemoteOutputCommand>),
+    
+    /// 结束投屏，恢复本地输出；resume_at 为远程设备上的播放位置，本地从该位置继续播放当前曲目
+    StopRemoteOutput { resume_at: Option<u64> },
+    
     /// 重置音频设备
     ResetAudioDevice,
     
@@ -57,13 +161,43 @@ impl PlayerCommand {
             PlayerCommand::Resume => "Resume",
             PlayerCommand::Stop => "Stop",
             PlayerCommand::Seek(_) => "Seek",
+            PlayerCommand::SeekAndWait { .. } => "SeekAndWait",
+            PlayerCommand::SeekRelative(_) => "SeekRelative",
+            PlayerCommand::ResumeAt { .. } => "ResumeAt",
+            PlayerCommand::SyncExternalPosition { .. } => "SyncExternalPosition",
+            PlayerCommand::HandoffToNative { .. } => "HandoffToNative",
+            PlayerCommand::SetChapters { .. } => "SetChapters",
+            PlayerCommand::SetTrimPoints { .. } => "SetTrimPoints",
+            PlayerCommand::NextChapter => "NextChapter",
+            PlayerCommand::PreviousChapter => "PreviousChapter",
+            PlayerCommand::SetLoopRegion { .. } => "SetLoopRegion",
             PlayerCommand::Next => "Next",
             PlayerCommand::Previous => "Previous",
             PlayerCommand::SetVolume(_) => "SetVolume",
+            PlayerCommand::SetVolumeSettings(_) => "SetVolumeSettings",
+            PlayerCommand::SetPlaybackRate(_) => "SetPlaybackRate",
+            PlayerCommand::SetPreservePitch(_) => "SetPreservePitch",
+            PlayerCommand::GetPlaybackRate(_) => "GetPlaybackRate",
             PlayerCommand::SetRepeatMode(_) => "SetRepeatMode",
             PlayerCommand::SetShuffle(_) => "SetShuffle",
+            PlayerCommand::SetShuffleSkipBroken(_) => "SetShuffleSkipBroken",
             PlayerCommand::LoadPlaylist(_) => "LoadPlaylist",
+            PlayerCommand::SetGapless(_) => "SetGapless",
+            PlayerCommand::SetCrossfade(_) => "SetCrossfade",
+            PlayerCommand::SetSleepTimer(_) => "SetSleepTimer",
+            PlayerCommand::SetSleepFade(_) => "SetSleepFade",
+            PlayerCommand::SetPauseFade(_) => "SetPauseFade",
+            PlayerCommand::SetSeekStep(_) => "SetSeekStep",
+            PlayerCommand::SetPositionInterval(_) => "SetPositionInterval",
+            PlayerCommand::GetSleepTimer(_) => "GetSleepTimer",
+            PlayerCommand::SetOutputDevice(_) => "SetOutputDevice",
+            PlayerCommand::SetOutputConfig(_) => "SetOutputConfig",
+            PlayerCommand::GetOutputFormat(_) => "GetOutputFormat",
+            PlayerCommand::GetStreamStats(_) => "GetStreamStats",
+            PlayerCommand::GetPreloadStats(_) => "GetPreloadStats",
             PlayerCommand::GetPosition(_) => "GetPosition",
+            PlayerCommand::StartRemoteOutput(_) => "StartRemoteOutput",
+            PlayerCommand::StopRemoteOutput { .. } => "StopRemoteOutput",
             PlayerCommand::ResetAudioDevice => "ResetAudioDevice",
             PlayerCommand::Shutdown => "Shutdown",
         }
@@ -78,7 +212,29 @@ impl PlayerCommand {
                 | PlayerCommand::Resume
                 | PlayerCommand::Stop
                 | PlayerCommand::Seek(_)
+                | PlayerCommand::SeekAndWait { .. }
+                | PlayerCommand::SeekRelative(_)
+                | PlayerCommand::ResumeAt { .. }
+                | PlayerCommand::SyncExternalPosition { .. }
+                | PlayerCommand::HandoffToNative { .. }
+                | PlayerCommand::SetChapters { .. }
+                | PlayerCommand::SetTrimPoints { .. }
+                | PlayerCommand::NextChapter
+                | PlayerCommand::PreviousChapter
+                | PlayerCommand::SetLoopRegion { .. }
                 | PlayerCommand::GetPosition(_)
+                | PlayerCommand::SetGapless(_)
+                | PlayerCommand::SetCrossfade(_)
+                | PlayerCommand::SetSleepTimer(_)
+                | PlayerCommand::SetSleepFade(_)
+                | PlayerCommand::SetPauseFade(_)
+                | PlayerCommand::SetVolumeSettings(_)
+                | PlayerCommand::SetPlaybackRate(_)
+                | PlayerCommand::SetPreservePitch(_)
+                | PlayerCommand::GetPlaybackRate(_)
+                | PlayerCommand::SetSeekStep(_)
+                | PlayerCommand::SetPositionInterval(_)
+                | PlayerCommand::GetSleepTimer(_)
         )
     }
     
@@ -90,13 +246,21 @@ impl PlayerCommand {
                 | PlayerCommand::Previous
                 | PlayerCommand::LoadPlaylist(_)
                 | PlayerCommand::SetShuffle(_)
+                | PlayerCommand::SetShuffleSkipBroken(_)
         )
     }
 }
 
-
-
-
-
-
-
+/// 投屏时发送到远程输出的播放控制
+#[derive(Debug, Clone)]
+pub enum RemoteOutputCommand {
+    /// 播放曲目（从头开始）
+    Play(Track),
+    Pause,
+    Resume,
+    Stop,
+    /// 跳转到指定位置（毫秒）
+    Seek(u64),
+    /// 设置音量（0.0 - 1.0）
+    SetVolume(f32),
+}
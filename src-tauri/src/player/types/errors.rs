@@ -43,6 +43,18 @@ pub enum PlayerError {
     #[error("跳转失败: {0}")]
     SeekFailed(String),
     
+    /// A-B循环区间无效
+    #[error("循环区间无效: {0}")]
+    InvalidLoopRegion(String),
+    
+    /// 音频尚未缓存，暂时无法跳转
+    #[error("音频尚未缓存完成，请稍后再试")]
+    NotCached,
+    
+    /// 音频尚未缓存，暂时无法A-B循环
+    #[error("音频缓存完成后才能设置A-B循环")]
+    LoopUnavailable,
+    
     /// 初始化失败
     #[error("播放器初始化失败: {0}")]
     InitializationFailed(String),
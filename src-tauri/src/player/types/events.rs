@@ -1,7 +1,28 @@
 // 播放器事件定义
 
 use serde::Serialize;
-use super::{track::Track, state::PlayerState};
+use super::{track::Track, state::PlayerState, sleep_timer::SleepTimerStatus, chapter::Chapter, loop_region::LoopRegion};
+use super::super::audio::OutputFormat;
+
+/// 播放位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PositionUpdate {
+    pub position_ms: u64,
+    /// 位置所属的曲目，前端据此忽略快速切歌后上一首的过期事件
+    pub track_id: Option<i64>,
+    /// 播放中按经过时间推算的位置；暂停、跳转完成和投屏设备报告的位置为 false
+    pub is_estimated: bool,
+}
+
+/// 正在输出声音的播放引擎
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackEngine {
+    /// Rust端音频输出
+    Native,
+    /// 前端的外部引擎（Web Audio），由前端同步位置
+    External,
+}
 
 /// 播放器事件
 /// 播放器事件 - 公共API
@@ -16,8 +37,8 @@ pub enum PlayerEvent {
     /// 曲目变化
     TrackChanged(Option<Track>),
     
-    /// 位置更新（毫秒）
-    PositionChanged(u64),
+    /// 位置更新（同一曲目内单调递增，跳转后除外；总是在该曲目的 TrackChanged 之后发出）
+    PositionChanged(PositionUpdate),
     
     /// 播放错误
     PlaybackError(String),
@@ -34,8 +55,66 @@ pub enum PlayerEvent {
         elapsed_ms: u64,
     },
     
-    /// 音频设备就绪
-    AudioDeviceReady,
+    /// 已续播到上次保存的位置
+    PositionResumed {
+        track_id: i64,
+        position_ms: u64,
+    },
+    
+    /// 播放引擎切换（前端接管播放，或交还给原生播放），位置为切换时的位置
+    EngineChanged {
+        engine: PlaybackEngine,
+        track_id: Option<i64>,
+        position_ms: u64,
+    },
+    
+    /// 可视化数据：[峰值, RMS, 频段幅度...]，均为0~1
+    VisualizationData(Vec<f32>),
+    
+    /// 播放进入新的章节
+    ChapterChanged {
+        track_id: i64,
+        chapter: Chapter,
+    },
+    
+    /// A-B循环区间变化（设置、清除，以及换曲或停止时自动清除）
+    LoopRegionChanged {
+        track_id: Option<i64>,
+        region: Option<LoopRegion>,
+    },
+    
+    /// 解码得到的实际时长与曲库记录不符（缺失或相差超过1秒），已按实际时长更正
+    DurationCorrected {
+        track_id: i64,
+        duration_ms: u64,
+    },
+    
+    /// 流式播放缓冲不足，暂停等待重新缓冲（进度%，100表示缓冲结束）
+    Buffering {
+        percent: u8,
+    },
+    
+    /// 交叉淡入淡出状态变化
+    CrossfadeStateChanged {
+        active: bool,
+        duration_ms: u64,
+    },
+    
+    /// 睡眠定时器剩余时间/曲目数（设置时及之后约每30秒）
+    SleepTimerTick(SleepTimerStatus),
+    
+    /// 睡眠定时器已触发，播放已暂停
+    SleepTimerFired,
+    
+    /// 睡眠定时器已取消
+    SleepTimerCancelled,
+    
+    /// 音频设备就绪（实际使用的输出设备）
+    AudioDeviceReady {
+        device_name: String,
+        /// 实际协商得到的输出格式
+        format: Option<OutputFormat>,
+    },
     
     /// 音频设备失败
     AudioDeviceFailed {
@@ -67,6 +146,18 @@ impl PlayerEvent {
     }
 }
 
+impl crate::event_channel::Coalesce for PlayerEvent {
+    /// 位置、可视化数据和缓冲进度是高频快照，前端只需要最新一条
+    fn coalesce_key(&self) -> Option<&'static str> {
+        match self {
+            PlayerEvent::PositionChanged(_) => Some("position"),
+            PlayerEvent::VisualizationData(_) => Some("visualization"),
+            PlayerEvent::Buffering { .. } => Some("buffering"),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,7 +168,11 @@ mod tests {
         assert!(error_event.is_error());
         assert!(!error_event.is_state_update());
         
-        let state_event = PlayerEvent::PositionChanged(1000);
+        let state_event = PlayerEvent::PositionChanged(PositionUpdate {
+            position_ms: 1000,
+            track_id: Some(1),
+            is_estimated: true,
+        });
         assert!(!state_event.is_error());
         assert!(state_event.is_state_update());
         
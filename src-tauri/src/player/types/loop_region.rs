@@ -0,0 +1,55 @@
+// A-B循环区间定义
+
+use serde::{Deserialize, Serialize};
+
+/// 循环区间的最短长度(ms)
+pub const MIN_LOOP_REGION_MS: u64 = 500;
+
+/// 曲目内的A-B循环区间：播放越过B点时跳回A点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    /// A点(ms)
+    pub start_ms: u64,
+    /// B点(ms)
+    pub end_ms: u64,
+}
+
+impl LoopRegion {
+    pub fn new(start_ms: u64, end_ms: u64) -> Self {
+        Self { start_ms, end_ms }
+    }
+
+    /// 检查A点在B点之前、区间不短于最短长度且在曲目时长以内（时长未知时不检查结尾）
+    pub fn validate(&self, duration_ms: Option<u64>) -> Result<(), String> {
+        if self.start_ms >= self.end_ms {
+            return Err(format!("循环起点({}ms)必须在终点({}ms)之前", self.start_ms, self.end_ms));
+        }
+        if self.end_ms - self.start_ms < MIN_LOOP_REGION_MS {
+            return Err(format!("循环区间不能短于{}ms", MIN_LOOP_REGION_MS));
+        }
+        if let Some(duration_ms) = duration_ms {
+            if self.end_ms > duration_ms {
+                return Err(format!("循环终点({}ms)超出曲目时长({}ms)", self.end_ms, duration_ms));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(LoopRegion::new(10_000, 20_000).validate(Some(180_000)).is_ok());
+        assert!(LoopRegion::new(10_000, 20_000).validate(None).is_ok());
+        // 终点可以正好是曲目结尾
+        assert!(LoopRegion::new(0, 180_000).validate(Some(180_000)).is_ok());
+
+        assert!(LoopRegion::new(20_000, 10_000).validate(None).is_err());
+        assert!(LoopRegion::new(10_000, 10_000).validate(None).is_err());
+        assert!(LoopRegion::new(10_000, 10_400).validate(None).is_err());
+        assert!(LoopRegion::new(170_000, 190_000).validate(Some(180_000)).is_err());
+    }
+}
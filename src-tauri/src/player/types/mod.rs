@@ -5,13 +5,22 @@ mod state;
 mod commands;
 mod events;
 mod errors;
+mod sleep_timer;
+mod chapter;
+mod loop_region;
 
 // 公开导出所有类型
 pub use track::Track;
 pub use state::{PlayerState, RepeatMode};
-pub use commands::PlayerCommand;
-pub use events::PlayerEvent;
+pub use commands::{PlayerCommand, RemoteOutputCommand};
+pub use events::{PlaybackEngine, PlayerEvent, PositionUpdate};
 pub use errors::PlayerError;
+pub use sleep_timer::{
+    SleepTimer, SleepTimerStatus, SleepCountdown,
+    MIN_SLEEP_FADE_SECS, MAX_SLEEP_FADE_SECS, DEFAULT_SLEEP_FADE_SECS,
+};
+pub use chapter::{Chapter, chapter_at, next_chapter_start, previous_chapter_start};
+pub use loop_region::{LoopRegion, MIN_LOOP_REGION_MS};
 
 // 类型别名
 pub type Result<T> = std::result::Result<T, PlayerError>;
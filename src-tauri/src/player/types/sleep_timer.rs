@@ -0,0 +1,155 @@
+// 睡眠定时器定义
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 睡眠定时器淡出时长范围（秒）
+pub const MIN_SLEEP_FADE_SECS: u64 = 5;
+pub const MAX_SLEEP_FADE_SECS: u64 = 10;
+pub const DEFAULT_SLEEP_FADE_SECS: u64 = 8;
+
+/// 睡眠定时器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum SleepTimer {
+    /// 指定分钟数后暂停
+    Duration(u32),
+    /// 播放完指定数量的曲目后停止（包括当前曲目）
+    AfterTracks(u32),
+    /// 当前曲目播放完后停止
+    AfterCurrentTrack,
+}
+
+impl SleepTimer {
+    /// 检查参数（时长和曲目数必须大于0）
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            SleepTimer::Duration(0) => Err("睡眠定时时长必须大于0分钟".to_string()),
+            SleepTimer::AfterTracks(0) => Err("睡眠定时曲目数必须大于0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 睡眠定时器状态（发送给前端）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SleepTimerStatus {
+    pub timer: SleepTimer,
+    /// 按时长定时的剩余时间（毫秒）
+    pub remaining_ms: Option<u64>,
+    /// 按曲目定时的剩余曲目数（包括当前曲目）
+    pub tracks_remaining: Option<u32>,
+}
+
+/// 运行中的睡眠定时器
+///
+/// 按时长定时使用绝对截止时间，暂停播放不影响倒计时；
+/// 按曲目定时在每首曲目播放完成时计数
+#[derive(Debug, Clone)]
+pub struct SleepCountdown {
+    timer: SleepTimer,
+    deadline: Option<Instant>,
+    tracks_remaining: u32,
+}
+
+impl SleepCountdown {
+    pub fn start(timer: SleepTimer) -> Self {
+        Self::start_at(timer, Instant::now())
+    }
+
+    pub fn start_at(timer: SleepTimer, now: Instant) -> Self {
+        let (deadline, tracks_remaining) = match timer {
+            SleepTimer::Duration(minutes) => (Some(now + Duration::from_secs(minutes as u64 * 60)), 0),
+            SleepTimer::AfterTracks(n) => (None, n.max(1)),
+            SleepTimer::AfterCurrentTrack => (None, 1),
+        };
+        Self { timer, deadline, tracks_remaining }
+    }
+
+    /// 是否按曲目计数
+    pub fn counts_tracks(&self) -> bool {
+        self.deadline.is_none()
+    }
+
+    /// 当前曲目是否为最后一首（此时不应再自动衔接下一首）
+    pub fn is_last_track(&self) -> bool {
+        self.counts_tracks() && self.tracks_remaining <= 1
+    }
+
+    /// 曲目播放完成，返回定时器是否因此触发
+    pub fn on_track_completed(&mut self) -> bool {
+        if !self.counts_tracks() {
+            return false;
+        }
+        self.tracks_remaining = self.tracks_remaining.saturating_sub(1);
+        self.tracks_remaining == 0
+    }
+
+    /// 按时长定时是否已到期
+    pub fn is_due_at(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.is_due_at(Instant::now())
+    }
+
+    pub fn status_at(&self, now: Instant) -> SleepTimerStatus {
+        SleepTimerStatus {
+            timer: self.timer,
+            remaining_ms: self.deadline
+                .map(|deadline| deadline.saturating_duration_since(now).as_millis() as u64),
+            tracks_remaining: if self.counts_tracks() { Some(self.tracks_remaining) } else { None },
+        }
+    }
+
+    pub fn status(&self) -> SleepTimerStatus {
+        self.status_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_after_tracks_counts_completions() {
+        let mut countdown = SleepCountdown::start(SleepTimer::AfterTracks(3));
+        assert!(!countdown.is_last_track());
+        assert!(!countdown.on_track_completed());
+        assert_eq!(countdown.status().tracks_remaining, Some(2));
+        assert!(!countdown.on_track_completed());
+        assert!(countdown.is_last_track());
+        assert!(countdown.on_track_completed());
+        assert_eq!(countdown.status().tracks_remaining, Some(0));
+    }
+
+    #[test]
+    fn test_after_current_track_fires_on_first_completion() {
+        let mut countdown = SleepCountdown::start(SleepTimer::AfterCurrentTrack);
+        assert!(countdown.is_last_track());
+        assert!(countdown.on_track_completed());
+    }
+
+    #[test]
+    fn test_duration_ignores_track_completions() {
+        let now = Instant::now();
+        let mut countdown = SleepCountdown::start_at(SleepTimer::Duration(1), now);
+        assert!(!countdown.on_track_completed());
+        assert!(!countdown.is_last_track());
+        assert!(!countdown.is_due_at(now + Duration::from_secs(59)));
+        assert!(countdown.is_due_at(now + Duration::from_secs(60)));
+
+        let status = countdown.status_at(now + Duration::from_secs(15));
+        assert_eq!(status.remaining_ms, Some(45_000));
+        assert_eq!(status.tracks_remaining, None);
+    }
+
+    #[test]
+    fn test_validate_rejects_zero() {
+        assert!(SleepTimer::Duration(0).validate().is_err());
+        assert!(SleepTimer::AfterTracks(0).validate().is_err());
+        assert!(SleepTimer::AfterTracks(2).validate().is_ok());
+        assert!(SleepTimer::AfterCurrentTrack.validate().is_ok());
+    }
+}
@@ -23,6 +23,9 @@ pub struct PlayerState {
     
     /// 随机播放
     pub shuffle: bool,
+    
+    /// 播放速度（1.0为原速）
+    pub playback_rate: f32,
 }
 
 impl PlayerState {
@@ -35,6 +38,7 @@ impl PlayerState {
             volume: 1.0,
             repeat_mode: RepeatMode::Off,
             shuffle: false,
+            playback_rate: 1.0,
         }
     }
 }
@@ -57,6 +61,24 @@ pub enum RepeatMode {
 }
 
 impl RepeatMode {
+    /// 存入数据库的名称（与序列化名称一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::All => "All",
+            RepeatMode::One => "One",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "Off" => Some(RepeatMode::Off),
+            "All" => Some(RepeatMode::All),
+            "One" => Some(RepeatMode::One),
+            _ => None,
+        }
+    }
+
     /// 切换到下一个模式（仅测试使用）
     #[cfg(test)]
     pub fn next(self) -> Self {
@@ -90,6 +112,7 @@ mod tests {
         assert!(state.current_track.is_none());
         assert_eq!(state.position_ms, 0);
         assert_eq!(state.volume, 1.0);
+        assert_eq!(state.playback_rate, 1.0);
     }
 }
 
@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use crate::player::audio::verify::VerifyStatus;
 
 /// 曲目信息
 #[derive(Clone, Serialize, Deserialize)]
@@ -24,24 +25,56 @@ pub struct Track {
     /// 时长（毫秒）
     pub duration_ms: Option<i64>,
     
-    /// 专辑封面数据（可选，用于缓存）
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// 流派
+    #[serde(default)]
+    pub genre: Option<String>,
+    
+    /// 发行年份
+    #[serde(default)]
+    pub year: Option<i64>,
+    
+    /// 音轨号
+    #[serde(default)]
+    pub track_number: Option<i64>,
+    
+    /// 碟片号（多碟专辑）
+    #[serde(default)]
+    pub disc_number: Option<i64>,
+    
+    /// 专辑艺术家
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    
+    /// 封面ID（covers表中的图片哈希），通过 get_cover 按需加载
+    #[serde(default)]
+    pub cover_id: Option<String>,
+    
+    /// 评分（0-5星），未评分时为None
+    #[serde(default)]
+    pub rating: Option<u8>,
+    
+    /// 完整性校验结果（ok / truncated / decode_error），未校验时为None
+    #[serde(default)]
+    pub verify_status: Option<VerifyStatus>,
+    
+    /// 专辑封面数据（仅扫描时用于写入封面存储，查询结果恒为None，需要时走get_cover）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub album_cover_data: Option<Vec<u8>>,
     
     /// 专辑封面MIME类型
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub album_cover_mime: Option<String>,
     
     /// 艺术家照片数据（可选，用于缓存）
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub artist_photo_data: Option<Vec<u8>>,
     
     /// 艺术家照片MIME类型
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub artist_photo_mime: Option<String>,
     
     /// 嵌入的歌词（来自元数据或外部.lrc文件）
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub embedded_lyrics: Option<String>,
 }
 
@@ -54,6 +87,9 @@ impl fmt::Debug for Track {
             .field("artist", &self.artist)
             .field("album", &self.album)
             .field("duration_ms", &self.duration_ms)
+            .field("disc_number", &self.disc_number)
+            .field("track_number", &self.track_number)
+            .field("cover_id", &self.cover_id)
             .field("has_cover", &self.album_cover_data.as_ref().map(|d| d.len()))
             .field("cover_mime", &self.album_cover_mime)
             .finish()
@@ -71,6 +107,14 @@ impl Track {
             artist: None,
             album: None,
             duration_ms: None,
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            cover_id: None,
+            rating: None,
+            verify_status: None,
             album_cover_data: None,
             album_cover_mime: None,
             artist_photo_data: None,
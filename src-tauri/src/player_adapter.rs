@@ -4,13 +4,15 @@ use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
 use crossbeam_channel::{Receiver, Sender, unbounded};
 use crate::player::{PlayerCore, PlayerCoreConfig, PlayerCommand, PlayerEvent};
+use crate::event_channel::{self, EventReceiver, EventSender};
 
 pub struct PlayerAdapter {
     core: Arc<TokioMutex<PlayerCore>>,
     cmd_tx: Sender<PlayerCommand>,
     cmd_rx: Arc<TokioMutex<Receiver<PlayerCommand>>>,
-    event_tx: Sender<PlayerEvent>,
-    event_rx: Receiver<PlayerEvent>,
+    event_tx: EventSender<PlayerEvent>,
+    /// 事件接收端只有一个，由事件监听任务取走
+    event_rx: parking_lot::Mutex<Option<EventReceiver<PlayerEvent>>>,
 }
 
 impl PlayerAdapter {
@@ -20,14 +22,14 @@ impl PlayerAdapter {
         log::info!("✅ PlayerCore 创建成功");
         
         let (cmd_tx, cmd_rx) = unbounded();
-        let (event_tx, event_rx) = unbounded();
+        let (event_tx, event_rx) = event_channel::channel();
         
         let adapter = Self {
             core: Arc::new(TokioMutex::new(core)),
             cmd_tx,
             cmd_rx: Arc::new(TokioMutex::new(cmd_rx)),
             event_tx,
-            event_rx,
+            event_rx: parking_lot::Mutex::new(Some(event_rx)),
         };
         
         log::info!("🚀 启动命令和事件转发循环...");
@@ -40,10 +42,16 @@ impl PlayerAdapter {
         self.cmd_tx.clone()
     }
     
-    pub fn event_receiver(&self) -> Receiver<PlayerEvent> {
-        self.event_rx.clone()
+    /// 取走事件接收端（只能取一次）
+    pub fn take_event_receiver(&self) -> Option<EventReceiver<PlayerEvent>> {
+        self.event_rx.lock().take()
     }
-    
+
+    /// 事件发送端（投屏时由远程设备的状态生成播放事件）
+    pub fn event_sender(&self) -> EventSender<PlayerEvent> {
+        self.event_tx.clone()
+    }
+
     fn spawn_loops(&self) {
         self.spawn_command_loop();
         self.spawn_event_loop();
@@ -93,7 +101,7 @@ impl PlayerAdapter {
                             match rx.try_recv() {
                                 Ok(next_cmd) => {
                                     if let PlayerCommand::Play(_, _) = next_cmd {
-                                        println!("⏭️ [ADAPTER] 跳过过期Play命令，保留最新");
+                                        log::debug!("⏭️ [ADAPTER] 跳过过期Play命令，保留最新");
                                         latest_play = next_cmd;
                                         skipped += 1;
                                     } else {
@@ -118,7 +126,6 @@ impl PlayerAdapter {
                 };
                 
                 if skipped_play_count > 0 {
-                    println!("✨ [ADAPTER] 跳过了 {} 个过期Play命令", skipped_play_count);
                     log::info!("✨ [ADAPTER] 跳过了 {} 个过期Play命令", skipped_play_count);
                 }
                 
@@ -0,0 +1,459 @@
+// 歌单连同音频文件导出 - 把曲目复制或转码到目标目录，并写入引用这些文件的M3U
+//
+// - 文件名为 "NN - 艺术家 - 标题.ext"，去掉文件系统不允许的字符
+// - 目标格式与源文件相同时直接复制，否则转码（见 crate::transcode），标签和封面一并保留
+// - 目标目录中的 .windchime-export.json 记录每个输出文件的来源（大小、修改时间）和格式，
+//   再次导出时源文件和输出文件都未变化的曲目直接跳过
+// - 单个曲目失败只记录下来，不影响其余曲目；进度通过 playlist-audio-export-progress 事件发送，可随时取消
+
+use super::exporter::PlaylistExporter;
+use super::types::{ExportFormat, ExportOptions, Playlist};
+use crate::error::{AppError, AppResult};
+use crate::player::Track;
+use crate::transcode::{self, TranscodeFormat};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 导出记录文件名
+pub const MANIFEST_FILE: &str = ".windchime-export.json";
+
+/// 文件名（不含扩展名）最大字符数
+const MAX_NAME_CHARS: usize = 120;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 输出音频格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioExportFormat {
+    /// 保持原格式，直接复制
+    Original,
+    Mp3,
+    Ogg,
+}
+
+impl AudioExportFormat {
+    fn transcode_format(self) -> Option<TranscodeFormat> {
+        match self {
+            AudioExportFormat::Original => None,
+            AudioExportFormat::Mp3 => Some(TranscodeFormat::Mp3),
+            AudioExportFormat::Ogg => Some(TranscodeFormat::Ogg),
+        }
+    }
+}
+
+/// 导出失败的曲目
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioExportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// 导出进度（playlist-audio-export-progress 事件）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioExportProgress {
+    pub processed: usize,
+    pub copied: usize,
+    pub transcoded: usize,
+    /// 目标目录中已是最新的曲目
+    pub skipped: usize,
+    pub failed: usize,
+    pub total: usize,
+    /// 正在处理的曲目（输出文件名）
+    pub current: Option<String>,
+    /// 任务已结束（完成或取消）
+    pub finished: bool,
+    pub cancelled: bool,
+    pub failures: Vec<AudioExportFailure>,
+    /// 写入的M3U路径（结束时设置）
+    pub playlist_file: Option<String>,
+}
+
+/// 导出记录：输出文件名 -> 来源和格式
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: HashMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ManifestEntry {
+    source: String,
+    source_size: u64,
+    source_mtime: Option<i64>,
+    format: AudioExportFormat,
+    bitrate: Option<u32>,
+    output_size: u64,
+}
+
+impl Manifest {
+    fn load(dest_dir: &Path) -> Self {
+        std::fs::read_to_string(dest_dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dest_dir: &Path) -> Result<()> {
+        std::fs::write(dest_dir.join(MANIFEST_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 单个曲目的处理结果
+enum Outcome {
+    Copied,
+    Transcoded,
+    Skipped,
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// 取消正在进行的导出
+pub fn cancel() {
+    if is_running() {
+        CANCELLED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 开始导出，返回曲目数；bitrate 为空时使用默认码率
+pub fn start<F>(
+    playlist: Playlist,
+    tracks: Vec<Track>,
+    dest_dir: String,
+    format: AudioExportFormat,
+    bitrate: Option<u32>,
+    shutdown: &'static AtomicBool,
+    emit: F,
+) -> AppResult<usize>
+where
+    F: Fn(&AudioExportProgress) + Send + Sync + 'static,
+{
+    let bitrate = bitrate.unwrap_or(transcode::DEFAULT_BITRATE);
+    if let Some(transcode_format) = format.transcode_format() {
+        transcode_format.validate_bitrate(bitrate).map_err(AppError::InvalidInput)?;
+    }
+    let dest_dir = PathBuf::from(dest_dir);
+    std::fs::create_dir_all(&dest_dir)?;
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::invalid_input("歌单音频导出正在进行中"));
+    }
+
+    let total = tracks.len();
+    log::info!("💾 开始导出歌单音频: {} ({} 首, {:?}) -> {}", playlist.name, total, format, dest_dir.display());
+    CANCELLED.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn_blocking(move || {
+        let should_stop = || CANCELLED.load(Ordering::Relaxed) || shutdown.load(Ordering::Relaxed);
+        let progress = export_tracks(&playlist, &tracks, &dest_dir, format, bitrate, &should_stop, &emit);
+        log::info!(
+            "💾 歌单音频导出结束: 复制 {}, 转码 {}, 跳过 {}, 失败 {}{}",
+            progress.copied,
+            progress.transcoded,
+            progress.skipped,
+            progress.failed,
+            if progress.cancelled { "（已取消）" } else { "" }
+        );
+        RUNNING.store(false, Ordering::SeqCst);
+        emit(&progress);
+    });
+    Ok(total)
+}
+
+/// 导出所有曲目并写入M3U，返回最终进度（由调用方发送）
+fn export_tracks(
+    playlist: &Playlist,
+    tracks: &[Track],
+    dest_dir: &Path,
+    format: AudioExportFormat,
+    bitrate: u32,
+    should_stop: &dyn Fn() -> bool,
+    emit: &dyn Fn(&AudioExportProgress),
+) -> AudioExportProgress {
+    let mut manifest = Manifest::load(dest_dir);
+    let mut progress = AudioExportProgress {
+        total: tracks.len(),
+        ..Default::default()
+    };
+    emit(&progress);
+
+    let mut exported: Vec<Track> = Vec::with_capacity(tracks.len());
+    for (index, track) in tracks.iter().enumerate() {
+        if should_stop() {
+            progress.cancelled = true;
+            break;
+        }
+
+        let file_name = output_file_name(index + 1, tracks.len(), track, format);
+        progress.current = Some(file_name.clone());
+        emit(&progress);
+
+        match export_track(track, dest_dir, &file_name, format, bitrate, &mut manifest, should_stop) {
+            Ok(outcome) => {
+                match outcome {
+                    Outcome::Copied => progress.copied += 1,
+                    Outcome::Transcoded => progress.transcoded += 1,
+                    Outcome::Skipped => progress.skipped += 1,
+                }
+                let mut copy = track.clone();
+                copy.path = dest_dir.join(&file_name).to_string_lossy().to_string();
+                exported.push(copy);
+            }
+            Err(_) if should_stop() => {
+                progress.cancelled = true;
+                break;
+            }
+            Err(e) => {
+                log::warn!("⚠️ 导出曲目失败: {} ({})", track.path, e);
+                progress.failed += 1;
+                progress.failures.push(AudioExportFailure {
+                    path: track.path.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        progress.processed += 1;
+    }
+
+    if let Err(e) = manifest.save(dest_dir) {
+        log::warn!("⚠️ 保存导出记录失败: {}", e);
+    }
+
+    // 取消时也写入已导出的部分，目标目录保持可用
+    let playlist_file = dest_dir.join(format!("{}.m3u", sanitize_file_name(&playlist.name)));
+    let options = ExportOptions {
+        relative_to: Some(dest_dir.to_string_lossy().to_string()),
+    };
+    match PlaylistExporter::export_to_file(
+        playlist,
+        &exported,
+        &[],
+        &playlist_file.to_string_lossy(),
+        ExportFormat::M3U,
+        &options,
+    ) {
+        Ok(()) => progress.playlist_file = Some(playlist_file.to_string_lossy().to_string()),
+        Err(e) => log::warn!("⚠️ 写入歌单文件失败: {}", e),
+    }
+
+    progress.current = None;
+    progress.finished = true;
+    progress
+}
+
+fn export_track(
+    track: &Track,
+    dest_dir: &Path,
+    file_name: &str,
+    format: AudioExportFormat,
+    bitrate: u32,
+    manifest: &mut Manifest,
+    should_stop: &dyn Fn() -> bool,
+) -> Result<Outcome> {
+    if crate::remote_source::is_remote_track_path(&track.path) {
+        return Err(anyhow!("远程曲目不支持导出音频文件"));
+    }
+    let source = Path::new(&track.path);
+    let metadata = std::fs::metadata(source).map_err(|e| anyhow!("无法读取源文件: {}", e))?;
+    let transcode_format = format.transcode_format().filter(|target| !has_extension(source, target.extension()));
+
+    let entry = ManifestEntry {
+        source: track.path.clone(),
+        source_size: metadata.len(),
+        source_mtime: crate::library::file_mtime(&metadata),
+        format,
+        bitrate: transcode_format.map(|_| bitrate),
+        output_size: 0,
+    };
+    let dest = dest_dir.join(file_name);
+    if is_up_to_date(manifest.files.get(file_name), &entry, &dest) {
+        return Ok(Outcome::Skipped);
+    }
+
+    let outcome = match transcode_format {
+        Some(target) => {
+            transcode::transcode_file(source, &dest, target, bitrate, should_stop)?;
+            Outcome::Transcoded
+        }
+        None => {
+            copy_file(source, &dest)?;
+            Outcome::Copied
+        }
+    };
+
+    let output_size = std::fs::metadata(&dest)?.len();
+    manifest.files.insert(file_name.to_string(), ManifestEntry { output_size, ..entry });
+    Ok(outcome)
+}
+
+/// 记录与当前来源一致，且输出文件仍存在、大小未变
+fn is_up_to_date(recorded: Option<&ManifestEntry>, current: &ManifestEntry, dest: &Path) -> bool {
+    let Some(recorded) = recorded else {
+        return false;
+    };
+    let same_source = ManifestEntry { output_size: recorded.output_size, ..current.clone() } == *recorded;
+    same_source && std::fs::metadata(dest).map(|m| m.len() == recorded.output_size).unwrap_or(false)
+}
+
+/// 先复制到临时文件再重命名，中断时不会留下不完整的文件
+fn copy_file(source: &Path, dest: &Path) -> Result<()> {
+    let temp = transcode::temp_path_for(dest)?;
+    if let Err(e) = std::fs::copy(source, &temp).and_then(|_| std::fs::rename(&temp, dest)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(anyhow!("复制文件失败: {}", e));
+    }
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+        .unwrap_or(false)
+}
+
+/// "NN - 艺术家 - 标题.ext"；序号位数随曲目数增加，没有艺术家时省略，没有标题时使用源文件名
+fn output_file_name(number: usize, total: usize, track: &Track, format: AudioExportFormat) -> String {
+    let source = Path::new(&track.path);
+    let extension = match format.transcode_format() {
+        Some(target) => target.extension().to_string(),
+        None => source
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default(),
+    };
+    let title = track
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .or_else(|| source.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_else(|| track.id.to_string());
+
+    let width = total.to_string().len().max(2);
+    let name = match track.artist.as_deref().map(str::trim).filter(|artist| !artist.is_empty()) {
+        Some(artist) => format!("{:0width$} - {} - {}", number, artist, title, width = width),
+        None => format!("{:0width$} - {}", number, title, width = width),
+    };
+    let name = sanitize_file_name(&name);
+    if extension.is_empty() {
+        name
+    } else {
+        format!("{}.{}", name, extension)
+    }
+}
+
+/// 替换 Windows / FAT32 不允许的字符，去掉结尾的点和空格，限制长度
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']);
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn playlist() -> Playlist {
+        Playlist {
+            id: 1,
+            name: "Road: Trip?".to_string(),
+            description: None,
+            cover_path: None,
+            color_theme: None,
+            is_smart: false,
+            smart_rules: None,
+            is_favorite: false,
+            is_pinned: false,
+            track_count: 0,
+            total_duration_ms: 0,
+            created_at: 1_700_000_000,
+            updated_at: None,
+            last_played: None,
+            play_count: 0,
+            folder_id: None,
+            sort_order: 0,
+            default_shuffle: None,
+            default_repeat_mode: None,
+            default_crossfade_ms: None,
+        }
+    }
+
+    fn track(id: i64, path: &str, title: Option<&str>, artist: Option<&str>) -> Track {
+        let mut track = Track::new(id, path.to_string());
+        track.title = title.map(str::to_string);
+        track.artist = artist.map(str::to_string);
+        track
+    }
+
+    #[test]
+    fn test_output_file_name() {
+        let song = track(1, "/music/a.FLAC", Some("What/Is: Love?"), Some("AC/DC"));
+        assert_eq!(output_file_name(3, 12, &song, AudioExportFormat::Original), "03 - AC_DC - What_Is_ Love_.flac");
+        assert_eq!(output_file_name(3, 120, &song, AudioExportFormat::Mp3), "003 - AC_DC - What_Is_ Love_.mp3");
+
+        let bare = track(2, "/music/intro.wav", None, Some("  "));
+        assert_eq!(output_file_name(1, 1, &bare, AudioExportFormat::Ogg), "01 - intro.ogg");
+        assert_eq!(sanitize_file_name("name. . "), "name");
+        assert_eq!(sanitize_file_name("..."), "_");
+    }
+
+    #[test]
+    fn test_reexport_skips_unchanged_and_isolates_failures() {
+        let dir = std::env::temp_dir().join(format!("windchime-audio-export-{}", uuid::Uuid::new_v4()));
+        let source_dir = dir.join("library");
+        let dest_dir = dir.join("usb");
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        let source = source_dir.join("song.mp3");
+        std::fs::write(&source, b"not really audio").unwrap();
+
+        let tracks = vec![
+            track(1, "webdav://1/remote.mp3", Some("Remote"), None),
+            track(2, source.to_str().unwrap(), Some("Song"), Some("Band")),
+            track(3, source_dir.join("missing.mp3").to_str().unwrap(), Some("Gone"), None),
+        ];
+        let events = RefCell::new(0);
+        let emit = |_: &AudioExportProgress| *events.borrow_mut() += 1;
+
+        let first = export_tracks(&playlist(), &tracks, &dest_dir, AudioExportFormat::Mp3, 192, &|| false, &emit);
+        assert!(first.finished && !first.cancelled);
+        assert_eq!((first.copied, first.transcoded, first.skipped, first.failed), (1, 0, 0, 2));
+        assert_eq!(first.failures[0].path, "webdav://1/remote.mp3");
+        assert_eq!(std::fs::read(dest_dir.join("02 - Band - Song.mp3")).unwrap(), b"not really audio");
+        assert!(*events.borrow() > tracks.len());
+
+        let m3u = std::fs::read_to_string(dest_dir.join("Road_ Trip_.m3u")).unwrap();
+        assert!(m3u.lines().any(|line| line == "02 - Band - Song.mp3"));
+        assert!(!m3u.contains("remote.mp3"));
+
+        let second = export_tracks(&playlist(), &tracks, &dest_dir, AudioExportFormat::Mp3, 192, &|| false, &emit);
+        assert_eq!((second.copied, second.skipped, second.failed), (0, 1, 2));
+
+        // 输出文件被改动后重新复制
+        std::fs::write(dest_dir.join("02 - Band - Song.mp3"), b"x").unwrap();
+        let third = export_tracks(&playlist(), &tracks, &dest_dir, AudioExportFormat::Mp3, 192, &|| false, &emit);
+        assert_eq!((third.copied, third.skipped), (1, 0));
+
+        let cancelled = export_tracks(&playlist(), &tracks, &dest_dir, AudioExportFormat::Mp3, 192, &|| true, &emit);
+        assert!(cancelled.cancelled && cancelled.finished);
+        assert_eq!(cancelled.processed, 0);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
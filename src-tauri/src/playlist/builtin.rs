@@ -0,0 +1,127 @@
+// 内置歌单 - 最近添加、最近播放、最常播放、我的收藏
+//
+// 内置歌单不保存在 playlists 表中（不能删除或编辑），每次按设置实时查询，
+// 使用保留的负数ID，详情、导出等接口可以和普通歌单一样按ID访问。
+
+use super::types::{Playlist, PlaylistWithTracks};
+use serde::{Deserialize, Serialize};
+
+/// 内置歌单设置（应用设置中的 builtin_playlists）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuiltinPlaylistsConfig {
+    /// 最近添加的曲目数
+    pub recently_added_limit: u32,
+    /// 最近播放的曲目数
+    pub recently_played_limit: u32,
+    /// 最近播放统计的天数，0 表示不限
+    pub recently_played_days: u32,
+    /// 最常播放的曲目数
+    pub most_played_limit: u32,
+    /// 最常播放统计的天数，0 表示不限
+    pub most_played_days: u32,
+}
+
+impl Default for BuiltinPlaylistsConfig {
+    fn default() -> Self {
+        Self {
+            recently_added_limit: 100,
+            recently_played_limit: 100,
+            recently_played_days: 30,
+            most_played_limit: 50,
+            most_played_days: 0,
+        }
+    }
+}
+
+/// 单个内置歌单的最大曲目数
+pub const MAX_BUILTIN_LIMIT: u32 = 5000;
+
+impl BuiltinPlaylistsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for limit in [self.recently_added_limit, self.recently_played_limit, self.most_played_limit] {
+            if !(1..=MAX_BUILTIN_LIMIT).contains(&limit) {
+                return Err(format!("内置歌单的曲目数必须在1到{}之间", MAX_BUILTIN_LIMIT));
+            }
+        }
+        Ok(())
+    }
+
+    /// 统计起始时间（秒），days 为 0 时不限
+    pub fn since(days: u32, now: i64) -> i64 {
+        if days == 0 {
+            0
+        } else {
+            now - days as i64 * 86_400
+        }
+    }
+}
+
+/// 内置歌单
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinPlaylist {
+    RecentlyAdded,
+    RecentlyPlayed,
+    MostPlayed,
+    Favorites,
+}
+
+impl BuiltinPlaylist {
+    pub const ALL: [BuiltinPlaylist; 4] = [
+        BuiltinPlaylist::RecentlyAdded,
+        BuiltinPlaylist::RecentlyPlayed,
+        BuiltinPlaylist::MostPlayed,
+        BuiltinPlaylist::Favorites,
+    ];
+
+    /// 保留的歌单ID（负数，不会与数据库中的歌单冲突）
+    pub fn id(self) -> i64 {
+        match self {
+            BuiltinPlaylist::RecentlyAdded => -1,
+            BuiltinPlaylist::RecentlyPlayed => -2,
+            BuiltinPlaylist::MostPlayed => -3,
+            BuiltinPlaylist::Favorites => -4,
+        }
+    }
+
+    pub fn from_id(id: i64) -> Option<Self> {
+        Self::ALL.into_iter().find(|builtin| builtin.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinPlaylist::RecentlyAdded => "最近添加",
+            BuiltinPlaylist::RecentlyPlayed => "最近播放",
+            BuiltinPlaylist::MostPlayed => "最常播放",
+            BuiltinPlaylist::Favorites => "我的收藏",
+        }
+    }
+
+    /// 组装成与普通歌单相同结构的详情
+    pub fn to_playlist(self, tracks: Vec<crate::player::Track>) -> PlaylistWithTracks {
+        let playlist = Playlist {
+            id: self.id(),
+            name: self.name().to_string(),
+            description: None,
+            cover_path: None,
+            color_theme: None,
+            is_smart: false,
+            smart_rules: None,
+            is_favorite: false,
+            is_pinned: false,
+            track_count: tracks.len() as i64,
+            total_duration_ms: tracks.iter().filter_map(|track| track.duration_ms).sum(),
+            created_at: 0,
+            updated_at: None,
+            last_played: None,
+            play_count: 0,
+            folder_id: None,
+            sort_order: Self::ALL.iter().position(|builtin| *builtin == self).unwrap_or_default() as i64,
+            default_shuffle: None,
+            default_repeat_mode: None,
+            default_crossfade_ms: None,
+        };
+        PlaylistWithTracks { playlist, tracks }
+    }
+}
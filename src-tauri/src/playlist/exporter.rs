@@ -4,21 +4,31 @@
 // - M3U: 标准播放列表格式（Latin-1编码）
 // - M3U8: UTF-8编码的播放列表格式（标准扩展）
 // - JSON: 自定义格式（包含完整元数据）
+// - JSONFull: 完整备份（智能规则、颜色主题、加入时间），可通过导入无损还原
+// - XSPF: XML Shareable Playlist Format（http://xspf.org/ns/0/）
 //
 // 设计特性：
 // - 正确的编码处理（M3U vs M3U8）
 // - 文件覆盖警告
 // - 预估容量优化（减少String重分配）
+// - 可选相对路径（相对于指定目录），便于跨设备使用
 
 use super::types::*;
 use crate::player::Track;
 use anyhow::{Result, Context};
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::path::{Component, Path};
+
+/// XSPF location 中保留不编码的字符（RFC 3986 unreserved + 路径分隔符）
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
 
 /// 歌单导出器
-/// 
+///
 /// 职责：
 /// - 将歌单导出为多种标准格式
 /// - 处理文件编码和格式规范
@@ -27,62 +37,77 @@ pub struct PlaylistExporter;
 
 impl PlaylistExporter {
     /// 导出歌单到文件
-    /// 
+    ///
     /// # 参数
     /// - playlist: 歌单信息
     /// - tracks: 曲目列表
+    /// - added_at: 每首曲目加入歌单的时间（与 tracks 对齐，仅完整备份使用）
     /// - file_path: 导出文件路径
     /// - format: 导出格式
-    /// 
+    /// - options: 导出选项（相对路径等）
+    ///
     /// # 注意
     /// - 如果文件已存在会被覆盖（会记录警告日志）
     pub fn export_to_file(
         playlist: &Playlist,
         tracks: &[Track],
+        added_at: &[Option<i64>],
         file_path: &str,
         format: ExportFormat,
+        options: &ExportOptions,
     ) -> Result<()> {
-        match format {
-            ExportFormat::M3U => Self::export_m3u(playlist, tracks, file_path, false),
-            ExportFormat::M3U8 => Self::export_m3u(playlist, tracks, file_path, true),
-            ExportFormat::JSON => Self::export_json(playlist, tracks, file_path),
+        // 🔧 P2修复：检查文件是否已存在，避免意外覆盖
+        let path = Path::new(file_path);
+        if path.exists() {
+            log::warn!("Export file already exists and will be overwritten: {}", file_path);
         }
+
+        let content = Self::export_to_string(playlist, tracks, added_at, format.clone(), options)?;
+        std::fs::write(path, content)
+            .context("Failed to write export file")?;
+
+        log::info!("Exported playlist '{}' to {} ({:?}, {} tracks)",
+            playlist.name, file_path, format, tracks.len());
+
+        Ok(())
     }
 
-    /// 🔧 P2修复：导出为M3U/M3U8格式（正确处理编码）
-    /// 
-    /// M3U: 使用系统默认编码（通常是Latin-1或本地编码）
-    /// M3U8: 强制使用UTF-8编码（标准规定）
-    fn export_m3u(
+    /// 🔧 P2修复：导出为字符串（优化性能）
+    pub fn export_to_string(
         playlist: &Playlist,
         tracks: &[Track],
-        file_path: &str,
-        utf8: bool,
-    ) -> Result<()> {
-        // 🔧 P2修复：检查文件是否已存在，避免意外覆盖
-        let path = Path::new(file_path);
-        if path.exists() {
-            log::warn!("Export file already exists and will be overwritten: {}", file_path);
+        added_at: &[Option<i64>],
+        format: ExportFormat,
+        options: &ExportOptions,
+    ) -> Result<String> {
+        match format {
+            ExportFormat::M3U => Ok(Self::render_m3u(playlist, tracks, options, false)),
+            ExportFormat::M3U8 => Ok(Self::render_m3u(playlist, tracks, options, true)),
+            ExportFormat::JSON => Self::render_json(playlist, tracks, added_at, options, false),
+            ExportFormat::JSONFull => Self::render_json(playlist, tracks, added_at, options, true),
+            ExportFormat::XSPF => Ok(Self::render_xspf(playlist, tracks, options)),
         }
-        
-        let mut file = File::create(file_path)
-            .context("Failed to create export file")?;
+    }
+
+    /// 🔧 P2修复：生成M3U/M3U8内容（正确处理编码）
+    ///
+    /// M3U: 使用系统默认编码（通常是Latin-1或本地编码）
+    /// M3U8: 强制使用UTF-8编码（标准规定）
+    fn render_m3u(playlist: &Playlist, tracks: &[Track], options: &ExportOptions, utf8: bool) -> String {
+        // 🔧 P2优化：预估容量，减少重分配
+        let estimated_size = 100 + tracks.len() * 150; // 估算：每个track约150字节
+        let mut output = String::with_capacity(estimated_size);
 
-        // 🔧 P2修复：M3U8明确标注UTF-8编码
+        output.push_str("#EXTM3U\n");
+        // 🔧 P2修复：M3U8明确标注版本
         if utf8 {
-            // M3U8标准格式：明确UTF-8 BOM（可选）
-            writeln!(file, "#EXTM3U")?;
-            writeln!(file, "#EXT-X-VERSION:3")?; // M3U8版本标识
-        } else {
-            // M3U标准格式
-            writeln!(file, "#EXTM3U")?;
+            output.push_str("#EXT-X-VERSION:3\n"); // M3U8版本标识
         }
 
         // 写入歌单信息
-        writeln!(file, "#PLAYLIST:{}", playlist.name)?;
-        
+        output.push_str(&format!("#PLAYLIST:{}\n", playlist.name));
         if let Some(desc) = &playlist.description {
-            writeln!(file, "#DESCRIPTION:{}", desc)?;
+            output.push_str(&format!("#DESCRIPTION:{}\n", desc));
         }
 
         // 写入每首曲目
@@ -92,121 +117,176 @@ impl PlaylistExporter {
                 let duration_sec = duration_ms / 1000;
                 let artist = track.artist.as_deref().unwrap_or("Unknown Artist");
                 let title = track.title.as_deref().unwrap_or("Unknown Title");
-                
-                writeln!(file, "#EXTINF:{},{} - {}", duration_sec, artist, title)?;
+                output.push_str(&format!("#EXTINF:{},{} - {}\n", duration_sec, artist, title));
             }
-            
+
             // 写入文件路径
-            writeln!(file, "{}", track.path)?;
+            output.push_str(&format!("{}\n", export_path(&track.path, options)));
         }
 
-        log::info!("Exported playlist '{}' to {} ({} tracks)", 
-            playlist.name, file_path, tracks.len());
-        
-        Ok(())
+        output
     }
 
-    /// 导出为JSON格式
-    fn export_json(
+    /// 生成JSON内容；full 为 true 时包含智能规则、颜色主题和加入时间
+    fn render_json(
         playlist: &Playlist,
         tracks: &[Track],
-        file_path: &str,
-    ) -> Result<()> {
+        added_at: &[Option<i64>],
+        options: &ExportOptions,
+        full: bool,
+    ) -> Result<String> {
+        let smart_rules = if full {
+            playlist.smart_rules.as_deref().and_then(|json| {
+                serde_json::from_str::<SmartRules>(json)
+                    .map_err(|e| log::warn!("歌单 {} 的智能规则无法解析，导出时忽略: {}", playlist.id, e))
+                    .ok()
+            })
+        } else {
+            None
+        };
+
         let export = PlaylistExport {
             name: playlist.name.clone(),
             description: playlist.description.clone(),
             created_at: playlist.created_at,
-            tracks: tracks.iter().map(TrackExport::from).collect(),
+            color_theme: if full { playlist.color_theme.clone() } else { None },
+            smart_rules,
+            tracks: tracks.iter()
+                .enumerate()
+                .map(|(index, track)| TrackExport {
+                    path: export_path(&track.path, options),
+                    added_at: if full { added_at.get(index).copied().flatten() } else { None },
+                    ..TrackExport::from(track)
+                })
+                .collect(),
         };
 
-        let json = serde_json::to_string_pretty(&export)
-            .context("Failed to serialize playlist")?;
-
-        std::fs::write(file_path, json)
-            .context("Failed to write JSON file")?;
+        serde_json::to_string_pretty(&export)
+            .context("Failed to serialize playlist")
+    }
 
-        log::info!("Exported playlist '{}' to {} (JSON, {} tracks)", 
-            playlist.name, file_path, tracks.len());
+    /// 生成XSPF内容
+    fn render_xspf(playlist: &Playlist, tracks: &[Track], options: &ExportOptions) -> String {
+        let estimated_size = 200 + tracks.len() * 250;
+        let mut output = String::with_capacity(estimated_size);
 
-        Ok(())
-    }
+        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+        push_xml_element(&mut output, 1, "title", &playlist.name);
+        if let Some(desc) = &playlist.description {
+            push_xml_element(&mut output, 1, "annotation", desc);
+        }
 
-    /// 🔧 P2修复：导出为字符串（优化性能）
-    pub fn export_to_string(
-        playlist: &Playlist,
-        tracks: &[Track],
-        format: ExportFormat,
-    ) -> Result<String> {
-        match format {
-            ExportFormat::JSON => {
-                let export = PlaylistExport {
-                    name: playlist.name.clone(),
-                    description: playlist.description.clone(),
-                    created_at: playlist.created_at,
-                    tracks: tracks.iter().map(TrackExport::from).collect(),
-                };
-                serde_json::to_string_pretty(&export)
-                    .context("Failed to serialize playlist")
+        output.push_str("  <trackList>\n");
+        for track in tracks {
+            output.push_str("    <track>\n");
+            push_xml_element(&mut output, 3, "location", &xspf_location(&export_path(&track.path, options)));
+            if let Some(title) = &track.title {
+                push_xml_element(&mut output, 3, "title", title);
+            }
+            if let Some(artist) = &track.artist {
+                push_xml_element(&mut output, 3, "creator", artist);
             }
-            _ => {
-                // 🔧 P2优化：预估容量，减少重分配
-                let estimated_size = 100 + tracks.len() * 150; // 估算：每个track约150字节
-                let mut output = String::with_capacity(estimated_size);
-                
-                output.push_str("#EXTM3U\n");
-                output.push_str(&format!("#PLAYLIST:{}\n", playlist.name));
-                
-                for track in tracks {
-                    if let Some(duration_ms) = track.duration_ms {
-                        let duration_sec = duration_ms / 1000;
-                        let artist = track.artist.as_deref().unwrap_or("Unknown Artist");
-                        let title = track.title.as_deref().unwrap_or("Unknown Title");
-                        output.push_str(&format!("#EXTINF:{},{} - {}\n", duration_sec, artist, title));
-                    }
-                    output.push_str(&format!("{}\n", track.path));
-                }
-                
-                Ok(output)
+            if let Some(album) = &track.album {
+                push_xml_element(&mut output, 3, "album", album);
+            }
+            if let Some(duration_ms) = track.duration_ms {
+                push_xml_element(&mut output, 3, "duration", &duration_ms.to_string());
             }
+            output.push_str("    </track>\n");
         }
+        output.push_str("  </trackList>\n");
+        output.push_str("</playlist>\n");
+
+        output
     }
 
     /// 验证导出路径
-    /// 
+    ///
     /// # 参数
     /// - file_path: 导出文件路径
     /// - format: 预期的导出格式
-    /// 
+    ///
     /// # 检查
     /// - 父目录是否存在
     /// - 文件扩展名是否匹配格式（不匹配会记录警告）
     #[allow(dead_code)]
     pub fn validate_export_path(file_path: &str, format: &ExportFormat) -> Result<()> {
         let path = Path::new(file_path);
-        
+
         // 检查目录是否存在
         if let Some(parent) = path.parent() {
             if !parent.exists() {
                 return Err(anyhow::anyhow!("Directory does not exist: {:?}", parent));
             }
         }
-        
+
         // 检查文件扩展名
         let expected_ext = match format {
             ExportFormat::M3U => "m3u",
             ExportFormat::M3U8 => "m3u8",
-            ExportFormat::JSON => "json",
+            ExportFormat::JSON | ExportFormat::JSONFull => "json",
+            ExportFormat::XSPF => "xspf",
         };
-        
+
         if let Some(ext) = path.extension() {
             if ext.to_string_lossy().to_lowercase() != expected_ext {
-                log::warn!("File extension mismatch: expected .{}, got .{}", 
+                log::warn!("File extension mismatch: expected .{}, got .{}",
                     expected_ext, ext.to_string_lossy());
             }
         }
-        
+
         Ok(())
     }
 }
 
+/// 写入一行带缩进的XML元素（内容转义）
+fn push_xml_element(output: &mut String, depth: usize, tag: &str, text: &str) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str(&format!("<{}>{}</{}>\n", tag, quick_xml::escape::escape(text), tag));
+}
+
+/// 按导出选项生成曲目路径：在基准目录下的写相对路径（统一用 / 分隔），否则保持原样
+fn export_path(path: &str, options: &ExportOptions) -> String {
+    options.relative_to.as_deref()
+        .and_then(|base| relative_path(Path::new(path), Path::new(base)))
+        .unwrap_or_else(|| path.to_string())
+}
 
+/// 计算 path 相对于 base 的路径；两者不在同一根（如不同盘符）时返回None
+fn relative_path(path: &Path, base: &Path) -> Option<String> {
+    if !path.is_absolute() || !base.is_absolute() {
+        return None;
+    }
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    if path.first() != base.first() {
+        return None;
+    }
+
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat("..".to_string())
+        .take(base.len() - common)
+        .chain(path[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()))
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// XSPF 的 location 必须是URI：绝对路径转为 file:// URI，相对路径按相对URI编码
+fn xspf_location(path: &str) -> String {
+    let unified = path.replace('\\', "/");
+    let bytes = unified.as_bytes();
+    let is_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+
+    if is_drive {
+        // file:///C:/Music/a.mp3，盘符冒号保持原样
+        format!("file:///{}{}", &unified[..2], utf8_percent_encode(&unified[2..], URI_PATH))
+    } else if unified.starts_with('/') {
+        format!("file://{}", utf8_percent_encode(&unified, URI_PATH))
+    } else {
+        utf8_percent_encode(&unified, URI_PATH).to_string()
+    }
+}
@@ -2,17 +2,73 @@
 //
 // 支持格式：
 // - M3U/M3U8: 标准播放列表格式
-// - JSON: 自定义格式（包含完整元数据）
+// - JSON: 自定义格式（包含完整元数据；完整备份还包含智能规则、颜色主题、加入时间）
+// - XSPF: XML Shareable Playlist Format
 //
 // 安全特性：
 // - 文件大小限制（防止OOM）
 // - 路径规范化（防止路径遍历）
 // - 完整的边界检查
+//
+// 曲目匹配（跨设备导入）：
+// 1. 路径完全一致（可先做路径前缀替换，如 D:\Music → /mnt/music）
+// 2. 文件名一致
+// 3. 标题、艺术家、时长（±2秒）一致，信息来自 #EXTINF、JSON 或 XSPF 元数据
 
 use super::types::*;
+use crate::player::Track;
 use anyhow::{Result, Context};
+use percent_encoding::percent_decode_str;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+
+/// 元数据匹配允许的时长误差
+const DURATION_TOLERANCE_MS: i64 = 2000;
+
+/// 导入文件中的一条曲目记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportEntry {
+    /// 源文件中的行号（JSON为曲目序号），从1开始
+    pub line: usize,
+    pub path: String,
+    pub hint: TrackHint,
+    /// 加入歌单的时间（仅完整备份包含）
+    pub added_at: Option<i64>,
+}
+
+/// 解析后的歌单文件
+#[derive(Debug, Clone)]
+pub struct ParsedPlaylist {
+    pub name: String,
+    pub description: Option<String>,
+    /// 以下两项仅完整备份包含
+    pub color_theme: Option<String>,
+    pub smart_rules: Option<SmartRules>,
+    pub entries: Vec<ImportEntry>,
+}
+
+impl ParsedPlaylist {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            color_theme: None,
+            smart_rules: None,
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// 用于模糊匹配的曲目信息（#EXTINF、JSON 或 XSPF 元数据）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackHint {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration_ms: Option<i64>,
+}
 
 /// 歌单导入器
 /// 
@@ -24,7 +80,9 @@ pub struct PlaylistImporter;
 
 impl PlaylistImporter {
     /// 🔧 P2修复：从文件导入歌单（带大小限制和路径验证）
-    pub fn import_from_file(file_path: &str) -> Result<(String, Vec<String>)> {
+    ///
+    /// 相对路径按歌单文件所在目录解析
+    pub fn import_from_file(file_path: &str) -> Result<ParsedPlaylist> {
         // 🔧 P2修复：规范化路径，防止路径遍历攻击
         let path = Path::new(file_path)
             .canonicalize()
@@ -56,20 +114,36 @@ impl PlaylistImporter {
             .unwrap_or("")
             .to_lowercase();
 
-        match extension.as_str() {
-            "m3u" | "m3u8" => Self::parse_m3u(&content),
-            "json" => Self::parse_json(&content),
+        let mut playlist = Self::parse_content(&content, &extension)?;
+
+        if let Some(base_dir) = path.parent() {
+            for entry in playlist.entries.iter_mut().filter(|e| !looks_absolute(&e.path)) {
+                if let Some(joined) = join_relative(base_dir, &entry.path).to_str() {
+                    entry.path = joined.to_string();
+                }
+            }
+        }
+
+        Ok(playlist)
+    }
+
+    /// 按扩展名解析歌单内容（路径保持文件中的原样）
+    fn parse_content(content: &str, extension: &str) -> Result<ParsedPlaylist> {
+        match extension {
+            "m3u" | "m3u8" => Self::parse_m3u(content),
+            "json" => Self::parse_json(content),
+            "xspf" => Self::parse_xspf(content),
             _ => Err(anyhow::anyhow!("Unsupported file format: {}", extension)),
         }
     }
 
     /// 🔧 P2修复：解析M3U/M3U8格式（完整实现+边界检查）
-    fn parse_m3u(content: &str) -> Result<(String, Vec<String>)> {
-        let mut name = "Imported Playlist".to_string();
-        let mut paths = Vec::new();
+    fn parse_m3u(content: &str) -> Result<ParsedPlaylist> {
+        let mut playlist = ParsedPlaylist::new("Imported Playlist");
+        let mut hint = TrackHint::default();
 
-        for line in content.lines() {
-            let line = line.trim();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
             
             // 跳过空行
             if line.is_empty() {
@@ -80,18 +154,27 @@ impl PlaylistImporter {
             if line.starts_with('#') {
                 // 🔧 P2修复：安全的字符串切片，防止越界
                 if line.starts_with("#PLAYLIST:") && line.len() > 10 {
-                    name = line[10..].trim().to_string();
+                    playlist.name = line[10..].trim().to_string();
+                } else if let Some(desc) = line.strip_prefix("#DESCRIPTION:") {
+                    playlist.description = Some(desc.trim().to_string());
+                } else if let Some(info) = line.strip_prefix("#EXTINF:") {
+                    // 作为下一条路径的匹配提示
+                    hint = parse_extinf(info);
                 }
-                // #EXTINF 等其他标签暂时不处理
                 continue;
             }
 
             // 文件路径
-            paths.push(line.to_string());
+            playlist.entries.push(ImportEntry {
+                line: index + 1,
+                path: line.to_string(),
+                hint: std::mem::take(&mut hint),
+                added_at: None,
+            });
         }
 
-        log::info!("Parsed M3U playlist '{}' with {} tracks", name, paths.len());
-        Ok((name, paths))
+        log::info!("Parsed M3U playlist '{}' with {} tracks", playlist.name, playlist.entries.len());
+        Ok(playlist)
     }
 
     /// 解析JSON格式
@@ -100,18 +183,180 @@ impl PlaylistImporter {
     /// - content: JSON文件内容
     /// 
     /// # 返回
-    /// - (歌单名称, 曲目路径列表)
-    fn parse_json(content: &str) -> Result<(String, Vec<String>)> {
+    /// - 歌单信息与曲目列表（完整备份额外包含智能规则、颜色主题、加入时间）
+    fn parse_json(content: &str) -> Result<ParsedPlaylist> {
         let export: PlaylistExport = serde_json::from_str(content)
             .context("Failed to parse JSON")?;
 
-        let paths: Vec<String> = export.tracks
+        let entries: Vec<ImportEntry> = export.tracks
             .into_iter()
-            .map(|t| t.path)
+            .enumerate()
+            .map(|(index, t)| ImportEntry {
+                line: index + 1,
+                path: t.path,
+                hint: TrackHint {
+                    title: t.title,
+                    artist: t.artist,
+                    duration_ms: t.duration_ms,
+                },
+                added_at: t.added_at,
+            })
+            .collect();
+
+        log::info!("Parsed JSON playlist '{}' with {} tracks", export.name, entries.len());
+        Ok(ParsedPlaylist {
+            name: export.name,
+            description: export.description,
+            color_theme: export.color_theme,
+            smart_rules: export.smart_rules,
+            entries,
+        })
+    }
+
+    /// 解析XSPF格式
+    ///
+    /// 读取歌单的 title/annotation 与每个 track 的 location/title/creator/duration，
+    /// 其他元素（extension、meta 等）忽略
+    fn parse_xspf(content: &str) -> Result<ParsedPlaylist> {
+        let mut reader = Reader::from_str(content);
+        reader.trim_text(true);
+
+        let mut playlist = ParsedPlaylist::new("Imported Playlist");
+        let mut text = String::new();
+        let mut track: Option<(Option<String>, TrackHint)> = None;
+
+        loop {
+            match reader.read_event().context("Failed to parse XSPF")? {
+                Event::Start(e) => {
+                    text.clear();
+                    if e.local_name().as_ref() == b"track" {
+                        track = Some((None, TrackHint::default()));
+                    }
+                }
+                Event::Text(t) => text.push_str(&t.unescape().context("Failed to parse XSPF")?),
+                Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t.into_inner())),
+                Event::End(e) => {
+                    let value = std::mem::take(&mut text).trim().to_string();
+                    let name = e.local_name();
+                    if name.as_ref() == b"track" {
+                        if let Some((Some(location), hint)) = track.take() {
+                            playlist.entries.push(ImportEntry {
+                                line: playlist.entries.len() + 1,
+                                path: xspf_location_to_path(&location),
+                                hint,
+                                added_at: None,
+                            });
+                        }
+                    } else if !value.is_empty() {
+                        match (name.as_ref(), track.as_mut()) {
+                            (b"location", Some((location, _))) => *location = Some(value),
+                            (b"title", Some((_, hint))) => hint.title = Some(value),
+                            (b"creator", Some((_, hint))) => hint.artist = Some(value),
+                            (b"duration", Some((_, hint))) => hint.duration_ms = value.parse().ok(),
+                            (b"title", None) => playlist.name = value,
+                            (b"annotation", None) => playlist.description = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        log::info!("Parsed XSPF playlist '{}' with {} tracks", playlist.name, playlist.entries.len());
+        Ok(playlist)
+    }
+
+    /// 将导入条目匹配到曲库曲目，生成导入预览
+    ///
+    /// # 参数
+    /// - entries: 导入文件中的条目
+    /// - library: 曲库全部曲目
+    /// - rewrite: 路径前缀替换规则
+    /// - overrides: 用户确认的匹配，优先于自动匹配
+    pub fn resolve(
+        name: String,
+        entries: &[ImportEntry],
+        library: &[Track],
+        rewrite: Option<&PathRewrite>,
+        overrides: &[ImportOverride],
+    ) -> ImportPreview {
+        let by_path: HashMap<&str, i64> = library.iter()
+            .map(|t| (t.path.as_str(), t.id))
+            .collect();
+        let mut by_filename: HashMap<String, Vec<&Track>> = HashMap::new();
+        for track in library {
+            by_filename.entry(file_name(&track.path).to_lowercase()).or_default().push(track);
+        }
+        let known_ids: HashSet<i64> = library.iter().map(|t| t.id).collect();
+        let overrides: HashMap<usize, i64> = overrides.iter()
+            .map(|o| (o.source_line, o.track_id))
             .collect();
 
-        log::info!("Parsed JSON playlist '{}' with {} tracks", export.name, paths.len());
-        Ok((export.name, paths))
+        let mut preview = ImportPreview {
+            name,
+            resolved: Vec::new(),
+            unresolved: Vec::new(),
+            exact_matches: 0,
+            fuzzy_matches: 0,
+            manual_matches: 0,
+        };
+
+        for entry in entries {
+            let manual = overrides.get(&entry.line).copied().filter(|id| {
+                let known = known_ids.contains(id);
+                if !known {
+                    log::warn!("导入映射指向不存在的曲目: 第{}行 → {}", entry.line, id);
+                }
+                known
+            });
+
+            let matched = manual.map(|id| (id, ImportMatchKind::Manual))
+                .or_else(|| {
+                    let path = match rewrite {
+                        Some(rule) => rewrite_path(&entry.path, rule),
+                        None => entry.path.clone(),
+                    };
+                    match_exact(&path, &by_path).map(|id| (id, ImportMatchKind::Exact))
+                })
+                .or_else(|| {
+                    match_filename(entry, &by_filename).map(|id| (id, ImportMatchKind::Filename))
+                })
+                .or_else(|| {
+                    match_metadata(&entry.hint, library).map(|id| (id, ImportMatchKind::Metadata))
+                });
+
+            match matched {
+                Some((track_id, match_kind)) => {
+                    match match_kind {
+                        ImportMatchKind::Exact => preview.exact_matches += 1,
+                        ImportMatchKind::Manual => preview.manual_matches += 1,
+                        _ => preview.fuzzy_matches += 1,
+                    }
+                    preview.resolved.push(ResolvedImportEntry {
+                        source_line: entry.line,
+                        source_path: entry.path.clone(),
+                        track_id,
+                        match_kind,
+                    });
+                }
+                None => preview.unresolved.push(UnresolvedImportEntry {
+                    source_line: entry.line,
+                    source_path: entry.path.clone(),
+                }),
+            }
+        }
+
+        log::info!(
+            "导入匹配 '{}': {} 精确, {} 模糊, {} 手动, {} 未匹配",
+            preview.name,
+            preview.exact_matches,
+            preview.fuzzy_matches,
+            preview.manual_matches,
+            preview.unresolved.len()
+        );
+        preview
     }
 
     /// 🔧 P2修复：验证和规范化导入的路径
@@ -120,6 +365,7 @@ impl PlaylistImporter {
     /// - 规范化路径（防止路径遍历）
     /// - 检查文件存在性
     /// - 返回有效路径的规范形式
+    #[allow(dead_code)]
     pub fn validate_paths(paths: &[String]) -> (Vec<String>, Vec<String>) {
         let mut valid = Vec::new();
         let mut invalid = Vec::new();
@@ -181,4 +427,375 @@ impl PlaylistImporter {
     }
 }
 
+/// 解析 #EXTINF 内容："时长[ 属性],艺术家 - 标题"
+fn parse_extinf(info: &str) -> TrackHint {
+    let (head, display) = info.split_once(',').unwrap_or((info, ""));
+    let duration_ms = head.split_whitespace()
+        .next()
+        .and_then(|secs| secs.parse::<i64>().ok())
+        .filter(|secs| *secs > 0)
+        .map(|secs| secs * 1000);
+
+    // 导出时缺失的信息写为占位符，不能用于匹配
+    let known = |text: &str, placeholder: &str| {
+        let text = text.trim();
+        (!text.is_empty() && text != placeholder).then(|| text.to_string())
+    };
+    let (artist, title) = match display.split_once(" - ") {
+        Some((artist, title)) => (known(artist, "Unknown Artist"), known(title, "Unknown Title")),
+        None => (None, known(display, "Unknown Title")),
+    };
+
+    TrackHint { title, artist, duration_ms }
+}
+
+/// XSPF location（URI）转为文件路径：file:// 去掉协议并解码，相对URI直接解码
+fn xspf_location_to_path(location: &str) -> String {
+    let decode = |uri: &str| percent_decode_str(uri).decode_utf8_lossy().into_owned();
+    match location.strip_prefix("file://") {
+        Some(rest) => {
+            let path = decode(rest.strip_prefix("localhost").unwrap_or(rest));
+            // file:///C:/Music/a.mp3 → C:/Music/a.mp3
+            let bytes = path.as_bytes();
+            if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+                path[1..].to_string()
+            } else {
+                path
+            }
+        }
+        None => decode(location),
+    }
+}
+
+/// 拼接相对路径并按字面消去 . 和 ..（文件可能不存在，不能用 canonicalize）
+fn join_relative(base: &Path, relative: &str) -> PathBuf {
+    let mut joined = PathBuf::new();
+    for component in base.join(relative.replace('\\', "/")).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                joined.pop();
+            }
+            other => joined.push(other),
+        }
+    }
+    joined
+}
+
+/// 是否为绝对路径（同时识别 Windows 与 Unix 风格，导入文件可能来自其他系统）
+fn looks_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || path.starts_with('\\')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// 路径最后一段（同时识别 / 与 \ 分隔符）
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// 前缀替换：分隔符与大小写不敏感，替换后的分隔符与目标前缀保持一致
+fn rewrite_path(path: &str, rule: &PathRewrite) -> String {
+    let normalize = |p: &str| p.replace('\\', "/").trim_end_matches('/').to_string();
+    let from = normalize(&rule.from);
+    let source = normalize(path);
+
+    let matches_prefix = !from.is_empty()
+        && source.is_char_boundary(from.len())
+        && source[..from.len()].eq_ignore_ascii_case(&from)
+        && (source.len() == from.len() || source[from.len()..].starts_with('/'));
+    if !matches_prefix {
+        return path.to_string();
+    }
+
+    let rest = &source[from.len()..];
+    let to = rule.to.trim_end_matches(['/', '\\']);
+    if rule.to.contains('\\') && !rule.to.contains('/') {
+        format!("{}{}", to, rest.replace('/', "\\"))
+    } else {
+        format!("{}{}", to, rest)
+    }
+}
+
+fn match_exact(path: &str, by_path: &HashMap<&str, i64>) -> Option<i64> {
+    if let Some(id) = by_path.get(path) {
+        return Some(*id);
+    }
+    // 符号链接、相对路径中的 .. 等
+    let canonical = Path::new(path).canonicalize().ok()?;
+    by_path.get(canonical.to_str()?).copied()
+}
+
+/// 文件名唯一时直接匹配；重名时用元数据区分
+fn match_filename(entry: &ImportEntry, by_filename: &HashMap<String, Vec<&Track>>) -> Option<i64> {
+    let candidates = by_filename.get(&file_name(&entry.path).to_lowercase())?;
+    if let [track] = candidates.as_slice() {
+        return Some(track.id);
+    }
+    let mut matching = candidates.iter().filter(|t| hint_matches(&entry.hint, t));
+    match (matching.next(), matching.next()) {
+        (Some(track), None) => Some(track.id),
+        _ => None,
+    }
+}
+
+/// 按标题、艺术家、时长匹配，多个候选时取时长最接近的
+fn match_metadata(hint: &TrackHint, library: &[Track]) -> Option<i64> {
+    library.iter()
+        .filter(|t| hint_matches(hint, t))
+        .min_by_key(|t| match (hint.duration_ms, t.duration_ms) {
+            (Some(expected), Some(actual)) => (expected - actual).abs(),
+            _ => DURATION_TOLERANCE_MS,
+        })
+        .map(|t| t.id)
+}
+
+/// 标题必须一致，且至少还有艺术家或时长可供核对
+fn hint_matches(hint: &TrackHint, track: &Track) -> bool {
+    let same = |a: &str, b: &Option<String>| {
+        b.as_deref().is_some_and(|b| a.trim().to_lowercase() == b.trim().to_lowercase())
+    };
+
+    let Some(title) = hint.title.as_deref() else { return false };
+    if hint.artist.is_none() && hint.duration_ms.is_none() {
+        return false;
+    }
+    if !same(title, &track.title) {
+        return false;
+    }
+    if let Some(artist) = hint.artist.as_deref() {
+        if !same(artist, &track.artist) {
+            return false;
+        }
+    }
+    match (hint.duration_ms, track.duration_ms) {
+        (Some(expected), Some(actual)) => (expected - actual).abs() <= DURATION_TOLERANCE_MS,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playlist::PlaylistExporter;
+
+    fn track(id: i64, path: &str, title: &str, artist: &str, duration_ms: i64) -> Track {
+        let mut track = Track::new(id, path.to_string());
+        track.title = Some(title.to_string());
+        track.artist = Some(artist.to_string());
+        track.duration_ms = Some(duration_ms);
+        track
+    }
+
+    #[test]
+    fn test_parse_m3u_with_extinf_hints() {
+        let content = "\u{feff}#EXTM3U\n#PLAYLIST:Road Trip\n#EXTINF:215,Queen - Bohemian Rhapsody\nD:\\Music\\queen.mp3\n\n#EXTINF:-1,Unknown Artist - Intro\nintro.mp3\nplain.mp3\n";
+        let parsed = PlaylistImporter::parse_m3u(content).unwrap();
+        let entries = parsed.entries;
+        assert_eq!(parsed.name, "Road Trip");
+        assert_eq!(entries.iter().map(|e| e.line).collect::<Vec<_>>(), vec![4, 7, 8]);
+        assert_eq!(entries[0].hint, TrackHint {
+            title: Some("Bohemian Rhapsody".to_string()),
+            artist: Some("Queen".to_string()),
+            duration_ms: Some(215_000),
+        });
+        assert_eq!(entries[1].hint, TrackHint { title: Some("Intro".to_string()), ..Default::default() });
+        // 提示只作用于紧随其后的一条路径
+        assert_eq!(entries[2].hint, TrackHint::default());
+    }
+
+    #[test]
+    fn test_rewrite_path_prefix() {
+        let rule = PathRewrite { from: "D:\\Music\\".to_string(), to: "/mnt/music".to_string() };
+        assert_eq!(rewrite_path("d:\\music\\Queen\\a.mp3", &rule), "/mnt/music/Queen/a.mp3");
+        // 只替换完整的目录前缀
+        assert_eq!(rewrite_path("D:\\Musicals\\a.mp3", &rule), "D:\\Musicals\\a.mp3");
 
+        let to_windows = PathRewrite { from: "/home/me/music".to_string(), to: "E:\\Audio".to_string() };
+        assert_eq!(rewrite_path("/home/me/music/x/y.flac", &to_windows), "E:\\Audio\\x\\y.flac");
+    }
+
+    #[test]
+    fn test_resolve_exact_fuzzy_manual_and_unresolved() {
+        let library = vec![
+            track(1, "/mnt/music/Queen/queen.mp3", "Bohemian Rhapsody", "Queen", 355_000),
+            track(2, "/mnt/music/a/01.mp3", "Intro", "Band A", 60_000),
+            track(3, "/mnt/music/b/01.mp3", "Intro", "Band B", 90_000),
+            track(4, "/mnt/music/x/unique.flac", "Unique", "X", 100_000),
+            track(5, "/mnt/music/y/renamed.flac", "Heroes", "David Bowie", 371_000),
+        ];
+        let entry = |line: usize, path: &str, hint: TrackHint| ImportEntry { line, path: path.to_string(), hint, added_at: None };
+        let hint = |title: &str, artist: Option<&str>, secs: Option<i64>| TrackHint {
+            title: Some(title.to_string()),
+            artist: artist.map(str::to_string),
+            duration_ms: secs.map(|s| s * 1000),
+        };
+        let entries = vec![
+            entry(1, "D:\\Music\\Queen\\queen.mp3", TrackHint::default()),
+            entry(2, "C:\\old\\UNIQUE.flac", TrackHint::default()),
+            // 文件名重名，用元数据区分
+            entry(3, "C:\\old\\01.mp3", hint("Intro", Some("band b"), None)),
+            // 文件名不同，按标题+艺术家+时长（±2秒）匹配
+            entry(4, "C:\\old\\bowie.mp3", hint("heroes", Some("David Bowie"), Some(373))),
+            entry(5, "C:\\old\\missing.mp3", hint("Heroes", Some("David Bowie"), Some(380))),
+            entry(6, "C:\\old\\01.mp3", TrackHint::default()),
+        ];
+        let rule = PathRewrite { from: "D:\\Music".to_string(), to: "/mnt/music".to_string() };
+        let overrides = [
+            ImportOverride { source_line: 6, track_id: 2 },
+            // 不存在的曲目被忽略
+            ImportOverride { source_line: 5, track_id: 99 },
+        ];
+
+        let preview = PlaylistImporter::resolve("P".to_string(), &entries, &library, Some(&rule), &overrides);
+        let resolved: Vec<(usize, i64, ImportMatchKind)> = preview.resolved.iter()
+            .map(|r| (r.source_line, r.track_id, r.match_kind))
+            .collect();
+        assert_eq!(resolved, vec![
+            (1, 1, ImportMatchKind::Exact),
+            (2, 4, ImportMatchKind::Filename),
+            (3, 3, ImportMatchKind::Filename),
+            (4, 5, ImportMatchKind::Metadata),
+            (6, 2, ImportMatchKind::Manual),
+        ]);
+        assert_eq!(preview.unresolved, vec![UnresolvedImportEntry {
+            source_line: 5,
+            source_path: "C:\\old\\missing.mp3".to_string(),
+        }]);
+        assert_eq!((preview.exact_matches, preview.fuzzy_matches, preview.manual_matches), (1, 3, 1));
+    }
+
+    fn playlist(description: Option<&str>, color_theme: Option<&str>, smart_rules: Option<&str>) -> Playlist {
+        Playlist {
+            id: 1,
+            name: "Late <Night> & Jazz".to_string(),
+            description: description.map(str::to_string),
+            cover_path: None,
+            color_theme: color_theme.map(str::to_string),
+            is_smart: smart_rules.is_some(),
+            smart_rules: smart_rules.map(str::to_string),
+            is_favorite: false,
+            is_pinned: false,
+            track_count: 0,
+            total_duration_ms: 0,
+            created_at: 1_700_000_000,
+            updated_at: None,
+            last_played: None,
+            play_count: 0,
+            folder_id: None,
+            sort_order: 0,
+            default_shuffle: None,
+            default_repeat_mode: None,
+            default_crossfade_ms: None,
+        }
+    }
+
+    fn library_tracks(dir: &str) -> Vec<Track> {
+        let mut blue = track(1, &format!("{}/Miles Davis/So What.flac", dir), "So What", "Miles Davis", 562_000);
+        blue.album = Some("Kind of Blue".to_string());
+        let mut rain = track(2, &format!("{}/日本/雨 & 涙 #1.mp3", dir), "雨 & 涙", "Ann <Sally>", 241_000);
+        rain.album = Some("Moment".to_string());
+        let mut bare = Track::new(3, format!("{}/bare 100%.ogg", dir));
+        bare.duration_ms = Some(1000);
+        vec![blue, rain, bare]
+    }
+
+    fn hint_of(track: &Track) -> TrackHint {
+        TrackHint {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            duration_ms: track.duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_json_full_round_trip_keeps_everything() {
+        let rules = r#"{"rules":[{"field":"genre","operator":"equals","value":"Jazz"}],"match_all":true,"limit":25}"#;
+        let source = playlist(Some("深夜"), Some("indigo"), Some(rules));
+        let tracks = library_tracks("/music");
+        let added_at = vec![Some(1_700_000_100), None, Some(1_700_000_300)];
+
+        let content = PlaylistExporter::export_to_string(
+            &source, &tracks, &added_at, ExportFormat::JSONFull, &ExportOptions::default(),
+        ).unwrap();
+        let parsed = PlaylistImporter::parse_content(&content, "json").unwrap();
+
+        assert_eq!(parsed.name, source.name);
+        assert_eq!(parsed.description.as_deref(), Some("深夜"));
+        assert_eq!(parsed.color_theme.as_deref(), Some("indigo"));
+        let expected_rules: SmartRules = serde_json::from_str(rules).unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed.smart_rules.unwrap()).unwrap(),
+            serde_json::to_value(expected_rules).unwrap()
+        );
+        assert_eq!(parsed.entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+            tracks.iter().map(|t| t.path.as_str()).collect::<Vec<_>>());
+        assert_eq!(parsed.entries.iter().map(|e| e.hint.clone()).collect::<Vec<_>>(),
+            tracks.iter().map(hint_of).collect::<Vec<_>>());
+        assert_eq!(parsed.entries.iter().map(|e| e.added_at).collect::<Vec<_>>(), added_at);
+
+        // 普通JSON不包含备份专用字段
+        let plain = PlaylistExporter::export_to_string(
+            &source, &tracks, &added_at, ExportFormat::JSON, &ExportOptions::default(),
+        ).unwrap();
+        assert!(!plain.contains("color_theme") && !plain.contains("added_at") && !plain.contains("smart_rules"));
+    }
+
+    #[test]
+    fn test_xspf_round_trip_escapes_text_and_locations() {
+        let source = playlist(Some("a < b && c"), None, None);
+        let tracks = library_tracks("/music");
+
+        let content = PlaylistExporter::export_to_string(
+            &source, &tracks, &[], ExportFormat::XSPF, &ExportOptions::default(),
+        ).unwrap();
+        assert!(content.contains("<location>file:///music/bare%20100%25.ogg</location>"));
+        assert!(content.contains("<creator>Ann &lt;Sally&gt;</creator>"));
+
+        let parsed = PlaylistImporter::parse_content(&content, "xspf").unwrap();
+        assert_eq!(parsed.name, source.name);
+        assert_eq!(parsed.description.as_deref(), Some("a < b && c"));
+        assert_eq!(parsed.entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+            tracks.iter().map(|t| t.path.as_str()).collect::<Vec<_>>());
+        assert_eq!(parsed.entries.iter().map(|e| e.hint.clone()).collect::<Vec<_>>(),
+            tracks.iter().map(hint_of).collect::<Vec<_>>());
+        assert_eq!(parsed.entries.iter().map(|e| e.line).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        assert_eq!(xspf_location_to_path("file:///C:/My%20Music/a.mp3"), "C:/My Music/a.mp3");
+        assert_eq!(xspf_location_to_path("file://localhost/srv/a.mp3"), "/srv/a.mp3");
+    }
+
+    #[test]
+    fn test_relative_paths_round_trip_through_files() {
+        let dir = std::env::temp_dir().join(format!("windchime-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("playlists")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let root = dir.to_str().unwrap().to_string();
+
+        let mut tracks = library_tracks(&root);
+        // 不在导出目录下的曲目用 ../ 表示
+        tracks.push(track(4, &format!("{}/Outside/x.mp3", root), "X", "Y", 1000));
+        let options = ExportOptions { relative_to: Some(format!("{}/playlists", root)) };
+        let source = playlist(None, None, None);
+
+        for (format, extension) in [
+            (ExportFormat::M3U8, "m3u8"),
+            (ExportFormat::XSPF, "xspf"),
+            (ExportFormat::JSONFull, "json"),
+        ] {
+            let file = dir.join("playlists").join(format!("export.{}", extension));
+            let file = file.to_str().unwrap();
+            PlaylistExporter::export_to_file(&source, &tracks, &[], file, format, &options).unwrap();
+
+            let content = std::fs::read_to_string(file).unwrap();
+            assert!(!content.contains(&root), "{} 中不应出现绝对路径", extension);
+
+            let parsed = PlaylistImporter::import_from_file(file).unwrap();
+            assert_eq!(parsed.entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+                tracks.iter().map(|t| t.path.as_str()).collect::<Vec<_>>(), "{}", extension);
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
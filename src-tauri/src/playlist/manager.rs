@@ -5,6 +5,7 @@
 // - 智能歌单：规则管理和自动刷新
 // - 曲目管理：添加、删除、重排曲目
 // - 统计信息：提供歌单统计数据
+// - 侧边栏组织：歌单文件夹（最多嵌套一层）和手动顺序
 // - 导入导出：集成导入导出功能
 //
 // 设计原则：
@@ -13,17 +14,22 @@
 // - 事务安全：所有修改操作使用数据库事务
 
 use super::types::*;
+use super::builtin::{BuiltinPlaylist, BuiltinPlaylistsConfig};
 use super::smart_playlist::SmartPlaylistEngine;
 use crate::db::Database;
-use anyhow::{Result, Context};
-use std::sync::{Arc, Mutex};
+use crate::db_pool::DbPool;
+use crate::error::{AppError, AppResult};
+use anyhow::Context;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// 歌单管理器
 /// 
 /// 核心业务逻辑层，协调数据库操作和智能规则引擎
 pub struct PlaylistManager {
-    /// 数据库连接（通过Arc<Mutex>实现线程安全）
-    db: Arc<Mutex<Database>>,
+    /// 数据库连接池
+    db: Arc<DbPool>,
 }
 
 impl PlaylistManager {
@@ -31,7 +37,7 @@ impl PlaylistManager {
     /// 
     /// # 参数
     /// - db: 数据库连接的Arc引用
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
+    pub fn new(db: Arc<DbPool>) -> Self {
         Self { db }
     }
 
@@ -42,8 +48,8 @@ impl PlaylistManager {
     /// 
     /// # 返回
     /// - 新创建的歌单ID
-    pub fn create_playlist(&self, options: CreatePlaylistOptions) -> Result<i64> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn create_playlist(&self, options: CreatePlaylistOptions) -> AppResult<i64> {
+        let db = self.db.lock()?;
         
         let smart_rules_json = if let Some(rules) = &options.smart_rules {
             Some(serde_json::to_string(rules)?)
@@ -51,23 +57,24 @@ impl PlaylistManager {
             None
         };
 
-        db.create_playlist_extended(
+        let playlist_id = db.create_playlist_extended(
             &options.name,
             options.description.as_deref(),
             None, // cover_path
             options.is_smart,
             smart_rules_json.as_deref(),
             options.color_theme.as_deref(),
-        )
+        )?;
+        Ok(playlist_id)
     }
 
     /// 获取所有歌单
     /// 
     /// # 返回
     /// - 所有歌单的列表（按更新时间倒序）
-    pub fn get_all_playlists(&self) -> Result<Vec<Playlist>> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
-        db.get_all_playlists_extended()
+    pub fn get_all_playlists(&self) -> AppResult<Vec<Playlist>> {
+        let db = self.db.lock()?;
+        Ok(db.get_all_playlists_extended()?)
     }
 
     /// 获取歌单详情（包含曲目）
@@ -77,20 +84,63 @@ impl PlaylistManager {
     /// 
     /// # 返回
     /// - PlaylistWithTracks: 歌单信息和曲目列表
-    pub fn get_playlist_with_tracks(&self, playlist_id: i64) -> Result<PlaylistWithTracks> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn get_playlist_with_tracks(&self, playlist_id: i64) -> AppResult<PlaylistWithTracks> {
+        let db = self.db.lock()?;
         
         let playlist = db.get_playlist_by_id(playlist_id)?
-            .ok_or_else(|| anyhow::anyhow!("Playlist not found"))?;
+            .ok_or_else(|| AppError::not_found("Playlist not found"))?;
         
         let tracks = db.get_playlist_tracks(playlist_id)?;
 
         Ok(PlaylistWithTracks { playlist, tracks })
     }
 
+    /// 获取内置歌单详情
+    pub fn get_builtin_playlist(&self, builtin: BuiltinPlaylist, config: &BuiltinPlaylistsConfig) -> AppResult<PlaylistWithTracks> {
+        let db = self.db.lock()?;
+        let now = chrono::Utc::now().timestamp();
+        let tracks = match builtin {
+            BuiltinPlaylist::RecentlyAdded => db.get_recently_added_tracks(config.recently_added_limit as i64)?,
+            BuiltinPlaylist::RecentlyPlayed => db.get_recently_played_tracks(
+                BuiltinPlaylistsConfig::since(config.recently_played_days, now),
+                config.recently_played_limit as i64,
+            )?,
+            BuiltinPlaylist::MostPlayed => db.get_most_played_tracks(
+                BuiltinPlaylistsConfig::since(config.most_played_days, now),
+                config.most_played_limit as i64,
+            )?,
+            BuiltinPlaylist::Favorites => db.get_all_favorites()?,
+        };
+        Ok(builtin.to_playlist(tracks))
+    }
+
+    /// 获取所有内置歌单
+    pub fn get_builtin_playlists(&self, config: &BuiltinPlaylistsConfig) -> AppResult<Vec<PlaylistWithTracks>> {
+        BuiltinPlaylist::ALL
+            .into_iter()
+            .map(|builtin| self.get_builtin_playlist(builtin, config))
+            .collect()
+    }
+
+    /// 按ID获取歌单详情，内置歌单的保留ID同样可用（用于详情、导出等接口）
+    pub fn resolve_playlist(&self, playlist_id: i64, config: &BuiltinPlaylistsConfig) -> AppResult<PlaylistWithTracks> {
+        match BuiltinPlaylist::from_id(playlist_id) {
+            Some(builtin) => self.get_builtin_playlist(builtin, config),
+            None => self.get_playlist_with_tracks(playlist_id),
+        }
+    }
+
+    /// 曲目的加入时间，内置歌单没有加入时间
+    pub fn resolve_added_at(&self, playlist_id: i64, track_count: usize) -> AppResult<Vec<Option<i64>>> {
+        match BuiltinPlaylist::from_id(playlist_id) {
+            Some(_) => Ok(vec![None; track_count]),
+            None => self.get_added_at(playlist_id),
+        }
+    }
+
     /// 更新歌单
-    pub fn update_playlist(&self, playlist_id: i64, options: UpdatePlaylistOptions) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn update_playlist(&self, playlist_id: i64, options: UpdatePlaylistOptions) -> AppResult<()> {
+        let db = self.db.lock()?;
         
         db.update_playlist_metadata(
             playlist_id,
@@ -99,13 +149,56 @@ impl PlaylistManager {
             options.cover_path.as_deref(),
             options.color_theme.as_deref(),
             options.is_favorite,
-        )
+        )?;
+
+        // 播放偏好只修改传入的项
+        if options.default_shuffle.is_some() || options.default_repeat_mode.is_some() || options.default_crossfade_ms.is_some() {
+            let current = db.get_playlist_by_id(playlist_id)?
+                .ok_or_else(|| AppError::not_found(format!("歌单不存在: {}", playlist_id)))?;
+            db.set_playlist_playback_defaults(
+                playlist_id,
+                options.default_shuffle.unwrap_or(current.default_shuffle),
+                options.default_repeat_mode.unwrap_or(current.default_repeat_mode),
+                options.default_crossfade_ms.unwrap_or(current.default_crossfade_ms),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 准备播放歌单：取出曲目、确定第一首并标记为最近播放
+    ///
+    /// 未指定第一首时从头播放，歌单默认随机播放时从随机一首开始
+    pub fn play_playlist(
+        &self,
+        playlist_id: i64,
+        start_track_id: Option<i64>,
+        config: &BuiltinPlaylistsConfig,
+    ) -> AppResult<(PlaylistWithTracks, i64)> {
+        let detail = self.resolve_playlist(playlist_id, config)?;
+        let start_track_id = match start_track_id {
+            Some(track_id) if detail.tracks.iter().any(|track| track.id == track_id) => track_id,
+            Some(track_id) => return Err(AppError::not_found(format!("曲目不在歌单中: {}", track_id))),
+            None if detail.playlist.default_shuffle == Some(true) => {
+                use rand::seq::SliceRandom;
+                detail.tracks.choose(&mut rand::thread_rng())
+                    .map(|track| track.id)
+                    .ok_or_else(|| AppError::not_found(format!("歌单没有曲目: {}", detail.playlist.name)))?
+            }
+            None => detail.tracks.first()
+                .map(|track| track.id)
+                .ok_or_else(|| AppError::not_found(format!("歌单没有曲目: {}", detail.playlist.name)))?,
+        };
+
+        if BuiltinPlaylist::from_id(playlist_id).is_none() {
+            self.mark_played(playlist_id)?;
+        }
+        Ok((detail, start_track_id))
     }
 
     /// 删除歌单
-    pub fn delete_playlist(&self, playlist_id: i64) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
-        db.delete_playlist(playlist_id)
+    pub fn delete_playlist(&self, playlist_id: i64) -> AppResult<()> {
+        let db = self.db.lock()?;
+        Ok(db.delete_playlist(playlist_id)?)
     }
 
     /// 添加曲目到歌单
@@ -113,25 +206,63 @@ impl PlaylistManager {
     /// # 参数
     /// - playlist_id: 歌单ID
     /// - track_ids: 要添加的曲目ID列表
+    /// - skip_existing: 跳过歌单中已有的曲目
     /// 
     /// # 注意
     /// - 智能歌单不支持手动添加曲目
-    pub fn add_tracks_to_playlist(&self, playlist_id: i64, track_ids: Vec<i64>) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn add_tracks_to_playlist(&self, playlist_id: i64, track_ids: Vec<i64>, skip_existing: bool) -> AppResult<AddTracksResult> {
+        let db = self.db.lock()?;
+        ensure_playlist(&db, playlist_id)?;
+        Ok(db.add_tracks_to_playlist_bulk(playlist_id, &track_ids, skip_existing)?)
+    }
+
+    /// 添加整张专辑（按碟号、音轨号）
+    pub fn add_album(&self, playlist_id: i64, album: &str, album_artist: Option<&str>, skip_existing: bool) -> AppResult<AddTracksResult> {
+        let db = self.db.lock()?;
+        ensure_playlist(&db, playlist_id)?;
+        let track_ids = db.get_album_track_ids(album, album_artist)?;
+        if track_ids.is_empty() {
+            return Err(AppError::not_found(format!("专辑没有曲目: {}", album)));
+        }
+        Ok(db.add_tracks_to_playlist_bulk(playlist_id, &track_ids, skip_existing)?)
+    }
+
+    /// 添加艺术家的全部曲目（按专辑、碟号、音轨号）
+    pub fn add_artist(&self, playlist_id: i64, artist: &str, skip_existing: bool) -> AppResult<AddTracksResult> {
+        let db = self.db.lock()?;
+        ensure_playlist(&db, playlist_id)?;
+        let track_ids = db.get_artist_track_ids(artist)?;
+        if track_ids.is_empty() {
+            return Err(AppError::not_found(format!("艺术家没有曲目: {}", artist)));
+        }
+        Ok(db.add_tracks_to_playlist_bulk(playlist_id, &track_ids, skip_existing)?)
+    }
+
+    /// 添加曲目并保留原加入时间（从备份还原）
+    ///
+    /// # 参数
+    /// - items: (曲目ID, 加入时间)，加入时间为None时使用当前时间
+    pub fn restore_tracks(&self, playlist_id: i64, items: Vec<(i64, Option<i64>)>) -> AppResult<()> {
+        let db = self.db.lock()?;
         
-        for track_id in track_ids {
-            db.add_track_to_playlist(playlist_id, track_id)?;
+        for (track_id, added_at) in items {
+            db.add_track_to_playlist_at(playlist_id, track_id, added_at)?;
         }
         
-        // 更新歌单的更新时间
         db.touch_playlist(playlist_id)?;
         
         Ok(())
     }
 
+    /// 获取歌单曲目的加入时间（顺序与曲目列表一致，用于导出备份）
+    pub fn get_added_at(&self, playlist_id: i64) -> AppResult<Vec<Option<i64>>> {
+        let db = self.db.lock()?;
+        Ok(db.get_playlist_added_at(playlist_id)?)
+    }
+
     /// 从歌单移除曲目
-    pub fn remove_track_from_playlist(&self, playlist_id: i64, track_id: i64) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn remove_track_from_playlist(&self, playlist_id: i64, track_id: i64) -> AppResult<()> {
+        let db = self.db.lock()?;
         
         db.remove_track_from_playlist(playlist_id, track_id)?;
         db.touch_playlist(playlist_id)?;
@@ -139,9 +270,23 @@ impl PlaylistManager {
         Ok(())
     }
 
+    /// 批量移除曲目，返回移除的条目数
+    pub fn remove_tracks(&self, playlist_id: i64, track_ids: Vec<i64>) -> AppResult<usize> {
+        let db = self.db.lock()?;
+        ensure_playlist(&db, playlist_id)?;
+        Ok(db.remove_tracks_from_playlist(playlist_id, &track_ids)?)
+    }
+
+    /// 移除重复的曲目（保留第一次出现的位置），返回移除的条目数
+    pub fn deduplicate(&self, playlist_id: i64) -> AppResult<usize> {
+        let db = self.db.lock()?;
+        ensure_playlist(&db, playlist_id)?;
+        Ok(db.deduplicate_playlist(playlist_id)?)
+    }
+
     /// 重排歌单曲目
-    pub fn reorder_tracks(&self, playlist_id: i64, track_ids: Vec<i64>) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn reorder_tracks(&self, playlist_id: i64, track_ids: Vec<i64>) -> AppResult<()> {
+        let db = self.db.lock()?;
         
         db.reorder_playlist_tracks(playlist_id, &track_ids)?;
         db.touch_playlist(playlist_id)?;
@@ -150,7 +295,7 @@ impl PlaylistManager {
     }
 
     /// 创建智能歌单
-    pub fn create_smart_playlist(&self, name: String, rules: SmartRules) -> Result<i64> {
+    pub fn create_smart_playlist(&self, name: String, rules: SmartRules) -> AppResult<i64> {
         let options = CreatePlaylistOptions {
             name,
             description: None,
@@ -163,8 +308,8 @@ impl PlaylistManager {
     }
 
     /// 更新智能歌单规则
-    pub fn update_smart_playlist(&self, playlist_id: i64, rules: SmartRules) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    pub fn update_smart_playlist(&self, playlist_id: i64, rules: SmartRules) -> AppResult<()> {
+        let db = self.db.lock()?;
         
         let rules_json = serde_json::to_string(&rules)?;
         db.update_smart_playlist_rules(playlist_id, &rules_json)?;
@@ -176,68 +321,30 @@ impl PlaylistManager {
         Ok(())
     }
 
-    /// 🔧 P2修复：刷新智能歌单（使用SQL优化，支持扩展字段）
-    pub fn refresh_smart_playlist(&self, playlist_id: i64) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+    /// 🔧 P2修复：刷新智能歌单（单条SQL查询，支持扩展字段）
+    pub fn refresh_smart_playlist(&self, playlist_id: i64) -> AppResult<()> {
+        let db = self.db.lock()?;
         
         // 获取歌单信息
         let playlist = db.get_playlist_by_id(playlist_id)?
-            .ok_or_else(|| anyhow::anyhow!("Playlist not found"))?;
+            .ok_or_else(|| AppError::not_found("Playlist not found"))?;
         
         if !playlist.is_smart {
-            return Err(anyhow::anyhow!("Not a smart playlist"));
+            return Err(AppError::invalid_input("Not a smart playlist"));
         }
         
         let rules_json = playlist.smart_rules
-            .ok_or_else(|| anyhow::anyhow!("Smart playlist has no rules"))?;
+            .ok_or_else(|| AppError::invalid_input("Smart playlist has no rules"))?;
         
         let rules: SmartRules = serde_json::from_str(&rules_json)
             .context("Failed to parse smart rules")?;
         
-        // 🔧 P2新增：尝试使用SQL查询优化（仅支持基本字段）
-        let use_sql_optimization = rules.rules.iter().all(|rule| {
-            matches!(rule.field, 
-                RuleField::Title | RuleField::Artist | RuleField::Album | RuleField::Duration
-            )
-        });
-        
-        let filtered_track_ids: Vec<i64> = if use_sql_optimization {
-            // 使用SQL WHERE子句优化查询
-            if let Some((where_clause, params)) = SmartPlaylistEngine::build_sql_where_clause(&rules) {
-                log::info!("Using SQL optimization for smart playlist refresh");
-                // 使用数据库直接查询
-                db.query_tracks_by_smart_rules(&where_clause, &params, rules.limit.map(|l| l as u32))?
-                    .into_iter()
-                    .map(|t| t.id)
-                    .collect()
-            } else {
-                // Fallback到内存筛选
-                let all_tracks = db.get_all_tracks()?;
-                SmartPlaylistEngine::filter_tracks(&all_tracks, &rules)?
-                    .into_iter()
-                    .map(|t| t.id)
-                    .collect()
-            }
-        } else {
-            // 包含扩展字段，需要内存筛选
-            log::info!("Using in-memory filtering for smart playlist (contains extended fields)");
-            let all_tracks = db.get_all_tracks()?;
-            
-            // 创建元数据提供器
-            let metadata_provider = |track_id: i64| -> Option<super::smart_playlist::TrackMetadata> {
-                Some(super::smart_playlist::TrackMetadata {
-                    date_added: db.get_track_date_added(track_id).ok()?,
-                    last_played: db.get_track_last_played(track_id).ok()?,
-                    play_count: db.get_track_play_count(track_id).unwrap_or(0),
-                    is_favorite: db.is_track_favorite(track_id).unwrap_or(false),
-                })
-            };
-            
-            SmartPlaylistEngine::filter_tracks_with_metadata(&all_tracks, &rules, &metadata_provider)?
-                .into_iter()
-                .map(|t| t.id)
-                .collect()
-        };
+        // 规则翻译为单条SQL查询（播放统计、收藏通过JOIN关联），由数据库完成筛选、排序和限制
+        let query = SmartPlaylistEngine::build_sql_query(&rules, chrono::Utc::now().timestamp())?;
+        let filtered_track_ids: Vec<i64> = db.query_tracks_by_smart_rules(&query)?
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
         
         // 清空现有曲目
         db.clear_playlist_items(playlist_id)?;
@@ -254,9 +361,9 @@ impl PlaylistManager {
     }
 
     /// 刷新所有智能歌单
-    pub fn refresh_all_smart_playlists(&self) -> Result<()> {
+    pub fn refresh_all_smart_playlists(&self) -> AppResult<()> {
         let playlist_ids = {
-            let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
+            let db = self.db.lock()?;
             db.get_smart_playlist_ids()?
         };
         
@@ -270,21 +377,439 @@ impl PlaylistManager {
     }
 
     /// 获取歌单统计信息
-    pub fn get_stats(&self) -> Result<PlaylistStats> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
-        db.get_playlist_stats()
+    pub fn get_stats(&self) -> AppResult<PlaylistStats> {
+        let db = self.db.lock()?;
+        Ok(db.get_playlist_stats()?)
     }
 
     /// 标记歌单为最近播放
-    pub fn mark_played(&self, playlist_id: i64) -> Result<()> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
-        db.mark_playlist_played(playlist_id)
+    pub fn mark_played(&self, playlist_id: i64) -> AppResult<()> {
+        let db = self.db.lock()?;
+        Ok(db.mark_playlist_played(playlist_id)?)
     }
 
     /// 切换收藏状态
-    pub fn toggle_favorite(&self, playlist_id: i64) -> Result<bool> {
-        let db = self.db.lock().map_err(|e| anyhow::anyhow!("Failed to lock database: {}", e))?;
-        db.toggle_playlist_favorite(playlist_id)
+    pub fn toggle_favorite(&self, playlist_id: i64) -> AppResult<bool> {
+        let db = self.db.lock()?;
+        Ok(db.toggle_playlist_favorite(playlist_id)?)
+    }
+
+    /// 获取侧边栏歌单树（文件夹及其中的歌单，之后是最外层的歌单）
+    pub fn get_playlist_tree(&self) -> AppResult<PlaylistTree> {
+        let playlists = self.get_all_playlists()?;
+        let folders = self.db.lock()?.get_playlist_folders()?;
+        Ok(build_tree(folders, playlists))
+    }
+
+    /// 创建歌单文件夹，排在同级文件夹的最后
+    ///
+    /// # 参数
+    /// - parent_id: 父文件夹，None 表示最外层；父文件夹本身必须在最外层
+    pub fn create_folder(&self, name: &str, parent_id: Option<i64>) -> AppResult<i64> {
+        let name = folder_name(name)?;
+        let db = self.db.lock()?;
+        if let Some(parent_id) = parent_id {
+            check_parent(&db.get_playlist_folders()?, None, parent_id)?;
+        }
+        Ok(db.create_playlist_folder(name, parent_id)?)
+    }
+
+    /// 重命名歌单文件夹
+    pub fn rename_folder(&self, folder_id: i64, name: &str) -> AppResult<()> {
+        let name = folder_name(name)?;
+        let db = self.db.lock()?;
+        if !db.rename_playlist_folder(folder_id, name)? {
+            return Err(AppError::not_found(format!("歌单文件夹不存在: {}", folder_id)));
+        }
+        Ok(())
+    }
+
+    /// 删除歌单文件夹，其中的歌单和子文件夹移到上一级，不会删除歌单
+    pub fn delete_folder(&self, folder_id: i64) -> AppResult<()> {
+        let db = self.db.lock()?;
+        if !db.delete_playlist_folder(folder_id)? {
+            return Err(AppError::not_found(format!("歌单文件夹不存在: {}", folder_id)));
+        }
+        Ok(())
+    }
+
+    /// 移动歌单文件夹
+    ///
+    /// # 参数
+    /// - parent_id: 新的父文件夹，None 表示最外层
+    /// - position: 在同级文件夹中的位置，None 表示放在最后
+    pub fn move_folder(&self, folder_id: i64, parent_id: Option<i64>, position: Option<usize>) -> AppResult<()> {
+        let db = self.db.lock()?;
+        let folders = db.get_playlist_folders()?;
+        if !folders.iter().any(|folder| folder.id == folder_id) {
+            return Err(AppError::not_found(format!("歌单文件夹不存在: {}", folder_id)));
+        }
+        if let Some(parent_id) = parent_id {
+            check_parent(&folders, Some(folder_id), parent_id)?;
+        }
+
+        let mut siblings: Vec<i64> = folders
+            .iter()
+            .filter(|folder| folder.parent_id == parent_id && folder.id != folder_id)
+            .map(|folder| folder.id)
+            .collect();
+        let position = position.unwrap_or(siblings.len()).min(siblings.len());
+        siblings.insert(position, folder_id);
+        Ok(db.place_playlist_folders(parent_id, &siblings)?)
+    }
+
+    /// 把歌单移到文件夹中（None 表示最外层），按给出的顺序排在该文件夹原有歌单之后
+    pub fn move_playlists_to_folder(&self, playlist_ids: Vec<i64>, folder_id: Option<i64>) -> AppResult<()> {
+        let db = self.db.lock()?;
+        let folders = db.get_playlist_folders()?;
+        if let Some(folder_id) = folder_id {
+            if !folders.iter().any(|folder| folder.id == folder_id) {
+                return Err(AppError::not_found(format!("歌单文件夹不存在: {}", folder_id)));
+            }
+        }
+        let playlists = db.get_all_playlists_extended()?;
+        if let Some(id) = playlist_ids.iter().find(|id| !playlists.iter().any(|p| p.id == **id)) {
+            return Err(AppError::not_found(format!("歌单不存在: {}", id)));
+        }
+
+        let moved = dedup(playlist_ids);
+        let mut ordered: Vec<i64> = manual_order(&folders, &playlists, folder_id)
+            .into_iter()
+            .filter(|id| !moved.contains(id))
+            .collect();
+        ordered.extend(moved);
+        Ok(db.place_playlists(folder_id, &ordered)?)
+    }
+
+    /// 设置文件夹（None 表示最外层）中歌单的手动顺序，未列出的歌单保持原来的相对顺序排在后面
+    pub fn set_playlist_order(&self, folder_id: Option<i64>, ordered_ids: Vec<i64>) -> AppResult<()> {
+        let db = self.db.lock()?;
+        let folders = db.get_playlist_folders()?;
+        let current = manual_order(&folders, &db.get_all_playlists_extended()?, folder_id);
+        if let Some(id) = ordered_ids.iter().find(|id| !current.contains(id)) {
+            return Err(AppError::invalid_input(format!("歌单 {} 不在该文件夹中", id)));
+        }
+
+        let mut ordered = dedup(ordered_ids);
+        let rest: Vec<i64> = current.into_iter().filter(|id| !ordered.contains(id)).collect();
+        ordered.extend(rest);
+        Ok(db.place_playlists(folder_id, &ordered)?)
+    }
+}
+
+fn ensure_playlist(db: &Database, playlist_id: i64) -> AppResult<()> {
+    if db.get_playlist_by_id(playlist_id)?.is_none() {
+        return Err(AppError::not_found(format!("歌单不存在: {}", playlist_id)));
+    }
+    Ok(())
+}
+
+/// 校验文件夹名称，返回去掉首尾空白后的名称
+fn folder_name(name: &str) -> AppResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("文件夹名称不能为空"));
+    }
+    Ok(name)
+}
+
+/// 检查 parent_id 能否作为父文件夹（moving 为正在移动的文件夹）：只允许嵌套一层
+fn check_parent(folders: &[PlaylistFolder], moving: Option<i64>, parent_id: i64) -> AppResult<()> {
+    let parent = folders
+        .iter()
+        .find(|folder| folder.id == parent_id)
+        .ok_or_else(|| AppError::not_found(format!("歌单文件夹不存在: {}", parent_id)))?;
+    if parent.parent_id.is_some() {
+        return Err(AppError::invalid_input("文件夹最多只能嵌套一层"));
+    }
+    if let Some(moving) = moving {
+        if moving == parent_id {
+            return Err(AppError::invalid_input("不能把文件夹移到自身中"));
+        }
+        if folders.iter().any(|folder| folder.parent_id == Some(moving)) {
+            return Err(AppError::invalid_input("包含子文件夹的文件夹不能再放入其他文件夹"));
+        }
+    }
+    Ok(())
+}
+
+fn dedup(ids: Vec<i64>) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    ids.into_iter().filter(|id| seen.insert(*id)).collect()
+}
+
+/// 歌单所在的文件夹；文件夹已不存在时视为在最外层
+fn effective_folder(folders: &[PlaylistFolder], playlist: &Playlist) -> Option<i64> {
+    playlist.folder_id.filter(|id| folders.iter().any(|folder| folder.id == *id))
+}
+
+/// 手动顺序（不考虑置顶）：sort_order 相同时新建的在前
+fn manual_order_key(playlist: &Playlist) -> (i64, Reverse<i64>, Reverse<i64>) {
+    (playlist.sort_order, Reverse(playlist.created_at), Reverse(playlist.id))
+}
+
+/// 文件夹中的歌单ID，按手动顺序
+fn manual_order(folders: &[PlaylistFolder], playlists: &[Playlist], folder_id: Option<i64>) -> Vec<i64> {
+    let mut in_folder: Vec<&Playlist> = playlists
+        .iter()
+        .filter(|playlist| effective_folder(folders, playlist) == folder_id)
+        .collect();
+    in_folder.sort_by_key(|playlist| manual_order_key(playlist));
+    in_folder.into_iter().map(|playlist| playlist.id).collect()
+}
+
+/// 组装歌单树：同一层内置顶的歌单在前，其余按手动顺序
+fn build_tree(folders: Vec<PlaylistFolder>, mut playlists: Vec<Playlist>) -> PlaylistTree {
+    playlists.sort_by_key(|playlist| (!playlist.is_pinned, manual_order_key(playlist)));
+    let mut by_folder: HashMap<Option<i64>, Vec<Playlist>> = HashMap::new();
+    for playlist in playlists {
+        by_folder.entry(effective_folder(&folders, &playlist)).or_default().push(playlist);
+    }
+
+    // 父文件夹不存在的按最外层处理；嵌套超过一层的（不应出现）也放到最外层
+    let top_level: HashSet<i64> = folders
+        .iter()
+        .filter(|folder| folder.parent_id.map_or(true, |parent| !folders.iter().any(|f| f.id == parent)))
+        .map(|folder| folder.id)
+        .collect();
+
+    let mut roots = Vec::new();
+    let mut children: HashMap<i64, Vec<PlaylistFolderNode>> = HashMap::new();
+    for folder in folders {
+        let parent = folder.parent_id.filter(|parent| top_level.contains(parent));
+        let node = PlaylistFolderNode {
+            playlists: by_folder.remove(&Some(folder.id)).unwrap_or_default(),
+            folders: Vec::new(),
+            folder,
+        };
+        match parent {
+            Some(parent) => children.entry(parent).or_default().push(node),
+            None => roots.push(node),
+        }
+    }
+    for root in &mut roots {
+        root.folders = children.remove(&root.folder.id).unwrap_or_default();
+    }
+
+    PlaylistTree {
+        folders: roots,
+        playlists: by_folder.remove(&None).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Track;
+
+    fn manager() -> PlaylistManager {
+        PlaylistManager::new(Arc::new(DbPool::single(Database::new(":memory:").unwrap())))
+    }
+
+    fn create(manager: &PlaylistManager, name: &str) -> i64 {
+        manager
+            .create_playlist(CreatePlaylistOptions {
+                name: name.to_string(),
+                description: None,
+                color_theme: None,
+                is_smart: false,
+                smart_rules: None,
+            })
+            .unwrap()
+    }
+
+    fn names(playlists: &[Playlist]) -> Vec<&str> {
+        playlists.iter().map(|playlist| playlist.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ordering_is_stable_after_moves() {
+        let manager = manager();
+        let a = create(&manager, "A");
+        let b = create(&manager, "B");
+        let c = create(&manager, "C");
+        let d = create(&manager, "D");
+        let folder = manager.create_folder(" Mixes ", None).unwrap();
+
+        // 未调整过顺序时新建的在前
+        let tree = manager.get_playlist_tree().unwrap();
+        assert_eq!(names(&tree.playlists), ["D", "C", "B", "A"]);
+        assert_eq!(tree.folders[0].folder.name, "Mixes");
+
+        manager.move_playlists_to_folder(vec![b], Some(folder)).unwrap();
+        manager.move_playlists_to_folder(vec![d], Some(folder)).unwrap();
+        let tree = manager.get_playlist_tree().unwrap();
+        assert_eq!(names(&tree.playlists), ["C", "A"]);
+        assert_eq!(names(&tree.folders[0].playlists), ["B", "D"]);
+
+        manager.set_playlist_order(Some(folder), vec![d]).unwrap();
+        manager.move_playlists_to_folder(vec![b], None).unwrap();
+        let tree = manager.get_playlist_tree().unwrap();
+        assert_eq!(names(&tree.playlists), ["C", "A", "B"]);
+        assert_eq!(names(&tree.folders[0].playlists), ["D"]);
+
+        // 置顶的歌单在前，取消置顶后回到原来的位置
+        manager.db.lock().unwrap().pin_playlist(a).unwrap();
+        assert_eq!(names(&manager.get_playlist_tree().unwrap().playlists), ["A", "C", "B"]);
+        manager.db.lock().unwrap().unpin_playlist(a).unwrap();
+
+        // 删除文件夹后其中的歌单排到最外层的最后
+        manager.delete_folder(folder).unwrap();
+        let tree = manager.get_playlist_tree().unwrap();
+        assert!(tree.folders.is_empty());
+        assert_eq!(names(&tree.playlists), ["C", "A", "B", "D"]);
+
+        assert!(manager.set_playlist_order(Some(folder), vec![c]).is_err());
+        assert!(manager.set_playlist_order(None, vec![999]).is_err());
+    }
+
+    #[test]
+    fn test_bulk_add_remove_and_deduplicate() {
+        let manager = manager();
+        let playlist = create(&manager, "Bulk");
+        {
+            let db = manager.db.lock().unwrap();
+            for (path, artist, album, number) in [
+                ("/m/2.flac", "Band", "First", 2),
+                ("/m/1.flac", "Band", "First", 1),
+                ("/m/3.flac", "Band", "Second", 1),
+                ("/m/4.flac", "Solo", "First", 1),
+            ] {
+                let mut track = Track::new(0, path.to_string());
+                track.artist = Some(artist.to_string());
+                track.album = Some(album.to_string());
+                track.track_number = Some(number);
+                db.insert_track(&track).unwrap();
+            }
+        }
+        let paths = |manager: &PlaylistManager| -> Vec<String> {
+            let db = manager.db.lock().unwrap();
+            db.get_playlist_tracks(playlist).unwrap().into_iter().map(|t| t.path).collect()
+        };
+
+        let result = manager.add_album(playlist, "First", Some("Band"), true).unwrap();
+        assert_eq!(result, AddTracksResult { added: 2, skipped: 0 });
+        assert_eq!(paths(&manager), ["/m/1.flac", "/m/2.flac"]);
+
+        let result = manager.add_artist(playlist, "Band", true).unwrap();
+        assert_eq!(result, AddTracksResult { added: 1, skipped: 2 });
+        assert_eq!(paths(&manager), ["/m/1.flac", "/m/2.flac", "/m/3.flac"]);
+
+        // 不跳过时允许重复
+        let result = manager.add_album(playlist, "First", Some("Band"), false).unwrap();
+        assert_eq!(result, AddTracksResult { added: 2, skipped: 0 });
+        assert_eq!(manager.deduplicate(playlist).unwrap(), 2);
+        assert_eq!(paths(&manager), ["/m/1.flac", "/m/2.flac", "/m/3.flac"]);
+        assert_eq!(manager.deduplicate(playlist).unwrap(), 0);
+
+        let first = manager.db.lock().unwrap().get_track_by_path("/m/1.flac").unwrap().unwrap().id;
+        let third = manager.db.lock().unwrap().get_track_by_path("/m/3.flac").unwrap().unwrap().id;
+        assert_eq!(manager.remove_tracks(playlist, vec![first, third]).unwrap(), 2);
+        assert_eq!(paths(&manager), ["/m/2.flac"]);
+
+        assert!(manager.add_artist(playlist, "Nobody", true).is_err());
+        assert!(manager.remove_tracks(999, vec![first]).is_err());
+    }
+
+    #[test]
+    fn test_builtin_playlists() {
+        let manager = manager();
+        let favorite = {
+            let db = manager.db.lock().unwrap();
+            let mut track = Track::new(0, "/m/fav.flac".to_string());
+            track.duration_ms = Some(1500);
+            let id = db.insert_track(&track).unwrap();
+            db.insert_track(&Track::new(0, "/m/other.flac".to_string())).unwrap();
+            db.add_favorite(id).unwrap();
+            id
+        };
+
+        let config = BuiltinPlaylistsConfig { recently_added_limit: 1, ..Default::default() };
+        let playlists = manager.get_builtin_playlists(&config).unwrap();
+        let ids: Vec<i64> = playlists.iter().map(|p| p.playlist.id).collect();
+        assert_eq!(ids, [-1, -2, -3, -4]);
+        assert_eq!(playlists[0].tracks.len(), 1);
+        assert!(playlists[1].tracks.is_empty());
+
+        // 内置歌单的保留ID可用于详情和导出
+        let favorites = manager.resolve_playlist(BuiltinPlaylist::Favorites.id(), &config).unwrap();
+        assert_eq!(favorites.tracks[0].id, favorite);
+        assert_eq!(favorites.playlist.total_duration_ms, 1500);
+        assert_eq!(manager.resolve_added_at(BuiltinPlaylist::Favorites.id(), 1).unwrap(), [None]);
+        assert!(manager.resolve_playlist(-99, &config).is_err());
+
+        assert!(BuiltinPlaylistsConfig { most_played_limit: 0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_folder_nesting_and_order() {
+        let manager = manager();
+        let outer = manager.create_folder("Outer", None).unwrap();
+        let inner = manager.create_folder("Inner", Some(outer)).unwrap();
+        let other = manager.create_folder("Other", None).unwrap();
+
+        assert!(manager.create_folder("Deep", Some(inner)).is_err());
+        assert!(manager.create_folder("  ", None).is_err());
+        assert!(manager.move_folder(outer, Some(outer), None).is_err());
+        assert!(manager.move_folder(outer, Some(other), None).is_err());
+
+        manager.move_folder(other, None, Some(0)).unwrap();
+        let tree = manager.get_playlist_tree().unwrap();
+        let top: Vec<&str> = tree.folders.iter().map(|node| node.folder.name.as_str()).collect();
+        assert_eq!(top, ["Other", "Outer"]);
+        assert_eq!(tree.folders[1].folders[0].folder.id, inner);
+
+        let playlist = create(&manager, "Inside");
+        manager.move_playlists_to_folder(vec![playlist], Some(inner)).unwrap();
+        manager.delete_folder(outer).unwrap();
+        let tree = manager.get_playlist_tree().unwrap();
+        let top: Vec<&str> = tree.folders.iter().map(|node| node.folder.name.as_str()).collect();
+        assert_eq!(top, ["Other", "Inner"]);
+        assert_eq!(names(&tree.folders[1].playlists), ["Inside"]);
+    }
+
+    #[test]
+    fn test_playback_defaults_and_play() {
+        use crate::player::RepeatMode;
+
+        let manager = manager();
+        let playlist = create(&manager, "Sleep");
+        let config = BuiltinPlaylistsConfig::default();
+        let update = |json: &str| {
+            let options: UpdatePlaylistOptions = serde_json::from_str(json).unwrap();
+            manager.update_playlist(playlist, options).unwrap();
+            manager.db.lock().unwrap().get_playlist_by_id(playlist).unwrap().unwrap()
+        };
+
+        let detail = update(r#"{"default_shuffle": false, "default_repeat_mode": "All", "default_crossfade_ms": 0}"#);
+        assert_eq!(detail.default_shuffle, Some(false));
+        assert_eq!(detail.default_repeat_mode, Some(RepeatMode::All));
+        assert_eq!(detail.default_crossfade_ms, Some(0));
+
+        // 缺省的字段不修改，null 清除
+        let detail = update(r#"{"name": "Sleep", "default_crossfade_ms": null}"#);
+        assert_eq!((detail.default_shuffle, detail.default_crossfade_ms), (Some(false), None));
+        assert_eq!(detail.default_repeat_mode, Some(RepeatMode::All));
+
+        assert!(manager.play_playlist(playlist, None, &config).is_err());
+        let ids: Vec<i64> = {
+            let db = manager.db.lock().unwrap();
+            ["/m/a.flac", "/m/b.flac", "/m/c.flac"]
+                .into_iter()
+                .map(|path| db.insert_track(&Track::new(0, path.to_string())).unwrap())
+                .collect()
+        };
+        manager.add_tracks_to_playlist(playlist, ids.clone(), true).unwrap();
+
+        let (detail, start) = manager.play_playlist(playlist, None, &config).unwrap();
+        assert_eq!((detail.tracks.len(), start), (3, ids[0]));
+        assert_eq!(manager.play_playlist(playlist, Some(ids[2]), &config).unwrap().1, ids[2]);
+        assert!(manager.play_playlist(playlist, Some(999), &config).is_err());
+        assert_eq!(manager.db.lock().unwrap().get_playlist_by_id(playlist).unwrap().unwrap().play_count, 2);
+
+        update(r#"{"default_shuffle": true}"#);
+        let (_, start) = manager.play_playlist(playlist, None, &config).unwrap();
+        assert!(ids.contains(&start));
     }
 }
 
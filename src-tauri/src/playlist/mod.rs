@@ -10,6 +10,8 @@ pub mod smart_playlist;
 pub mod manager;
 pub mod exporter;
 pub mod importer;
+pub mod builtin;
+pub mod audio_export;
 
 // Re-exports for convenience
 pub use types::*;
@@ -17,5 +19,6 @@ pub use types::*;
 pub use manager::PlaylistManager;
 pub use exporter::PlaylistExporter;
 pub use importer::PlaylistImporter;
+pub use builtin::{BuiltinPlaylist, BuiltinPlaylistsConfig};
 
 
@@ -2,23 +2,58 @@
 //
 // 职责：
 // - 解析智能规则
-// - 执行内存筛选生成曲目列表
-// - 生成SQL查询优化
-// - 支持复杂的AND/OR逻辑组合
+// - 将规则翻译为单条SQL查询（关联播放历史、收藏表）
+// - 执行内存筛选生成曲目列表（预览等场景）
+// - 支持AND/OR逻辑组合及一层分组嵌套
 //
 // 设计原则：
-// - 性能优化：提供零拷贝的引用版本筛选方法
+// - 性能优化：刷新时由数据库完成筛选、排序和限制数量
 // - 可扩展性：支持元数据提供器模式
-// - 双路径：内存筛选 + SQL优化
+// - 双路径：SQL查询 + 内存筛选
 
-use super::types::{SmartRules, SmartRule, RuleField, RuleOperator};
+use super::types::{RuleField, RuleOperator, RuleValue, SmartRule, SmartRules, SmartSortField};
 use crate::player::Track;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use rusqlite::types::Value;
+
+/// 智能歌单查询的FROM子句：播放统计和收藏通过LEFT JOIN关联
+///
+/// 规则生成的条件使用这里的表别名：t=tracks，ph=播放统计，f=favorites
+pub const SMART_QUERY_FROM: &str = "tracks t
+     LEFT JOIN (SELECT track_id, COUNT(*) AS play_count, MAX(played_at) AS last_played
+                FROM play_history GROUP BY track_id) ph ON ph.track_id = t.id
+     LEFT JOIN favorites f ON f.track_id = t.id";
+
+/// 默认排序
+const DEFAULT_ORDER: &str = "t.artist, t.album, t.disc_number, t.track_number, t.title";
+
+/// 由智能规则生成的SQL查询
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartQuery {
+    /// WHERE条件（没有规则时为 "1"）
+    pub where_clause: String,
+    pub order_by: String,
+    pub limit: Option<i64>,
+    /// 按出现顺序对应 where_clause 中的 ? 占位符
+    pub params: Vec<Value>,
+}
+
+impl SmartQuery {
+    /// 完整SQL，columns 为要选择的列（使用 t. 前缀）
+    pub fn to_sql(&self, columns: &str) -> String {
+        let limit = self.limit.map(|l| format!(" LIMIT {}", l)).unwrap_or_default();
+        format!(
+            "SELECT {} FROM {} WHERE {} ORDER BY {}{}",
+            columns, SMART_QUERY_FROM, self.where_clause, self.order_by, limit
+        )
+    }
+}
 
 /// 🔧 P2新增：曲目扩展元数据（用于智能歌单筛选）
 /// 
 /// 包含Track结构之外的元数据信息，用于高级筛选功能
 #[derive(Debug, Clone)]
+#[allow(dead_code)] // 预留：内存筛选路径使用，刷新已改为SQL查询
 pub struct TrackMetadata {
     /// 添加到音乐库的时间（Unix时间戳）
     pub date_added: Option<i64>,
@@ -33,28 +68,46 @@ pub struct TrackMetadata {
 /// 智能歌单引擎
 /// 
 /// 提供两种筛选方式：
-/// 1. 内存筛选：适用于所有规则类型
-/// 2. SQL优化：仅适用于基本字段，性能更优
+/// 1. SQL查询：支持所有规则类型，刷新智能歌单时使用
+/// 2. 内存筛选：对已加载的曲目列表筛选
 pub struct SmartPlaylistEngine;
 
 impl SmartPlaylistEngine {
+    /// 按规则组合判断：顶层规则和分组按 rules.match_all 组合，空分组忽略
+    fn matches(rules: &SmartRules, matcher: &dyn Fn(&SmartRule) -> bool) -> bool {
+        let group_results = rules.groups.iter()
+            .filter(|group| !group.rules.is_empty())
+            .map(|group| {
+                if group.match_all {
+                    group.rules.iter().all(matcher)
+                } else {
+                    group.rules.iter().any(matcher)
+                }
+            });
+        let mut results = rules.rules.iter().map(matcher).chain(group_results);
+        if rules.match_all {
+            results.all(|r| r)
+        } else {
+            results.any(|r| r)
+        }
+    }
+
+    fn has_conditions(rules: &SmartRules) -> bool {
+        !rules.rules.is_empty() || rules.groups.iter().any(|g| !g.rules.is_empty())
+    }
+
     /// 🔧 P2修复：根据智能规则筛选曲目（优化版 - 单次迭代+避免克隆）
     /// 
     /// 设计改进：
     /// - 返回track引用而非克隆，避免大量内存分配
     /// - 单次迭代完成过滤和限制，提升性能
+    #[allow(dead_code)] // 预留：内存筛选（预览等场景）
     pub fn filter_tracks_optimized<'a>(tracks: &'a [Track], rules: &SmartRules) -> Result<Vec<&'a Track>> {
-        if rules.rules.is_empty() {
+        if !Self::has_conditions(rules) {
             return Ok(tracks.iter().collect());
         }
 
-        let predicate = |track: &&Track| {
-            if rules.match_all {
-                rules.rules.iter().all(|rule| Self::match_rule(track, rule))
-            } else {
-                rules.rules.iter().any(|rule| Self::match_rule(track, rule))
-            }
-        };
+        let predicate = |track: &&Track| Self::matches(rules, &|rule: &SmartRule| Self::match_rule(track, rule));
 
         let filtered: Vec<&Track> = if let Some(limit) = rules.limit {
             if limit > 0 {
@@ -73,6 +126,7 @@ impl SmartPlaylistEngine {
     }
 
     /// 兼容性方法：保留原有API，内部使用优化版本
+    #[allow(dead_code)] // 预留：内存筛选（预览等场景）
     pub fn filter_tracks(tracks: &[Track], rules: &SmartRules) -> Result<Vec<Track>> {
         Ok(Self::filter_tracks_optimized(tracks, rules)?
             .into_iter()
@@ -94,9 +148,18 @@ impl SmartPlaylistEngine {
             RuleField::Album => {
                 Self::match_string_field(&track.album, &rule.operator, &rule.value)
             }
+            RuleField::Genre => {
+                Self::match_string_field(&track.genre, &rule.operator, &rule.value)
+            }
+            RuleField::Year => {
+                Self::match_number_field(track.year, &rule.operator, &rule.value)
+            }
             RuleField::Duration => {
                 Self::match_number_field(track.duration_ms, &rule.operator, &rule.value)
             }
+            RuleField::Rating => {
+                Self::match_number_field(Some(track.rating.unwrap_or(0) as i64), &rule.operator, &rule.value)
+            }
             // 🔧 扩展字段支持
             // 注意：这些字段需要使用 filter_tracks_with_metadata 方法
             // 该方法接受 metadata_provider 来提供扩展信息（播放次数、收藏状态等）
@@ -117,26 +180,19 @@ impl SmartPlaylistEngine {
     /// 🔧 P2新增：支持扩展字段的筛选（接受额外的元数据）
     /// 
     /// 用于需要扩展字段（DateAdded, LastPlayed等）的场景
+    #[allow(dead_code)] // 预留：内存筛选（预览等场景）
     pub fn filter_tracks_with_metadata<'a>(
         tracks: &'a [Track],
         rules: &SmartRules,
         metadata_provider: &dyn Fn(i64) -> Option<TrackMetadata>,
     ) -> Result<Vec<&'a Track>> {
-        if rules.rules.is_empty() {
+        if !Self::has_conditions(rules) {
             return Ok(tracks.iter().collect());
         }
 
+        let now = chrono::Utc::now().timestamp();
         let predicate = |track: &&Track| {
-            let matches_all_or_any = if rules.match_all {
-                rules.rules.iter().all(|rule| {
-                    Self::match_rule_with_metadata(track, rule, metadata_provider)
-                })
-            } else {
-                rules.rules.iter().any(|rule| {
-                    Self::match_rule_with_metadata(track, rule, metadata_provider)
-                })
-            };
-            matches_all_or_any
+            Self::matches(rules, &|rule: &SmartRule| Self::match_rule_with_metadata(track, rule, metadata_provider, now))
         };
 
         let filtered: Vec<&Track> = if let Some(limit) = rules.limit {
@@ -157,21 +213,23 @@ impl SmartPlaylistEngine {
         track: &Track,
         rule: &SmartRule,
         metadata_provider: &dyn Fn(i64) -> Option<TrackMetadata>,
+        now: i64,
     ) -> bool {
         match &rule.field {
-            RuleField::Title | RuleField::Artist | RuleField::Album | RuleField::Duration => {
+            RuleField::Title | RuleField::Artist | RuleField::Album | RuleField::Genre | RuleField::Year
+            | RuleField::Duration | RuleField::Rating => {
                 Self::match_rule(track, rule)
             }
             RuleField::DateAdded => {
                 if let Some(meta) = metadata_provider(track.id) {
-                    Self::match_number_field(meta.date_added, &rule.operator, &rule.value)
+                    Self::match_date_field(meta.date_added, &rule.operator, &rule.value, now)
                 } else {
                     false
                 }
             }
             RuleField::LastPlayed => {
                 if let Some(meta) = metadata_provider(track.id) {
-                    Self::match_number_field(meta.last_played, &rule.operator, &rule.value)
+                    Self::match_date_field(meta.last_played, &rule.operator, &rule.value, now)
                 } else {
                     false
                 }
@@ -201,35 +259,23 @@ impl SmartPlaylistEngine {
     fn match_string_field(
         field: &Option<String>,
         operator: &RuleOperator,
-        value: &str,
+        value: &RuleValue,
     ) -> bool {
         let field_value = match field {
             Some(v) => v,
             None => return false,
         };
         
-        // 🔧 P3优化：只在需要大小写不敏感比较时才转换
+        let field_lower = field_value.to_lowercase();
+        let search_lower = value.as_text().to_lowercase();
         match operator {
-            RuleOperator::Equals | RuleOperator::NotEquals => {
-                let field_lower = field_value.to_lowercase();
-                let search_lower = value.to_lowercase();
-                match operator {
-                    RuleOperator::Equals => field_lower == search_lower,
-                    RuleOperator::NotEquals => field_lower != search_lower,
-                    _ => unreachable!(),
-                }
-            }
-            _ => {
-                let field_lower = field_value.to_lowercase();
-                let search_lower = value.to_lowercase();
-                match operator {
-                    RuleOperator::Contains => field_lower.contains(&search_lower),
-                    RuleOperator::NotContains => !field_lower.contains(&search_lower),
-                    RuleOperator::StartsWith => field_lower.starts_with(&search_lower),
-                    RuleOperator::EndsWith => field_lower.ends_with(&search_lower),
-                    _ => false,
-                }
-            }
+            RuleOperator::Equals => field_lower == search_lower,
+            RuleOperator::NotEquals => field_lower != search_lower,
+            RuleOperator::Contains => field_lower.contains(&search_lower),
+            RuleOperator::NotContains => !field_lower.contains(&search_lower),
+            RuleOperator::StartsWith => field_lower.starts_with(&search_lower),
+            RuleOperator::EndsWith => field_lower.ends_with(&search_lower),
+            _ => false,
         }
     }
 
@@ -237,7 +283,7 @@ impl SmartPlaylistEngine {
     fn match_number_field(
         field: Option<i64>,
         operator: &RuleOperator,
-        value: &str,
+        value: &RuleValue,
     ) -> bool {
         let field_value = match field {
             Some(v) => v,
@@ -245,10 +291,10 @@ impl SmartPlaylistEngine {
         };
 
         // 🔧 P2修复：解析失败时记录警告
-        let compare_value = match value.parse::<i64>() {
-            Ok(v) => v,
-            Err(e) => {
-                log::warn!("Failed to parse number value '{}' for rule: {}", value, e);
+        let compare_value = match value.as_number() {
+            Some(v) => v,
+            None => {
+                log::warn!("Failed to parse number value {:?} for rule", value);
                 return false;
             }
         };
@@ -264,85 +310,179 @@ impl SmartPlaylistEngine {
         }
     }
 
-    /// 🔧 P2功能：构建SQL查询的WHERE子句（用于数据库层面的优化）
-    /// 
-    /// 仅支持基本字段（Title, Artist, Album, Duration）
-    /// 
-    /// # 返回
-    /// - Some((where_clause, params)): SQL WHERE子句和参数
-    /// - None: 规则为空或不支持SQL优化
-    pub fn build_sql_where_clause(rules: &SmartRules) -> Option<(String, Vec<String>)> {
-        if rules.rules.is_empty() {
-            return None;
+    /// 匹配日期字段（支持最近N天和相对日期）
+    fn match_date_field(field: Option<i64>, operator: &RuleOperator, value: &RuleValue, now: i64) -> bool {
+        match operator {
+            RuleOperator::WithinDays | RuleOperator::NotWithinDays => {
+                let Some(days) = value.as_days() else {
+                    return false;
+                };
+                let within = field.is_some_and(|t| t >= now - days * 86_400);
+                within == (*operator == RuleOperator::WithinDays)
+            }
+            _ => {
+                let (Some(field), Some(timestamp)) = (field, value.as_timestamp(now)) else {
+                    return false;
+                };
+                match operator {
+                    RuleOperator::Before | RuleOperator::LessThan => field < timestamp,
+                    RuleOperator::After | RuleOperator::GreaterThan => field > timestamp,
+                    RuleOperator::LessOrEqual => field <= timestamp,
+                    RuleOperator::GreaterOrEqual => field >= timestamp,
+                    RuleOperator::Equals => field == timestamp,
+                    RuleOperator::NotEquals => field != timestamp,
+                    _ => false,
+                }
+            }
         }
+    }
 
-        let mut conditions = Vec::new();
+    /// 🔧 P2功能：把智能规则翻译为单条SQL查询
+    ///
+    /// 播放次数、最后播放时间、收藏通过 SMART_QUERY_FROM 中的JOIN得到，
+    /// 相对日期按 now 换算为时间戳。规则与字段不匹配（如对标题用“最近N天”）时返回错误。
+    pub fn build_sql_query(rules: &SmartRules, now: i64) -> Result<SmartQuery> {
         let mut params = Vec::new();
+        let mut conditions = Vec::new();
 
         for rule in &rules.rules {
-            if let Some((condition, param)) = Self::rule_to_sql(rule) {
-                conditions.push(condition);
-                if let Some(p) = param {
-                    params.push(p);
-                }
-            }
+            conditions.push(Self::rule_to_sql(rule, now, &mut params)?);
         }
-
-        if conditions.is_empty() {
-            return None;
+        for group in rules.groups.iter().filter(|g| !g.rules.is_empty()) {
+            let parts = group.rules.iter()
+                .map(|rule| Self::rule_to_sql(rule, now, &mut params))
+                .collect::<Result<Vec<_>>>()?;
+            let connector = if group.match_all { " AND " } else { " OR " };
+            conditions.push(format!("({})", parts.join(connector)));
         }
 
-        let connector = if rules.match_all { " AND " } else { " OR " };
-        let where_clause = conditions.join(connector);
-
-        Some((where_clause, params))
-    }
-
-    /// 将单条规则转换为SQL条件
-    fn rule_to_sql(rule: &SmartRule) -> Option<(String, Option<String>)> {
-        let column = match rule.field {
-            RuleField::Title => "title",
-            RuleField::Artist => "artist",
-            RuleField::Album => "album",
-            RuleField::Duration => "duration_ms",
-            _ => return None, // 其他字段暂不支持SQL查询
+        let where_clause = if conditions.is_empty() {
+            "1".to_string()
+        } else {
+            conditions.join(if rules.match_all { " AND " } else { " OR " })
         };
 
-        let (operator_sql, needs_param) = match rule.operator {
-            RuleOperator::Equals => ("=", true),
-            RuleOperator::NotEquals => ("!=", true),
-            RuleOperator::Contains => ("LIKE", true),
-            RuleOperator::NotContains => ("NOT LIKE", true),
-            RuleOperator::StartsWith => ("LIKE", true),
-            RuleOperator::EndsWith => ("LIKE", true),
-            RuleOperator::GreaterThan => (">", true),
-            RuleOperator::LessThan => ("<", true),
-            RuleOperator::GreaterOrEqual => (">=", true),
-            RuleOperator::LessOrEqual => ("<=", true),
-            _ => return None,
+        let order_by = match &rules.sort {
+            Some(sort) if sort.field == SmartSortField::Random => "RANDOM()".to_string(),
+            Some(sort) => {
+                let column = match sort.field {
+                    SmartSortField::Title => "t.title",
+                    SmartSortField::Artist => "t.artist",
+                    SmartSortField::Album => "t.album",
+                    SmartSortField::Year => "t.year",
+                    SmartSortField::Duration => "t.duration_ms",
+                    SmartSortField::DateAdded => "t.created_at",
+                    SmartSortField::LastPlayed => "ph.last_played",
+                    SmartSortField::PlayCount => "COALESCE(ph.play_count, 0)",
+                    SmartSortField::Rating => "COALESCE(t.rating, 0)",
+                    SmartSortField::Random => unreachable!(),
+                };
+                let direction = if sort.descending { "DESC" } else { "ASC" };
+                format!("{} {}, t.id", column, direction)
+            }
+            None => DEFAULT_ORDER.to_string(),
         };
 
-        let param_value = if needs_param {
-            Some(match rule.operator {
-                RuleOperator::Contains | RuleOperator::NotContains => {
-                    format!("%{}%", rule.value)
+        Ok(SmartQuery {
+            where_clause,
+            order_by,
+            limit: rules.limit.filter(|&l| l > 0),
+            params,
+        })
+    }
+
+    /// 将单条规则转换为SQL条件，参数追加到 params
+    fn rule_to_sql(rule: &SmartRule, now: i64, params: &mut Vec<Value>) -> Result<String> {
+        let invalid = || anyhow!("智能规则不支持: {:?} {:?} {:?}", rule.field, rule.operator, rule.value);
+        let number = || rule.value.as_number().ok_or_else(invalid);
+
+        match rule.field {
+            RuleField::Title | RuleField::Artist | RuleField::Album | RuleField::Genre => {
+                let column = match rule.field {
+                    RuleField::Title => "t.title",
+                    RuleField::Artist => "t.artist",
+                    RuleField::Album => "t.album",
+                    _ => "t.genre",
+                };
+                let text = rule.value.as_text();
+                let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+                let (condition, param) = match rule.operator {
+                    RuleOperator::Equals => (format!("{} = ? COLLATE NOCASE", column), text),
+                    RuleOperator::NotEquals => (format!("{} != ? COLLATE NOCASE", column), text),
+                    RuleOperator::Contains => (format!("{} LIKE ? ESCAPE '\\'", column), format!("%{}%", escaped)),
+                    RuleOperator::NotContains => (format!("{} NOT LIKE ? ESCAPE '\\'", column), format!("%{}%", escaped)),
+                    RuleOperator::StartsWith => (format!("{} LIKE ? ESCAPE '\\'", column), format!("{}%", escaped)),
+                    RuleOperator::EndsWith => (format!("{} LIKE ? ESCAPE '\\'", column), format!("%{}", escaped)),
+                    _ => return Err(invalid()),
+                };
+                params.push(Value::Text(param));
+                Ok(condition)
+            }
+            RuleField::Year | RuleField::Duration | RuleField::PlayCount | RuleField::Rating => {
+                let column = match rule.field {
+                    RuleField::Year => "t.year",
+                    RuleField::Duration => "t.duration_ms",
+                    // 未评分按0星
+                    RuleField::Rating => "COALESCE(t.rating, 0)",
+                    _ => "COALESCE(ph.play_count, 0)",
+                };
+                let operator = Self::comparison_sql(&rule.operator).ok_or_else(invalid)?;
+                params.push(Value::Integer(number()?));
+                Ok(format!("{} {} ?", column, operator))
+            }
+            RuleField::DateAdded | RuleField::LastPlayed => {
+                let column = if rule.field == RuleField::DateAdded { "t.created_at" } else { "ph.last_played" };
+                match rule.operator {
+                    RuleOperator::WithinDays => {
+                        let days = rule.value.as_days().ok_or_else(invalid)?;
+                        params.push(Value::Integer(now - days * 86_400));
+                        Ok(format!("{} >= ?", column))
+                    }
+                    // 从未播放也算“N天内没有播放”
+                    RuleOperator::NotWithinDays => {
+                        let days = rule.value.as_days().ok_or_else(invalid)?;
+                        params.push(Value::Integer(now - days * 86_400));
+                        Ok(format!("({} IS NULL OR {} < ?)", column, column))
+                    }
+                    RuleOperator::Before | RuleOperator::After => {
+                        let timestamp = rule.value.as_timestamp(now).ok_or_else(invalid)?;
+                        params.push(Value::Integer(timestamp));
+                        let operator = if rule.operator == RuleOperator::Before { "<" } else { ">" };
+                        Ok(format!("{} {} ?", column, operator))
+                    }
+                    _ => {
+                        let operator = Self::comparison_sql(&rule.operator).ok_or_else(invalid)?;
+                        let timestamp = rule.value.as_timestamp(now).ok_or_else(invalid)?;
+                        params.push(Value::Integer(timestamp));
+                        Ok(format!("{} {} ?", column, operator))
+                    }
                 }
-                RuleOperator::StartsWith => format!("{}%", rule.value),
-                RuleOperator::EndsWith => format!("%{}", rule.value),
-                _ => rule.value.clone(),
-            })
-        } else {
-            None
-        };
+            }
+            RuleField::IsFavorite => match rule.operator {
+                RuleOperator::IsTrue => Ok("f.track_id IS NOT NULL".to_string()),
+                RuleOperator::IsFalse => Ok("f.track_id IS NULL".to_string()),
+                _ => Err(invalid()),
+            },
+        }
+    }
 
-        let condition = format!("{} {} ?", column, operator_sql);
-        Some((condition, param_value))
+    fn comparison_sql(operator: &RuleOperator) -> Option<&'static str> {
+        Some(match operator {
+            RuleOperator::Equals => "=",
+            RuleOperator::NotEquals => "!=",
+            RuleOperator::GreaterThan => ">",
+            RuleOperator::LessThan => "<",
+            RuleOperator::GreaterOrEqual => ">=",
+            RuleOperator::LessOrEqual => "<=",
+            _ => return None,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::playlist::types::{RuleGroup, SmartSort};
 
     fn create_test_track(title: &str, artist: &str, duration_ms: i64) -> Track {
         Track {
@@ -352,6 +492,14 @@ mod tests {
             artist: Some(artist.to_string()),
             album: Some("Test Album".to_string()),
             duration_ms: Some(duration_ms),
+            genre: None,
+            year: None,
+            track_number: None,
+            disc_number: None,
+            album_artist: None,
+            cover_id: None,
+            rating: None,
+            verify_status: None,
             album_cover_data: None,
             album_cover_mime: None,
             artist_photo_data: None,
@@ -372,10 +520,11 @@ mod tests {
             rules: vec![SmartRule {
                 field: RuleField::Artist,
                 operator: RuleOperator::Equals,
-                value: "Artist A".to_string(),
+                value: "Artist A".into(),
             }],
             match_all: true,
             limit: None,
+            ..Default::default()
         };
 
         let filtered = SmartPlaylistEngine::filter_tracks(&tracks, &rules).unwrap();
@@ -393,10 +542,11 @@ mod tests {
             rules: vec![SmartRule {
                 field: RuleField::Duration,
                 operator: RuleOperator::LessThan,
-                value: "250000".to_string(),
+                value: "250000".into(),
             }],
             match_all: true,
             limit: None,
+            ..Default::default()
         };
 
         let filtered = SmartPlaylistEngine::filter_tracks(&tracks, &rules).unwrap();
@@ -416,16 +566,176 @@ mod tests {
             rules: vec![SmartRule {
                 field: RuleField::Artist,
                 operator: RuleOperator::Contains,
-                value: "Artist".to_string(),
+                value: "Artist".into(),
             }],
             match_all: true,
             limit: Some(2),
+            ..Default::default()
         };
 
         let filtered = SmartPlaylistEngine::filter_tracks(&tracks, &rules).unwrap();
         assert_eq!(filtered.len(), 2);
     }
-}
 
+    #[test]
+    fn test_filter_by_genre_and_year() {
+        let mut tracks = vec![
+            create_test_track("So What", "Miles Davis", 540000),
+            create_test_track("Giant Steps", "John Coltrane", 290000),
+            create_test_track("Yesterday", "The Beatles", 125000),
+        ];
+        tracks[0].genre = Some("Jazz".to_string());
+        tracks[0].year = Some(1959);
+        tracks[1].genre = Some("Jazz".to_string());
+        tracks[1].year = Some(1975);
+        tracks[2].genre = Some("Rock".to_string());
+        tracks[2].year = Some(1965);
+
+        let rules = SmartRules {
+            rules: vec![
+                SmartRule {
+                    field: RuleField::Genre,
+                    operator: RuleOperator::Equals,
+                    value: "Jazz".into(),
+                },
+                SmartRule {
+                    field: RuleField::Year,
+                    operator: RuleOperator::LessThan,
+                    value: "1970".into(),
+                },
+            ],
+            match_all: true,
+            limit: None,
+            ..Default::default()
+        };
+
+        let filtered = SmartPlaylistEngine::filter_tracks(&tracks, &rules).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].title, Some("So What".to_string()));
+
+        let query = SmartPlaylistEngine::build_sql_query(&rules, 0).unwrap();
+        assert_eq!(query.where_clause, "t.genre = ? COLLATE NOCASE AND t.year < ?");
+        assert_eq!(query.params, vec![Value::Text("Jazz".to_string()), Value::Integer(1970)]);
+    }
+
+    fn rule(field: RuleField, operator: RuleOperator, value: RuleValue) -> SmartRule {
+        SmartRule { field, operator, value }
+    }
+
+    const DAY: i64 = 86_400;
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_sql_for_relative_dates_play_stats_and_groups() {
+        // 收藏 AND (流派=rock OR 最近30天添加) AND 播放超过20次，按播放次数倒序取50首
+        let rules = SmartRules {
+            rules: vec![
+                rule(RuleField::IsFavorite, RuleOperator::IsTrue, "".into()),
+                rule(RuleField::PlayCount, RuleOperator::GreaterThan, RuleValue::Number(20)),
+            ],
+            groups: vec![RuleGroup {
+                rules: vec![
+                    rule(RuleField::Genre, RuleOperator::Equals, "rock".into()),
+                    rule(RuleField::DateAdded, RuleOperator::After, RuleValue::LastNDays { last_n_days: 30 }),
+                ],
+                match_all: false,
+            }],
+            match_all: true,
+            limit: Some(50),
+            sort: Some(SmartSort { field: SmartSortField::PlayCount, descending: true }),
+        };
+
+        let query = SmartPlaylistEngine::build_sql_query(&rules, NOW).unwrap();
+        assert_eq!(
+            query.where_clause,
+            "f.track_id IS NOT NULL AND COALESCE(ph.play_count, 0) > ? AND (t.genre = ? COLLATE NOCASE OR t.created_at > ?)"
+        );
+        assert_eq!(
+            query.params,
+            vec![Value::Integer(20), Value::Text("rock".to_string()), Value::Integer(NOW - 30 * DAY)]
+        );
+        assert_eq!(query.order_by, "COALESCE(ph.play_count, 0) DESC, t.id");
+        assert!(query.to_sql("t.id").starts_with("SELECT t.id FROM tracks t"));
+        assert!(query.to_sql("t.id").ends_with("ORDER BY COALESCE(ph.play_count, 0) DESC, t.id LIMIT 50"));
+
+        // 6个月内没有播放（含从未播放）
+        let not_played = SmartRules {
+            rules: vec![rule(RuleField::LastPlayed, RuleOperator::NotWithinDays, RuleValue::LastNDays { last_n_days: 180 })],
+            match_all: true,
+            ..Default::default()
+        };
+        let query = SmartPlaylistEngine::build_sql_query(&not_played, NOW).unwrap();
+        assert_eq!(query.where_clause, "(ph.last_played IS NULL OR ph.last_played < ?)");
+        assert_eq!(query.params, vec![Value::Integer(NOW - 180 * DAY)]);
+        assert_eq!(query.limit, None);
+
+        // 空规则匹配全部
+        let all = SmartPlaylistEngine::build_sql_query(&SmartRules::default(), NOW).unwrap();
+        assert_eq!(all.where_clause, "1");
+        assert!(all.params.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        for bad in [
+            rule(RuleField::Title, RuleOperator::WithinDays, RuleValue::Number(3)),
+            rule(RuleField::PlayCount, RuleOperator::GreaterThan, "many".into()),
+            rule(RuleField::IsFavorite, RuleOperator::Equals, "yes".into()),
+        ] {
+            let rules = SmartRules { rules: vec![bad], match_all: true, ..Default::default() };
+            assert!(SmartPlaylistEngine::build_sql_query(&rules, NOW).is_err());
+        }
 
+        // 旧格式：数值以字符串保存，没有 groups/sort 字段
+        let rules: SmartRules = serde_json::from_str(
+            r#"{"rules":[{"field":"year","operator":"less_than","value":"1970"},
+                         {"field":"date_added","operator":"within_days","value":{"last_n_days":7}}],
+                "match_all":false,"limit":null}"#,
+        ).unwrap();
+        let query = SmartPlaylistEngine::build_sql_query(&rules, NOW).unwrap();
+        assert_eq!(query.where_clause, "t.year < ? OR t.created_at >= ?");
+        assert_eq!(query.params, vec![Value::Integer(1970), Value::Integer(NOW - 7 * DAY)]);
+    }
 
+    #[test]
+    fn test_in_memory_groups_and_dates() {
+        let mut tracks = vec![
+            create_test_track("Fresh Rock", "A", 1000),
+            create_test_track("Old Rock", "B", 1000),
+            create_test_track("Fresh Jazz", "C", 1000),
+        ];
+        tracks[0].id = 1;
+        tracks[1].id = 2;
+        tracks[2].id = 3;
+        tracks[0].genre = Some("Rock".to_string());
+        tracks[1].genre = Some("Rock".to_string());
+        tracks[2].genre = Some("Jazz".to_string());
+
+        let now = chrono::Utc::now().timestamp();
+        let metadata = |id: i64| Some(TrackMetadata {
+            date_added: Some(if id == 2 { now - 400 * DAY } else { now - DAY }),
+            last_played: None,
+            play_count: 0,
+            is_favorite: id != 3,
+        });
+
+        let rules = SmartRules {
+            rules: vec![rule(RuleField::IsFavorite, RuleOperator::IsTrue, "".into())],
+            groups: vec![RuleGroup {
+                rules: vec![
+                    rule(RuleField::Genre, RuleOperator::Equals, "rock".into()),
+                    rule(RuleField::DateAdded, RuleOperator::WithinDays, RuleValue::LastNDays { last_n_days: 30 }),
+                ],
+                match_all: true,
+            }],
+            match_all: true,
+            ..Default::default()
+        };
+        let ids: Vec<i64> = SmartPlaylistEngine::filter_tracks_with_metadata(&tracks, &rules, &metadata)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![1]);
+    }
+}
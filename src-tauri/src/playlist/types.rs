@@ -5,8 +5,8 @@
 // - 类型安全：使用枚举而非字符串
 // - 可序列化：所有类型都支持Serialize/Deserialize
 
-use serde::{Deserialize, Serialize};
-use crate::player::Track;
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::player::{RepeatMode, Track};
 
 // ==================== 核心类型 ====================
 
@@ -28,6 +28,57 @@ pub struct Playlist {
     pub updated_at: Option<i64>,
     pub last_played: Option<i64>,
     pub play_count: i64,
+    /// 所在文件夹，None 表示在最外层
+    #[serde(default)]
+    pub folder_id: Option<i64>,
+    /// 在所在文件夹中的手动顺序
+    #[serde(default)]
+    pub sort_order: i64,
+    /// 播放该歌单时的随机播放设置，None 表示沿用播放器当前设置
+    #[serde(default)]
+    pub default_shuffle: Option<bool>,
+    /// 播放该歌单时的重复模式，None 表示沿用播放器当前设置
+    #[serde(default)]
+    pub default_repeat_mode: Option<RepeatMode>,
+    /// 播放该歌单时的交叉淡入淡出时长(ms)，None 表示使用全局设置
+    #[serde(default)]
+    pub default_crossfade_ms: Option<u64>,
+}
+
+/// 批量添加曲目的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddTracksResult {
+    pub added: usize,
+    /// 因歌单中已有而跳过的曲目数
+    pub skipped: usize,
+}
+
+/// 歌单文件夹（最多嵌套一层）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistFolder {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub sort_order: i64,
+    pub created_at: i64,
+}
+
+/// 侧边栏歌单树中的文件夹：子文件夹和其中的歌单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistFolderNode {
+    #[serde(flatten)]
+    pub folder: PlaylistFolder,
+    pub folders: Vec<PlaylistFolderNode>,
+    pub playlists: Vec<Playlist>,
+}
+
+/// 侧边栏歌单树：文件夹在前，之后是不在文件夹中的歌单
+///
+/// 同一层内置顶的歌单在前，其余按手动顺序排列（未调整过顺序的按创建时间倒序）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaylistTree {
+    pub folders: Vec<PlaylistFolderNode>,
+    pub playlists: Vec<Playlist>,
 }
 
 /// 歌单项（扩展版）- 预留类型
@@ -51,11 +102,25 @@ pub struct PlaylistWithTracks {
 // ==================== 智能歌单规则 ====================
 
 /// 智能歌单规则集合
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 顶层的 rules 和 groups 按 match_all 组合；每个 group 内部再按自己的 match_all 组合，
+/// 例如 “收藏 AND (流派=rock OR 流派=metal)”
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SmartRules {
     pub rules: Vec<SmartRule>,
+    #[serde(default)]
+    pub groups: Vec<RuleGroup>,
     pub match_all: bool, // true=AND, false=OR
     pub limit: Option<i64>, // 最大曲目数量
+    #[serde(default)]
+    pub sort: Option<SmartSort>, // 排序方式，缺省按艺术家/专辑/音轨号
+}
+
+/// 规则分组（嵌套一层的 AND/OR）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleGroup {
+    pub rules: Vec<SmartRule>,
+    pub match_all: bool,
 }
 
 /// 单条智能规则
@@ -63,7 +128,87 @@ pub struct SmartRules {
 pub struct SmartRule {
     pub field: RuleField,
     pub operator: RuleOperator,
-    pub value: String,
+    pub value: RuleValue,
+}
+
+/// 规则值：文本、数值，或相对日期 {"last_n_days": 30}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RuleValue {
+    LastNDays { last_n_days: i64 },
+    Number(i64),
+    Text(String),
+}
+
+impl RuleValue {
+    pub fn as_text(&self) -> String {
+        match self {
+            RuleValue::Text(text) => text.clone(),
+            RuleValue::Number(n) => n.to_string(),
+            RuleValue::LastNDays { last_n_days } => last_n_days.to_string(),
+        }
+    }
+
+    /// 数值（文本形式的数字也接受）
+    pub fn as_number(&self) -> Option<i64> {
+        match self {
+            RuleValue::Number(n) => Some(*n),
+            RuleValue::Text(text) => text.trim().parse().ok(),
+            RuleValue::LastNDays { .. } => None,
+        }
+    }
+
+    /// 天数（用于最近N天类操作符）
+    pub fn as_days(&self) -> Option<i64> {
+        match self {
+            RuleValue::LastNDays { last_n_days } => Some(*last_n_days),
+            _ => self.as_number(),
+        }
+    }
+
+    /// 时间戳：相对日期换算为 now 之前N天
+    pub fn as_timestamp(&self, now: i64) -> Option<i64> {
+        match self {
+            RuleValue::LastNDays { last_n_days } => Some(now - last_n_days * 86_400),
+            _ => self.as_number(),
+        }
+    }
+}
+
+impl From<&str> for RuleValue {
+    fn from(text: &str) -> Self {
+        RuleValue::Text(text.to_string())
+    }
+}
+
+impl From<String> for RuleValue {
+    fn from(text: String) -> Self {
+        RuleValue::Text(text)
+    }
+}
+
+/// 智能歌单排序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartSort {
+    pub field: SmartSortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// 智能歌单排序字段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartSortField {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Duration,
+    DateAdded,
+    LastPlayed,
+    PlayCount,
+    Rating,
+    Random,
 }
 
 /// 规则字段
@@ -73,11 +218,14 @@ pub enum RuleField {
     Title,
     Artist,
     Album,
+    Genre,         // 流派
+    Year,          // 发行年份
     Duration,      // 时长（毫秒）
     DateAdded,     // 添加日期（时间戳）
     LastPlayed,    // 最后播放时间
     PlayCount,     // 播放次数
     IsFavorite,    // 是否收藏
+    Rating,        // 评分（0-5星，未评分按0）
 }
 
 /// 规则操作符
@@ -118,6 +266,19 @@ pub enum ExportFormat {
     M3U,
     M3U8,
     JSON,
+    /// XML Shareable Playlist Format，多数播放器均可导入
+    XSPF,
+    /// 完整备份：额外包含智能规则、颜色主题和每首曲目的加入时间，可无损还原
+    #[serde(rename = "json_full")]
+    JSONFull,
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// 写入相对于该目录的路径（便于拷贝到其他设备）；与该目录不在同一根（如盘符不同）的曲目仍写绝对路径
+    #[serde(default)]
+    pub relative_to: Option<String>,
 }
 
 /// JSON导出格式
@@ -126,6 +287,11 @@ pub struct PlaylistExport {
     pub name: String,
     pub description: Option<String>,
     pub created_at: i64,
+    /// 以下字段仅完整备份（JSONFull）包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smart_rules: Option<SmartRules>,
     pub tracks: Vec<TrackExport>,
 }
 
@@ -137,6 +303,9 @@ pub struct TrackExport {
     pub artist: Option<String>,
     pub album: Option<String>,
     pub duration_ms: Option<i64>,
+    /// 加入歌单的时间（仅完整备份）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<i64>,
 }
 
 impl From<&Track> for TrackExport {
@@ -147,10 +316,77 @@ impl From<&Track> for TrackExport {
             artist: track.artist.clone(),
             album: track.album.clone(),
             duration_ms: track.duration_ms,
+            added_at: None,
         }
     }
 }
 
+// ==================== 导入 ====================
+
+/// 导入条目与曲库曲目的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMatchKind {
+    /// 路径完全一致（含前缀替换后）
+    Exact,
+    /// 文件名一致
+    Filename,
+    /// 标题、艺术家、时长（±2秒）一致
+    Metadata,
+    /// 用户手动指定
+    Manual,
+}
+
+impl ImportMatchKind {
+    /// 是否为模糊匹配（需要用户确认）
+    pub fn is_fuzzy(&self) -> bool {
+        matches!(self, ImportMatchKind::Filename | ImportMatchKind::Metadata)
+    }
+}
+
+/// 已匹配的导入条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedImportEntry {
+    /// 源文件中的行号（JSON为曲目序号），从1开始
+    pub source_line: usize,
+    /// 源文件中记录的路径
+    pub source_path: String,
+    pub track_id: i64,
+    pub match_kind: ImportMatchKind,
+}
+
+/// 未能匹配的导入条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnresolvedImportEntry {
+    pub source_line: usize,
+    pub source_path: String,
+}
+
+/// 导入预览（创建歌单前供用户确认）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub name: String,
+    pub resolved: Vec<ResolvedImportEntry>,
+    pub unresolved: Vec<UnresolvedImportEntry>,
+    pub exact_matches: usize,
+    pub fuzzy_matches: usize,
+    pub manual_matches: usize,
+}
+
+/// 用户确认的匹配（行号 → 曲目ID）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportOverride {
+    pub source_line: usize,
+    pub track_id: i64,
+}
+
+/// 路径前缀替换规则，如 `D:\Music` → `/mnt/music`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRewrite {
+    pub from: String,
+    pub to: String,
+}
+
 // ==================== 创建/更新选项 ====================
 
 /// 创建歌单选项
@@ -171,6 +407,22 @@ pub struct UpdatePlaylistOptions {
     pub cover_path: Option<String>,
     pub color_theme: Option<String>,
     pub is_favorite: Option<bool>,
+    /// 播放偏好：字段缺省表示不修改，null 表示清除（恢复使用全局设置）
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub default_shuffle: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub default_repeat_mode: Option<Option<RepeatMode>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub default_crossfade_ms: Option<Option<u64>>,
+}
+
+/// 区分"字段缺省"和"字段为null"：出现的字段（含null）一律包一层 Some
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 // ==================== 统计信息 ====================
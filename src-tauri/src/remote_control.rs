@@ -0,0 +1,435 @@
+// 远程控制 - 通过HTTP接口在手机等设备上控制播放
+//
+// - 默认关闭；开启后只监听 127.0.0.1，allow_lan 时监听所有网卡供局域网访问
+// - GET /status、GET /artwork 只读；POST /play、/pause、/next、/previous、/seek、/volume
+//   需要 Authorization: Bearer <token>
+// - 控制转换为 PlayerCommand 发送到播放器，状态由播放器事件更新（与系统媒体会话相同）
+// - HTTP服务在独立线程中运行，关闭时解除阻塞并等待线程退出
+
+use crate::cover_cache::CoverSize;
+use crate::db_pool::DbPool;
+use crate::player::{PlayerCommand, Track};
+use crossbeam_channel::Sender;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::Arc;
+
+/// 默认端口
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// 请求体最大字节数（控制命令只有很小的JSON）
+const MAX_BODY_BYTES: u64 = 4096;
+
+/// 远程控制设置（应用设置中的 remote_control）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteControlConfig {
+    /// 是否启用（启动时按此自动开启）
+    pub enabled: bool,
+    pub port: u16,
+    /// 监听所有网卡，允许局域网内的设备访问
+    pub allow_lan: bool,
+    /// 控制命令需要的令牌，首次开启时生成
+    pub token: String,
+}
+
+impl Default for RemoteControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            allow_lan: false,
+            token: String::new(),
+        }
+    }
+}
+
+impl RemoteControlConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port < 1024 {
+            return Err("远程控制端口必须在1024到65535之间".to_string());
+        }
+        Ok(())
+    }
+
+    fn bind_addr(&self) -> SocketAddr {
+        let ip = if self.allow_lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        SocketAddr::new(IpAddr::V4(ip), self.port)
+    }
+}
+
+/// 生成新的令牌
+pub fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// 配对信息：手机扫码后即可访问
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemotePairing {
+    pub url: String,
+    pub token: String,
+    /// 可直接编码为二维码的字符串
+    pub qr_text: String,
+}
+
+/// 当前的配对信息；局域网模式使用本机的局域网地址
+pub fn pairing(config: &RemoteControlConfig) -> RemotePairing {
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let host = if config.allow_lan { lan_address().unwrap_or(localhost) } else { localhost };
+    let url = format!("http://{}:{}", host, config.port);
+    RemotePairing {
+        qr_text: format!("windchime://remote?url={}&token={}", url, config.token),
+        url,
+        token: config.token.clone(),
+    }
+}
+
+/// 本机的局域网地址（UDP connect 只选择路由，不发送数据）
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+// ==================== 播放状态 ====================
+
+/// 状态中的曲目信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemoteTrack {
+    pub id: i64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+impl From<&Track> for RemoteTrack {
+    fn from(track: &Track) -> Self {
+        Self {
+            id: track.id,
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            duration_ms: track.duration_ms,
+        }
+    }
+}
+
+/// GET /status 的返回内容
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemoteStatus {
+    pub track: Option<RemoteTrack>,
+    pub is_playing: bool,
+    pub position_ms: u64,
+    pub volume: f32,
+    pub queue: Vec<RemoteTrack>,
+    /// 当前曲目在队列中的位置
+    pub queue_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PlaybackSnapshot {
+    track: Option<Track>,
+    is_playing: bool,
+    position_ms: u64,
+    volume: f32,
+    queue: Vec<RemoteTrack>,
+}
+
+static SNAPSHOT: Lazy<Mutex<PlaybackSnapshot>> = Lazy::new(|| Mutex::new(PlaybackSnapshot::default()));
+
+/// 曲目变化
+pub fn update_track(track: Option<&Track>) {
+    let mut snapshot = SNAPSHOT.lock();
+    snapshot.track = track.cloned();
+    snapshot.position_ms = 0;
+}
+
+/// 播放/暂停状态和音量变化
+pub fn update_state(is_playing: bool, volume: f32) {
+    let mut snapshot = SNAPSHOT.lock();
+    snapshot.is_playing = is_playing;
+    snapshot.volume = volume;
+}
+
+/// 播放位置更新
+pub fn update_position(position_ms: u64) {
+    SNAPSHOT.lock().position_ms = position_ms;
+}
+
+/// 加载了新的播放队列
+pub fn update_queue(tracks: &[Track]) {
+    SNAPSHOT.lock().queue = tracks.iter().map(RemoteTrack::from).collect();
+}
+
+fn status() -> RemoteStatus {
+    let snapshot = SNAPSHOT.lock();
+    let track = snapshot.track.as_ref().map(RemoteTrack::from);
+    RemoteStatus {
+        queue_index: track.as_ref().and_then(|t| snapshot.queue.iter().position(|q| q.id == t.id)),
+        track,
+        is_playing: snapshot.is_playing,
+        position_ms: snapshot.position_ms,
+        volume: snapshot.volume,
+        queue: snapshot.queue.clone(),
+    }
+}
+
+// ==================== 请求处理 ====================
+
+#[derive(Debug, Deserialize)]
+struct SeekBody {
+    position_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeBody {
+    volume: f32,
+}
+
+/// 处理结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    fn json(status: u16, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    fn ok() -> Self {
+        Self::json(200, &serde_json::json!({ "ok": true }))
+    }
+}
+
+/// 请求处理器：令牌、播放器命令通道和读取封面用的数据库
+pub struct RemoteApi {
+    token: String,
+    player_tx: Sender<PlayerCommand>,
+    db: Option<Arc<DbPool>>,
+}
+
+impl RemoteApi {
+    pub fn new(token: String, player_tx: Sender<PlayerCommand>, db: Option<Arc<DbPool>>) -> Self {
+        Self { token, player_tx, db }
+    }
+
+    pub fn handle(&self, method: &str, path: &str, authorization: Option<&str>, body: &[u8]) -> ApiResponse {
+        let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+        match (method, path) {
+            ("GET", "/status") => ApiResponse::json(200, &status()),
+            ("GET", "/artwork") => self.artwork(),
+            ("POST", _) => {
+                if !self.authorized(authorization) {
+                    return ApiResponse::error(401, "需要有效的令牌");
+                }
+                let command = match path {
+                    "/play" => PlayerCommand::Resume,
+                    "/pause" => PlayerCommand::Pause,
+                    "/next" => PlayerCommand::Next,
+                    "/previous" => PlayerCommand::Previous,
+                    "/seek" => match serde_json::from_slice::<SeekBody>(body) {
+                        Ok(seek) => PlayerCommand::Seek(seek.position_ms),
+                        Err(_) => return ApiResponse::error(400, "请求体应为 {\"position_ms\": 毫秒}"),
+                    },
+                    "/volume" => match serde_json::from_slice::<VolumeBody>(body) {
+                        Ok(v) if (0.0..=1.0).contains(&v.volume) => PlayerCommand::SetVolume(v.volume),
+                        _ => return ApiResponse::error(400, "请求体应为 {\"volume\": 0到1}"),
+                    },
+                    _ => return ApiResponse::error(404, "未知的接口"),
+                };
+                match self.player_tx.send(command) {
+                    Ok(()) => ApiResponse::ok(),
+                    Err(_) => ApiResponse::error(503, "播放器未就绪"),
+                }
+            }
+            _ => ApiResponse::error(404, "未知的接口"),
+        }
+    }
+
+    /// 令牌比较耗时与内容无关
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        let (a, b) = (token.trim().as_bytes(), self.token.as_bytes());
+        !b.is_empty() && a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+    }
+
+    fn artwork(&self) -> ApiResponse {
+        let cover_id = SNAPSHOT.lock().track.as_ref().and_then(|t| t.cover_id.clone());
+        let cover = match (cover_id, &self.db) {
+            (Some(cover_id), Some(db)) => db.with_read(|db| db.get_cover(&cover_id, CoverSize::Large)).ok().flatten(),
+            _ => None,
+        };
+        match cover {
+            Some(cover) => ApiResponse {
+                status: 200,
+                content_type: cover.mime,
+                body: cover.data,
+            },
+            None => ApiResponse::error(404, "当前曲目没有封面"),
+        }
+    }
+}
+
+// ==================== HTTP服务 ====================
+
+struct RunningServer {
+    server: Arc<tiny_http::Server>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    config: RemoteControlConfig,
+}
+
+static SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn is_running() -> bool {
+    SERVER.lock().is_some()
+}
+
+/// 按设置启动HTTP服务；已在运行时先关闭再按新设置启动
+pub fn start(config: &RemoteControlConfig, player_tx: Sender<PlayerCommand>, db: Arc<DbPool>) -> Result<(), String> {
+    config.validate()?;
+    stop();
+
+    let addr = config.bind_addr();
+    let server = Arc::new(tiny_http::Server::http(addr).map_err(|e| format!("无法监听 {}: {}", addr, e))?);
+    let api = RemoteApi::new(config.token.clone(), player_tx, Some(db));
+    let thread_server = Arc::clone(&server);
+    let thread = std::thread::Builder::new()
+        .name("remote-control".to_string())
+        .spawn(move || serve(&thread_server, &api))
+        .map_err(|e| format!("启动远程控制线程失败: {}", e))?;
+
+    log::info!("📱 远程控制已开启: http://{}", addr);
+    *SERVER.lock() = Some(RunningServer {
+        server,
+        thread: Some(thread),
+        config: config.clone(),
+    });
+    Ok(())
+}
+
+/// 关闭HTTP服务并等待线程退出
+pub fn stop() {
+    let running = SERVER.lock().take();
+    if let Some(mut running) = running {
+        running.server.unblock();
+        if let Some(thread) = running.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("远程控制已关闭");
+    }
+}
+
+/// 正在使用的设置（未运行时为None）
+pub fn running_config() -> Option<RemoteControlConfig> {
+    SERVER.lock().as_ref().map(|running| running.config.clone())
+}
+
+fn serve(server: &tiny_http::Server, api: &RemoteApi) {
+    for mut request in server.incoming_requests() {
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.as_str().to_string());
+        let mut body = Vec::new();
+        if let Err(e) = request.as_reader().take(MAX_BODY_BYTES).read_to_end(&mut body) {
+            log::debug!("读取远程控制请求失败: {}", e);
+            continue;
+        }
+
+        let response = api.handle(request.method().as_str(), request.url(), authorization.as_deref(), &body);
+        let mut http_response = tiny_http::Response::from_data(response.body).with_status_code(response.status);
+        if let Ok(header) = tiny_http::Header::from_bytes(&b"Content-Type"[..], response.content_type.as_bytes()) {
+            http_response.add_header(header);
+        }
+        if let Err(e) = request.respond(http_response) {
+            log::debug!("发送远程控制响应失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api() -> (RemoteApi, crossbeam_channel::Receiver<PlayerCommand>) {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        (RemoteApi::new("secret".to_string(), tx, None), rx)
+    }
+
+    #[test]
+    fn test_mutating_endpoints_require_token() {
+        let (api, rx) = api();
+        assert_eq!(api.handle("POST", "/next", None, b"").status, 401);
+        assert_eq!(api.handle("POST", "/next", Some("Bearer wrong!"), b"").status, 401);
+        assert_eq!(api.handle("POST", "/next", Some("secret"), b"").status, 401);
+        assert!(rx.try_recv().is_err());
+
+        let auth = Some("Bearer secret");
+        assert_eq!(api.handle("POST", "/next", auth, b"").status, 200);
+        assert!(matches!(rx.try_recv(), Ok(PlayerCommand::Next)));
+        assert_eq!(api.handle("POST", "/pause/", auth, b"").status, 200);
+        assert!(matches!(rx.try_recv(), Ok(PlayerCommand::Pause)));
+        assert_eq!(api.handle("POST", "/seek", auth, br#"{"position_ms": 61000}"#).status, 200);
+        assert!(matches!(rx.try_recv(), Ok(PlayerCommand::Seek(61000))));
+        assert_eq!(api.handle("POST", "/volume", auth, br#"{"volume": 0.5}"#).status, 200);
+        assert!(matches!(rx.try_recv(), Ok(PlayerCommand::SetVolume(v)) if v == 0.5));
+
+        assert_eq!(api.handle("POST", "/volume", auth, br#"{"volume": 2}"#).status, 400);
+        assert_eq!(api.handle("POST", "/seek", auth, b"soon").status, 400);
+        assert_eq!(api.handle("POST", "/shutdown", auth, b"").status, 404);
+        assert_eq!(api.handle("DELETE", "/status", auth, b"").status, 404);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_status_reports_track_and_queue() {
+        let (api, _rx) = api();
+        let mut first = Track::new(1, "/m/1.flac".to_string());
+        first.title = Some("One".to_string());
+        let second = Track::new(2, "/m/2.flac".to_string());
+        update_queue(&[first, second.clone()]);
+        update_track(Some(&second));
+        update_state(true, 0.8);
+        update_position(1500);
+
+        let response = api.handle("GET", "/status?t=1", None, b"");
+        assert_eq!(response.status, 200);
+        let status: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(status["track"]["id"], 2);
+        assert_eq!(status["is_playing"], true);
+        assert_eq!(status["position_ms"], 1500);
+        assert_eq!(status["queue"][0]["title"], "One");
+        assert_eq!(status["queue_index"], 1);
+
+        // 没有数据库或封面时返回404
+        assert_eq!(api.handle("GET", "/artwork", None, b"").status, 404);
+    }
+
+    #[test]
+    fn test_pairing_and_validation() {
+        let config = RemoteControlConfig { port: 9000, token: "abc".to_string(), ..Default::default() };
+        let pairing = pairing(&config);
+        assert_eq!(pairing.url, "http://127.0.0.1:9000");
+        assert_eq!(pairing.qr_text, "windchime://remote?url=http://127.0.0.1:9000&token=abc");
+        assert!(RemoteControlConfig { port: 80, ..Default::default() }.validate().is_err());
+        assert_eq!(generate_token().len(), 32);
+    }
+}
@@ -2,19 +2,21 @@
 use crate::remote_source::RemoteSourceClient;
 use crate::webdav::{WebDAVClient, WebDAVRemoteAdapter};
 use crate::webdav::types::WebDAVConfig;
-use crate::db::Database;
+use crate::subsonic::{SubsonicClient, SubsonicRemoteAdapter};
+use crate::subsonic::types::SubsonicConfig;
+use crate::db_pool::DbPool;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use anyhow::Result;
 
 pub struct RemoteClientManager {
     clients: Arc<RwLock<HashMap<String, Arc<dyn RemoteSourceClient>>>>,
-    db: Arc<Mutex<Database>>,
+    db: Arc<DbPool>,
 }
 
 impl RemoteClientManager {
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
+    pub fn new(db: Arc<DbPool>) -> Self {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             db,
@@ -45,14 +47,19 @@ impl RemoteClientManager {
             return Err(anyhow::anyhow!("服务器已禁用"));
         }
         
-        // 3. 创建客户端（仅支持WebDAV）
+        // 3. 创建客户端
         let client: Arc<dyn RemoteSourceClient> = match server_type.as_str() {
             "webdav" => {
                 let config: WebDAVConfig = serde_json::from_str(&config_json)?;
                 let webdav_client = WebDAVClient::new(config)?;
                 Arc::new(WebDAVRemoteAdapter::new(webdav_client))
             },
-            _ => return Err(anyhow::anyhow!("不支持的服务器类型（仅支持WebDAV）: {}", server_type)),
+            "subsonic" => {
+                let config: SubsonicConfig = serde_json::from_str(&config_json)?;
+                let subsonic_client = SubsonicClient::new(config)?;
+                Arc::new(SubsonicRemoteAdapter::new(subsonic_client))
+            },
+            _ => return Err(anyhow::anyhow!("不支持的服务器类型: {}", server_type)),
         };
         
         // 4. 缓存客户端
@@ -0,0 +1,446 @@
+// 远程目录列表缓存 - 避免每次浏览都向服务器发送 PROPFIND
+//
+// - TTL 内直接返回缓存的列表
+// - 过期后仍先返回旧列表，同时在后台重新请求；内容有变化时更新缓存，由调用方通知前端刷新
+// - 新建目录、删除文件后使所在目录失效，扫描某个目录后使整个子树失效
+// - 失效期间正在进行的请求结果不会写回缓存，避免把变更前的列表再缓存一个 TTL
+// 列表只保存在内存中，应用重启后重新请求
+
+use crate::remote_source::{RemoteFileInfo, RemoteSourceClient};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use percent_encoding::percent_decode_str;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 目录列表的有效期
+pub const LISTING_TTL: Duration = Duration::from_secs(60);
+
+/// 全局目录列表缓存（RemoteClientManager 每个命令都会重新创建，缓存不能放在其中）
+pub static LISTING_CACHE: Lazy<ListingCache> = Lazy::new(|| ListingCache::new(LISTING_TTL));
+
+/// 缓存键：(服务器ID, 规范化后的目录路径)
+type ListingKey = (String, String);
+
+struct CachedListing {
+    files: Arc<Vec<RemoteFileInfo>>,
+    /// 列表内容签名，用于判断重新请求后是否有变化
+    signature: u64,
+    fetched_at: Instant,
+    /// 是否已有后台重新请求在进行
+    revalidating: bool,
+}
+
+/// 查询缓存的结果
+pub enum Lookup {
+    /// 在有效期内
+    Fresh(Arc<Vec<RemoteFileInfo>>),
+    /// 已过期；revalidate 为 true 时由调用方负责在后台重新请求
+    Stale {
+        files: Arc<Vec<RemoteFileInfo>>,
+        revalidate: bool,
+    },
+    Miss,
+}
+
+/// 浏览目录的结果
+pub struct Listing {
+    pub files: Arc<Vec<RemoteFileInfo>>,
+    /// 返回的是过期列表，需要在后台重新请求
+    pub needs_revalidate: bool,
+}
+
+pub struct ListingCache {
+    entries: Mutex<HashMap<ListingKey, CachedListing>>,
+    /// 每次失效时递增，请求开始后发生过失效的结果不写回缓存
+    generation: Mutex<u64>,
+    ttl: Duration,
+}
+
+impl ListingCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            generation: Mutex::new(0),
+            ttl,
+        }
+    }
+
+    pub fn lookup(&self, server_id: &str, path: &str) -> Lookup {
+        self.lookup_at(server_id, path, Instant::now())
+    }
+
+    fn lookup_at(&self, server_id: &str, path: &str, now: Instant) -> Lookup {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries.get_mut(&key(server_id, path)) else {
+            return Lookup::Miss;
+        };
+        if now.duration_since(entry.fetched_at) < self.ttl {
+            return Lookup::Fresh(entry.files.clone());
+        }
+        let revalidate = !entry.revalidating;
+        entry.revalidating = true;
+        Lookup::Stale { files: entry.files.clone(), revalidate }
+    }
+
+    /// 当前失效代数，请求目录前记录，写回时传给 store
+    pub fn generation(&self) -> u64 {
+        *self.generation.lock()
+    }
+
+    /// 写入列表，返回内容是否与之前缓存的不同；期间发生过失效时不写入并返回 false
+    pub fn store(&self, server_id: &str, path: &str, files: Vec<RemoteFileInfo>, generation: u64) -> bool {
+        let generation_now = self.generation.lock();
+        let mut entries = self.entries.lock();
+        let key = key(server_id, path);
+        if *generation_now != generation {
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.revalidating = false;
+            }
+            return false;
+        }
+        let signature = signature(&files);
+        let changed = entries.get(&key).is_some_and(|entry| entry.signature != signature);
+        entries.insert(key, CachedListing {
+            files: Arc::new(files),
+            signature,
+            fetched_at: Instant::now(),
+            revalidating: false,
+        });
+        changed
+    }
+
+    /// 后台请求失败时清除进行中标记，下次浏览再重试
+    fn revalidate_failed(&self, server_id: &str, path: &str) {
+        if let Some(entry) = self.entries.lock().get_mut(&key(server_id, path)) {
+            entry.revalidating = false;
+        }
+    }
+
+    /// 使目录失效；server_id 为 None 时对所有服务器生效
+    pub fn invalidate(&self, server_id: Option<&str>, path: &str) {
+        let path = normalize_path(path);
+        self.remove_where(|(id, cached)| server_id.map_or(true, |s| s == id) && *cached == path);
+    }
+
+    /// 使目录及其所有子目录失效；server_id 为 None 时对所有服务器生效
+    pub fn invalidate_subtree(&self, server_id: Option<&str>, root: &str) {
+        let root = normalize_path(root);
+        self.remove_where(|(id, cached)| server_id.map_or(true, |s| s == id) && is_within(cached, &root));
+    }
+
+    /// 文件或目录被新建、删除后，使其所在目录和它自身（目录时含子树）失效
+    pub fn invalidate_entry(&self, server_id: Option<&str>, entry_path: &str) {
+        self.invalidate(server_id, &parent_path(entry_path));
+        self.invalidate_subtree(server_id, entry_path);
+    }
+
+    fn remove_where(&self, matches: impl Fn(&ListingKey) -> bool) {
+        let mut generation = self.generation.lock();
+        *generation += 1;
+        let mut entries = self.entries.lock();
+        let before = entries.len();
+        entries.retain(|key, _| !matches(key));
+        log::debug!("目录列表缓存失效 {} 项", before - entries.len());
+    }
+}
+
+/// 浏览目录：有缓存时直接返回（过期时标记需要后台重新请求），否则请求服务器并写入缓存
+pub async fn list(
+    cache: &ListingCache,
+    client: &dyn RemoteSourceClient,
+    server_id: &str,
+    path: &str,
+    force_refresh: bool,
+) -> Result<Listing> {
+    if !force_refresh {
+        match cache.lookup(server_id, path) {
+            Lookup::Fresh(files) => {
+                log::debug!("目录列表缓存命中: {} - {}", server_id, path);
+                return Ok(Listing { files, needs_revalidate: false });
+            }
+            Lookup::Stale { files, revalidate } => {
+                log::debug!("目录列表缓存已过期，先返回旧列表: {} - {}", server_id, path);
+                return Ok(Listing { files, needs_revalidate: revalidate });
+            }
+            Lookup::Miss => {}
+        }
+    }
+
+    let generation = cache.generation();
+    let files = client.list_directory(path).await?;
+    let shared = Arc::new(files.clone());
+    cache.store(server_id, path, files, generation);
+    Ok(Listing { files: shared, needs_revalidate: false })
+}
+
+/// 后台重新请求目录，内容有变化时返回新列表
+pub async fn revalidate(
+    cache: &ListingCache,
+    client: &dyn RemoteSourceClient,
+    server_id: &str,
+    path: &str,
+) -> Result<Option<Vec<RemoteFileInfo>>> {
+    let generation = cache.generation();
+    let files = match client.list_directory(path).await {
+        Ok(files) => files,
+        Err(e) => {
+            cache.revalidate_failed(server_id, path);
+            return Err(e);
+        }
+    };
+    let changed = cache.store(server_id, path, files.clone(), generation);
+    Ok(changed.then_some(files))
+}
+
+fn key(server_id: &str, path: &str) -> ListingKey {
+    (server_id.to_string(), normalize_path(path))
+}
+
+/// 统一路径写法：解码百分号编码，以 / 开头，去掉末尾的 /
+fn normalize_path(path: &str) -> String {
+    let decoded = percent_decode_str(path).decode_utf8_lossy();
+    let trimmed = decoded.trim_matches('/');
+    format!("/{}", trimmed)
+}
+
+fn parent_path(path: &str) -> String {
+    let path = normalize_path(path);
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// path 是否为 root 本身或其子路径（两者都已规范化）
+fn is_within(path: &str, root: &str) -> bool {
+    root == "/"
+        || path == root
+        || path.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// 列表内容签名，与服务器返回的顺序无关
+fn signature(files: &[RemoteFileInfo]) -> u64 {
+    let mut items: Vec<_> = files
+        .iter()
+        .map(|f| (&f.path, f.is_directory, f.size, f.last_modified, &f.etag))
+        .collect();
+    items.sort();
+    let mut hasher = DefaultHasher::new();
+    items.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_source::{ConnectionStatus, HealthStatus, RemoteSourceType};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::AsyncRead;
+
+    /// 模拟客户端：返回可修改的目录内容并记录请求次数
+    struct MockClient {
+        listings: Mutex<HashMap<String, Vec<RemoteFileInfo>>>,
+        requests: AtomicUsize,
+    }
+
+    impl MockClient {
+        fn new() -> Self {
+            Self { listings: Mutex::new(HashMap::new()), requests: AtomicUsize::new(0) }
+        }
+
+        fn set(&self, dir: &str, names: &[&str]) {
+            let files = names
+                .iter()
+                .map(|name| RemoteFileInfo {
+                    path: format!("{}/{}", dir.trim_end_matches('/'), name),
+                    name: name.to_string(),
+                    is_directory: !name.contains('.'),
+                    size: Some(1024),
+                    mime_type: None,
+                    last_modified: Some(1_700_000_000),
+                    etag: None,
+                    source_type: RemoteSourceType::WebDAV,
+                })
+                .collect();
+            self.listings.lock().insert(dir.to_string(), files);
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl RemoteSourceClient for MockClient {
+        async fn test_connection(&self) -> Result<ConnectionStatus> {
+            Ok(ConnectionStatus::Connected)
+        }
+
+        async fn list_directory(&self, path: &str) -> Result<Vec<RemoteFileInfo>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            self.listings
+                .lock()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("目录不存在: {}", path))
+        }
+
+        async fn get_file_info(&self, path: &str) -> Result<RemoteFileInfo> {
+            Err(anyhow::anyhow!("不支持: {}", path))
+        }
+
+        async fn download_stream(&self, path: &str) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+            Err(anyhow::anyhow!("不支持: {}", path))
+        }
+
+        async fn download_range(&self, path: &str, _start: u64, _end: Option<u64>)
+            -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+            Err(anyhow::anyhow!("不支持: {}", path))
+        }
+
+        fn get_health(&self) -> HealthStatus {
+            HealthStatus { is_healthy: true, last_check: 0, error_count: 0, connection_status: ConnectionStatus::Connected }
+        }
+
+        fn get_source_type(&self) -> RemoteSourceType {
+            RemoteSourceType::WebDAV
+        }
+    }
+
+    fn names(files: &[RemoteFileInfo]) -> Vec<&str> {
+        files.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_and_force_refresh() {
+        let cache = ListingCache::new(Duration::from_secs(60));
+        let client = MockClient::new();
+        client.set("/music", &["a.flac", "Live"]);
+
+        let first = list(&cache, &client, "s1", "/music", false).await.unwrap();
+        assert_eq!(names(&first.files), vec!["a.flac", "Live"]);
+        // 末尾斜杠和百分号编码不影响命中
+        let second = list(&cache, &client, "s1", "/music/", false).await.unwrap();
+        assert!(!second.needs_revalidate);
+        assert_eq!(names(&second.files), vec!["a.flac", "Live"]);
+        assert_eq!(client.requests(), 1);
+
+        // 其他服务器的同名目录不共享缓存
+        list(&cache, &client, "s2", "/music", false).await.unwrap();
+        assert_eq!(client.requests(), 2);
+
+        client.set("/music", &["a.flac", "b.flac", "Live"]);
+        let refreshed = list(&cache, &client, "s1", "/music", true).await.unwrap();
+        assert_eq!(names(&refreshed.files), vec!["a.flac", "b.flac", "Live"]);
+        assert_eq!(client.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stale_listing_revalidates_once() {
+        let cache = ListingCache::new(Duration::from_secs(60));
+        let client = MockClient::new();
+        client.set("/music", &["a.flac"]);
+        list(&cache, &client, "s1", "/music", false).await.unwrap();
+
+        // 模拟过期
+        let later = Instant::now() + Duration::from_secs(61);
+        let Lookup::Stale { files, revalidate } = cache.lookup_at("s1", "/music", later) else {
+            panic!("过期的列表应返回 Stale");
+        };
+        assert_eq!(names(&files), vec!["a.flac"]);
+        assert!(revalidate);
+        // 同时浏览时只发起一次后台请求
+        assert!(matches!(cache.lookup_at("s1", "/music", later), Lookup::Stale { revalidate: false, .. }));
+
+        // 内容未变化时不需要通知
+        assert_eq!(revalidate_listing(&cache, &client).await, None);
+        assert!(matches!(cache.lookup("s1", "/music"), Lookup::Fresh(_)));
+
+        client.set("/music", &["a.flac", "b.flac"]);
+        assert_eq!(revalidate_listing(&cache, &client).await, Some(vec!["a.flac".to_string(), "b.flac".to_string()]));
+        let Lookup::Fresh(files) = cache.lookup("s1", "/music") else { panic!("应已更新缓存") };
+        assert_eq!(names(&files), vec!["a.flac", "b.flac"]);
+
+        // 请求失败时保留旧列表，下次过期后可再次重试
+        let later = Instant::now() + Duration::from_secs(61);
+        assert!(matches!(cache.lookup_at("s1", "/music", later), Lookup::Stale { revalidate: true, .. }));
+        client.listings.lock().clear();
+        assert!(revalidate(&cache, &client, "s1", "/music").await.is_err());
+        assert!(matches!(cache.lookup_at("s1", "/music", later), Lookup::Stale { revalidate: true, .. }));
+    }
+
+    async fn revalidate_listing(cache: &ListingCache, client: &MockClient) -> Option<Vec<String>> {
+        revalidate(cache, client, "s1", "/music")
+            .await
+            .unwrap()
+            .map(|files| files.into_iter().map(|f| f.name).collect())
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_after_changes_and_scan() {
+        let cache = ListingCache::new(Duration::from_secs(60));
+        let client = MockClient::new();
+        client.set("/", &["music", "other"]);
+        client.set("/music", &["Live", "a.flac"]);
+        client.set("/music/Live", &["b.flac"]);
+        client.set("/other", &["c.flac"]);
+        for dir in ["/", "/music", "/music/Live", "/other"] {
+            list(&cache, &client, "s1", dir, false).await.unwrap();
+        }
+        assert_eq!(client.requests(), 4);
+
+        // 新建 /music/New 只影响 /music
+        client.set("/music", &["Live", "New", "a.flac"]);
+        cache.invalidate_entry(Some("s1"), "/music/New");
+        assert!(matches!(cache.lookup("s1", "/music"), Lookup::Miss));
+        assert!(matches!(cache.lookup("s1", "/music/Live"), Lookup::Fresh(_)));
+        let music = list(&cache, &client, "s1", "/music", false).await.unwrap();
+        assert_eq!(names(&music.files), vec!["Live", "New", "a.flac"]);
+
+        // 删除目录时连同其子树一起失效；未指定服务器时按路径匹配所有服务器
+        cache.invalidate_entry(None, "/music/Live/");
+        assert!(matches!(cache.lookup("s1", "/music"), Lookup::Miss));
+        assert!(matches!(cache.lookup("s1", "/music/Live"), Lookup::Miss));
+        assert!(matches!(cache.lookup("s1", "/"), Lookup::Fresh(_)));
+
+        // 扫描 /music 后整个子树失效，/music2 这类同前缀目录不受影响
+        client.set("/music2", &["d.flac"]);
+        list(&cache, &client, "s1", "/music", false).await.unwrap();
+        list(&cache, &client, "s1", "/music2", false).await.unwrap();
+        cache.invalidate_subtree(Some("s1"), "/music");
+        assert!(matches!(cache.lookup("s1", "/music"), Lookup::Miss));
+        assert!(matches!(cache.lookup("s1", "/music2"), Lookup::Fresh(_)));
+        assert!(matches!(cache.lookup("s1", "/other"), Lookup::Fresh(_)));
+    }
+
+    #[tokio::test]
+    async fn test_request_in_flight_during_invalidation_is_not_cached() {
+        let cache = ListingCache::new(Duration::from_secs(60));
+        let client = MockClient::new();
+        client.set("/music", &["a.flac"]);
+
+        let generation = cache.generation();
+        let files = client.list_directory("/music").await.unwrap();
+        // 请求返回前目录被修改
+        cache.invalidate_entry(Some("s1"), "/music/b.flac");
+        assert!(!cache.store("s1", "/music", files, generation));
+        assert!(matches!(cache.lookup("s1", "/music"), Lookup::Miss));
+    }
+
+    #[test]
+    fn test_path_helpers() {
+        assert_eq!(normalize_path(""), "/");
+        assert_eq!(normalize_path("/%E9%9F%B3%E4%B9%90/"), "/音乐");
+        assert_eq!(parent_path("/music/a.flac"), "/music");
+        assert_eq!(parent_path("/music"), "/");
+        assert!(is_within("/music/Live", "/music"));
+        assert!(!is_within("/music2", "/music"));
+        assert!(is_within("/anything", "/"));
+    }
+}
@@ -2,6 +2,7 @@
 pub mod types;
 pub mod client_manager;
 pub mod scanner;
+pub mod listing_cache;
 
 pub use types::*;
 pub use client_manager::RemoteClientManager;
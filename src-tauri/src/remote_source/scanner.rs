@@ -1,8 +1,13 @@
 // 远程音乐扫描器 - 单一职责：扫描远程音乐库并提取元数据
 use crate::remote_source::{RemoteSourceClient, RemoteFileInfo};
-use crate::db::Database;
+use crate::db::{RemoteFileState, ReplayGainInfo};
+use crate::db_pool::DbPool;
 use crate::player::Track;
 use crate::metadata_extractor::MetadataExtractor;
+use futures::stream::{FuturesUnordered, StreamExt};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 use anyhow::Result;
@@ -22,13 +27,71 @@ enum MetadataStrategy {
     Skip(String),
 }
 
-#[derive(Debug, Clone, Serialize)]
-#[allow(dead_code)]
+/// 默认并发请求数（小型NAS也能承受）
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 4;
+
+/// 并发请求数上限
+pub const MAX_SCAN_CONCURRENCY: usize = 16;
+
+/// 正在进行的扫描（服务器ID → 取消标志）
+static ACTIVE_SCANS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 取消指定服务器正在进行的扫描，没有扫描时返回false
+pub fn cancel_scan(server_id: &str) -> bool {
+    match ACTIVE_SCANS.lock().unwrap().get(server_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 登记中的扫描，结束时自动注销
+struct ActiveScan {
+    server_id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ActiveScan {
+    fn register(server_id: &str) -> Result<Self> {
+        let mut scans = ACTIVE_SCANS.lock().unwrap();
+        if scans.contains_key(server_id) {
+            return Err(anyhow::anyhow!("服务器 {} 正在扫描", server_id));
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        scans.insert(server_id.to_string(), cancelled.clone());
+        Ok(Self { server_id: server_id.to_string(), cancelled })
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ActiveScan {
+    fn drop(&mut self) {
+        ACTIVE_SCANS.lock().unwrap().remove(&self.server_id);
+    }
+}
+
+/// 扫描进度（remote-scan-progress 事件）
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ScanProgress {
-    pub current_file: String,
+    pub server_id: String,
+    /// 正在列目录时为true，处理文件时为false
+    pub listing: bool,
+    /// 刚完成的目录或文件
+    pub current_path: String,
     pub files_found: usize,
     pub files_processed: usize,
-    pub errors: Vec<String>,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    /// 扫描已结束（完成或取消）
+    pub finished: bool,
+    pub cancelled: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -36,22 +99,44 @@ pub struct ScanResult {
     pub total_files: usize,
     pub added: usize,
     pub updated: usize,
+    /// 与上次扫描相比未变化而跳过的文件数
+    pub unchanged: usize,
     pub failed: usize,
     pub errors: Vec<String>,
     pub duration_seconds: u64,
+    pub cancelled: bool,
+}
+
+/// 文件自上次扫描后是否未变化：优先比较ETag，没有ETag时比较修改时间和大小
+///
+/// 服务器两者都不提供时（如Subsonic）每次都重新处理
+fn is_unchanged(previous: &RemoteFileState, file: &RemoteFileInfo) -> bool {
+    match (&previous.etag, &file.etag) {
+        (Some(previous), Some(current)) => previous == current,
+        _ => {
+            previous.mtime.is_some()
+                && previous.mtime == file.last_modified
+                && previous.size == file.size.map(|s| s as i64)
+        }
+    }
 }
 
+type ProgressCallback = Box<dyn Fn(&ScanProgress) + Send + Sync>;
+
 pub struct RemoteScanner {
     client: Arc<dyn RemoteSourceClient>,
-    db: Arc<Mutex<Database>>,
+    db: Arc<DbPool>,
     server_id: String,
     metadata_extractor: MetadataExtractor,
+    /// 同时进行的目录列举或元数据请求数
+    concurrency: usize,
+    on_progress: Option<ProgressCallback>,
 }
 
 impl RemoteScanner {
     pub fn new(
         client: Arc<dyn RemoteSourceClient>,
-        db: Arc<Mutex<Database>>,
+        db: Arc<DbPool>,
         server_id: String,
     ) -> Self {
         Self { 
@@ -59,112 +144,209 @@ impl RemoteScanner {
             db, 
             server_id,
             metadata_extractor: MetadataExtractor::new(),
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            on_progress: None,
+        }
+    }
+
+    /// 设置并发请求数（1 ~ MAX_SCAN_CONCURRENCY）
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.clamp(1, MAX_SCAN_CONCURRENCY);
+        self
+    }
+
+    /// 设置进度回调
+    pub fn with_progress<F>(mut self, on_progress: F) -> Self
+    where
+        F: Fn(&ScanProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    fn emit(&self, progress: &ScanProgress) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(progress);
         }
     }
 
     /// 开始扫描远程音乐库
+    ///
+    /// 并发列举目录和提取元数据，跳过自上次扫描后未变化的文件；
+    /// 单个文件失败只记入 errors，不中断扫描
     pub async fn scan(&self, root_path: &str) -> Result<ScanResult> {
         let start_time = std::time::Instant::now();
+        let active = ActiveScan::register(&self.server_id)?;
         
-        log::info!("开始扫描远程音乐库: {}", root_path);
+        log::info!("开始扫描远程音乐库: {} (并发 {})", root_path, self.concurrency);
         
-        let mut files_found = 0;
-        let mut added = 0;
-        let mut updated = 0;
+        let mut progress = ScanProgress {
+            server_id: self.server_id.clone(),
+            listing: true,
+            ..Default::default()
+        };
         let mut errors = Vec::new();
         
         // 递归扫描目录
-        let audio_files = match self.scan_directory_recursive(root_path, &mut files_found).await {
+        let audio_files = match self.list_audio_files(root_path, &active, &mut progress, &mut errors).await {
             Ok(files) => files,
             Err(e) => {
                 errors.push(format!("扫描目录失败: {}", e));
+                progress.failed = 1;
+                progress.finished = true;
+                self.emit(&progress);
                 return Ok(ScanResult {
                     total_files: 0,
                     added: 0,
                     updated: 0,
+                    unchanged: 0,
                     failed: 1,
                     errors,
                     duration_seconds: start_time.elapsed().as_secs(),
+                    cancelled: false,
                 });
             }
         };
         
         log::info!("找到 {} 个音频文件", audio_files.len());
         
-        // 处理音频文件
-        for (index, file) in audio_files.iter().enumerate() {
-            log::debug!("处理文件 {}/{}: {}", index + 1, audio_files.len(), file.name);
+        // 跳过未变化的文件
+        let previous_states = {
+            let db = self.db.lock().map_err(|e| anyhow::anyhow!("数据库锁定失败: {}", e))?;
+            db.get_remote_file_states(&self.server_id)?
+        };
+        let (unchanged, changed): (Vec<_>, Vec<_>) = audio_files.into_iter().partition(|file| {
+            previous_states
+                .get(&self.track_path(file))
+                .is_some_and(|previous| is_unchanged(previous, file))
+        });
+        log::info!("{} 个文件未变化，{} 个文件需要处理", unchanged.len(), changed.len());
+        
+        progress.listing = false;
+        progress.unchanged = unchanged.len();
+        progress.files_processed = unchanged.len();
+        self.emit(&progress);
+        
+        // 并发处理音频文件
+        let mut changed = changed.into_iter();
+        let mut processing = FuturesUnordered::new();
+        loop {
+            while processing.len() < self.concurrency && !active.is_cancelled() {
+                let Some(file) = changed.next() else { break };
+                processing.push(async move {
+                    let result = self.process_audio_file(&file).await;
+                    (file, result)
+                });
+            }
             
-            match self.process_audio_file(file).await {
-                Ok(is_new) => {
-                    if is_new {
-                        added += 1;
-                    } else {
-                        updated += 1;
-                    }
-                },
+            let Some((file, result)) = processing.next().await else { break };
+            match result {
+                Ok(true) => progress.added += 1,
+                Ok(false) => progress.updated += 1,
                 Err(e) => {
                     let error_msg = format!("{}: {}", file.path, e);
                     log::error!("处理文件失败: {}", error_msg);
                     errors.push(error_msg);
+                    progress.failed += 1;
                 }
             }
+            progress.files_processed += 1;
+            progress.current_path = file.path;
+            self.emit(&progress);
+            
+            if active.is_cancelled() {
+                break;
+            }
         }
+        drop(processing);
         
+        let cancelled = active.is_cancelled();
         let duration = start_time.elapsed();
         log::info!(
-            "扫描完成：添加 {} 首，更新 {} 首，失败 {} 个，耗时 {:?}",
-            added, updated, errors.len(), duration
+            "扫描{}：添加 {} 首，更新 {} 首，未变化 {} 个，失败 {} 个，耗时 {:?}",
+            if cancelled { "已取消" } else { "完成" },
+            progress.added, progress.updated, progress.unchanged, errors.len(), duration
         );
         
+        progress.finished = true;
+        progress.cancelled = cancelled;
+        self.emit(&progress);
+        
         Ok(ScanResult {
-            total_files: files_found,
-            added,
-            updated,
+            total_files: progress.files_found,
+            added: progress.added,
+            updated: progress.updated,
+            unchanged: progress.unchanged,
             failed: errors.len(),
             errors,
             duration_seconds: duration.as_secs(),
+            cancelled,
         })
     }
 
-    /// 递归扫描目录
-    fn scan_directory_recursive<'a>(
-        &'a self,
-        path: &'a str,
-        counter: &'a mut usize,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<RemoteFileInfo>>> + Send + 'a>> {
-        Box::pin(async move {
+    /// 并发遍历目录树，收集音频文件
+    ///
+    /// 根目录列举失败时返回错误；子目录失败记入 errors 后跳过
+    async fn list_audio_files(
+        &self,
+        root_path: &str,
+        active: &ActiveScan,
+        progress: &mut ScanProgress,
+        errors: &mut Vec<String>,
+    ) -> Result<Vec<RemoteFileInfo>> {
         let mut audio_files = Vec::new();
-        
-        log::debug!("扫描目录: {}", path);
-        let items = self.client.list_directory(path).await?;
-        log::info!("📁 目录 {} 中找到 {} 个项目", path, items.len());
-        
-        for item in items {
-            if item.is_directory {
-                log::debug!("  📂 子目录: {}", item.name);
-                // 递归扫描子目录
-                match self.scan_directory_recursive(&item.path, counter).await {
-                    Ok(sub_files) => audio_files.extend(sub_files),
-                    Err(e) => {
-                        log::warn!("跳过子目录 {}: {}", item.path, e);
+        let mut pending = VecDeque::from([root_path.to_string()]);
+        let mut listings = FuturesUnordered::new();
+        
+        loop {
+            while listings.len() < self.concurrency {
+                let Some(path) = pending.pop_front() else { break };
+                log::debug!("扫描目录: {}", path);
+                listings.push(async move {
+                    let result = self.client.list_directory(&path).await;
+                    (path, result)
+                });
+            }
+            
+            let Some((path, result)) = listings.next().await else { break };
+            match result {
+                Ok(items) => {
+                    log::info!("📁 目录 {} 中找到 {} 个项目", path, items.len());
+                    for item in items {
+                        if item.is_directory {
+                            log::debug!("  📂 子目录: {}", item.name);
+                            pending.push_back(item.path);
+                        } else if self.is_audio_file(&item) {
+                            log::debug!("  ✅ 识别为音频文件: {}", item.name);
+                            audio_files.push(item);
+                        } else {
+                            log::debug!("  ❌ 跳过非音频文件: {}", item.name);
+                        }
                     }
                 }
-            } else {
-                log::debug!("  📄 文件: {} (is_audio: {})", item.name, self.is_audio_file(&item));
-                if self.is_audio_file(&item) {
-                    log::info!("  ✅ 识别为音频文件: {}", item.name);
-                    audio_files.push(item);
-                    *counter += 1;
-                } else {
-                    log::debug!("  ❌ 跳过非音频文件: {}", item.name);
+                Err(e) if path == root_path => return Err(e),
+                Err(e) => {
+                    log::warn!("跳过子目录 {}: {}", path, e);
+                    errors.push(format!("扫描目录失败 {}: {}", path, e));
                 }
             }
+            
+            progress.files_found = audio_files.len();
+            progress.current_path = path;
+            self.emit(progress);
+            
+            if active.is_cancelled() {
+                log::info!("扫描已取消，停止列举目录");
+                break;
+            }
         }
         
-        log::info!("📊 目录 {} 扫描完成，找到 {} 个音频文件", path, audio_files.len());
         Ok(audio_files)
-        })
+    }
+
+    /// 远程路径标识：webdav://server_id#/path/to/file.mp3 或 subsonic://server_id#/song/<id>.flac
+    fn track_path(&self, file: &RemoteFileInfo) -> String {
+        format!("{}://{}#{}", file.source_type, self.server_id, file.path)
     }
 
     /// 判断是否为音频文件
@@ -189,10 +371,7 @@ impl RemoteScanner {
 
     /// 处理单个音频文件
     async fn process_audio_file(&self, file: &RemoteFileInfo) -> Result<bool> {
-        let source_type = file.source_type.to_string();
-        
-        // 构建远程路径标识：webdav://server_id#/path/to/file.mp3
-        let track_path = format!("{}://{}#{}", source_type, self.server_id, file.path);
+        let track_path = self.track_path(file);
         
         // 检查是否已存在 - 使用块来确保锁立即释放
         let (existing, is_new) = {
@@ -202,17 +381,27 @@ impl RemoteScanner {
             (existing, is_new)
         }; // db 锁在这里释放
         
-        // 下载并提取元数据
+        // 服务器提供元数据时直接使用，否则下载并提取
+        let api_metadata = self.client.get_metadata(&file.path).await.unwrap_or_else(|e| {
+            log::warn!("获取服务器元数据失败 ({}): {}", file.path, e);
+            None
+        });
         log::debug!("开始下载并提取元数据: {}", file.path);
-        println!("📊 [Scanner] 提取元数据: {} ({})", file.name, file.size.unwrap_or(0));
-        let metadata = match self.download_and_extract_metadata(file).await {
+        log::debug!("📊 [Scanner] 提取元数据: {} ({})", file.name, file.size.unwrap_or(0));
+        let metadata = match api_metadata {
+            Some(meta) => Ok(meta),
+            None => self.download_and_extract_metadata(file).await,
+        };
+        // 只有成功提取元数据时才记录文件状态，回退到文件名的文件下次扫描重试
+        let extracted = metadata.is_ok();
+        let metadata = match metadata {
             Ok(meta) => {
-                println!("✅ [Scanner] 元数据提取成功: duration={:?}ms", meta.duration_ms);
+                log::debug!("✅ [Scanner] 元数据提取成功: duration={:?}ms", meta.duration_ms);
                 meta
             },
             Err(e) => {
                 log::warn!("提取元数据失败 ({}): {}, 使用文件名解析", file.path, e);
-                println!("⚠️ [Scanner] 元数据提取失败: {}, 使用文件名", e);
+                log::warn!("⚠️ [Scanner] 元数据提取失败: {}, 使用文件名", e);
                 // 如果下载失败，回退到文件名解析
                 let (title, artist) = self.parse_filename(&file.name);
                 crate::metadata_extractor::MusicMetadata {
@@ -237,7 +426,16 @@ impl RemoteScanner {
             }
         }
         
+        let replay_gain = ReplayGainInfo {
+            track_gain_db: metadata.replaygain_track_gain,
+            album_gain_db: metadata.replaygain_album_gain,
+            loudness_lufs: None,
+        };
+        
+        let audio_properties = metadata.audio_properties();
+        
         // 构建 Track 对象
+        let rating = metadata.star_rating();
         let track = Track {
             id: track_id,
             path: track_path,
@@ -245,6 +443,13 @@ impl RemoteScanner {
             artist: metadata.artist,
             album: metadata.album,
             duration_ms: metadata.duration_ms.map(|d| d as i64),
+            genre: metadata.genre,
+            year: metadata.year.map(i64::from),
+            track_number: metadata.track_number.map(i64::from),
+            disc_number: metadata.disc_number.map(i64::from),
+            album_artist: metadata.album_artist,
+            cover_id: None,
+            rating,
             album_cover_data: metadata.album_cover_data,
             album_cover_mime: metadata.album_cover_mime,
             artist_photo_data: metadata.artist_photo_data,
@@ -256,6 +461,15 @@ impl RemoteScanner {
         {
             let db = self.db.lock().map_err(|e| anyhow::anyhow!("数据库锁定失败: {}", e))?;
             db.insert_track(&track)?;
+            db.update_replay_gain(&track.path, &replay_gain)?;
+            if extracted {
+                db.update_audio_properties(&track.path, &audio_properties)?;
+                db.update_remote_file_state(&track.path, &RemoteFileState {
+                    mtime: file.last_modified,
+                    size: file.size.map(|s| s as i64),
+                    etag: file.etag.clone(),
+                })?;
+            }
         } // db 锁在这里释放
         
         log::info!("✅ 处理完成: {} (专辑: {:?}, 封面: {}, 时长: {:?}ms)", 
@@ -552,4 +766,52 @@ impl RemoteScanner {
             (name_without_ext.to_string(), None)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_source::RemoteSourceType;
+
+    fn file(last_modified: Option<i64>, size: Option<u64>, etag: Option<&str>) -> RemoteFileInfo {
+        RemoteFileInfo {
+            path: "/music/a.flac".to_string(),
+            name: "a.flac".to_string(),
+            is_directory: false,
+            size,
+            mime_type: None,
+            last_modified,
+            etag: etag.map(str::to_string),
+            source_type: RemoteSourceType::WebDAV,
+        }
+    }
+
+    #[test]
+    fn test_unchanged_prefers_etag() {
+        let previous = RemoteFileState { mtime: Some(100), size: Some(10), etag: Some("v1".into()) };
+        assert!(is_unchanged(&previous, &file(Some(200), Some(20), Some("v1"))));
+        assert!(!is_unchanged(&previous, &file(Some(100), Some(10), Some("v2"))));
+
+        // 没有ETag时比较修改时间和大小
+        let previous = RemoteFileState { mtime: Some(100), size: Some(10), etag: None };
+        assert!(is_unchanged(&previous, &file(Some(100), Some(10), None)));
+        assert!(!is_unchanged(&previous, &file(Some(100), Some(11), None)));
+        assert!(!is_unchanged(&previous, &file(Some(101), Some(10), None)));
+
+        // 从未记录状态或服务器不提供时总是重新处理
+        assert!(!is_unchanged(&RemoteFileState::default(), &file(None, None, None)));
+    }
+
+    #[test]
+    fn test_cancel_only_active_scan() {
+        assert!(!cancel_scan("scanner-test"));
+        {
+            let active = ActiveScan::register("scanner-test").unwrap();
+            assert!(ActiveScan::register("scanner-test").is_err());
+            assert!(cancel_scan("scanner-test"));
+            assert!(active.is_cancelled());
+        }
+        assert!(!cancel_scan("scanner-test"));
+        drop(ActiveScan::register("scanner-test").unwrap());
+    }
+}
@@ -2,21 +2,29 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncRead;
 use anyhow::Result;
+use crate::metadata_extractor::MusicMetadata;
 
-/// 远程源类型 (仅支持WebDAV)
+/// 远程源类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum RemoteSourceType {
     WebDAV,
+    Subsonic,
 }
 
 impl std::fmt::Display for RemoteSourceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RemoteSourceType::WebDAV => write!(f, "webdav"),
+            RemoteSourceType::Subsonic => write!(f, "subsonic"),
         }
     }
 }
 
+/// 是否为远程曲目路径（webdav://、subsonic://）
+pub fn is_remote_track_path(path: &str) -> bool {
+    path.starts_with("webdav://") || path.starts_with("subsonic://")
+}
+
 /// 统一的远程文件信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteFileInfo {
@@ -70,6 +78,11 @@ pub trait RemoteSourceClient: Send + Sync {
     async fn download_range(&self, path: &str, start: u64, end: Option<u64>) 
         -> Result<Box<dyn AsyncRead + Send + Unpin>>;
     
+    /// 服务器直接提供的曲目元数据，返回 None 时扫描器下载文件解析标签
+    async fn get_metadata(&self, _path: &str) -> Result<Option<MusicMetadata>> {
+        Ok(None)
+    }
+    
     /// 获取健康状态（预留功能）
     #[allow(dead_code)]
     fn get_health(&self) -> HealthStatus;
@@ -0,0 +1,179 @@
+// 长曲目续播位置
+//
+// 有声书章节、DJ 混音等长曲目在暂停、停止或切走时记住播放位置，下次播放同一曲目时自动跳回：
+// - 只对时长不短于阈值（默认20分钟）的曲目生效
+// - 播放超过95%视为听完，清除已保存的位置
+// - 开头不到30秒的位置不保存，避免重新开始播放时覆盖之前的进度
+
+use serde::{Deserialize, Serialize};
+use crate::player::Track;
+
+/// 默认生效的最短曲目时长（20分钟）
+pub const DEFAULT_MIN_DURATION_MS: u64 = 20 * 60 * 1000;
+
+/// 最短曲目时长的下限（1分钟）
+const MIN_DURATION_FLOOR_MS: u64 = 60 * 1000;
+
+/// 播放超过该比例视为听完（百分比）
+const FINISHED_PERCENT: u64 = 95;
+
+/// 小于该位置不保存
+const MIN_SAVE_POSITION_MS: u64 = 30_000;
+
+/// 续播设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeSettings {
+    pub enabled: bool,
+    /// 只对时长不短于该值的曲目保存位置(ms)
+    pub min_duration_ms: u64,
+}
+
+impl Default for ResumeSettings {
+    fn default() -> Self {
+        Self { enabled: true, min_duration_ms: DEFAULT_MIN_DURATION_MS }
+    }
+}
+
+impl ResumeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_duration_ms < MIN_DURATION_FLOOR_MS {
+            return Err(format!("最短曲目时长不能少于 {} 分钟", MIN_DURATION_FLOOR_MS / 60_000));
+        }
+        Ok(())
+    }
+}
+
+/// 需要写入数据库的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeAction {
+    Save { track_id: i64, position_ms: u64 },
+    Clear { track_id: i64 },
+}
+
+#[derive(Debug, Clone)]
+struct CurrentTrack {
+    track_id: i64,
+    duration_ms: u64,
+    position_ms: u64,
+    /// 上次写入的变化，位置没变时不重复写入
+    last_action: Option<ResumeAction>,
+}
+
+/// 根据播放器事件跟踪长曲目的播放位置
+#[derive(Debug, Default)]
+pub struct ResumeTracker {
+    settings: ResumeSettings,
+    current: Option<CurrentTrack>,
+}
+
+impl ResumeTracker {
+    pub fn set_settings(&mut self, settings: ResumeSettings) {
+        self.settings = settings;
+        if !settings.enabled {
+            self.current = None;
+        }
+    }
+
+    /// 曲目切换：返回上一首需要保存或清除的位置，以及需要查询续播位置的新曲目ID
+    pub fn track_changed(&mut self, track: Option<&Track>) -> (Option<ResumeAction>, Option<i64>) {
+        let previous = self.checkpoint();
+        self.current = track
+            .filter(|_| self.settings.enabled)
+            .and_then(|t| {
+                let duration_ms = t.duration_ms.filter(|d| *d > 0)? as u64;
+                (duration_ms >= self.settings.min_duration_ms).then_some(CurrentTrack {
+                    track_id: t.id,
+                    duration_ms,
+                    position_ms: 0,
+                    last_action: None,
+                })
+            });
+        (previous, self.current.as_ref().map(|current| current.track_id))
+    }
+
+    /// 播放位置更新
+    pub fn position(&mut self, position_ms: u64) {
+        if let Some(current) = &mut self.current {
+            current.position_ms = position_ms;
+        }
+    }
+
+    /// 暂停、停止或退出：返回需要保存或清除的位置
+    pub fn checkpoint(&mut self) -> Option<ResumeAction> {
+        let current = self.current.as_mut()?;
+        let action = if current.position_ms * 100 >= current.duration_ms * FINISHED_PERCENT {
+            ResumeAction::Clear { track_id: current.track_id }
+        } else if current.position_ms >= MIN_SAVE_POSITION_MS {
+            ResumeAction::Save { track_id: current.track_id, position_ms: current.position_ms }
+        } else {
+            return None;
+        };
+        if current.last_action == Some(action) {
+            return None;
+        }
+        current.last_action = Some(action);
+        Some(action)
+    }
+
+    /// 曲目播放完毕：清除位置，之后的位置更新属于下一首
+    pub fn track_completed(&mut self) -> Option<ResumeAction> {
+        self.current.take().map(|current| ResumeAction::Clear { track_id: current.track_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i64, duration_ms: i64) -> Track {
+        let mut track = Track::new(id, format!("/music/{}.mp3", id));
+        track.duration_ms = Some(duration_ms);
+        track
+    }
+
+    const HOUR: i64 = 3_600_000;
+
+    #[test]
+    fn test_short_tracks_are_ignored() {
+        let mut tracker = ResumeTracker::default();
+        assert_eq!(tracker.track_changed(Some(&track(1, 240_000))), (None, None));
+        tracker.position(120_000);
+        assert_eq!(tracker.checkpoint(), None);
+    }
+
+    #[test]
+    fn test_position_saved_on_switch_and_cleared_near_end() {
+        let mut tracker = ResumeTracker::default();
+        assert_eq!(tracker.track_changed(Some(&track(1, 2 * HOUR))), (None, Some(1)));
+        tracker.position(10_000);
+        assert_eq!(tracker.checkpoint(), None);
+        tracker.position(2_832_000);
+        assert_eq!(tracker.checkpoint(), Some(ResumeAction::Save { track_id: 1, position_ms: 2_832_000 }));
+        // 位置没变时不重复写入
+        assert_eq!(tracker.checkpoint(), None);
+        tracker.position(2_840_000);
+
+        let (previous, lookup) = tracker.track_changed(Some(&track(2, HOUR)));
+        assert_eq!(previous, Some(ResumeAction::Save { track_id: 1, position_ms: 2_840_000 }));
+        assert_eq!(lookup, Some(2));
+
+        tracker.position(3_500_000);
+        assert_eq!(tracker.checkpoint(), Some(ResumeAction::Clear { track_id: 2 }));
+        assert_eq!(tracker.track_completed(), Some(ResumeAction::Clear { track_id: 2 }));
+        assert_eq!(tracker.checkpoint(), None);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut tracker = ResumeTracker::default();
+        tracker.set_settings(ResumeSettings { enabled: true, min_duration_ms: 5 * 60_000 });
+        assert_eq!(tracker.track_changed(Some(&track(1, 6 * 60_000))).1, Some(1));
+
+        tracker.set_settings(ResumeSettings { enabled: false, ..ResumeSettings::default() });
+        assert_eq!(tracker.checkpoint(), None);
+        assert_eq!(tracker.track_changed(Some(&track(2, 2 * HOUR))), (None, None));
+
+        assert!(ResumeSettings { enabled: true, min_duration_ms: 1_000 }.validate().is_err());
+        assert!(ResumeSettings::default().validate().is_ok());
+    }
+}
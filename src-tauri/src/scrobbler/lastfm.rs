@@ -0,0 +1,162 @@
+// Last.fm 提交客户端
+// 文档: https://www.last.fm/api/scrobbling
+// 令牌为会话密钥（sk），所有写操作都需要用 API Secret 签名
+
+use super::{status_error, Listen, ScrobbleError};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_ENDPOINT: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// track.scrobble 单次最多50条
+pub const MAX_BATCH: usize = 50;
+
+pub struct LastFmClient {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastFmClient {
+    pub fn new(
+        client: reqwest::Client,
+        endpoint: String,
+        api_key: String,
+        api_secret: String,
+        session_key: String,
+    ) -> Self {
+        Self { client, endpoint, api_key, api_secret, session_key }
+    }
+
+    pub async fn now_playing(&self, listen: &Listen) -> Result<(), ScrobbleError> {
+        let mut params = BTreeMap::new();
+        params.insert("artist".to_string(), listen.artist.clone());
+        params.insert("track".to_string(), listen.title.clone());
+        if let Some(album) = &listen.album {
+            params.insert("album".to_string(), album.clone());
+        }
+        if let Some(duration_ms) = listen.duration_ms {
+            params.insert("duration".to_string(), (duration_ms / 1000).to_string());
+        }
+        self.call("track.updateNowPlaying", params).await.map(|_| ())
+    }
+
+    pub async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        self.call("track.scrobble", scrobble_params(listens)).await.map(|_| ())
+    }
+
+    /// 验证会话密钥，返回用户名
+    pub async fn validate(&self) -> Result<String, ScrobbleError> {
+        let response = self.call("user.getInfo", BTreeMap::new()).await?;
+        response["user"]["name"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| ScrobbleError::Retryable("响应中缺少用户名".to_string()))
+    }
+
+    /// 签名并调用API方法（POST表单）
+    async fn call(&self, method: &str, mut params: BTreeMap<String, String>) -> Result<Value, ScrobbleError> {
+        params.insert("method".to_string(), method.to_string());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        params.insert("sk".to_string(), self.session_key.clone());
+        let signature = sign(&params, &self.api_secret);
+        params.insert("api_sig".to_string(), signature);
+        // format 不参与签名
+        params.insert("format".to_string(), "json".to_string());
+
+        let response = self.client.post(&self.endpoint).form(&params).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+
+        // 出错时通常也返回JSON，优先按错误码分类
+        let json: Option<Value> = serde_json::from_str(&body).ok();
+        if let Some(code) = json.as_ref().and_then(|json| json["error"].as_i64()) {
+            let message = json.as_ref()
+                .and_then(|json| json["message"].as_str())
+                .unwrap_or_default();
+            return Err(error_code(code, message));
+        }
+        if !status.is_success() {
+            return Err(status_error(status, &body));
+        }
+        json.ok_or_else(|| ScrobbleError::Retryable("解析响应失败".to_string()))
+    }
+}
+
+/// track.scrobble 的批量参数：artist[i]、track[i]、timestamp[i]...
+fn scrobble_params(listens: &[Listen]) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    for (i, listen) in listens.iter().enumerate() {
+        params.insert(format!("artist[{}]", i), listen.artist.clone());
+        params.insert(format!("track[{}]", i), listen.title.clone());
+        params.insert(format!("timestamp[{}]", i), listen.listened_at.to_string());
+        if let Some(album) = &listen.album {
+            params.insert(format!("album[{}]", i), album.clone());
+        }
+        if let Some(duration_ms) = listen.duration_ms {
+            params.insert(format!("duration[{}]", i), (duration_ms / 1000).to_string());
+        }
+    }
+    params
+}
+
+/// api_sig：按参数名排序拼接 名称+值，末尾加上 secret 后取MD5
+fn sign(params: &BTreeMap<String, String>, secret: &str) -> String {
+    let mut raw = String::new();
+    for (key, value) in params {
+        raw.push_str(key);
+        raw.push_str(value);
+    }
+    raw.push_str(secret);
+    format!("{:x}", md5::compute(raw.as_bytes()))
+}
+
+/// 按Last.fm错误码分类
+///
+/// 8 操作失败、11 服务离线、16 暂时不可用、29 限流，以及 9 会话失效（重新配置后可恢复）
+/// 都保留在队列中重试，其他错误视为被拒绝
+fn error_code(code: i64, message: &str) -> ScrobbleError {
+    let message = format!("Last.fm错误 {}: {}", code, message);
+    match code {
+        8 | 9 | 11 | 16 | 29 => ScrobbleError::Retryable(message),
+        _ => ScrobbleError::Rejected(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_sorts_params_and_appends_secret() {
+        let mut params = BTreeMap::new();
+        params.insert("method".to_string(), "track.scrobble".to_string());
+        params.insert("api_key".to_string(), "key".to_string());
+        params.insert("artist[0]".to_string(), "A".to_string());
+        let expected = format!("{:x}", md5::compute(b"api_keykeyartist[0]Amethodtrack.scrobblesecret"));
+        assert_eq!(sign(&params, "secret"), expected);
+    }
+
+    #[test]
+    fn test_scrobble_params_are_indexed() {
+        let listens = vec![
+            Listen { artist: "A".into(), title: "T1".into(), album: None, duration_ms: Some(180_500), listened_at: 100 },
+            Listen { artist: "B".into(), title: "T2".into(), album: Some("X".into()), duration_ms: None, listened_at: 200 },
+        ];
+        let params = scrobble_params(&listens);
+        assert_eq!(params["artist[0]"], "A");
+        assert_eq!(params["duration[0]"], "180");
+        assert_eq!(params["timestamp[1]"], "200");
+        assert_eq!(params["album[1]"], "X");
+        assert!(!params.contains_key("album[0]"));
+        assert!(!params.contains_key("duration[1]"));
+    }
+
+    #[test]
+    fn test_error_code_classification() {
+        assert!(matches!(error_code(29, "rate limit"), ScrobbleError::Retryable(_)));
+        assert!(matches!(error_code(9, "invalid session"), ScrobbleError::Retryable(_)));
+        assert!(matches!(error_code(6, "invalid parameters"), ScrobbleError::Rejected(_)));
+    }
+}
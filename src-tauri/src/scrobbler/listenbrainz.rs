@@ -0,0 +1,150 @@
+// ListenBrainz 提交客户端
+// 文档: https://listenbrainz.readthedocs.io/en/latest/users/api/core.html
+
+use super::{status_error, Listen, ScrobbleError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.listenbrainz.org";
+
+/// 单次提交的记录数上限（服务端允许更多，分批可减小单次请求体积）
+pub const MAX_BATCH: usize = 100;
+
+pub struct ListenBrainzClient {
+    client: reqwest::Client,
+    endpoint: String,
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidateTokenResponse {
+    #[serde(default)]
+    valid: bool,
+    user_name: Option<String>,
+    message: Option<String>,
+}
+
+impl ListenBrainzClient {
+    pub fn new(client: reqwest::Client, endpoint: String, token: String) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    pub async fn now_playing(&self, listen: &Listen) -> Result<(), ScrobbleError> {
+        self.post_listens(&submit_payload("playing_now", std::slice::from_ref(listen))).await
+    }
+
+    pub async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        let listen_type = if listens.len() == 1 { "single" } else { "import" };
+        self.post_listens(&submit_payload(listen_type, listens)).await
+    }
+
+    /// 验证令牌，返回用户名
+    pub async fn validate(&self) -> Result<String, ScrobbleError> {
+        let response = self.client
+            .get(format!("{}/1/validate-token", self.endpoint))
+            .header("Authorization", format!("Token {}", self.token))
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(status_error(status, &body));
+        }
+
+        let result: ValidateTokenResponse = serde_json::from_str(&body)
+            .map_err(|e| ScrobbleError::Retryable(format!("解析响应失败: {}", e)))?;
+        match result.user_name {
+            Some(user_name) if result.valid => Ok(user_name),
+            _ => Err(ScrobbleError::Rejected(result.message.unwrap_or_else(|| "令牌无效".to_string()))),
+        }
+    }
+
+    async fn post_listens(&self, payload: &Value) -> Result<(), ScrobbleError> {
+        let response = self.client
+            .post(format!("{}/1/submit-listens", self.endpoint))
+            .header("Authorization", format!("Token {}", self.token))
+            .json(payload)
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(status_error(status, &body))
+    }
+}
+
+/// submit-listens 请求体，playing_now 不带收听时间
+fn submit_payload(listen_type: &str, listens: &[Listen]) -> Value {
+    let payload: Vec<Value> = listens.iter()
+        .map(|listen| {
+            let mut additional_info = json!({
+                "media_player": "WindChime Player",
+                "submission_client": "WindChime Player",
+                "submission_client_version": env!("CARGO_PKG_VERSION"),
+            });
+            if let Some(duration_ms) = listen.duration_ms {
+                additional_info["duration_ms"] = json!(duration_ms);
+            }
+
+            let mut track_metadata = json!({
+                "artist_name": listen.artist,
+                "track_name": listen.title,
+                "additional_info": additional_info,
+            });
+            if let Some(album) = &listen.album {
+                track_metadata["release_name"] = json!(album);
+            }
+
+            let mut item = json!({ "track_metadata": track_metadata });
+            if listen_type != "playing_now" {
+                item["listened_at"] = json!(listen.listened_at);
+            }
+            item
+        })
+        .collect();
+
+    json!({ "listen_type": listen_type, "payload": payload })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen() -> Listen {
+        Listen {
+            artist: "周杰伦".to_string(),
+            title: "晴天".to_string(),
+            album: Some("叶惠美".to_string()),
+            duration_ms: Some(269_000),
+            listened_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_submit_payload() {
+        let payload = submit_payload("single", &[listen()]);
+        assert_eq!(payload["listen_type"], "single");
+        let item = &payload["payload"][0];
+        assert_eq!(item["listened_at"], 1_700_000_000);
+        assert_eq!(item["track_metadata"]["artist_name"], "周杰伦");
+        assert_eq!(item["track_metadata"]["track_name"], "晴天");
+        assert_eq!(item["track_metadata"]["release_name"], "叶惠美");
+        assert_eq!(item["track_metadata"]["additional_info"]["duration_ms"], 269_000);
+    }
+
+    #[test]
+    fn test_playing_now_payload_has_no_timestamp() {
+        let mut without_album = listen();
+        without_album.album = None;
+        let payload = submit_payload("playing_now", &[without_album]);
+        let item = &payload["payload"][0];
+        assert!(item.get("listened_at").is_none());
+        assert!(item["track_metadata"].get("release_name").is_none());
+    }
+}
@@ -0,0 +1,272 @@
+// 播放记录提交（Scrobbling）
+// 可选地把播放记录提交到 ListenBrainz 或 Last.fm：
+// - 曲目切换时发送“正在播放”
+// - 后端记录一次有效播放后加入 scrobble_queue，由后台任务批量提交
+// - 提交失败按指数退避重试，离线期间的记录不会丢失
+// 所有网络请求都在独立的tokio任务中执行，不阻塞播放
+
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod worker;
+
+pub use worker::{configure, now_playing, start, status, submit_play, test_connection, ScrobblerStatus};
+
+use crate::player::Track;
+use lastfm::LastFmClient;
+use listenbrainz::ListenBrainzClient;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// 单次请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 提交服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrobbleService {
+    #[serde(rename = "listenbrainz")]
+    ListenBrainz,
+    #[serde(rename = "lastfm")]
+    LastFm,
+}
+
+impl ScrobbleService {
+    /// scrobble_queue.service 列中的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrobbleService::ListenBrainz => "listenbrainz",
+            ScrobbleService::LastFm => "lastfm",
+        }
+    }
+}
+
+/// 提交设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrobblerConfig {
+    pub enabled: bool,
+    pub service: ScrobbleService,
+    /// 自定义服务地址（如自建的ListenBrainz兼容服务），为空使用官方地址
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// ListenBrainz用户令牌 / Last.fm会话密钥
+    #[serde(default)]
+    pub token: String,
+    /// Last.fm API Key
+    #[serde(default)]
+    pub api_key: String,
+    /// Last.fm API Secret
+    #[serde(default)]
+    pub api_secret: String,
+}
+
+impl ScrobblerConfig {
+    /// 检查必填项和服务地址
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.token.trim().is_empty() {
+            return Err("缺少用户令牌".to_string());
+        }
+        if self.service == ScrobbleService::LastFm
+            && (self.api_key.trim().is_empty() || self.api_secret.trim().is_empty())
+        {
+            return Err("Last.fm需要填写API Key和API Secret".to_string());
+        }
+        if let Some(endpoint) = self.custom_endpoint() {
+            let url = url::Url::parse(endpoint).map_err(|e| format!("服务地址无效: {}", e))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err("服务地址必须以http或https开头".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn custom_endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref().map(str::trim).filter(|endpoint| !endpoint.is_empty())
+    }
+
+    /// 实际使用的服务地址
+    pub fn endpoint(&self) -> String {
+        match self.custom_endpoint() {
+            Some(endpoint) => endpoint.to_string(),
+            None => match self.service {
+                ScrobbleService::ListenBrainz => listenbrainz::DEFAULT_ENDPOINT.to_string(),
+                ScrobbleService::LastFm => lastfm::DEFAULT_ENDPOINT.to_string(),
+            },
+        }
+    }
+}
+
+/// 一次收听（队列中以JSON保存）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration_ms: Option<i64>,
+    /// 开始收听的时间（Unix秒）
+    pub listened_at: i64,
+}
+
+impl Listen {
+    /// 缺少艺术家或标题的曲目服务端无法识别，返回None
+    pub fn from_track(track: &Track, listened_at: i64) -> Option<Self> {
+        let non_empty = |value: &Option<String>| {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+        };
+        Some(Self {
+            artist: non_empty(&track.artist)?,
+            title: non_empty(&track.title)?,
+            album: non_empty(&track.album),
+            duration_ms: track.duration_ms.filter(|ms| *ms > 0),
+            listened_at,
+        })
+    }
+}
+
+/// 提交错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrobbleError {
+    /// 网络错误、限流、服务端错误或令牌失效，稍后重试
+    Retryable(String),
+    /// 服务端拒绝该记录，重试也不会成功
+    Rejected(String),
+}
+
+impl fmt::Display for ScrobbleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrobbleError::Retryable(message) => write!(f, "{}", message),
+            ScrobbleError::Rejected(message) => write!(f, "服务拒绝: {}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ScrobbleError {
+    fn from(e: reqwest::Error) -> Self {
+        ScrobbleError::Retryable(format!("网络请求失败: {}", e))
+    }
+}
+
+/// 按HTTP状态码分类：400类为拒绝，认证失败、限流和服务端错误可重试
+fn status_error(status: reqwest::StatusCode, body: &str) -> ScrobbleError {
+    let message = format!("HTTP {}: {}", status.as_u16(), body.chars().take(200).collect::<String>());
+    if status.is_server_error()
+        || status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        ScrobbleError::Retryable(message)
+    } else {
+        ScrobbleError::Rejected(message)
+    }
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("WindChimePlayer/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .unwrap_or_default()
+}
+
+/// 按设置创建的服务客户端
+pub enum ScrobbleClient {
+    ListenBrainz(ListenBrainzClient),
+    LastFm(LastFmClient),
+}
+
+impl ScrobbleClient {
+    pub fn new(config: &ScrobblerConfig) -> Self {
+        let client = http_client();
+        match config.service {
+            ScrobbleService::ListenBrainz => ScrobbleClient::ListenBrainz(
+                ListenBrainzClient::new(client, config.endpoint(), config.token.clone()),
+            ),
+            ScrobbleService::LastFm => ScrobbleClient::LastFm(LastFmClient::new(
+                client,
+                config.endpoint(),
+                config.api_key.clone(),
+                config.api_secret.clone(),
+                config.token.clone(),
+            )),
+        }
+    }
+
+    /// 单次最多提交的记录数
+    pub fn max_batch(&self) -> usize {
+        match self {
+            ScrobbleClient::ListenBrainz(_) => listenbrainz::MAX_BATCH,
+            ScrobbleClient::LastFm(_) => lastfm::MAX_BATCH,
+        }
+    }
+
+    pub async fn now_playing(&self, listen: &Listen) -> Result<(), ScrobbleError> {
+        match self {
+            ScrobbleClient::ListenBrainz(client) => client.now_playing(listen).await,
+            ScrobbleClient::LastFm(client) => client.now_playing(listen).await,
+        }
+    }
+
+    pub async fn submit(&self, listens: &[Listen]) -> Result<(), ScrobbleError> {
+        match self {
+            ScrobbleClient::ListenBrainz(client) => client.submit(listens).await,
+            ScrobbleClient::LastFm(client) => client.submit(listens).await,
+        }
+    }
+
+    /// 验证令牌，返回用户名
+    pub async fn validate(&self) -> Result<String, ScrobbleError> {
+        match self {
+            ScrobbleClient::ListenBrainz(client) => client.validate().await,
+            ScrobbleClient::LastFm(client) => client.validate().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(service: ScrobbleService) -> ScrobblerConfig {
+        ScrobblerConfig {
+            enabled: true,
+            service,
+            endpoint: None,
+            token: "token".to_string(),
+            api_key: String::new(),
+            api_secret: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_config_validate() {
+        assert!(config(ScrobbleService::ListenBrainz).validate().is_ok());
+        assert!(config(ScrobbleService::LastFm).validate().is_err());
+
+        let mut lastfm = config(ScrobbleService::LastFm);
+        lastfm.api_key = "key".to_string();
+        lastfm.api_secret = "secret".to_string();
+        assert!(lastfm.validate().is_ok());
+
+        let mut no_token = config(ScrobbleService::ListenBrainz);
+        no_token.token = "  ".to_string();
+        assert!(no_token.validate().is_err());
+
+        let mut custom = config(ScrobbleService::ListenBrainz);
+        custom.endpoint = Some("ftp://example.com".to_string());
+        assert!(custom.validate().is_err());
+        custom.endpoint = Some("https://lb.example.com".to_string());
+        assert!(custom.validate().is_ok());
+        assert_eq!(custom.endpoint(), "https://lb.example.com");
+        custom.endpoint = Some(" ".to_string());
+        assert_eq!(custom.endpoint(), listenbrainz::DEFAULT_ENDPOINT);
+    }
+
+    #[test]
+    fn test_status_error_classification() {
+        use reqwest::StatusCode;
+        assert!(matches!(status_error(StatusCode::BAD_REQUEST, ""), ScrobbleError::Rejected(_)));
+        assert!(matches!(status_error(StatusCode::UNAUTHORIZED, ""), ScrobbleError::Retryable(_)));
+        assert!(matches!(status_error(StatusCode::TOO_MANY_REQUESTS, ""), ScrobbleError::Retryable(_)));
+        assert!(matches!(status_error(StatusCode::BAD_GATEWAY, ""), ScrobbleError::Retryable(_)));
+    }
+}
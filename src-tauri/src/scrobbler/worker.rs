@@ -0,0 +1,343 @@
+// 提交工作器
+//
+// 播放路径只往通道里发消息，网络请求全部在这个任务中执行：
+// - “正在播放”直接发送，失败只记录日志
+// - 有效播放先写入 scrobble_queue 再按批提交，成功后删除
+// - 批量提交被拒绝时逐条重试，只丢弃被拒绝的那一条
+// - 网络错误、限流等按指数退避推迟，离线恢复后继续提交
+
+use super::{Listen, ScrobbleClient, ScrobbleError, ScrobbleService, ScrobblerConfig};
+use crate::db::{Database, QueuedScrobble};
+use crate::db_pool::DbPool;
+use crate::player::Track;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex as StateMutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 第一次重试前的等待时间（秒），之后每次翻倍
+const RETRY_BASE_SECS: i64 = 60;
+
+/// 重试等待上限（秒）
+const RETRY_MAX_SECS: i64 = 6 * 3600;
+
+/// 队列为空时的轮询间隔
+const IDLE_POLL: Duration = Duration::from_secs(300);
+
+enum ScrobbleMsg {
+    NowPlaying(Listen),
+    /// 后端记录了一次有效播放
+    Submit { track_id: i64, played_ms: u64 },
+    /// 设置已变化，唤醒工作器
+    Reconfigure,
+}
+
+/// 当前设置和最近一次提交结果
+#[derive(Default)]
+struct ScrobblerState {
+    config: Option<ScrobblerConfig>,
+    last_error: Option<String>,
+    last_submitted_at: Option<i64>,
+}
+
+static SCROBBLER_TX: OnceLock<mpsc::UnboundedSender<ScrobbleMsg>> = OnceLock::new();
+
+static STATE: Lazy<StateMutex<ScrobblerState>> = Lazy::new(|| StateMutex::new(ScrobblerState::default()));
+
+/// 提交状态（不包含令牌）
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrobblerStatus {
+    pub enabled: bool,
+    pub service: Option<ScrobbleService>,
+    pub endpoint: Option<String>,
+    pub has_token: bool,
+    /// 等待提交的记录数
+    pub queue_depth: i64,
+    pub last_error: Option<String>,
+    /// 最近一次成功提交的时间（Unix秒）
+    pub last_submitted_at: Option<i64>,
+}
+
+/// 第 attempts 次失败后的等待时间（秒）
+pub fn retry_delay_secs(attempts: i64) -> i64 {
+    let exponent = attempts.clamp(0, 16) as u32;
+    (RETRY_BASE_SECS << exponent).min(RETRY_MAX_SECS)
+}
+
+/// 启动工作器，直到 shutdown 为 true
+pub fn start(db: Arc<DbPool>, config: Option<ScrobblerConfig>, shutdown: &'static AtomicBool) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    if SCROBBLER_TX.set(tx).is_err() {
+        log::warn!("⚠️ 提交工作器已启动，忽略重复启动");
+        return;
+    }
+    STATE.lock().config = config;
+    tauri::async_runtime::spawn(run(db, rx, shutdown));
+}
+
+/// 更新设置（None 表示关闭）
+pub fn configure(config: Option<ScrobblerConfig>) {
+    {
+        let mut state = STATE.lock();
+        state.config = config;
+        state.last_error = None;
+    }
+    send(ScrobbleMsg::Reconfigure);
+}
+
+/// 曲目切换时发送“正在播放”，未启用时什么也不做
+pub fn now_playing(track: &Track) {
+    if enabled_config().is_none() {
+        return;
+    }
+    if let Some(listen) = Listen::from_track(track, chrono::Utc::now().timestamp()) {
+        send(ScrobbleMsg::NowPlaying(listen));
+    }
+}
+
+/// 提交一次有效播放，未启用时什么也不做
+pub fn submit_play(track_id: i64, played_ms: u64) {
+    if enabled_config().is_some() {
+        send(ScrobbleMsg::Submit { track_id, played_ms });
+    }
+}
+
+/// 当前状态，队列长度按当前服务统计
+pub fn status(db: &Database) -> ScrobblerStatus {
+    let state = STATE.lock();
+    let config = state.config.as_ref();
+    ScrobblerStatus {
+        enabled: config.is_some_and(|config| config.enabled),
+        service: config.map(|config| config.service),
+        endpoint: config.map(|config| config.endpoint()),
+        has_token: config.is_some_and(|config| !config.token.is_empty()),
+        queue_depth: config
+            .and_then(|config| db.scrobble_queue_depth(config.service.as_str()).ok())
+            .unwrap_or(0),
+        last_error: state.last_error.clone(),
+        last_submitted_at: state.last_submitted_at,
+    }
+}
+
+/// 验证设置能否连接服务，返回用户名
+pub async fn test_connection(config: &ScrobblerConfig) -> Result<String, String> {
+    config.validate()?;
+    ScrobbleClient::new(config).validate().await.map_err(|e| e.to_string())
+}
+
+fn enabled_config() -> Option<ScrobblerConfig> {
+    STATE.lock().config.clone().filter(|config| config.enabled)
+}
+
+fn send(msg: ScrobbleMsg) {
+    if let Some(tx) = SCROBBLER_TX.get() {
+        let _ = tx.send(msg);
+    }
+}
+
+fn record_error(error: &ScrobbleError) {
+    STATE.lock().last_error = Some(error.to_string());
+}
+
+fn record_success() {
+    let mut state = STATE.lock();
+    state.last_error = None;
+    state.last_submitted_at = Some(chrono::Utc::now().timestamp());
+}
+
+async fn run(db: Arc<DbPool>, mut rx: mpsc::UnboundedReceiver<ScrobbleMsg>, shutdown: &'static AtomicBool) {
+    // 设置变化时才重建客户端
+    let mut client: Option<(ScrobblerConfig, Arc<ScrobbleClient>)> = None;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let config = enabled_config();
+        let active = match config {
+            Some(config) => {
+                if client.as_ref().map(|(current, _)| current) != Some(&config) {
+                    client = Some((config.clone(), Arc::new(ScrobbleClient::new(&config))));
+                }
+                client.as_ref().map(|(config, client)| (config.service, client.clone()))
+            }
+            None => None,
+        };
+
+        let wait = match &active {
+            Some((service, client)) => {
+                drain(&db, *service, client, shutdown).await;
+                next_wait(&db, *service)
+            }
+            None => IDLE_POLL,
+        };
+
+        let msg = match tokio::time::timeout(wait, rx.recv()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        let Some((service, client)) = active else { continue };
+        match msg {
+            ScrobbleMsg::NowPlaying(listen) => {
+                if let Err(e) = client.now_playing(&listen).await {
+                    log::debug!("发送正在播放失败: {}", e);
+                }
+            }
+            ScrobbleMsg::Submit { track_id, played_ms } => enqueue(&db, service, track_id, played_ms),
+            ScrobbleMsg::Reconfigure => {}
+        }
+    }
+}
+
+/// 距离下一条记录可以重试的时间，队列为空时按空闲间隔轮询
+fn next_wait(db: &Arc<DbPool>, service: ScrobbleService) -> Duration {
+    let now = chrono::Utc::now().timestamp();
+    db.with(|db| db.next_scrobble_attempt_at(service.as_str()))
+        .ok()
+        .flatten()
+        .map(|at| Duration::from_secs((at - now).max(1) as u64).min(IDLE_POLL))
+        .unwrap_or(IDLE_POLL)
+}
+
+/// 有效播放写入队列，收听开始时间按播放时长倒推
+fn enqueue(db: &Arc<DbPool>, service: ScrobbleService, track_id: i64, played_ms: u64) {
+    let db = match db.lock() {
+        Ok(db) => db,
+        Err(e) => {
+            log::warn!("⚠️ 播放记录加入提交队列失败: {}", e);
+            return;
+        }
+    };
+    let track = match db.get_track_by_id(track_id) {
+        Ok(Some(track)) => track,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("⚠️ 读取曲目失败，跳过提交: {}", e);
+            return;
+        }
+    };
+    let listened_at = chrono::Utc::now().timestamp() - (played_ms / 1000) as i64;
+    let Some(listen) = Listen::from_track(&track, listened_at) else {
+        log::debug!("曲目缺少艺术家或标题，跳过提交: {}", track.path);
+        return;
+    };
+    match serde_json::to_string(&listen) {
+        Ok(payload) => {
+            if let Err(e) = db.enqueue_scrobble(service.as_str(), &payload, listened_at) {
+                log::warn!("⚠️ 播放记录加入提交队列失败: {}", e);
+            }
+        }
+        Err(e) => log::warn!("⚠️ 序列化播放记录失败: {}", e),
+    }
+}
+
+/// 提交所有已到重试时间的记录
+async fn drain(db: &Arc<DbPool>, service: ScrobbleService, client: &ScrobbleClient, shutdown: &AtomicBool) {
+    while !shutdown.load(Ordering::Relaxed) {
+        let now = chrono::Utc::now().timestamp();
+        let batch = match db.with(|db| db.due_scrobbles(service.as_str(), now, client.max_batch() as i64)) {
+            Ok(batch) if !batch.is_empty() => batch,
+            Ok(_) => return,
+            Err(e) => {
+                log::error!("读取提交队列失败: {}", e);
+                return;
+            }
+        };
+
+        let (batch, listens) = decode(db, batch);
+        if batch.is_empty() {
+            continue;
+        }
+
+        match client.submit(&listens).await {
+            Ok(()) => {
+                delete(db, &batch);
+                record_success();
+                log::info!("🎵 已提交 {} 条播放记录到 {}", batch.len(), service.as_str());
+            }
+            Err(ScrobbleError::Rejected(_)) if batch.len() > 1 => {
+                // 整批被拒绝时逐条提交，找出有问题的记录
+                for (item, listen) in batch.iter().zip(&listens) {
+                    match client.submit(std::slice::from_ref(listen)).await {
+                        Ok(()) => {
+                            delete(db, std::slice::from_ref(item));
+                            record_success();
+                        }
+                        Err(e @ ScrobbleError::Rejected(_)) => {
+                            log::warn!("⚠️ 播放记录被拒绝，已丢弃: {} - {} ({})", listen.artist, listen.title, e);
+                            delete(db, std::slice::from_ref(item));
+                            record_error(&e);
+                        }
+                        Err(e @ ScrobbleError::Retryable(_)) => {
+                            defer(db, std::slice::from_ref(item), &e);
+                            record_error(&e);
+                        }
+                    }
+                }
+            }
+            Err(e @ ScrobbleError::Rejected(_)) => {
+                log::warn!("⚠️ 播放记录被拒绝，已丢弃: {}", e);
+                delete(db, &batch);
+                record_error(&e);
+            }
+            Err(e @ ScrobbleError::Retryable(_)) => {
+                log::warn!("⚠️ 提交播放记录失败，稍后重试: {}", e);
+                defer(db, &batch, &e);
+                record_error(&e);
+                return;
+            }
+        }
+    }
+}
+
+/// 解析队列中的记录，无法解析的直接删除
+fn decode(db: &Arc<DbPool>, batch: Vec<QueuedScrobble>) -> (Vec<QueuedScrobble>, Vec<Listen>) {
+    let mut valid = Vec::with_capacity(batch.len());
+    let mut listens = Vec::with_capacity(batch.len());
+    let mut invalid = Vec::new();
+    for item in batch {
+        match serde_json::from_str::<Listen>(&item.payload) {
+            Ok(listen) => {
+                listens.push(listen);
+                valid.push(item);
+            }
+            Err(_) => invalid.push(item),
+        }
+    }
+    if !invalid.is_empty() {
+        log::warn!("⚠️ 丢弃 {} 条无法解析的播放记录", invalid.len());
+        delete(db, &invalid);
+    }
+    (valid, listens)
+}
+
+fn delete(db: &Arc<DbPool>, items: &[QueuedScrobble]) {
+    let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+    if let Err(e) = db.with(|db| db.delete_scrobbles(&ids)) {
+        log::error!("删除已提交的播放记录失败: {}", e);
+    }
+}
+
+fn defer(db: &Arc<DbPool>, items: &[QueuedScrobble], error: &ScrobbleError) {
+    let attempts = items.iter().map(|item| item.attempts).max().unwrap_or(0);
+    let next_attempt_at = chrono::Utc::now().timestamp() + retry_delay_secs(attempts);
+    let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+    if let Err(e) = db.with(|db| db.defer_scrobbles(&ids, next_attempt_at, &error.to_string())) {
+        log::error!("更新提交队列失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay_secs(0), 60);
+        assert_eq!(retry_delay_secs(1), 120);
+        assert_eq!(retry_delay_secs(3), 480);
+        assert_eq!(retry_delay_secs(9), RETRY_MAX_SECS);
+        assert_eq!(retry_delay_secs(100), RETRY_MAX_SECS);
+    }
+}
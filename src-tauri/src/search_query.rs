@@ -0,0 +1,341 @@
+// 曲库搜索语法解析
+//
+// 支持字段限定：artist:"miles davis" album:blue genre:jazz year:1959 year:1950-1969
+// 引号包裹的内容作为整体短语；未知字段（如 foo:bar）按普通关键词处理。
+//
+// 结果排序：标题完全匹配 > 标题前缀 > 艺术家完全匹配 > 专辑 > 其他模糊匹配，
+// 同一档内收藏与播放次数较多的曲目靠前。
+
+use crate::player::Track;
+
+/// 可限定的文本字段，与 tracks_fts 的列一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+}
+
+impl TextField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "title" => Some(TextField::Title),
+            "artist" => Some(TextField::Artist),
+            "album" => Some(TextField::Album),
+            "albumartist" | "album_artist" => Some(TextField::AlbumArtist),
+            "genre" => Some(TextField::Genre),
+            _ => None,
+        }
+    }
+
+    pub fn fts_column(&self) -> &'static str {
+        match self {
+            TextField::Title => "title",
+            TextField::Artist => "artist",
+            TextField::Album => "album",
+            TextField::AlbumArtist => "album_artist",
+            TextField::Genre => "genre",
+        }
+    }
+}
+
+/// 年份范围（闭区间）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YearRange {
+    pub from: i64,
+    pub to: i64,
+}
+
+impl YearRange {
+    /// 解析 1959、1950-1969 或 1950..1969
+    fn parse(value: &str) -> Option<Self> {
+        let (from, to) = match value.split_once("..").or_else(|| value.split_once('-')) {
+            Some((from, to)) => (from.trim().parse().ok()?, to.trim().parse().ok()?),
+            None => {
+                let year = value.trim().parse().ok()?;
+                (year, year)
+            }
+        };
+        Some(Self { from: from.min(to), to: from.max(to) })
+    }
+}
+
+/// 解析后的搜索条件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// 未限定字段的关键词（引号短语保持为一项）
+    pub terms: Vec<String>,
+    pub fields: Vec<(TextField, String)>,
+    pub years: Vec<YearRange>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        for token in tokenize(input) {
+            if let Some((key, value)) = token.qualifier() {
+                if let Some(field) = TextField::from_name(&key) {
+                    if !value.is_empty() {
+                        query.fields.push((field, value));
+                    }
+                    continue;
+                }
+                if key == "year" {
+                    if let Some(range) = YearRange::parse(&value) {
+                        query.years.push(range);
+                        continue;
+                    }
+                }
+            }
+            query.terms.push(token.raw);
+        }
+        query
+    }
+
+    /// 是否包含字段限定（否则走原有的模糊搜索）
+    pub fn is_qualified(&self) -> bool {
+        !self.fields.is_empty() || !self.years.is_empty()
+    }
+
+    /// 未限定字段的关键词，用空格连接
+    pub fn free_text(&self) -> String {
+        self.terms.join(" ")
+    }
+
+    /// 生成 FTS5 MATCH 表达式；没有文本条件（只有年份）时返回 None
+    ///
+    /// 字段条件生成列过滤，普通关键词按前缀匹配，各条件之间为 AND
+    pub fn fts_match(&self) -> Option<String> {
+        let parts: Vec<String> = self.fields.iter()
+            .map(|(field, value)| format!("{} : {}", field.fts_column(), fts_phrase(value)))
+            .chain(self.terms.iter().map(|term| format!("{}*", fts_phrase(term))))
+            .collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" AND "))
+        }
+    }
+}
+
+/// 排序用的用户行为信号
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RankSignals {
+    pub is_favorite: bool,
+    pub play_count: i64,
+}
+
+/// 各匹配档位的基础分，档位间距大于加成上限，保证加成只影响同档内的顺序
+const SCORE_TITLE_EXACT: i64 = 500;
+const SCORE_TITLE_PREFIX: i64 = 400;
+const SCORE_ARTIST_EXACT: i64 = 300;
+const SCORE_ALBUM: i64 = 200;
+const SCORE_FUZZY: i64 = 100;
+
+const FAVORITE_BOOST: i64 = 30;
+const MAX_PLAY_COUNT_BOOST: i64 = 60;
+
+/// 计算曲目对搜索词的相关度得分（越大越靠前）
+pub fn relevance_score(query: &str, track: &Track, signals: RankSignals) -> i64 {
+    let query = query.trim().to_lowercase();
+    let lower = |value: &Option<String>| value.as_deref().map(str::to_lowercase).unwrap_or_default();
+    let title = lower(&track.title);
+    let album = lower(&track.album);
+
+    let base = if query.is_empty() {
+        SCORE_FUZZY
+    } else if title == query {
+        SCORE_TITLE_EXACT
+    } else if title.starts_with(&query) {
+        SCORE_TITLE_PREFIX
+    } else if lower(&track.artist) == query {
+        SCORE_ARTIST_EXACT
+    } else if album.starts_with(&query) {
+        SCORE_ALBUM
+    } else {
+        SCORE_FUZZY
+    };
+
+    let favorite_boost = if signals.is_favorite { FAVORITE_BOOST } else { 0 };
+    // 播放次数按对数加成：1次≈10分，7次≈30分，63次封顶
+    let play_count_boost = ((signals.play_count.max(0) as f64 + 1.0).log2() * 10.0) as i64;
+
+    base + favorite_boost + play_count_boost.min(MAX_PLAY_COUNT_BOOST)
+}
+
+/// 按相关度降序排列，得分相同时保持原有顺序
+pub fn rank_tracks<F>(query: &str, tracks: Vec<Track>, signals: F) -> Vec<Track>
+where
+    F: Fn(&Track) -> RankSignals,
+{
+    let mut scored: Vec<(i64, Track)> = tracks.into_iter()
+        .map(|track| (relevance_score(query, &track, signals(&track)), track))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().map(|(_, track)| track).collect()
+}
+
+/// FTS5 短语：双引号包裹，内部引号加倍转义
+fn fts_phrase(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// 词法单元：key 为冒号前、引号外的部分
+#[derive(Debug)]
+struct Token {
+    raw: String,
+    /// 冒号在去掉引号后文本中的位置（仅当冒号出现在第一个引号之前）
+    colon: Option<usize>,
+}
+
+impl Token {
+    fn qualifier(&self) -> Option<(String, String)> {
+        let colon = self.colon?;
+        let key = self.raw[..colon].to_lowercase();
+        if key.is_empty() {
+            return None;
+        }
+        Some((key, self.raw[colon + 1..].trim().to_string()))
+    }
+}
+
+/// 按空白切分，引号内的空白不切分，引号本身不保留
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut raw = String::new();
+    let mut colon = None;
+    let mut in_quotes = false;
+    let mut seen_quote = false;
+
+    let mut flush = |raw: &mut String, colon: &mut Option<usize>, seen_quote: &mut bool| {
+        let token = Token { raw: std::mem::take(raw), colon: colon.take() };
+        if !token.raw.trim().is_empty() {
+            tokens.push(token);
+        }
+        *seen_quote = false;
+    };
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                seen_quote = true;
+            }
+            c if c.is_whitespace() && !in_quotes => flush(&mut raw, &mut colon, &mut seen_quote),
+            ':' if colon.is_none() && !seen_quote => {
+                colon = Some(raw.len());
+                raw.push(c);
+            }
+            c => raw.push(c),
+        }
+    }
+    flush(&mut raw, &mut colon, &mut seen_quote);
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_phrases_stay_together() {
+        let query = SearchQuery::parse(r#"artist:"miles davis" "kind of blue""#);
+        assert_eq!(query.fields, vec![(TextField::Artist, "miles davis".to_string())]);
+        assert_eq!(query.terms, vec!["kind of blue".to_string()]);
+        assert_eq!(
+            query.fts_match().as_deref(),
+            Some(r#"artist : "miles davis" AND "kind of blue"*"#)
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_plain_terms() {
+        let query = SearchQuery::parse("foo:bar year:abc \"a:b\" Artist:X");
+        assert_eq!(query.terms, vec!["foo:bar".to_string(), "year:abc".to_string(), "a:b".to_string()]);
+        // 字段名不区分大小写
+        assert_eq!(query.fields, vec![(TextField::Artist, "X".to_string())]);
+        assert!(query.years.is_empty());
+    }
+
+    #[test]
+    fn test_mixed_qualified_and_free_terms() {
+        let query = SearchQuery::parse("  blue album:blue year:1959 genre:jazz year:1970-1960 train ");
+        assert_eq!(query.terms, vec!["blue".to_string(), "train".to_string()]);
+        assert_eq!(
+            query.fields,
+            vec![(TextField::Album, "blue".to_string()), (TextField::Genre, "jazz".to_string())]
+        );
+        assert_eq!(
+            query.years,
+            vec![YearRange { from: 1959, to: 1959 }, YearRange { from: 1960, to: 1970 }]
+        );
+        assert!(query.is_qualified());
+        assert_eq!(query.free_text(), "blue train");
+    }
+
+    fn fixture(id: i64, title: &str, artist: &str, album: &str) -> Track {
+        let mut track = Track::new(id, format!("/music/{}.mp3", id));
+        track.title = Some(title.to_string());
+        track.artist = Some(artist.to_string());
+        track.album = Some(album.to_string());
+        track
+    }
+
+    fn ranked_ids(query: &str, tracks: Vec<Track>, favorites: &[i64], plays: &[(i64, i64)]) -> Vec<i64> {
+        rank_tracks(query, tracks, |t| RankSignals {
+            is_favorite: favorites.contains(&t.id),
+            play_count: plays.iter().find(|(id, _)| *id == t.id).map(|(_, n)| *n).unwrap_or(0),
+        })
+        .into_iter()
+        .map(|t| t.id)
+        .collect()
+    }
+
+    #[test]
+    fn test_ranking_tiers() {
+        let library = vec![
+            fixture(1, "Love Me Do", "The Beatles", "Please Please Me"),
+            fixture(2, "Songs About Love", "Love", "Forever Changes"),
+            fixture(3, "Alone Again", "Love", "Love Story"),
+            fixture(4, "Love", "John Lennon", "Plastic Ono Band"),
+            fixture(5, "Crazy", "Gnarls Barkley", "Lovely Album"),
+        ];
+        // 标题完全匹配 > 标题前缀 > 艺术家完全匹配（按原顺序）> 专辑
+        assert_eq!(ranked_ids("LOVE", library, &[], &[]), vec![4, 1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_favorites_and_play_count_boost_within_tier() {
+        let library = vec![
+            fixture(1, "Yesterday Once More", "Carpenters", "Now & Then"),
+            fixture(2, "Yesterday's Gone", "Chad & Jeremy", "Yesterday's Gone"),
+            fixture(3, "Yesterdays", "Billie Holiday", "Lady in Satin"),
+            fixture(4, "Yesterday", "The Beatles", "Help!"),
+        ];
+        let ids = ranked_ids("yesterday", library, &[3], &[(2, 5), (1, 1000)]);
+        // 精确标题始终第一；同为前缀匹配时，1000次播放(封顶60) > 收藏(30) > 5次播放(25)
+        assert_eq!(ids, vec![4, 1, 3, 2]);
+
+        let weak = fixture(9, "Other", "Yesterday", "x");
+        let strong = fixture(8, "Yesterday Girl", "x", "x");
+        // 加成不足以跨越档位
+        assert_eq!(ranked_ids("yesterday", vec![weak, strong], &[9], &[(9, 1_000_000)]), vec![8, 9]);
+    }
+
+    #[test]
+    fn test_plain_query_is_not_qualified() {
+        let query = SearchQuery::parse("miles davis");
+        assert!(!query.is_qualified());
+        assert_eq!(query.terms.len(), 2);
+
+        let years_only = SearchQuery::parse("year:1959..1961");
+        assert_eq!(years_only.years, vec![YearRange { from: 1959, to: 1961 }]);
+        assert_eq!(years_only.fts_match(), None);
+
+        // 短语中的引号被转义；空字段值被忽略
+        assert_eq!(fts_phrase(r#"say "hi""#), r#""say ""hi""""#);
+        assert!(SearchQuery::parse("artist:").fields.is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+// 远程服务器凭据加密
+//
+// remote_servers.config_json 中的密码以 AES-256-GCM 加密存储，格式为 enc:v1:<base64(nonce + 密文)>：
+// - 加密密钥由数据库旁的随机密钥文件（<数据库文件名>.key）经 HKDF-SHA256 派生
+// - 密钥文件只允许当前系统用户读写（Unix 为 0600，Windows 位于用户自己的应用数据目录）
+// - Database 读取服务器配置时透明解密，前端拿到的配置中不包含密码
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// 配置中需要加密的字段（WebDAV和Subsonic都使用该字段）
+pub const PASSWORD_FIELD: &str = "password";
+
+/// 脱敏后的配置用该字段表示是否已保存密码
+pub const HAS_PASSWORD_FIELD: &str = "has_password";
+
+/// 已加密值的前缀
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// 密钥文件长度
+const SEED_LEN: usize = 32;
+
+/// HKDF派生参数，修改会导致已加密的凭据无法解密
+const KDF_SALT: &[u8] = b"windchime-secrets";
+const KDF_INFO: &[u8] = b"remote-server-credentials";
+
+/// 数据库对应的密钥文件路径
+pub fn key_path_for(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".key");
+    db_path.with_file_name(name)
+}
+
+/// 值是否已加密
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// 凭据加解密
+pub struct SecretBox {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretBox {
+    /// 读取密钥文件，不存在时生成新的随机密钥
+    pub fn load_or_create(key_path: &Path) -> Result<Self> {
+        let seed = match fs::read(key_path) {
+            Ok(bytes) => Zeroizing::new(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let seed = Self::random_seed()?;
+                write_private_file(key_path, &seed[..])
+                    .with_context(|| format!("写入密钥文件失败: {}", key_path.display()))?;
+                log::info!("🔑 已生成凭据加密密钥: {}", key_path.display());
+                Zeroizing::new(seed.to_vec())
+            }
+            Err(e) => return Err(e).with_context(|| format!("读取密钥文件失败: {}", key_path.display())),
+        };
+        if seed.len() != SEED_LEN {
+            return Err(anyhow!("密钥文件已损坏: {}", key_path.display()));
+        }
+        Self::from_seed(&seed)
+    }
+
+    /// 只在本进程内有效的密钥（内存数据库使用）
+    pub fn ephemeral() -> Result<Self> {
+        Self::from_seed(&Self::random_seed()?[..])
+    }
+
+    fn random_seed() -> Result<Zeroizing<[u8; SEED_LEN]>> {
+        let mut seed = Zeroizing::new([0u8; SEED_LEN]);
+        SystemRandom::new()
+            .fill(&mut seed[..])
+            .map_err(|_| anyhow!("生成随机密钥失败"))?;
+        Ok(seed)
+    }
+
+    fn from_seed(seed: &[u8]) -> Result<Self> {
+        let prk = Salt::new(HKDF_SHA256, KDF_SALT).extract(seed);
+        let okm = prk
+            .expand(&[KDF_INFO], &AES_256_GCM)
+            .map_err(|_| anyhow!("派生加密密钥失败"))?;
+        Ok(Self {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// 加密字符串
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| anyhow!("生成随机数失败"))?;
+
+        let mut data = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("加密失败"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&data);
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(payload)
+        ))
+    }
+
+    /// 解密 encrypt 生成的字符串
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let encoded = value.strip_prefix(ENCRYPTED_PREFIX).ok_or_else(|| anyhow!("不是加密的值"))?;
+        let payload = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("加密数据格式错误")?;
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("加密数据格式错误"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("加密数据格式错误"))?;
+        let mut data = Zeroizing::new(ciphertext.to_vec());
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("解密失败（密钥不匹配或数据已损坏）"))?;
+        String::from_utf8(plaintext.to_vec()).context("解密结果不是有效的UTF-8")
+    }
+
+    /// 加密配置中的明文密码
+    ///
+    /// 配置中没有密码字段时沿用 previous（已保存的配置）中的密码，前端编辑时可以不回传密码
+    pub fn encrypt_config(&self, config_json: &str, previous: Option<&str>) -> Result<String> {
+        let mut config: serde_json::Value = serde_json::from_str(config_json).context("服务器配置不是有效的JSON")?;
+        let Some(object) = config.as_object_mut() else {
+            return Ok(config_json.to_string());
+        };
+        object.remove(HAS_PASSWORD_FIELD);
+
+        match object.get(PASSWORD_FIELD) {
+            Some(serde_json::Value::String(password)) if !is_encrypted(password) => {
+                let encrypted = self.encrypt(password)?;
+                object.insert(PASSWORD_FIELD.to_string(), encrypted.into());
+            }
+            Some(_) => {}
+            None => {
+                let stored = previous
+                    .and_then(|previous| serde_json::from_str::<serde_json::Value>(previous).ok())
+                    .and_then(|previous| previous.get(PASSWORD_FIELD).cloned());
+                if let Some(stored) = stored {
+                    object.insert(PASSWORD_FIELD.to_string(), stored);
+                }
+            }
+        }
+        Ok(config.to_string())
+    }
+
+    /// 解密配置中的密码，明文（尚未迁移）的配置原样返回
+    pub fn decrypt_config(&self, config_json: &str) -> Result<String> {
+        let mut config: serde_json::Value = serde_json::from_str(config_json).context("服务器配置不是有效的JSON")?;
+        let Some(password) = config.get(PASSWORD_FIELD).and_then(|p| p.as_str()).filter(|p| is_encrypted(p)) else {
+            return Ok(config_json.to_string());
+        };
+        let password = self.decrypt(password)?;
+        config[PASSWORD_FIELD] = password.into();
+        Ok(config.to_string())
+    }
+}
+
+/// 去掉配置中的密码，只保留是否已设置
+pub fn redact_config(config_json: &str) -> serde_json::Value {
+    let mut config: serde_json::Value = serde_json::from_str(config_json).unwrap_or(serde_json::json!({}));
+    if let Some(object) = config.as_object_mut() {
+        let has_password = object
+            .remove(PASSWORD_FIELD)
+            .and_then(|p| p.as_str().map(|p| !p.is_empty()))
+            .unwrap_or(false);
+        object.insert(HAS_PASSWORD_FIELD.to_string(), has_password.into());
+    }
+    config
+}
+
+/// 创建只允许当前用户读写的文件（已存在时失败，避免覆盖其他进程刚生成的密钥）
+fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_key_path() -> PathBuf {
+        std::env::temp_dir().join(format!("windchime-test-{}.db.key", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_round_trip_and_key_file() {
+        let path = temp_key_path();
+        let secrets = SecretBox::load_or_create(&path).unwrap();
+        let encrypted = secrets.encrypt("p@ss wörd").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("p@ss"));
+        // 每次加密使用不同的随机数
+        assert_ne!(encrypted, secrets.encrypt("p@ss wörd").unwrap());
+        assert_eq!(secrets.decrypt(&encrypted).unwrap(), "p@ss wörd");
+
+        // 重新加载同一密钥文件仍可解密，其他密钥不能
+        let reloaded = SecretBox::load_or_create(&path).unwrap();
+        assert_eq!(reloaded.decrypt(&encrypted).unwrap(), "p@ss wörd");
+        assert!(SecretBox::ephemeral().unwrap().decrypt(&encrypted).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_config_encryption_keeps_stored_password() {
+        let secrets = SecretBox::ephemeral().unwrap();
+        let stored = secrets
+            .encrypt_config(r#"{"url":"https://dav.example.com","username":"wind","password":"secret"}"#, None)
+            .unwrap();
+        assert!(!stored.contains("secret"));
+        let decrypted: serde_json::Value = serde_json::from_str(&secrets.decrypt_config(&stored).unwrap()).unwrap();
+        assert_eq!(decrypted["password"], "secret");
+        assert_eq!(decrypted["username"], "wind");
+
+        // 前端编辑时未回传密码：沿用已保存的密码
+        let updated = secrets
+            .encrypt_config(r#"{"url":"https://nas.local","username":"wind","has_password":true}"#, Some(&stored))
+            .unwrap();
+        let decrypted: serde_json::Value = serde_json::from_str(&secrets.decrypt_config(&updated).unwrap()).unwrap();
+        assert_eq!(decrypted["password"], "secret");
+        assert_eq!(decrypted["url"], "https://nas.local");
+        assert!(decrypted.get(HAS_PASSWORD_FIELD).is_none());
+
+        let redacted = redact_config(&updated);
+        assert!(redacted.get(PASSWORD_FIELD).is_none());
+        assert_eq!(redacted[HAS_PASSWORD_FIELD], true);
+    }
+}
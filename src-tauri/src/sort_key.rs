@@ -0,0 +1,247 @@
+// 排序键和拼音/罗马字转写
+//
+// 直接 ORDER BY 原始文本时按字节序排列，中日文全部排在拉丁字母之后。
+// 这里为标题、艺术家、专辑计算排序键：汉字转为不带声调的拼音，假名转为罗马字（平文式），
+// 其余字符做兼容分解后去掉附加符号并转小写。于是 "周杰伦" 排在 Z 开头的艺术家中间，
+// "Édith" 与 "edith" 相邻，全角字母与半角字母相同。日文汉字同样按汉语拼音排序。
+//
+// 同样的转写（连写、首字母、各音节）写入全文索引的 translit 列，输入 "zhoujielun" 或 "zjl" 也能搜到 周杰伦。
+// 转写会让索引变大一些，可以在设置中关闭（library.transliterate_search）。
+
+use pinyin::ToPinyin;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+/// 是否把转写写入全文索引（"true" / "false"，默认开启）
+pub const SETTING_TRANSLITERATE_SEARCH: &str = "library.transliterate_search";
+
+/// 曲目的排序键和转写
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackSortKeys {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// 标题、艺术家、专辑、专辑艺术家中的中日文转写；没有中日文或未开启转写搜索时为 None
+    pub translit: Option<String>,
+}
+
+impl TrackSortKeys {
+    pub fn compute(
+        title: Option<&str>,
+        artist: Option<&str>,
+        album: Option<&str>,
+        album_artist: Option<&str>,
+        transliterate: bool,
+    ) -> Self {
+        let translit = if transliterate {
+            let words: Vec<String> = [title, artist, album, album_artist]
+                .into_iter()
+                .flatten()
+                .filter_map(search_transliteration)
+                .collect();
+            (!words.is_empty()).then(|| words.join(" "))
+        } else {
+            None
+        };
+        Self {
+            title: title.map(sort_key),
+            artist: artist.map(sort_key),
+            album: album.map(sort_key),
+            translit,
+        }
+    }
+}
+
+/// 文本的排序键，如 "周杰伦" → "zhou jie lun"、"Beyoncé" → "beyonce"
+pub fn sort_key(text: &str) -> String {
+    let mut key = String::with_capacity(text.len());
+    let mut kana = String::new();
+    for c in text.nfkc() {
+        if is_kana(c) {
+            kana.push(c);
+            continue;
+        }
+        if !kana.is_empty() {
+            push_word(&mut key, &kana_to_romaji(&kana));
+            kana.clear();
+        }
+        match c.to_pinyin() {
+            Some(pinyin) => push_word(&mut key, pinyin.plain()),
+            None => decompose_canonical(c, |d| {
+                if !is_combining_mark(d) {
+                    key.extend(d.to_lowercase());
+                }
+            }),
+        }
+    }
+    if !kana.is_empty() {
+        push_word(&mut key, &kana_to_romaji(&kana));
+    }
+    key.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 全文索引用的转写：每段连续的中日文生成连写、首字母和各音节，如 "周杰伦" → "zhoujielun zjl zhou jie lun"；
+/// 没有中日文时返回 None
+pub fn search_transliteration(text: &str) -> Option<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut syllables: Vec<String> = Vec::new();
+    let mut kana = String::new();
+    for c in text.nfkc() {
+        if is_kana(c) {
+            kana.push(c);
+            continue;
+        }
+        if !kana.is_empty() {
+            syllables.push(kana_to_romaji(&kana));
+            kana.clear();
+        }
+        match c.to_pinyin() {
+            Some(pinyin) => syllables.push(pinyin.plain().to_string()),
+            None => flush_run(&mut words, &mut syllables),
+        }
+    }
+    if !kana.is_empty() {
+        syllables.push(kana_to_romaji(&kana));
+    }
+    flush_run(&mut words, &mut syllables);
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+fn flush_run(words: &mut Vec<String>, syllables: &mut Vec<String>) {
+    match syllables.len() {
+        0 => {}
+        1 => words.append(syllables),
+        _ => {
+            words.push(syllables.concat());
+            words.push(syllables.iter().filter_map(|s| s.chars().next()).collect());
+            words.append(syllables);
+        }
+    }
+}
+
+fn push_word(key: &mut String, word: &str) {
+    key.push(' ');
+    key.push_str(word);
+    key.push(' ');
+}
+
+/// 平假名 U+3041（ぁ）到 U+3096（ゖ）的罗马字，っ 单独处理
+const HIRAGANA_ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o",
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do",
+    "na", "ni", "nu", "ne", "no",
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po",
+    "ma", "mi", "mu", "me", "mo",
+    "ya", "ya", "yu", "yu", "yo", "yo",
+    "ra", "ri", "ru", "re", "ro",
+    "wa", "wa", "i", "e", "o", "n",
+    "vu", "ka", "ke",
+];
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30F6}' | 'ー')
+}
+
+/// 片假名转为对应的平假名
+fn to_hiragana(c: char) -> char {
+    match c {
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiou".contains(c)
+}
+
+/// 假名转为罗马字：拗音（きゃ → kya、しゃ → sha）、促音（っ 重复下一个辅音）、长音（ー 重复前一个元音）
+fn kana_to_romaji(kana: &str) -> String {
+    let mut romaji = String::with_capacity(kana.len());
+    let mut sokuon = false;
+    for c in kana.chars().map(to_hiragana) {
+        match c {
+            'ー' => {
+                if let Some(vowel) = romaji.chars().next_back().filter(|v| is_vowel(*v)) {
+                    romaji.push(vowel);
+                }
+                continue;
+            }
+            'っ' => {
+                sokuon = true;
+                continue;
+            }
+            'ゃ' | 'ゅ' | 'ょ' if romaji.ends_with('i') && romaji.len() > 1 => {
+                romaji.pop();
+                if !(romaji.ends_with("sh") || romaji.ends_with("ch") || romaji.ends_with('j')) {
+                    romaji.push('y');
+                }
+                romaji.push(match c {
+                    'ゃ' => 'a',
+                    'ゅ' => 'u',
+                    _ => 'o',
+                });
+                continue;
+            }
+            _ => {}
+        }
+        let syllable = HIRAGANA_ROMAJI[(c as u32 - 0x3041) as usize];
+        if std::mem::take(&mut sokuon) {
+            if let Some(consonant) = syllable.chars().next().filter(|c| !is_vowel(*c)) {
+                romaji.push(consonant);
+            }
+        }
+        romaji.push_str(syllable);
+    }
+    romaji
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_key() {
+        assert_eq!(sort_key("周杰伦"), "zhou jie lun");
+        assert_eq!(sort_key("Beyoncé"), "beyonce");
+        assert_eq!(sort_key("ＡＢＣ  Song"), "abc song");
+        assert_eq!(sort_key("陈奕迅 - 十年"), "chen yi xun - shi nian");
+        assert_eq!(sort_key("ヨルシカ"), "yorushika");
+
+        // 中文与拉丁字母按字母顺序混排
+        let mut artists = vec!["周杰伦", "Zara", "Adele", "陈奕迅", "Émilie"];
+        artists.sort_by_key(|a| sort_key(a));
+        assert_eq!(artists, vec!["Adele", "陈奕迅", "Émilie", "Zara", "周杰伦"]);
+    }
+
+    #[test]
+    fn test_kana_to_romaji() {
+        assert_eq!(kana_to_romaji("ありがとう"), "arigatou");
+        assert_eq!(kana_to_romaji("きょうしゃ"), "kyousha");
+        assert_eq!(kana_to_romaji("ちゃっと"), "chatto");
+        assert_eq!(kana_to_romaji("ラーメン"), "raamen");
+        assert_eq!(kana_to_romaji("ゃ"), "ya");
+    }
+
+    #[test]
+    fn test_search_transliteration() {
+        assert_eq!(search_transliteration("周杰伦").as_deref(), Some("zhoujielun zjl zhou jie lun"));
+        assert_eq!(search_transliteration("晴天 (Live)").as_deref(), Some("qingtian qt qing tian"));
+        assert_eq!(search_transliteration("爱"), Some("ai".to_string()));
+        assert_eq!(search_transliteration("Adele"), None);
+    }
+
+    #[test]
+    fn test_track_sort_keys() {
+        let keys = TrackSortKeys::compute(Some("晴天"), Some("周杰伦"), None, Some("Jay Chou"), true);
+        assert_eq!(keys.title.as_deref(), Some("qing tian"));
+        assert_eq!(keys.artist.as_deref(), Some("zhou jie lun"));
+        assert_eq!(keys.album, None);
+        assert_eq!(keys.translit.as_deref(), Some("qingtian qt qing tian zhoujielun zjl zhou jie lun"));
+
+        let keys = TrackSortKeys::compute(Some("晴天"), None, None, None, false);
+        assert_eq!(keys.translit, None);
+    }
+}
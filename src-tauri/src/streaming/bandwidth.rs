@@ -0,0 +1,377 @@
+// 带宽限制
+//
+// 所有远程传输（流式播放、预加载/后台缓存、同步）共用一个令牌桶：
+// - 全局上限（KB/s，0表示不限速），按流量类别优先级分配
+// - 检测到按流量计费的网络时自动使用更低的上限
+// - 每收到一块数据前先向令牌桶申请额度，优先级更高的类别在等待时低优先级类别让行
+// - 按类别统计最近几秒的吞吐量
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 可设置的最大上限（KB/s）
+pub const MAX_LIMIT_KBPS: u32 = 1_000_000;
+
+/// 吞吐量统计窗口
+const USAGE_WINDOW: Duration = Duration::from_secs(3);
+
+/// 单次等待的范围，避免忙等也避免设置变化后迟迟不生效
+const MIN_WAIT: Duration = Duration::from_millis(5);
+const MAX_WAIT: Duration = Duration::from_millis(100);
+
+/// 计费网络检测间隔
+pub const METERED_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 流量类别，按优先级从高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficClass {
+    /// 正在播放的流
+    Playback,
+    /// 预加载和后台缓存下载
+    Prefetch,
+    /// 同步上传/下载
+    Sync,
+}
+
+impl TrafficClass {
+    const COUNT: usize = 3;
+
+    fn index(self) -> usize {
+        match self {
+            TrafficClass::Playback => 0,
+            TrafficClass::Prefetch => 1,
+            TrafficClass::Sync => 2,
+        }
+    }
+}
+
+/// 带宽设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BandwidthSettings {
+    /// 全局上限（KB/s），0表示不限速
+    pub limit_kbps: u32,
+    /// 计费网络下的上限（KB/s），0表示与全局上限相同
+    pub metered_limit_kbps: u32,
+}
+
+impl BandwidthSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit_kbps > MAX_LIMIT_KBPS || self.metered_limit_kbps > MAX_LIMIT_KBPS {
+            return Err(format!("带宽上限不能超过 {} KB/s", MAX_LIMIT_KBPS));
+        }
+        Ok(())
+    }
+
+    /// 当前生效的上限（KB/s），0表示不限速
+    pub fn effective_limit_kbps(&self, metered: bool) -> u32 {
+        match (metered, self.limit_kbps, self.metered_limit_kbps) {
+            (false, limit, _) | (true, limit, 0) => limit,
+            (true, 0, metered_limit) => metered_limit,
+            (true, limit, metered_limit) => limit.min(metered_limit),
+        }
+    }
+}
+
+/// 各类别最近的吞吐量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BandwidthUsage {
+    pub playback_kbps: f64,
+    pub prefetch_kbps: f64,
+    pub sync_kbps: f64,
+    /// 当前生效的上限（KB/s），0表示不限速
+    pub limit_kbps: u32,
+    /// 是否为计费网络，无法检测时为None
+    pub metered: Option<bool>,
+}
+
+struct BucketState {
+    settings: BandwidthSettings,
+    metered: Option<bool>,
+    /// 可用额度（字节），允许为负（先用后还）
+    tokens: f64,
+    last_refill: Instant,
+    /// 各类别正在等待额度的任务数
+    waiting: [usize; TrafficClass::COUNT],
+    /// 各类别最近传输的字节数
+    usage: [VecDeque<(Instant, u64)>; TrafficClass::COUNT],
+}
+
+impl BucketState {
+    fn limit_bytes_per_sec(&self) -> f64 {
+        self.settings.effective_limit_kbps(self.metered == Some(true)) as f64 * 1024.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let rate = self.limit_bytes_per_sec();
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        // 最多积攒一秒的额度
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last_refill = now;
+    }
+
+    fn record(&mut self, class: TrafficClass, bytes: u64, now: Instant) {
+        let usage = &mut self.usage[class.index()];
+        usage.push_back((now, bytes));
+        Self::prune(usage, now);
+    }
+
+    fn prune(usage: &mut VecDeque<(Instant, u64)>, now: Instant) {
+        while usage.front().is_some_and(|(at, _)| now.saturating_duration_since(*at) > USAGE_WINDOW) {
+            usage.pop_front();
+        }
+    }
+
+    fn kbps(&mut self, class: TrafficClass, now: Instant) -> f64 {
+        let usage = &mut self.usage[class.index()];
+        Self::prune(usage, now);
+        let bytes: u64 = usage.iter().map(|(_, bytes)| bytes).sum();
+        bytes as f64 / 1024.0 / USAGE_WINDOW.as_secs_f64()
+    }
+}
+
+/// 全局带宽管理器
+pub struct BandwidthManager {
+    state: Mutex<BucketState>,
+}
+
+static MANAGER: Lazy<Arc<BandwidthManager>> = Lazy::new(|| Arc::new(BandwidthManager::new()));
+
+/// 全局带宽管理器
+pub fn manager() -> Arc<BandwidthManager> {
+    Arc::clone(&MANAGER)
+}
+
+/// 等待额度期间登记在 waiting 中，任务被取消时也会撤销
+struct WaitingGuard<'a> {
+    manager: &'a BandwidthManager,
+    class: TrafficClass,
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.manager.state.lock().waiting[self.class.index()] -= 1;
+    }
+}
+
+impl BandwidthManager {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                settings: BandwidthSettings::default(),
+                metered: None,
+                tokens: 0.0,
+                last_refill: Instant::now(),
+                waiting: [0; TrafficClass::COUNT],
+                usage: Default::default(),
+            }),
+        }
+    }
+
+    pub fn settings(&self) -> BandwidthSettings {
+        self.state.lock().settings
+    }
+
+    pub fn set_settings(&self, settings: BandwidthSettings) {
+        let mut state = self.state.lock();
+        if state.settings != settings {
+            log::info!(
+                "📶 带宽上限: {} KB/s，计费网络: {} KB/s",
+                settings.limit_kbps, settings.metered_limit_kbps
+            );
+        }
+        state.settings = settings;
+        // 上限变化后已积攒的额度不再有效
+        state.tokens = state.tokens.min(state.limit_bytes_per_sec());
+    }
+
+    /// 更新计费网络检测结果
+    pub fn set_metered(&self, metered: Option<bool>) {
+        let mut state = self.state.lock();
+        if state.metered != metered {
+            log::info!("📶 计费网络: {:?}", metered);
+            state.metered = metered;
+            state.tokens = state.tokens.min(state.limit_bytes_per_sec());
+        }
+    }
+
+    /// 申请传输 bytes 字节的额度，超出上限时等待
+    pub async fn acquire(&self, class: TrafficClass, bytes: usize) {
+        let mut guard: Option<WaitingGuard> = None;
+        loop {
+            let wait = {
+                let mut state = self.state.lock();
+                let now = Instant::now();
+                let rate = state.limit_bytes_per_sec();
+                if rate <= 0.0 {
+                    state.record(class, bytes as u64, now);
+                    None
+                } else {
+                    state.refill(now);
+                    // 自己已登记的不算在内
+                    let higher_waiting = state.waiting[..class.index()].iter().any(|&n| n > 0);
+                    if !higher_waiting && state.tokens > 0.0 {
+                        state.tokens -= bytes as f64;
+                        state.record(class, bytes as u64, now);
+                        None
+                    } else {
+                        if guard.is_none() {
+                            state.waiting[class.index()] += 1;
+                        }
+                        let deficit = (-state.tokens).max(0.0) + 1.0;
+                        Some(Duration::from_secs_f64(deficit / rate).clamp(MIN_WAIT, MAX_WAIT))
+                    }
+                }
+            };
+
+            match wait {
+                None => return,
+                Some(wait) => {
+                    if guard.is_none() {
+                        guard = Some(WaitingGuard { manager: self, class });
+                    }
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    /// 各类别最近的吞吐量和当前上限
+    pub fn usage(&self) -> BandwidthUsage {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        BandwidthUsage {
+            playback_kbps: state.kbps(TrafficClass::Playback, now),
+            prefetch_kbps: state.kbps(TrafficClass::Prefetch, now),
+            sync_kbps: state.kbps(TrafficClass::Sync, now),
+            limit_kbps: state.settings.effective_limit_kbps(state.metered == Some(true)),
+            metered: state.metered,
+        }
+    }
+}
+
+impl Default for BandwidthManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 检测当前网络是否按流量计费，无法检测时返回None
+///
+/// Linux 通过 NetworkManager 的 Metered 属性检测，其他平台暂不支持。
+pub fn detect_metered() -> Option<bool> {
+    #[cfg(all(feature = "media-session", target_os = "linux"))]
+    {
+        detect_metered_networkmanager()
+    }
+    #[cfg(not(all(feature = "media-session", target_os = "linux")))]
+    {
+        None
+    }
+}
+
+#[cfg(all(feature = "media-session", target_os = "linux"))]
+fn detect_metered_networkmanager() -> Option<bool> {
+    let connection = zbus::blocking::Connection::system().ok()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+    )
+    .ok()?;
+    let metered: u32 = proxy.get_property("Metered").ok()?;
+    // NMMetered: 0=未知 1=是 2=否 3=推测是 4=推测否
+    match metered {
+        1 | 3 => Some(true),
+        2 | 4 => Some(false),
+        _ => None,
+    }
+}
+
+/// 定期检测计费网络并更新全局带宽管理器
+pub fn spawn_metered_monitor() {
+    tokio::spawn(async {
+        loop {
+            match tokio::task::spawn_blocking(detect_metered).await {
+                Ok(metered) => manager().set_metered(metered),
+                Err(e) => log::warn!("检测计费网络失败: {}", e),
+            }
+            tokio::time::sleep(METERED_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_limit() {
+        let settings = BandwidthSettings { limit_kbps: 1000, metered_limit_kbps: 200 };
+        assert_eq!(settings.effective_limit_kbps(false), 1000);
+        assert_eq!(settings.effective_limit_kbps(true), 200);
+        assert_eq!(BandwidthSettings { limit_kbps: 0, metered_limit_kbps: 200 }.effective_limit_kbps(true), 200);
+        assert_eq!(BandwidthSettings { limit_kbps: 100, metered_limit_kbps: 0 }.effective_limit_kbps(true), 100);
+        assert_eq!(BandwidthSettings::default().effective_limit_kbps(true), 0);
+        assert!(BandwidthSettings { limit_kbps: MAX_LIMIT_KBPS + 1, metered_limit_kbps: 0 }.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_throttles_and_records_usage() {
+        let manager = BandwidthManager::new();
+        // 不限速时立即返回
+        manager.acquire(TrafficClass::Sync, 1024 * 1024).await;
+        assert!(manager.usage().sync_kbps > 0.0);
+
+        manager.set_settings(BandwidthSettings { limit_kbps: 100, metered_limit_kbps: 0 });
+        let started = Instant::now();
+        for _ in 0..4 {
+            manager.acquire(TrafficClass::Prefetch, 25 * 1024).await;
+        }
+        // 100KB/s 下传输 100KB 至少需要约0.75秒（首块几乎不等待）
+        assert!(started.elapsed() >= Duration::from_millis(700), "{:?}", started.elapsed());
+        let usage = manager.usage();
+        assert_eq!(usage.limit_kbps, 100);
+        assert!(usage.prefetch_kbps > 0.0);
+
+        manager.set_metered(Some(true));
+        manager.set_settings(BandwidthSettings { limit_kbps: 100, metered_limit_kbps: 10 });
+        assert_eq!(manager.usage().limit_kbps, 10);
+        assert_eq!(manager.usage().metered, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_playback_is_served_before_background() {
+        let manager = Arc::new(BandwidthManager::new());
+        manager.set_settings(BandwidthSettings { limit_kbps: 50, metered_limit_kbps: 0 });
+        // 先把额度用成负数，两个任务都需要等待
+        manager.acquire(TrafficClass::Sync, 0).await;
+        manager.state.lock().tokens = -25.0 * 1024.0;
+
+        let background = {
+            let manager = Arc::clone(&manager);
+            tokio::spawn(async move {
+                manager.acquire(TrafficClass::Sync, 10 * 1024).await;
+                Instant::now()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let playback = {
+            let manager = Arc::clone(&manager);
+            tokio::spawn(async move {
+                manager.acquire(TrafficClass::Playback, 10 * 1024).await;
+                Instant::now()
+            })
+        };
+
+        let playback_done = playback.await.unwrap();
+        let background_done = background.await.unwrap();
+        assert!(playback_done <= background_done);
+        assert_eq!(manager.state.lock().waiting, [0; TrafficClass::COUNT]);
+    }
+}
@@ -0,0 +1,275 @@
+// 流式播放缓冲
+//
+// - 可设置的水位线：开始播放前的初始缓冲、触发重新缓冲的低水位、恢复播放的高水位
+// - 播放中缓冲低于低水位且下载未完成时暂停，缓冲到高水位后恢复，期间上报缓冲进度
+// - 连接中断时从已收到的位置按Range请求重连，重连次数有上限
+// - 统计当前流的缓冲量、吞吐量、重新缓冲和重连次数
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 水位线的最大值（KB）
+pub const MAX_WATERMARK_KB: u32 = 64 * 1024;
+
+/// 重连次数的最大值
+pub const MAX_RECONNECTS: u32 = 100;
+
+/// 吞吐量统计窗口
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(3);
+
+/// 流式播放缓冲设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamBufferSettings {
+    /// 开始播放前等待的初始缓冲（KB）
+    pub initial_kb: u32,
+    /// 播放中缓冲低于该值时暂停重新缓冲（KB），0表示不自动暂停
+    pub low_watermark_kb: u32,
+    /// 重新缓冲到该值后恢复播放（KB）
+    pub high_watermark_kb: u32,
+    /// 连接中断后的最大重连次数
+    pub max_reconnects: u32,
+}
+
+impl Default for StreamBufferSettings {
+    fn default() -> Self {
+        Self {
+            initial_kb: 256,
+            low_watermark_kb: 64,
+            high_watermark_kb: 512,
+            max_reconnects: 10,
+        }
+    }
+}
+
+impl StreamBufferSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_kb > MAX_WATERMARK_KB || self.high_watermark_kb > MAX_WATERMARK_KB {
+            return Err(format!("缓冲大小不能超过 {} KB", MAX_WATERMARK_KB));
+        }
+        if self.low_watermark_kb >= self.high_watermark_kb {
+            return Err("低水位必须小于高水位".to_string());
+        }
+        if self.max_reconnects > MAX_RECONNECTS {
+            return Err(format!("重连次数不能超过 {}", MAX_RECONNECTS));
+        }
+        Ok(())
+    }
+
+    pub fn initial_bytes(&self) -> usize {
+        self.initial_kb as usize * 1024
+    }
+
+    pub fn low_watermark_bytes(&self) -> usize {
+        self.low_watermark_kb as usize * 1024
+    }
+
+    pub fn high_watermark_bytes(&self) -> usize {
+        self.high_watermark_kb as usize * 1024
+    }
+}
+
+static SETTINGS: Lazy<RwLock<StreamBufferSettings>> = Lazy::new(|| RwLock::new(StreamBufferSettings::default()));
+
+/// 当前的缓冲设置（新建的流和下一次缓冲检查生效）
+pub fn settings() -> StreamBufferSettings {
+    *SETTINGS.read()
+}
+
+pub fn set_settings(settings: StreamBufferSettings) {
+    log::info!(
+        "🌊 流式缓冲: 初始{}KB, 低水位{}KB, 高水位{}KB, 最多重连{}次",
+        settings.initial_kb, settings.low_watermark_kb, settings.high_watermark_kb, settings.max_reconnects
+    );
+    *SETTINGS.write() = settings;
+}
+
+/// 当前流的缓冲状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferLevel {
+    /// 已下载未读取的字节数
+    pub buffered: usize,
+    /// 下载已结束（完成或出错），不会再有新数据
+    pub complete: bool,
+}
+
+/// 当前流的统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamStats {
+    /// 已下载未读取的字节数
+    pub bytes_buffered: u64,
+    /// 本次播放累计下载的字节数
+    pub bytes_received: u64,
+    /// 文件大小，未知时为None
+    pub file_size: Option<u64>,
+    /// 最近几秒的下载速度（KB/s）
+    pub throughput_kbps: f64,
+    /// 播放中因缓冲不足暂停的次数
+    pub rebuffer_count: u32,
+    /// 连接中断后的重连次数
+    pub reconnect_count: u32,
+    /// 是否正在重新缓冲
+    pub buffering: bool,
+    /// 下载是否已结束
+    pub complete: bool,
+}
+
+/// 按时间窗口统计下载速度
+#[derive(Debug, Default)]
+pub struct ThroughputMeter {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ThroughputMeter {
+    pub fn record(&mut self, bytes: u64, now: Instant) {
+        self.samples.push_back((now, bytes));
+        self.prune(now);
+    }
+
+    pub fn kbps(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        let bytes: u64 = self.samples.iter().map(|(_, bytes)| bytes).sum();
+        bytes as f64 / 1024.0 / THROUGHPUT_WINDOW.as_secs_f64()
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self.samples.front().is_some_and(|(at, _)| now.saturating_duration_since(*at) > THROUGHPUT_WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// 重新缓冲的状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebufferAction {
+    /// 缓冲不足，暂停播放（当前进度%）
+    Started(u8),
+    /// 缓冲进度变化（%）
+    Progress(u8),
+    /// 缓冲完成；resume 为 false 表示用户在缓冲期间暂停了播放，保持暂停
+    Finished { resume: bool },
+}
+
+/// 重新缓冲控制：低于低水位时暂停，达到高水位或下载结束后恢复
+#[derive(Debug, Default)]
+pub struct Rebuffer {
+    active: bool,
+    percent: u8,
+    resume: bool,
+    count: u32,
+}
+
+impl Rebuffer {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// 本次播放中重新缓冲的次数
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// 用户在重新缓冲期间暂停或恢复，缓冲完成后按此决定是否继续播放
+    pub fn set_resume(&mut self, resume: bool) {
+        self.resume = resume;
+    }
+
+    /// 根据缓冲状态更新，playing 为当前是否正在播放
+    pub fn update(&mut self, level: BufferLevel, playing: bool, settings: &StreamBufferSettings) -> Option<RebufferAction> {
+        let high = settings.high_watermark_bytes().max(1);
+        let percent = (level.buffered.min(high) * 100 / high) as u8;
+
+        if !self.active {
+            if !playing || level.complete || level.buffered >= settings.low_watermark_bytes() {
+                return None;
+            }
+            self.active = true;
+            self.resume = true;
+            self.percent = percent;
+            self.count += 1;
+            return Some(RebufferAction::Started(percent));
+        }
+
+        if level.complete || level.buffered >= high {
+            self.active = false;
+            return Some(RebufferAction::Finished { resume: self.resume });
+        }
+        if percent != self.percent {
+            self.percent = percent;
+            return Some(RebufferAction::Progress(percent));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(kb: usize, complete: bool) -> BufferLevel {
+        BufferLevel { buffered: kb * 1024, complete }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(StreamBufferSettings::default().validate().is_ok());
+        let inverted = StreamBufferSettings { low_watermark_kb: 512, high_watermark_kb: 512, ..Default::default() };
+        assert!(inverted.validate().is_err());
+        let huge = StreamBufferSettings { high_watermark_kb: MAX_WATERMARK_KB + 1, ..Default::default() };
+        assert!(huge.validate().is_err());
+        let retries = StreamBufferSettings { max_reconnects: MAX_RECONNECTS + 1, ..Default::default() };
+        assert!(retries.validate().is_err());
+    }
+
+    #[test]
+    fn test_rebuffer_pauses_below_low_and_resumes_at_high() {
+        let settings = StreamBufferSettings::default();
+        let mut rebuffer = Rebuffer::default();
+
+        assert_eq!(rebuffer.update(level(100, false), true, &settings), None);
+        // 暂停中缓冲不足不触发
+        assert_eq!(rebuffer.update(level(10, false), false, &settings), None);
+
+        assert_eq!(rebuffer.update(level(32, false), true, &settings), Some(RebufferAction::Started(6)));
+        assert!(rebuffer.is_active());
+        assert_eq!(rebuffer.update(level(32, false), false, &settings), None);
+        assert_eq!(rebuffer.update(level(256, false), false, &settings), Some(RebufferAction::Progress(50)));
+        // 高于低水位但未到高水位时继续等待
+        assert_eq!(rebuffer.update(level(300, false), false, &settings), Some(RebufferAction::Progress(58)));
+        assert_eq!(rebuffer.update(level(512, false), false, &settings), Some(RebufferAction::Finished { resume: true }));
+        assert!(!rebuffer.is_active());
+        assert_eq!(rebuffer.count(), 1);
+    }
+
+    #[test]
+    fn test_rebuffer_finishes_when_download_complete() {
+        let settings = StreamBufferSettings::default();
+        let mut rebuffer = Rebuffer::default();
+
+        // 下载已结束时剩余的数据直接播完，不再等待
+        assert_eq!(rebuffer.update(level(1, true), true, &settings), None);
+
+        assert!(matches!(rebuffer.update(level(1, false), true, &settings), Some(RebufferAction::Started(_))));
+        rebuffer.set_resume(false);
+        assert_eq!(rebuffer.update(level(8, true), false, &settings), Some(RebufferAction::Finished { resume: false }));
+    }
+
+    #[test]
+    fn test_zero_low_watermark_disables_rebuffer() {
+        let settings = StreamBufferSettings { low_watermark_kb: 0, ..Default::default() };
+        let mut rebuffer = Rebuffer::default();
+        assert_eq!(rebuffer.update(level(0, false), true, &settings), None);
+    }
+
+    #[test]
+    fn test_throughput_window() {
+        let mut meter = ThroughputMeter::default();
+        let start = Instant::now();
+        meter.record(3 * 1024 * 1024, start);
+        assert!((meter.kbps(start) - 1024.0).abs() < 0.01);
+        assert_eq!(meter.kbps(start + THROUGHPUT_WINDOW + Duration::from_millis(1)), 0.0);
+    }
+}
@@ -0,0 +1,254 @@
+// WebDAV后台完整下载
+//
+// 流式播放开始后在后台把整首曲目下载到缓存目录，解码后交给PlaybackActor用于秒速跳转：
+// - 延迟几秒启动并限速，不与流式播放争抢带宽
+// - 切歌时通过 CancellationToken 取消
+// - 下载结果交给 CacheManager 登记到 remote_cache 表，下次播放同一曲目直接读取本地文件
+
+use crate::cache::CacheStrategy;
+use crate::player::audio::AudioDecoder;
+use crate::webdav::types::WebDAVConfig;
+use crate::webdav::WebDAVClient;
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// 流式播放开始后延迟多久再启动完整下载
+pub const START_DELAY: Duration = Duration::from_secs(5);
+
+/// 后台下载限速（字节/秒）
+pub const RATE_LIMIT_BYTES_PER_SEC: u64 = 2 * 1024 * 1024;
+
+/// 每解码多少个采样检查一次取消标志
+const DECODE_CANCEL_CHECK: usize = 1 << 16;
+
+/// webdav:// 路径对应的服务器和远程文件
+pub struct WebDavTarget {
+    pub server_id: String,
+    pub remote_path: String,
+    pub config: WebDAVConfig,
+}
+
+/// 解码后的完整曲目样本
+pub struct DecodedSamples {
+    pub samples: Arc<[i16]>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// 解析 webdav://server_id#/path/to/file.flac 并从数据库加载服务器配置
+pub fn resolve_webdav_track(track_path: &str) -> Result<WebDavTarget> {
+    let path_without_prefix = track_path.strip_prefix("webdav://")
+        .ok_or_else(|| anyhow!("无效的WEBDAV路径"))?;
+
+    let (server_id, remote_path) = path_without_prefix.split_once('#')
+        .ok_or_else(|| anyhow!("WEBDAV路径格式错误"))?;
+
+    let db = crate::DB.get().ok_or_else(|| anyhow!("数据库未初始化"))?;
+    let servers = db.with(|db| db.get_remote_servers())
+        .map_err(|e| anyhow!("获取服务器列表失败: {}", e))?;
+
+    let server_config = servers.iter()
+        .find(|(id, _, server_type, _, _)| id == server_id && server_type == "webdav")
+        .ok_or_else(|| anyhow!("找不到WEBDAV服务器: {}", server_id))?;
+
+    let config: WebDAVConfig = serde_json::from_str(&server_config.3)
+        .map_err(|e| anyhow!("解析配置失败: {}", e))?;
+
+    Ok(WebDavTarget {
+        server_id: server_id.to_string(),
+        remote_path: remote_path.to_string(),
+        config,
+    })
+}
+
+/// 已缓存到本地的文件（缓存记录存在且文件仍在），命中时更新LRU访问记录
+pub fn cached_file_for(track_id: i64, track_path: &str) -> Option<PathBuf> {
+    let path = cached_file_path(track_path)?;
+    if let Some(manager) = crate::CACHE_MANAGER.get() {
+        manager.touch_cache(track_id);
+    }
+    Some(path)
+}
+
+/// 已缓存到本地的文件，不更新LRU访问记录（后台任务读取时使用）
+pub fn cached_file_path(track_path: &str) -> Option<PathBuf> {
+    let target = resolve_webdav_track(track_path).ok()?;
+    let db = crate::DB.get()?;
+    let cached = db.lock().ok()?
+        .get_cache_entry(&target.server_id, &target.remote_path)
+        .ok()
+        .flatten()?;
+    let path = PathBuf::from(cached);
+    path.exists().then_some(path)
+}
+
+/// 通知缓存管理器当前正在播放的缓存文件，避免被清理
+pub fn mark_playing(path: Option<&Path>) {
+    if let Some(manager) = crate::CACHE_MANAGER.get() {
+        manager.set_playing(path);
+    }
+}
+
+/// 缓存文件路径：缓存目录/webdav/<md5>.<扩展名>
+pub fn cache_file_path(cache_dir: &Path, server_id: &str, remote_path: &str) -> PathBuf {
+    let digest = md5::compute(format!("{}#{}", server_id, remote_path));
+    let extension = Path::new(remote_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("bin")
+        .to_lowercase();
+    cache_dir.join("webdav").join(format!("{:x}.{}", digest, extension))
+}
+
+/// 按限速计算还需等待的时间
+pub fn throttle_delay(bytes: u64, elapsed: Duration, rate: u64) -> Option<Duration> {
+    let expected = Duration::from_secs_f64(bytes as f64 / rate as f64);
+    expected.checked_sub(elapsed).filter(|d| !d.is_zero())
+}
+
+/// 获取曲目的完整样本：优先读取本地缓存，否则延迟启动限速下载并写入缓存
+///
+/// 被取消时返回 None。
+pub async fn fetch_samples(track_id: i64, track_path: &str, cancel: &CancellationToken) -> Result<Option<DecodedSamples>> {
+    let data = match cached_file_for(track_id, track_path) {
+        Some(path) => {
+            log::info!("💾 使用本地缓存: {:?}", path);
+            Arc::new(tokio::fs::read(&path).await?)
+        }
+        None => {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(None),
+                _ = tokio::time::sleep(START_DELAY) => {}
+            }
+            let target = resolve_webdav_track(track_path)?;
+            match download_to_cache(track_id, &target, cancel).await? {
+                Some(data) => data,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let cancel = cancel.clone();
+    let hint = PathBuf::from(track_path);
+    tokio::task::spawn_blocking(move || decode_samples(&hint, data, &cancel))
+        .await
+        .map_err(|e| anyhow!("解码任务失败: {}", e))?
+}
+
+/// 限速下载整个文件并写入缓存目录和 remote_cache 表
+async fn download_to_cache(track_id: i64, target: &WebDavTarget, cancel: &CancellationToken) -> Result<Option<Arc<Vec<u8>>>> {
+    log::info!("⬇️ 后台下载WebDAV文件: {}", target.remote_path);
+    let client = WebDAVClient::new(target.config.clone())?;
+    let mut stream = Box::pin(client.download_stream(&target.remote_path).await?);
+
+    let started = Instant::now();
+    let mut data = Vec::new();
+    loop {
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => {
+                log::info!("⏹️ 后台下载已取消: {}", target.remote_path);
+                return Ok(None);
+            }
+            chunk = stream.next() => chunk,
+        };
+        match chunk {
+            Some(chunk) => {
+                let chunk = chunk?;
+                tokio::select! {
+                    _ = cancel.cancelled() => return Ok(None),
+                    _ = super::bandwidth::manager().acquire(super::TrafficClass::Prefetch, chunk.len()) => {}
+                }
+                data.extend_from_slice(&chunk);
+            }
+            None => break,
+        }
+
+        if let Some(delay) = throttle_delay(data.len() as u64, started.elapsed(), RATE_LIMIT_BYTES_PER_SEC) {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(None),
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    }
+    log::info!("✅ 后台下载完成: {:.2}MB ({}ms)", data.len() as f64 / 1024.0 / 1024.0, started.elapsed().as_millis());
+
+    // 写入缓存失败不影响本次跳转
+    if let Err(e) = write_cache_file(track_id, target, &data).await {
+        log::warn!("写入WebDAV缓存失败: {}", e);
+    }
+
+    Ok(Some(Arc::new(data)))
+}
+
+async fn write_cache_file(track_id: i64, target: &WebDavTarget, data: &[u8]) -> Result<()> {
+    let manager = crate::CACHE_MANAGER.get().ok_or_else(|| anyhow!("缓存管理器未初始化"))?;
+    let config = manager.get_config();
+    // 下载期间切换为仅流式播放时不再写入磁盘
+    if !config.enabled || config.strategy == CacheStrategy::StreamOnly {
+        return Ok(());
+    }
+
+    let path = cache_file_path(&config.cache_path, &target.server_id, &target.remote_path);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, data).await?;
+
+    // 空间不足（全部为固定缓存）时放弃本次缓存
+    if let Err(e) = manager.register_remote(track_id, &target.server_id, &target.remote_path, &path, data.len() as u64) {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(anyhow!(e));
+    }
+    Ok(())
+}
+
+/// 解码为交错样本
+fn decode_samples(hint: &Path, data: Arc<Vec<u8>>, cancel: &CancellationToken) -> Result<Option<DecodedSamples>> {
+    use rodio::Source;
+
+    let decoder = AudioDecoder::new(hint).decode_from_memory(data)?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+
+    let mut samples = Vec::new();
+    for (i, sample) in decoder.enumerate() {
+        if i % DECODE_CANCEL_CHECK == 0 && cancel.is_cancelled() {
+            return Ok(None);
+        }
+        samples.push(sample);
+    }
+
+    Ok(Some(DecodedSamples {
+        samples: samples.into(),
+        channels,
+        sample_rate,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_delay() {
+        let rate = 1024 * 1024;
+        // 1秒内下载了2MB，需要再等1秒
+        assert_eq!(throttle_delay(2 * rate, Duration::from_secs(1), rate), Some(Duration::from_secs(1)));
+        // 低于限速时不等待
+        assert_eq!(throttle_delay(rate / 2, Duration::from_secs(1), rate), None);
+    }
+
+    #[test]
+    fn test_cache_file_path_is_stable() {
+        let dir = Path::new("/cache");
+        let a = cache_file_path(dir, "srv", "/音乐/歌曲.FLAC");
+        assert_eq!(a, cache_file_path(dir, "srv", "/音乐/歌曲.FLAC"));
+        assert_ne!(a, cache_file_path(dir, "other", "/音乐/歌曲.FLAC"));
+        assert!(a.starts_with("/cache/webdav"));
+        assert_eq!(a.extension().unwrap(), "flac");
+    }
+}
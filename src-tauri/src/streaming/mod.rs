@@ -4,8 +4,15 @@
 // - HTTP流式传输（chunked encoding）
 // - 边接收边播放
 // - 零等待启动
+// - 后台完整下载并缓存（用于跳转和再次播放）
+// - 全局带宽限制（按流量类别优先级分配）
+// - 可设置的缓冲水位线、断流重新缓冲和断线重连
 
 pub mod simple_http_reader;
+pub mod full_download;
+pub mod bandwidth;
+pub mod buffering;
 
-pub use simple_http_reader::SimpleHttpReader;
+pub use simple_http_reader::{SimpleHttpReader, StreamMonitor};
+pub use bandwidth::TrafficClass;
 
@@ -1,5 +1,7 @@
 // HTTP streaming reader
 // Single GET request with chunked transfer encoding
+// Seeks outside the buffered window reopen the request with a Range header
+// A connection that drops or stalls mid-stream is reopened from the last received byte
 
 use bytes::Bytes;
 use reqwest::Client;
@@ -8,7 +10,16 @@ use std::io::{self, Read, Seek, SeekFrom};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use std::thread;
+use std::time::{Duration, Instant};
 use futures::StreamExt;
+use super::bandwidth::{self, TrafficClass};
+use super::buffering::{self, BufferLevel, StreamStats, ThroughputMeter};
+
+/// Time allowed to establish a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A body that delivers nothing for this long is treated as a dropped connection
+const STALL_TIMEOUT: Duration = Duration::from_secs(20);
 
 /// Buffer state
 struct BufferState {
@@ -21,10 +32,15 @@ struct BufferState {
     file_size: Option<u64>,  // Total file size if known
     current_offset: u64,  // Current file offset for seek support
     seek_requested: Option<u64>,  // Seek position requested
+    accepts_ranges: Option<bool>,  // Whether the server honours Range requests (None = unknown)
+    traffic_class: TrafficClass,  // Bandwidth priority of this stream
+    bytes_received: u64,  // Total bytes downloaded, including reconnects and seeks
+    reconnect_count: u32,  // Reconnects after a dropped or stalled connection
+    throughput: ThroughputMeter,
 }
 
 impl BufferState {
-    fn new() -> Self {
+    fn new(traffic_class: TrafficClass) -> Self {
         Self {
             chunks: VecDeque::new(),
             current_chunk_pos: 0,
@@ -35,6 +51,11 @@ impl BufferState {
             file_size: None,
             current_offset: 0,
             seek_requested: None,
+            accepts_ranges: None,
+            traffic_class,
+            bytes_received: 0,
+            reconnect_count: 0,
+            throughput: ThroughputMeter::default(),
         }
     }
     
@@ -55,6 +76,11 @@ impl BufferState {
         true
     }
     
+    fn record_received(&mut self, bytes: usize) {
+        self.bytes_received += bytes as u64;
+        self.throughput.record(bytes as u64, Instant::now());
+    }
+    
     fn read_bytes(&mut self, buf: &mut [u8]) -> usize {
         let mut total_read = 0;
         
@@ -81,6 +107,29 @@ impl BufferState {
         total_read
     }
     
+    /// Seek inside the buffered window by discarding bytes, without a new request
+    fn skip_buffered(&mut self, offset: u64) -> bool {
+        if offset < self.current_offset || offset > self.current_offset + self.total_buffered as u64 {
+            return false;
+        }
+        
+        let mut remaining = (offset - self.current_offset) as usize;
+        while remaining > 0 {
+            let chunk_left = self.chunks[0].len() - self.current_chunk_pos;
+            let n = remaining.min(chunk_left);
+            remaining -= n;
+            self.current_chunk_pos += n;
+            self.total_buffered -= n;
+            
+            if self.current_chunk_pos >= self.chunks[0].len() {
+                self.chunks.pop_front();
+                self.current_chunk_pos = 0;
+            }
+        }
+        self.current_offset = offset;
+        true
+    }
+    
     fn handle_seek(&mut self, offset: u64) {
         // Clear buffers
         self.chunks.clear();
@@ -94,6 +143,52 @@ impl BufferState {
     }
 }
 
+/// How a response body ended
+enum BodyEnd {
+    /// The server closed the body
+    Closed,
+    /// A seek outside the buffer restarts the request
+    Seek,
+    /// The connection failed or stalled
+    Failed(String),
+}
+
+/// Whether a body that closed normally stopped short of the known file size
+fn ended_early(offset: u64, file_size: Option<u64>) -> bool {
+    file_size.is_some_and(|size| offset < size)
+}
+
+/// Read-only view of a stream's buffer, kept by the player after the reader moves into the decoder
+#[derive(Clone)]
+pub struct StreamMonitor {
+    state: Arc<Mutex<BufferState>>,
+}
+
+impl StreamMonitor {
+    /// Bytes buffered ahead of the decoder and whether the download has ended
+    pub fn level(&self) -> BufferLevel {
+        let s = self.state.lock();
+        BufferLevel {
+            buffered: s.available(),
+            complete: s.eof,
+        }
+    }
+    
+    /// Download statistics; rebuffering is tracked by the player and left at zero here
+    pub fn stats(&self) -> StreamStats {
+        let mut s = self.state.lock();
+        StreamStats {
+            bytes_buffered: s.available() as u64,
+            bytes_received: s.bytes_received,
+            file_size: s.file_size,
+            throughput_kbps: s.throughput.kbps(Instant::now()),
+            reconnect_count: s.reconnect_count,
+            complete: s.eof,
+            ..Default::default()
+        }
+    }
+}
+
 /// HTTP streaming reader
 pub struct SimpleHttpReader {
     state: Arc<Mutex<BufferState>>,
@@ -114,15 +209,36 @@ impl SimpleHttpReader {
         self.state.lock().available()
     }
     
+    /// Whether the download has ended (the whole file arrived or it failed)
+    pub fn is_complete(&self) -> bool {
+        self.state.lock().eof
+    }
+    
+    /// Whether the server supports Range requests (None until known)
+    pub fn supports_range(&self) -> Option<bool> {
+        self.state.lock().accepts_ranges
+    }
+    
+    /// Handle for watching the buffer after the reader is handed to the decoder
+    pub fn monitor(&self) -> StreamMonitor {
+        StreamMonitor { state: self.state.clone() }
+    }
+    
+    /// Change the bandwidth priority (a preloaded stream becomes Playback once it starts playing)
+    pub fn set_traffic_class(&self, traffic_class: TrafficClass) {
+        self.state.lock().traffic_class = traffic_class;
+    }
+    
     /// Create new HTTP stream reader
-    pub async fn new(url: String, username: String, password: String) -> io::Result<Self> {
+    pub async fn new(url: String, username: String, password: String, traffic_class: TrafficClass) -> io::Result<Self> {
         use base64::Engine;
         
         let mut client_builder = Client::builder()
             .pool_max_idle_per_host(5)
-            .pool_idle_timeout(Some(std::time::Duration::from_secs(90)))
-            // Increased timeout from 30s to 60s for large files and slow networks
-            .timeout(std::time::Duration::from_secs(60));
+            .pool_idle_timeout(Some(Duration::from_secs(90)))
+            // No total timeout: streaming a long track can outlast any fixed limit,
+            // stalls are detected per chunk instead
+            .connect_timeout(CONNECT_TIMEOUT);
         
         // 添加Basic认证
         if !username.is_empty() {
@@ -143,7 +259,7 @@ impl SimpleHttpReader {
             .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("创建HTTP客户端失败: {}", e)))?;
         
         let client = Arc::new(client);
-        let state = Arc::new(Mutex::new(BufferState::new()));
+        let state = Arc::new(Mutex::new(BufferState::new(traffic_class)));
         
         // 启动下载线程
         let downloader_thread = Self::start_downloader(
@@ -153,7 +269,7 @@ impl SimpleHttpReader {
         )?;
         
         log::info!("HTTP stream reader created");
-        println!("[HttpReader] Streaming download started");
+        log::debug!("[HttpReader] Streaming download started");
         
         Ok(Self {
             state,
@@ -194,18 +310,22 @@ impl SimpleHttpReader {
     }
     
     /// Stream download
+    ///
+    /// Failed requests, dropped connections, stalls and bodies that end before the known
+    /// file size are retried with a Range request from the last received byte,
+    /// up to the configured number of reconnects.
     async fn download_stream(
         client: Arc<Client>,
         url: String,
         state: Arc<Mutex<BufferState>>,
     ) {
-        use std::time::Duration;
+        log::debug!("[HttpReader] Starting streaming download");
         
-        println!("[HttpReader] Starting streaming download");
+        let bandwidth = bandwidth::manager();
+        let max_reconnects = buffering::settings().max_reconnects;
         
-        // Increased max retries from 3 to 10 for better resilience to network issues
-        const MAX_RETRIES: u32 = 10;
         const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+        const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
         
         let mut retry_count = 0u32;
         let mut retry_delay = INITIAL_RETRY_DELAY;
@@ -226,13 +346,14 @@ impl SimpleHttpReader {
             
             // Build request with optional Range header
             let mut request = client.get(&url);
-            if current_download_offset > 0 || seek_offset.is_some() {
+            let ranged = current_download_offset > 0 || seek_offset.is_some();
+            if ranged {
                 let range_header = format!("bytes={}-", current_download_offset);
                 log::info!("[HttpReader] Using Range header: {}", range_header);
                 request = request.header("Range", range_header);
             }
             
-            match request.send().await {
+            let failure = match request.send().await {
                 Ok(response) => {
                     let status = response.status();
                     
@@ -246,41 +367,77 @@ impl SimpleHttpReader {
                         return;
                     }
                     
-                    if let Some(content_length) = response.headers()
-                        .get("content-length")
+                    // A 200 reply to a Range request means the server ignored it and resent the whole file
+                    if ranged && status != reqwest::StatusCode::PARTIAL_CONTENT {
+                        let error_msg = "服务器不支持Range请求，无法跳转".to_string();
+                        log::error!("[HttpReader] {} (status {})", error_msg, status);
+                        
+                        let mut s = state.lock();
+                        s.accepts_ranges = Some(false);
+                        s.eof = true;
+                        s.error = Some(error_msg);
+                        return;
+                    }
+                    
+                    let header = |name: &str| response.headers()
+                        .get(name)
                         .and_then(|v| v.to_str().ok())
-                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(|v| v.to_string());
+                    
+                    // Partial responses carry the full size in Content-Range ("bytes start-end/total")
+                    let file_size = if ranged {
+                        header("content-range")
+                            .and_then(|v| v.rsplit('/').next().and_then(|total| total.parse::<u64>().ok()))
+                    } else {
+                        header("content-length").and_then(|v| v.parse::<u64>().ok())
+                    };
+                    
                     {
-                        log::info!("File size: {:.2}MB", content_length as f64 / 1024.0 / 1024.0);
-                        state.lock().file_size = Some(content_length);
+                        let mut s = state.lock();
+                        if let Some(size) = file_size {
+                            log::info!("File size: {:.2}MB", size as f64 / 1024.0 / 1024.0);
+                            s.file_size = Some(size);
+                        }
+                        if ranged {
+                            s.accepts_ranges = Some(true);
+                        } else if let Some(accept_ranges) = header("accept-ranges") {
+                            s.accepts_ranges = Some(accept_ranges.trim().eq_ignore_ascii_case("bytes"));
+                        }
                     }
                     
-                    retry_count = 0;  // 重置重试计数
-                    retry_delay = INITIAL_RETRY_DELAY;
-                    
                     let mut stream = response.bytes_stream();
                     let mut total = 0u64;
                     let mut chunk_count = 0u64;
                     
-                    while let Some(chunk_result) = stream.next().await {
+                    let end = loop {
+                        let chunk_result = match tokio::time::timeout(STALL_TIMEOUT, stream.next()).await {
+                            Ok(Some(chunk_result)) => chunk_result,
+                            Ok(None) => break BodyEnd::Closed,
+                            Err(_) => break BodyEnd::Failed(format!("No data received for {}s", STALL_TIMEOUT.as_secs())),
+                        };
+                        
                         let s = state.lock();
                         let should_exit = s.should_exit;
                         let has_seek = s.seek_requested.is_some();
+                        let traffic_class = s.traffic_class;
                         drop(s);
                         
                         if should_exit {
-                            println!("[HttpReader] Downloader thread exiting");
+                            log::debug!("[HttpReader] Downloader thread exiting");
                             return;
                         }
                         
                         // If seek was requested, abort current download and restart
                         if has_seek {
                             log::info!("[HttpReader] Seek requested, restarting download");
-                            break;
+                            break BodyEnd::Seek;
                         }
                         
                         match chunk_result {
                             Ok(chunk) => {
+                                // Wait for bandwidth before buffering (and thus before reading the next chunk)
+                                bandwidth.acquire(traffic_class, chunk.len()).await;
+                                
                                 let chunk_len = chunk.len() as u64;
                                 total += chunk_len;
                                 current_download_offset += chunk_len;
@@ -289,62 +446,60 @@ impl SimpleHttpReader {
                                 while !state.lock().add_chunk(chunk.clone()) {
                                     tokio::time::sleep(Duration::from_millis(50)).await;
                                 }
+                                state.lock().record_received(chunk.len());
+                                
+                                // Data is flowing again: later drops get a fresh set of reconnects
+                                retry_count = 0;
+                                retry_delay = INITIAL_RETRY_DELAY;
                                 
                                 if chunk_count % 100 == 0 {
-                                    println!("[HttpReader] Received: {:.2}MB", total as f64 / 1024.0 / 1024.0);
+                                    log::debug!("[HttpReader] Received: {:.2}MB", total as f64 / 1024.0 / 1024.0);
                                 }
                             }
-                            Err(e) => {
-                                retry_count += 1;
-                                
-                                if retry_count > MAX_RETRIES {
-                                    let error_msg = format!("Data receive failed, max retries reached: {}", e);
-                                    log::error!("{}", error_msg);
-                                    
-                                    let mut s = state.lock();
-                                    s.eof = true;
-                                    s.error = Some(error_msg);
-                                    return;
-                                }
-                                
-                                // Improved error handling with exponential backoff (max 5s)
-                                let delay = retry_delay.min(Duration::from_secs(5));
-                                log::warn!("Data receive failed (attempt {}/{}), retrying in {}ms: {}", 
-                                    retry_count, MAX_RETRIES, delay.as_millis(), e);
-                                
-                                tokio::time::sleep(delay).await;
-                                retry_delay = (retry_delay * 2).min(Duration::from_secs(5));
-                                break;
-                            }
+                            Err(e) => break BodyEnd::Failed(format!("Data receive failed: {}", e)),
                         }
-                    }
+                    };
                     
-                    if retry_count == 0 {
-                        state.lock().eof = true;
-                        println!("[HttpReader] Download complete: {:.2}MB", total as f64 / 1024.0 / 1024.0);
-                        return;
-                    }
-                }
-                Err(e) => {
-                    retry_count += 1;
-                    
-                    if retry_count > MAX_RETRIES {
-                        let error_msg = format!("HTTP request failed, max retries reached: {}", e);
-                        log::error!("{}", error_msg);
-                        
-                        let mut s = state.lock();
-                        s.eof = true;
-                        s.error = Some(error_msg);
-                        return;
+                    match end {
+                        BodyEnd::Seek => continue,
+                        BodyEnd::Closed => {
+                            let file_size = state.lock().file_size;
+                            if !ended_early(current_download_offset, file_size) {
+                                state.lock().eof = true;
+                                log::debug!("[HttpReader] Download complete: {:.2}MB", total as f64 / 1024.0 / 1024.0);
+                                return;
+                            }
+                            format!(
+                                "Connection closed at byte {} of {}",
+                                current_download_offset,
+                                file_size.unwrap_or_default()
+                            )
+                        }
+                        BodyEnd::Failed(e) => e,
                     }
-                    
-                    log::warn!("HTTP request failed (attempt {}), retrying in {}ms: {}", 
-                        retry_count, retry_delay.as_millis(), e);
-                    
-                    tokio::time::sleep(retry_delay).await;
-                    retry_delay = retry_delay * 2;
                 }
+                Err(e) => format!("HTTP request failed: {}", e),
+            };
+            
+            retry_count += 1;
+            
+            if retry_count > max_reconnects {
+                let error_msg = format!("{}, max retries reached", failure);
+                log::error!("{}", error_msg);
+                
+                let mut s = state.lock();
+                s.eof = true;
+                s.error = Some(error_msg);
+                return;
             }
+            
+            // Exponential backoff (max 5s), then resume from the last received byte
+            log::warn!("{} (attempt {}/{}), reconnecting from byte {} in {}ms",
+                failure, retry_count, max_reconnects, current_download_offset, retry_delay.as_millis());
+            state.lock().reconnect_count += 1;
+            
+            tokio::time::sleep(retry_delay).await;
+            retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
         }
     }
 }
@@ -371,8 +526,9 @@ impl Read for SimpleHttpReader {
             }
             
             drop(state);
-            // Optimized polling wait time (increased from 1ms to 10ms to reduce CPU usage)
-            thread::sleep(std::time::Duration::from_millis(10));
+            // An empty buffer is an underrun, not EOF: wait for the downloader
+            // (polling every 10ms rather than 1ms to reduce CPU usage)
+            thread::sleep(Duration::from_millis(10));
         }
     }
 }
@@ -382,6 +538,7 @@ impl Seek for SimpleHttpReader {
         let state = self.state.lock();
         let file_size = state.file_size;
         let current_offset = state.current_offset;
+        let accepts_ranges = state.accepts_ranges;
         drop(state);
         
         // Calculate target position
@@ -419,14 +576,26 @@ impl Seek for SimpleHttpReader {
             }
         }
         
+        // Already buffered: no new request needed
+        if self.state.lock().skip_buffered(target_offset) {
+            return Ok(target_offset);
+        }
+        
+        if accepts_ranges == Some(false) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "服务器不支持Range请求，无法跳转"
+            ));
+        }
+        
         log::info!("Seek requested: {:?} -> offset {}", pos, target_offset);
-        println!("[HttpReader] Seek to offset: {}", target_offset);
+        log::debug!("[HttpReader] Seek to offset: {}", target_offset);
         
         // Handle the seek
         self.state.lock().handle_seek(target_offset);
         
         // Wait a bit for buffer to refill
-        thread::sleep(std::time::Duration::from_millis(100));
+        thread::sleep(Duration::from_millis(100));
         
         Ok(target_offset)
     }
@@ -435,7 +604,8 @@ impl Seek for SimpleHttpReader {
 impl symphonia::core::io::MediaSource for SimpleHttpReader {
     fn is_seekable(&self) -> bool {
         // Now we support seek via HTTP Range requests
-        self.state.lock().file_size.is_some()
+        let state = self.state.lock();
+        state.file_size.is_some() && state.accepts_ranges != Some(false)
     }
     
     fn byte_len(&self) -> Option<u64> {
@@ -454,3 +624,58 @@ impl Drop for SimpleHttpReader {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seek_within_buffer_skips_without_request() {
+        let mut state = BufferState::new(TrafficClass::Playback);
+        state.add_chunk(Bytes::from_static(b"0123456789"));
+        state.add_chunk(Bytes::from_static(b"abcdef"));
+
+        assert!(state.skip_buffered(12));
+        assert_eq!(state.seek_requested, None);
+        let mut buf = [0u8; 4];
+        assert_eq!(state.read_bytes(&mut buf), 4);
+        assert_eq!(&buf, b"cdef");
+
+        // 已读取过的位置和超出缓冲的位置都需要重新请求
+        assert!(!state.skip_buffered(3));
+        assert!(!state.skip_buffered(17));
+    }
+
+    #[test]
+    fn test_body_ending_before_file_size_is_a_drop() {
+        assert!(ended_early(1000, Some(4096)));
+        assert!(!ended_early(4096, Some(4096)));
+        // 分块传输、大小未知时只能以服务器关闭连接为准
+        assert!(!ended_early(1000, None));
+    }
+
+    #[test]
+    fn test_monitor_reports_buffer_and_download_stats() {
+        let state = Arc::new(Mutex::new(BufferState::new(TrafficClass::Playback)));
+        let monitor = StreamMonitor { state: state.clone() };
+        {
+            let mut s = state.lock();
+            s.file_size = Some(100);
+            s.add_chunk(Bytes::from_static(b"0123456789"));
+            s.record_received(10);
+            s.reconnect_count = 2;
+            let mut buf = [0u8; 4];
+            s.read_bytes(&mut buf);
+        }
+
+        assert_eq!(monitor.level(), BufferLevel { buffered: 6, complete: false });
+        let stats = monitor.stats();
+        assert_eq!(stats.bytes_buffered, 6);
+        assert_eq!(stats.bytes_received, 10);
+        assert_eq!(stats.file_size, Some(100));
+        assert_eq!(stats.reconnect_count, 2);
+        assert!(stats.throughput_kbps > 0.0);
+
+        state.lock().eof = true;
+        assert!(monitor.level().complete);
+    }
+}
@@ -0,0 +1,270 @@
+// Subsonic API客户端 - token/salt认证、调用限速、JSON响应解析
+
+use super::types::*;
+use reqwest::{header::*, Client as HttpClient, Url};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+/// 声明支持的API版本（token认证需要 1.13.0+）
+pub const API_VERSION: &str = "1.16.1";
+
+/// 客户端标识
+pub const CLIENT_NAME: &str = "WindChimePlayer";
+
+/// getAlbumList2 单页数量（服务器上限为500）
+const ALBUM_PAGE_SIZE: usize = 500;
+
+/// 封面尺寸上限（像素），避免原图过大撑满数据库
+const COVER_ART_SIZE: u32 = 1000;
+
+/// 距离上次调用还需等待多久才能满足限速
+pub fn rate_limit_wait(last_request: Option<Instant>, now: Instant, interval: Duration) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(last_request?);
+    interval.checked_sub(elapsed).filter(|d| !d.is_zero())
+}
+
+/// token = md5(password + salt)
+pub fn auth_token(password: &str, salt: &str) -> String {
+    format!("{:x}", md5::compute(format!("{}{}", password, salt)))
+}
+
+/// Subsonic API客户端
+pub struct SubsonicClient {
+    http_client: HttpClient,
+    config: SubsonicConfig,
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+}
+
+impl SubsonicClient {
+    pub fn new(config: SubsonicConfig) -> SubsonicResult<Self> {
+        config.validate()?;
+
+        let mut builder = HttpClient::builder()
+            .pool_max_idle_per_host(5)
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .user_agent(CLIENT_NAME);
+        if !config.verify_ssl {
+            builder = builder.danger_accept_invalid_certs(true);
+            log::warn!("SSL certificate verification disabled");
+        }
+        let http_client = builder.build()
+            .map_err(|e| SubsonicError::ConfigError(format!("创建HTTP客户端失败: {}", e)))?;
+
+        Ok(Self {
+            http_client,
+            config,
+            last_request: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    /// 认证和公共参数，每次调用使用新的随机salt
+    fn auth_params(&self) -> Vec<(&'static str, String)> {
+        use rand::Rng;
+
+        let salt: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(12)
+            .map(char::from)
+            .collect();
+        vec![
+            ("u", self.config.username.clone()),
+            ("t", auth_token(&self.config.password, &salt)),
+            ("s", salt),
+            ("v", API_VERSION.to_string()),
+            ("c", CLIENT_NAME.to_string()),
+            ("f", "json".to_string()),
+        ]
+    }
+
+    fn build_url(&self, method: &str, params: &[(&str, String)]) -> SubsonicResult<Url> {
+        let mut all_params = self.auth_params();
+        all_params.extend(params.iter().map(|(k, v)| (*k, v.clone())));
+        Url::parse_with_params(&self.config.endpoint(method), &all_params)
+            .map_err(|e| SubsonicError::ConfigError(format!("无效的服务器地址: {}", e)))
+    }
+
+    /// 按配置的频率限速
+    async fn throttle(&self) {
+        let interval = Duration::from_secs(1) / self.config.max_requests_per_second;
+        let mut last = self.last_request.lock().await;
+        if let Some(wait) = rate_limit_wait(*last, Instant::now(), interval) {
+            tokio::time::sleep(wait).await;
+        }
+        *last = Some(Instant::now());
+    }
+
+    /// 调用JSON接口
+    async fn call(&self, method: &str, params: &[(&str, String)]) -> SubsonicResult<SubsonicResponse> {
+        self.throttle().await;
+        let url = self.build_url(method, params)?;
+        let response = self.http_client.get(url).send().await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(SubsonicError::AuthenticationFailed(format!("HTTP {}", status.as_u16())));
+        }
+        if !status.is_success() {
+            return Err(SubsonicError::ApiError {
+                code: status.as_u16() as i32,
+                message: format!("HTTP {}", status),
+            });
+        }
+
+        let body = response.text().await?;
+        Self::parse_response(&body)
+    }
+
+    fn parse_response(body: &str) -> SubsonicResult<SubsonicResponse> {
+        let envelope: ResponseEnvelope = serde_json::from_str(body)
+            .map_err(|e| SubsonicError::ParseError(e.to_string()))?;
+        let response = envelope.response;
+        if response.status != "ok" {
+            let error = response.error.unwrap_or(ApiError { code: 0, message: "未知错误".to_string() });
+            return Err(SubsonicError::from_api(error.code, error.message));
+        }
+        Ok(response)
+    }
+
+    /// 测试连接和认证
+    pub async fn ping(&self) -> SubsonicResult<()> {
+        self.call("ping", &[]).await?;
+        log::info!("Subsonic连接成功: {}", self.config.url);
+        Ok(())
+    }
+
+    /// 获取全部专辑（getAlbumList2 分页）
+    pub async fn get_all_albums(&self) -> SubsonicResult<Vec<Album>> {
+        let mut albums = Vec::new();
+        loop {
+            let response = self.call("getAlbumList2", &[
+                ("type", "alphabeticalByName".to_string()),
+                ("size", ALBUM_PAGE_SIZE.to_string()),
+                ("offset", albums.len().to_string()),
+            ]).await?;
+            let page = response.album_list2.unwrap_or_default().album;
+            let count = page.len();
+            albums.extend(page);
+            if count < ALBUM_PAGE_SIZE {
+                break;
+            }
+        }
+        log::info!("📀 Subsonic专辑数: {}", albums.len());
+        Ok(albums)
+    }
+
+    /// 获取目录内容（专辑ID也可作为目录ID）
+    pub async fn get_music_directory(&self, id: &str) -> SubsonicResult<Vec<Child>> {
+        let response = self.call("getMusicDirectory", &[("id", id.to_string())]).await?;
+        Ok(response.directory.map(|d| d.child).unwrap_or_default())
+    }
+
+    /// 获取单首歌曲信息
+    pub async fn get_song(&self, id: &str) -> SubsonicResult<Child> {
+        let response = self.call("getSong", &[("id", id.to_string())]).await?;
+        response.song.ok_or_else(|| SubsonicError::ParseError("响应中缺少song".to_string()))
+    }
+
+    /// 获取封面图片，返回 (数据, MIME类型)
+    pub async fn get_cover_art(&self, id: &str) -> SubsonicResult<(Vec<u8>, String)> {
+        self.throttle().await;
+        let url = self.build_url("getCoverArt", &[
+            ("id", id.to_string()),
+            ("size", COVER_ART_SIZE.to_string()),
+        ])?;
+        let response = self.http_client.get(url).send().await?.error_for_status()?;
+
+        let mime = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let data = response.bytes().await?;
+
+        // 出错时服务器返回JSON错误体而不是图片
+        if mime.contains("json") {
+            Self::parse_response(&String::from_utf8_lossy(&data))?;
+            return Err(SubsonicError::ParseError("封面响应不是图片".to_string()));
+        }
+        Ok((data.to_vec(), mime))
+    }
+
+    /// 播放用的流地址（format=raw 不转码，保证支持Range请求）
+    pub fn stream_url(&self, id: &str) -> SubsonicResult<String> {
+        let url = self.build_url("stream", &[
+            ("id", id.to_string()),
+            ("format", "raw".to_string()),
+        ])?;
+        Ok(url.to_string())
+    }
+
+    /// 请求歌曲数据，原样转发 Range 头并返回响应（投屏媒体服务使用）
+    pub async fn stream_response(&self, id: &str, range: Option<&str>) -> SubsonicResult<reqwest::Response> {
+        self.throttle().await;
+        let mut request = self.http_client.get(self.stream_url(id)?);
+        if let Some(range) = range {
+            request = request.header(RANGE, range);
+        }
+        Ok(request.send().await?)
+    }
+
+    /// 打开歌曲数据流，可指定字节范围
+    pub async fn open_stream(&self, id: &str, start: u64, end: Option<u64>) -> SubsonicResult<Box<dyn AsyncRead + Send + Unpin>> {
+        use futures::TryStreamExt;
+
+        self.throttle().await;
+        let mut request = self.http_client.get(self.stream_url(id)?);
+        if start > 0 || end.is_some() {
+            let range = match end {
+                Some(end) => format!("bytes={}-{}", start, end),
+                None => format!("bytes={}-", start),
+            };
+            request = request.header(RANGE, range);
+        }
+        let response = request.send().await?.error_for_status()?;
+
+        let stream = response.bytes_stream()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+        Ok(Box::new(StreamReader::new(stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_token() {
+        // Subsonic API文档中的示例
+        assert_eq!(auth_token("sesame", "c19b2d"), "26719a1196d2a940705a59634eb18eab");
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let interval = Duration::from_millis(100);
+        let now = Instant::now();
+        assert_eq!(rate_limit_wait(None, now, interval), None);
+        assert_eq!(rate_limit_wait(Some(now), now + Duration::from_millis(40), interval), Some(Duration::from_millis(60)));
+        assert_eq!(rate_limit_wait(Some(now), now + Duration::from_millis(150), interval), None);
+    }
+
+    #[test]
+    fn test_stream_url_contains_token_auth() {
+        let client = SubsonicClient::new(SubsonicConfig {
+            url: "https://music.example.com/".to_string(),
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+            timeout_seconds: 30,
+            max_requests_per_second: 10,
+            verify_ssl: true,
+        }).unwrap();
+
+        let url = Url::parse(&client.stream_url("tr-1").unwrap()).unwrap();
+        assert_eq!(url.path(), "/rest/stream");
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["id"], "tr-1");
+        assert_eq!(params["u"], "alice");
+        assert_eq!(params["t"], auth_token("secret", &params["s"]));
+        assert!(!params.contains_key("p"));
+    }
+}
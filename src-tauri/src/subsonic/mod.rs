@@ -0,0 +1,42 @@
+// Subsonic API客户端模块（兼容Navidrome等实现）
+// 通过RemoteSourceClient接入远程音乐源，元数据直接取自API响应
+
+pub mod client;
+pub mod types;
+pub mod remote_adapter;
+
+pub use client::SubsonicClient;
+pub use remote_adapter::SubsonicRemoteAdapter;
+
+use anyhow::{anyhow, Result};
+use remote_adapter::SubsonicPath;
+use types::SubsonicConfig;
+
+/// 解析 subsonic://server_id#/song/<id>.ext，返回带认证参数的流地址
+pub fn stream_url_for_track(track_path: &str) -> Result<String> {
+    let (client, song_id) = client_for_track(track_path)?;
+    Ok(client.stream_url(&song_id)?)
+}
+
+/// 解析 subsonic://server_id#/song/<id>.ext，返回该服务器的客户端和歌曲ID
+pub fn client_for_track(track_path: &str) -> Result<(SubsonicClient, String)> {
+    let (server_id, remote_path) = track_path.strip_prefix("subsonic://")
+        .and_then(|p| p.split_once('#'))
+        .ok_or_else(|| anyhow!("Subsonic路径格式错误: {}", track_path))?;
+
+    let song_id = match SubsonicPath::parse(remote_path)? {
+        SubsonicPath::Song(id) => id,
+        _ => return Err(anyhow!("不是歌曲路径: {}", remote_path)),
+    };
+
+    let db = crate::DB.get().ok_or_else(|| anyhow!("数据库未初始化"))?;
+    let servers = db.with(|db| db.get_remote_servers())
+        .map_err(|e| anyhow!("获取服务器列表失败: {}", e))?;
+    let (_, _, _, config_json, _) = servers.iter()
+        .find(|(id, _, server_type, _, _)| id == server_id && server_type == "subsonic")
+        .ok_or_else(|| anyhow!("找不到Subsonic服务器: {}", server_id))?;
+
+    let config: SubsonicConfig = serde_json::from_str(config_json)
+        .map_err(|e| anyhow!("解析配置失败: {}", e))?;
+    Ok((SubsonicClient::new(config)?, song_id.to_string()))
+}
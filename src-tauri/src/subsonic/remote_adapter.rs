@@ -0,0 +1,239 @@
+// Subsonic远程源适配器 - 实现RemoteSourceClient trait
+//
+// 把Subsonic的专辑/目录结构映射为远程文件列表：
+// - "/"              → 全部专辑（getAlbumList2），每个专辑是一个目录
+// - "/dir/<id>"      → 目录内容（getMusicDirectory）
+// - "/song/<id>.ext" → 歌曲文件
+use super::{types::*, SubsonicClient};
+use crate::metadata_extractor::MusicMetadata;
+use crate::remote_source::{RemoteSourceClient, RemoteFileInfo, RemoteSourceType, ConnectionStatus, HealthStatus};
+use async_trait::async_trait;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use tokio::io::AsyncRead;
+
+/// 远程路径对应的Subsonic资源
+#[derive(Debug, PartialEq)]
+pub enum SubsonicPath<'a> {
+    Root,
+    Directory(&'a str),
+    Song(&'a str),
+}
+
+impl<'a> SubsonicPath<'a> {
+    pub fn parse(path: &'a str) -> Result<Self> {
+        let path = path.trim_end_matches('/');
+        if path.is_empty() {
+            return Ok(SubsonicPath::Root);
+        }
+        if let Some(id) = path.strip_prefix("/dir/") {
+            return Ok(SubsonicPath::Directory(id));
+        }
+        if let Some(file) = path.strip_prefix("/song/") {
+            let id = file.rsplit_once('.').map(|(id, _)| id).unwrap_or(file);
+            return Ok(SubsonicPath::Song(id));
+        }
+        Err(anyhow::anyhow!("无效的Subsonic路径: {}", path))
+    }
+}
+
+/// 歌曲的远程路径
+pub fn song_path(song: &Child) -> String {
+    format!("/song/{}.{}", song.id, song.extension())
+}
+
+/// Subsonic远程源适配器
+pub struct SubsonicRemoteAdapter {
+    client: SubsonicClient,
+    /// 列目录时得到的歌曲信息，扫描时直接用作元数据
+    songs: Mutex<HashMap<String, Child>>,
+    /// 最近一次获取的封面（同一专辑的歌曲连续扫描，只缓存一张即可）
+    last_cover: tokio::sync::Mutex<Option<(String, Option<(Vec<u8>, String)>)>>,
+}
+
+impl SubsonicRemoteAdapter {
+    pub fn new(client: SubsonicClient) -> Self {
+        Self {
+            client,
+            songs: Mutex::new(HashMap::new()),
+            last_cover: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn song_info(song: &Child) -> RemoteFileInfo {
+        RemoteFileInfo {
+            path: song_path(song),
+            name: format!("{}.{}", song.title, song.extension()),
+            is_directory: false,
+            size: song.size,
+            mime_type: song.content_type.clone(),
+            last_modified: None,
+            etag: None,
+            source_type: RemoteSourceType::Subsonic,
+        }
+    }
+
+    async fn song(&self, id: &str) -> Result<Child> {
+        if let Some(song) = self.songs.lock().get(id).cloned() {
+            return Ok(song);
+        }
+        let song = self.client.get_song(id).await?;
+        self.songs.lock().insert(id.to_string(), song.clone());
+        Ok(song)
+    }
+
+    /// 获取封面，失败时不影响元数据
+    async fn cover_art(&self, cover_id: &str) -> Option<(Vec<u8>, String)> {
+        let mut last = self.last_cover.lock().await;
+        if let Some((id, cover)) = last.as_ref() {
+            if id == cover_id {
+                return cover.clone();
+            }
+        }
+        let cover = match self.client.get_cover_art(cover_id).await {
+            Ok(cover) => Some(cover),
+            Err(e) => {
+                log::warn!("获取Subsonic封面失败 ({}): {}", cover_id, e);
+                None
+            }
+        };
+        *last = Some((cover_id.to_string(), cover.clone()));
+        cover
+    }
+
+    fn song_id(path: &str) -> Result<&str> {
+        match SubsonicPath::parse(path)? {
+            SubsonicPath::Song(id) => Ok(id),
+            _ => Err(anyhow::anyhow!("不是歌曲路径: {}", path)),
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteSourceClient for SubsonicRemoteAdapter {
+    async fn test_connection(&self) -> Result<ConnectionStatus> {
+        match self.client.ping().await {
+            Ok(_) => Ok(ConnectionStatus::Connected),
+            Err(SubsonicError::AuthenticationFailed(msg)) => {
+                Ok(ConnectionStatus::Error(format!("认证失败，请检查用户名和密码: {}", msg)))
+            }
+            Err(e) => Ok(ConnectionStatus::Error(e.to_string())),
+        }
+    }
+
+    async fn list_directory(&self, path: &str) -> Result<Vec<RemoteFileInfo>> {
+        let items = match SubsonicPath::parse(path)? {
+            SubsonicPath::Root => self.client.get_all_albums().await?
+                .into_iter()
+                .map(|album| RemoteFileInfo {
+                    path: format!("/dir/{}", album.id),
+                    name: match album.artist {
+                        Some(artist) if !artist.is_empty() => format!("{} - {}", artist, album.name),
+                        _ => album.name,
+                    },
+                    is_directory: true,
+                    size: None,
+                    mime_type: None,
+                    last_modified: None,
+                    etag: None,
+                    source_type: RemoteSourceType::Subsonic,
+                })
+                .collect(),
+            SubsonicPath::Directory(id) => {
+                let children = self.client.get_music_directory(id).await?;
+                let mut songs = self.songs.lock();
+                children.into_iter()
+                    .map(|child| {
+                        if child.is_dir {
+                            RemoteFileInfo {
+                                path: format!("/dir/{}", child.id),
+                                name: child.title,
+                                is_directory: true,
+                                size: None,
+                                mime_type: None,
+                                last_modified: None,
+                                etag: None,
+                                source_type: RemoteSourceType::Subsonic,
+                            }
+                        } else {
+                            let info = Self::song_info(&child);
+                            songs.insert(child.id.clone(), child);
+                            info
+                        }
+                    })
+                    .collect()
+            }
+            SubsonicPath::Song(_) => return Err(anyhow::anyhow!("不是目录: {}", path)),
+        };
+
+        log::info!("📁 Subsonic目录 '{}': {} 个项目", path, items.len());
+        Ok(items)
+    }
+
+    async fn get_file_info(&self, path: &str) -> Result<RemoteFileInfo> {
+        let song = self.song(Self::song_id(path)?).await?;
+        Ok(Self::song_info(&song))
+    }
+
+    async fn download_stream(&self, path: &str) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(self.client.open_stream(Self::song_id(path)?, 0, None).await?)
+    }
+
+    async fn download_range(&self, path: &str, start: u64, end: Option<u64>)
+        -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(self.client.open_stream(Self::song_id(path)?, start, end).await?)
+    }
+
+    async fn get_metadata(&self, path: &str) -> Result<Option<MusicMetadata>> {
+        let song = self.song(Self::song_id(path)?).await?;
+        let cover = match song.cover_art.as_deref() {
+            Some(cover_id) => self.cover_art(cover_id).await,
+            None => None,
+        };
+        let (album_cover_data, album_cover_mime) = cover.unzip();
+
+        Ok(Some(MusicMetadata {
+            title: Some(song.title).filter(|t| !t.is_empty()),
+            artist: song.artist,
+            album: song.album,
+            track_number: song.track,
+            disc_number: song.disc_number,
+            year: song.year,
+            genre: song.genre,
+            duration_ms: song.duration.map(|s| s * 1000),
+            bit_rate: song.bit_rate,
+            format: song.suffix,
+            album_cover_data,
+            album_cover_mime,
+            ..Default::default()
+        }))
+    }
+
+    fn get_health(&self) -> HealthStatus {
+        HealthStatus {
+            is_healthy: true,
+            last_check: chrono::Utc::now().timestamp(),
+            error_count: 0,
+            connection_status: ConnectionStatus::Connected,
+        }
+    }
+
+    fn get_source_type(&self) -> RemoteSourceType {
+        RemoteSourceType::Subsonic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths() {
+        assert_eq!(SubsonicPath::parse("/").unwrap(), SubsonicPath::Root);
+        assert_eq!(SubsonicPath::parse("").unwrap(), SubsonicPath::Root);
+        assert_eq!(SubsonicPath::parse("/dir/al-1").unwrap(), SubsonicPath::Directory("al-1"));
+        assert_eq!(SubsonicPath::parse("/song/tr-1.flac").unwrap(), SubsonicPath::Song("tr-1"));
+        assert!(SubsonicPath::parse("/music/a.flac").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+// Subsonic API类型定义 - 服务器配置、错误类型和JSON响应结构
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Subsonic操作结果类型
+pub type SubsonicResult<T> = Result<T, SubsonicError>;
+
+/// Subsonic错误类型
+#[derive(Debug, thiserror::Error)]
+pub enum SubsonicError {
+    #[error("网络错误: {0}")]
+    NetworkError(#[from] reqwest::Error),
+
+    #[error("认证失败: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("API错误 ({code}): {message}")]
+    ApiError { code: i32, message: String },
+
+    #[error("响应解析失败: {0}")]
+    ParseError(String),
+
+    #[error("配置错误: {0}")]
+    ConfigError(String),
+}
+
+impl SubsonicError {
+    /// 根据Subsonic错误码构造错误（40/41/44为认证相关）
+    pub fn from_api(code: i32, message: String) -> Self {
+        match code {
+            40 | 41 | 44 => SubsonicError::AuthenticationFailed(message),
+            _ => SubsonicError::ApiError { code, message },
+        }
+    }
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_requests_per_second() -> u32 {
+    10
+}
+
+fn default_verify_ssl() -> bool {
+    true
+}
+
+/// Subsonic/Navidrome服务器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsonicConfig {
+    /// 服务器地址，如 https://music.example.com
+    pub url: String,
+    pub username: String,
+    /// 仅用于生成 token/salt，不会以明文发送
+    pub password: String,
+    #[serde(default = "default_timeout")]
+    pub timeout_seconds: u64,
+    /// API调用频率上限（次/秒）
+    #[serde(default = "default_requests_per_second")]
+    pub max_requests_per_second: u32,
+    #[serde(default = "default_verify_ssl")]
+    pub verify_ssl: bool,
+}
+
+impl SubsonicConfig {
+    /// 验证配置
+    pub fn validate(&self) -> SubsonicResult<()> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(SubsonicError::ConfigError("服务器地址必须以 http:// 或 https:// 开头".to_string()));
+        }
+        if self.username.is_empty() {
+            return Err(SubsonicError::ConfigError("用户名不能为空".to_string()));
+        }
+        if self.max_requests_per_second == 0 {
+            return Err(SubsonicError::ConfigError("API调用频率必须大于0".to_string()));
+        }
+        Ok(())
+    }
+
+    /// REST接口地址
+    pub fn endpoint(&self, method: &str) -> String {
+        format!("{}/rest/{}", self.url.trim_end_matches('/'), method)
+    }
+}
+
+/// 兼容字符串和数字两种格式的ID（Navidrome为字符串，部分Subsonic实现为数字）
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Ok(s),
+        serde_json::Value::Number(n) => Ok(n.to_string()),
+        other => Err(serde::de::Error::custom(format!("无效的ID: {}", other))),
+    }
+}
+
+fn optional_string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(s)) => Ok(Some(s)),
+        Some(serde_json::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(other) => Err(serde::de::Error::custom(format!("无效的ID: {}", other))),
+    }
+}
+
+/// 响应外层包装
+#[derive(Debug, Deserialize)]
+pub struct ResponseEnvelope {
+    #[serde(rename = "subsonic-response")]
+    pub response: SubsonicResponse,
+}
+
+/// 通用响应体，按调用的接口填充对应字段
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsonicResponse {
+    pub status: String,
+    pub error: Option<ApiError>,
+    pub album_list2: Option<AlbumList2>,
+    pub directory: Option<Directory>,
+    pub song: Option<Child>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    pub code: i32,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlbumList2 {
+    #[serde(default)]
+    pub album: Vec<Album>,
+}
+
+/// getAlbumList2 中的专辑
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub name: String,
+    pub artist: Option<String>,
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    pub cover_art: Option<String>,
+}
+
+/// getMusicDirectory 返回的目录
+#[derive(Debug, Deserialize)]
+pub struct Directory {
+    #[serde(default)]
+    pub child: Vec<Child>,
+}
+
+/// 目录项（子目录或歌曲）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Child {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    #[serde(default)]
+    pub is_dir: bool,
+    #[serde(default)]
+    pub title: String,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub track: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    pub cover_art: Option<String>,
+    pub size: Option<u64>,
+    pub suffix: Option<String>,
+    pub content_type: Option<String>,
+    /// 时长（秒）
+    pub duration: Option<u64>,
+    pub bit_rate: Option<u32>,
+}
+
+impl Child {
+    /// 文件扩展名，缺失时按MIME类型推断
+    pub fn extension(&self) -> String {
+        if let Some(suffix) = self.suffix.as_deref().filter(|s| !s.is_empty()) {
+            return suffix.to_lowercase();
+        }
+        match self.content_type.as_deref() {
+            Some("audio/flac") | Some("audio/x-flac") => "flac",
+            Some("audio/ogg") => "ogg",
+            Some("audio/mp4") | Some("audio/x-m4a") => "m4a",
+            Some("audio/wav") | Some("audio/x-wav") => "wav",
+            _ => "mp3",
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_directory_response() {
+        let json = r#"{"subsonic-response":{"status":"ok","version":"1.16.1","directory":{"id":"al-1","name":"Album","child":[
+            {"id":"tr-1","isDir":false,"title":"Song","album":"Album","artist":"Artist","track":3,"year":2020,
+             "coverArt":"al-1","size":1234,"suffix":"flac","contentType":"audio/flac","duration":215},
+            {"id":42,"isDir":true,"title":"CD2"}
+        ]}}}"#;
+        let envelope: ResponseEnvelope = serde_json::from_str(json).unwrap();
+        let directory = envelope.response.directory.unwrap();
+        assert_eq!(directory.child.len(), 2);
+        assert_eq!(directory.child[0].duration, Some(215));
+        assert_eq!(directory.child[0].cover_art.as_deref(), Some("al-1"));
+        assert_eq!(directory.child[1].id, "42");
+        assert!(directory.child[1].is_dir);
+    }
+
+    #[test]
+    fn test_auth_errors_are_classified() {
+        let json = r#"{"subsonic-response":{"status":"failed","version":"1.16.1","error":{"code":40,"message":"Wrong username or password"}}}"#;
+        let envelope: ResponseEnvelope = serde_json::from_str(json).unwrap();
+        let error = envelope.response.error.unwrap();
+        assert!(matches!(SubsonicError::from_api(error.code, error.message), SubsonicError::AuthenticationFailed(_)));
+        assert!(matches!(SubsonicError::from_api(70, String::new()), SubsonicError::ApiError { code: 70, .. }));
+    }
+}
@@ -0,0 +1,258 @@
+// 同步冲突检测与解决策略
+//
+// 以曲目上次同步成功时记录的基线（last_sync / remote_etag / remote_modified）为准：
+// - 本地文件修改时间晚于 last_sync 视为本地已修改
+// - 远程 ETag 变化（服务器不提供ETag时比较修改时间）视为远程已修改
+// 只有一端变化时直接传输；两端都变化或一端被删除另一端被修改时记录冲突，交给用户选择
+
+use crate::db::{SyncBaseline, SyncConflict};
+use serde::Deserialize;
+
+/// 文件在某一端的状态
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileState {
+    pub size: Option<i64>,
+    pub modified: Option<i64>,
+    pub etag: Option<String>,
+    /// 内容MD5（本地计算，远程仅在ETag为MD5时可得）
+    pub md5: Option<String>,
+}
+
+/// 冲突类型，与 sync_conflicts.conflict_type 的取值一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictType {
+    ModifiedBoth,
+    LocalDeletedRemoteModified,
+    LocalModifiedRemoteDeleted,
+    DifferentSize,
+    DifferentHash,
+}
+
+impl ConflictType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictType::ModifiedBoth => "modified_both",
+            ConflictType::LocalDeletedRemoteModified => "local_deleted_remote_modified",
+            ConflictType::LocalModifiedRemoteDeleted => "local_modified_remote_deleted",
+            ConflictType::DifferentSize => "different_size",
+            ConflictType::DifferentHash => "different_hash",
+        }
+    }
+}
+
+/// 同步决策
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDecision {
+    /// 两端一致，无需传输
+    UpToDate,
+    /// 本地较新，上传
+    Upload,
+    /// 远程较新，下载
+    Download,
+    Conflict(ConflictType),
+}
+
+/// 冲突解决策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStrategy {
+    PreferLocal,
+    PreferRemote,
+    PreferNewer,
+}
+
+impl ResolutionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResolutionStrategy::PreferLocal => "prefer_local",
+            ResolutionStrategy::PreferRemote => "prefer_remote",
+            ResolutionStrategy::PreferNewer => "prefer_newer",
+        }
+    }
+}
+
+/// 冲突中胜出的一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// ETag是否为内容MD5（Apache、nginx等常见实现不是，部分对象存储网关是）
+pub fn etag_md5(etag: &str) -> Option<String> {
+    let etag = etag.trim_start_matches("W/").trim_matches('"');
+    (etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit())).then(|| etag.to_ascii_lowercase())
+}
+
+fn local_changed(baseline: &SyncBaseline, local: &FileState) -> bool {
+    match (baseline.last_sync, local.modified) {
+        (Some(last_sync), Some(modified)) => modified > last_sync,
+        _ => true,
+    }
+}
+
+fn remote_changed(baseline: &SyncBaseline, remote: &FileState) -> bool {
+    match (&baseline.remote_etag, &remote.etag) {
+        (Some(old), Some(new)) => old != new,
+        _ => match (baseline.remote_modified.or(baseline.last_sync), remote.modified) {
+            (Some(old), Some(new)) => new > old,
+            _ => true,
+        },
+    }
+}
+
+/// 比较两端当前状态与同步基线，决定如何同步
+pub fn detect(baseline: &SyncBaseline, local: Option<&FileState>, remote: Option<&FileState>) -> SyncDecision {
+    // 从未同步过：没有基线，只能比较两端内容
+    if baseline.last_sync.is_none() {
+        return match (local, remote) {
+            (Some(_), None) => SyncDecision::Upload,
+            (None, Some(_)) => SyncDecision::Download,
+            (None, None) => SyncDecision::UpToDate,
+            (Some(local), Some(remote)) => compare_contents(local, remote),
+        };
+    }
+
+    match (local, remote) {
+        (None, None) => SyncDecision::UpToDate,
+        (Some(local), None) if local_changed(baseline, local) => {
+            SyncDecision::Conflict(ConflictType::LocalModifiedRemoteDeleted)
+        }
+        (Some(_), None) => SyncDecision::Upload,
+        (None, Some(remote)) if remote_changed(baseline, remote) => {
+            SyncDecision::Conflict(ConflictType::LocalDeletedRemoteModified)
+        }
+        (None, Some(_)) => SyncDecision::Download,
+        (Some(local), Some(remote)) => match (local_changed(baseline, local), remote_changed(baseline, remote)) {
+            (true, true) => SyncDecision::Conflict(ConflictType::ModifiedBoth),
+            (true, false) => SyncDecision::Upload,
+            (false, true) => SyncDecision::Download,
+            (false, false) => compare_contents(local, remote),
+        },
+    }
+}
+
+fn compare_contents(local: &FileState, remote: &FileState) -> SyncDecision {
+    if let (Some(a), Some(b)) = (local.size, remote.size) {
+        if a != b {
+            return SyncDecision::Conflict(ConflictType::DifferentSize);
+        }
+    }
+    if let (Some(a), Some(b)) = (&local.md5, &remote.md5) {
+        if a != b {
+            return SyncDecision::Conflict(ConflictType::DifferentHash);
+        }
+    }
+    SyncDecision::UpToDate
+}
+
+/// 按策略选出胜出的一方；prefer_newer 比较修改时间，相同时保留本地
+pub fn winner(strategy: ResolutionStrategy, conflict: &SyncConflict) -> Side {
+    match strategy {
+        ResolutionStrategy::PreferLocal => Side::Local,
+        ResolutionStrategy::PreferRemote => Side::Remote,
+        ResolutionStrategy::PreferNewer => {
+            if conflict.remote_modified.unwrap_or(i64::MIN) > conflict.local_modified.unwrap_or(i64::MIN) {
+                Side::Remote
+            } else {
+                Side::Local
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced_at(last_sync: i64) -> SyncBaseline {
+        SyncBaseline {
+            last_sync: Some(last_sync),
+            remote_etag: Some("\"v1\"".to_string()),
+            remote_modified: Some(last_sync),
+        }
+    }
+
+    fn local(modified: i64, size: i64) -> FileState {
+        FileState { size: Some(size), modified: Some(modified), ..Default::default() }
+    }
+
+    fn remote(etag: &str, size: i64) -> FileState {
+        FileState { size: Some(size), modified: Some(100), etag: Some(etag.to_string()), md5: etag_md5(etag) }
+    }
+
+    #[test]
+    fn test_conflict_matrix_after_sync() {
+        let base = synced_at(100);
+        let cases = [
+            (Some(local(90, 10)), Some(remote("\"v1\"", 10)), SyncDecision::UpToDate),
+            (Some(local(200, 12)), Some(remote("\"v1\"", 10)), SyncDecision::Upload),
+            (Some(local(90, 10)), Some(remote("\"v2\"", 11)), SyncDecision::Download),
+            (Some(local(200, 12)), Some(remote("\"v2\"", 11)), SyncDecision::Conflict(ConflictType::ModifiedBoth)),
+            (None, Some(remote("\"v2\"", 11)), SyncDecision::Conflict(ConflictType::LocalDeletedRemoteModified)),
+            (None, Some(remote("\"v1\"", 10)), SyncDecision::Download),
+            (Some(local(200, 12)), None, SyncDecision::Conflict(ConflictType::LocalModifiedRemoteDeleted)),
+            (Some(local(90, 10)), None, SyncDecision::Upload),
+            (Some(local(90, 10)), Some(remote("\"v1\"", 9)), SyncDecision::Conflict(ConflictType::DifferentSize)),
+            (None, None, SyncDecision::UpToDate),
+        ];
+        for (i, (l, r, expected)) in cases.iter().enumerate() {
+            assert_eq!(detect(&base, l.as_ref(), r.as_ref()), *expected, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn test_remote_change_without_etag_uses_modified_time() {
+        let base = SyncBaseline { last_sync: Some(100), remote_etag: None, remote_modified: Some(100) };
+        let mut r = FileState { size: Some(10), modified: Some(100), ..Default::default() };
+        assert_eq!(detect(&base, Some(&local(90, 10)), Some(&r)), SyncDecision::UpToDate);
+        r.modified = Some(150);
+        assert_eq!(detect(&base, Some(&local(90, 10)), Some(&r)), SyncDecision::Download);
+    }
+
+    #[test]
+    fn test_first_sync_compares_contents() {
+        let base = SyncBaseline::default();
+        let md5 = "0123456789abcdef0123456789abcdef";
+        let mut l = local(500, 10);
+        assert_eq!(detect(&base, Some(&l), None), SyncDecision::Upload);
+        assert_eq!(detect(&base, None, Some(&remote("\"x\"", 10))), SyncDecision::Download);
+        assert_eq!(detect(&base, Some(&l), Some(&remote("\"x\"", 11))), SyncDecision::Conflict(ConflictType::DifferentSize));
+
+        l.md5 = Some(md5.to_string());
+        assert_eq!(detect(&base, Some(&l), Some(&remote(&format!("\"{}\"", md5), 10))), SyncDecision::UpToDate);
+        assert_eq!(
+            detect(&base, Some(&l), Some(&remote("\"ffffffffffffffffffffffffffffffff\"", 10))),
+            SyncDecision::Conflict(ConflictType::DifferentHash)
+        );
+    }
+
+    #[test]
+    fn test_winner_by_strategy() {
+        let conflict = SyncConflict {
+            id: 1,
+            track_id: 1,
+            server_id: "srv".to_string(),
+            conflict_type: "modified_both".to_string(),
+            local_path: None,
+            remote_path: None,
+            local_size: None,
+            remote_size: None,
+            local_modified: Some(200),
+            remote_modified: Some(300),
+            local_hash: None,
+            remote_hash: None,
+            resolution_strategy: None,
+            resolved: false,
+            resolved_at: None,
+            created_at: 0,
+        };
+        assert_eq!(winner(ResolutionStrategy::PreferLocal, &conflict), Side::Local);
+        assert_eq!(winner(ResolutionStrategy::PreferRemote, &conflict), Side::Remote);
+        assert_eq!(winner(ResolutionStrategy::PreferNewer, &conflict), Side::Remote);
+
+        // 本地已删除时没有修改时间，远程更新
+        let deleted = SyncConflict { local_modified: None, remote_modified: Some(1), ..conflict };
+        assert_eq!(winner(ResolutionStrategy::PreferNewer, &deleted), Side::Remote);
+    }
+}
@@ -0,0 +1,7 @@
+// 双向同步模块
+// 本地曲目上传到WebDAV服务器，任务持久化在 sync_queue 表中，两端都被修改时记录到 sync_conflicts
+
+pub mod conflict;
+pub mod worker;
+
+pub use worker::{wake, TASK_UPLOAD};
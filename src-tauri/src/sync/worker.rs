@@ -0,0 +1,424 @@
+// 上传同步工作器
+//
+// 后台逐个执行 sync_queue 中的 upload 任务：
+// - 把本地曲目通过 WebDAV PUT 流式上传到目标服务器
+// - 上传过程中更新 bytes_transferred / progress_percent 并发送进度事件
+// - 上传前比较两端与上次同步的状态，两端都被修改时记录冲突而不是直接覆盖
+// - 成功后曲目 sync_status 置为 synced；失败按指数退避重试，超过 max_retries 后标记为 failed
+
+use super::conflict::{detect, etag_md5, winner, FileState, ResolutionStrategy, Side, SyncDecision};
+use crate::db::{SyncBaseline, SyncConflict, SyncTask};
+use crate::db_pool::DbPool;
+use crate::streaming::{bandwidth, TrafficClass};
+use crate::webdav::types::{UploadOptions, WebDAVConfig, WebDAVError};
+use crate::webdav::WebDAVClient;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+/// 上传任务类型
+pub const TASK_UPLOAD: &str = "upload";
+
+/// 第一次重试前的等待时间（秒），之后每次翻倍
+const RETRY_BASE_SECS: i64 = 30;
+
+/// 重试等待上限（秒）
+const RETRY_MAX_SECS: i64 = 3600;
+
+/// 队列为空时的轮询间隔
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+/// 有新任务入队时唤醒工作器
+static WAKE: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// 通知工作器处理新任务
+pub fn wake() {
+    WAKE.notify_one();
+}
+
+/// 第 retry_count 次失败后的等待时间（秒）
+pub fn retry_delay_secs(retry_count: i64) -> i64 {
+    let exponent = retry_count.clamp(0, 16) as u32;
+    (RETRY_BASE_SECS << exponent).min(RETRY_MAX_SECS)
+}
+
+/// 上传进度（sync-progress 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgress {
+    pub task_id: i64,
+    pub track_id: Option<i64>,
+    pub status: String,
+    pub bytes_transferred: i64,
+    pub file_size: i64,
+    pub progress_percent: i64,
+    pub error_message: Option<String>,
+}
+
+/// 运行上传工作器，直到 shutdown 为 true
+pub async fn run<F>(db: Arc<DbPool>, shutdown: &'static AtomicBool, emit: F)
+where
+    F: Fn(SyncProgress) + Send + Sync + 'static,
+{
+    let emit = Arc::new(emit);
+    if let Ok(count) = db.with(|db| db.reset_running_sync_tasks()) {
+        if count > 0 {
+            log::info!("🔁 {} 个中断的同步任务重新排队", count);
+        }
+    }
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let now = chrono::Utc::now().timestamp();
+        let next = db.with(|db| db.next_sync_task(TASK_UPLOAD, now));
+        match next {
+            Ok(Some(task)) => process_upload(&db, task, emit.clone()).await,
+            Ok(None) => {
+                let wait = db.with(|db| db.next_sync_retry_at(TASK_UPLOAD))
+                    .ok()
+                    .flatten()
+                    .map(|at| Duration::from_secs((at - now).max(1) as u64).min(IDLE_POLL))
+                    .unwrap_or(IDLE_POLL);
+                let _ = tokio::time::timeout(wait, WAKE.notified()).await;
+            }
+            Err(e) => {
+                log::error!("读取同步队列失败: {}", e);
+                tokio::time::sleep(IDLE_POLL).await;
+            }
+        }
+    }
+}
+
+/// 任务执行结果
+enum SyncOutcome {
+    /// 已同步，值为文件大小
+    Synced(u64),
+    /// 发现冲突，值为冲突记录ID
+    Conflict(i64),
+}
+
+async fn process_upload<F>(db: &Arc<DbPool>, task: SyncTask, emit: Arc<F>)
+where
+    F: Fn(SyncProgress) + Send + Sync + 'static,
+{
+    log::info!("⬆️ 开始上传: {} → {}:{}", task.source_path, task.server_id, task.target_path.as_deref().unwrap_or(""));
+
+    let file_size = std::fs::metadata(&task.source_path).map(|m| m.len() as i64).unwrap_or(0);
+    if let Ok(db) = db.lock() {
+        let _ = db.start_sync_task(task.id, file_size);
+        if let Some(track_id) = task.track_id {
+            let _ = db.set_track_sync_status(track_id, "syncing", None);
+        }
+    }
+    emit(SyncProgress {
+        task_id: task.id,
+        track_id: task.track_id,
+        status: "running".to_string(),
+        bytes_transferred: 0,
+        file_size,
+        progress_percent: 0,
+        error_message: None,
+    });
+
+    match upload(db, &task, emit.clone()).await {
+        Ok(SyncOutcome::Synced(size)) => {
+            let _ = db.with(|db| db.complete_sync_task(task.id));
+            log::info!("✅ 同步完成: {}", task.source_path);
+            emit(SyncProgress {
+                task_id: task.id,
+                track_id: task.track_id,
+                status: "completed".to_string(),
+                bytes_transferred: size as i64,
+                file_size: size as i64,
+                progress_percent: 100,
+                error_message: None,
+            });
+        }
+        Ok(SyncOutcome::Conflict(conflict_id)) => {
+            let message = format!("存在同步冲突 (#{})", conflict_id);
+            let _ = db.with(|db| db.cancel_sync_task(task.id, &message));
+            log::warn!("⚠️ 两端均已修改，跳过上传: {} (冲突 #{})", task.source_path, conflict_id);
+            emit(SyncProgress {
+                task_id: task.id,
+                track_id: task.track_id,
+                status: "conflict".to_string(),
+                bytes_transferred: 0,
+                file_size,
+                progress_percent: 0,
+                error_message: Some(message),
+            });
+        }
+        Err(e) => {
+            let error = e.to_string();
+            let next_retry_at = chrono::Utc::now().timestamp() + retry_delay_secs(task.retry_count);
+            let will_retry = db.with(|db| {
+                let will_retry = db.fail_sync_task(task.id, &error, next_retry_at).unwrap_or(false);
+                if let Some(track_id) = task.track_id {
+                    let status = if will_retry { "local_only" } else { "sync_error" };
+                    let _ = db.set_track_sync_status(track_id, status, None);
+                }
+                Ok(will_retry)
+            }).unwrap_or(true);
+            log::warn!("⚠️ 上传失败 ({}){}: {}", task.source_path, if will_retry { "，稍后重试" } else { "" }, error);
+            emit(SyncProgress {
+                task_id: task.id,
+                track_id: task.track_id,
+                status: if will_retry { "pending" } else { "failed" }.to_string(),
+                bytes_transferred: 0,
+                file_size,
+                progress_percent: 0,
+                error_message: Some(error),
+            });
+        }
+    }
+}
+
+/// 从remote_servers加载WebDAV客户端
+fn webdav_client(db: &Arc<DbPool>, server_id: &str) -> Result<WebDAVClient> {
+    let servers = db.with(|db| db.get_remote_servers())?;
+    let (_, _, server_type, config_json, _) = servers.into_iter()
+        .find(|(id, _, _, _, _)| id == server_id)
+        .ok_or_else(|| anyhow!("服务器不存在: {}", server_id))?;
+    if server_type != "webdav" {
+        return Err(anyhow!("服务器不支持上传: {} ({})", server_id, server_type));
+    }
+    let config: WebDAVConfig = serde_json::from_str(&config_json)?;
+    Ok(WebDAVClient::new(config)?)
+}
+
+fn local_state(path: &Path) -> Option<FileState> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    Some(FileState { size: Some(meta.len() as i64), modified, ..Default::default() })
+}
+
+/// 远程文件状态，文件不存在时返回 None
+async fn remote_state(client: &WebDAVClient, path: &str) -> Result<Option<FileState>> {
+    match client.get_file_info(path).await {
+        Ok(info) => Ok(Some(FileState {
+            size: info.size.map(|s| s as i64),
+            modified: info.last_modified,
+            md5: info.etag.as_deref().and_then(etag_md5),
+            etag: info.etag,
+        })),
+        Err(WebDAVError::FileNotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn file_md5(path: &Path) -> Result<String> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        context.consume(&buf[..n]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// 下载远程文件覆盖本地文件（先写临时文件再替换）
+async fn download_to_local(client: &WebDAVClient, remote_path: &str, local_path: &Path) -> Result<u64> {
+    use futures::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    if let Some(parent) = local_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let part_path = local_path.with_file_name(format!(
+        "{}.part",
+        local_path.file_name().and_then(|n| n.to_str()).unwrap_or("download")
+    ));
+
+    let result: Result<u64> = async {
+        let mut stream = Box::pin(client.download_stream(remote_path).await?);
+        let mut file = tokio::fs::File::create(&part_path).await?;
+        let mut written = 0u64;
+        let bandwidth = bandwidth::manager();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            bandwidth.acquire(TrafficClass::Sync, chunk.len()).await;
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await?;
+        Ok(written)
+    }.await;
+
+    match result {
+        Ok(written) => {
+            tokio::fs::rename(&part_path, local_path).await?;
+            Ok(written)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&part_path).await;
+            Err(e)
+        }
+    }
+}
+
+/// 传输完成后记录新的同步基线
+async fn record_synced(db: &Arc<DbPool>, client: &WebDAVClient, track_id: Option<i64>, server_id: &str, remote_path: &str) {
+    let Some(track_id) = track_id else { return };
+    let remote = remote_state(client, remote_path).await.ok().flatten().unwrap_or_default();
+    let _ = db.with(|db| db.record_track_sync(track_id, server_id, remote.etag.as_deref(), remote.modified));
+}
+
+async fn upload<F>(db: &Arc<DbPool>, task: &SyncTask, emit: Arc<F>) -> Result<SyncOutcome>
+where
+    F: Fn(SyncProgress) + Send + Sync + 'static,
+{
+    let target_path = task.target_path.clone().ok_or_else(|| anyhow!("缺少目标路径"))?;
+    let client = webdav_client(db, &task.server_id)?;
+    let source_path = Path::new(&task.source_path);
+
+    // 上传前先比较两端状态，避免覆盖远程的修改
+    let baseline = match task.track_id {
+        Some(track_id) => db.with(|db| db.get_sync_baseline(track_id))?,
+        None => SyncBaseline::default(),
+    };
+    let mut local = local_state(source_path);
+    let remote = remote_state(&client, &target_path).await?;
+    if let (Some(local), Some(remote)) = (local.as_mut(), remote.as_ref()) {
+        // 首次同步且大小相同时，若服务器ETag是MD5则比较内容
+        if baseline.last_sync.is_none() && local.size == remote.size && remote.md5.is_some() {
+            local.md5 = Some(file_md5(source_path).await?);
+        }
+    }
+
+    let size = match detect(&baseline, local.as_ref(), remote.as_ref()) {
+        SyncDecision::UpToDate => remote.and_then(|r| r.size).unwrap_or(0) as u64,
+        SyncDecision::Download => {
+            log::info!("⬇️ 远程文件较新，下载覆盖本地: {}", target_path);
+            download_to_local(&client, &target_path, source_path).await?
+        }
+        SyncDecision::Conflict(conflict_type) => {
+            let track_id = task.track_id.ok_or_else(|| anyhow!("同步冲突: {}", conflict_type.as_str()))?;
+            let local = local.unwrap_or_default();
+            let remote = remote.unwrap_or_default();
+            let conflict_id = db.with(|db| db.add_sync_conflict(&SyncConflict {
+                id: 0,
+                track_id,
+                server_id: task.server_id.clone(),
+                conflict_type: conflict_type.as_str().to_string(),
+                local_path: Some(task.source_path.clone()),
+                remote_path: Some(target_path.clone()),
+                local_size: local.size,
+                remote_size: remote.size,
+                local_modified: local.modified,
+                remote_modified: remote.modified,
+                local_hash: local.md5,
+                remote_hash: remote.etag,
+                resolution_strategy: None,
+                resolved: false,
+                resolved_at: None,
+                created_at: chrono::Utc::now().timestamp(),
+            }))?;
+            return Ok(SyncOutcome::Conflict(conflict_id));
+        }
+        SyncDecision::Upload => {
+            // 进度百分比变化时才写库和发事件
+            let last_percent = Arc::new(AtomicI64::new(-1));
+            let progress_db = db.clone();
+            let task_id = task.id;
+            let track_id = task.track_id;
+            let options = UploadOptions {
+                progress_callback: Some(Box::new(move |sent, total| {
+                    let percent = if total > 0 { (sent * 100 / total) as i64 } else { 0 };
+                    if last_percent.swap(percent, Ordering::Relaxed) == percent {
+                        return;
+                    }
+                    if let Ok(db) = progress_db.lock() {
+                        let _ = db.update_sync_progress(task_id, sent as i64, percent);
+                    }
+                    emit(SyncProgress {
+                        task_id,
+                        track_id,
+                        status: "running".to_string(),
+                        bytes_transferred: sent as i64,
+                        file_size: total as i64,
+                        progress_percent: percent,
+                        error_message: None,
+                    });
+                })),
+                traffic_class: Some(TrafficClass::Sync),
+                ..UploadOptions::default()
+            };
+            client.upload_local_file(&target_path, source_path, options).await?
+        }
+    };
+
+    record_synced(db, &client, task.track_id, &task.server_id, &target_path).await;
+    Ok(SyncOutcome::Synced(size))
+}
+
+/// 按策略解决冲突：执行胜出一方的传输，然后标记冲突已解决
+///
+/// 胜出方已被删除时：本地胜出则删除远程文件；远程胜出则只解除曲目与服务器的同步关系，不删除本地文件。
+pub async fn resolve_conflict(db: &Arc<DbPool>, conflict_id: i64, strategy: ResolutionStrategy) -> Result<()> {
+    let conflict = db.with(|db| db.get_sync_conflict(conflict_id))?
+        .ok_or_else(|| anyhow!("冲突不存在: {}", conflict_id))?;
+    if conflict.resolved {
+        return Ok(());
+    }
+    let local_path = conflict.local_path.clone().ok_or_else(|| anyhow!("冲突缺少本地路径"))?;
+    let remote_path = conflict.remote_path.clone().ok_or_else(|| anyhow!("冲突缺少远程路径"))?;
+    let client = webdav_client(db, &conflict.server_id)?;
+    let local_exists = Path::new(&local_path).exists();
+    let side = winner(strategy, &conflict);
+
+    let synced = match side {
+        Side::Local if local_exists => {
+            client.upload_local_file(&remote_path, Path::new(&local_path), UploadOptions::default()).await?;
+            true
+        }
+        Side::Local => {
+            match client.delete_file(&remote_path).await {
+                Ok(()) | Err(WebDAVError::FileNotFound { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            false
+        }
+        Side::Remote => match remote_state(&client, &remote_path).await? {
+            Some(_) => {
+                download_to_local(&client, &remote_path, Path::new(&local_path)).await?;
+                true
+            }
+            None => false,
+        },
+    };
+
+    if synced {
+        record_synced(db, &client, Some(conflict.track_id), &conflict.server_id, &remote_path).await;
+    }
+    db.with(|db| db.resolve_sync_conflict(
+        conflict_id,
+        strategy.as_str(),
+        if synced { "synced" } else { "local_only" },
+    ))?;
+    log::info!("🤝 冲突 #{} 已解决: {} ({:?} 胜出)", conflict_id, strategy.as_str(), side);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay_secs(0), 30);
+        assert_eq!(retry_delay_secs(1), 60);
+        assert_eq!(retry_delay_secs(2), 120);
+        assert_eq!(retry_delay_secs(20), RETRY_MAX_SECS);
+    }
+}
@@ -0,0 +1,305 @@
+// 歌词、封面和元数据写回音频文件标签
+//
+// 把数据库中的歌词、封面和编辑过的元数据写入文件自身的标签，让其他播放器也能看到：
+// - 歌词写入 ItemKey::Lyrics（ID3v2 的 USLT、MP4 的 ©lyr、Vorbis 的 LYRICS）
+// - 封面写为 CoverFront 图片，替换原有的封面
+// - 标题、艺术家、专辑、专辑艺术家、流派、年份、音轨号
+// - 评分写入 ItemKey::Popularimeter（0-100，与扫描时读取的范围一致）
+// - 先在同目录复制临时文件并写入，成功后原子重命名覆盖原文件，失败不会损坏原文件
+// - 写入后更新数据库中的文件状态，下次扫描不会当作外部修改
+
+use crate::cover_cache::CoverSize;
+use crate::db::TrackMetadataUpdate;
+use crate::db_pool::DbPool;
+use crate::remote_source;
+use anyhow::{anyhow, Result};
+use lofty::config::WriteOptions;
+use lofty::picture::{Picture, PictureType};
+use lofty::prelude::*;
+use lofty::tag::{Tag, TagType};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// 要写入的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagFields {
+    pub lyrics: bool,
+    pub cover: bool,
+}
+
+/// 单个文件的写入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct TagWriteResult {
+    pub track_id: i64,
+    pub path: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 写入后的文件状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrittenFileState {
+    pub mtime: Option<i64>,
+    pub size: i64,
+    pub hash: String,
+}
+
+/// 按数据库中的内容写入一首曲目的标签
+///
+/// 只写入已有的内容；要求的内容都不存在时返回错误
+pub fn write_track_tags(db: &DbPool, track_id: i64, fields: TagFields) -> Result<()> {
+    let (path, lyrics, cover) = {
+        let db = db.lock().map_err(|e| anyhow!(e.to_string()))?;
+        let track = db.get_track_by_id(track_id)?
+            .ok_or_else(|| anyhow!("曲目不存在: {}", track_id))?;
+        let lyrics = if fields.lyrics {
+            db.get_lyrics_by_track_id(track_id)?
+                .map(|lyrics| lyrics.content)
+                .filter(|content| !content.trim().is_empty())
+        } else {
+            None
+        };
+        let cover = match (&track.cover_id, fields.cover) {
+            (Some(cover_id), true) => db.get_cover(cover_id, CoverSize::Original)?.map(|cover| cover.data),
+            _ => None,
+        };
+        (track.path, lyrics, cover)
+    };
+
+    if lyrics.is_none() && cover.is_none() {
+        return Err(anyhow!(match (fields.lyrics, fields.cover) {
+            (true, false) => "没有可写入的歌词",
+            (false, true) => "没有可写入的封面",
+            _ => "没有可写入的歌词或封面",
+        }));
+    }
+
+    let state = write_tags(Path::new(&path), lyrics.as_deref(), cover.as_deref())?;
+    db.lock().map_err(|e| anyhow!(e.to_string()))?
+        .record_tag_write(track_id, state.mtime, state.size, &state.hash)?;
+    log::info!("🏷️ 已写入标签: {}", path);
+    Ok(())
+}
+
+/// 批量写入，逐个文件报告结果
+pub fn write_tracks_tags(db: &DbPool, track_ids: &[i64], fields: TagFields) -> Vec<TagWriteResult> {
+    track_ids.iter()
+        .map(|&track_id| {
+            let path = db.lock().ok()
+                .and_then(|db| db.get_track_by_id(track_id).ok().flatten())
+                .map(|track| track.path)
+                .unwrap_or_default();
+            match write_track_tags(db, track_id, fields) {
+                Ok(()) => TagWriteResult { track_id, path, success: true, error: None },
+                Err(e) => {
+                    log::warn!("⚠️ 写入标签失败 {}: {}", path, e);
+                    TagWriteResult { track_id, path, success: false, error: Some(e.to_string()) }
+                }
+            }
+        })
+        .collect()
+}
+
+/// 把编辑后的元数据写入多首曲目的文件，逐个文件报告结果
+pub fn write_tracks_metadata(db: &DbPool, track_ids: &[i64], update: &TrackMetadataUpdate) -> Vec<TagWriteResult> {
+    track_ids.iter()
+        .map(|&track_id| {
+            let path = db.lock().ok()
+                .and_then(|db| db.get_track_by_id(track_id).ok().flatten())
+                .map(|track| track.path)
+                .unwrap_or_default();
+            let result = write_metadata(Path::new(&path), update).and_then(|state| {
+                db.lock().map_err(|e| anyhow!(e.to_string()))?
+                    .record_tag_write(track_id, state.mtime, state.size, &state.hash)
+            });
+            match result {
+                Ok(()) => TagWriteResult { track_id, path, success: true, error: None },
+                Err(e) => {
+                    log::warn!("⚠️ 写入元数据失败 {}: {}", path, e);
+                    TagWriteResult { track_id, path, success: false, error: Some(e.to_string()) }
+                }
+            }
+        })
+        .collect()
+}
+
+/// 把编辑后的元数据写入文件，返回写入后的文件状态
+pub fn write_metadata(path: &Path, update: &TrackMetadataUpdate) -> Result<WrittenFileState> {
+    modify_file_tags(path, |tag| apply_metadata(tag, update))
+}
+
+/// 把评分写入文件（None 删除评分），返回写入后的文件状态
+pub fn write_rating(path: &Path, rating: Option<u8>) -> Result<WrittenFileState> {
+    modify_file_tags(path, |tag| apply_rating(tag, rating))
+}
+
+/// 把歌词和封面写入文件，返回写入后的文件状态
+pub fn write_tags(path: &Path, lyrics: Option<&str>, cover: Option<&[u8]>) -> Result<WrittenFileState> {
+    modify_file_tags(path, |tag| apply_lyrics_and_cover(tag, lyrics, cover))
+}
+
+/// 复制到临时文件修改标签，成功后原子替换原文件
+fn modify_file_tags<F>(path: &Path, modify: F) -> Result<WrittenFileState>
+where
+    F: FnOnce(&mut Tag) -> Result<()>,
+{
+    if remote_source::is_remote_track_path(&path.to_string_lossy()) {
+        return Err(anyhow!("只能写入本地文件"));
+    }
+    let metadata = std::fs::metadata(path).map_err(|e| anyhow!("无法读取文件: {}", e))?;
+    if metadata.permissions().readonly() {
+        return Err(anyhow!("文件为只读"));
+    }
+
+    let temp_path = temp_path_for(path)?;
+    let result = std::fs::copy(path, &temp_path)
+        .map_err(|e| anyhow!("创建临时文件失败: {}", e))
+        .and_then(|_| apply_to_file(&temp_path, modify))
+        .and_then(|_| {
+            std::fs::File::open(&temp_path)?.sync_all()?;
+            std::fs::rename(&temp_path, path).map_err(|e| anyhow!("替换原文件失败: {}", e))
+        });
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    let metadata = std::fs::metadata(path)?;
+    Ok(WrittenFileState {
+        mtime: crate::library::file_mtime(&metadata),
+        size: metadata.len() as i64,
+        hash: crate::library::file_md5(path)?,
+    })
+}
+
+/// 同目录下的隐藏临时文件，保证重命名在同一文件系统内完成
+fn temp_path_for(path: &Path) -> Result<PathBuf> {
+    let file_name = path.file_name()
+        .ok_or_else(|| anyhow!("无效的文件路径: {}", path.display()))?;
+    Ok(path.with_file_name(format!(".{}.windchime-tmp", file_name.to_string_lossy())))
+}
+
+/// 在临时文件上修改标签；文件没有标签时按格式创建主标签
+fn apply_to_file<F>(path: &Path, modify: F) -> Result<()>
+where
+    F: FnOnce(&mut Tag) -> Result<()>,
+{
+    let mut tagged_file = lofty::read_from_path(path).map_err(|e| anyhow!("不支持的文件格式: {}", e))?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file.primary_tag_mut()
+        .ok_or_else(|| anyhow!("无法创建标签"))?;
+
+    modify(tag)?;
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| anyhow!("写入标签失败: {}", e))
+}
+
+/// 修改元数据，空字符串表示删除该字段
+fn apply_metadata(tag: &mut Tag, update: &TrackMetadataUpdate) -> Result<()> {
+    let text = |value: &Option<String>| value.as_deref().map(|v| v.trim().to_string());
+
+    if let Some(title) = text(&update.title) {
+        tag.set_title(title);
+    }
+    match text(&update.artist) {
+        Some(artist) if artist.is_empty() => tag.remove_artist(),
+        Some(artist) => tag.set_artist(artist),
+        None => {}
+    }
+    match text(&update.album) {
+        Some(album) if album.is_empty() => tag.remove_album(),
+        Some(album) => tag.set_album(album),
+        None => {}
+    }
+    match text(&update.genre) {
+        Some(genre) if genre.is_empty() => tag.remove_genre(),
+        Some(genre) => tag.set_genre(genre),
+        None => {}
+    }
+    match text(&update.album_artist) {
+        Some(album_artist) if album_artist.is_empty() => tag.remove_key(&ItemKey::AlbumArtist),
+        Some(album_artist) => {
+            if !tag.insert_text(ItemKey::AlbumArtist, album_artist) {
+                return Err(anyhow!("{:?} 标签不支持专辑艺术家", tag.tag_type()));
+            }
+        }
+        None => {}
+    }
+    if let Some(year) = update.year {
+        tag.set_year(year as u32);
+    }
+    if let Some(track_number) = update.track_number {
+        tag.set_track(track_number as u32);
+    }
+    Ok(())
+}
+
+fn apply_rating(tag: &mut Tag, rating: Option<u8>) -> Result<()> {
+    match rating {
+        None => tag.remove_key(&ItemKey::Popularimeter),
+        Some(stars) => {
+            if !tag.insert_text(ItemKey::Popularimeter, (stars as u32 * 20).to_string()) {
+                return Err(anyhow!("{:?} 标签不支持评分", tag.tag_type()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_lyrics_and_cover(tag: &mut Tag, lyrics: Option<&str>, cover: Option<&[u8]>) -> Result<()> {
+    if let Some(lyrics) = lyrics {
+        if !tag.insert_text(ItemKey::Lyrics, lyrics.to_string()) {
+            return Err(anyhow!("{:?} 标签不支持内嵌歌词", tag.tag_type()));
+        }
+    }
+
+    if let Some(cover) = cover {
+        if !supports_pictures(tag.tag_type()) {
+            return Err(anyhow!("{:?} 标签不支持内嵌封面", tag.tag_type()));
+        }
+        let mut picture = Picture::from_reader(&mut &cover[..])
+            .map_err(|e| anyhow!("无法识别封面图片: {}", e))?;
+        picture.set_pic_type(PictureType::CoverFront);
+        tag.remove_picture_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+    }
+    Ok(())
+}
+
+fn supports_pictures(tag_type: TagType) -> bool {
+    matches!(tag_type, TagType::Id3v2 | TagType::Mp4Ilst | TagType::VorbisComments | TagType::Ape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_path_stays_in_same_directory() {
+        let temp = temp_path_for(Path::new("/music/album/song.flac")).unwrap();
+        assert_eq!(temp, Path::new("/music/album/.song.flac.windchime-tmp"));
+    }
+
+    #[test]
+    fn test_unsupported_file_is_left_untouched() {
+        let dir = std::env::temp_dir().join(format!("windchime-tag-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("not-audio.mp3");
+        std::fs::write(&path, b"definitely not audio").unwrap();
+
+        assert!(write_tags(&path, Some("[00:01.00]hi"), None).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"definitely not audio");
+        assert!(!temp_path_for(&path).unwrap().exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remote_paths_are_rejected() {
+        let err = write_tags(Path::new("webdav://server/song.mp3"), Some("x"), None).unwrap_err();
+        assert!(err.to_string().contains("本地"));
+    }
+}
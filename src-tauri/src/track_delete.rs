@@ -0,0 +1,129 @@
+// 从曲库删除曲目（可选同时删除文件）
+//
+// - 曲目记录在一个事务中删除，歌单项、收藏、歌词、播放历史等由外键级联删除
+// - delete_files 时先把本地文件移到系统回收站，移动失败的曲目保留记录，避免曲库和磁盘不一致
+// - 文件已不存在视为成功；远程曲目（WebDAV / Subsonic）只删除记录
+
+use crate::db_pool::DbPool;
+use crate::error::AppResult;
+use crate::remote_source;
+use serde::Serialize;
+use std::path::Path;
+
+/// 单个文件的删除结果
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDeleteResult {
+    pub track_id: i64,
+    pub path: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 删除结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeleteTracksReport {
+    /// 从曲库删除的曲目数
+    pub removed: usize,
+    /// 各文件的删除结果（delete_files 为 false 时为空）
+    pub files: Vec<FileDeleteResult>,
+}
+
+/// 删除曲目，delete_files 时用 remove_file 删除本地文件
+pub fn delete_tracks<F>(db: &DbPool, track_ids: &[i64], delete_files: bool, remove_file: F) -> AppResult<DeleteTracksReport>
+where
+    F: Fn(&Path) -> Result<(), String>,
+{
+    let paths = db.with(|db| db.get_track_paths(track_ids))?;
+
+    let mut report = DeleteTracksReport::default();
+    let mut removable = Vec::with_capacity(paths.len());
+    for (track_id, path) in paths {
+        if !delete_files || remote_source::is_remote_track_path(&path) {
+            removable.push(track_id);
+            continue;
+        }
+
+        let file = Path::new(&path);
+        let result = if file.exists() { remove_file(file) } else { Ok(()) };
+        match result {
+            Ok(()) => {
+                removable.push(track_id);
+                report.files.push(FileDeleteResult { track_id, path, success: true, error: None });
+            }
+            Err(e) => {
+                log::warn!("⚠️ 删除文件失败 {}: {}", path, e);
+                report.files.push(FileDeleteResult { track_id, path, success: false, error: Some(e) });
+            }
+        }
+    }
+
+    report.removed = db.with(|db| db.delete_tracks(&removable))?;
+    log::info!("🗑️ 已从曲库删除 {} 首曲目", report.removed);
+    Ok(report)
+}
+
+/// 把文件移到系统回收站
+pub fn move_to_trash(path: &Path) -> Result<(), String> {
+    trash::delete(path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::player::Track;
+
+    fn track(path: &str) -> Track {
+        let mut track = Track::new(0, path.to_string());
+        track.title = Some("Song".to_string());
+        track
+    }
+
+    #[test]
+    fn test_delete_tracks_with_files() {
+        let dir = std::env::temp_dir().join(format!("windchime-delete-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let kept = dir.join("kept.mp3");
+        let trashed = dir.join("trashed.mp3");
+        let missing = dir.join("missing.mp3");
+        std::fs::write(&kept, b"kept").unwrap();
+        std::fs::write(&trashed, b"trashed").unwrap();
+
+        let pool = DbPool::single(Database::new(":memory:").unwrap());
+        let ids: Vec<i64> = [&kept, &trashed, &missing]
+            .iter()
+            .map(|path| pool.with(|db| db.insert_track(&track(&path.to_string_lossy()))).unwrap())
+            .chain([pool.with(|db| db.insert_track(&track("webdav://1/music/remote.mp3"))).unwrap()])
+            .collect();
+
+        // 只删除记录时不动文件
+        let report = delete_tracks(&pool, &ids[1..2], false, |_| Err("不应删除文件".to_string())).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(report.files.is_empty());
+        assert!(trashed.exists());
+
+        let trashed_id = pool.with(|db| db.insert_track(&track(&trashed.to_string_lossy()))).unwrap();
+        let ids = [ids[0], trashed_id, ids[2], ids[3]];
+        let report = delete_tracks(&pool, &ids, true, |path| {
+            if path == kept.as_path() {
+                Err("文件被占用".to_string())
+            } else {
+                std::fs::remove_file(path).map_err(|e| e.to_string())
+            }
+        })
+        .unwrap();
+
+        // 移动失败的曲目保留记录，已不存在的文件视为成功，远程曲目不删除文件
+        assert_eq!(report.removed, 3);
+        assert_eq!(report.files.len(), 3);
+        assert!(!report.files[0].success);
+        assert_eq!(report.files[0].error.as_deref(), Some("文件被占用"));
+        assert!(report.files[1].success && report.files[2].success);
+        assert!(kept.exists());
+        assert!(!trashed.exists());
+        let remaining = pool.with(|db| db.get_track_paths(&ids)).unwrap();
+        assert_eq!(remaining, vec![(ids[0], kept.to_string_lossy().to_string())]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+// 音频转码 - 导出时把曲目转换为MP3或Ogg Vorbis
+//
+// - 解码使用播放器的解码器（symphonia），编码使用 LAME 和 libvorbis
+// - MP3最多两个声道，多声道音源只保留前两个声道；LAME按码率自动重采样到MP3支持的采样率
+// - 源文件的标签和内嵌封面复制到输出文件（MP3为ID3v2，Ogg为Vorbis Comments）
+// - 先写入同目录的临时文件，完成后再重命名；取消或失败时删除临时文件
+
+use crate::player::AudioDecoder;
+use anyhow::{anyhow, Result};
+use lofty::config::WriteOptions;
+use lofty::prelude::*;
+use lofty::tag::TagType;
+use mp3lame_encoder::{Bitrate, FlushNoGap, InterleavedPcm, MonoPcm, Quality};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::{NonZeroU32, NonZeroU8};
+use std::path::{Path, PathBuf};
+
+/// 默认码率（kbps）
+pub const DEFAULT_BITRATE: u32 = 192;
+
+/// 每次送入编码器的帧数
+const FRAMES_PER_BLOCK: usize = 4096;
+
+/// 转码目标格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscodeFormat {
+    Mp3,
+    Ogg,
+}
+
+impl TranscodeFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscodeFormat::Mp3 => "mp3",
+            TranscodeFormat::Ogg => "ogg",
+        }
+    }
+
+    /// 检查码率（kbps）：MP3只支持标准码率，Ogg Vorbis为45到500
+    pub fn validate_bitrate(self, bitrate: u32) -> Result<(), String> {
+        let valid = match self {
+            TranscodeFormat::Mp3 => mp3_bitrate(bitrate).is_some(),
+            TranscodeFormat::Ogg => (45..=500).contains(&bitrate),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(match self {
+                TranscodeFormat::Mp3 => format!("不支持的MP3码率: {}kbps（可选 32-320 的标准码率）", bitrate),
+                TranscodeFormat::Ogg => format!("Ogg Vorbis码率必须在45到500kbps之间: {}", bitrate),
+            })
+        }
+    }
+
+    fn tag_type(self) -> TagType {
+        match self {
+            TranscodeFormat::Mp3 => TagType::Id3v2,
+            TranscodeFormat::Ogg => TagType::VorbisComments,
+        }
+    }
+}
+
+fn mp3_bitrate(kbps: u32) -> Option<Bitrate> {
+    Some(match kbps {
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => return None,
+    })
+}
+
+/// 把 source 转码为 dest，复制标签和封面；should_stop 返回 true 时中止并返回错误
+pub fn transcode_file(
+    source: &Path,
+    dest: &Path,
+    format: TranscodeFormat,
+    bitrate: u32,
+    should_stop: &dyn Fn() -> bool,
+) -> Result<()> {
+    format.validate_bitrate(bitrate).map_err(|e| anyhow!(e))?;
+    let decoder = AudioDecoder::new(source).decode()?;
+
+    let temp = temp_path_for(dest)?;
+    let result = File::create(&temp)
+        .map_err(|e| anyhow!("创建输出文件失败: {}", e))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            match format {
+                TranscodeFormat::Mp3 => encode_mp3(decoder, &mut writer, bitrate, should_stop)?,
+                TranscodeFormat::Ogg => encode_ogg(decoder, &mut writer, bitrate, should_stop)?,
+            }
+            writer.into_inner().map_err(|e| anyhow!("写入输出文件失败: {}", e))?.sync_all()?;
+            copy_tags(source, &temp, format.tag_type())?;
+            std::fs::rename(&temp, dest).map_err(|e| anyhow!("保存输出文件失败: {}", e))
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// 同目录下的临时文件（.part），写完后重命名
+pub fn temp_path_for(dest: &Path) -> Result<PathBuf> {
+    let file_name = dest.file_name().ok_or_else(|| anyhow!("无效的文件路径: {}", dest.display()))?;
+    Ok(dest.with_file_name(format!("{}.part", file_name.to_string_lossy())))
+}
+
+/// 按帧读取交错样本，每块最多 FRAMES_PER_BLOCK 帧
+fn read_block<S: Iterator<Item = i16>>(samples: &mut S, channels: usize, block: &mut Vec<i16>) {
+    block.clear();
+    block.extend(samples.by_ref().take(FRAMES_PER_BLOCK * channels));
+    // 丢弃末尾不完整的帧
+    block.truncate(block.len() / channels * channels);
+}
+
+fn encode_mp3<S, W>(source: S, out: &mut W, bitrate: u32, should_stop: &dyn Fn() -> bool) -> Result<()>
+where
+    S: Source<Item = i16>,
+    W: Write,
+{
+    let channels = source.channels().max(1) as usize;
+    let out_channels = channels.min(2);
+
+    let mut builder = mp3lame_encoder::Builder::new().ok_or_else(|| anyhow!("无法创建MP3编码器"))?;
+    builder.set_num_channels(out_channels as u8).map_err(|e| anyhow!("MP3编码器不支持该声道数: {:?}", e))?;
+    builder.set_sample_rate(source.sample_rate()).map_err(|e| anyhow!("MP3编码器不支持该采样率: {:?}", e))?;
+    builder
+        .set_brate(mp3_bitrate(bitrate).unwrap_or(Bitrate::Kbps192))
+        .map_err(|e| anyhow!("设置MP3码率失败: {:?}", e))?;
+    builder.set_quality(Quality::Good).map_err(|e| anyhow!("设置MP3编码质量失败: {:?}", e))?;
+    let mut encoder = builder.build().map_err(|e| anyhow!("初始化MP3编码器失败: {:?}", e))?;
+
+    let mut samples = source;
+    let mut block = Vec::with_capacity(FRAMES_PER_BLOCK * channels);
+    let mut pcm = Vec::with_capacity(FRAMES_PER_BLOCK * out_channels);
+    let mut encoded = Vec::new();
+    loop {
+        if should_stop() {
+            return Err(anyhow!("已取消"));
+        }
+        read_block(&mut samples, channels, &mut block);
+        if block.is_empty() {
+            break;
+        }
+
+        pcm.clear();
+        pcm.extend(block.chunks_exact(channels).flat_map(|frame| frame[..out_channels].iter().copied()));
+        encoded.clear();
+        let result = if out_channels == 1 {
+            encoder.encode_to_vec(MonoPcm(&pcm), &mut encoded)
+        } else {
+            encoder.encode_to_vec(InterleavedPcm(&pcm), &mut encoded)
+        };
+        result.map_err(|e| anyhow!("MP3编码失败: {:?}", e))?;
+        out.write_all(&encoded)?;
+    }
+
+    encoded.clear();
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut encoded)
+        .map_err(|e| anyhow!("MP3编码失败: {:?}", e))?;
+    out.write_all(&encoded)?;
+    Ok(())
+}
+
+fn encode_ogg<S, W>(source: S, out: &mut W, bitrate: u32, should_stop: &dyn Fn() -> bool) -> Result<()>
+where
+    S: Source<Item = i16>,
+    W: Write,
+{
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = NonZeroU32::new(source.sample_rate()).ok_or_else(|| anyhow!("无效的采样率"))?;
+    let channel_count = u8::try_from(channels)
+        .ok()
+        .and_then(NonZeroU8::new)
+        .ok_or_else(|| anyhow!("不支持的声道数: {}", channels))?;
+    let target_bitrate = NonZeroU32::new(bitrate * 1000).ok_or_else(|| anyhow!("无效的码率"))?;
+
+    let mut encoder = vorbis_rs::VorbisEncoderBuilder::new(sample_rate, channel_count, out)?
+        .bitrate_management_strategy(vorbis_rs::VorbisBitrateManagementStrategy::Vbr { target_bitrate })
+        .build()?;
+
+    let mut samples = source;
+    let mut block = Vec::with_capacity(FRAMES_PER_BLOCK * channels);
+    let mut planar = vec![Vec::with_capacity(FRAMES_PER_BLOCK); channels];
+    loop {
+        if should_stop() {
+            return Err(anyhow!("已取消"));
+        }
+        read_block(&mut samples, channels, &mut block);
+        if block.is_empty() {
+            break;
+        }
+
+        for channel in planar.iter_mut() {
+            channel.clear();
+        }
+        for frame in block.chunks_exact(channels) {
+            for (channel, &sample) in planar.iter_mut().zip(frame) {
+                channel.push(sample as f32 / 32768.0);
+            }
+        }
+        encoder.encode_audio_block(&planar)?;
+    }
+
+    encoder.finish()?;
+    Ok(())
+}
+
+/// 把源文件的主标签（含封面）转换为 tag_type 写入 dest；源文件没有标签时不做任何事
+pub fn copy_tags(source: &Path, dest: &Path, tag_type: TagType) -> Result<()> {
+    let source_file = lofty::read_from_path(source).map_err(|e| anyhow!("读取源文件标签失败: {}", e))?;
+    let Some(tag) = source_file.primary_tag().or_else(|| source_file.first_tag()) else {
+        return Ok(());
+    };
+
+    let mut tag = tag.clone();
+    tag.re_map(tag_type);
+    tag.save_to_path(dest, WriteOptions::default())
+        .map_err(|e| anyhow!("写入标签失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitrate_validation() {
+        assert!(TranscodeFormat::Mp3.validate_bitrate(DEFAULT_BITRATE).is_ok());
+        assert!(TranscodeFormat::Mp3.validate_bitrate(320).is_ok());
+        assert!(TranscodeFormat::Mp3.validate_bitrate(200).is_err());
+        assert!(TranscodeFormat::Ogg.validate_bitrate(200).is_ok());
+        assert!(TranscodeFormat::Ogg.validate_bitrate(20).is_err());
+    }
+
+    #[test]
+    fn test_read_block_drops_partial_frame() {
+        let mut samples = (0..11i16).into_iter();
+        let mut block = Vec::new();
+        read_block(&mut samples, 2, &mut block);
+        assert_eq!(block.len(), 10);
+        read_block(&mut samples, 2, &mut block);
+        assert!(block.is_empty());
+    }
+
+    #[test]
+    fn test_temp_path() {
+        let temp = temp_path_for(Path::new("/usb/01 - A - B.mp3")).unwrap();
+        assert_eq!(temp, Path::new("/usb/01 - A - B.mp3.part"));
+    }
+}
@@ -0,0 +1,237 @@
+// 系统托盘 - 播放控制菜单、当前曲目提示、关闭窗口时隐藏到托盘
+
+use crate::player::{PlayerCommand, Track};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Wry};
+
+/// 关闭窗口时隐藏到托盘的设置键
+pub const SETTING_CLOSE_TO_TRAY: &str = "ui.close_to_tray";
+
+const MENU_SHOW: &str = "tray_show";
+const MENU_PLAY_PAUSE: &str = "tray_play_pause";
+const MENU_NEXT: &str = "tray_next";
+const MENU_PREVIOUS: &str = "tray_previous";
+const MENU_NOW_PLAYING: &str = "tray_now_playing";
+const MENU_QUIT: &str = "tray_quit";
+
+const APP_NAME: &str = "WindChime Player";
+
+/// 关闭按钮是否隐藏到托盘
+static CLOSE_TO_TRAY: AtomicBool = AtomicBool::new(false);
+
+/// 正在真正退出（托盘菜单“退出”），此时关闭窗口不再隐藏
+static QUITTING: AtomicBool = AtomicBool::new(false);
+
+static TRAY: Lazy<Mutex<Option<TrayHandles>>> = Lazy::new(|| Mutex::new(None));
+
+/// 托盘图标和需要动态更新的菜单项
+///
+/// 托盘和菜单的方法会切换到主线程执行，调用前先克隆出来并释放锁，
+/// 避免与主线程中的菜单事件互相等待
+#[derive(Clone)]
+struct TrayHandles {
+    tray: TrayIcon<Wry>,
+    play_pause: MenuItem<Wry>,
+    now_playing: MenuItem<Wry>,
+    icon: Option<Image<'static>>,
+    /// 暂停时使用的半透明图标
+    paused_icon: Option<Image<'static>>,
+    is_playing: bool,
+    track_label: Option<String>,
+}
+
+/// 创建托盘图标和菜单
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, MENU_SHOW, "显示窗口", true, None::<&str>)?;
+    let now_playing = MenuItem::with_id(app, MENU_NOW_PLAYING, "未在播放", false, None::<&str>)?;
+    let play_pause = MenuItem::with_id(app, MENU_PLAY_PAUSE, "播放", true, None::<&str>)?;
+    let previous = MenuItem::with_id(app, MENU_PREVIOUS, "上一首", true, None::<&str>)?;
+    let next = MenuItem::with_id(app, MENU_NEXT, "下一首", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "退出", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[
+        &now_playing,
+        &PredefinedMenuItem::separator(app)?,
+        &play_pause,
+        &previous,
+        &next,
+        &PredefinedMenuItem::separator(app)?,
+        &show,
+        &quit,
+    ])?;
+
+    let icon = app.default_window_icon().map(|icon| icon.clone().to_owned());
+    let paused_icon = icon.as_ref().map(|icon| {
+        let mut rgba = icon.rgba().to_vec();
+        dim_rgba(&mut rgba);
+        Image::new_owned(rgba, icon.width(), icon.height())
+    });
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip(APP_NAME)
+        .menu(&menu)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::DoubleClick { button: MouseButton::Left, .. } = event {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = paused_icon.clone() {
+        builder = builder.icon(icon);
+    }
+    let tray = builder.build(app)?;
+
+    *TRAY.lock() = Some(TrayHandles {
+        tray,
+        play_pause,
+        now_playing,
+        icon,
+        paused_icon,
+        is_playing: false,
+        track_label: None,
+    });
+    log::info!("✅ 系统托盘已创建");
+    Ok(())
+}
+
+/// 设置关闭按钮是否隐藏到托盘
+pub fn set_close_to_tray(enabled: bool) {
+    CLOSE_TO_TRAY.store(enabled, Ordering::Relaxed);
+}
+
+pub fn close_to_tray() -> bool {
+    CLOSE_TO_TRAY.load(Ordering::Relaxed)
+}
+
+/// 标记为真正退出
+pub fn mark_quitting() {
+    QUITTING.store(true, Ordering::Relaxed);
+}
+
+/// 关闭窗口时是否隐藏到托盘而不是退出（托盘不可用时总是退出）
+pub fn should_hide_on_close() -> bool {
+    close_to_tray() && !QUITTING.load(Ordering::Relaxed) && TRAY.lock().is_some()
+}
+
+/// 曲目变化时更新菜单和提示
+pub fn update_track(track: Option<&Track>) {
+    let handles = TRAY.lock().as_mut().map(|handles| {
+        handles.track_label = track.map(track_label);
+        handles.clone()
+    });
+    if let Some(handles) = handles {
+        refresh(&handles);
+    }
+}
+
+/// 播放状态变化时更新菜单、提示和图标
+pub fn update_state(is_playing: bool) {
+    let handles = TRAY.lock().as_mut()
+        .filter(|handles| handles.is_playing != is_playing)
+        .map(|handles| {
+            handles.is_playing = is_playing;
+            handles.clone()
+        });
+    if let Some(handles) = handles {
+        refresh(&handles);
+    }
+}
+
+/// 显示并聚焦主窗口
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    let command = match id {
+        MENU_SHOW => {
+            show_main_window(app);
+            return;
+        }
+        MENU_QUIT => {
+            crate::quit_app(app);
+            return;
+        }
+        MENU_PLAY_PAUSE => {
+            let is_playing = TRAY.lock().as_ref().is_some_and(|handles| handles.is_playing);
+            if is_playing { PlayerCommand::Pause } else { PlayerCommand::Resume }
+        }
+        MENU_NEXT => PlayerCommand::Next,
+        MENU_PREVIOUS => PlayerCommand::Previous,
+        _ => return,
+    };
+
+    match crate::PLAYER_TX.get() {
+        Some(tx) => {
+            if let Err(e) = tx.send(command) {
+                log::warn!("⚠️ 托盘发送播放命令失败: {}", e);
+            }
+        }
+        None => log::warn!("⚠️ 播放器尚未初始化，忽略托盘命令"),
+    }
+}
+
+fn refresh(handles: &TrayHandles) {
+    let _ = handles.play_pause.set_text(if handles.is_playing { "暂停" } else { "播放" });
+    let _ = handles.now_playing.set_text(handles.track_label.as_deref().unwrap_or("未在播放"));
+    let _ = handles.tray.set_tooltip(Some(tooltip(handles.track_label.as_deref(), handles.is_playing)));
+    let icon = if handles.is_playing { &handles.icon } else { &handles.paused_icon };
+    if let Some(icon) = icon.clone() {
+        let _ = handles.tray.set_icon(Some(icon));
+    }
+}
+
+/// 菜单中显示的曲目：标题 - 艺术家
+fn track_label(track: &Track) -> String {
+    let title = track.title.clone().unwrap_or_else(|| {
+        std::path::Path::new(&track.path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| track.path.clone())
+    });
+    match &track.artist {
+        Some(artist) => format!("{} - {}", title, artist),
+        None => title,
+    }
+}
+
+fn tooltip(track_label: Option<&str>, is_playing: bool) -> String {
+    match track_label {
+        Some(label) => format!("{}\n{} {}", APP_NAME, if is_playing { "▶" } else { "⏸" }, label),
+        None => APP_NAME.to_string(),
+    }
+}
+
+/// 图标透明度减半，表示暂停
+fn dim_rgba(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel[3] /= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tooltip() {
+        assert_eq!(tooltip(None, true), APP_NAME);
+        assert_eq!(tooltip(Some("晴天 - 周杰伦"), true), format!("{}\n▶ 晴天 - 周杰伦", APP_NAME));
+        assert_eq!(tooltip(Some("晴天 - 周杰伦"), false), format!("{}\n⏸ 晴天 - 周杰伦", APP_NAME));
+    }
+
+    #[test]
+    fn test_dim_rgba_halves_alpha() {
+        let mut rgba = vec![10, 20, 30, 255, 1, 2, 3, 0];
+        dim_rgba(&mut rgba);
+        assert_eq!(rgba, vec![10, 20, 30, 127, 1, 2, 3, 0]);
+    }
+}
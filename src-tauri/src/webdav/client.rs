@@ -1,7 +1,7 @@
 // WebDAV client core implementation
 // Focused on WebDAV protocol operations
 
-use super::{auth::AuthManager, types::*};
+use super::{auth::AuthManager, tls::{self, CertificateRejections}, types::*};
 // 已删除：旧版 provider trait，现在使用 remote_adapter
 use futures::stream::Stream;
 use reqwest::{header::*, Client as HttpClient, Response};
@@ -15,6 +15,8 @@ pub struct WebDAVClient {
     config: WebDAVConfig,
     auth_manager: AuthManager,
     stats: Arc<tokio::sync::RwLock<WebDAVStats>>,
+    /// 证书校验失败时记下的服务器证书，用于生成 UntrustedCertificate 错误
+    tls_rejections: Arc<CertificateRejections>,
 }
 
 impl WebDAVClient {
@@ -44,10 +46,9 @@ impl WebDAVClient {
             },
         };
         
-        if !config.verify_ssl {
-            builder = builder.danger_accept_invalid_certs(true);
-            log::warn!("SSL certificate verification disabled");
-        }
+        // 证书校验、自定义CA和证书固定都在 tls 模块的校验器中处理
+        let tls_rejections = Arc::new(CertificateRejections::default());
+        builder = builder.use_preconfigured_tls(tls::client_config(&config, tls_rejections.clone())?);
         
         let http_client = builder.build()
             .map_err(|e| WebDAVError::ConfigError(format!("HTTP client creation failed: {}", e)))?;
@@ -60,14 +61,15 @@ impl WebDAVClient {
             config,
             auth_manager,
             stats: Arc::new(tokio::sync::RwLock::new(WebDAVStats::default())),
+            tls_rejections,
         };
         
         log::info!("WebDAV client created: {}", client.config.name);
         Ok(client)
     }
     
-    /// Test connection
-    pub async fn test_connection(&self) -> WebDAVResult<bool> {
+    /// Test connection, returns the negotiated HTTP version (e.g. "HTTP/2")
+    pub async fn test_connection(&self) -> WebDAVResult<String> {
         log::info!("Testing WebDAV connection: {}", self.config.url);
         
         let start_time = Instant::now();
@@ -78,7 +80,8 @@ impl WebDAVClient {
         
         match result {
             Ok(response) => {
-                log::info!("WebDAV connection test successful: {}", response.status());
+                let protocol = protocol_name(response.version());
+                log::info!("WebDAV connection test successful: {} ({})", response.status(), protocol);
                 
                 if let Some(allow_header) = response.headers().get("allow") {
                     if let Ok(methods) = allow_header.to_str() {
@@ -86,7 +89,7 @@ impl WebDAVClient {
                     }
                 }
                 
-                Ok(true)
+                Ok(protocol.to_string())
             }
             Err(e) => {
                 log::error!("WebDAV connection test failed: {}", e);
@@ -179,6 +182,10 @@ impl WebDAVClient {
         
         self.update_stats(start_time, true).await;
         
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(WebDAVError::FileNotFound { path: path.to_string() });
+        }
+        
         let response_text = response.text().await?;
         let mut files = self.parse_propfind_response(&response_text)?;
         
@@ -200,6 +207,15 @@ impl WebDAVClient {
         let response = self.send_request(WebDAVMethod::Get, path, None, None).await?;
         self.update_stats(start_time, true).await;
         
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(WebDAVError::FileNotFound { path: path.to_string() });
+        } else if !response.status().is_success() {
+            return Err(WebDAVError::HttpStatusError {
+                status: response.status().as_u16(),
+                message: format!("Download failed: {}", response.status()),
+            });
+        }
+        
         // 返回响应流
         use futures::stream::TryStreamExt;
         Ok(response.bytes_stream().map_err(WebDAVError::from))
@@ -229,12 +245,49 @@ impl WebDAVClient {
         Ok(response.bytes_stream().map_err(WebDAVError::from))
     }
     
+    /// GET with an optional raw Range header, returning the response as-is
+    /// (the caller forwards status and Content-Range, e.g. the cast media server)
+    pub async fn get_with_range(&self, path: &str, range: Option<&str>) -> WebDAVResult<Response> {
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(
+                "Range",
+                HeaderValue::from_str(range)
+                    .map_err(|e| WebDAVError::ConfigError(format!("无效的Range请求: {}", e)))?
+            );
+        }
+        
+        let start_time = Instant::now();
+        let response = self.send_request(WebDAVMethod::Get, path, Some(headers), None).await?;
+        self.update_stats(start_time, response.status().is_success()).await;
+        Ok(response)
+    }
+    
     /// Upload file
     #[allow(dead_code)]
     pub async fn upload_file<R>(&self, path: &str, reader: R, options: UploadOptions) -> WebDAVResult<()>
     where
         R: AsyncRead + Send + Sync + 'static,
     {
+        self.upload_reader(path, reader, None, options).await
+    }
+    
+    /// Upload local file with known length (progress callback receives sent/total bytes)
+    pub async fn upload_local_file(&self, path: &str, local_path: &std::path::Path, options: UploadOptions) -> WebDAVResult<u64> {
+        let file = tokio::fs::File::open(local_path).await?;
+        let size = file.metadata().await?.len();
+        self.upload_reader(path, file, Some(size), options).await?;
+        Ok(size)
+    }
+    
+    /// PUT with streaming body
+    async fn upload_reader<R>(&self, path: &str, reader: R, content_length: Option<u64>, options: UploadOptions) -> WebDAVResult<()>
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        
         log::debug!("Uploading file: {}", path);
         
         let start_time = Instant::now();
@@ -252,15 +305,46 @@ impl WebDAVClient {
             );
         }
         
+        // Many servers (e.g. nginx dav) reject chunked PUT bodies
+        if let Some(length) = content_length {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(length));
+        }
+        
         if !options.overwrite {
             headers.insert("If-None-Match", HeaderValue::from_static("*"));
         }
         
-        let stream = ReaderStream::new(reader);
-        let body = reqwest::Body::wrap_stream(stream);
+        let stream = match options.chunk_size {
+            Some(capacity) => ReaderStream::with_capacity(reader, capacity),
+            None => ReaderStream::new(reader),
+        };
+        let stream = match options.traffic_class {
+            Some(traffic_class) => stream
+                .then(move |chunk| async move {
+                    if let Ok(chunk) = &chunk {
+                        crate::streaming::bandwidth::manager().acquire(traffic_class, chunk.len()).await;
+                    }
+                    chunk
+                })
+                .boxed(),
+            None => stream.boxed(),
+        };
+        let body = match options.progress_callback {
+            Some(callback) => {
+                let sent = AtomicU64::new(0);
+                let total = content_length.unwrap_or(0);
+                reqwest::Body::wrap_stream(stream.inspect(move |chunk| {
+                    if let Ok(chunk) = chunk {
+                        let sent = sent.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+                        callback(sent, total);
+                    }
+                }))
+            }
+            None => reqwest::Body::wrap_stream(stream),
+        };
         
         let response = self.send_request_with_body(WebDAVMethod::Put, path, Some(headers), body).await?;
-        self.update_stats(start_time, true).await;
+        self.update_stats(start_time, response.status().is_success()).await;
         
         if response.status().is_success() {
             log::info!("File upload successful: {}", path);
@@ -386,7 +470,8 @@ impl WebDAVClient {
         
         log::debug!("Sending WebDAV request: {} {}", method, url);
         
-        let response = request.send().await?;
+        let response = request.send().await
+            .map_err(|e| self.tls_rejections.map_send_error(e))?;
         
         if response.status() == 401 {
             return Err(WebDAVError::AuthenticationFailed("Authentication failed".to_string()));
@@ -396,7 +481,6 @@ impl WebDAVClient {
     }
     
     /// Send WebDAV request with body
-    #[allow(dead_code)]
     async fn send_request_with_body(
         &self,
         method: WebDAVMethod,
@@ -423,7 +507,8 @@ impl WebDAVClient {
         
         log::debug!("Sending WebDAV request with body: {} {}", method, url);
         
-        let response = request.send().await?;
+        let response = request.send().await
+            .map_err(|e| self.tls_rejections.map_send_error(e))?;
         
         if response.status() == 401 {
             return Err(WebDAVError::AuthenticationFailed("Authentication failed".to_string()));
@@ -465,7 +550,6 @@ impl WebDAVClient {
     }
     
     /// Ensure parent directories exist
-    #[allow(dead_code)]
     async fn ensure_parent_directories(&self, path: &str) -> WebDAVResult<()> {
         let mut directories_to_create = Vec::new();
         let mut current_path = path;
@@ -520,6 +604,18 @@ impl WebDAVClient {
     }
 }
 
+/// Display name of a negotiated HTTP version
+fn protocol_name(version: reqwest::Version) -> &'static str {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_11 => "HTTP/1.1",
+        reqwest::Version::HTTP_2 => "HTTP/2",
+        reqwest::Version::HTTP_3 => "HTTP/3",
+        _ => "unknown",
+    }
+}
+
 impl Drop for WebDAVClient {
     fn drop(&mut self) {
         log::debug!("WebDAV client dropped: {}", self.config.name);
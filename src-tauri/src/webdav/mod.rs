@@ -8,6 +8,7 @@ pub mod safe_stream;
 // 已删除：resilient_client（高级弹性客户端，项目使用简化版）
 pub mod xml_parser;
 pub mod remote_adapter;
+pub mod tls;
 
 // pub use auth::*; // 未使用
 pub use client::WebDAVClient;
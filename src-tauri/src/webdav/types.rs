@@ -45,6 +45,10 @@ pub enum WebDAVError {
     
     #[error("HTTP方法错误: {0}")]
     HttpMethodError(#[from] http::method::InvalidMethod),
+    
+    /// 服务器证书未通过校验，fingerprint 为服务器证书的SHA-256指纹，用户确认后可固定该证书
+    #[error("服务器证书不受信任: {reason}（指纹 {fingerprint}）")]
+    UntrustedCertificate { fingerprint: String, reason: String },
 }
 
 /// HTTP协议版本偏好
@@ -80,11 +84,25 @@ pub struct WebDAVConfig {
     pub password: String,
     pub timeout_seconds: u64,
     pub max_redirects: u32,
+    #[serde(default = "default_verify_ssl")]
     pub verify_ssl: bool,
     pub user_agent: String,
     /// HTTP协议偏好（可选，默认HTTP/1.1）
     #[serde(default)]
     pub http_protocol: HttpProtocolPreference,
+    /// 允许证书主机名与服务器地址不匹配（如通过IP访问NAS）
+    #[serde(default)]
+    pub accept_invalid_hostnames: bool,
+    /// 自定义CA证书（PEM内容或PEM文件路径）
+    #[serde(default)]
+    pub ca_certificate: Option<String>,
+    /// 用户确认信任的服务器证书SHA-256指纹，匹配时不再校验证书链
+    #[serde(default)]
+    pub pinned_certificate: Option<String>,
+}
+
+fn default_verify_ssl() -> bool {
+    true
 }
 
 impl Default for WebDAVConfig {
@@ -101,7 +119,36 @@ impl Default for WebDAVConfig {
             verify_ssl: true,
             user_agent: "WindChimePlayer/1.0".to_string(),
             http_protocol: HttpProtocolPreference::default(),
+            accept_invalid_hostnames: false,
+            ca_certificate: None,
+            pinned_certificate: None,
+        }
+    }
+}
+
+/// 未保存的服务器（前端直接传入地址和凭据）的连接选项，字段与 WebDAVConfig 同名，未提供时使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebDAVConnectionOptions {
+    pub verify_ssl: Option<bool>,
+    pub accept_invalid_hostnames: Option<bool>,
+    pub ca_certificate: Option<String>,
+    pub pinned_certificate: Option<String>,
+    pub http_protocol: Option<HttpProtocolPreference>,
+}
+
+impl WebDAVConnectionOptions {
+    pub fn apply_to(self, config: &mut WebDAVConfig) {
+        if let Some(verify_ssl) = self.verify_ssl {
+            config.verify_ssl = verify_ssl;
         }
+        if let Some(accept) = self.accept_invalid_hostnames {
+            config.accept_invalid_hostnames = accept;
+        }
+        if let Some(protocol) = self.http_protocol {
+            config.http_protocol = protocol;
+        }
+        config.ca_certificate = self.ca_certificate.or(config.ca_certificate.take());
+        config.pinned_certificate = self.pinned_certificate.or(config.pinned_certificate.take());
     }
 }
 
@@ -279,18 +326,18 @@ impl fmt::Display for RangeRequest {
     }
 }
 
-/// 文件上传进度回调
-#[allow(dead_code)]
+/// 文件上传进度回调（已发送字节数，总字节数）
 pub type ProgressCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
 
 /// WebDAV上传选项
-#[allow(dead_code)]
 pub struct UploadOptions {
     pub overwrite: bool,
     pub create_directories: bool,
     pub content_type: Option<String>,
     pub progress_callback: Option<ProgressCallback>,
     pub chunk_size: Option<usize>,
+    /// 按该流量类别参与全局带宽限制，None表示不限速
+    pub traffic_class: Option<crate::streaming::TrafficClass>,
 }
 
 impl Default for UploadOptions {
@@ -301,6 +348,7 @@ impl Default for UploadOptions {
             content_type: None,
             progress_callback: None,
             chunk_size: Some(1024 * 1024), // 1MB chunks
+            traffic_class: None,
         }
     }
 }
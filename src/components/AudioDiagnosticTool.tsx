@@ -5,6 +5,7 @@
 import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { Play, AlertTriangle, CheckCircle, RefreshCw, Wrench, XCircle } from 'lucide-react';
+import { errorMessage } from '../utils/appError';
 
 interface AudioDevice {
   name: string;
@@ -70,7 +71,7 @@ export function AudioDiagnosticTool() {
         has_devices: false,
         default_device: null,
         available_devices: [],
-        issues: [`诊断失败: ${error}`],
+        issues: [`诊断失败: ${errorMessage(error)}`],
         recommendations: ['请检查系统音频设置'],
       });
     } finally {
@@ -115,7 +116,7 @@ export function AudioDiagnosticTool() {
         has_devices: false,
         default_device: null,
         available_devices: [],
-        issues: [`调试失败: ${error}`],
+        issues: [`调试失败: ${errorMessage(error)}`],
         recommendations: ['请检查系统音频设置'],
       });
     } finally {
@@ -142,7 +143,7 @@ export function AudioDiagnosticTool() {
       console.error('音频修复失败:', error);
       setFixResult({
         success: false,
-        message: `修复失败: ${error}`,
+        message: `修复失败: ${errorMessage(error)}`,
         fixed_issues: [],
       });
     } finally {
@@ -168,7 +169,7 @@ export function AudioDiagnosticTool() {
       console.error('重置失败:', error);
       setFixResult({
         success: false,
-        message: `重置失败: ${error}`,
+        message: `重置失败: ${errorMessage(error)}`,
         fixed_issues: [],
       });
     } finally {
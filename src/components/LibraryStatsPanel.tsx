@@ -4,6 +4,7 @@
 import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { Music, Album, User, Clock, TrendingUp, Loader2 } from 'lucide-react';
+import { errorMessage } from '../utils/appError';
 
 interface LibraryStats {
   total_tracks: number;
@@ -28,7 +29,7 @@ export function LibraryStatsPanel() {
       setStats(result);
     } catch (err) {
       console.error('加载库统计失败:', err);
-      setError(`加载失败: ${err}`);
+      setError(`加载失败: ${errorMessage(err)}`);
     } finally {
       setIsLoading(false);
     }
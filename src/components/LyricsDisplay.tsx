@@ -2,6 +2,7 @@ import React, { useState, useEffect, useRef, useCallback, useMemo } from 'react'
 import { invoke } from '@tauri-apps/api/core';
 import { ImmersiveLyricsPanel } from './immersive';
 import type { Track } from '../types/music';
+import { errorMessage } from '../utils/appError';
 
 export interface LyricLine {
   timestamp_ms: number;
@@ -320,7 +321,7 @@ function LyricsDisplay({
                       setError(null);
                       return;
                     } catch (e) {
-                      const errorStr = String(e);
+                      const errorStr = errorMessage(e);
                       console.log(`❌ 文件不存在或无法读取: ${testPath}`, errorStr);
                       
                       // 特别处理编码问题
@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useToast } from '../contexts/ToastContext';
 import { useLibrary } from '../contexts/LibraryContext';
+import { errorMessage } from '../utils/appError';
 
 interface MusicFolderManagerProps {
   className?: string;
@@ -157,19 +158,11 @@ export default function MusicFolderManager({ className = '' }: MusicFolderManage
       console.error('文件夹选择或启动扫描失败:', error);
       
       // 更详细的错误处理
-      let errorMessage = '扫描失败';
-      if (error instanceof Error) {
-        errorMessage += ': ' + error.message;
-        
-        // 特殊处理扫描中的错误
-        if (error.message.includes('Scan already in progress')) {
-          errorMessage = '扫描正在进行中，请等待当前扫描完成后再试';
-        }
-      } else {
-        errorMessage += ': 未知错误';
-      }
-      
-      toast.error(errorMessage);
+      // 特殊处理扫描中的错误
+      const message = errorMessage(error);
+      toast.error(message.includes('Scan already in progress')
+        ? '扫描正在进行中，请等待当前扫描完成后再试'
+        : `扫描失败: ${message}`);
     }
   };
 
@@ -203,7 +196,7 @@ export default function MusicFolderManager({ className = '' }: MusicFolderManage
       toast.success(`成功删除了 ${deletedCount} 首曲目`);
     } catch (error) {
       console.error('删除文件夹失败:', error);
-      toast.error('删除失败: ' + (error instanceof Error ? error.message : '未知错误'));
+      toast.error('删除失败: ' + errorMessage(error));
     } finally {
       setIsLoading(false);
     }
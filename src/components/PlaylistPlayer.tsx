@@ -5,6 +5,7 @@ import ImmersiveLyricsView from './ImmersiveLyricsView';
 import LyricsManager from './LyricsManager';
 import { useToast } from '../contexts/ToastContext';
 import { usePlaybackState, usePlaybackPosition } from '../contexts/PlaybackContext';
+import { errorKind, errorMessage } from '../utils/appError';
 
 interface Track {
   id: number;
@@ -528,12 +529,11 @@ export default function PlaylistPlayer({ currentTrack }: PlaylistPlayerProps) {
       console.error('[Seek] Jump failed:', error);
       
       // 如果是 Rust seek 失败（流式播放不支持），提示用户
-      const errorMessage = error instanceof Error ? error.message : String(error);
-      if (errorMessage.includes('音频尚未缓存') || errorMessage.includes('seek暂时不可用')) {
+      if (errorKind(error) === 'NotReady') {
         console.log('[Seek] Tip: Web Audio engine loading in background, fast seeking available soon');
         toast.info('正在加载高速跳转功能，请稍候...', 2000);
       } else {
-        toast.error(`跳转失败: ${errorMessage}`, 3000);
+        toast.error(`跳转失败: ${errorMessage(error)}`, 3000);
       }
     }
   };
@@ -652,9 +652,9 @@ export default function PlaylistPlayer({ currentTrack }: PlaylistPlayerProps) {
       setAudioDeviceError(null);
       setShowAudioTroubleshooter(false);
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : String(error);
-      setAudioDeviceError(errorMessage);
-      toast.error(`音频设备检测失败: ${errorMessage}`);
+      const message = errorMessage(error);
+      setAudioDeviceError(message);
+      toast.error(`音频设备检测失败: ${message}`);
     }
   };
 
@@ -5,6 +5,7 @@ import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { Search, Loader2 } from 'lucide-react';
 import { useToast } from '../contexts/ToastContext';
+import { errorMessage } from '../utils/appError';
 
 interface RemoteScanButtonProps {
   serverId: string;
@@ -47,7 +48,7 @@ export function RemoteScanButton({ serverId, serverName, onScanComplete }: Remot
     } catch (error) {
       console.error('扫描失败:', error);
       toast.error(
-        `扫描失败: ${error}`,
+        `扫描失败: ${errorMessage(error)}`,
         5000
       );
     } finally {
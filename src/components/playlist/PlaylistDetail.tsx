@@ -26,6 +26,7 @@ import {
   Pin,
   PinOff,
 } from 'lucide-react';
+import { errorMessage } from '../../utils/appError';
 
 interface PlaylistDetailProps {
   playlistId: number;
@@ -151,7 +152,7 @@ export const PlaylistDetail: React.FC<PlaylistDetailProps> = ({
       await removeTrackFromPlaylist(playlist.id, trackId);
     } catch (err) {
       console.error('移除曲目失败:', err);
-      alert('移除曲目失败：' + (err instanceof Error ? err.message : String(err)));
+      alert('移除曲目失败：' + errorMessage(err));
     }
   };
 
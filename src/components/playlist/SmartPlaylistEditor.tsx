@@ -104,6 +104,8 @@ const fieldOptions: { value: RuleField; label: string }[] = [
   { value: 'title', label: '标题' },
   { value: 'artist', label: '艺术家' },
   { value: 'album', label: '专辑' },
+  { value: 'genre', label: '流派' },
+  { value: 'year', label: '年份' },
   { value: 'duration', label: '时长' },
   { value: 'date_added', label: '添加日期' },
   { value: 'last_played', label: '最后播放' },
@@ -143,9 +145,9 @@ const getOperatorOptions = (field: RuleField): { value: RuleOperator; label: str
     { value: 'is_false' as RuleOperator, label: '否' },
   ];
 
-  if (field === 'title' || field === 'artist' || field === 'album') {
+  if (field === 'title' || field === 'artist' || field === 'album' || field === 'genre') {
     return stringOps;
-  } else if (field === 'duration' || field === 'play_count') {
+  } else if (field === 'duration' || field === 'play_count' || field === 'year') {
     return numberOps;
   } else if (field === 'date_added' || field === 'last_played') {
     return dateOps;
@@ -530,8 +532,8 @@ export const SmartPlaylistEditor: React.FC<SmartPlaylistEditorProps> = ({
                 {/* 值输入 */}
                 {rule.field !== 'is_favorite' && (
                   <input
-                    type={rule.field === 'duration' || rule.field === 'play_count' ? 'number' : 'text'}
-                    value={rule.value}
+                    type={rule.field === 'duration' || rule.field === 'play_count' || rule.field === 'year' ? 'number' : 'text'}
+                    value={typeof rule.value === 'object' ? rule.value.last_n_days : rule.value}
                     onChange={(e) => handleUpdateRule(index, { value: e.target.value })}
                     placeholder="输入值..."
                     className="flex-1 px-3 py-2 bg-slate-50 dark:bg-gray-800 border border-slate-300 dark:border-gray-700 rounded-lg 
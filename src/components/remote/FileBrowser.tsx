@@ -1,6 +1,8 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useToast } from '../../contexts/ToastContext';
+import { errorMessage } from '../../utils/appError';
 
 interface RemoteFile {
   path: string;
@@ -31,7 +33,19 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
     loadDirectory(currentPath);
   }, [currentPath]);
 
-  const loadDirectory = async (path: string) => {
+  // 后端返回缓存的旧列表后，后台刷新发现变化时推送新列表
+  useEffect(() => {
+    const unlisten = listen<{ server_id: string; path: string; files: RemoteFile[] }>('remote-directory-changed', (event) => {
+      if (event.payload.server_id === serverId && event.payload.path === currentPath) {
+        setFiles(event.payload.files);
+      }
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [serverId, currentPath]);
+
+  const loadDirectory = async (path: string, forceRefresh = false) => {
     setLoading(true);
     setError(null);
     
@@ -39,10 +53,11 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
       const data = await invoke<RemoteFile[]>('remote_browse_directory', {
         serverId,
         path,
+        forceRefresh,
       });
       setFiles(data);
     } catch (err) {
-      setError(`加载失败: ${err}`);
+      setError(`加载失败: ${errorMessage(err)}`);
       console.error('加载目录失败:', err);
     } finally {
       setLoading(false);
@@ -93,7 +108,7 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
       }
     } catch (error) {
       console.error('扫描失败:', error);
-      toast.error(`扫描失败: ${error}`, 5000);
+      toast.error(`扫描失败: ${errorMessage(error)}`, 5000);
     }
   };
 
@@ -116,11 +131,15 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
         throw new Error('服务器配置不存在');
       }
 
+      const credentials = await invoke<{ username: string; password: string }>('remote_get_server_credentials', { serverId });
+
       await invoke('webdav_create_directory', {
         url: server.config.url,
-        username: server.config.username,
-        password: server.config.password,
+        username: credentials.username,
+        password: credentials.password,
         dirPath: newPath,
+        serverId,
+        options: server.config,
       });
 
       toast.success(`文件夹 "${newDirName}" 创建成功`, 2000);
@@ -129,7 +148,7 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
       loadDirectory(currentPath); // 刷新列表
     } catch (error) {
       console.error('创建目录失败:', error);
-      toast.error(`创建失败: ${error}`, 3000);
+      toast.error(`创建失败: ${errorMessage(error)}`, 3000);
     }
   };
 
@@ -147,18 +166,22 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
         throw new Error('服务器配置不存在');
       }
 
+      const credentials = await invoke<{ username: string; password: string }>('remote_get_server_credentials', { serverId });
+
       await invoke('webdav_delete_file', {
         url: server.config.url,
-        username: server.config.username,
-        password: server.config.password,
+        username: credentials.username,
+        password: credentials.password,
         filePath: file.path,
+        serverId,
+        options: server.config,
       });
 
       toast.success(`${file.is_directory ? '文件夹' : '文件'} "${file.name}" 已删除`, 2000);
       loadDirectory(currentPath); // 刷新列表
     } catch (error) {
       console.error('删除失败:', error);
-      toast.error(`删除失败: ${error}`, 3000);
+      toast.error(`删除失败: ${errorMessage(error)}`, 3000);
     }
   };
 
@@ -281,7 +304,7 @@ export function FileBrowser({ serverId, serverName, onClose }: FileBrowserProps)
             <div className="text-center py-12">
               <p className="text-red-600 dark:text-red-400">{error}</p>
               <button
-                onClick={() => loadDirectory(currentPath)}
+                onClick={() => loadDirectory(currentPath, true)}
                 className="mt-4 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
               >
                 重试
@@ -2,6 +2,7 @@
 
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../../utils/appError';
 
 interface CacheConfig {
   enabled: boolean;
@@ -75,7 +76,7 @@ export default function AudioCacheSettings() {
       loadStats(); // 刷新统计
     } catch (error) {
       console.error('保存缓存配置失败:', error);
-      alert(`❌ 保存失败: ${error}`);
+      alert(`❌ 保存失败: ${errorMessage(error)}`);
     } finally {
       setIsSaving(false);
     }
@@ -92,7 +93,7 @@ export default function AudioCacheSettings() {
       loadStats();
     } catch (error) {
       console.error('清空缓存失败:', error);
-      alert(`❌ 清空失败: ${error}`);
+      alert(`❌ 清空失败: ${errorMessage(error)}`);
     }
   };
 
@@ -103,7 +104,7 @@ export default function AudioCacheSettings() {
       loadStats();
     } catch (error) {
       console.error('自动清理失败:', error);
-      alert(`❌ 清理失败: ${error}`);
+      alert(`❌ 清理失败: ${errorMessage(error)}`);
     }
   };
 
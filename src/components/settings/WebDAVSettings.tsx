@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { FileBrowser } from '../remote/FileBrowser';
 import { useRemoteSource, RemoteServer } from '../../contexts/RemoteSourceContext';
 import { RemoteScanButton } from '../RemoteScanButton';
+import { errorKind, errorMessage, type AppError } from '../../utils/appError';
 
 export default function WebDAVSettings() {
   const {
@@ -56,7 +57,7 @@ export default function WebDAVSettings() {
       alert('✅ 服务器添加成功！\n\n您现在可以点击"浏览"查看文件，或点击"扫描"将音乐添加到库中。');
     } catch (error) {
       console.error('添加服务器失败:', error);
-      const errorMsg = typeof error === 'string' ? error : String(error);
+      const errorMsg = errorMessage(error);
       alert(`❌ 添加失败\n\n${errorMsg}\n\n请检查：\n• URL 格式是否正确\n• 用户名和密码是否正确\n• 服务器是否可访问`);
     } finally {
       setIsTestingConnection(false);
@@ -76,7 +77,7 @@ export default function WebDAVSettings() {
       alert('✅ 服务器更新成功！');
     } catch (error) {
       console.error('更新服务器失败:', error);
-      const errorMsg = typeof error === 'string' ? error : String(error);
+      const errorMsg = errorMessage(error);
       alert(`❌ 更新失败\n\n${errorMsg}\n\n请检查：\n• URL 格式是否正确\n• 用户名和密码是否正确\n• 服务器是否可访问`);
     } finally {
       setIsTestingConnection(false);
@@ -96,7 +97,7 @@ export default function WebDAVSettings() {
       ]);
     } catch (error) {
       console.error('删除服务器失败:', error);
-      alert(`❌ 删除失败: ${error}`);
+      alert(`❌ 删除失败: ${errorMessage(error)}`);
     }
   };
 
@@ -414,6 +415,117 @@ function ServerList({ servers, onBrowse, onEdit, onDelete, onAddClick }: {
   );
 }
 
+/**
+ * 测试 WebDAV 连接；服务器证书不受信任时询问用户，确认后固定该证书指纹并重新测试
+ * 返回测试结果和（可能已写入 pinned_certificate 的）配置，保存服务器时一并保存指纹
+ */
+async function testWithCertificatePrompt<T extends { pinned_certificate?: string | null }>(
+  config: T
+): Promise<{ message: string; config: T }> {
+  const test = (candidate: T) => invoke<string>('remote_test_connection', {
+    serverType: 'webdav',
+    configJson: JSON.stringify(candidate),
+  });
+
+  try {
+    return { message: await test(config), config };
+  } catch (error) {
+    const fingerprint = errorKind(error) === 'UntrustedCertificate'
+      ? (error as AppError).detail?.fingerprint as string | undefined
+      : undefined;
+    if (!fingerprint || fingerprint === config.pinned_certificate) {
+      throw error;
+    }
+    const accepted = window.confirm(
+      `服务器证书不受信任。\n\n证书指纹 (SHA-256):\n${fingerprint}\n\n` +
+      '请确认这是你自己的服务器（如局域网 NAS 的自签名证书）。是否信任此证书？'
+    );
+    if (!accepted) {
+      throw error;
+    }
+    const pinned = { ...config, pinned_certificate: fingerprint };
+    return { message: await test(pinned), config: pinned };
+  }
+}
+
+// 连接选项：HTTP 协议、证书校验、自定义 CA
+function TransportOptionsFields({ config, setConfig, disabled }: any) {
+  const inputClass = "w-full px-3 py-2 rounded-lg border border-slate-300 dark:border-dark-400 bg-white dark:bg-dark-300 text-slate-900 dark:text-dark-900 focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent outline-none transition";
+
+  return (
+    <details className="rounded-lg border border-slate-200 dark:border-dark-400 px-3 py-2">
+      <summary className="text-sm font-medium text-slate-700 dark:text-dark-800 cursor-pointer">
+        连接选项
+      </summary>
+      <div className="space-y-3 pt-3">
+        <div>
+          <label className="block text-sm font-medium text-slate-700 dark:text-dark-800 mb-1">
+            HTTP 协议
+          </label>
+          <select
+            value={config.http_protocol}
+            onChange={e => setConfig({ ...config, http_protocol: e.target.value })}
+            className={inputClass}
+            disabled={disabled}
+          >
+            <option value="http1">仅 HTTP/1.1（兼容性最好）</option>
+            <option value="auto">自动协商</option>
+            <option value="http2_preferred">HTTP/2</option>
+          </select>
+        </div>
+
+        <label className="flex items-center space-x-2 text-sm text-slate-700 dark:text-dark-800">
+          <input
+            type="checkbox"
+            checked={config.verify_ssl}
+            onChange={e => setConfig({ ...config, verify_ssl: e.target.checked })}
+            disabled={disabled}
+          />
+          <span>校验服务器证书</span>
+        </label>
+
+        <label className="flex items-center space-x-2 text-sm text-slate-700 dark:text-dark-800">
+          <input
+            type="checkbox"
+            checked={config.accept_invalid_hostnames}
+            onChange={e => setConfig({ ...config, accept_invalid_hostnames: e.target.checked })}
+            disabled={disabled || !config.verify_ssl}
+          />
+          <span>允许证书主机名不匹配（如通过 IP 地址访问）</span>
+        </label>
+
+        <div>
+          <label className="block text-sm font-medium text-slate-700 dark:text-dark-800 mb-1">
+            自定义 CA 证书
+          </label>
+          <textarea
+            value={config.ca_certificate}
+            onChange={e => setConfig({ ...config, ca_certificate: e.target.value })}
+            placeholder="粘贴 PEM 内容，或填写 PEM 文件路径"
+            rows={3}
+            className={`${inputClass} font-mono text-xs`}
+            disabled={disabled || !config.verify_ssl}
+          />
+        </div>
+
+        {config.pinned_certificate && (
+          <div className="flex items-start justify-between text-xs text-slate-500 dark:text-dark-700">
+            <span className="break-all">已信任的证书: {config.pinned_certificate}</span>
+            <button
+              type="button"
+              onClick={() => setConfig({ ...config, pinned_certificate: null })}
+              className="ml-2 text-red-600 dark:text-red-400 hover:underline flex-shrink-0"
+              disabled={disabled}
+            >
+              移除
+            </button>
+          </div>
+        )}
+      </div>
+    </details>
+  );
+}
+
 // 编辑服务器对话框组件
 function EditServerDialog({ server, onUpdate, onClose, isLoading }: any) {
   const [config, setConfig] = useState(() => ({
@@ -421,13 +533,24 @@ function EditServerDialog({ server, onUpdate, onClose, isLoading }: any) {
     url: server.config.url || 'https://',
     mount_path: server.config.mount_path || '',
     username: server.config.username || '',
-    password: server.config.password || '',
+    password: '',
     timeout_seconds: server.config.timeout_seconds || 30,
     verify_ssl: server.config.verify_ssl !== undefined ? server.config.verify_ssl : true,
+    http_protocol: server.config.http_protocol || 'http1',
+    accept_invalid_hostnames: server.config.accept_invalid_hostnames || false,
+    ca_certificate: server.config.ca_certificate || '',
+    pinned_certificate: server.config.pinned_certificate || null,
     max_redirects: server.config.max_redirects || 5,
     user_agent: server.config.user_agent || 'WindChimePlayer/1.0',
     server_id: server.config.server_id || server.id,
   }));
+
+  // 服务器列表不含密码，编辑时单独获取
+  useEffect(() => {
+    invoke<{ username: string; password: string }>('remote_get_server_credentials', { serverId: server.id })
+      .then((credentials) => setConfig((prev) => ({ ...prev, password: credentials.password || '' })))
+      .catch((error) => console.error('获取服务器凭据失败:', error));
+  }, [server.id]);
   const [isTesting, setIsTesting] = useState(false);
   const [testResult, setTestResult] = useState<{ success: boolean; message: string } | null>(null);
 
@@ -436,16 +559,13 @@ function EditServerDialog({ server, onUpdate, onClose, isLoading }: any) {
     setTestResult(null);
     
     try {
-      const configJson = JSON.stringify(config);
-      const result = await invoke<string>('remote_test_connection', {
-        serverType: 'webdav',
-        configJson,
-      });
-      setTestResult({ success: true, message: result });
+      const result = await testWithCertificatePrompt(config);
+      setConfig(result.config);
+      setTestResult({ success: true, message: result.message });
     } catch (error) {
       setTestResult({ 
         success: false, 
-        message: `连接失败: ${error}` 
+        message: `连接失败: ${errorMessage(error)}` 
       });
     } finally {
       setIsTesting(false);
@@ -549,6 +669,8 @@ function EditServerDialog({ server, onUpdate, onClose, isLoading }: any) {
             />
           </div>
 
+          <TransportOptionsFields config={config} setConfig={setConfig} disabled={isLoading} />
+
           {/* 测试结果显示 */}
           {testResult && (
             <div className={`px-4 py-3 rounded-lg ${
@@ -631,6 +753,10 @@ function AddServerDialog({ onAdd, onClose, isLoading }: any) {
     password: '',
     timeout_seconds: 30,
     verify_ssl: true,
+    http_protocol: 'http1',
+    accept_invalid_hostnames: false,
+    ca_certificate: '',
+    pinned_certificate: null as string | null,
     max_redirects: 5,
     user_agent: 'WindChimePlayer/1.0',
     server_id: generateServerId(),
@@ -643,16 +769,13 @@ function AddServerDialog({ onAdd, onClose, isLoading }: any) {
     setTestResult(null);
     
     try {
-      const configJson = JSON.stringify(config);
-      const result = await invoke<string>('remote_test_connection', {
-        serverType: 'webdav',
-        configJson,
-      });
-      setTestResult({ success: true, message: result });
+      const result = await testWithCertificatePrompt(config);
+      setConfig(result.config);
+      setTestResult({ success: true, message: result.message });
     } catch (error) {
       setTestResult({ 
         success: false, 
-        message: `连接失败: ${error}` 
+        message: `连接失败: ${errorMessage(error)}` 
       });
     } finally {
       setIsTesting(false);
@@ -756,6 +879,8 @@ function AddServerDialog({ onAdd, onClose, isLoading }: any) {
             />
           </div>
 
+          <TransportOptionsFields config={config} setConfig={setConfig} disabled={isLoading} />
+
           {/* 测试结果显示 */}
           {testResult && (
             <div className={`px-4 py-3 rounded-lg ${
@@ -7,7 +7,7 @@
  * - 单一职责：只负责音乐库数据管理，不涉及UI或播放器状态
  */
 
-import { createContext, useContext, useState, useCallback, useEffect, ReactNode } from 'react';
+import { createContext, useContext, useState, useCallback, useEffect, useRef, ReactNode } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import type { Track, LibraryStats, ScanProgress } from '../types/music';
 import { useTauriEvent } from '../hooks/useEventManager';
@@ -63,6 +63,8 @@ export function LibraryProvider({ children }: LibraryProviderProps) {
   // 新增：标识是否正在从缓存加载和后台同步
   const [, setIsLoadingFromCache] = useState(true);
   const [, setIsSyncing] = useState(false);
+  // 正在接收的分批曲目（收到结束事件后一次性更新状态）
+  const pendingLoadRef = useRef<{ loadId: number; tracks: Track[] } | null>(null);
 
   // ========== 核心操作方法 ==========
 
@@ -236,34 +238,70 @@ export function LibraryProvider({ children }: LibraryProviderProps) {
   // ========== 事件监听 ==========
 
   /**
-   * Listen for tracks loaded
+   * Listen for tracks loaded（begin -> chunk... -> end，按顺序拼接）
    */
-  useTauriEvent('library-tracks-loaded', (payload) => {
-    console.log(`[LibraryContext] Received track data, ${payload.length} tracks`);
-    setTracks(payload);
+  useTauriEvent('library-tracks-loaded-begin', (payload) => {
+    // 新的加载开始，丢弃尚未完成的旧批次
+    pendingLoadRef.current = { loadId: payload.load_id, tracks: [] };
+  });
+
+  useTauriEvent('library-tracks-loaded-chunk', (payload) => {
+    const pending = pendingLoadRef.current;
+    if (!pending || pending.loadId !== payload.load_id) return;
+    if (payload.offset !== pending.tracks.length) {
+      console.warn(`[LibraryContext] Track chunk out of order (offset ${payload.offset}, have ${pending.tracks.length}), dropping load`);
+      pendingLoadRef.current = null;
+      return;
+    }
+    for (const track of payload.tracks) {
+      pending.tracks.push(track);
+    }
+  });
+
+  useTauriEvent('library-tracks-loaded-end', (payload) => {
+    const pending = pendingLoadRef.current;
+    if (!pending || pending.loadId !== payload.load_id) return;
+    pendingLoadRef.current = null;
     setIsLoading(false);
     setIsSyncing(false);
+    if (pending.tracks.length !== payload.total) {
+      console.warn(`[LibraryContext] Incomplete track load: ${pending.tracks.length}/${payload.total}`);
+      return;
+    }
+
+    const loaded = pending.tracks;
+    console.log(`[LibraryContext] Received track data, ${loaded.length} tracks`);
+    setTracks(loaded);
     setHasInitialized(true);
     setIsCached(true);
     
     // 🚀 性能优化：保存到IndexedDB缓存
-    if (payload.length > 0) {
-      cacheService.saveTracks(payload).catch(error => {
+    if (loaded.length > 0) {
+      cacheService.saveTracks(loaded).catch(error => {
         console.warn('⚠️ 保存曲目到缓存失败:', error);
       });
       
-      silentSyncArtistCovers(payload).catch(error => {
+      silentSyncArtistCovers(loaded).catch(error => {
         console.warn('Artist cover auto-sync failed:', error);
       });
     }
   });
 
+  /**
+   * Listen for corrected track durations
+   */
+  useTauriEvent('track-updated', (payload) => {
+    setTracks(prev => prev.map(track =>
+      track.id === payload.trackId ? { ...track, duration_ms: payload.durationMs } : track
+    ));
+  });
+
   /**
    * Listen for search results
    */
   useTauriEvent('library-search-results', (payload) => {
-    console.log(`[LibraryContext] Search results, ${payload.length} tracks`);
-    setTracks(payload);
+    console.log(`[LibraryContext] Search results, ${payload.tracks.length} tracks, ${payload.playlists.length} playlists`);
+    setTracks(payload.tracks);
     setIsLoading(false);
   });
 
@@ -11,6 +11,7 @@ import React, { createContext, useContext, useState, useCallback, useEffect, use
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import type { PlayHistoryEntry, PlayStatistics, HistorySortBy } from '../types/music';
+import { errorMessage } from '../utils/appError';
 
 // ==================== Context接口 ====================
 
@@ -48,20 +49,6 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
   const [sortBy, setSortBy] = useState<HistorySortBy>('last_played');
   const [lastUpdateTime, setLastUpdateTime] = useState<number | null>(null);
   
-  // 用于跟踪当前播放的曲目和播放时长
-  const currentPlayingRef = useRef<{
-    trackId: number;
-    startTime: number;
-    lastPosition: number;
-    trackDurationMs?: number; // 歌曲总时长（用于计算播放百分比）
-  } | null>(null);
-  
-  // 用于去重：记录最近一次记录的曲目ID和时间戳
-  const lastRecordedRef = useRef<{
-    trackId: number;
-    timestamp: number;
-  } | null>(null);
-
   // ==================== 数据加载 ====================
 
   const loadHistory = useCallback(async () => {
@@ -75,7 +62,7 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
       setHistory(data);
       setLastUpdateTime(Date.now());
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(`加载播放历史失败: ${message}`);
       console.error('[PlayHistoryContext] 加载历史失败:', err);
     } finally {
@@ -107,7 +94,7 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
       setStatistics(null);
       await loadStatistics();
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(`清空历史失败: ${message}`);
       console.error('[PlayHistoryContext] 清空历史失败:', err);
     } finally {
@@ -121,7 +108,7 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
       await loadHistory();
       await loadStatistics();
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(`删除失败: ${message}`);
       console.error('[PlayHistoryContext] 删除失败:', err);
     }
@@ -144,7 +131,7 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
     loadHistory();
   }, [sortBy, loadHistory]);
 
-  // ==================== 自动记录与刷新监听 ====================
+  // ==================== 自动刷新监听 ====================
 
   // 使用 ref 保存最新的 sortBy 值，避免监听器重新创建
   const sortByRef = useRef(sortBy);
@@ -152,105 +139,24 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
     sortByRef.current = sortBy;
   }, [sortBy]);
 
-  // 记录上一首歌的播放历史
-  const recordPreviousTrack = useCallback(async (durationMs: number, trackDurationMs?: number) => {
-    const current = currentPlayingRef.current;
-    if (!current) return;
-    
-    // 🔒 去重检查：如果在3秒内已经记录过同一首歌，则跳过
-    const now = Date.now();
-    const lastRecorded = lastRecordedRef.current;
-    if (lastRecorded && 
-        lastRecorded.trackId === current.trackId && 
-        now - lastRecorded.timestamp < 3000) {
-      console.log(`[PlayHistoryContext] Skipping duplicate record: track_id=${current.trackId} (${now - lastRecorded.timestamp}ms since last)`);
-      return;
-    }
-    
-    // 📊 播放时长过滤：参考 Spotify/Last.fm 标准
-    // 条件1: 播放时长 >= 30秒
-    // 条件2: 播放时长 >= 歌曲总时长的 50%（用于短歌曲）
-    const MIN_DURATION_MS = 30 * 1000; // 30秒
-    const MIN_PERCENTAGE = 0.5; // 50%
-    
-    const playedSeconds = durationMs / 1000;
-    const isLongEnough = durationMs >= MIN_DURATION_MS;
-    const isHalfPlayed = trackDurationMs ? (durationMs >= trackDurationMs * MIN_PERCENTAGE) : false;
-    
-    if (!isLongEnough && !isHalfPlayed) {
-      console.log(`[PlayHistoryContext] Insufficient play duration: track_id=${current.trackId}, played=${playedSeconds.toFixed(1)}s (requires ≥30s or ≥50%)`);
-      return;
-    }
-    
-    try {
-      console.log(`[PlayHistoryContext] 📝 记录播放: track_id=${current.trackId}, 时长=${durationMs}ms (${playedSeconds.toFixed(1)}秒)${trackDurationMs ? ` / 总时长${(trackDurationMs/1000).toFixed(0)}秒 (${((durationMs/trackDurationMs)*100).toFixed(1)}%)` : ''}`);
-      await invoke('add_play_history', { 
-        trackId: current.trackId, 
-        durationPlayedMs: durationMs 
-      });
-      
-      // 更新最近记录的信息
-      lastRecordedRef.current = {
-        trackId: current.trackId,
-        timestamp: now
-      };
-      
-      console.log('[PlayHistoryContext] Play history recorded');
-    } catch (err) {
-      console.warn('[PlayHistoryContext] Failed to record play history:', err);
-    }
-  }, []);
-
-  // 监听播放器曲目切换事件，自动记录播放历史并刷新数据
+  // 播放历史由后端根据实际播放时长自动记录，这里只在记录后刷新数据
   // ⚠️ 重要：此 useEffect 只在组件挂载时执行一次，不应该依赖 sortBy
   useEffect(() => {
     let isActive = true; // 标记组件是否处于活动状态
     let refreshTimeout: NodeJS.Timeout | null = null;
+    let unlisten: (() => void) | null = null;
     
-    // 使用 ref 保存取消监听函数，确保清理函数能访问到
-    const unlistenersRef = {
-      trackChanged: null as (() => void) | null,
-      position: null as (() => void) | null,
-    };
-    
-    // 设置监听器
-    const setupListeners = async () => {
+    const setupListener = async () => {
       try {
-        // 监听曲目切换
-        const unlistenTrack = await listen('player-track-changed', async (event: any) => {
-          // 🔒 检查组件是否仍然挂载
+        const unlistenRecorded = await listen('play-history-recorded', () => {
           if (!isActive) return;
           
-          const trackData = event.payload;
-          console.log('[PlayHistoryContext] Track change detected:', trackData);
-          
-          // 如果有上一首歌，先记录它的播放时长
-          if (currentPlayingRef.current) {
-            const playedDuration = currentPlayingRef.current.lastPosition;
-            const trackDuration = currentPlayingRef.current.trackDurationMs;
-            await recordPreviousTrack(playedDuration, trackDuration);
-          }
-          
-          // 记录新曲目的开始信息
-          if (trackData && trackData.id) {
-            currentPlayingRef.current = {
-              trackId: trackData.id,
-              startTime: Date.now(),
-              lastPosition: 0,
-              trackDurationMs: trackData.duration_ms, // 保存歌曲总时长
-            };
-            console.log('[PlayHistoryContext] 🆕 开始跟踪新曲目:', trackData.id, trackData.duration_ms ? `(${(trackData.duration_ms/1000).toFixed(0)}秒)` : '');
-          } else {
-            currentPlayingRef.current = null;
-          }
-          
-          // 防抖：延迟1.5秒后刷新UI
+          // 防抖：连续记录时只刷新一次
           if (refreshTimeout) {
             clearTimeout(refreshTimeout);
           }
           
           refreshTimeout = setTimeout(async () => {
-            // 🔒 再次检查组件是否仍然挂载
             if (!isActive) return;
             
             console.log('[PlayHistoryContext] Refreshing play history and statistics');
@@ -260,8 +166,6 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
                 sortBy: sortByRef.current,
                 limit: 100,
               });
-              
-              // 🔒 在更新状态前检查组件是否仍然挂载
               if (!isActive) return;
               
               setHistory(historyData);
@@ -271,72 +175,33 @@ export const PlayHistoryProvider: React.FC<{ children: React.ReactNode }> = ({ c
               if (!isActive) return;
               
               setStatistics(stats);
-              
-              console.log('[PlayHistoryContext] Data refresh complete');
             } catch (err) {
               console.error('[PlayHistoryContext] Data refresh failed:', err);
             }
-          }, 1500);
-        });
-        
-        // 监听播放位置更新，持续跟踪实际播放进度
-        const unlistenPos = await listen('player-position-changed', (event: any) => {
-          // 🔒 检查组件是否仍然挂载
-          if (!isActive) return;
-          
-          const positionMs = event.payload as number;
-          if (currentPlayingRef.current) {
-            currentPlayingRef.current.lastPosition = positionMs;
-          }
+          }, 500);
         });
         
-        // 保存取消监听函数到 ref
-        unlistenersRef.trackChanged = unlistenTrack;
-        unlistenersRef.position = unlistenPos;
-        
-        // 最后检查组件是否还活跃
         if (isActive) {
-          console.log('[PlayHistoryContext] Play history listener set');
+          unlisten = unlistenRecorded;
         } else {
           // 如果在设置期间组件已卸载，立即清理
-          unlistenTrack();
-          unlistenPos();
-          console.log('[PlayHistoryContext] Component unmounted, canceling listener');
+          unlistenRecorded();
         }
       } catch (err) {
         console.error('[PlayHistoryContext] Failed to set listener:', err);
       }
     };
     
-    setupListeners();
+    setupListener();
 
-    // 清理函数：在组件卸载时，记录当前正在播放的曲目
     return () => {
-      // 🔒 标记组件为非活动状态
       isActive = false;
-      
       if (refreshTimeout) {
         clearTimeout(refreshTimeout);
       }
-      
-      if (currentPlayingRef.current) {
-        const playedDuration = currentPlayingRef.current.lastPosition;
-        const trackDuration = currentPlayingRef.current.trackDurationMs;
-        if (playedDuration > 0) {
-          // 异步记录，不阻塞卸载
-          recordPreviousTrack(playedDuration, trackDuration);
-        }
-      }
-      
-      // 取消监听器
-      if (unlistenersRef.trackChanged) {
-        unlistenersRef.trackChanged();
-      }
-      if (unlistenersRef.position) {
-        unlistenersRef.position();
+      if (unlisten) {
+        unlisten();
       }
-      
-      console.log('[PlayHistoryContext] 🧹 已清理播放历史监听器');
     };
   }, []); // ✅ 移除所有依赖，确保只在挂载/卸载时执行
 
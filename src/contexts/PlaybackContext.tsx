@@ -230,6 +230,7 @@ export function PlaybackProvider({ children }: PlaybackProviderProps) {
     const unlistenersRef = {
       state: null as (() => void) | null,
       track: null as (() => void) | null,
+      trackUpdated: null as (() => void) | null,
       position: null as (() => void) | null,
     };
     
@@ -289,17 +290,32 @@ export function PlaybackProvider({ children }: PlaybackProviderProps) {
         const unlistenPosition = await listen('player-position-changed', (event: any) => {
           if (!isActive) return; // 🔒 检查组件是否仍然挂载
           
+          const update = event.payload as { position_ms: number; track_id: number | null; is_estimated: boolean };
+          // 快速切歌后忽略上一首的过期位置
+          if (update.track_id !== null && update.track_id !== lastTrackIdRef.current) return;
+
           // 🔥 只在 Rust 引擎下更新 positionRef
           if (currentEngineRef.current === 'rust') {
-            positionRef.current = event.payload as number;
+            positionRef.current = update.position_ms;
           }
           // Web Audio 引擎下忽略 Rust 的位置事件
         });
         
+        // 监听时长更正（标签缺失时长的曲目播放后得到实际时长）
+        const unlistenTrackUpdated = await listen('track-updated', (event: any) => {
+          if (!isActive) return;
+
+          const update = event.payload as { trackId: number; durationMs: number };
+          setState(prev => prev.track && prev.track.id === update.trackId
+            ? { ...prev, track: { ...prev.track, duration_ms: update.durationMs } }
+            : prev);
+        });
+        
         // 保存取消监听函数到 ref
         unlistenersRef.state = unlistenState;
         unlistenersRef.track = unlistenTrack;
         unlistenersRef.position = unlistenPosition;
+        unlistenersRef.trackUpdated = unlistenTrackUpdated;
         
         // 最后检查组件是否还活跃
         if (!isActive) {
@@ -307,6 +323,7 @@ export function PlaybackProvider({ children }: PlaybackProviderProps) {
           unlistenState();
           unlistenTrack();
           unlistenPosition();
+          unlistenTrackUpdated();
           console.log('[PlaybackContext] Component unmounted, canceling listeners');
         }
       } catch (err) {
@@ -330,6 +347,9 @@ export function PlaybackProvider({ children }: PlaybackProviderProps) {
       if (unlistenersRef.position) {
         unlistenersRef.position();
       }
+      if (unlistenersRef.trackUpdated) {
+        unlistenersRef.trackUpdated();
+      }
     };
   }, []);
 
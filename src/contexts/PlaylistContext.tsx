@@ -10,6 +10,7 @@
 
 import React, { createContext, useContext, useState, useCallback, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../utils/appError';
 
 // ==================== 类型定义 ====================
 
@@ -48,13 +49,15 @@ export interface PlaylistWithTracks {
 export interface SmartRule {
   field: RuleField;
   operator: RuleOperator;
-  value: string;
+  value: string | number | { last_n_days: number }; // 相对日期：{ last_n_days: 30 }
 }
 
 export type RuleField = 
   | 'title' 
   | 'artist' 
   | 'album' 
+  | 'genre' 
+  | 'year' 
   | 'duration' 
   | 'date_added' 
   | 'last_played' 
@@ -79,10 +82,28 @@ export type RuleOperator =
   | 'is_true' 
   | 'is_false';
 
+export interface RuleGroup {
+  rules: SmartRule[];
+  match_all: boolean;
+}
+
+export type SmartSortField =
+  | 'title'
+  | 'artist'
+  | 'album'
+  | 'year'
+  | 'duration'
+  | 'date_added'
+  | 'last_played'
+  | 'play_count'
+  | 'random';
+
 export interface SmartRules {
   rules: SmartRule[];
+  groups?: RuleGroup[]; // 嵌套一层的 AND/OR 分组
   match_all: boolean; // true=AND, false=OR
   limit?: number;
+  sort?: { field: SmartSortField; descending?: boolean };
 }
 
 export interface CreatePlaylistOptions {
@@ -101,7 +122,50 @@ export interface UpdatePlaylistOptions {
   is_favorite?: boolean;
 }
 
-export type ExportFormat = 'M3U' | 'M3U8' | 'JSON';
+export type ExportFormat = 'M3U' | 'M3U8' | 'JSON' | 'xspf' | 'json_full';
+
+export interface ExportOptions {
+  /** 写入相对于该目录的路径 */
+  relative_to?: string | null;
+}
+
+export type ImportMatchKind = 'exact' | 'filename' | 'metadata' | 'manual';
+
+export interface ResolvedImportEntry {
+  source_line: number;
+  source_path: string;
+  track_id: number;
+  match_kind: ImportMatchKind;
+}
+
+export interface UnresolvedImportEntry {
+  source_line: number;
+  source_path: string;
+}
+
+export interface ImportPreview {
+  name: string;
+  resolved: ResolvedImportEntry[];
+  unresolved: UnresolvedImportEntry[];
+  exact_matches: number;
+  fuzzy_matches: number;
+  manual_matches: number;
+}
+
+export interface ImportOverride {
+  source_line: number;
+  track_id: number;
+}
+
+export interface PathRewrite {
+  from: string;
+  to: string;
+}
+
+export interface ImportOptions {
+  overrides?: ImportOverride[];
+  pathRewrite?: PathRewrite;
+}
 
 export interface PlaylistStats {
   total_playlists: number;
@@ -139,9 +203,10 @@ interface PlaylistContextType {
   refreshAllSmartPlaylists: () => Promise<void>;
 
   // 导入导出
-  exportPlaylist: (id: number, filePath: string, format: ExportFormat) => Promise<void>;
-  exportPlaylistPreview: (id: number, format: ExportFormat) => Promise<string>;
-  importPlaylist: (filePath: string) => Promise<number>;
+  exportPlaylist: (id: number, filePath: string, format: ExportFormat, options?: ExportOptions) => Promise<void>;
+  exportPlaylistPreview: (id: number, format: ExportFormat, options?: ExportOptions) => Promise<string>;
+  previewImport: (filePath: string, pathRewrite?: PathRewrite) => Promise<ImportPreview>;
+  importPlaylist: (filePath: string, options?: ImportOptions) => Promise<number>;
 
   // 其他功能
   loadStats: () => Promise<void>;
@@ -176,7 +241,7 @@ export const PlaylistProvider: React.FC<{ children: React.ReactNode }> = ({ chil
   // ==================== 辅助函数 ====================
 
   const handleError = useCallback((err: unknown, action: string) => {
-    const message = err instanceof Error ? err.message : String(err);
+    const message = errorMessage(err);
     console.error(`[PlaylistContext] ${action} 失败:`, err);
     setError(`${action}失败: ${message}`);
   }, []);
@@ -374,11 +439,11 @@ export const PlaylistProvider: React.FC<{ children: React.ReactNode }> = ({ chil
 
   // ==================== 导入导出 ====================
 
-  const exportPlaylist = useCallback(async (id: number, filePath: string, format: ExportFormat) => {
+  const exportPlaylist = useCallback(async (id: number, filePath: string, format: ExportFormat, options?: ExportOptions) => {
     try {
       setLoading(true);
       setError(null);
-      await invoke('playlists_export', { playlistId: id, filePath, format });
+      await invoke('playlists_export', { playlistId: id, filePath, format, options: options ?? null });
     } catch (err) {
       handleError(err, '导出歌单');
     } finally {
@@ -386,21 +451,35 @@ export const PlaylistProvider: React.FC<{ children: React.ReactNode }> = ({ chil
     }
   }, [handleError]);
 
-  const exportPlaylistPreview = useCallback(async (id: number, format: ExportFormat): Promise<string> => {
+  const exportPlaylistPreview = useCallback(async (id: number, format: ExportFormat, options?: ExportOptions): Promise<string> => {
     try {
       setError(null);
-      return await invoke<string>('playlists_export_preview', { playlistId: id, format });
+      return await invoke<string>('playlists_export_preview', { playlistId: id, format, options: options ?? null });
     } catch (err) {
       handleError(err, '预览导出');
       throw err;
     }
   }, [handleError]);
 
-  const importPlaylist = useCallback(async (filePath: string): Promise<number> => {
+  const previewImport = useCallback(async (filePath: string, pathRewrite?: PathRewrite): Promise<ImportPreview> => {
+    try {
+      setError(null);
+      return await invoke<ImportPreview>('playlists_import_preview', { filePath, pathRewrite: pathRewrite ?? null });
+    } catch (err) {
+      handleError(err, '预览导入');
+      throw err;
+    }
+  }, [handleError]);
+
+  const importPlaylist = useCallback(async (filePath: string, options?: ImportOptions): Promise<number> => {
     try {
       setLoading(true);
       setError(null);
-      const playlistId = await invoke<number>('playlists_import', { filePath });
+      const playlistId = await invoke<number>('playlists_import', {
+        filePath,
+        overrides: options?.overrides ?? null,
+        pathRewrite: options?.pathRewrite ?? null,
+      });
       await loadPlaylists(); // 刷新列表
       return playlistId;
     } catch (err) {
@@ -541,6 +620,7 @@ export const PlaylistProvider: React.FC<{ children: React.ReactNode }> = ({ chil
     // 导入导出
     exportPlaylist,
     exportPlaylistPreview,
+    previewImport,
     importPlaylist,
 
     // 其他功能
@@ -59,10 +59,21 @@ export interface WebDAVConfig {
   name: string;
   url: string;
   username: string;
-  password: string;
+  /** remote_get_servers 返回的配置不含密码，需要时调用 remote_get_server_credentials */
+  password?: string;
+  /** 是否已保存密码 */
+  has_password?: boolean;
   timeout_seconds: number;
   max_redirects: number;
   verify_ssl: boolean;
+  /** HTTP 协议偏好：http1（默认）、auto、http2_preferred */
+  http_protocol?: 'http1' | 'auto' | 'http2_preferred';
+  /** 允许证书主机名不匹配 */
+  accept_invalid_hostnames?: boolean;
+  /** 自定义 CA 证书（PEM 内容或文件路径） */
+  ca_certificate?: string | null;
+  /** 用户确认信任的服务器证书 SHA-256 指纹 */
+  pinned_certificate?: string | null;
   user_agent: string;
 }
 
@@ -5,6 +5,7 @@
 
 import { invoke } from '@tauri-apps/api/core';
 import { webAudioPlayer } from './webAudioPlayer';
+import { errorKind } from '../utils/appError';
 
 export interface Track {
   id: number;
@@ -411,18 +412,25 @@ export class HybridPlayer {
       console.log(`[HybridPlayer] ⏳ Web Audio not ready, queuing seek (Rust continues)`);
       
       // 🔥 可选：如果是 Rust 引擎且支持 seek，可以先用 Rust seek
+      let rustSeekError: unknown = null;
       if (this.currentEngine === 'rust') {
         try {
           await invoke('player_seek', { positionMs: positionMsInt });
           console.log(`[HybridPlayer] Rust seek executed: ${positionMsInt}ms`);
         } catch (error) {
           console.warn(`[HybridPlayer] Rust seek failed (expected for WebDAV):`, error);
+          rustSeekError = error;
         }
       }
       
       // 保存 pending seek，Web Audio 准备好后会从这个位置开始
       this.pendingSeekPosition = positionMsInt;
       console.log(`[HybridPlayer] Seek queued: ${positionMsInt}ms (will apply when Web Audio ready)`);
+
+      // 音频尚未缓存时告诉调用方，由界面提示稍后生效
+      if (errorKind(rustSeekError) === 'NotReady') {
+        throw rustSeekError;
+      }
     }
   }
   
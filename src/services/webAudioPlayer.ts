@@ -3,7 +3,7 @@
  * Full file decoding to memory for instant seeking
  */
 
-import { readFile } from '@tauri-apps/plugin-fs';
+import { invoke } from '@tauri-apps/api/core';
 
 export interface Track {
   id: number;
@@ -22,6 +22,45 @@ export interface AudioPlayerCallbacks {
   onTrackEnded?: () => void;
 }
 
+/** read_audio_file_open 的返回值 */
+interface AudioFileInfo {
+  handle: number;
+  size: number;
+  mime: string;
+}
+
+/** 单次读取的字节数，与后端 MAX_CHUNK_SIZE 一致 */
+const CHUNK_SIZE = 4 * 1024 * 1024;
+
+/**
+ * 分块读取本地音频文件（只能读取音乐文件夹中的文件）
+ */
+async function readLocalFile(path: string): Promise<Uint8Array> {
+  const info = await invoke<AudioFileInfo>('read_audio_file_open', { path });
+  try {
+    const data = new Uint8Array(info.size);
+    let offset = 0;
+    while (offset < info.size) {
+      const chunk = await invoke<ArrayBuffer>('read_audio_file_chunk', {
+        handle: info.handle,
+        offset,
+        len: Math.min(CHUNK_SIZE, info.size - offset),
+      });
+      if (chunk.byteLength === 0) {
+        // 文件在读取过程中被截断
+        break;
+      }
+      data.set(new Uint8Array(chunk), offset);
+      offset += chunk.byteLength;
+    }
+    return offset < info.size ? data.subarray(0, offset) : data;
+  } finally {
+    await invoke('read_audio_file_close', { handle: info.handle }).catch(err => {
+      console.warn('[WebAudioPlayer] Failed to close audio file handle:', err);
+    });
+  }
+}
+
 /**
  * Web Audio API audio player
  */
@@ -95,7 +134,6 @@ export class WebAudioPlayer {
         const [, serverId, filePath] = match;
         console.log(`[WebAudioPlayer] Resolving WebDAV server: ${serverId}`);
         
-        const { invoke } = await import('@tauri-apps/api/core');
         const servers = await invoke<any[]>('remote_get_servers');
         const server = (servers as any[]).find(s => s.id === serverId && s.server_type === 'webdav');
         
@@ -103,9 +141,11 @@ export class WebAudioPlayer {
           throw new Error(`WebDAV server not found: ${serverId}`);
         }
         
-        const config = server.config as { url: string; username: string; password: string; mount_path?: string };
-        const username = config.username;
-        const password = config.password;
+        const config = server.config as { url: string; mount_path?: string };
+        const { username, password } = await invoke<{ username: string; password: string }>(
+          'remote_get_server_credentials',
+          { serverId }
+        );
 
         // 构造包含挂载路径的基础 URL
         let baseUrl = (config.url || '').replace(/\/+$/, '');
@@ -130,8 +170,8 @@ export class WebAudioPlayer {
         fileData = new Uint8Array(bytes);
         console.log(`[WebAudioPlayer] WebDAV download complete (${fileData.byteLength} bytes, ${Math.round(performance.now() - readStart)}ms)`);
       } else {
-        // 本地文件，使用标准 readFile
-        fileData = await readFile(track.path);
+        // 本地文件，通过后端句柄分块读取
+        fileData = await readLocalFile(track.path);
         console.log(`[WebAudioPlayer] File read complete (${fileData.byteLength} bytes, ${Math.round(performance.now() - readStart)}ms)`);
       }
       
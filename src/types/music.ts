@@ -20,6 +20,13 @@ export interface Track {
   artist?: string;
   album?: string;
   duration_ms?: number;
+  genre?: string;
+  year?: number;
+  track_number?: number;
+  disc_number?: number;
+  album_artist?: string;
+  /** 封面ID，通过 get_cover 按需加载缩略图 */
+  cover_id?: string;
   // 封面和歌词数据（二进制数据）
   album_cover_data?: Uint8Array;
   album_cover_mime?: string;
@@ -106,6 +113,61 @@ export interface Playlist {
   updated_at?: string;
 }
 
+// ==================== 搜索 ====================
+
+/**
+ * 专辑摘要（按专辑名 + 专辑艺术家聚合）
+ */
+export interface AlbumSummary {
+  album: string;
+  album_artist?: string;
+  year?: number;
+  track_count: number;
+  total_duration_ms: number;
+  cover_track_id: number;
+  cover_id?: string;
+}
+
+/**
+ * 艺术家摘要
+ */
+export interface ArtistSummary {
+  artist: string;
+  album_count: number;
+  track_count: number;
+}
+
+/**
+ * 曲库搜索结果（支持 artist:"..." album:... genre:... year:1950-1969 字段限定）
+ */
+export interface LibrarySearchResult {
+  tracks: Track[];
+  playlists: Array<{ id: number; name: string; description?: string; track_count: number }>;
+  albums: AlbumSummary[];
+  artists: ArtistSummary[];
+}
+
+/**
+ * 搜索补全项（library_search_suggest）
+ */
+export interface SearchSuggestion {
+  kind: 'artist' | 'album' | 'title';
+  value: string;
+}
+
+// ==================== 重复曲目 ====================
+
+/**
+ * 重复曲目分组（library_find_duplicates）
+ * - exact: 文件内容完全相同
+ * - acoustic_match: 声学指纹相似（模糊匹配，可能是同一录音的不同编码）
+ */
+export interface DuplicateGroup {
+  kind: 'exact' | 'acoustic_match';
+  similarity: number;
+  tracks: Track[];
+}
+
 // ==================== 收藏与历史 ====================
 
 /**
@@ -139,6 +201,23 @@ export interface ScanProgress {
 
 // ==================== 事件相关 ====================
 
+/**
+ * 全量曲目分批加载的开始/结束通知
+ */
+export interface TracksLoadInfo {
+  load_id: number;
+  total: number;
+}
+
+/**
+ * 一批曲目，offset 为该批第一首在全量列表中的位置
+ */
+export interface TracksChunk {
+  load_id: number;
+  offset: number;
+  tracks: Track[];
+}
+
 /**
  * Tauri事件Payload类型映射
  */
@@ -146,9 +225,12 @@ export interface TauriEventPayloads {
   'library-scan-started': void;
   'library-scan-progress': ScanProgress;
   'library-scan-complete': { total_tracks: number };
-  'library-tracks-loaded': Track[];
-  'library-search-results': Track[];
+  'library-tracks-loaded-begin': TracksLoadInfo;
+  'library-tracks-loaded-chunk': TracksChunk;
+  'library-tracks-loaded-end': TracksLoadInfo;
+  'library-search-results': LibrarySearchResult;
   'library-stats': LibraryStats;
+  'track-updated': { trackId: number; durationMs: number };
   'player-state-changed': PlayerState;
   'player-track-changed': Track;
   'player-error': { PlaybackError?: string } | string;
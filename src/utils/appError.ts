@@ -0,0 +1,47 @@
+// ============================================================
+// 后端命令错误
+// ============================================================
+//
+// Tauri 命令失败时返回 { kind, message, detail }（见 src-tauri/src/error.rs），
+// 不再是字符串；直接 `${error}` 或 String(error) 会显示为 "[object Object]"。
+
+/** 错误类别 */
+export type AppErrorKind =
+  | 'NotFound'
+  | 'Database'
+  | 'Io'
+  | 'Network'
+  | 'Unauthorized'
+  | 'UntrustedCertificate'
+  | 'InvalidInput'
+  | 'Conflict'
+  | 'NotReady'
+  | 'Internal';
+
+export interface AppError {
+  kind: AppErrorKind;
+  message: string;
+  /** 附加信息，例如网络错误的 { status }、不受信任证书的 { fingerprint } */
+  detail: Record<string, unknown> | null;
+}
+
+/** 是否为后端命令返回的错误 */
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object'
+    && error !== null
+    && typeof (error as AppError).kind === 'string'
+    && typeof (error as AppError).message === 'string';
+}
+
+/** 可展示的错误描述：后端错误取 message，JS 异常取 message，其余转为字符串 */
+export function errorMessage(error: unknown): string {
+  if (isAppError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}
+
+/** 后端错误的类别，不是后端错误时为 undefined */
+export function errorKind(error: unknown): AppErrorKind | undefined {
+  return isAppError(error) ? error.kind : undefined;
+}
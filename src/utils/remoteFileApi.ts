@@ -13,9 +13,21 @@ export interface WebDAVConfig {
   timeout_seconds: number;
   max_redirects: number;
   verify_ssl: boolean;
+  /** HTTP 协议偏好：http1（默认）、auto、http2_preferred */
+  http_protocol?: 'http1' | 'auto' | 'http2_preferred';
+  /** 允许证书主机名不匹配 */
+  accept_invalid_hostnames?: boolean;
+  /** 自定义 CA 证书（PEM 内容或文件路径） */
+  ca_certificate?: string | null;
+  /** 用户确认信任的服务器证书 SHA-256 指纹 */
+  pinned_certificate?: string | null;
   user_agent: string;
 }
 
+/** 未保存的服务器使用的连接选项（未提供的使用默认值） */
+export type WebDAVConnectionOptions = Partial<Pick<WebDAVConfig,
+  'verify_ssl' | 'http_protocol' | 'accept_invalid_hostnames' | 'ca_certificate' | 'pinned_certificate'>>;
+
 export interface WebDAVFileInfo {
   path: string;
   name: string;
@@ -36,12 +48,14 @@ export const webdavApi = {
   async testConnection(
     url: string,
     username: string,
-    password: string
+    password: string,
+    options?: WebDAVConnectionOptions
   ): Promise<string> {
     return await invoke('webdav_test_connection', {
       url,
       username,
       password,
+      options,
     });
   },
 